@@ -215,7 +215,8 @@ fn frag(@builtin(position) position: vec4<f32>,
                                       ssao,
                                       lightdata,
                                       in_wpos,
-                                      fog
+                                      fog,
+                                      vec3(0.0)
                                       );
     return FragmentOutput(vec4(final_rgb, 1.0));
 }
@@ -0,0 +1,66 @@
+#include "render_params.wgsl"
+
+struct VertexOutput {
+    @location(0) out_uv: vec2<f32>,
+    @builtin(position) member: vec4<f32>,
+}
+
+@vertex
+fn vert(@location(0) in_pos: vec3<f32>,
+        @location(1) in_uv: vec2<f32>) -> VertexOutput {
+    return VertexOutput(in_uv, vec4(in_pos.xy, 1.0, 1.0));
+}
+
+struct OutlineParams {
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: RenderParams;
+
+#ifdef MSAA
+@group(1) @binding(0) var t_mask: texture_multisampled_2d<f32>;
+#else
+@group(1) @binding(0) var t_mask: texture_2d<f32>;
+#endif
+@group(1) @binding(1) var s_mask: sampler;
+
+@group(2) @binding(0) var<uniform> outline: OutlineParams;
+
+// in pixels
+const WIDTH: i32 = 3;
+
+fn covered(coords: vec2<i32>) -> bool {
+    let dim = vec2<i32>(textureDimensions(t_mask));
+    if (any(coords < vec2(0)) || any(coords >= dim)) {
+        return false;
+    }
+    // reverse z, the mask is cleared to 0
+    return textureLoad(t_mask, coords, 0).r > 0.0;
+}
+
+@fragment
+fn frag(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(position.xy);
+    if (covered(pos)) {
+        discard;
+    }
+
+    // distance to the closest covered pixel
+    var closest: i32 = WIDTH * WIDTH + 1;
+    for (var y: i32 = -WIDTH; y <= WIDTH; y++) {
+        for (var x: i32 = -WIDTH; x <= WIDTH; x++) {
+            let d = x * x + y * y;
+            if (d < closest && covered(pos + vec2(x, y))) {
+                closest = d;
+            }
+        }
+    }
+    if (closest > WIDTH * WIDTH) {
+        discard;
+    }
+
+    // antialiased outer edge, pulsing slowly
+    let edge = clamp(f32(WIDTH) - sqrt(f32(closest)) + 0.5, 0.0, 1.0);
+    let pulse = 0.8 + 0.2 * sin(params.time_always * 4.0);
+    return vec4(outline.color.rgb, outline.color.a * edge * pulse);
+}
@@ -158,6 +158,7 @@ fn render(sun: vec3<f32>,
           lightdata: LightData,
           wpos: vec3<f32>,
           fog: vec3<f32>,
+          emissive: vec3<f32>,
           ) -> vec3<f32>  {
 
     var Lo: vec3<f32> = vec3(0.0);
@@ -175,7 +176,7 @@ fn render(sun: vec3<f32>,
     let dkD: vec3<f32> = (1.0 - F_spec) * (1.0 - vec3(metallic));
 
     let ambient: vec3<f32> = (0.2 * dkD * (0.04 + irradiance_diffuse) * albedo + specular) * ssao;
-    var color: vec3<f32>   = ambient + Lo + fog;
+    var color: vec3<f32>   = ambient + Lo + fog + emissive;
 
     let autoexposure = 1.0 + smoothstep(0.0, 0.1, -sun.z) * 10.0;
 
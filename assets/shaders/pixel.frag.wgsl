@@ -7,6 +7,9 @@ struct FragmentOutput {
 
 const HAS_METALLIC_ROUGHNESS_TEXTURE: u32 = 1u;
 const HAS_NORMAL_MAP: u32 = 2u;
+const NIGHT_WINDOWS: u32 = 4u;
+const WET_SURFACE: u32 = 8u;
+const FOLIAGE: u32 = 16u;
 
 struct MaterialParams {
     flags: u32,
@@ -51,6 +54,24 @@ struct MaterialParams {
 
 const MAX_REFLECTION_LOD: f32 = 4.0;
 
+// Windows drawn on the walls in a grid of 3m floors, some of them lit at night
+fn night_windows(wpos: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    if (abs(normal.z) > 0.3) {
+        return vec3(0.0);
+    }
+    let along: f32 = dot(wpos.xy, normalize(vec2(-normal.y, normal.x)));
+    let cell: vec2<f32> = vec2(along, wpos.z) / 3.0;
+    let inside: vec2<f32> = fract(cell);
+    if (inside.x < 0.3 || inside.x > 0.7 || inside.y < 0.35 || inside.y > 0.75) {
+        return vec3(0.0);
+    }
+    let lit: f32 = fract(sin(dot(floor(cell), vec2(12.9898, 78.233))) * 43758.5453);
+    if (lit > 0.6) {
+        return vec3(0.0);
+    }
+    return vec3(1.0, 0.75, 0.45) * (0.1 + 0.1 * lit);
+}
+
 @fragment
 fn frag(@location(0) in_tint: vec4<f32>,
         @location(1) in_normal: vec3<f32>,
@@ -97,7 +118,19 @@ fn frag(@location(0) in_tint: vec4<f32>,
     }
 
     let irradiance_diffuse: vec3<f32> = textureSample(t_diffuse_irradiance, s_diffuse_irradiance, normal).rgb;
-    let c = mix(in_tint, vec4(1.0), metallic) * albedo;
+    var c = mix(in_tint, vec4(1.0), metallic) * albedo;
+
+    if ((u_mat.flags & FOLIAGE) != 0u) {
+        // only the leaves change color, not the trunk
+        let greenness = clamp((c.g - max(c.r, c.b)) * 8.0, 0.0, 1.0);
+        c = vec4(mix(c.rgb, c.rgb * params.foliage_col.rgb, greenness), c.a);
+    }
+
+    if ((u_mat.flags & WET_SURFACE) != 0u && normal.z > 0.5) {
+        // water fills the pores: darker and more reflective
+        c = vec4(c.rgb * (1.0 - 0.45 * params.wetness), c.a);
+        roughness = mix(roughness, 0.2, params.wetness);
+    }
 
     let V_denorm: vec3<f32> = params.cam_pos.xyz - in_wpos;
     let dist: f32 = length(V_denorm);
@@ -131,6 +164,11 @@ fn frag(@location(0) in_tint: vec4<f32>,
     #endif
     #endif
 
+    var emissive = vec3(0.0);
+    if ((u_mat.flags & NIGHT_WINDOWS) != 0u) {
+        emissive = (1.0 - smoothstep(-0.05, 0.1, params.sun.z)) * night_windows(in_wpos, normal);
+    }
+
     #ifdef OFFSCREEN_RENDER
     let lightdata = LightData(vec4(0), vec4(0), vec2(0));
     #else
@@ -153,7 +191,8 @@ fn frag(@location(0) in_tint: vec4<f32>,
                                       ssao,
                                       lightdata,
                                       in_wpos,
-                                      fog
+                                      fog,
+                                      emissive
                                       );
 
     return FragmentOutput(vec4<f32>(final_rgb, c.a));
@@ -10,10 +10,13 @@ struct RenderParams {
     sun_col: vec4<f32>,
     sand_col: vec4<f32>,
     sea_col: vec4<f32>,
+    foliage_col: vec4<f32>,
     viewport: vec2<f32>,
     unproj_pos: vec2<f32>,
     time: f32,
     time_always: f32,
     shadow_mapping_resolution: i32,
     terraforming_mode_radius: f32,
+    wetness: f32,
+    shadow_cascades: i32,
 }
\ No newline at end of file
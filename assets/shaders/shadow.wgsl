@@ -1,7 +1,7 @@
 fn sampleShadow(in_wpos: vec3<f32>) -> f32 {
     var cascade_idx = 100;
     var blend = 0.0;
-    for (var i = 0 ; i < N_SHADOWS ; i++) {
+    for (var i = 0 ; i < params.shadow_cascades ; i++) {
         let light_local: vec4<f32> = params.sunproj[i] * vec4(in_wpos, 1.0);
         let corrected: vec3<f32> = light_local.xyz / light_local.w * vec3(0.5, -0.5, 1.0) + vec3(0.5, 0.5, 0.0);
 
@@ -15,16 +15,18 @@ fn sampleShadow(in_wpos: vec3<f32>) -> f32 {
         return 1.0;
     }
 
+    // the last cascade rendered has no next one to blend with
+    if (cascade_idx == params.shadow_cascades - 1) {
+        return sampleOneShadow(in_wpos, cascade_idx);
+    }
+
     var s1 = 1.0;
     var s2 = 1.0;
     if (blend < 1.0 - 1e-3) {
         s1 = sampleOneShadow(in_wpos, cascade_idx);
-        if (cascade_idx == N_SHADOWS - 1) {
-            return s1;
-        }
     }
     if (blend > 1e-3) {
-        s2 = sampleOneShadow(in_wpos, (cascade_idx + 1)%4);
+        s2 = sampleOneShadow(in_wpos, cascade_idx + 1);
     }
     return mix(s1, s2, blend);
 }
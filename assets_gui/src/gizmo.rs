@@ -0,0 +1,202 @@
+//! The footprint of the inspected company drawn on the ground under its model, with its door
+//! that can be dragged to edit the building generator.
+//! The model is shown in the frame of the building, x along the street and y away from it.
+
+use engine::{Context, GfxContext, Mesh, MeshBuilder, MouseButton};
+use geom::{vec2, Camera, LinearColor, Plane, Vec2, Vec3};
+use prototypes::{BuildingGen, GoodsCompanyPrototype};
+
+/// Distance to the door at which it can be grabbed, in meters
+const GRAB_RADIUS: f32 = 2.0;
+const DOOR_RADIUS: f32 = 1.0;
+/// Height of the gizmo above the ground, not to be hidden by the floor of the model
+const Z: f32 = 0.1;
+
+#[derive(Default)]
+pub struct DoorGizmo {
+    dragging: bool,
+    hovered: bool,
+    was_pressed: bool,
+    pub mesh: Option<Mesh>,
+}
+
+impl DoorGizmo {
+    /// Moves the door where the ground is dragged to, then builds the mesh of the gizmo.
+    /// Returns whether the generator changed.
+    pub fn update(
+        &mut self,
+        ctx: &mut Context,
+        camera: &Camera,
+        comp: &GoodsCompanyPrototype,
+        bgen: &mut BuildingGen,
+    ) -> bool {
+        let mut changed = false;
+        let ground = camera
+            .unproj_ray(ctx.input.mouse.screen)
+            .and_then(|r| r.intersection_plane(&Plane::new(Vec3::Z, 0.0)))
+            .map(Vec3::xy);
+
+        let door = door_pos(*bgen, comp.size.h);
+        self.hovered = !ctx.yakui.mouse_captured
+            && door
+                .zip(ground)
+                .map_or(false, |(door, ground)| door.is_close(ground, GRAB_RADIUS));
+
+        let pressed = ctx.input.mouse.pressed.contains(&MouseButton::Left);
+        if !pressed {
+            self.dragging = false;
+        } else if !self.was_pressed && self.hovered {
+            self.dragging = true;
+        }
+        self.was_pressed = pressed;
+
+        if let (true, Some(ground)) = (self.dragging, ground) {
+            changed = move_door(bgen, ground, comp.size.h);
+        }
+
+        self.mesh = build_mesh(&mut ctx.gfx, comp, *bgen, self.dragging || self.hovered);
+        changed
+    }
+}
+
+/// Where the door of the building is, relative to its center, like when the building is placed.
+/// The size is the side of the building along the street.
+pub fn door_pos(bgen: BuildingGen, size: f32) -> Option<Vec2> {
+    match bgen {
+        BuildingGen::CenteredDoor { vertical_factor } => {
+            Some(Vec2::y(-vertical_factor * 0.5 * size))
+        }
+        BuildingGen::NoWalkway { door_pos } => Some(door_pos),
+        BuildingGen::House | BuildingGen::Farm => None,
+    }
+}
+
+/// The centered door only slides along the y axis, between the center and the street.
+/// The values are rounded so that the sources stay readable.
+fn move_door(bgen: &mut BuildingGen, to: Vec2, size: f32) -> bool {
+    let new = match *bgen {
+        BuildingGen::CenteredDoor { .. } => BuildingGen::CenteredDoor {
+            vertical_factor: round_to(-to.y / (0.5 * size).max(0.01), 0.01).clamp(0.0, 1.0),
+        },
+        BuildingGen::NoWalkway { .. } => BuildingGen::NoWalkway {
+            door_pos: vec2(round_to(to.x, 0.1), round_to(to.y, 0.1)),
+        },
+        BuildingGen::House | BuildingGen::Farm => return false,
+    };
+    let changed = door_pos(new, size) != door_pos(*bgen, size);
+    *bgen = new;
+    changed
+}
+
+fn round_to(v: f32, step: f32) -> f32 {
+    (v / step).round() * step
+}
+
+fn build_mesh(
+    gfx: &mut GfxContext,
+    comp: &GoodsCompanyPrototype,
+    bgen: BuildingGen,
+    door_active: bool,
+) -> Option<Mesh> {
+    let mut mb = MeshBuilder::<false>::new(gfx.tess_material);
+    let mut tess = mb.mk_tess();
+
+    let (hw, hh) = (comp.size.h * 0.5, comp.size.w * 0.5);
+    let corners = [vec2(-hw, -hh), vec2(hw, -hh), vec2(hw, hh), vec2(-hw, hh)];
+
+    // the zone starts as the footprint when the building is placed
+    if comp.zone.is_some() {
+        tess.set_color(LinearColor::new(0.2, 0.8, 0.3, 0.3));
+        tess.draw_filled_polygon(&corners, Z);
+    }
+
+    tess.set_color(LinearColor::WHITE);
+    tess.draw_polyline(&corners.map(|c| c.z(Z)), 0.3, true);
+
+    // the side of the street
+    tess.set_color(LinearColor::new(0.3, 0.3, 0.3, 1.0));
+    tess.draw_stroke(vec2(-hw, -hh - 1.0).z(Z), vec2(hw, -hh - 1.0).z(Z), 1.0);
+
+    if let Some(door) = door_pos(bgen, comp.size.h) {
+        tess.set_color(if door_active {
+            LinearColor::new(1.0, 0.8, 0.2, 1.0)
+        } else {
+            LinearColor::new(1.0, 0.4, 0.1, 1.0)
+        });
+        tess.draw_circle(door.z(Z), DOOR_RADIUS);
+        if let BuildingGen::CenteredDoor { .. } = bgen {
+            tess.draw_stroke(Vec3::z(Z), door.z(Z), 0.2);
+        }
+    }
+
+    let mut mesh = mb.build(gfx)?;
+    mesh.skip_depth = true;
+    Some(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factor(bgen: BuildingGen) -> f32 {
+        match bgen {
+            BuildingGen::CenteredDoor { vertical_factor } => vertical_factor,
+            _ => panic!("not a centered door"),
+        }
+    }
+
+    #[test]
+    fn door_pos_is_relative_to_the_center() {
+        let centered = BuildingGen::CenteredDoor {
+            vertical_factor: 1.0,
+        };
+        assert_eq!(door_pos(centered, 10.0), Some(vec2(0.0, -5.0)));
+        let no_walkway = BuildingGen::NoWalkway {
+            door_pos: vec2(2.0, 3.0),
+        };
+        assert_eq!(door_pos(no_walkway, 10.0), Some(vec2(2.0, 3.0)));
+        assert_eq!(door_pos(BuildingGen::House, 10.0), None);
+        assert_eq!(door_pos(BuildingGen::Farm, 10.0), None);
+    }
+
+    #[test]
+    fn centered_door_slides_between_the_center_and_the_street() {
+        let mut bgen = BuildingGen::CenteredDoor {
+            vertical_factor: 1.0,
+        };
+
+        // only the y axis is followed
+        assert!(move_door(&mut bgen, vec2(3.0, -2.5), 10.0));
+        assert!((factor(bgen) - 0.5).abs() < 1e-4);
+        assert!(!move_door(&mut bgen, vec2(-3.0, -2.501), 10.0));
+
+        // the door stays inside the building
+        assert!(move_door(&mut bgen, vec2(0.0, 4.0), 10.0));
+        assert_eq!(factor(bgen), 0.0);
+        assert!(move_door(&mut bgen, vec2(0.0, -20.0), 10.0));
+        assert_eq!(factor(bgen), 1.0);
+    }
+
+    #[test]
+    fn free_door_is_rounded() {
+        let mut bgen = BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        };
+        assert!(move_door(&mut bgen, vec2(1.23, -4.56), 10.0));
+        let door = door_pos(bgen, 10.0).unwrap();
+        assert!(door.is_close(vec2(1.2, -4.6), 1e-4), "{:?}", door);
+        assert!(!move_door(&mut bgen, vec2(1.21, -4.58), 10.0));
+    }
+
+    #[test]
+    fn houses_and_farms_have_no_door_to_move() {
+        for bgen in [BuildingGen::House, BuildingGen::Farm] {
+            let mut moved = bgen;
+            assert!(!move_door(&mut moved, vec2(1.0, 1.0), 10.0));
+            assert_eq!(
+                std::mem::discriminant(&moved),
+                std::mem::discriminant(&bgen)
+            );
+        }
+    }
+}
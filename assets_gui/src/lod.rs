@@ -8,7 +8,7 @@ use common::unwrap_cont;
 use engine::gltf::json::validation::{Checked, USize64};
 use engine::gltf::json::{accessor, Accessor, Index, Value};
 use engine::gltf::{json, Document, Semantic};
-use engine::meshload::CPUMesh;
+use engine::meshload::{auto_lod_coverage, CPUMesh};
 use geom::{Vec2, Vec3};
 
 #[derive(Debug)]
@@ -245,17 +245,13 @@ pub fn lod_generate(m: &mut CPUMesh, params: LodGenerateParams) -> Result<(), Lo
 
         let entry = ext.others.entry("MSFT_lod".to_string());
 
-        fn autocoverage(triangles: usize) -> f32 {
-            (triangles as f32 / 100000.0).min(0.5)
-        }
-
         let obj = entry.or_insert_with(|| {
             Value::Object(
                 [
                     ("ids".to_string(), Value::Array(vec![])),
                     (
                         "screencoverage".to_string(),
-                        Value::Array(vec![autocoverage(m.n_triangles).into()]),
+                        Value::Array(vec![auto_lod_coverage(m.n_triangles).into()]),
                     ),
                 ]
                 .into_iter()
@@ -266,7 +262,7 @@ pub fn lod_generate(m: &mut CPUMesh, params: LodGenerateParams) -> Result<(), Lo
         obj["screencoverage"]
             .as_array_mut()
             .unwrap()
-            .push(autocoverage(triangles).into());
+            .push(auto_lod_coverage(triangles).into());
 
         let lod_node = json::Node {
             camera: None,
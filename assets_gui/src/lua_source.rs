@@ -0,0 +1,703 @@
+//! Edits the prototypes in the lua sources of the base mod, renamed items are also renamed in the
+//! sources of the mods. Only the tables of the edited prototypes change, the rest of the files,
+//! comments and unknown fields included, is kept as written.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use prototypes::{
+    prototypes_iter, swap_prototypes, try_prototype, BuildingGen, GoodsCompanyPrototype, ItemID,
+};
+
+const BASE_MOD: &str = "base_mod/";
+const MODS: &str = "mods/";
+const ITEMS_FILE: &str = "base_mod/items.lua";
+const COMPANIES_FILE: &str = "base_mod/companies.lua";
+
+#[derive(Debug, Clone)]
+pub enum ItemEdit {
+    Add,
+    Delete(String),
+    Update {
+        name: String,
+        new_name: String,
+        label: String,
+        optout_exttrade: bool,
+        /// Renames the item in the recipes, warehouses and scenarios referencing it
+        update_refs: bool,
+    },
+}
+
+/// The new building generator of a company, and the rows of its recipe if it has one
+#[derive(Debug, Clone)]
+pub struct CompanyEdit {
+    pub company: String,
+    pub bgen: BuildingGen,
+    pub recipe: Option<RecipeEdit>,
+}
+
+/// The new rows of the recipe of a company, the items given by name
+#[derive(Debug, Clone)]
+pub struct RecipeEdit {
+    pub consumption: Vec<(String, i32)>,
+    pub production: Vec<(String, i32)>,
+}
+
+/// Returns the name of the item to inspect afterwards, none if it was deleted
+pub fn apply_item_edit(edit: &ItemEdit) -> Result<Option<String>, String> {
+    edit_sources(|sources| {
+        let inspect = edit_items(edit, sources)?;
+        if let ItemEdit::Update {
+            name,
+            new_name,
+            update_refs: true,
+            ..
+        } = edit
+        {
+            for (path, src) in sources.iter_mut() {
+                if path != &PathBuf::from(ITEMS_FILE) {
+                    *src = rename_refs(src, name, new_name);
+                }
+            }
+        }
+        Ok(inspect)
+    })
+}
+
+/// Replaces the building generator of the company, and the consumption and production of its
+/// recipe, each written on one line
+pub fn apply_company_edit(edit: &CompanyEdit) -> Result<(), String> {
+    edit_sources(|sources| {
+        let companies = sources
+            .get_mut(&PathBuf::from(COMPANIES_FILE))
+            .ok_or("base_mod/companies.lua not found")?;
+        edit_company(companies, edit)
+    })
+}
+
+fn edit_company(companies: &mut String, edit: &CompanyEdit) -> Result<(), String> {
+    let block =
+        table_block(companies, &edit.company).ok_or("company not found in companies.lua")?;
+
+    let mut table = companies[block.clone()].to_string();
+    table = set_table_field(&table, "bgen", &bgen_fields(edit.bgen))
+        .ok_or_else(|| format!("{} has no bgen in companies.lua", edit.company))?;
+
+    if let Some(ref recipe) = edit.recipe {
+        for (key, rows) in [
+            ("consumption", &recipe.consumption),
+            ("production", &recipe.production),
+        ] {
+            if !is_single_line(&table, key) {
+                return Err(format!(
+                    "the {} of {} is not written on one line, edit it in companies.lua",
+                    key, edit.company
+                ));
+            }
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|(item, amount)| format!("{{{}, {}}}", lua_string(item), amount))
+                .collect();
+            table = set_field(&table, key, Some(&format!("{{{}}}", rows.join(", "))));
+        }
+    }
+    companies.replace_range(block, &table);
+    Ok(())
+}
+
+/// Applies the change to the sources, writes them then reloads the prototypes from them.
+/// The sources are restored if the prototypes are no longer valid.
+fn edit_sources<T>(
+    f: impl FnOnce(&mut BTreeMap<PathBuf, String>) -> Result<T, String>,
+) -> Result<T, String> {
+    let sources = read_sources()?;
+    let mut edited = sources.clone();
+    let v = f(&mut edited)?;
+
+    write_changed(&sources, &edited)?;
+    if let Err(e) = reload() {
+        write_changed(&edited, &sources)?;
+        return Err(e);
+    }
+    Ok(v)
+}
+
+fn edit_items(
+    edit: &ItemEdit,
+    sources: &mut BTreeMap<PathBuf, String>,
+) -> Result<Option<String>, String> {
+    let items = sources
+        .get_mut(&PathBuf::from(ITEMS_FILE))
+        .ok_or("base_mod/items.lua not found")?;
+
+    Ok(match edit {
+        ItemEdit::Add => {
+            let name = new_item_name();
+            add_item(items, &name)?;
+            Some(name)
+        }
+        ItemEdit::Delete(name) => {
+            let block = table_block(items, name).ok_or("item not found in items.lua")?;
+            items.replace_range(line_range(items, block), "");
+            None
+        }
+        ItemEdit::Update {
+            name,
+            new_name,
+            label,
+            optout_exttrade,
+            ..
+        } => {
+            if new_name.is_empty() {
+                return Err("the name must not be empty".to_string());
+            }
+            if new_name != name && try_prototype(ItemID::new(new_name)).is_some() {
+                return Err(format!("an item named {} already exists", new_name));
+            }
+            let block = table_block(items, name).ok_or("item not found in items.lua")?;
+            let mut table = items[block.clone()].to_string();
+            table = set_field(&table, "name", Some(&lua_string(new_name)));
+            table = set_field(&table, "label", Some(&lua_string(label)));
+            table = set_field(
+                &table,
+                "optout_exttrade",
+                (*optout_exttrade).then_some("true"),
+            );
+            items.replace_range(block, &table);
+            Some(new_name.clone())
+        }
+    })
+}
+
+/// Loads the prototypes again from the sources, with the mods loaded at the start
+pub fn reload() -> Result<(), String> {
+    let candidate = prototypes::reload_candidate("./").map_err(|e| e.problems().join("\n"))?;
+    swap_prototypes(candidate);
+    Ok(())
+}
+
+fn read_sources() -> Result<BTreeMap<PathBuf, String>, String> {
+    let mut sources = BTreeMap::new();
+    for dir in [BASE_MOD, MODS] {
+        if !Path::new(dir).is_dir() {
+            continue;
+        }
+        for path in common::saveload::walkdir(dir.as_ref()) {
+            if path.extension().map_or(false, |ext| ext == "lua") {
+                let src = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                sources.insert(path, src);
+            }
+        }
+    }
+    Ok(sources)
+}
+
+fn write_changed(
+    before: &BTreeMap<PathBuf, String>,
+    after: &BTreeMap<PathBuf, String>,
+) -> Result<(), String> {
+    for (path, src) in after {
+        if before.get(path) != Some(src) {
+            std::fs::write(path, src).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn new_item_name() -> String {
+    let taken = |name: &str| try_prototype(ItemID::new(name)).is_some();
+    let mut name = "new-item".to_string();
+    let mut i = 2;
+    while taken(&name) {
+        name = format!("new-item-{}", i);
+        i += 1;
+    }
+    name
+}
+
+/// Appends the item at the end of the `data:extend` table of the file
+fn add_item(src: &mut String, name: &str) -> Result<(), String> {
+    let close = src.rfind('}').ok_or("items.lua has no data:extend table")?;
+    let after_last = src[..close].trim_end().len();
+    let comma = if src[..after_last].ends_with([',', '{']) {
+        ""
+    } else {
+        ","
+    };
+    src.insert_str(
+        after_last,
+        &format!(
+            "{}\n    {{\n        type = \"item\",\n        name = {},\n        label = {},\n    }},",
+            comma,
+            lua_string(name),
+            lua_string("New item"),
+        ),
+    );
+    Ok(())
+}
+
+/// The table defining the prototype, from its opening brace to its closing brace
+fn table_block(src: &str, name: &str) -> Option<Range<usize>> {
+    let at = find_assignment(src, "name", &lua_string(name))?;
+
+    let mut depth = 0;
+    let start = src[..at].char_indices().rev().find_map(|(i, c)| {
+        match c {
+            '}' => depth += 1,
+            '{' if depth == 0 => return Some(i),
+            '{' => depth -= 1,
+            _ => {}
+        }
+        None
+    })?;
+
+    let mut depth = 0;
+    let end = src[start..].char_indices().find_map(|(i, c)| {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(start + i + 1),
+            '}' => depth -= 1,
+            _ => {}
+        }
+        None
+    })?;
+
+    Some(start..end)
+}
+
+/// Extends the range of a table to its whole lines and the comma following it, to remove it
+fn line_range(src: &str, block: Range<usize>) -> Range<usize> {
+    let start = src[..block.start].trim_end_matches([' ', '\t']).len();
+    let start = src[..start].strip_suffix('\n').map_or(start, str::len);
+
+    let rest = &src[block.end..];
+    let rest_trimmed = rest.trim_start_matches([' ', '\t']);
+    let rest_trimmed = rest_trimmed.strip_prefix(',').unwrap_or(rest_trimmed);
+    let end = block.end + rest.len() - rest_trimmed.len();
+    start..end
+}
+
+/// Position of `value` in the first `key = value` of the source
+fn find_assignment(src: &str, key: &str, value: &str) -> Option<usize> {
+    src.match_indices(value)
+        .map(|(i, _)| i)
+        .find(|&i| assigned_to(&src[..i], key))
+}
+
+/// Whether the source before a value ends with `key =`
+fn assigned_to(before: &str, key: &str) -> bool {
+    let Some(before) = before.trim_end().strip_suffix('=') else {
+        return false;
+    };
+    let Some(before) = before.trim_end().strip_suffix(key) else {
+        return false;
+    };
+    !before.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+}
+
+fn is_key(line: &str, key: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(key)
+        .map_or(false, |rest| rest.trim_start().starts_with('='))
+}
+
+/// Whether the value of the first `key` of the table ends on its line
+fn is_single_line(table: &str, key: &str) -> bool {
+    table
+        .lines()
+        .find(|l| is_key(l, key))
+        .map_or(false, |l| l.matches('{').count() == l.matches('}').count())
+}
+
+/// Sets `key = value` in a table written one field per line, `None` removes the field.
+/// A new field goes before the closing brace, indented like the other fields.
+fn set_field(table: &str, key: &str, value: Option<&str>) -> String {
+    let mut lines: Vec<String> = table.lines().map(str::to_string).collect();
+    let indent = lines
+        .iter()
+        .find(|l| l.trim_start().starts_with("name"))
+        .map(|l| l[..l.len() - l.trim_start().len()].to_string())
+        .unwrap_or_default();
+
+    match (lines.iter().position(|l| is_key(l, key)), value) {
+        (Some(i), Some(value)) => {
+            let line_indent = &lines[i][..lines[i].len() - lines[i].trim_start().len()];
+            lines[i] = format!("{}{} = {},", line_indent, key, value);
+        }
+        (Some(i), None) => {
+            lines.remove(i);
+        }
+        (None, Some(value)) => {
+            let close = lines.len().saturating_sub(1);
+            lines.insert(close, format!("{}{} = {},", indent, key, value));
+        }
+        (None, None) => {}
+    }
+    lines.join("\n")
+}
+
+/// Replaces the value of `key`, written on one line or as a table over several lines, by a table
+/// with one field per line. Returns none if the table has no such key.
+fn set_table_field(table: &str, key: &str, fields: &[(&str, String)]) -> Option<String> {
+    let line_start = table
+        .match_indices(key)
+        .map(|(i, _)| table[..i].rfind('\n').map_or(0, |n| n + 1))
+        .find(|&start| is_key(table[start..].lines().next().unwrap_or(""), key))?;
+    let indent = &table[line_start
+        ..line_start + table[line_start..].len() - table[line_start..].trim_start().len()];
+    let value_start = line_start + table[line_start..].find('=')? + 1;
+
+    let rest = &table[value_start..];
+    let value_len = if rest.trim_start().starts_with('{') {
+        let mut depth = 0;
+        rest.char_indices().find_map(|(i, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 1 => return Some(i + 1),
+                '}' => depth -= 1,
+                _ => {}
+            }
+            None
+        })?
+    } else {
+        rest.find([',', '\n']).unwrap_or(rest.len())
+    };
+
+    let mut value = " {\n".to_string();
+    for (field, v) in fields {
+        value += &format!("{}    {} = {},\n", indent, field, v);
+    }
+    value += &format!("{}}}", indent);
+
+    let mut table = table.to_string();
+    table.replace_range(value_start..value_start + value_len, &value);
+    Some(table)
+}
+
+fn bgen_fields(bgen: BuildingGen) -> Vec<(&'static str, String)> {
+    let kind = |kind: &str| ("kind", lua_string(kind));
+    match bgen {
+        BuildingGen::House => vec![kind("house")],
+        BuildingGen::Farm => vec![kind("farm")],
+        BuildingGen::CenteredDoor { vertical_factor } => vec![
+            kind("centered_door"),
+            ("vertical_factor", format!("{:?}", vertical_factor)),
+        ],
+        BuildingGen::NoWalkway { door_pos } => vec![
+            kind("no_walkway"),
+            (
+                "door_pos",
+                format!("{{ x = {:?}, y = {:?} }}", door_pos.x, door_pos.y),
+            ),
+        ],
+    }
+}
+
+/// Renames the item where other prototypes reference it: the `{"name", amount}` and
+/// `{id = "name", amount = n}` rows of recipes and warehouses, and the `export = "name"`
+/// objectives of scenarios. The name is matched in single or double quotes, the definitions of
+/// items and the other lists of names are left alone.
+fn rename_refs(src: &str, old: &str, new: &str) -> String {
+    let mut refs: Vec<(usize, usize)> = [lua_string(old), format!("'{}'", old)]
+        .iter()
+        .flat_map(|quoted| {
+            src.match_indices(quoted.as_str())
+                .map(|(i, m)| (i, i + m.len()))
+        })
+        .filter(|&(start, end)| is_item_ref(src, start, end))
+        .collect();
+    refs.sort_unstable();
+
+    let new = lua_string(new);
+    let mut renamed = String::with_capacity(src.len());
+    let mut last = 0;
+    for (start, end) in refs {
+        renamed += &src[last..start];
+        renamed += &new;
+        last = end;
+    }
+    renamed += &src[last..];
+    renamed
+}
+
+/// Whether the quoted name between start and end is an item referenced by a recipe row or an
+/// export objective
+fn is_item_ref(src: &str, start: usize, end: usize) -> bool {
+    let before = &src[..start];
+    let after = src[end..].trim_start();
+    let is_row = before.trim_end().ends_with('{')
+        && after.strip_prefix(',').map_or(false, |amount| {
+            amount
+                .trim_start()
+                .starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        });
+    is_row || assigned_to(before, "id") || assigned_to(before, "export")
+}
+
+fn lua_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The companies consuming or producing the item, with how they use it
+pub fn item_users(id: ItemID) -> Vec<(&'static GoodsCompanyPrototype, &'static str)> {
+    let mut users = vec![];
+    for comp in prototypes_iter::<GoodsCompanyPrototype>() {
+        let Some(ref recipe) = comp.recipe else {
+            continue;
+        };
+        if recipe.consumption.iter().any(|item| item.id == id) {
+            users.push((comp, "consumes"));
+        }
+        if recipe.production.iter().any(|item| item.id == id) {
+            users.push((comp, "produces"));
+        }
+    }
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec2;
+
+    const ITEMS: &str = r#"data:extend {
+    {
+        type = "item",
+        name = "cereal",
+        label = "Cereal",
+    },
+    {
+        type = "item",
+        name = "flour",
+        label = "Flour", -- from the mill
+        optout_exttrade = true,
+    },
+    {
+        type = "item",
+        name = "bread",
+        label = "Bread",
+    },
+}
+"#;
+
+    const COMPANIES: &str = r#"data:extend {
+    {
+        type = "goods-company",
+        name = "bakery",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        recipe = {
+            consumption = {{"flour", 1}},
+            production = {{"bread", 1}},
+            duration = "100s",
+        },
+        size = 10.0,
+    },
+    {
+        type = "goods-company",
+        name = "flour-factory",
+        bgen = "farm",
+        recipe = {
+            consumption = {
+                {"cereal", 1},
+            },
+            production = {{"flour", 10}},
+        },
+    },
+}
+"#;
+
+    #[test]
+    fn table_block_is_the_whole_table() {
+        let block = table_block(ITEMS, "flour").unwrap();
+        let table = &ITEMS[block];
+        assert!(table.starts_with('{'));
+        assert!(table.ends_with('}'));
+        assert!(table.contains(r#"name = "flour""#));
+        assert!(table.contains("optout_exttrade"));
+        assert!(!table.contains("cereal"));
+        assert!(!table.contains("bread"));
+
+        // the nested tables are part of the block
+        let block = table_block(COMPANIES, "bakery").unwrap();
+        assert!(COMPANIES[block]
+            .trim_end_matches('}')
+            .contains("size = 10.0,"));
+    }
+
+    #[test]
+    fn table_block_only_matches_names() {
+        let src = r#"{ label = "bread", name = "toast" }, { name = "bread" }"#;
+        assert_eq!(
+            &src[table_block(src, "bread").unwrap()],
+            r#"{ name = "bread" }"#
+        );
+        assert!(table_block(ITEMS, "bre").is_none());
+        assert!(table_block(ITEMS, "Cereal").is_none());
+    }
+
+    #[test]
+    fn deleted_table_leaves_no_blank_line() {
+        let mut src = ITEMS.to_string();
+        let block = table_block(&src, "flour").unwrap();
+        src.replace_range(line_range(&src, block), "");
+
+        assert!(table_block(&src, "flour").is_none());
+        assert!(src.contains("    {\n        type = \"item\",\n        name = \"bread\""));
+        assert!(src.contains("label = \"Cereal\",\n    },\n    {\n"));
+        assert_eq!(src.matches("type = \"item\"").count(), 2);
+    }
+
+    #[test]
+    fn set_field_updates_adds_and_removes() {
+        let block = table_block(ITEMS, "flour").unwrap();
+        let table = &ITEMS[block];
+
+        let t = set_field(table, "label", Some(r#""Fine flour""#));
+        assert!(t.contains(r#"        label = "Fine flour","#));
+        assert!(!t.contains("from the mill"));
+
+        let t = set_field(&t, "optout_exttrade", None);
+        assert!(!t.contains("optout_exttrade"));
+
+        let t = set_field(&t, "color", Some("1"));
+        assert!(t.ends_with("        color = 1,\n    }"));
+
+        // the other fields are kept as written
+        assert!(t.contains(r#"        name = "flour","#));
+        assert_eq!(set_field(&t, "unknown", None), t);
+    }
+
+    #[test]
+    fn set_table_field_replaces_one_line_and_nested_values() {
+        let fields = bgen_fields(BuildingGen::NoWalkway {
+            door_pos: vec2(1.5, -2.0),
+        });
+
+        let block = table_block(COMPANIES, "flour-factory").unwrap();
+        let t = set_table_field(&COMPANIES[block], "bgen", &fields).unwrap();
+        assert!(t.contains(
+            "        bgen = {\n            kind = \"no_walkway\",\n            door_pos = { x = 1.5, y = -2.0 },\n        },\n        recipe"
+        ));
+
+        let block = table_block(COMPANIES, "bakery").unwrap();
+        let t = set_table_field(&COMPANIES[block.clone()], "bgen", &fields).unwrap();
+        assert!(!t.contains("centered_door"));
+        assert!(!t.contains("vertical_factor"));
+        assert!(t.contains("door_pos = { x = 1.5, y = -2.0 },\n        },\n        recipe"));
+
+        assert!(set_table_field(&COMPANIES[block], "zone", &fields).is_none());
+    }
+
+    #[test]
+    fn single_line_values() {
+        let block = table_block(COMPANIES, "bakery").unwrap();
+        assert!(is_single_line(&COMPANIES[block], "consumption"));
+        let block = table_block(COMPANIES, "flour-factory").unwrap();
+        assert!(!is_single_line(&COMPANIES[block.clone()], "consumption"));
+        assert!(is_single_line(&COMPANIES[block.clone()], "production"));
+        assert!(!is_single_line(&COMPANIES[block], "unknown"));
+    }
+
+    #[test]
+    fn company_edit_rewrites_its_table_only() {
+        let mut src = COMPANIES.to_string();
+        edit_company(
+            &mut src,
+            &CompanyEdit {
+                company: "bakery".to_string(),
+                bgen: BuildingGen::CenteredDoor {
+                    vertical_factor: 0.5,
+                },
+                recipe: Some(RecipeEdit {
+                    consumption: vec![("flour".to_string(), 2), ("cereal".to_string(), 1)],
+                    production: vec![],
+                }),
+            },
+        )
+        .unwrap();
+
+        assert!(src.contains(r#"consumption = {{"flour", 2}, {"cereal", 1}},"#));
+        assert!(src.contains("production = {},"));
+        assert!(src.contains("vertical_factor = 0.5,"));
+        assert!(src.contains(r#"duration = "100s","#));
+        let factory = table_block(&src, "flour-factory").unwrap();
+        assert_eq!(
+            &src[factory],
+            &COMPANIES[table_block(COMPANIES, "flour-factory").unwrap()]
+        );
+    }
+
+    #[test]
+    fn company_edit_refuses_recipes_over_several_lines() {
+        let mut src = COMPANIES.to_string();
+        let edit = CompanyEdit {
+            company: "flour-factory".to_string(),
+            bgen: BuildingGen::Farm,
+            recipe: Some(RecipeEdit {
+                consumption: vec![],
+                production: vec![],
+            }),
+        };
+        assert!(edit_company(&mut src, &edit).is_err());
+
+        let unknown = CompanyEdit {
+            company: "mill".to_string(),
+            ..edit
+        };
+        assert!(edit_company(&mut src, &unknown).is_err());
+    }
+
+    #[test]
+    fn rename_refs_renames_the_references_only() {
+        let src = r#"
+        consumption = {{"flour", 1}, { "flour" , 2 }, {'flour', 3}},
+        production = {{id = "flour", amount = 1}},
+        objectives = {{ export = "flour", amount = 100 }},
+        stock = {
+            {"flour", -1},
+        },
+        name = "flour",
+        locked = {"flour", "hotel"},
+        label = "flour",
+        other = {{"flour-mix", 1}, {"cereal", 1}},
+        "#;
+        let renamed = rename_refs(src, "flour", "dough");
+
+        assert!(renamed.contains(r#"consumption = {{"dough", 1}, { "dough" , 2 }, {"dough", 3}},"#));
+        assert!(renamed.contains(r#"production = {{id = "dough", amount = 1}},"#));
+        assert!(renamed.contains(r#"{{ export = "dough", amount = 100 }}"#));
+        assert!(renamed.contains(r#"{"dough", -1},"#));
+
+        assert!(renamed.contains(r#"name = "flour","#));
+        assert!(renamed.contains(r#"locked = {"flour", "hotel"},"#));
+        assert!(renamed.contains(r#"label = "flour","#));
+        assert!(renamed.contains(r#"other = {{"flour-mix", 1}, {"cereal", 1}},"#));
+    }
+
+    #[test]
+    fn add_item_appends_to_the_table() {
+        let mut src = ITEMS.to_string();
+        add_item(&mut src, "new-item").unwrap();
+        assert!(src.ends_with(
+            "        label = \"Bread\",\n    },\n    {\n        type = \"item\",\n        name = \"new-item\",\n        label = \"New item\",\n    },\n}\n"
+        ));
+
+        // a comma is added after the last table when it has none
+        let mut src = "data:extend {\n    { name = \"a\" }\n}".to_string();
+        add_item(&mut src, "b").unwrap();
+        assert!(src.starts_with("data:extend {\n    { name = \"a\" },\n    {"));
+        assert!(table_block(&src, "b").is_some());
+
+        assert!(add_item(&mut "no table".to_string(), "b").is_err());
+    }
+
+    #[test]
+    fn lua_strings_are_escaped() {
+        assert_eq!(lua_string("flour"), r#""flour""#);
+        assert_eq!(lua_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+}
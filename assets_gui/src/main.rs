@@ -6,26 +6,41 @@ use engine::{
     SpriteBatchBuilder,
 };
 use geom::{vec3, InfiniteFrustrum, LinearColor, Plane, Vec2, Vec3};
-use prototypes::{try_prototype, RenderAsset};
+use prototypes::{try_prototype, ItemID, RenderAsset, SourcesWatcher};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use crate::gizmo::DoorGizmo;
+use crate::lua_source::{CompanyEdit, ItemEdit};
 use crate::orbit_camera::OrbitCamera;
 use crate::yakui_gui::{Gui, Inspected, Shown};
 
+mod gizmo;
 mod lod;
+mod lua_source;
 mod orbit_camera;
+mod report;
 mod yakui_gui;
 
+/// Time between two looks at the prototype files, to follow the edits made outside the editor
+const CHECK_SOURCES_EVERY: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub enum GUIAction {
     GenerateLOD(PathBuf, LodGenerateParams),
+    EditItem(ItemEdit),
+    EditCompany(CompanyEdit),
+    ExportReport,
 }
 
 struct State {
     camera: OrbitCamera,
     last_inspect: Inspected,
     gui: Gui,
+    gizmo: DoorGizmo,
     actions: Vec<GUIAction>,
+    sources: SourcesWatcher,
+    last_sources_check: Instant,
 }
 
 impl engine::framework::State for State {
@@ -48,7 +63,10 @@ impl engine::framework::State for State {
             camera,
             last_inspect: Inspected::None,
             gui,
+            gizmo: DoorGizmo::default(),
             actions: vec![],
+            sources: SourcesWatcher::default(),
+            last_sources_check: Instant::now(),
         }
     }
 
@@ -58,12 +76,16 @@ impl engine::framework::State for State {
         for action in std::mem::take(&mut self.actions) {
             self.do_action(ctx, action);
         }
+        self.reload_changed_sources();
 
         if self.gui.inspected != self.last_inspect {
             self.last_inspect = self.gui.inspected;
             self.gui.shown = create_shown(&mut ctx.gfx, self, self.gui.inspected);
         }
 
+        self.update_gizmo(ctx);
+        self.gui.report.step(&mut ctx.gfx);
+
         let gfx = &mut ctx.gfx;
 
         let params = gfx.render_params.value_mut();
@@ -92,6 +114,9 @@ impl engine::framework::State for State {
 
     fn render(&mut self, fc: &mut FrameContext) {
         fc.draw(self.gui.shown.clone());
+        if let Some(ref gizmo) = self.gizmo.mesh {
+            fc.draw(gizmo.clone());
+        }
     }
 
     fn resized(&mut self, ctx: &mut Context, size: (u32, u32, f64)) {
@@ -120,15 +145,93 @@ impl State {
 
                 self.last_inspect = Inspected::None;
             }
+            GUIAction::EditItem(ref edit) => match lua_source::apply_item_edit(edit) {
+                Ok(inspect) => {
+                    self.gui.edit_error = None;
+                    // the form is filled again from the reloaded prototype
+                    self.gui.item_form.item = None;
+                    self.gui.inspected = match inspect {
+                        Some(name) => Inspected::Item(ItemID::new(&name)),
+                        None => Inspected::None,
+                    };
+                }
+                Err(e) => {
+                    log::error!("could not edit the item: {}", e);
+                    self.gui.edit_error = Some(e);
+                }
+            },
+            GUIAction::EditCompany(ref edit) => match lua_source::apply_company_edit(edit) {
+                Ok(()) => {
+                    self.gui.edit_error = None;
+                    self.gui.company_form.company = None;
+                }
+                Err(e) => {
+                    log::error!("could not edit the company: {}", e);
+                    self.gui.edit_error = Some(e);
+                }
+            },
+            GUIAction::ExportReport => self.gui.report.start(),
+        }
+
+        if matches!(action, GUIAction::EditItem(_) | GUIAction::EditCompany(_)) {
+            // the edited files were reloaded already
+            self.sources.changed("./");
+        }
+    }
+
+    /// Reloads the prototypes when their files were edited outside the editor, the forms and the
+    /// shown model are filled again from them
+    fn reload_changed_sources(&mut self) {
+        if self.last_sources_check.elapsed() < CHECK_SOURCES_EVERY {
+            return;
+        }
+        self.last_sources_check = Instant::now();
+        if !self.sources.changed("./") {
+            return;
+        }
+
+        match lua_source::reload() {
+            Ok(()) => {
+                self.gui.edit_error = None;
+                self.gui.item_form.item = None;
+                self.gui.company_form.company = None;
+                self.last_inspect = Inspected::None;
+            }
+            Err(e) => {
+                log::error!("could not reload the prototypes: {}", e);
+                self.gui.edit_error = Some(e);
+            }
+        }
+    }
+
+    /// The footprint and door of the shown company, dragging the door marks the company as modified
+    fn update_gizmo(&mut self, ctx: &mut Context) {
+        self.gizmo.mesh = None;
+        let Inspected::Company(id) = self.gui.inspected else {
+            return;
+        };
+        if matches!(self.gui.shown, Shown::None | Shown::Error(_)) {
+            return;
+        }
+        let form = &mut self.gui.company_form;
+        if form.company != Some(id) {
+            return;
         }
+        let (Some(comp), Some(bgen)) = (try_prototype(id), form.bgen.as_mut()) else {
+            return;
+        };
+        form.changed |= self.gizmo.update(ctx, &self.camera.camera, comp, bgen);
     }
 }
 
 fn create_shown(gfx: &mut GfxContext, _state: &State, inspected: Inspected) -> Shown {
     match inspected {
-        Inspected::None => Shown::None,
+        Inspected::None | Inspected::Item(_) => Shown::None,
         Inspected::Company(i) => {
-            let comp = try_prototype(i).unwrap();
+            // the company may have been removed from the sources
+            let Some(comp) = try_prototype(i) else {
+                return Shown::None;
+            };
             match comp.asset {
                 RenderAsset::Sprite { ref path } => {
                     let tex = match gfx.try_texture(path, "sprite texture") {
@@ -0,0 +1,188 @@
+//! A report of the models of all the companies, to get an overview of the asset pack.
+//! One company is done per frame so that the UI stays responsive, its problems are written in the
+//! report instead of stopping the export.
+
+use std::path::{Path, PathBuf};
+
+use engine::meshload::load_mesh_with_properties;
+use engine::wgpu::{TextureFormat, TextureUsages};
+use engine::{GfxContext, TextureBuilder};
+use geom::{Camera, Degrees, Vec3};
+use prototypes::{
+    prototypes_iter, try_prototype, GoodsCompanyID, GoodsCompanyPrototype, RenderAsset,
+};
+
+const REPORT_DIR: &str = "assets/generated/report";
+const THUMBNAIL_SIZE: u32 = 256;
+/// More draw calls than this are flagged, each material of a model is drawn separately
+const MAX_DRAW_CALLS: usize = 8;
+const HEADER: &str = "company,asset,vertices,triangles,materials,textures,draw_calls,lods,problems";
+
+#[derive(Default)]
+pub struct Report {
+    pending: Vec<GoodsCompanyID>,
+    total: usize,
+    rows: Vec<String>,
+    /// Where the last report was written, or why it could not be
+    pub result: Option<Result<PathBuf, String>>,
+}
+
+impl Report {
+    pub fn start(&mut self) {
+        self.pending = prototypes_iter::<GoodsCompanyPrototype>()
+            .map(|comp| comp.id)
+            .collect();
+        self.pending.reverse();
+        self.total = self.pending.len();
+        self.rows.clear();
+        self.result = None;
+    }
+
+    pub fn running(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Number of companies done, and the total
+    pub fn progress(&self) -> (usize, usize) {
+        (self.total - self.pending.len(), self.total)
+    }
+
+    /// Reports the next company, and writes the report after the last one
+    pub fn step(&mut self, gfx: &mut GfxContext) {
+        let Some(id) = self.pending.pop() else {
+            return;
+        };
+        if let Some(comp) = try_prototype(id) {
+            self.rows.push(report_company(gfx, comp));
+        }
+        if self.pending.is_empty() {
+            self.result = Some(self.write());
+        }
+    }
+
+    fn write(&self) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(REPORT_DIR).map_err(|e| e.to_string())?;
+        let path = Path::new(REPORT_DIR).join("report.csv");
+        let mut csv = HEADER.to_string();
+        for row in &self.rows {
+            csv.push('\n');
+            csv.push_str(row);
+        }
+        csv.push('\n');
+        std::fs::write(&path, csv).map_err(|e| format!("{}: {}", path.display(), e))?;
+        log::info!("wrote the report of {} companies", self.rows.len());
+        Ok(path)
+    }
+}
+
+/// One line of the report, the thumbnail of the model is saved next to it
+fn report_company(gfx: &mut GfxContext, comp: &GoodsCompanyPrototype) -> String {
+    let mut fields = vec![comp.name.clone(), comp.asset.to_string()];
+    let mut problems = vec![];
+
+    match comp.asset {
+        RenderAsset::Sprite { ref path } => {
+            if let Err(e) = gfx.try_texture(path, "report sprite") {
+                problems.push(format!("could not load the sprite: {}", e));
+            }
+        }
+        RenderAsset::Mesh { ref path } => {
+            if !Path::new("assets/models").join(path).exists() {
+                problems.push("missing file".to_string());
+            }
+            match load_mesh_with_properties(gfx, path, false) {
+                Ok((mesh, cpu)) => {
+                    let lod = &mesh.lods[0];
+                    let mut materials = vec![];
+                    for &(material, _) in &lod.primitives {
+                        if !materials.contains(&material) {
+                            materials.push(material);
+                        }
+                    }
+                    fields.extend([
+                        lod.n_vertices.to_string(),
+                        (lod.n_indices / 3).to_string(),
+                        materials.len().to_string(),
+                        cpu.n_textures.to_string(),
+                        lod.draw_calls().to_string(),
+                        mesh.lods.len().to_string(),
+                    ]);
+
+                    if lod.draw_calls() > MAX_DRAW_CALLS {
+                        problems.push(format!("{} draw calls", lod.draw_calls()));
+                    }
+                    if mesh.lods.len() == 1 {
+                        problems.push("no LODs".to_string());
+                    }
+                    if let Err(e) = save_thumbnail(gfx, &mesh, &comp.name) {
+                        problems.push(format!("could not save the thumbnail: {}", e));
+                    }
+                }
+                Err(e) => problems.push(format!("could not load the model: {:?}", e)),
+            }
+        }
+    }
+
+    // the sprites have no stats
+    fields.resize(HEADER.split(',').count() - 1, String::new());
+    fields.push(problems.join("; "));
+    fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Renders the model from the same angle as the building icons of the game
+fn save_thumbnail(gfx: &GfxContext, mesh: &engine::Mesh, name: &str) -> Result<(), String> {
+    let dir = Path::new(REPORT_DIR).join("thumbnails");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut cam = Camera::new(Vec3::ZERO, THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32);
+    cam.fovy = 30.0;
+    cam.pitch = Degrees(35.0).into();
+    cam.yaw = Degrees(-130.0).into();
+    let aabb3 = mesh.lods[0].aabb3;
+    cam.pos = aabb3.center();
+    cam.dist = aabb3.ll.distance(aabb3.ur);
+    cam.update();
+
+    let t = TextureBuilder::empty(
+        THUMBNAIL_SIZE,
+        THUMBNAIL_SIZE,
+        1,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+    .with_label("report thumbnail")
+    .with_usage(
+        TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+    )
+    .build_no_queue(&gfx.device);
+    let t_msaa = TextureBuilder::empty(
+        THUMBNAIL_SIZE,
+        THUMBNAIL_SIZE,
+        1,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+    .with_label("report thumbnail msaa")
+    .with_usage(TextureUsages::RENDER_ATTACHMENT)
+    .with_sample_count(4)
+    .build_no_queue(&gfx.device);
+
+    mesh.render_to_texture(&cam, gfx, &t, &t_msaa);
+    t.save_to_file(
+        &gfx.device,
+        &gfx.queue,
+        dir.join(format!("{}.png", name)),
+        0,
+    );
+    Ok(())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -1,7 +1,8 @@
-use yakui::widgets::{CountGrid, List, Pad, StateResponse};
+use yakui::widgets::{CountGrid, List, Pad, StateResponse, TextBox};
 use yakui::{
-    colored_box_container, column, constrained, divider, row, use_state, Constraints,
-    CrossAxisAlignment, MainAxisAlignment, MainAxisSize, Response, Vec2,
+    align, colored_box_container, column, constrained, divider, row, use_state, Alignment,
+    Constraints, CrossAxisAlignment, MainAxisAlignItems, MainAxisAlignment, MainAxisSize, Response,
+    Vec2,
 };
 
 use engine::meshload::CPUMesh;
@@ -9,20 +10,28 @@ use engine::wgpu::RenderPass;
 use engine::{set_cursor_icon, CursorIcon, Drawable, GfxContext, InstancedMesh, Mesh, SpriteBatch};
 use geom::Matrix4;
 use goryak::{
-    background, button_primary, checkbox_value, constrained_viewport, dragvalue, icon,
-    interact_box_radius, is_hovered, on_secondary_container, on_surface, outline_variant,
-    round_rect, secondary_container, set_theme, surface, surface_variant, textc, use_changed,
+    background, button_primary, button_secondary, center_width, checkbox_value, combo_box,
+    constrained_viewport, dragvalue, icon, interact_box_radius, is_hovered, minrow, on_secondary,
+    on_secondary_container, on_surface, outline_variant, round_rect, secondary,
+    secondary_container, set_theme, surface, surface_variant, textc, use_changed, ProgressBar,
     RoundRect, Theme, VertScrollSize,
 };
-use prototypes::{prototypes_iter, GoodsCompanyID, GoodsCompanyPrototype};
+use prototypes::{
+    prototypes_iter, try_prototype, BuildingGen, GoodsCompanyID, GoodsCompanyPrototype, ItemID,
+    ItemPrototype, RecipeItem,
+};
 
+use crate::gizmo::door_pos;
 use crate::lod::LodGenerateParams;
+use crate::lua_source::{item_users, CompanyEdit, ItemEdit, RecipeEdit};
+use crate::report::Report;
 use crate::{GUIAction, State};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum Inspected {
     None,
     Company(GoodsCompanyID),
+    Item(ItemID),
 }
 
 #[derive(Clone)]
@@ -36,6 +45,11 @@ pub enum Shown {
 pub struct Gui {
     pub inspected: Inspected,
     pub shown: Shown,
+    pub item_form: ItemForm,
+    pub company_form: CompanyForm,
+    pub report: Report,
+    /// Why the last edit of the sources was rejected
+    pub edit_error: Option<String>,
 }
 
 impl Gui {
@@ -43,6 +57,60 @@ impl Gui {
         Self {
             inspected: Inspected::None,
             shown: Shown::None,
+            item_form: ItemForm::default(),
+            company_form: CompanyForm::default(),
+            report: Report::default(),
+            edit_error: None,
+        }
+    }
+}
+
+/// Values of the inspected item being edited, applied when saving
+#[derive(Default)]
+pub struct ItemForm {
+    pub item: Option<ItemID>,
+    pub name: String,
+    pub label: String,
+    pub optout_exttrade: bool,
+    pub update_refs: bool,
+}
+
+impl ItemForm {
+    fn new(item: &ItemPrototype) -> Self {
+        Self {
+            item: Some(item.id),
+            name: item.name.clone(),
+            label: item.label.clone(),
+            optout_exttrade: item.optout_exttrade,
+            update_refs: true,
+        }
+    }
+}
+
+/// Building generator and recipe of the inspected company being edited, applied when saving
+#[derive(Default)]
+pub struct CompanyForm {
+    pub company: Option<GoodsCompanyID>,
+    /// Also moved by dragging the door in the viewport
+    pub bgen: Option<BuildingGen>,
+    pub consumption: Vec<(ItemID, i32)>,
+    pub production: Vec<(ItemID, i32)>,
+    /// The form differs from the sources, shows the Save button
+    pub changed: bool,
+    pub warning: Option<&'static str>,
+}
+
+impl CompanyForm {
+    fn new(comp: &GoodsCompanyPrototype) -> Self {
+        let rows = |items: &[RecipeItem]| items.iter().map(|x| (x.id, x.amount)).collect();
+        let recipe = comp.recipe.as_ref();
+        Self {
+            company: Some(comp.id),
+            bgen: Some(comp.bgen),
+            consumption: recipe.map(|r| rows(&r.consumption)).unwrap_or_default(),
+            production: recipe.map(|r| rows(&r.production)).unwrap_or_default(),
+            changed: false,
+            warning: None,
         }
     }
 }
@@ -54,6 +122,8 @@ impl State {
                 self.explorer();
                 self.model_properties();
                 //self.properties();
+                self.item_properties();
+                self.company_properties();
             });
         });
     }
@@ -80,6 +150,7 @@ impl State {
                                 }
                             });
                         });
+                        self.report_progress();
                         VertScrollSize::Percent(1.0).show(|| {
                             let mut l = List::column();
                             l.cross_axis_alignment = CrossAxisAlignment::Stretch;
@@ -108,6 +179,39 @@ impl State {
                                         );
                                     }
                                 }
+
+                                let items_open = use_state(|| false);
+                                Self::explore_item(
+                                    0,
+                                    false,
+                                    "Items".to_string(),
+                                    Some(items_open.get()),
+                                    || {
+                                        items_open.modify(|x| !x);
+                                    },
+                                );
+                                if items_open.get() {
+                                    Self::explore_item(
+                                        4,
+                                        false,
+                                        "+ New item".to_string(),
+                                        None,
+                                        || {
+                                            self.actions.push(GUIAction::EditItem(ItemEdit::Add));
+                                        },
+                                    );
+                                    for item in prototypes_iter::<ItemPrototype>() {
+                                        Self::explore_item(
+                                            4,
+                                            Inspected::Item(item.id) == self.gui.inspected,
+                                            item.name.to_string(),
+                                            None,
+                                            || {
+                                                self.gui.inspected = Inspected::Item(item.id);
+                                            },
+                                        );
+                                    }
+                                }
                             });
                         });
                     });
@@ -117,6 +221,43 @@ impl State {
         resizebar_vert(&mut off, false);
     }
 
+    /// The button to export the report of the models, then its progress and where it was written
+    fn report_progress(&mut self) {
+        Pad::all(5.0).show(|| {
+            let mut l = List::column();
+            l.item_spacing = 5.0;
+            l.cross_axis_alignment = CrossAxisAlignment::Center;
+            l.show(|| {
+                let report = &self.gui.report;
+                if report.running() {
+                    let (done, total) = report.progress();
+                    ProgressBar {
+                        value: done as f32 / total.max(1) as f32,
+                        size: Vec2::new(250.0, 25.0),
+                        color: secondary().adjust(0.7),
+                    }
+                    .show_children(|| {
+                        textc(on_secondary(), format!("Exporting {}/{}", done, total));
+                    });
+                    return;
+                }
+
+                if button_primary("Export report").show().clicked {
+                    self.actions.push(GUIAction::ExportReport);
+                }
+                match report.result {
+                    Some(Ok(ref path)) => {
+                        textc(on_surface(), format!("Written to {}", path.display()));
+                    }
+                    Some(Err(ref e)) => {
+                        textc(on_surface(), format!("Could not write the report: {}", e));
+                    }
+                    None => {}
+                }
+            });
+        });
+    }
+
     fn explore_item(
         indent: usize,
         selected: bool,
@@ -221,8 +362,8 @@ impl State {
                             }
 
                             textc(tc, "Triangles");
-                            for lod in &*mesh.lods {
-                                textc(tc, format!("{}", lod.n_indices / 3));
+                            for triangles in &props.lod_triangles {
+                                textc(tc, format!("{}", triangles));
                             }
 
                             textc(tc, "Draw calls");
@@ -261,6 +402,172 @@ impl State {
         });
     }
 
+    fn item_properties(&mut self) {
+        let Inspected::Item(id) = self.gui.inspected else {
+            return;
+        };
+        let Some(item) = try_prototype(id) else {
+            return;
+        };
+        if self.gui.item_form.item != Some(id) {
+            self.gui.item_form = ItemForm::new(item);
+            self.gui.edit_error = None;
+        }
+        let users = item_users(id);
+
+        properties_container(|| {
+            let form = &mut self.gui.item_form;
+
+            label("Name");
+            text_inp(&mut form.name);
+
+            label("Label");
+            text_inp(&mut form.label);
+
+            label("optout_exttrade");
+            center_width(|| {
+                form.optout_exttrade = yakui::checkbox(form.optout_exttrade).checked;
+            });
+
+            let renamed = form.name != item.name;
+            if renamed && !users.is_empty() {
+                label("Rename in recipes");
+                center_width(|| {
+                    form.update_refs = yakui::checkbox(form.update_refs).checked;
+                });
+            }
+
+            // two items can't share a name, the first one would be replaced by the second one
+            let taken = renamed && try_prototype(ItemID::new(&form.name)).is_some();
+            label(" ");
+            if form.name.is_empty() {
+                label("The name must not be empty");
+            } else if taken {
+                label("Name already taken");
+            } else if button_primary("Save").show().clicked {
+                self.actions.push(GUIAction::EditItem(ItemEdit::Update {
+                    name: item.name.clone(),
+                    new_name: form.name.clone(),
+                    label: form.label.clone(),
+                    optout_exttrade: form.optout_exttrade,
+                    update_refs: form.update_refs,
+                }));
+            }
+
+            label(" ");
+            if users.is_empty() {
+                if button_secondary("Delete").show().clicked {
+                    self.actions
+                        .push(GUIAction::EditItem(ItemEdit::Delete(item.name.clone())));
+                }
+            } else {
+                label("Used by recipes, can't delete");
+            }
+
+            label("Used by");
+            label(if users.is_empty() { "nothing" } else { " " });
+            for (comp, usage) in users {
+                label(usage);
+                if button_secondary(comp.name.clone()).show().clicked {
+                    self.gui.inspected = Inspected::Company(comp.id);
+                }
+            }
+
+            if let Some(ref e) = self.gui.edit_error {
+                label("Error");
+                label(e);
+            }
+        });
+    }
+
+    fn company_properties(&mut self) {
+        let Inspected::Company(id) = self.gui.inspected else {
+            return;
+        };
+        let Some(comp) = try_prototype(id) else {
+            return;
+        };
+        if self.gui.company_form.company != Some(id) {
+            self.gui.company_form = CompanyForm::new(comp);
+            self.gui.edit_error = None;
+        }
+
+        let items: Vec<&ItemPrototype> = prototypes_iter::<ItemPrototype>().collect();
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+
+        properties_container(|| {
+            let form = &mut self.gui.company_form;
+
+            if let Some(ref mut bgen) = form.bgen {
+                form.changed |= bgen_rows(bgen);
+            }
+
+            if comp.recipe.is_some() {
+                let (consumption_changed, _) =
+                    recipe_rows("consumption", &mut form.consumption, &items, &names);
+                let (production_changed, production_deleted) =
+                    recipe_rows("production", &mut form.production, &items, &names);
+                form.changed |= consumption_changed || production_changed;
+                if !form.production.is_empty() {
+                    form.warning = None;
+                } else if production_deleted {
+                    form.warning = Some("The company produces nothing, is it a mistake?");
+                }
+            }
+
+            let mut invalid = false;
+            for (list, rows) in [
+                ("consumption", &form.consumption),
+                ("production", &form.production),
+            ] {
+                for (i, &(item, _)) in rows.iter().enumerate() {
+                    let Some(proto) = try_prototype(item) else {
+                        label("Unknown item");
+                        label(&format!("row {} of {}", i + 1, list));
+                        invalid = true;
+                        continue;
+                    };
+                    if rows[..i].iter().any(|&(other, _)| other == item) {
+                        label("Duplicated");
+                        label(&format!("{} in {}", proto.name, list));
+                        invalid = true;
+                    }
+                }
+            }
+
+            if let Some(warning) = form.warning {
+                label("Warning");
+                label(warning);
+            }
+
+            if form.changed {
+                label(" ");
+                if invalid {
+                    label("Fix the items to save");
+                } else if button_primary("Save").show().clicked {
+                    let named = |rows: &[(ItemID, i32)]| -> Vec<(String, i32)> {
+                        rows.iter()
+                            .map(|&(item, amount)| (item.prototype().name.clone(), amount))
+                            .collect()
+                    };
+                    self.actions.push(GUIAction::EditCompany(CompanyEdit {
+                        company: comp.name.clone(),
+                        bgen: form.bgen.unwrap_or(comp.bgen),
+                        recipe: comp.recipe.is_some().then(|| RecipeEdit {
+                            consumption: named(&form.consumption),
+                            production: named(&form.production),
+                        }),
+                    }));
+                }
+            }
+
+            if let Some(ref e) = self.gui.edit_error {
+                label("Error");
+                label(e);
+            }
+        });
+    }
+
     /*
     fn properties(&mut self) {
         match self.gui.inspected {
@@ -397,7 +704,7 @@ impl State {
     }*/
 }
 
-/*fn properties_container(children: impl FnOnce()) {
+fn properties_container(children: impl FnOnce()) {
     let mut off = use_state(|| 350.0);
     resizebar_vert(&mut off, true);
     constrained(
@@ -421,7 +728,109 @@ impl State {
             });
         },
     );
-}*/
+}
+
+/// The rows of a list of the recipe in the grid: a title with a combo box to add an item, then an
+/// item picker, a delete button and the amount per row.
+/// Returns whether the rows changed and whether one was deleted.
+fn recipe_rows(
+    title: &'static str,
+    rows: &mut Vec<(ItemID, i32)>,
+    items: &[&ItemPrototype],
+    names: &[&str],
+) -> (bool, bool) {
+    let mut changed = false;
+    let mut deleted = None;
+
+    label(title);
+    let add_title = format!("Add {}", title);
+    let add_names: Vec<&str> = std::iter::once(add_title.as_str())
+        .chain(names.iter().copied())
+        .collect();
+    let mut add = 0;
+    if combo_box(&mut add, &add_names, 150.0) && add > 0 {
+        rows.push((items[add - 1].id, 1));
+        changed = true;
+    }
+
+    for (i, (item, amount)) in rows.iter_mut().enumerate() {
+        minrow(5.0, || {
+            if button_secondary("x").show().clicked {
+                deleted = Some(i);
+            }
+            // an item missing from the prototypes is shown as such until another one is picked
+            let Some(mut selected) = items.iter().position(|x| x.id == *item) else {
+                let mut unknown_names = vec!["unknown item"];
+                unknown_names.extend_from_slice(names);
+                let mut selected = 0;
+                if combo_box(&mut selected, &unknown_names, 150.0) && selected > 0 {
+                    *item = items[selected - 1].id;
+                    changed = true;
+                }
+                return;
+            };
+            if combo_box(&mut selected, names, 150.0) {
+                *item = items[selected].id;
+                changed = true;
+            }
+        });
+        let before = *amount;
+        Pad::all(5.0).show(|| {
+            dragvalue().min(1.0).show(amount);
+        });
+        changed |= *amount != before;
+    }
+
+    if let Some(i) = deleted {
+        rows.remove(i);
+        changed = true;
+    }
+    (changed, deleted.is_some())
+}
+
+/// The kind of the building generator and its parameters, the door can also be dragged in the
+/// viewport. Returns whether they changed.
+fn bgen_rows(bgen: &mut BuildingGen) -> bool {
+    let before = *bgen;
+
+    label("building generator");
+    label(match bgen {
+        BuildingGen::House => "house",
+        BuildingGen::Farm => "farm",
+        BuildingGen::CenteredDoor { .. } => "centered door",
+        BuildingGen::NoWalkway { .. } => "no walkway",
+    });
+
+    match bgen {
+        BuildingGen::CenteredDoor { vertical_factor } => {
+            label("vertical_factor");
+            Pad::all(5.0).show(|| {
+                dragvalue()
+                    .minmax(0.0..1.0)
+                    .step(0.01)
+                    .show(vertical_factor);
+            });
+        }
+        BuildingGen::NoWalkway { door_pos } => {
+            for (name, v) in [("door x", &mut door_pos.x), ("door y", &mut door_pos.y)] {
+                label(name);
+                Pad::all(5.0).show(|| {
+                    dragvalue().step(0.1).show(v);
+                });
+            }
+        }
+        BuildingGen::House | BuildingGen::Farm => {}
+    }
+
+    // the unit size compares the factors of the centered doors
+    door_pos(before, 1.0) != door_pos(*bgen, 1.0)
+}
+
+fn label(name: &str) {
+    Pad::all(3.0).show(|| {
+        textc(on_secondary_container(), name.to_string());
+    });
+}
 
 /// A horizontal resize bar.
 pub fn resizebar_vert(off: &mut Response<StateResponse<f32>>, scrollbar_on_left_side: bool) {
@@ -461,8 +870,8 @@ pub fn resizebar_vert(off: &mut Response<StateResponse<f32>>, scrollbar_on_left_
     });
 }
 
-/*fn text_inp(v: &mut String) {
-    center(|| {
+fn text_inp(v: &mut String) {
+    center_width(|| {
         let mut t = TextBox::new(v.clone());
         t.fill = Some(secondary());
         t.style.color = on_secondary();
@@ -470,7 +879,7 @@ pub fn resizebar_vert(off: &mut Response<StateResponse<f32>>, scrollbar_on_left_
             *v = x;
         }
     });
-}*/
+}
 
 impl Drawable for Shown {
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
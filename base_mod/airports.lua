@@ -0,0 +1,24 @@
+data:extend {
+    {
+        type = "airport",
+        order = "a-0",
+        name = "small-airport",
+        label = "Small Airport",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        -- the runway goes along the longest side
+        size = {360, 80},
+        asset = "rail_freight_station.glb",
+        price = 20000,
+        power_consumption = "20kW",
+        water_consumption = 4.0,
+        garbage_production = 6.0,
+        max_height_difference = 2.0,
+        flights_per_day = 8,
+        seats = 60,
+        business_share = 0.3,
+        noise = 12.0,
+    },
+}
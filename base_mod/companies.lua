@@ -16,10 +16,14 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 3,
+        work_hours = "6h -> 15h",
+        opening_hours = "7h -> 20h",
         size = 10.0,
         asset = "bakery.glb",
         price = 1000,
         power_consumption = "200W",
+        water_consumption = 0.2,
+        garbage_production = 2.0,
     },
     {
         type = "goods-company",
@@ -43,6 +47,8 @@ data:extend {
         asset = "flour_factory.glb",
         price = 1000,
         power_consumption = "10kW",
+        water_consumption = 2.0,
+        garbage_production = 5.0,
     },
     {
         type = "goods-company",
@@ -69,6 +75,12 @@ data:extend {
             randomize_filler = true,
         },
         power_consumption = "100W",
+        water_consumption = 5.0,
+        season_productivity = {
+            summer = 1.3,
+            autumn = 1.2,
+            winter = 0.5,
+        },
     },
     {
         type = "solar-panel",
@@ -109,10 +121,111 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        min_education = "basic",
         size = 165.0,
         asset = "coal_power_plant.glb",
         price = 1000,
         power_production = "2.46MW",
+        -- sells nothing, it is run at a loss to power the city
+        bankruptcy_days = 0,
+    },
+    {
+        type = "goods-company",
+        order = "b-3",
+        name = "battery",
+        label = "Battery storage",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_workers = 0,
+        size = 60.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 800,
+        storage_capacity = "10MWh",
+        max_charge_rate = "1MW",
+    },
+    {
+        type = "goods-company",
+        order = "b-4",
+        name = "water-pump",
+        label = "Water pump station",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_workers = 2,
+        size = 40.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 1500,
+        power_consumption = "20kW",
+        -- in cubic meters per hour, enough for about 200 houses
+        water_production = 100.0,
+        -- pumps from a river or a lake
+        shoreline = true,
+        -- sells nothing, it is run at a loss to supply the city
+        bankruptcy_days = 0,
+    },
+    {
+        type = "goods-company",
+        order = "b-5",
+        name = "landfill",
+        label = "Landfill",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        n_workers = 4,
+        size = 80.0,
+        asset = "assets/sprites/dirt.jpg",
+        price = 1000,
+        -- in kilograms, emptied at each visit of its truck
+        garbage_truck_capacity = 200.0,
+        -- sells nothing, it is run at a loss to keep the city clean
+        bankruptcy_days = 0,
+    },
+    {
+        type = "goods-company",
+        order = "b-6",
+        name = "incinerator",
+        label = "Incinerator",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        n_workers = 6,
+        size = 50.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 3000,
+        power_production = "500kW",
+        garbage_truck_capacity = 400.0,
+        bankruptcy_days = 0,
+    },
+    {
+        type = "goods-company",
+        order = "b-7",
+        name = "fire-station",
+        label = "Fire station",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        n_workers = 4,
+        size = 40.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 2000,
+        power_consumption = "5kW",
+        -- in meters, fires further away are left to other stations
+        service_radius = 1500.0,
+        bankruptcy_days = 0,
     },
     {
         type = "goods-company",
@@ -135,6 +248,7 @@ data:extend {
         asset = "assets/sprites/supermarket.png",
         price = 1000,
         power_consumption = "1kW",
+        garbage_production = 5.0,
     },
     {
         type = "goods-company",
@@ -157,6 +271,7 @@ data:extend {
         asset = "assets/sprites/clothes_store.png",
         price = 1000,
         power_consumption = "1kW",
+        garbage_production = 1.0,
     },
     {
         type = "goods-company",
@@ -180,6 +295,7 @@ data:extend {
         asset = "assets/sprites/cloth_factory.png",
         price = 1000,
         power_consumption = "10kW",
+        garbage_production = 5.0,
     },
     {
         type = "goods-company",
@@ -199,6 +315,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        min_education = "basic",
         size = 80.0,
         asset = "assets/sprites/textile_processing_facility.png",
         price = 1000,
@@ -222,6 +339,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 5,
+        min_education = "basic",
         size = 80.0,
         asset = "assets/sprites/polyester_refinery.png",
         price = 1000,
@@ -261,6 +379,7 @@ data:extend {
         },
         kind = "factory",
         n_trucks = 1,
+        truck = "heavy_truck",
         recipe = {
             consumption = {},
             production = {{"coal", 1}},
@@ -380,6 +499,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        min_education = "higher",
         size = 80.0,
         asset = "assets/sprites/hightech_facility.png",
         price = 1000,
@@ -396,6 +516,7 @@ data:extend {
         },
         kind = "factory",
         n_trucks = 1,
+        truck = "heavy_truck",
         recipe = {
             consumption = {},
             production = {{"iron-ore", 1}},
@@ -419,6 +540,7 @@ data:extend {
         },
         kind = "factory",
         n_trucks = 1,
+        truck = "heavy_truck",
         recipe = {
             consumption = {},
             production = {{"gold", 1}},
@@ -439,6 +561,7 @@ data:extend {
         bgen = "farm",
         kind = "factory",
         n_trucks = 1,
+        truck = "heavy_truck",
         recipe = {
             consumption = {},
             production = {{"tree-log", 1}},
@@ -541,6 +664,7 @@ data:extend {
         asset = "assets/sprites/slaughterhouse.png",
         price = 1000,
         power_consumption = "1kW",
+        water_consumption = 2.0,
     },
     {
         type = "goods-company",
@@ -561,6 +685,32 @@ data:extend {
         asset = "assets/sprites/animal_farm.png",
         price = 1000,
         power_consumption = "100W",
+        water_consumption = 3.0,
+    },
+    {
+        type = "goods-company",
+        order = "k-1",
+        name = "fishing-harbor",
+        label = "Fishing Harbor",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        recipe = {
+            consumption = {},
+            production = {{"raw-meat", 1}},
+            duration = "150s",
+            storage_multiplier = 5,
+        },
+        n_workers = 6,
+        size = 40.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 1200,
+        power_consumption = "500W",
+        water_consumption = 1.0,
+        shoreline = true,
     },
     {
         type = "goods-company",
@@ -609,5 +759,10 @@ data:extend {
             price_per_area = 100,
         },
         power_consumption = "100W",
+        water_consumption = 5.0,
+        season_productivity = {
+            summer = 1.2,
+            winter = 0,
+        },
     },
 }
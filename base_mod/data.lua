@@ -1,9 +1,19 @@
 require("items")
 require("companies")
 require("leisure")
+require("warehouses")
+require("schools")
+require("hotels")
+require("harbors")
+require("airports")
 require("colors")
 require("roadvehicles")
 require("rollingstock")
+require("trees")
+require("happiness")
+require("demographics")
+require("milestones")
+require("scenarios")
 
 data:extend {
     {
@@ -13,5 +23,16 @@ data:extend {
         asset = "rail_freight_station.glb",
         price = 1000,
         size = {160, 200},
+        throughput = 2000,
+    },
+    {
+        type = "passenger-station",
+        name = "passenger-station",
+        label = "Passenger Station",
+        asset = "rail_freight_station.glb",
+        price = 1500,
+        size = {40, 120},
+        train = {"passenger-emu-front", "passenger-emu-middle", "passenger-emu-rear"},
+        train_capacity = 300,
     }
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+data:extend {
+    type = "demographics",
+    name = "demographics",
+    label = "Demographics",
+
+    days_per_year = 4.0,
+    adult_age = 18.0,
+
+    couple_chance = 0.6,
+    birth_rate = 0.15,
+    max_parent_age = 45.0,
+    max_household = 5,
+
+    mortality_at_birth = 0.0005,
+    mortality_doubling_years = 8.0,
+}
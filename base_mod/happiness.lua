@@ -0,0 +1,21 @@
+data:extend {
+    type = "happiness",
+    name = "happiness",
+    label = "Happiness",
+
+    commute_weight = 1.0,
+    max_commute_minutes = 90.0,
+
+    food_weight = 2.0,
+    starving_days = 2.0,
+
+    utilities_weight = 2.0,
+
+    noise_weight = 0.5,
+    quiet_noise = 2.0,
+    max_noise = 10.0,
+
+    leisure_weight = 1.0,
+    weekly_leisure_visits = 2.0,
+    max_leisure_trip_minutes = 20.0,
+}
@@ -0,0 +1,23 @@
+data:extend {
+    {
+        type = "harbor",
+        order = "a-0",
+        name = "cargo-harbor",
+        label = "Cargo Harbor",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        -- the ships dock on the side facing away from the road
+        docks = {
+            {-12, 30},
+            {12, 30},
+        },
+        throughput = 1000,
+        size = 50.0,
+        asset = "external_trading.glb",
+        price = 6000,
+        power_consumption = "1kW",
+        shoreline = true,
+    },
+}
@@ -0,0 +1,19 @@
+data:extend {
+    {
+        type = "hotel",
+        order = "a-1",
+        name = "hotel",
+        label = "Hotel",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        rooms = 40,
+        size = 40.0,
+        asset = "cinema.glb",
+        price = "4000$",
+        power_consumption = "500W",
+        water_consumption = 1.0,
+        garbage_production = 2.0,
+    },
+}
@@ -16,4 +16,38 @@ data:extend {
         capacity = 50,
         entry_fee = "10$",
     },
+    {
+        type = "leisure",
+        order = "a-1",
+        name = "park",
+        label = "Park",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        size = 40.0,
+        asset = "pine.glb",
+        price = "2000$",
+        opening_hours = "6h -> 22h",
+        capacity = 100,
+        entry_fee = "0$",
+        walkable = true,
+    },
+    {
+        type = "leisure",
+        order = "a-2",
+        name = "plaza",
+        label = "Plaza",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        size = 25.0,
+        asset = "streetlamp.glb",
+        price = "1000$",
+        capacity = 40,
+        opening_hours = "0h -> 24h",
+        entry_fee = "0$",
+        walkable = true,
+    },
 }
\ No newline at end of file
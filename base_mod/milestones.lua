@@ -0,0 +1,27 @@
+data:extend {
+    {
+        type = "milestone",
+        order = "a",
+        name = "village",
+        label = "Village",
+        population = 50,
+        unlocks = {"supermarket", "clothes-store", "school", "park", "avenue"},
+    },
+    {
+        type = "milestone",
+        order = "b",
+        name = "town",
+        label = "Town",
+        population = 200,
+        unlocks = {"cinema", "plaza", "hotel", "passenger-station", "drive", "fire-station"},
+    },
+    {
+        type = "milestone",
+        order = "c",
+        name = "city",
+        label = "City",
+        population = 1000,
+        money = 100000,
+        unlocks = {"high-tech-store", "high-tech-facility", "furniture-store", "highway", "small-airport"},
+    },
+}
@@ -4,22 +4,64 @@ data:extend {
         order = "a-1",
         name = "simple_car",
         label = "Simple Car",
-        max_speed = 50.0,
-        acceleration = 8.0,
-        deceleration = 12.0,
+        max_speed = 40.0,
+        acceleration = 3.0,
+        deceleration = 6.0,
+        length = 4.5,
+        spawn_weight = 0.7,
         asset = "simple_car.glb",
         price = 100.0,
     },
+    {
+        type = "road-vehicle",
+        order = "a-2",
+        name = "compact_car",
+        label = "Compact Car",
+        max_speed = 30.0,
+        acceleration = 2.5,
+        deceleration = 6.0,
+        length = 3.8,
+        spawn_weight = 0.3,
+        asset = "simple_car.glb",
+        price = 80.0,
+    },
     {
         type = "road-vehicle",
         order = "b-1",
         name = "simple_truck",
         label = "simple truck",
         max_speed = 22.0,
-        acceleration = 6.0,
-        deceleration = 10.0,
+        acceleration = 2.5,
+        deceleration = 6.0,
+        length = 6.0,
+        capacity = 20,
         asset = "truck.glb",
         price = 100.0,
+    },
+    {
+        type = "road-vehicle",
+        order = "b-2",
+        name = "heavy_truck",
+        label = "Heavy Truck",
+        max_speed = 16.0,
+        acceleration = 1.5,
+        deceleration = 5.0,
+        length = 8.0,
+        capacity = 50,
+        asset = "truck.glb",
+        price = 150.0,
+    },
+    {
+        type = "road-vehicle",
+        order = "c-1",
+        name = "city_bus",
+        label = "City Bus",
+        max_speed = 20.0,
+        acceleration = 2.0,
+        deceleration = 6.0,
+        length = 9.0,
+        asset = "truck.glb",
+        price = 300.0,
     }
 }
 
@@ -0,0 +1,16 @@
+data:extend {
+    {
+        type = "scenario",
+        order = "a",
+        name = "first-village",
+        label = "First Village",
+        description = "An empty valley and a modest budget. Settle 20 citizens within 5 days without going into debt.",
+        terrain_size = 10,
+        money = 100000,
+        min_money = 0,
+        locked = {"highway", "hotel", "passenger-station"},
+        objectives = {
+            { population = 20, within = "5d" },
+        },
+    },
+}
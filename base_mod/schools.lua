@@ -0,0 +1,22 @@
+data:extend {
+    {
+        type = "school",
+        order = "a-1",
+        name = "school",
+        label = "School",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        capacity = 200,
+        service_radius = 1000.0,
+        max_education = "higher",
+        years_per_level = 6.0,
+        size = 80.0,
+        asset = "cinema.glb",
+        price = 3000,
+        power_consumption = "2kW",
+        water_consumption = 2.0,
+        garbage_production = 2.0,
+    },
+}
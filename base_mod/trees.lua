@@ -0,0 +1,44 @@
+data:extend {
+    {
+        type = "tree",
+        order = "a",
+        name = "pine",
+        label = "Pine",
+        min_size = 5.0,
+        max_size = 8.0,
+        tint = {
+            r = 0.6,
+            g = 0.65,
+            b = 0.6,
+        },
+        price = "5$",
+    },
+    {
+        type = "tree",
+        order = "b",
+        name = "spruce",
+        label = "Spruce",
+        min_size = 6.0,
+        max_size = 10.0,
+        tint = {
+            r = 0.45,
+            g = 0.55,
+            b = 0.5,
+        },
+        price = "8$",
+    },
+    {
+        type = "tree",
+        order = "c",
+        name = "birch",
+        label = "Birch",
+        min_size = 4.0,
+        max_size = 6.0,
+        tint = {
+            r = 0.8,
+            g = 0.85,
+            b = 0.55,
+        },
+        price = "4$",
+    },
+}
@@ -0,0 +1,22 @@
+data:extend {
+    {
+        type = "warehouse",
+        order = "a-0",
+        name = "food-warehouse",
+        label = "Food Warehouse",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        capacity = {
+            {"cereal", 200},
+            {"flour", 200},
+            {"vegetable", 200},
+            {"meat", 100},
+        },
+        size = 80.0,
+        asset = "flour_factory.glb",
+        price = 2000,
+        power_consumption = "1kW",
+    },
+}
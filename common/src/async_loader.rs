@@ -0,0 +1,129 @@
+//! Loads values on a pool of background threads, so that slow loads like reading and decoding
+//! assets don't stall the frame. The results are collected on the thread owning the loader.
+
+use crate::FastSet;
+use std::hash::Hash;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+const MAX_WORKERS: usize = 4;
+
+/// The workers are shared by all the loaders and started on the first request
+fn pool() -> &'static Mutex<Sender<Job>> {
+    static POOL: OnceLock<Mutex<Sender<Job>>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (tx, rx) = channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let n = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(MAX_WORKERS);
+        for i in 0..n {
+            let rx = rx.clone();
+            std::thread::Builder::new()
+                .name(format!("async loader {}", i))
+                .spawn(move || loop {
+                    let job = rx.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    job();
+                })
+                .expect("could not start the async loader");
+        }
+        Mutex::new(tx)
+    })
+}
+
+/// Deduplicates the requests of the same key while they are loading
+pub struct AsyncLoader<K, T> {
+    pending: FastSet<K>,
+    done_tx: Sender<(K, T)>,
+    done_rx: Receiver<(K, T)>,
+}
+
+impl<K, T> Default for AsyncLoader<K, T> {
+    fn default() -> Self {
+        let (done_tx, done_rx) = channel();
+        Self {
+            pending: Default::default(),
+            done_tx,
+            done_rx,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + Send + 'static, T: Send + 'static> AsyncLoader<K, T> {
+    /// Starts loading the key in the background, unless it is already loading.
+    /// Returns whether a load was started.
+    pub fn request(&mut self, key: K, load: impl FnOnce() -> T + Send + 'static) -> bool {
+        if !self.pending.insert(key.clone()) {
+            return false;
+        }
+        let done = self.done_tx.clone();
+        let job: Job = Box::new(move || {
+            let _ = done.send((key, load()));
+        });
+        pool()
+            .lock()
+            .unwrap()
+            .send(job)
+            .expect("async loader stopped");
+        true
+    }
+
+    pub fn is_pending(&self, key: &K) -> bool {
+        self.pending.contains(key)
+    }
+
+    /// The values loaded since the last call, they are not kept by the loader
+    pub fn finished(&mut self) -> Vec<(K, T)> {
+        let done: Vec<_> = self.done_rx.try_iter().collect();
+        for (key, _) in &done {
+            self.pending.remove(key);
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncLoader;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn slow_loads_are_deduplicated() {
+        let mut loader = AsyncLoader::<&'static str, usize>::default();
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let slow_load = |loads: &Arc<AtomicUsize>| {
+            let loads = loads.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                loads.fetch_add(1, Ordering::SeqCst) + 1
+            }
+        };
+
+        assert!(loader.request("house.glb", slow_load(&loads)));
+        assert!(!loader.request("house.glb", slow_load(&loads)));
+        assert!(loader.is_pending(&"house.glb"));
+
+        let start = Instant::now();
+        let mut finished = vec![];
+        while finished.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5), "never loaded");
+            std::thread::sleep(Duration::from_millis(5));
+            finished = loader.finished();
+        }
+
+        assert_eq!(finished, vec![("house.glb", 1)]);
+        assert!(!loader.is_pending(&"house.glb"));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        // once loaded, the key can be requested again
+        assert!(loader.request("house.glb", slow_load(&loads)));
+    }
+}
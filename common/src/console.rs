@@ -0,0 +1,386 @@
+//! Developer console: a registry of commands run from a line of text.
+//! Each command is registered with its name, a help text, a parser for its arguments and a
+//! closure running it on a context given by the caller, so that any module can add its own.
+
+use std::str::FromStr;
+
+/// Text shown in the scrollback, an error otherwise
+pub type ConsoleResult = Result<String, String>;
+
+/// Splits a line in words, double quotes group words: `rename "Main street"`
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut cur = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut cur));
+                    in_token = false;
+                }
+            }
+            c => {
+                cur.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if in_token {
+        tokens.push(cur);
+    }
+    Ok(tokens)
+}
+
+/// Arguments of a command, the words following its name
+pub struct Args {
+    tokens: Vec<String>,
+    next: usize,
+}
+
+impl Args {
+    pub fn new(tokens: Vec<String>) -> Self {
+        Self { tokens, next: 0 }
+    }
+
+    pub fn opt_str(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.next)?;
+        self.next += 1;
+        Some(token)
+    }
+
+    pub fn str(&mut self, name: &str) -> Result<&str, String> {
+        self.opt_str().ok_or_else(|| format!("missing <{}>", name))
+    }
+
+    pub fn opt_parse<T: FromStr>(&mut self, name: &str) -> Result<Option<T>, String> {
+        let Some(token) = self.opt_str() else {
+            return Ok(None);
+        };
+        token
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid <{}>: {}", name, token))
+    }
+
+    pub fn parse<T: FromStr>(&mut self, name: &str) -> Result<T, String> {
+        self.opt_parse(name)?
+            .ok_or_else(|| format!("missing <{}>", name))
+    }
+
+    /// Fails if some arguments were not used
+    pub fn finish(&self) -> Result<(), String> {
+        match self.tokens.get(self.next) {
+            Some(extra) => Err(format!("unexpected argument: {}", extra)),
+            None => Ok(()),
+        }
+    }
+}
+
+type RunFn<C> = Box<dyn Fn(&mut C, &mut Args) -> ConsoleResult>;
+
+pub struct ConsoleCommand<C> {
+    /// Words typed to run the command, e.g. "money add"
+    pub name: &'static str,
+    /// Arguments of the command, e.g. "<amount>"
+    pub usage: &'static str,
+    pub help: &'static str,
+    run: RunFn<C>,
+}
+
+pub struct CommandRegistry<C> {
+    /// Sorted by name
+    commands: Vec<ConsoleCommand<C>>,
+}
+
+impl<C> Default for CommandRegistry<C> {
+    fn default() -> Self {
+        Self { commands: vec![] }
+    }
+}
+
+/// Result of the completion of a partial command name
+#[derive(Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// The input completed as far as all the candidates agree
+    pub input: String,
+    /// Names of the commands starting with the input, shown when there are several
+    pub candidates: Vec<&'static str>,
+}
+
+impl<C> CommandRegistry<C> {
+    /// Registers a command, replacing the one with the same name.
+    /// The arguments are parsed by `parse` before calling `run`, extra arguments are an error.
+    pub fn register<A>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        parse: impl Fn(&mut Args) -> Result<A, String> + 'static,
+        run: impl Fn(&mut C, A) -> ConsoleResult + 'static,
+    ) {
+        let command = ConsoleCommand {
+            name,
+            usage,
+            help,
+            run: Box::new(move |ctx, args| {
+                let parsed = parse(args)?;
+                args.finish()?;
+                run(ctx, parsed)
+            }),
+        };
+        match self.commands.binary_search_by(|c| c.name.cmp(name)) {
+            Ok(i) => self.commands[i] = command,
+            Err(i) => self.commands.insert(i, command),
+        }
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &ConsoleCommand<C>> {
+        self.commands.iter()
+    }
+
+    /// The command with the longest name matching the first words, and the number of words of its name
+    fn find(&self, tokens: &[String]) -> Option<(&ConsoleCommand<C>, usize)> {
+        self.commands
+            .iter()
+            .filter_map(|c| {
+                let words = c.name.split(' ').count();
+                let matches =
+                    tokens.len() >= words && c.name.split(' ').zip(tokens).all(|(w, t)| w == t);
+                matches.then_some((c, words))
+            })
+            .max_by_key(|&(_, words)| words)
+    }
+
+    /// Runs the line, `help` lists the commands or describes the ones starting with its argument
+    pub fn execute(&self, ctx: &mut C, line: &str) -> ConsoleResult {
+        let tokens = tokenize(line)?;
+        if tokens.is_empty() {
+            return Ok(String::new());
+        }
+        if tokens[0] == "help" {
+            return self.help(&tokens[1..].join(" "));
+        }
+        let Some((command, words)) = self.find(&tokens) else {
+            return Err(format!(
+                "unknown command: {}, type help for the list",
+                tokens[0]
+            ));
+        };
+        let mut args = Args::new(tokens[words..].to_vec());
+        (command.run)(ctx, &mut args)
+            .map_err(|e| format!("{}\nusage: {} {}", e, command.name, command.usage))
+    }
+
+    fn help(&self, topic: &str) -> ConsoleResult {
+        let lines: Vec<String> = self
+            .commands
+            .iter()
+            .filter(|c| c.name.starts_with(topic))
+            .map(|c| format!("{} {} - {}", c.name, c.usage, c.help))
+            .collect();
+        if lines.is_empty() {
+            return Err(format!("no command starts with {}", topic));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Completes the name of the command being typed
+    pub fn complete(&self, input: &str) -> Completion {
+        let typed = input.trim_start();
+        let candidates: Vec<&'static str> = std::iter::once("help")
+            .chain(self.commands.iter().map(|c| c.name))
+            .filter(|name| name.starts_with(typed))
+            .collect();
+
+        let input = match candidates[..] {
+            [] => input.to_string(),
+            [name] => format!("{} ", name),
+            [first, ..] => {
+                let common = candidates.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                first[..common].to_string()
+            }
+        };
+        Completion { input, candidates }
+    }
+}
+
+/// Lines entered in the console, browsed with the up and down arrows
+#[derive(Default)]
+pub struct ConsoleHistory {
+    lines: Vec<String>,
+    /// Line being shown, None when typing a new one
+    browsing: Option<usize>,
+}
+
+impl ConsoleHistory {
+    /// Remembers the line, unless it is empty or the same as the previous one
+    pub fn push(&mut self, line: &str) {
+        self.browsing = None;
+        if line.trim().is_empty() || self.lines.last().is_some_and(|l| l == line) {
+            return;
+        }
+        self.lines.push(line.to_string());
+    }
+
+    /// The line before the one being shown, the last one when typing a new line
+    pub fn older(&mut self) -> Option<&str> {
+        let i = match self.browsing {
+            Some(0) => 0,
+            Some(i) => i - 1,
+            None => self.lines.len().checked_sub(1)?,
+        };
+        self.browsing = Some(i);
+        Some(&self.lines[i])
+    }
+
+    /// The line after the one being shown, an empty line after the last one
+    pub fn newer(&mut self) -> Option<&str> {
+        let i = self.browsing? + 1;
+        if i >= self.lines.len() {
+            self.browsing = None;
+            return Some("");
+        }
+        self.browsing = Some(i);
+        Some(&self.lines[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commands of the tests add to a counter
+    fn registry() -> CommandRegistry<i64> {
+        let mut reg = CommandRegistry::default();
+        reg.register(
+            "money add",
+            "<amount>",
+            "adds money",
+            |args| args.parse::<i64>("amount"),
+            |money, amount| {
+                *money += amount;
+                Ok(format!("added {}", amount))
+            },
+        );
+        reg.register(
+            "money",
+            "",
+            "shows the money",
+            |_| Ok(()),
+            |money, ()| Ok(money.to_string()),
+        );
+        reg.register(
+            "mode",
+            "[name]",
+            "shows or sets the mode",
+            |args| Ok(args.opt_str().map(str::to_string)),
+            |_, name| Ok(name.unwrap_or_else(|| "normal".to_string())),
+        );
+        reg
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("  money  add 10 ").unwrap(),
+            ["money", "add", "10"]
+        );
+        assert_eq!(
+            tokenize(r#"rename "Main street" now"#).unwrap(),
+            ["rename", "Main street", "now"]
+        );
+        assert_eq!(tokenize(r#"set """#).unwrap(), ["set", ""]);
+        assert!(tokenize(r#"rename "Main"#).is_err());
+        assert!(tokenize("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_execute_parses_arguments() {
+        let reg = registry();
+        let mut money = 0;
+
+        assert_eq!(
+            reg.execute(&mut money, "money add 10"),
+            Ok("added 10".into())
+        );
+        assert_eq!(
+            reg.execute(&mut money, "money add -3"),
+            Ok("added -3".into())
+        );
+        assert_eq!(money, 7);
+        assert_eq!(reg.execute(&mut money, "money"), Ok("7".into()));
+        assert_eq!(reg.execute(&mut money, "mode"), Ok("normal".into()));
+        assert_eq!(reg.execute(&mut money, "mode fast"), Ok("fast".into()));
+        assert_eq!(reg.execute(&mut money, ""), Ok(String::new()));
+
+        let err = reg.execute(&mut money, "money add lots").unwrap_err();
+        assert!(err.starts_with("invalid <amount>: lots"), "{}", err);
+        assert!(err.contains("usage: money add <amount>"), "{}", err);
+        let err = reg.execute(&mut money, "money add").unwrap_err();
+        assert!(err.starts_with("missing <amount>"), "{}", err);
+        let err = reg.execute(&mut money, "money add 1 2").unwrap_err();
+        assert!(err.starts_with("unexpected argument: 2"), "{}", err);
+        assert!(reg.execute(&mut money, "teleport").is_err());
+        assert_eq!(money, 7);
+    }
+
+    #[test]
+    fn test_help() {
+        let reg = registry();
+        let help = reg.execute(&mut 0, "help").unwrap();
+        assert_eq!(help.lines().count(), 3);
+        let help = reg.execute(&mut 0, "help money add").unwrap();
+        assert_eq!(help, "money add <amount> - adds money");
+        assert!(reg.execute(&mut 0, "help teleport").is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        let reg = registry();
+        let complete = |input| reg.complete(input);
+
+        assert_eq!(complete("mon").input, "money");
+        assert_eq!(complete("mon").candidates, ["money", "money add"]);
+        assert_eq!(complete("money a").input, "money add ");
+        assert_eq!(complete("he").input, "help ");
+        assert_eq!(complete("m").input, "mo");
+        assert_eq!(complete("x").input, "x");
+        assert!(complete("x").candidates.is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = ConsoleHistory::default();
+        assert_eq!(history.older(), None);
+        assert_eq!(history.newer(), None);
+
+        history.push("money");
+        history.push("money");
+        history.push("");
+        history.push("money add 10");
+
+        assert_eq!(history.older(), Some("money add 10"));
+        assert_eq!(history.older(), Some("money"));
+        assert_eq!(history.older(), Some("money"));
+        assert_eq!(history.newer(), Some("money add 10"));
+        assert_eq!(history.newer(), Some(""));
+        assert_eq!(history.newer(), None);
+    }
+}
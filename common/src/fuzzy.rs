@@ -0,0 +1,74 @@
+/// Scores how well the text matches the query, ignoring case.
+/// The characters of the query must appear in the text in the same order, but not necessarily
+/// next to each other: "hsp" matches "Hospital".
+/// Returns None if it doesn't match, higher scores are better matches.
+///
+/// Exact matches come first, then prefixes, then substrings, then scattered characters.
+/// Characters at the start of a word or following the previous match are worth more, and shorter
+/// texts are preferred.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.trim().chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+
+    let mut score = 0;
+    let mut qi = 0;
+    let mut last_match = None;
+    for (ti, &c) in text.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if c != query[qi] {
+            continue;
+        }
+        score += 10;
+        if ti == 0 || !text[ti - 1].is_alphanumeric() {
+            score += 30;
+        }
+        if last_match == Some(ti.wrapping_sub(1)) {
+            score += 15;
+        }
+        last_match = Some(ti);
+        qi += 1;
+    }
+    if qi < query.len() {
+        return None;
+    }
+
+    if text == query {
+        score += 1000;
+    } else if text.starts_with(&query) {
+        score += 500;
+    } else if text.windows(query.len()).any(|w| w == query.as_slice()) {
+        score += 200;
+    }
+
+    Some(score - text.len() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matches_in_order() {
+        assert!(fuzzy_score("hsp", "Hospital").is_some());
+        assert!(fuzzy_score("HOSP", "hospital").is_some());
+        assert_eq!(fuzzy_score("psh", "Hospital"), None);
+        assert_eq!(fuzzy_score("hospitals", "Hospital"), None);
+        assert_eq!(fuzzy_score("  ", "Hospital"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let score = |q, t| fuzzy_score(q, t).unwrap();
+
+        assert!(score("bakery", "Bakery") > score("bakery", "Bakery shop"));
+        assert!(score("bak", "Bakery") > score("bak", "Big bakery"));
+        assert!(score("bak", "Big bakery") > score("bak", "Big black kettle"));
+        assert!(score("st 4", "Street 42") > score("st 4", "Street 142"));
+        assert!(score("hsp", "Hospital") > score("hsp", "Hash spot"));
+    }
+}
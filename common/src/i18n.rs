@@ -0,0 +1,290 @@
+//! Translations of the interface.
+//! Each language is a file of `key = value` lines in `assets/lang`, named after its code, e.g. `fr.txt`.
+//! Values can contain `{name}` placeholders, filled by the arguments of [`t!`](crate::t).
+//! Keys missing from the active language fall back to English, then to the key itself.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+
+use crate::{FastMap, FastSet};
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+pub const LANG_DIR: &str = "assets/lang";
+
+/// Strings of a single language
+pub struct Translations {
+    code: String,
+    strings: FastMap<String, String>,
+}
+
+impl Translations {
+    /// Lines are `key = value`, empty lines and lines starting with `#` are ignored.
+    /// `\n` in a value is a line break, values can be quoted to keep their surrounding spaces.
+    pub fn parse(code: &str, src: &str) -> Self {
+        let mut strings = FastMap::default();
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                log::warn!("{}.txt:{}: expected `key = value`", code, i + 1);
+                continue;
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            strings.insert(key.trim().to_string(), value.replace("\\n", "\n"));
+        }
+        Self {
+            code: code.to_string(),
+            strings,
+        }
+    }
+
+    pub fn load(dir: &Path, code: &str) -> Option<Self> {
+        let path = dir.join(format!("{code}.txt"));
+        match std::fs::read_to_string(&path) {
+            Ok(src) => Some(Self::parse(code, &src)),
+            Err(e) => {
+                log::error!("could not load language {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Name of the language in the language itself, e.g. "Français"
+    pub fn name(&self) -> &str {
+        self.get("language-name").unwrap_or(&self.code)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+}
+
+/// The active language and English to fall back to
+pub struct Locale {
+    /// Language asked for, even if it could not be loaded
+    code: String,
+    active: Translations,
+    fallback: Translations,
+    /// Missing keys already logged, to log them only once
+    missing: Mutex<FastSet<String>>,
+}
+
+impl Locale {
+    pub fn load(dir: &Path, code: &str) -> Self {
+        let fallback = Translations::load(dir, DEFAULT_LANGUAGE)
+            .unwrap_or_else(|| Translations::parse(DEFAULT_LANGUAGE, ""));
+        let active = match code {
+            DEFAULT_LANGUAGE => None,
+            _ => Translations::load(dir, code),
+        };
+        Self {
+            code: code.to_string(),
+            active: active.unwrap_or_else(|| Translations::parse(DEFAULT_LANGUAGE, "")),
+            fallback,
+            missing: Default::default(),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        if let Some(v) = self.active.get(key) {
+            return v;
+        }
+        if self.active.code() != DEFAULT_LANGUAGE || self.fallback.get(key).is_none() {
+            let mut missing = self.missing.lock().unwrap();
+            if !missing.contains(key) {
+                log::debug!("missing translation for {} in {}", key, self.active.code());
+                missing.insert(key.to_string());
+            }
+        }
+        self.fallback.get(key).unwrap_or(key)
+    }
+
+    /// Looks up the key and replaces the `{name}` placeholders by the arguments
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut s = self.get(key).to_string();
+        for (name, value) in args {
+            s = s.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        s
+    }
+
+    /// Separates the thousands and the decimals the way the language does
+    pub fn number(&self, v: f64, decimals: usize) -> String {
+        let s = format!("{:.*}", decimals, v.abs());
+        let (int, frac) = s.split_once('.').unwrap_or((&s, ""));
+        let group_sep = self.get("number-group-separator");
+        let decimal_sep = self.get("number-decimal-separator");
+
+        let mut out = String::with_capacity(s.len() + 4);
+        if v < 0.0 && s.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            out.push('-');
+        }
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                out.push_str(group_sep);
+            }
+            out.push(c);
+        }
+        if !frac.is_empty() {
+            out.push_str(decimal_sep);
+            out.push_str(frac);
+        }
+        out
+    }
+}
+
+static LOCALE: RwLock<Option<Locale>> = RwLock::new(None);
+
+fn with_locale<T>(f: impl FnOnce(&Locale) -> T) -> T {
+    if let Some(ref locale) = *LOCALE.read().unwrap() {
+        return f(locale);
+    }
+    let mut locale = LOCALE.write().unwrap();
+    f(locale.get_or_insert_with(|| Locale::load(Path::new(LANG_DIR), DEFAULT_LANGUAGE)))
+}
+
+/// Switches the interface to the language, does nothing if it is already active
+pub fn set_language(code: &str) {
+    if with_locale(|l| l.code() == code) {
+        return;
+    }
+    log::info!("switching language to {}", code);
+    *LOCALE.write().unwrap() = Some(Locale::load(Path::new(LANG_DIR), code));
+}
+
+/// Codes and names of the languages shipped in the assets, sorted by code
+pub fn available_languages() -> Vec<(String, String)> {
+    let mut langs = BTreeMap::new();
+    let Ok(dir) = std::fs::read_dir(LANG_DIR) else {
+        return vec![(DEFAULT_LANGUAGE.to_string(), "English".to_string())];
+    };
+    for entry in dir.flatten() {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "txt") {
+            continue;
+        }
+        let Some(code) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if let Some(t) = Translations::load(Path::new(LANG_DIR), code) {
+            langs.insert(code.to_string(), t.name().to_string());
+        }
+    }
+    langs.into_iter().collect()
+}
+
+/// Translation of the key in the active language, use [`t!`](crate::t) instead
+pub fn tr(key: &str) -> String {
+    with_locale(|l| l.get(key).to_string())
+}
+
+/// Translation of the key with its placeholders filled, use [`t!`](crate::t) instead
+pub fn tr_args(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    with_locale(|l| l.format(key, args))
+}
+
+/// Number with the separators of the active language
+pub fn format_number(v: f64, decimals: usize) -> String {
+    with_locale(|l| l.number(v, decimals))
+}
+
+/// Looks up a string of the interface in the active language.
+/// ```ignore
+/// t!("menu-save");
+/// t!("menu-money", money = gov.money.localized());
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr_args(
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lang_dir() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/lang"))
+    }
+
+    #[test]
+    fn test_parse() {
+        let t = Translations::parse(
+            "xx",
+            "# comment\n\nmenu-save = Save\nbad line\nmulti = a\\nb\nempty =",
+        );
+        assert_eq!(t.get("menu-save"), Some("Save"));
+        assert_eq!(t.get("multi"), Some("a\nb"));
+        assert_eq!(t.get("empty"), Some(""));
+        assert_eq!(t.get("bad line"), None);
+        assert_eq!(t.name(), "xx");
+    }
+
+    #[test]
+    fn test_second_language_resolves() {
+        let en = Translations::load(lang_dir(), DEFAULT_LANGUAGE).unwrap();
+        let fr = Locale::load(lang_dir(), "fr");
+
+        assert_eq!(fr.code(), "fr");
+        assert_eq!(fr.get("menu-save"), "Sauvegarder");
+        assert_ne!(
+            fr.get("window-settings"),
+            en.get("window-settings").unwrap()
+        );
+        assert_eq!(fr.get("no-such-key"), "no-such-key");
+        assert_eq!(
+            fr.format("menu-money", &[("money", &"12 $")]),
+            "Argent : 12 $"
+        );
+
+        // every key of the french file is known in english
+        let fr = Translations::load(lang_dir(), "fr").unwrap();
+        for key in fr.strings.keys() {
+            assert!(en.get(key).is_some(), "{} is not in en.txt", key);
+        }
+    }
+
+    #[test]
+    fn test_fallback_to_english() {
+        let mut locale = Locale::load(lang_dir(), DEFAULT_LANGUAGE);
+        locale.active = Translations::parse("xx", "menu-save = Sv");
+        assert_eq!(locale.get("menu-save"), "Sv");
+        assert_eq!(locale.get("menu-exit"), "Exit");
+        assert_eq!(locale.missing.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_number() {
+        let en = Locale::load(lang_dir(), DEFAULT_LANGUAGE);
+        assert_eq!(en.number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(en.number(-999.0, 0), "-999");
+        assert_eq!(en.number(-1000.0, 0), "-1,000");
+        assert_eq!(en.number(-0.001, 1), "0.0");
+
+        let fr = Locale::load(lang_dir(), "fr");
+        assert_eq!(fr.number(1234567.5, 1), "1 234 567,5");
+    }
+}
@@ -1,9 +1,13 @@
 use std::cmp::Ordering;
 
+pub mod async_loader;
 mod chunkid;
+pub mod console;
 pub mod error;
+pub mod fuzzy;
 mod hash;
 pub mod history;
+pub mod i18n;
 pub mod iter;
 pub mod logger;
 pub mod macros;
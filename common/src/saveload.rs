@@ -25,6 +25,37 @@ pub fn walkdir(dir: &Path) -> impl Iterator<Item = PathBuf> {
     paths.into_iter()
 }
 
+/// Writes to a temporary file first, the file is only replaced once fully written
+/// so that a crash while saving doesn't corrupt it
+pub fn write_atomic(
+    path: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::result::Result<(), ()>,
+) -> Option<()> {
+    let _ = std::fs::create_dir("world");
+
+    let tmp_path = format!("{path}.tmp");
+
+    let file = create_file(&tmp_path)?;
+
+    let mut w = BufWriter::new(file);
+
+    write(&mut w).ok()?;
+
+    let file = w
+        .into_inner()
+        .map_err(|e| log::error!("failed writing {}: {}", tmp_path, e))
+        .ok()?;
+    file.sync_all()
+        .map_err(|e| log::error!("failed writing {}: {}", tmp_path, e))
+        .ok()?;
+    drop(file);
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| log::error!("failed moving {} to {}: {}", tmp_path, path, e))
+        .ok()?;
+    Some(())
+}
+
 fn open_file(path: &str) -> Result<File> {
     File::open(path)
 }
@@ -62,16 +93,9 @@ pub trait Encoder {
     }
 
     fn save_silent(x: &impl Serialize, name: &str) -> Option<()> {
-        let _ = std::fs::create_dir("world");
-
-        let file = create_file(&Self::filename(name))?;
-
-        let w = BufWriter::new(file);
-
-        Self::encode_writer(x, w)
-            .map_err(|e| log::error!("failed serializing: {}", e))
-            .ok()?;
-        Some(())
+        write_atomic(&Self::filename(name), |w| {
+            Self::encode_writer(x, w).map_err(|e| log::error!("failed serializing: {}", e))
+        })
     }
 
     fn load<T: DeserializeOwned>(name: &str) -> Result<T> {
@@ -94,6 +118,7 @@ pub struct Bincode;
 use ::bincode::{DefaultOptions, Options};
 use std::io::Result;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 impl Encoder for Bincode {
     const EXTENSION: &'static str = "bc";
@@ -143,6 +168,145 @@ impl Encoder for CompressedBincode {
     }
 }
 
+/// Prefix of the save files: magic bytes, version of the saved data, payload length and checksum.
+/// Allows to detect truncated or corrupted saves before deserializing them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SaveHeader {
+    /// Version of the saved data, up to the caller to interpret
+    pub version: u32,
+    pub len: u64,
+    pub checksum: u64,
+}
+
+impl SaveHeader {
+    pub const MAGIC: [u8; 4] = *b"EGSV";
+    pub const SIZE: usize = 4 + 4 + 8 + 8;
+
+    pub fn new(version: u32, payload: &[u8]) -> Self {
+        Self {
+            version,
+            len: payload.len() as u64,
+            checksum: crate::hash_u64(payload),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut v = [0; Self::SIZE];
+        v[0..4].copy_from_slice(&Self::MAGIC);
+        v[4..8].copy_from_slice(&self.version.to_le_bytes());
+        v[8..16].copy_from_slice(&self.len.to_le_bytes());
+        v[16..24].copy_from_slice(&self.checksum.to_le_bytes());
+        v
+    }
+
+    /// None if the data doesn't start with a header, saves from before headers were added
+    pub fn read(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE || data[0..4] != Self::MAGIC {
+            return None;
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(data[i..i + 8].try_into().unwrap());
+        Some(Self {
+            version: u32_at(4),
+            len: u64_at(8),
+            checksum: u64_at(16),
+        })
+    }
+
+    /// The version and the payload following the header, if it is complete and matches the checksum.
+    /// Data without a header is returned as is with version 0.
+    pub fn check(data: &[u8]) -> Result<(u32, &[u8])> {
+        let invalid = |msg: String| Err(io::Error::new(ErrorKind::InvalidData, msg));
+
+        let Some(header) = Self::read(data) else {
+            return Ok((0, data));
+        };
+        let payload = &data[Self::SIZE..];
+        if payload.len() as u64 != header.len {
+            return invalid(format!(
+                "save is truncated: expected {} bytes, found {}",
+                header.len,
+                payload.len()
+            ));
+        }
+        if crate::hash_u64(payload) != header.checksum {
+            return invalid("save is corrupted: checksum mismatch".to_string());
+        }
+        Ok((header.version, payload))
+    }
+}
+
+/// Compressed bincode prefixed by a [`SaveHeader`], used for the save files.
+/// Through [`Encoder`] the version is ignored and written as 0.
+pub struct CheckedCompressedBincode;
+
+impl CheckedCompressedBincode {
+    pub fn encode_versioned(x: &impl Serialize, version: u32) -> Result<Vec<u8>> {
+        let payload = CompressedBincode::encode(x)?;
+        let mut v = Vec::with_capacity(SaveHeader::SIZE + payload.len());
+        v.extend_from_slice(&SaveHeader::new(version, &payload).to_bytes());
+        v.extend_from_slice(&payload);
+        Ok(v)
+    }
+}
+
+impl Encoder for CheckedCompressedBincode {
+    const EXTENSION: &'static str = "zip";
+
+    fn encode(x: &impl Serialize) -> Result<Vec<u8>> {
+        Self::encode_versioned(x, 0)
+    }
+
+    fn decode<T: DeserializeOwned>(x: &[u8]) -> Result<T> {
+        CompressedBincode::decode(SaveHeader::check(x)?.1)
+    }
+}
+
+/// Rotating save slots named `{prefix}_0` to `{prefix}_{n_slots - 1}`
+#[derive(Debug, Clone)]
+pub struct AutoSaveSlots {
+    pub prefix: String,
+    pub n_slots: u32,
+}
+
+impl AutoSaveSlots {
+    pub fn new(prefix: impl Into<String>, n_slots: u32) -> Self {
+        Self {
+            prefix: prefix.into(),
+            n_slots: n_slots.max(1),
+        }
+    }
+
+    pub fn name(&self, slot: u32) -> String {
+        format!("{}_{}", self.prefix, slot)
+    }
+
+    /// Names of the slots that were written with their modification time, newest first
+    pub fn list<E: Encoder>(&self) -> Vec<(String, SystemTime)> {
+        let mut v: Vec<_> = (0..self.n_slots)
+            .filter_map(|slot| {
+                let name = self.name(slot);
+                let modified = std::fs::metadata(E::filename(&name))
+                    .and_then(|m| m.modified())
+                    .ok()?;
+                Some((name, modified))
+            })
+            .collect();
+        v.sort_by(|a, b| b.1.cmp(&a.1));
+        v
+    }
+
+    /// Name of the slot to write next: the first unused one, or else the oldest one
+    pub fn next<E: Encoder>(&self) -> String {
+        let written = self.list::<E>();
+        (0..self.n_slots)
+            .map(|slot| self.name(slot))
+            .find(|name| !written.iter().any(|(w, _)| w == name))
+            .or_else(|| written.last().map(|(name, _)| name.clone()))
+            .unwrap_or_else(|| self.name(0))
+    }
+}
+
 pub struct JSON;
 
 impl Encoder for JSON {
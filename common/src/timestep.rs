@@ -2,6 +2,9 @@ use std::time::{Duration, Instant};
 
 const UP_DT: Duration = Duration::from_millis(20);
 
+/// Time warp running as many ticks as the frame budget allows
+pub const UNCAPPED_WARP: u32 = u32::MAX;
+
 pub fn debug_up_dt() -> Duration {
     UP_DT
 }
@@ -13,6 +16,12 @@ pub struct Timestep {
     last_time: Instant,
     acc: Duration,
     real_delta: Duration,
+    warp: u32,
+    /// Runs one tick on the next frame, even when paused
+    step: bool,
+    measure_start: Instant,
+    measure_ticks: u32,
+    actual_tps: Option<f32>,
     pub period: Duration,
 }
 
@@ -24,12 +33,20 @@ impl Default for Timestep {
 
 impl Timestep {
     const MAXTIME: Duration = Duration::from_millis(25);
+    /// Time spent ticking each frame with the uncapped warp, the rest is left to the interface
+    const UNCAPPED_BUDGET: Duration = Duration::from_millis(12);
+    const MEASURE_PERIOD: Duration = Duration::from_secs(1);
 
     pub fn new(period: Duration) -> Self {
         Self {
             last_time: Instant::now(),
             acc: Default::default(),
             real_delta: Default::default(),
+            warp: 1,
+            step: false,
+            measure_start: Instant::now(),
+            measure_ticks: 0,
+            actual_tps: None,
             period,
         }
     }
@@ -41,13 +58,40 @@ impl Timestep {
         }
         self.last_time = Instant::now();
 
+        if warp != self.warp {
+            self.warp = warp;
+            self.restart_measure();
+        }
+        let measured = self.measure_start.elapsed();
+        if measured >= Self::MEASURE_PERIOD {
+            self.actual_tps = Some(self.measure_ticks as f32 / measured.as_secs_f32());
+            self.measure_ticks = 0;
+            self.measure_start = Instant::now();
+        }
+
+        if warp == UNCAPPED_WARP {
+            self.acc = Default::default();
+            return;
+        }
         self.acc += self.real_delta * warp;
     }
 
     pub fn tick(&mut self) -> bool {
+        if std::mem::take(&mut self.step) {
+            self.measure_ticks += 1;
+            return true;
+        }
+        if self.warp == UNCAPPED_WARP {
+            if self.last_time.elapsed() > Self::UNCAPPED_BUDGET {
+                return false;
+            }
+            self.measure_ticks += 1;
+            return true;
+        }
         if self.acc < self.period {
             return false;
         }
+        self.measure_ticks += 1;
         if self.last_time.elapsed() > Timestep::MAXTIME {
             self.acc = Default::default();
             return true;
@@ -55,4 +99,65 @@ impl Timestep {
         self.acc -= self.period;
         true
     }
+
+    /// Runs a single tick on the next frame, to advance the game while it is paused
+    pub fn step_once(&mut self) {
+        self.step = true;
+    }
+
+    /// Ticks per second asked by the time warp, None for the uncapped warp
+    pub fn requested_tps(&self) -> Option<f32> {
+        if self.warp == UNCAPPED_WARP {
+            return None;
+        }
+        Some(self.warp as f32 / self.period.as_secs_f32())
+    }
+
+    /// Ticks per second that were run over the last second,
+    /// None until a full second was measured since the time warp changed
+    pub fn actual_tps(&self) -> Option<f32> {
+        self.actual_tps
+    }
+
+    fn restart_measure(&mut self) {
+        self.measure_start = Instant::now();
+        self.measure_ticks = 0;
+        self.actual_tps = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Timestep, UNCAPPED_WARP};
+
+    #[test]
+    fn step_while_paused() {
+        let mut step = Timestep::default();
+        step.prepare_frame(0);
+        assert!(!step.tick());
+
+        step.step_once();
+        step.prepare_frame(0);
+        assert!(step.tick());
+        assert!(!step.tick());
+        assert_eq!(step.requested_tps(), Some(0.0));
+    }
+
+    #[test]
+    fn uncapped_stops_at_the_budget() {
+        let mut step = Timestep::default();
+        step.prepare_frame(UNCAPPED_WARP);
+        assert_eq!(step.requested_tps(), None);
+
+        let start = Instant::now();
+        let mut ticks = 0;
+        while step.tick() {
+            ticks += 1;
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(ticks > 1);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
 }
@@ -93,10 +93,10 @@ impl AudioContext {
         }
     }
 
-    pub fn preload<'a>(&mut self, sounds: impl Iterator<Item = &'a str> + Send + 'static) {
+    pub fn preload(&mut self, sounds: impl Iterator<Item = impl Into<String>>) {
         sounds.for_each(move |v| {
-            self.preloading.insert(v.to_string());
-            let s = v.to_string();
+            let s = v.into();
+            self.preloading.insert(s.clone());
             let cache = self.cache.clone();
             rayon::spawn(move || {
                 if let Some(audio) = Self::decode(&s) {
@@ -117,10 +117,6 @@ impl AudioContext {
     }
 
     fn decode(name: &str) -> Option<StoredAudio> {
-        #[cfg(debug_assertions)]
-        if name.starts_with("music") {
-            return None;
-        }
         let p = format!("assets/sounds/{name}.ogg");
         let t = Instant::now();
         let buf = match common::saveload::load_raw(&p) {
@@ -171,45 +167,53 @@ impl AudioContext {
         Some(frames)
     }
 
-    fn get(
-        preloading: &FastSet<String>,
-        cache: &RwLock<FastMap<String, StoredAudio>>,
-        name: &str,
-    ) -> Option<StoredAudio> {
-        if preloading.contains(name) {
-            for _ in 0..100 {
-                if let Some(v) = cache.read().unwrap().get(name) {
-                    return Some(v.clone());
-                }
-                std::thread::sleep(Duration::from_millis(100))
-            }
-        }
-        if let Some(v) = cache.read().unwrap().get(name) {
+    /// Never waits for the decoding, a sound still being decoded isn't played
+    fn get(&mut self, name: &str) -> Option<StoredAudio> {
+        if let Some(v) = self.cache.read().unwrap().get(name) {
             return Some(v.clone());
         }
-        if let Some(decoded) = Self::decode(name) {
-            cache
-                .write()
-                .unwrap()
-                .insert(name.to_string(), decoded.clone());
-            return Some(decoded);
+        if !self.preloading.contains(name) {
+            self.preload(std::iter::once(name.to_string()));
         }
         None
     }
 
+    /// Adds a sound made by the game instead of being loaded from the assets
+    pub fn insert(&mut self, name: &str, sample_rate: u32, samples: &[Stereo]) {
+        self.preloading.insert(name.to_string());
+        self.cache
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Frames::from_slice(sample_rate, samples));
+    }
+
+    /// Whether an output device was found, nothing is played otherwise
+    pub fn is_enabled(&self) -> bool {
+        self.scene_handle.is_some()
+    }
+
     pub fn play(&mut self, name: &'static str, kind: AudioKind) {
-        let vol = self.g_volume(kind);
+        self.play_panned(name, kind, 1.0, 0.0);
+    }
+
+    /// Plays a sound once, `gain` scales its volume and `pan` moves it from the left (-1) to the
+    /// right (1) speaker
+    pub fn play_panned(&mut self, name: &'static str, kind: AudioKind, gain: f32, pan: f32) {
+        if let AudioKind::Music = kind {
+            log::error!(
+                "shouldn't play music with base play as it's not affected by global volume changes"
+            );
+        }
+        let vol = self.g_volume(kind) * gain;
+        if vol <= 0.001 || self.scene_handle.is_none() {
+            return;
+        }
+        let Some(x) = self.get(name) else {
+            return;
+        };
         if let Some(ref mut h) = self.scene_handle {
-            if let Some(x) = Self::get(&self.preloading, &self.cache, name) {
-                if let AudioKind::Music = kind {
-                    log::error!("shouldn't play music with base play as it's not affected by global volume changes");
-                }
-                if vol <= 0.001 {
-                    return;
-                }
-                let g = FixedGain::new(FramesSignal::new(x, 0.0).1, vol.log10() * 20.0);
-                h.play(g);
-            }
+            let g = FixedGain::new(FramesSignal::new(x, 0.0).1, vol.log10() * 20.0);
+            h.play(Pan::new(g, pan));
         }
     }
 
@@ -226,19 +230,30 @@ impl AudioContext {
     where
         S: Signal<Frame = [Sample; 2]> + Send,
     {
-        if let Some(ref mut h) = self.scene_handle {
-            if let Some(x) = Self::get(&self.preloading, &self.cache, name) {
-                let (control, signal) = transform(x);
-                let test = GlobalGain {
-                    volume: RefCell::new(Smoothed::new(1.0)),
-                    kind,
-                    inner: signal,
-                };
-                let mixed = h.play(test);
-                return Some((control, mixed));
-            }
-        }
-        None
+        self.scene_handle.as_ref()?;
+        let x = self.get(name)?;
+        let h = self.scene_handle.as_mut()?;
+        let (control, signal) = transform(x);
+        let test = GlobalGain {
+            volume: RefCell::new(Smoothed::new(1.0)),
+            kind,
+            inner: signal,
+        };
+        let mixed = h.play(test);
+        Some((control, mixed))
+    }
+
+    /// Plays a signal made by the caller, like a [`StreamedAudio`](crate::StreamedAudio)
+    pub fn play_signal<S>(&mut self, signal: S, kind: AudioKind) -> Option<Mixed>
+    where
+        S: Signal<Frame = [Sample; 2]> + Send + 'static,
+    {
+        let h = self.scene_handle.as_mut()?;
+        Some(h.play(GlobalGain {
+            volume: RefCell::new(Smoothed::new(1.0)),
+            kind,
+            inner: signal,
+        }))
     }
 
     pub fn set_settings(
@@ -322,6 +337,41 @@ impl<T: Signal<Frame = [Sample; 2]>> Signal for GlobalGain<T> {
     }
 }
 
+/// Moves a signal between the speakers, keeping the same loudness
+pub struct Pan<T: ?Sized> {
+    left: f32,
+    right: f32,
+    inner: T,
+}
+
+impl<T> Pan<T> {
+    /// `pan` goes from -1 (left) to 1 (right), 0 leaves the signal unchanged
+    pub fn new(signal: T, pan: f32) -> Self {
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        Self {
+            left: angle.cos() * std::f32::consts::SQRT_2,
+            right: angle.sin() * std::f32::consts::SQRT_2,
+            inner: signal,
+        }
+    }
+}
+
+impl<T: Signal<Frame = [Sample; 2]>> Signal for Pan<T> {
+    type Frame = [Sample; 2];
+
+    fn sample(&mut self, interval: f32, out: &mut [Self::Frame]) {
+        self.inner.sample(interval, out);
+        for x in out {
+            x[0] *= self.left;
+            x[1] *= self.right;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+}
+
 pub struct FadeIn<T: ?Sized> {
     fadetime: f32,
     advance: RefCell<f32>,
@@ -0,0 +1,239 @@
+use crate::Stereo;
+use oddio::Signal;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::sync::Arc;
+
+/// Seconds of audio sent at once by the decoder
+const CHUNK_SECONDS: f32 = 0.5;
+/// Chunks decoded ahead of the playback, the decoder waits when they are all full
+const CHUNKS_AHEAD: usize = 8;
+
+struct StreamState {
+    paused: AtomicBool,
+    /// The decoder reached the end of the file
+    decoded: AtomicBool,
+    /// Frames decoded but not played yet
+    buffered: AtomicUsize,
+    /// Zero until the header is read
+    sample_rate: AtomicU32,
+}
+
+/// Thread-safe control for a [`StreamedAudio`]
+#[derive(Clone)]
+pub struct TrackControl(Arc<StreamState>);
+
+impl TrackControl {
+    /// A paused track outputs silence and keeps its position
+    pub fn set_paused(&self, paused: bool) {
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    /// Seconds left to play, only known once the whole file is decoded
+    pub fn remaining(&self) -> Option<f32> {
+        if !self.0.decoded.load(Ordering::Acquire) {
+            return None;
+        }
+        let rate = self.0.sample_rate.load(Ordering::Relaxed).max(1);
+        Some(self.0.buffered.load(Ordering::Relaxed) as f32 / rate as f32)
+    }
+}
+
+/// An ogg file decoded on its own thread while it plays, so that only a few seconds of it are in
+/// memory. A file that can't be read or decoded is logged and ends the signal, the thread playing
+/// the audio never waits for the decoder and plays silence if it falls behind.
+pub struct StreamedAudio {
+    state: Arc<StreamState>,
+    chunks: Receiver<Vec<Stereo>>,
+    chunk: Vec<Stereo>,
+    idx: usize,
+    /// The output is interpolated between these two frames of the track
+    prev: Stereo,
+    next: Stereo,
+    frac: f32,
+    finished: bool,
+}
+
+impl StreamedAudio {
+    pub fn open(path: PathBuf) -> (TrackControl, Self) {
+        let state = Arc::new(StreamState {
+            paused: AtomicBool::new(false),
+            decoded: AtomicBool::new(false),
+            buffered: AtomicUsize::new(0),
+            sample_rate: AtomicU32::new(0),
+        });
+        let (send, chunks) = sync_channel(CHUNKS_AHEAD);
+
+        let state2 = state.clone();
+        let spawned = std::thread::Builder::new()
+            .name("audio decoder".to_string())
+            .spawn(move || decode(path, send, &state2));
+        if let Err(e) = spawned {
+            log::error!("could not start the audio decoder: {}", e);
+        }
+
+        (
+            TrackControl(state.clone()),
+            Self {
+                state,
+                chunks,
+                chunk: vec![],
+                idx: 0,
+                prev: [0.0; 2],
+                next: [0.0; 2],
+                frac: 0.0,
+                finished: false,
+            },
+        )
+    }
+
+    fn pop(&mut self) -> Option<Stereo> {
+        if self.idx >= self.chunk.len() {
+            match self.chunks.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.idx = 0;
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+        let frame = self.chunk[self.idx];
+        self.idx += 1;
+        self.state.buffered.fetch_sub(1, Ordering::Relaxed);
+        Some(frame)
+    }
+}
+
+impl Signal for StreamedAudio {
+    type Frame = Stereo;
+
+    fn sample(&mut self, interval: f32, out: &mut [Stereo]) {
+        let rate = self.state.sample_rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            // the header isn't read yet, or couldn't be
+            match self.chunks.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.idx = 0;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.finished = true,
+            }
+            out.fill([0.0; 2]);
+            return;
+        }
+        if self.state.paused.load(Ordering::Relaxed) {
+            out.fill([0.0; 2]);
+            return;
+        }
+        let step = interval * rate as f32;
+
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = [
+                self.prev[0] + (self.next[0] - self.prev[0]) * self.frac,
+                self.prev[1] + (self.next[1] - self.prev[1]) * self.frac,
+            ];
+            self.frac += step;
+            while self.frac >= 1.0 {
+                let Some(frame) = self.pop() else {
+                    // the decoder fell behind or is done
+                    self.frac = 0.0;
+                    self.prev = [0.0; 2];
+                    self.next = [0.0; 2];
+                    out[i + 1..].fill([0.0; 2]);
+                    return;
+                };
+                self.prev = self.next;
+                self.next = frame;
+                self.frac -= 1.0;
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+fn decode(path: PathBuf, send: SyncSender<Vec<Stereo>>, state: &StreamState) {
+    let file = match File::open(&path) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("could not open {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut decoder = match lewton::inside_ogg::OggStreamReader::new(BufReader::new(file)) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("could not decode {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let rate = decoder.ident_hdr.audio_sample_rate;
+    let mono = decoder.ident_hdr.audio_channels == 1;
+    state.sample_rate.store(rate, Ordering::Relaxed);
+    let chunk_len = (rate as f32 * CHUNK_SECONDS) as usize;
+
+    let mut chunk = Vec::with_capacity(chunk_len);
+    loop {
+        let packets = match decoder.read_dec_packet() {
+            Ok(Some(x)) => x,
+            Ok(None) => break,
+            Err(e) => {
+                log::error!(
+                    "error decoding {}, skipping the rest: {}",
+                    path.display(),
+                    e
+                );
+                break;
+            }
+        };
+
+        let mut it = packets.into_iter();
+        let Some(left) = it.next() else {
+            continue;
+        };
+        let to_f32 = |x: i16| x as f32 / (i16::MAX as f32);
+        if mono {
+            chunk.extend(left.into_iter().map(|x| [to_f32(x), to_f32(x)]));
+        } else {
+            let Some(right) = it.next() else {
+                continue;
+            };
+            chunk.extend(
+                left.into_iter()
+                    .zip(right)
+                    .map(|(l, r)| [to_f32(l), to_f32(r)]),
+            );
+        }
+
+        if chunk.len() >= chunk_len {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_len));
+            state.buffered.fetch_add(full.len(), Ordering::Relaxed);
+            // the receiver was dropped, the track was stopped
+            if send.send(full).is_err() {
+                return;
+            }
+        }
+    }
+
+    if !chunk.is_empty() {
+        state.buffered.fetch_add(chunk.len(), Ordering::Relaxed);
+        // the sender is dropped below, the signal ends once it played the last chunk
+        let _ = send.send(chunk);
+    }
+    state.decoded.store(true, Ordering::Release);
+}
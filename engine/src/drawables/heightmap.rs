@@ -15,7 +15,6 @@ use crate::{
 };
 
 const LOD: usize = 5;
-const LOD_MIN_DIST_LOG2: f32 = 9.0; // 2^9 = 512, meaning until 1048m away, we use the highest lod
 const MAX_HEIGHT: f32 = 2008.0;
 const MIN_HEIGHT: f32 = -40.0;
 const UPSCALE_LOD: usize = 2; // amount of LOD that are superior to base heightmap data
@@ -29,6 +28,9 @@ pub struct HeightmapRender<const CSIZE: u32, const CRESOLUTION: usize> {
     indices: [(PBuffer, u32); LOD],
     instances: [(PBuffer, u32); LOD],
     bgs: Arc<[wgpu::BindGroup; LOD]>,
+    chunk_unis: [Uniform<HeightmapChunkData>; LOD],
+    /// From the terrain detail setting, 9.0 means that until 2^10 = 1024m away we use the highest lod
+    lod_distance_log2: f32,
     w: u32,
     h: u32,
 
@@ -97,7 +99,9 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
         .with_no_anisotropy()
         .build(&gfx.device, &gfx.queue);
 
+        let lod_distance_log2 = gfx.terrain_detail().lod_distance_log2();
         let mut bgs = vec![];
+        let mut chunk_unis = vec![];
         for lod in 0..LOD {
             let scale = 1 << lod as u32;
             let uni = Uniform::new(
@@ -105,8 +109,7 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
                     lod: lod as u32,
                     lod_pow2: scale,
                     resolution: 1 + Self::LOD0_RESOLUTION as u32 / scale,
-                    distance_lod_cutoff: 2.0f32.powf(1.0 + LOD_MIN_DIST_LOG2 + lod as f32)
-                        - std::f32::consts::FRAC_1_SQRT_2 * CSIZE as f32,
+                    distance_lod_cutoff: Self::distance_lod_cutoff(lod_distance_log2, lod),
                     cell_size: CSIZE as f32 / Self::LOD0_RESOLUTION as f32,
                     inv_cell_size: Self::LOD0_RESOLUTION as f32 / CSIZE as f32,
                 },
@@ -129,6 +132,7 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
                     label: Some("heightmap bindgroup"),
                 }),
             );
+            chunk_unis.push(uni);
         }
 
         defer!(log::info!("finished init of heightmap render"));
@@ -144,6 +148,8 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             upsample_pipeline: resample_pipeline(gfx, &heightmap_tex, "upsample"),
 
             bgs: Arc::new(collect_arrlod(bgs)),
+            chunk_unis: collect_arrlod(chunk_unis),
+            lod_distance_log2,
             heightmap_tex: Arc::new(heightmap_tex),
             normal_tex: Arc::new(normals_tex),
             indices,
@@ -202,10 +208,25 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
         );
     }
 
+    fn distance_lod_cutoff(lod_distance_log2: f32, lod: usize) -> f32 {
+        2.0f32.powf(1.0 + lod_distance_log2 + lod as f32)
+            - std::f32::consts::FRAC_1_SQRT_2 * CSIZE as f32
+    }
+
     pub fn draw_heightmap(&mut self, cam: &Camera, fctx: &mut FrameContext<'_>) {
         profiling::scope!("heightmap::draw_heightmap");
         let eye = cam.eye();
 
+        let lod_distance_log2 = fctx.gfx.terrain_detail().lod_distance_log2();
+        if lod_distance_log2 != self.lod_distance_log2 {
+            self.lod_distance_log2 = lod_distance_log2;
+            for (lod, uni) in self.chunk_unis.iter_mut().enumerate() {
+                uni.value_mut().distance_lod_cutoff =
+                    Self::distance_lod_cutoff(lod_distance_log2, lod);
+                uni.upload_to_gpu(&fctx.gfx.queue);
+            }
+        }
+
         let mut instances = vec![Vec::<HeightmapInstance>::new(); LOD];
 
         // We calculate lod in 2 passes to be able to generate the stitches
@@ -225,8 +246,8 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
                     continue;
                 }
 
-                let lod =
-                    (eye.distance(chunk_center.z0()).log2() - LOD_MIN_DIST_LOG2).max(0.0) as usize;
+                let lod = (eye.distance(chunk_center.z0()).log2() - self.lod_distance_log2).max(0.0)
+                    as usize;
                 let lod = lod.min(LOD - 1);
 
                 assigned_lod[(y * self.w + x) as usize] = 1 + lod as u8;
@@ -1,9 +1,10 @@
 #![allow(clippy::collapsible_else_if)]
 
 use crate::pbuffer::PBuffer;
-use crate::{Drawable, GfxContext, Mesh, MeshPipeline};
-use geom::{LinearColor, Matrix4, Vec3};
-use std::sync::Arc;
+use crate::{screen_coverage, Drawable, GfxContext, Mesh, MeshPipeline};
+use geom::{LinearColor, Matrix4, Sphere, Vec3, AABB3};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 use wgpu::{BufferUsages, IndexFormat, RenderPass, VertexAttribute, VertexBufferLayout};
 
 #[derive(Copy, Clone)]
@@ -52,6 +53,10 @@ impl<const PERSISTENT: bool> InstancedMeshBuilder<PERSISTENT> {
         }
     }
 
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
     pub fn build(&mut self, gfx: &GfxContext) -> Option<InstancedMesh> {
         if self.instances.is_empty() {
             return None;
@@ -68,10 +73,31 @@ impl<const PERSISTENT: bool> InstancedMeshBuilder<PERSISTENT> {
 
         ibuffer.write(gfx, bytemuck::cast_slice(&self.instances));
 
+        // the instances are only needed to be sorted by level of detail
+        let instances = if self.mesh.lods.len() > 1 {
+            Arc::new(self.instances.clone())
+        } else {
+            Arc::default()
+        };
+
+        // how far the mesh goes from its origin, in any direction since the instances are rotated
+        let reach = self.mesh.lods.first().map_or(0.0, |lod| {
+            lod.bounding_sphere.center.mag() + lod.bounding_sphere.radius
+        });
+        let aabb3 = self
+            .instances
+            .iter()
+            .map(|i| AABB3::centered(i.pos, Vec3::splat(2.0 * reach * i.dir.mag())))
+            .reduce(AABB3::union)
+            .unwrap_or(AABB3::zero());
+
         Some(InstancedMesh {
             mesh: self.mesh.clone(),
             instance_buffer: ibuffer.inner()?,
             n_instances: self.instances.len() as u32,
+            aabb3,
+            instances,
+            lods: Arc::default(),
         })
     }
 }
@@ -81,33 +107,136 @@ pub struct InstancedMesh {
     mesh: Mesh,
     instance_buffer: Arc<wgpu::Buffer>,
     n_instances: u32,
+    aabb3: AABB3,
+    instances: Arc<Vec<MeshInstance>>,
+    lods: Arc<Mutex<LodCache>>,
+}
+
+/// Lod of each instance when the instance buffer was last sorted, and the range of instances of
+/// each lod in the buffer
+#[derive(Default)]
+struct LodCache {
+    assigned: Vec<u8>,
+    ranges: Vec<Range<u32>>,
+}
+
+/// Instances too small to pass the coverage of any lod are not drawn
+const NO_LOD: u8 = u8::MAX;
+
+impl InstancedMesh {
+    /// Bounds of all the instances, the dir of an instance scales the mesh
+    pub fn aabb3(&self) -> AABB3 {
+        self.aabb3
+    }
+
+    /// The range of instances drawn with each lod, as sorted by [`Drawable::prepare`]
+    fn lod_ranges(&self) -> Vec<Range<u32>> {
+        if self.mesh.lods.len() <= 1 {
+            return vec![0..self.n_instances];
+        }
+        self.lods.lock().unwrap().ranges.clone()
+    }
+}
+
+/// The bounding sphere of a lod once placed like the instance shader does, the dir rotates the
+/// mesh around Z and scales it
+fn instance_sphere(instance: &MeshInstance, sphere: Sphere) -> Sphere {
+    let s = instance.dir.mag();
+    let Some(x) = instance.dir.try_normalize() else {
+        return Sphere::new(instance.pos, 0.0);
+    };
+    let y = Vec3::new(-x.y, x.x, 0.0).try_normalize().unwrap_or(Vec3::Y);
+    let z = x.cross(y).normalize();
+    let c = sphere.center;
+    Sphere::new(
+        instance.pos + s * (c.x * x + c.y * y + c.z * z),
+        s * sphere.radius,
+    )
+}
+
+/// The first lod whose coverage the instance passes, from the (coverage of the instance, coverage
+/// needed by the lod) of each lod
+fn select_lod(mut coverages: impl Iterator<Item = (f32, f32)>) -> u8 {
+    coverages
+        .position(|(coverage, needed)| coverage >= needed)
+        .map_or(NO_LOD, |lod| lod as u8)
+}
+
+/// The order of the instances sorted by lod, and the range of each lod in that order
+fn sort_by_lod(assigned: &[u8], n_lods: usize) -> (Vec<usize>, Vec<Range<u32>>) {
+    let mut order = Vec::with_capacity(assigned.len());
+    let mut ranges = Vec::with_capacity(n_lods);
+    for lod in 0..n_lods {
+        let start = order.len() as u32;
+        order.extend((0..assigned.len()).filter(|&i| assigned[i] as usize == lod));
+        ranges.push(start..order.len() as u32);
+    }
+    (order, ranges)
 }
 
 impl Drawable for InstancedMesh {
-    fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
-        let Some(lod_select) = self.mesh.lods.first() else {
+    /// Selects the lod of each instance from the same screen coverage as the lods of a single mesh,
+    /// so that every pass agrees. The buffer is only sorted again when an instance changes lod.
+    fn prepare(&self, gfx: &GfxContext) {
+        let lods = &self.mesh.lods;
+        if lods.len() <= 1 {
             return;
-        };
+        }
+
+        let mut cache = self.lods.lock().unwrap();
+        let cache = &mut *cache;
+        let mut changed = cache.assigned.len() != self.instances.len();
+        cache.assigned.resize(self.instances.len(), NO_LOD);
+        for (instance, assigned) in self.instances.iter().zip(cache.assigned.iter_mut()) {
+            let lod = select_lod(lods.iter().map(|lod| {
+                let sphere = instance_sphere(instance, lod.bounding_sphere);
+                (screen_coverage(gfx, sphere), lod.screen_coverage)
+            }));
+            changed |= lod != *assigned;
+            *assigned = lod;
+        }
+
+        if changed {
+            let (order, ranges) = sort_by_lod(&cache.assigned, lods.len());
+            let sorted: Vec<MeshInstance> = order.into_iter().map(|i| self.instances[i]).collect();
+            // applied before the passes of this frame are submitted
+            gfx.queue
+                .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&sorted));
+            cache.ranges = ranges;
+        }
+
+        for (lod, range) in cache.ranges.iter().enumerate() {
+            gfx.perf
+                .lod_instances(lod, (range.end - range.start) as usize);
+        }
+    }
 
+    fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         rp.set_bind_group(1, &gfx.simplelit_bg, &[]);
         rp.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
         rp.set_vertex_buffer(1, self.instance_buffer.slice(..));
         rp.set_index_buffer(self.mesh.index_buffer.slice(..), IndexFormat::Uint32);
 
-        for (mat, indices) in &lod_select.primitives {
-            let mat = gfx.material(*mat);
-            let pipeline = gfx.get_pipeline(MeshPipeline {
-                offscreen_render: false,
-                instanced: true,
-                alpha: false,
-                smap: false,
-                depth: false,
-            });
-            rp.set_pipeline(pipeline);
-            rp.set_bind_group(2, &mat.bg, &[]);
-            rp.draw_indexed(indices.clone(), 0, 0..self.n_instances);
-            gfx.perf
-                .drawcall((indices.end - indices.start) / 3 * self.n_instances);
+        for (lod, instances) in self.mesh.lods.iter().zip(self.lod_ranges()) {
+            if instances.is_empty() {
+                continue;
+            }
+            let n_instances = instances.end - instances.start;
+            for (mat, indices) in &lod.primitives {
+                let mat = gfx.material(*mat);
+                let pipeline = gfx.get_pipeline(MeshPipeline {
+                    offscreen_render: false,
+                    instanced: true,
+                    alpha: false,
+                    smap: false,
+                    depth: false,
+                });
+                rp.set_pipeline(pipeline);
+                rp.set_bind_group(2, &mat.bg, &[]);
+                rp.draw_indexed(indices.clone(), 0, instances.clone());
+                gfx.perf
+                    .drawcall((indices.end - indices.start) / 3 * n_instances);
+            }
         }
     }
 
@@ -117,32 +246,87 @@ impl Drawable for InstancedMesh {
         rp: &mut RenderPass<'a>,
         shadow_cascade: Option<&Matrix4>,
     ) {
-        let Some(lod_select) = self.mesh.lods.first() else {
-            return;
-        };
-
         rp.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
         rp.set_vertex_buffer(1, self.instance_buffer.slice(..));
         rp.set_index_buffer(self.mesh.index_buffer.slice(..), IndexFormat::Uint32);
 
-        for (mat, indices) in &lod_select.primitives {
-            let mat = gfx.material(*mat);
-            rp.set_pipeline(gfx.get_pipeline(MeshPipeline {
-                offscreen_render: false,
-                instanced: true,
-                alpha: mat.transparent,
-                smap: shadow_cascade.is_some(),
-                depth: true,
-            }));
+        let last_lod = self.mesh.lods.len().saturating_sub(1);
+        for (i, instances) in self.lod_ranges().into_iter().enumerate() {
+            if instances.is_empty() {
+                continue;
+            }
+            // the shadows don't need the details, the depth prepass must match the main pass
+            let lod = if shadow_cascade.is_some() {
+                &self.mesh.lods[(i + 1).min(last_lod)]
+            } else {
+                &self.mesh.lods[i]
+            };
+            let n_instances = instances.end - instances.start;
+            for (mat, indices) in &lod.primitives {
+                let mat = gfx.material(*mat);
+                rp.set_pipeline(gfx.get_pipeline(MeshPipeline {
+                    offscreen_render: false,
+                    instanced: true,
+                    alpha: mat.transparent,
+                    smap: shadow_cascade.is_some(),
+                    depth: true,
+                }));
 
-            if mat.transparent {
-                rp.set_bind_group(1, &mat.bg, &[]);
+                if mat.transparent {
+                    rp.set_bind_group(1, &mat.bg, &[]);
+                }
+                rp.draw_indexed(indices.clone(), 0, instances.clone());
+                gfx.perf.depth_drawcall(
+                    (indices.end - indices.start) / 3 * n_instances,
+                    shadow_cascade.is_some(),
+                );
             }
-            rp.draw_indexed(indices.clone(), 0, 0..self.n_instances);
-            gfx.perf.depth_drawcall(
-                (indices.end - indices.start) / 3 * self.n_instances,
-                shadow_cascade.is_some(),
-            );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(pos: Vec3, dir: Vec3) -> MeshInstance {
+        MeshInstance {
+            pos,
+            dir,
+            tint: LinearColor::WHITE,
+        }
+    }
+
+    #[test]
+    fn sphere_follows_the_rotation_and_scale() {
+        let sphere = Sphere::new(Vec3::new(1.0, 0.0, 2.0), 1.5);
+
+        let s = instance_sphere(&instance(Vec3::ZERO, Vec3::X), sphere);
+        assert!(s.center.distance(Vec3::new(1.0, 0.0, 2.0)) < 1e-5);
+        assert_eq!(s.radius, 1.5);
+
+        let s = instance_sphere(&instance(Vec3::new(10.0, 0.0, 0.0), 2.0 * Vec3::Y), sphere);
+        assert!(s.center.distance(Vec3::new(10.0, 2.0, 4.0)) < 1e-5);
+        assert_eq!(s.radius, 3.0);
+    }
+
+    #[test]
+    fn first_lod_passing_its_coverage_is_selected() {
+        let needed = [0.5, 0.1, 0.01];
+        let lod = |coverage: f32| select_lod(needed.iter().map(|&n| (coverage, n)));
+
+        assert_eq!(lod(0.8), 0);
+        assert_eq!(lod(0.5), 0);
+        assert_eq!(lod(0.2), 1);
+        assert_eq!(lod(0.05), 2);
+        assert_eq!(lod(0.001), NO_LOD);
+    }
+
+    #[test]
+    fn instances_are_sorted_by_lod() {
+        let (order, ranges) = sort_by_lod(&[2, 0, NO_LOD, 0, 2], 3);
+
+        assert_eq!(order, vec![1, 3, 0, 4]);
+        assert_eq!(ranges, vec![0..2, 2..2, 2..4]);
+    }
+}
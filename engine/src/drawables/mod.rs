@@ -14,12 +14,16 @@ pub use multispritebatch::*;
 pub use spritebatch::*;
 pub use water::*;
 
-use geom::Matrix4;
+use geom::{Intersect3, Matrix4, Vec3, Vec4, AABB3};
 use std::sync::Arc;
 
 pub type IndexType = u32;
 
 pub trait Drawable: Send + Sync {
+    /// Called once per frame before any pass, to update the buffers the passes read
+    #[allow(unused)]
+    fn prepare(&self, gfx: &GfxContext) {}
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>);
 
     #[allow(unused)]
@@ -33,6 +37,11 @@ pub trait Drawable: Send + Sync {
 }
 
 impl<T: ?Sized + Drawable> Drawable for Arc<T> {
+    fn prepare(&self, gfx: &GfxContext) {
+        let s: &T = self;
+        s.prepare(gfx);
+    }
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         let s: &T = self;
         s.draw(gfx, rp);
@@ -50,6 +59,12 @@ impl<T: ?Sized + Drawable> Drawable for Arc<T> {
 }
 
 impl<T: Drawable> Drawable for Option<T> {
+    fn prepare(&self, gfx: &GfxContext) {
+        if let Some(s) = self {
+            s.prepare(gfx);
+        }
+    }
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         if let Some(s) = self {
             s.draw(gfx, rp);
@@ -69,6 +84,12 @@ impl<T: Drawable> Drawable for Option<T> {
 }
 
 impl<T: Drawable> Drawable for [T] {
+    fn prepare(&self, gfx: &GfxContext) {
+        for s in self {
+            s.prepare(gfx);
+        }
+    }
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         for s in self {
             s.draw(gfx, rp);
@@ -88,6 +109,12 @@ impl<T: Drawable> Drawable for [T] {
 }
 
 impl<T: Drawable> Drawable for Vec<T> {
+    fn prepare(&self, gfx: &GfxContext) {
+        for s in self {
+            s.prepare(gfx);
+        }
+    }
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         for s in self {
             s.draw(gfx, rp);
@@ -107,6 +134,11 @@ impl<T: Drawable> Drawable for Vec<T> {
 }
 
 impl<T: Drawable, U: Drawable> Drawable for (T, U) {
+    fn prepare(&self, gfx: &GfxContext) {
+        self.0.prepare(gfx);
+        self.1.prepare(gfx);
+    }
+
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         self.0.draw(gfx, rp);
         self.1.draw(gfx, rp);
@@ -122,3 +154,57 @@ impl<T: Drawable, U: Drawable> Drawable for (T, U) {
         self.1.draw_depth(gfx, rp, shadow_cascade);
     }
 }
+
+/// Draws `T` only in the passes that see its bounds. The shadow cascades are tested on their own
+/// so that what is out of the camera still casts its shadows on screen.
+pub struct Culled<T> {
+    pub aabb: AABB3,
+    pub inner: T,
+}
+
+impl<T: Drawable> Drawable for Culled<T> {
+    fn prepare(&self, gfx: &GfxContext) {
+        self.inner.prepare(gfx);
+    }
+
+    fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
+        if !gfx.frustum_culling() || gfx.frustrum.intersects(&self.aabb) {
+            self.inner.draw(gfx, rp);
+        }
+    }
+
+    fn draw_depth<'a>(
+        &'a self,
+        gfx: &'a GfxContext,
+        rp: &mut RenderPass<'a>,
+        shadow_cascade: Option<&Matrix4>,
+    ) {
+        let visible = match shadow_cascade {
+            Some(proj) => in_cascade(proj, &self.aabb),
+            None => gfx.frustrum.intersects(&self.aabb),
+        };
+        if visible || !gfx.frustum_culling() {
+            self.inner.draw_depth(gfx, rp, shadow_cascade);
+        }
+    }
+}
+
+/// Whether the box is on the shadow map of the cascade. Only the sides are tested, anything
+/// between the sun and the cascade casts a shadow on it.
+pub fn in_cascade(proj: &Matrix4, aabb: &AABB3) -> bool {
+    let mut outside = [true; 4];
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { aabb.ll.x } else { aabb.ur.x },
+            if i & 2 == 0 { aabb.ll.y } else { aabb.ur.y },
+            if i & 4 == 0 { aabb.ll.z } else { aabb.ur.z },
+        );
+        let clip = proj * Vec4::new(corner.x, corner.y, corner.z, 1.0);
+        let (x, y) = (clip.x / clip.w, clip.y / clip.w);
+        outside[0] &= x < -1.0;
+        outside[1] &= x > 1.0;
+        outside[2] &= y < -1.0;
+        outside[3] &= y > 1.0;
+    }
+    !outside.contains(&true)
+}
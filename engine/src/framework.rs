@@ -1,6 +1,6 @@
 use rayon::ThreadPoolBuilder;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
@@ -96,6 +96,12 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                         }
                     }
                     WindowEvent::RedrawRequested => {
+                        if let Some(max_fps) = ctx.gfx.max_fps().filter(|&fps| fps > 0) {
+                            let frame = Duration::from_secs_f32(1.0 / max_fps as f32);
+                            if let Some(left) = frame.checked_sub(last_update.elapsed()) {
+                                std::thread::sleep(left);
+                            }
+                        }
                         let sco = match ctx.gfx.surface.get_current_texture() {
                             Ok(swapchainframe) => swapchainframe,
                             Err(wgpu::SurfaceError::Timeout) => ctx
@@ -121,6 +127,7 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                         let d = last_update.elapsed();
                         last_update = Instant::now();
                         ctx.delta = d.as_secs_f32();
+                        ctx.gfx.finish_mesh_loads();
                         state.update(&mut ctx);
 
                         let (mut enc, view) = ctx.gfx.start_frame(&sco);
@@ -136,6 +143,7 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                                 });
                             });
 
+                        ctx.gfx.capture_screenshot(&mut enc, &sco.texture);
                         ctx.gfx.finish_frame(enc);
                         ctx.gfx.window.set_cursor_icon(get_cursor_icon());
                         ctx.input.end_frame();
@@ -16,17 +16,21 @@ use wgpu::{
 };
 use winit::window::{Fullscreen, Window};
 
+use common::async_loader::AsyncLoader;
 use common::FastMap;
-use geom::{vec2, Camera, InfiniteFrustrum, LinearColor, Matrix4, Plane, Vec2, Vec3};
+use geom::{
+    vec2, Camera, InfiniteFrustrum, Intersect3, LinearColor, Matrix4, Plane, Vec2, Vec3, AABB3,
+};
 
 use crate::framework::State;
-use crate::meshload::{load_mesh, LoadMeshError};
-use crate::passes::{BackgroundPipeline, Pbr};
+use crate::meshload::{import_mesh, load_mesh, upload_mesh, LoadMeshError, MeshImport};
+use crate::passes::{BackgroundPipeline, OutlineParams, Pbr};
 use crate::perf_counters::PerfCounters;
+use crate::screenshot::{PendingScreenshot, ScreenshotRequest, ScreenshotResult};
 use crate::{
-    bg_layout_litmesh, passes, CompiledModule, Drawable, IndexType, LampLights, Material,
-    MaterialID, MaterialMap, Mesh, MetallicRoughness, MipmapGenerator, PipelineKey, Pipelines,
-    Texture, TextureBuildError, TextureBuilder, Uniform, UvVertex, WaterPipeline, TL,
+    bg_layout_litmesh, in_cascade, passes, CompiledModule, Drawable, IndexType, LampLights,
+    Material, MaterialID, MaterialMap, Mesh, MetallicRoughness, MipmapGenerator, PipelineKey,
+    Pipelines, Texture, TextureBuildError, TextureBuilder, Uniform, UvVertex, WaterPipeline, TL,
 };
 
 pub struct FBOs {
@@ -35,7 +39,13 @@ pub struct FBOs {
     pub(crate) color_msaa: TextureView,
     pub(crate) ssao: Texture,
     pub(crate) fog: Texture,
+    /// Depth of the outlined objects of one color
+    pub(crate) outline_mask: Texture,
+    pub(crate) outline_mask_bg: wgpu::BindGroup,
     pub(crate) ui_blur: Texture,
+    /// The 3D scene when it isn't rendered at the size of the window, blitted to the frame
+    /// before the GUI
+    pub(crate) scene: Option<Texture>,
     pub format: TextureFormat,
 }
 
@@ -50,6 +60,8 @@ pub struct GfxContext {
     pub(crate) sc_desc: SurfaceConfiguration,
     pub update_sc: bool,
     settings: Option<GfxSettings>,
+    /// Size of the 3D scene relative to the window
+    render_scale: f32,
 
     pub(crate) materials: MaterialMap,
     pub(crate) default_material: Material,
@@ -66,6 +78,10 @@ pub struct GfxContext {
 
     pub(crate) mesh_cache: FastMap<PathBuf, Arc<Mesh>>,
     pub(crate) mesh_errors: FastMap<PathBuf, LoadMeshError>,
+    mesh_loader: AsyncLoader<PathBuf, (Instant, Result<MeshImport, LoadMeshError>)>,
+    /// The meshes requested with [`GfxContext::request_mesh`] that finished loading this frame,
+    /// successfully or not
+    pub meshes_loaded: Vec<PathBuf>,
 
     pub(crate) samples: u32,
     pub(crate) screen_uv_vertices: wgpu::Buffer,
@@ -73,6 +89,8 @@ pub struct GfxContext {
     pub sun_shadowmap: Texture,
     pub pbr: Pbr,
     pub lamplights: LampLights,
+    /// Colors of the outlines of the frame, one per color
+    pub(crate) outline_params: Vec<Uniform<OutlineParams>>,
     pub(crate) defines: FastMap<String, String>,
     pub(crate) defines_changed: bool,
 
@@ -81,13 +99,17 @@ pub struct GfxContext {
     pub sky_bg: wgpu::BindGroup,
     pub water_bg: wgpu::BindGroup,
 
-    #[allow(dead_code)] // keep adapter alive
     pub(crate) adapter: Adapter,
 
     pub perf: PerfCounters,
+
+    pub(crate) screenshot_request: Option<ScreenshotRequest>,
+    pub(crate) pending_screenshot: Option<PendingScreenshot>,
+    /// Results of the screenshots asked with [`Self::request_capture`]
+    screenshot_results: Arc<Mutex<Vec<ScreenshotResult>>>,
 }
 
-#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
 pub enum ShadowQuality {
     NoShadows,
     Low,
@@ -133,19 +155,71 @@ impl ShadowQuality {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+/// How far the terrain keeps its detailed levels of detail
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TerrainDetail {
+    Low,
+    Medium,
+    High,
+}
+
+impl AsRef<str> for TerrainDetail {
+    fn as_ref(&self) -> &str {
+        match self {
+            TerrainDetail::Low => "Low",
+            TerrainDetail::Medium => "Medium",
+            TerrainDetail::High => "High",
+        }
+    }
+}
+
+impl From<u8> for TerrainDetail {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => TerrainDetail::Low,
+            1 => TerrainDetail::Medium,
+            2 => TerrainDetail::High,
+            _ => TerrainDetail::Medium,
+        }
+    }
+}
+
+impl TerrainDetail {
+    /// The highest lod is used until 2^(1 + this) meters
+    pub fn lod_distance_log2(&self) -> f32 {
+        match self {
+            TerrainDetail::Low => 8.0,
+            TerrainDetail::Medium => 9.0,
+            TerrainDetail::High => 10.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct GfxSettings {
     pub vsync: bool,
     pub fullscreen: bool,
     pub shadows: ShadowQuality,
+    /// Number of shadow cascades rendered, from 1 to [`N_CASCADES`], the farthest are dropped
+    pub shadow_cascades: u32,
     pub fog: bool,
     pub ssao: bool,
     pub terrain_grid: bool,
+    pub terrain_detail: TerrainDetail,
     pub shader_debug: bool,
     pub pbr_enabled: bool,
     pub fog_shader_debug: bool,
     pub parallel_render: bool,
     pub msaa: bool,
+    /// Size of the 3D scene relative to the window, from 0.5 to 2.0.
+    /// The GUI is always drawn at the size of the window.
+    pub render_scale: f32,
+    /// Skips what is out of view in each pass. Turned off from the debug window to compare the
+    /// frame times.
+    pub frustum_culling: bool,
+    /// The frames are waited for not to go over it
+    pub max_fps: Option<u32>,
 }
 
 impl Default for GfxSettings {
@@ -154,18 +228,181 @@ impl Default for GfxSettings {
             vsync: true,
             fullscreen: false,
             shadows: ShadowQuality::High,
+            shadow_cascades: N_CASCADES as u32,
             fog: true,
             ssao: true,
             terrain_grid: true,
+            terrain_detail: TerrainDetail::Medium,
             shader_debug: false,
             pbr_enabled: true,
             fog_shader_debug: false,
             parallel_render: false,
             msaa: false,
+            render_scale: 1.0,
+            frustum_culling: true,
+            max_fps: None,
+        }
+    }
+}
+
+/// What has to be rebuilt in the context to apply new settings, the shader defines are compared
+/// on their own
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct GfxSettingsChanges {
+    pub fullscreen: bool,
+    pub present_mode: bool,
+    pub shadowmap: bool,
+    pub fbos: bool,
+}
+
+impl GfxSettings {
+    /// Settings for the gpu, used at the first launch. Integrated and software gpus get the cheap
+    /// effects turned off, and the shadows are limited by the size of the textures.
+    pub fn recommended(device_type: wgpu::DeviceType, max_texture_size: u32) -> Self {
+        let mut settings = Self::default();
+        match device_type {
+            wgpu::DeviceType::DiscreteGpu => {}
+            wgpu::DeviceType::Cpu => {
+                settings.shadows = ShadowQuality::NoShadows;
+                settings.ssao = false;
+                settings.terrain_detail = TerrainDetail::Low;
+                settings.render_scale = 0.5;
+            }
+            _ => {
+                settings.shadows = ShadowQuality::Low;
+                settings.shadow_cascades = 2;
+                settings.ssao = false;
+                settings.terrain_detail = TerrainDetail::Low;
+            }
+        }
+        while settings
+            .shadows
+            .size()
+            .is_some_and(|size| size > max_texture_size)
+        {
+            settings.shadows = ShadowQuality::from(settings.shadows as u8 - 1);
+        }
+        settings
+    }
+
+    /// None for the first settings, when everything has to be built
+    pub fn changes_from(&self, old: Option<&GfxSettings>) -> GfxSettingsChanges {
+        let Some(old) = old else {
+            return GfxSettingsChanges {
+                fullscreen: true,
+                present_mode: true,
+                shadowmap: true,
+                fbos: true,
+            };
+        };
+        GfxSettingsChanges {
+            fullscreen: self.fullscreen != old.fullscreen,
+            present_mode: self.vsync != old.vsync,
+            shadowmap: self.shadows != old.shadows,
+            fbos: self.msaa != old.msaa || self.render_scale != old.render_scale,
         }
     }
 }
 
+#[cfg(test)]
+#[test]
+fn test_gfx_settings_changes() {
+    use common::saveload::{Encoder, JSON};
+
+    let settings = GfxSettings::default();
+    let bytes = JSON::encode(&settings).unwrap();
+    assert_eq!(JSON::decode::<GfxSettings>(&bytes).unwrap(), settings);
+    // the settings saved before a field existed still load
+    let old: GfxSettings = JSON::decode(br#"{"vsync": false}"#).unwrap();
+    assert!(!old.vsync);
+    assert_eq!(old.terrain_detail, TerrainDetail::Medium);
+
+    assert_eq!(
+        settings.changes_from(Some(&settings)),
+        GfxSettingsChanges::default()
+    );
+    assert!(settings.changes_from(None).fbos);
+
+    let mut changed = settings;
+    changed.msaa = !settings.msaa;
+    changed.shadows = ShadowQuality::Low;
+    changed.fog = !settings.fog;
+    assert_eq!(
+        changed.changes_from(Some(&settings)),
+        GfxSettingsChanges {
+            shadowmap: true,
+            fbos: true,
+            ..Default::default()
+        }
+    );
+
+    let mut scaled = settings;
+    scaled.render_scale = 0.5;
+    scaled.shadow_cascades = 2;
+    assert_eq!(
+        scaled.changes_from(Some(&settings)),
+        GfxSettingsChanges {
+            fbos: true,
+            ..Default::default()
+        }
+    );
+    assert_eq!(render_size((1920, 1080), 0.5, 8192), (960, 540));
+    assert_eq!(render_size((1920, 1080), 2.0, 2048), (2048, 1152));
+    assert_eq!(render_size((1080, 1920), 2.0, 2048), (1152, 2048));
+    assert_eq!(render_size((1920, 1080), 2.0, 8192), (3840, 2160));
+    assert_eq!(render_size((1, 1), 0.5, 8192), (1, 1));
+
+    let cpu = GfxSettings::recommended(wgpu::DeviceType::Cpu, 8192);
+    assert_eq!(cpu.shadows, ShadowQuality::NoShadows);
+    let small = GfxSettings::recommended(wgpu::DeviceType::DiscreteGpu, 1024);
+    assert_eq!(small.shadows, ShadowQuality::Medium);
+}
+
+#[cfg(test)]
+#[test]
+fn test_in_cascade() {
+    use geom::Vec4;
+
+    // the clip space is the world, the cascade sees x and y in [-1; 1]
+    let proj = Matrix4 {
+        x: Vec4::new(1.0, 0.0, 0.0, 0.0),
+        y: Vec4::new(0.0, 1.0, 0.0, 0.0),
+        z: Vec4::new(0.0, 0.0, 1.0, 0.0),
+        w: Vec4::new(0.0, 0.0, 0.0, 1.0),
+    };
+
+    assert!(in_cascade(
+        &proj,
+        &AABB3::new(Vec3::splat(-0.5), Vec3::splat(0.5))
+    ));
+    assert!(in_cascade(
+        &proj,
+        &AABB3::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(3.0, 3.0, 1.0))
+    ));
+    assert!(!in_cascade(
+        &proj,
+        &AABB3::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 1.0))
+    ));
+    assert!(!in_cascade(
+        &proj,
+        &AABB3::new(Vec3::new(0.0, -3.0, 0.0), Vec3::new(1.0, -2.0, 1.0))
+    ));
+    // the casters far above the cascade are kept
+    assert!(in_cascade(
+        &proj,
+        &AABB3::new(Vec3::new(0.0, 0.0, 50.0), Vec3::new(1.0, 1.0, 60.0))
+    ));
+}
+
+/// Size of the scene for the window size and the render scale, within the texture limits.
+/// Both sides are scaled by the same factor, lowered so that the longest fits, to keep the aspect ratio.
+fn render_size(window: (u32, u32), scale: f32, max_dimension: u32) -> (u32, u32) {
+    let longest = window.0.max(window.1).max(1) as f32;
+    let scale = scale.min(max_dimension as f32 / longest);
+    let scaled = |v: u32| ((v as f32 * scale).round() as u32).clamp(1, max_dimension);
+    (scaled(window.0), scaled(window.1))
+}
+
 pub struct Encoders {
     pub pbr: Option<CommandBuffer>,
     pub smap: Vec<CommandBuffer>,
@@ -174,6 +411,8 @@ pub struct Encoders {
     pub before_main: CommandEncoder,
     pub after_main: CommandEncoder,
     pub gui: Option<CommandBuffer>,
+    /// Copy of the frame for a screenshot that includes the GUI
+    pub after_gui: Option<CommandBuffer>,
 }
 
 pub const N_CASCADES: usize = 4;
@@ -193,12 +432,19 @@ pub struct RenderParams {
     pub sun_col: LinearColor,
     pub sand_col: LinearColor,
     pub sea_col: LinearColor,
+    /// Tint of the foliage for the current season
+    pub foliage_col: LinearColor,
     pub viewport: Vec2,
     pub unproj_pos: Vec2,
     pub time: f32,
     pub time_always: f32,
     pub shadow_mapping_resolution: i32,
     pub terraforming_mode_radius: f32,
+    /// How wet the roads look after the rain, in [0; 1]
+    pub wetness: f32,
+    /// Number of cascades of the shadow map that are rendered
+    pub shadow_cascades: i32,
+    pub _pad5: [f32; 2],
 }
 
 #[cfg(test)]
@@ -220,6 +466,7 @@ impl Default for RenderParams {
             sun_col: Default::default(),
             sand_col: Default::default(),
             sea_col: Default::default(),
+            foliage_col: LinearColor::WHITE,
             cam_pos: Default::default(),
             cam_dir: Default::default(),
             sun: Default::default(),
@@ -229,15 +476,25 @@ impl Default for RenderParams {
             time_always: 0.0,
             shadow_mapping_resolution: 2048,
             terraforming_mode_radius: 0.0,
+            wetness: 0.0,
+            shadow_cascades: N_CASCADES as i32,
             _pad: 0.0,
             _pad2: 0.0,
             _pad4: 0.0,
+            _pad5: [0.0; 2],
         }
     }
 }
 
 u8slice_impl!(RenderParams);
 
+#[derive(Clone)]
+pub enum MeshLoadState {
+    Loading,
+    Ready(Arc<Mesh>),
+    Failed(LoadMeshError),
+}
+
 pub struct GuiRenderContext<'a> {
     pub gfx: &'a GfxContext,
     pub encoder: &'a mut CommandEncoder,
@@ -248,12 +505,19 @@ pub struct GuiRenderContext<'a> {
 pub struct FrameContext<'a> {
     pub gfx: &'a mut GfxContext,
     pub objs: &'a mut Vec<Box<dyn Drawable>>,
+    pub outlines: &'a mut Vec<(LinearColor, Box<dyn Drawable>)>,
 }
 
 impl<'a> FrameContext<'a> {
     pub fn draw(&mut self, v: impl Drawable + 'static) {
         self.objs.push(Box::new(v))
     }
+
+    /// Draws a few pixels wide outline of this color around the object on the screen.
+    /// The object is only drawn to the mask of the outline, it must also be drawn to be seen.
+    pub fn outline(&mut self, color: LinearColor, v: impl Drawable + 'static) {
+        self.outlines.push((color, Box::new(v)))
+    }
 }
 
 impl GfxContext {
@@ -307,7 +571,9 @@ impl GfxContext {
         let win_scale_factor = window.scale_factor();
 
         let sc_desc = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | (capabilities.usages & TextureUsages::COPY_SRC),
             format,
             width: win_width,
             height: win_height,
@@ -318,7 +584,7 @@ impl GfxContext {
         };
         //        let samples = if cfg!(target_arch = "wasm32") { 1 } else { 4 };
         let samples = 1;
-        let fbos = Self::create_textures(&device, &sc_desc, samples);
+        let fbos = Self::create_textures(&device, &sc_desc, samples, (win_width, win_height));
         surface.configure(&device, &sc_desc);
 
         let screen_uv_vertices = device.create_buffer_init(&BufferInitDescriptor {
@@ -404,6 +670,7 @@ impl GfxContext {
             size: (win_width, win_height, win_scale_factor),
             sc_desc,
             update_sc: false,
+            render_scale: 1.0,
             adapter,
             fbos,
             surface,
@@ -421,6 +688,8 @@ impl GfxContext {
             linear_sampler,
             mesh_cache: Default::default(),
             mesh_errors: Default::default(),
+            mesh_loader: Default::default(),
+            meshes_loaded: vec![],
             samples,
             screen_uv_vertices,
             rect_indices,
@@ -430,6 +699,7 @@ impl GfxContext {
             bnoise_bg,
             sun_shadowmap: Self::mk_shadowmap(&device, 2048),
             lamplights: LampLights::new(&device, &queue),
+            outline_params: vec![],
             device,
             queue,
             pbr,
@@ -437,6 +707,9 @@ impl GfxContext {
             defines_changed: false,
             settings: None,
             perf: Default::default(),
+            screenshot_request: None,
+            pending_screenshot: None,
+            screenshot_results: Default::default(),
             mipmap_gen,
         };
 
@@ -568,6 +841,56 @@ impl GfxContext {
         }
     }
 
+    /// Starts loading the mesh in the background if it is not loaded yet, the same path is only
+    /// loaded once. The mesh is ready after [`GfxContext::finish_mesh_loads`].
+    pub fn request_mesh(&mut self, path: &Path) -> MeshLoadState {
+        if let Some(state) = self.mesh_state(path) {
+            return state;
+        }
+        let asset = path.to_path_buf();
+        self.mesh_loader.request(path.to_path_buf(), move || {
+            (Instant::now(), import_mesh(&asset, false))
+        });
+        MeshLoadState::Loading
+    }
+
+    /// Where the mesh is at, None if it was never loaded nor requested
+    pub fn mesh_state(&self, path: &Path) -> Option<MeshLoadState> {
+        if let Some(m) = self.mesh_cache.get(path) {
+            return Some(MeshLoadState::Ready(m.clone()));
+        }
+        if let Some(e) = self.mesh_errors.get(path) {
+            return Some(MeshLoadState::Failed(e.clone()));
+        }
+        if self.mesh_loader.is_pending(&path.to_path_buf()) {
+            return Some(MeshLoadState::Loading);
+        }
+        None
+    }
+
+    /// Uploads the meshes read in the background, called at the start of the frame.
+    /// The failures are logged here once, the errors are kept so they are not loaded again.
+    pub fn finish_mesh_loads(&mut self) {
+        self.meshes_loaded.clear();
+        for (path, (t, import)) in self.mesh_loader.finished() {
+            // it might have been loaded synchronously in the meantime
+            if self.mesh_state(&path).is_some() {
+                self.meshes_loaded.push(path);
+                continue;
+            }
+            match import.and_then(|import| upload_mesh(self, import, t)) {
+                Ok((m, _)) => {
+                    self.mesh_cache.insert(path.clone(), Arc::new(m));
+                }
+                Err(e) => {
+                    log::error!("Failed to load mesh {:?}: {:?}", path, e);
+                    self.mesh_errors.insert(path.clone(), e);
+                }
+            }
+            self.meshes_loaded.push(path);
+        }
+    }
+
     pub fn palette(&self) -> Arc<Texture> {
         self.texture_cache_paths
             .get(&*PathBuf::from("assets/sprites/palette.png"))
@@ -581,12 +904,14 @@ impl GfxContext {
             .expect("palette not loaded")
     }
 
+    /// Applies the settings live, only what changed is rebuilt
     pub fn update_settings(&mut self, settings: GfxSettings) {
         if self.settings == Some(settings) {
             return;
         }
+        let changes = settings.changes_from(self.settings.as_ref());
 
-        if Some(settings.fullscreen) != self.settings.map(|s| s.fullscreen) {
+        if changes.fullscreen {
             self.window.set_fullscreen(
                 settings
                     .fullscreen
@@ -594,35 +919,48 @@ impl GfxContext {
             )
         }
 
-        let present_mode = if settings.vsync {
-            wgpu::PresentMode::AutoVsync
-        } else {
-            wgpu::PresentMode::AutoNoVsync
-        };
-        if self.sc_desc.present_mode != present_mode {
-            self.sc_desc.present_mode = present_mode;
-            self.update_sc = true;
+        if changes.present_mode {
+            let present_mode = if settings.vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            };
+            if self.sc_desc.present_mode != present_mode {
+                self.sc_desc.present_mode = present_mode;
+                self.update_sc = true;
+            }
         }
 
-        let params = self.render_params.value_mut();
-        params.shadow_mapping_resolution = settings.shadows.size().unwrap_or(0) as i32;
+        if changes.shadowmap {
+            let params = self.render_params.value_mut();
+            params.shadow_mapping_resolution = settings.shadows.size().unwrap_or(0) as i32;
 
-        if let Some(v) = settings.shadows.size() {
-            if self.sun_shadowmap.extent.width != v {
-                self.sun_shadowmap = GfxContext::mk_shadowmap(&self.device, v);
-                self.update_simplelit_bg();
+            if let Some(v) = settings.shadows.size() {
+                if self.sun_shadowmap.extent.width != v {
+                    self.sun_shadowmap = GfxContext::mk_shadowmap(&self.device, v);
+                    self.update_simplelit_bg();
+                }
             }
         }
 
+        self.render_params.value_mut().shadow_cascades =
+            settings.shadow_cascades.clamp(1, N_CASCADES as u32) as i32;
+
         let samples = match settings.msaa {
             true => 4,
             false => 1,
         };
 
-        if self.samples != samples {
-            self.samples = samples;
-            self.pipelines.write().unwrap().invalidate_all();
-            self.fbos = Self::create_textures(&self.device, &self.sc_desc, samples);
+        // compared once clamped, the framebuffers are only built again when their size changes
+        let render_scale = settings.render_scale.clamp(0.5, 2.0);
+        if changes.fbos && (self.samples != samples || self.render_scale != render_scale) {
+            if self.samples != samples {
+                self.samples = samples;
+                self.pipelines.write().unwrap().invalidate_all();
+            }
+            self.render_scale = render_scale;
+            self.fbos =
+                Self::create_textures(&self.device, &self.sc_desc, samples, self.render_size());
             self.update_simplelit_bg();
         }
 
@@ -637,6 +975,62 @@ impl GfxContext {
         self.settings = Some(settings);
     }
 
+    /// The settings for the gpu of this context
+    pub fn recommended_settings(&self) -> GfxSettings {
+        GfxSettings::recommended(
+            self.adapter.get_info().device_type,
+            self.device.limits().max_texture_dimension_2d,
+        )
+    }
+
+    /// Size of the textures the 3D scene is rendered to, the screen-space passes use it
+    pub fn render_size(&self) -> (u32, u32) {
+        render_size(
+            (self.size.0, self.size.1),
+            self.render_scale,
+            self.device.limits().max_texture_dimension_2d,
+        )
+    }
+
+    /// Whether the 3D scene is rendered at another size than the window's
+    pub fn is_scaled(&self) -> bool {
+        self.fbos.scene.is_some()
+    }
+
+    pub fn frustum_culling(&self) -> bool {
+        self.settings.map_or(true, |s| s.frustum_culling)
+    }
+
+    /// Whether the box is seen by the camera or casts a shadow on one of the rendered cascades,
+    /// for what is uploaded once for all the passes
+    pub fn in_any_pass(&self, aabb: &AABB3) -> bool {
+        if !self.frustum_culling() || self.frustrum.intersects(aabb) {
+            return true;
+        }
+        let params = self.render_params.value();
+        if params.shadow_mapping_resolution == 0 {
+            return false;
+        }
+        params.sun_shadow_proj[..params.shadow_cascades as usize]
+            .iter()
+            .any(|proj| in_cascade(proj, aabb))
+    }
+
+    pub fn terrain_detail(&self) -> TerrainDetail {
+        self.settings
+            .map_or(TerrainDetail::Medium, |s| s.terrain_detail)
+    }
+
+    pub fn max_fps(&self) -> Option<u32> {
+        self.settings.and_then(|s| s.max_fps)
+    }
+
+    /// Whether the device can render and read back the mask of the outlines,
+    /// nothing is drawn for [`FrameContext::outline`] otherwise
+    pub fn outlines_supported(&self) -> bool {
+        passes::outlines_supported(self)
+    }
+
     pub fn set_time(&mut self, time: f32) {
         self.render_params.value_mut().time = time;
     }
@@ -684,6 +1078,7 @@ impl GfxContext {
                 before_main,
                 after_main,
                 gui: None,
+                after_gui: None,
             },
             sco.texture.create_view(&TextureViewDescriptor::default()),
         )
@@ -713,16 +1108,26 @@ impl GfxContext {
         self.perf.clear();
 
         let mut objs = vec![];
+        let mut outlines = vec![];
         let mut fc = FrameContext {
             objs: &mut objs,
+            outlines: &mut outlines,
             gfx: self,
         };
 
         state.render(&mut fc);
 
+        for obj in objs.iter().chain(outlines.iter().map(|(_, obj)| obj)) {
+            obj.prepare(self);
+        }
+
+        let outlines = passes::prepare_outlines(self, outlines);
+
         let start_time = Instant::now();
 
         let objsref = &*objs;
+        // the GUI is drawn on the frame, the scene is blitted to it when scaled
+        let scene = self.fbos.scene.as_ref().map_or(frame, |scene| &scene.view);
 
         let mut gui_elapsed = 0.0;
 
@@ -744,12 +1149,14 @@ impl GfxContext {
                     passes::render_ssao(self, &mut encs.before_main);
                     passes::render_fog(self, &mut encs.before_main);
 
-                    passes::render_background(self, &mut encs.after_main, frame);
+                    passes::render_background(self, &mut encs.after_main, scene);
+                    passes::render_outlines(self, &mut encs.after_main, scene, &outlines);
+                    self.blit_scene(&mut encs.after_main, frame);
                     passes::gen_ui_blur(self, &mut encs.after_main, frame);
                 });
 
                 scope.spawn(|_| {
-                    encs.main = Some(self.main_render_pass(frame, objsref));
+                    encs.main = Some(self.main_render_pass(scene, objsref));
                 });
 
                 (gui_elapsed, encs.gui) = self.render_gui(frame, state, render_gui);
@@ -762,8 +1169,10 @@ impl GfxContext {
             }
             passes::render_ssao(self, &mut encs.before_main);
             passes::render_fog(self, &mut encs.before_main);
-            encs.main = Some(self.main_render_pass(frame, objsref));
-            passes::render_background(self, &mut encs.after_main, frame);
+            encs.main = Some(self.main_render_pass(scene, objsref));
+            passes::render_background(self, &mut encs.after_main, scene);
+            passes::render_outlines(self, &mut encs.after_main, scene, &outlines);
+            self.blit_scene(&mut encs.after_main, frame);
             passes::gen_ui_blur(self, &mut encs.after_main, frame);
             (gui_elapsed, encs.gui) = self.render_gui(frame, state, render_gui);
         }
@@ -849,6 +1258,25 @@ impl GfxContext {
         main_enc.finish()
     }
 
+    /// Resolves the scene rendered at another size to the frame, filtered linearly
+    fn blit_scene(&self, enc: &mut CommandEncoder, frame: &TextureView) {
+        let Some(scene) = &self.fbos.scene else {
+            return;
+        };
+        profiling::scope!("render scale blit");
+        self.mipmap_gen
+            .with_pipeline(&self.device, self.fbos.format, |pipe| {
+                self.mipmap_gen.mipmap_one(
+                    enc,
+                    &self.device,
+                    pipe,
+                    &scene.view,
+                    frame,
+                    "render scale",
+                );
+            });
+    }
+
     fn pbr_prepass(&self) -> Option<CommandBuffer> {
         if !self.defines.contains_key("PBR_ENABLED") {
             return None;
@@ -867,17 +1295,22 @@ impl GfxContext {
         &'a self,
         objsref: &'a [Box<dyn Drawable>],
     ) -> impl Iterator<Item = CommandBuffer> + 'a {
-        self.sun_params.iter().enumerate().map(move |(i, u)| {
-            profiling::scope!(&format!("cascade shadow pass {}", i));
-            let mut smap_enc = self
-                .device
-                .create_command_encoder(&CommandEncoderDescriptor {
-                    label: Some("shadow map encoder"),
-                });
-            let sun_view = self.sun_shadowmap.layer_view(i as u32);
-            self.shadow_map_one_pass(u, objsref, &sun_view, &mut smap_enc);
-            smap_enc.finish()
-        })
+        let cascades = self.render_params.value().shadow_cascades as usize;
+        self.sun_params
+            .iter()
+            .take(cascades)
+            .enumerate()
+            .map(move |(i, u)| {
+                profiling::scope!(&format!("cascade shadow pass {}", i));
+                let mut smap_enc = self
+                    .device
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("shadow map encoder"),
+                    });
+                let sun_view = self.sun_shadowmap.layer_view(i as u32);
+                self.shadow_map_one_pass(u, objsref, &sun_view, &mut smap_enc);
+                smap_enc.finish()
+            })
     }
 
     fn shadow_map_one_pass<'a>(
@@ -941,6 +1374,51 @@ impl GfxContext {
         prepass.finish()
     }
 
+    /// Saves the next frame without the GUI as a png, downscaled to fit in `max_size` pixels
+    pub fn request_screenshot(&mut self, path: impl Into<PathBuf>, max_size: u32) {
+        self.screenshot_request = Some(ScreenshotRequest {
+            path: path.into(),
+            max_size,
+            with_gui: false,
+            report: None,
+        });
+    }
+
+    /// Saves the next frame as a png at full resolution without blocking the frame,
+    /// the outcome is given by [`Self::take_screenshot_results`]
+    pub fn request_capture(&mut self, path: impl Into<PathBuf>, with_gui: bool) {
+        self.screenshot_request = Some(ScreenshotRequest {
+            path: path.into(),
+            max_size: u32::MAX,
+            with_gui,
+            report: Some(self.screenshot_results.clone()),
+        });
+    }
+
+    /// Paths of the captures written since the last call, or why they failed
+    pub fn take_screenshot_results(&self) -> Vec<ScreenshotResult> {
+        std::mem::take(&mut *self.screenshot_results.lock().unwrap())
+    }
+
+    /// Copies the frame if a screenshot was requested, must be called after [`Self::render`]
+    pub fn capture_screenshot(&mut self, encs: &mut Encoders, frame: &wgpu::Texture) {
+        let Some(request) = self.screenshot_request.take() else {
+            return;
+        };
+        if !request.with_gui {
+            self.pending_screenshot =
+                PendingScreenshot::record(&self.device, &mut encs.after_main, frame, request);
+            return;
+        }
+        let mut enc = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("screenshot after gui"),
+            });
+        self.pending_screenshot = PendingScreenshot::record(&self.device, &mut enc, frame, request);
+        encs.after_gui = Some(enc.finish());
+    }
+
     pub fn finish_frame(&mut self, encoder: Encoders) {
         self.queue.submit(
             encoder
@@ -951,8 +1429,12 @@ impl GfxContext {
                 .chain(Some(encoder.before_main.finish()))
                 .chain(encoder.main)
                 .chain(Some(encoder.after_main.finish()))
-                .chain(encoder.gui),
+                .chain(encoder.gui)
+                .chain(encoder.after_gui),
         );
+        if let Some(screenshot) = self.pending_screenshot.take() {
+            screenshot.finish();
+        }
         if self.defines_changed {
             self.defines_changed = false;
             self.pipelines.write().unwrap().invalidate_all();
@@ -967,8 +1449,28 @@ impl GfxContext {
         self.tick += 1;
     }
 
-    pub fn create_textures(device: &Device, desc: &SurfaceConfiguration, samples: u32) -> FBOs {
-        let size = (desc.width, desc.height);
+    /// The screen-space textures for the scene rendered at `size`, the GUI blur stays at the size
+    /// of the surface
+    pub fn create_textures(
+        device: &Device,
+        desc: &SurfaceConfiguration,
+        samples: u32,
+        size: (u32, u32),
+    ) -> FBOs {
+        let scene_desc = SurfaceConfiguration {
+            width: size.0,
+            height: size.1,
+            ..desc.clone()
+        };
+        let scene = (size != (desc.width, desc.height)).then(|| {
+            Texture::create_fbo(
+                device,
+                size,
+                desc.format,
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                None,
+            )
+        });
         let ssao = Texture::create_fbo(
             device,
             size,
@@ -997,19 +1499,34 @@ impl GfxContext {
             TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
             None,
         );
+        let outline_mask = Texture::create_depth_texture(device, size, samples);
+        let outline_mask_bg = outline_mask.bindgroup(
+            device,
+            &Texture::bindgroup_layout(
+                device,
+                [if samples > 1 {
+                    TL::NonfilterableFloatMultisampled
+                } else {
+                    TL::NonfilterableFloat
+                }],
+            ),
+        );
         let ui_blur = passes::gen_blur_texture(device, desc);
 
         FBOs {
             depth,
             depth_bg,
             color_msaa: if samples > 1 {
-                Texture::create_color_msaa(device, desc, samples)
+                Texture::create_color_msaa(device, &scene_desc, samples)
             } else {
                 ssao.mip_view(0) // bogus
             },
             ssao,
             fog,
+            outline_mask,
+            outline_mask_bg,
             ui_blur,
+            scene,
             format: desc.format,
         }
     }
@@ -1020,7 +1537,12 @@ impl GfxContext {
         self.sc_desc.height = self.size.1;
 
         self.surface.configure(&self.device, &self.sc_desc);
-        self.fbos = Self::create_textures(&self.device, &self.sc_desc, self.samples);
+        self.fbos = Self::create_textures(
+            &self.device,
+            &self.sc_desc,
+            self.samples,
+            self.render_size(),
+        );
         self.update_simplelit_bg();
     }
 
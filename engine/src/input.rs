@@ -21,17 +21,25 @@ pub fn get_cursor_icon() -> CursorIcon {
     *CURSOR_ICON.lock().unwrap()
 }
 
+/// Magnification of a ctrl+wheel pinch for each wheel line
+const PINCH_PER_LINE: f32 = 0.1;
+
 #[derive(Default)]
 pub struct InputContext {
     pub mouse: MouseInfo,
     pub keyboard: KeyboardInfo,
     pub cursor_left: bool,
+    /// Whether the cursor is currently outside of the window
+    pub cursor_outside: bool,
+    /// Whether the window lost focus
+    pub unfocused: bool,
 }
 
 impl InputContext {
     pub fn end_frame(&mut self) {
         self.keyboard.last_characters.clear();
         self.mouse.wheel_delta = 0.0;
+        self.mouse.pinch_delta = 0.0;
         self.mouse.screen_delta = Vec2::ZERO;
         self.cursor_left = false;
     }
@@ -50,8 +58,17 @@ impl InputContext {
         match event {
             WindowEvent::CursorLeft { .. } => {
                 self.cursor_left = true;
+                self.cursor_outside = true;
+                true
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.cursor_outside = false;
                 true
             }
+            WindowEvent::Focused(focused) => {
+                self.unfocused = !focused;
+                false
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -106,17 +123,25 @@ impl InputContext {
                 true
             }
             WindowEvent::MouseWheel { delta, .. } => {
-                match delta {
+                let (lines, fine) = match delta {
                     // Provided mainly by the scroll wheel of computer mouse devices
-                    MouseScrollDelta::LineDelta(_, y) => {
-                        self.mouse.wheel_delta = *y * 10.0;
-                    }
+                    MouseScrollDelta::LineDelta(_, y) => (*y, y.fract() != 0.0),
                     // Provided by touchpads and drawing tablets
-                    MouseScrollDelta::PixelDelta(pos) => {
-                        self.mouse.wheel_delta = pos.y as f32;
-                    }
+                    MouseScrollDelta::PixelDelta(pos) => (pos.y as f32 / 10.0, true),
+                };
+
+                // Touchpads report pinching as a ctrl+wheel with fine grained deltas where mouse
+                // wheels move by whole lines, it goes to the pinch before any ctrl+wheel binding
+                if fine && self.keyboard.pressed.contains(&Key::Control) {
+                    self.mouse.pinch_delta += lines * PINCH_PER_LINE;
+                    return true;
                 }
 
+                self.mouse.wheel_delta = lines * 10.0;
+                true
+            }
+            WindowEvent::TouchpadMagnify { delta, .. } => {
+                self.mouse.pinch_delta += *delta as f32;
                 true
             }
             _ => false,
@@ -127,6 +152,9 @@ impl InputContext {
 #[derive(Clone, Default)]
 pub struct MouseInfo {
     pub wheel_delta: f32,
+    /// Touchpad magnification, positive when zooming in.
+    /// Also given by pinching on the touchpads reporting it as a ctrl+wheel.
+    pub pinch_delta: f32,
     pub screen: Vec2,
     pub screen_delta: Vec2,
     pub pressed: FastSet<MouseButton>,
@@ -312,3 +340,45 @@ pub enum Key {
     Paste,
     Cut,
 }
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, MouseScrollDelta, TouchPhase, WindowEvent};
+
+    use super::{InputContext, Key};
+
+    fn wheel(delta: MouseScrollDelta) -> WindowEvent {
+        WindowEvent::MouseWheel {
+            device_id: unsafe { DeviceId::dummy() },
+            delta,
+            phase: TouchPhase::Moved,
+        }
+    }
+
+    #[test]
+    fn ctrl_touchpad_wheel_is_a_pinch() {
+        let mut input = InputContext::default();
+        input.keyboard.pressed.insert(Key::Control);
+
+        // a mouse wheel still goes to the ctrl+wheel bindings
+        input.handle(&wheel(MouseScrollDelta::LineDelta(0.0, 1.0)));
+        assert_eq!(input.mouse.wheel_delta, 10.0);
+        assert_eq!(input.mouse.pinch_delta, 0.0);
+        input.end_frame();
+
+        input.handle(&wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            0.0, 20.0,
+        ))));
+        assert_eq!(input.mouse.wheel_delta, 0.0);
+        assert!(input.mouse.pinch_delta > 0.0);
+        input.end_frame();
+
+        input.keyboard.pressed.remove(&Key::Control);
+        input.handle(&wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            0.0, 20.0,
+        ))));
+        assert_eq!(input.mouse.wheel_delta, 20.0);
+        assert_eq!(input.mouse.pinch_delta, 0.0);
+    }
+}
@@ -5,6 +5,7 @@ extern crate common;
 pub mod u8slice;
 
 mod audio;
+mod audio_stream;
 mod drawables;
 pub mod egui;
 pub mod framework;
@@ -20,6 +21,7 @@ pub mod pbuffer;
 mod perf_counters;
 mod pipeline_builder;
 mod pipelines;
+mod screenshot;
 mod shader;
 mod texture;
 mod uniform;
@@ -29,6 +31,7 @@ mod vertex_types;
 pub mod yakui;
 
 pub use audio::*;
+pub use audio_stream::*;
 pub use drawables::*;
 pub use framework::Context;
 pub use geometry::*;
@@ -40,6 +43,7 @@ pub use meshbuild::*;
 pub use perf_counters::*;
 pub use pipeline_builder::*;
 pub use pipelines::*;
+pub use screenshot::ScreenshotResult;
 pub use shader::*;
 pub use texture::*;
 pub use u8slice::*;
@@ -20,6 +20,7 @@ pub struct Material {
     pub mat_params: wgpu::Buffer,
     pub metallic_roughness_map: Option<Arc<Texture>>,
     pub transparent: bool,
+    params: MaterialParams,
 }
 
 pub struct MetallicRoughness {
@@ -30,6 +31,9 @@ pub struct MetallicRoughness {
 
 const HAS_METALLIC_ROUGHNESS_MAP: u32 = 1 << 0;
 const HAS_NORMAL_MAP: u32 = 1 << 1;
+const NIGHT_WINDOWS: u32 = 1 << 2;
+const WET_SURFACE: u32 = 1 << 3;
+const FOLIAGE: u32 = 1 << 4;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -72,13 +76,14 @@ impl Material {
             flags |= HAS_NORMAL_MAP;
         }
 
+        let params = MaterialParams {
+            roughness: metallic_roughness.roughness,
+            metallic: metallic_roughness.metallic,
+            flags,
+        };
         let mat_params = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("metallic"),
-            contents: ToU8Slice::cast_slice(std::slice::from_ref(&MaterialParams {
-                roughness: metallic_roughness.roughness,
-                metallic: metallic_roughness.metallic,
-                flags,
-            })),
+            contents: ToU8Slice::cast_slice(std::slice::from_ref(&params)),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -157,9 +162,58 @@ impl Material {
             mat_params,
             metallic_roughness_map: metallic_roughness.tex,
             transparent: false,
+            params,
         }
     }
 
+    /// The same material drawn with another albedo texture, to give variants to a mesh.
+    /// The normal map is not kept.
+    pub fn with_albedo(&self, gfx: &GfxContext, albedo: &Texture) -> Self {
+        let mut mat = Self::new(
+            gfx,
+            albedo,
+            MetallicRoughness {
+                metallic: self.params.metallic,
+                roughness: self.params.roughness,
+                tex: self.metallic_roughness_map.clone(),
+            },
+            None,
+        );
+        mat.transparent = albedo.transparent;
+        mat.set_flag(self.params.flags & !HAS_NORMAL_MAP, &gfx.queue);
+        mat
+    }
+
+    /// Lights up windows drawn on the walls at night, for the buildings that have no texture
+    pub fn with_night_windows(self, queue: &Queue) -> Self {
+        self.with_flag(NIGHT_WINDOWS, queue)
+    }
+
+    /// Darkens and makes shiny the flat parts of the mesh when the ground is wet after the rain
+    pub fn with_wet_surface(self, queue: &Queue) -> Self {
+        self.with_flag(WET_SURFACE, queue)
+    }
+
+    /// Tints the green parts of the material with the foliage color of the season,
+    /// so that the same tree mesh is used all year long
+    pub fn set_foliage(&mut self, queue: &Queue) {
+        self.set_flag(FOLIAGE, queue);
+    }
+
+    fn with_flag(mut self, flag: u32, queue: &Queue) -> Self {
+        self.set_flag(flag, queue);
+        self
+    }
+
+    fn set_flag(&mut self, flag: u32, queue: &Queue) {
+        self.params.flags |= flag;
+        queue.write_buffer(
+            &self.mat_params,
+            0,
+            ToU8Slice::cast_slice(std::slice::from_ref(&self.params)),
+        );
+    }
+
     pub(crate) fn bindgroup_layout(device: &Device) -> BindGroupLayout {
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("material layout"),
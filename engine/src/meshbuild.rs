@@ -217,13 +217,15 @@ impl<const PERSISTENT: bool> MeshBuilder<PERSISTENT> {
             if lod.aabb3.ll != Vec3::ZERO || lod.aabb3.ur != Vec3::ZERO {
                 continue;
             }
-            let mut aabb3 = AABB3::zero();
+            // starting from the first vertex, the meshes far from the origin don't include it
+            let mut aabb3: Option<AABB3> = None;
             for (_, range) in &lod.primitives {
                 for &idx in &self.indices[range.start as usize..range.end as usize] {
-                    aabb3 = aabb3.union_vec(self.vertices[idx as usize].position.into());
+                    let p: Vec3 = self.vertices[idx as usize].position.into();
+                    aabb3 = Some(aabb3.map_or(AABB3::new(p, p), |aabb3| aabb3.union_vec(p)));
                 }
             }
-            lod.aabb3 = aabb3;
+            lod.aabb3 = aabb3.unwrap_or(AABB3::zero());
         }
 
         for lod in &mut self.lods {
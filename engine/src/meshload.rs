@@ -234,6 +234,8 @@ impl From<ImageLoadError> for LoadMeshError {
 pub struct CPUMesh {
     pub n_textures: usize,
     pub n_triangles: usize,
+    /// Triangles of each lod, the first one is the full model
+    pub lod_triangles: Vec<usize>,
     pub gltf_doc: Document,
     pub gltf_data: Vec<gltf::buffer::Data>,
     pub asset_path: PathBuf,
@@ -263,17 +265,18 @@ pub fn find_nodes<'a>(
     let mut result = Vec::new();
     for node in scene.nodes() {
         let (mat, rot) = mat_rot(&node);
-        result.push((node, 0, 0.0, mat, rot));
+        let lod = name_lod(&node);
+        result.push((node, lod, 0.0, mat, rot));
     }
     let mut traversed = 0;
     while traversed < result.len() {
         let (node, lod_level, _, parent_mat, parent_rot) = result[traversed].clone();
-        if lod_level > 0 {
-            traversed += 1;
-            continue;
-        }
 
-        if let Some(v) = node.extension_value("MSFT_lod") {
+        // the lods of a lod are not looked at, only the full model lists its lods
+        let msft_lod = (lod_level == 0)
+            .then(|| node.extension_value("MSFT_lod"))
+            .flatten();
+        if let Some(v) = msft_lod {
             let coverage = v.get("screencoverage").map(|v| {
                 v.as_array()
                     .unwrap()
@@ -298,9 +301,11 @@ pub fn find_nodes<'a>(
             }
         }
 
+        // the children are part of the lod of their parent
         result.extend(node.children().map(|node| {
             let (mat, rot) = mat_rot(&node);
-            (node, 0, 0.0, parent_mat * mat, parent_rot * rot)
+            let lod = name_lod(&node).max(lod_level);
+            (node, lod, 0.0, parent_mat * mat, parent_rot * rot)
         }));
 
         traversed += 1;
@@ -309,6 +314,19 @@ pub fn find_nodes<'a>(
     result
 }
 
+/// The lod given by the name of the node like `House_LOD1`, for the models exported without MSFT_lod
+fn name_lod(node: &Node) -> usize {
+    node.name()
+        .and_then(|name| name.rsplit_once("_LOD"))
+        .and_then(|(_, lod)| lod.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Screen coverage under which a lod switches to the next one, the detailed lods are kept longer
+pub fn auto_lod_coverage(triangles: usize) -> f32 {
+    (triangles as f32 / 100000.0).min(0.5)
+}
+
 pub fn load_mesh(gfx: &mut GfxContext, asset_name: &Path) -> Result<Mesh, LoadMeshError> {
     load_mesh_with_properties(gfx, asset_name, false).map(|x| x.0)
 }
@@ -318,6 +336,20 @@ pub fn load_mesh_with_properties(
     asset_name: &Path,
     force_base_model: bool,
 ) -> Result<(Mesh, CPUMesh), LoadMeshError> {
+    let t = Instant::now();
+    let import = import_mesh(asset_name, force_base_model)?;
+    upload_mesh(gfx, import, t)
+}
+
+/// The gltf file read and decoded, which doesn't need the gpu so it can be done on another thread
+pub struct MeshImport {
+    path: PathBuf,
+    doc: Document,
+    data: Vec<gltf::buffer::Data>,
+    images: Vec<Data>,
+}
+
+pub fn import_mesh(asset_name: &Path, force_base_model: bool) -> Result<MeshImport, LoadMeshError> {
     let mut path = PathBuf::new();
     path.push("assets/models_opt/");
     path.push(asset_name);
@@ -328,11 +360,30 @@ pub fn load_mesh_with_properties(
         path.push(asset_name);
     }
 
-    let t = Instant::now();
-
     let (doc, data, images) =
         gltf::import(&path).map_err(|e| LoadMeshError::GltfLoadError(Arc::new(e)))?;
 
+    Ok(MeshImport {
+        path,
+        doc,
+        data,
+        images,
+    })
+}
+
+/// Creates the materials and the buffers of the imported mesh, the time is when the load started
+pub fn upload_mesh(
+    gfx: &mut GfxContext,
+    import: MeshImport,
+    t: Instant,
+) -> Result<(Mesh, CPUMesh), LoadMeshError> {
+    let MeshImport {
+        path,
+        doc,
+        data,
+        images,
+    } = import;
+
     let exts = doc
         .extensions_used()
         .filter(|x| !matches!(x, &"MSFT_lod"))
@@ -398,9 +449,20 @@ pub fn load_mesh_with_properties(
         }
     }
 
+    // the lods given by name have no coverage, the last one is drawn until the model is culled
+    let n_lods = meshb.lods().len();
+    for lod in 0..n_lods.saturating_sub(1) {
+        let l = &meshb.lods()[lod];
+        if l.screen_coverage == 0.0 {
+            let coverage = auto_lod_coverage(l.n_indices / 3);
+            meshb.set_lod(lod, coverage as f64);
+        }
+    }
+
     let props = CPUMesh {
         n_textures: images.len(),
         n_triangles: meshb.lods()[0].n_indices / 3,
+        lod_triangles: meshb.lods().iter().map(|l| l.n_indices / 3).collect(),
         gltf_doc: doc,
         gltf_data: data,
         asset_path: path,
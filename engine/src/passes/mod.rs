@@ -1,11 +1,13 @@
 mod background;
 mod blur;
 mod fog;
+mod outline;
 mod pbr;
 mod ssao;
 
 pub use background::*;
 pub use blur::*;
 pub use fog::*;
+pub use outline::*;
 pub use pbr::*;
 pub use ssao::*;
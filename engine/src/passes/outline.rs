@@ -0,0 +1,199 @@
+use crate::{
+    CompiledModule, Drawable, GfxContext, PipelineKey, RenderParams, Texture, Uniform, UvVertex, TL,
+};
+use geom::LinearColor;
+use wgpu::{
+    BlendState, CommandEncoder, FragmentState, IndexFormat, PipelineLayoutDescriptor,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    TextureFormat, TextureUsages, TextureView, VertexState,
+};
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct OutlineParams {
+    pub color: LinearColor,
+}
+
+u8slice_impl!(OutlineParams);
+
+/// The objects outlined with the same color, drawn together to the mask
+pub struct OutlineGroup {
+    params: usize,
+    objs: Vec<Box<dyn Drawable>>,
+}
+
+#[derive(Copy, Clone, Hash)]
+pub struct OutlinePipeline;
+
+pub(crate) fn outlines_supported(gfx: &GfxContext) -> bool {
+    let features = gfx
+        .adapter
+        .get_texture_format_features(TextureFormat::Depth32Float);
+    features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
+        && features.flags.sample_count_supported(gfx.samples)
+}
+
+/// Groups the objects by color and uploads the colors, one uniform per group
+pub fn prepare_outlines(
+    gfx: &mut GfxContext,
+    outlines: Vec<(LinearColor, Box<dyn Drawable>)>,
+) -> Vec<OutlineGroup> {
+    if !outlines_supported(gfx) {
+        return vec![];
+    }
+
+    let mut colors: Vec<LinearColor> = vec![];
+    let mut groups: Vec<OutlineGroup> = vec![];
+    for (color, obj) in outlines {
+        let key: [f32; 4] = color.into();
+        let params = colors
+            .iter()
+            .position(|c| <[f32; 4]>::from(c) == key)
+            .unwrap_or_else(|| {
+                colors.push(color);
+                groups.push(OutlineGroup {
+                    params: groups.len(),
+                    objs: vec![],
+                });
+                groups.len() - 1
+            });
+        groups[params].objs.push(obj);
+    }
+
+    while gfx.outline_params.len() < colors.len() {
+        let u = Uniform::new(
+            OutlineParams {
+                color: LinearColor::WHITE,
+            },
+            &gfx.device,
+        );
+        gfx.outline_params.push(u);
+    }
+    for (u, color) in gfx.outline_params.iter_mut().zip(colors) {
+        u.value_mut().color = color;
+        u.upload_to_gpu(&gfx.queue);
+    }
+
+    groups
+}
+
+/// For each group, draws the depth of its objects to the mask then the outline around it on the
+/// frame. Must run after the background so that the outline isn't drawn over by the sky.
+pub fn render_outlines(
+    gfx: &GfxContext,
+    enc: &mut CommandEncoder,
+    frame: &TextureView,
+    groups: &[OutlineGroup],
+) {
+    if groups.is_empty() {
+        return;
+    }
+    profiling::scope!("outlines");
+    let pipeline = gfx.get_pipeline(OutlinePipeline);
+
+    for group in groups {
+        let mut mask_pass = enc.begin_render_pass(&RenderPassDescriptor {
+            label: Some("outline mask pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &gfx.fbos.outline_mask.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        mask_pass.set_bind_group(0, &gfx.render_params.bg, &[]);
+        for obj in &group.objs {
+            obj.draw_depth(gfx, &mut mask_pass, None);
+        }
+        drop(mask_pass);
+
+        let mut outline_pass = enc.begin_render_pass(&RenderPassDescriptor {
+            label: Some("outline pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        outline_pass.set_pipeline(pipeline);
+        outline_pass.set_bind_group(0, &gfx.render_params.bg, &[]);
+        outline_pass.set_bind_group(1, &gfx.fbos.outline_mask_bg, &[]);
+        outline_pass.set_bind_group(2, &gfx.outline_params[group.params].bg, &[]);
+        outline_pass.set_vertex_buffer(0, gfx.screen_uv_vertices.slice(..));
+        outline_pass.set_index_buffer(gfx.rect_indices.slice(..), IndexFormat::Uint32);
+        outline_pass.draw_indexed(0..6, 0, 0..1);
+    }
+}
+
+impl PipelineKey for OutlinePipeline {
+    fn build(
+        &self,
+        gfx: &GfxContext,
+        mut mk_module: impl FnMut(&str, &[&str]) -> CompiledModule,
+    ) -> RenderPipeline {
+        let render_pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("outline pipeline"),
+                bind_group_layouts: &[
+                    &Uniform::<RenderParams>::bindgroup_layout(&gfx.device),
+                    &Texture::bindgroup_layout(
+                        &gfx.device,
+                        [if gfx.samples > 1 {
+                            TL::NonfilterableFloatMultisampled
+                        } else {
+                            TL::NonfilterableFloat
+                        }],
+                    ),
+                    &Uniform::<OutlineParams>::bindgroup_layout(&gfx.device),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let color_states = [Some(wgpu::ColorTargetState {
+            format: gfx.sc_desc.format,
+            blend: Some(BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::COLOR,
+        })];
+
+        let outline = mk_module("outline", &[]);
+
+        let render_pipeline_desc = RenderPipelineDescriptor {
+            label: Some("outline pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &outline,
+                entry_point: "vert",
+                compilation_options: Default::default(),
+                buffers: &[UvVertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &outline,
+                entry_point: "frag",
+                compilation_options: Default::default(),
+                targets: &color_states,
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        };
+
+        gfx.device.create_render_pipeline(&render_pipeline_desc)
+    }
+}
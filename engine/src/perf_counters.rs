@@ -1,5 +1,8 @@
 use std::sync::atomic::AtomicUsize;
 
+/// Number of lods whose instances are counted, the coarser ones are counted in the last one
+pub const LOD_COUNTERS: usize = 4;
+
 #[derive(Default)]
 pub struct PerfCounters {
     total_triangles: AtomicUsize,
@@ -14,6 +17,8 @@ pub struct PerfCounters {
     heightmap_triangles: AtomicUsize,
     heightmap_depth_triangles: AtomicUsize,
     heightmap_shadows_triangles: AtomicUsize,
+
+    lod_instances: [AtomicUsize; LOD_COUNTERS],
 }
 
 pub struct PerfCountersStatic {
@@ -29,6 +34,9 @@ pub struct PerfCountersStatic {
     pub heightmap_triangles: usize,
     pub heightmap_depth_triangles: usize,
     pub heightmap_shadows_triangles: usize,
+
+    /// Instances drawn with each lod
+    pub lod_instances: [usize; LOD_COUNTERS],
 }
 
 impl PerfCounters {
@@ -47,6 +55,7 @@ impl PerfCounters {
             heightmap_triangles: *self.heightmap_triangles.get_mut(),
             heightmap_depth_triangles: *self.heightmap_depth_triangles.get_mut(),
             heightmap_shadows_triangles: *self.heightmap_shadows_triangles.get_mut(),
+            lod_instances: std::array::from_fn(|i| *self.lod_instances[i].get_mut()),
         }
     }
 
@@ -60,6 +69,9 @@ impl PerfCounters {
         *self.heightmap_triangles.get_mut() = 0;
         *self.heightmap_depth_triangles.get_mut() = 0;
         *self.heightmap_shadows_triangles.get_mut() = 0;
+        for v in &mut self.lod_instances {
+            *v.get_mut() = 0;
+        }
     }
 
     pub fn drawcall(&self, triangles: impl TryInto<usize>) {
@@ -89,6 +101,11 @@ impl PerfCounters {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 
+    pub fn lod_instances(&self, lod: usize, instances: usize) {
+        self.lod_instances[lod.min(LOD_COUNTERS - 1)]
+            .fetch_add(instances, std::sync::atomic::Ordering::Relaxed);
+    }
+
     pub fn heightmap_drawcall(&self, triangles: impl TryInto<usize>) {
         self.heightmap_triangles.fetch_add(
             triangles.try_into().unwrap_or(0),
@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use wgpu::{
+    Buffer, CommandEncoder, Device, ImageCopyTexture, ImageDataLayout, MapMode, TextureFormat,
+};
+
+/// Where the png was written, or why it could not be
+pub type ScreenshotResult = Result<PathBuf, String>;
+
+/// Screenshot asked for the next frame, saved as a png no larger than `max_size` pixels
+pub(crate) struct ScreenshotRequest {
+    pub path: PathBuf,
+    pub max_size: u32,
+    /// The copy is made after the GUI was drawn so that it is included
+    pub with_gui: bool,
+    /// Receives the result once the png is written, otherwise failures are only logged
+    pub report: Option<Arc<Mutex<Vec<ScreenshotResult>>>>,
+}
+
+impl ScreenshotRequest {
+    fn done(&self, result: ScreenshotResult) {
+        if let Err(ref e) = result {
+            log::error!("{}", e);
+        }
+        if let Some(ref report) = self.report {
+            report.lock().unwrap().push(result);
+        }
+    }
+}
+
+/// Frame copied to a buffer, waiting for the copy to be submitted to be read back
+pub(crate) struct PendingScreenshot {
+    buffer: Arc<Buffer>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    request: ScreenshotRequest,
+}
+
+impl PendingScreenshot {
+    /// Records the copy of the frame, it must be recorded before the GUI to not include it
+    /// and after it otherwise
+    pub fn record(
+        device: &Device,
+        enc: &mut CommandEncoder,
+        frame: &wgpu::Texture,
+        request: ScreenshotRequest,
+    ) -> Option<Self> {
+        let bgra = match frame.format() {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            format => {
+                request.done(Err(format!(
+                    "screenshot not implemented for format {:?}",
+                    format
+                )));
+                return None;
+            }
+        };
+        if !frame.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            request.done(Err(
+                "screenshot not supported: the surface cannot be copied from".to_string(),
+            ));
+            return None;
+        }
+
+        let width = frame.width();
+        let height = frame.height();
+        let padded_bytes_per_row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("screenshot"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        }));
+
+        enc.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: frame,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            frame.size(),
+        );
+
+        Some(Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+            request,
+        })
+    }
+
+    /// Reads the buffer back once the copy was submitted, the png is written on another thread
+    pub fn finish(self) {
+        let Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+            request,
+        } = self;
+        let buffer_cpy = buffer.clone();
+        buffer.slice(..).map_async(MapMode::Read, move |v| {
+            if v.is_err() {
+                request.done(Err(
+                    "Failed to map buffer for reading for screenshot".to_string()
+                ));
+                return;
+            }
+
+            let mut rgba = Vec::with_capacity((4 * width * height) as usize);
+            {
+                let data = buffer_cpy.slice(..).get_mapped_range();
+                for row in data.chunks(padded_bytes_per_row as usize) {
+                    rgba.extend_from_slice(&row[..4 * width as usize]);
+                }
+            }
+            buffer_cpy.unmap();
+
+            for px in rgba.chunks_exact_mut(4) {
+                if bgra {
+                    px.swap(0, 2);
+                }
+                px[3] = 255;
+            }
+
+            std::thread::spawn(move || {
+                let Some(img) = image::RgbaImage::from_raw(width, height, rgba) else {
+                    request.done(Err(
+                        "Failed to create image from buffer for screenshot".to_string()
+                    ));
+                    return;
+                };
+                let scale = (request.max_size as f32 / width.max(height) as f32).min(1.0);
+                let img = image::imageops::thumbnail(
+                    &img,
+                    ((width as f32 * scale) as u32).max(1),
+                    ((height as f32 * scale) as u32).max(1),
+                );
+
+                if let Some(parent) = request.path.parent() {
+                    if let Err(e) = std::fs::create_dir_all(parent) {
+                        request.done(Err(format!(
+                            "Failed to create the directory {}: {}",
+                            parent.display(),
+                            e
+                        )));
+                        return;
+                    }
+                }
+                let result = img
+                    .save(&request.path)
+                    .map(|_| request.path.clone())
+                    .map_err(|e| format!("Failed to save screenshot to file: {}", e));
+                request.done(result);
+            });
+        });
+    }
+}
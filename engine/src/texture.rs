@@ -361,6 +361,37 @@ impl Texture {
         })
     }
 
+    /// Replaces a rectangle of the first mip level with rgba8 pixels, row by row.
+    /// Used to update parts of a texture drawn on the cpu
+    pub fn write_region(
+        &self,
+        queue: &wgpu::Queue,
+        (x, y): (u32, u32),
+        (w, h): (u32, u32),
+        data: &[u8],
+    ) {
+        debug_assert_eq!(data.len(), (w * h * 4) as usize);
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: Default::default(),
+            },
+            data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * w),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
     pub fn layer_view(&self, layer: u32) -> TextureView {
         self.texture.create_view(&TextureViewDescriptor {
             label: Some("texture array one layer view"),
@@ -2,6 +2,7 @@ use crate::{GfxContext, GuiRenderContext, Texture};
 use std::path::PathBuf;
 use std::sync::Arc;
 use wgpu::{TextureFormat, TextureViewDescriptor};
+use winit::event::WindowEvent;
 use winit::window::Window;
 use yakui::font::{Font, FontSettings, Fonts};
 use yakui::{TextureId, Yakui};
@@ -13,6 +14,8 @@ pub struct YakuiWrapper {
     pub zoom_factor: f32,
     pub format: TextureFormat,
     pub blur_bg_texture: TextureId,
+    /// Whether the last mouse event was captured by the UI, meaning a widget is hovered
+    pub mouse_captured: bool,
 }
 
 impl YakuiWrapper {
@@ -52,6 +55,7 @@ impl YakuiWrapper {
             platform,
             zoom_factor: 1.0,
             format: gfx.fbos.format,
+            mouse_captured: false,
         }
     }
 
@@ -82,7 +86,8 @@ impl YakuiWrapper {
             self.yakui.finish();
         }
 
-        let surface_info = if gfx.gfx.samples > 1 {
+        // the multisampled buffer has the size of the scene, which may not be the window's
+        let surface_info = if gfx.gfx.samples > 1 && !gfx.gfx.is_scaled() {
             yakui_wgpu::SurfaceInfo {
                 format: self.format,
                 sample_count: gfx.gfx.samples,
@@ -108,6 +113,17 @@ impl YakuiWrapper {
     }
 
     pub fn handle_event(&mut self, e: &winit::event::Event<()>) -> bool {
-        self.platform.handle_event(&mut self.yakui, e)
+        let captured = self.platform.handle_event(&mut self.yakui, e);
+        if let winit::event::Event::WindowEvent {
+            event:
+                WindowEvent::CursorMoved { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. },
+            ..
+        } = e
+        {
+            self.mouse_captured = captured;
+        }
+        captured
     }
 }
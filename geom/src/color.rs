@@ -149,6 +149,46 @@ impl Color {
         b: 0.9,
         a: 1.0,
     };
+
+    pub fn lerp(self, other: Color, coeff: f32) -> Color {
+        Color {
+            r: crate::lerp(self.r, other.r, coeff),
+            g: crate::lerp(self.g, other.g, coeff),
+            b: crate::lerp(self.b, other.b, coeff),
+            a: crate::lerp(self.a, other.a, coeff),
+        }
+    }
+}
+
+/// Colors evenly spread between 0 and 1, values in between are interpolated
+#[derive(Copy, Clone, Debug)]
+pub struct ColorRamp(pub &'static [Color]);
+
+impl ColorRamp {
+    /// Green to yellow to red, for values from good to bad
+    pub const HEATMAP: ColorRamp = ColorRamp(&[Color::GREEN, Color::YELLOW, Color::RED]);
+
+    /// The color at `t`, clamped between 0 and 1
+    pub fn at(&self, t: f32) -> Color {
+        let stops = self.0;
+        let Some(&last) = stops.last() else {
+            return Color::TRANSPARENT;
+        };
+        if stops.len() == 1 || t >= 1.0 || t.is_nan() {
+            return last;
+        }
+        let pos = t.max(0.0) * (stops.len() - 1) as f32;
+        let i = pos as usize;
+        stops[i].lerp(stops[i + 1], pos - i as f32)
+    }
+
+    /// The color of the value, `min` and `max` being the ends of the ramp
+    pub fn map(&self, value: f32, min: f32, max: f32) -> Color {
+        if max <= min {
+            return self.at(0.0);
+        }
+        self.at((value - min) / (max - min))
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -361,3 +401,21 @@ impl From<&LinearColor> for LinearColor {
         *x
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, ColorRamp};
+
+    #[test]
+    fn ramp_maps_values_between_stops() {
+        let ramp = ColorRamp(&[Color::BLACK, Color::WHITE, Color::RED]);
+        assert_eq!(ramp.map(0.0, 0.0, 100.0), Color::BLACK);
+        assert_eq!(ramp.map(25.0, 0.0, 100.0), Color::gray(0.5));
+        assert_eq!(ramp.map(50.0, 0.0, 100.0), Color::WHITE);
+        assert_eq!(ramp.map(75.0, 0.0, 100.0), Color::new(1.0, 0.5, 0.5, 1.0));
+        // out of range values take the color of the closest end
+        assert_eq!(ramp.map(-10.0, 0.0, 100.0), Color::BLACK);
+        assert_eq!(ramp.map(150.0, 0.0, 100.0), Color::RED);
+        assert_eq!(ramp.map(f32::NAN, 0.0, 100.0), Color::RED);
+    }
+}
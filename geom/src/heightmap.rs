@@ -2,6 +2,7 @@ use crate::{vec2, vec3, Ray3, Vec2, Vec3, AABB, AABB3};
 use serde::de::{SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 
 pub type HeightmapChunkID = (u16, u16);
 
@@ -119,6 +120,20 @@ impl<const RESOLUTION: usize, const SIZE: u32> HeightmapChunk<RESOLUTION, SIZE>
     }
 }
 
+/// The packed heights of whole chunks, to put them back exactly as they were.
+/// A brush stroke only copies the chunks it reaches for the first time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeightmapPatch {
+    /// Heights of each chunk, indexed with (x + y * RESOLUTION)
+    chunks: BTreeMap<HeightmapChunkID, Vec<u16>>,
+}
+
+impl HeightmapPatch {
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Heightmap<const RESOLUTION: usize, const SIZE: u32> {
     chunks: Vec<HeightmapChunk<RESOLUTION, SIZE>>, // chunks is an array of length w * h, indexed with (x + y * w)
@@ -168,6 +183,16 @@ impl<const RESOLUTION: usize, const SIZE: u32> Heightmap<RESOLUTION, SIZE> {
         unsafe { Some(self.chunks.get_unchecked((id.0 + id.1 * self.w) as usize)) }
     }
 
+    /// Copies the chunks the bounds cover from a heightmap of the same size,
+    /// to keep a copy of it up to date
+    pub fn copy_chunks(&mut self, other: &Self, bounds: AABB) {
+        for id in other.covered_chunks(bounds) {
+            if let (Some(dst), Some(src)) = (self.get_chunk_mut(id), other.get_chunk(id)) {
+                dst.clone_from(src);
+            }
+        }
+    }
+
     pub fn set_override(
         &mut self,
         id: HeightmapChunkID,
@@ -315,6 +340,60 @@ impl<const RESOLUTION: usize, const SIZE: u32> Heightmap<RESOLUTION, SIZE> {
             .flat_map(move |y| (ll.x as u16..ur.x as u16).map(move |x| (x, y)))
     }
 
+    /// Copies the heights of the chunks the given bounds cover, overrides excluded
+    pub fn patch(&self, bounds: AABB) -> HeightmapPatch {
+        let mut patch = HeightmapPatch::default();
+        self.extend_patch(&mut patch, bounds);
+        patch
+    }
+
+    /// Copies the current heights of the chunks of the patch
+    pub fn patch_like(&self, patch: &HeightmapPatch) -> HeightmapPatch {
+        HeightmapPatch {
+            chunks: patch
+                .chunks
+                .keys()
+                .filter_map(|&id| Some((id, self.copy_heights(id)?)))
+                .collect(),
+        }
+    }
+
+    /// Adds the chunks the bounds cover to the patch.
+    /// The chunks it already has keep the heights they had when they were copied,
+    /// so a brush stroke only copies each chunk once.
+    pub fn extend_patch(&self, patch: &mut HeightmapPatch, bounds: AABB) {
+        for id in self.covered_chunks(bounds) {
+            if patch.chunks.contains_key(&id) {
+                continue;
+            }
+            if let Some(heights) = self.copy_heights(id) {
+                patch.chunks.insert(id, heights);
+            }
+        }
+    }
+
+    fn copy_heights(&self, id: HeightmapChunkID) -> Option<Vec<u16>> {
+        let chunk = self.get_chunk(id)?;
+        Some(chunk.heights.iter().flatten().copied().collect())
+    }
+
+    /// Puts back the heights of the patch, returns the modified chunks
+    pub fn apply_patch(&mut self, patch: &HeightmapPatch) -> Vec<HeightmapChunkID> {
+        let mut modified = Vec::with_capacity(patch.chunks.len());
+        for (&id, heights) in &patch.chunks {
+            let Some(chunk) = self.get_chunk_mut(id) else {
+                continue;
+            };
+            for (row, packed) in chunk.heights.iter_mut().zip(heights.chunks(RESOLUTION)) {
+                row.copy_from_slice(packed);
+            }
+            chunk.max_height = 0.0;
+            chunk.update_max_height();
+            modified.push(id);
+        }
+        modified
+    }
+
     /// Returns height at any point using the cell to the bottom left of the point
     pub fn height_nearest(&self, p: Vec2) -> Option<f32> {
         let cell = HeightmapChunk::<RESOLUTION, SIZE>::id(p);
@@ -563,6 +642,41 @@ mod tests {
             }
         }
     }
+
+    type TestHeightmap = Heightmap<4, 64>;
+
+    fn packed(h: &TestHeightmap) -> Vec<[[u16; 4]; 4]> {
+        h.chunks().map(|(_, c)| *c.heights()).collect()
+    }
+
+    #[test]
+    fn extended_patch_restores_heights() {
+        let mut h = TestHeightmap::new(3, 3);
+        let original = packed(&h);
+
+        // two dabs of a stroke, the second one overlapping the first
+        let first = AABB::new_ll_ur(vec2(10.0, 10.0), vec2(60.0, 60.0));
+        let second = AABB::new_ll_ur(vec2(40.0, 40.0), vec2(150.0, 100.0));
+
+        let mut before = h.patch(first);
+        h.apply(first, |p| p.z + 10.0);
+        h.extend_patch(&mut before, second);
+        h.apply(second, |p| p.z + 5.0);
+
+        // dabbing again over the same chunks copies nothing
+        let copied = before.clone();
+        h.extend_patch(&mut before, first.union(second));
+        assert_eq!(before, copied);
+        assert_ne!(packed(&h), original);
+
+        let after = h.patch_like(&before);
+        h.apply_patch(&before);
+        assert_eq!(packed(&h), original);
+
+        h.apply_patch(&after);
+        assert_eq!(h.patch_like(&before), after);
+        assert!(h.height_nearest(vec2(50.0, 50.0)).unwrap() > 14.0);
+    }
 }
 
 impl<const RESOLUTION: usize, const SIZE: u32> Serialize for HeightmapChunk<RESOLUTION, SIZE> {
@@ -1,6 +1,7 @@
 use super::Vec2;
 use crate::aabb::AABB;
 use crate::segment::Segment;
+use crate::Intersect;
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use std::hint::unreachable_unchecked;
@@ -291,6 +292,54 @@ impl PolyLine {
         }
     }
 
+    /// Shifts the polyline sideways by dist, to the right of its direction (to the left if negative).
+    /// Corners are mitered so that the offset line stays at dist from every segment.
+    /// Returns None when the offset line would fold or cross itself, i.e. when a curve is tighter than dist.
+    pub fn offset(&self, dist: f32) -> Option<PolyLine> {
+        let pts = &self.points;
+        let dirs: Vec<Vec2> = pts
+            .windows(2)
+            .filter_map(|w| (w[1] - w[0]).try_normalize())
+            .collect();
+        if dirs.is_empty() || dirs.len() + 1 != pts.len() {
+            // not a single segment or duplicate points
+            return None;
+        }
+
+        let mut points = Vec::with_capacity(pts.len());
+        points.push(pts[0] + dirs[0].perpendicular() * dist);
+        for (i, w) in dirs.windows(2).enumerate() {
+            let (a, b) = (w[0].perpendicular(), w[1].perpendicular());
+            let miter = (a + b).try_normalize()?;
+            let cos = miter.dot(a);
+            if cos < 0.1 {
+                // hairpin, the miter would go to infinity
+                return None;
+            }
+            points.push(pts[i + 1] + miter * (dist / cos));
+        }
+        points.push(pts[pts.len() - 1] + dirs[dirs.len() - 1].perpendicular() * dist);
+
+        // a segment going backward means the inner side of a curve folded
+        for (w, dir) in points.windows(2).zip(&dirs) {
+            if (w[1] - w[0]).dot(*dir) <= 0.0 {
+                return None;
+            }
+        }
+
+        let segments: Vec<Segment> = points
+            .windows(2)
+            .map(|w| Segment::new(w[0], w[1]))
+            .collect();
+        for (i, s) in segments.iter().enumerate() {
+            if segments.iter().skip(i + 2).any(|s2| s.intersects(s2)) {
+                return None;
+            }
+        }
+
+        Some(PolyLine::new(points))
+    }
+
     #[inline]
     pub fn bbox(&self) -> AABB {
         let (min, max) = match super::minmax(self.points.iter().copied()) {
@@ -404,3 +453,34 @@ impl<'a> PointsAlongs<'a> {
 fn length(v: &[Vec2]) -> f32 {
     v.windows(2).map(|x| (x[1] - x[0]).mag()).sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{vec2, PolyLine};
+
+    #[test]
+    fn offset_straight() {
+        let p = PolyLine::new(vec![vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(20.0, 0.0)]);
+        let right = p.offset(2.0).unwrap();
+        assert_eq!(
+            right.as_slice(),
+            &[vec2(0.0, -2.0), vec2(10.0, -2.0), vec2(20.0, -2.0)]
+        );
+        let left = p.offset(-2.0).unwrap();
+        assert_eq!(left.first(), vec2(0.0, 2.0));
+    }
+
+    #[test]
+    fn offset_corner() {
+        let p = PolyLine::new(vec![vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0)]);
+        let outer = p.offset(1.0).unwrap();
+        assert!(outer[1].distance(vec2(11.0, -1.0)) < 1e-4);
+
+        let inner = p.offset(-1.0).unwrap();
+        assert!(inner[1].distance(vec2(9.0, 1.0)) < 1e-4);
+
+        // the inner side of a curve tighter than the offset folds
+        assert!(p.offset(-15.0).is_none());
+        assert!(p.offset(15.0).is_some());
+    }
+}
@@ -1,5 +1,9 @@
+use std::cell::Cell;
+
 use yakui_core::event::{EventInterest, EventResponse, WidgetEvent};
-use yakui_core::widget::{EventContext, Widget};
+use yakui_core::geometry::{Rect, Vec2};
+use yakui_core::input::MouseButton;
+use yakui_core::widget::{EventContext, PaintContext, Widget};
 use yakui_core::Response;
 use yakui_widgets::util::widget_children;
 
@@ -44,3 +48,128 @@ impl Widget for IsHoveredWidget {
         EventResponse::Bubble
     }
 }
+
+/// Position of the mouse relative to the top left corner of the children, None if it is outside
+pub fn hover_pos(children: impl FnOnce()) -> Response<HoverPosResponse> {
+    widget_children::<HoverPosWidget, _>(children, ())
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HoverPosResponse {
+    pub pos: Option<Vec2>,
+}
+
+#[derive(Debug)]
+pub struct HoverPosWidget {
+    rect: Cell<Rect>,
+    mouse: Option<Vec2>,
+}
+
+impl Widget for HoverPosWidget {
+    type Props<'a> = ();
+    type Response = HoverPosResponse;
+
+    fn new() -> Self {
+        Self {
+            rect: Cell::new(Rect::ZERO),
+            mouse: None,
+        }
+    }
+
+    fn update(&mut self, _: Self::Props<'_>) -> Self::Response {
+        let rect = self.rect.get();
+        HoverPosResponse {
+            pos: self
+                .mouse
+                .filter(|&p| rect.contains_point(p))
+                .map(|p| p - rect.pos()),
+        }
+    }
+
+    fn paint(&self, mut ctx: PaintContext<'_>) {
+        self.rect
+            .set(ctx.layout.get(ctx.dom.current()).unwrap().rect);
+        let node = ctx.dom.get_current();
+        for &child in &node.children {
+            ctx.paint(child);
+        }
+    }
+
+    fn event_interest(&self) -> EventInterest {
+        EventInterest::MOUSE_ALL
+    }
+
+    fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
+        match *event {
+            WidgetEvent::MouseMoved(pos) => self.mouse = pos,
+            WidgetEvent::MouseLeave => self.mouse = None,
+            _ => {}
+        };
+        EventResponse::Bubble
+    }
+}
+
+/// Position of the mouse relative to the top left corner of the children while the left button
+/// is held after being pressed on them, even when the mouse leaves them. None otherwise
+pub fn drag_pos(children: impl FnOnce()) -> Response<HoverPosResponse> {
+    widget_children::<DragPosWidget, _>(children, ())
+}
+
+#[derive(Debug)]
+pub struct DragPosWidget {
+    rect: Cell<Rect>,
+    mouse: Option<Vec2>,
+    dragging: bool,
+}
+
+impl Widget for DragPosWidget {
+    type Props<'a> = ();
+    type Response = HoverPosResponse;
+
+    fn new() -> Self {
+        Self {
+            rect: Cell::new(Rect::ZERO),
+            mouse: None,
+            dragging: false,
+        }
+    }
+
+    fn update(&mut self, _: Self::Props<'_>) -> Self::Response {
+        let rect = self.rect.get();
+        HoverPosResponse {
+            pos: self.mouse.filter(|_| self.dragging).map(|p| p - rect.pos()),
+        }
+    }
+
+    fn paint(&self, mut ctx: PaintContext<'_>) {
+        self.rect
+            .set(ctx.layout.get(ctx.dom.current()).unwrap().rect);
+        let node = ctx.dom.get_current();
+        for &child in &node.children {
+            ctx.paint(child);
+        }
+    }
+
+    fn event_interest(&self) -> EventInterest {
+        EventInterest::MOUSE_ALL
+    }
+
+    fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
+        match *event {
+            WidgetEvent::MouseMoved(Some(pos)) => self.mouse = Some(pos),
+            WidgetEvent::MouseButtonChanged {
+                button: MouseButton::One,
+                down,
+                inside,
+                ..
+            } => {
+                self.dragging = down && (inside || self.dragging);
+                if self.dragging {
+                    return EventResponse::Sink;
+                }
+            }
+            _ => {}
+        };
+        EventResponse::Bubble
+    }
+}
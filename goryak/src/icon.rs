@@ -2,16 +2,19 @@ use phf::phf_map;
 use std::borrow::Cow;
 use yakui_core::geometry::{Color, Constraints, Vec2};
 use yakui_widgets::font::FontName;
-use yakui_widgets::widgets::{Button, Text};
+use yakui_widgets::widgets::Text;
 use yakui_widgets::{center, constrained};
 
+use crate::SoundButton;
+
 pub fn icon_map(name: &str) -> (&'static str, FontName) {
     let mapped = ICON_NAME_MAPPING.get(name).copied().unwrap_or("?");
     (mapped, FontName::new("icons"))
 }
 
 #[must_use = "call show() to show the widget"]
-pub fn icon_button(mut b: Button) -> Button {
+pub fn icon_button(b: impl Into<SoundButton>) -> SoundButton {
+    let mut b = b.into();
     let (mapped, name) = icon_map(&b.text);
 
     b.text = Cow::Borrowed(mapped);
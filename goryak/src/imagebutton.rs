@@ -1,6 +1,3 @@
-use std::borrow::Cow;
-use std::time::Instant;
-
 use yakui_core::event::{EventInterest, EventResponse, WidgetEvent};
 use yakui_core::geometry::{Color, Constraints, Rect, Vec2};
 use yakui_core::input::MouseButton;
@@ -8,7 +5,7 @@ use yakui_core::paint::PaintRect;
 use yakui_core::widget::{EventContext, LayoutContext, PaintContext, Widget};
 use yakui_core::{Response, TextureId};
 
-use crate::{on_primary, padxy, primary, round_rect, textc};
+use crate::{primary, tooltip, ui_sound, Tooltip, UiSound};
 
 /**
 A button based on an image
@@ -22,7 +19,7 @@ pub struct ImageButton {
     pub color: Color,
     pub hover_color: Color,
     pub active_color: Color,
-    pub tooltip: Cow<'static, str>,
+    pub tooltip: Tooltip,
 }
 
 impl ImageButton {
@@ -33,7 +30,7 @@ impl ImageButton {
             color: Color::WHITE,
             hover_color: Color::WHITE,
             active_color: Color::WHITE,
-            tooltip: Cow::Borrowed(""),
+            tooltip: Tooltip::default(),
         }
     }
 
@@ -50,20 +47,29 @@ impl ImageButton {
             color,
             hover_color,
             active_color,
-            tooltip: Cow::Borrowed(""),
+            tooltip: Tooltip::default(),
         }
     }
 
     pub fn show(self) -> Response<ImageButtonResponse> {
-        yakui_widgets::util::widget::<ImageButtonWidget>(self)
+        if self.tooltip.is_empty() {
+            return yakui_widgets::util::widget::<ImageButtonWidget>(self);
+        }
+        let content = self.tooltip.clone();
+        let mut resp = None;
+        tooltip(content, || {
+            resp = Some(yakui_widgets::util::widget::<ImageButtonWidget>(self));
+        });
+        resp.unwrap()
     }
 }
 
+/// Without a texture the button is a plain square of its color
 pub fn primary_image_button(
-    texture: TextureId,
+    texture: impl Into<Option<TextureId>>,
     size: Vec2,
     enabled: bool,
-    tooltip: impl Into<Cow<'static, str>>,
+    tooltip: impl Into<Tooltip>,
 ) -> Response<ImageButtonResponse> {
     let (default_col, hover_col) = if enabled {
         let c = primary().lerp(&Color::WHITE, 0.3);
@@ -75,15 +81,15 @@ pub fn primary_image_button(
 }
 
 pub fn image_button(
-    texture: TextureId,
+    texture: impl Into<Option<TextureId>>,
     size: Vec2,
     color: Color,
     hover_color: Color,
     active_color: Color,
-    tooltip: impl Into<Cow<'static, str>>,
+    tooltip: impl Into<Tooltip>,
 ) -> Response<ImageButtonResponse> {
     ImageButton {
-        texture: Some(texture),
+        texture: texture.into(),
         size,
         color,
         hover_color,
@@ -97,8 +103,6 @@ pub fn image_button(
 pub struct ImageButtonWidget {
     props: ImageButton,
     resp: ImageButtonResponse,
-    stopped_moving: Option<Instant>,
-    show_tooltip: bool,
 }
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -117,8 +121,6 @@ impl Widget for ImageButtonWidget {
         Self {
             props: ImageButton::empty(),
             resp: ImageButtonResponse::default(),
-            stopped_moving: None,
-            show_tooltip: false,
         }
     }
 
@@ -127,22 +129,6 @@ impl Widget for ImageButtonWidget {
         let resp = self.resp;
         self.resp.mouse_entered = false;
         self.resp.clicked = false;
-
-        if !self.props.tooltip.is_empty() {
-            if let Some(i) = self.stopped_moving {
-                if i.elapsed().as_millis() > 500 {
-                    self.show_tooltip = true;
-                }
-                if self.show_tooltip {
-                    round_rect(5.0, primary(), || {
-                        padxy(5.0, 4.0, || {
-                            textc(on_primary(), self.props.tooltip.clone());
-                        });
-                    });
-                }
-            }
-        }
-
         resp
     }
 
@@ -174,36 +160,20 @@ impl Widget for ImageButtonWidget {
         EventInterest::MOUSE_ALL
     }
 
-    fn layout(&self, mut ctx: LayoutContext<'_>, input: Constraints) -> Vec2 {
-        if let Some(tooltip) = ctx.dom.get_current().children.first() {
-            let size = ctx.calculate_layout(*tooltip, Constraints::none());
-            ctx.layout.set_pos(
-                *tooltip,
-                Vec2::new((self.props.size.x - size.x) / 2.0, -size.y - 10.0),
-            );
-        }
-
+    fn layout(&self, _: LayoutContext<'_>, input: Constraints) -> Vec2 {
         input.constrain_min(self.props.size)
     }
 
     fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
         match *event {
-            WidgetEvent::MouseMoved(Some(_)) => {
-                if self.resp.hovering {
-                    self.stopped_moving = Some(Instant::now());
-                }
-                self.show_tooltip = false;
-                EventResponse::Bubble
-            }
             WidgetEvent::MouseEnter => {
+                ui_sound(UiSound::Hover);
                 self.resp.mouse_entered = true;
                 self.resp.hovering = true;
                 EventResponse::Bubble
             }
             WidgetEvent::MouseLeave => {
                 self.resp.hovering = false;
-                self.show_tooltip = false;
-                self.stopped_moving = None;
                 EventResponse::Bubble
             }
             WidgetEvent::MouseButtonChanged {
@@ -213,6 +183,7 @@ impl Widget for ImageButtonWidget {
                 ..
             } => {
                 if down && inside {
+                    ui_sound(UiSound::Click);
                     self.resp.clicked = true;
                     self.resp.mouse_down = true;
                 } else {
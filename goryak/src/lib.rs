@@ -13,6 +13,7 @@ mod roundrect;
 mod scroll;
 mod selectable_label;
 mod sized_canvas;
+mod sound;
 mod text;
 mod theme;
 mod tooltip;
@@ -34,8 +35,10 @@ pub use roundrect::*;
 pub use scroll::*;
 pub use selectable_label::*;
 pub use sized_canvas::*;
+pub use sound::*;
 pub use text::*;
 pub use theme::*;
+pub use tooltip::*;
 pub use util::*;
 pub use window::*;
 
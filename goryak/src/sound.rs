@@ -0,0 +1,90 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use yakui_core::event::{EventInterest, EventResponse, WidgetEvent};
+use yakui_core::widget::{EventContext, Widget};
+use yakui_core::Response;
+use yakui_widgets::util::widget_children;
+use yakui_widgets::widgets::{Button, ButtonResponse};
+
+/// Sounds asked by the widgets, the game plays them since goryak has no audio
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UiSound {
+    Click,
+    Hover,
+}
+
+static UI_SOUNDS: Mutex<Vec<UiSound>> = Mutex::new(Vec::new());
+
+pub fn ui_sound(sound: UiSound) {
+    let mut sounds = UI_SOUNDS.lock().unwrap();
+    if !sounds.contains(&sound) {
+        sounds.push(sound);
+    }
+}
+
+/// The sounds asked since the last call, each at most once
+pub fn take_ui_sounds() -> Vec<UiSound> {
+    std::mem::take(&mut *UI_SOUNDS.lock().unwrap())
+}
+
+/// A button that clicks when pressed and ticks when the mouse enters it
+#[must_use = "call show() to show the widget"]
+pub struct SoundButton(pub Button);
+
+impl SoundButton {
+    pub fn show(self) -> Response<ButtonResponse> {
+        let mut resp = None;
+        widget_children::<HoverSoundWidget, _>(|| resp = Some(self.0.show()), ());
+        let resp = resp.unwrap();
+        if resp.clicked {
+            ui_sound(UiSound::Click);
+        }
+        resp
+    }
+}
+
+impl From<Button> for SoundButton {
+    fn from(b: Button) -> Self {
+        Self(b)
+    }
+}
+
+impl Deref for SoundButton {
+    type Target = Button;
+
+    fn deref(&self) -> &Button {
+        &self.0
+    }
+}
+
+impl DerefMut for SoundButton {
+    fn deref_mut(&mut self) -> &mut Button {
+        &mut self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct HoverSoundWidget;
+
+impl Widget for HoverSoundWidget {
+    type Props<'a> = ();
+    type Response = ();
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn update(&mut self, _: Self::Props<'_>) -> Self::Response {}
+
+    fn event_interest(&self) -> EventInterest {
+        EventInterest::MOUSE_INSIDE
+    }
+
+    fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
+        if let WidgetEvent::MouseEnter = *event {
+            ui_sound(UiSound::Hover);
+        }
+        EventResponse::Bubble
+    }
+}
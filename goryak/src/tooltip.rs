@@ -1,76 +1,219 @@
-/*
-use lazy_static::lazy_static;
-use std::sync::Mutex;
-use yakui_core::geometry::{Constraints, Vec2};
-use yakui_core::widget::{LayoutContext, Widget};
-use yakui_core::Response;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+use yakui_core::event::{EventInterest, EventResponse, WidgetEvent};
+use yakui_core::geometry::{Constraints, Dim2, Vec2};
+use yakui_core::widget::{EventContext, LayoutContext, Widget};
+use yakui_core::{Alignment, Flow, Response};
 use yakui_widgets::util::widget_children;
 
-struct TooltipState(Option<TooltipStateInner>);
+use crate::{mincolumn, minrow, on_primary, padxy, primary, round_rect, textc};
 
-struct TooltipStateInner {
-    tooltip: &'static str,
-    position: Vec2,
-}
+/// How long a widget must be hovered before its tooltip shows up
+pub const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
+
+/// A tooltip shown less than this long ago makes the next one show up without delay,
+/// so that moving between adjacent buttons doesn't hide the tooltip
+const TOOLTIP_WARM: Duration = Duration::from_millis(300);
 
-lazy_static! {
-    static ref TOOLTIP_STATE: Mutex<TooltipState> = Mutex::new(TooltipState(None));
+/// Distance between the cursor and the tooltip
+const CURSOR_OFFSET: Vec2 = Vec2::new(16.0, 20.0);
+
+/// Content of a tooltip: multi-line text and an optional table of stats below it
+#[derive(Debug, Clone, Default)]
+pub struct Tooltip {
+    pub text: Cow<'static, str>,
+    /// Label and value pairs, e.g. the speed limit and price of a road type
+    pub rows: Vec<(Cow<'static, str>, String)>,
 }
 
-/// Call this at the end of the gui where the "reflow" refers to the root widget
-/// so that we can put the tooltip at the right place but the tooltip is necessarily
-/// above everything
-pub fn render_tooltip() {
-    let mut tooltip_state = TOOLTIP_STATE.lock().unwrap();
-    if let Some(tooltip_state_inner) = tooltip_state.0 {}
+impl Tooltip {
+    pub fn new(text: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            text: text.into(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(mut self, label: impl Into<Cow<'static, str>>, value: impl ToString) -> Self {
+        self.rows.push((label.into(), value.to_string()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.rows.is_empty()
+    }
 }
 
-/// Positions the children of this widget as close to the mouse as possible while staying
-/// within the bounds of the window.
-#[derive(Debug)]
-struct PositionTooltip {
-    position: Vec2,
+impl From<&'static str> for Tooltip {
+    fn from(text: &'static str) -> Self {
+        Self::new(text)
+    }
 }
 
-impl PositionTooltip {
-    pub fn new(position: Vec2) -> Self {
-        Self { position }
+impl From<String> for Tooltip {
+    fn from(text: String) -> Self {
+        Self::new(text)
     }
+}
 
-    pub fn show<F: FnOnce()>(self, children: F) -> Response<()> {
-        widget_children::<PositionTooltipWidget, F>(children, self)
+impl From<Cow<'static, str>> for Tooltip {
+    fn from(text: Cow<'static, str>) -> Self {
+        Self::new(text)
     }
 }
 
+thread_local! {
+    /// Tooltip to show this frame and the position of the cursor
+    static REQUEST: RefCell<Option<(Tooltip, Vec2)>> = const { RefCell::new(None) };
+    static LAST_SHOWN: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Shows the tooltip near the cursor once the children are hovered for [TOOLTIP_DELAY].
+/// Nothing is shown while a mouse button is held, e.g. when dragging the camera.
+/// The tooltip itself is drawn by [render_tooltip].
+pub fn tooltip(content: impl Into<Tooltip>, children: impl FnOnce()) -> Response<()> {
+    widget_children::<TooltipWidget, _>(children, content.into())
+}
+
+/// Call this at the end of the gui, at the root, so that the tooltip is above everything.
+/// The tooltip stays within the screen and doesn't capture the mouse.
+pub fn render_tooltip() {
+    let Some((content, mouse)) = REQUEST.with(|r| r.borrow_mut().take()) else {
+        return;
+    };
+    LAST_SHOWN.with(|l| l.set(Some(Instant::now())));
+
+    widget_children::<TooltipLayerWidget, _>(
+        || {
+            round_rect(5.0, primary(), || {
+                padxy(6.0, 4.0, || {
+                    mincolumn(2.0, || {
+                        for line in content.text.lines() {
+                            textc(on_primary(), line.to_string());
+                        }
+                        if content.rows.is_empty() {
+                            return;
+                        }
+                        minrow(10.0, || {
+                            mincolumn(2.0, || {
+                                for (label, _) in &content.rows {
+                                    textc(on_primary().with_alpha(0.7), label.clone());
+                                }
+                            });
+                            mincolumn(2.0, || {
+                                for (_, value) in &content.rows {
+                                    textc(on_primary(), value.clone());
+                                }
+                            });
+                        });
+                    });
+                });
+            });
+        },
+        mouse,
+    );
+}
+
 #[derive(Debug)]
-struct PositionTooltipWidget {
-    props: PositionTooltip,
+pub struct TooltipWidget {
+    content: Tooltip,
+    hovered_since: Option<Instant>,
+    mouse: Vec2,
+    /// A mouse button is held, anywhere on screen
+    pressed: bool,
 }
 
-impl Widget for PositionTooltipWidget {
-    type Props<'a> = PositionTooltip;
+impl Widget for TooltipWidget {
+    type Props<'a> = Tooltip;
     type Response = ();
 
     fn new() -> Self {
         Self {
-            props: PositionTooltip::new(Vec2::ZERO),
+            content: Tooltip::default(),
+            hovered_since: None,
+            mouse: Vec2::ZERO,
+            pressed: false,
         }
     }
 
     fn update(&mut self, props: Self::Props<'_>) -> Self::Response {
-        self.props = props;
+        self.content = props;
+        if self.pressed || self.content.is_empty() {
+            return;
+        }
+        let Some(since) = self.hovered_since else {
+            return;
+        };
+        let warm = LAST_SHOWN.with(|l| l.get().is_some_and(|t| t.elapsed() < TOOLTIP_WARM));
+        if warm || since.elapsed() >= TOOLTIP_DELAY {
+            REQUEST.with(|r| *r.borrow_mut() = Some((self.content.clone(), self.mouse)));
+        }
+    }
+
+    fn event_interest(&self) -> EventInterest {
+        EventInterest::MOUSE_ALL
+    }
+
+    fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
+        match *event {
+            WidgetEvent::MouseEnter => self.hovered_since = Some(Instant::now()),
+            WidgetEvent::MouseLeave => self.hovered_since = None,
+            WidgetEvent::MouseMoved(Some(pos)) => self.mouse = pos,
+            WidgetEvent::MouseButtonChanged { down, inside, .. } => {
+                self.pressed = down;
+                // start over after a click, the tooltip would hide what the click did
+                self.hovered_since = (!down && inside).then(Instant::now);
+            }
+            _ => {}
+        }
+        EventResponse::Bubble
     }
+}
+
+/// Places its child next to the cursor, on top of everything else
+#[derive(Debug)]
+pub struct TooltipLayerWidget {
+    mouse: Vec2,
+}
 
-    fn layout(&self, mut ctx: LayoutContext<'_>, constraints: Constraints) -> Vec2 {
+impl Widget for TooltipLayerWidget {
+    type Props<'a> = Vec2;
+    type Response = ();
+
+    fn new() -> Self {
+        Self { mouse: Vec2::ZERO }
+    }
+
+    fn update(&mut self, mouse: Self::Props<'_>) -> Self::Response {
+        self.mouse = mouse;
+    }
+
+    fn flow(&self) -> Flow {
+        Flow::Relative {
+            anchor: Alignment::TOP_LEFT,
+            offset: Dim2::ZERO,
+        }
+    }
+
+    fn layout(&self, mut ctx: LayoutContext<'_>, _: Constraints) -> Vec2 {
+        ctx.layout.new_layer(ctx.dom);
+        let vp = ctx.layout.viewport().size();
         let node = ctx.dom.get_current();
-        let mut size = Vec2::ZERO;
         for &child in &node.children {
-            let child_size = ctx.calculate_layout(child, constraints);
-            size = size.max(child_size);
-        }
-        let v = ctx.layout.viewport();
+            let size = ctx.calculate_layout(child, Constraints::loose(vp));
 
+            // flip to the other side of the cursor when there is no room
+            let mut pos = self.mouse + CURSOR_OFFSET;
+            if pos.x + size.x > vp.x {
+                pos.x = self.mouse.x - size.x - 4.0;
+            }
+            if pos.y + size.y > vp.y {
+                pos.y = self.mouse.y - size.y - 4.0;
+            }
+            ctx.layout.set_pos(child, pos.max(Vec2::ZERO));
+        }
         Vec2::ZERO
     }
 }
- */
@@ -9,7 +9,7 @@ use yakui_core::{context, CrossAxisAlignment, MainAxisSize, Response, WidgetId};
 use yakui_widgets::util::widget;
 use yakui_widgets::widgets::{Button, List, ListResponse, Pad, PadResponse, Text};
 
-use crate::{on_primary, on_secondary, primary, secondary, DEFAULT_FONT_SIZE};
+use crate::{on_primary, on_secondary, primary, secondary, SoundButton, DEFAULT_FONT_SIZE};
 
 pub fn checkbox_value(v: &mut bool, color: Color, label: &'static str) {
     minrow(5.0, || {
@@ -114,8 +114,8 @@ where
 }
 
 #[must_use = "call show() to show the widget"]
-pub fn button_primary(text: impl Into<String>) -> Button {
-    let mut b = Button::styled(text.into());
+pub fn button_primary(text: impl Into<String>) -> SoundButton {
+    let mut b = SoundButton(Button::styled(text.into()));
     b.style.fill = primary();
     b.style.text.color = on_primary();
     b.hover_style.fill = primary().adjust(1.2);
@@ -126,8 +126,8 @@ pub fn button_primary(text: impl Into<String>) -> Button {
 }
 
 #[must_use = "call show() to show the widget"]
-pub fn button_secondary(text: impl Into<String>) -> Button {
-    let mut b = Button::styled(text.into());
+pub fn button_secondary(text: impl Into<String>) -> SoundButton {
+    let mut b = SoundButton(Button::styled(text.into()));
     b.style.fill = secondary();
     b.style.text.color = on_secondary();
     b.hover_style.fill = secondary().adjust(1.2);
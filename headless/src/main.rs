@@ -1,8 +1,10 @@
 use common::logger::MyLog;
 use common::unwrap_or;
 use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
+use simulation::scenario::Scenario;
 use simulation::world_command::WorldCommands;
 use simulation::Simulation;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
@@ -27,12 +29,65 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Runs the scenario as fast as possible then exits, instead of starting a server
+    #[structopt(long, parse(from_os_str))]
+    scenario: Option<PathBuf>,
+
+    /// In-game days to run the scenario for, overrides the days of the scenario
+    #[structopt(long)]
+    days: Option<u32>,
+
+    /// Where to write the report of the scenario, as csv or json depending on the extension
+    #[structopt(long, parse(from_os_str), default_value = "report.csv")]
+    report: PathBuf,
+}
+
+fn run_scenario(opt: &Opt, path: &Path) {
+    let scenario = match Scenario::load(path) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("could not load scenario {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut sim = match scenario.simulation() {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("could not load the save of the scenario: {}", e);
+            return;
+        }
+    };
+
+    let days = opt.days.unwrap_or(scenario.days);
+    log::info!("running scenario {} for {} days", path.display(), days);
+
+    let start = Instant::now();
+    let report = scenario.run(&mut sim, days);
+    log::info!("scenario done in {:.1}s", start.elapsed().as_secs_f32());
+
+    if let Err(e) = report.save(&opt.report) {
+        log::error!("could not write report {}: {}", opt.report.display(), e);
+        return;
+    }
+    log::info!("report written to {}", opt.report.display());
 }
 
 fn main() {
     let opt: Opt = Opt::from_args();
     MyLog::init();
-    simulation::init::init();
+    if let Err(e) = simulation::init::try_init() {
+        log::error!("could not load the prototypes:");
+        for problem in e.problems() {
+            log::error!("{}", problem);
+        }
+        std::process::exit(1);
+    }
+
+    if let Some(ref path) = opt.scenario {
+        run_scenario(&opt, path);
+        return;
+    }
 
     log::info!("starting server with version: {}", VERSION);
 
@@ -1,68 +1,174 @@
+use crate::rendering::overlays::{overlay_cell, OVERLAY_CELL};
 use crate::uiworld::UiWorld;
 use engine::{AudioContext, AudioKind, Gain, GainControl};
-use geom::{lerp, Camera, Vec2, AABB};
+use geom::{lerp, vec2, Camera, Vec2, AABB};
 use oddio::{Cycle, Mixed};
+use simulation::map::ProjectFilter;
+use simulation::transportation::{TransportGrid, TransportationGroup};
 use simulation::Simulation;
+use std::time::Instant;
+
+/// Cells sampled on each side of the camera, on the grid of the overlays
+const SAMPLE_RADIUS: i32 = 4;
+/// Real time seconds between two samplings of the surroundings
+const SAMPLE_PERIOD: f32 = 0.5;
+/// Seconds for a loop to cover most of the way to its new volume
+const FADE_TIME: f32 = 1.5;
+/// Trees in a cell for it to count as forest
+const FOREST_TREES: usize = 3;
+
+/// Share of the cells around the camera covered by each kind of ground
+#[derive(Default, Copy, Clone)]
+struct Surroundings {
+    water: f32,
+    forest: f32,
+    city: f32,
+    /// Vehicles on the roads around the camera
+    cars: usize,
+}
+
+impl Surroundings {
+    fn sample(sim: &Simulation, center: Vec2) -> Self {
+        profiling::scope!("ambient::sample");
+        let map = sim.map();
+        let (cx, cy) = overlay_cell(center);
+
+        let mut s = Self::default();
+        let mut n = 0;
+        for y in cy - SAMPLE_RADIUS..=cy + SAMPLE_RADIUS {
+            for x in cx - SAMPLE_RADIUS..=cx + SAMPLE_RADIUS {
+                let ll = vec2(x as f32, y as f32) * OVERLAY_CELL;
+                let ur = ll + Vec2::splat(OVERLAY_CELL);
+                let middle = ll + Vec2::splat(OVERLAY_CELL * 0.5);
+                n += 1;
+
+                if map.environment.true_height(middle).is_some_and(|h| h < 0.0) {
+                    s.water += 1.0;
+                    continue;
+                }
+                let trees = map
+                    .environment
+                    .trees
+                    .query(ll, ur)
+                    .take(FOREST_TREES)
+                    .count();
+                s.forest += trees as f32 / FOREST_TREES as f32;
+                if map
+                    .spatial_map()
+                    .query(AABB::new_ll_ur(ll, ur), ProjectFilter::BUILDING)
+                    .next()
+                    .is_some()
+                {
+                    s.city += 1.0;
+                }
+            }
+        }
+        s.water /= n as f32;
+        s.forest /= n as f32;
+        s.city /= n as f32;
+
+        let radius = OVERLAY_CELL * (SAMPLE_RADIUS as f32 + 0.5);
+        let transport_grid = sim.read::<TransportGrid>();
+        s.cars = transport_grid
+            .query_around(center, radius)
+            .filter_map(|(h, _)| transport_grid.get(h))
+            .filter(|(_, obj)| matches!(obj.group, TransportationGroup::Vehicles))
+            .count();
+        s
+    }
+}
+
+/// A looping sound faded in and out
+struct AmbientLoop {
+    name: &'static str,
+    control: Option<(GainControl, Mixed)>,
+    volume: f32,
+}
+
+impl AmbientLoop {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            control: None,
+            volume: 0.0,
+        }
+    }
+
+    /// Moves the volume toward the target, the loop starts once its sound is decoded
+    fn fade_to(&mut self, ctx: &mut AudioContext, target: f32, t: f32) {
+        self.volume += (target - self.volume) * t;
+        if self.control.is_none() {
+            self.control = ctx.play_with_control(
+                self.name,
+                |s| Gain::new(Cycle::new(s), 0.0),
+                AudioKind::Effect,
+            );
+        }
+        if let Some((ref mut gain, _)) = self.control {
+            gain.set_amplitude_ratio(self.volume);
+        }
+    }
+}
 
 /// Ambient sounds
 /// These are sounds that are played in the background
-/// They are not tied to any entity
+/// They are not tied to any entity, they crossfade with the zoom and what is under the camera
 pub struct Ambient {
-    wind: Option<(GainControl, Mixed)>,
-    forest: Option<(GainControl, Mixed)>,
+    wind: AmbientLoop,
+    forest: AmbientLoop,
+    waves: AmbientLoop,
+    traffic: AmbientLoop,
+    surroundings: Surroundings,
+    last_sample: Option<((i32, i32), Instant)>,
+    last_update: Instant,
 }
 
 impl Ambient {
-    pub fn new(ctx: &mut AudioContext) -> Self {
-        let wind = ctx.play_with_control(
-            "calm_wind",
-            |s| {
-                let (g_control, signal) = Gain::new(Cycle::new(s), 0.0);
-                (g_control, signal)
-            },
-            AudioKind::Effect,
-        );
-        let forest = ctx.play_with_control(
-            "forest",
-            |s| {
-                let (g_control, signal) = Gain::new(Cycle::new(s), 0.0);
-                (g_control, signal)
-            },
-            AudioKind::Effect,
-        );
-
-        Self { wind, forest }
+    pub fn new() -> Self {
+        Self {
+            wind: AmbientLoop::new("calm_wind"),
+            forest: AmbientLoop::new("forest"),
+            waves: AmbientLoop::new("waves"),
+            traffic: AmbientLoop::new("car_loop"),
+            surroundings: Surroundings::default(),
+            last_sample: None,
+            last_update: Instant::now(),
+        }
     }
 
-    pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld) {
+    pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld, ctx: &mut AudioContext) {
         let eye = uiworld.read::<Camera>().eye();
-        let map = sim.map();
-
         let h = eye.z;
 
-        // Wind
-        let volume = lerp(0.1, 0.8, (h - 100.0) / 4000.0);
-        if let Some(ref mut wind) = self.wind {
-            wind.0.set_amplitude_ratio(volume);
+        let cell = overlay_cell(eye.xy());
+        let stale = self.last_sample.map_or(true, |(c, time)| {
+            c != cell || time.elapsed().as_secs_f32() > SAMPLE_PERIOD
+        });
+        if stale {
+            self.surroundings = Surroundings::sample(sim, eye.xy());
+            self.last_sample = Some((cell, Instant::now()));
         }
+        let s = self.surroundings;
 
-        // Forest
-        let bbox = AABB::centered(eye.xy(), Vec2::splat(200.0));
-        let mut volume = lerp(1.0, 0.0, h / 300.0);
+        let dt = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+        let t = 1.0 - (-dt * 3.0 / FADE_TIME).exp();
 
-        if volume > 0.0 {
-            let mut matches = 0;
+        // Wind
+        let wind = lerp(0.1, 0.8, (h - 100.0) / 4000.0);
+        self.wind.fade_to(ctx, wind, t);
 
-            for _ in map.environment.trees.query(bbox.ll, bbox.ur) {
-                matches += 1;
-                if matches > 50 {
-                    break;
-                }
-            }
-            volume *= matches as f32 / 50.0;
-        }
-        if let Some(ref mut forest) = self.forest {
-            forest.0.set_amplitude_ratio(volume);
-        }
+        // Birds in the forests
+        let forest = lerp(1.0, 0.0, h / 300.0) * s.forest;
+        self.forest.fade_to(ctx, forest, t);
+
+        // Waves along the coasts
+        let waves = lerp(0.6, 0.0, h / 1000.0) * s.water;
+        self.waves.fade_to(ctx, waves, t);
+
+        // Rumble of the traffic downtown
+        let busy = s.city.max((s.cars as f32 / 100.0).min(1.0));
+        let traffic = lerp(0.03, 0.0, h / 1000.0) * busy;
+        self.traffic.fade_to(ctx, traffic, t);
     }
 }
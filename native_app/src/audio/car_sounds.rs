@@ -1,7 +1,7 @@
 use crate::uiworld::UiWorld;
 use engine::{AudioContext, AudioKind, Gain, GainControl};
 use flat_spatial::grid::GridHandle;
-use geom::{Camera, Vec2, AABB};
+use geom::Camera;
 use oddio::{Cycle, Mixed, Seek, Speed, SpeedControl};
 use simulation::transportation::TransportGrid;
 use simulation::Simulation;
@@ -15,29 +15,21 @@ pub struct CarSound {
 
 /// CarSounds are sounds that are played when cars are near the player
 /// They are tied to a car entity
+/// The rumble of the traffic further away is part of the [`Ambient`](super::ambient::Ambient)
 pub struct CarSounds {
     sounds: SecondaryMap<GridHandle, CarSound>,
-    generic_car_sound: Option<GainControl>,
 }
 
 impl CarSounds {
-    pub fn new(ctx: &mut AudioContext) -> Self {
+    pub fn new() -> Self {
         Self {
             sounds: SecondaryMap::new(),
-            generic_car_sound: ctx
-                .play_with_control(
-                    "car_loop",
-                    |x| Gain::new(Cycle::new(x), 0.0),
-                    AudioKind::Effect,
-                )
-                .map(|x| x.0),
         }
     }
 
     pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld, ctx: &mut AudioContext) {
         let transport_grid = sim.read::<TransportGrid>();
         let campos = uiworld.read::<Camera>().eye();
-        let cambox = AABB::centered(campos.xy(), Vec2::splat(200.0));
 
         const HEAR_RADIUS: f32 = 200.0;
 
@@ -140,26 +132,5 @@ impl CarSounds {
                 speed.set_speed(boost)
             }
         }
-
-        if campos.z < 1000.0 {
-            let cars_on_screen = transport_grid
-                .query_aabb(cambox.ll, cambox.ur)
-                .filter_map(|(h, _)| transport_grid.get(h))
-                .filter(|(_, obj)| {
-                    matches!(
-                        obj.group,
-                        simulation::transportation::TransportationGroup::Vehicles
-                    )
-                })
-                .count();
-            if let Some(ref mut s) = self.generic_car_sound {
-                s.set_amplitude_ratio(
-                    ((cars_on_screen as f32).min(100.0) / 100.0 * (1.0 - campos.z / 1000.0))
-                        .min(0.03),
-                );
-            }
-        } else if let Some(ref mut s) = self.generic_car_sound {
-            s.set_amplitude_ratio(0.0);
-        }
     }
 }
@@ -1,20 +1,34 @@
 use crate::audio::ambient::Ambient;
 use crate::audio::car_sounds::CarSounds;
 use crate::audio::music::Music;
+use crate::audio::train_horns::TrainHorns;
+use crate::rendering::immediate::ImmediateSound;
 use crate::uiworld::UiWorld;
-use engine::AudioContext;
+use engine::{AudioContext, AudioKind};
+use geom::{Camera, Vec3};
+use goryak::UiSound;
 use simulation::Simulation;
 
 mod ambient;
 mod car_sounds;
 mod music;
+mod synth;
+mod train_horns;
+
+pub use music::MusicState;
 
 pub static SOUNDS_LIST: include_dir::Dir = include_dir::include_dir!("assets/sounds");
 
+/// Distance to the camera under which the positioned sounds are played at full volume
+const FULL_VOLUME_DIST: f32 = 100.0;
+/// Positioned sounds further than this aren't played
+const HEAR_DIST: f32 = 3000.0;
+
 pub struct GameAudio {
     music: Music,
     ambiant: Ambient,
     carsounds: CarSounds,
+    train_horns: TrainHorns,
 }
 
 impl GameAudio {
@@ -28,17 +42,59 @@ impl GameAudio {
                 .flat_map(|x| x.to_str())
                 .map(|x| x.trim_end_matches(".ogg")),
         );
+        synth::register(ctx);
 
         Self {
             music: Music::new(),
-            ambiant: Ambient::new(ctx),
-            carsounds: CarSounds::new(ctx),
+            ambiant: Ambient::new(),
+            carsounds: CarSounds::new(),
+            train_horns: TrainHorns::default(),
         }
     }
 
     pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld, ctx: &mut AudioContext) {
-        self.music.update(ctx);
-        self.ambiant.update(sim, uiworld);
+        let camera = *uiworld.read::<Camera>();
+
+        for sound in goryak::take_ui_sounds() {
+            let name = match sound {
+                UiSound::Click => "ui_click",
+                UiSound::Hover => "ui_hover",
+            };
+            ctx.play(name, AudioKind::Ui);
+        }
+        for (sound, pos, kind) in uiworld.write::<ImmediateSound>().positioned.drain(..) {
+            play_positioned(ctx, &camera, sound, pos, kind);
+        }
+
+        if !ctx.is_enabled() {
+            return;
+        }
+        self.music.update(sim, uiworld, ctx);
+        self.ambiant.update(sim, uiworld, ctx);
         self.carsounds.update(sim, uiworld, ctx);
+        self.train_horns.update(sim, &camera, ctx);
+    }
+}
+
+/// Plays a sound once, quieter the further it is from the camera and panned to its side
+pub fn play_positioned(
+    ctx: &mut AudioContext,
+    camera: &Camera,
+    name: &'static str,
+    pos: Vec3,
+    kind: AudioKind,
+) {
+    let eye = camera.eye();
+    let dist = eye.distance(pos);
+    if dist > HEAR_DIST {
+        return;
     }
+    let gain = FULL_VOLUME_DIST / dist.max(FULL_VOLUME_DIST);
+
+    // the camera looks toward its target, sounds right below it come from both sides
+    let forward = -camera.yaw.vec2();
+    let right = forward.perpendicular();
+    let side = (pos - eye).xy().dot(right) / dist.max(1.0);
+
+    ctx.play_panned(name, kind, gain, side * 0.8);
 }
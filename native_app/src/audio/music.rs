@@ -1,48 +1,251 @@
-use engine::{AudioContext, AudioKind, FadeIn};
-use oddio::{FramesSignal, Mixed};
-use std::time::{Duration, Instant};
+use crate::uiworld::UiWorld;
+use common::saveload::{Encoder, JSON};
+use common::FastMap;
+use engine::{AudioContext, AudioKind, Gain, GainControl, StreamedAudio, TrackControl};
+use oddio::Mixed;
+use prototypes::GameTime;
+use serde::Deserialize;
+use simulation::Simulation;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
 
-const TRACKS: &[&str] = &["music2", "music1"];
+/// Folders scanned for tracks at startup, the second one is for the player's own music
+const MUSIC_DIRS: &[&str] = &["assets/music", "world/music"];
+/// File in a music folder tagging its tracks by mood, from the file name without extension
+const MOODS_FILE: &str = "moods.json";
+/// Seconds over which a track fades into the next one
+const CROSSFADE: f32 = 3.0;
+/// A track ending sooner than this after starting couldn't be decoded and isn't played again
+const BROKEN_TRACK: f32 = 1.0;
+/// Population from which the busy tracks are preferred
+const BUSY_POPULATION: usize = 2000;
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Mood {
+    Calm,
+    Busy,
+}
+
+struct Track {
+    path: PathBuf,
+    name: String,
+    mood: Option<Mood>,
+}
+
+struct Playing {
+    track: usize,
+    control: TrackControl,
+    gain: GainControl,
+    mixed: Mixed,
+    volume: f32,
+    started: Instant,
+}
+
+/// What the player shows of the music and what the interface asks of it
+#[derive(Default)]
+pub struct MusicState {
+    /// Name of the track being played and when it started, in [`UiWorld::time_always`] seconds
+    pub now_playing: Option<(String, f32)>,
+    pub paused: bool,
+    /// Set by the interface, the player fades to the next track
+    pub skip: bool,
+}
 
 /// Music handles background music
+/// The tracks found in the music folders are played in a shuffled playlist, preferring the calm
+/// ones at night and the busy ones once the city is large
 pub struct Music {
-    track_id: usize,
-    time_between_tracks: Duration,
-    last_played: Instant,
-    cur_track: Option<Mixed>,
+    tracks: Vec<Track>,
+    /// Tracks not played yet in this round, the next one first
+    queue: Vec<usize>,
+    broken: Vec<usize>,
+    /// The last track started
+    last: Option<usize>,
+    current: Option<Playing>,
+    /// The previous tracks fading out
+    fading: Vec<Playing>,
+    rng: common::rand::RandGen,
+    last_update: Instant,
 }
 
 impl Music {
     pub fn new() -> Self {
+        let tracks = MUSIC_DIRS
+            .iter()
+            .flat_map(|dir| scan(Path::new(dir)))
+            .collect::<Vec<_>>();
+        log::info!("found {} music tracks", tracks.len());
+
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
         Self {
-            track_id: 0,
-            time_between_tracks: Duration::new(5, 0),
-            last_played: Instant::now(),
-            cur_track: None,
+            tracks,
+            queue: vec![],
+            broken: vec![],
+            last: None,
+            current: None,
+            fading: vec![],
+            rng: common::rand::gen(seed),
+            last_update: Instant::now(),
         }
     }
 
-    pub fn update(&mut self, ctx: &mut AudioContext) {
-        if !ctx.is_all_ready() {
+    pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld, ctx: &mut AudioContext) {
+        let dt = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+        let mut state = uiworld.write::<MusicState>();
+
+        for p in self.current.iter().chain(&self.fading) {
+            p.control.set_paused(state.paused);
+        }
+        if state.paused {
             return;
         }
-        if let Some(ref mut x) = self.cur_track {
-            if !x.is_stopped() {
-                return;
+
+        let step = dt / CROSSFADE;
+        self.fading.retain_mut(|p| {
+            p.volume -= step;
+            if p.volume <= 0.0 || p.mixed.is_stopped() {
+                p.mixed.stop();
+                return false;
+            }
+            p.gain.set_amplitude_ratio(p.volume);
+            true
+        });
+
+        if let Some(ref mut cur) = self.current {
+            if cur.mixed.is_stopped() && cur.started.elapsed().as_secs_f32() < BROKEN_TRACK {
+                log::warn!(
+                    "skipping {} from now on",
+                    self.tracks[cur.track].path.display()
+                );
+                self.broken.push(cur.track);
+            }
+            cur.volume = (cur.volume + step).min(1.0);
+            cur.gain.set_amplitude_ratio(cur.volume);
+        }
+
+        let change = match self.current {
+            None => true,
+            Some(ref cur) => {
+                state.skip
+                    || cur.mixed.is_stopped()
+                    || cur.control.remaining().is_some_and(|r| r < CROSSFADE)
             }
-            self.cur_track = None;
+        };
+        state.skip = false;
+        if !change {
+            return;
         }
 
-        if self.last_played.elapsed() > self.time_between_tracks {
-            self.track_id = (self.track_id + 1) % TRACKS.len();
-            let h = ctx.play_with_control(
-                TRACKS[self.track_id],
-                |s| ((), FadeIn::new(FramesSignal::new(s, 0.0).1, 5.0)),
-                AudioKind::Music,
-            );
-            self.cur_track = h.map(|x| x.1);
-            log::info!("playing soundtrack {}", TRACKS[self.track_id]);
-            self.last_played = Instant::now();
+        if let Some(cur) = self.current.take() {
+            if !cur.mixed.is_stopped() {
+                self.fading.push(cur);
+            }
+        }
+
+        let Some(i) = self.next_track(wanted_mood(sim)) else {
+            return;
+        };
+        let track = &self.tracks[i];
+        let (control, signal) = StreamedAudio::open(track.path.clone());
+        let (gain, signal) = Gain::new(signal, 0.0);
+        let Some(mixed) = ctx.play_signal(signal, AudioKind::Music) else {
+            return;
+        };
+
+        log::info!("playing soundtrack {}", track.name);
+        self.last = Some(i);
+        state.now_playing = Some((track.name.clone(), uiworld.time_always()));
+        self.current = Some(Playing {
+            track: i,
+            control,
+            gain,
+            mixed,
+            volume: 0.0,
+            started: Instant::now(),
+        });
+    }
+
+    /// Takes the first track of the wanted mood left in the round, or the first one
+    fn next_track(&mut self, wanted: Option<Mood>) -> Option<usize> {
+        self.queue.retain(|i| !self.broken.contains(i));
+        if self.queue.is_empty() {
+            self.queue = (0..self.tracks.len())
+                .filter(|i| !self.broken.contains(i))
+                .collect();
+            // Fisher-Yates
+            for i in (1..self.queue.len()).rev() {
+                let j = (self.rng.next_f32() * (i + 1) as f32) as usize;
+                self.queue.swap(i, j.min(i));
+            }
+            // the track just played isn't played again right away
+            if self.queue.len() > 1 && self.queue.first().copied() == self.last {
+                self.queue.swap(0, 1);
+            }
+        }
+
+        let pos = wanted
+            .and_then(|m| {
+                self.queue
+                    .iter()
+                    .position(|&i| self.tracks[i].mood == Some(m))
+            })
+            .unwrap_or(0);
+        (!self.queue.is_empty()).then(|| self.queue.remove(pos))
+    }
+}
+
+/// Calm at night, busy in a large city, any track otherwise
+fn wanted_mood(sim: &Simulation) -> Option<Mood> {
+    let hour = sim.read::<GameTime>().daytime.hour;
+    if !(6..21).contains(&hour) {
+        return Some(Mood::Calm);
+    }
+    if sim.world().humans.len() >= BUSY_POPULATION {
+        return Some(Mood::Busy);
+    }
+    None
+}
+
+/// The ogg files of the folder, a missing folder has no tracks
+fn scan(dir: &Path) -> Vec<Track> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let moods: FastMap<String, Mood> = common::saveload::load_raw(dir.join(MOODS_FILE))
+        .ok()
+        .and_then(|buf| {
+            JSON::decode(&buf)
+                .map_err(|e| log::error!("could not read the moods of {}: {}", dir.display(), e))
+                .ok()
+        })
+        .unwrap_or_default();
+
+    let mut tracks = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(stem) = path.file_stem().and_then(|x| x.to_str()) else {
+            continue;
+        };
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("ogg") => {}
+            Some("mp3") => {
+                log::warn!("{} is skipped, only ogg is supported", path.display());
+                continue;
+            }
+            _ => continue,
         }
+        tracks.push(Track {
+            name: stem.replace(['_', '-'], " "),
+            mood: moods.get(stem).copied(),
+            path,
+        });
     }
+    tracks.sort_by(|a, b| a.path.cmp(&b.path));
+    tracks
 }
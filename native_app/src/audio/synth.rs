@@ -0,0 +1,129 @@
+//! Sounds generated at startup instead of being shipped in the assets.
+//! They are short or noisy enough that a few oscillators and filtered noise make them.
+
+use std::f32::consts::TAU;
+
+use engine::{AudioContext, Stereo};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Adds the generated sounds to the context, they are then played by name like the others
+pub fn register(ctx: &mut AudioContext) {
+    ctx.insert("ui_click", SAMPLE_RATE, &ui_click());
+    ctx.insert("ui_hover", SAMPLE_RATE, &ui_hover());
+    ctx.insert("waves", SAMPLE_RATE, &waves());
+    ctx.insert("train_horn", SAMPLE_RATE, &train_horn());
+    ctx.insert("bulldozer", SAMPLE_RATE, &bulldozer());
+}
+
+fn generate(duration: f32, mut f: impl FnMut(f32) -> Stereo) -> Vec<Stereo> {
+    let n = (duration * SAMPLE_RATE as f32) as usize;
+    (0..n).map(|i| f(i as f32 / SAMPLE_RATE as f32)).collect()
+}
+
+/// White noise in [-1, 1]
+struct Noise(common::rand::RandGen);
+
+impl Noise {
+    fn new(seed: u64) -> Self {
+        Self(common::rand::gen(seed))
+    }
+
+    fn next(&mut self) -> f32 {
+        self.0.next_f32() * 2.0 - 1.0
+    }
+}
+
+/// One pole low pass filter, `cutoff` in Hz
+struct LowPass {
+    alpha: f32,
+    value: f32,
+}
+
+impl LowPass {
+    fn new(cutoff: f32) -> Self {
+        let dt = 1.0 / SAMPLE_RATE as f32;
+        Self {
+            alpha: dt / (dt + 1.0 / (TAU * cutoff)),
+            value: 0.0,
+        }
+    }
+
+    fn next(&mut self, x: f32) -> f32 {
+        self.value += self.alpha * (x - self.value);
+        self.value
+    }
+}
+
+/// Rises linearly over `attack` seconds then decays exponentially with the `decay` time constant
+fn envelope(t: f32, attack: f32, decay: f32) -> f32 {
+    if t < attack {
+        t / attack
+    } else {
+        (-(t - attack) / decay).exp()
+    }
+}
+
+fn ui_click() -> Vec<Stereo> {
+    let mut phase = 0.0;
+    generate(0.05, |t| {
+        // a short chirp down from 1800Hz
+        phase += TAU * (1800.0 - 12000.0 * t) / SAMPLE_RATE as f32;
+        let v = 0.25 * phase.sin() * envelope(t, 0.002, 0.012);
+        [v, v]
+    })
+}
+
+fn ui_hover() -> Vec<Stereo> {
+    generate(0.03, |t| {
+        let v = 0.06 * (TAU * 2600.0 * t).sin() * envelope(t, 0.002, 0.006);
+        [v, v]
+    })
+}
+
+/// Swells of filtered noise, loops seamlessly since the swell period divides the duration
+fn waves() -> Vec<Stereo> {
+    const DURATION: f32 = 8.0;
+    const SWELL: f32 = 4.0;
+    let mut noise = [Noise::new(1), Noise::new(2)];
+    let mut filters = [LowPass::new(500.0), LowPass::new(500.0)];
+    generate(DURATION, |t| {
+        let swell = 0.5 - 0.5 * (TAU * t / SWELL).cos();
+        let amp = 0.15 + 0.35 * swell * swell;
+        let l = filters[0].next(noise[0].next()) * amp;
+        let r = filters[1].next(noise[1].next()) * amp;
+        [l, r]
+    })
+}
+
+/// Two detuned chords of harmonics, like an air horn
+fn train_horn() -> Vec<Stereo> {
+    const DURATION: f32 = 1.6;
+    const RELEASE: f32 = 0.3;
+    generate(DURATION, |t| {
+        let env = (t / 0.05).min(1.0) * ((DURATION - t) / RELEASE).clamp(0.0, 1.0);
+        let mut v = 0.0;
+        for freq in [311.0, 370.0] {
+            for h in 1..=5 {
+                v += (TAU * freq * h as f32 * t).sin() / h as f32;
+            }
+        }
+        let v = 0.08 * v * env;
+        [v, v]
+    })
+}
+
+/// A low engine rumble over the crunch of what is torn down
+fn bulldozer() -> Vec<Stereo> {
+    let mut noise = Noise::new(3);
+    let mut rumble = LowPass::new(120.0);
+    let mut crunch = LowPass::new(1500.0);
+    generate(1.2, |t| {
+        let n = noise.next();
+        let engine = rumble.next((TAU * 55.0 * t).sin().signum() + n) * 3.0;
+        let tremolo = 0.7 + 0.3 * (TAU * 12.0 * t).sin();
+        let v = 0.2 * engine * tremolo * envelope(t, 0.02, 0.5)
+            + 0.3 * crunch.next(n) * envelope(t, 0.005, 0.15);
+        [v, v]
+    })
+}
@@ -0,0 +1,37 @@
+use common::FastMap;
+use engine::{AudioContext, AudioKind};
+use geom::Camera;
+use simulation::{Simulation, TrainID};
+
+use crate::audio::play_positioned;
+
+/// Speed under which a train is considered stopped, in m/s
+const STOPPED_SPEED: f32 = 0.1;
+
+/// Trains sound their horn when they leave a stop
+#[derive(Default)]
+pub struct TrainHorns {
+    /// Whether each train was stopped at the last update
+    stopped: FastMap<TrainID, bool>,
+}
+
+impl TrainHorns {
+    pub fn update(&mut self, sim: &Simulation, camera: &Camera, ctx: &mut AudioContext) {
+        let trains = &sim.world().trains;
+        self.stopped.retain(|id, _| trains.contains_key(*id));
+
+        for (id, train) in trains.iter() {
+            let stopped = train.speed.0 < STOPPED_SPEED;
+            let was_stopped = self.stopped.insert(id, stopped);
+            if was_stopped == Some(true) && !stopped {
+                play_positioned(
+                    ctx,
+                    camera,
+                    "train_horn",
+                    train.trans.pos,
+                    AudioKind::Effect,
+                );
+            }
+        }
+    }
+}
@@ -3,31 +3,55 @@ use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+use crate::rendering::garbage_overlay::draw_garbage_overlay;
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::rendering::land_value_overlay::draw_land_value_overlay;
+use crate::rendering::noise_overlay::draw_noise_overlay;
+use crate::rendering::overlays::draw_overlays;
+use crate::rendering::planes::draw_planes;
+use crate::rendering::selection_outline::SelectionOutlines;
+use crate::rendering::ships::draw_ships;
+use crate::rendering::traffic_overlay::draw_traffic_overlay;
 use common::history::History;
 use engine::{Context, FrameContext, MeshBuilder};
-use geom::{vec2, vec3, Camera, LinearColor};
-use simulation::Simulation;
+use geom::{vec2, Camera, LinearColor};
+use simulation::{saves, Simulation};
 
 use crate::audio::GameAudio;
 use crate::gui::debug_window::DebugObjs;
 use crate::gui::render_oldgui;
 use crate::inputmap::{Bindings, InputAction, InputMap};
 use crate::newgui;
+use crate::newgui::console::GUIConsoleState;
 use crate::newgui::follow::FollowEntity;
+use crate::newgui::hot_reload::hot_reload_prototypes;
 use crate::newgui::keybinds::KeybindState;
+use crate::newgui::screenshot;
 use crate::newgui::terraforming::TerraformingResource;
 use crate::newgui::toolbox::building;
-use crate::newgui::windows::settings::{manage_settings, Settings};
+use crate::newgui::windows::load::{load_thumbnails, LoadState};
+use crate::newgui::windows::settings::{
+    init_audio_settings, init_gfx_settings, manage_settings, Settings,
+};
 use crate::newgui::UiTextures;
 use crate::newgui::{render_newgui, ExitState, GuiState, TimeAlways, Tool};
-use crate::rendering::{InstancedRender, MapRenderOptions, MapRenderer, OrbitCamera};
-use crate::uiworld::{SaveLoadState, UiWorld};
+use crate::rendering::minimap::MinimapRenderer;
+use crate::rendering::sun;
+use crate::rendering::weather::Wetness;
+use crate::rendering::{
+    foliage_color, CrowdStressTest, InstancedRender, MapMeshStats, MapRenderOptions, MapRenderer,
+    OrbitCamera,
+};
+use crate::uiworld::{CurrentSave, SaveLoadState, UiWorld};
 use prototypes::GameTime;
 use simulation::utils::scheduler::SeqSchedule;
+use simulation::weather::Weather;
 
 pub const VERSION: &str = include_str!("../../VERSION");
 
+/// Largest side in pixels of the thumbnails of the named saves
+const THUMBNAIL_SIZE: u32 = 256;
+
 /// State is the main struct that contains all the state of the game and game UI.
 pub struct State {
     pub sim: Arc<RwLock<Simulation>>,
@@ -36,6 +60,8 @@ pub struct State {
 
     instanced_renderer: InstancedRender,
     map_renderer: MapRenderer,
+    minimap_renderer: MinimapRenderer,
+    selection_outlines: SelectionOutlines,
     immediate_renderer: MeshBuilder<true>,
 
     all_audio: GameAudio,
@@ -50,19 +76,27 @@ impl engine::framework::State for State {
 
         log::info!("loaded egui_render");
 
-        let sim: Simulation =
-            Simulation::load_from_disk("world").unwrap_or_else(|| Simulation::new(true));
         let game_schedule = Simulation::schedule();
         let mut uiworld = UiWorld::init();
 
-        let mut bindings = uiworld.write::<Bindings>();
-        let default_bindings = Bindings::default();
-        bindings
-            .0
-            .retain(|act, _| default_bindings.0.contains_key(act));
-        for (act, comb) in default_bindings.0 {
-            bindings.0.entry(act).or_insert(comb);
+        let slots = uiworld.read::<Settings>().auto_save_slots();
+        let (latest, errors) = Simulation::load_latest_save("world", &slots);
+        if !errors.is_empty() {
+            uiworld.write::<LoadState>().load_fail =
+                format!("Skipped corrupted saves:\n{}", errors.join("\n"));
+            uiworld.write::<GuiState>().windows.open_load();
         }
+        let sim = match latest {
+            Some((name, sim)) => {
+                log::info!("loaded {}", name);
+                uiworld.load_world_resources(&name);
+                sim
+            }
+            None => Simulation::new(true),
+        };
+
+        let mut bindings = uiworld.write::<Bindings>();
+        bindings.merge_defaults();
         uiworld.write::<InputMap>().build_input_tree(&mut bindings);
         drop(bindings);
 
@@ -73,6 +107,8 @@ impl engine::framework::State for State {
 
         log::info!("version is {}", VERSION);
 
+        init_gfx_settings(&uiworld, &ctx.gfx);
+        init_audio_settings(&uiworld, &ctx.audio);
         {
             let s = uiworld.read::<Settings>();
             manage_settings(ctx, &s);
@@ -81,11 +117,15 @@ impl engine::framework::State for State {
         defer!(log::info!("finished init of game loop"));
         building::do_icons(ctx, &uiworld);
 
+        let minimap_renderer = MinimapRenderer::new(&ctx.gfx, &mut ctx.yakui, &uiworld, &sim);
+
         let me = Self {
             uiw: uiworld,
             game_schedule,
             instanced_renderer: InstancedRender::new(&mut ctx.gfx),
             map_renderer: MapRenderer::new(&mut ctx.gfx, &sim),
+            minimap_renderer,
+            selection_outlines: SelectionOutlines::new(&sim),
             all_audio: GameAudio::new(&mut ctx.audio),
             sim: Arc::new(RwLock::new(sim)),
             immediate_renderer: MeshBuilder::new(ctx.gfx.tess_material),
@@ -105,15 +145,50 @@ impl engine::framework::State for State {
             timings.total_cpu_time.add_value(ctx.times.total_cpu_time);
         }
 
+        self.uiw.write::<CurrentSave>().play_time += ctx.delta as f64;
+
         let mut slstate = self.uiw.write::<SaveLoadState>();
         if slstate.please_save && !slstate.saving_status.load(Ordering::SeqCst) {
             slstate.please_save = false;
+            let save_as = slstate.save_as.take();
+            let name = slstate
+                .save_name
+                .take()
+                .unwrap_or_else(|| "world".to_string());
+
+            let mut current = self.uiw.write::<CurrentSave>();
+            let meta = self.sim.read().unwrap().metadata(
+                save_as.as_deref().unwrap_or(&name),
+                &current.city_name,
+                current.play_time,
+            );
+            if let Some(ref save_as) = save_as {
+                current.name = Some(save_as.clone());
+                ctx.gfx
+                    .request_screenshot(saves::thumbnail_path(save_as), THUMBNAIL_SIZE);
+            }
+            drop(current);
+
+            let save_name = match save_as {
+                Some(ref save_as) => {
+                    let _ = std::fs::create_dir_all(saves::save_dir(save_as));
+                    saves::world_name(save_as)
+                }
+                None => name.clone(),
+            };
+            self.uiw.save_world_resources(&save_name);
+
             let cpy = self.sim.clone();
             slstate.saving_status.store(true, Ordering::SeqCst);
             let status = slstate.saving_status.clone();
             std::thread::spawn(move || {
                 profiling::scope!("game_loop::update::save");
-                cpy.read().unwrap().save_to_disk("world");
+                let sim = cpy.read().unwrap();
+                if save_as.is_some() {
+                    sim.save_named(&meta);
+                } else {
+                    sim.save_with_metadata(&name, &meta);
+                }
                 status.store(false, Ordering::SeqCst);
             });
         }
@@ -125,6 +200,9 @@ impl engine::framework::State for State {
             self.reset(ctx);
         }
 
+        load_thumbnails(&self.uiw, &mut ctx.gfx, &mut ctx.yakui);
+        hot_reload_prototypes(&self.uiw, &self.sim);
+
         if !ctx.egui.last_mouse_captured {
             let sim = self.sim.read().unwrap();
             let map = sim.map();
@@ -160,6 +238,19 @@ impl engine::framework::State for State {
             !ctx.egui.last_kb_captured,
             !ctx.egui.last_mouse_captured,
         );
+        if self.uiw.read::<GUIConsoleState>().is_opened() {
+            // typing in the console doesn't move the camera, the arrows browse its history
+            let inp = &mut *self.uiw.write::<InputMap>();
+            for action in [
+                InputAction::GoForward,
+                InputAction::GoBackward,
+                InputAction::GoLeft,
+                InputAction::GoRight,
+            ] {
+                inp.act.remove(&action);
+                inp.just_act.remove(&action);
+            }
+        }
         newgui::run_ui_systems(&self.sim.read().unwrap(), &self.uiw);
 
         self.uiw.write::<Timings>().all.add_value(ctx.delta);
@@ -172,9 +263,11 @@ impl engine::framework::State for State {
             .contains(&InputAction::HideInterface);
 
         manage_settings(ctx, &self.uiw.read::<Settings>());
+        screenshot::restore_camera(&self.uiw);
         self.manage_io(ctx);
 
         self.map_renderer.update(&self.sim.read().unwrap(), ctx);
+        self.minimap_renderer.update(&self.sim, &self.uiw, &ctx.gfx);
 
         ctx.gfx
             .set_time(self.sim.read().unwrap().read::<GameTime>().timestamp as f32);
@@ -185,8 +278,9 @@ impl engine::framework::State for State {
         self.all_audio
             .update(&self.sim.read().unwrap(), &self.uiw, &mut ctx.audio);
 
-        FollowEntity::update_camera(self);
+        FollowEntity::update_camera(self, ctx.delta);
         self.uiw.camera_mut().update(ctx);
+        screenshot::capture(&self.uiw, &self.sim.read().unwrap(), ctx);
         self.manage_gfx_params(ctx);
     }
 
@@ -210,9 +304,21 @@ impl engine::framework::State for State {
             &mut self.uiw.write::<ImmediateDraw>(),
             ctx,
         );
+        *self.uiw.write::<MapMeshStats>() = self.map_renderer.meshb.stats;
 
-        self.instanced_renderer
-            .render(&self.sim.read().unwrap(), ctx);
+        self.instanced_renderer.render(
+            &self.sim.read().unwrap(),
+            &self.uiw.read::<CrowdStressTest>(),
+            ctx,
+        );
+
+        self.selection_outlines.render(
+            &sim,
+            &self.uiw,
+            &self.instanced_renderer,
+            &self.map_renderer,
+            ctx,
+        );
 
         drop(sim);
         drop(camera);
@@ -261,33 +367,38 @@ impl engine::framework::State for State {
 
 impl State {
     fn reset(&mut self, ctx: &mut Context) {
+        self.uiw.reset_world_resources();
+        let save = self.uiw.write::<SaveLoadState>().please_load_save.take();
+        if let Some(save) = save {
+            self.uiw.load_world_resources(&save);
+        }
         ctx.gfx.lamplights.reset(&ctx.gfx.device, &ctx.gfx.queue);
         self.map_renderer = MapRenderer::new(&mut ctx.gfx, &self.sim.read().unwrap());
+        self.selection_outlines = SelectionOutlines::new(&self.sim.read().unwrap());
+        self.minimap_renderer
+            .reset(&self.uiw, &self.sim.read().unwrap());
         self.sim.write().unwrap().map().dispatch_all();
         ctx.gfx.update_simplelit_bg();
     }
 
     fn manage_gfx_params(&mut self, ctx: &mut Context) {
-        let t = std::f32::consts::TAU
-            * (ctx.gfx.render_params.value().time - 8.0 * GameTime::HOUR as f32)
-            / GameTime::DAY as f32;
-
-        let sun = vec3(t.cos(), t.sin() * 0.5, t.sin() + 0.5).normalize();
+        let daysec = ctx.gfx.render_params.value().time % GameTime::DAY as f32;
+        let sun = sun::sun_direction(daysec);
 
         self.uiw.insert(ctx.gfx.perf.as_static());
 
+        // the screen-space passes sample the textures at the size of the scene
+        let (render_w, render_h) = ctx.gfx.render_size();
         let params = ctx.gfx.render_params.value_mut();
         params.time_always = self.uiw.time_always();
-        params.sun_col = 4.0
-            * sun.z.max(0.0).sqrt().sqrt()
-            * LinearColor::new(1.0, 0.95 + sun.z * 0.05, 0.95 + sun.z * 0.05, 1.0);
+        params.sun_col = sun::sun_color(sun);
         let camera = self.uiw.read::<OrbitCamera>();
         params.sun = sun;
-        params.viewport = vec2(ctx.gfx.size.0 as f32, ctx.gfx.size.1 as f32);
+        params.viewport = vec2(render_w as f32, render_h as f32);
         params.sun_shadow_proj = camera
             .camera
             .build_sun_shadowmap_matrix(
-                sun,
+                sun::shadow_direction(sun),
                 params.shadow_mapping_resolution as f32,
                 &ctx.gfx.frustrum,
             )
@@ -306,6 +417,13 @@ impl State {
         let c = simulation::colors();
         params.sand_col = c.sand_col.into();
         params.sea_col = c.sea_col.into();
+
+        let sim = self.sim.read().unwrap();
+        params.foliage_col = foliage_color(sim.read::<GameTime>().season());
+        params.wetness = self
+            .uiw
+            .write::<Wetness>()
+            .update(sim.read::<Weather>().current(), &sim.read::<GameTime>());
     }
 
     fn manage_io(&mut self, ctx: &mut Context) {
@@ -317,6 +435,7 @@ impl State {
             ctx.delta,
             &self.uiw.read::<InputMap>(),
             &self.uiw.read::<Settings>(),
+            !ctx.egui.last_mouse_captured,
             map.environment.bounds().expand(-3000.0),
             |p| map.environment.height(p),
         );
@@ -345,6 +464,17 @@ impl State {
             }
         }
 
+        {
+            let sim = self.sim.read().unwrap();
+            draw_ships(&mut tess, &sim);
+            draw_planes(&mut tess, &sim);
+            draw_overlays(&mut tess, &sim, &self.uiw);
+            draw_garbage_overlay(&mut tess, &sim, &self.uiw);
+            draw_traffic_overlay(&mut tess, &sim, &self.uiw);
+            draw_noise_overlay(&mut tess, &sim, &self.uiw);
+            draw_land_value_overlay(&mut tess, &sim, &self.uiw);
+        }
+
         {
             let sim = self.sim.read().unwrap();
             let immediate = &mut *self.uiw.write::<ImmediateDraw>();
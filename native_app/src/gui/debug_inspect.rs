@@ -7,7 +7,7 @@ use simulation::economy::Market;
 use simulation::transportation::Location;
 use simulation::{
     AnyEntity, CompanyEnt, FreightStationEnt, HumanEnt, Simulation, SoulID, TrainEnt, VehicleEnt,
-    WagonEnt,
+    WagonEnt, WarehouseEnt,
 };
 
 use crate::newgui::follow::FollowEntity;
@@ -79,6 +79,9 @@ impl InspectRenderer {
             AnyEntity::HumanID(x) => {
                 <HumanEnt as Inspect<HumanEnt>>::render(sim.get(x).unwrap(), "", ui, &args)
             }
+            AnyEntity::WarehouseID(x) => {
+                <WarehouseEnt as Inspect<WarehouseEnt>>::render(sim.get(x).unwrap(), "", ui, &args)
+            }
         }
 
         if let AnyEntity::VehicleID(id) = entity {
@@ -123,7 +126,7 @@ impl InspectRenderer {
         {
             let mut follow = uiworld.write::<FollowEntity>();
             if ui.small_button("Follow").clicked() {
-                follow.0.replace(entity);
+                follow.target = Some(entity);
             }
         }
 
@@ -5,11 +5,12 @@ use crate::newgui::{GuiState, InspectedEntity};
 use crate::uiworld::UiWorld;
 use simulation::map_dynamic::ParkingManagement;
 use simulation::transportation::TransportGrid;
-use simulation::{Simulation, TrainID};
+use simulation::{Simulation, SimulationOptions, TrainID};
 use std::time::{Duration, Instant};
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::settings::Settings;
+use crate::rendering::MapMeshStats;
 use egui::{Context, Widget};
 use engine::{PerfCountersStatic, Tesselator};
 use geom::{Camera, Color, LinearColor, Spline3, Vec2};
@@ -101,6 +102,16 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
             &mut uiworld.write::<Settings>().gfx.fog_shader_debug,
             "Debug fog shader",
         );
+        ui.checkbox(
+            &mut uiworld.write::<Settings>().gfx.frustum_culling,
+            "Frustum culling",
+        );
+        let mut landmarks = sim.read::<SimulationOptions>().landmark_routing;
+        if ui.checkbox(&mut landmarks, "Landmark routing").changed() {
+            uiworld
+                .commands()
+                .push(WorldCommand::SetLandmarkRouting(landmarks));
+        }
         drop(objs);
 
         let time = *sim.read::<GameTime>();
@@ -213,7 +224,22 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
             "{}k heightmap shadow triangles",
             counters.heightmap_shadows_triangles / 1000
         ));
+        ui.add_space(5.0);
+        ui.label(format!(
+            "instances per lod: {}",
+            counters
+                .lod_instances
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(" / ")
+        ));
         drop(counters);
+        let map_stats = *uiworld.read::<MapMeshStats>();
+        ui.label(format!(
+            "map chunks drawn: {} / {}, {} rebuilt",
+            map_stats.chunks_drawn, map_stats.chunks_total, map_stats.rebuilds
+        ));
 
         if let Some(mouse) = mouse {
             ui.label(format!(
@@ -540,6 +566,12 @@ pub fn debug_trainreservations(
                     .find_turn(*tid)?
                     .points
             }
+            TraverseKind::Crossing(cid) => {
+                &unwrap_cont!(unwrap_cont!(map.roads().get(cid.road))
+                    .crossings
+                    .get(cid.idx as usize))
+                .points
+            }
         };
 
         for p in poses.values() {
@@ -566,7 +598,7 @@ pub fn debug_trainreservations(
     let travers = t.it.get_travers()?;
     let dist_to_next = travers
         .kind
-        .length(map.lanes(), map.intersections())
+        .length(map.lanes(), map.intersections(), map.roads())
         .unwrap_or(0.0)
         - t.res.cur_travers_dist;
 
@@ -579,7 +611,7 @@ pub fn debug_trainreservations(
         t.locomotive.length + 50.0,
     ) {
         match v {
-            TraverseKind::Lane(_) => {}
+            TraverseKind::Lane(_) | TraverseKind::Crossing(_) => {}
             TraverseKind::Turn(t) => {
                 if map
                     .intersections()
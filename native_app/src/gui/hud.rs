@@ -2,11 +2,14 @@ use egui::{Color32, Context, Id, RichText};
 
 use prototypes::Money;
 use simulation::economy::Government;
+use simulation::world_command::FailedCommands;
 use simulation::Simulation;
 
 use crate::gui::debug_inspect::debug_inspector;
 use crate::gui::debug_window::debug_window;
-use crate::newgui::{ErrorTooltip, GuiState, PotentialCommands};
+use crate::newgui::notifications::{jump_to, Notifications};
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::{ErrorTooltip, GuiState, PotentialCommands, Toasts};
 use crate::uiworld::UiWorld;
 
 /// Root GUI entrypoint
@@ -21,6 +24,58 @@ pub fn render_oldgui(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
     debug_window(ui, uiworld, sim);
 
     tooltip(ui, uiworld, sim);
+
+    toasts(ui, uiworld, sim);
+}
+
+pub fn toasts(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("gui::toasts");
+    let now = uiworld.time_always();
+    let mut toasts = uiworld.write::<Toasts>();
+
+    let failed = sim.read::<FailedCommands>();
+    let mut seen = toasts.seen_command_errors;
+    for err in failed.since(&mut seen) {
+        toasts.push(err.to_string(), now);
+    }
+    toasts.seen_command_errors = seen;
+    drop(failed);
+
+    let duration = uiworld.read::<Settings>().notification_duration;
+    uiworld
+        .write::<Notifications>()
+        .update(sim, now, &mut toasts, duration);
+
+    toasts.update(now);
+    if toasts.msgs.is_empty() {
+        return;
+    }
+
+    let mut jump = None;
+    egui::Area::new(Id::new("toasts"))
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .interactable(toasts.msgs.iter().any(|t| t.target.is_some()))
+        .show(ui, |ui| {
+            for toast in &toasts.msgs {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let text = if toast.count > 1 {
+                        format!("{} (x{})", toast.msg, toast.count)
+                    } else {
+                        toast.msg.to_string()
+                    };
+                    if toast.target.is_none() {
+                        ui.label(text);
+                    } else if ui.link(text).clicked() {
+                        jump = toast.target;
+                    }
+                });
+            }
+        });
+
+    drop(toasts);
+    if let Some(target) = jump {
+        jump_to(uiworld, sim, target);
+    }
 }
 
 pub fn tooltip(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
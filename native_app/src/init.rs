@@ -1,55 +1,108 @@
+use crate::audio::MusicState;
 use crate::game_loop::Timings;
 use crate::gui::debug_window::{DebugObjs, DebugState, TestFieldProperties};
-use crate::inputmap::{Bindings, InputMap};
+use crate::inputmap::{Bindings, InputMap, BINDINGS_SAVE_NAME};
 use crate::network::NetworkState;
 use crate::newgui::addtrain::TrainSpawnResource;
 use crate::newgui::bulldozer::BulldozerState;
 use crate::newgui::chat::GUIChatState;
+use crate::newgui::console::{ConsoleCommands, GUIConsoleState};
+use crate::newgui::copypaste::{Blueprint, CopyPasteResource};
+use crate::newgui::districts::DistrictsResource;
 use crate::newgui::follow::FollowEntity;
+use crate::newgui::forestry::ForestryResource;
+use crate::newgui::hot_reload::PrototypesWatcher;
 use crate::newgui::keybinds::KeybindState;
 use crate::newgui::lotbrush::LotBrushResource;
+use crate::newgui::map_editor::MapEditorState;
+use crate::newgui::notifications::Notifications;
 use crate::newgui::roadbuild::RoadBuildResource;
 use crate::newgui::roadeditor::RoadEditorResource;
+use crate::newgui::screenshot::{ScreenshotState, Timelapse};
 use crate::newgui::specialbuilding::SpecialBuildingResource;
 use crate::newgui::terraforming::TerraformingResource;
 use crate::newgui::toolbox::building::BuildingIcons;
+use crate::newgui::windows::bookmarks::CameraBookmarks;
+use crate::newgui::windows::chronicle::ChronicleState;
 use crate::newgui::windows::economy::EconomyState;
 use crate::newgui::windows::load::LoadState;
+use crate::newgui::windows::mods::ModsState;
+use crate::newgui::windows::save_as::SaveAsState;
+use crate::newgui::windows::scenarios::ScenariosState;
+use crate::newgui::windows::search::SearchState;
 use crate::newgui::windows::settings::{Settings, SettingsState};
+use crate::newgui::windows::statistics::StatisticsState;
+use crate::newgui::windows::transit::TransitEditor;
 use crate::newgui::zoneedit::ZoneEditState;
+use crate::newgui::zoning::ZoningResource;
 use crate::newgui::{
     ErrorTooltip, ExitState, GuiState, InspectedBuilding, InspectedEntity, PotentialCommands,
-    TimeAlways, Tool,
+    TimeAlways, Toasts, Tool,
 };
+use crate::rendering::garbage_overlay::GarbageOverlay;
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
-use crate::uiworld::{ReceivedCommands, SaveLoadState, UiWorld};
+use crate::rendering::land_value_overlay::LandValueOverlay;
+use crate::rendering::map_labels::MapLabels;
+use crate::rendering::minimap::Minimap;
+use crate::rendering::noise_overlay::NoiseOverlay;
+use crate::rendering::overlays::Overlays;
+use crate::rendering::traffic_overlay::TrafficOverlay;
+use crate::rendering::weather::Wetness;
+use crate::rendering::{CrowdStressTest, MapMeshStats};
+use crate::uiworld::{CurrentSave, ReceivedCommands, SaveLoadState, UiWorld};
 use common::saveload::Encoder;
+use prototypes::PrototypeLoadError;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use simulation::world_command::WorldCommands;
 
 /// init is called at the beginning of the program to initialize the globals
 /// It is mostly to register types for serialization and initialization of the engine
-pub fn init() {
-    simulation::init::init();
+pub fn init() -> Result<(), PrototypeLoadError> {
+    simulation::init::try_init()?;
     register_resource::<Settings>("settings");
     #[cfg(feature = "multiplayer")]
     register_resource::<crate::newgui::windows::network::NetworkConnectionInfo>("netinfo");
     register_resource::<LotBrushResource>("lot_brush");
-    register_resource::<Bindings>("bindings");
+    register_resource::<Bindings>(BINDINGS_SAVE_NAME);
+
+    register_world_resource::<CameraBookmarks>("camera_bookmarks");
+    register_world_resource::<Timelapse>("timelapse");
 
     register_resource_noserialize::<GuiState>();
     register_resource_noserialize::<TerraformingResource>();
     register_resource_noserialize::<BulldozerState>();
+    register_resource_noserialize::<ZoningResource>();
+    register_resource_noserialize::<ForestryResource>();
+    register_resource_noserialize::<DistrictsResource>();
+    register_resource_noserialize::<MapLabels>();
+    register_resource_noserialize::<Blueprint>();
+    register_resource_noserialize::<CopyPasteResource>();
     register_resource_noserialize::<DebugObjs>();
     register_resource_noserialize::<DebugState>();
     register_resource_noserialize::<ErrorTooltip>();
+    register_resource_noserialize::<Toasts>();
+    register_resource_noserialize::<Notifications>();
+    register_resource_noserialize::<PrototypesWatcher>();
+    register_resource_noserialize::<ScenariosState>();
+    register_resource_noserialize::<ModsState>();
+    register_resource_noserialize::<Minimap>();
+    register_resource_noserialize::<ScreenshotState>();
+    register_resource_noserialize::<Wetness>();
     register_resource_noserialize::<ExitState>();
     register_resource_noserialize::<FollowEntity>();
     register_resource_noserialize::<GUIChatState>();
+    register_resource_noserialize::<GUIConsoleState>();
+    register_resource_noserialize::<ConsoleCommands>();
     register_resource_noserialize::<TimeAlways>();
     register_resource_noserialize::<ImmediateDraw>();
     register_resource_noserialize::<ImmediateSound>();
+    register_resource_noserialize::<MusicState>();
+    register_resource_noserialize::<Overlays>();
+    register_resource_noserialize::<GarbageOverlay>();
+    register_resource_noserialize::<TrafficOverlay>();
+    register_resource_noserialize::<NoiseOverlay>();
+    register_resource_noserialize::<LandValueOverlay>();
     register_resource_noserialize::<InputMap>();
     register_resource_noserialize::<InspectedEntity>();
     register_resource_noserialize::<InspectedBuilding>();
@@ -62,15 +115,47 @@ pub fn init() {
     register_resource_noserialize::<RoadEditorResource>();
     register_resource_noserialize::<SpecialBuildingResource>();
     register_resource_noserialize::<TrainSpawnResource>();
+    register_resource_noserialize::<TransitEditor>();
     register_resource_noserialize::<Timings>();
+    register_resource_noserialize::<MapMeshStats>();
+    register_resource_noserialize::<CrowdStressTest>();
     register_resource_noserialize::<Tool>();
     register_resource_noserialize::<WorldCommands>();
     register_resource_noserialize::<LoadState>();
     register_resource_noserialize::<SaveLoadState>();
     register_resource_noserialize::<EconomyState>();
+    register_resource_noserialize::<StatisticsState>();
+    register_resource_noserialize::<SearchState>();
+    register_resource_noserialize::<ChronicleState>();
     register_resource_noserialize::<SettingsState>();
     register_resource_noserialize::<BuildingIcons>();
     register_resource_noserialize::<KeybindState>();
+    register_resource_noserialize::<CurrentSave>();
+    register_resource_noserialize::<SaveAsState>();
+    register_resource_noserialize::<MapEditorState>();
+
+    // state referring to the entities of the world, it would dangle once another world is loaded
+    reset_on_world_change::<InspectedEntity>();
+    reset_on_world_change::<InspectedBuilding>();
+    reset_on_world_change::<FollowEntity>();
+    reset_on_world_change::<Tool>();
+    reset_on_world_change::<RoadBuildResource>();
+    reset_on_world_change::<RoadEditorResource>();
+    reset_on_world_change::<SearchState>();
+    reset_on_world_change::<Notifications>();
+    reset_on_world_change::<BulldozerState>();
+    reset_on_world_change::<ZoneEditState>();
+    reset_on_world_change::<TransitEditor>();
+    reset_on_world_change::<CopyPasteResource>();
+    reset_on_world_change::<DistrictsResource>();
+    reset_on_world_change::<MapLabels>();
+    reset_on_world_change::<PotentialCommands>();
+    reset_on_world_change::<WorldCommands>();
+    reset_on_world_change::<ErrorTooltip>();
+    // the profile is read again, a scenario may have been won in the previous world
+    reset_on_world_change::<ScenariosState>();
+
+    Ok(())
 }
 
 pub struct InitFunc {
@@ -82,8 +167,16 @@ pub struct SaveLoadFunc {
     pub load: Box<dyn Fn(&mut UiWorld) + 'static>,
 }
 
+/// Saves and loads the state of the interface belonging to a game, given the name of its save
+pub struct WorldSaveLoadFunc {
+    pub save: Box<dyn Fn(&UiWorld, &str) + 'static>,
+    pub load: Box<dyn Fn(&mut UiWorld, &str) + 'static>,
+}
+
 pub static mut INIT_FUNCS: Vec<InitFunc> = Vec::new();
+pub static mut WORLD_RESET_FUNCS: Vec<InitFunc> = Vec::new();
 pub static mut SAVELOAD_FUNCS: Vec<SaveLoadFunc> = Vec::new();
+pub static mut WORLD_SAVELOAD_FUNCS: Vec<WorldSaveLoadFunc> = Vec::new();
 
 fn register_resource_noserialize<T: 'static + Default>() {
     unsafe {
@@ -93,6 +186,14 @@ fn register_resource_noserialize<T: 'static + Default>() {
     }
 }
 
+fn reset_on_world_change<T: 'static + Default>() {
+    unsafe {
+        WORLD_RESET_FUNCS.push(InitFunc {
+            f: Box::new(|uiw| uiw.insert(T::default())),
+        });
+    }
+}
+
 fn register_resource<T: 'static + Default + Serialize + DeserializeOwned>(name: &'static str) {
     unsafe {
         INIT_FUNCS.push(InitFunc {
@@ -110,3 +211,32 @@ fn register_resource<T: 'static + Default + Serialize + DeserializeOwned>(name:
         });
     }
 }
+
+/// The resource is written next to the save of the game and loaded back with it,
+/// new games start from the default
+fn register_world_resource<T: 'static + Default + Serialize + DeserializeOwned>(
+    name: &'static str,
+) {
+    unsafe {
+        INIT_FUNCS.push(InitFunc {
+            f: Box::new(|uiw| uiw.insert(T::default())),
+        });
+        WORLD_RESET_FUNCS.push(InitFunc {
+            f: Box::new(|uiw| uiw.insert(T::default())),
+        });
+        WORLD_SAVELOAD_FUNCS.push(WorldSaveLoadFunc {
+            save: Box::new(move |uiworld, save_name| {
+                <common::saveload::JSONPretty as Encoder>::save(
+                    &*uiworld.read::<T>(),
+                    &format!("{save_name}_{name}"),
+                );
+            }),
+            load: Box::new(move |uiworld, save_name| {
+                let res =
+                    <common::saveload::JSON as Encoder>::load::<T>(&format!("{save_name}_{name}"))
+                        .unwrap_or_default();
+                uiworld.insert(res);
+            }),
+        });
+    }
+}
@@ -1,13 +1,18 @@
 use common::{FastMap, FastSet};
 use engine::{InputContext, Key, MouseButton};
 use geom::{Ray3, Vec2, Vec3};
-use serde::{Deserialize, Serialize};
+use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
+use std::sync::OnceLock;
+
+/// Name of the config file of the bindings, kept apart from the saves
+pub const BINDINGS_SAVE_NAME: &str = "bindings";
 
 // Either combinations can work
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputCombinations(pub Vec<InputCombination>);
 
 #[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -26,9 +31,13 @@ pub enum InputAction {
     GoForward,
     GoBackward,
     CameraMove,
+    CameraGrab,
     CameraRotate,
     Zoom,
     Dezoom,
+    ZoomStreet,
+    ZoomDistrict,
+    ZoomCity,
     Rotate,
     SizeUp,
     SizeDown,
@@ -42,7 +51,31 @@ pub enum InputAction {
     OpenEconomyMenu,
     OpenDebugMenu,
     PausePlay,
+    /// Runs a single tick while the game is paused
+    StepTick,
+    SpeedNormal,
+    SpeedFast,
+    SpeedFaster,
+    /// As fast as the computer allows
+    SpeedUncapped,
     OpenChat,
+    OpenConsole,
+    /// Previous line of the console history
+    ConsoleOlder,
+    /// Next line of the console history
+    ConsoleNewer,
+    ConsoleComplete,
+    Undo,
+    Redo,
+    BlueprintRotate,
+    BlueprintMirror,
+    FlipRoad,
+    ToggleMinimap,
+    Screenshot,
+    /// Stores the camera into the bookmark slot
+    SaveBookmark(u8),
+    /// Moves the camera to the bookmark slot
+    RecallBookmark(u8),
 }
 
 // All unit inputs need to match
@@ -74,7 +107,8 @@ pub struct InputMap {
     input_tree: InputTree,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Serialized as a list of (action, combinations), as the bookmark actions cannot be JSON keys
+#[derive(Clone, PartialEq, Debug)]
 pub struct Bindings(pub BTreeMap<InputAction, InputCombinations>);
 
 use InputAction::*;
@@ -90,9 +124,13 @@ const DEFAULT_BINDINGS: &[(InputAction, &[&[UnitInput]])] = &[
     (GoLeft,          &[&[KeyScan(30)], &[Key(K::ArrowLeft)]]),
     (GoRight,         &[&[KeyScan(32)], &[Key(K::ArrowRight)]]),
     (CameraRotate,    &[&[Mouse(Right)]]),
-    (CameraMove,      &[&[Key(K::Shift), Mouse(Right)], &[Mouse(Middle)]]),
+    (CameraMove,      &[&[Key(K::Shift), Mouse(Right)]]),
+    (CameraGrab,      &[&[Mouse(Middle)]]),
     (Zoom,            &[&[Key(K::c("+"))], &[WheelUp]]),
     (Dezoom,          &[&[Key(K::c("-"))], &[WheelDown]]),
+    (ZoomStreet,      &[&[Key(K::c("1"))]]),
+    (ZoomDistrict,    &[&[Key(K::c("2"))]]),
+    (ZoomCity,        &[&[Key(K::c("3"))]]),
     (Rotate,          &[&[Key(K::Control), WheelUp], &[Key(K::Control), WheelDown]]),
     (SizeUp,          &[&[Key(K::Control), WheelUp]]),
     (SizeDown,        &[&[Key(K::Control), WheelDown]]),
@@ -104,9 +142,45 @@ const DEFAULT_BINDINGS: &[(InputAction, &[&[UnitInput]])] = &[
     (UpElevation,     &[&[Key(K::Control), WheelUp]]),
     (DownElevation,   &[&[Key(K::Control), WheelDown]]),
     (OpenEconomyMenu, &[&[Key(K::c("E"))]]),
-    (OpenDebugMenu,   &[&[Key(K::F3)]]),
+    (OpenDebugMenu,   &[&[Key(K::F12)]]),
     (PausePlay,       &[&[Key(K::Space)]]),
+    (StepTick,        &[&[Key(K::c("."))]]),
+    (SpeedNormal,     &[&[Key(K::Control), Key(K::c("1"))]]),
+    (SpeedFast,       &[&[Key(K::Control), Key(K::c("2"))]]),
+    (SpeedFaster,     &[&[Key(K::Control), Key(K::c("3"))]]),
+    (SpeedUncapped,   &[&[Key(K::Control), Key(K::c("4"))]]),
     (OpenChat,        &[&[Key(K::c("T"))]]),
+    (OpenConsole,     &[&[Key(K::c("`"))]]),
+    (ConsoleOlder,    &[&[Key(K::ArrowUp)]]),
+    (ConsoleNewer,    &[&[Key(K::ArrowDown)]]),
+    (ConsoleComplete, &[&[Key(K::Tab)]]),
+    (Undo,            &[&[Key(K::Control), Key(K::c("Z"))]]),
+    (Redo,            &[&[Key(K::Control), Key(K::Shift), Key(K::c("Z"))]]),
+    (BlueprintRotate, &[&[Key(K::c("R"))]]),
+    (BlueprintMirror, &[&[Key(K::c("M"))]]),
+    (FlipRoad,        &[&[Key(K::c("F"))]]),
+    (ToggleMinimap,   &[&[Key(K::c("N"))]]),
+    (Screenshot,      &[&[Key(K::F11)]]),
+    (SaveBookmark(0),    &[&[Key(K::Control), Key(K::F1)]]),
+    (SaveBookmark(1),    &[&[Key(K::Control), Key(K::F2)]]),
+    (SaveBookmark(2),    &[&[Key(K::Control), Key(K::F3)]]),
+    (SaveBookmark(3),    &[&[Key(K::Control), Key(K::F4)]]),
+    (SaveBookmark(4),    &[&[Key(K::Control), Key(K::F5)]]),
+    (SaveBookmark(5),    &[&[Key(K::Control), Key(K::F6)]]),
+    (SaveBookmark(6),    &[&[Key(K::Control), Key(K::F7)]]),
+    (SaveBookmark(7),    &[&[Key(K::Control), Key(K::F8)]]),
+    (SaveBookmark(8),    &[&[Key(K::Control), Key(K::F9)]]),
+    (SaveBookmark(9),    &[&[Key(K::Control), Key(K::F10)]]),
+    (RecallBookmark(0),  &[&[Key(K::F1)]]),
+    (RecallBookmark(1),  &[&[Key(K::F2)]]),
+    (RecallBookmark(2),  &[&[Key(K::F3)]]),
+    (RecallBookmark(3),  &[&[Key(K::F4)]]),
+    (RecallBookmark(4),  &[&[Key(K::F5)]]),
+    (RecallBookmark(5),  &[&[Key(K::F6)]]),
+    (RecallBookmark(6),  &[&[Key(K::F7)]]),
+    (RecallBookmark(7),  &[&[Key(K::F8)]]),
+    (RecallBookmark(8),  &[&[Key(K::F9)]]),
+    (RecallBookmark(9),  &[&[Key(K::F10)]]),
 ];
 
 impl Default for Bindings {
@@ -128,6 +202,116 @@ impl Default for Bindings {
     }
 }
 
+/// The defaults never change, so they are only built once
+fn default_bindings() -> &'static Bindings {
+    static DEFAULTS: OnceLock<Bindings> = OnceLock::new();
+    DEFAULTS.get_or_init(Bindings::default)
+}
+
+impl Bindings {
+    /// Drops the actions that no longer exist and gives their default bindings to the new ones
+    pub fn merge_defaults(&mut self) {
+        let defaults = &default_bindings().0;
+        self.0.retain(|act, _| defaults.contains_key(act));
+        for (act, comb) in defaults {
+            self.0.entry(act.clone()).or_insert_with(|| comb.clone());
+        }
+    }
+
+    pub fn reset(&mut self, action: &InputAction) {
+        if let Some(comb) = default_bindings().0.get(action) {
+            self.0.insert(action.clone(), comb.clone());
+        }
+    }
+
+    /// Other actions bound to the same combination.
+    /// Combinations the actions already share in the default bindings are not conflicts,
+    /// e.g. rotating and changing the size are used by different tools.
+    pub fn conflicts(&self, action: &InputAction, comb: &InputCombination) -> Vec<InputAction> {
+        let defaults = default_bindings();
+        let shared_by_default = |other: &InputAction| {
+            [action, other].iter().all(|act| {
+                defaults
+                    .0
+                    .get(act)
+                    .is_some_and(|combs| combs.0.iter().any(|c| c.same_as(comb)))
+            })
+        };
+
+        self.0
+            .iter()
+            .filter(|&(other, combs)| {
+                other != action
+                    && combs.0.iter().any(|c| c.same_as(comb))
+                    && !shared_by_default(other)
+            })
+            .map(|(other, _)| other.clone())
+            .collect()
+    }
+}
+
+impl Serialize for Bindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+/// Deserializes to None what this version doesn't know, e.g. a removed action
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Known<T> {
+    Yes(T),
+    No(IgnoredAny),
+}
+
+impl<T> Known<T> {
+    fn ok(self) -> Option<T> {
+        match self {
+            Known::Yes(v) => Some(v),
+            Known::No(_) => None,
+        }
+    }
+}
+
+struct BindingsVisitor;
+
+impl<'de> Visitor<'de> for BindingsVisitor {
+    type Value = Bindings;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a list of bindings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bindings, A::Error> {
+        let mut m = BTreeMap::new();
+        while let Some(entry) = seq.next_element::<Known<(InputAction, InputCombinations)>>()? {
+            if let Some((act, combs)) = entry.ok() {
+                m.insert(act, combs);
+            }
+        }
+        Ok(Bindings(m))
+    }
+
+    /// Older config files stored the bindings as a map
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Bindings, A::Error> {
+        let mut m = BTreeMap::new();
+        while let Some((act, combs)) =
+            map.next_entry::<Known<InputAction>, Known<InputCombinations>>()?
+        {
+            if let (Some(act), Some(combs)) = (act.ok(), combs.ok()) {
+                m.insert(act, combs);
+            }
+        }
+        Ok(Bindings(m))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BindingsVisitor)
+    }
+}
+
 impl InputMap {
     pub fn build_input_tree(&mut self, bindings: &mut Bindings) {
         for v in &mut bindings.0.values_mut() {
@@ -172,6 +356,19 @@ impl InputCombination {
         self.0.clear();
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Same inputs, regardless of the order they were pressed in
+    pub fn same_as(&self, other: &InputCombination) -> bool {
+        let mut a = self.0.clone();
+        let mut b = other.0.clone();
+        a.sort();
+        b.sort();
+        a == b
+    }
+
     /// A valid combination is a list of modifiers and a single key/mouse button
     pub fn is_valid(&self) -> bool {
         let mut has_primary = false;
@@ -368,6 +565,11 @@ impl InputTree {
 
 impl Display for InputAction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveBookmark(i) => return write!(f, "Save Camera Bookmark {}", i + 1),
+            RecallBookmark(i) => return write!(f, "Recall Camera Bookmark {}", i + 1),
+            _ => {}
+        }
         write!(
             f,
             "{}",
@@ -377,9 +579,13 @@ impl Display for InputAction {
                 GoForward => "Go Forward",
                 GoBackward => "Go Backward",
                 CameraMove => "Camera Move",
+                CameraGrab => "Camera Grab",
                 CameraRotate => "Camera Rotate",
                 Zoom => "Zoom",
                 Dezoom => "Dezoom",
+                ZoomStreet => "Zoom to Street",
+                ZoomDistrict => "Zoom to District",
+                ZoomCity => "Zoom to City",
                 Rotate => "Rotate",
                 Close => "Close",
                 Select => "Select",
@@ -390,11 +596,141 @@ impl Display for InputAction {
                 DownElevation => "Down Elevation",
                 OpenEconomyMenu => "Economy Menu",
                 PausePlay => "Pause/Play",
+                StepTick => "Step One Tick",
+                SpeedNormal => "Normal Speed",
+                SpeedFast => "Speed x3",
+                SpeedFaster => "Speed x10",
+                SpeedUncapped => "Fastest Speed",
                 OpenChat => "Interact with Chat",
+                OpenConsole => "Open Console",
+                ConsoleOlder => "Console Previous Line",
+                ConsoleNewer => "Console Next Line",
+                ConsoleComplete => "Console Completion",
+                Undo => "Undo",
+                Redo => "Redo",
+                BlueprintRotate => "Rotate Blueprint",
+                BlueprintMirror => "Mirror Blueprint",
+                FlipRoad => "Flip Road Direction",
+                ToggleMinimap => "Toggle Minimap",
+                Screenshot => "Screenshot",
                 SizeUp => "Size Up",
                 SizeDown => "Size Down",
                 OpenDebugMenu => "Debug Menu",
+                SaveBookmark(_) | RecallBookmark(_) => unreachable!(),
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::saveload::{Encoder, JSON};
+
+    fn comb(units: &[UnitInput]) -> InputCombination {
+        InputCombination(units.to_vec())
+    }
+
+    /// Sharing a combination is only fine for actions that are never active at the same time
+    #[test]
+    fn default_bindings_have_no_duplicate_combos() {
+        let intended: &[&[InputAction]] = &[
+            &[GoForward, ConsoleOlder],
+            &[GoBackward, ConsoleNewer],
+            &[Rotate, SizeUp, UpElevation],
+            &[Rotate, SizeDown, DownElevation],
+        ];
+
+        let mut users: BTreeMap<Vec<UnitInput>, Vec<InputAction>> = BTreeMap::new();
+        for (act, combs) in &default_bindings().0 {
+            for c in &combs.0 {
+                let mut units = c.0.clone();
+                units.sort();
+                let acts = users.entry(units).or_default();
+                assert!(!acts.contains(act), "{act:?} is bound twice to {c}");
+                acts.push(act.clone());
+            }
+        }
+
+        for (units, mut acts) in users {
+            if acts.len() < 2 {
+                continue;
+            }
+            acts.sort();
+            let allowed = intended.iter().any(|group| {
+                let mut group = group.to_vec();
+                group.sort();
+                group == acts
+            });
+            assert!(allowed, "{acts:?} share {:?}", comb(&units));
+        }
+    }
+
+    #[test]
+    fn conflicts_ignore_default_shares() {
+        let mut b = Bindings::default();
+        let ctrl_wheel = comb(&[Key(K::Control), WheelUp]);
+        assert!(b.conflicts(&SizeUp, &ctrl_wheel).is_empty());
+
+        let f3 = comb(&[Key(K::F3)]);
+        assert_eq!(b.conflicts(&OpenDebugMenu, &f3), vec![RecallBookmark(2)]);
+
+        b.0.insert(Zoom, InputCombinations(vec![comb(&[Key(K::c("H"))])]));
+        assert_eq!(
+            b.conflicts(&HideInterface, &comb(&[Key(K::c("H"))])),
+            vec![Zoom]
+        );
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        let mut b = Bindings::default();
+        b.0.insert(
+            SaveBookmark(3),
+            InputCombinations(vec![comb(&[Key(K::Shift), Key(K::c("B"))])]),
+        );
+
+        let data = JSON::encode(&b).unwrap();
+        let back: Bindings = JSON::decode(&data).unwrap();
+        assert_eq!(back, b);
+    }
+
+    #[test]
+    fn legacy_map_bindings_are_loaded() {
+        let data = br#"{
+            "Zoom": [[{"Key": "F5"}]],
+            "PausePlay": [[{"Key": "Control"}, {"Key": "Space"}]]
+        }"#;
+        let mut b: Bindings = JSON::decode(data).unwrap();
+        assert_eq!(b.0.len(), 2);
+        assert_eq!(b.0[&Zoom], InputCombinations(vec![comb(&[Key(K::F5)])]));
+
+        b.merge_defaults();
+        assert_eq!(b.0.len(), default_bindings().0.len());
+        assert_eq!(b.0[&Zoom], InputCombinations(vec![comb(&[Key(K::F5)])]));
+        assert_eq!(b.0[&Dezoom], default_bindings().0[&Dezoom]);
+    }
+
+    #[test]
+    fn unknown_actions_are_skipped() {
+        let list = br#"[
+            ["RemovedAction", [[{"Key": "F5"}]]],
+            [{"SaveBookmark": 1}, [[{"Key": "F6"}]]],
+            ["Zoom", [[{"UnknownInput": 3}]]]
+        ]"#;
+        let b: Bindings = JSON::decode(list).unwrap();
+        assert_eq!(b.0.len(), 1);
+        assert_eq!(
+            b.0[&SaveBookmark(1)],
+            InputCombinations(vec![comb(&[Key(K::F6)])])
+        );
+
+        let map = br#"{
+            "RemovedAction": [[{"Key": "F5"}]],
+            "Zoom": [[{"Key": "F6"}]]
+        }"#;
+        let b: Bindings = JSON::decode(map).unwrap();
+        assert_eq!(b.0.len(), 1);
+        assert_eq!(b.0[&Zoom], InputCombinations(vec![comb(&[Key(K::F6)])]));
+    }
+}
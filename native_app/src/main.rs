@@ -20,6 +20,7 @@ mod inputmap;
 mod network;
 mod newgui;
 mod rendering;
+mod startup_error;
 
 fn main() {
     #[cfg(feature = "profile")]
@@ -27,7 +28,10 @@ fn main() {
     profiling::register_thread!("Main Thread");
 
     engine::framework::init();
-    init::init();
+    if let Err(e) = init::init() {
+        startup_error::start(e.problems());
+        return;
+    }
 
     engine::framework::start::<game_loop::State>();
 }
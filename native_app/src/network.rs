@@ -65,6 +65,8 @@ fn handle_singleplayer(state: &mut State) {
     };
 
     let mut commands_once = Some(commands.clone());
+    // many ticks can run in one frame, each of them runs the whole schedule so the systems
+    // sampling on tick boundaries like the hourly economy history don't miss any
     step.prepare_frame(timewarp);
     while step.tick() || (has_commands && commands_once.is_some()) {
         let t = sim.tick(sched, commands_once.take().unwrap_or_default().as_ref());
@@ -85,6 +87,7 @@ fn handle_replay(
 ) -> bool {
     if let Some(new_sim) = slstate.please_load_sim.take() {
         *sim = new_sim;
+        slstate.editing_map = std::mem::take(&mut slstate.please_edit_map);
         slstate.render_reset = true;
         log::info!("replaced sim");
     }
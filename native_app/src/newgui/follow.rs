@@ -1,17 +1,38 @@
 use crate::game_loop::State;
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::Toasts;
 use simulation::AnyEntity;
 
+/// Maximum time between two clicks on the same entity link to start following it
+const DOUBLE_CLICK_DELAY: f32 = 0.4;
+
 /// FollowEntity is a component that tells the camera to follow an entity
 #[derive(Default)]
-pub struct FollowEntity(pub Option<AnyEntity>);
+pub struct FollowEntity {
+    pub target: Option<AnyEntity>,
+    /// Last entity link clicked and when, to detect double clicks
+    last_click: Option<(AnyEntity, f32)>,
+}
 
 impl FollowEntity {
-    pub fn update_camera(state: &mut State) {
+    /// Registers a click on an entity link, returns true if it was a double click
+    pub fn link_clicked(&mut self, e: AnyEntity, now: f32) -> bool {
+        if let Some((last, t)) = self.last_click.take() {
+            if last == e && now - t < DOUBLE_CLICK_DELAY {
+                return true;
+            }
+        }
+        self.last_click = Some((e, now));
+        false
+    }
+
+    pub fn update_camera(state: &mut State, delta: f32) {
         let just = &state.uiw.read::<InputMap>().just_act;
         if [
             InputAction::Close,
             InputAction::CameraMove,
+            InputAction::CameraGrab,
             InputAction::GoForward,
             InputAction::GoBackward,
             InputAction::GoLeft,
@@ -20,13 +41,25 @@ impl FollowEntity {
         .iter()
         .any(|x| just.contains(x))
         {
-            state.uiw.write::<FollowEntity>().0.take();
+            state.uiw.write::<FollowEntity>().target.take();
         }
 
-        if let Some(e) = state.uiw.read::<FollowEntity>().0 {
-            if let Some(pos) = state.sim.read().unwrap().pos_any(e) {
-                state.uiw.camera_mut().follow(pos);
-            }
-        }
+        let Some(e) = state.uiw.read::<FollowEntity>().target else {
+            return;
+        };
+
+        let Some(pos) = state.sim.read().unwrap().pos_any(e) else {
+            // the entity despawned
+            state.uiw.write::<FollowEntity>().target = None;
+            let now = state.uiw.time_always();
+            state
+                .uiw
+                .write::<Toasts>()
+                .push(format!("Stopped following {}: it no longer exists", e), now);
+            return;
+        };
+
+        let half_life = state.uiw.read::<Settings>().camera_follow_half_life;
+        state.uiw.camera_mut().follow(pos, delta, half_life);
     }
 }
@@ -0,0 +1,52 @@
+use std::sync::RwLock;
+
+use prototypes::SourcesWatcher;
+use simulation::reload::reload_prototypes;
+use simulation::Simulation;
+
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::Toasts;
+use crate::uiworld::UiWorld;
+
+/// Seconds between two looks at the prototype files
+const CHECK_EVERY: f32 = 1.0;
+
+/// Watches the prototype files to reload them when one changes
+#[derive(Default)]
+pub struct PrototypesWatcher {
+    last_check: f32,
+    sources: SourcesWatcher,
+}
+
+/// Reloads the prototypes when their files change, in debug builds or if enabled in the settings.
+/// The mods stay the ones loaded at the start. Invalid files are reported in a toast and the
+/// current prototypes are kept.
+pub fn hot_reload_prototypes(uiw: &UiWorld, sim: &RwLock<Simulation>) {
+    if !cfg!(debug_assertions) && !uiw.read::<Settings>().hot_reload_prototypes {
+        return;
+    }
+
+    let now = uiw.time_always();
+    let mut watcher = uiw.write::<PrototypesWatcher>();
+    if now - watcher.last_check < CHECK_EVERY {
+        return;
+    }
+    watcher.last_check = now;
+    if !watcher.sources.changed("./") {
+        return;
+    }
+    drop(watcher);
+
+    let result = prototypes::reload_candidate("./")
+        .map_err(|e| e.to_string())
+        .and_then(|candidate| reload_prototypes(&mut sim.write().unwrap(), candidate));
+
+    let message = match result {
+        Ok(()) => "Prototypes reloaded".to_string(),
+        Err(e) => {
+            log::error!("failed to reload the prototypes: {}", e);
+            format!("Failed to reload the prototypes: {}", e)
+        }
+    };
+    uiw.write::<Toasts>().push(message, now);
+}
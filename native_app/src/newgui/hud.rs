@@ -1,25 +1,44 @@
 use std::time::Instant;
 
+use fontdue::layout::GlyphRasterConfig;
 use goryak::{image_button, minrow, on_secondary_container, textc};
 use ordered_float::OrderedFloat;
 use prototypes::ItemID;
-use yakui::{reflow, Alignment, Color, Dim2, Pivot, Vec2};
+use yakui::font::{FontName, Fonts};
+use yakui::paint::{PaintMesh, Pipeline, Vertex};
+use yakui::text_renderer::TextGlobalState;
+use yakui::widget::PaintContext;
+use yakui::widgets::{Pad, Text};
+use yakui::{reflow, Alignment, Color, Dim2, Pivot, Rect, TextureId, Vec2};
 
-use simulation::map_dynamic::ElectricityFlow;
+use common::saveload::CheckedCompressedBincode;
+use engine::Tesselator;
+use simulation::map::{BuildingID, Map};
+use simulation::map_dynamic::{ElectricityFlow, Fires, WaterFlow};
+use simulation::weather::{Weather, WeatherKind};
 use simulation::Simulation;
 
 use crate::newgui::hud::menu::menu_bar;
 use crate::newgui::hud::time_controls::time_controls;
 use crate::newgui::hud::toolbox::new_toolbox;
+use crate::newgui::hud::toolbox::roadbuild::roadbuild_readout;
 use crate::newgui::inspect::new_inspector;
 use crate::newgui::textures::UiTextures;
 use crate::newgui::windows::settings::Settings;
 use crate::newgui::GuiState;
+use crate::rendering::map_labels::{
+    district_labels_alpha, road_labels_alpha, MapLabels, DISTRICT_FONT_SIZE, ROAD_FONT_SIZE,
+};
+use crate::rendering::weather;
 use crate::uiworld::{SaveLoadState, UiWorld};
 
 pub mod chat;
+pub mod console;
 pub mod keybinds;
+pub mod map_editor;
 mod menu;
+pub mod minimap;
+pub mod notifications;
 mod time_controls;
 pub mod toolbox;
 pub mod windows;
@@ -34,74 +53,310 @@ pub fn render_newgui(uiworld: &UiWorld, sim: &Simulation) {
     }
 
     yakui::column(|| {
-        power_errors(uiworld, sim);
+        map_labels(uiworld, sim);
+        precipitation(uiworld, sim);
+        utility_errors(uiworld, sim);
+        roadbuild_readout(uiworld);
         new_toolbox(uiworld, sim);
         menu_bar(uiworld, sim);
         chat::chat(uiworld, sim);
+        console::console(uiworld, sim);
         new_inspector(uiworld, sim);
         uiworld.write::<GuiState>().windows.render(uiworld, sim);
         time_controls(uiworld, sim);
-        keybinds::keybind_modal(uiworld, sim)
+        minimap::minimap(uiworld, sim);
+        notifications::music_toast(uiworld);
+        keybinds::keybind_modal(uiworld, sim);
+        goryak::render_tooltip();
     });
     //goryak::debug_layout();
 }
 
+/// Saves to the oldest autosave slot at the interval set in the settings, maps being edited are
+/// saved by hand
 fn auto_save(uiworld: &UiWorld) {
-    let every = uiworld.read::<Settings>().auto_save_every.into();
+    if uiworld.read::<SaveLoadState>().editing_map {
+        return;
+    }
+    let settings = uiworld.read::<Settings>().clone();
+    let every = settings.auto_save_every.into();
     let mut gui = uiworld.write::<GuiState>();
     if let Some(every) = every {
         if gui.last_save.elapsed() > every {
-            uiworld.write::<SaveLoadState>().please_save = true;
+            let mut slstate = uiworld.write::<SaveLoadState>();
+            // the pending save keeps its name, the autosave is done once it is written
+            if slstate.please_save {
+                return;
+            }
+            slstate.please_save = true;
+            slstate.save_name = Some(
+                settings
+                    .auto_save_slots()
+                    .next::<CheckedCompressedBincode>(),
+            );
+            drop(slstate);
             uiworld.save_to_disk();
             gui.last_save = Instant::now();
         }
     }
 }
 
-fn power_errors(uiworld: &UiWorld, sim: &Simulation) {
-    profiling::scope!("hud::power_errors");
-    let map = sim.map();
-    let flow = sim.read::<ElectricityFlow>();
+/// Rain streaks or snowflakes falling in front of the camera, under the rest of the GUI
+fn precipitation(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::precipitation");
+    let kind = sim.read::<Weather>().current();
+    if !kind.has_precipitation() {
+        return;
+    }
+    let segments = weather::precipitation(&uiworld.camera().camera, kind, uiworld.time_always());
 
-    let no_power_img = uiworld.read::<UiTextures>().get("no_power");
+    yakui::canvas(move |ctx| {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut tess = Tesselator::new(&mut vertices, &mut indices, None, 15.0);
+        if kind == WeatherKind::Snow {
+            tess.set_color([1.0, 1.0, 1.0, 0.8]);
+            for (p, _) in &segments {
+                tess.draw_circle(p.z(0.0), 1.5);
+            }
+        } else {
+            tess.set_color([0.75, 0.8, 0.9, 0.35]);
+            for (p, tail) in &segments {
+                tess.draw_stroke(p.z(0.0), tail.z(0.0), 1.0);
+            }
+        }
 
-    for network in map.electricity.networks() {
-        if !flow.blackout(network.id) {
+        ctx.paint.add_mesh(PaintMesh::new(
+            vertices
+                .into_iter()
+                .map(|v| yakui::paint::Vertex::new([v.position[0], v.position[1]], v.uv, v.color)),
+            indices.into_iter().map(|x| x as _),
+        ));
+    });
+}
+
+/// Road names along the roads when zoomed in, district names over their area when zoomed out,
+/// under the rest of the GUI
+fn map_labels(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::map_labels");
+    let camera = uiworld.camera().camera.clone();
+    let road_alpha = road_labels_alpha(camera.dist);
+    let district_alpha = district_labels_alpha(camera.dist);
+    if road_alpha <= 0.0 && district_alpha <= 0.0 {
+        return;
+    }
+
+    let mut labels = uiworld.write::<MapLabels>();
+    labels.update(&sim.map(), &camera);
+
+    if road_alpha > 0.0 {
+        let glyphs: Vec<(char, geom::Vec2, geom::Vec2)> = labels
+            .roads
+            .iter()
+            .flat_map(|l| l.name.chars().zip(&l.glyphs))
+            .map(|(c, &(pos, dir))| (c, pos, dir))
+            .collect();
+        yakui::canvas(move |ctx| {
+            for (offset, color) in label_colors(road_alpha) {
+                paint_glyphs(ctx, ROAD_FONT_SIZE, &glyphs, offset, color);
+            }
+        });
+    }
+
+    if district_alpha > 0.0 {
+        for d in &labels.districts {
+            if let Some(pos) = d.pos {
+                map_label(pos, d.name.clone(), DISTRICT_FONT_SIZE, district_alpha);
+            }
+        }
+    }
+}
+
+/// A black shadow under the white text, to stay readable over the map
+fn label_colors(alpha: f32) -> [(f32, Color); 2] {
+    [
+        (1.5, Color::BLACK.with_alpha(alpha * 0.6)),
+        (0.0, Color::WHITE.with_alpha(alpha)),
+    ]
+}
+
+/// Text centered on the screen position
+fn map_label(pos: geom::Vec2, text: String, size: f32, alpha: f32) {
+    for (offset, color) in label_colors(alpha) {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(pos.x + offset, pos.y + offset),
+            || {
+                let mut t = Text::new(size, text.clone().into());
+                t.style.color = color;
+                t.padding = Pad::all(0.0);
+                t.show();
+            },
+        );
+    }
+}
+
+/// Glyphs of the monospace font centered on their position and turned along their direction,
+/// which the text widgets cannot do. They are painted from the glyph cache of the text widgets.
+fn paint_glyphs(
+    ctx: &mut PaintContext<'_>,
+    size: f32,
+    glyphs: &[(char, geom::Vec2, geom::Vec2)],
+    offset: f32,
+    color: Color,
+) {
+    let fonts = ctx.dom.get_global_or_init(Fonts::default);
+    let Some(font) = fonts.get(&FontName::new("monospace")) else {
+        return;
+    };
+    let text_global = ctx.dom.get_global_or_init(TextGlobalState::new);
+    let mut glyph_cache = text_global.glyph_cache.borrow_mut();
+    glyph_cache.ensure_texture(ctx.paint);
+    let Some(texture) = glyph_cache.texture else {
+        return;
+    };
+
+    let scale = ctx.layout.scale_factor();
+    let px = size * scale;
+    // glyphs are centered vertically on the middle of the line
+    let baseline = font
+        .horizontal_line_metrics(px)
+        .map_or(0.0, |m| (m.ascent + m.descent) * 0.5)
+        / scale;
+    let atlas_size = glyph_cache.texture_size.as_vec2();
+    let color = color.to_linear();
+
+    let mut vertices = Vec::with_capacity(glyphs.len() * 4);
+    let mut indices: Vec<u32> = Vec::with_capacity(glyphs.len() * 6);
+    for &(c, pos, dir) in glyphs {
+        let glyph_index = font.lookup_glyph_index(c);
+        let metrics = font.metrics_indexed(glyph_index, px);
+        if metrics.width == 0 || metrics.height == 0 {
             continue;
         }
+        let key = GlyphRasterConfig {
+            glyph_index,
+            px,
+            font_hash: font.file_hash(),
+        };
+        let uv = glyph_cache
+            .get_or_insert(ctx.paint, &font, key)
+            .as_rect()
+            .div_vec2(atlas_size);
+        let (uv0, uv1) = (uv.pos(), uv.max());
 
-        let mut buildings_with_issues = Vec::with_capacity(network.buildings.len());
+        let x0 = (metrics.xmin as f32 - metrics.advance_width * 0.5) / scale;
+        let x1 = x0 + metrics.width as f32 / scale;
+        let y1 = baseline - metrics.ymin as f32 / scale;
+        let y0 = y1 - metrics.height as f32 / scale;
 
-        for &building in &network.buildings {
-            let Some(b) = map.get(building) else {
-                continue;
-            };
+        let center = pos + geom::Vec2::splat(offset);
+        let normal = geom::vec2(-dir.y, dir.x);
+        let corner = |x: f32, y: f32| {
+            let p = center + dir * x + normal * y;
+            [p.x, p.y]
+        };
 
-            let center = b.obb.center();
+        let base = vertices.len() as u32;
+        vertices.extend([
+            Vertex::new(corner(x0, y0), [uv0.x, uv0.y], color),
+            Vertex::new(corner(x1, y0), [uv1.x, uv0.y], color),
+            Vertex::new(corner(x1, y1), [uv1.x, uv1.y], color),
+            Vertex::new(corner(x0, y1), [uv0.x, uv1.y], color),
+        ]);
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
 
-            let pos = center
-                .z(b.height + 20.0 + 1.0 * f32::cos(uiworld.time_always() + center.mag() * 0.05));
-            let (screenpos, depth) = uiworld.camera().project(pos);
+    let mut mesh = PaintMesh::new(vertices, indices.into_iter().map(|x| x as _));
+    mesh.texture = Some((texture.into(), Rect::from_pos_size(Vec2::ZERO, Vec2::ONE)));
+    mesh.pipeline = Pipeline::Text;
+    ctx.paint.add_mesh(mesh);
+}
 
-            let size = 10000.0 / depth;
+/// Warning icons above the buildings that lack power or water, or are on fire
+fn utility_errors(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::utility_errors");
+    let map = sim.map();
+    let elec_flow = sim.read::<ElectricityFlow>();
+    let water_flow = sim.read::<WaterFlow>();
 
-            buildings_with_issues.push((screenpos, size));
-        }
+    let textures = uiworld.read::<UiTextures>();
+    let no_power_img = textures.get("no_power");
+    let no_water_img = textures.get("no_water");
 
-        buildings_with_issues.sort_by_key(|x| OrderedFloat(x.1));
-
-        for (screenpos, size) in buildings_with_issues {
-            reflow(
-                Alignment::TOP_LEFT,
-                Pivot::TOP_LEFT,
-                Dim2::pixels(screenpos.x - size * 0.5, screenpos.y - size * 0.5),
-                || {
-                    let mut image = yakui::widgets::Image::new(no_power_img, Vec2::new(size, size));
-                    image.color = Color::WHITE.with_alpha(0.7);
-                    image.show();
-                },
+    for network in map.electricity.networks() {
+        if elec_flow.brownout(network.id) {
+            overlay_building_icons(
+                uiworld,
+                &map,
+                network.buildings.iter().filter(|&&b| elec_flow.is_shed(b)),
+                no_power_img,
+                Color::WHITE,
+                20.0,
             );
         }
+        if water_flow.shortage(network.id) {
+            overlay_building_icons(
+                uiworld,
+                &map,
+                network.buildings.iter().filter(|&&b| water_flow.is_dry(b)),
+                no_water_img,
+                Color::WHITE,
+                35.0,
+            );
+        }
+    }
+
+    let fires = sim.read::<Fires>();
+    // without its own icon, fire uses an orange power icon
+    let (fire_img, fire_col) = match textures.try_get("fire") {
+        Some(img) => (img, Color::WHITE),
+        None => (no_power_img, Color::rgb(255, 120, 30)),
+    };
+    overlay_building_icons(uiworld, &map, fires.burning(), fire_img, fire_col, 50.0);
+}
+
+/// Draws an icon floating above each building, the closest ones on top
+fn overlay_building_icons<'a>(
+    uiworld: &UiWorld,
+    map: &Map,
+    buildings: impl Iterator<Item = &'a BuildingID>,
+    img: TextureId,
+    color: Color,
+    height: f32,
+) {
+    let mut icons = Vec::new();
+
+    for &building in buildings {
+        let Some(b) = map.get(building) else {
+            continue;
+        };
+
+        let center = b.obb.center();
+
+        let pos = center
+            .z(b.height + height + 1.0 * f32::cos(uiworld.time_always() + center.mag() * 0.05));
+        let (screenpos, depth) = uiworld.camera().project(pos);
+
+        let size = 10000.0 / depth;
+
+        icons.push((screenpos, size));
+    }
+
+    icons.sort_by_key(|x| OrderedFloat(x.1));
+
+    for (screenpos, size) in icons {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::TOP_LEFT,
+            Dim2::pixels(screenpos.x - size * 0.5, screenpos.y - size * 0.5),
+            || {
+                let mut image = yakui::widgets::Image::new(img, Vec2::new(size, size));
+                image.color = color.with_alpha(0.7);
+                image.show();
+            },
+        );
     }
 }
 
@@ -0,0 +1,161 @@
+//! Commands of the console changing the game, using them marks the save
+
+use common::console::{Args, ConsoleResult};
+use geom::vec2;
+use prototypes::{GameTime, Money, Tick, TICKS_PER_HOUR, TICKS_PER_MINUTE, TICKS_PER_SECOND};
+use simulation::economy::Government;
+use simulation::map::{BuildingID, BuildingKind};
+use simulation::map_dynamic::ElectricityFlow;
+use simulation::world_command::WorldCommand;
+use slotmapd::Key;
+
+use super::{ConsoleCtx, ConsoleRegistry};
+use crate::newgui::InspectedBuilding;
+
+pub fn register(reg: &mut ConsoleRegistry<'_>) {
+    reg.register(
+        "money",
+        "",
+        "shows the money of the government",
+        |_| Ok(()),
+        |ctx, ()| {
+            let money = ctx.sim.read::<Government>().money;
+            Ok(money.to_string())
+        },
+    );
+    reg.register(
+        "money add",
+        "<amount>",
+        "gives money to the government (like 500, 1.5M or 20c), takes it if negative",
+        |args| args.parse::<Money>("amount"),
+        |ctx, amount| {
+            ctx.push(WorldCommand::AddMoney(amount));
+            Ok(format!("added {}", amount))
+        },
+    );
+    reg.register(
+        "spawn human",
+        "[building]",
+        "spawns a household in the house, the inspected one by default",
+        |args| Ok(args.opt_str().map(str::to_string)),
+        spawn_human,
+    );
+    reg.register(
+        "time set",
+        "<hh:mm>",
+        "skips to the next time of the day",
+        parse_daytime,
+        |ctx, (hour, minute)| {
+            let offset = 8 * TICKS_PER_HOUR;
+            let day = GameTime::DAY as u64 * TICKS_PER_SECOND;
+
+            // tick 0 is at 8:00 on the first day
+            let now = ctx.sim.read::<GameTime>().tick.0 + offset;
+            let mut target = now - now % day + hour * TICKS_PER_HOUR + minute * TICKS_PER_MINUTE;
+            if target <= now {
+                target += day;
+            }
+            ctx.push(WorldCommand::SetGameTime(GameTime::new(Tick(
+                target - offset,
+            ))));
+            Ok(format!("time set to {:02}:{:02}", hour, minute))
+        },
+    );
+    reg.register(
+        "blackout list",
+        "",
+        "lists the electricity networks",
+        |_| Ok(()),
+        |ctx, ()| {
+            let map = ctx.sim.map();
+            let flow = ctx.sim.read::<ElectricityFlow>();
+            let lines: Vec<String> = map
+                .electricity
+                .networks()
+                .enumerate()
+                .map(|(i, network)| {
+                    format!(
+                        "{}: {} buildings{}",
+                        i,
+                        network.buildings.len(),
+                        if flow.blackout(network.id) {
+                            ", blackout"
+                        } else {
+                            ""
+                        }
+                    )
+                })
+                .collect();
+            if lines.is_empty() {
+                return Ok("no electricity network".to_string());
+            }
+            Ok(lines.join("\n"))
+        },
+    );
+    reg.register(
+        "blackout toggle",
+        "<network>",
+        "forces a blackout of the network listed by blackout list, or ends it",
+        |args| args.parse::<usize>("network"),
+        |ctx, i| {
+            let Some(network) = ctx.sim.map().electricity.networks().nth(i).map(|n| n.id) else {
+                return Err(format!("no network {}, see blackout list", i));
+            };
+            ctx.push(WorldCommand::ToggleBlackout(network));
+            Ok(format!("toggled the blackout of network {}", i))
+        },
+    );
+    reg.register(
+        "teleport camera",
+        "<x> <y>",
+        "moves the camera above the position",
+        |args| Ok(vec2(args.parse("x")?, args.parse("y")?)),
+        |ctx, pos| {
+            let Some(height) = ctx.sim.map().environment.height(pos) else {
+                return Err("the position is outside of the map".to_string());
+            };
+            let mut cam = ctx.uiw.camera_mut();
+            cam.camera.pos = pos.z(height);
+            cam.targetpos = pos.z(height);
+            Ok(format!("camera moved to {} {}", pos.x, pos.y))
+        },
+    );
+}
+
+fn parse_daytime(args: &mut Args) -> Result<(u64, u64), String> {
+    let time = args.str("hh:mm")?;
+    let invalid = || format!("invalid <hh:mm>: {}", time);
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let hour: u64 = hour.parse().map_err(|_| invalid())?;
+    let minute: u64 = minute.parse().map_err(|_| invalid())?;
+    if hour >= 24 || minute >= 60 {
+        return Err(invalid());
+    }
+    Ok((hour, minute))
+}
+
+/// The building is given as shown by the inspector, like 12v1, or by its index only
+fn spawn_human(ctx: &mut ConsoleCtx<'_>, building: Option<String>) -> ConsoleResult {
+    let house = match building {
+        Some(name) => find_building(ctx, &name).ok_or_else(|| format!("no building {}", name))?,
+        None => ctx
+            .uiw
+            .read::<InspectedBuilding>()
+            .e
+            .ok_or("no building given nor inspected")?,
+    };
+    let map = ctx.sim.map();
+    if map.buildings().get(house).map(|b| b.kind) != Some(BuildingKind::House) {
+        return Err("the building is not a house".to_string());
+    }
+    drop(map);
+    ctx.push(WorldCommand::SpawnHuman(house));
+    Ok(format!("spawning a household in {:?}", house.data()))
+}
+
+fn find_building(ctx: &ConsoleCtx<'_>, name: &str) -> Option<BuildingID> {
+    ctx.sim.map().buildings().keys().find(|id| {
+        let shown = format!("{:?}", id.data());
+        shown == name || shown.split('v').next() == Some(name)
+    })
+}
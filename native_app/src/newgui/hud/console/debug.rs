@@ -0,0 +1,26 @@
+//! Commands of the console for debugging the rendering, they don't change the game
+
+use super::ConsoleRegistry;
+use crate::rendering::{CrowdStressTest, MAX_STRESS_CROWD};
+
+pub fn register(reg: &mut ConsoleRegistry<'_>) {
+    reg.register(
+        "stress crowd",
+        "<count>",
+        "draws fake pedestrians around the camera to measure the rendering, 0 to stop",
+        |args| args.parse::<u32>("count"),
+        |ctx, n| {
+            if n > MAX_STRESS_CROWD {
+                return Err(format!("at most {} fake pedestrians", MAX_STRESS_CROWD));
+            }
+            let center = ctx.uiw.camera().camera.pos;
+            let mut stress = ctx.uiw.write::<CrowdStressTest>();
+            stress.n = n;
+            stress.center = center;
+            if n == 0 {
+                return Ok("stopped the crowd stress test".to_string());
+            }
+            Ok(format!("drawing {} fake pedestrians around the camera", n))
+        },
+    );
+}
@@ -0,0 +1,201 @@
+use yakui::{constrained, reflow, Alignment, Color, Constraints, Dim2, Pivot, Vec2};
+
+use common::console::{CommandRegistry, ConsoleHistory, ConsoleResult};
+use goryak::{
+    blur_bg, error, mincolumn, on_secondary_container, outline, padxy, secondary_container,
+    text_edit, textc, VertScroll, VertScrollSize,
+};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::uiworld::UiWorld;
+
+mod cheats;
+mod debug;
+
+pub type ConsoleRegistry<'a> = CommandRegistry<ConsoleCtx<'a>>;
+
+/// What the console commands can act on
+pub struct ConsoleCtx<'a> {
+    pub uiw: &'a UiWorld,
+    pub sim: &'a Simulation,
+    /// Sent once the command succeeded, after marking the save as using cheats
+    commands: Vec<WorldCommand>,
+}
+
+impl ConsoleCtx<'_> {
+    /// Queues a command changing the world, which makes the command a cheat
+    pub fn push(&mut self, command: WorldCommand) {
+        self.commands.push(command);
+    }
+}
+
+/// Functions registering the commands of the console.
+/// The registry is rebuilt from them on each use, push yours at init to add commands.
+pub struct ConsoleCommands(pub Vec<fn(&mut ConsoleRegistry<'_>)>);
+
+impl Default for ConsoleCommands {
+    fn default() -> Self {
+        Self(vec![cheats::register, debug::register])
+    }
+}
+
+impl ConsoleCommands {
+    pub fn registry<'a>(&self) -> ConsoleRegistry<'a> {
+        let mut registry = CommandRegistry::default();
+        for register in &self.0 {
+            register(&mut registry);
+        }
+        registry
+    }
+}
+
+#[derive(Copy, Clone)]
+enum LineKind {
+    Input,
+    Output,
+    Error,
+}
+
+#[derive(Default)]
+pub struct GUIConsoleState {
+    opened: bool,
+    input: String,
+    scrollback: Vec<(LineKind, String)>,
+    history: ConsoleHistory,
+}
+
+impl GUIConsoleState {
+    const MAX_LINES: usize = 200;
+
+    pub fn is_opened(&self) -> bool {
+        self.opened
+    }
+
+    fn print(&mut self, kind: LineKind, text: &str) {
+        self.scrollback
+            .extend(text.lines().map(|line| (kind, line.to_string())));
+        let extra = self.scrollback.len().saturating_sub(Self::MAX_LINES);
+        self.scrollback.drain(..extra);
+    }
+}
+
+/// Developer console toggled with the backquote, runs the commands of [`ConsoleCommands`]
+pub fn console(uiw: &UiWorld, sim: &Simulation) {
+    let mut state = uiw.write::<GUIConsoleState>();
+    let inp = uiw.read::<InputMap>();
+
+    if inp.just_act.contains(&InputAction::OpenConsole) {
+        state.opened = !state.opened;
+    }
+    if inp.just_act.contains(&InputAction::Close) {
+        state.opened = false;
+    }
+    if !state.opened {
+        return;
+    }
+
+    if inp.just_act.contains(&InputAction::ConsoleOlder) {
+        if let Some(line) = state.history.older() {
+            state.input = line.to_string();
+        }
+    }
+    if inp.just_act.contains(&InputAction::ConsoleNewer) {
+        if let Some(line) = state.history.newer() {
+            state.input = line.to_string();
+        }
+    }
+    let complete = inp.just_act.contains(&InputAction::ConsoleComplete);
+    drop(inp);
+
+    // the key opening the console is typed in the text box
+    state.input.retain(|c| c != '`' && c != '\t');
+
+    if complete {
+        let completion = uiw
+            .read::<ConsoleCommands>()
+            .registry()
+            .complete(&state.input);
+        if completion.candidates.len() > 1 {
+            state.print(LineKind::Output, &completion.candidates.join("  "));
+        }
+        state.input = completion.input;
+    }
+
+    let mut submitted = None;
+    reflow(
+        Alignment::TOP_LEFT,
+        Pivot::TOP_LEFT,
+        Dim2::pixels(0.0, 40.0),
+        || {
+            blur_bg(secondary_container().with_alpha(0.8), 0.0, || {
+                mincolumn(0.0, || {
+                    VertScroll {
+                        size: VertScrollSize::Exact(300.0),
+                        align_bot: true,
+                    }
+                    .show(|| {
+                        constrained(
+                            Constraints {
+                                min: Vec2::new(600.0, 0.0),
+                                max: Vec2::new(600.0, f32::INFINITY),
+                            },
+                            || {
+                                padxy(8.0, 8.0, || {
+                                    mincolumn(2.0, || {
+                                        for (kind, line) in &state.scrollback {
+                                            let color: Color = match kind {
+                                                LineKind::Input => outline(),
+                                                LineKind::Output => on_secondary_container(),
+                                                LineKind::Error => error(),
+                                            };
+                                            textc(color, line.clone());
+                                        }
+                                    });
+                                });
+                            },
+                        );
+                    });
+                    if text_edit(600.0, &mut state.input, "type help for the commands") {
+                        submitted = Some(std::mem::take(&mut state.input));
+                    }
+                });
+            });
+        },
+    );
+
+    let Some(line) = submitted else {
+        return;
+    };
+    state.history.push(&line);
+    state.print(LineKind::Input, &format!("> {}", line));
+    // the commands might read the console state
+    drop(state);
+
+    let result = run(uiw, sim, &line);
+
+    let mut state = uiw.write::<GUIConsoleState>();
+    match result {
+        Ok(text) => state.print(LineKind::Output, &text),
+        Err(text) => state.print(LineKind::Error, &text),
+    }
+}
+
+/// Runs the line, the world commands it queued are sent only if it succeeded
+fn run(uiw: &UiWorld, sim: &Simulation, line: &str) -> ConsoleResult {
+    let registry = uiw.read::<ConsoleCommands>().registry();
+    let mut ctx = ConsoleCtx {
+        uiw,
+        sim,
+        commands: vec![],
+    };
+    let text = registry.execute(&mut ctx, line)?;
+
+    if !ctx.commands.is_empty() {
+        let mut commands = uiw.commands();
+        commands.push(WorldCommand::MarkCheatsUsed);
+        commands.extend(ctx.commands);
+    }
+    Ok(text)
+}
@@ -1,11 +1,14 @@
 use yakui::widgets::Layer;
 use yakui::{center, reflow, Alignment, Dim2, Pivot};
 
-use engine::InputContext;
+use common::saveload::Encoder;
+use engine::{InputContext, Key};
 use goryak::{blur_bg, constrained_viewport, mincolumn, on_secondary, primary, textc, titlec};
 use simulation::Simulation;
 
-use crate::inputmap::{Bindings, InputAction, InputCombination, InputMap, UnitInput};
+use crate::inputmap::{
+    Bindings, InputAction, InputCombination, InputMap, UnitInput, BINDINGS_SAVE_NAME,
+};
 use crate::uiworld::UiWorld;
 
 #[derive(Default)]
@@ -17,6 +20,20 @@ pub struct KeybindStateInner {
     pub to_bind_to: InputAction,
     pub bind_index: usize,
     pub cur: InputCombination,
+    /// Inputs are only recorded once everything held when binding started is released,
+    /// so that the click on the binding button is not bound
+    pub armed: bool,
+}
+
+impl KeybindStateInner {
+    pub fn new(to_bind_to: InputAction, bind_index: usize) -> Self {
+        Self {
+            to_bind_to,
+            bind_index,
+            cur: Default::default(),
+            armed: false,
+        }
+    }
 }
 
 pub fn keybind_modal(uiw: &UiWorld, _: &Simulation) {
@@ -38,7 +55,11 @@ pub fn keybind_modal(uiw: &UiWorld, _: &Simulation) {
                         center(|| {
                             mincolumn(10.0, || {
                                 titlec(on_secondary(), format!("{}", state.to_bind_to));
-                                textc(on_secondary(), "Press key/mouse to bind to action");
+                                textc(on_secondary(), "Press key/mouse/scroll to bind to action");
+                                textc(
+                                    on_secondary(),
+                                    "Modifiers alone are bound when released, Escape cancels",
+                                );
                             });
                         });
                     });
@@ -59,6 +80,17 @@ impl KeybindState {
             return;
         };
 
+        let held = !inp.keyboard.pressed.is_empty() || !inp.mouse.pressed.is_empty();
+        if !state.armed {
+            state.armed = !held;
+            return;
+        }
+
+        if inp.keyboard.pressed.contains(&Key::Escape) {
+            self.enabled = None;
+            return;
+        }
+
         for key in &inp.keyboard.pressed {
             state.cur.push_unique(UnitInput::Key(key.clone()));
         }
@@ -72,11 +104,12 @@ impl KeybindState {
             state.cur.push_unique(UnitInput::WheelDown);
         }
 
-        if state.cur.is_modifiers_only() {
+        // modifiers alone are bound once they are released without anything else being pressed
+        if state.cur.is_modifiers_only() && (state.cur.is_empty() || held) {
             return;
         }
 
-        if !state.cur.is_valid() {
+        if !state.cur.is_modifiers_only() && !state.cur.is_valid() {
             state.cur.clear();
             return;
         }
@@ -93,6 +126,7 @@ impl KeybindState {
         comb.dedup_by(|a, b| a == b);
 
         input_map.build_input_tree(bindings);
+        common::saveload::JSONPretty::save_silent(&*bindings, BINDINGS_SAVE_NAME);
 
         self.enabled = None;
     }
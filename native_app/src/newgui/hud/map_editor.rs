@@ -0,0 +1,43 @@
+use goryak::{button_primary, error, on_secondary_container, text_edit, textc};
+use simulation::map_files::save_map;
+use simulation::{Simulation, SimulationOptions};
+
+use crate::uiworld::{CurrentSave, SaveLoadState, UiWorld};
+
+/// The map being edited in the map editor
+#[derive(Default)]
+pub struct MapEditorState {
+    /// Name of the map file it is saved to
+    pub name: String,
+    /// Outcome of the last save
+    pub status: Option<Result<String, String>>,
+}
+
+/// Replaces the save button of the menu bar while editing a map
+/// Saves the terrain as a map, or starts a new game on it
+pub fn map_editor_menu(uiw: &UiWorld, sim: &Simulation) {
+    let mut state = uiw.write::<MapEditorState>();
+    let state = &mut *state;
+
+    text_edit(150.0, &mut state.name, "Map name");
+    if button_primary("Save map").show().clicked {
+        let name = state.name.trim();
+        state.status = Some(match save_map(name, &sim.map().environment) {
+            Ok(()) => Ok(format!("Saved {name}")),
+            Err(e) => Err(format!("Failed to save {name}: {e}")),
+        });
+    }
+    if button_primary("Play this map").show().clicked {
+        let sim =
+            Simulation::new_on_map(SimulationOptions::default(), sim.map().environment.clone());
+        uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+        *uiw.write::<CurrentSave>() = CurrentSave::default();
+        state.status = None;
+    }
+
+    match state.status {
+        Some(Ok(ref msg)) => textc(on_secondary_container(), msg.clone()),
+        Some(Err(ref msg)) => textc(error(), msg.clone()),
+        None => {}
+    }
+}
@@ -2,16 +2,20 @@ use std::sync::atomic::Ordering;
 use std::time::Instant;
 
 use yakui::widgets::{List, Pad};
-use yakui::{column, opaque, reflow, spacer, Alignment, CrossAxisAlignment, Dim2, Pivot};
+use yakui::{column, opaque, reflow, spacer, Alignment, Color, CrossAxisAlignment, Dim2, Pivot};
 
 use goryak::{
-    blur_bg, button_primary, button_secondary, constrained_viewport, on_primary_container,
-    on_secondary_container, padxy, secondary_container, textc, Window,
+    blur_bg, button_primary, button_secondary, constrained_viewport, error, icon, icon_button,
+    is_hovered, mincolumn, on_primary_container, on_secondary_container, padxy,
+    secondary_container, textc, tooltip, Window,
 };
 use simulation::economy::Government;
+use simulation::souls::happiness::CityStats;
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::map_editor::map_editor_menu;
+use crate::newgui::screenshot::ScreenshotState;
 use crate::newgui::{ExitState, GuiState};
 use crate::uiworld::{SaveLoadState, UiWorld};
 
@@ -30,12 +34,24 @@ pub fn menu_bar(uiworld: &UiWorld, sim: &Simulation) {
 
                             l.show(|| {
                                 let mut gui = uiworld.write::<GuiState>();
-                                gui.windows.menu();
+                                gui.windows.menu(sim);
                                 save_window(&mut gui, uiworld);
+                                if uiworld.read::<SaveLoadState>().editing_map {
+                                    map_editor_menu(uiworld, sim);
+                                }
+                                tooltip(t!("tooltip-screenshot"), || {
+                                    if icon_button(button_secondary("camera")).show().clicked {
+                                        uiworld.write::<ScreenshotState>().requested = true;
+                                    }
+                                });
                                 textc(
                                     on_primary_container(),
-                                    format!("Money: {}", sim.read::<Government>().money),
+                                    t!(
+                                        "menu-money",
+                                        money = sim.read::<Government>().money.localized()
+                                    ),
                                 );
+                                approval_rating(sim);
                             });
                         });
                     });
@@ -46,11 +62,65 @@ pub fn menu_bar(uiworld: &UiWorld, sim: &Simulation) {
     });
 }
 
+/// Smiley with the mean happiness of the citizens, the main causes of unhappiness on hover
+fn approval_rating(sim: &Simulation) {
+    let stats = sim.read::<CityStats>();
+    let (name, col) = match stats.mean {
+        m if m >= 70.0 => ("face-smile", Color::rgb(80, 200, 80)),
+        m if m >= 40.0 => ("face-meh", on_primary_container()),
+        _ => ("face-frown", error()),
+    };
+
+    let hovered = is_hovered(|| {
+        let mut l = List::row();
+        l.item_spacing = 3.0;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.show(|| {
+            icon(col, name);
+            textc(on_primary_container(), format!("{:.0}%", stats.mean));
+        });
+    })
+    .hovered;
+
+    if !hovered || stats.population == 0 {
+        return;
+    }
+    reflow(
+        Alignment::BOTTOM_LEFT,
+        Pivot::TOP_LEFT,
+        Dim2::pixels(0.0, 5.0),
+        || {
+            blur_bg(secondary_container().with_alpha(0.8), 5.0, || {
+                padxy(8.0, 8.0, || {
+                    mincolumn(3.0, || {
+                        textc(
+                            on_secondary_container(),
+                            format!("Happiness of {} citizens", stats.population),
+                        );
+                        let top = stats.top_losses();
+                        if top.is_empty() {
+                            textc(on_secondary_container(), "Nothing to complain about");
+                        }
+                        for (factor, loss) in top {
+                            textc(
+                                on_secondary_container(),
+                                format!("{}: -{:.0}%", factor.label(), loss),
+                            );
+                        }
+                    });
+                });
+            });
+        },
+    );
+}
+
 fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
     let mut slstate = uiw.write::<SaveLoadState>();
-    if slstate.saving_status.load(Ordering::SeqCst) {
-        textc(on_secondary_container(), "Saving...");
-    } else if button_primary("Save").show().clicked {
+    if slstate.editing_map {
+        // the map editor has its own save
+    } else if slstate.saving_status.load(Ordering::SeqCst) {
+        textc(on_secondary_container(), t!("menu-saving"));
+    } else if button_primary(t!("menu-save")).show().clicked {
         slstate.please_save = true;
         gui.last_save = Instant::now();
         uiw.save_to_disk();
@@ -63,7 +133,7 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
         ExitState::ExitAsk | ExitState::Saving => {
             let mut opened = true;
             Window {
-                title: "Exit Menu".into(),
+                title: t!("exit-title").into(),
                 pad: Pad::all(15.0),
                 radius: 10.0,
                 opened: &mut opened,
@@ -71,22 +141,22 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
             }
             .show(|| {
                 if let ExitState::Saving = *estate {
-                    textc(on_secondary_container(), "Saving...");
+                    textc(on_secondary_container(), t!("menu-saving"));
                     if !slstate.please_save && !slstate.saving_status.load(Ordering::SeqCst) {
                         std::process::exit(0);
                     }
                     return;
                 }
-                if button_secondary("Save and exit").show().clicked {
+                if button_secondary(t!("exit-save-and-exit")).show().clicked {
                     if let ExitState::ExitAsk = *estate {
                         slstate.please_save = true;
                         *estate = ExitState::Saving;
                     }
                 }
-                if button_secondary("Exit without saving").show().clicked {
+                if button_secondary(t!("exit-without-saving")).show().clicked {
                     std::process::exit(0);
                 }
-                if button_secondary("Cancel").show().clicked {
+                if button_secondary(t!("exit-cancel")).show().clicked {
                     *estate = ExitState::NoExit;
                 }
             });
@@ -107,12 +177,12 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
 
     match *estate {
         ExitState::NoExit => {
-            if button_secondary("Exit").show().clicked {
+            if button_secondary(t!("menu-exit")).show().clicked {
                 *estate = ExitState::ExitAsk;
             }
         }
         ExitState::ExitAsk => {
-            if button_secondary("Save and exit").show().clicked {
+            if button_secondary(t!("exit-save-and-exit")).show().clicked {
                 if let ExitState::ExitAsk = *estate {
                     slstate.please_save = true;
                     *estate = ExitState::Saving;
@@ -120,7 +190,7 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
             }
         }
         ExitState::Saving => {
-            textc(on_secondary_container(), "Saving...");
+            textc(on_secondary_container(), t!("menu-saving"));
         }
     }
 }
@@ -0,0 +1,147 @@
+use yakui::paint::{PaintMesh, PaintRect};
+use yakui::{opaque, reflow, Alignment, Color, Dim2, Pivot, Rect, Vec2};
+
+use engine::Tesselator;
+use geom::{vec2, Camera, AABB};
+use goryak::{
+    blur_bg, button_secondary, drag_pos, icon_button, mincolumn, minrow, on_secondary_container,
+    padxy, secondary_container, sized_canvas, textc, tooltip,
+};
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::windows::settings::Settings;
+use crate::rendering::minimap::Minimap;
+use crate::uiworld::UiWorld;
+
+/// Side of the minimap on screen
+const MINIMAP_SIZE: f32 = 200.0;
+
+/// Height taken by the minimap in the bottom right corner, for what is stacked above it
+pub fn minimap_height(uiw: &UiWorld) -> f32 {
+    if uiw.read::<Settings>().show_minimap {
+        MINIMAP_SIZE + 50.0
+    } else {
+        45.0
+    }
+}
+
+/// Top down view of the map in the bottom right corner with the area seen by the camera.
+/// Clicking or dragging on it moves the camera there. It collapses to a button.
+pub fn minimap(uiw: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::minimap");
+    if uiw
+        .read::<InputMap>()
+        .just_act
+        .contains(&InputAction::ToggleMinimap)
+    {
+        uiw.write::<Settings>().show_minimap ^= true;
+    }
+    let shown = uiw.read::<Settings>().show_minimap;
+    let minimap = uiw.read::<Minimap>();
+    let Some(texture) = minimap.texture else {
+        return;
+    };
+    let proj = minimap.proj;
+    drop(minimap);
+
+    let mut toggle = false;
+    let mut clicked = None;
+    reflow(
+        Alignment::BOTTOM_RIGHT,
+        Pivot::BOTTOM_RIGHT,
+        Dim2::pixels(-10.0, -10.0),
+        || {
+            opaque(|| {
+                blur_bg(secondary_container().with_alpha(0.5), 5.0, || {
+                    padxy(5.0, 5.0, || {
+                        if !shown {
+                            tooltip(t!("tooltip-minimap"), || {
+                                toggle = icon_button(button_secondary("map")).show().clicked;
+                            });
+                            return;
+                        }
+                        mincolumn(5.0, || {
+                            minrow(5.0, || {
+                                tooltip(t!("tooltip-minimap"), || {
+                                    toggle = icon_button(button_secondary("minus")).show().clicked;
+                                });
+                                textc(on_secondary_container(), t!("minimap-title"));
+                            });
+
+                            let view: Vec<geom::Vec2> = view_corners(&uiw.camera().camera)
+                                .into_iter()
+                                .map(|p| proj.to_uv(p) * MINIMAP_SIZE)
+                                .collect();
+                            let resp = drag_pos(|| {
+                                sized_canvas(Vec2::splat(MINIMAP_SIZE), Color::BLACK, move |ctx| {
+                                    let rect = ctx.layout.get(ctx.dom.current()).unwrap().rect;
+                                    let mut map = PaintRect::new(rect);
+                                    map.color = Color::WHITE;
+                                    map.texture = Some((texture, Rect::ONE));
+                                    map.add(ctx.paint);
+
+                                    paint_view(ctx.paint, rect.pos(), &view);
+                                });
+                            });
+                            clicked = resp.pos;
+                        });
+                    });
+                });
+            });
+        },
+    );
+
+    if toggle {
+        uiw.write::<Settings>().show_minimap ^= true;
+    }
+    if let Some(pos) = clicked {
+        let uv = (pos / MINIMAP_SIZE).clamp(Vec2::ZERO, Vec2::ONE);
+        let target = proj.from_uv(vec2(uv.x, uv.y));
+        let height = sim.map().environment.height(target).unwrap_or(0.0);
+        uiw.camera_mut().follow(target.z(height), 0.0, 0.0);
+    }
+}
+
+/// Where the corners of the screen meet the ground, limited in distance when looking at the horizon
+fn view_corners(camera: &Camera) -> Vec<geom::Vec2> {
+    let (w, h) = (camera.viewport_w, camera.viewport_h);
+    [vec2(0.0, 0.0), vec2(w, 0.0), vec2(w, h), vec2(0.0, h)]
+        .into_iter()
+        .filter_map(|screen| {
+            let ray = camera.unproj_ray(screen)?;
+            let max_t = camera.dist * 10.0;
+            let t = if ray.dir.z < -1e-3 {
+                (-ray.from.z / ray.dir.z).min(max_t)
+            } else {
+                max_t
+            };
+            Some((ray.from + ray.dir * t).xy())
+        })
+        .collect()
+}
+
+/// Outline of the area seen by the camera
+fn paint_view(paint: &mut yakui::paint::PaintDom, offset: Vec2, corners: &[geom::Vec2]) {
+    if corners.len() < 2 {
+        return;
+    }
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let cull_rect = AABB::new_ll_size(vec2(0.0, 0.0), vec2(MINIMAP_SIZE, MINIMAP_SIZE));
+    let mut tess = Tesselator::new(&mut vertices, &mut indices, Some(cull_rect), 15.0);
+    tess.set_color([1.0, 1.0, 1.0, 0.9]);
+    let points: Vec<_> = corners.iter().map(|p| p.z(0.0)).collect();
+    tess.draw_polyline(&points, 1.5, true);
+
+    paint.add_mesh(PaintMesh::new(
+        vertices.into_iter().map(|v| {
+            yakui::paint::Vertex::new(
+                [offset.x + v.position[0], offset.y + v.position[1]],
+                v.uv,
+                v.color,
+            )
+        }),
+        indices.into_iter().map(|x| x as _),
+    ));
+}
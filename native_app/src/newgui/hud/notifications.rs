@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use yakui::{reflow, Alignment, Color, Dim2, Pivot};
+
+use goryak::{
+    blur_bg, error, icon, minrow, on_secondary_container, padxy, primary, primary_link,
+    secondary_container, textc,
+};
+use prototypes::Tick;
+use simulation::notifications::{Notification, NotificationTarget, Severity, SimNotifications};
+use simulation::Simulation;
+
+use crate::audio::MusicState;
+use crate::newgui::windows::search::focus_camera;
+use crate::newgui::{InspectedBuilding, InspectedEntity, Toasts};
+use crate::uiworld::UiWorld;
+
+/// Notifications kept for the history window
+pub const HISTORY_LEN: usize = 100;
+
+/// The same notification received again within this many seconds is counted instead of listed twice
+const COALESCE_WINDOW: f32 = 10.0;
+
+/// Seconds the name of a new music track is shown
+const MUSIC_TOAST_DURATION: f32 = 4.0;
+
+pub struct NotificationEntry {
+    pub severity: Severity,
+    pub message: String,
+    pub target: Option<NotificationTarget>,
+    /// When it was last received, in game time
+    pub tick: Tick,
+    /// How many times it was received in a row
+    pub count: u32,
+    /// When it was last received, in [`UiWorld::time_always`] seconds
+    received: f32,
+}
+
+/// Events of the simulation, shown as [`Toasts`] when they happen and kept for the history window
+#[derive(Default)]
+pub struct Notifications {
+    /// The oldest first
+    pub history: VecDeque<NotificationEntry>,
+    /// Counter of the notifications already received, see [`SimNotifications::since`]
+    seen: u64,
+}
+
+impl Notifications {
+    pub fn push(&mut self, n: &Notification, now: f32) {
+        let same = self.history.iter().rposition(|e| {
+            e.severity == n.severity && e.message == n.message && now - e.received < COALESCE_WINDOW
+        });
+        if let Some(i) = same {
+            // moved to the end so that it is shown as the newest
+            let mut e = self.history.remove(i).unwrap();
+            e.count += 1;
+            e.received = now;
+            e.tick = n.tick;
+            e.target = n.target;
+            self.history.push_back(e);
+            return;
+        }
+
+        if self.history.len() >= HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(NotificationEntry {
+            severity: n.severity,
+            message: n.message.clone(),
+            target: n.target,
+            tick: n.tick,
+            count: 1,
+            received: now,
+        });
+    }
+
+    /// Picks up the notifications of the simulation and shows them as toasts for `duration`
+    /// seconds
+    pub fn update(&mut self, sim: &Simulation, now: f32, toasts: &mut Toasts, duration: f32) {
+        let new: Vec<Notification> = sim
+            .read::<SimNotifications>()
+            .since(&mut self.seen)
+            .cloned()
+            .collect();
+        for n in new {
+            self.push(&n, now);
+            toasts.push_target(n.message, n.target, now, duration);
+        }
+    }
+}
+
+/// Color and icon of the severity
+pub fn severity_style(severity: Severity) -> (Color, &'static str) {
+    match severity {
+        Severity::Info => (primary(), "circle-info"),
+        Severity::Milestone => (Color::rgb(250, 200, 60), "trophy"),
+        Severity::Warning => (Color::rgb(240, 160, 40), "triangle-exclamation"),
+        Severity::Critical => (error(), "circle-exclamation"),
+    }
+}
+
+/// Message of the notification, a link if it has a target. Returns true if the link was clicked
+pub fn notification_text(e: &NotificationEntry) -> bool {
+    let text = if e.count > 1 {
+        format!("{} (x{})", e.message, e.count)
+    } else {
+        e.message.clone()
+    };
+    if e.target.is_none() {
+        textc(on_secondary_container(), text);
+        return false;
+    }
+    primary_link(text)
+}
+
+/// Moves the camera to what the notification is about and inspects it
+pub fn jump_to(uiw: &UiWorld, sim: &Simulation, target: NotificationTarget) {
+    match target {
+        NotificationTarget::Building(id) => {
+            let Some((pos, size)) = sim.map().buildings().get(id).map(|b| {
+                let size = b.obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max);
+                (b.door_pos, size)
+            }) else {
+                return;
+            };
+            focus_camera(uiw, pos, size);
+            uiw.write::<InspectedBuilding>().e = Some(id);
+        }
+        NotificationTarget::Entity(e) => {
+            let Some(pos) = sim.world().pos_any(e) else {
+                return;
+            };
+            focus_camera(uiw, pos, 0.0);
+            uiw.write::<InspectedEntity>().e = Some(e);
+        }
+        NotificationTarget::Position(pos) => focus_camera(uiw, pos, 0.0),
+    }
+}
+
+/// Name of the music track that just started, at the top of the screen
+pub fn music_toast(uiw: &UiWorld) {
+    let now = uiw.time_always();
+    let Some((name, since)) = uiw.read::<MusicState>().now_playing.clone() else {
+        return;
+    };
+    if now - since > MUSIC_TOAST_DURATION {
+        return;
+    }
+
+    reflow(
+        Alignment::TOP_CENTER,
+        Pivot::TOP_CENTER,
+        Dim2::pixels(0.0, 10.0),
+        || {
+            blur_bg(secondary_container().with_alpha(0.7), 5.0, || {
+                padxy(8.0, 4.0, || {
+                    minrow(8.0, || {
+                        icon(on_secondary_container(), "music");
+                        textc(on_secondary_container(), name);
+                    });
+                });
+            });
+        },
+    );
+}
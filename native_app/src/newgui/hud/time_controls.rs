@@ -1,32 +1,71 @@
 use yakui::widgets::{List, Pad};
 use yakui::{
-    constrained, opaque, reflow, row, spacer, Alignment, Color, Constraints, CrossAxisAlignment,
-    Dim2, MainAxisAlignment, MainAxisSize, Pivot, Vec2,
+    colored_box, constrained, opaque, reflow, row, spacer, Alignment, Color, Constraints,
+    CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize, Pivot, Vec2,
 };
 
+use common::timestep::UNCAPPED_WARP;
 use goryak::{
-    blur_bg, button_primary, button_secondary, constrained_viewport, icon_button, monospace,
-    on_secondary_container, padx, padxy, secondary_container,
+    blur_bg, button_primary, button_secondary, constrained_viewport, error, icon, icon_button,
+    monospace, on_secondary_container, padx, padxy, secondary_container, tooltip, Tooltip,
 };
-use prototypes::GameTime;
+use prototypes::{GameTime, Season};
+use simulation::map::ZoningKind;
+use simulation::map_dynamic::ZoneDemand;
+use simulation::weather::{Weather, WeatherKind};
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::network::NetworkState;
 use crate::newgui::windows::settings::Settings;
+use crate::newgui::zoning::zoning_color;
 use crate::newgui::GuiState;
 use crate::uiworld::UiWorld;
 
+/// Speed buttons: the action selecting them, their icon, their tooltip and their time warp
+const SPEEDS: [(InputAction, &str, &str, u32); 4] = [
+    (InputAction::SpeedNormal, "play", "tooltip-play", 1),
+    (InputAction::SpeedFast, "forward", "tooltip-forward", 3),
+    (
+        InputAction::SpeedFaster,
+        "fast-forward",
+        "tooltip-fast-forward",
+        10,
+    ),
+    (
+        InputAction::SpeedUncapped,
+        "infinity",
+        "tooltip-uncapped",
+        UNCAPPED_WARP,
+    ),
+];
+
+/// The simulation is lagging when it runs less than this fraction of the requested ticks
+const LAGGING_RATIO: f32 = 0.9;
+
 pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
     profiling::scope!("hud::time_controls");
-    let time = sim.read::<GameTime>().daytime;
+    let game_time = *sim.read::<GameTime>();
+    let time = game_time.daytime;
+    let demand = *sim.read::<ZoneDemand>();
+    let weather = sim.read::<Weather>();
     let warp = &mut uiworld.write::<Settings>().time_warp;
     let mut gui = uiworld.write::<GuiState>();
     let depause_warp = &mut gui.depause_warp;
-    if uiworld
-        .read::<InputMap>()
-        .just_act
-        .contains(&InputAction::PausePlay)
-    {
+    let mut net_state = uiworld.write::<NetworkState>();
+    // only the singleplayer timestep can be stepped and measured
+    #[allow(irrefutable_let_patterns)]
+    let step = if let NetworkState::Singleplayer(ref mut step) = *net_state {
+        Some(step)
+    } else {
+        None
+    };
+    let tps = step
+        .as_ref()
+        .map(|step| (step.actual_tps(), step.requested_tps()));
+
+    let inp = uiworld.read::<InputMap>();
+    if inp.just_act.contains(&InputAction::PausePlay) {
         if *warp == 0 {
             *warp = *depause_warp;
         } else {
@@ -34,6 +73,13 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
             *warp = 0;
         }
     }
+    for (act, _, _, b_warp) in &SPEEDS {
+        if inp.just_act.contains(act) {
+            *warp = *b_warp;
+        }
+    }
+    let mut step_tick = inp.just_act.contains(&InputAction::StepTick);
+    drop(inp);
 
     if *warp == 0 {
         yakui::canvas(|ctx| {
@@ -48,6 +94,17 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
     }
 
     let time_text = || {
+        padx(5.0, || {
+            monospace(
+                on_secondary_container(),
+                t!(
+                    "time-date",
+                    season = t!(season_key(time.season())),
+                    day = time.day_of_season(),
+                    year = time.year()
+                ),
+            );
+        });
         padx(5.0, || {
             row(|| {
                 monospace(on_secondary_container(), format!("Day {}", time.day));
@@ -58,34 +115,44 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
                 );
             });
         });
+        weather_row(&weather, time.day);
         let mut l = List::row();
         l.main_axis_alignment = MainAxisAlignment::SpaceBetween;
         l.show(|| {
-            let mut time_button = |text: &str, b_warp: u32| {
-                let mut b = if *warp == b_warp {
+            let time_button = |text: &str, tooltip_key: &str, active: bool| {
+                let mut b = if active {
                     icon_button(button_primary(text))
                 } else {
                     icon_button(button_secondary(text))
                 };
 
-                b.padding = Pad::balanced(10.0, 3.0);
-                if b.show().clicked {
-                    if b_warp == 0 {
-                        if *warp == 0 {
-                            *warp = *depause_warp;
-                        } else {
-                            *depause_warp = *warp;
-                        }
-                    }
-                    *warp = b_warp;
-                }
+                b.padding = Pad::balanced(6.0, 3.0);
+                let mut clicked = false;
+                tooltip(t!(tooltip_key), || {
+                    clicked = b.show().clicked;
+                });
+                clicked
             };
 
-            time_button("pause", 0);
-            time_button("play", 1);
-            time_button("forward", 3);
-            time_button("fast-forward", 1000);
+            if time_button("pause", "tooltip-pause", *warp == 0) && *warp != 0 {
+                *depause_warp = *warp;
+                *warp = 0;
+            }
+            step_tick |= time_button("step-forward", "tooltip-step", false);
+            for &(_, icon, tooltip_key, b_warp) in &SPEEDS {
+                if time_button(icon, tooltip_key, *warp == b_warp) {
+                    *warp = b_warp;
+                }
+            }
         });
+        if *warp != 0 {
+            if let Some((Some(actual), requested)) = tps {
+                sim_speed(actual, requested);
+            }
+        }
+        demand_bars(&demand);
+        #[cfg(debug_assertions)]
+        time_scrub(uiworld, &game_time);
     };
 
     reflow(
@@ -101,7 +168,7 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
                         blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
                             padxy(10.0, 5.0, || {
                                 constrained(
-                                    Constraints::loose(Vec2::new(170.0, f32::INFINITY)),
+                                    Constraints::loose(Vec2::new(200.0, f32::INFINITY)),
                                     || {
                                         let mut l = List::column();
                                         l.cross_axis_alignment = CrossAxisAlignment::Stretch;
@@ -117,4 +184,156 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
             });
         },
     );
+
+    // stepping while the game runs pauses it first
+    if step_tick {
+        if *warp != 0 {
+            *depause_warp = *warp;
+            *warp = 0;
+        }
+        if let Some(step) = step {
+            step.step_once();
+        }
+    }
+}
+
+/// Ticks simulated per second with the uncapped speed,
+/// or out of the requested ones when the computer cannot keep up with the speed
+fn sim_speed(actual: f32, requested: Option<f32>) {
+    let (text, tooltip_key, color) = match requested {
+        None => (
+            format!("{:.0} ticks/s", actual),
+            "tooltip-sim-speed",
+            on_secondary_container(),
+        ),
+        Some(requested) if actual < requested * LAGGING_RATIO => (
+            format!("{:.0}/{:.0} ticks/s", actual, requested),
+            "tooltip-sim-lagging",
+            error(),
+        ),
+        Some(_) => return,
+    };
+    tooltip(t!(tooltip_key), || {
+        padx(5.0, || {
+            monospace(color, text);
+        });
+    });
+}
+
+/// Moves the time of day forward, to look at the lighting and the schedules of the citizens
+#[cfg(debug_assertions)]
+fn time_scrub(uiworld: &UiWorld, time: &GameTime) {
+    let before = time.daysec() as f32 / GameTime::HOUR as f32;
+    let mut hour = before;
+    let mut l = List::row();
+    l.cross_axis_alignment = CrossAxisAlignment::Center;
+    l.item_spacing = 5.0;
+    l.show(|| {
+        monospace(on_secondary_container(), "Hour");
+        goryak::dragvalue()
+            .min(before as f64)
+            .max(24.0)
+            .step(0.25)
+            .show(&mut hour);
+    });
+    if hour > before {
+        let ahead = (hour - before) * GameTime::HOUR as f32;
+        uiworld
+            .commands()
+            .set_game_time(*time + prototypes::GameDuration::from_secs(ahead as u64));
+    }
+}
+
+/// Today's weather followed by the forecast of the next days, with its effects in the tooltip
+fn weather_row(weather: &Weather, day: i32) {
+    let today = weather.current();
+    let mut tip = Tooltip::new(t!(weather_key(today)))
+        .row(
+            t!("tooltip-weather-season"),
+            t!(season_key(weather.season())),
+        )
+        .row(
+            t!("tooltip-road-speed-limit"),
+            format!("{:.0}%", today.speed_limit_factor() * 100.0),
+        )
+        .row(
+            t!("tooltip-weather-farms"),
+            format!("{:.0}%", weather.farm_factor() * 100.0),
+        );
+    for (i, &kind) in weather.forecast().iter().enumerate() {
+        tip = tip.row(
+            t!("tooltip-weather-day", day = day + 1 + i as i32),
+            t!(weather_key(kind)),
+        );
+    }
+
+    tooltip(tip, || {
+        padx(5.0, || {
+            let mut l = List::row();
+            l.cross_axis_alignment = CrossAxisAlignment::Center;
+            l.item_spacing = 5.0;
+            l.show(|| {
+                icon(on_secondary_container(), weather_icon(today));
+                spacer(1);
+                for &kind in weather.forecast() {
+                    icon(on_secondary_container().with_alpha(0.6), weather_icon(kind));
+                }
+            });
+        });
+    });
+}
+
+fn weather_icon(kind: WeatherKind) -> &'static str {
+    match kind {
+        WeatherKind::Clear => "sun",
+        WeatherKind::Cloudy => "cloud",
+        WeatherKind::Rain => "cloud-rain",
+        WeatherKind::Snow => "snowflake",
+    }
+}
+
+fn weather_key(kind: WeatherKind) -> &'static str {
+    match kind {
+        WeatherKind::Clear => "weather-clear",
+        WeatherKind::Cloudy => "weather-cloudy",
+        WeatherKind::Rain => "weather-rain",
+        WeatherKind::Snow => "weather-snow",
+    }
+}
+
+fn season_key(season: Season) -> &'static str {
+    match season {
+        Season::Spring => "season-spring",
+        Season::Summer => "season-summer",
+        Season::Autumn => "season-autumn",
+        Season::Winter => "season-winter",
+    }
+}
+
+const DEMAND_BAR_WIDTH: f32 = 140.0;
+
+/// One bar per kind of zone, full when it is really needed
+fn demand_bars(demand: &ZoneDemand) {
+    for (kind, label) in [
+        (ZoningKind::Residential, "R"),
+        (ZoningKind::Commercial, "C"),
+        (ZoningKind::Industrial, "I"),
+    ] {
+        let c = zoning_color(kind);
+        let col = Color::rgb(
+            (c.r * 255.0) as u8,
+            (c.g * 255.0) as u8,
+            (c.b * 255.0) as u8,
+        );
+        let w = DEMAND_BAR_WIDTH * demand.get(kind);
+
+        let mut l = List::row();
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 5.0;
+        l.show(|| {
+            monospace(on_secondary_container(), label);
+            colored_box(col, Vec2::new(w, 6.0));
+            colored_box(col.with_alpha(0.2), Vec2::new(DEMAND_BAR_WIDTH - w, 6.0));
+        });
+    }
 }
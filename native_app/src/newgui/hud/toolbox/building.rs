@@ -14,18 +14,22 @@ use goryak::{
     padxy, primary, secondary_container, textc, titlec,
 };
 use prototypes::{
-    prototypes_iter, BuildingPrototypeID, GoodsCompanyID, GoodsCompanyPrototype, Prototype,
-    RenderAsset,
+    prototypes_iter, AirportPrototype, BuildingPrototypeID, GoodsCompanyID, GoodsCompanyPrototype,
+    HarborPrototype, HotelPrototype, LeisurePrototype, Prototype, RenderAsset, SchoolPrototype,
+    WarehousePrototype,
 };
 use simulation::map::{BuildingKind, Zone};
+use simulation::milestones::lock_reason;
 use simulation::world_command::WorldCommand;
+use simulation::Simulation;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use crate::newgui::hud::toolbox::locked_button;
 use crate::newgui::specialbuilding::{SpecialBuildKind, SpecialBuildingResource};
 use crate::uiworld::UiWorld;
 
-pub fn special_building_properties(uiw: &UiWorld) {
+pub fn special_building_properties(uiw: &UiWorld, sim: &Simulation) {
     let mut state = uiw.write::<SpecialBuildingResource>();
     let icons = uiw.read::<BuildingIcons>();
 
@@ -42,6 +46,11 @@ pub fn special_building_properties(uiw: &UiWorld) {
                 };
 
                 minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
                     let default_col = Color::WHITE;
                     let hover_col = primary();
                     let active_col = default_col.with_alpha(0.5);
@@ -173,6 +182,418 @@ pub fn special_building_properties(uiw: &UiWorld) {
                     }
                 });
             }
+
+            for descr in prototypes_iter::<WarehousePrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(on_secondary_container(), "capacity:");
+                                            for item in &descr.capacity {
+                                                item_icon_yakui(uiw, item.id, item.amount);
+                                            }
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Warehouse(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
+
+            for descr in prototypes_iter::<SchoolPrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("capacity: {}", descr.capacity),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("radius: {}m", descr.service_radius),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                format!(
+                                                    "teaches up to: {}",
+                                                    descr.max_education.label()
+                                                ),
+                                            );
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::School(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
+
+            for descr in prototypes_iter::<LeisurePrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("capacity: {}", descr.capacity),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                format!(
+                                                    "entry fee: {}",
+                                                    descr.entry_fee.localized()
+                                                ),
+                                            );
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Leisure(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
+
+            for descr in prototypes_iter::<HotelPrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("rooms: {}", descr.rooms),
+                                            );
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Hotel(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
+
+            for descr in prototypes_iter::<HarborPrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("docks: {}", descr.docks.len()),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("throughput: {}/h", descr.throughput),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                "must be built on the shore",
+                                            );
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Harbor(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
+            for descr in prototypes_iter::<AirportPrototype>() {
+                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                    continue;
+                };
+
+                minrow(0.0, || {
+                    if let Some(reason) = lock_reason(sim, &descr.name) {
+                        locked_button(*tex_id, descr.label.clone(), reason);
+                        return;
+                    }
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.hovering {
+                        reflow(
+                            Alignment::TOP_CENTER,
+                            Pivot::BOTTOM_CENTER,
+                            Dim2::pixels(0.0, -20.0),
+                            || {
+                                blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                    padxy(10.0, 10.0, || {
+                                        mincolumn(3.0, || {
+                                            titlec(on_secondary_container(), &descr.label);
+                                            textc(
+                                                on_secondary_container(),
+                                                format!(
+                                                    "up to {} flights/day",
+                                                    descr.flights_per_day
+                                                ),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                format!("seats: {}", descr.seats),
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                "must be built on flat ground",
+                                            );
+                                            textc(
+                                                on_secondary_container(),
+                                                "the planes are very noisy",
+                                            );
+                                        });
+                                    });
+                                });
+                            },
+                        );
+                    }
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Airport(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                });
+            }
         });
     });
 
@@ -0,0 +1,100 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{button_primary, button_secondary, mincolumn, on_primary_container, padxy, textc};
+use simulation::economy::Government;
+use simulation::gameplay::GameplayParams;
+use simulation::map_dynamic::Fires;
+use simulation::Simulation;
+
+use crate::inputmap::InputMap;
+use crate::newgui::bulldozer::{bulldozer_sound, AreaState, BulldozerState};
+use crate::newgui::inspect::building_link;
+use crate::uiworld::UiWorld;
+
+fn toggle(text: &str, enabled: &mut bool) {
+    let b = if *enabled {
+        button_primary(text)
+    } else {
+        button_secondary(text)
+    };
+    if b.show().clicked {
+        *enabled = !*enabled;
+    }
+}
+
+pub fn bulldozer_properties(uiw: &UiWorld, sim: &Simulation) {
+    let state = &mut *uiw.write::<BulldozerState>();
+    let cursor = uiw.read::<InputMap>().unprojected.map(|p| p.xy());
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            let mut area = state.area;
+            toggle("Area", &mut area);
+            if area != state.area {
+                state.area = area;
+                state.area_state = AreaState::Idle;
+            }
+
+            if !state.area {
+                return;
+            }
+
+            toggle("Roads", &mut state.filter.roads);
+            toggle("Rails", &mut state.filter.rails);
+            toggle("Buildings", &mut state.filter.buildings);
+            toggle("Trees", &mut state.filter.trees);
+
+            let Some(area) = state.selected_area(cursor) else {
+                textc(on_primary_container(), "Drag a rectangle to bulldoze it");
+                return;
+            };
+
+            let map = sim.map();
+            let sel = map.bulldoze_selection(area, state.filter);
+            let refund = Government::bulldoze_refund(&map, &sim.read::<Fires>(), &sel)
+                * sim.read::<GameplayParams>().building_cost_multiplier as f64;
+            drop(map);
+
+            mincolumn(0.0, || {
+                textc(
+                    on_primary_container(),
+                    format!(
+                        "{} roads, {} buildings, {} trees",
+                        sel.roads.len(),
+                        sel.buildings.len(),
+                        sel.n_trees
+                    ),
+                );
+                textc(on_primary_container(), format!("Refund: {}$", refund));
+            });
+
+            if !matches!(state.area_state, AreaState::Confirm(_)) {
+                return;
+            }
+
+            mincolumn(0.0, || {
+                textc(
+                    on_primary_container(),
+                    "These buildings will lose their road access:",
+                );
+                for &b in &sel.orphaned {
+                    building_link(uiw, sim, b);
+                }
+            });
+
+            if button_primary("Confirm").show().clicked {
+                uiw.commands().map_bulldoze_area(area, state.filter);
+                bulldozer_sound(&sim.map(), uiw, area);
+                state.area_state = AreaState::Idle;
+            }
+            if button_secondary("Cancel").show().clicked {
+                state.area_state = AreaState::Idle;
+            }
+        });
+    });
+}
@@ -0,0 +1,63 @@
+use common::saveload::{Encoder, JSONPretty, JSON};
+use goryak::{mincolumn, padxy};
+use yakui::widgets::List;
+use yakui::{button, label, CrossAxisAlignment, MainAxisAlignment};
+
+use crate::newgui::copypaste::{Blueprint, CopyPasteResource, CopyState};
+use crate::newgui::Toasts;
+use crate::uiworld::UiWorld;
+
+pub fn copypaste_properties(uiw: &UiWorld) {
+    let mut state = uiw.write::<CopyPasteResource>();
+    let mut blueprint = uiw.write::<Blueprint>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            mincolumn(0.0, || {
+                label(match state.state {
+                    CopyState::Select | CopyState::Selecting(_) => {
+                        "Drag a rectangle to copy the roads inside"
+                    }
+                    CopyState::Paste => "Click to paste, R to rotate, M to mirror",
+                });
+                label(format!(
+                    "Blueprint: {} roads, {} intersections",
+                    blueprint.roads.len(),
+                    blueprint.nodes.len()
+                ));
+            });
+
+            if button("Select").clicked {
+                state.state = CopyState::Select;
+            }
+            if !blueprint.is_empty() && button("Paste").clicked {
+                state.state = CopyState::Paste;
+            }
+            if !blueprint.is_empty() && button("Save").clicked {
+                JSONPretty::save(&*blueprint, Blueprint::SAVE_NAME);
+            }
+            if button("Load").clicked {
+                match Blueprint::load() {
+                    Ok(loaded) => {
+                        *blueprint = loaded;
+                        state.angle = 0.0;
+                        state.mirror = false;
+                        state.state = CopyState::Paste;
+                    }
+                    Err(err) => uiw.write::<Toasts>().push(
+                        format!(
+                            "Could not load the blueprint from {}: {}",
+                            JSON::filename(Blueprint::SAVE_NAME),
+                            err
+                        ),
+                        uiw.time_always(),
+                    ),
+                }
+            }
+        });
+    });
+}
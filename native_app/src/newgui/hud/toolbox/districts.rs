@@ -0,0 +1,38 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{button_primary, button_secondary, on_primary_container, padxy, text_edit, textc};
+
+use crate::newgui::districts::DistrictsResource;
+use crate::uiworld::UiWorld;
+
+pub fn districts_properties(uiw: &UiWorld) {
+    let state = &mut *uiw.write::<DistrictsResource>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            if state.selected.is_some() {
+                state.rename |= text_edit(200.0, &mut state.selected_name, "Name");
+                if button_primary("Rename").show().clicked {
+                    state.rename = true;
+                }
+                if button_secondary("Remove").show().clicked {
+                    state.remove = true;
+                }
+                return;
+            }
+
+            text_edit(200.0, &mut state.name, "Name of the new district");
+            let hint = if state.points.len() < 3 {
+                "Click to place the corners, Ctrl+click removes the last one"
+            } else {
+                "Click the first corner to close the district"
+            };
+            textc(on_primary_container(), hint);
+        });
+    });
+}
@@ -0,0 +1,49 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{button_primary, button_secondary, fixed_spacer, padxy};
+use prototypes::{prototypes_iter, TreePrototype};
+
+use crate::newgui::forestry::ForestryResource;
+use crate::newgui::hud::toolbox::updown_value;
+use crate::uiworld::UiWorld;
+
+pub fn forestry_properties(uiw: &UiWorld) {
+    let state = &mut *uiw.write::<ForestryResource>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            for tree in prototypes_iter::<TreePrototype>() {
+                let enabled = !state.disabled.contains(&tree.id);
+                let b = if enabled {
+                    button_primary(&tree.label)
+                } else {
+                    button_secondary(&tree.label)
+                };
+                if b.show().clicked {
+                    if enabled {
+                        state.disabled.push(tree.id);
+                    } else {
+                        state.disabled.retain(|&id| id != tree.id);
+                    }
+                }
+            }
+
+            fixed_spacer((30.0, 0.0));
+
+            if updown_value(&mut state.radius, 5.0, "m") {
+                state.radius = state.radius.clamp(5.0, 200.0);
+            }
+
+            fixed_spacer((30.0, 0.0));
+
+            if updown_value(&mut state.density, 20.0, "/ha") {
+                state.density = state.density.clamp(20.0, 1000.0);
+            }
+        });
+    });
+}
@@ -1,26 +1,39 @@
-use yakui::widgets::{Button, List, Pad};
+use yakui::widgets::{List, Pad};
 use yakui::{
-    colored_box_container, column, image, opaque, reflow, spacer, Alignment, Color,
-    CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize, Pivot, Vec2,
+    colored_box, colored_box_container, column, image, opaque, reflow, spacer, use_state,
+    Alignment, Color, CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize, Pivot, TextureId,
+    Vec2,
 };
 
 use goryak::{
-    blur_bg, button_primary, constrained_viewport, fixed_spacer, icon_button, image_button,
-    monospace, on_primary, outline, padxy, primary, primary_container, round_rect,
-    secondary_container,
+    blur_bg, button_primary, constrained_viewport, fixed_spacer, icon, icon_button, image_button,
+    mincolumn, minrow, monospace, on_primary, on_secondary_container, outline, padxy, primary,
+    primary_container, round_rect, secondary_container, selectable_label_primary, textc,
+    ImageButton, SoundButton, Tooltip,
 };
 use simulation::Simulation;
+use std::borrow::Cow;
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::textures::UiTextures;
 use crate::newgui::Tool;
-use crate::uiworld::UiWorld;
+use crate::rendering::garbage_overlay::GarbageOverlay;
+use crate::rendering::land_value_overlay::LandValueOverlay;
+use crate::rendering::noise_overlay::NoiseOverlay;
+use crate::rendering::overlays::{OverlayLayer, Overlays};
+use crate::rendering::traffic_overlay::TrafficOverlay;
+use crate::uiworld::{SaveLoadState, UiWorld};
 
 pub mod building;
+pub mod bulldozer;
+pub mod copypaste;
+pub mod districts;
+pub mod forestry;
 pub mod roadbuild;
 pub mod roadedit;
 pub mod terraforming;
 pub mod train;
+pub mod zoning;
 
 pub fn new_toolbox(uiworld: &UiWorld, sim: &Simulation) {
     if uiworld
@@ -67,45 +80,80 @@ pub fn new_toolbox(uiworld: &UiWorld, sim: &Simulation) {
     });
 }
 
-fn tool_properties(uiw: &UiWorld, _sim: &Simulation) -> bool {
+fn tool_properties(uiw: &UiWorld, sim: &Simulation) -> bool {
     let tool = *uiw.read::<Tool>();
 
     match tool {
         Tool::Hand => return false,
-        Tool::Bulldozer => return false,
         Tool::LotBrush => return false,
+        Tool::Crossing => return false,
+        Tool::BusStop => return false,
         Tool::RoadbuildStraight | Tool::RoadbuildCurved => {
-            roadbuild::roadbuild_properties(uiw);
+            roadbuild::roadbuild_properties(uiw, sim);
         }
         Tool::RoadEditor => {
-            roadedit::roadedit_properties(uiw);
+            roadedit::roadedit_properties(uiw, sim);
         }
         Tool::SpecialBuilding => {
-            building::special_building_properties(uiw);
+            building::special_building_properties(uiw, sim);
         }
         Tool::Train => {
-            train::train_properties(uiw);
+            train::train_properties(uiw, sim);
         }
         Tool::Terraforming => {
             terraforming::terraform_properties(uiw);
         }
+        Tool::Copy => {
+            copypaste::copypaste_properties(uiw);
+        }
+        Tool::Bulldozer => {
+            bulldozer::bulldozer_properties(uiw, sim);
+        }
+        Tool::Zoning => {
+            zoning::zoning_properties(uiw);
+        }
+        Tool::Forestry => {
+            forestry::forestry_properties(uiw);
+        }
+        Tool::Districts => {
+            districts::districts_properties(uiw);
+        }
     }
     true
 }
 
 fn tools_list(uiworld: &UiWorld) {
     let tools = [
-        ("toolbar_straight_road", Tool::RoadbuildStraight),
-        ("toolbar_curved_road", Tool::RoadbuildCurved),
-        ("toolbar_road_edit", Tool::RoadEditor),
-        ("toolbar_housetool", Tool::LotBrush),
-        ("toolbar_companies", Tool::SpecialBuilding),
-        ("toolbar_bulldozer", Tool::Bulldozer),
-        ("toolbar_train", Tool::Train),
-        ("toolbar_terraform", Tool::Terraforming),
+        (
+            "toolbar_straight_road",
+            "tool-straight-road",
+            Tool::RoadbuildStraight,
+        ),
+        (
+            "toolbar_curved_road",
+            "tool-curved-road",
+            Tool::RoadbuildCurved,
+        ),
+        ("toolbar_road_edit", "tool-road-editor", Tool::RoadEditor),
+        ("toolbar_housetool", "tool-houses", Tool::LotBrush),
+        ("toolbar_companies", "tool-buildings", Tool::SpecialBuilding),
+        ("toolbar_bulldozer", "tool-bulldozer", Tool::Bulldozer),
+        ("toolbar_train", "tool-trains", Tool::Train),
+        ("toolbar_terraform", "tool-terraforming", Tool::Terraforming),
+        ("toolbar_copy", "tool-copy-paste", Tool::Copy),
+        ("toolbar_crossing", "tool-crossings", Tool::Crossing),
+        ("toolbar_zoning", "tool-zoning", Tool::Zoning),
+        ("toolbar_forestry", "tool-trees", Tool::Forestry),
+        ("toolbar_busstop", "tool-bus-stops", Tool::BusStop),
+        ("toolbar_districts", "tool-districts", Tool::Districts),
     ];
 
-    for (name, tool) in &tools {
+    // only the terrain can be edited in the map editor
+    let editing_map = uiworld.read::<SaveLoadState>().editing_map;
+    for (name, tooltip_key, tool) in &tools {
+        if editing_map && !matches!(tool, Tool::Terraforming | Tool::Forestry) {
+            continue;
+        }
         column(|| {
             let (default_col, hover_col) = if *tool == *uiworld.read::<Tool>() {
                 let c = primary().lerp(&Color::WHITE, 0.3);
@@ -113,16 +161,16 @@ fn tools_list(uiworld: &UiWorld) {
             } else {
                 (Color::WHITE, Color::WHITE.with_alpha(0.7))
             };
-            if image_button(
-                uiworld.read::<UiTextures>().get(name),
-                Vec2::new(64.0, 64.0),
-                default_col,
-                hover_col,
-                primary(),
-                "",
-            )
-            .clicked
-            {
+            // tools without an icon yet are shown as an empty button with their tooltip
+            let button = ImageButton {
+                texture: uiworld.read::<UiTextures>().try_get(name),
+                size: Vec2::new(64.0, 64.0),
+                color: default_col,
+                hover_color: hover_col,
+                active_color: primary(),
+                tooltip: t!(tooltip_key).into(),
+            };
+            if button.show().clicked {
                 *uiworld.write::<Tool>() = *tool;
             }
 
@@ -131,6 +179,181 @@ fn tools_list(uiworld: &UiWorld) {
             }
         });
     }
+
+    overlay_picker(uiworld);
+
+    let garbage = uiworld.read::<GarbageOverlay>().enabled;
+    if overlay_toggle(uiworld, "overlay_garbage", "overlay-garbage", garbage) {
+        uiworld.write::<GarbageOverlay>().enabled = !garbage;
+    }
+
+    let traffic = uiworld.read::<TrafficOverlay>().enabled;
+    if overlay_toggle(uiworld, "overlay_traffic", "overlay-traffic", traffic) {
+        uiworld.write::<TrafficOverlay>().enabled = !traffic;
+    }
+
+    let noise = uiworld.read::<NoiseOverlay>().enabled;
+    if overlay_toggle(uiworld, "overlay_noise", "overlay-noise", noise) {
+        uiworld.write::<NoiseOverlay>().enabled = !noise;
+    }
+
+    let land_value = uiworld.read::<LandValueOverlay>().enabled;
+    if overlay_toggle(
+        uiworld,
+        "overlay_land_value",
+        "overlay-land-value",
+        land_value,
+    ) {
+        uiworld.write::<LandValueOverlay>().enabled = !land_value;
+    }
+}
+
+/// Button below the tools list, returns whether it was clicked
+fn overlay_toggle(uiworld: &UiWorld, icon: &str, tooltip_key: &str, enabled: bool) -> bool {
+    let mut clicked = false;
+    column(|| {
+        let (default_col, hover_col) = if enabled {
+            let c = primary().lerp(&Color::WHITE, 0.3);
+            (c, c)
+        } else {
+            (Color::WHITE, Color::WHITE.with_alpha(0.7))
+        };
+        let button = ImageButton {
+            texture: uiworld.read::<UiTextures>().try_get(icon),
+            size: Vec2::new(64.0, 64.0),
+            color: default_col,
+            hover_color: hover_col,
+            active_color: primary(),
+            tooltip: t!(tooltip_key).into(),
+        };
+        clicked = button.show().clicked;
+
+        if enabled {
+            select_triangle(uiworld);
+        }
+    });
+    clicked
+}
+
+/// Colored boxes in the legend of a heatmap
+const LEGEND_STEPS: usize = 16;
+
+/// Button below the tools list opening the list of the heatmaps above it.
+/// The legend of the drawn heatmap is shown above the button.
+fn overlay_picker(uiworld: &UiWorld) {
+    let mut picked = None;
+    column(|| {
+        let open = use_state(|| false);
+        let overlays = uiworld.read::<Overlays>();
+        let active = overlays.active();
+
+        if open.get() || active.is_some() {
+            reflow(
+                Alignment::TOP_LEFT,
+                Pivot::BOTTOM_LEFT,
+                Dim2::pixels(0.0, -10.0),
+                || {
+                    opaque(|| {
+                        blur_bg(secondary_container().with_alpha(0.5), 5.0, || {
+                            padxy(5.0, 5.0, || {
+                                mincolumn(5.0, || {
+                                    if open.get() {
+                                        if selectable_label_primary(
+                                            active.is_none(),
+                                            &t!("overlay-none"),
+                                        )
+                                        .clicked
+                                        {
+                                            picked = Some(None);
+                                        }
+                                        for (i, layer) in overlays.layers().enumerate() {
+                                            if selectable_label_primary(
+                                                active == Some(i),
+                                                &t!(layer.name()),
+                                            )
+                                            .clicked
+                                            {
+                                                picked = Some(Some(i));
+                                            }
+                                        }
+                                    }
+                                    if let Some(layer) = overlays.active_layer() {
+                                        overlay_legend(layer);
+                                    }
+                                });
+                            });
+                        });
+                    });
+                },
+            );
+        }
+        drop(overlays);
+
+        let enabled = open.get() || active.is_some();
+        let (default_col, hover_col) = if enabled {
+            let c = primary().lerp(&Color::WHITE, 0.3);
+            (c, c)
+        } else {
+            (Color::WHITE, Color::WHITE.with_alpha(0.7))
+        };
+        let button = ImageButton {
+            texture: uiworld.read::<UiTextures>().try_get("no_power"),
+            size: Vec2::new(64.0, 64.0),
+            color: default_col,
+            hover_color: hover_col,
+            active_color: primary(),
+            tooltip: t!("overlay-heatmaps").into(),
+        };
+        if button.show().clicked {
+            open.modify(|x| !x);
+        }
+        if picked.is_some() {
+            open.set(false);
+        }
+        if active.is_some() {
+            select_triangle(uiworld);
+        }
+    });
+
+    if let Some(picked) = picked {
+        uiworld.write::<Overlays>().set_active(picked);
+    }
+}
+
+/// Name of the heatmap and its color ramp between the labels of its ends, then the color of the
+/// values above the ramp if it has one
+fn overlay_legend(layer: &dyn OverlayLayer) {
+    let (low, high) = layer.legend();
+    let ramp = layer.ramp();
+    textc(on_secondary_container(), t!(layer.name()));
+    minrow(5.0, || {
+        textc(on_secondary_container(), low);
+        minrow(0.0, || {
+            for i in 0..LEGEND_STEPS {
+                let c = ramp.at(i as f32 / (LEGEND_STEPS - 1) as f32);
+                colored_box(
+                    Color::rgb(
+                        (c.r * 255.0) as u8,
+                        (c.g * 255.0) as u8,
+                        (c.b * 255.0) as u8,
+                    ),
+                    Vec2::new(6.0, 12.0),
+                );
+            }
+        });
+        textc(on_secondary_container(), high);
+        if let Some((c, label)) = layer.overflow() {
+            colored_box(
+                Color::rgb(
+                    (c.r * 255.0) as u8,
+                    (c.g * 255.0) as u8,
+                    (c.b * 255.0) as u8,
+                ),
+                Vec2::new(12.0, 12.0),
+            );
+            textc(on_secondary_container(), label);
+        }
+    });
 }
 
 pub(crate) fn select_triangle(uiworld: &UiWorld) {
@@ -147,7 +370,25 @@ pub(crate) fn select_triangle(uiworld: &UiWorld) {
     );
 }
 
-pub fn updown_button(text: &str) -> Button {
+/// Greyed out button that does nothing with a lock over it, its tooltip tells how to unlock it
+pub fn locked_button(texture: TextureId, label: impl Into<Cow<'static, str>>, reason: String) {
+    column(|| {
+        let grey = Color::WHITE.with_alpha(0.25);
+        image_button(
+            texture,
+            Vec2::new(64.0, 64.0),
+            grey,
+            grey,
+            grey,
+            Tooltip::new(label).row("Locked", reason),
+        );
+        reflow(Alignment::CENTER, Pivot::CENTER, Dim2::ZERO, || {
+            icon(Color::WHITE, "lock");
+        });
+    });
+}
+
+pub fn updown_button(text: &str) -> SoundButton {
     let mut b = icon_button(button_primary(text));
     b.padding = Pad::balanced(5.0, 2.0);
     b.style.text.font_size = 13.0;
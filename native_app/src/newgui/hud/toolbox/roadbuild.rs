@@ -4,15 +4,23 @@ use yakui::{
     Pivot, Vec2,
 };
 
-use goryak::{image_button, mincolumn, minrow, padxy, primary};
-use simulation::map::LanePatternBuilder;
+use goryak::{
+    button_primary, button_secondary, image_button, mincolumn, minrow, monospace, on_primary,
+    on_primary_container, padxy, primary, round_rect, text_edit, textc, Tooltip,
+};
+use simulation::economy::Government;
+use simulation::map::{LanePatternBuilder, RoadStructure};
+use simulation::milestones::lock_reason;
+use simulation::Simulation;
 
-use crate::newgui::hud::toolbox::updown_value;
-use crate::newgui::roadbuild::{HeightReference, RoadBuildResource, Snapping};
+use crate::inputmap::InputMap;
+use crate::newgui::hud::toolbox::{locked_button, updown_value};
+use crate::newgui::roadbuild::{BuildState, HeightReference, RoadBuildResource, Snapping};
 use crate::newgui::textures::UiTextures;
+use crate::newgui::Tool;
 use crate::uiworld::UiWorld;
 
-pub fn roadbuild_properties(uiw: &UiWorld) {
+pub fn roadbuild_properties(uiw: &UiWorld, sim: &Simulation) {
     let mut state = uiw.write::<RoadBuildResource>();
 
     padxy(0.0, 10.0, || {
@@ -38,7 +46,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         snapping_none.0,
                         snapping_none.1,
                         primary(),
-                        "no snapping",
+                        t!("tooltip-snap-none"),
                     )
                     .clicked
                     {
@@ -50,7 +58,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         snapping_grid.0,
                         snapping_grid.1,
                         primary(),
-                        "snap to grid",
+                        t!("tooltip-snap-grid"),
                     )
                     .clicked
                     {
@@ -62,7 +70,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         snapping_angel.0,
                         snapping_angel.1,
                         primary(),
-                        "snap to angle",
+                        t!("tooltip-snap-angle"),
                     )
                     .clicked
                     {
@@ -84,7 +92,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         hos_ground.0,
                         hos_ground.1,
                         primary(),
-                        "Relative to ground",
+                        t!("tooltip-height-ground"),
                     )
                     .clicked
                     {
@@ -96,7 +104,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         hos_start.0,
                         hos_start.1,
                         primary(),
-                        "Relative to start",
+                        t!("tooltip-height-start"),
                     )
                     .clicked
                     {
@@ -108,7 +116,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         hos_incline.0,
                         hos_incline.1,
                         primary(),
-                        "Maximum incline",
+                        t!("tooltip-height-incline"),
                     )
                     .clicked
                     {
@@ -120,7 +128,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                         hos_decline.0,
                         hos_decline.1,
                         primary(),
-                        "Maximum decline",
+                        t!("tooltip-height-decline"),
                     )
                     .clicked
                     {
@@ -128,117 +136,247 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                     }
                 });
             });
-            // Road elevation
+            // Road elevation, below the terrain for tunnels
             updown_value(&mut state.height_offset, 2.0, "m");
 
-            // image name, label, builder
-            let builders: &[(&str, &str, LanePatternBuilder)] = &[
-                ("roadtypes_street", "Street", LanePatternBuilder::new()),
-                (
-                    "roadtypes_street_1way",
-                    "Street one-way",
-                    LanePatternBuilder::new().one_way(true),
-                ),
-                (
-                    "roadtypes_avenue",
-                    "Avenue",
-                    LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_avenue_1way",
-                    "Avenue one-way",
-                    LanePatternBuilder::new()
-                        .n_lanes(2)
-                        .one_way(true)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_drive",
-                    "Drive",
-                    LanePatternBuilder::new()
-                        .parking(false)
-                        .sidewalks(false)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_drive_1way",
-                    "Drive one-way",
-                    LanePatternBuilder::new()
-                        .parking(false)
-                        .sidewalks(false)
-                        .one_way(true)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_highway",
-                    "Highway",
-                    LanePatternBuilder::new()
-                        .n_lanes(3)
-                        .speed_limit(25.0)
-                        .parking(false)
-                        .sidewalks(false),
-                ),
-                (
-                    "roadtypes_highway_1way",
-                    "Highway one-way",
-                    LanePatternBuilder::new()
-                        .n_lanes(3)
-                        .speed_limit(25.0)
-                        .parking(false)
-                        .sidewalks(false)
-                        .one_way(true),
-                ),
-                (
-                    "roadtypes_rail",
-                    "Rail",
-                    LanePatternBuilder::new().rail(true),
-                ),
-                (
-                    "roadtypes_rail_1way",
-                    "Rail one-way",
-                    LanePatternBuilder::new().rail(true).one_way(true),
-                ),
-            ];
-
-            for (icon, label, builder) in builders {
-                let mut l = List::column();
-                l.main_axis_size = MainAxisSize::Min;
-                l.show(|| {
-                    let is_active = &state.pattern_builder == builder;
-                    let (default_col, hover_col) = if is_active {
-                        let c = Color::WHITE.adjust(0.5);
-                        (c, c)
-                    } else {
-                        (Color::WHITE, Color::WHITE.with_alpha(0.7))
-                    };
-                    if image_button(
-                        uiw.read::<UiTextures>().get(icon),
-                        Vec2::new(64.0, 64.0),
-                        default_col,
-                        hover_col,
-                        primary(),
-                        *label,
-                    )
-                    .clicked
-                    {
-                        state.pattern_builder = *builder;
-                    }
+            // Steepest gradient allowed
+            mincolumn(2.0, || {
+                textc(on_primary_container(), "Max slope");
+                if updown_value(&mut state.max_slope, 5.0, "%") {
+                    state.max_slope = state.max_slope.clamp(5.0, 100.0);
+                }
+            });
 
-                    if is_active {
-                        reflow(
-                            Alignment::CENTER_LEFT,
-                            Pivot::TOP_LEFT,
-                            Dim2::pixels(0.0, 32.0),
-                            || {
-                                image(
-                                    uiw.read::<UiTextures>().get("select_triangle_under"),
-                                    Vec2::new(64.0, 10.0),
-                                );
-                            },
-                        );
-                    }
-                });
+            // Segment length lock, empty for a free length
+            mincolumn(2.0, || {
+                textc(on_primary_container(), "Length (m)");
+                text_edit(60.0, &mut state.length_input, "free");
+            });
+            state.length_lock = state
+                .length_input
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|&l| l >= 1.0);
+
+            // Parallel road and its offset
+            let parallel_button = if state.parallel {
+                button_primary("Parallel")
+            } else {
+                button_secondary("Parallel")
+            };
+            if parallel_button.show().clicked {
+                state.parallel = !state.parallel;
+                state.build_state = BuildState::Hover;
             }
+            if state.parallel {
+                updown_value(&mut state.parallel_offset, 1.0, "m");
+            }
+
+            lane_pattern_palette(uiw, sim, &mut state.pattern_builder);
         });
     });
 }
+
+/// Road types to pick from, the selected one is marked with a triangle
+pub fn lane_pattern_palette(
+    uiw: &UiWorld,
+    sim: &Simulation,
+    pattern_builder: &mut LanePatternBuilder,
+) {
+    // image name, label, builder
+    let builders: &[(&str, &str, LanePatternBuilder)] = &[
+        ("roadtypes_street", "Street", LanePatternBuilder::new()),
+        (
+            "roadtypes_street_1way",
+            "Street one-way",
+            LanePatternBuilder::new().one_way(true),
+        ),
+        (
+            "roadtypes_avenue",
+            "Avenue",
+            LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
+        ),
+        (
+            "roadtypes_avenue_1way",
+            "Avenue one-way",
+            LanePatternBuilder::new()
+                .n_lanes(2)
+                .one_way(true)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_drive",
+            "Drive",
+            LanePatternBuilder::new()
+                .parking(false)
+                .sidewalks(false)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_drive_1way",
+            "Drive one-way",
+            LanePatternBuilder::new()
+                .parking(false)
+                .sidewalks(false)
+                .one_way(true)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_highway",
+            "Highway",
+            LanePatternBuilder::new()
+                .n_lanes(3)
+                .speed_limit(25.0)
+                .parking(false)
+                .sidewalks(false),
+        ),
+        (
+            "roadtypes_highway_1way",
+            "Highway one-way",
+            LanePatternBuilder::new()
+                .n_lanes(3)
+                .speed_limit(25.0)
+                .parking(false)
+                .sidewalks(false)
+                .one_way(true),
+        ),
+        (
+            "roadtypes_rail",
+            "Rail",
+            LanePatternBuilder::new().rail(true),
+        ),
+        (
+            "roadtypes_rail_1way",
+            "Rail one-way",
+            LanePatternBuilder::new().rail(true).one_way(true),
+        ),
+    ];
+
+    for (icon, label, builder) in builders {
+        let mut l = List::column();
+        l.main_axis_size = MainAxisSize::Min;
+        l.show(|| {
+            let name = icon
+                .trim_start_matches("roadtypes_")
+                .trim_end_matches("_1way");
+            if let Some(reason) = lock_reason(sim, name) {
+                locked_button(uiw.read::<UiTextures>().get(icon), *label, reason);
+                return;
+            }
+
+            let is_active = *pattern_builder == *builder;
+            let (default_col, hover_col) = if is_active {
+                let c = Color::WHITE.adjust(0.5);
+                (c, c)
+            } else {
+                (Color::WHITE, Color::WHITE.with_alpha(0.7))
+            };
+            if image_button(
+                uiw.read::<UiTextures>().get(icon),
+                Vec2::new(64.0, 64.0),
+                default_col,
+                hover_col,
+                primary(),
+                road_type_tooltip(*label, builder),
+            )
+            .clicked
+            {
+                *pattern_builder = *builder;
+            }
+
+            if is_active {
+                reflow(
+                    Alignment::CENTER_LEFT,
+                    Pivot::TOP_LEFT,
+                    Dim2::pixels(0.0, 32.0),
+                    || {
+                        image(
+                            uiw.read::<UiTextures>().get("select_triangle_under"),
+                            Vec2::new(64.0, 10.0),
+                        );
+                    },
+                );
+            }
+        });
+    }
+}
+
+/// Name of the road type and what it is made of
+fn road_type_tooltip(label: &'static str, builder: &LanePatternBuilder) -> Tooltip {
+    let lanes = if builder.one_way {
+        builder.n_lanes.to_string()
+    } else {
+        format!("{} + {}", builder.n_lanes, builder.n_lanes)
+    };
+    let mut tooltip = Tooltip::new(label)
+        .row(
+            t!(if builder.rail {
+                "tooltip-road-tracks"
+            } else {
+                "tooltip-road-lanes"
+            }),
+            lanes,
+        )
+        .row(
+            t!("tooltip-road-speed-limit"),
+            format!("{:.0} km/h", builder.speed_limit * 3.6),
+        )
+        .row(t!("tooltip-road-width"), format!("{:.1}m", builder.width()));
+    if !builder.rail {
+        let yes_no = |b: bool| t!(if b { "tooltip-yes" } else { "tooltip-no" });
+        tooltip = tooltip
+            .row(t!("tooltip-road-sidewalks"), yes_no(builder.sidewalks))
+            .row(t!("tooltip-road-parking"), yes_no(builder.parking));
+    }
+    tooltip.row(
+        t!("tooltip-road-price"),
+        t!(
+            "tooltip-per-meter",
+            price = Government::road_price_per_m(&builder.build()).localized()
+        ),
+    )
+}
+
+/// Length and angle of the segment being drawn, next to the cursor
+pub fn roadbuild_readout(uiw: &UiWorld) {
+    if !uiw.read::<Tool>().is_roadbuild() {
+        return;
+    }
+    let Some(readout) = uiw.read::<RoadBuildResource>().readout else {
+        return;
+    };
+    let screen = uiw.read::<InputMap>().screen;
+
+    let mut text = format!("{:.1}m", readout.length);
+    if readout.locked {
+        text.push_str(" (locked)");
+    }
+    if let Some(angle) = readout.angle {
+        text.push_str(&format!("  {:.0}°", angle));
+    }
+    if let Some(slope) = readout.slope {
+        text.push_str(&format!("  {:.0}%", slope));
+    }
+    match readout.structure {
+        Some(RoadStructure::Bridge) => text.push_str("  bridge"),
+        Some(RoadStructure::Tunnel) => text.push_str("  tunnel"),
+        Some(RoadStructure::Ground) | None => {}
+    }
+    if let Some(cost) = readout.cost {
+        text.push_str(&format!("  {}$", cost));
+    }
+
+    reflow(
+        Alignment::TOP_LEFT,
+        Pivot::TOP_LEFT,
+        Dim2::pixels(screen.x + 16.0, screen.y + 16.0),
+        || {
+            round_rect(3.0, primary(), || {
+                padxy(5.0, 2.0, || {
+                    monospace(on_primary(), text);
+                });
+            });
+        },
+    );
+}
@@ -3,20 +3,22 @@ use yakui::{
     column, image, reflow, Alignment, CrossAxisAlignment, Dim2, MainAxisAlignment, Pivot, Vec2,
 };
 
-use goryak::{padxy, primary_image_button};
-use simulation::map::LightPolicy;
+use goryak::{
+    button_primary, button_secondary, is_hovered, mincolumn, minrow, on_primary_container, padxy,
+    primary_image_button, text_edit, textc,
+};
+use simulation::map::{LightPolicy, LightTiming};
+use simulation::Simulation;
 
 use crate::newgui::hud::toolbox;
+use crate::newgui::hud::toolbox::roadbuild::lane_pattern_palette;
 use crate::newgui::hud::toolbox::select_triangle;
 use crate::newgui::roadeditor::RoadEditorResource;
 use crate::newgui::textures::UiTextures;
 use crate::uiworld::UiWorld;
 
-pub fn roadedit_properties(uiw: &UiWorld) {
+pub fn roadedit_properties(uiw: &UiWorld, sim: &Simulation) {
     let state = &mut *uiw.write::<RoadEditorResource>();
-    let Some(ref mut v) = state.inspect else {
-        return;
-    };
 
     padxy(0.0, 10.0, || {
         let mut l = List::row();
@@ -24,8 +26,67 @@ pub fn roadedit_properties(uiw: &UiWorld) {
         l.cross_axis_alignment = CrossAxisAlignment::Center;
         l.item_spacing = 10.0;
         l.show(|| {
+            let upgrade_button = if state.upgrade {
+                button_primary("Upgrade roads")
+            } else {
+                button_secondary("Upgrade roads")
+            };
+            if upgrade_button.show().clicked {
+                state.upgrade = !state.upgrade;
+                state.inspect = None;
+            }
+
+            if state.upgrade {
+                lane_pattern_palette(uiw, sim, &mut state.upgrade_pattern);
+                return;
+            }
+
+            if let Some(road) = state.road {
+                mincolumn(4.0, || {
+                    textc(on_primary_container(), state.road_label.clone());
+                    if let Some(ref mut name) = state.road_name {
+                        minrow(4.0, || {
+                            state.rename |= text_edit(150.0, name, "Name");
+                            if button_primary("Rename").show().clicked {
+                                state.rename = true;
+                            }
+                        });
+                    }
+                });
+                if road.one_way {
+                    if button_primary("Flip direction (F)").show().clicked {
+                        state.flip = true;
+                    }
+                } else if is_hovered(|| {
+                    button_secondary("Flip direction (F)").show();
+                })
+                .hovered
+                {
+                    textc(
+                        on_primary_container(),
+                        "Only one-way roads can be flipped, traffic already goes both ways",
+                    );
+                }
+                return;
+            }
+
+            let Some(ref mut v) = state.inspect else {
+                return;
+            };
+
             let texs = uiw.read::<UiTextures>();
 
+            if v.at_border {
+                let connection_button = if v.road_connection {
+                    button_primary("Outside connection")
+                } else {
+                    button_secondary("Outside connection")
+                };
+                if connection_button.show().clicked {
+                    state.toggle_connection = true;
+                }
+            }
+
             let light_policy_choices = &[
                 (LightPolicy::NoLights, "No lights", "roadedit_no_light"),
                 (LightPolicy::Lights, "Traffic lights", "roadedit_light"),
@@ -104,6 +165,79 @@ pub fn roadedit_properties(uiw: &UiWorld) {
             if let Some(ref mut roundabout) = v.turn_policy.roundabout {
                 state.dirty |= toolbox::updown_value(&mut roundabout.radius, 2.0, "m");
             }
+
+            // Traffic light cycle and the share of each phase
+            if v.light_policy == LightPolicy::Lights && v.n_phases > 0 {
+                mincolumn(4.0, || {
+                    let custom_button = if v.light_timing.is_some() {
+                        button_primary("Custom timing")
+                    } else {
+                        button_secondary("Custom timing")
+                    };
+                    if custom_button.show().clicked {
+                        v.light_timing = match v.light_timing {
+                            Some(_) => None,
+                            None => Some(LightTiming::auto(v.n_phases)),
+                        };
+                        state.dirty = true;
+                    }
+
+                    let Some(ref mut timing) = v.light_timing else {
+                        return;
+                    };
+                    if timing.splits.len() != v.n_phases {
+                        *timing = LightTiming::auto(v.n_phases);
+                        state.dirty = true;
+                    }
+
+                    minrow(4.0, || {
+                        mincolumn(2.0, || {
+                            textc(on_primary_container(), "Cycle");
+                            let min_cycle = LightTiming::MIN_PHASE * v.n_phases as u16;
+                            let mut cycle = timing.cycle as f32;
+                            if toolbox::updown_value(&mut cycle, 2.0, "s") {
+                                timing.cycle =
+                                    (cycle as u16).min(LightTiming::MAX_CYCLE).max(min_cycle);
+                                state.dirty = true;
+                            }
+                        });
+                        for (i, split) in timing.splits.iter_mut().enumerate() {
+                            mincolumn(2.0, || {
+                                textc(on_primary_container(), format!("Phase {}", i + 1));
+                                let mut percent = *split * 100.0;
+                                if toolbox::updown_value(&mut percent, 5.0, "%") {
+                                    *split = percent.clamp(5.0, 100.0) / 100.0;
+                                    state.dirty = true;
+                                }
+                            });
+                        }
+                    });
+                });
+            }
+
+            // Turns between each pair of roads
+            if !v.turn_toggles.is_empty() {
+                mincolumn(2.0, || {
+                    for toggle in &v.turn_toggles {
+                        let enabled = toggle.turns.iter().all(|id| !v.disabled_turns.contains(id));
+                        let button = if enabled {
+                            button_primary(&toggle.label)
+                        } else {
+                            button_secondary(&toggle.label)
+                        };
+                        if button.show().clicked {
+                            for id in &toggle.turns {
+                                if enabled {
+                                    v.disabled_turns.insert(*id);
+                                } else {
+                                    v.disabled_turns.remove(id);
+                                }
+                            }
+                            state.dirty = true;
+                        }
+                    }
+                });
+            }
         });
     });
 }
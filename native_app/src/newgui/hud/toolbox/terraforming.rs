@@ -30,13 +30,23 @@ pub fn terraform_properties(uiw: &UiWorld) {
                 (TerraformKind::Level, "Level", "terraforming_level"),
                 (TerraformKind::Slope, "Slope", "terraforming_slope"),
                 (TerraformKind::Erode, "Erode", "terraforming_erode"),
+                (
+                    TerraformKind::Water,
+                    "Water (paint/remove)",
+                    "terraforming_water",
+                ),
             ];
 
             for (kind, label, icon) in terraform_choices {
                 column(|| {
                     let enabled = state.kind == *kind;
-                    if primary_image_button(texs.get(icon), Vec2::new(64.0, 64.0), enabled, *label)
-                        .clicked
+                    if primary_image_button(
+                        texs.try_get(icon),
+                        Vec2::new(64.0, 64.0),
+                        enabled,
+                        *label,
+                    )
+                    .clicked
                     {
                         state.kind = *kind;
                     }
@@ -104,6 +114,12 @@ pub fn terraform_properties(uiw: &UiWorld) {
             }
 
             updown_value(&mut state.amount, 100.0, "");
+
+            if state.kind == TerraformKind::Water {
+                fixed_spacer((30.0, 0.0));
+                updown_value(&mut state.water_depth, 5.0, "m deep");
+                state.water_depth = state.water_depth.clamp(5.0, 35.0);
+            }
         });
     });
 }
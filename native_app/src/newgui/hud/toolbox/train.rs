@@ -1,12 +1,21 @@
+use geom::Vec2;
 use goryak::{mincolumn, minrow, outline, padxy};
-use prototypes::{prototypes_iter, RollingStockID, RollingStockPrototype};
+use prototypes::{
+    prototypes_iter, BuildingGen, PassengerStationPrototype, RollingStockID, RollingStockPrototype,
+};
+use simulation::map::BuildingKind;
+use simulation::milestones::lock_reason;
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
 use yakui::widgets::List;
 use yakui::{button, divider, label, CrossAxisAlignment, MainAxisAlignment};
 
 use crate::newgui::addtrain::TrainSpawnResource;
+use crate::newgui::specialbuilding::{SpecialBuildKind, SpecialBuildingResource};
+use crate::newgui::Tool;
 use crate::uiworld::UiWorld;
 
-pub fn train_properties(uiw: &UiWorld) {
+pub fn train_properties(uiw: &UiWorld, sim: &Simulation) {
     let mut state = uiw.write::<TrainSpawnResource>();
 
     padxy(0.0, 0.0, || {
@@ -25,6 +34,18 @@ pub fn train_properties(uiw: &UiWorld) {
                 label(format!("Total Lenght: {} m", state.total_lenght.ceil()));
             });
 
+            mincolumn(0.1, || {
+                for proto in prototypes_iter::<PassengerStationPrototype>() {
+                    if let Some(reason) = lock_reason(sim, &proto.name) {
+                        label(format!("{} ({})", proto.label, reason));
+                        continue;
+                    }
+                    if button(proto.label.clone()).clicked {
+                        passenger_station_tool(uiw, proto);
+                    }
+                }
+            });
+
             mincolumn(0.5, || {
                 minrow(0.0, || {
                     let mut remove: Option<usize> = None;
@@ -60,6 +81,29 @@ pub fn train_properties(uiw: &UiWorld) {
     });
 }
 
+/// Stations are placed next to existing tracks, trains start serving them once there are two
+fn passenger_station_tool(uiw: &UiWorld, proto: &'static PassengerStationPrototype) {
+    *uiw.write::<Tool>() = Tool::SpecialBuilding;
+
+    let kind = BuildingKind::RailPassengerStation(proto.id);
+    uiw.write::<SpecialBuildingResource>().opt = Some(SpecialBuildKind {
+        make: Box::new(move |args| {
+            vec![WorldCommand::MapBuildSpecialBuilding {
+                pos: args.obb,
+                kind,
+                gen: BuildingGen::NoWalkway {
+                    door_pos: Vec2::ZERO,
+                },
+                zone: None,
+                connected_road: args.connected_road,
+            }]
+        }),
+        size: proto.size,
+        asset: proto.asset.clone(),
+        road_snap: true,
+    });
+}
+
 /*
 if ui.button(freightstation).clicked() {
    *uiworld.write::<Tool>() = Tool::SpecialBuilding;
@@ -0,0 +1,45 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{button_primary, button_secondary, fixed_spacer, padxy};
+use simulation::map::ZoningKind;
+
+use crate::newgui::hud::toolbox::updown_value;
+use crate::newgui::zoning::ZoningResource;
+use crate::uiworld::UiWorld;
+
+pub fn zoning_properties(uiw: &UiWorld) {
+    let state = &mut *uiw.write::<ZoningResource>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            let choices = [
+                (Some(ZoningKind::Residential), "Residential"),
+                (Some(ZoningKind::Commercial), "Commercial"),
+                (Some(ZoningKind::Industrial), "Industrial"),
+                (None, "Erase"),
+            ];
+
+            for (kind, label) in choices {
+                let b = if state.kind == kind {
+                    button_primary(label)
+                } else {
+                    button_secondary(label)
+                };
+                if b.show().clicked {
+                    state.kind = kind;
+                }
+            }
+
+            fixed_spacer((30.0, 0.0));
+
+            if updown_value(&mut state.radius, 10.0, "m") {
+                state.radius = state.radius.clamp(10.0, 500.0);
+            }
+        });
+    });
+}
@@ -0,0 +1,118 @@
+use goryak::{
+    button_primary, button_secondary, minrow, on_secondary_container, text_edit, textc, Window,
+};
+use serde::{Deserialize, Serialize};
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::CameraView;
+use crate::uiworld::UiWorld;
+
+pub const N_BOOKMARKS: usize = 10;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub view: CameraView,
+}
+
+/// Named camera views the player can jump between, stored with F-keys.
+/// Saved with the game.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CameraBookmarks {
+    pub slots: [Option<CameraBookmark>; N_BOOKMARKS],
+}
+
+impl CameraBookmarks {
+    /// Stores the view into the slot, keeping the name if the slot was already used
+    pub fn store(&mut self, slot: usize, view: CameraView) {
+        let Some(s) = self.slots.get_mut(slot) else {
+            return;
+        };
+        match s {
+            Some(b) => b.view = view,
+            None => {
+                *s = Some(CameraBookmark {
+                    name: format!("Bookmark {}", slot + 1),
+                    view,
+                })
+            }
+        }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<CameraView> {
+        self.slots.get(slot)?.as_ref().map(|b| b.view)
+    }
+}
+
+/// Applies the bookmark shortcuts: Ctrl+F1..F10 stores the camera, F1..F10 recalls it.
+/// Recalling an empty slot does nothing.
+fn bookmark_shortcuts(uiw: &UiWorld) {
+    let inp = uiw.read::<InputMap>();
+    let mut bookmarks = uiw.write::<CameraBookmarks>();
+
+    for act in &inp.just_act {
+        match *act {
+            InputAction::SaveBookmark(slot) => {
+                let view = uiw.camera().view();
+                bookmarks.store(slot as usize, view);
+            }
+            InputAction::RecallBookmark(slot) => {
+                if let Some(view) = bookmarks.get(slot as usize) {
+                    uiw.camera_mut().transition_to(view);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Bookmarks window
+/// Lists the camera bookmarks, allows to rename them and to jump to them
+pub fn bookmarks(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+    bookmark_shortcuts(uiw);
+
+    Window {
+        title: t!("window-bookmarks").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut bookmarks = uiw.write::<CameraBookmarks>();
+
+        if bookmarks.slots.iter().all(Option::is_none) {
+            textc(
+                on_secondary_container(),
+                "No bookmarks, use Ctrl+F1..F10 to store the camera",
+            );
+        }
+
+        let mut jump = None;
+        let mut remove = None;
+        for (i, slot) in bookmarks.slots.iter_mut().enumerate() {
+            let Some(b) = slot else {
+                continue;
+            };
+            minrow(5.0, || {
+                textc(on_secondary_container(), format!("F{}", i + 1));
+                text_edit(150.0, &mut b.name, "Name");
+                if button_primary("Go").show().clicked {
+                    jump = Some(b.view);
+                }
+                if button_secondary("Delete").show().clicked {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if let Some(i) = remove {
+            bookmarks.slots[i] = None;
+        }
+        if let Some(view) = jump {
+            uiw.camera_mut().transition_to(view);
+        }
+    });
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeSet;
+
+use yakui::widgets::{CountGrid, Pad};
+use yakui::MainAxisSize;
+
+use goryak::{
+    button_primary, button_secondary, error, minrow, on_primary_container, padxy, primary, textc,
+    VertScrollSize, Window,
+};
+use prototypes::Money;
+use simulation::economy::{BudgetReason, Government, LedgerDay, LOAN_DAILY_INTEREST, MAX_LOAN};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Money borrowed or repaid by one click
+const LOAN_STEP: Money = Money::new_bucks(10_000);
+
+/// Budget window
+/// The money received and spent by the government today and over the last days, by reason,
+/// and the loan
+pub fn budget(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-budget").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let gvt = sim.read::<Government>();
+        let today = gvt.ledger.today();
+        let trailing = gvt.ledger.trailing();
+        let days = gvt.ledger.past().count();
+
+        let reasons: BTreeSet<BudgetReason> = today
+            .entries
+            .keys()
+            .chain(trailing.entries.keys())
+            .copied()
+            .collect();
+
+        VertScrollSize::Fixed(400.0).show(|| {
+            let mut grid = CountGrid::col(3);
+            grid.main_axis_size = MainAxisSize::Min;
+            grid.show(|| {
+                let cell = |text: String| padxy(5.0, 3.0, || textc(on_primary_container(), text));
+                let amount = |m: Money| {
+                    let col = if m < Money::ZERO {
+                        error()
+                    } else {
+                        on_primary_container()
+                    };
+                    padxy(5.0, 3.0, || textc(col, m.to_string()));
+                };
+                let get =
+                    |day: &LedgerDay, reason| day.entries.get(&reason).copied().unwrap_or_default();
+
+                padxy(5.0, 3.0, || textc(primary(), "Reason"));
+                padxy(5.0, 3.0, || textc(primary(), "Today"));
+                padxy(5.0, 3.0, || textc(primary(), format!("Last {} days", days)));
+
+                for reason in reasons {
+                    cell(reason.label());
+                    amount(get(today, reason));
+                    amount(get(&trailing, reason));
+                }
+
+                cell("Income".to_string());
+                amount(today.income());
+                amount(trailing.income());
+                cell("Expenses".to_string());
+                amount(today.expenses());
+                amount(trailing.expenses());
+                cell("Net".to_string());
+                amount(today.net());
+                amount(trailing.net());
+            });
+        });
+
+        padxy(5.0, 5.0, || {
+            textc(
+                on_primary_container(),
+                format!(
+                    "Loan: {} of {}, {} of interest per day",
+                    gvt.loan,
+                    MAX_LOAN,
+                    gvt.loan * LOAN_DAILY_INTEREST
+                ),
+            )
+        });
+
+        let can_borrow = gvt.loan < MAX_LOAN;
+        let can_repay = gvt.loan > Money::ZERO && gvt.money > Money::ZERO;
+        drop(gvt);
+
+        minrow(5.0, || {
+            if can_borrow
+                && button_primary(format!("Borrow {}", LOAN_STEP))
+                    .show()
+                    .clicked
+            {
+                uiw.commands().push(WorldCommand::TakeLoan(LOAN_STEP));
+            }
+            if can_repay
+                && button_secondary(format!("Repay {}", LOAN_STEP))
+                    .show()
+                    .clicked
+            {
+                uiw.commands().push(WorldCommand::RepayLoan(LOAN_STEP));
+            }
+        });
+    });
+}
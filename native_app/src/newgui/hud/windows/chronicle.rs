@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use goryak::{
+    mincolumn, minrow, on_secondary_container, primary, primary_link, selectable_label_primary,
+    textc, VertScrollSize, Window,
+};
+use prototypes::{GameInstant, GameTime};
+use simulation::chronicle::{Chronicle, ChronicleKind};
+use simulation::notifications::NotificationTarget;
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::newgui::notifications::jump_to;
+use crate::uiworld::UiWorld;
+
+#[derive(Default)]
+pub struct ChronicleState {
+    /// Kinds of entries hidden from the list
+    pub hidden: BTreeSet<ChronicleKind>,
+}
+
+/// Chronicle window
+/// The history of the city grouped by year, the newest first
+pub fn chronicle(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-chronicle").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<ChronicleState>();
+        minrow(5.0, || {
+            for kind in ChronicleKind::ALL {
+                let shown = !state.hidden.contains(&kind);
+                if selectable_label_primary(shown, kind.label()).clicked {
+                    if shown {
+                        state.hidden.insert(kind);
+                    } else {
+                        state.hidden.remove(&kind);
+                    }
+                }
+            }
+        });
+
+        let chronicle = sim.read::<Chronicle>();
+        let mut entries = chronicle
+            .entries()
+            .iter()
+            .rev()
+            .filter(|e| !state.hidden.contains(&e.kind))
+            .peekable();
+        if entries.peek().is_none() {
+            textc(on_secondary_container(), "Nothing happened yet");
+            return;
+        }
+
+        let mut jump = None;
+        VertScrollSize::Fixed(400.0).show(|| {
+            mincolumn(2.0, || {
+                let mut year = None;
+                for e in entries {
+                    let y = GameTime::new(e.tick).daytime.year();
+                    if year != Some(y) {
+                        year = Some(y);
+                        textc(primary(), format!("Year {}", y));
+                    }
+                    minrow(10.0, || {
+                        textc(on_secondary_container(), GameInstant(e.tick).to_string());
+                        match e.pos {
+                            Some(pos) => {
+                                if primary_link(e.text.clone()) {
+                                    jump = Some(pos);
+                                }
+                            }
+                            None => {
+                                textc(on_secondary_container(), e.text.clone());
+                            }
+                        }
+                    });
+                }
+            });
+        });
+        drop(chronicle);
+        drop(state);
+
+        if let Some(pos) = jump {
+            jump_to(uiw, sim, NotificationTarget::Position(pos));
+        }
+    });
+}
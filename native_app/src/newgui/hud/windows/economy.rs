@@ -1,3 +1,4 @@
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashSet;
 
 use yakui::paint::PaintMesh;
@@ -7,18 +8,23 @@ use yakui::{
     MainAxisSize, Vec2,
 };
 
-use engine::Tesselator;
+use engine::{IndexType, MeshVertex, Tesselator};
 use geom::AABB;
 use goryak::{
-    constrained_viewport, mincolumn, minrow, on_primary_container, padxy, pady,
-    selectable_label_primary, sized_canvas, textc, VertScrollSize, Window,
+    constrained_viewport, dragvalue, mincolumn, minrow, on_primary_container, padxy, pady,
+    primary_link, selectable_label_primary, sized_canvas, text_edit, textc, VertScrollSize, Window,
 };
-use prototypes::{ItemID, DELTA_F64};
+use prototypes::{Education, ItemID, ItemPrototype, Money, DELTA_F64, HOURS_PER_DAY};
 use simulation::economy::{
-    EcoStats, ItemHistories, Market, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
+    EcoStats, EconomyHistory, ElectricityBilling, Government, HistoryLevel, ItemHistories, Market,
+    TradePolicy, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
 };
-use simulation::Simulation;
+use simulation::notifications::NotificationTarget;
+use simulation::souls::happiness::CityStats;
+use simulation::world_command::WorldCommand;
+use simulation::{AnyEntity, Simulation};
 
+use crate::newgui::notifications::jump_to;
 use crate::uiworld::UiWorld;
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -26,7 +32,10 @@ pub enum EconomyTab {
     #[default]
     ImportExports,
     InternalTrade,
-    MarketPrices,
+    Market,
+    TradePolicy,
+    Electricity,
+    Population,
 }
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -36,18 +45,71 @@ pub enum HistoryType {
     Items,
 }
 
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum MarketColumn {
+    #[default]
+    Name,
+    Demand,
+    Supply,
+    LocalTrades,
+    Imports,
+    Exports,
+    BaseValue,
+    Price,
+}
+
+impl MarketColumn {
+    const ALL: [MarketColumn; 8] = [
+        MarketColumn::Name,
+        MarketColumn::Demand,
+        MarketColumn::Supply,
+        MarketColumn::LocalTrades,
+        MarketColumn::Imports,
+        MarketColumn::Exports,
+        MarketColumn::BaseValue,
+        MarketColumn::Price,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            MarketColumn::Name => "Item",
+            MarketColumn::Demand => "Buying",
+            MarketColumn::Supply => "Selling",
+            MarketColumn::LocalTrades => "Local (24h)",
+            MarketColumn::Imports => "Imports (24h)",
+            MarketColumn::Exports => "Exports (24h)",
+            MarketColumn::BaseValue => "Base value",
+            MarketColumn::Price => "Price",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MarketTabState {
+    pub sort: MarketColumn,
+    pub descending: bool,
+    /// Only the items whose name contains this are listed
+    pub filter: String,
+    /// Item whose orders and price history are shown under the list
+    pub expanded: Option<ItemID>,
+}
+
 #[derive(Default)]
 pub struct EconomyState {
     pub curlevel: usize,
     pub tab: EconomyTab,
     pub hist_type: HistoryType,
+    pub market: MarketTabState,
 }
 
+/// Orders shown on each side of the order book of an item
+const ORDER_BOOK_LEN: usize = 10;
+
 /// Economy window
 /// Shows the economy stats
 pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
     Window {
-        title: "Economy".into(),
+        title: t!("window-economy").into(),
         pad: Pad::all(10.0),
         radius: 10.0,
         opened,
@@ -60,7 +122,10 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             let tabs = &[
                 ("Import/Exports", EconomyTab::ImportExports),
                 ("Internal Trade", EconomyTab::InternalTrade),
-                ("Market Prices", EconomyTab::MarketPrices),
+                ("Market", EconomyTab::Market),
+                ("Trade Policy", EconomyTab::TradePolicy),
+                ("Electricity", EconomyTab::Electricity),
+                ("Population", EconomyTab::Population),
             ];
 
             for (label, tab) in tabs {
@@ -101,8 +166,9 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             .collect();
         let EconomyState {
             curlevel,
-            ref tab,
+            tab,
             hist_type,
+            ..
         } = *state;
 
         let render_history = |history: &ItemHistories, hist_type: HistoryType| {
@@ -216,23 +282,7 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
                     drop(filterid_b);
 
                     padxy(5.0, 5.0, || {
-                        sized_canvas(Vec2::new(plot_size_x, 200.0), Color::BLACK, move |paint| {
-                            let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
-
-                            let [x, y]: [f32; 2] = rect.pos().into();
-                            let [_sx, sy]: [f32; 2] = rect.size().into();
-
-                            paint.paint.add_mesh(PaintMesh::new(
-                                vertices.into_iter().map(|v| {
-                                    yakui::paint::Vertex::new(
-                                        [x + v.position[0], y + sy - v.position[1]],
-                                        v.uv,
-                                        v.color,
-                                    )
-                                }),
-                                indices.into_iter().map(|x| x as _),
-                            ));
-                        });
+                        mesh_canvas(Vec2::new(plot_size_x, 200.0), vertices, indices);
                     });
 
                     VertScrollSize::Fixed(300.0).show(|| {
@@ -321,30 +371,381 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             EconomyTab::InternalTrade => {
                 render_history(&ecostats.internal_trade, HistoryType::Items);
             }
-            EconomyTab::MarketPrices => {
-                render_market_prices(sim);
+            EconomyTab::Market => {
+                render_market(uiw, sim, &mut state.market);
+            }
+            EconomyTab::TradePolicy => {
+                render_trade_policy(uiw, sim);
+            }
+            EconomyTab::Electricity => {
+                render_electricity(uiw, sim);
+            }
+            EconomyTab::Population => {
+                render_population(sim);
             }
         }
     });
 }
 
-fn render_market_prices(sim: &Simulation) {
+/// One line of the market tab
+struct MarketRow {
+    id: ItemID,
+    name: &'static str,
+    demand: u64,
+    supply: u64,
+    local: u64,
+    imports: u64,
+    exports: u64,
+    ext_value: Money,
+    price: Money,
+}
+
+impl MarketRow {
+    fn cmp(&self, other: &Self, column: MarketColumn) -> Ordering {
+        match column {
+            MarketColumn::Name => self.name.cmp(other.name),
+            MarketColumn::Demand => self.demand.cmp(&other.demand),
+            MarketColumn::Supply => self.supply.cmp(&other.supply),
+            MarketColumn::LocalTrades => self.local.cmp(&other.local),
+            MarketColumn::Imports => self.imports.cmp(&other.imports),
+            MarketColumn::Exports => self.exports.cmp(&other.exports),
+            MarketColumn::BaseValue => self.ext_value.cmp(&other.ext_value),
+            MarketColumn::Price => self.price.cmp(&other.price),
+        }
+    }
+}
+
+/// Every item of the market with its open orders and the trades of the last day.
+/// Clicking an item shows its order book and its price over the last 30 days.
+fn render_market(uiw: &UiWorld, sim: &Simulation, state: &mut MarketTabState) {
     let market = sim.read::<Market>();
+    let history = sim.read::<EconomyHistory>();
+
+    let filter = state.filter.trim().to_lowercase();
+    let mut rows: Vec<MarketRow> = market
+        .iter()
+        .filter_map(|(&id, m)| {
+            let name = id.prototype().name.as_str();
+            if !filter.is_empty() && !name.to_lowercase().contains(&filter) {
+                return None;
+            }
+            let day = history.last_hours(id, HOURS_PER_DAY as usize);
+            Some(MarketRow {
+                id,
+                name,
+                demand: m.total_demand(),
+                supply: m.total_supply(),
+                local: day.traded.saturating_sub(day.imports + day.exports),
+                imports: day.imports,
+                exports: day.exports,
+                ext_value: m.ext_value,
+                price: m.price(),
+            })
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        let ord = a.cmp(b, state.sort);
+        if state.descending {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+
+    padxy(5.0, 5.0, || {
+        text_edit(200.0, &mut state.filter, "Filter items");
+    });
 
     VertScrollSize::Fixed(300.0).show(|| {
-        let mut grid = CountGrid::col(2);
+        let mut grid = CountGrid::col(MarketColumn::ALL.len());
         grid.main_axis_size = MainAxisSize::Min;
         grid.show(|| {
-            for (id, market) in market.iter() {
+            for column in MarketColumn::ALL {
+                let sorted = state.sort == column;
+                let label = match (sorted, state.descending) {
+                    (false, _) => column.label().to_string(),
+                    (true, false) => format!("{} ^", column.label()),
+                    (true, true) => format!("{} v", column.label()),
+                };
                 padxy(5.0, 3.0, || {
-                    textc(on_primary_container(), &id.prototype().name)
+                    if selectable_label_primary(sorted, &label).clicked {
+                        if sorted {
+                            state.descending = !state.descending;
+                        } else {
+                            state.sort = column;
+                            // the biggest numbers are the interesting ones
+                            state.descending = column != MarketColumn::Name;
+                        }
+                    }
                 });
+            }
+
+            for row in &rows {
+                let expanded = state.expanded == Some(row.id);
                 padxy(5.0, 3.0, || {
-                    textc(on_primary_container(), market.ext_value.to_string())
+                    if selectable_label_primary(expanded, row.name).clicked {
+                        state.expanded = if expanded { None } else { Some(row.id) };
+                    }
                 });
+                for value in [
+                    row.demand.to_string(),
+                    row.supply.to_string(),
+                    row.local.to_string(),
+                    row.imports.to_string(),
+                    row.exports.to_string(),
+                    row.ext_value.to_string(),
+                    row.price.to_string(),
+                ] {
+                    padxy(5.0, 3.0, || textc(on_primary_container(), value));
+                }
             }
         });
     });
+
+    let Some(id) = state.expanded else {
+        return;
+    };
+    let Some(m) = market.inner().get(&id) else {
+        state.expanded = None;
+        return;
+    };
+
+    padxy(5.0, 5.0, || {
+        textc(
+            on_primary_container(),
+            format!("{}: price over the last 30 days", id.prototype().name),
+        );
+    });
+    let prices: Vec<i64> = history
+        .item_series(id, HistoryLevel::Hourly)
+        .iter()
+        .map(|p| p.avg_price.inner())
+        .collect();
+    padxy(5.0, 5.0, || {
+        if prices.len() < 2 {
+            textc(on_primary_container(), "No history yet");
+            return;
+        }
+        sparkline(&prices, Vec2::new(400.0, 60.0));
+    });
+
+    let mut clicked = None;
+    minrow(20.0, || {
+        let mut buy: Vec<_> = m
+            .buy_orders()
+            .map(|(soul, o)| (soul, o.qty, o.pos))
+            .collect();
+        let mut sell: Vec<_> = m
+            .sell_orders()
+            .map(|(soul, o)| (soul, o.qty, o.pos))
+            .collect();
+        for (title, orders) in [("Buy orders", &mut buy), ("Sell orders", &mut sell)] {
+            orders.sort_by_key(|&(_, qty, _)| Reverse(qty));
+            orders.truncate(ORDER_BOOK_LEN);
+            mincolumn(0.0, || {
+                padxy(5.0, 3.0, || textc(on_primary_container(), title));
+                let mut grid = CountGrid::col(3);
+                grid.main_axis_size = MainAxisSize::Min;
+                grid.show(|| {
+                    for &(soul, qty, pos) in orders.iter() {
+                        padxy(5.0, 3.0, || {
+                            if primary_link(soul.to_string()) {
+                                clicked = Some((soul, pos));
+                            }
+                        });
+                        padxy(5.0, 3.0, || textc(on_primary_container(), qty.to_string()));
+                        padxy(5.0, 3.0, || {
+                            textc(
+                                on_primary_container(),
+                                format!("{:.0}, {:.0}", pos.x, pos.y),
+                            )
+                        });
+                    }
+                });
+            });
+        }
+    });
+
+    drop(market);
+    drop(history);
+    if let Some((soul, pos)) = clicked {
+        // the road connections are not entities, their orders are placed at the connection
+        let target = match AnyEntity::try_from(soul) {
+            Ok(e) => NotificationTarget::Entity(e),
+            Err(_) => NotificationTarget::Position(pos.z0()),
+        };
+        jump_to(uiw, sim, target);
+    }
+}
+
+/// The values as a line filling the canvas, from the lowest to the highest
+fn sparkline(values: &[i64], size: Vec2) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    let cull_rect = AABB::new_ll_size([0.0, 0.0].into(), [size.x, size.y].into());
+    let mut tess = Tesselator::new(&mut vertices, &mut indices, Some(cull_rect), 15.0);
+    tess.set_color([1.0f32, 1.0, 1.0, 1.0]);
+
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    let span = (max - min).max(1) as f32;
+    let step = size.x / (values.len() - 1).max(1) as f32;
+    let positions: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            let y = 2.0 + (v - min) as f32 / span * (size.y - 4.0);
+            geom::vec3(i as f32 * step, y, 0.0)
+        })
+        .collect();
+    tess.draw_polyline(&positions, 1.5, false);
+
+    mesh_canvas(size, vertices, indices);
+}
+
+/// Canvas showing a mesh tesselated with y going up
+fn mesh_canvas(size: Vec2, vertices: Vec<MeshVertex>, indices: Vec<IndexType>) {
+    sized_canvas(size, Color::BLACK, move |paint| {
+        let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+
+        let [x, y]: [f32; 2] = rect.pos().into();
+        let [_sx, sy]: [f32; 2] = rect.size().into();
+
+        paint.paint.add_mesh(PaintMesh::new(
+            vertices.into_iter().map(|v| {
+                yakui::paint::Vertex::new(
+                    [x + v.position[0], y + sy - v.position[1]],
+                    v.uv,
+                    v.color,
+                )
+            }),
+            indices.into_iter().map(|x| x as _),
+        ));
+    });
+}
+
+fn render_trade_policy(uiw: &UiWorld, sim: &Simulation) {
+    let mut policy = sim.read::<TradePolicy>().clone();
+    let tariff_income = sim.read::<Government>().tariff_income;
+    let mut changed = false;
+
+    padxy(5.0, 5.0, || {
+        textc(
+            on_primary_container(),
+            format!("Tariff income: {}", tariff_income),
+        )
+    });
+
+    let mut grid = CountGrid::col(3);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        padxy(5.0, 3.0, || textc(on_primary_container(), ""));
+        padxy(5.0, 3.0, || textc(on_primary_container(), "Import tariff"));
+        padxy(5.0, 3.0, || textc(on_primary_container(), "Export tax"));
+
+        padxy(5.0, 3.0, || textc(on_primary_container(), "All items"));
+        padxy(5.0, 3.0, || {
+            changed |= dragvalue()
+                .minmax(0.0..2.0)
+                .step(0.01)
+                .show(&mut policy.global.import);
+        });
+        padxy(5.0, 3.0, || {
+            changed |= dragvalue()
+                .minmax(0.0..1.0)
+                .step(0.01)
+                .show(&mut policy.global.export);
+        });
+    });
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(3);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for item in ItemPrototype::iter() {
+                let mut tariffs = policy.tariffs(item.id);
+                padxy(5.0, 3.0, || textc(on_primary_container(), &item.name));
+                let mut item_changed = false;
+                padxy(5.0, 3.0, || {
+                    item_changed |= dragvalue()
+                        .minmax(0.0..2.0)
+                        .step(0.01)
+                        .show(&mut tariffs.import);
+                });
+                padxy(5.0, 3.0, || {
+                    item_changed |= dragvalue()
+                        .minmax(0.0..1.0)
+                        .step(0.01)
+                        .show(&mut tariffs.export);
+                });
+                if item_changed {
+                    policy.per_item.insert(item.id, tariffs);
+                    changed = true;
+                }
+            }
+        });
+    });
+
+    if changed {
+        uiw.commands().push(WorldCommand::SetTradePolicy(policy));
+    }
+}
+
+fn render_electricity(uiw: &UiWorld, sim: &Simulation) {
+    let price = sim.read::<Government>().electricity_price;
+    let last_total = sim.read::<ElectricityBilling>().last_total;
+
+    padxy(5.0, 5.0, || {
+        textc(
+            on_primary_container(),
+            format!("Billed yesterday: {}", last_total),
+        )
+    });
+
+    minrow(5.0, || {
+        padxy(5.0, 3.0, || {
+            textc(on_primary_container(), "Price per kWh ($)")
+        });
+        let mut bucks = price.inner() as f64 / 1000.0;
+        if dragvalue().minmax(0.0..10.0).step(0.01).show(&mut bucks) {
+            uiw.commands()
+                .push(WorldCommand::SetElectricityPrice(Money::new_inner(
+                    (bucks * 1000.0).round() as i64,
+                )));
+        }
+    });
+}
+
+fn render_population(sim: &Simulation) {
+    let stats = sim.read::<CityStats>();
+
+    let mut grid = CountGrid::col(2);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        let row = |label: &str, value: String| {
+            padxy(5.0, 3.0, || textc(on_primary_container(), label));
+            padxy(5.0, 3.0, || textc(on_primary_container(), value));
+        };
+
+        row("Population", stats.population.to_string());
+        row("Births", stats.births.to_string());
+        row("Deaths", stats.deaths.to_string());
+
+        let last = stats.age_histogram().len() - 1;
+        for (i, &n) in stats.age_histogram().iter().enumerate() {
+            let label = if i == last {
+                format!("Aged {}+", i * 10)
+            } else {
+                format!("Aged {}-{}", i * 10, i * 10 + 9)
+            };
+            row(&label, n.to_string());
+        }
+
+        for (level, &n) in Education::ALL.iter().zip(stats.education.iter()) {
+            row(level.label(), n.to_string());
+        }
+    });
 }
 
 /*
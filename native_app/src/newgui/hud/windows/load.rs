@@ -1,20 +1,108 @@
 #![allow(unused)]
-use crate::uiworld::{SaveLoadState, UiWorld};
+use crate::newgui::map_editor::MapEditorState;
+use crate::newgui::windows::new_game;
+use crate::newgui::windows::new_game::MapPreview;
+use crate::newgui::windows::settings::Settings;
+use crate::uiworld::{CurrentSave, SaveLoadState, UiWorld};
+use common::saveload::CheckedCompressedBincode;
+use common::FastMap;
 use egui::{Color32, DroppedFile, Widget};
+use engine::yakui::YakuiWrapper;
+use engine::{GfxContext, TextureBuilder};
 use goryak::{
-    button_primary, error, minrow, on_primary, on_secondary_container, primary, textc, ProgressBar,
+    button_primary, button_secondary, dragvalue, error, mincolumn, minrow, on_primary,
+    on_secondary_container, primary, selectable_label_primary, text_edit, textc, ProgressBar,
     Window,
 };
+use simulation::gameplay::GameplayParams;
+use simulation::map::procgen::MapGenParams;
+use simulation::map::Environment;
+use simulation::map_files::{list_maps, load_map, MAX_MAP_SIZE, MIN_MAP_SIZE};
+use simulation::saves::{delete_save, is_valid_save_name, rename_save, world_name};
 use simulation::utils::scheduler::SeqSchedule;
-use simulation::Simulation;
+use simulation::{SaveMetadata, Simulation, SimulationOptions};
 use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::SystemTime;
 use yakui::widgets::Pad;
-use yakui::{Color, Vec2};
+use yakui::{image, Color, TextureId, Vec2};
+
+/// Size of the thumbnails in the save list
+const THUMBNAIL_WIDTH: f32 = 128.0;
+/// Side of the preview of the generated map
+const PREVIEW_SIZE: f32 = 256.0;
 
 pub struct LoadState {
     curpath: Option<PathBuf>,
-    load_fail: String,
+    pub load_fail: String,
     has_save: bool,
+    was_opened: bool,
+
+    /// Named saves, the most recent first
+    saves: Vec<SaveMetadata>,
+    /// Reading the manifests happens on another thread
+    scan: Option<JoinHandle<Vec<SaveMetadata>>>,
+    /// Thumbnail textures by save name and time of the save
+    thumbnails: FastMap<(String, u64), Option<TextureId>>,
+    /// Save being renamed and its new name
+    renaming: Option<(String, String)>,
+    /// Save to delete once confirmed
+    confirm_delete: Option<String>,
+    /// The new game being set up
+    new_game: Option<NewGame>,
+    /// Image of the map the new game would be generated with
+    preview: MapPreview,
+    /// Map the new game starts on, a generated terrain if None
+    new_game_map: Option<String>,
+    /// Terrain of the map about to be edited
+    new_map: Option<NewMap>,
+    /// Maps made in the map editor
+    maps: Vec<String>,
+}
+
+/// Parameters of a new game
+struct NewGame {
+    params: GameplayParams,
+    mapgen: MapGenParams,
+    /// Chunks on each side of the generated map
+    size: u16,
+    /// Seed as typed
+    seed: String,
+}
+
+impl Default for NewGame {
+    fn default() -> Self {
+        let mapgen = MapGenParams::default();
+        Self {
+            params: GameplayParams::default(),
+            mapgen,
+            size: SimulationOptions::default().terrain_size,
+            seed: mapgen.seed.to_string(),
+        }
+    }
+}
+
+/// Terrain the map editor starts from
+#[derive(Clone, PartialEq, Eq)]
+enum MapStart {
+    Flat,
+    Generated,
+    Map(String),
+}
+
+struct NewMap {
+    start: MapStart,
+    /// Size in chunks of a flat or generated terrain
+    size: u16,
+}
+
+impl Default for NewMap {
+    fn default() -> Self {
+        Self {
+            start: MapStart::Flat,
+            size: 20,
+        }
+    }
 }
 
 impl Default for LoadState {
@@ -23,15 +111,83 @@ impl Default for LoadState {
             curpath: None,
             load_fail: String::new(),
             has_save: std::fs::metadata("world/world_replay.json").is_ok(),
+            was_opened: false,
+            saves: vec![],
+            scan: None,
+            thumbnails: Default::default(),
+            renaming: None,
+            confirm_delete: None,
+            new_game: None,
+            preview: MapPreview::default(),
+            new_game_map: None,
+            new_map: None,
+            maps: vec![],
+        }
+    }
+}
+
+impl LoadState {
+    pub fn rescan(&mut self) {
+        self.scan = Some(std::thread::spawn(SaveMetadata::scan));
+        self.maps = list_maps();
+        if !self
+            .maps
+            .iter()
+            .any(|m| Some(m) == self.new_game_map.as_ref())
+        {
+            self.new_game_map = None;
+        }
+    }
+
+    fn poll_scan(&mut self) {
+        if !self.scan.as_ref().is_some_and(|s| s.is_finished()) {
+            return;
+        }
+        if let Some(scan) = self.scan.take() {
+            self.saves = scan.join().unwrap_or_default();
         }
     }
 }
 
+/// Loads the thumbnails of the scanned saves and the preview of the new map, it needs the graphics
+/// context so it is called by the game loop
+pub fn load_thumbnails(uiw: &UiWorld, gfx: &mut GfxContext, yakui: &mut YakuiWrapper) {
+    let mut state = uiw.write::<LoadState>();
+    let state = &mut *state;
+    state.preview.upload(gfx, yakui);
+    for save in &state.saves {
+        let key = (save.name.clone(), save.saved_at);
+        if state.thumbnails.contains_key(&key) {
+            continue;
+        }
+        let tex = TextureBuilder::try_from_path(save.thumbnail_path())
+            .ok()
+            .map(|b| {
+                yakui.add_texture(
+                    &b.with_label("save thumbnail")
+                        .build(&gfx.device, &gfx.queue),
+                )
+            });
+        state.thumbnails.insert(key, tex);
+    }
+}
+
 /// Load window
-/// Allows to load a replay from disk and play it
-pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+/// Lists the named saves to load, rename or delete them.
+/// Also allows to load an autosave, or a replay from disk and play it, to start a new game and to
+/// open the map editor
+pub fn load(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    {
+        let mut state = uiw.write::<LoadState>();
+        if *opened && !state.was_opened {
+            state.rescan();
+        }
+        state.was_opened = *opened;
+        state.poll_scan();
+    }
+
     Window {
-        title: "Load".into(),
+        title: t!("window-load").into(),
         pad: Pad::all(10.0),
         radius: 10.0,
         opened,
@@ -39,9 +195,125 @@ pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
     }
     .show(|| {
         let mut state = uiw.write::<LoadState>();
+        let state = &mut *state;
 
         if button_primary("New Game").show().clicked {
-            uiw.write::<SaveLoadState>().please_load_sim = Some(Simulation::new(true));
+            state.new_game = match state.new_game {
+                Some(_) => None,
+                None => Some(NewGame::default()),
+            };
+        }
+        if let Some(ref mut new) = state.new_game {
+            new_game::difficulty_picker(&mut new.params);
+            if !state.maps.is_empty() {
+                minrow(5.0, || {
+                    textc(on_secondary_container(), "Map");
+                    if selectable_label_primary(state.new_game_map.is_none(), "Generated").clicked {
+                        state.new_game_map = None;
+                    }
+                    for map in &state.maps {
+                        let selected = state.new_game_map.as_ref() == Some(map);
+                        if selectable_label_primary(selected, map).clicked {
+                            state.new_game_map = Some(map.clone());
+                        }
+                    }
+                });
+            }
+            if state.new_game_map.is_none() {
+                new_game::mapgen_picker(&mut new.mapgen, &mut new.size, &mut new.seed);
+                let current = *sim.read::<SimulationOptions>();
+                if current.terrain_size > 0
+                    && button_secondary("Same map as this game").show().clicked
+                {
+                    new.mapgen = current.mapgen;
+                    new.size = current.terrain_size;
+                    new.seed = current.mapgen.seed.to_string();
+                }
+                state.preview.request(new.mapgen, new.size);
+                if let Some(tex) = state.preview.texture() {
+                    image(tex, Vec2::splat(PREVIEW_SIZE));
+                }
+            }
+            if button_primary("Start").show().clicked {
+                let opts = SimulationOptions {
+                    terrain_size: new.size,
+                    params: new.params,
+                    mapgen: new.mapgen,
+                    ..Default::default()
+                };
+                let sim = match state.new_game_map {
+                    Some(ref name) => load_map(name)
+                        .map(|terrain| Simulation::new_on_map(opts, terrain))
+                        .map_err(|e| format!("Failed to load the map {name}: {e}")),
+                    None => Ok(Simulation::new_with_options(opts)),
+                };
+                match sim {
+                    Ok(sim) => {
+                        uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+                        *uiw.write::<CurrentSave>() = CurrentSave::default();
+                        state.new_game = None;
+                        state.load_fail.clear();
+                    }
+                    Err(e) => state.load_fail = e,
+                }
+            }
+        }
+
+        if button_primary("Map editor").show().clicked {
+            state.new_map = match state.new_map {
+                Some(_) => None,
+                None => Some(NewMap::default()),
+            };
+        }
+        if let Some(ref mut new_map) = state.new_map {
+            if let Some(name) = new_map_picker(new_map, &state.maps) {
+                let terrain = match new_map.start {
+                    MapStart::Flat => Ok(Environment::flat(new_map.size, new_map.size)),
+                    MapStart::Generated => Ok(Environment::new(new_map.size, new_map.size)),
+                    MapStart::Map(ref name) => load_map(name),
+                };
+                match terrain {
+                    Ok(terrain) => {
+                        let mut slstate = uiw.write::<SaveLoadState>();
+                        slstate.please_load_sim = Some(Simulation::new_editor(terrain));
+                        slstate.please_edit_map = true;
+                        *uiw.write::<MapEditorState>() = MapEditorState { name, status: None };
+                        *uiw.write::<CurrentSave>() = CurrentSave::default();
+                        state.new_map = None;
+                        state.load_fail.clear();
+                    }
+                    Err(e) => state.load_fail = format!("Failed to load the map {name}: {e}"),
+                }
+            }
+        }
+
+        save_list(uiw, state);
+
+        let slots = uiw.read::<Settings>().auto_save_slots();
+        let autosaves = slots.list::<CheckedCompressedBincode>();
+        if autosaves.is_empty() {
+            textc(on_secondary_container(), "No autosave found");
+        }
+        for (name, modified) in autosaves {
+            minrow(10.0, || {
+                if button_primary(format!("Load {name}")).show().clicked {
+                    match Simulation::try_load_from_disk(&name) {
+                        Ok(sim) => {
+                            let mut slstate = uiw.write::<SaveLoadState>();
+                            slstate.please_load_sim = Some(sim);
+                            slstate.please_load_save = Some(name.clone());
+                            drop(slstate);
+                            *uiw.write::<CurrentSave>() = CurrentSave::default();
+                            state.load_fail.clear();
+                        }
+                        Err(e) => state.load_fail = format!("Failed to load {name}: {e}"),
+                    }
+                }
+                textc(
+                    on_secondary_container(),
+                    autosave_description(&name, modified),
+                );
+            });
         }
 
         if state.has_save {
@@ -123,3 +395,220 @@ pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
         }
     });
 }
+
+/// Terrain and size of the map to edit, returns the name of the map once the editor is opened
+fn new_map_picker(new_map: &mut NewMap, maps: &[String]) -> Option<String> {
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Terrain");
+        if selectable_label_primary(new_map.start == MapStart::Flat, "Flat").clicked {
+            new_map.start = MapStart::Flat;
+        }
+        if selectable_label_primary(new_map.start == MapStart::Generated, "Generated").clicked {
+            new_map.start = MapStart::Generated;
+        }
+        for map in maps {
+            let start = MapStart::Map(map.clone());
+            if selectable_label_primary(new_map.start == start, map).clicked {
+                new_map.start = start;
+            }
+        }
+    });
+
+    if !matches!(new_map.start, MapStart::Map(_)) {
+        minrow(5.0, || {
+            dragvalue()
+                .minmax(MIN_MAP_SIZE as f64..MAX_MAP_SIZE as f64)
+                .show(&mut new_map.size);
+            textc(on_secondary_container(), "Size (chunks)");
+        });
+    }
+
+    if !button_primary("Edit").show().clicked {
+        return None;
+    }
+    Some(match new_map.start {
+        MapStart::Map(ref name) => name.clone(),
+        _ => "new map".to_string(),
+    })
+}
+
+/// In-game date of the save and how long ago it was written
+fn autosave_description(name: &str, modified: SystemTime) -> String {
+    let ago = modified.elapsed().unwrap_or_default().as_secs();
+    let ago = match ago {
+        0..=59 => format!("{ago}s ago"),
+        60..=3599 => format!("{}min ago", ago / 60),
+        _ => format!("{}h ago", ago / 3600),
+    };
+    match Simulation::load_meta_from_disk(name) {
+        Some(meta) => format!(
+            "Day {} {:02}:{:02}, saved {ago}",
+            meta.daytime.day, meta.daytime.hour, meta.daytime.minute
+        ),
+        None => format!("saved {ago}"),
+    }
+}
+
+/// The named saves with their thumbnail, description and actions
+fn save_list(uiw: &UiWorld, state: &mut LoadState) {
+    if state.saves.is_empty() {
+        let msg = if state.scan.is_some() {
+            "Looking for saves..."
+        } else {
+            "No saves, use Save as to create one"
+        };
+        textc(on_secondary_container(), msg);
+        return;
+    }
+
+    let mut load = None;
+    let mut rename = None;
+    let mut delete = None;
+
+    for save in &state.saves {
+        minrow(10.0, || {
+            let thumbnail = state
+                .thumbnails
+                .get(&(save.name.clone(), save.saved_at))
+                .copied()
+                .flatten();
+            if let Some(tex) = thumbnail {
+                image(
+                    tex,
+                    Vec2::new(THUMBNAIL_WIDTH, THUMBNAIL_WIDTH * 9.0 / 16.0),
+                );
+            }
+
+            mincolumn(2.0, || {
+                textc(on_secondary_container(), save_title(save));
+                textc(on_secondary_container(), save_description(save));
+                if let Some(mismatch) = save.mods_mismatch() {
+                    textc(error(), format!("Different mods: {mismatch}"));
+                }
+
+                minrow(5.0, || {
+                    match state.renaming {
+                        Some((ref from, ref mut to)) if *from == save.name => {
+                            text_edit(150.0, to, "New name");
+                            if button_primary("Ok").show().clicked {
+                                rename = Some((from.clone(), to.clone()));
+                            }
+                            if button_secondary("Cancel").show().clicked {
+                                rename = Some((from.clone(), from.clone()));
+                            }
+                            return;
+                        }
+                        _ => {}
+                    }
+
+                    if state.confirm_delete.as_deref() == Some(&*save.name) {
+                        textc(error(), "Delete this save?");
+                        if button_primary("Delete").show().clicked {
+                            delete = Some(save.name.clone());
+                        }
+                        if button_secondary("Cancel").show().clicked {
+                            state.confirm_delete = None;
+                        }
+                        return;
+                    }
+
+                    if button_primary("Load").show().clicked {
+                        load = Some(save.clone());
+                    }
+                    if button_secondary("Rename").show().clicked {
+                        state.renaming = Some((save.name.clone(), save.name.clone()));
+                    }
+                    if button_secondary("Delete").show().clicked {
+                        state.confirm_delete = Some(save.name.clone());
+                    }
+                });
+            });
+        });
+    }
+
+    if let Some(save) = load {
+        match Simulation::load_named(&save.name) {
+            Ok(sim) => {
+                let mut slstate = uiw.write::<SaveLoadState>();
+                slstate.please_load_sim = Some(sim);
+                slstate.please_load_save = Some(world_name(&save.name));
+                drop(slstate);
+                *uiw.write::<CurrentSave>() = CurrentSave {
+                    name: Some(save.name.clone()),
+                    city_name: save.city_name.clone(),
+                    play_time: save.play_time,
+                };
+                state.load_fail.clear();
+            }
+            Err(e) => state.load_fail = format!("Failed to load {}: {e}", save.name),
+        }
+    }
+
+    if let Some((from, to)) = rename {
+        let to = to.trim().to_string();
+        if from == to {
+            state.renaming = None;
+        } else if !is_valid_save_name(&to) {
+            state.load_fail = "Save names can only use letters, digits, spaces, - _ and .".into();
+        } else {
+            match rename_save(&from, &to) {
+                Ok(()) => {
+                    let mut current = uiw.write::<CurrentSave>();
+                    if current.name.as_deref() == Some(&*from) {
+                        current.name = Some(to);
+                    }
+                    state.renaming = None;
+                    state.load_fail.clear();
+                    state.rescan();
+                }
+                Err(e) => state.load_fail = format!("Failed to rename {from}: {e}"),
+            }
+        }
+    }
+
+    if let Some(name) = delete {
+        state.confirm_delete = None;
+        match delete_save(&name) {
+            Ok(()) => {
+                let mut current = uiw.write::<CurrentSave>();
+                if current.name.as_deref() == Some(&*name) {
+                    current.name = None;
+                }
+                state.load_fail.clear();
+                state.rescan();
+            }
+            Err(e) => state.load_fail = format!("Failed to delete {name}: {e}"),
+        }
+    }
+}
+
+fn save_title(save: &SaveMetadata) -> String {
+    let mut title = if save.city_name.is_empty() || save.city_name == save.name {
+        save.name.clone()
+    } else {
+        format!("{} ({})", save.name, save.city_name)
+    };
+    title += &format!(" - {}", save.difficulty.label());
+    if save.sandbox {
+        title += " (sandbox)";
+    }
+    if save.cheats_used {
+        title += " (cheats)";
+    }
+    title
+}
+
+/// Population, money, in-game date and play time of the save
+fn save_description(save: &SaveMetadata) -> String {
+    let play = save.play_time as u64;
+    format!(
+        "{} citizens, {}, Day {} {:02}:{:02}, played {}h{:02}",
+        save.population,
+        save.money.localized(),
+        save.daytime.day,
+        save.daytime.hour,
+        save.daytime.minute,
+        play / 3600,
+        (play / 60) % 60,
+    )
+}
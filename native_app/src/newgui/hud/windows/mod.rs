@@ -1,10 +1,24 @@
+pub mod bookmarks;
+pub mod budget;
+pub mod chronicle;
 pub mod economy;
 pub mod load;
+pub mod mods;
+pub mod new_game;
+pub mod notifications;
+pub mod objectives;
+pub mod save_as;
+pub mod scenarios;
+pub mod search;
 pub mod settings;
+pub mod statistics;
+pub mod transit;
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::uiworld::UiWorld;
 use goryak::button_primary;
+use prototypes::{GameInstant, ScenarioPrototypeID};
+use simulation::objectives::ScenarioRunner;
 use simulation::Simulation;
 
 #[cfg(feature = "multiplayer")]
@@ -13,32 +27,95 @@ pub mod network;
 #[derive(Default)]
 pub struct GUIWindows {
     economy_open: bool,
+    budget_open: bool,
+    statistics_open: bool,
+    bookmarks_open: bool,
+    search_open: bool,
+    notifications_open: bool,
+    chronicle_open: bool,
+    transit_open: bool,
     settings_open: bool,
     load_open: bool,
+    mods_open: bool,
+    save_as_open: bool,
+    scenarios_open: bool,
+    objectives_open: bool,
+    /// The scenario the objectives were last opened for, to open them again for a new one
+    objectives_scenario: Option<(ScenarioPrototypeID, GameInstant)>,
     #[cfg(feature = "multiplayer")]
     network_open: bool,
 }
 
 impl GUIWindows {
-    pub fn menu(&mut self) {
-        if button_primary("Economy").show().clicked {
+    pub fn menu(&mut self, sim: &Simulation) {
+        if button_primary(t!("menu-economy")).show().clicked {
             self.economy_open ^= true;
         }
 
-        if button_primary("Settings").show().clicked {
+        if button_primary(t!("menu-budget")).show().clicked {
+            self.budget_open ^= true;
+        }
+
+        if button_primary(t!("menu-statistics")).show().clicked {
+            self.statistics_open ^= true;
+        }
+
+        if button_primary(t!("menu-bookmarks")).show().clicked {
+            self.bookmarks_open ^= true;
+        }
+
+        if button_primary(t!("menu-search")).show().clicked {
+            self.search_open ^= true;
+        }
+
+        if button_primary(t!("menu-notifications")).show().clicked {
+            self.notifications_open ^= true;
+        }
+
+        if button_primary(t!("menu-chronicle")).show().clicked {
+            self.chronicle_open ^= true;
+        }
+
+        if button_primary(t!("menu-bus-lines")).show().clicked {
+            self.transit_open ^= true;
+        }
+
+        if button_primary(t!("menu-settings")).show().clicked {
             self.settings_open ^= true;
         }
 
-        if button_primary("Load").show().clicked {
+        if button_primary(t!("menu-save-as")).show().clicked {
+            self.save_as_open ^= true;
+        }
+
+        if button_primary(t!("menu-load")).show().clicked {
             self.load_open ^= true;
         }
 
+        if button_primary(t!("menu-mods")).show().clicked {
+            self.mods_open ^= true;
+        }
+
+        if button_primary(t!("menu-scenarios")).show().clicked {
+            self.scenarios_open ^= true;
+        }
+
+        if sim.read::<ScenarioRunner>().active().is_some()
+            && button_primary(t!("menu-objectives")).show().clicked
+        {
+            self.objectives_open ^= true;
+        }
+
         #[cfg(feature = "multiplayer")]
-        if button_primary("Network").show().clicked {
+        if button_primary(t!("menu-network")).show().clicked {
             self.network_open ^= true;
         }
     }
 
+    pub fn open_load(&mut self) {
+        self.load_open = true;
+    }
+
     pub fn render(&mut self, uiworld: &UiWorld, sim: &Simulation) {
         profiling::scope!("windows::render");
         if uiworld
@@ -49,9 +126,29 @@ impl GUIWindows {
             self.economy_open ^= true;
         }
 
+        let scenario = sim
+            .read::<ScenarioRunner>()
+            .active()
+            .map(|a| (a.id, a.start));
+        if scenario != self.objectives_scenario {
+            self.objectives_open = scenario.is_some();
+            self.objectives_scenario = scenario;
+        }
+
         economy::economy(uiworld, sim, &mut self.economy_open);
+        budget::budget(uiworld, sim, &mut self.budget_open);
+        statistics::statistics(uiworld, sim, &mut self.statistics_open);
+        bookmarks::bookmarks(uiworld, sim, &mut self.bookmarks_open);
+        search::search(uiworld, sim, &mut self.search_open);
+        notifications::notifications(uiworld, sim, &mut self.notifications_open);
+        chronicle::chronicle(uiworld, sim, &mut self.chronicle_open);
+        transit::transit(uiworld, sim, &mut self.transit_open);
         settings::settings(uiworld, sim, &mut self.settings_open);
+        save_as::save_as(uiworld, sim, &mut self.save_as_open);
         load::load(uiworld, sim, &mut self.load_open);
+        mods::mods(uiworld, sim, &mut self.mods_open);
+        scenarios::scenarios(uiworld, sim, &mut self.scenarios_open);
+        objectives::objectives(uiworld, sim, &mut self.objectives_open);
 
         #[cfg(feature = "multiplayer")]
         network::network(uiworld, sim, &mut self.network_open);
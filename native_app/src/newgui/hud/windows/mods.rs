@@ -0,0 +1,85 @@
+use goryak::{error, mincolumn, minrow, on_secondary_container, primary, textc, Window};
+use prototypes::{loaded_mods, ModProfile};
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::uiworld::UiWorld;
+
+/// The profile being edited, saved at each change
+pub struct ModsState {
+    profile: ModProfile,
+}
+
+impl Default for ModsState {
+    fn default() -> Self {
+        Self {
+            profile: ModProfile::load(),
+        }
+    }
+}
+
+/// Mods window
+/// Lists the mods of the mods folder to enable or disable them, and the problems found when loading them.
+/// The prototypes are loaded once at startup so the changes apply after a restart.
+pub fn mods(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-mods").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 10.0,
+    }
+    .show(|| {
+        let report = loaded_mods();
+        let mut state = uiw.write::<ModsState>();
+        let profile = &mut state.profile;
+
+        if report.available.is_empty() {
+            textc(
+                on_secondary_container(),
+                "No mods found, add them to the mods folder",
+            );
+        }
+
+        let mut changed = false;
+        for manifest in &report.available {
+            minrow(10.0, || {
+                let enabled = profile.is_enabled(&manifest.name);
+                if yakui::checkbox(enabled).checked != enabled {
+                    if enabled {
+                        profile.disabled.insert(manifest.name.clone());
+                    } else {
+                        profile.disabled.remove(&manifest.name);
+                    }
+                    changed = true;
+                }
+                mincolumn(2.0, || {
+                    textc(
+                        on_secondary_container(),
+                        format!("{} {}", manifest.name, manifest.version),
+                    );
+                    if !manifest.dependencies.is_empty() {
+                        textc(
+                            on_secondary_container(),
+                            format!("Needs {}", manifest.dependencies.join(", ")),
+                        );
+                    }
+                });
+                if report.active.contains(manifest) {
+                    textc(primary(), "Loaded");
+                }
+            });
+        }
+        if changed {
+            profile.save();
+        }
+
+        if profile.disabled != report.disabled {
+            textc(primary(), "Restart the game to apply the changes");
+        }
+
+        for problem in &report.problems {
+            textc(error(), problem.to_string());
+        }
+    });
+}
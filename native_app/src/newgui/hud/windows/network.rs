@@ -37,7 +37,7 @@ fn label(x: impl Into<Cow<'static, str>>) {
 /// Allows to connect to a server or start a server
 pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
     Window {
-        title: "Network".into(),
+        title: t!("window-network").into(),
         opened,
         pad: Pad::all(10.0),
         radius: 10.0,
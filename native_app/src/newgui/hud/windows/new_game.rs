@@ -0,0 +1,221 @@
+use std::thread::JoinHandle;
+
+use engine::wgpu::TextureFormat;
+use engine::yakui::YakuiWrapper;
+use engine::{GfxContext, Texture, TextureBuilder};
+use geom::Color;
+use goryak::{
+    button_secondary, dragvalue, minrow, on_secondary_container, selectable_label_primary,
+    text_edit, textc,
+};
+use prototypes::Money;
+use simulation::gameplay::{Difficulty, GameplayParams};
+use simulation::map::procgen::{MapGen, MapGenParams, MAX_RIVERS};
+use yakui::TextureId;
+
+use crate::rendering::minimap::{mix, to_rgba};
+
+/// Side in pixels of the preview of the generated map
+pub const PREVIEW_RESOLUTION: u32 = 128;
+
+/// Sizes of the generated maps, in chunks on each side
+const MAP_SIZES: [(&str, u16); 4] = [("Small", 25), ("Medium", 50), ("Large", 65), ("Huge", 80)];
+
+/// Difficulty presets of a new game, the custom mode exposes the sliders of every parameter
+pub fn difficulty_picker(params: &mut GameplayParams) {
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Difficulty");
+        for difficulty in Difficulty::PRESETS {
+            if selectable_label_primary(params.difficulty == difficulty, difficulty.label()).clicked
+            {
+                *params = GameplayParams::preset(difficulty);
+            }
+        }
+        if selectable_label_primary(
+            params.difficulty == Difficulty::Custom,
+            Difficulty::Custom.label(),
+        )
+        .clicked
+        {
+            params.difficulty = Difficulty::Custom;
+        }
+    });
+
+    if params.difficulty != Difficulty::Custom {
+        return;
+    }
+    minrow(5.0, || {
+        let mut bucks = params.starting_money.bucks();
+        if dragvalue()
+            .minmax(0.0..10_000_000.0)
+            .step(1000.0)
+            .show(&mut bucks)
+        {
+            params.starting_money = Money::new_bucks(bucks);
+        }
+        textc(on_secondary_container(), "Starting money ($)");
+    });
+    params_sliders(params);
+}
+
+/// Sliders of the multipliers of the gameplay parameters, returns true if one of them changed
+pub fn params_sliders(params: &mut GameplayParams) -> bool {
+    let mut changed = false;
+
+    let mut slider = |value: &mut f32, max: f64, label: &'static str| {
+        minrow(5.0, || {
+            changed |= dragvalue().minmax(0.0..max).step(0.05).show(value);
+            textc(on_secondary_container(), label);
+        });
+    };
+    slider(&mut params.price_multiplier, 3.0, "Price multiplier");
+    slider(
+        &mut params.building_cost_multiplier,
+        5.0,
+        "Building cost multiplier",
+    );
+    slider(
+        &mut params.external_trade_margin,
+        0.5,
+        "External trade margin",
+    );
+    slider(&mut params.disaster_frequency, 5.0, "Disaster frequency");
+
+    changed
+}
+
+/// Seed, relief, forests, rivers and size of the generated map.
+/// The seed is typed in `seed_text`, words are hashed into a seed.
+pub fn mapgen_picker(mapgen: &mut MapGenParams, size: &mut u16, seed_text: &mut String) {
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Seed");
+        text_edit(120.0, seed_text, "Seed");
+        if button_secondary("Random").show().clicked {
+            *seed_text = (common::hash_u64(std::time::SystemTime::now()) as u32).to_string();
+        }
+    });
+    if let Some(seed) = parse_seed(seed_text) {
+        mapgen.seed = seed;
+    }
+
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Size");
+        for (label, preset) in MAP_SIZES {
+            if selectable_label_primary(*size == preset, label).clicked {
+                *size = preset;
+            }
+        }
+    });
+
+    minrow(5.0, || {
+        dragvalue()
+            .minmax(0.0..1.0)
+            .step(0.05)
+            .show(&mut mapgen.mountains);
+        textc(on_secondary_container(), "Mountains");
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .minmax(0.0..3.0)
+            .step(0.05)
+            .show(&mut mapgen.forests);
+        textc(on_secondary_container(), "Forests");
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .minmax(0.0..MAX_RIVERS as f64)
+            .show(&mut mapgen.rivers);
+        textc(on_secondary_container(), "Rivers");
+    });
+}
+
+/// Numbers are used as they are, words are hashed, nothing keeps the current seed
+fn parse_seed(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(
+        text.parse()
+            .unwrap_or_else(|_| common::hash_u64(text) as u32),
+    )
+}
+
+/// Low resolution image of the map about to be generated, drawn on a worker thread
+#[derive(Default)]
+pub struct MapPreview {
+    /// Parameters and size of the last image drawn or being drawn
+    drawn: Option<(MapGenParams, u16)>,
+    job: Option<JoinHandle<Vec<u8>>>,
+    /// Rgba pixels drawn but not uploaded yet
+    pixels: Option<Vec<u8>>,
+    texture: Option<(Texture, TextureId)>,
+}
+
+impl MapPreview {
+    /// Draws the image again when the parameters changed, once the previous drawing is done
+    pub fn request(&mut self, mapgen: MapGenParams, size: u16) {
+        if self.job.as_ref().is_some_and(|j| j.is_finished()) {
+            if let Ok(pixels) = self.job.take().unwrap().join() {
+                self.pixels = Some(pixels);
+            }
+        }
+        if self.job.is_some() || self.drawn == Some((mapgen, size)) {
+            return;
+        }
+        self.drawn = Some((mapgen, size));
+        self.job = Some(std::thread::spawn(move || draw_preview(&mapgen, size)));
+    }
+
+    /// Uploads the image once drawn, it needs the graphics context so it is called by the game loop
+    pub fn upload(&mut self, gfx: &GfxContext, yakui: &mut YakuiWrapper) {
+        let Some(pixels) = self.pixels.take() else {
+            return;
+        };
+        let (texture, _) = self.texture.get_or_insert_with(|| {
+            let texture = TextureBuilder::empty(
+                PREVIEW_RESOLUTION,
+                PREVIEW_RESOLUTION,
+                1,
+                TextureFormat::Rgba8UnormSrgb,
+            )
+            .with_label("map preview")
+            .build(&gfx.device, &gfx.queue);
+            let id = yakui.add_texture(&texture);
+            (texture, id)
+        });
+        texture.write_region(
+            &gfx.queue,
+            (0, 0),
+            (PREVIEW_RESOLUTION, PREVIEW_RESOLUTION),
+            &pixels,
+        );
+    }
+
+    /// None until the first image is uploaded
+    pub fn texture(&self) -> Option<TextureId> {
+        self.texture.as_ref().map(|(_, id)| *id)
+    }
+}
+
+/// Water darker with depth, land from grass to rock with height and darker in the forests
+fn draw_preview(mapgen: &MapGenParams, size: u16) -> Vec<u8> {
+    profiling::scope!("new_game::draw_preview");
+    let colors = simulation::colors();
+    let grass = Color::new(0.34, 0.47, 0.27, 1.0);
+    let rock = Color::new(0.55, 0.51, 0.43, 1.0);
+    let forest = Color::new(0.13, 0.27, 0.12, 1.0);
+
+    MapGen::new(mapgen, size, size)
+        .preview(PREVIEW_RESOLUTION as usize)
+        .into_iter()
+        .flat_map(|(height, trees)| {
+            if height < 0.0 {
+                let deep = mix(colors.sea_col, Color::BLACK, 0.6);
+                return to_rgba(mix(colors.sea_col, deep, -height / 100.0));
+            }
+            let land = mix(grass, rock, height / 400.0);
+            to_rgba(mix(land, forest, trees * 0.7))
+        })
+        .collect()
+}
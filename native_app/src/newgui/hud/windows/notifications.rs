@@ -0,0 +1,54 @@
+use goryak::{
+    button_secondary, icon, mincolumn, minrow, on_secondary_container, textc, VertScrollSize,
+    Window,
+};
+use prototypes::GameInstant;
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::newgui::notifications::{jump_to, notification_text, severity_style, Notifications};
+use crate::uiworld::UiWorld;
+
+/// Notifications window
+/// Lists the last notifications, the newest first, and jumps to what they are about
+pub fn notifications(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-notifications").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut notifs = uiw.write::<Notifications>();
+        if notifs.history.is_empty() {
+            textc(on_secondary_container(), "Nothing to report");
+            return;
+        }
+
+        let mut jump = None;
+        VertScrollSize::Fixed(300.0).show(|| {
+            mincolumn(2.0, || {
+                for e in notifs.history.iter().rev() {
+                    minrow(10.0, || {
+                        let (color, icon_name) = severity_style(e.severity);
+                        icon(color, icon_name);
+                        textc(on_secondary_container(), GameInstant(e.tick).to_string());
+                        if notification_text(e) {
+                            jump = e.target;
+                        }
+                    });
+                }
+            });
+        });
+
+        if button_secondary("Clear").show().clicked {
+            notifs.history.clear();
+        }
+        drop(notifs);
+
+        if let Some(target) = jump {
+            jump_to(uiw, sim, target);
+        }
+    });
+}
@@ -0,0 +1,77 @@
+use goryak::{error, mincolumn, on_secondary_container, primary, textc, ProgressBar, Window};
+use prototypes::{GameTime, TICKS_PER_SECOND};
+use simulation::objectives::{ScenarioOutcome, ScenarioRunner};
+use simulation::Simulation;
+use yakui::widgets::Pad;
+use yakui::{Color, Vec2};
+
+use crate::uiworld::UiWorld;
+
+/// Objectives window
+/// Progress of the objectives of the scenario being played
+pub fn objectives(_: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    let runner = sim.read::<ScenarioRunner>();
+    let Some(active) = runner.active() else {
+        return;
+    };
+    let proto = active.id.prototype();
+
+    Window {
+        title: t!("window-objectives").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        textc(on_secondary_container(), proto.label.clone());
+        let now = sim.read::<GameTime>().instant();
+
+        for (objective, progress) in proto.objectives.iter().zip(&active.progress) {
+            mincolumn(2.0, || {
+                let target = objective.target();
+                ProgressBar {
+                    value: (progress.current / target).clamp(0.0, 1.0) as f32,
+                    size: Vec2::new(400.0, 25.0),
+                    color: if progress.completed {
+                        Color::rgb(80, 200, 80)
+                    } else {
+                        primary().adjust(0.7)
+                    },
+                }
+                .show_children(|| {
+                    textc(
+                        on_secondary_container(),
+                        format!(
+                            "{} ({:.0}/{:.0})",
+                            objective.description(),
+                            progress.current.min(target),
+                            target
+                        ),
+                    );
+                });
+
+                let Some(within) = objective.within else {
+                    return;
+                };
+                if progress.completed || active.outcome.is_some() {
+                    return;
+                }
+                let deadline = active.start + within;
+                let left = deadline.0 .0.saturating_sub(now.0 .0) as f64
+                    / (GameTime::DAY as f64 * TICKS_PER_SECOND as f64);
+                textc(on_secondary_container(), format!("{:.1} days left", left));
+            });
+        }
+
+        match active.outcome {
+            Some(ScenarioOutcome::Won) => {
+                textc(Color::rgb(80, 200, 80), "Scenario completed!");
+            }
+            Some(ScenarioOutcome::Lost(ref reason)) => {
+                textc(error(), format!("Scenario lost: {}", reason));
+            }
+            None => {}
+        }
+    });
+}
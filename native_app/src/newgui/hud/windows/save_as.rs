@@ -0,0 +1,93 @@
+use std::sync::atomic::Ordering;
+
+use goryak::{
+    button_primary, button_secondary, error, minrow, on_secondary_container, text_edit, textc,
+    Window,
+};
+use simulation::saves::{is_valid_save_name, save_exists};
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::uiworld::{CurrentSave, SaveLoadState, UiWorld};
+
+#[derive(Default)]
+pub struct SaveAsState {
+    name: String,
+    city_name: String,
+    was_opened: bool,
+}
+
+/// Save as window
+/// Saves the world under a name, asking before overwriting an existing save
+pub fn save_as(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+    let mut state = uiw.write::<SaveAsState>();
+    if *opened && !state.was_opened {
+        let current = uiw.read::<CurrentSave>();
+        state.name = current.name.clone().unwrap_or_default();
+        state.city_name = current.city_name.clone();
+    }
+    state.was_opened = *opened;
+
+    let mut close = false;
+    Window {
+        title: t!("window-save-as").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened: &mut *opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        minrow(5.0, || {
+            textc(on_secondary_container(), "Save name");
+            text_edit(200.0, &mut state.name, "Name");
+        });
+        minrow(5.0, || {
+            textc(on_secondary_container(), "City name");
+            text_edit(200.0, &mut state.city_name, "City");
+        });
+
+        let name = state.name.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        if !is_valid_save_name(&name) {
+            textc(
+                error(),
+                "Save names can only use letters, digits, spaces, - _ and .",
+            );
+            return;
+        }
+
+        let mut slstate = uiw.write::<SaveLoadState>();
+        if slstate.please_save || slstate.saving_status.load(Ordering::SeqCst) {
+            textc(on_secondary_container(), "Saving...");
+            return;
+        }
+
+        let mut save = false;
+        if save_exists(&name) {
+            textc(error(), format!("A save named {name} already exists"));
+            minrow(5.0, || {
+                if button_primary("Overwrite").show().clicked {
+                    save = true;
+                }
+                if button_secondary("Cancel").show().clicked {
+                    close = true;
+                }
+            });
+        } else if button_primary("Save").show().clicked {
+            save = true;
+        }
+
+        if save {
+            uiw.write::<CurrentSave>().city_name = state.city_name.trim().to_string();
+            slstate.please_save = true;
+            slstate.save_as = Some(name);
+            close = true;
+        }
+    });
+
+    if close {
+        *opened = false;
+    }
+}
@@ -0,0 +1,84 @@
+use goryak::{
+    button_primary, error, mincolumn, minrow, on_secondary_container, primary, textc, Window,
+};
+use prototypes::{prototypes_iter, GameTime, ScenarioPrototype};
+use simulation::objectives::{new_scenario, ScenarioProfile};
+use simulation::Simulation;
+use yakui::widgets::Pad;
+
+use crate::uiworld::{CurrentSave, SaveLoadState, UiWorld};
+
+/// Loaded again when the world changes, to show the scenario that was just won
+pub struct ScenariosState {
+    pub start_fail: String,
+    profile: ScenarioProfile,
+}
+
+impl Default for ScenariosState {
+    fn default() -> Self {
+        Self {
+            start_fail: String::new(),
+            profile: ScenarioProfile::load(),
+        }
+    }
+}
+
+/// Scenarios window
+/// Lists the scenarios with their objectives, starting one replaces the current world
+pub fn scenarios(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-scenarios").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 10.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<ScenariosState>();
+        let mut start = None;
+
+        for scenario in prototypes_iter::<ScenarioPrototype>() {
+            mincolumn(2.0, || {
+                minrow(10.0, || {
+                    textc(on_secondary_container(), scenario.label.clone());
+                    if let Some(best) = state.profile.best(&scenario.name) {
+                        textc(
+                            primary(),
+                            format!(
+                                "Completed in {:.1} days",
+                                best.seconds() / GameTime::DAY as f64
+                            ),
+                        );
+                    }
+                });
+                textc(on_secondary_container(), scenario.description.clone());
+                for objective in &scenario.objectives {
+                    textc(
+                        on_secondary_container(),
+                        format!("- {}", objective.description()),
+                    );
+                }
+                if button_primary("Start").show().clicked {
+                    start = Some(scenario.id);
+                }
+            });
+        }
+
+        if let Some(id) = start {
+            match new_scenario(id) {
+                Ok(sim) => {
+                    uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+                    *uiw.write::<CurrentSave>() = CurrentSave::default();
+                    state.start_fail.clear();
+                }
+                Err(e) => {
+                    state.start_fail = format!("Failed to start {}: {e}", id.prototype().label)
+                }
+            }
+        }
+
+        if !state.start_fail.is_empty() {
+            textc(error(), state.start_fail.clone());
+        }
+    });
+}
@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::fuzzy::fuzzy_score;
+use geom::Vec3;
+use goryak::{
+    mincolumn, minrow, on_secondary_container, primary_link, selectable_label_primary, text_edit,
+    textc, VertScrollSize, Window,
+};
+use simulation::map::{
+    Building, BuildingID, BuildingKind, CanonicalPosition, District, DistrictID, Map,
+    MapSubscriber, ProjectFilter, ProjectKind, Road, RoadID, SubscriberChunkID, UpdateType,
+};
+use simulation::{AnyEntity, HumanID, Simulation};
+use yakui::widgets::Pad;
+
+use crate::newgui::districts::DistrictsResource;
+use crate::newgui::roadeditor::{RoadEditorResource, SelectedRoad};
+use crate::newgui::{InspectedBuilding, InspectedEntity, Tool};
+use crate::rendering::{ZOOM_DISTRICT, ZOOM_STREET};
+use crate::uiworld::UiWorld;
+
+/// Results shown at most, the best ones first
+const MAX_RESULTS: usize = 50;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchKind {
+    Road,
+    District,
+    House,
+    Company,
+    FreightStation,
+    PassengerStation,
+    Warehouse,
+    School,
+    Leisure,
+    Hotel,
+    Harbor,
+    Airport,
+    TrainStation,
+    ExternalTrading,
+    Citizen,
+}
+
+impl SearchKind {
+    pub const ALL: [SearchKind; 15] = [
+        SearchKind::Road,
+        SearchKind::District,
+        SearchKind::House,
+        SearchKind::Company,
+        SearchKind::FreightStation,
+        SearchKind::PassengerStation,
+        SearchKind::Warehouse,
+        SearchKind::School,
+        SearchKind::Leisure,
+        SearchKind::Hotel,
+        SearchKind::Harbor,
+        SearchKind::Airport,
+        SearchKind::TrainStation,
+        SearchKind::ExternalTrading,
+        SearchKind::Citizen,
+    ];
+
+    pub fn of(kind: &BuildingKind) -> Self {
+        match kind {
+            BuildingKind::House => SearchKind::House,
+            BuildingKind::GoodsCompany(_) => SearchKind::Company,
+            BuildingKind::RailFreightStation(_) => SearchKind::FreightStation,
+            BuildingKind::RailPassengerStation(_) => SearchKind::PassengerStation,
+            BuildingKind::Warehouse(_) => SearchKind::Warehouse,
+            BuildingKind::School(_) => SearchKind::School,
+            BuildingKind::Leisure(_) => SearchKind::Leisure,
+            BuildingKind::Hotel(_) => SearchKind::Hotel,
+            BuildingKind::Harbor(_) => SearchKind::Harbor,
+            BuildingKind::Airport(_) => SearchKind::Airport,
+            BuildingKind::TrainStation => SearchKind::TrainStation,
+            BuildingKind::ExternalTrading => SearchKind::ExternalTrading,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchKind::Road => "Road",
+            SearchKind::District => "District",
+            SearchKind::House => "House",
+            SearchKind::Company => "Company",
+            SearchKind::FreightStation => "Freight station",
+            SearchKind::PassengerStation => "Passenger station",
+            SearchKind::Warehouse => "Warehouse",
+            SearchKind::School => "School",
+            SearchKind::Leisure => "Leisure",
+            SearchKind::Hotel => "Hotel",
+            SearchKind::Harbor => "Harbor",
+            SearchKind::Airport => "Airport",
+            SearchKind::TrainStation => "Train station",
+            SearchKind::ExternalTrading => "External trading",
+            SearchKind::Citizen => "Citizen",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum SearchTarget {
+    Building(BuildingID),
+    Road(RoadID),
+    District(DistrictID),
+    Human(HumanID),
+}
+
+#[derive(Clone)]
+struct SearchEntry {
+    target: SearchTarget,
+    kind: SearchKind,
+    name: String,
+    /// Also matched, e.g. the name of the prototype
+    alias: String,
+    pos: Vec3,
+    /// Rough size of the object, to zoom enough to see all of it
+    size: f32,
+}
+
+impl SearchEntry {
+    fn building(b: &Building) -> Self {
+        let (name, alias) = match b.kind {
+            BuildingKind::GoodsCompany(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::RailFreightStation(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::RailPassengerStation(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::Warehouse(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::School(id) => (id.prototype().label.clone(), id.prototype().name.clone()),
+            BuildingKind::Leisure(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::Hotel(id) => (id.prototype().label.clone(), id.prototype().name.clone()),
+            BuildingKind::Harbor(id) => (id.prototype().label.clone(), id.prototype().name.clone()),
+            BuildingKind::Airport(id) => {
+                (id.prototype().label.clone(), id.prototype().name.clone())
+            }
+            BuildingKind::House | BuildingKind::TrainStation | BuildingKind::ExternalTrading => {
+                (SearchKind::of(&b.kind).label().to_string(), String::new())
+            }
+        };
+        Self {
+            target: SearchTarget::Building(b.id),
+            kind: SearchKind::of(&b.kind),
+            name,
+            alias,
+            pos: b.door_pos,
+            size: b.obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max),
+        }
+    }
+
+    fn road(map: &Map, r: &Road) -> Self {
+        let length = r.points.length();
+        Self {
+            target: SearchTarget::Road(r.id),
+            kind: SearchKind::Road,
+            name: map.road_name(r.id).into_owned(),
+            alias: String::new(),
+            pos: r.points.point_along(length * 0.5),
+            size: length,
+        }
+    }
+
+    fn district(map: &Map, d: &District) -> Self {
+        let pos = d.label_pos();
+        Self {
+            target: SearchTarget::District(d.id),
+            kind: SearchKind::District,
+            name: d.name.clone(),
+            alias: String::new(),
+            pos: pos.z(map.environment.height(pos).unwrap_or(0.0)),
+            size: d.polygon.bcircle().radius * 2.0,
+        }
+    }
+
+    fn score(&self, query: &str) -> Option<i32> {
+        [&*self.name, &*self.alias, self.kind.label()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| fuzzy_score(query, s))
+            .max()
+    }
+}
+
+/// Buildings and roads by chunk of the map.
+/// Only the chunks the map reports as changed are indexed again.
+#[derive(Default)]
+pub struct SearchIndex {
+    sub: Option<MapSubscriber>,
+    chunks: BTreeMap<SubscriberChunkID, Vec<SearchEntry>>,
+}
+
+impl SearchIndex {
+    /// Indexes the chunks that changed, e.g. a renamed road. Returns whether anything changed.
+    pub fn update(&mut self, map: &Map) -> bool {
+        let Some(ref mut sub) = self.sub else {
+            self.sub = Some(map.subscribe(UpdateType::Road | UpdateType::Building));
+            self.rebuild(map);
+            return true;
+        };
+
+        if sub.take_cleared() {
+            sub.take_updated_chunks().for_each(drop);
+            self.rebuild(map);
+            return true;
+        }
+
+        let updated: Vec<_> = sub.take_updated_chunks().collect();
+        for &chunk in &updated {
+            self.index_chunk(map, chunk);
+        }
+        !updated.is_empty()
+    }
+
+    fn rebuild(&mut self, map: &Map) {
+        self.chunks.clear();
+        for b in map.buildings().values() {
+            self.chunks
+                .entry(SubscriberChunkID::new(b.canonical_position()))
+                .or_default()
+                .push(SearchEntry::building(b));
+        }
+        for r in map.roads().values() {
+            self.chunks
+                .entry(SubscriberChunkID::new(r.canonical_position()))
+                .or_default()
+                .push(SearchEntry::road(map, r));
+        }
+    }
+
+    fn index_chunk(&mut self, map: &Map, chunk: SubscriberChunkID) {
+        let entries: Vec<_> = map
+            .spatial_map()
+            .query(chunk.bbox(), ProjectFilter::ROAD | ProjectFilter::BUILDING)
+            .filter_map(|obj| match obj {
+                ProjectKind::Building(id) => {
+                    let b = map.buildings().get(id)?;
+                    (SubscriberChunkID::new(b.canonical_position()) == chunk)
+                        .then(|| SearchEntry::building(b))
+                }
+                ProjectKind::Road(id) => {
+                    let r = map.roads().get(id)?;
+                    (SubscriberChunkID::new(r.canonical_position()) == chunk)
+                        .then(|| SearchEntry::road(map, r))
+                }
+                _ => None,
+            })
+            .collect();
+
+        if entries.is_empty() {
+            self.chunks.remove(&chunk);
+        } else {
+            self.chunks.insert(chunk, entries);
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &SearchEntry> {
+        self.chunks.values().flatten()
+    }
+}
+
+#[derive(Default)]
+pub struct SearchState {
+    pub query: String,
+    /// Kinds shown in the results, all of them if empty
+    pub filters: BTreeSet<SearchKind>,
+    pub index: SearchIndex,
+    /// The matches of the last query, scored again only when the query, the filters or the index
+    /// change
+    scored: Option<ScoredQuery>,
+}
+
+struct ScoredQuery {
+    query: String,
+    filters: BTreeSet<SearchKind>,
+    matches: Vec<(i32, SearchEntry)>,
+}
+
+impl SearchState {
+    fn shown(&self, kind: SearchKind) -> bool {
+        self.filters.is_empty() || self.filters.contains(&kind)
+    }
+
+    /// Every entry matching the query with its score
+    fn score(&self, sim: &Simulation, query: &str) -> Vec<(i32, SearchEntry)> {
+        let mut matches: Vec<(i32, SearchEntry)> = self
+            .index
+            .entries()
+            .filter(|e| self.shown(e.kind))
+            .filter_map(|e| Some((e.score(query)?, e.clone())))
+            .collect();
+
+        // there are few districts and they don't belong to a chunk, they are not indexed
+        if self.shown(SearchKind::District) {
+            let map = sim.map();
+            matches.extend(map.districts.iter().filter_map(|d| {
+                let e = SearchEntry::district(&map, d);
+                Some((e.score(query)?, e))
+            }));
+        }
+
+        // citizens come and go too often to be indexed, and there are too many to list them all
+        if !query.is_empty() && self.shown(SearchKind::Citizen) {
+            matches.extend(sim.world().humans.iter().filter_map(|(id, h)| {
+                let name = h.personal_info.name.to_string();
+                let score = fuzzy_score(query, &name)?;
+                Some((
+                    score,
+                    SearchEntry {
+                        target: SearchTarget::Human(id),
+                        kind: SearchKind::Citizen,
+                        name,
+                        alias: String::new(),
+                        pos: h.trans.pos,
+                        size: 0.0,
+                    },
+                ))
+            }));
+        }
+
+        matches
+    }
+
+    /// Best matches of the query, the closest first among equally good ones
+    fn results(&mut self, sim: &Simulation, from: Vec3) -> Vec<(SearchEntry, f32)> {
+        let query = self.query.trim();
+        let up_to_date = self
+            .scored
+            .as_ref()
+            .is_some_and(|s| s.query == query && s.filters == self.filters);
+        if !up_to_date {
+            self.scored = Some(ScoredQuery {
+                query: query.to_string(),
+                filters: self.filters.clone(),
+                matches: self.score(sim, query),
+            });
+        }
+        let Some(ref scored) = self.scored else {
+            return vec![];
+        };
+
+        // the citizens moved since they were scored, the ones who left are not shown
+        let world = sim.world();
+        let mut found: Vec<(i32, f32, Vec3, &SearchEntry)> = scored
+            .matches
+            .iter()
+            .filter_map(|(score, e)| {
+                let pos = match e.target {
+                    SearchTarget::Human(id) => world.humans.get(id)?.trans.pos,
+                    _ => e.pos,
+                };
+                Some((*score, pos.distance(from), pos, e))
+            })
+            .collect();
+
+        found.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+        found
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, dist, pos, e)| (SearchEntry { pos, ..e.clone() }, dist))
+            .collect()
+    }
+}
+
+/// Search window
+/// Finds buildings, companies, roads, districts and citizens by name or kind and jumps to them
+pub fn search(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-search").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<SearchState>();
+        if state.index.update(&sim.map()) {
+            state.scored = None;
+        }
+
+        text_edit(300.0, &mut state.query, "Name or kind, e.g. hospital");
+
+        for row in SearchKind::ALL.chunks(5) {
+            minrow(5.0, || {
+                for &kind in row {
+                    let selected = state.filters.contains(&kind);
+                    if selectable_label_primary(selected, kind.label()).clicked {
+                        if selected {
+                            state.filters.remove(&kind);
+                        } else {
+                            state.filters.insert(kind);
+                        }
+                    }
+                }
+            });
+        }
+
+        let cam_pos = uiw.camera().view().pos;
+        let results = state.results(sim, cam_pos);
+        drop(state);
+
+        if results.is_empty() {
+            textc(on_secondary_container(), "Nothing found");
+            return;
+        }
+
+        let mut clicked = None;
+        VertScrollSize::Fixed(300.0).show(|| {
+            mincolumn(2.0, || {
+                for (entry, dist) in &results {
+                    minrow(10.0, || {
+                        if primary_link(entry.name.clone()) {
+                            clicked = Some(entry.clone());
+                        }
+                        textc(on_secondary_container(), entry.kind.label());
+                        textc(on_secondary_container(), format_dist(*dist));
+                    });
+                }
+            });
+        });
+
+        if let Some(entry) = clicked {
+            go_to(uiw, sim, &entry);
+        }
+    });
+}
+
+/// Moves the camera over the result and selects it
+fn go_to(uiw: &UiWorld, sim: &Simulation, entry: &SearchEntry) {
+    focus_camera(uiw, entry.pos, entry.size);
+
+    match entry.target {
+        SearchTarget::Building(id) => uiw.write::<InspectedBuilding>().e = Some(id),
+        SearchTarget::Human(id) => uiw.write::<InspectedEntity>().e = Some(AnyEntity::HumanID(id)),
+        SearchTarget::District(id) => {
+            // districts are renamed in the district tool
+            let Some(name) = sim.map().districts.get(id).map(|d| d.name.clone()) else {
+                return;
+            };
+            *uiw.write::<Tool>() = Tool::Districts;
+            let mut districts = uiw.write::<DistrictsResource>();
+            districts.points.clear();
+            districts.selected = Some(id);
+            districts.selected_name = name;
+        }
+        SearchTarget::Road(id) => {
+            // roads are inspected and renamed in the road editor
+            let Some(road) = sim.map().roads().get(id).map(|r| (r.src, r.dst)) else {
+                return;
+            };
+            *uiw.write::<Tool>() = Tool::RoadEditor;
+            let mut editor = uiw.write::<RoadEditorResource>();
+            editor.upgrade = false;
+            editor.inspect = None;
+            editor.road = Some(SelectedRoad {
+                src: road.0,
+                dst: road.1,
+                one_way: false,
+            });
+        }
+    }
+}
+
+/// Moves the camera over the position, zoomed out enough to see an object of the given size
+pub fn focus_camera(uiw: &UiWorld, pos: Vec3, size: f32) {
+    let mut camera = uiw.camera_mut();
+    let mut view = camera.view();
+    view.pos = pos;
+    view.dist = (size * 1.5).clamp(ZOOM_STREET, ZOOM_DISTRICT);
+    camera.transition_to(view);
+}
+
+fn format_dist(d: f32) -> String {
+    if d < 1000.0 {
+        format!("{:.0}m", d)
+    } else {
+        format!("{:.1}km", d / 1000.0)
+    }
+}
@@ -5,34 +5,46 @@ use yakui::{
     constrained, divider, Constraints, CrossAxisAlignment, MainAxisAlignItems, MainAxisSize, Vec2,
 };
 
-use common::saveload::Encoder;
-use engine::GfxSettings;
-use engine::ShadowQuality;
+use common::saveload::{AutoSaveSlots, Encoder, JSONPretty};
+use engine::{AudioContext, GfxContext, GfxSettings, ShadowQuality, TerrainDetail, N_CASCADES};
 use goryak::{
-    button_primary, checkbox_value, combo_box, dragvalue, icon_button, minrow,
-    on_secondary_container, outline, padx, padxy, textc, VertScrollSize, Window,
+    button_primary, button_secondary, checkbox_value, combo_box, dragvalue, error, icon_button,
+    minrow, on_secondary_container, outline, padx, padxy, selectable_label_primary, textc,
+    VertScrollSize, Window,
 };
 use serde::{Deserialize, Serialize};
+use simulation::gameplay::{Difficulty, GameplayParams};
+use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
+use crate::audio::MusicState;
 use crate::game_loop::Timings;
-use crate::inputmap::{Bindings, InputMap};
+use crate::inputmap::{Bindings, InputMap, BINDINGS_SAVE_NAME};
 use crate::newgui::keybinds::{KeybindState, KeybindStateInner};
+use crate::newgui::screenshot::Timelapse;
+use crate::newgui::windows::new_game;
 use crate::uiworld::UiWorld;
 
 const SETTINGS_SAVE_NAME: &str = "settings";
+const AUTOSAVE_PREFIX: &str = "autosave";
 
-#[derive(Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct Settings {
     pub camera_border_move: bool,
     pub camera_smooth: bool,
     pub camera_smooth_tightness: f32,
+    pub camera_zoom_sensitivity: f32,
+    pub camera_zoom_invert: bool,
+    /// Seconds for the camera to cover half the distance to a followed entity
+    pub camera_follow_half_life: f32,
     pub camera_fov: f32,
 
     pub gfx: GfxSettings,
 
     pub gui_scale: f32,
+    /// Code of the language of the interface, e.g. "fr"
+    pub language: String,
 
     pub master_volume_percent: f32,
     pub music_volume_percent: f32,
@@ -42,6 +54,16 @@ pub struct Settings {
     #[serde(skip)]
     pub time_warp: u32,
     pub auto_save_every: AutoSaveEvery,
+    /// Number of autosaves kept, the oldest one is overwritten
+    pub auto_save_slots: u32,
+    /// Seconds a notification stays in the corner of the screen
+    pub notification_duration: f32,
+    /// The minimap is expanded, otherwise it is a button
+    pub show_minimap: bool,
+    /// Screenshots are taken without the interface
+    pub clean_shot: bool,
+    /// Reload the prototypes when their files change, always done in debug builds
+    pub hot_reload_prototypes: bool,
 }
 
 impl Default for Settings {
@@ -55,20 +77,36 @@ impl Default for Settings {
             ui_volume_percent: 100.0,
             time_warp: 1,
             auto_save_every: AutoSaveEvery::FiveMinutes,
+            auto_save_slots: 3,
+            notification_duration: 8.0,
+            show_minimap: true,
+            clean_shot: true,
+            hot_reload_prototypes: false,
             camera_smooth_tightness: 1.0,
+            camera_zoom_sensitivity: 1.0,
+            camera_zoom_invert: false,
+            camera_follow_half_life: 0.15,
             camera_fov: 60.0,
             gui_scale: 1.0,
+            language: common::i18n::DEFAULT_LANGUAGE.to_string(),
             gfx: GfxSettings::default(),
         }
     }
 }
 
+impl Settings {
+    pub fn auto_save_slots(&self) -> AutoSaveSlots {
+        AutoSaveSlots::new(AUTOSAVE_PREFIX, self.auto_save_slots)
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(u8)]
 pub enum AutoSaveEvery {
     Never = 0,
     OneMinute = 1,
     FiveMinutes = 2,
+    FifteenMinutes = 3,
 }
 
 impl From<AutoSaveEvery> for Option<Duration> {
@@ -77,6 +115,7 @@ impl From<AutoSaveEvery> for Option<Duration> {
             AutoSaveEvery::Never => None,
             AutoSaveEvery::OneMinute => Some(Duration::from_secs(60)),
             AutoSaveEvery::FiveMinutes => Some(Duration::from_secs(5 * 60)),
+            AutoSaveEvery::FifteenMinutes => Some(Duration::from_secs(15 * 60)),
         }
     }
 }
@@ -87,6 +126,7 @@ impl From<u8> for AutoSaveEvery {
             0 => Self::Never,
             1 => Self::OneMinute,
             2 => Self::FiveMinutes,
+            3 => Self::FifteenMinutes,
             _ => Self::Never,
         }
     }
@@ -98,14 +138,32 @@ impl AsRef<str> for AutoSaveEvery {
             AutoSaveEvery::Never => "Never",
             AutoSaveEvery::OneMinute => "Minute",
             AutoSaveEvery::FiveMinutes => "Five Minutes",
+            AutoSaveEvery::FifteenMinutes => "Fifteen Minutes",
         }
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SettingsTab {
+    General,
+    Graphics,
+    Sound,
+    Controls,
+}
+
 pub struct SettingsState {
     fps: f32,
     ms: f32,
     instant: Instant,
+    tab: SettingsTab,
+    /// Codes and names of the languages found in the assets
+    languages: Vec<(String, String)>,
+    /// Shows the sliders changing the gameplay parameters of the current game
+    sandbox: bool,
+    /// Graphics settings for the gpu
+    recommended: GfxSettings,
+    /// An output device was found
+    audio_enabled: bool,
 }
 
 impl Default for SettingsState {
@@ -114,267 +172,563 @@ impl Default for SettingsState {
             fps: 0.0,
             ms: 0.0,
             instant: Instant::now(),
+            tab: SettingsTab::General,
+            languages: common::i18n::available_languages(),
+            sandbox: false,
+            recommended: GfxSettings::default(),
+            audio_enabled: true,
         }
     }
 }
 
 /// Settings window
-/// This window is used to change the settings of the game
-pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
+/// This window is used to change the settings of the game and the key bindings
+pub fn settings(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
     Window {
-        title: "Settings".into(),
+        title: t!("window-settings").into(),
         pad: Pad::all(10.0),
         radius: 10.0,
         opened,
-        child_spacing: 0.0,
+        child_spacing: 5.0,
     }
     .show(|| {
         profiling::scope!("gui::window::settings");
 
+        let mut tab = uiw.read::<SettingsState>().tab;
+        minrow(5.0, || {
+            for (t, name) in [
+                (SettingsTab::General, "settings-general"),
+                (SettingsTab::Graphics, "settings-graphics"),
+                (SettingsTab::Sound, "settings-sound"),
+                (SettingsTab::Controls, "settings-controls"),
+            ] {
+                if selectable_label_primary(tab == t, &t!(name)).clicked {
+                    tab = t;
+                }
+            }
+        });
+        uiw.write::<SettingsState>().tab = tab;
+
         VertScrollSize::Percent(0.8).show(|| {
             let mut l = List::column();
             l.item_spacing = 5.0;
             l.main_axis_size = MainAxisSize::Min;
-            l.show(|| {
-                let mut settings = uiw.write::<Settings>();
-                let mut state = uiw.write::<SettingsState>();
-                let before = *settings;
-
-                textc(on_secondary_container(), "Gameplay");
-                minrow(5.0, || {
-                    textc(on_secondary_container(), "Auto save every");
-                    let mut id = settings.auto_save_every as u8 as usize;
-                    if combo_box(
-                        &mut id,
-                        &[
-                            AutoSaveEvery::Never.as_ref(),
-                            AutoSaveEvery::OneMinute.as_ref(),
-                            AutoSaveEvery::FiveMinutes.as_ref(),
-                        ],
-                        200.0,
-                    ) {
-                        settings.auto_save_every = AutoSaveEvery::from(id as u8);
-                    }
-                });
+            l.show(|| match tab {
+                SettingsTab::General => general(uiw, sim),
+                SettingsTab::Graphics => graphics(uiw),
+                SettingsTab::Sound => sound(uiw),
+                SettingsTab::Controls => controls(uiw),
+            });
+        });
+    })
+}
 
-                divider(outline(), 10.0, 1.0);
-                textc(on_secondary_container(), "Input");
-                checkbox_value(
-                    &mut settings.camera_border_move,
-                    on_secondary_container(),
-                    "Border screen camera movement",
-                );
-                checkbox_value(
-                    &mut settings.camera_smooth,
-                    on_secondary_container(),
-                    "Camera smooth",
-                );
-
-                if settings.camera_smooth {
-                    minrow(5.0, || {
-                        dragvalue()
-                            .min(0.1)
-                            .max(2.0)
-                            .step(0.1)
-                            .show(&mut settings.camera_smooth_tightness);
-                        textc(on_secondary_container(), "Camera smoothing tightness");
-                    });
-                }
+/// Difficulty of the current game, it can only be changed in sandbox mode which marks the save
+fn difficulty(uiw: &UiWorld, sim: &Simulation, sandbox: &mut bool) {
+    let mut params = *sim.read::<GameplayParams>();
+    let mut label = format!("Difficulty: {}", params.difficulty.label());
+    if params.sandbox {
+        label += " (sandbox)";
+    }
+    textc(on_secondary_container(), label);
+    checkbox_value(
+        sandbox,
+        on_secondary_container(),
+        "Sandbox mode, changing the difficulty marks the save",
+    );
+    if *sandbox && new_game::params_sliders(&mut params) {
+        params.difficulty = Difficulty::Custom;
+        uiw.commands().push(WorldCommand::SetGameplayParams(params));
+    }
+}
 
-                minrow(5.0, || {
-                    dragvalue()
-                        .min(2.0)
-                        .max(179.0)
-                        .step(1.0)
-                        .show(&mut settings.camera_fov);
-                    textc(on_secondary_container(), "Camera Field of View (FOV)");
-                });
+/// Timelapse of the current city, captured from a view chosen by the player
+fn timelapse(uiw: &UiWorld) {
+    let mut timelapse = uiw.write::<Timelapse>();
+    checkbox_value(
+        &mut timelapse.enabled,
+        on_secondary_container(),
+        "Timelapse",
+    );
+    if !timelapse.enabled {
+        return;
+    }
+    minrow(5.0, || {
+        if button_primary("Use current view").show().clicked {
+            timelapse.view = Some(uiw.camera().view());
+        }
+        let status = match timelapse.view {
+            Some(_) => format!("{} frames captured", timelapse.next_frame),
+            None => "Choose the view to capture".to_string(),
+        };
+        textc(on_secondary_container(), status);
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .min(1.0)
+            .max(30.0)
+            .step(1.0)
+            .show(&mut timelapse.every_days);
+        textc(on_secondary_container(), "Capture every (days)");
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(23.0)
+            .step(1.0)
+            .show(&mut timelapse.hour);
+        textc(on_secondary_container(), "At hour");
+    });
+}
 
-                // only update the fps every 300ms to avoid flickering
-                if state.fps == 0.0 || state.instant.elapsed() > Duration::from_millis(300) {
-                    state.ms = uiw.read::<Timings>().all.avg();
-                    state.fps = 1.0 / state.ms;
-                    state.instant = Instant::now();
-                }
+fn general(uiw: &UiWorld, sim: &Simulation) {
+    let mut settings = uiw.write::<Settings>();
+    let mut state = uiw.write::<SettingsState>();
+    let before = settings.clone();
+
+    textc(on_secondary_container(), "Gameplay");
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Auto save every");
+        let mut id = settings.auto_save_every as u8 as usize;
+        if combo_box(
+            &mut id,
+            &[
+                AutoSaveEvery::Never.as_ref(),
+                AutoSaveEvery::OneMinute.as_ref(),
+                AutoSaveEvery::FiveMinutes.as_ref(),
+                AutoSaveEvery::FifteenMinutes.as_ref(),
+            ],
+            200.0,
+        ) {
+            settings.auto_save_every = AutoSaveEvery::from(id as u8);
+        }
+    });
+    if settings.auto_save_every != AutoSaveEvery::Never {
+        minrow(5.0, || {
+            dragvalue()
+                .min(1.0)
+                .max(10.0)
+                .step(1.0)
+                .show(&mut settings.auto_save_slots);
+            textc(on_secondary_container(), "Autosaves kept");
+        });
+    }
+    minrow(5.0, || {
+        dragvalue()
+            .min(2.0)
+            .max(60.0)
+            .step(1.0)
+            .show(&mut settings.notification_duration);
+        textc(on_secondary_container(), "Notifications shown for (s)");
+    });
+    difficulty(uiw, sim, &mut state.sandbox);
+
+    divider(outline(), 10.0, 1.0);
+    textc(on_secondary_container(), "Screenshots");
+    checkbox_value(
+        &mut settings.clean_shot,
+        on_secondary_container(),
+        "Hide the interface in screenshots",
+    );
+    timelapse(uiw);
+
+    divider(outline(), 10.0, 1.0);
+    textc(on_secondary_container(), "Input");
+    checkbox_value(
+        &mut settings.camera_border_move,
+        on_secondary_container(),
+        "Border screen camera movement",
+    );
+    checkbox_value(
+        &mut settings.camera_smooth,
+        on_secondary_container(),
+        "Camera smooth",
+    );
 
-                divider(outline(), 10.0, 1.0);
-                #[cfg(debug_assertions)]
-                textc(
-                    on_secondary_container(),
-                    "shouldn't be looking at FPS in debug mode! use --release",
-                );
-                textc(
-                    on_secondary_container(),
-                    format!(
-                        "Graphics - {:.1}FPS - {:.1}ms",
-                        state.fps,
-                        1000.0 * state.ms
-                    ),
-                );
-                checkbox_value(
-                    &mut settings.gfx.fullscreen,
-                    on_secondary_container(),
-                    "Fullscreen",
-                );
-                checkbox_value(
-                    &mut settings.gfx.terrain_grid,
-                    on_secondary_container(),
-                    "Terrain Grid",
-                );
-                checkbox_value(&mut settings.gfx.fog, on_secondary_container(), "Fog");
-                checkbox_value(
-                    &mut settings.gfx.ssao,
-                    on_secondary_container(),
-                    "Ambient Occlusion (SSAO)",
-                );
-                checkbox_value(
-                    &mut settings.gfx.msaa,
-                    on_secondary_container(),
-                    "MSAA 4x Anti-aliasing",
-                );
-                checkbox_value(&mut settings.gfx.vsync, on_secondary_container(), "VSync");
-                checkbox_value(
-                    &mut settings.gfx.parallel_render,
-                    on_secondary_container(),
-                    "Threaded rendering",
-                );
-
-                minrow(5.0, || {
-                    let mut id = settings.gfx.shadows as u8 as usize;
-                    if combo_box(
-                        &mut id,
-                        &[
-                            ShadowQuality::NoShadows.as_ref(),
-                            ShadowQuality::Low.as_ref(),
-                            ShadowQuality::Medium.as_ref(),
-                            ShadowQuality::High.as_ref(),
-                            ShadowQuality::Ultra.as_ref(),
-                        ],
-                        200.0,
-                    ) {
-                        settings.gfx.shadows = ShadowQuality::from(id as u8);
-                    }
-                    textc(on_secondary_container(), "Shadow Quality");
-                });
+    if settings.camera_smooth {
+        minrow(5.0, || {
+            dragvalue()
+                .min(0.1)
+                .max(2.0)
+                .step(0.1)
+                .show(&mut settings.camera_smooth_tightness);
+            textc(on_secondary_container(), "Camera smoothing tightness");
+        });
+    }
 
-                divider(outline(), 10.0, 1.0);
-                textc(on_secondary_container(), "GUI");
-                minrow(5.0, || {
-                    dragvalue().min(0.5).max(2.0).show(&mut settings.gui_scale);
-                    textc(on_secondary_container(), "GUI Scale");
-                });
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.1)
+            .max(3.0)
+            .step(0.1)
+            .show(&mut settings.camera_zoom_sensitivity);
+        textc(on_secondary_container(), "Zoom sensitivity");
+    });
+    checkbox_value(
+        &mut settings.camera_zoom_invert,
+        on_secondary_container(),
+        "Invert zoom direction",
+    );
 
-                divider(outline(), 10.0, 1.0);
-                textc(on_secondary_container(), "Audio");
-                minrow(5.0, || {
-                    dragvalue()
-                        .min(0.0)
-                        .max(100.0)
-                        .step(1.0)
-                        .show(&mut settings.master_volume_percent);
-                    textc(on_secondary_container(), "Master volume");
-                });
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(2.0)
+            .step(0.05)
+            .show(&mut settings.camera_follow_half_life);
+        textc(on_secondary_container(), "Camera follow smoothing (s)");
+    });
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(2.0)
+            .max(179.0)
+            .step(1.0)
+            .show(&mut settings.camera_fov);
+        textc(on_secondary_container(), "Camera Field of View (FOV)");
+    });
+
+    divider(outline(), 10.0, 1.0);
+    textc(on_secondary_container(), "Modding");
+    checkbox_value(
+        &mut settings.hot_reload_prototypes,
+        on_secondary_container(),
+        "Reload the prototypes when their files change",
+    );
 
-                minrow(5.0, || {
-                    dragvalue()
-                        .min(0.0)
-                        .max(100.0)
-                        .step(1.0)
-                        .show(&mut settings.music_volume_percent);
-                    textc(on_secondary_container(), "Music volume");
-                });
+    divider(outline(), 10.0, 1.0);
+    textc(on_secondary_container(), "GUI");
+    minrow(5.0, || {
+        dragvalue().min(0.5).max(2.0).show(&mut settings.gui_scale);
+        textc(on_secondary_container(), "GUI Scale");
+    });
+    minrow(5.0, || {
+        let languages = &state.languages;
+        let mut id = languages
+            .iter()
+            .position(|(code, _)| *code == settings.language)
+            .unwrap_or(0);
+        let names: Vec<&str> = languages.iter().map(|(_, name)| name.as_str()).collect();
+        if combo_box(&mut id, &names, 200.0) {
+            settings.language = languages[id].0.clone();
+        }
+        textc(on_secondary_container(), t!("settings-language"));
+    });
 
-                minrow(5.0, || {
-                    dragvalue()
-                        .min(0.0)
-                        .max(100.0)
-                        .step(1.0)
-                        .show(&mut settings.effects_volume_percent);
-                    textc(on_secondary_container(), "Effects volume");
-                });
+    if *settings != before {
+        common::saveload::JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
+    }
+}
 
-                minrow(5.0, || {
-                    dragvalue()
-                        .min(0.0)
-                        .max(100.0)
-                        .step(1.0)
-                        .show(&mut settings.ui_volume_percent);
-                    textc(on_secondary_container(), "Ui volume");
-                });
+/// The volumes are applied live by [`manage_settings`]
+fn sound(uiw: &UiWorld) {
+    let mut settings = uiw.write::<Settings>();
+    let before = settings.clone();
 
-                divider(outline(), 10.0, 1.0);
-                textc(on_secondary_container(), "Keybinds");
-                let mut bindings = uiw.write::<Bindings>();
-                if button_primary("Reset").show().clicked {
-                    *bindings = Bindings::default();
-                    uiw.write::<InputMap>().build_input_tree(&mut bindings);
-                }
+    if !uiw.read::<SettingsState>().audio_enabled {
+        textc(error(), t!("settings-no-audio"));
+    }
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(100.0)
+            .step(1.0)
+            .show(&mut settings.master_volume_percent);
+        textc(on_secondary_container(), "Master volume");
+    });
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(100.0)
+            .step(1.0)
+            .show(&mut settings.music_volume_percent);
+        textc(on_secondary_container(), "Music volume");
+    });
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(100.0)
+            .step(1.0)
+            .show(&mut settings.effects_volume_percent);
+        textc(on_secondary_container(), "Effects volume");
+    });
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(100.0)
+            .step(1.0)
+            .show(&mut settings.ui_volume_percent);
+        textc(on_secondary_container(), "Ui volume");
+    });
+
+    divider(outline(), 10.0, 1.0);
+    textc(on_secondary_container(), t!("settings-music"));
+    let mut music = uiw.write::<MusicState>();
+    if let Some((ref name, _)) = music.now_playing {
+        textc(on_secondary_container(), name.clone());
+    }
+    minrow(5.0, || {
+        let pause = if music.paused { "play" } else { "pause" };
+        if icon_button(button_secondary(pause)).show().clicked {
+            music.paused = !music.paused;
+        }
+        if icon_button(button_secondary("forward-step")).show().clicked {
+            music.skip = true;
+            music.paused = false;
+        }
+    });
+    textc(on_secondary_container(), t!("settings-music-folder"));
+
+    if *settings != before {
+        common::saveload::JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
+    }
+}
+
+/// The graphics are applied live by [`manage_settings`]
+fn graphics(uiw: &UiWorld) {
+    let mut settings = uiw.write::<Settings>();
+    let mut state = uiw.write::<SettingsState>();
+    let before = settings.clone();
+
+    // only update the fps every 300ms to avoid flickering
+    if state.fps == 0.0 || state.instant.elapsed() > Duration::from_millis(300) {
+        state.ms = uiw.read::<Timings>().all.avg();
+        state.fps = 1.0 / state.ms;
+        state.instant = Instant::now();
+    }
+
+    #[cfg(debug_assertions)]
+    textc(
+        on_secondary_container(),
+        "shouldn't be looking at FPS in debug mode! use --release",
+    );
+    textc(
+        on_secondary_container(),
+        format!("{:.1}FPS - {:.1}ms", state.fps, 1000.0 * state.ms),
+    );
+    checkbox_value(
+        &mut settings.gfx.fullscreen,
+        on_secondary_container(),
+        "Fullscreen",
+    );
+    checkbox_value(
+        &mut settings.gfx.terrain_grid,
+        on_secondary_container(),
+        "Terrain Grid",
+    );
+    checkbox_value(&mut settings.gfx.fog, on_secondary_container(), "Fog");
+    checkbox_value(
+        &mut settings.gfx.ssao,
+        on_secondary_container(),
+        "Ambient Occlusion (SSAO)",
+    );
+    checkbox_value(
+        &mut settings.gfx.msaa,
+        on_secondary_container(),
+        "MSAA 4x Anti-aliasing",
+    );
+    checkbox_value(&mut settings.gfx.vsync, on_secondary_container(), "VSync");
+    checkbox_value(
+        &mut settings.gfx.parallel_render,
+        on_secondary_container(),
+        "Threaded rendering",
+    );
+
+    minrow(5.0, || {
+        let mut id = settings.gfx.shadows as u8 as usize;
+        if combo_box(
+            &mut id,
+            &[
+                ShadowQuality::NoShadows.as_ref(),
+                ShadowQuality::Low.as_ref(),
+                ShadowQuality::Medium.as_ref(),
+                ShadowQuality::High.as_ref(),
+                ShadowQuality::Ultra.as_ref(),
+            ],
+            200.0,
+        ) {
+            settings.gfx.shadows = ShadowQuality::from(id as u8);
+        }
+        textc(on_secondary_container(), "Shadow Quality");
+    });
+    if settings.gfx.shadows != ShadowQuality::NoShadows {
+        minrow(5.0, || {
+            dragvalue()
+                .min(1.0)
+                .max(N_CASCADES as f64)
+                .step(1.0)
+                .show(&mut settings.gfx.shadow_cascades);
+            textc(on_secondary_container(), "Shadow cascades");
+        });
+    }
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.5)
+            .max(2.0)
+            .step(0.05)
+            .show(&mut settings.gfx.render_scale);
+        textc(on_secondary_container(), "Render scale");
+    });
+
+    minrow(5.0, || {
+        let mut id = settings.gfx.terrain_detail as u8 as usize;
+        if combo_box(
+            &mut id,
+            &[
+                TerrainDetail::Low.as_ref(),
+                TerrainDetail::Medium.as_ref(),
+                TerrainDetail::High.as_ref(),
+            ],
+            200.0,
+        ) {
+            settings.gfx.terrain_detail = TerrainDetail::from(id as u8);
+        }
+        textc(on_secondary_container(), "Terrain Detail");
+    });
+
+    let mut capped = settings.gfx.max_fps.is_some();
+    checkbox_value(&mut capped, on_secondary_container(), "Limit FPS");
+    if !capped {
+        settings.gfx.max_fps = None;
+    } else {
+        let mut max_fps = settings.gfx.max_fps.unwrap_or(60);
+        minrow(5.0, || {
+            dragvalue()
+                .min(15.0)
+                .max(360.0)
+                .step(5.0)
+                .show(&mut max_fps);
+            textc(on_secondary_container(), "Max FPS");
+        });
+        settings.gfx.max_fps = Some(max_fps);
+    }
 
-                let mut sorted_inps = bindings.0.keys().cloned().collect::<Vec<_>>();
-                sorted_inps.sort();
-
-                constrained(
-                    Constraints::loose(Vec2::new(f32::INFINITY, 100000.0)),
-                    || {
-                        CountGrid::col(4)
-                            .main_axis_size(MainAxisSize::Min)
-                            .cross_axis_aligment(CrossAxisAlignment::Start)
-                            .main_axis_align_items(MainAxisAlignItems::Center)
-                            .show(|| {
-                                for action in &sorted_inps {
-                                    let comb = bindings.0.get_mut(action).unwrap();
-                                    padx(2.0, || {
-                                        textc(on_secondary_container(), action.to_string());
-                                    });
-                                    let print_comb = |index: usize| {
-                                        padx(2.0, || {
-                                            minrow(0.0, || {
-                                                let resp = if comb.0.len() > index {
-                                                    button_primary(format!("{}", comb.0[index]))
-                                                        .show()
-                                                } else {
-                                                    button_primary("<empty>").show()
-                                                };
-                                                if resp.clicked {
-                                                    let mut state = uiw.write::<KeybindState>();
-                                                    state.enabled = Some(KeybindStateInner {
-                                                        to_bind_to: action.clone(),
-                                                        cur: Default::default(),
-                                                        bind_index: index,
-                                                    });
-                                                }
-                                            });
-                                        });
+    divider(outline(), 10.0, 1.0);
+    if button_primary("Reset to the recommended settings")
+        .show()
+        .clicked
+    {
+        settings.gfx = state.recommended;
+    }
+
+    if *settings != before {
+        common::saveload::JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
+    }
+}
+
+/// Every action with its two bindings.
+/// Bindings also used by another action are highlighted, clicking one waits for the new input.
+fn controls(uiw: &UiWorld) {
+    let mut bindings = uiw.write::<Bindings>();
+    let before = bindings.clone();
+
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Click a binding to change it");
+        if button_primary("Reset all").show().clicked {
+            *bindings = Bindings::default();
+        }
+    });
+
+    let mut sorted_inps = bindings.0.keys().cloned().collect::<Vec<_>>();
+    sorted_inps.sort();
+
+    constrained(
+        Constraints::loose(Vec2::new(f32::INFINITY, 100000.0)),
+        || {
+            CountGrid::col(5)
+                .main_axis_size(MainAxisSize::Min)
+                .cross_axis_aligment(CrossAxisAlignment::Start)
+                .main_axis_align_items(MainAxisAlignItems::Center)
+                .show(|| {
+                    for action in &sorted_inps {
+                        let combs = bindings.0[action].0.clone();
+                        let mut conflicts = vec![];
+                        for comb in &combs {
+                            for other in bindings.conflicts(action, comb) {
+                                if !conflicts.contains(&other) {
+                                    conflicts.push(other);
+                                }
+                            }
+                        }
+
+                        padx(2.0, || {
+                            textc(on_secondary_container(), action.to_string());
+                        });
+                        for index in 0..2 {
+                            padx(2.0, || {
+                                minrow(0.0, || {
+                                    let mut b = match combs.get(index) {
+                                        Some(comb) => button_primary(format!("{}", comb)),
+                                        None => button_primary("<empty>"),
                                     };
-                                    print_comb(0);
-                                    print_comb(1);
-                                    padxy(8.0, 2.0, || {
-                                        minrow(0.0, || {
-                                            if icon_button(button_primary("arrows-rotate"))
-                                                .show()
-                                                .clicked
-                                            {
-                                                comb.0 =
-                                                    Bindings::default().0.remove(action).unwrap().0;
-                                            }
-                                        });
-                                    });
+                                    if combs
+                                        .get(index)
+                                        .is_some_and(|c| !bindings.conflicts(action, c).is_empty())
+                                    {
+                                        b.style.fill = error();
+                                        b.hover_style.fill = error().adjust(1.2);
+                                    }
+                                    if b.show().clicked {
+                                        uiw.write::<KeybindState>().enabled =
+                                            Some(KeybindStateInner::new(action.clone(), index));
+                                    }
+                                });
+                            });
+                        }
+                        padxy(8.0, 2.0, || {
+                            minrow(0.0, || {
+                                if icon_button(button_primary("arrows-rotate")).show().clicked {
+                                    bindings.reset(action);
                                 }
                             });
-                    },
-                );
+                        });
+                        padx(2.0, || {
+                            if conflicts.is_empty() {
+                                textc(on_secondary_container(), "");
+                            } else {
+                                let names: Vec<_> =
+                                    conflicts.iter().map(ToString::to_string).collect();
+                                textc(error(), format!("Also used by {}", names.join(", ")));
+                            }
+                        });
+                    }
+                });
+        },
+    );
 
-                if *settings != before {
-                    common::saveload::JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
-                }
-            });
-        });
-    })
+    if *bindings != before {
+        uiw.write::<InputMap>().build_input_tree(&mut bindings);
+        common::saveload::JSONPretty::save_silent(&*bindings, BINDINGS_SAVE_NAME);
+    }
+}
+
+/// At the first launch, when no settings were saved, the graphics are picked for the gpu
+pub fn init_gfx_settings(uiw: &UiWorld, gfx: &GfxContext) {
+    let recommended = gfx.recommended_settings();
+    uiw.write::<SettingsState>().recommended = recommended;
+
+    if std::path::Path::new(&JSONPretty::filename(SETTINGS_SAVE_NAME)).exists() {
+        return;
+    }
+    let mut settings = uiw.write::<Settings>();
+    settings.gfx = recommended;
+    JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
+}
+
+pub fn init_audio_settings(uiw: &UiWorld, audio: &AudioContext) {
+    uiw.write::<SettingsState>().audio_enabled = audio.is_enabled();
 }
 
 pub fn manage_settings(ctx: &mut engine::Context, settings: &Settings) {
     ctx.gfx.update_settings(settings.gfx);
 
+    common::i18n::set_language(&settings.language);
+
     ctx.egui.zoom_factor = settings.gui_scale;
 
     ctx.audio.set_settings(
@@ -0,0 +1,307 @@
+use yakui::paint::PaintMesh;
+use yakui::widgets::Pad;
+use yakui::{use_state, Color, Vec2};
+
+use engine::Tesselator;
+use geom::{vec2, vec3, AABB};
+use goryak::{
+    hover_pos, mincolumn, minrow, on_primary_container, padxy, pady, selectable_label_primary,
+    sized_canvas, textc, Window,
+};
+use prototypes::Money;
+use simulation::souls::happiness::CityStats;
+use simulation::souls::tourism::Tourism;
+use simulation::statistics::{StatSeries, Statistics};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+const PLOT_WIDTH: f32 = 360.0;
+const PLOT_HEIGHT: f32 = 80.0;
+
+const BLUE: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+const GREEN: [f32; 4] = [0.3, 0.9, 0.4, 1.0];
+const RED: [f32; 4] = [1.0, 0.35, 0.3, 1.0];
+const YELLOW: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum StatisticsTab {
+    #[default]
+    Population,
+    Economy,
+    Traffic,
+    Power,
+}
+
+#[derive(Default)]
+pub struct StatisticsState {
+    pub tab: StatisticsTab,
+}
+
+/// Statistics window
+/// Plots the hourly statistics of the city over the last days
+pub fn statistics(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-statistics").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<StatisticsState>();
+        let stats = sim.read::<Statistics>();
+
+        pady(10.0, || {
+            minrow(10.0, || {
+                let tabs = &[
+                    ("Population", StatisticsTab::Population),
+                    ("Economy", StatisticsTab::Economy),
+                    ("Traffic", StatisticsTab::Traffic),
+                    ("Power", StatisticsTab::Power),
+                ];
+                for (label, tab) in tabs {
+                    if selectable_label_primary(state.tab == *tab, label).clicked {
+                        state.tab = *tab;
+                    }
+                }
+            });
+        });
+
+        if stats.population.is_empty() {
+            textc(
+                on_primary_container(),
+                "Statistics are collected every in-game hour",
+            );
+            return;
+        }
+
+        mincolumn(5.0, || match state.tab {
+            StatisticsTab::Population => {
+                plot("Population", &stats.population, "", BLUE);
+                plot("Births per hour", &stats.births, "", GREEN);
+                plot("Deaths per hour", &stats.deaths, "", RED);
+                textc(
+                    on_primary_container(),
+                    format!(
+                        "Last day: {:.0} births, {:.0} deaths",
+                        stats.births.sum_last(24),
+                        stats.deaths.sum_last(24)
+                    ),
+                );
+                age_histogram(&sim.read::<CityStats>());
+                plot_values(
+                    "Tourist arrivals per day",
+                    &stats.tourist_arrivals.per_day(),
+                    "",
+                    YELLOW,
+                    "d",
+                );
+                let tourism = sim.read::<Tourism>();
+                textc(
+                    on_primary_container(),
+                    format!(
+                        "Last day: {:.0} tourist arrivals, {}/{} hotel rooms taken",
+                        stats.tourist_arrivals.sum_last(24),
+                        tourism.tourists,
+                        tourism.rooms
+                    ),
+                );
+            }
+            StatisticsTab::Economy => {
+                plot("Treasury", &stats.money, "$", YELLOW);
+                plot("External trade income", &stats.export_income, "$", GREEN);
+                plot("External trade expenses", &stats.import_expenses, "$", RED);
+                plot("Tariffs", &stats.tariff_income, "$", GREEN);
+                plot("Construction", &stats.construction_expenses, "$", RED);
+                plot("Other (bills and upkeep)", &stats.other, "$", BLUE);
+            }
+            StatisticsTab::Traffic => {
+                plot("Average trip time", &stats.trip_time, "s", BLUE);
+                plot("Vehicles en route", &stats.vehicles_en_route, "", YELLOW);
+                plot("Congestion index", &stats.congestion, "", RED);
+            }
+            StatisticsTab::Power => {
+                plot("Production", &stats.power_production, "kW", GREEN);
+                plot("Consumption", &stats.power_consumption, "kW", YELLOW);
+                plot("Blackout", &stats.blackout, "", RED);
+                textc(
+                    on_primary_container(),
+                    format!(
+                        "Blackout hours in the last day: {:.1}",
+                        stats.blackout.sum_last(24)
+                    ),
+                );
+            }
+        });
+    });
+}
+
+/// Filled line chart of the hourly series, hovering shows the value at the cursor
+fn plot(label: &str, series: &StatSeries, unit: &str, color: [f32; 4]) {
+    let values: Vec<f32> = series.values().collect();
+    plot_values(label, &values, unit, color, "h");
+}
+
+/// Filled line chart of values `period` apart, e.g. "d" for one value per day
+fn plot_values(label: &str, values: &[f32], unit: &str, color: [f32; 4], period: &str) {
+    let hovered = use_state(|| None::<usize>);
+    if values.is_empty() {
+        return;
+    }
+
+    let shown = hovered
+        .get()
+        .filter(|&i| i < values.len())
+        .unwrap_or(values.len() - 1);
+    let ago = values.len() - 1 - shown;
+    let when = if ago == 0 {
+        "now".to_string()
+    } else {
+        format!("{ago}{period} ago")
+    };
+    textc(
+        on_primary_container(),
+        format!(
+            "{label}: {} {unit} ({when})",
+            format_value(values[shown], unit)
+        ),
+    );
+
+    let min = values.iter().copied().fold(0.0f32, f32::min);
+    let max = values.iter().copied().fold(0.0f32, f32::max);
+    let range = (max - min).max(1e-3);
+    let x_step = PLOT_WIDTH / (values.len().max(2) - 1) as f32;
+    let to_plot = |i: usize, v: f32| {
+        vec2(
+            i as f32 * x_step,
+            (v - min) / range * (PLOT_HEIGHT - 4.0) + 2.0,
+        )
+    };
+    let zero = to_plot(0, 0.0).y;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let cull_rect = AABB::new_ll_size(vec2(0.0, 0.0), vec2(PLOT_WIDTH, PLOT_HEIGHT));
+    let mut tess = Tesselator::new(&mut vertices, &mut indices, Some(cull_rect), 15.0);
+
+    tess.set_color([color[0], color[1], color[2], 0.3]);
+    for (i, w) in values.windows(2).enumerate() {
+        let a = to_plot(i, w[0]);
+        let b = to_plot(i + 1, w[1]);
+        tess.draw_filled_polygon(&[vec2(a.x, zero), a, b, vec2(b.x, zero)], 0.0);
+    }
+
+    tess.set_color(color);
+    let line: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| to_plot(i, v).z(0.0))
+        .collect();
+    tess.draw_polyline(&line, 1.5, false);
+
+    if hovered.get().is_some() {
+        let x = to_plot(shown, 0.0).x;
+        tess.set_color([1.0, 1.0, 1.0, 0.6]);
+        tess.draw_line(vec3(x, 0.0, 0.0), vec3(x, PLOT_HEIGHT, 0.0));
+    }
+
+    let resp = hover_pos(|| {
+        sized_canvas(
+            Vec2::new(PLOT_WIDTH, PLOT_HEIGHT),
+            Color::BLACK,
+            move |paint| {
+                let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+
+                let [x, y]: [f32; 2] = rect.pos().into();
+                let [_sx, sy]: [f32; 2] = rect.size().into();
+
+                paint.paint.add_mesh(PaintMesh::new(
+                    vertices.into_iter().map(|v| {
+                        yakui::paint::Vertex::new(
+                            [x + v.position[0], y + sy - v.position[1]],
+                            v.uv,
+                            v.color,
+                        )
+                    }),
+                    indices.into_iter().map(|x| x as _),
+                ));
+            },
+        );
+    });
+
+    hovered.set(
+        resp.pos
+            .map(|p| ((p.x / x_step).round().max(0.0) as usize).min(values.len() - 1)),
+    );
+}
+
+/// Number of citizens in each decade of age
+fn age_histogram(city: &CityStats) {
+    let ages = city.age_histogram();
+    let max = ages.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bar_width = PLOT_WIDTH / ages.len() as f32;
+    let hovered = use_state(|| None::<usize>);
+
+    let shown = hovered.get().filter(|&i| i < ages.len());
+    let last = ages.len() - 1;
+    let text = match shown {
+        Some(i) if i == last => format!("Aged {}+: {}", i * 10, ages[i]),
+        Some(i) => format!("Aged {}-{}: {}", i * 10, i * 10 + 9, ages[i]),
+        None => "Ages".to_string(),
+    };
+    padxy(0.0, 5.0, || textc(on_primary_container(), text));
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let cull_rect = AABB::new_ll_size(vec2(0.0, 0.0), vec2(PLOT_WIDTH, PLOT_HEIGHT));
+    let mut tess = Tesselator::new(&mut vertices, &mut indices, Some(cull_rect), 15.0);
+
+    for (i, &n) in ages.iter().enumerate() {
+        let alpha = if shown == Some(i) { 1.0 } else { 0.7 };
+        tess.set_color([BLUE[0], BLUE[1], BLUE[2], alpha]);
+        let x0 = i as f32 * bar_width + 1.0;
+        let x1 = (i + 1) as f32 * bar_width - 1.0;
+        let h = n as f32 / max * (PLOT_HEIGHT - 2.0);
+        tess.draw_filled_polygon(
+            &[vec2(x0, 0.0), vec2(x0, h), vec2(x1, h), vec2(x1, 0.0)],
+            0.0,
+        );
+    }
+
+    let resp = hover_pos(|| {
+        sized_canvas(
+            Vec2::new(PLOT_WIDTH, PLOT_HEIGHT),
+            Color::BLACK,
+            move |paint| {
+                let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+
+                let [x, y]: [f32; 2] = rect.pos().into();
+                let [_sx, sy]: [f32; 2] = rect.size().into();
+
+                paint.paint.add_mesh(PaintMesh::new(
+                    vertices.into_iter().map(|v| {
+                        yakui::paint::Vertex::new(
+                            [x + v.position[0], y + sy - v.position[1]],
+                            v.uv,
+                            v.color,
+                        )
+                    }),
+                    indices.into_iter().map(|x| x as _),
+                ));
+            },
+        );
+    });
+
+    hovered.set(resp.pos.map(|p| ((p.x / bar_width) as usize).min(last)));
+}
+
+fn format_value(v: f32, unit: &str) -> String {
+    if unit == "$" {
+        Money::from_float_bucks(v as f64).fmt_compact()
+    } else if v.abs() >= 100.0 || v == v.trunc() {
+        format!("{:.0}", v)
+    } else {
+        format!("{:.2}", v)
+    }
+}
@@ -0,0 +1,104 @@
+use goryak::{button_primary, button_secondary, minrow, on_secondary_container, textc, Window};
+use simulation::transportation::transit::{BusLineID, Transit};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+use std::borrow::Cow;
+use yakui::widgets::Pad;
+
+use crate::newgui::hud::toolbox::updown_value;
+use crate::newgui::Tool;
+use crate::uiworld::UiWorld;
+
+/// The line whose stops are being picked with the bus stop tool
+#[derive(Default)]
+pub struct TransitEditor {
+    pub editing: Option<BusLineID>,
+}
+
+fn label(x: impl Into<Cow<'static, str>>) {
+    textc(on_secondary_container(), x);
+}
+
+/// Bus lines window
+/// Lists the lines with their stops in order, the number of buses and the ridership
+pub fn transit(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: t!("window-bus-lines").into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let transit = sim.read::<Transit>();
+        let mut editor = uiw.write::<TransitEditor>();
+        if let Some(l) = editor.editing {
+            if !transit.lines().contains_key(l) {
+                editor.editing = None;
+            }
+        }
+
+        if transit.lines().is_empty() {
+            label("No bus lines, place stops with the bus stop tool then create a line");
+        }
+
+        let mut commands = uiw.commands();
+        for (id, line) in transit.lines() {
+            let editing = editor.editing == Some(id);
+            minrow(5.0, || {
+                label(line.name.clone());
+                label(format!("{} stops", line.stops.len()));
+                label(format!("{} riders", line.ridership));
+
+                let mut n_buses = line.n_buses as f32;
+                if updown_value(&mut n_buses, 1.0, " buses") {
+                    commands.push(WorldCommand::UpdateBusLine {
+                        line: id,
+                        stops: line.stops.clone(),
+                        n_buses: n_buses.max(0.0) as u32,
+                    });
+                }
+
+                if button_primary(if editing { "Done" } else { "Edit stops" })
+                    .show()
+                    .clicked
+                {
+                    editor.editing = if editing { None } else { Some(id) };
+                    if !editing {
+                        *uiw.write::<Tool>() = Tool::BusStop;
+                    }
+                }
+                if button_secondary("Delete").show().clicked {
+                    commands.push(WorldCommand::RemoveBusLine(id));
+                }
+            });
+
+            if !editing {
+                continue;
+            }
+            if line.stops.is_empty() {
+                label("Click on bus stops to add them to the line");
+            }
+            for (i, &stop) in line.stops.iter().enumerate() {
+                minrow(5.0, || {
+                    label(format!("{}. {} waiting", i + 1, transit.waiting_at(stop)));
+                    if button_secondary("Remove").show().clicked {
+                        let mut stops = line.stops.clone();
+                        stops.remove(i);
+                        commands.push(WorldCommand::UpdateBusLine {
+                            line: id,
+                            stops,
+                            n_buses: line.n_buses,
+                        });
+                    }
+                });
+            }
+        }
+
+        if button_primary("New line").show().clicked {
+            commands.push(WorldCommand::AddBusLine {
+                name: format!("Line {}", transit.lines().len() + 1),
+            });
+        }
+    });
+}
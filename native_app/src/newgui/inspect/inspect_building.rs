@@ -1,18 +1,32 @@
 use goryak::{
-    dragvalue, fixed_spacer, minrow, on_secondary_container, primary, textc, ProgressBar, Window,
+    button_primary, button_secondary, dragvalue, error, fixed_spacer, minrow,
+    on_secondary_container, primary, textc, ProgressBar, Window,
 };
-use prototypes::{ItemID, Recipe};
-use simulation::economy::Market;
+use prototypes::{
+    AirportPrototypeID, Education, GoodsCompanyPrototype, HarborPrototypeID, HotelPrototypeID,
+    LeisurePrototypeID, Money, SchoolPrototypeID,
+};
+use simulation::economy::{daily_rent, FreightThroughput, JobMarket, Market, SeaTrade, ShipLeg};
 use simulation::map::{Building, BuildingID, BuildingKind, Zone, MAX_ZONE_AREA};
-use simulation::map_dynamic::{BuildingInfos, ElectricityFlow};
+use simulation::map_dynamic::{
+    BuildingInfos, ElectricityFlow, Fires, Garbage, LandValue, ParkingManagement, WaterFlow,
+    GARBAGE_THRESHOLD, MAX_GARBAGE,
+};
+use simulation::overview::{ItemStock, OpenOrder, UtilityStatus};
+use simulation::souls::desire::LeisureVisitors;
+use simulation::souls::education::Schools;
 use simulation::souls::freight_station::FreightTrainState;
+use simulation::souls::tourism::hotel_guests;
+use simulation::transportation::air_traffic::{AirTraffic, FlightPhase};
+use simulation::transportation::passenger_rail::PassengerRail;
+use simulation::weather::Weather;
 use simulation::world_command::WorldCommand;
-use simulation::{Simulation, SoulID};
+use simulation::{HumanID, Simulation, SoulID};
 use std::borrow::Cow;
 use yakui::widgets::Pad;
-use yakui::Vec2;
+use yakui::{use_state, Vec2};
 
-use crate::newgui::inspect::entity_link;
+use crate::newgui::inspect::{building_link, entity_link, follow_button};
 use crate::newgui::item_icon_yakui;
 use crate::uiworld::UiWorld;
 
@@ -31,8 +45,15 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
         BuildingKind::House => "House",
         BuildingKind::GoodsCompany(id) => &id.prototype().name,
         BuildingKind::RailFreightStation(id) => &id.prototype().name,
+        BuildingKind::RailPassengerStation(id) => &id.prototype().name,
+        BuildingKind::Warehouse(id) => &id.prototype().name,
+        BuildingKind::School(id) => &id.prototype().name,
+        BuildingKind::Leisure(id) => &id.prototype().name,
+        BuildingKind::Hotel(id) => &id.prototype().name,
         BuildingKind::TrainStation => "Train Station",
         BuildingKind::ExternalTrading => "External Trading",
+        BuildingKind::Harbor(id) => &id.prototype().name,
+        BuildingKind::Airport(id) => &id.prototype().name,
     };
 
     let mut is_open = true;
@@ -56,10 +77,35 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
             BuildingKind::RailFreightStation(_) => {
                 render_freightstation(uiworld, sim, building);
             }
+            BuildingKind::RailPassengerStation(_) => {
+                render_passenger_station(sim, building);
+            }
+            BuildingKind::Warehouse(_) => {
+                render_warehouse(uiworld, sim, building);
+            }
+            BuildingKind::School(id) => {
+                render_school(sim, building, id);
+            }
+            BuildingKind::Leisure(id) => {
+                render_leisure(sim, building, id);
+            }
+            BuildingKind::Hotel(id) => {
+                render_hotel(sim, building, id);
+            }
             BuildingKind::TrainStation => {}
             BuildingKind::ExternalTrading => {}
+            BuildingKind::Harbor(id) => {
+                render_harbor(sim, building, id);
+            }
+            BuildingKind::Airport(id) => {
+                render_airport(sim, building, id);
+            }
         };
 
+        render_fire(uiworld, sim, building);
+        render_garbage(sim, building);
+        render_parking(sim, building);
+
         if let Some(ref zone) = building.zone {
             let mut cpy = zone.filldir;
             minrow(5.0, || {
@@ -93,25 +139,101 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
     is_open
 }
 
-fn render_house(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
-    let binfos = sim.read::<BuildingInfos>();
-    let Some(info) = binfos.get(b.id) else {
+fn render_fire(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let fires = sim.read::<Fires>();
+    if fires.is_ruin(b.id) {
+        textc(error(), "Burned down, bulldoze it to rebuild");
         return;
-    };
-    let Some(SoulID::Human(owner)) = info.owner else {
+    }
+    let Some(fire) = fires.get(b.id) else {
         return;
     };
+    textc(error(), "On fire!");
+    match (fire.responder, fire.arrived) {
+        (_, Some(_)) => label("Firefighters are putting out the fire"),
+        (Some(station), None) => {
+            minrow(5.0, || {
+                label("Responding:");
+                building_link(uiworld, sim, station);
+            });
+        }
+        (None, None) => textc(error(), "No fire station in range"),
+    }
+}
 
-    minrow(5.0, || {
-        label("Owner");
-        entity_link(uiworld, sim, owner);
+fn render_garbage(sim: &Simulation, b: &Building) {
+    let garbage = sim.read::<Garbage>();
+    let level = garbage.level(b.id);
+    if level == 0.0 {
+        return;
+    }
+
+    ProgressBar {
+        value: level / MAX_GARBAGE,
+        size: Vec2::new(200.0, 25.0),
+        color: if garbage.is_dirty(b.id) {
+            error()
+        } else {
+            primary().adjust(0.7)
+        },
+    }
+    .show_children(|| {
+        label(format!("garbage: {:.0}/{:.0}kg", level, GARBAGE_THRESHOLD));
     });
+    if garbage.is_dirty(b.id) {
+        textc(error(), "Garbage is not collected, productivity drops");
+    }
+}
 
-    label("Currently in the house:");
-    for &soul in info.inside.iter() {
-        let SoulID::Human(soul) = soul else {
-            continue;
-        };
+fn render_parking(sim: &Simulation, b: &Building) {
+    let failed = sim.read::<ParkingManagement>().failed_parking(b.id);
+    if failed == 0 {
+        return;
+    }
+    label(format!("Drivers who couldn't find parking: {}", failed));
+}
+
+fn render_house(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let owner = sim.read::<BuildingInfos>().owner(b.id);
+    let land_value = sim.read::<LandValue>().land_value(b.obb.center());
+    label(format!(
+        "Land value: {:.0}% ({}/day rent)",
+        land_value * 100.0,
+        daily_rent(land_value)
+    ));
+
+    let residents = sim.residents(b.id);
+    if residents.is_empty() {
+        label("Nobody lives here");
+        return;
+    }
+
+    label(format!("Residents: {}", residents.len()));
+    for resident in residents {
+        minrow(5.0, || {
+            entity_link(uiworld, sim, resident);
+            if owner == Some(SoulID::Human(resident)) {
+                label("(owner)");
+            }
+            follow_button(uiworld, resident);
+        });
+    }
+
+    let inside: Vec<HumanID> = sim
+        .read::<BuildingInfos>()
+        .get(b.id)
+        .map(|info| {
+            info.inside
+                .iter()
+                .filter_map(|&soul| match soul {
+                    SoulID::Human(id) => Some(id),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    label(format!("Currently in the house: {}", inside.len()));
+    for soul in inside {
         entity_link(uiworld, sim, soul);
     }
 }
@@ -127,6 +249,14 @@ fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     label(format!("Waiting cargo: {}", freight.f.waiting_cargo));
     label(format!("Wanted cargo: {}", freight.f.wanted_cargo));
 
+    let capacity = freight.f.proto.prototype().throughput;
+    let utilization = sim.read::<FreightThroughput>().utilization(b.id, capacity);
+    label(format!(
+        "Freight station: {:.0}% capacity ({}/h)",
+        utilization * 100.0,
+        capacity
+    ));
+
     fixed_spacer((0.0, 10.0));
     label("Trains:");
     for (tid, state) in &freight.f.trains {
@@ -147,40 +277,242 @@ fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
-fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
-    let owner = sim.read::<BuildingInfos>().owner(b.id);
+fn render_warehouse(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let Some(SoulID::Warehouse(owner)) = sim.read::<BuildingInfos>().owner(b.id) else {
+        return;
+    };
+    let Some(w) = sim.world().get(owner) else {
+        return;
+    };
+    let market = sim.read::<Market>();
+
+    label("Stock");
+    for item in &w.w.proto.prototype().capacity {
+        let stock = market.capital(owner.into(), item.id);
+        minrow(5.0, || {
+            item_icon_yakui(uiworld, item.id, stock);
+            label(format!("{}/{}", stock, item.amount));
+        });
+    }
+}
+
+fn render_school(sim: &Simulation, b: &Building, id: SchoolPrototypeID) {
+    let proto = id.prototype();
+    let schools = sim.read::<Schools>();
+    let enrolled = schools.enrolled(b.id);
+
+    ProgressBar {
+        value: enrolled.len() as f32 / proto.capacity.max(1) as f32,
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("Enrolled: {}/{}", enrolled.len(), proto.capacity));
+    });
+
+    if sim.read::<ElectricityFlow>().is_shed(b.id) {
+        textc(error(), "No power, classes are cancelled");
+    }
+
+    label(format!("Teaches up to: {}", proto.max_education.label()));
+}
 
-    let Some(SoulID::GoodsCompany(c_id)) = owner else {
+fn render_leisure(sim: &Simulation, b: &Building, id: LeisurePrototypeID) {
+    let proto = id.prototype();
+    let visitors = sim.read::<LeisureVisitors>().visitors(b.id);
+
+    ProgressBar {
+        value: visitors as f32 / proto.capacity.max(1) as f32,
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("Visitors: {}/{}", visitors, proto.capacity));
+    });
+
+    if proto.entry_fee > Money::ZERO {
+        label(format!("Entry fee: {}", proto.entry_fee.localized()));
+    }
+}
+
+fn render_hotel(sim: &Simulation, b: &Building, id: HotelPrototypeID) {
+    let proto = id.prototype();
+    let guests = hotel_guests(sim, b.id);
+
+    ProgressBar {
+        value: guests as f32 / proto.rooms.max(1) as f32,
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("Guests: {}/{}", guests, proto.rooms));
+    });
+
+    if guests >= proto.rooms as usize {
+        label("Fully booked, tourists are turned away");
+    }
+}
+
+fn render_harbor(sim: &Simulation, b: &Building, id: HarborPrototypeID) {
+    let capacity = id.prototype().throughput;
+    let utilization = sim.read::<FreightThroughput>().utilization(b.id, capacity);
+    label(format!(
+        "Harbor: {:.0}% capacity ({}/h)",
+        utilization * 100.0,
+        capacity
+    ));
+
+    let sea = sim.read::<SeaTrade>();
+    if sea.reaches_sea(b.id) == Some(false) {
+        textc(error(), "Ships can't reach the docks from the sea");
+    }
+
+    let count = |leg| {
+        sea.ships
+            .iter()
+            .filter(|s| s.harbor == b.id && s.leg == leg)
+            .count()
+    };
+    label(format!("Ships arriving: {}", count(ShipLeg::Arriving)));
+    label(format!("Ships docked: {}", count(ShipLeg::Docked)));
+    label(format!("Ships leaving: {}", count(ShipLeg::Leaving)));
+}
+
+fn render_airport(sim: &Simulation, b: &Building, id: AirportPrototypeID) {
+    let air = sim.read::<AirTraffic>();
+    let stats = air.airports.get(&b.id).cloned().unwrap_or_default();
+    if stats.schedule < 0.1 {
+        textc(error(), "The city is too small for planes to come");
+    }
+    label(format!(
+        "Flights per day: {:.1} (up to {})",
+        stats.schedule,
+        id.prototype().flights_per_day
+    ));
+    label(format!(
+        "Flights landed: {} today, {} yesterday",
+        stats.flights_today, stats.flights_yesterday
+    ));
+    label(format!(
+        "Passengers: {} today, {} yesterday, {} in total",
+        stats.passengers_today, stats.passengers_yesterday, stats.passengers
+    ));
+    label(format!(
+        "Business travelers in the city: {}",
+        air.business_travelers(b.id)
+    ));
+    label(format!(
+        "Spent by the business travelers: {}",
+        stats.business_spending
+    ));
+
+    let count = |phase| {
+        air.flights
+            .iter()
+            .filter(|f| f.airport == b.id && f.phase == phase)
+            .count()
+    };
+    label(format!(
+        "Planes landing: {}",
+        count(FlightPhase::Approach) + count(FlightPhase::Rollout)
+    ));
+    label(format!(
+        "Planes at the gate: {}",
+        count(FlightPhase::AtGate)
+    ));
+    label(format!(
+        "Planes taking off: {}",
+        count(FlightPhase::TakeOff)
+    ));
+}
+
+fn render_passenger_station(sim: &Simulation, b: &Building) {
+    let rail = sim.read::<PassengerRail>();
+    let Some(station) = rail.stations().get(&b.id) else {
+        textc(error(), "Too far from the tracks to be served");
+        return;
+    };
+    if rail.trains().is_empty() {
+        textc(error(), "Needs another station to be served by trains");
+    }
+    label(format!(
+        "Waiting on the platform: {}",
+        rail.waiting_at(b.id)
+    ));
+    label(format!("Passengers carried: {}", station.ridership));
+}
+
+fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let Some(overview) = sim.company_overview(b.id) else {
         return;
     };
-    let Some(c) = sim.world().companies.get(c_id) else {
+    let Some(c) = sim.world().companies.get(overview.company) else {
         return;
     };
     let goods = &c.comp;
-    let workers = &c.workers;
     let proto = c.comp.proto.prototype();
 
-    let market = &*sim.read::<Market>();
-    let map = &*sim.map();
-    let elec_flow = &*sim.read::<ElectricityFlow>();
-
-    let max_workers = goods.max_workers;
     ProgressBar {
-        value: workers.0.len() as f32 / max_workers as f32,
+        value: overview.workers as f32 / overview.max_workers.max(1) as f32,
         size: Vec2::new(200.0, 25.0),
         color: primary().adjust(0.7),
     }
     .show_children(|| {
-        label(format!("workers: {}/{}", workers.0.len(), max_workers));
+        label(format!(
+            "workers: {}/{}, {} at work",
+            overview.workers, overview.max_workers, overview.workers_present
+        ));
     });
 
+    if let Some(offer) = sim.read::<JobMarket>().offer_of(overview.company) {
+        label(format!("Wage: {}/day", offer.wage));
+    }
+    if proto.min_education != Education::None {
+        label(format!("Requires: {}", proto.min_education.label()));
+    }
+    label(format!("Balance: {}", c.finances.balance));
+    if let Some(days) = c.finances.days_until_bankruptcy(proto.bankruptcy_days) {
+        textc(
+            error(),
+            format!("Bankrupt in {} days if the balance stays negative", days),
+        );
+    }
+
     if let Some(driver) = goods.driver {
         minrow(5.0, || {
             label("Driver is");
             entity_link(uiworld, sim, driver);
         });
     }
-    let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
+    if overview.trucks > 0 {
+        label(format!(
+            "Trucks out: {}/{}",
+            overview.trucks_out.len(),
+            overview.trucks
+        ));
+        for &truck in &overview.trucks_out {
+            entity_link(uiworld, sim, truck);
+        }
+        if overview.trips_saved > 0 {
+            label(format!(
+                "Trips saved by grouping deliveries: {}",
+                overview.trips_saved
+            ));
+        }
+    }
+
+    render_utility("Power", overview.power);
+    render_utility("Water", overview.water);
+
+    let productivity = c.productivity(
+        proto,
+        b.zone.as_ref(),
+        &sim.read::<ElectricityFlow>(),
+        &sim.read::<WaterFlow>(),
+        &sim.read::<Garbage>(),
+        &sim.read::<Fires>(),
+        &sim.read::<Weather>(),
+    );
     if productivity < 1.0 {
         ProgressBar {
             value: productivity,
@@ -195,11 +527,63 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
         });
     }
 
-    if let Some(ref r) = proto.recipe {
-        render_recipe(uiworld, r);
+    if proto.recipe.is_some() {
+        render_stock(uiworld, "Input", &overview.inputs);
+        render_stock(uiworld, "Output", &overview.outputs);
     }
 
-    if let Some(net_id) = map.electricity.net_id(b.id) {
+    render_power(sim, b, proto, productivity);
+
+    ProgressBar {
+        value: goods.progress,
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("{:.0}%", goods.progress * 100.0));
+    });
+
+    render_orders(uiworld, "Buying", &overview.buy_orders);
+    render_orders(uiworld, "Selling", &overview.sell_orders);
+
+    fixed_spacer((0.0, 10.0));
+    let confirm_close = use_state(|| false);
+    minrow(5.0, || {
+        let prioritize = if overview.prioritized {
+            "Stop prioritizing"
+        } else {
+            "Prioritize"
+        };
+        if button_primary(prioritize).show().clicked {
+            uiworld.commands().push(WorldCommand::SetCompanyPriority {
+                building: b.id,
+                prioritized: !overview.prioritized,
+            });
+        }
+
+        if !confirm_close.get() {
+            if button_secondary("Close company").show().clicked {
+                confirm_close.set(true);
+            }
+            return;
+        }
+        if button_primary("Confirm closing").show().clicked {
+            uiworld.commands().push(WorldCommand::CloseCompany(b.id));
+            confirm_close.set(false);
+        }
+        if button_secondary("Cancel").show().clicked {
+            confirm_close.set(false);
+        }
+    });
+    if overview.prioritized {
+        label("Its buy orders are served before the others");
+    }
+}
+
+fn render_power(sim: &Simulation, b: &Building, proto: &GoodsCompanyPrototype, productivity: f32) {
+    let elec_flow = sim.read::<ElectricityFlow>();
+
+    if let Some(net_id) = sim.map().electricity.net_id(b.id) {
         let blackout = elec_flow.blackout(net_id);
 
         if let Some(power_c) = proto.power_consumption {
@@ -241,59 +625,61 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
         }
     }
 
-    ProgressBar {
-        value: goods.progress,
-        size: Vec2::new(200.0, 25.0),
-        color: primary().adjust(0.7),
-    }
-    .show_children(|| {
-        label(format!("{:.0}%", goods.progress * 100.0));
-    });
-
-    fixed_spacer((0.0, 10.0));
-    label("Storage");
-
-    let jobopening = ItemID::new("job-opening");
-    for (&id, m) in market.iter() {
-        let Some(v) = m.capital(c_id.into()) else {
-            continue;
-        };
-        if id == jobopening && v == 0 {
-            continue;
+    if let Some(capacity) = proto.storage_capacity {
+        let charge = elec_flow.storage_charge(b.id, capacity);
+        ProgressBar {
+            value: charge,
+            size: Vec2::new(200.0, 25.0),
+            color: primary().adjust(0.7),
         }
-
-        item_icon_yakui(uiworld, id, v);
+        .show_children(|| {
+            label(format!(
+                "stored: {}/{} ({:.0}%)",
+                elec_flow.stored_energy(b.id),
+                capacity,
+                (charge * 100.0).round()
+            ));
+        });
     }
 }
 
-fn render_recipe(uiworld: &UiWorld, recipe: &Recipe) {
-    if recipe.consumption.is_empty() {
-        label("No Inputs");
-    } else {
-        label(if recipe.consumption.len() == 1 {
-            "Input"
-        } else {
-            "Inputs"
-        });
-        minrow(5.0, || {
-            for item in recipe.consumption.iter() {
-                item_icon_yakui(uiworld, item.id, item.amount);
-            }
-        });
+fn render_utility(name: &str, status: UtilityStatus) {
+    match status {
+        UtilityStatus::Unused => {}
+        UtilityStatus::Disconnected => textc(error(), format!("{}: not connected", name)),
+        UtilityStatus::Shortage => textc(error(), format!("{}: not enough on the network", name)),
+        UtilityStatus::Ok => label(format!("{}: connected", name)),
     }
+}
 
-    if recipe.production.is_empty() {
-        label("No Outputs");
+/// Stock of the recipe items, compared to what one batch uses or makes
+fn render_stock(uiworld: &UiWorld, what: &str, stocks: &[ItemStock]) {
+    if stocks.is_empty() {
+        label(format!("No {}s", what));
+        return;
+    }
+    label(if stocks.len() == 1 {
+        what.to_string()
     } else {
-        label(if recipe.production.len() == 1 {
-            "Output"
-        } else {
-            "Outputs"
-        });
-        minrow(5.0, || {
-            for item in recipe.production.iter() {
-                item_icon_yakui(uiworld, item.id, item.amount);
-            }
-        });
+        format!("{}s", what)
+    });
+    minrow(5.0, || {
+        for s in stocks {
+            item_icon_yakui(uiworld, s.item, s.stock);
+            label(format!("{}/{}", s.stock, s.per_batch));
+        }
+    });
+}
+
+fn render_orders(uiworld: &UiWorld, what: &'static str, orders: &[OpenOrder]) {
+    if orders.is_empty() {
+        return;
     }
+    label(what);
+    minrow(5.0, || {
+        for o in orders {
+            item_icon_yakui(uiworld, o.item, o.qty as i32);
+            label(format!("x{}", o.qty));
+        }
+    });
 }
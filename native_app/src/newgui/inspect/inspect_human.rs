@@ -1,33 +1,39 @@
-use goryak::{dragvalue, fixed_spacer, minrow, on_secondary_container, textc, Window};
-use prototypes::ItemID;
+use goryak::{
+    button_primary, fixed_spacer, minrow, on_secondary_container, outline, textc, Window,
+};
+use prototypes::HOURS_PER_DAY;
 use std::borrow::Cow;
 use yakui::widgets::Pad;
+use yakui::{colored_box, Color, Vec2};
 
 use simulation::economy::Market;
 use simulation::map_dynamic::Destination;
+use simulation::souls::activity::{Activity, ActivityLog, ActivitySpan};
 use simulation::souls::desire::WorkKind;
 use simulation::transportation::Location;
 use simulation::{HumanID, Simulation};
 
-use crate::newgui::inspect::{building_link, follow_button};
+use crate::newgui::inspect::{building_link, entity_link, follow_button};
 use crate::newgui::item_icon_yakui;
 use crate::uiworld::UiWorld;
 
-/// Inspect a specific building, showing useful information about it
+const TIMELINE_WIDTH: f32 = 288.0;
+
+fn label(x: impl Into<Cow<'static, str>>) {
+    textc(on_secondary_container(), x);
+}
+
+/// Inspect a specific human, showing where they live and work and what they did today
 pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
     let Some(human) = sim.get(id) else {
         return false;
     };
 
     let pinfo = &human.personal_info;
-    let title = format!("{}{:?} • {}", pinfo.age, pinfo.gender, pinfo.name);
+    let title = format!("{}{:?} • {}", pinfo.age as u32, pinfo.gender, pinfo.name);
 
     let mut is_open = true;
 
-    fn label(x: impl Into<Cow<'static, str>>) {
-        textc(on_secondary_container(), x);
-    }
-
     Window {
         title: title.into(),
         pad: Pad::all(10.0),
@@ -40,10 +46,27 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
             label(format!("{:?}", id));
         }
 
+        minrow(5.0, || {
+            follow_button(uiworld, id);
+            if button_primary("teleport camera").show().clicked {
+                if let Some(pos) = sim.pos(id) {
+                    uiworld.camera_mut().follow(pos, 0.0, 0.0);
+                }
+            }
+        });
+
+        label(pinfo.education.label());
+
         match human.location {
             Location::Outside => {}
-            Location::Vehicle(_) => {
-                label("In a vehicle");
+            Location::Vehicle(v) => {
+                minrow(5.0, || {
+                    label("In a vehicle:");
+                    entity_link(uiworld, sim, v);
+                });
+            }
+            Location::Train(_) => {
+                label("On a train");
             }
             Location::Building(x) => {
                 minrow(5.0, || {
@@ -67,63 +90,156 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
             }
         }
 
-        minrow(5.0, || {
-            label("House is");
-            building_link(uiworld, sim, human.home.house);
-        });
+        if let Some(ref tourist) = human.tourist {
+            minrow(5.0, || {
+                label("Tourist staying at");
+                building_link(uiworld, sim, tourist.hotel);
+            });
+            label(format!("Leaves on {}", tourist.departure));
+            label(format!("Spent at the shops: {}", tourist.spent));
+        } else {
+            minrow(5.0, || {
+                label("House is");
+                building_link(uiworld, sim, human.home.house);
+            });
+        }
 
         label(format!("Last ate: {}", human.food.last_ate));
 
+        if let Some(venue) = human.leisure.visiting() {
+            minrow(5.0, || {
+                label("Visiting");
+                building_link(uiworld, sim, venue);
+            });
+        }
+        label(format!(
+            "Went out {} times this week",
+            human
+                .leisure
+                .visits_last_week(&sim.read::<prototypes::GameTime>())
+        ));
+
         if let Some(ref x) = human.work {
             minrow(5.0, || {
                 label("Working at");
                 building_link(uiworld, sim, x.workplace);
-                match x.kind {
-                    WorkKind::Driver { .. } => {
-                        label("as a driver");
-                    }
-                    WorkKind::Worker => {
-                        label("as a worker");
-                    }
-                }
+                label(match x.kind {
+                    WorkKind::Driver { .. } => "as a driver",
+                    WorkKind::GarbageCollector { .. } => "as a garbage collector",
+                    WorkKind::Firefighter { .. } => "as a firefighter",
+                    WorkKind::Worker => "as a worker",
+                });
             });
+            label(format!("Wage: {}/day", x.wage));
         }
 
+        label(format!("Money: {}", human.wallet.0.localized()));
+
         fixed_spacer((0.0, 10.0));
-        label("Desires");
-        minrow(5.0, || {
-            let mut score = human.food.last_score;
-            dragvalue().show(&mut score);
-            label("Food");
-        });
-        minrow(5.0, || {
-            let mut score = human.home.last_score;
-            dragvalue().show(&mut score);
-            label("Home");
-        });
-        minrow(5.0, || {
-            let mut score = human.work.as_ref().map(|x| x.last_score).unwrap_or(0.0);
-            dragvalue().show(&mut score);
-            label("Work");
-        });
+        let desires = [
+            ("Home", Some(human.home.last_score)),
+            ("Work", human.work.as_ref().map(|w| w.last_score)),
+            ("BuyFood", Some(human.food.last_score)),
+            ("Leisure", Some(human.leisure.last_score)),
+            ("Tourist", human.tourist.as_ref().map(|t| t.last_score)),
+        ];
+        let current = desires
+            .iter()
+            .filter_map(|&(name, score)| Some((name, score?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(name, _)| name);
+        label(format!("Current desire: {}", current.unwrap_or("None")));
+        for (name, score) in desires {
+            let Some(score) = score else {
+                continue;
+            };
+            label(format!("{}: {:.2}", name, score));
+        }
+
+        fixed_spacer((0.0, 10.0));
+        let log = sim.read::<ActivityLog>();
+        if let Some(activity) = log.current(id) {
+            label(format!("Now: {}", activity.label()));
+        }
+        label("Today");
+        timeline(&log.today(id, &sim.read::<prototypes::GameTime>()));
+        drop(log);
+        legend();
+
+        if !human.bought.0.is_empty() {
+            fixed_spacer((0.0, 10.0));
+            label("Bought");
+            minrow(5.0, || {
+                for (&item_id, trades) in human.bought.0.iter() {
+                    let qty: i32 = trades.iter().map(|t| t.qty).sum();
+                    item_icon_yakui(uiworld, item_id, qty);
+                }
+            });
+        }
 
         let market = sim.read::<Market>();
 
         fixed_spacer((0.0, 10.0));
 
-        let jobopening = ItemID::new("job-opening");
         for (&item_id, m) in market.iter() {
             let Some(v) = m.capital(id.into()) else {
                 continue;
             };
-            if item_id == jobopening {
-                continue;
-            }
 
             item_icon_yakui(uiworld, item_id, v);
         }
-
-        follow_button(uiworld, id);
     });
     is_open
 }
+
+fn activity_color(activity: Activity) -> Color {
+    match activity {
+        Activity::Sleep => Color::rgb(60, 70, 140),
+        Activity::Home => Color::rgb(90, 140, 220),
+        Activity::Commute => Color::rgb(230, 180, 60),
+        Activity::Work => Color::rgb(90, 190, 110),
+        Activity::Shop => Color::rgb(210, 100, 180),
+        Activity::Leisure => Color::rgb(120, 210, 200),
+    }
+}
+
+/// 24 hour bar of the activities of the day, what was not recorded is left blank
+fn timeline(spans: &[ActivitySpan]) {
+    let px_per_hour = TIMELINE_WIDTH / HOURS_PER_DAY as f32;
+    let blank = outline().with_alpha(0.3);
+
+    minrow(0.0, || {
+        let mut t = 0.0;
+        for span in spans {
+            let start = span.start.max(t);
+            if start > t {
+                colored_box(blank, Vec2::new((start - t) * px_per_hour, 12.0));
+            }
+            let w = (span.end - start).max(0.0) * px_per_hour;
+            colored_box(activity_color(span.activity), Vec2::new(w, 12.0));
+            t = span.end.max(t);
+        }
+        if t < HOURS_PER_DAY as f32 {
+            colored_box(
+                blank,
+                Vec2::new((HOURS_PER_DAY as f32 - t) * px_per_hour, 12.0),
+            );
+        }
+    });
+}
+
+fn legend() {
+    minrow(5.0, || {
+        for activity in [
+            Activity::Sleep,
+            Activity::Home,
+            Activity::Commute,
+            Activity::Work,
+            Activity::Shop,
+            Activity::Leisure,
+        ] {
+            colored_box(activity_color(activity), Vec2::new(8.0, 8.0));
+            label(activity.label());
+        }
+    });
+}
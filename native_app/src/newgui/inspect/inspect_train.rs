@@ -12,7 +12,7 @@ pub fn inspect_train(uiworld: &UiWorld, sim: &Simulation, id: TrainID) -> bool {
     let mut is_open = true;
 
     Window {
-        title: "Train".into(),
+        title: t!("window-train").into(),
         pad: Pad::all(10.0),
         radius: 10.0,
         opened: &mut is_open,
@@ -1,6 +1,7 @@
 use crate::newgui::inspect::{entity_link, follow_button};
 use crate::uiworld::UiWorld;
 use goryak::{minrow, on_secondary_container, textc, Window};
+use simulation::transportation::transit::Transit;
 use simulation::transportation::VehicleState;
 use simulation::{Simulation, VehicleID};
 use yakui::widgets::Pad;
@@ -43,6 +44,18 @@ pub fn inspect_vehicle(uiworld: &UiWorld, sim: &Simulation, id: VehicleID) -> bo
             }
         }
 
+        let transit = sim.read::<Transit>();
+        if let Some(bus) = transit.bus(id) {
+            if let Some(line) = transit.lines().get(bus.line) {
+                textc(on_secondary_container(), format!("Serving {}", line.name));
+            }
+            textc(
+                on_secondary_container(),
+                format!("{} passengers", transit.n_passengers(id)),
+            );
+        }
+        drop(transit);
+
         for (human_id, human) in &sim.world().humans {
             if human.router.personal_car == Some(id) {
                 minrow(5.0, || {
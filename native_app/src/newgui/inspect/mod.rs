@@ -86,8 +86,10 @@ fn entity_link_inner(uiworld: &UiWorld, sim: &Simulation, e: AnyEntity) {
 
     if primary_link(linkname) {
         uiworld.write::<InspectedEntity>().e = Some(e);
-        if sim.pos_any(e).is_some() {
-            uiworld.write::<FollowEntity>().0 = Some(e);
+        let now = uiworld.time_always();
+        let mut follow = uiworld.write::<FollowEntity>();
+        if follow.link_clicked(e, now) && sim.pos_any(e).is_some() {
+            follow.target = Some(e);
         }
     }
 }
@@ -98,7 +100,7 @@ pub fn follow_button(uiworld: &UiWorld, id: impl Into<AnyEntity>) {
 
 fn follow_button_inner(uiworld: &UiWorld, id: AnyEntity) {
     let mut follow = uiworld.write::<FollowEntity>();
-    if follow.0 != Some(id) && button_primary("follow").show().clicked {
-        follow.0 = Some(id);
+    if follow.target != Some(id) && button_primary("follow").show().clicked {
+        follow.target = Some(id);
     }
 }
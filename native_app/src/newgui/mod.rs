@@ -1,15 +1,19 @@
+use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::GUIWindows;
 use crate::uiworld::UiWorld;
 use serde::{Deserialize, Serialize};
 use simulation::map::BuildingID;
+use simulation::notifications::NotificationTarget;
 use simulation::world_command::WorldCommand;
 use simulation::{AnyEntity, Simulation};
 use std::borrow::Cow;
 use std::time::Instant;
 
 pub mod follow;
+pub mod hot_reload;
 mod hud;
 pub mod inspect;
+pub mod screenshot;
 mod textures;
 mod tools;
 
@@ -39,20 +43,38 @@ impl Default for GuiState {
 
 pub fn run_ui_systems(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::run_ui_systems");
+    undo_redo(uiworld);
     bulldozer::bulldozer(sim, uiworld);
+    copypaste::copypaste(sim, uiworld);
+    crossing::crossing(sim, uiworld);
+    districts::districts(sim, uiworld);
+    forestry::forestry(sim, uiworld);
     inspected_aura::inspected_aura(sim, uiworld);
     lotbrush::lotbrush(sim, uiworld);
     roadbuild::roadbuild(sim, uiworld);
     roadeditor::roadeditor(sim, uiworld);
     specialbuilding::specialbuilding(sim, uiworld);
     addtrain::addtrain(sim, uiworld);
+    busstop::busstop(sim, uiworld);
     zoneedit::zoneedit(sim, uiworld);
     terraforming::terraforming(sim, uiworld);
+    zoning::zoning(sim, uiworld);
 
     // run last so other systems can have the chance to cancel select
     selectable::selectable(sim, uiworld);
 }
 
+/// Undoes or redoes the last map edit
+fn undo_redo(uiworld: &UiWorld) {
+    let inp = uiworld.read::<InputMap>();
+    if inp.just_act.contains(&InputAction::Undo) {
+        uiworld.commands().map_undo();
+    }
+    if inp.just_act.contains(&InputAction::Redo) {
+        uiworld.commands().map_redo();
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct ErrorTooltip {
     pub msg: Option<Cow<'static, str>>,
@@ -78,6 +100,70 @@ impl ErrorTooltip {
     }
 }
 
+/// Short messages shown at the top of the screen for a few seconds
+#[derive(Default, Clone, Debug)]
+pub struct Toasts {
+    /// The oldest first
+    pub msgs: Vec<Toast>,
+    /// Counter of the failed commands already shown, see [`simulation::world_command::FailedCommands::since`]
+    pub seen_command_errors: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub msg: Cow<'static, str>,
+    /// When it was last pushed
+    pub time: f32,
+    /// Seconds it stays shown
+    pub duration: f32,
+    /// How many times it was pushed while shown
+    pub count: u32,
+    /// What the camera moves to when the toast is clicked
+    pub target: Option<NotificationTarget>,
+}
+
+impl Toasts {
+    pub const DURATION: f32 = 4.0;
+
+    pub fn push(&mut self, msg: impl Into<Cow<'static, str>>, now: f32) {
+        self.push_target(msg, None, now, Self::DURATION);
+    }
+
+    /// Pushes a message shown for `duration` seconds, the same message pushed again while it is
+    /// shown is counted instead of shown twice
+    pub fn push_target(
+        &mut self,
+        msg: impl Into<Cow<'static, str>>,
+        target: Option<NotificationTarget>,
+        now: f32,
+        duration: f32,
+    ) {
+        let msg = msg.into();
+        if let Some(i) = self.msgs.iter().position(|t| t.msg == msg) {
+            // moved to the end so that it is shown as the newest
+            let mut toast = self.msgs.remove(i);
+            toast.count += 1;
+            toast.time = now;
+            toast.duration = duration;
+            toast.target = target;
+            self.msgs.push(toast);
+            return;
+        }
+        self.msgs.push(Toast {
+            msg,
+            time: now,
+            duration,
+            count: 1,
+            target,
+        });
+    }
+
+    /// Removes the expired messages
+    pub fn update(&mut self, now: f32) {
+        self.msgs.retain(|t| now - t.time < t.duration);
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct PotentialCommands(pub Vec<WorldCommand>);
 
@@ -91,12 +177,16 @@ impl PotentialCommands {
 #[derive(Copy, Clone, Debug, Default)]
 pub struct InspectedBuilding {
     pub e: Option<BuildingID>,
+    /// Building under the cursor when there is no entity under it
+    pub hovered: Option<BuildingID>,
     pub dontclear: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct InspectedEntity {
     pub e: Option<AnyEntity>,
+    /// Entity that would be inspected by clicking
+    pub hovered: Option<AnyEntity>,
     pub dist2: f32,
     pub dontclear: bool,
 }
@@ -105,6 +195,7 @@ impl Default for InspectedEntity {
     fn default() -> Self {
         Self {
             e: None,
+            hovered: None,
             dist2: 0.0,
             dontclear: false,
         }
@@ -128,6 +219,12 @@ pub enum Tool {
     SpecialBuilding,
     Train,
     Terraforming,
+    Copy,
+    Crossing,
+    Zoning,
+    Forestry,
+    BusStop,
+    Districts,
 }
 
 impl Tool {
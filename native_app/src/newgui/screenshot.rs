@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use engine::Context;
+use geom::Camera;
+use prototypes::GameTime;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::Toasts;
+use crate::rendering::CameraView;
+use crate::uiworld::{CurrentSave, UiWorld};
+
+const SCREENSHOTS_DIR: &str = "screenshots";
+
+/// Screenshots asked with the hotkey or the menu button
+#[derive(Default)]
+pub struct ScreenshotState {
+    /// A screenshot is taken at the end of the next update
+    pub requested: bool,
+    /// Camera of the player, moved away for a timelapse frame and put back on the next frame
+    restore: Option<Camera>,
+    /// The last screenshot asked by the player, to tell them once it is written
+    manual: Option<PathBuf>,
+}
+
+/// Frames of the city captured from the same view at a fixed in-game time,
+/// numbered so that they can be assembled into a video.
+/// Saved with the game, so that the numbering goes on after loading it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timelapse {
+    pub enabled: bool,
+    pub view: Option<CameraView>,
+    /// A frame is captured every this many in-game days
+    pub every_days: u32,
+    /// Hour of the day at which the frame is captured
+    pub hour: u32,
+    pub next_frame: u32,
+    /// Day of the last frame, to capture once per day
+    last_day: Option<i32>,
+}
+
+impl Default for Timelapse {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            view: None,
+            every_days: 1,
+            hour: 12,
+            next_frame: 0,
+            last_day: None,
+        }
+    }
+}
+
+impl Timelapse {
+    pub fn due(&self, time: &GameTime) -> bool {
+        self.enabled
+            && self.view.is_some()
+            && self.last_day != Some(time.daytime.day)
+            && time.daytime.day % self.every_days.max(1) as i32 == 0
+            && time.daytime.hour >= self.hour as i32
+    }
+}
+
+/// Puts back the camera of the player after a timelapse frame, before it is moved again
+pub fn restore_camera(uiw: &UiWorld) {
+    if let Some(camera) = uiw.write::<ScreenshotState>().restore.take() {
+        uiw.camera_mut().camera = camera;
+    }
+}
+
+/// Asks the renderer for the screenshot of this frame if one is due, and reports the written ones.
+/// Must be called once the camera is updated.
+pub fn capture(uiw: &UiWorld, sim: &Simulation, ctx: &mut Context) {
+    profiling::scope!("screenshot::capture");
+    report(uiw, ctx);
+
+    let mut state = uiw.write::<ScreenshotState>();
+    if uiw
+        .read::<InputMap>()
+        .just_act
+        .contains(&InputAction::Screenshot)
+    {
+        state.requested = true;
+    }
+    if std::mem::take(&mut state.requested) {
+        let path = unused_path(
+            PathBuf::from(SCREENSHOTS_DIR),
+            &format!("egregoria_{}", timestamp()),
+        );
+        let with_gui = !uiw.read::<Settings>().clean_shot;
+        ctx.gfx.request_capture(path.clone(), with_gui);
+        state.manual = Some(path);
+        return;
+    }
+
+    let time = *sim.read::<GameTime>();
+    let mut timelapse = uiw.write::<Timelapse>();
+    if !timelapse.due(&time) {
+        return;
+    }
+    let Some(view) = timelapse.view else {
+        return;
+    };
+    let save = uiw
+        .read::<CurrentSave>()
+        .name
+        .clone()
+        .unwrap_or_else(|| "world".to_string());
+    let dir = PathBuf::from(SCREENSHOTS_DIR).join("timelapse").join(save);
+    // an older save of the game is behind the frames already captured, they are kept
+    let mut path = dir.join(format!("frame_{:05}.png", timelapse.next_frame));
+    while path.exists() {
+        timelapse.next_frame += 1;
+        path = dir.join(format!("frame_{:05}.png", timelapse.next_frame));
+    }
+    timelapse.next_frame += 1;
+    timelapse.last_day = Some(time.daytime.day);
+
+    let mut camera = uiw.camera_mut();
+    state.restore = Some(camera.camera);
+    camera.camera.pos = view.pos;
+    camera.camera.yaw = view.yaw;
+    camera.camera.pitch = view.pitch;
+    camera.camera.dist = view.dist;
+    camera.update(ctx);
+    ctx.gfx.request_capture(path, false);
+}
+
+/// Toasts for the screenshots of the player that were written and for the failures
+fn report(uiw: &UiWorld, ctx: &Context) {
+    let results = ctx.gfx.take_screenshot_results();
+    if results.is_empty() {
+        return;
+    }
+    let now = uiw.time_always();
+    let mut state = uiw.write::<ScreenshotState>();
+    let mut toasts = uiw.write::<Toasts>();
+    for result in results {
+        match result {
+            Ok(path) if state.manual.as_ref() == Some(&path) => {
+                state.manual = None;
+                toasts.push(format!("Screenshot saved to {}", path.display()), now);
+            }
+            Ok(_) => {}
+            Err(e) => toasts.push(e, now),
+        }
+    }
+}
+
+/// `dir/name.png`, or with a number appended if it already exists
+fn unused_path(dir: PathBuf, name: &str) -> PathBuf {
+    let mut path = dir.join(format!("{name}.png"));
+    let mut i = 2;
+    while path.exists() {
+        path = dir.join(format!("{name}_{i}.png"));
+        i += 1;
+    }
+    path
+}
+
+/// Current date and time in UTC, usable in a file name
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (y, m, d) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{y:04}-{m:02}-{d:02}_{:02}-{:02}-{:02}",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// Year, month and day of the number of days since 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::{Radians, Vec3};
+    use prototypes::{DayTime, Tick};
+
+    fn at(day: i32, hour: i32) -> GameTime {
+        let mut time = GameTime::new(Tick(0));
+        time.daytime = DayTime {
+            day,
+            hour,
+            minute: 0,
+            second: 0,
+        };
+        time
+    }
+
+    fn timelapse(every_days: u32) -> Timelapse {
+        Timelapse {
+            enabled: true,
+            view: Some(CameraView {
+                pos: Vec3::ZERO,
+                yaw: Radians::ZERO,
+                pitch: Radians::ZERO,
+                dist: 100.0,
+            }),
+            every_days,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timelapse_is_due_once_per_period_after_its_hour() {
+        let mut t = timelapse(2);
+        assert!(!t.due(&at(0, 11)));
+        assert!(t.due(&at(0, 12)));
+        assert!(t.due(&at(0, 20)));
+
+        t.last_day = Some(0);
+        assert!(!t.due(&at(0, 13)));
+        assert!(!t.due(&at(1, 13)));
+        assert!(t.due(&at(2, 12)));
+
+        // a period of 0 days is every day
+        t.every_days = 0;
+        assert!(t.due(&at(1, 12)));
+
+        assert!(!Timelapse::default().due(&at(0, 12)));
+        t.view = None;
+        assert!(!t.due(&at(2, 12)));
+    }
+
+    #[test]
+    fn days_since_epoch_to_civil_date() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+}
@@ -1,15 +1,48 @@
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::specialbuilding::SpecialBuildingResource;
 use crate::newgui::Tool;
-use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use crate::uiworld::UiWorld;
 use egui_inspect::Inspect;
-use simulation::map::{BuildingKind, Map, ProjectFilter, ProjectKind};
+use engine::AudioKind;
+use geom::{Vec2, AABB};
+use simulation::map::{BuildingKind, BulldozeFilter, Map, ProjectFilter, ProjectKind};
 use simulation::Simulation;
 
+#[derive(Copy, Clone, Default)]
+pub enum AreaState {
+    /// Waiting for the player to start dragging
+    #[default]
+    Idle,
+    /// Dragging a rectangle from this corner
+    Selecting(Vec2),
+    /// Some buildings would lose their road access, waiting for the player to confirm
+    Confirm(AABB),
+}
+
 #[derive(Copy, Clone, Default, Inspect)]
 pub struct BulldozerState {
     hold: bool,
+    /// Removes everything inside a dragged rectangle instead of the hovered object
+    pub area: bool,
+    #[inspect(skip)]
+    pub filter: BulldozeFilter,
+    #[inspect(skip)]
+    pub area_state: AreaState,
+}
+
+impl BulldozerState {
+    /// The area currently being selected or waiting for confirmation
+    pub fn selected_area(&self, cursor: Option<Vec2>) -> Option<AABB> {
+        match self.area_state {
+            AreaState::Idle => None,
+            AreaState::Selecting(start) => {
+                let cursor = cursor?;
+                Some(AABB::new_ll_ur(start.min(cursor), start.max(cursor)))
+            }
+            AreaState::Confirm(area) => Some(area),
+        }
+    }
 }
 
 /// Bulldozer tool
@@ -17,8 +50,15 @@ pub struct BulldozerState {
 pub fn bulldozer(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::bulldozer");
     let tool: &Tool = &uiworld.read::<Tool>();
+    let state: &mut BulldozerState = &mut uiworld.write::<BulldozerState>();
 
     if !matches!(*tool, Tool::Bulldozer) {
+        state.area_state = AreaState::Idle;
+        return;
+    }
+
+    if state.area {
+        bulldozer_area(sim, uiworld, state);
         return;
     }
 
@@ -26,7 +66,6 @@ pub fn bulldozer(sim: &Simulation, uiworld: &UiWorld) {
     let map: &Map = &sim.map();
     let draw: &mut ImmediateDraw = &mut uiworld.write::<ImmediateDraw>();
     let mut commands = uiworld.commands();
-    let state: &BulldozerState = &uiworld.read::<BulldozerState>();
 
     let cur_proj = map.project(unwrap_ret!(inp.unprojected), 0.0, ProjectFilter::ALL);
 
@@ -46,6 +85,9 @@ pub fn bulldozer(sim: &Simulation, uiworld: &UiWorld) {
         && !matches!(cur_proj.kind, ProjectKind::Ground)
     {
         uiworld.write::<SpecialBuildingResource>().last_obb = None;
+        uiworld
+            .write::<ImmediateSound>()
+            .play_at("bulldozer", cur_proj.pos, AudioKind::Effect);
 
         let mut potentially_empty = Vec::new();
         log::info!("bulldozer {:?}", cur_proj);
@@ -79,3 +121,90 @@ pub fn bulldozer(sim: &Simulation, uiworld: &UiWorld) {
         }
     }
 }
+
+/// Plays the bulldozer in the middle of the removed area
+pub fn bulldozer_sound(map: &Map, uiworld: &UiWorld, area: AABB) {
+    let center = area.center();
+    let z = map.environment.height(center).unwrap_or(0.0);
+    uiworld
+        .write::<ImmediateSound>()
+        .play_at("bulldozer", center.z(z), AudioKind::Effect);
+}
+
+/// Drag a rectangle to remove everything matching the filter inside it at once
+fn bulldozer_area(sim: &Simulation, uiworld: &UiWorld, state: &mut BulldozerState) {
+    let mut inp = uiworld.write::<InputMap>();
+    let map: &Map = &sim.map();
+    let draw: &mut ImmediateDraw = &mut uiworld.write::<ImmediateDraw>();
+
+    let unproj = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::Close) && !matches!(state.area_state, AreaState::Idle) {
+        inp.just_act.remove(&InputAction::Close);
+        state.area_state = AreaState::Idle;
+        return;
+    }
+
+    let Some(area) = state.selected_area(Some(unproj.xy())) else {
+        draw.circle(unproj.up(0.5), 2.0)
+            .color(simulation::colors().gui_danger);
+        if inp.just_act.contains(&InputAction::Select) {
+            state.area_state = AreaState::Selecting(unproj.xy());
+        }
+        return;
+    };
+
+    let sel = map.bulldoze_selection(area, state.filter);
+
+    let z = unproj.z + 0.5;
+    draw.polyline(
+        vec![
+            area.ll.z(z),
+            Vec2::new(area.ur.x, area.ll.y).z(z),
+            area.ur.z(z),
+            Vec2::new(area.ll.x, area.ur.y).z(z),
+        ],
+        1.0,
+        true,
+    )
+    .color(simulation::colors().gui_danger);
+
+    for &b in &sel.buildings {
+        if let Some(b) = map.buildings().get(b) {
+            draw.obb(b.obb, b.height + 0.1)
+                .color(simulation::colors().gui_danger.a(0.5));
+        }
+    }
+    for &b in &sel.orphaned {
+        if let Some(b) = map.buildings().get(b) {
+            draw.obb(b.obb, b.height + 0.1)
+                .color(simulation::colors().gui_disabled.a(0.5));
+        }
+    }
+    for &r in &sel.roads {
+        if let Some(r) = map.roads().get(r) {
+            draw.polyline(
+                r.points().iter().map(|p| p.up(0.3)).collect::<Vec<_>>(),
+                r.width,
+                false,
+            )
+            .color(simulation::colors().gui_danger.a(0.5));
+        }
+    }
+
+    if let AreaState::Selecting(_) = state.area_state {
+        if inp.act.contains(&InputAction::Select) {
+            return;
+        }
+        if sel.is_empty() {
+            state.area_state = AreaState::Idle;
+        } else if sel.orphaned.is_empty() {
+            uiworld.commands().map_bulldoze_area(area, state.filter);
+            bulldozer_sound(map, uiworld, area);
+            state.area_state = AreaState::Idle;
+        } else {
+            // confirmation happens in the toolbox
+            state.area_state = AreaState::Confirm(area);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::windows::transit::TransitEditor;
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use simulation::transportation::transit::{closest_stop, snap_bus_stop, Transit};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+/// Distance under which the cursor is over an existing stop
+const HOVER_DIST: f32 = 5.0;
+
+/// Bus stop tool
+/// Places bus stops on the side of roads. Clicking on a stop adds it to the line being edited,
+/// or removes it if no line is being edited.
+pub fn busstop(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::busstop");
+    let tool = *uiworld.read::<Tool>();
+
+    if !matches!(tool, Tool::BusStop) {
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let draw = &mut *uiworld.write::<ImmediateDraw>();
+    let transit = sim.read::<Transit>();
+    let editing = uiworld
+        .read::<TransitEditor>()
+        .editing
+        .and_then(|id| Some((id, transit.lines().get(id)?)));
+
+    for stop in transit.stops().values() {
+        draw.circle(stop.pos.up(0.5), 2.0)
+            .color(simulation::colors().gui_primary);
+    }
+    if let Some((_, line)) = editing {
+        let points: Vec<_> = line
+            .stops
+            .iter()
+            .filter_map(|&s| Some(transit.stops().get(s)?.pos.up(0.6)))
+            .collect();
+        if points.len() >= 2 {
+            draw.polyline(points, 2.0, false).color(line.color);
+        }
+    }
+
+    let unproj = unwrap_ret!(inp.unprojected);
+    let clicked = inp.just_act.contains(&InputAction::Select);
+
+    if let Some(hovered) = closest_stop(&transit, unproj, HOVER_DIST) {
+        let pos = transit.stops()[hovered].pos;
+        match editing {
+            Some((line_id, line)) => {
+                draw.circle(pos.up(0.6), 3.0)
+                    .color(simulation::colors().gui_primary);
+                if clicked {
+                    let mut stops = line.stops.clone();
+                    stops.push(hovered);
+                    uiworld.commands().push(WorldCommand::UpdateBusLine {
+                        line: line_id,
+                        stops,
+                        n_buses: line.n_buses,
+                    });
+                }
+            }
+            None => {
+                draw.circle(pos.up(0.6), 3.0)
+                    .color(simulation::colors().gui_danger);
+                if clicked {
+                    uiworld
+                        .commands()
+                        .push(WorldCommand::RemoveBusStop(hovered));
+                }
+            }
+        }
+        return;
+    }
+
+    let Some(stop) = snap_bus_stop(&sim.map(), unproj) else {
+        draw.circle(unproj.up(0.5), 2.0)
+            .color(simulation::colors().gui_disabled);
+        return;
+    };
+
+    draw.polyline(vec![stop.pos.up(0.5), stop.drive_pos.up(0.5)], 1.0, false)
+        .color(simulation::colors().gui_primary);
+    draw.circle(stop.pos.up(0.5), 2.0)
+        .color(simulation::colors().gui_primary);
+
+    if clicked {
+        uiworld.commands().push(WorldCommand::AddBusStop(unproj));
+    }
+}
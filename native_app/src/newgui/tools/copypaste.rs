@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+
+use common::saveload::{Encoder, JSON};
+use engine::AudioKind;
+use geom::{Radians, Vec2, Vec3, AABB};
+use serde::{Deserialize, Serialize};
+use simulation::map::{
+    IntersectionID, LanePattern, Map, MapProject, ProjectFilter, ProjectKind, Road, RoadSegmentKind,
+};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::roadbuild::draw_road_preview;
+use crate::newgui::{PotentialCommands, Tool};
+use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::uiworld::UiWorld;
+
+/// Rotation applied to the blueprint each time the rotate key is pressed
+const ROTATION_STEP: f32 = std::f32::consts::PI / 12.0;
+
+/// Distance under which a pasted intersection is merged with an existing one
+const SNAP_DIST: f32 = 5.0;
+
+/// An intersection of a blueprint
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlueprintNode {
+    /// Position relative to the center of the blueprint
+    pub pos: Vec2,
+    /// Height above the terrain, for bridges
+    pub height: f32,
+}
+
+/// A road of a blueprint, between two of its nodes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlueprintRoad {
+    pub src: usize,
+    pub dst: usize,
+    /// Elbow of curved roads, relative to the center of the blueprint
+    pub elbow: Option<Vec2>,
+    pub pattern: LanePattern,
+}
+
+/// Roads and intersections copied from the map, that can be pasted elsewhere.
+/// Saved to a small file so that it can be shared.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub nodes: Vec<BlueprintNode>,
+    pub roads: Vec<BlueprintRoad>,
+}
+
+/// Where the blueprint is pasted
+#[derive(Copy, Clone)]
+pub struct BlueprintPlacement {
+    pub pos: Vec2,
+    pub angle: f32,
+    pub mirror: bool,
+}
+
+impl BlueprintPlacement {
+    pub fn apply(&self, p: Vec2) -> Vec2 {
+        let p = if self.mirror { Vec2::new(-p.x, p.y) } else { p };
+        self.pos + p.rotated_by_angle(Radians(self.angle))
+    }
+}
+
+impl Blueprint {
+    pub const SAVE_NAME: &'static str = "blueprint";
+
+    /// Copies the roads that are entirely inside the area
+    pub fn from_area(map: &Map, area: AABB) -> Self {
+        let center = area.center();
+        let mut nodes = Vec::new();
+        let mut node_ids: BTreeMap<IntersectionID, usize> = BTreeMap::new();
+        let mut roads = Vec::new();
+
+        for r in map.roads().values() {
+            let (Some(src), Some(dst)) = (
+                map.intersections().get(r.src),
+                map.intersections().get(r.dst),
+            ) else {
+                continue;
+            };
+            if !area.contains(src.pos.xy()) || !area.contains(dst.pos.xy()) {
+                continue;
+            }
+
+            let mut node = |id: IntersectionID, pos: Vec3| {
+                *node_ids.entry(id).or_insert_with(|| {
+                    let ground = map.environment.height(pos.xy()).unwrap_or(pos.z);
+                    nodes.push(BlueprintNode {
+                        pos: pos.xy() - center,
+                        height: pos.z - ground,
+                    });
+                    nodes.len() - 1
+                })
+            };
+            let src_node = node(src.id, src.pos);
+            let dst_node = node(dst.id, dst.pos);
+
+            let elbow = match r.segment {
+                RoadSegmentKind::Straight => None,
+                RoadSegmentKind::Curved((from_derivative, to_derivative)) => {
+                    let from_elbow = src.pos.xy() + from_derivative * std::f32::consts::SQRT_2;
+                    let to_elbow = dst.pos.xy() - to_derivative * std::f32::consts::SQRT_2;
+                    Some((from_elbow + to_elbow) * 0.5 - center)
+                }
+            };
+
+            roads.push(BlueprintRoad {
+                src: src_node,
+                dst: dst_node,
+                elbow,
+                pattern: r.pattern(map.lanes()),
+            });
+        }
+
+        Self { nodes, roads }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roads.is_empty()
+    }
+
+    /// Loads the shared blueprint file.
+    /// Files edited by hand are rejected when a road doesn't link two of the nodes.
+    pub fn load() -> Result<Self, String> {
+        let blueprint = JSON::load::<Blueprint>(Self::SAVE_NAME).map_err(|e| e.to_string())?;
+        if blueprint.is_empty() {
+            return Err("it has no roads".to_string());
+        }
+        let n = blueprint.nodes.len();
+        if let Some(i) = blueprint
+            .roads
+            .iter()
+            .position(|r| r.src >= n || r.dst >= n || r.src == r.dst)
+        {
+            return Err(format!(
+                "road {} doesn't link two of its {} intersections",
+                i, n
+            ));
+        }
+        Ok(blueprint)
+    }
+
+    /// Projects of the nodes once placed, merged with the existing intersections nearby.
+    /// None if a node is outside of the map.
+    fn place(&self, map: &Map, placement: BlueprintPlacement) -> Option<Vec<MapProject>> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let pos = placement.apply(node.pos);
+                let ground = map.environment.height(pos)?;
+                let proj = map.project(pos.z(ground), SNAP_DIST, ProjectFilter::INTER);
+                if let ProjectKind::Inter(_) = proj.kind {
+                    return Some(proj);
+                }
+                Some(MapProject::ground(pos.z(ground + node.height)))
+            })
+            .collect()
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub enum CopyState {
+    /// Waiting for the player to start selecting an area
+    #[default]
+    Select,
+    /// Dragging a rectangle from this corner
+    Selecting(Vec2),
+    /// The blueprint follows the cursor
+    Paste,
+}
+
+#[derive(Default)]
+pub struct CopyPasteResource {
+    pub state: CopyState,
+    pub angle: f32,
+    pub mirror: bool,
+}
+
+/// Copy tool
+/// Copies the roads inside a rectangle into the blueprint, then pastes it under the cursor
+pub fn copypaste(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::copypaste");
+    let state = &mut *uiworld.write::<CopyPasteResource>();
+    let tool = *uiworld.read::<Tool>();
+
+    if !matches!(tool, Tool::Copy) {
+        if let CopyState::Selecting(_) = state.state {
+            state.state = CopyState::Select;
+        }
+        return;
+    }
+
+    let mut inp = uiworld.write::<InputMap>();
+    let mut blueprint = uiworld.write::<Blueprint>();
+    let immdraw = &mut *uiworld.write::<ImmediateDraw>();
+    let potential_command = &mut *uiworld.write::<PotentialCommands>();
+    let map = &*sim.map();
+
+    potential_command.0.clear();
+
+    let unproj = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::Close) && !matches!(state.state, CopyState::Select) {
+        inp.just_act.remove(&InputAction::Close);
+        state.state = CopyState::Select;
+        return;
+    }
+
+    match state.state {
+        CopyState::Select => {
+            immdraw
+                .circle(unproj.up(0.5), 2.0)
+                .color(simulation::colors().gui_primary);
+            if inp.just_act.contains(&InputAction::Select) {
+                state.state = CopyState::Selecting(unproj.xy());
+            }
+        }
+        CopyState::Selecting(start) => {
+            let area = AABB::new_ll_ur(start.min(unproj.xy()), start.max(unproj.xy()));
+            let z = unproj.z + 0.5;
+            immdraw
+                .polyline(
+                    vec![
+                        area.ll.z(z),
+                        Vec2::new(area.ur.x, area.ll.y).z(z),
+                        area.ur.z(z),
+                        Vec2::new(area.ll.x, area.ur.y).z(z),
+                    ],
+                    1.0,
+                    true,
+                )
+                .color(simulation::colors().gui_primary);
+
+            if !inp.act.contains(&InputAction::Select) {
+                let copied = Blueprint::from_area(map, area);
+                if copied.is_empty() {
+                    state.state = CopyState::Select;
+                } else {
+                    *blueprint = copied;
+                    state.angle = 0.0;
+                    state.mirror = false;
+                    state.state = CopyState::Paste;
+                }
+            }
+        }
+        CopyState::Paste => {
+            if inp.just_act.contains(&InputAction::BlueprintRotate) {
+                state.angle += ROTATION_STEP;
+            }
+            if inp.just_act.contains(&InputAction::BlueprintMirror) {
+                state.mirror = !state.mirror;
+            }
+
+            let placement = BlueprintPlacement {
+                pos: unproj.xy(),
+                angle: state.angle,
+                mirror: state.mirror,
+            };
+
+            let Some(projects) = blueprint.place(map, placement) else {
+                immdraw
+                    .circle(unproj.up(0.5), 10.0)
+                    .color(simulation::colors().gui_danger);
+                return;
+            };
+
+            let mut is_valid = true;
+            let mut ghost = Vec::with_capacity(blueprint.roads.len());
+            let mut links = Vec::with_capacity(blueprint.roads.len());
+
+            for road in &blueprint.roads {
+                let src = projects[road.src];
+                let dst = projects[road.dst];
+                if !src.kind.is_ground() && src.kind == dst.kind {
+                    // both ends were merged into the same intersection
+                    is_valid = false;
+                    continue;
+                }
+
+                let elbow = road.elbow.map(|e| placement.apply(e));
+                let segment = match elbow {
+                    Some(e) => RoadSegmentKind::from_elbow(src.pos.xy(), dst.pos.xy(), e),
+                    None => RoadSegmentKind::Straight,
+                };
+                let is_rail = road.pattern.lanes().any(|(kind, _, _)| kind.is_rail());
+
+                let (points, err) =
+                    Road::generate_points(src.pos, dst.pos, segment, is_rail, &map.environment);
+                let over_water = points.iter().any(|p| {
+                    map.environment
+                        .true_height(p.xy())
+                        .map_or(true, |h| h < 0.0)
+                });
+                if err.is_some() || over_water {
+                    is_valid = false;
+                }
+
+                ghost.push((points, road.pattern.width()));
+                links.push((road.src, road.dst, elbow, road.pattern.clone()));
+            }
+
+            let col = if is_valid {
+                simulation::colors().gui_primary
+            } else {
+                simulation::colors().gui_danger
+            };
+            for (points, width) in ghost {
+                draw_road_preview(map, immdraw, points, width, col);
+            }
+
+            if !is_valid {
+                return;
+            }
+
+            potential_command.set(WorldCommand::MapMakeMultipleConnections(projects, links));
+
+            if inp.just_act.contains(&InputAction::Select) {
+                uiworld
+                    .write::<ImmediateSound>()
+                    .play("road_lay", AudioKind::Ui);
+                if let Some(wc) = potential_command.0.drain(..).next() {
+                    uiworld.commands().push(wc);
+                }
+            }
+        }
+    }
+}
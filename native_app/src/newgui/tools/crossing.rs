@@ -0,0 +1,79 @@
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use simulation::map::{Map, ProjectFilter, ProjectKind};
+use simulation::Simulation;
+
+/// Distance under which the cursor is over an existing crossing
+const HOVER_DIST: f32 = 3.0;
+
+/// Crossing tool
+/// Paints marked pedestrian crossings in the middle of roads, clicking on one removes it
+pub fn crossing(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::crossing");
+    let tool = *uiworld.read::<Tool>();
+
+    if !matches!(tool, Tool::Crossing) {
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let map: &Map = &sim.map();
+    let draw = &mut *uiworld.write::<ImmediateDraw>();
+
+    let unproj = unwrap_ret!(inp.unprojected);
+    let proj = map.project(unproj, 0.0, ProjectFilter::ROAD);
+
+    let ProjectKind::Road(road_id) = proj.kind else {
+        draw.circle(unproj.up(0.5), 2.0)
+            .color(simulation::colors().gui_disabled);
+        return;
+    };
+    let road = &map.roads()[road_id];
+
+    let hovered = road
+        .crossings
+        .iter()
+        .position(|c| c.points.middle().is_close(proj.pos, HOVER_DIST));
+
+    if let Some(idx) = hovered {
+        let c = &road.crossings[idx];
+        draw.polyline(
+            c.points.iter().map(|p| p.up(0.5)).collect::<Vec<_>>(),
+            4.0,
+            false,
+        )
+        .color(simulation::colors().gui_danger);
+
+        if inp.just_act.contains(&InputAction::Select) {
+            uiworld.commands().map_remove_crossing(road_id, idx as u16);
+        }
+        return;
+    }
+
+    let Some((fw, bw)) = road.crossing_sidewalks() else {
+        // a crossing needs sidewalks on both sides
+        draw.circle(proj.pos.up(0.5), 2.0)
+            .color(simulation::colors().gui_danger);
+        return;
+    };
+
+    let lanes = map.lanes();
+    let (Some(fw), Some(bw)) = (lanes.get(fw), lanes.get(bw)) else {
+        return;
+    };
+    draw.polyline(
+        vec![
+            fw.points.project(proj.pos).up(0.5),
+            bw.points.project(proj.pos).up(0.5),
+        ],
+        4.0,
+        false,
+    )
+    .color(simulation::colors().gui_primary);
+
+    if inp.just_act.contains(&InputAction::Select) {
+        uiworld.commands().map_add_crossing(road_id, proj.pos);
+    }
+}
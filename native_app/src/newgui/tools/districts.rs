@@ -0,0 +1,126 @@
+use geom::{Polygon, Vec2, Vec3};
+use simulation::map::DistrictID;
+use simulation::world_command::MAX_DISTRICT_POINTS;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// Clicking this close to the first corner closes the district
+const CLOSE_DIST: f32 = 10.0;
+
+#[derive(Default)]
+pub struct DistrictsResource {
+    /// Corners of the district being drawn
+    pub points: Vec<Vec2>,
+    /// Name of the next district, a generated one if empty
+    pub name: String,
+    pub selected: Option<DistrictID>,
+    /// Name being edited for the selected district, loaded from the map when it is selected
+    pub selected_name: String,
+    /// The edited name is given to the selected district on the next update
+    pub rename: bool,
+    /// The selected district is removed on the next update
+    pub remove: bool,
+}
+
+/// Districts tool
+/// Allows to name areas of the map by clicking their corners, the district is closed by clicking
+/// its first corner again. Clicking an existing district selects it to rename or remove it.
+pub fn districts(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::districts");
+    let mut state = uiworld.write::<DistrictsResource>();
+    let tool = *uiworld.read::<Tool>();
+
+    if !matches!(tool, Tool::Districts) {
+        state.points.clear();
+        state.selected = None;
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+    let commands = &mut *uiworld.commands();
+
+    if let Some(id) = state.selected {
+        if !map.districts.contains(id) {
+            state.selected = None;
+        }
+    }
+    if let Some(id) = state.selected {
+        if std::mem::take(&mut state.rename) {
+            commands.map_set_district_name(id, state.selected_name.clone());
+        }
+        if std::mem::take(&mut state.remove) {
+            commands.map_remove_district(id);
+            state.selected = None;
+        }
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+    let z = mpos.z + 0.3;
+
+    for d in map.districts.iter() {
+        let col = if state.selected == Some(d.id) {
+            simulation::colors().gui_primary
+        } else {
+            simulation::colors().gui_disabled
+        };
+        draw.polygon(d.polygon.clone(), z).color(col.a(0.15));
+        draw.polyline(
+            d.polygon.iter().map(|p| p.z(z)).collect::<Vec<Vec3>>(),
+            2.0,
+            true,
+        )
+        .color(col);
+    }
+
+    let closing = state.points.len() >= 3 && state.points[0].is_close(mpos.xy(), CLOSE_DIST);
+    if let Some(&first) = state.points.first() {
+        let mut points: Vec<Vec3> = state.points.iter().map(|p| p.z(z)).collect();
+        points.push(if closing { first.z(z) } else { mpos.up(0.3) });
+        draw.polyline(points, 2.0, false)
+            .color(simulation::colors().gui_primary);
+        let col = if closing {
+            simulation::colors().gui_success
+        } else {
+            simulation::colors().gui_primary
+        };
+        draw.circle(first.z(z), CLOSE_DIST * 0.5).color(col.a(0.5));
+    }
+
+    // the last corner is removed before the secondary click places a new one
+    if inp.just_act.contains(&InputAction::SecondarySelect) {
+        state.points.pop();
+        return;
+    }
+    if !inp.just_act.contains(&InputAction::Select) {
+        return;
+    }
+
+    if closing {
+        let polygon = Polygon(std::mem::take(&mut state.points));
+        commands.map_add_district(polygon, std::mem::take(&mut state.name));
+        return;
+    }
+
+    if state.points.is_empty() {
+        if let Some(d) = map.districts.at(mpos.xy()) {
+            // clicking the selected district again starts drawing one inside it
+            if state.selected != Some(d.id) {
+                state.selected = Some(d.id);
+                state.selected_name = d.name.clone();
+                return;
+            }
+        }
+        state.selected = None;
+    }
+    // the simulation refuses bigger polygons, the district has to be closed
+    if state.points.len() >= MAX_DISTRICT_POINTS {
+        return;
+    }
+    state.points.push(mpos.xy());
+}
@@ -0,0 +1,170 @@
+use geom::{vec2, Vec2};
+use prototypes::{prototypes_iter, TreePrototype, TreePrototypeID};
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// What is being painted until the mouse is released
+enum Stroke {
+    None,
+    Plant(Vec<(Vec2, TreePrototypeID)>),
+    Erase(Vec<Vec2>),
+}
+
+pub struct ForestryResource {
+    pub radius: f32,
+    /// Trees planted per hectare
+    pub density: f32,
+    /// Species left out of the mix when planting
+    pub disabled: Vec<TreePrototypeID>,
+    stroke: Stroke,
+    last_stamp: Option<Vec2>,
+    seed: u64,
+}
+
+impl ForestryResource {
+    /// Species picked at random when planting
+    pub fn species(&self) -> Vec<TreePrototypeID> {
+        prototypes_iter::<TreePrototype>()
+            .map(|t| t.id)
+            .filter(|id| !self.disabled.contains(id))
+            .collect()
+    }
+}
+
+/// Forestry tool
+/// Allows to plant trees by dragging, and to erase them with the secondary select
+pub fn forestry(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::forestry");
+    let mut res = uiworld.write::<ForestryResource>();
+    let tool = *uiworld.read::<Tool>();
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+
+    if !matches!(tool, Tool::Forestry) {
+        res.stroke = Stroke::None;
+        res.last_stamp = None;
+        return;
+    }
+
+    if inp.act.contains(&InputAction::SizeUp) {
+        res.radius = (res.radius * 1.1).min(200.0);
+    }
+    if inp.act.contains(&InputAction::SizeDown) {
+        res.radius = (res.radius / 1.1).max(5.0);
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    let erasing = inp.act.contains(&InputAction::SecondarySelect);
+    let painting = erasing || inp.act.contains(&InputAction::Select);
+
+    let col = if erasing {
+        simulation::colors().gui_danger
+    } else {
+        simulation::colors().gui_primary
+    };
+    draw.circle(mpos.up(0.3), res.radius).color(col.a(0.2));
+
+    if !painting {
+        // the whole stroke is sent at once so that it is a single undoable edit
+        match std::mem::replace(&mut res.stroke, Stroke::None) {
+            Stroke::None => {}
+            Stroke::Plant(trees) => uiworld.commands().map_plant_trees(trees),
+            Stroke::Erase(stroke) => uiworld.commands().map_remove_trees(stroke, res.radius),
+        }
+        res.last_stamp = None;
+        return;
+    }
+
+    if res
+        .last_stamp
+        .map_or(false, |last| last.is_close(mpos.xy(), res.radius * 0.5))
+    {
+        draw_stroke(&res, &mut draw, mpos.z);
+        return;
+    }
+    res.last_stamp = Some(mpos.xy());
+
+    if erasing {
+        if !matches!(res.stroke, Stroke::Erase(_)) {
+            res.stroke = Stroke::Erase(Vec::new());
+        }
+        if let Stroke::Erase(ref mut stroke) = res.stroke {
+            stroke.push(mpos.xy());
+        }
+    } else {
+        if !matches!(res.stroke, Stroke::Plant(_)) {
+            res.stroke = Stroke::Plant(Vec::new());
+        }
+        let stamp = stamp_trees(&mut res, mpos.xy());
+        let map = sim.map();
+        if let Stroke::Plant(ref mut trees) = res.stroke {
+            trees.extend(
+                stamp
+                    .into_iter()
+                    .filter(|(pos, _)| map.can_plant_tree(*pos)),
+            );
+        }
+    }
+
+    draw_stroke(&res, &mut draw, mpos.z);
+}
+
+/// Random trees inside the brush, following the density and the species mix
+fn stamp_trees(res: &mut ForestryResource, center: Vec2) -> Vec<(Vec2, TreePrototypeID)> {
+    let species = res.species();
+    if species.is_empty() {
+        return vec![];
+    }
+    res.seed += 1;
+    let mut rng = common::rand::gen(res.seed);
+
+    let area = std::f32::consts::PI * res.radius * res.radius;
+    // only the part of the brush not covered by the previous stamp gets new trees
+    let n = (res.density * area / 10000.0 * 0.5).round() as usize;
+
+    (0..n)
+        .map(|_| {
+            let r = res.radius * rng.next_f32().sqrt();
+            let angle = std::f32::consts::TAU * rng.next_f32();
+            let pos = center + vec2(angle.cos(), angle.sin()) * r;
+            let i = (rng.next_f32() * species.len() as f32) as usize % species.len();
+            (pos, species[i])
+        })
+        .collect()
+}
+
+fn draw_stroke(res: &ForestryResource, draw: &mut ImmediateDraw, z: f32) {
+    match res.stroke {
+        Stroke::None => {}
+        Stroke::Plant(ref trees) => {
+            for (pos, _) in trees {
+                draw.circle(pos.z(z + 0.3), 1.5)
+                    .color(simulation::colors().gui_primary);
+            }
+        }
+        Stroke::Erase(ref stroke) => {
+            for pos in stroke {
+                draw.circle(pos.z(z + 0.3), res.radius)
+                    .color(simulation::colors().gui_danger.a(0.2));
+            }
+        }
+    }
+}
+
+impl Default for ForestryResource {
+    fn default() -> Self {
+        Self {
+            radius: 30.0,
+            density: 100.0,
+            disabled: Vec::new(),
+            stroke: Stroke::None,
+            last_stamp: None,
+            seed: 0,
+        }
+    }
+}
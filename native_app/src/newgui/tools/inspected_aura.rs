@@ -1,53 +1,48 @@
-use crate::newgui::selectable::select_radius;
-use crate::newgui::{InspectedBuilding, InspectedEntity};
+use crate::newgui::InspectedEntity;
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
-use geom::Color;
+use geom::Vec3;
+use simulation::map::Map;
 use simulation::transportation::Location;
-use simulation::{AnyEntity, Simulation};
+use simulation::{AnyEntity, HumanID, Simulation};
 
-/// InspectedAura shows the circle around the inspected entity
+/// InspectedAura shows where the inspected human is going,
+/// the inspected entity itself is outlined by the selection outlines
 pub fn inspected_aura(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::inspected_aura");
-    let inspected = uiworld.write::<InspectedEntity>();
-    let mut inspected_b = uiworld.write::<InspectedBuilding>();
+    let inspected = uiworld.read::<InspectedEntity>();
     let map = sim.map();
     let mut draw = uiworld.write::<ImmediateDraw>();
 
-    if let Some(sel) = inspected.e {
-        let mut pos = sim.pos_any(sel);
-
-        if let AnyEntity::HumanID(id) = sel {
-            let loc = &sim.world().get(id).unwrap().location;
-            match *loc {
-                Location::Outside => {}
-                Location::Vehicle(v) => pos = sim.pos(v),
-                Location::Building(b) => pos = map.buildings().get(b).map(|b| b.door_pos),
-            }
-        }
-
-        if let Some(pos) = pos {
-            let select_radius = select_radius(sel);
-
-            if select_radius > 0.0 {
-                draw.stroke_circle(pos.up(0.25), select_radius, (select_radius * 0.01).max(0.1))
-                    .color(Color::gray(0.7));
-            }
+    if let Some(AnyEntity::HumanID(id)) = inspected.e {
+        let path = itinerary_path(sim, &map, id);
+        if path.len() >= 2 {
+            draw.polyline(path, 1.0, false)
+                .color(simulation::colors().gui_primary);
         }
     }
+}
 
-    if let Some(sel) = inspected_b.e {
-        let Some(b) = map.buildings().get(sel) else {
-            inspected_b.e = None;
-            return;
-        };
-
-        // already shown by zonedit
-        if b.zone.is_some() {
-            return;
+/// Where the human is going, following their own itinerary or the one of the vehicle they are in
+fn itinerary_path(sim: &Simulation, map: &Map, id: HumanID) -> Vec<Vec3> {
+    let Some(h) = sim.get(id) else {
+        return vec![];
+    };
+    let (pos, it) = match h.location {
+        Location::Outside => (h.trans.pos, &h.it),
+        Location::Vehicle(v) => {
+            let Some(v) = sim.get(v) else {
+                return vec![];
+            };
+            (v.trans.pos, &v.it)
         }
+        Location::Train(_) | Location::Building(_) => return vec![],
+    };
 
-        draw.obb(b.obb, b.height + 0.01)
-            .color(simulation::colors().gui_primary);
+    let mut path = vec![pos];
+    path.extend(it.remaining_path(map));
+    for p in &mut path {
+        *p = p.up(0.3);
     }
+    path
 }
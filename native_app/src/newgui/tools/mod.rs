@@ -1,5 +1,10 @@
 pub mod addtrain;
 pub mod bulldozer;
+pub mod busstop;
+pub mod copypaste;
+pub mod crossing;
+pub mod districts;
+pub mod forestry;
 pub mod inspected_aura;
 pub mod lotbrush;
 pub mod roadbuild;
@@ -8,3 +13,4 @@ pub mod selectable;
 pub mod specialbuilding;
 pub mod terraforming;
 pub mod zoneedit;
+pub mod zoning;
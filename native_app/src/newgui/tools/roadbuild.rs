@@ -1,12 +1,15 @@
 use engine::AudioKind;
-use geom::{BoldLine, BoldSpline, Camera, Line, PolyLine, ShapeEnum, Spline};
+use geom::{BoldLine, BoldSpline, Camera, Color, Line, PolyLine, ShapeEnum, Spline};
 use geom::{PolyLine3, Vec2, Vec3};
+use prototypes::Money;
+use simulation::economy::Government;
 use simulation::map::{
-    LanePatternBuilder, Map, MapProject, ProjectFilter, ProjectKind, PylonPosition, RoadSegmentKind,
+    LanePatternBuilder, Map, MapProject, ProjectFilter, ProjectKind, PylonPosition, RoadID,
+    RoadSegmentKind, RoadStructure, BRIDGE_WATER_CLEARANCE, MAX_SLOPE,
 };
 use simulation::world_command::{WorldCommand, WorldCommands};
 use simulation::Simulation;
-use BuildState::{Connection, Hover, Interpolation, Start, StartInterp};
+use BuildState::{Connection, Hover, Interpolation, OffsetFrom, Start, StartInterp};
 use ProjectKind::{Building, Ground, Inter, Road};
 
 use crate::inputmap::{InputAction, InputMap};
@@ -22,8 +25,16 @@ pub enum BuildState {
     StartInterp(MapProject),
     Connection(MapProject, MapProject),
     Interpolation(Vec2, MapProject),
+    /// Building a road parallel to this road, at this offset to its right (left if negative)
+    OffsetFrom(RoadID, f32),
 }
 
+/// Gap between the sides of the source road and of the parallel road
+const PARALLEL_GAP: f32 = 2.0;
+
+/// Deepest a road can be dug under the terrain, roads below it are tunnels
+const MAX_DEPTH: f32 = 50.0;
+
 /// Road building tool
 /// Allows to build roads and intersections
 pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
@@ -38,6 +49,8 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
     let commands: &mut WorldCommands = &mut uiworld.commands();
     let cam = &*uiworld.read::<Camera>();
 
+    state.readout = None;
+
     if !tool.is_roadbuild() {
         state.build_state = Hover;
         state.height_offset = 0.0;
@@ -53,7 +66,9 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         (HeightReference::Start, Start(id) | StartInterp(id) | Connection(id, _)) => {
             id.pos.z + state.height_offset
         }
-        (HeightReference::Ground | HeightReference::Start, _) => unproj.z + state.height_offset,
+        (HeightReference::Ground | HeightReference::Start, _) => {
+            road_ground_z(map, unproj) + state.height_offset
+        }
         (HeightReference::MaxIncline | HeightReference::MaxDecline, _) => unproj.z, // work in progress
     };
 
@@ -82,6 +97,28 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         }
     };
 
+    // Lock the length of the segment being drawn.
+    // It is applied after the snapping so that it composes with the angle snap.
+    let anchor = state.segment_anchor();
+    let length_locked = state.length_lock.is_some() && anchor.is_some();
+    let mousepos = match (state.length_lock, anchor) {
+        (Some(length), Some(anchor)) => {
+            let dir = (mousepos.xy() - anchor).try_normalize().unwrap_or(Vec2::X);
+            let p = anchor + dir * length;
+            let z = match state.height_reference {
+                HeightReference::Start => mousepos.z,
+                _ => {
+                    map.environment
+                        .height(p)
+                        .map_or(unproj.z, |h| road_ground_z(map, p.z(h)))
+                        + state.height_offset
+                }
+            };
+            p.z(z)
+        }
+        _ => mousepos,
+    };
+
     let log_camheight = cam.eye().z.log10();
     /*
     let cutoff = 3.3;
@@ -115,6 +152,9 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
 
     // If a road was placed recently (as it is async with networking) prepare the next road
     for command in uiworld.received_commands().iter() {
+        if state.parallel {
+            break;
+        }
         if let WorldCommand::MapMakeConnection { to, .. } = command {
             if let proj @ MapProject { kind: Inter(_), .. } =
                 map.project(to.pos, 0.0, ProjectFilter::ALL)
@@ -140,7 +180,21 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
 
     if inp.just_act.contains(&InputAction::DownElevation) {
         state.height_offset -= 5.0;
-        state.height_offset = state.height_offset.max(0.0);
+        state.height_offset = state.height_offset.max(-MAX_DEPTH);
+    }
+
+    if state.parallel {
+        parallel_build(
+            state,
+            map,
+            unproj,
+            &inp,
+            immdraw,
+            immsound,
+            potential_command,
+            commands,
+        );
+        return;
     }
 
     let mut cur_proj = if !matches!(state.build_state, Connection(..)) {
@@ -177,7 +231,7 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         }
     }
 
-    if nosnapping {
+    if nosnapping || length_locked {
         cur_proj = MapProject {
             pos: mousepos,
             kind: Ground,
@@ -283,6 +337,8 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
     potential_command.0.clear();
 
     let mut points = None;
+    let mut slope = None;
+    let mut structure = None;
 
     if let Some((src, dst, inter, pat)) = build_args {
         potential_command.set(WorldCommand::MapMakeConnection {
@@ -304,12 +360,27 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
             is_rail,
             &map.environment,
         );
-        points = Some(p);
-        if err.is_some() {
+        let max_slope = max_gradient(&p);
+        if err.is_some() || max_slope > state.max_slope / 100.0 {
             is_valid = false;
         }
+        slope = Some(max_slope * 100.0);
+        structure = Some(RoadStructure::of(&p, &map.environment));
+        points = Some(p);
     }
 
+    state.readout = anchor.map(|anchor| SegmentReadout {
+        length: anchor.distance(cur_proj.pos.xy()),
+        angle: state.previous_segment_angle(map, cur_proj.pos.xy()),
+        locked: length_locked,
+        cost: potential_command
+            .0
+            .first()
+            .map(|command| Government::action_cost(command, sim)),
+        slope,
+        structure,
+    });
+
     state.update_drawing(
         map,
         immdraw,
@@ -373,13 +444,234 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
     }
 }
 
-#[derive(Default)]
 pub struct RoadBuildResource {
     pub build_state: BuildState,
     pub pattern_builder: LanePatternBuilder,
     pub snapping: Snapping,
     pub height_offset: f32,
     pub height_reference: HeightReference,
+    /// Whether clicking a road builds a road parallel to it
+    pub parallel: bool,
+    /// Distance between the center of the source road and the center of the parallel road
+    pub parallel_offset: f32,
+    /// Length the segment being drawn is locked to, as typed in the toolbox
+    pub length_input: String,
+    pub length_lock: Option<f32>,
+    /// Steepest gradient allowed, in percent
+    pub max_slope: f32,
+    /// Shown next to the cursor while drawing
+    pub readout: Option<SegmentReadout>,
+}
+
+impl Default for RoadBuildResource {
+    fn default() -> Self {
+        Self {
+            build_state: Default::default(),
+            pattern_builder: Default::default(),
+            snapping: Default::default(),
+            height_offset: 0.0,
+            height_reference: Default::default(),
+            parallel: false,
+            parallel_offset: 0.0,
+            length_input: String::new(),
+            length_lock: None,
+            max_slope: MAX_SLOPE * 100.0,
+            readout: None,
+        }
+    }
+}
+
+/// Measures of the segment being drawn
+#[derive(Copy, Clone)]
+pub struct SegmentReadout {
+    /// Meters
+    pub length: f32,
+    /// Degrees between the previous segment and this one, 0 going straight on
+    pub angle: Option<f32>,
+    pub locked: bool,
+    /// Price of the segment, including bridges and tunnels
+    pub cost: Option<Money>,
+    /// Steepest gradient of the segment, in percent
+    pub slope: Option<f32>,
+    /// Whether the segment is a bridge or a tunnel, bridges are made over water automatically
+    pub structure: Option<RoadStructure>,
+}
+
+/// Height of the terrain under the cursor for the roads, which cross water on bridges
+fn road_ground_z(map: &Map, pos: Vec3) -> f32 {
+    if map.water_at(pos.xy()).is_some() {
+        return BRIDGE_WATER_CLEARANCE;
+    }
+    pos.z
+}
+
+/// Steepest height difference per meter between two consecutive points
+fn max_gradient(points: &PolyLine3) -> f32 {
+    points
+        .as_slice()
+        .windows(2)
+        .map(|w| {
+            let d = w[0].xy().distance(w[1].xy());
+            if d < 0.1 {
+                return 0.0;
+            }
+            (w[1].z - w[0].z).abs() / d
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Parallel road building
+/// Clicking a road selects it, then a road following it is proposed on the side of the cursor.
+/// Its ends are connected to the roads and intersections they land on.
+#[allow(clippy::too_many_arguments)]
+fn parallel_build(
+    state: &mut RoadBuildResource,
+    map: &Map,
+    unproj: Vec3,
+    inp: &InputMap,
+    immdraw: &mut ImmediateDraw,
+    immsound: &mut ImmediateSound,
+    potential_command: &mut PotentialCommands,
+    commands: &mut WorldCommands,
+) {
+    potential_command.0.clear();
+    let patwidth = state.pattern_builder.width();
+
+    let OffsetFrom(src_road, _) = state.build_state else {
+        let Road(r_id) = map.project(unproj, 5.0, ProjectFilter::ROAD).kind else {
+            immdraw
+                .circle(unproj.up(0.4), patwidth * 0.5)
+                .color(simulation::colors().gui_disabled);
+            return;
+        };
+        let r = &map.roads()[r_id];
+        immdraw
+            .polyline(r.points.as_slice(), r.width, false)
+            .color(simulation::colors().gui_primary.a(0.5));
+
+        if inp.just_act.contains(&InputAction::Select) {
+            state.parallel_offset = (r.width + patwidth) * 0.5 + PARALLEL_GAP;
+            state.build_state = OffsetFrom(r_id, state.parallel_offset);
+        }
+        return;
+    };
+
+    let Some(r) = map.roads().get(src_road) else {
+        state.build_state = Hover;
+        return;
+    };
+
+    // build on the side of the cursor
+    state.parallel_offset = state.parallel_offset.max(1.0);
+    let (proj, _, dir) = r.points.project_segment_dir(unproj);
+    let offset = if dir.xy().perpendicular().dot(unproj.xy() - proj.xy()) >= 0.0 {
+        state.parallel_offset
+    } else {
+        -state.parallel_offset
+    };
+    state.build_state = OffsetFrom(src_road, offset);
+
+    let center = PolyLine::new(r.points.iter().map(|p| p.xy()).collect());
+    let Some(parallel) = center.offset(offset) else {
+        // the road curves too tightly for this offset
+        immdraw
+            .circle(unproj.up(0.4), patwidth * 0.5)
+            .color(simulation::colors().gui_danger);
+        return;
+    };
+
+    // connect the ends to what they land on, except the source road and its intersections
+    let connect = |pos: Vec3| {
+        let p = map.project(
+            pos,
+            patwidth * 0.5,
+            ProjectFilter::INTER | ProjectFilter::ROAD,
+        );
+        match p.kind {
+            Road(id) if id == src_road => MapProject::ground(pos),
+            Inter(id) if id == r.src || id == r.dst => MapProject::ground(pos),
+            Road(_) | Inter(_) => p,
+            _ => MapProject::ground(pos),
+        }
+    };
+    let from = connect(parallel.first().z(r.points.first().z));
+    let to = connect(parallel.last().z(r.points.last().z));
+
+    let inter = match r.segment {
+        RoadSegmentKind::Straight => None,
+        RoadSegmentKind::Curved((from_derivative, to_derivative)) => {
+            let from_tangent = Line::new(from.pos.xy(), from.pos.xy() + from_derivative);
+            let to_tangent = Line::new(to.pos.xy(), to.pos.xy() + to_derivative);
+            from_tangent.intersection_point(&to_tangent)
+        }
+    };
+    let segment = match inter {
+        Some(x) => RoadSegmentKind::from_elbow(from.pos.xy(), to.pos.xy(), x),
+        None => RoadSegmentKind::Straight,
+    };
+
+    let is_rail = state.pattern_builder.rail;
+    let (points, err) = simulation::map::Road::generate_points(
+        from.pos,
+        to.pos,
+        segment,
+        is_rail,
+        &map.environment,
+    );
+
+    let shape = ShapeEnum::BoldLine(BoldLine::new(parallel, patwidth * 0.5));
+    let z = (from.pos.z + to.pos.z) / 2.0;
+    let overlaps = map
+        .spatial_map()
+        .query(&shape, ProjectFilter::ROAD | ProjectFilter::INTER)
+        .any(|x| {
+            if x == from.kind || x == to.kind || x == Inter(r.src) || x == Inter(r.dst) {
+                return false;
+            }
+            if let Road(rid) = x {
+                let other = &map.roads()[rid];
+                if (other.points.first().z - z).abs() > 1.0
+                    || (other.points.last().z - z).abs() > 1.0
+                {
+                    return false;
+                }
+                if [r.src, r.dst]
+                    .iter()
+                    .any(|&i| other.src == i || other.dst == i)
+                {
+                    return false;
+                }
+            }
+            true
+        });
+
+    let is_valid = err.is_none() && !overlaps && compatible(map, from, to);
+
+    let col = if is_valid {
+        simulation::colors().gui_primary
+    } else {
+        simulation::colors().gui_danger
+    };
+    draw_road_preview(map, immdraw, points, patwidth, col);
+
+    if !is_valid {
+        return;
+    }
+
+    potential_command.set(WorldCommand::MapMakeConnection {
+        from,
+        to,
+        inter,
+        pat: state.pattern_builder.build(),
+    });
+
+    if inp.just_act.contains(&InputAction::Select) {
+        immsound.play("road_lay", AudioKind::Ui);
+        if let Some(wc) = potential_command.0.drain(..).next() {
+            commands.push(wc);
+        }
+        state.build_state = Hover;
+    }
 }
 
 #[derive(Default, Clone, Copy)]
@@ -485,7 +777,75 @@ fn check_intersect(
         })
 }
 
+/// Draws the road that would be built along the points, with its pylons
+pub fn draw_road_preview(
+    map: &Map,
+    immdraw: &mut ImmediateDraw,
+    p: PolyLine3,
+    patwidth: f32,
+    col: Color,
+) {
+    for PylonPosition {
+        terrain_height,
+        pos,
+        ..
+    } in simulation::map::Road::pylons_positions(&p, &map.environment)
+    {
+        immdraw
+            .circle(pos.xy().z(terrain_height + 0.1), patwidth * 0.5)
+            .color(col);
+    }
+
+    immdraw.circle(p.first(), patwidth * 0.5).color(col);
+    immdraw.circle(p.last(), patwidth * 0.5).color(col);
+    immdraw.polyline(p.into_vec(), patwidth, false).color(col);
+}
+
 impl RoadBuildResource {
+    /// Start of the segment being drawn, None when no segment is being drawn
+    fn segment_anchor(&self) -> Option<Vec2> {
+        match self.build_state {
+            Start(p) | StartInterp(p) => Some(p.pos.xy()),
+            Interpolation(interpoint, _) => Some(interpoint),
+            Hover | Connection(..) | OffsetFrom(..) => None,
+        }
+    }
+
+    /// Angle in degrees between the segment ending at `to` and the road it continues.
+    /// When starting from an intersection, the road going the most straight on is used.
+    fn previous_segment_angle(&self, map: &Map, to: Vec2) -> Option<f32> {
+        let (anchor, previous_dirs): (Vec2, Vec<Vec2>) = match self.build_state {
+            Start(p) | StartInterp(p) => {
+                let dirs = match p.kind {
+                    Inter(i) => {
+                        let inter = map.intersections().get(i)?;
+                        inter
+                            .roads
+                            .iter()
+                            .map(|&r| -map.roads()[r].dir_from(i))
+                            .collect()
+                    }
+                    Road(r) => {
+                        let (_, _, dir) = map.roads().get(r)?.points().project_segment_dir(p.pos);
+                        vec![dir.xy(), -dir.xy()]
+                    }
+                    _ => return None,
+                };
+                (p.pos.xy(), dirs)
+            }
+            Interpolation(interpoint, p) => {
+                (interpoint, vec![(interpoint - p.pos.xy()).try_normalize()?])
+            }
+            Hover | Connection(..) | OffsetFrom(..) => return None,
+        };
+
+        let dir = (to - anchor).try_normalize()?;
+        previous_dirs
+            .into_iter()
+            .map(|prev| prev.angle(dir).abs().to_degrees())
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
     pub fn update_drawing(
         &self,
         map: &Map,
@@ -541,25 +901,12 @@ impl RoadBuildResource {
             _ => unwrap_ret!(points),
         };
 
-        for PylonPosition {
-            terrain_height,
-            pos,
-            ..
-        } in simulation::map::Road::pylons_positions(&p, &map.environment)
-        {
-            immdraw
-                .circle(pos.xy().z(terrain_height + 0.1), patwidth * 0.5)
-                .color(col);
-        }
-
-        immdraw.circle(p.first(), patwidth * 0.5).color(col);
-        immdraw.circle(p.last(), patwidth * 0.5).color(col);
-        immdraw.polyline(p.into_vec(), patwidth, false).color(col);
+        draw_road_preview(map, immdraw, p, patwidth, col);
     }
 
     pub fn posible_interpolations(&self, map: &Map, mousepos: Vec3) -> Vec<Vec3> {
         let (start, end) = match self.build_state {
-            Hover | Interpolation(_, _) => {
+            Hover | Interpolation(_, _) | OffsetFrom(..) => {
                 return vec![];
             }
             Connection(src, dst) => (src, dst),
@@ -2,26 +2,156 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::Tool;
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
-use geom::Color;
-use simulation::map::{IntersectionID, LightPolicy, TurnPolicy};
+use geom::{Color, Vec2};
+use simulation::map::{
+    Intersection, IntersectionID, LanePatternBuilder, LightPolicy, LightTiming, Map, RoadID,
+    TurnID, TurnKind, TurnPolicy,
+};
 use simulation::map::{ProjectFilter, ProjectKind};
 use simulation::Simulation;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The turns from one road to another, or the crosswalk over a road, enabled or disabled together
+#[derive(Clone)]
+pub struct TurnToggle {
+    pub label: String,
+    pub turns: Vec<TurnID>,
+}
 
 #[derive(Clone)]
 pub struct IntersectionComponent {
     pub id: IntersectionID,
     pub turn_policy: TurnPolicy,
     pub light_policy: LightPolicy,
+    pub light_timing: Option<LightTiming>,
+    pub disabled_turns: BTreeSet<TurnID>,
+    /// Number of traffic light phases, to edit the timings
+    pub n_phases: usize,
+    pub turn_toggles: Vec<TurnToggle>,
+    /// Close enough to the border of the map to be a connection to the outside
+    pub at_border: bool,
+    /// Trucks trading with the outside come and go through this intersection
+    pub road_connection: bool,
+}
+
+impl IntersectionComponent {
+    fn new(inter: &Intersection) -> Self {
+        Self {
+            id: inter.id,
+            turn_policy: inter.turn_policy,
+            light_policy: inter.light_policy,
+            light_timing: inter.light_timing.clone(),
+            disabled_turns: inter.disabled_turns.clone(),
+            n_phases: 0,
+            turn_toggles: Vec::new(),
+            at_border: false,
+            road_connection: false,
+        }
+    }
+}
+
+/// Compass direction of the vector, north being +y
+fn compass(dir: Vec2) -> &'static str {
+    const NAMES: [&str; 8] = ["E", "NE", "N", "NW", "W", "SW", "S", "SE"];
+    let deg = dir.y.atan2(dir.x).to_degrees();
+    NAMES[((deg + 360.0 + 22.5) / 45.0) as usize % 8]
+}
+
+/// Groups the vehicle turns the intersection can generate by pair of roads and the
+/// crosswalks by road, including the ones that are disabled
+fn turn_toggles(map: &Map, inter: &Intersection) -> Vec<TurnToggle> {
+    let mut groups: BTreeMap<(usize, usize), Vec<TurnID>> = BTreeMap::new();
+    let mut crosswalks: BTreeMap<usize, Vec<TurnID>> = BTreeMap::new();
+    for (id, kind) in inter
+        .turn_policy
+        .generate_turns(inter, map.lanes(), map.roads())
+    {
+        if !matches!(
+            kind,
+            TurnKind::Driving | TurnKind::Rail | TurnKind::Crosswalk
+        ) {
+            continue;
+        }
+        let (Some(src), Some(dst)) = (map.lanes().get(id.src), map.lanes().get(id.dst)) else {
+            continue;
+        };
+        let from = inter.roads.iter().position(|&r| r == src.parent);
+        let to = inter.roads.iter().position(|&r| r == dst.parent);
+        if let (Some(from), Some(to)) = (from, to) {
+            if kind.is_crosswalk() {
+                crosswalks.entry(from).or_default().push(id);
+                continue;
+            }
+            groups.entry((from, to)).or_default().push(id);
+        }
+    }
+
+    // Marked crossing on each approach
+    let crosswalk_toggles = crosswalks.into_iter().filter_map(|(road, turns)| {
+        let dir = map.roads().get(inter.roads[road])?.dir_from(inter.id);
+        Some(TurnToggle {
+            label: format!("{} crossing", compass(dir)),
+            turns,
+        })
+    });
+
+    groups
+        .into_iter()
+        .filter_map(|((from, to), turns)| {
+            let from_dir = map.roads().get(inter.roads[from])?.dir_from(inter.id);
+            let to_dir = map.roads().get(inter.roads[to])?.dir_from(inter.id);
+            let angle = (-from_dir).angle(to_dir);
+            let kind = if from == to {
+                "U-turn"
+            } else if angle.abs() < 0.5 {
+                "straight"
+            } else if angle > 0.0 {
+                "left"
+            } else {
+                "right"
+            };
+            Some(TurnToggle {
+                label: format!("{} → {} ({})", compass(from_dir), compass(to_dir), kind),
+                turns,
+            })
+        })
+        .chain(crosswalk_toggles)
+        .collect()
+}
+
+/// A road selected in the road editor, identified by its intersections
+/// so that it stays selected when its lanes are rebuilt
+#[derive(Clone, Copy)]
+pub struct SelectedRoad {
+    pub src: IntersectionID,
+    pub dst: IntersectionID,
+    pub one_way: bool,
 }
 
 #[derive(Default)]
 pub struct RoadEditorResource {
     pub inspect: Option<IntersectionComponent>,
     pub dirty: bool,
+    pub road: Option<SelectedRoad>,
+    /// The direction of the selected road is flipped on the next update
+    pub flip: bool,
+    /// Clicking or dragging over roads changes their lanes to the upgrade pattern
+    pub upgrade: bool,
+    pub upgrade_pattern: LanePatternBuilder,
+    /// Roads already upgraded during the current drag
+    pub upgraded: Vec<RoadID>,
+    /// Name of the selected road, as shown on the map
+    pub road_label: String,
+    /// Name being edited for the selected road, loaded from the map when the road is selected
+    pub road_name: Option<String>,
+    /// The edited name is given to the selected road on the next update
+    pub rename: bool,
+    /// The selected intersection becomes a road connection, or stops being one, on the next update
+    pub toggle_connection: bool,
 }
 
 /// RoadEditor tool
-/// Allows to edit intersections properties like turns and signals
+/// Allows to edit intersections properties like turns and signals, and to flip one-way roads
 pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::roadeditor");
     let tool = uiworld.read::<Tool>();
@@ -33,11 +163,57 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
 
     if !matches!(*tool, Tool::RoadEditor) {
         state.inspect = None;
+        state.road = None;
+        return;
+    }
+
+    if state.upgrade {
+        let proj_pos = unwrap_ret!(inp.unprojected);
+        let cur_proj = map.project(proj_pos, 0.0, ProjectFilter::ROAD);
+
+        if !inp.act.contains(&InputAction::Select) {
+            state.upgraded.clear();
+        }
+
+        let ProjectKind::Road(id) = cur_proj.kind else {
+            imm_draw
+                .circle(proj_pos.up(0.5), 2.0)
+                .color(simulation::colors().gui_disabled);
+            return;
+        };
+        let road = unwrap_ret!(map.roads().get(id));
+        let pattern = state.upgrade_pattern.build();
+        let is_same = road.pattern(map.lanes()) == pattern;
+
+        let col = if is_same || state.upgraded.contains(&id) {
+            simulation::colors().gui_disabled
+        } else {
+            simulation::colors().gui_primary
+        };
+        imm_draw
+            .polyline(
+                road.points.iter().map(|p| p.up(0.1)).collect::<Vec<_>>(),
+                road.width,
+                false,
+            )
+            .color(col.a(0.5));
+
+        if inp.act.contains(&InputAction::Select) && !is_same && !state.upgraded.contains(&id) {
+            state.upgraded.push(id);
+            commands.map_set_road_pattern(id, pattern);
+        }
         return;
     }
 
     if let Some(id) = state.inspect.as_ref().map(|x| x.id) {
         if let Some(inter) = map.intersections().get(id) {
+            if let Some(ref mut interc) = state.inspect {
+                interc.n_phases = LightPolicy::n_phases(inter, map.roads());
+                interc.turn_toggles = turn_toggles(&map, inter);
+                interc.at_border = map.is_near_border(inter.pos.xy());
+                interc.road_connection = map.is_road_connection(id);
+            }
+
             let lanes = map.lanes();
             for turn in inter.turns() {
                 let r = common::rand::randhash(turn.id);
@@ -59,6 +235,12 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
         }
     }
 
+    for (_, pos) in map.road_connections() {
+        imm_draw
+            .circle(pos.up(0.3), 12.0)
+            .color(simulation::colors().gui_primary.a(0.5));
+    }
+
     let mut proj_pos = unwrap_ret!(inp.unprojected);
     let cur_proj = map.project(proj_pos, 10.0, ProjectFilter::INTER);
 
@@ -77,24 +259,73 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
         if let ProjectKind::Inter(id) = cur_proj.kind {
             proj_col = simulation::colors().gui_success;
             proj_pos = cur_proj.pos;
-            let inter = &map.intersections()[id];
-            state.inspect = Some(IntersectionComponent {
-                id,
-                turn_policy: inter.turn_policy,
-                light_policy: inter.light_policy,
-            });
+            state.inspect = Some(IntersectionComponent::new(&map.intersections()[id]));
+            state.road = None;
             state.dirty = false;
+        } else if let ProjectKind::Road(id) = map.project(proj_pos, 0.0, ProjectFilter::ROAD).kind {
+            let road = &map.roads()[id];
+            state.road = Some(SelectedRoad {
+                src: road.src,
+                dst: road.dst,
+                one_way: false,
+            });
+            state.road_name = None;
+            state.inspect = None;
         }
     }
 
     imm_draw.circle(proj_pos.up(0.5), 10.0).color(proj_col);
 
+    let flip = std::mem::take(&mut state.flip) || inp.just_act.contains(&InputAction::FlipRoad);
+    let selected = state
+        .road
+        .and_then(|r| map.find_road(r.src, r.dst))
+        .and_then(|id| map.roads().get(id));
+    match selected {
+        Some(road) => {
+            let one_way = road.pattern(map.lanes()).is_one_way();
+            if let Some(ref mut sel) = state.road {
+                sel.one_way = one_way;
+            }
+            imm_draw
+                .polyline(
+                    road.points.iter().map(|p| p.up(0.1)).collect::<Vec<_>>(),
+                    road.width,
+                    false,
+                )
+                .color(simulation::colors().gui_success.a(0.5));
+
+            if one_way && flip {
+                commands.map_flip_road(road.id);
+            }
+
+            state.road_label = map.road_name(road.id).into_owned();
+            if state.road_name.is_none() {
+                state.road_name = Some(map.custom_road_name(road.id).unwrap_or("").to_string());
+            }
+            if std::mem::take(&mut state.rename) {
+                if let Some(ref name) = state.road_name {
+                    commands.map_set_road_name(road.id, name.clone());
+                }
+            }
+        }
+        None => state.road = None,
+    }
+
+    if std::mem::take(&mut state.toggle_connection) {
+        if let Some(interc) = &state.inspect {
+            commands.map_set_road_connection(interc.id, !interc.road_connection);
+        }
+    }
+
     if state.dirty {
         if let Some(interc) = &state.inspect {
             commands.map_update_intersection_policy(
                 interc.id,
                 interc.turn_policy,
                 interc.light_policy,
+                interc.light_timing.clone(),
+                interc.disabled_turns.clone(),
             );
         }
         state.dirty = false;
@@ -2,9 +2,13 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::{InspectedBuilding, InspectedEntity, Tool};
 use crate::uiworld::UiWorld;
 use geom::Vec2;
-use simulation::map::ProjectFilter;
+use simulation::map::{BuildingID, ProjectFilter};
+use simulation::transportation::TransportGrid;
 use simulation::{AnyEntity, Simulation};
 
+/// Largest select radius of the humans and vehicles
+const MAX_MOVER_SELECT_RADIUS: f32 = 5.0;
+
 pub fn select_radius(id: AnyEntity) -> f32 {
     match id {
         AnyEntity::VehicleID(_) => 5.0,
@@ -13,6 +17,7 @@ pub fn select_radius(id: AnyEntity) -> f32 {
         AnyEntity::FreightStationID(_) => 0.0,
         AnyEntity::CompanyID(_) => 0.0,
         AnyEntity::HumanID(_) => 3.0,
+        AnyEntity::WarehouseID(_) => 0.0,
     }
 }
 
@@ -30,21 +35,9 @@ pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
     {
         let unproj = unwrap_ret!(inp.unprojected);
 
-        let w = sim.world();
-
-        inspected.dist2 = f32::INFINITY;
-        inspected.e = None;
-
-        w.query_selectable_pos()
-            .for_each(|(id, pos): (AnyEntity, Vec2)| {
-                let dist2 = (pos - unproj.xy()).mag2();
-                let rad = select_radius(id);
-                if dist2 >= rad * rad || dist2 >= inspected.dist2 {
-                    return;
-                }
-                inspected.dist2 = dist2;
-                inspected.e = Some(id);
-            });
+        let under = entity_under(sim, unproj.xy());
+        inspected.e = under.map(|(id, _)| id);
+        inspected.dist2 = under.map_or(f32::INFINITY, |(_, dist2)| dist2);
     }
 
     if inp.just_act.contains(&InputAction::Select)
@@ -54,11 +47,7 @@ pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
         inspected_b.e = None;
         if inspected.e.is_none() {
             let unproj = unwrap_ret!(inp.unprojected);
-            let map = sim.map();
-            inspected_b.e = map
-                .spatial_map()
-                .query(unproj.xy(), ProjectFilter::BUILDING)
-                .find_map(|x| x.as_building());
+            inspected_b.e = building_under(sim, unproj.xy());
         }
     }
     inspected.dontclear = false;
@@ -80,4 +69,52 @@ pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
         inspected.e = None;
         inspected_b.e = None;
     }
+
+    inspected.hovered = None;
+    inspected_b.hovered = None;
+    if !matches!(*tool, Tool::Hand) {
+        return;
+    }
+    let unproj = unwrap_ret!(inp.unprojected);
+    inspected.hovered = entity_under(sim, unproj.xy()).map(|(id, _)| id);
+    if inspected.hovered.is_none() {
+        inspected_b.hovered = building_under(sim, unproj.xy());
+    }
+}
+
+/// The closest entity whose select radius contains the position, and the squared distance to it.
+/// The humans and vehicles on the move are looked up in the transport grid, the trains are few.
+fn entity_under(sim: &Simulation, pos: Vec2) -> Option<(AnyEntity, f32)> {
+    let mut closest: Option<(AnyEntity, f32)> = None;
+    let mut consider = |id: AnyEntity, p: Vec2| {
+        let dist2 = (p - pos).mag2();
+        let rad = select_radius(id);
+        if dist2 >= rad * rad || closest.is_some_and(|(_, d)| dist2 >= d) {
+            return;
+        }
+        closest = Some((id, dist2));
+    };
+
+    let grid = sim.read::<TransportGrid>();
+    for (h, p) in grid.query_around(pos, MAX_MOVER_SELECT_RADIUS) {
+        if let Some(id) = grid.get(h).and_then(|(_, state)| state.owner) {
+            consider(id, p);
+        }
+    }
+
+    let w = sim.world();
+    for (id, train) in w.trains.iter() {
+        consider(AnyEntity::TrainID(id), train.trans.pos.xy());
+    }
+    for (id, wagon) in w.wagons.iter() {
+        consider(AnyEntity::WagonID(id), wagon.trans.pos.xy());
+    }
+    closest
+}
+
+fn building_under(sim: &Simulation, pos: Vec2) -> Option<BuildingID> {
+    sim.map()
+        .spatial_map()
+        .query(pos, ProjectFilter::BUILDING)
+        .find_map(|x| x.as_building())
 }
@@ -184,15 +184,30 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
         return;
     }
 
-    draw(obb, false);
-
     let cmds: Vec<WorldCommand> = make(&SpecialBuildArgs {
         obb,
         connected_road: rid,
     });
+
+    let site_error = cmds.iter().find_map(|cmd| match cmd {
+        WorldCommand::MapBuildSpecialBuilding { pos, kind, .. } => {
+            map.check_building_site(pos, *kind).err()
+        }
+        _ => None,
+    });
+    if let Some(err) = site_error {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(Cow::Borrowed(err.reason()));
+        draw(obb, true);
+        return;
+    }
+
+    draw(obb, false);
+
     if inp.act.contains(&InputAction::Select) {
         commands.extend(cmds);
-        sound.play("road_lay", AudioKind::Ui);
+        let center = obb.center();
+        let z = map.environment.height(center).unwrap_or(0.0);
+        sound.play_at("road_lay", center.z(z), AudioKind::Effect);
         state.last_obb = Some(obb);
     } else if let Some(last) = cmds.last() {
         uiworld.write::<PotentialCommands>().set(last.clone());
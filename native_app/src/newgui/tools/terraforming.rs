@@ -1,6 +1,6 @@
 use geom::{Vec2, Vec3, OBB};
 use simulation::map::TerraformKind;
-use simulation::world_command::WorldCommand;
+use simulation::world_command::{WorldCommand, WorldCommands, MAX_BRUSH_RADIUS};
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
@@ -12,7 +12,11 @@ pub struct TerraformingResource {
     pub kind: TerraformKind,
     pub radius: f32,
     pub amount: f32,
+    /// Depth of the water painted with [`TerraformKind::Water`]
+    pub water_depth: f32,
     level: Option<f32>,
+    /// A brush stroke is in progress, it ends when the buttons are released
+    stroking: bool,
     slope_start: Option<Vec3>,
     slope_end: Option<Vec3>,
 }
@@ -31,11 +35,18 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
     if !matches!(tool, Tool::Terraforming) {
         res.slope_start = None;
         res.slope_end = None;
+        end_stroke(&mut res, commands);
         return;
     }
 
+    let pressed =
+        inp.act.contains(&InputAction::Select) || inp.act.contains(&InputAction::SecondarySelect);
+    if !pressed {
+        end_stroke(&mut res, commands);
+    }
+
     if inp.act.contains(&InputAction::SizeUp) {
-        res.radius *= 1.1;
+        res.radius = (res.radius * 1.1).min(MAX_BRUSH_RADIUS);
     }
     if inp.act.contains(&InputAction::SizeDown) {
         res.radius /= 1.1;
@@ -44,6 +55,7 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
     let mpos = unwrap_ret!(inp.unprojected);
 
     let mut amount_multiplier = 1.0;
+    let mut water_level = None;
 
     // handle actions
     match res.kind {
@@ -85,20 +97,29 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
             }
         }
         TerraformKind::Erode => {}
+        TerraformKind::Water => {
+            // paint water, or paint the land back with the secondary button
+            water_level = Some(if inp.act.contains(&InputAction::SecondarySelect) {
+                0.0
+            } else {
+                -res.water_depth
+            });
+        }
     }
 
-    if inp.act.contains(&InputAction::Select) || inp.act.contains(&InputAction::SecondarySelect) {
+    if pressed {
         if res.kind == TerraformKind::Level && res.level.is_none() {
             return;
         }
         if res.kind == TerraformKind::Slope && res.slope_end.is_none() {
             return;
         }
+        res.stroking = true;
         commands.push(WorldCommand::Terraform {
             center: mpos.xy(),
             radius: res.radius,
             amount: res.amount * amount_multiplier,
-            level: res.level.unwrap_or(0.0),
+            level: water_level.or(res.level).unwrap_or(0.0),
             kind: res.kind,
             slope: res.slope_start.zip(res.slope_end),
         })
@@ -133,6 +154,15 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
             .color(simulation::colors().gui_primary.a(0.2));
         }
         TerraformKind::Erode => {}
+        TerraformKind::Water => {}
+    }
+}
+
+/// The edits of a stroke are undone together
+fn end_stroke(res: &mut TerraformingResource, commands: &mut WorldCommands) {
+    if res.stroking {
+        res.stroking = false;
+        commands.map_end_terraform();
     }
 }
 
@@ -142,7 +172,9 @@ impl Default for TerraformingResource {
             kind: TerraformKind::Elevation,
             radius: 200.0,
             amount: 300.0,
+            water_depth: 10.0,
             level: None,
+            stroking: false,
             slope_start: None,
             slope_end: None,
         }
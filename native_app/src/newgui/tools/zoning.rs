@@ -0,0 +1,93 @@
+use geom::{Color, Vec2, AABB};
+use simulation::map::ZoningKind;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// Zones are shown around the cursor up to this distance
+const OVERLAY_RADIUS: f32 = 500.0;
+
+pub struct ZoningResource {
+    /// None erases the zoning
+    pub kind: Option<ZoningKind>,
+    pub radius: f32,
+    last_paint: Option<Vec2>,
+}
+
+pub fn zoning_color(kind: ZoningKind) -> Color {
+    match kind {
+        ZoningKind::Residential => Color::new(0.2, 0.8, 0.2, 1.0),
+        ZoningKind::Commercial => Color::new(0.2, 0.4, 0.9, 1.0),
+        ZoningKind::Industrial => Color::new(0.9, 0.7, 0.1, 1.0),
+    }
+}
+
+/// Zoning tool
+/// Allows to paint where buildings should grow on their own
+pub fn zoning(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::zoning");
+    let mut res = uiworld.write::<ZoningResource>();
+    let tool = *uiworld.read::<Tool>();
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+
+    if !matches!(tool, Tool::Zoning) {
+        res.last_paint = None;
+        return;
+    }
+
+    if inp.act.contains(&InputAction::SizeUp) {
+        res.radius = (res.radius * 1.1).min(500.0);
+    }
+    if inp.act.contains(&InputAction::SizeDown) {
+        res.radius = (res.radius / 1.1).max(10.0);
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    for (aabb, kind) in map
+        .zones
+        .query(AABB::centered(mpos.xy(), Vec2::splat(OVERLAY_RADIUS * 2.0)))
+    {
+        draw.aabb(aabb, mpos.z + 0.2)
+            .color(zoning_color(kind).a(0.3));
+    }
+
+    let col = match res.kind {
+        Some(kind) => zoning_color(kind),
+        None => simulation::colors().gui_danger,
+    };
+    draw.circle(mpos.up(0.3), res.radius).color(col.a(0.2));
+
+    if !inp.act.contains(&InputAction::Select) {
+        res.last_paint = None;
+        return;
+    }
+
+    // avoid sending the same stroke every frame while the mouse is still
+    if res
+        .last_paint
+        .map_or(false, |last| last.is_close(mpos.xy(), res.radius * 0.25))
+    {
+        return;
+    }
+    res.last_paint = Some(mpos.xy());
+
+    uiworld
+        .commands()
+        .map_paint_zone(mpos.xy(), res.radius, res.kind);
+}
+
+impl Default for ZoningResource {
+    fn default() -> Self {
+        Self {
+            kind: Some(ZoningKind::Residential),
+            radius: 40.0,
+            last_paint: None,
+        }
+    }
+}
@@ -1,20 +1,61 @@
+use common::rand::{randhash, randu};
 use common::FastMap;
-use engine::{FrameContext, GfxContext, InstancedMeshBuilder, MeshInstance, SpriteBatchBuilder};
-use geom::{LinearColor, Vec3, V3};
-use prototypes::{RenderAsset, RollingStockID, RollingStockPrototype};
-use simulation::transportation::{Location, VehicleKind};
+use engine::image::{DynamicImage, Rgba, RgbaImage};
+use engine::{
+    FrameContext, GfxContext, InstancedMeshBuilder, Mesh, MeshInstance, SpriteBatchBuilder,
+    Texture, TextureBuilder,
+};
+use geom::{vec2, Color, LinearColor, Vec3, AABB3, V3};
+use prototypes::{
+    RenderAsset, RoadVehicleID, RoadVehiclePrototype, RollingStockID, RollingStockPrototype,
+};
+use rayon::iter::{ParallelBridge, ParallelExtend, ParallelIterator};
+use simulation::transportation::Location;
 use simulation::Simulation;
 
+/// Sizes of the boxes tested against the frustum, bigger than the models not to pop at the edges
+const PEDESTRIAN_SIZE: f32 = 3.0;
+const VEHICLE_SIZE: f32 = 12.0;
+const WAGON_SIZE: f32 = 30.0;
+
+/// Colors of the clothes of the pedestrians, light so that the texture still shows
+const PEDESTRIAN_TINTS: [u64; 6] = [
+    0xff_ff_ff, 0xf2_d4_c2, 0xc9_dc_f0, 0xd9_ee_cf, 0xee_e2_b8, 0xe0_cc_e8,
+];
+
+/// Colors of the (shirt, trousers) of the variant textures of the pedestrians,
+/// the model's own texture is the first variant
+const PEDESTRIAN_VARIANTS: [(u64, u64); 4] = [
+    (0x3b_5b_92, 0x2f_2f_38),
+    (0xb8_3a_3a, 0x4a_4a_55),
+    (0x4f_8a_4b, 0x6b_55_3f),
+    (0xe3_c5_6b, 0x30_3d_5c),
+];
+
+/// Most fake pedestrians the stress console command can draw
+pub const MAX_STRESS_CROWD: u32 = 1_000_000;
+
+/// Fake pedestrians drawn around a position to measure the rendering of crowds,
+/// set by the stress console command. They are not part of the simulation.
+#[derive(Default)]
+pub struct CrowdStressTest {
+    pub n: u32,
+    pub center: Vec3,
+}
+
 /// Render all entities using instanced rendering for performance
 pub struct InstancedRender {
     pub path_not_found: SpriteBatchBuilder<true>,
     pub rolling_stock: FastMap<RollingStockID, InstancedMeshBuilder<true>>,
-    pub cars: InstancedMeshBuilder<true>,
+    pub road_vehicles: FastMap<RoadVehicleID, InstancedMeshBuilder<true>>,
     // pub locomotives: InstancedMeshBuilder<true>,
     // pub wagons_passenger: InstancedMeshBuilder<true>,
     // pub wagons_freight: InstancedMeshBuilder<true>,
-    pub trucks: InstancedMeshBuilder<true>,
-    pub pedestrians: InstancedMeshBuilder<true>,
+    /// One builder per variant texture of the pedestrians
+    pub pedestrians: Vec<InstancedMeshBuilder<true>>,
+    /// The pedestrians extracted in parallel with their variant, before they are split between
+    /// the builders
+    pedestrian_staging: Vec<(usize, MeshInstance)>,
 }
 
 impl InstancedRender {
@@ -40,7 +81,27 @@ impl InstancedRender {
                 rolling_stock.insert(id, InstancedMeshBuilder::new_ref(&mesh));
             });
 
-        let car = gfx.mesh("simple_car.glb".as_ref()).unwrap();
+        let mut road_vehicles = FastMap::default();
+        RoadVehiclePrototype::iter()
+            .map(|vehicle_proto| (&vehicle_proto.asset, vehicle_proto.id))
+            .filter_map(|(asset, id)| {
+                let RenderAsset::Mesh { path } = asset else {
+                    None?
+                };
+                match gfx.mesh(path) {
+                    Err(e) => {
+                        log::error!("Failed to load mesh {}: {:?}", asset, e);
+                        None
+                    }
+                    Ok(m) => Some((id, m)),
+                }
+            })
+            .for_each(|(id, mesh)| {
+                road_vehicles.insert(id, InstancedMeshBuilder::new_ref(&mesh));
+            });
+
+        let pedestrian = gfx.mesh("pedestrian.glb".as_ref()).unwrap();
+
         InstancedRender {
             path_not_found: SpriteBatchBuilder::new(
                 &gfx.texture("assets/sprites/path_not_found.png", "path_not_found"),
@@ -48,35 +109,48 @@ impl InstancedRender {
             ),
 
             rolling_stock,
+            road_vehicles,
 
-            cars: InstancedMeshBuilder::new_ref(&car),
             // locomotives: InstancedMeshBuilder::new_ref(&gfx.mesh("train.glb".as_ref()).unwrap()),
             // wagons_freight: InstancedMeshBuilder::new_ref(&gfx.mesh("wagon_freight.glb".as_ref()).unwrap()),
             // wagons_passenger: InstancedMeshBuilder::new_ref(&gfx.mesh("wagon.glb".as_ref()).unwrap()),
-            trucks: InstancedMeshBuilder::new_ref(&gfx.mesh("truck.glb".as_ref()).unwrap()),
-            pedestrians: InstancedMeshBuilder::new_ref(
-                &gfx.mesh("pedestrian.glb".as_ref()).unwrap(),
-            ),
+            pedestrians: pedestrian_variants(gfx, pedestrian)
+                .iter()
+                .map(InstancedMeshBuilder::new_ref)
+                .collect(),
+            pedestrian_staging: Vec::new(),
         }
     }
 
-    pub fn render(&mut self, sim: &Simulation, fctx: &mut FrameContext<'_>) {
+    /// Only the entities seen by the camera or casting a shadow on screen are uploaded,
+    /// the buffers of the builders are kept between frames
+    pub fn render(
+        &mut self,
+        sim: &Simulation,
+        stress: &CrowdStressTest,
+        fctx: &mut FrameContext<'_>,
+    ) {
         profiling::scope!("entity_render::render");
-        self.cars.instances.clear();
-        self.trucks.instances.clear();
-        self.pedestrians.instances.clear();
+        let gfx = &*fctx.gfx;
+        self.road_vehicles.iter_mut().for_each(|(_, m)| {
+            m.instances.clear();
+        });
+        self.pedestrians.iter_mut().for_each(|m| {
+            m.instances.clear();
+        });
         for v in sim.world().vehicles.values() {
             let trans = &v.trans;
+            if !visible(gfx, trans.pos, VEHICLE_SIZE) {
+                continue;
+            }
             let instance = MeshInstance {
                 pos: trans.pos,
                 dir: trans.dir,
                 tint: v.vehicle.tint.into(),
             };
 
-            match v.vehicle.kind {
-                VehicleKind::Car => self.cars.instances.push(instance),
-                VehicleKind::Truck => self.trucks.instances.push(instance),
-                _ => {}
+            if let Some(mesh) = self.road_vehicles.get_mut(&v.vehicle.proto().id) {
+                mesh.instances.push(instance);
             }
         }
 
@@ -85,6 +159,9 @@ impl InstancedRender {
         });
         for wagon in sim.world().wagons.values() {
             let trans = &wagon.trans;
+            if !visible(gfx, trans.pos, WAGON_SIZE) {
+                continue;
+            }
             let instance = MeshInstance {
                 pos: trans.pos,
                 dir: trans.dir,
@@ -96,14 +173,32 @@ impl InstancedRender {
             }
         }
 
-        for p in sim.world().humans.values() {
-            if matches!(p.location, Location::Outside) {
-                self.pedestrians.instances.push(MeshInstance {
-                    pos: p.trans.pos.up(0.5 + 0.4 * p.pedestrian.walk_anim.cos()),
-                    dir: p.trans.dir.xy().z0(),
-                    tint: LinearColor::WHITE,
+        self.pedestrian_staging.clear();
+        {
+            profiling::scope!("extract pedestrians");
+            let n_variants = self.pedestrians.len();
+            let instances = sim
+                .world()
+                .humans
+                .iter()
+                .par_bridge()
+                .filter(|(_, p)| {
+                    matches!(p.location, Location::Outside)
+                        && visible(gfx, p.trans.pos, PEDESTRIAN_SIZE)
+                })
+                .map(|(id, p)| {
+                    let instance = MeshInstance {
+                        pos: p.trans.pos.up(walk_bob(p.pedestrian.walk_anim)),
+                        dir: p.trans.dir.xy().z0(),
+                        tint: pedestrian_tint(randhash(id)),
+                    };
+                    (pick(randhash((id, 1)), n_variants), instance)
                 });
-            }
+            self.pedestrian_staging.par_extend(instances);
+        }
+        self.push_stress_crowd(stress, gfx, gfx.render_params.value().time_always);
+        for &(variant, instance) in &self.pedestrian_staging {
+            self.pedestrians[variant].instances.push(instance);
         }
 
         self.path_not_found.clear();
@@ -130,15 +225,17 @@ impl InstancedRender {
         if let Some(x) = self.path_not_found.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
-        if let Some(x) = self.cars.build(fctx.gfx) {
-            fctx.objs.push(Box::new(x));
-        }
-        if let Some(x) = self.trucks.build(fctx.gfx) {
-            fctx.objs.push(Box::new(x));
-        }
-        if let Some(x) = self.pedestrians.build(fctx.gfx) {
-            fctx.objs.push(Box::new(x));
-        }
+        self.pedestrians.iter_mut().for_each(|imb| {
+            if let Some(x) = imb.build(fctx.gfx) {
+                fctx.objs.push(Box::new(x));
+            }
+        });
+
+        self.road_vehicles.iter_mut().for_each(|(_, imb)| {
+            if let Some(x) = imb.build(fctx.gfx) {
+                fctx.objs.push(Box::new(x));
+            }
+        });
 
         self.rolling_stock.iter_mut().for_each(|(_, imb)| {
             if let Some(x) = imb.build(fctx.gfx) {
@@ -146,4 +243,107 @@ impl InstancedRender {
             }
         });
     }
+
+    /// The fake pedestrians walk in circles on a grid, each with its own phase so that they don't
+    /// move in lockstep
+    fn push_stress_crowd(&mut self, stress: &CrowdStressTest, gfx: &GfxContext, time: f32) {
+        if stress.n == 0 {
+            return;
+        }
+        profiling::scope!("stress crowd");
+        let side = (stress.n as f32).sqrt().ceil() as usize;
+        let spacing = 2.0;
+        let offset = side as f32 * spacing * 0.5;
+        let n_variants = self.pedestrians.len();
+        let instances = (0..stress.n).filter_map(|i| {
+            let phase = randu(i) * std::f32::consts::TAU;
+            let angle = phase + time * 0.5;
+            let dir = vec2(angle.cos(), angle.sin());
+            let cell = vec2(
+                (i as usize % side) as f32 * spacing - offset,
+                (i as usize / side) as f32 * spacing - offset,
+            );
+            let pos = stress.center + (cell + dir.perpendicular() * 0.5).z0();
+            if !visible(gfx, pos, PEDESTRIAN_SIZE) {
+                return None;
+            }
+            let instance = MeshInstance {
+                pos: pos.up(walk_bob(phase + time * 7.0)),
+                dir: dir.z0(),
+                tint: pedestrian_tint(randhash(i)),
+            };
+            Some((pick(randhash((i, 1)), n_variants), instance))
+        });
+        self.pedestrian_staging.extend(instances);
+    }
+}
+
+/// The instances are drawn in all the passes, the shadow cascades are tested too so that what is
+/// out of the camera still casts its shadow on screen
+fn visible(gfx: &GfxContext, pos: Vec3, size: f32) -> bool {
+    gfx.in_any_pass(&AABB3::centered(pos, Vec3::splat(size)))
+}
+
+/// Height of the model above the ground from the phase of the walk
+pub(crate) fn walk_bob(phase: f32) -> f32 {
+    0.5 + 0.4 * phase.cos()
+}
+
+/// The random value in 0..1 picks an index below n
+fn pick(r: f32, n: usize) -> usize {
+    ((r * n as f32) as usize).min(n - 1)
+}
+
+/// The random value in 0..1 picks the tint
+fn pedestrian_tint(r: f32) -> LinearColor {
+    Color::from_hex(PEDESTRIAN_TINTS[pick(r, PEDESTRIAN_TINTS.len())]).into()
+}
+
+/// The pedestrian mesh, then a copy of it for each variant texture
+fn pedestrian_variants(gfx: &mut GfxContext, mesh: Mesh) -> Vec<Mesh> {
+    let mut variants = vec![mesh.clone()];
+    for &(shirt, trousers) in &PEDESTRIAN_VARIANTS {
+        let albedo = clothes_texture(gfx, shirt, trousers);
+        let mut variant = mesh.clone();
+        let mut materials = FastMap::default();
+        for lod in variant.lods.iter_mut() {
+            for (mat, _) in &mut lod.primitives {
+                *mat = *materials.entry(*mat).or_insert_with(|| {
+                    let m = gfx.material(*mat).with_albedo(gfx, &albedo);
+                    gfx.register_material(m)
+                });
+            }
+        }
+        variants.push(variant);
+    }
+    variants
+}
+
+/// The top of the texture colors the shirt and the bottom the trousers
+fn clothes_texture(gfx: &GfxContext, shirt: u64, trousers: u64) -> Texture {
+    let rgba = |hex: u64| {
+        let c = Color::from_hex(hex);
+        Rgba([
+            (c.r * 255.0).round() as u8,
+            (c.g * 255.0).round() as u8,
+            (c.b * 255.0).round() as u8,
+            255,
+        ])
+    };
+    let img = RgbaImage::from_fn(
+        4,
+        8,
+        |_, y| {
+            if y < 4 {
+                rgba(shirt)
+            } else {
+                rgba(trousers)
+            }
+        },
+    );
+    TextureBuilder::from_img(DynamicImage::ImageRgba8(img))
+        .with_srgb(true)
+        .with_label("pedestrian variant")
+        .with_sampler(Texture::nearest_sampler())
+        .build(&gfx.device, &gfx.queue)
 }
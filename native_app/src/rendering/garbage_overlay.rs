@@ -0,0 +1,37 @@
+use engine::Tesselator;
+use geom::Color;
+use simulation::map_dynamic::{Garbage, GARBAGE_THRESHOLD};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Whether the garbage overlay is shown, toggled from the toolbox
+#[derive(Default)]
+pub struct GarbageOverlay {
+    pub enabled: bool,
+}
+
+/// From green when clean to red when the garbage reaches the threshold
+fn level_color(level: f32) -> Color {
+    let t = (level / GARBAGE_THRESHOLD).min(1.0);
+    Color::hsv(120.0 * (1.0 - t), 0.9, 0.9, 0.6)
+}
+
+/// Draws the footprint of the buildings colored by their garbage level
+pub fn draw_garbage_overlay(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld) {
+    if !uiw.read::<GarbageOverlay>().enabled {
+        return;
+    }
+    profiling::scope!("garbage_overlay");
+
+    let map = sim.map();
+    let garbage = sim.read::<Garbage>();
+
+    for (building, level) in garbage.iter() {
+        let Some(b) = map.get(building) else {
+            continue;
+        };
+        tess.set_color(level_color(level));
+        tess.draw_filled_polygon(&b.obb.corners, b.height + 1.0);
+    }
+}
@@ -11,12 +11,18 @@ use std::path::{Path, PathBuf};
 #[derive(Default)]
 pub struct ImmediateSound {
     pub orders: Vec<(&'static str, AudioKind)>,
+    /// Sounds fading with their distance to the camera
+    pub positioned: Vec<(&'static str, Vec3, AudioKind)>,
 }
 
 impl ImmediateSound {
     pub fn play(&mut self, sound: &'static str, kind: AudioKind) {
         self.orders.push((sound, kind))
     }
+
+    pub fn play_at(&mut self, sound: &'static str, pos: Vec3, kind: AudioKind) {
+        self.positioned.push((sound, pos, kind))
+    }
 }
 
 #[allow(clippy::upper_case_acronyms)]
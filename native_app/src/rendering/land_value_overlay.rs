@@ -0,0 +1,43 @@
+use engine::Tesselator;
+use geom::{vec2, Color};
+use simulation::map_dynamic::{LandValue, BASE_LAND_VALUE};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Whether the land value overlay is shown, toggled from the toolbox
+#[derive(Default)]
+pub struct LandValueOverlay {
+    pub enabled: bool,
+}
+
+/// From red on worthless land to green on land worth twice the base value
+fn value_color(value: f32) -> Color {
+    let t = (value / (2.0 * BASE_LAND_VALUE)).clamp(0.0, 1.0);
+    Color::hsv(120.0 * t, 0.9, 0.9, 0.5)
+}
+
+/// Draws the land value grid as a heatmap over the ground
+pub fn draw_land_value_overlay(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld) {
+    if !uiw.read::<LandValueOverlay>().enabled {
+        return;
+    }
+    profiling::scope!("land_value_overlay");
+
+    let map = sim.map();
+    let land = sim.read::<LandValue>();
+
+    for (aabb, value) in land.iter() {
+        let z = map.environment.height(aabb.center()).unwrap_or(0.0) + 1.0;
+        tess.set_color(value_color(value));
+        tess.draw_filled_polygon(
+            &[
+                aabb.ll,
+                vec2(aabb.ur.x, aabb.ll.y),
+                aabb.ur,
+                vec2(aabb.ll.x, aabb.ur.y),
+            ],
+            z,
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Names written over the map: the road names follow their road when zoomed in, the district
+//! names are centered over their area when zoomed out.
+//! The names are laid out glyph by glyph in screen space, each glyph placed along the
+//! projected polyline of its road and turned to follow it.
+
+use geom::{Camera, Vec2, Vec3, AABB};
+use simulation::map::{DistrictID, Map, MapSubscriber, ProjectFilter, ProjectKind, UpdateType};
+
+/// Road names fade out between these camera distances
+pub const ROAD_LABELS_FADE: (f32, f32) = (250.0, 450.0);
+/// District names fade in between these camera distances
+pub const DISTRICT_LABELS_FADE: (f32, f32) = (300.0, 600.0);
+
+pub const ROAD_FONT_SIZE: f32 = 14.0;
+pub const DISTRICT_FONT_SIZE: f32 = 26.0;
+
+/// Width of a glyph of the monospace font, relative to the font size
+pub const GLYPH_ADVANCE: f32 = 0.6;
+
+/// Road names shown at most, the first ones found around the camera
+pub const MAX_ROAD_LABELS: usize = 150;
+/// Minimum distance in pixels between the centers of two road names
+pub const ROAD_LABELS_SPACING: f32 = 120.0;
+
+/// Opacity of the road names at this camera distance
+pub fn road_labels_alpha(dist: f32) -> f32 {
+    let (start, end) = ROAD_LABELS_FADE;
+    1.0 - ((dist - start) / (end - start)).clamp(0.0, 1.0)
+}
+
+/// Opacity of the district names at this camera distance
+pub fn district_labels_alpha(dist: f32) -> f32 {
+    let (start, end) = DISTRICT_LABELS_FADE;
+    ((dist - start) / (end - start)).clamp(0.0, 1.0)
+}
+
+/// Centers of the glyphs of a text of `n_glyphs` glyphs written along the polyline, centered on it,
+/// with the direction of the polyline under each glyph.
+/// The polyline is followed from its leftmost end so that the text reads from left to right.
+/// None if the polyline is too short to hold the text.
+pub fn glyphs_along(points: &[Vec2], n_glyphs: usize, advance: f32) -> Option<Vec<(Vec2, Vec2)>> {
+    let (&first, &last) = (points.first()?, points.last()?);
+    let length: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    let text_length = n_glyphs as f32 * advance;
+    if n_glyphs == 0 || text_length > length * 0.9 {
+        return None;
+    }
+
+    let mut points = points.to_vec();
+    if last.x < first.x {
+        points.reverse();
+    }
+
+    let mut glyphs = Vec::with_capacity(n_glyphs);
+    let mut next = (length - text_length + advance) * 0.5;
+    let mut walked = 0.0;
+    let mut dir = Vec2::X;
+    for w in points.windows(2) {
+        let seg = w[0].distance(w[1]);
+        if seg > 0.0 {
+            dir = (w[1] - w[0]) / seg;
+        }
+        while glyphs.len() < n_glyphs && next <= walked + seg {
+            let t = if seg > 0.0 {
+                (next - walked) / seg
+            } else {
+                0.0
+            };
+            glyphs.push((w[0] + (w[1] - w[0]) * t, dir));
+            next += advance;
+        }
+        walked += seg;
+    }
+    // rounding errors at the very end of the polyline
+    while glyphs.len() < n_glyphs {
+        glyphs.push((*points.last()?, dir));
+    }
+    Some(glyphs)
+}
+
+/// A road name laid out on the screen
+pub struct RoadLabel {
+    pub name: String,
+    /// Center and direction of each glyph of the name
+    pub glyphs: Vec<(Vec2, Vec2)>,
+}
+
+/// A district name centered on its screen position
+pub struct DistrictLabel {
+    pub id: DistrictID,
+    pub name: String,
+    pub pos: Option<Vec2>,
+}
+
+/// The labels laid out for the last camera, they are only laid out again when the camera moves or
+/// the roads or districts change
+#[derive(Default)]
+pub struct MapLabels {
+    sub: Option<MapSubscriber>,
+    camera: Option<[f32; 8]>,
+    pub roads: Vec<RoadLabel>,
+    pub districts: Vec<DistrictLabel>,
+}
+
+impl MapLabels {
+    pub fn update(&mut self, map: &Map, camera: &Camera) {
+        profiling::scope!("map_labels::update");
+        let key = [
+            camera.pos.x,
+            camera.pos.y,
+            camera.pos.z,
+            camera.yaw.0,
+            camera.pitch.0,
+            camera.dist,
+            camera.viewport_w,
+            camera.viewport_h,
+        ];
+        let sub = self
+            .sub
+            .get_or_insert_with(|| map.subscribe(UpdateType::Road));
+        let mut changed = sub.take_cleared();
+        changed |= sub.take_updated_chunks().count() > 0;
+        changed |= self.camera != Some(key);
+        changed |= self.districts.len() != map.districts.len()
+            || self
+                .districts
+                .iter()
+                .zip(map.districts.iter())
+                .any(|(label, d)| label.id != d.id || label.name != d.name);
+        if !changed {
+            return;
+        }
+        self.camera = Some(key);
+        self.layout_roads(map, camera);
+        self.layout_districts(map, camera);
+    }
+
+    fn layout_roads(&mut self, map: &Map, camera: &Camera) {
+        self.roads.clear();
+        if road_labels_alpha(camera.dist) <= 0.0 {
+            return;
+        }
+
+        let area = AABB::centered(camera.pos.xy(), Vec2::splat(camera.dist * 4.0));
+        let mut placed: Vec<Vec2> = Vec::new();
+        for obj in map.spatial_map().query(area, ProjectFilter::ROAD) {
+            if self.roads.len() >= MAX_ROAD_LABELS {
+                break;
+            }
+            let ProjectKind::Road(id) = obj else {
+                continue;
+            };
+            let Some(road) = map.roads().get(id) else {
+                continue;
+            };
+            if !road.points.iter().all(|&p| in_front(camera, p)) {
+                continue;
+            }
+            let screen: Vec<Vec2> = road
+                .points
+                .iter()
+                .map(|&p| camera.project(p.up(0.5)).0)
+                .collect();
+
+            let name = map.road_name(id);
+            let n_glyphs = name.chars().count();
+            let Some(glyphs) = glyphs_along(&screen, n_glyphs, ROAD_FONT_SIZE * GLYPH_ADVANCE)
+            else {
+                continue;
+            };
+            let middle = glyphs[n_glyphs / 2].0;
+            if !on_screen(camera, middle)
+                || placed
+                    .iter()
+                    .any(|p| p.is_close(middle, ROAD_LABELS_SPACING))
+            {
+                continue;
+            }
+            placed.push(middle);
+            self.roads.push(RoadLabel {
+                name: name.into_owned(),
+                glyphs,
+            });
+        }
+    }
+
+    fn layout_districts(&mut self, map: &Map, camera: &Camera) {
+        self.districts = map
+            .districts
+            .iter()
+            .map(|d| {
+                let pos = d.label_pos();
+                let pos = pos.z(map.environment.height(pos).unwrap_or(0.0));
+                DistrictLabel {
+                    id: d.id,
+                    name: d.name.clone(),
+                    pos: Some(pos)
+                        .filter(|&p| in_front(camera, p))
+                        .map(|p| camera.project(p).0)
+                        .filter(|&p| on_screen(camera, p)),
+                }
+            })
+            .collect();
+    }
+}
+
+fn in_front(camera: &Camera, p: Vec3) -> bool {
+    (p - camera.eye()).dot(-camera.dir()) > 1.0
+}
+
+fn on_screen(camera: &Camera, p: Vec2) -> bool {
+    p.x >= 0.0 && p.y >= 0.0 && p.x <= camera.viewport_w && p.y <= camera.viewport_h
+}
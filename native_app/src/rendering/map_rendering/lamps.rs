@@ -3,9 +3,27 @@ use engine::{Context, LampLights, LightChunkID};
 use flat_spatial::AABBGrid;
 use geom::{Vec3, AABB3, V3};
 use simulation::map::{
-    Map, MapSubscriber, ProjectFilter, ProjectKind, SubscriberChunkID, UpdateType,
+    LaneKind, Map, MapSubscriber, ProjectFilter, ProjectKind, Road, SubscriberChunkID, UpdateType,
 };
 
+/// Height of the light above the foot of a street lamp
+const LAMP_LIGHT_HEIGHT: f32 = 8.0;
+
+/// Foot and facing direction of the street lamps along the sidewalk of the road, none on rail roads
+pub fn lamp_posts(road: &Road) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+    let has_lamps = !road.lanes_iter().all(|(_, kind)| kind.is_rail());
+    let offset = if road.has_sidewalks() {
+        LaneKind::Walking.width()
+    } else {
+        0.0
+    };
+    let w = road.width * 0.5 - offset;
+    road.interfaced_points()
+        .equipoints_dir(45.0, true)
+        .filter(move |_| has_lamps)
+        .map(move |(point, dir)| (point - dir.perp_up() * w, dir.perp_up()))
+}
+
 pub struct LampsRender {
     lamp_memory: FastMap<LightChunkID, Vec<Vec3>>,
     lamp_road_memory: FastMap<SubscriberChunkID, Vec<(LightChunkID, Vec3)>>,
@@ -66,12 +84,8 @@ impl LampsRender {
             let inters = map.intersections();
 
             for road in chunk_roads {
-                let road = &roads[road];
-                if road.lanes_iter().all(|(_, kind)| kind.is_rail()) {
-                    continue;
-                }
-                for (point, _) in road.points().equipoints_dir(45.0, true) {
-                    add_light(point + 8.0 * V3::Z);
+                for (foot, _) in lamp_posts(&roads[road]) {
+                    add_light(foot + LAMP_LIGHT_HEIGHT * V3::Z);
                 }
             }
             for i in chunk_inter {
@@ -84,7 +98,7 @@ impl LampsRender {
                     continue;
                 }
 
-                add_light(i.pos + 8.0 * V3::Z);
+                add_light(i.pos + LAMP_LIGHT_HEIGHT * V3::Z);
             }
 
             for (cell_idx, cell) in by_chunk.storage().cells.iter() {
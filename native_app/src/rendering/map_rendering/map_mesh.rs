@@ -1,20 +1,30 @@
 use crate::rendering::MapRenderOptions;
-use common::FastMap;
+use common::{FastMap, FastSet};
 use engine::earcut::earcut;
 use engine::MeshBuilder;
 use engine::{
-    Drawable, FrameContext, GfxContext, InstancedMeshBuilder, Material, Mesh, MeshInstance,
-    MeshVertex, MetallicRoughness, SpriteBatch, SpriteBatchBuilder, Tesselator,
+    Culled, Drawable, FrameContext, GfxContext, InstancedMesh, InstancedMeshBuilder, Material,
+    Mesh, MeshInstance, MeshLoadState, MeshVertex, MetallicRoughness, SpriteBatch,
+    SpriteBatchBuilder, Tesselator,
+};
+use geom::{
+    minmax, vec2, vec3, Color, Intersect3, LinearColor, PolyLine3, Polygon, Radians, Vec2, Vec3,
+    AABB3,
+};
+use prototypes::{
+    AirportPrototype, FreightStationPrototype, GoodsCompanyPrototype, HarborPrototype,
+    HotelPrototype, LeisurePrototype, PassengerStationPrototype, RenderAsset, SchoolPrototype,
+    WarehousePrototype,
 };
-use geom::{minmax, vec2, vec3, Color, LinearColor, PolyLine3, Polygon, Radians, Vec2, Vec3};
-use prototypes::{FreightStationPrototype, GoodsCompanyPrototype, RenderAsset};
 use simulation::map::{
     Building, BuildingKind, CanonicalPosition, Environment, Intersection, LaneKind, Lanes, LotKind,
-    Map, MapSubscriber, ProjectFilter, ProjectKind, PylonPosition, Road, Roads, SubscriberChunkID,
-    Turn, TurnKind, UpdateType, CROSSWALK_WIDTH, ROAD_Z_OFFSET,
+    Map, MapSubscriber, ProjectFilter, ProjectKind, PylonPosition, Road, RoadStructure, Roads,
+    SubscriberChunkID, Turn, TurnKind, UpdateType, CROSSWALK_WIDTH, ROAD_Z_OFFSET,
 };
 use simulation::Simulation;
+use std::collections::BTreeSet;
 use std::ops::{Mul, Neg};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// This is the main struct that handles the map rendering.
@@ -25,6 +35,15 @@ pub struct MapMeshHandler {
     cache: FastMap<SubscriberChunkID, CachedObj>,
     road_sub: MapSubscriber,
     building_sub: MapSubscriber,
+    pub stats: MapMeshStats,
+}
+
+/// How many chunks of the map mesh were in the camera and rebuilt on the last frame
+#[derive(Default, Clone, Copy)]
+pub struct MapMeshStats {
+    pub chunks_drawn: usize,
+    pub chunks_total: usize,
+    pub rebuilds: usize,
 }
 
 #[derive(Default)]
@@ -33,6 +52,10 @@ struct CachedObj {
     build: Vec<Arc<dyn Drawable>>,
     lots: Option<Mesh>,
     arrows: Option<SpriteBatch>,
+    /// Bounds of the roads and lots, the arrows are on the roads
+    road_aabb: Option<AABB3>,
+    /// Bounds of the buildings and their zones
+    build_aabb: Option<AABB3>,
 }
 
 impl CachedObj {
@@ -42,11 +65,41 @@ impl CachedObj {
             && self.arrows.is_none()
             && self.build.is_empty()
     }
+
+    fn aabb(&self) -> Option<AABB3> {
+        match (self.road_aabb, self.build_aabb) {
+            (Some(a), Some(b)) => Some(a.union(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+fn union_aabbs(aabbs: impl IntoIterator<Item = AABB3>) -> Option<AABB3> {
+    aabbs.into_iter().reduce(AABB3::union)
+}
+
+fn draw_culled(ctx: &mut FrameContext<'_>, aabb: Option<AABB3>, v: impl Drawable + 'static) {
+    match aabb {
+        Some(aabb) => ctx.draw(Culled { aabb, inner: v }),
+        None => ctx.draw(v),
+    }
 }
 
+fn mesh_aabb(mesh: &Mesh) -> Option<AABB3> {
+    mesh.lods.first().map(|lod| lod.aabb3)
+}
+
+/// Height of the gray boxes drawn while the mesh of a building loads
+const PLACEHOLDER_HEIGHT: f32 = 6.0;
+
 struct MapBuilders {
     buildsprites: FastMap<BuildingKind, SpriteBatchBuilder<false>>,
     buildmeshes: FastMap<BuildingKind, InstancedMeshBuilder<false>>,
+    /// The kinds whose mesh is loading or failed to load, drawn as boxes
+    placeholders: FastSet<BuildingKind>,
+    pending_meshes: Vec<(BuildingKind, PathBuf)>,
+    /// Bounds of the sprites of the buildings of the chunk, the sprite batches don't have any
+    buildsprites_aabb: Option<AABB3>,
     houses_mesh: MeshBuilder<false>,
     zonemeshes: FastMap<BuildingKind, (MeshBuilder<false>, InstancedMeshBuilder<false>, bool)>,
     arrow_builder: SpriteBatchBuilder<false>,
@@ -62,6 +115,8 @@ impl MapMeshHandler {
         let mut buildsprites = FastMap::default();
         let mut buildmeshes = FastMap::default();
         let mut zonemeshes = FastMap::default();
+        let mut placeholders = FastSet::default();
+        let mut pending_meshes = vec![];
 
         for descr in GoodsCompanyPrototype::iter() {
             if descr.zone.is_some() {
@@ -83,6 +138,31 @@ impl MapMeshHandler {
                 FreightStationPrototype::iter()
                     .map(|descr| (&descr.asset, BuildingKind::RailFreightStation(descr.id))),
             )
+            .chain(
+                PassengerStationPrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::RailPassengerStation(descr.id))),
+            )
+            .chain(
+                WarehousePrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Warehouse(descr.id))),
+            )
+            .chain(
+                SchoolPrototype::iter().map(|descr| (&descr.asset, BuildingKind::School(descr.id))),
+            )
+            .chain(
+                LeisurePrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Leisure(descr.id))),
+            )
+            .chain(
+                HotelPrototype::iter().map(|descr| (&descr.asset, BuildingKind::Hotel(descr.id))),
+            )
+            .chain(
+                HarborPrototype::iter().map(|descr| (&descr.asset, BuildingKind::Harbor(descr.id))),
+            )
+            .chain(
+                AirportPrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Airport(descr.id))),
+            )
             .chain([(
                 &RenderAsset::Mesh {
                     path: "external_trading.glb".into(),
@@ -93,15 +173,18 @@ impl MapMeshHandler {
             let RenderAsset::Mesh { path } = asset else {
                 continue;
             };
-            let m = match gfx.mesh(path) {
-                Ok(m) => m,
-                Err(e) => {
-                    log::error!("Failed to load mesh {}: {:?}", asset, e);
-                    continue;
+            match gfx.request_mesh(path) {
+                MeshLoadState::Ready(m) => {
+                    buildmeshes.insert(bkind, InstancedMeshBuilder::new_ref(&m));
                 }
-            };
-
-            buildmeshes.insert(bkind, InstancedMeshBuilder::new_ref(&m));
+                MeshLoadState::Loading => {
+                    placeholders.insert(bkind);
+                    pending_meshes.push((bkind, path.clone()));
+                }
+                MeshLoadState::Failed(_) => {
+                    placeholders.insert(bkind);
+                }
+            }
         }
 
         for descr in GoodsCompanyPrototype::iter() {
@@ -149,23 +232,42 @@ impl MapMeshHandler {
             },
             None,
         ));
-        let houses_mat = gfx.register_material(Material::new(
-            gfx,
-            &gfx.palette(),
-            MetallicRoughness {
-                metallic: 0.0,
-                roughness: 1.0,
-                tex: None,
-            },
-            None,
-        ));
+        let houses_mat = gfx.register_material(
+            Material::new(
+                gfx,
+                &gfx.palette(),
+                MetallicRoughness {
+                    metallic: 0.0,
+                    roughness: 1.0,
+                    tex: None,
+                },
+                None,
+            )
+            .with_night_windows(&gfx.queue),
+        );
+        let roads_mat = gfx.register_material(
+            Material::new(
+                gfx,
+                &gfx.null_texture,
+                MetallicRoughness {
+                    metallic: 0.0,
+                    roughness: 1.0,
+                    tex: None,
+                },
+                None,
+            )
+            .with_wet_surface(&gfx.queue),
+        );
         let builders = MapBuilders {
             arrow_builder,
             buildsprites,
             crosswalk_builder: MeshBuilder::new(crosswalk_mat),
-            mesh_map: MeshBuilder::new(gfx.tess_material),
+            mesh_map: MeshBuilder::new(roads_mat),
             houses_mesh: MeshBuilder::new(houses_mat),
             buildmeshes,
+            placeholders,
+            pending_meshes,
+            buildsprites_aabb: None,
             zonemeshes,
             mesh_lots: MeshBuilder::new(gfx.tess_material),
         };
@@ -175,6 +277,7 @@ impl MapMeshHandler {
             cache: Default::default(),
             road_sub: sim.map().subscribe(UpdateType::Road),
             building_sub: sim.map().subscribe(UpdateType::Building),
+            stats: MapMeshStats::default(),
         }
     }
 
@@ -185,8 +288,10 @@ impl MapMeshHandler {
         ctx: &mut FrameContext<'_>,
     ) {
         profiling::scope!("draw map mesh");
+        self.stats.rebuilds = 0;
         for chunk in self.road_sub.take_updated_chunks() {
             profiling::scope!("build road chunk");
+            self.stats.rebuilds += 1;
             let b = &mut self.builders;
             b.map_mesh(map, chunk);
 
@@ -204,14 +309,29 @@ impl MapMeshHandler {
 
             cached.lots = b.mesh_lots.build(ctx.gfx);
             cached.arrows = b.arrow_builder.build(ctx.gfx);
+            cached.road_aabb = union_aabbs(
+                cached
+                    .road
+                    .iter()
+                    .map(|m| &**m)
+                    .chain(&cached.lots)
+                    .filter_map(mesh_aabb),
+            );
 
             if cached.is_empty() {
                 self.cache.remove(&chunk);
             }
         }
 
-        for chunk in self.building_sub.take_updated_chunks() {
+        let mut building_chunks: BTreeSet<_> = self.building_sub.take_updated_chunks().collect();
+        if !ctx.gfx.meshes_loaded.is_empty() && self.builders.swap_loaded_meshes(ctx.gfx) {
+            // the placeholders are replaced everywhere
+            building_chunks.extend(self.cache.keys().copied());
+        }
+
+        for chunk in building_chunks {
             profiling::scope!("build building chunk");
+            self.stats.rebuilds += 1;
 
             let b = &mut self.builders;
             b.buildings_mesh(map, chunk);
@@ -220,6 +340,7 @@ impl MapMeshHandler {
 
             cached.build.clear();
             cached.build.reserve(4);
+            let mut aabbs: Vec<AABB3> = b.buildsprites_aabb.into_iter().collect();
 
             let sprites = b
                 .buildsprites
@@ -237,11 +358,13 @@ impl MapMeshHandler {
                 .flat_map(|x| x.build(ctx.gfx))
                 .collect::<Vec<_>>();
 
+            aabbs.extend(buildmeshes.iter().map(InstancedMesh::aabb3));
             if !buildmeshes.is_empty() {
                 cached.build.push(Arc::new(buildmeshes));
             }
 
             if let Some(mesh) = b.houses_mesh.build(ctx.gfx) {
+                aabbs.extend(mesh_aabb(&mesh));
                 cached.build.push(Arc::new(mesh));
             }
 
@@ -250,9 +373,14 @@ impl MapMeshHandler {
                 .values_mut()
                 .flat_map(|(a, b, _)| a.build(ctx.gfx).zip(b.build(ctx.gfx)))
                 .collect::<Vec<_>>();
+            for (floor, filler) in &zonemeshes {
+                aabbs.extend(mesh_aabb(floor));
+                aabbs.push(filler.aabb3());
+            }
             if !zonemeshes.is_empty() {
                 cached.build.push(Arc::new(zonemeshes));
             }
+            cached.build_aabb = union_aabbs(aabbs);
 
             if cached.is_empty() {
                 self.cache.remove(&chunk);
@@ -260,24 +388,75 @@ impl MapMeshHandler {
         }
 
         profiling::scope!("prepare map mesh");
+        self.stats.chunks_total = self.cache.len();
+        self.stats.chunks_drawn = 0;
         for v in self.cache.values() {
-            ctx.draw(v.build.clone());
-            ctx.draw(v.road.clone());
+            // the bounds are 3D so that the elevated roads and the tall buildings are kept when the
+            // camera is tilted, each pass culls on its own so that the chunks out of the camera
+            // still cast their shadows
+            if v.aabb()
+                .map_or(true, |aabb| ctx.gfx.frustrum.intersects(&aabb))
+            {
+                self.stats.chunks_drawn += 1;
+            }
+
+            draw_culled(ctx, v.build_aabb, v.build.clone());
+            draw_culled(ctx, v.road_aabb, v.road.clone());
             if options.show_arrows {
                 if let Some(ref x) = v.arrows {
-                    ctx.draw(x.clone());
+                    draw_culled(ctx, v.road_aabb, x.clone());
                 }
             }
             if options.show_lots {
                 if let Some(ref x) = v.lots {
-                    ctx.draw(x.clone());
+                    draw_culled(ctx, v.road_aabb, x.clone());
                 }
             }
         }
     }
+
+    /// Mesh of the outline of the building as it is drawn, none for the buildings drawn as sprites
+    /// or as the boxes of the meshes that are loading
+    pub fn building_outline(
+        &self,
+        building: &Building,
+        gfx: &GfxContext,
+    ) -> Option<Arc<dyn Drawable>> {
+        if let Some(m) = self.builders.buildmeshes.get(&building.kind) {
+            let mut mesh = InstancedMeshBuilder::<false>::new_ref(m.mesh());
+            mesh.instances.push(building_instance(building));
+            return Some(Arc::new(mesh.build(gfx)?));
+        }
+        if building.mesh.faces.is_empty() {
+            return None;
+        }
+        let mut mesh = MeshBuilder::<false>::new(gfx.tess_material);
+        houses_faces(&mut mesh, building);
+        Some(Arc::new(mesh.build(gfx)?))
+    }
 }
 
 impl MapBuilders {
+    /// Replaces the placeholders of the meshes that finished loading.
+    /// Returns whether the buildings have to be rebuilt.
+    fn swap_loaded_meshes(&mut self, gfx: &GfxContext) -> bool {
+        let mut changed = false;
+        self.pending_meshes
+            .retain(|(bkind, path)| match gfx.mesh_state(path) {
+                Some(MeshLoadState::Ready(m)) => {
+                    self.buildmeshes
+                        .insert(*bkind, InstancedMeshBuilder::new_ref(&m));
+                    self.placeholders.remove(bkind);
+                    changed = true;
+                    false
+                }
+                // the placeholder stays, the error was logged when loading
+                Some(MeshLoadState::Failed(_)) => false,
+                Some(MeshLoadState::Loading) | None => true,
+            });
+        changed
+    }
+
     fn arrows(arrow_builder: &mut SpriteBatchBuilder<false>, road: &Road, lanes: &Lanes) {
         let has_forward = road
             .outgoing_lanes_from(road.src)
@@ -369,6 +548,31 @@ impl MapBuilders {
         }
     }
 
+    /// Stripes of the crossings in the middle of the road, between the sidewalks
+    fn zebra_crossings(tess: &mut Tesselator, road: &Road, col: LinearColor) {
+        const WALKING_W: f32 = LaneKind::Walking.width();
+        const STRIPE_W: f32 = 0.5;
+
+        tess.set_color(col);
+        for crossing in &road.crossings {
+            let from = crossing.points.first().up(0.02);
+            let to = crossing.points.last().up(0.02);
+
+            let l = (to - from).mag();
+            if l < WALKING_W {
+                continue;
+            }
+
+            let dir = (to - from) / l;
+            let perp = dir.perp_up() * CROSSWALK_WIDTH * 0.5;
+            let n_stripes = ((l - WALKING_W) / (STRIPE_W * 2.0)) as usize;
+            for i in 0..n_stripes {
+                let mid = from + dir * (WALKING_W * 0.5 + STRIPE_W * (2 * i) as f32 + STRIPE_W);
+                tess.draw_stroke(mid - perp, mid + perp, STRIPE_W);
+            }
+        }
+    }
+
     fn buildings_mesh(&mut self, map: &Map, chunk: SubscriberChunkID) {
         for v in self.buildsprites.values_mut() {
             v.clear();
@@ -381,6 +585,7 @@ impl MapBuilders {
             v.1.instances.clear();
         }
         self.houses_mesh.clear();
+        self.buildsprites_aabb = None;
 
         let buildings = &map.buildings();
         for building in map
@@ -413,21 +618,57 @@ impl MapBuilders {
                     LinearColor::WHITE,
                     (w, h),
                 );
+                let aabb = AABB3::centered(c.z(building.height), Vec3::splat(w.hypot(h)));
+                self.buildsprites_aabb = Some(
+                    self.buildsprites_aabb
+                        .map_or(aabb, |other| other.union(aabb)),
+                );
             }
 
             if let Some(x) = self.buildmeshes.get_mut(&building.kind) {
-                let pos = building.obb.center().z(building.height);
-                let dir = building.obb.axis()[0].normalize().z0();
-
-                x.instances.push(MeshInstance {
-                    pos,
-                    dir,
-                    tint: LinearColor::WHITE,
-                });
+                x.instances.push(building_instance(building));
+            } else if self.placeholders.contains(&building.kind) {
+                self.placeholder_mesh(building);
             }
         }
     }
 
+    /// A gray box on the footprint of the building, which has the size of its prototype
+    fn placeholder_mesh(&mut self, building: &Building) {
+        let mut corners = building.obb.corners;
+        let signed_area: f32 = (0..4).map(|i| corners[i].cross(corners[(i + 1) % 4])).sum();
+        if signed_area < 0.0 {
+            corners.reverse();
+        }
+        let bot = building.height;
+        let top = bot + PLACEHOLDER_HEIGHT;
+        let color: LinearColor = Color::gray(0.6).into();
+
+        let mut quad = |quad: [Vec3; 4], normal: Vec3| {
+            self.houses_mesh.extend_with(None, |vertices, add_index| {
+                for p in quad {
+                    vertices.push(MeshVertex {
+                        position: p.into(),
+                        normal,
+                        uv: [0.0; 2],
+                        color: color.into(),
+                        tangent: [0.0; 4],
+                    });
+                }
+                for i in [0, 1, 2, 0, 2, 3] {
+                    add_index(i);
+                }
+            });
+        };
+
+        quad(corners.map(|c| c.z(top)), Vec3::Z);
+        for i in 0..4 {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            let normal = unwrap_cont!((b - a).perpendicular().try_normalize()).z0();
+            quad([a.z(bot), b.z(bot), b.z(top), a.z(top)], normal);
+        }
+    }
+
     fn zone_mesh(&mut self, building: &Building) {
         let Some(bzone) = &building.zone else {
             return;
@@ -518,47 +759,7 @@ impl MapBuilders {
     }
 
     fn houses_mesh(&mut self, building: &Building) {
-        for (face, col) in &building.mesh.faces {
-            self.houses_mesh.extend_with(None, |vertices, add_index| {
-                let o = face[1];
-                let u = unwrap_ret!((face[0] - o).try_normalize());
-                let v = unwrap_ret!((face[2] - o).try_normalize());
-
-                let mut nor = u.cross(v);
-
-                let mut reverse = false;
-
-                if nor.z < 0.0 {
-                    reverse = true;
-                    nor = -nor;
-                }
-
-                let mut projected = Polygon(Vec::with_capacity(face.len()));
-                for &p in face {
-                    let off = p - o;
-                    projected.0.push(vec2(off.dot(u), off.dot(v)));
-
-                    vertices.push(MeshVertex {
-                        position: p.into(),
-                        normal: nor,
-                        uv: [0.0; 2],
-                        color: col.into(),
-                        tangent: [0.0; 4],
-                    })
-                }
-
-                projected.simplify();
-
-                earcut(&projected.0, &[], |mut a, b, mut c| {
-                    if reverse {
-                        std::mem::swap(&mut a, &mut c);
-                    }
-                    add_index(a as u32);
-                    add_index(b as u32);
-                    add_index(c as u32);
-                })
-            });
-        }
+        houses_faces(&mut self.houses_mesh, building);
     }
 
     fn draw_rail(tess: &mut Tesselator, cut: &PolyLine3, off: f32, _limits: bool) {
@@ -639,7 +840,10 @@ impl MapBuilders {
             let first_dir = unwrap_cont!(cut.first_dir());
             let last_dir = unwrap_cont!(cut.last_dir());
 
-            road_pylons(&mut tess_map, env, road);
+            // tunnels are hidden by the terrain, only bridges need pillars
+            if road.structure == RoadStructure::Bridge {
+                road_pylons(&mut tess_map, env, road);
+            }
 
             tess_map.normal.z = -1.0;
             tess_map.draw_polyline_full(
@@ -697,6 +901,8 @@ impl MapBuilders {
                     l.dist_from_bottom - road.width * 0.5 + l.kind.width(),
                 );
             }
+
+            Self::zebra_crossings(&mut tess_map, road, line_col);
         }
 
         // Intersections
@@ -771,6 +977,60 @@ impl MapBuilders {
     }
 }
 
+/// The instance of the mesh of the building kind
+fn building_instance(building: &Building) -> MeshInstance {
+    MeshInstance {
+        pos: building.obb.center().z(building.height),
+        dir: building.obb.axis()[0].normalize().z0(),
+        tint: LinearColor::WHITE,
+    }
+}
+
+/// The walls and roofs of the procedural buildings
+fn houses_faces(houses_mesh: &mut MeshBuilder<false>, building: &Building) {
+    for (face, col) in &building.mesh.faces {
+        houses_mesh.extend_with(None, |vertices, add_index| {
+            let o = face[1];
+            let u = unwrap_ret!((face[0] - o).try_normalize());
+            let v = unwrap_ret!((face[2] - o).try_normalize());
+
+            let mut nor = u.cross(v);
+
+            let mut reverse = false;
+
+            if nor.z < 0.0 {
+                reverse = true;
+                nor = -nor;
+            }
+
+            let mut projected = Polygon(Vec::with_capacity(face.len()));
+            for &p in face {
+                let off = p - o;
+                projected.0.push(vec2(off.dot(u), off.dot(v)));
+
+                vertices.push(MeshVertex {
+                    position: p.into(),
+                    normal: nor,
+                    uv: [0.0; 2],
+                    color: col.into(),
+                    tangent: [0.0; 4],
+                })
+            }
+
+            projected.simplify();
+
+            earcut(&projected.0, &[], |mut a, b, mut c| {
+                if reverse {
+                    std::mem::swap(&mut a, &mut c);
+                }
+                add_index(a as u32);
+                add_index(b as u32);
+                add_index(c as u32);
+            })
+        });
+    }
+}
+
 fn add_polyon(
     mut tess: &mut Tesselator,
     w: f32,
@@ -859,7 +1119,8 @@ fn inter_pylon(tess: &mut Tesselator, env: &Environment, inter: &Intersection, r
     let interpos = inter.pos.up(ROAD_Z_OFFSET);
 
     let h = unwrap_ret!(env.true_height(inter.pos.xy()));
-    if (h - interpos.z).abs() <= 2.0 {
+    // underground intersections belong to tunnels
+    if interpos.z - h <= 2.0 {
         return;
     }
 
@@ -14,6 +14,9 @@ mod map_mesh;
 mod terrain;
 mod trees;
 
+pub use map_mesh::MapMeshStats;
+pub use trees::foliage_color;
+
 /// Render the entire map including the terrain, trees, water etc
 pub struct MapRenderer {
     pub meshb: MapMeshHandler,
@@ -137,17 +140,8 @@ impl MapRenderer {
                 continue;
             }
 
-            if !r.lanes_iter().all(|(_, kind)| kind.is_rail()) {
-                let has_sidewalks = r.has_sidewalks();
-                let offset = if has_sidewalks {
-                    LaneKind::Walking.width()
-                } else {
-                    0.0
-                };
-                let w = r.width * 0.5 - offset;
-                for (point, dir) in r.interfaced_points().equipoints_dir(45.0, true) {
-                    draw.mesh("streetlamp.glb", point - dir.perp_up() * w, dir.perp_up());
-                }
+            for (foot, dir) in lamps::lamp_posts(r) {
+                draw.mesh("streetlamp.glb", foot, dir);
             }
 
             Self::render_lanes(
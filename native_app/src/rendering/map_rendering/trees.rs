@@ -6,8 +6,19 @@ use engine::{
     Drawable, FrameContext, GfxContext, InstancedMesh, InstancedMeshBuilder, MeshInstance,
 };
 use geom::{vec3, vec4, Camera, HeightmapChunk, Intersect3, LinearColor, Matrix4, Vec3, AABB3};
+use prototypes::Season;
 use simulation::map::{Map, MapSubscriber, SubscriberChunkID, UpdateType};
 
+/// Tint of the leaves in each season, applied by the shader on the green parts of the trees
+pub fn foliage_color(season: Season) -> LinearColor {
+    match season {
+        Season::Spring => LinearColor::new(0.9, 1.15, 0.8, 1.0),
+        Season::Summer => LinearColor::WHITE,
+        Season::Autumn => LinearColor::new(1.8, 0.9, 0.35, 1.0),
+        Season::Winter => LinearColor::new(0.8, 0.85, 0.85, 1.0),
+    }
+}
+
 pub struct TreesRender {
     tree_builder: InstancedMeshBuilder<false>,
     trees_cache: FastMap<SubscriberChunkID, InstancedMesh>,
@@ -17,6 +28,13 @@ pub struct TreesRender {
 impl TreesRender {
     pub fn new(gfx: &mut GfxContext, map: &Map) -> Self {
         let mesh = gfx.mesh("pine.glb".as_ref()).expect("could not load pine");
+        for lod in mesh.lods.iter() {
+            for (mat, _) in &lod.primitives {
+                if let Some((mat, queue)) = gfx.material_mut(*mat) {
+                    mat.set_foliage(queue);
+                }
+            }
+        }
 
         let tree_sub = map.subscribe(UpdateType::Terrain);
         Self {
@@ -37,10 +55,13 @@ impl TreesRender {
                     let Some((_, t)) = map.environment.trees.get(obj.0) else {
                         return;
                     };
+                    let species_tint = t
+                        .species
+                        .map_or(LinearColor::WHITE, |s| s.prototype().tint.into());
                     self.tree_builder.instances.push(MeshInstance {
                         pos: t.pos.z(map.environment.height(t.pos).unwrap_or_default()),
                         dir: t.dir.z0() * t.size * 0.2,
-                        tint: ((1.0 - t.size * 0.05) * t.col * LinearColor::WHITE).a(1.0),
+                        tint: ((1.0 - t.size * 0.05) * t.col * species_tint).a(1.0),
                     });
                 });
 
@@ -61,6 +82,9 @@ impl TreesRender {
         struct TreeMesh(InstancedMesh, Vec3);
 
         impl Drawable for TreeMesh {
+            fn prepare(&self, gfx: &GfxContext) {
+                self.0.prepare(gfx);
+            }
             fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
                 self.0.draw(gfx, rp);
             }
@@ -0,0 +1,469 @@
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+use engine::wgpu::TextureFormat;
+use engine::yakui::YakuiWrapper;
+use engine::{GfxContext, Texture, TextureBuilder};
+use geom::{vec2, Color, Vec2, AABB};
+use prototypes::ColorsPrototype;
+use simulation::map::{
+    BuildingID, BuildingKind, Heightmap, Map, MapSubscriber, ProjectFilter, ProjectKind, UpdateType,
+};
+use simulation::map_dynamic::ElectricityFlow;
+use simulation::Simulation;
+use yakui::TextureId;
+
+use crate::newgui::windows::settings::Settings;
+use crate::rendering::overlays::Overlays;
+use crate::rendering::traffic_overlay::{congestion_color, TrafficOverlay};
+use crate::uiworld::UiWorld;
+
+/// Side in pixels of the minimap texture
+pub const MINIMAP_RESOLUTION: u32 = 512;
+
+/// Congestion and power change without the map being edited,
+/// so a tinted minimap is drawn again entirely this often, in seconds
+const TINT_REFRESH: f32 = 5.0;
+
+/// What the minimap widget needs to show the texture and move the camera
+#[derive(Default)]
+pub struct Minimap {
+    pub texture: Option<TextureId>,
+    pub proj: MinimapProjection,
+}
+
+/// Maps positions of the world to the minimap, north up.
+/// The minimap is square so the longest side of the map fits in it.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MinimapProjection {
+    /// South west corner of the map
+    origin: Vec2,
+    /// Meters covered by each side of the minimap
+    side: f32,
+}
+
+impl MinimapProjection {
+    pub fn new(bounds: AABB) -> Self {
+        Self {
+            origin: bounds.ll,
+            side: bounds.w().max(bounds.h()).max(1.0),
+        }
+    }
+
+    /// Position in [0; 1] from the top left corner of the minimap
+    pub fn to_uv(&self, p: Vec2) -> Vec2 {
+        let t = (p - self.origin) / self.side;
+        vec2(t.x, 1.0 - t.y)
+    }
+
+    pub fn from_uv(&self, uv: Vec2) -> Vec2 {
+        self.origin + vec2(uv.x, 1.0 - uv.y) * self.side
+    }
+
+    fn meters_per_pixel(&self) -> f32 {
+        self.side / MINIMAP_RESOLUTION as f32
+    }
+
+    fn to_pixel(&self, p: Vec2) -> Vec2 {
+        self.to_uv(p) * MINIMAP_RESOLUTION as f32
+    }
+
+    fn from_pixel(&self, px: Vec2) -> Vec2 {
+        self.from_uv(px / MINIMAP_RESOLUTION as f32)
+    }
+
+    /// Pixels covering the area, clamped to the minimap
+    fn pixel_rect(&self, area: AABB) -> PixelRect {
+        let res = MINIMAP_RESOLUTION as f32;
+        let a = self.to_pixel(vec2(area.ll.x, area.ur.y));
+        let b = self.to_pixel(vec2(area.ur.x, area.ll.y));
+        let x0 = a.x.floor().clamp(0.0, res) as u32;
+        let y0 = a.y.floor().clamp(0.0, res) as u32;
+        let x1 = b.x.ceil().clamp(0.0, res) as u32;
+        let y1 = b.y.ceil().clamp(0.0, res) as u32;
+        PixelRect {
+            x: x0,
+            y: y0,
+            w: x1 - x0,
+            h: y1 - y0,
+        }
+    }
+
+    /// Area of the world covered by the pixels
+    fn world_rect(&self, rect: PixelRect) -> AABB {
+        let a = self.from_pixel(vec2(rect.x as f32, (rect.y + rect.h) as f32));
+        let b = self.from_pixel(vec2((rect.x + rect.w) as f32, rect.y as f32));
+        AABB::new_ll_ur(a, b)
+    }
+}
+
+/// Overlays of the toolbox that tint the minimap
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+struct MinimapTint {
+    traffic: bool,
+    power: bool,
+}
+
+impl MinimapTint {
+    fn any(self) -> bool {
+        self.traffic || self.power
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct PixelRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+/// What is drawn on a part of the minimap, copied from the simulation
+/// so that drawing doesn't keep it locked. Positions are in pixels of the part.
+struct MinimapScene {
+    rect: PixelRect,
+    /// Points, half width and color of the roads
+    roads: Vec<(Vec<Vec2>, f32, [u8; 4])>,
+    /// Center, radius and color of the buildings
+    buildings: Vec<(Vec2, f32, [u8; 4])>,
+}
+
+/// Rgba pixels of a part of the minimap, ready to be uploaded
+struct MinimapPatch {
+    rect: PixelRect,
+    pixels: Vec<u8>,
+}
+
+/// Draws a top down view of the map into a texture, shown by the minimap widget.
+/// Only the chunks of the map that changed are drawn again, on a worker thread.
+pub struct MinimapRenderer {
+    texture: Texture,
+    proj: MinimapProjection,
+    sub: MapSubscriber,
+    /// Area of the world to draw again
+    dirty: Option<AABB>,
+    /// Copy of the heights of the terrain, sampled by the worker without locking the simulation
+    terrain: Arc<Heightmap>,
+    /// Area of the copy of the terrain to update before the next drawing
+    terrain_dirty: Option<AABB>,
+    tint: MinimapTint,
+    /// When the whole minimap was last drawn again because of the tint
+    last_tint_refresh: f32,
+    job: Option<JoinHandle<MinimapPatch>>,
+}
+
+impl MinimapRenderer {
+    pub fn new(
+        gfx: &GfxContext,
+        yakui: &mut YakuiWrapper,
+        uiw: &UiWorld,
+        sim: &Simulation,
+    ) -> Self {
+        let texture = TextureBuilder::empty(
+            MINIMAP_RESOLUTION,
+            MINIMAP_RESOLUTION,
+            1,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+        .with_label("minimap")
+        .build(&gfx.device, &gfx.queue);
+        let map = sim.map();
+        let bounds = map.environment.bounds();
+        let proj = MinimapProjection::new(bounds);
+        *uiw.write::<Minimap>() = Minimap {
+            texture: Some(yakui.add_texture(&texture)),
+            proj,
+        };
+
+        Self {
+            texture,
+            proj,
+            sub: map.subscribe(UpdateType::Road | UpdateType::Building | UpdateType::Terrain),
+            dirty: Some(bounds),
+            terrain: Arc::new(map.environment.heightmap().clone()),
+            terrain_dirty: None,
+            tint: MinimapTint::default(),
+            last_tint_refresh: 0.0,
+            job: None,
+        }
+    }
+
+    /// Starts over with a new map, the texture is kept
+    pub fn reset(&mut self, uiw: &UiWorld, sim: &Simulation) {
+        let map = sim.map();
+        let bounds = map.environment.bounds();
+        self.proj = MinimapProjection::new(bounds);
+        self.sub = map.subscribe(UpdateType::Road | UpdateType::Building | UpdateType::Terrain);
+        self.dirty = Some(bounds);
+        // a job started on the previous map would draw over the new one
+        self.job = None;
+        self.terrain = Arc::new(map.environment.heightmap().clone());
+        self.terrain_dirty = None;
+        uiw.write::<Minimap>().proj = self.proj;
+    }
+
+    pub fn update(&mut self, sim: &Arc<RwLock<Simulation>>, uiw: &UiWorld, gfx: &GfxContext) {
+        profiling::scope!("minimap::update");
+        if self.job.as_ref().is_some_and(|j| j.is_finished()) {
+            if let Ok(patch) = self.job.take().unwrap().join() {
+                let r = patch.rect;
+                self.texture
+                    .write_region(&gfx.queue, (r.x, r.y), (r.w, r.h), &patch.pixels);
+            }
+        }
+
+        let sim_guard = sim.read().unwrap();
+        let map = sim_guard.map();
+        let bounds = map.environment.bounds();
+
+        let tint = MinimapTint {
+            traffic: uiw.read::<TrafficOverlay>().enabled,
+            power: uiw.read::<Overlays>().is_active("overlay-power"),
+        };
+        let now = uiw.time_always();
+        let refresh = tint.any() && now - self.last_tint_refresh > TINT_REFRESH;
+        let cleared = self.sub.take_cleared();
+        if cleared || tint != self.tint || refresh {
+            self.tint = tint;
+            self.last_tint_refresh = now;
+            self.dirty = Some(bounds);
+        }
+        if cleared {
+            self.terrain_dirty = Some(bounds);
+        }
+        for chunk in self.sub.take_updated_chunks() {
+            let area = chunk.bbox();
+            self.dirty = Some(self.dirty.map_or(area, |d| d.union(area)));
+            self.terrain_dirty = Some(self.terrain_dirty.map_or(area, |d| d.union(area)));
+        }
+
+        if self.job.is_some() || !uiw.read::<Settings>().show_minimap {
+            return;
+        }
+        let Some(dirty) = self.dirty.take() else {
+            return;
+        };
+        let rect = self.proj.pixel_rect(dirty);
+        if rect.w == 0 || rect.h == 0 {
+            return;
+        }
+
+        // the previous job is done so the copy is not shared, only the changed chunks are copied
+        if let Some(area) = self.terrain_dirty.take() {
+            Arc::make_mut(&mut self.terrain).copy_chunks(map.environment.heightmap(), area);
+        }
+        drop(map);
+        drop(sim_guard);
+
+        let sim = sim.clone();
+        let terrain = self.terrain.clone();
+        let proj = self.proj;
+        let tint = self.tint;
+        self.job = Some(std::thread::spawn(move || {
+            profiling::scope!("minimap::draw");
+            // the simulation is only locked while the roads and buildings are copied,
+            // the terrain is sampled from the copy so that the ticks aren't held back
+            let scene = MinimapScene::extract(&sim.read().unwrap(), proj, rect, tint);
+            scene.draw(&terrain, proj)
+        }));
+    }
+}
+
+impl MinimapScene {
+    fn extract(
+        sim: &Simulation,
+        proj: MinimapProjection,
+        rect: PixelRect,
+        tint: MinimapTint,
+    ) -> Self {
+        let map = sim.map();
+        let flow = sim.read::<ElectricityFlow>();
+        let mpp = proj.meters_per_pixel();
+        let offset = vec2(rect.x as f32, rect.y as f32);
+
+        let mut roads = Vec::new();
+        let mut buildings = Vec::new();
+        // objects just outside of the rectangle can still overlap its pixels
+        let area = proj.world_rect(rect).expand(2.0 * mpp + 50.0);
+        for obj in map
+            .spatial_map()
+            .query(area, ProjectFilter::ROAD | ProjectFilter::BUILDING)
+        {
+            match obj {
+                ProjectKind::Road(id) => {
+                    let Some(r) = map.roads().get(id) else {
+                        continue;
+                    };
+                    let color = if tint.traffic {
+                        let congestion = r
+                            .lanes_iter()
+                            .filter(|(_, kind)| kind.vehicles())
+                            .map(|(lane, _)| map.lane_congestion(lane))
+                            .fold(0.0, f32::max);
+                        congestion_color(congestion)
+                    } else {
+                        simulation::colors().road_mid_col
+                    };
+                    roads.push((
+                        r.points
+                            .iter()
+                            .map(|p| proj.to_pixel(p.xy()) - offset)
+                            .collect(),
+                        (r.width * 0.5 / mpp).max(0.6),
+                        to_rgba(color),
+                    ));
+                }
+                ProjectKind::Building(id) => {
+                    let Some(b) = map.buildings().get(id) else {
+                        continue;
+                    };
+                    let color = if tint.power {
+                        power_color(&map, &flow, b.id)
+                    } else {
+                        building_color(&b.kind)
+                    };
+                    let size = b.obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max);
+                    buildings.push((
+                        proj.to_pixel(b.obb.center()) - offset,
+                        (size * 0.5 / mpp).max(0.8),
+                        to_rgba(color),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            rect,
+            roads,
+            buildings,
+        }
+    }
+
+    fn draw(self, terrain: &Heightmap, proj: MinimapProjection) -> MinimapPatch {
+        let r = self.rect;
+        let (w, h) = (r.w as usize, r.h as usize);
+        let colors = simulation::colors();
+        // terrain at the center of each pixel, row by row
+        let mut pixels: Vec<[u8; 4]> = (r.y..r.y + r.h)
+            .flat_map(|y| (r.x..r.x + r.w).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let p = proj.from_pixel(vec2(x as f32 + 0.5, y as f32 + 0.5));
+                terrain_color(colors, terrain.height(p).unwrap_or(f32::NAN))
+            })
+            .collect();
+
+        for (points, half_width, color) in &self.roads {
+            for seg in points.windows(2) {
+                let (a, b) = (seg[0], seg[1]);
+                let area = AABB::new_ll_ur(a.min(b), a.max(b)).expand(*half_width);
+                fill(&mut pixels, (w, h), area, *color, |p| {
+                    segment_dist(a, b, p) <= *half_width
+                });
+            }
+        }
+
+        for &(center, radius, color) in &self.buildings {
+            let area = AABB::centered(center, Vec2::splat(radius * 2.0));
+            fill(&mut pixels, (w, h), area, color, |p| {
+                p.distance(center) <= radius
+            });
+        }
+
+        MinimapPatch {
+            rect: self.rect,
+            pixels: pixels.into_iter().flatten().collect(),
+        }
+    }
+}
+
+/// Colors the pixels of the area whose center is inside the shape
+fn fill(
+    pixels: &mut [[u8; 4]],
+    (w, h): (usize, usize),
+    area: AABB,
+    color: [u8; 4],
+    inside: impl Fn(Vec2) -> bool,
+) {
+    let x0 = area.ll.x.floor().max(0.0) as usize;
+    let y0 = area.ll.y.floor().max(0.0) as usize;
+    let x1 = (area.ur.x.ceil().max(0.0) as usize).min(w);
+    let y1 = (area.ur.y.ceil().max(0.0) as usize).min(h);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            if inside(vec2(x as f32 + 0.5, y as f32 + 0.5)) {
+                pixels[y * w + x] = color;
+            }
+        }
+    }
+}
+
+fn segment_dist(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.mag2().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+pub(crate) fn to_rgba(c: Color) -> [u8; 4] {
+    [
+        (c.r.clamp(0.0, 1.0) * 255.0) as u8,
+        (c.g.clamp(0.0, 1.0) * 255.0) as u8,
+        (c.b.clamp(0.0, 1.0) * 255.0) as u8,
+        255,
+    ]
+}
+
+pub(crate) fn mix(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    Color::new(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        1.0,
+    )
+}
+
+/// Sea gets darker with depth, land from green to rocky brown with height
+fn terrain_color(colors: &ColorsPrototype, height: f32) -> [u8; 4] {
+    if height.is_nan() {
+        return [20, 20, 20, 255];
+    }
+    if height < 0.0 {
+        let deep = mix(colors.sea_col, Color::BLACK, 0.6);
+        return to_rgba(mix(colors.sea_col, deep, -height / 100.0));
+    }
+    if height < 2.0 {
+        return to_rgba(colors.sand_col);
+    }
+    let grass = Color::new(0.34, 0.47, 0.27, 1.0);
+    let rock = Color::new(0.55, 0.51, 0.43, 1.0);
+    to_rgba(mix(grass, rock, height / 800.0))
+}
+
+fn building_color(kind: &BuildingKind) -> Color {
+    match kind {
+        BuildingKind::House => simulation::colors().house_col,
+        BuildingKind::GoodsCompany(_) => Color::new(0.67, 0.43, 0.78, 1.0),
+        BuildingKind::Warehouse(_) => Color::new(0.55, 0.39, 0.24, 1.0),
+        BuildingKind::School(_) => Color::new(0.31, 0.63, 0.86, 1.0),
+        BuildingKind::Leisure(_) => Color::new(0.35, 0.7, 0.35, 1.0),
+        BuildingKind::Hotel(_) => Color::new(0.75, 0.55, 0.8, 1.0),
+        BuildingKind::Harbor(_) => Color::new(0.2, 0.45, 0.7, 1.0),
+        BuildingKind::Airport(_) => Color::new(0.6, 0.6, 0.65, 1.0),
+        BuildingKind::RailFreightStation(_)
+        | BuildingKind::RailPassengerStation(_)
+        | BuildingKind::TrainStation => Color::new(0.86, 0.78, 0.31, 1.0),
+        BuildingKind::ExternalTrading => Color::WHITE,
+    }
+}
+
+/// Green when powered, red when without power, gray when not connected
+fn power_color(map: &Map, flow: &ElectricityFlow, b: BuildingID) -> Color {
+    if map.electricity.net_id(b).is_none() {
+        return Color::gray(0.5);
+    }
+    if flow.is_shed(b) {
+        return Color::RED;
+    }
+    Color::GREEN
+}
@@ -3,6 +3,19 @@ pub use map_rendering::*;
 pub use orbit_camera::*;
 
 mod entity_render;
+pub mod garbage_overlay;
 pub mod immediate;
+pub mod land_value_overlay;
+pub mod map_labels;
 mod map_rendering;
+pub mod minimap;
+pub mod noise_overlay;
 mod orbit_camera;
+pub mod overlays;
+pub mod planes;
+pub mod power_overlay;
+pub mod selection_outline;
+pub mod ships;
+pub mod sun;
+pub mod traffic_overlay;
+pub mod weather;
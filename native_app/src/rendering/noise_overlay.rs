@@ -0,0 +1,42 @@
+use engine::Tesselator;
+use geom::{vec2, Color};
+use simulation::souls::happiness::happiness_weights;
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Whether the road noise overlay is shown, toggled from the toolbox
+#[derive(Default)]
+pub struct NoiseOverlay {
+    pub enabled: bool,
+}
+
+/// From faint yellow when barely audible to red when the noise makes residents fully unhappy
+fn noise_color(noise: f32) -> Color {
+    let t = (noise / happiness_weights().max_noise).min(1.0);
+    Color::hsv(60.0 * (1.0 - t), 0.9, 0.9, 0.2 + 0.4 * t)
+}
+
+/// Draws the noise grid as a heatmap over the ground
+pub fn draw_noise_overlay(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld) {
+    if !uiw.read::<NoiseOverlay>().enabled {
+        return;
+    }
+    profiling::scope!("noise_overlay");
+
+    let map = sim.map();
+
+    for (aabb, noise) in map.noise_cells() {
+        let z = map.environment.height(aabb.center()).unwrap_or(0.0) + 1.0;
+        tess.set_color(noise_color(noise));
+        tess.draw_filled_polygon(
+            &[
+                aabb.ll,
+                vec2(aabb.ur.x, aabb.ll.y),
+                aabb.ur,
+                vec2(aabb.ll.x, aabb.ur.y),
+            ],
+            z,
+        );
+    }
+}
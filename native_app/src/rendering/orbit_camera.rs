@@ -3,22 +3,67 @@
 use common::saveload::Encoder;
 use engine::{Context, Tesselator};
 use geom::{Camera, Plane, Radians, Vec2, Vec3, AABB};
-use simulation::map::pathfinding_crate::num_traits::Pow;
+use serde::{Deserialize, Serialize};
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::settings::Settings;
 
+/// Camera distances for the zoom presets
+pub const ZOOM_STREET: f32 = 60.0;
+pub const ZOOM_DISTRICT: f32 = 400.0;
+pub const ZOOM_CITY: f32 = 1500.0;
+
+/// Closest the camera can get to the ground
+const MIN_DIST: f32 = 5.0;
+/// Farthest the camera can be, on big maps
+const MAX_DIST: f32 = 1500.0;
+/// Zoom factor for one wheel notch at sensitivity 1
+const ZOOM_STEP: f32 = 1.1;
+/// Notches per second when zooming with the keyboard
+const KEY_ZOOM_SPEED: f32 = 10.0;
+/// Notches per unit of touchpad magnification
+const PINCH_NOTCHES: f32 = 10.0;
+/// Rate of the exponential interpolation of the zoom toward its target
+const ZOOM_SMOOTHING: f32 = 12.0;
+
+/// Distance in pixels from the window border at which edge scrolling starts
+const EDGE_SCROLL_MARGIN: f32 = 8.0;
+
+/// Duration in seconds of the animation when jumping to a view
+const TRANSITION_DURATION: f32 = 0.3;
+
+/// The part of the camera state that can be saved and restored
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct CameraView {
+    pub pos: Vec3,
+    pub yaw: Radians,
+    pub pitch: Radians,
+    pub dist: f32,
+}
+
+/// Eased animation from one view to another
+struct CameraTransition {
+    from: CameraView,
+    to: CameraView,
+    t: f32,
+}
+
 /// CameraHandler3D is the camera handler for the 3D view
 /// It controls the camera using an orbit view
 pub struct OrbitCamera {
     pub camera: Camera,
     pub lastscreenpos: Vec2,
     pub last_pos: Option<Vec2>,
+    /// World position grabbed with the middle mouse, kept under the cursor while dragging
+    pub grab: Option<Vec3>,
     pub targetpos: Vec3,
     pub targetyaw: Radians,
     pub targetpitch: Radians,
     pub targetdist: f32,
     pub maxdist: f32,
+    /// Whether the current zoom keeps the point under the cursor in place
+    zoom_to_cursor: bool,
+    transition: Option<CameraTransition>,
 }
 
 impl OrbitCamera {
@@ -40,9 +85,72 @@ impl OrbitCamera {
         tess.zoom = 1000.0 / self.height().max(1.0);
     }
 
-    pub fn follow(&mut self, p: Vec3) {
-        self.camera.pos = p;
-        self.targetpos = p;
+    /// Moves the camera toward p, halving the distance every `half_life` seconds.
+    /// A half life of zero snaps to p.
+    pub fn follow(&mut self, p: Vec3, delta: f32, half_life: f32) {
+        let coeff = if half_life <= 0.0 {
+            1.0
+        } else {
+            1.0 - 0.5f32.powf(delta / half_life)
+        };
+        let pos = self.camera.pos + (p - self.camera.pos) * coeff;
+        self.camera.pos = pos;
+        self.targetpos = pos;
+    }
+
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            pos: self.camera.pos,
+            yaw: self.camera.yaw,
+            pitch: self.camera.pitch,
+            dist: self.camera.dist,
+        }
+    }
+
+    /// Animates the camera toward the given view instead of teleporting
+    pub fn transition_to(&mut self, to: CameraView) {
+        self.transition = Some(CameraTransition {
+            from: self.view(),
+            to,
+            t: 0.0,
+        });
+    }
+
+    /// Interpolates the zoom exponentially toward its target.
+    /// When zooming to the cursor, the camera is re-anchored every frame
+    /// so that the point under the cursor stays in place during the whole interpolation.
+    fn zoom_step(&mut self, delta: f32, settings: &Settings, screenpos: Vec2) {
+        if self.camera.dist == self.targetdist {
+            self.zoom_to_cursor = false;
+            return;
+        }
+
+        let anchor = if self.zoom_to_cursor {
+            self.camera.update();
+            self.unproject(screenpos, |_| Some(0.0))
+        } else {
+            None
+        };
+
+        let k = if settings.camera_smooth {
+            1.0 - (-ZOOM_SMOOTHING * settings.camera_smooth_tightness * delta).exp()
+        } else {
+            1.0
+        };
+        self.camera.dist *= (self.targetdist / self.camera.dist).powf(k);
+        if (self.targetdist / self.camera.dist - 1.0).abs() < 0.002 {
+            self.camera.dist = self.targetdist;
+        }
+
+        let Some(anchor) = anchor else {
+            return;
+        };
+        self.camera.update();
+        if let Some(after) = self.unproject(screenpos, |_| Some(0.0)) {
+            let shift = (anchor - after).xy().z0();
+            self.camera.pos += shift;
+            self.targetpos += shift;
+        }
     }
 
     pub fn resize(&mut self, ctx: &mut Context, width: f32, height: f32) {
@@ -85,11 +193,14 @@ impl OrbitCamera {
             camera,
             lastscreenpos: Default::default(),
             last_pos: Default::default(),
+            grab: None,
             targetpos: camera.pos,
             targetyaw: camera.yaw,
             targetpitch: camera.pitch,
             targetdist: camera.dist,
-            maxdist: 1500.0,
+            maxdist: MAX_DIST,
+            zoom_to_cursor: false,
+            transition: None,
         }
     }
 
@@ -99,6 +210,7 @@ impl OrbitCamera {
         delta: f32,
         inps: &InputMap,
         settings: &Settings,
+        mouse_enabled: bool,
         map_bounds: AABB,
         height: impl Fn(Vec2) -> Option<f32>,
     ) {
@@ -118,6 +230,9 @@ impl OrbitCamera {
 
         self.save();
 
+        // don't zoom out further than the map
+        self.maxdist = (0.5 * map_bounds.w().max(map_bounds.h())).clamp(10.0 * MIN_DIST, MAX_DIST);
+
         // prepare useful variables
         let delta = delta.min(0.1);
         let off = self.camera.offset();
@@ -138,25 +253,51 @@ impl OrbitCamera {
             self.targetpos += delta * d.z0();
         }
 
-        if inps.act.contains(&InputAction::Zoom) {
-            self.targetdist *= (1.0f32 / 1.05).pow(0.5 + 0.1 * inps.wheel.abs());
+        // zoom is counted in wheel notches, positive zooms in
+        let invert = if settings.camera_zoom_invert {
+            -1.0
+        } else {
+            1.0
+        };
+        let mut notches = 0.0;
+        for (action, dir) in [(InputAction::Zoom, 1.0), (InputAction::Dezoom, -1.0)] {
+            if !inps.act.contains(&action) {
+                continue;
+            }
+            if inps.wheel != 0.0 {
+                notches += dir * invert * inps.wheel.abs() / 10.0;
+                self.zoom_to_cursor = true;
+            } else {
+                notches += dir * delta * KEY_ZOOM_SPEED;
+                self.zoom_to_cursor = false;
+            }
         }
-
-        if inps.act.contains(&InputAction::Dezoom) {
-            self.targetdist *= 1.05f32.pow(0.5 + 0.1 * inps.wheel.abs());
+        let pinch = ctx.input.mouse.pinch_delta;
+        if mouse_enabled && pinch != 0.0 {
+            notches += invert * pinch * PINCH_NOTCHES;
+            self.zoom_to_cursor = true;
+        }
+        if notches != 0.0 {
+            self.targetdist /= ZOOM_STEP.powf(notches * settings.camera_zoom_sensitivity);
         }
 
-        if settings.camera_border_move {
-            if screenpos.x < 2.0 {
+        // edge scrolling, d is proportional to the zoom so the speed is too
+        if settings.camera_border_move
+            && mouse_enabled
+            && !ctx.yakui.mouse_captured
+            && !ctx.input.unfocused
+            && !ctx.input.cursor_outside
+        {
+            if screenpos.x < EDGE_SCROLL_MARGIN {
                 self.targetpos += delta * d.perpendicular().z0();
             }
-            if screenpos.x > self.camera.viewport_w - 2.0 {
+            if screenpos.x > self.camera.viewport_w - EDGE_SCROLL_MARGIN {
                 self.targetpos += -delta * d.perpendicular().z0();
             }
-            if screenpos.y < 2.0 {
+            if screenpos.y < EDGE_SCROLL_MARGIN {
                 self.targetpos += -delta * d.z0();
             }
-            if screenpos.y > self.camera.viewport_h - 2.0 {
+            if screenpos.y > self.camera.viewport_h - EDGE_SCROLL_MARGIN {
                 self.targetpos += delta * d.z0();
             }
         }
@@ -182,8 +323,34 @@ impl OrbitCamera {
             self.last_pos = unprojected.map(Vec3::xy);
         }
 
+        // middle mouse drag: the grabbed point stays pinned under the cursor
+        if mouse_enabled && inps.act.contains(&InputAction::CameraGrab) {
+            if let Some(unprojected) = unprojected {
+                match self.grab {
+                    None => self.grab = Some(unprojected),
+                    Some(grab) => {
+                        self.camera.pos += (grab - unprojected).xy().z0();
+                        self.targetpos = self.camera.pos;
+                    }
+                }
+            }
+        } else {
+            self.grab = None;
+        }
+
+        for (action, dist) in [
+            (InputAction::ZoomStreet, ZOOM_STREET),
+            (InputAction::ZoomDistrict, ZOOM_DISTRICT),
+            (InputAction::ZoomCity, ZOOM_CITY),
+        ] {
+            if inps.just_act.contains(&action) {
+                self.targetdist = dist;
+                self.zoom_to_cursor = false;
+            }
+        }
+
         // make sure things are in reasonable bounds
-        self.targetdist = self.targetdist.clamp(5.0, self.maxdist);
+        self.targetdist = self.targetdist.clamp(MIN_DIST, self.maxdist);
         self.camera.fovy = settings.camera_fov.clamp(1.0, 179.0);
         self.targetpos.x = self.targetpos.x.clamp(map_bounds.ll.x, map_bounds.ur.x);
         self.targetpos.y = self.targetpos.y.clamp(map_bounds.ll.y, map_bounds.ur.y);
@@ -210,15 +377,36 @@ impl OrbitCamera {
             lerpp!(self.camera.pitch, self.targetpitch, 8.0, |x: Radians| x
                 .0
                 .abs());
-            lerpp!(self.camera.dist, self.targetdist, 8.0, |x: f32| x.abs());
-            if (self.targetdist / self.camera.dist - 1.0).abs() < 0.002 {
-                self.camera.dist = self.targetdist;
-            }
         } else {
             self.camera.pos = self.targetpos;
             self.camera.yaw = self.targetyaw;
             self.camera.pitch = self.targetpitch;
-            self.camera.dist = self.targetdist;
+        }
+
+        self.zoom_step(delta, settings, screenpos);
+
+        if let Some(ref mut tr) = self.transition {
+            tr.to.pos.x = tr.to.pos.x.clamp(map_bounds.ll.x, map_bounds.ur.x);
+            tr.to.pos.y = tr.to.pos.y.clamp(map_bounds.ll.y, map_bounds.ur.y);
+            tr.to.dist = tr.to.dist.clamp(MIN_DIST, self.maxdist);
+
+            tr.t = (tr.t + delta / TRANSITION_DURATION).min(1.0);
+            // smoothstep easing
+            let k = tr.t * tr.t * (3.0 - 2.0 * tr.t);
+
+            self.camera.pos = tr.from.pos + (tr.to.pos - tr.from.pos) * k;
+            self.camera.yaw = tr.from.yaw + (tr.to.yaw - tr.from.yaw) * k;
+            self.camera.pitch = tr.from.pitch + (tr.to.pitch - tr.from.pitch) * k;
+            self.camera.dist = tr.from.dist + (tr.to.dist - tr.from.dist) * k;
+
+            self.targetpos = tr.to.pos;
+            self.targetyaw = tr.to.yaw;
+            self.targetpitch = tr.to.pitch;
+            self.targetdist = tr.to.dist;
+
+            if tr.t >= 1.0 {
+                self.transition = None;
+            }
         }
 
         // update orbit center to be height aware
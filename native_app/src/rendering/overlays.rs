@@ -0,0 +1,189 @@
+//! Heatmaps drawn over the terrain.
+//! Each layer samples a value over the map, the active one is sampled on a grid around the camera
+//! and drawn as translucent cells colored by its ramp. The grid is kept until the layer asks to
+//! be sampled again or the camera moves away.
+
+use engine::Tesselator;
+use geom::{vec2, Color, ColorRamp, Vec2};
+use simulation::Simulation;
+
+use crate::rendering::power_overlay::PowerLayer;
+use crate::uiworld::UiWorld;
+
+/// Side of a cell of the grid, in meters
+pub const OVERLAY_CELL: f32 = 32.0;
+/// Cells sampled on each side of the camera
+const OVERLAY_RADIUS: i32 = 48;
+/// The grid is sampled again once the camera moved this many cells away from its center
+const OVERLAY_RECENTER: i32 = OVERLAY_RADIUS / 4;
+const OVERLAY_ALPHA: f32 = 0.5;
+
+/// How often a layer is sampled again
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OverlayRefresh {
+    EveryFrame,
+    /// Real time seconds
+    Every(f32),
+}
+
+/// A value over the map drawn as a heatmap
+pub trait OverlayLayer {
+    /// Lang key of the name of the layer
+    fn name(&self) -> &'static str;
+
+    fn refresh(&self) -> OverlayRefresh;
+
+    fn ramp(&self) -> ColorRamp {
+        ColorRamp::HEATMAP
+    }
+
+    /// Values mapped to the ends of the ramp
+    fn range(&self) -> (f32, f32);
+
+    /// Legend of the ends of the ramp
+    fn legend(&self) -> (String, String);
+
+    /// Color and legend of the values above the range, which take the end of the ramp if None
+    fn overflow(&self) -> Option<(Color, String)> {
+        None
+    }
+
+    /// Called before the grid is sampled
+    fn update(&mut self, _sim: &Simulation) {}
+
+    /// Value at the position, None where the layer has nothing to show
+    fn sample(&self, sim: &Simulation, pos: Vec2) -> Option<f32>;
+}
+
+/// The cell containing the position
+pub fn overlay_cell(pos: Vec2) -> (i32, i32) {
+    (
+        (pos.x / OVERLAY_CELL).floor() as i32,
+        (pos.y / OVERLAY_CELL).floor() as i32,
+    )
+}
+
+struct Sampled {
+    layer: usize,
+    /// Cell of the center of the grid
+    center: (i32, i32),
+    time: f32,
+    /// Lower left corner, height and color of the cells with a value
+    cells: Vec<(Vec2, f32, Color)>,
+}
+
+/// The heatmap layers, picked from the toolbox
+pub struct Overlays {
+    layers: Vec<Box<dyn OverlayLayer>>,
+    active: Option<usize>,
+    sampled: Option<Sampled>,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Self {
+            layers: vec![Box::<PowerLayer>::default() as Box<dyn OverlayLayer>],
+            active: None,
+            sampled: None,
+        }
+    }
+}
+
+impl Overlays {
+    pub fn layers(&self) -> impl Iterator<Item = &dyn OverlayLayer> {
+        self.layers.iter().map(|l| &**l)
+    }
+
+    /// Index of the drawn layer
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    pub fn active_layer(&self) -> Option<&dyn OverlayLayer> {
+        Some(&*self.layers[self.active?])
+    }
+
+    /// Whether the layer of this name is drawn
+    pub fn is_active(&self, name: &str) -> bool {
+        self.active_layer().is_some_and(|l| l.name() == name)
+    }
+
+    pub fn set_active(&mut self, active: Option<usize>) {
+        self.active = active.filter(|&i| i < self.layers.len());
+        self.sampled = None;
+    }
+
+    fn is_stale(&self, layer: usize, center: (i32, i32), time: f32) -> bool {
+        let Some(ref s) = self.sampled else {
+            return true;
+        };
+        if s.layer != layer
+            || (s.center.0 - center.0).abs() > OVERLAY_RECENTER
+            || (s.center.1 - center.1).abs() > OVERLAY_RECENTER
+        {
+            return true;
+        }
+        match self.layers[layer].refresh() {
+            OverlayRefresh::EveryFrame => true,
+            OverlayRefresh::Every(period) => time - s.time >= period,
+        }
+    }
+
+    fn sample(&mut self, sim: &Simulation, layer: usize, center: (i32, i32), time: f32) {
+        profiling::scope!("overlays::sample");
+        let map = sim.map();
+        let l = &mut self.layers[layer];
+        l.update(sim);
+        let (min, max) = l.range();
+        let ramp = l.ramp();
+        let overflow = l.overflow().map(|(c, _)| c);
+
+        let mut cells = Vec::new();
+        for y in center.1 - OVERLAY_RADIUS..=center.1 + OVERLAY_RADIUS {
+            for x in center.0 - OVERLAY_RADIUS..=center.0 + OVERLAY_RADIUS {
+                let ll = vec2(x as f32, y as f32) * OVERLAY_CELL;
+                let middle = ll + Vec2::splat(OVERLAY_CELL * 0.5);
+                let Some(value) = l.sample(sim, middle) else {
+                    continue;
+                };
+                let z = map.environment.height(middle).unwrap_or(0.0) + 1.0;
+                let color = match overflow {
+                    Some(c) if value > max => c,
+                    _ => ramp.map(value, min, max),
+                };
+                cells.push((ll, z, color.a(OVERLAY_ALPHA)));
+            }
+        }
+
+        self.sampled = Some(Sampled {
+            layer,
+            center,
+            time,
+            cells,
+        });
+    }
+}
+
+/// Draws the active layer as a grid of translucent cells over the ground
+pub fn draw_overlays(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld) {
+    let mut overlays = uiw.write::<Overlays>();
+    let Some(layer) = overlays.active else {
+        return;
+    };
+    profiling::scope!("overlays");
+
+    let center = overlay_cell(uiw.camera().camera.pos.xy());
+    let time = uiw.time_always();
+    if overlays.is_stale(layer, center, time) {
+        overlays.sample(sim, layer, center, time);
+    }
+
+    let Some(ref sampled) = overlays.sampled else {
+        return;
+    };
+    for &(ll, z, color) in &sampled.cells {
+        let ur = ll + Vec2::splat(OVERLAY_CELL);
+        tess.set_color(color);
+        tess.draw_filled_polygon(&[ll, vec2(ur.x, ll.y), ur, vec2(ll.x, ur.y)], z);
+    }
+}
@@ -0,0 +1,71 @@
+use engine::Tesselator;
+use geom::{Color, Vec2, Vec3};
+use prototypes::GameTime;
+use simulation::transportation::air_traffic::AirTraffic;
+use simulation::Simulation;
+
+/// Length of a plane in meters
+const PLANE_LENGTH: f32 = 32.0;
+/// Distance between the tips of the wings
+const WINGSPAN: f32 = 30.0;
+/// Distance between the tips of the horizontal stabilizer at the tail
+const TAIL_SPAN: f32 = 11.0;
+/// Width of the fuselage
+const FUSELAGE_WIDTH: f32 = 3.5;
+
+/// Draws the planes landing, parked and taking off at the airports, with their shadow on the
+/// ground while they fly
+pub fn draw_planes(tess: &mut Tesselator, sim: &Simulation) {
+    let air = sim.read::<AirTraffic>();
+    if air.flights.is_empty() {
+        return;
+    }
+    profiling::scope!("planes");
+    let now = sim.read::<GameTime>().timestamp;
+    let map = sim.map();
+
+    for flight in &air.flights {
+        let (pos, dir) = flight.pos_dir(now);
+        let flat = dir.xy().try_normalize().unwrap_or(Vec2::X);
+        let side = flat.perpendicular().z0();
+
+        if flight.is_airborne(now) {
+            let ground = map
+                .environment
+                .true_height(pos.xy())
+                .unwrap_or(0.0)
+                .max(0.0);
+            let shadow = pos.xy().z(ground + 0.3);
+            let flat = flat.z0();
+            tess.set_color(Color::BLACK.a(0.25));
+            draw_silhouette(tess, shadow, flat, side);
+        }
+
+        tess.set_color(Color::new(0.92, 0.93, 0.95, 1.0));
+        draw_silhouette(tess, pos.up(1.5), dir, side);
+        tess.set_color(Color::new(0.2, 0.35, 0.65, 1.0));
+        let tail = pos.up(1.5) - dir * (PLANE_LENGTH * 0.45);
+        tess.draw_stroke(tail, tail.up(5.0) - dir * 2.0, 1.0);
+    }
+}
+
+/// The fuselage along the direction with the wings and the tail across it
+fn draw_silhouette(tess: &mut Tesselator, pos: Vec3, dir: Vec3, side: Vec3) {
+    let nose = pos + dir * (PLANE_LENGTH * 0.5);
+    let tail = pos - dir * (PLANE_LENGTH * 0.5);
+    tess.draw_stroke(tail, nose, FUSELAGE_WIDTH);
+
+    let wing = pos + dir * (PLANE_LENGTH * 0.05);
+    tess.draw_stroke(
+        wing - side * (WINGSPAN * 0.5),
+        wing + side * (WINGSPAN * 0.5),
+        4.0,
+    );
+
+    let stabilizer = pos - dir * (PLANE_LENGTH * 0.42);
+    tess.draw_stroke(
+        stabilizer - side * (TAIL_SPAN * 0.5),
+        stabilizer + side * (TAIL_SPAN * 0.5),
+        2.5,
+    );
+}
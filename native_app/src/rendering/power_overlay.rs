@@ -0,0 +1,75 @@
+use common::FastMap;
+use geom::{Color, Vec2};
+use simulation::map::{Map, NetworkObjectID};
+use simulation::map_dynamic::ElectricityFlow;
+use simulation::Simulation;
+
+use crate::rendering::overlays::{overlay_cell, OverlayLayer, OverlayRefresh, OVERLAY_CELL};
+
+/// Load of the power lines: the cells crossed by an edge of an electricity network take its load
+/// relative to its capacity, the most loaded edge when several cross the same cell.
+/// Overloaded lines stand out from the ones that are merely full.
+#[derive(Default)]
+pub struct PowerLayer {
+    loads: FastMap<(i32, i32), f32>,
+}
+
+fn object_pos(map: &Map, object: NetworkObjectID) -> Option<Vec2> {
+    match object {
+        NetworkObjectID::Building(b) => Some(map.get(b)?.obb.center()),
+        NetworkObjectID::Intersection(i) => Some(map.get(i)?.pos.xy()),
+        NetworkObjectID::Road(r) => Some(map.get(r)?.points.middle().xy()),
+    }
+}
+
+impl OverlayLayer for PowerLayer {
+    fn name(&self) -> &'static str {
+        "overlay-power"
+    }
+
+    fn refresh(&self) -> OverlayRefresh {
+        OverlayRefresh::Every(1.0)
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 1.0)
+    }
+
+    fn legend(&self) -> (String, String) {
+        (t!("overlay-power-idle"), t!("overlay-power-full"))
+    }
+
+    fn overflow(&self) -> Option<(Color, String)> {
+        Some((
+            Color::new(0.6, 0.0, 0.9, 1.0),
+            t!("overlay-power-overloaded"),
+        ))
+    }
+
+    fn update(&mut self, sim: &Simulation) {
+        self.loads.clear();
+        let map = sim.map();
+        let flow = sim.read::<ElectricityFlow>();
+
+        for network in map.electricity.networks() {
+            for (edge, load) in flow.edge_loads(network.id) {
+                let (Some(from), Some(to)) =
+                    (object_pos(&map, edge.from), object_pos(&map, edge.to))
+                else {
+                    continue;
+                };
+                // half a cell steps, so that the line has no gaps
+                let steps = (from.distance(to) / (OVERLAY_CELL * 0.5)).ceil() as usize;
+                for i in 0..=steps {
+                    let pos = from + (to - from) * (i as f32 / steps.max(1) as f32);
+                    let cell = self.loads.entry(overlay_cell(pos)).or_insert(0.0);
+                    *cell = cell.max(load);
+                }
+            }
+        }
+    }
+
+    fn sample(&self, _: &Simulation, pos: Vec2) -> Option<f32> {
+        self.loads.get(&overlay_cell(pos)).copied()
+    }
+}
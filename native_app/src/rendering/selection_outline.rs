@@ -0,0 +1,310 @@
+//! Outlines of the inspected, followed and hovered entities and buildings.
+//! The engine draws them as a few pixels wide line around the objects on the screen, the
+//! instances of the same mesh and selection are drawn together, and their meshes are kept until
+//! the objects move or are no longer selected. The devices that can't draw the outlines show a
+//! circle around the entities and the box of the buildings instead.
+
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+
+use common::FastMap;
+use engine::{Drawable, FrameContext, InstancedMesh, InstancedMeshBuilder, MeshInstance};
+use geom::{Color, LinearColor};
+use prototypes::{RoadVehicleID, RollingStockID};
+use simulation::map::{BuildingID, MapSubscriber, UpdateType};
+use simulation::transportation::Location;
+use simulation::{AnyEntity, Simulation, TrainID, WagonID};
+
+use crate::newgui::follow::FollowEntity;
+use crate::newgui::selectable::select_radius;
+use crate::newgui::{InspectedBuilding, InspectedEntity};
+use crate::rendering::entity_render::walk_bob;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::{InstancedRender, MapRenderer};
+use crate::uiworld::UiWorld;
+
+/// Why an object is outlined, an object selected for several reasons takes the first one
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SelectionKind {
+    Followed,
+    Inspected,
+    Hovered,
+}
+
+impl SelectionKind {
+    pub fn color(self) -> Color {
+        match self {
+            SelectionKind::Followed => simulation::colors().gui_success,
+            SelectionKind::Inspected => simulation::colors().gui_primary,
+            SelectionKind::Hovered => Color::WHITE.a(0.6),
+        }
+    }
+}
+
+/// What is drawn for a selected entity, the humans in a vehicle or a building are shown by it
+#[derive(Copy, Clone, PartialEq)]
+enum Outlined {
+    Entity(AnyEntity),
+    Building(BuildingID),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum MeshKey {
+    Vehicle(RoadVehicleID),
+    Wagon(RollingStockID),
+    Pedestrian,
+}
+
+fn outlined(sim: &Simulation, e: AnyEntity) -> Option<Outlined> {
+    let w = sim.world();
+    Some(match e {
+        AnyEntity::HumanID(id) => match w.get(id)?.location {
+            Location::Outside => Outlined::Entity(e),
+            Location::Vehicle(v) => Outlined::Entity(AnyEntity::VehicleID(v)),
+            Location::Train(t) => Outlined::Entity(AnyEntity::TrainID(t)),
+            Location::Building(b) => Outlined::Building(b),
+        },
+        AnyEntity::CompanyID(id) => Outlined::Building(w.get(id)?.comp.building),
+        AnyEntity::FreightStationID(id) => Outlined::Building(w.get(id)?.f.building),
+        AnyEntity::WarehouseID(id) => Outlined::Building(w.get(id)?.w.building),
+        AnyEntity::VehicleID(_) | AnyEntity::TrainID(_) | AnyEntity::WagonID(_) => {
+            Outlined::Entity(e)
+        }
+    })
+}
+
+/// The selected objects, each once
+fn selections(sim: &Simulation, uiw: &UiWorld) -> Vec<(Outlined, SelectionKind)> {
+    let inspected = *uiw.read::<InspectedEntity>();
+    let inspected_b = *uiw.read::<InspectedBuilding>();
+    let followed = uiw.read::<FollowEntity>().target;
+
+    let candidates = [
+        (
+            followed.and_then(|e| outlined(sim, e)),
+            SelectionKind::Followed,
+        ),
+        (
+            inspected.e.and_then(|e| outlined(sim, e)),
+            SelectionKind::Inspected,
+        ),
+        (
+            inspected_b.e.map(Outlined::Building),
+            SelectionKind::Inspected,
+        ),
+        (
+            inspected.hovered.and_then(|e| outlined(sim, e)),
+            SelectionKind::Hovered,
+        ),
+        (
+            inspected_b.hovered.map(Outlined::Building),
+            SelectionKind::Hovered,
+        ),
+    ];
+
+    let mut selections: Vec<(Outlined, SelectionKind)> = vec![];
+    for (obj, kind) in candidates {
+        let Some(obj) = obj else {
+            continue;
+        };
+        if selections.iter().any(|(o, _)| *o == obj) {
+            continue;
+        }
+        selections.push((obj, kind));
+    }
+    selections
+}
+
+/// Outline of the instances of a mesh, built again only when they move
+struct EntityOutline {
+    builder: InstancedMeshBuilder<true>,
+    mesh: Option<InstancedMesh>,
+}
+
+/// Outlines drawn on the previous frame, kept while the objects stay selected
+pub struct SelectionOutlines {
+    entities: FastMap<(MeshKey, SelectionKind), EntityOutline>,
+    buildings: FastMap<(BuildingID, SelectionKind), Option<Arc<dyn Drawable>>>,
+    building_sub: MapSubscriber,
+}
+
+impl SelectionOutlines {
+    pub fn new(sim: &Simulation) -> Self {
+        Self {
+            entities: FastMap::default(),
+            buildings: FastMap::default(),
+            building_sub: sim.map().subscribe(UpdateType::Building),
+        }
+    }
+
+    /// Outlines the selected objects, or draws the circles and boxes where outlines aren't supported
+    pub fn render(
+        &mut self,
+        sim: &Simulation,
+        uiw: &UiWorld,
+        entities: &InstancedRender,
+        map_renderer: &MapRenderer,
+        fctx: &mut FrameContext<'_>,
+    ) {
+        profiling::scope!("selection_outlines");
+        // the buildings are built again when they change
+        if self.building_sub.take_cleared() || self.building_sub.take_updated_chunks().count() > 0 {
+            self.buildings.clear();
+        }
+
+        let selections = selections(sim, uiw);
+        if selections.is_empty() || !fctx.gfx.outlines_supported() {
+            self.entities.clear();
+            self.buildings.clear();
+            if !selections.is_empty() {
+                selection_aura(sim, uiw, &selections);
+            }
+            return;
+        }
+
+        let map = sim.map();
+        let mut instances: FastMap<(MeshKey, SelectionKind), Vec<MeshInstance>> =
+            FastMap::default();
+        let mut buildings: FastMap<(BuildingID, SelectionKind), Option<Arc<dyn Drawable>>> =
+            FastMap::default();
+        for &(obj, kind) in &selections {
+            let e = match obj {
+                Outlined::Entity(e) => e,
+                Outlined::Building(b) => {
+                    let outline = self.buildings.remove(&(b, kind)).unwrap_or_else(|| {
+                        let b = map.buildings().get(b)?;
+                        map_renderer.meshb.building_outline(b, fctx.gfx)
+                    });
+                    if let Some(ref outline) = outline {
+                        fctx.outline(kind.color().into(), outline.clone());
+                    }
+                    buildings.insert((b, kind), outline);
+                    continue;
+                }
+            };
+            for (key, instance) in entity_instances(sim, e) {
+                instances.entry((key, kind)).or_default().push(instance);
+            }
+        }
+        self.buildings = buildings;
+
+        self.entities.retain(|k, _| instances.contains_key(k));
+        for ((key, kind), instances) in instances {
+            let outline = match self.entities.entry((key, kind)) {
+                Entry::Occupied(o) => o.into_mut(),
+                Entry::Vacant(v) => {
+                    let mesh = match key {
+                        MeshKey::Vehicle(id) => entities.road_vehicles.get(&id),
+                        MeshKey::Wagon(id) => entities.rolling_stock.get(&id),
+                        MeshKey::Pedestrian => entities.pedestrians.first(),
+                    };
+                    let Some(mesh) = mesh else {
+                        continue;
+                    };
+                    v.insert(EntityOutline {
+                        builder: InstancedMeshBuilder::new_ref(mesh.mesh()),
+                        mesh: None,
+                    })
+                }
+            };
+            let moved = outline.mesh.is_none()
+                || bytemuck::cast_slice::<MeshInstance, u8>(&outline.builder.instances)
+                    != bytemuck::cast_slice::<MeshInstance, u8>(&instances);
+            if moved {
+                outline.builder.instances = instances;
+                outline.mesh = outline.builder.build(fctx.gfx);
+            }
+            if let Some(ref mesh) = outline.mesh {
+                fctx.outline(kind.color().into(), mesh.clone());
+            }
+        }
+    }
+}
+
+/// The instances drawn for the entity, placed like in [`InstancedRender::render`]
+fn entity_instances(sim: &Simulation, e: AnyEntity) -> Vec<(MeshKey, MeshInstance)> {
+    let w = sim.world();
+    let wagon = |id: WagonID| {
+        let wagon = w.get(id)?;
+        Some((
+            MeshKey::Wagon(wagon.wagon.rolling_stock),
+            MeshInstance {
+                pos: wagon.trans.pos,
+                dir: wagon.trans.dir,
+                tint: LinearColor::WHITE,
+            },
+        ))
+    };
+
+    match e {
+        AnyEntity::VehicleID(id) => w
+            .get(id)
+            .map(|v| {
+                (
+                    MeshKey::Vehicle(v.vehicle.proto().id),
+                    MeshInstance {
+                        pos: v.trans.pos,
+                        dir: v.trans.dir,
+                        tint: v.vehicle.tint.into(),
+                    },
+                )
+            })
+            .into_iter()
+            .collect(),
+        AnyEntity::WagonID(id) => wagon(id).into_iter().collect(),
+        AnyEntity::TrainID(train) => train_wagons(sim, train)
+            .into_iter()
+            .filter_map(wagon)
+            .collect(),
+        AnyEntity::HumanID(id) => w
+            .get(id)
+            .map(|h| {
+                (
+                    MeshKey::Pedestrian,
+                    MeshInstance {
+                        pos: h.trans.pos.up(walk_bob(h.pedestrian.walk_anim)),
+                        dir: h.trans.dir.xy().z0(),
+                        tint: LinearColor::WHITE,
+                    },
+                )
+            })
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn train_wagons(sim: &Simulation, train: TrainID) -> Vec<WagonID> {
+    sim.world()
+        .wagons
+        .iter()
+        .filter(|(_, w)| w.itfollower.leader == train)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Circles around the selected entities and boxes of the selected buildings
+fn selection_aura(sim: &Simulation, uiw: &UiWorld, selections: &[(Outlined, SelectionKind)]) {
+    let map = sim.map();
+    let mut draw = uiw.write::<ImmediateDraw>();
+    for &(obj, kind) in selections {
+        match obj {
+            Outlined::Entity(e) => {
+                let radius = select_radius(e);
+                let Some(pos) = sim.pos_any(e) else {
+                    continue;
+                };
+                if radius > 0.0 {
+                    draw.stroke_circle(pos.up(0.25), radius, (radius * 0.01).max(0.1))
+                        .color(kind.color());
+                }
+            }
+            Outlined::Building(b) => {
+                let Some(b) = map.buildings().get(b) else {
+                    continue;
+                };
+                draw.obb(b.obb, b.height + 0.01).color(kind.color());
+            }
+        }
+    }
+}
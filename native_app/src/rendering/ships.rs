@@ -0,0 +1,95 @@
+use engine::Tesselator;
+use geom::{vec2, Color, Vec2};
+use simulation::economy::{SeaTrade, ShipLeg, SHIP_MAX_SPEED};
+use simulation::Simulation;
+
+/// Length of a cargo ship in meters
+const SHIP_LENGTH: f32 = 40.0;
+/// Width of a cargo ship in meters
+const SHIP_BEAM: f32 = 9.0;
+/// Height of the deck above the water
+const DECK_Z: f32 = 0.6;
+
+/// Length of the wake at full speed
+const WAKE_LENGTH: f32 = 120.0;
+/// Half angle of the V spreading behind a ship, the same for all ships in deep water
+const WAKE_HALF_ANGLE: f32 = 0.34; // ~19.5°
+/// Number of pieces the wake is cut into to fade away
+const WAKE_SEGMENTS: u32 = 8;
+
+/// Outline of the hull pointing toward +x, centered on the middle of the ship
+const HULL: [Vec2; 5] = [
+    vec2(-SHIP_LENGTH * 0.5, -SHIP_BEAM * 0.5),
+    vec2(SHIP_LENGTH * 0.3, -SHIP_BEAM * 0.5),
+    vec2(SHIP_LENGTH * 0.5, 0.0),
+    vec2(SHIP_LENGTH * 0.3, SHIP_BEAM * 0.5),
+    vec2(-SHIP_LENGTH * 0.5, SHIP_BEAM * 0.5),
+];
+
+/// Draws the cargo ships and the wakes they leave behind them on the water
+pub fn draw_ships(tess: &mut Tesselator, sim: &Simulation) {
+    let sea = sim.read::<SeaTrade>();
+    if sea.ships.is_empty() {
+        return;
+    }
+    profiling::scope!("ships");
+
+    for ship in &sea.ships {
+        let dir = ship.dir;
+        let side = dir.perpendicular();
+        let stern = ship.pos - dir * SHIP_LENGTH * 0.5;
+
+        let strength = ship.speed / SHIP_MAX_SPEED;
+        if strength > 0.02 {
+            let length = WAKE_LENGTH * strength;
+            let (sin, cos) = WAKE_HALF_ANGLE.sin_cos();
+            for i in 0..WAKE_SEGMENTS {
+                let t0 = i as f32 / WAKE_SEGMENTS as f32;
+                let t1 = (i + 1) as f32 / WAKE_SEGMENTS as f32;
+                let alpha = 0.6 * strength * (1.0 - t0);
+
+                // the churned water right behind the propeller
+                tess.set_color(Color::WHITE.a(alpha * 0.5));
+                tess.draw_stroke(
+                    (stern - dir * length * 0.5 * t0).z(DECK_Z * 0.5),
+                    (stern - dir * length * 0.5 * t1).z(DECK_Z * 0.5),
+                    SHIP_BEAM * (0.8 + t0),
+                );
+
+                // the two arms of the V
+                tess.set_color(Color::WHITE.a(alpha));
+                for s in [-1.0, 1.0] {
+                    let arm = -dir * cos + side * (s * sin);
+                    tess.draw_stroke(
+                        (stern + arm * length * t0).z(DECK_Z * 0.5),
+                        (stern + arm * length * t1).z(DECK_Z * 0.5),
+                        1.0 + 2.0 * t0,
+                    );
+                }
+            }
+        }
+
+        let hull: Vec<Vec2> = HULL
+            .iter()
+            .map(|p| ship.pos + dir * p.x + side * p.y)
+            .collect();
+        tess.set_color(Color::new(0.25, 0.27, 0.32, 1.0));
+        tess.draw_filled_polygon(&hull, DECK_Z);
+
+        // containers are on deck while the goods are carried
+        let loaded = match ship.leg {
+            ShipLeg::Arriving => ship.import,
+            ShipLeg::Docked => true,
+            ShipLeg::Leaving => !ship.import,
+        };
+        if loaded {
+            tess.set_color(Color::new(0.75, 0.3, 0.2, 1.0));
+            tess.draw_rect_cos_sin(
+                (ship.pos - dir * SHIP_LENGTH * 0.05).z(DECK_Z + 0.5),
+                SHIP_LENGTH * 0.6,
+                SHIP_BEAM * 0.7,
+                dir,
+            );
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use geom::{vec3, LinearColor, Vec2, Vec3};
+use prototypes::GameTime;
+
+/// Sunlight color at noon
+const NOON_COLOR: (f32, f32, f32) = (1.0, 1.0, 1.0);
+/// Sunlight color when the sun touches the horizon
+const HORIZON_COLOR: (f32, f32, f32) = (1.0, 0.55, 0.3);
+/// Height of the sun under which the light starts turning orange
+const GOLDEN_HOUR_HEIGHT: f32 = 0.35;
+/// Lowest height of the sun used for the shadows
+const MIN_SHADOW_HEIGHT: f32 = 0.05;
+
+/// Direction toward the sun at the given second of the day.
+/// It rises at 6h in the east, is the highest at 14h and sets at 22h.
+pub fn sun_direction(daysec: f32) -> Vec3 {
+    let t = std::f32::consts::TAU * (daysec - 8.0 * GameTime::HOUR as f32) / GameTime::DAY as f32;
+    vec3(t.cos(), t.sin() * 0.5, t.sin() + 0.5).normalize()
+}
+
+/// Direction of the light casting the shadows.
+/// It stays above the horizon so that the shadow cascades are still well defined at dawn and dusk,
+/// the sunlight is off at night anyway.
+pub fn shadow_direction(sun: Vec3) -> Vec3 {
+    if sun.z >= MIN_SHADOW_HEIGHT {
+        return sun;
+    }
+    let horizontal = sun.xy().try_normalize().unwrap_or(Vec2::X);
+    let c = (1.0 - MIN_SHADOW_HEIGHT * MIN_SHADOW_HEIGHT).sqrt();
+    (horizontal * c).z(MIN_SHADOW_HEIGHT)
+}
+
+/// Color and intensity of the sunlight, orange around dawn and dusk and off at night
+pub fn sun_color(sun: Vec3) -> LinearColor {
+    let height = sun.z.max(0.0);
+    let warmth = 1.0 - smoothstep(0.0, GOLDEN_HOUR_HEIGHT, height);
+    let mix = |noon: f32, horizon: f32| noon + (horizon - noon) * warmth;
+    let intensity = 4.0 * height.sqrt().sqrt();
+    LinearColor::new(
+        intensity * mix(NOON_COLOR.0, HORIZON_COLOR.0),
+        intensity * mix(NOON_COLOR.1, HORIZON_COLOR.1),
+        intensity * mix(NOON_COLOR.2, HORIZON_COLOR.2),
+        1.0,
+    )
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
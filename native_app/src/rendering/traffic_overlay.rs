@@ -0,0 +1,36 @@
+use engine::Tesselator;
+use geom::Color;
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Whether the traffic congestion overlay is shown, toggled from the toolbox
+#[derive(Default)]
+pub struct TrafficOverlay {
+    pub enabled: bool,
+}
+
+/// From green when traffic flows freely to red when it is stopped
+pub fn congestion_color(congestion: f32) -> Color {
+    let t = congestion.clamp(0.0, 1.0);
+    Color::hsv(120.0 * (1.0 - t), 0.9, 0.9, 0.8)
+}
+
+/// Draws the vehicle lanes colored by their measured congestion
+pub fn draw_traffic_overlay(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld) {
+    if !uiw.read::<TrafficOverlay>().enabled {
+        return;
+    }
+    profiling::scope!("traffic_overlay");
+
+    let map = sim.map();
+
+    for lane in map.lanes().values() {
+        if !lane.kind.vehicles() {
+            continue;
+        }
+        let points: Vec<_> = lane.points.iter().map(|p| p.up(0.5)).collect();
+        tess.set_color(congestion_color(map.lane_congestion(lane.id)));
+        tess.draw_polyline(&points, 1.5, false);
+    }
+}
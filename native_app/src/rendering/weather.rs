@@ -0,0 +1,85 @@
+use common::rand::rand2;
+use geom::{vec3, Camera, Vec2, Vec3};
+use prototypes::GameTime;
+use simulation::weather::WeatherKind;
+
+/// Time for a dry road to look soaked under the rain, in game seconds
+const WETTING_TIME: f32 = 20.0 * 60.0;
+/// Time for a soaked road to dry once the rain stops, in game seconds
+const DRYING_TIME: f32 = 3.0 * 3600.0;
+
+/// Number of drops or flakes falling around the camera
+const N_PARTICLES: u32 = 1200;
+/// Side of the box in front of the camera where the particles fall
+const BOX_SIZE: f32 = 60.0;
+
+/// How wet the ground looks, in [0; 1].
+/// It follows the weather slowly, so that the roads stay dark for a while after the rain.
+#[derive(Default)]
+pub struct Wetness {
+    value: f32,
+    last: Option<f64>,
+}
+
+impl Wetness {
+    pub fn update(&mut self, weather: WeatherKind, time: &GameTime) -> f32 {
+        let target = match weather {
+            WeatherKind::Rain => 1.0,
+            WeatherKind::Snow => 0.5,
+            WeatherKind::Clear | WeatherKind::Cloudy => 0.0,
+        };
+        let elapsed = self.last.map(|last| (time.timestamp - last) as f32);
+        self.last = Some(time.timestamp);
+
+        match elapsed {
+            // first frame or the time went backward after loading a save
+            None => self.value = target,
+            Some(dt) if dt < 0.0 => self.value = target,
+            Some(dt) if target > self.value => {
+                self.value = (self.value + dt / WETTING_TIME).min(target)
+            }
+            Some(dt) => self.value = (self.value - dt / DRYING_TIME).max(target),
+        }
+        self.value
+    }
+}
+
+/// Screen segments of the rain streaks or snowflakes seen by the camera.
+/// The particles are anchored in the world so that they don't follow the camera around.
+pub fn precipitation(camera: &Camera, kind: WeatherKind, time: f32) -> Vec<(Vec2, Vec2)> {
+    let (speed, length) = match kind {
+        WeatherKind::Rain => (9.0, 0.6),
+        WeatherKind::Snow => (1.2, 0.05),
+        WeatherKind::Clear | WeatherKind::Cloudy => return Vec::new(),
+    };
+
+    let eye = camera.eye();
+    let forward = -camera.dir();
+    let min = eye + forward * BOX_SIZE * 0.5 - Vec3::splat(BOX_SIZE * 0.5);
+
+    let mut segments = Vec::with_capacity(N_PARTICLES as usize);
+    for i in 0..N_PARTICLES {
+        let seed = i as f32;
+        let start = vec3(rand2(seed, 0.0), rand2(seed, 1.0), rand2(seed, 2.0)) * BOX_SIZE;
+        let mut fall = vec3(0.0, 0.0, -speed * time);
+        if kind == WeatherKind::Snow {
+            let phase = rand2(seed, 3.0) * std::f32::consts::TAU;
+            fall.x += (time * 0.7 + phase).sin() * 0.8;
+            fall.y += (time * 0.5 + phase).cos() * 0.8;
+        }
+        let local = start + fall - min;
+        let p = min
+            + vec3(
+                local.x.rem_euclid(BOX_SIZE),
+                local.y.rem_euclid(BOX_SIZE),
+                local.z.rem_euclid(BOX_SIZE),
+            );
+
+        let tail = p + Vec3::Z * length;
+        if (p - eye).dot(forward) < 1.0 || (tail - eye).dot(forward) < 1.0 {
+            continue;
+        }
+        segments.push((camera.project(p).0, camera.project(tail).0));
+    }
+    segments
+}
@@ -0,0 +1,74 @@
+use std::sync::OnceLock;
+
+use engine::{Context, FrameContext};
+use goryak::{
+    button_primary, error, mincolumn, on_secondary_container, textc, VertScroll, VertScrollSize,
+    Window,
+};
+use yakui::widgets::Pad;
+
+/// Problems of the prototypes, set before starting the engine
+static PROBLEMS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Shows the problems found when loading the prototypes instead of the game, all at once so that
+/// modders don't have to fix them one restart at a time
+pub fn start(problems: Vec<String>) {
+    for problem in &problems {
+        log::error!("{}", problem);
+    }
+    let _ = PROBLEMS.set(problems);
+    engine::framework::start::<StartupError>();
+}
+
+pub struct StartupError;
+
+impl engine::framework::State for StartupError {
+    fn new(ctx: &mut Context) -> Self {
+        goryak::set_blur_texture(ctx.yakui.blur_bg_texture);
+        Self
+    }
+
+    fn update(&mut self, _: &mut Context) {}
+
+    fn render(&mut self, _: &mut FrameContext) {}
+
+    fn render_yakui(&mut self) {
+        let problems = PROBLEMS.get().map(Vec::as_slice).unwrap_or_default();
+
+        let mut opened = true;
+        Window {
+            title: "Could not load the prototypes".into(),
+            pad: Pad::all(15.0),
+            radius: 10.0,
+            opened: &mut opened,
+            child_spacing: 5.0,
+        }
+        .show(|| {
+            textc(
+                on_secondary_container(),
+                format!(
+                    "{} problems found in base_mod and the mods:",
+                    problems.len()
+                ),
+            );
+            VertScroll {
+                size: VertScrollSize::Fixed(400.0),
+                align_bot: false,
+            }
+            .show(|| {
+                mincolumn(2.0, || {
+                    for problem in problems {
+                        textc(error(), problem.clone());
+                    }
+                });
+            });
+            if button_primary("Quit").show().clicked {
+                std::process::exit(1);
+            }
+        });
+
+        if !opened {
+            std::process::exit(1);
+        }
+    }
+}
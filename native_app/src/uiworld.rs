@@ -1,4 +1,4 @@
-use crate::init::{INIT_FUNCS, SAVELOAD_FUNCS};
+use crate::init::{INIT_FUNCS, SAVELOAD_FUNCS, WORLD_RESET_FUNCS, WORLD_SAVELOAD_FUNCS};
 use crate::newgui::TimeAlways;
 use simulation::utils::resources::{RefMutSingle, RefSingle, ResourcesSingleThread};
 use simulation::world_command::{WorldCommand, WorldCommands};
@@ -17,11 +17,30 @@ pub struct UiWorld {
 pub struct SaveLoadState {
     pub please_load: Option<SimulationReplayLoader>,
     pub please_load_sim: Option<Simulation>,
+    /// Save the sim to load was read from, to load the state of the interface written with it
+    pub please_load_save: Option<String>,
+    /// The sim to load is a map to edit in the map editor
+    pub please_edit_map: bool,
+    /// The current sim is a map being edited, it isn't saved as a game
+    pub editing_map: bool,
     pub render_reset: bool,
     pub please_save: bool,
+    /// Name of the next save, the main save if None
+    pub save_name: Option<String>,
+    /// Named save to write to, along with a thumbnail
+    pub save_as: Option<String>,
     pub saving_status: Arc<AtomicBool>,
 }
 
+/// The named save the current world was loaded from or last saved to
+#[derive(Default)]
+pub struct CurrentSave {
+    pub name: Option<String>,
+    pub city_name: String,
+    /// Real seconds spent playing the city, including the previous sessions
+    pub play_time: f64,
+}
+
 #[allow(dead_code)]
 impl UiWorld {
     pub fn init() -> UiWorld {
@@ -83,6 +102,15 @@ impl UiWorld {
         }
     }
 
+    /// Clears the state referring to the entities of the previous world
+    pub fn reset_world_resources(&mut self) {
+        unsafe {
+            for s in &*addr_of!(WORLD_RESET_FUNCS) {
+                (s.f)(self);
+            }
+        }
+    }
+
     pub fn save_to_disk(&self) {
         unsafe {
             for l in &*addr_of!(SAVELOAD_FUNCS) {
@@ -90,6 +118,24 @@ impl UiWorld {
             }
         }
     }
+
+    /// Writes the state belonging to the game next to its save
+    pub fn save_world_resources(&self, save_name: &str) {
+        unsafe {
+            for l in &*addr_of!(WORLD_SAVELOAD_FUNCS) {
+                (l.save)(self, save_name);
+            }
+        }
+    }
+
+    /// Reads the state belonging to the game written next to its save
+    pub fn load_world_resources(&mut self, save_name: &str) {
+        unsafe {
+            for l in &*addr_of!(WORLD_SAVELOAD_FUNCS) {
+                (l.load)(self, save_name);
+            }
+        }
+    }
 }
 
 #[derive(Default)]
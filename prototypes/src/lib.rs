@@ -3,18 +3,22 @@ use geom::Vec2;
 use mlua::{FromLua, Table};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 mod macros;
 
 mod load;
+mod mods;
 mod prototypes;
 mod tests;
 mod types;
 mod validation;
 
 pub use load::*;
+pub use mods::*;
 pub use prototypes::*;
 pub use types::*;
+pub use validation::ValidationError;
 
 /// A prototype is a collection of data that is dynamically loaded with Lua and defines a type of object
 pub trait Prototype: 'static + Sized {
@@ -100,21 +104,34 @@ impl ConcretePrototype for NoParent {
     fn insert_parents(&self, _prototypes: &mut Prototypes) {}
 }
 
-static mut PROTOTYPES: Option<&'static Prototypes> = None;
+/// Replaced as a whole when reloading, the previous registries are leaked as references to them might remain
+static PROTOTYPES: AtomicPtr<Prototypes> = AtomicPtr::new(std::ptr::null_mut());
 
 #[inline]
 pub fn prototypes() -> &'static Prototypes {
+    let p = PROTOTYPES.load(Ordering::Acquire);
     #[cfg(debug_assertions)]
     {
-        assert!(unsafe { PROTOTYPES.is_some() });
+        assert!(!p.is_null());
     }
 
     // Safety: Please just don't use prototypes before they were loaded... We can allow this footgun
-    unsafe { PROTOTYPES.unwrap_unchecked() }
+    unsafe { &*p }
 }
 
 pub fn try_prototypes() -> Option<&'static Prototypes> {
-    unsafe { PROTOTYPES }
+    unsafe { PROTOTYPES.load(Ordering::Acquire).as_ref() }
+}
+
+/// Makes the registry the current one, the ids of the previous one stay valid only if
+/// the new one still defines them
+pub fn swap_prototypes(p: Box<Prototypes>) {
+    PROTOTYPES.store(Box::leak(p), Ordering::Release);
+}
+
+/// The mods found when loading the prototypes
+pub fn loaded_mods() -> &'static ModsReport {
+    &prototypes().mods
 }
 
 #[inline]
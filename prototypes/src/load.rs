@@ -1,8 +1,12 @@
+use crate::mods::{depends_on, discover_mods, sort_mods, ModInfo, ModProblem, ModProfile};
 use crate::validation::ValidationError;
-use crate::{validation, Prototypes, PROTOTYPES};
+use crate::{loaded_mods, swap_prototypes, validation, Prototypes};
 use common::error::MultiError;
 use mlua::{Lua, Table};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use thiserror::Error;
 
 pub fn test_prototypes(lua: &str) {
@@ -11,23 +15,143 @@ pub fn test_prototypes(lua: &str) {
     unsafe { load_prototypes_str(l, lua).unwrap() };
 }
 
-/// Loads the prototypes from the data.lua file
+/// Problems found in the prototypes defined by the lua code, without making them current.
+/// Lets mods check their prototypes in their tests, the files they use are checked if
+/// `assets_base` is the game folder.
+pub fn validate_prototypes_str(lua: &str, assets_base: Option<&str>) -> Vec<ValidationError> {
+    let l = Lua::new();
+    let parsed = l
+        .load(include_str!("prototype_init.lua"))
+        .exec()
+        .and_then(|_| l.load(lua).exec())
+        .map_err(PrototypeLoadError::from)
+        .and_then(|_| parse_prototypes(&l, &[], &[], assets_base));
+    match parsed {
+        Ok(_) => vec![],
+        Err(PrototypeLoadError::ValidationErrors(errors)) => errors.0,
+        Err(e) => panic!("could not parse the prototypes: {}", e),
+    }
+}
+
+/// Loads the prototypes from the data.lua file of the base mod, then from the enabled mods
 /// # Safety
 /// This function is not thread safe, and should only be called once at the start of the program.
 pub unsafe fn load_prototypes(base: &str) -> Result<(), PrototypeLoadError> {
     log::info!("loading prototypes from {}", base);
+    swap_prototypes(load_candidate(base)?);
+    Ok(())
+}
+
+/// Loads the prototypes into a new registry validated but not made current, with the mods enabled
+/// in the profile
+pub fn load_candidate(base: &str) -> Result<Box<Prototypes>, PrototypeLoadError> {
+    let profile = ModProfile::load();
+    load_candidate_with(
+        base,
+        |name| profile.is_enabled(name),
+        profile.disabled.clone(),
+    )
+}
+
+/// Loads the prototypes into a new registry to reload them while running, with the mods loaded at
+/// the start. The changes to the profile only apply at the next start.
+pub fn reload_candidate(base: &str) -> Result<Box<Prototypes>, PrototypeLoadError> {
+    let started = loaded_mods();
+    load_candidate_with(
+        base,
+        |name| started.active.iter().any(|m| m.name == name),
+        started.disabled.clone(),
+    )
+}
+
+fn load_candidate_with(
+    base: &str,
+    enabled: impl Fn(&str) -> bool,
+    disabled: BTreeSet<String>,
+) -> Result<Box<Prototypes>, PrototypeLoadError> {
+    let (available, mut problems) = discover_mods(Path::new(&format!("{}mods", base)));
+    let manifests = available.iter().map(|m| m.manifest.clone()).collect();
+    let (mods, sort_problems) = sort_mods(
+        available
+            .into_iter()
+            .filter(|m| enabled(&m.manifest.name))
+            .collect(),
+    );
+    problems.extend(sort_problems);
+
+    let mut p = load_prototypes_with_mods(base, &mods)?;
+    p.mods.available = manifests;
+    p.mods.disabled = disabled;
+    problems.append(&mut p.mods.problems);
+    p.mods.problems = problems;
+    for problem in &p.mods.problems {
+        log::warn!("mod problem: {}", problem);
+    }
+
+    Ok(p)
+}
+
+/// Modification times of the files of the base mod and of the mods, to reload the prototypes
+/// when one changes
+#[derive(Default)]
+pub struct SourcesWatcher {
+    mtimes: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl SourcesWatcher {
+    /// Whether a file was added, removed or modified since the last call. The first call only records them.
+    pub fn changed(&mut self, base: &str) -> bool {
+        let mut mtimes = BTreeMap::new();
+        for dir in ["base_mod", "mods"] {
+            let dir = Path::new(base).join(dir);
+            if !dir.is_dir() {
+                continue;
+            }
+            for path in common::saveload::walkdir(&dir) {
+                if let Ok(t) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+                    mtimes.insert(path, t);
+                }
+            }
+        }
+        let changed = !self.mtimes.is_empty() && mtimes != self.mtimes;
+        self.mtimes = mtimes;
+        changed
+    }
+}
+
+/// Runs the data.lua of the base mod then the one of each mod in order, so that the mods can
+/// replace the prototypes of the mods loaded before them
+pub fn load_prototypes_with_mods(
+    base: &str,
+    mods: &[ModInfo],
+) -> Result<Box<Prototypes>, PrototypeLoadError> {
     let l = Lua::new();
+    l.load(include_str!("prototype_init.lua")).exec()?;
 
-    let base = base.to_string();
+    let package = l.globals().get::<_, Table>("package")?;
+    let data_table = l.globals().get::<_, Table>("data")?;
 
-    l.globals()
-        .get::<_, Table>("package")?
-        .set("path", base.clone() + "base_mod/?.lua")?;
+    let base_dir = base.to_string() + "base_mod/";
+    package.set("path", base_dir.clone() + "?.lua")?;
+    let main = common::saveload::load_string(base_dir + "data.lua")?;
+    l.load(main.as_str()).exec()?;
+
+    // index of the first prototype of each mod in the data table
+    let mut starts = vec![];
+    for m in mods {
+        log::info!("loading mod {}", m.manifest.tag());
+        starts.push(data_table.raw_len());
+        package.set("path", m.path.clone() + "?.lua")?;
+        let main = common::saveload::load_string(m.path.clone() + "data.lua")?;
+        l.load(main.as_str())
+            .set_name(m.manifest.name.as_str())
+            .exec()
+            .map_err(|e| PrototypeLoadError::ModError(m.manifest.name.clone(), e))?;
+    }
 
-    load_prototypes_str(
-        l,
-        &common::saveload::load_string(base + "base_mod/data.lua")?,
-    )
+    let mut p = parse_prototypes(&l, mods, &starts, Some(base))?;
+    p.mods.active = mods.iter().map(|m| m.manifest.clone()).collect();
+    Ok(p)
 }
 
 unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadError> {
@@ -35,34 +159,61 @@ unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadErr
 
     l.load(main).exec()?;
 
+    swap_prototypes(parse_prototypes(&l, &[], &[], None)?);
+
+    Ok(())
+}
+
+/// Parses the data table, `starts` being the index of the first prototype of each mod.
+/// The files used by the prototypes are checked if they were loaded from the game folder `base`.
+fn parse_prototypes(
+    l: &Lua,
+    mods: &[ModInfo],
+    starts: &[usize],
+    base: Option<&str>,
+) -> Result<Box<Prototypes>, PrototypeLoadError> {
     let mut p = Box::<Prototypes>::default();
 
     let mut errors = Vec::new();
+    // mod defining each prototype, to report the mods replacing the same prototype
+    let mut defined_by: BTreeMap<(String, String), usize> = BTreeMap::new();
 
     let data_table = l.globals().get::<_, Table>("data")?;
 
-    let _ = data_table.for_each(|_: String, t: Table| {
-        let r = p.parse_prototype(t);
-        if let Err(e) = r {
+    for (i, t) in data_table.sequence_values::<Table>().enumerate() {
+        let t = t?;
+        if let Some(m) = starts.iter().rposition(|&start| i >= start) {
+            let key = (
+                t.get::<_, String>("type").unwrap_or_default(),
+                t.get::<_, String>("name").unwrap_or_default(),
+            );
+            if let Some(prev) = defined_by.insert(key.clone(), m) {
+                let (first, second) = (&mods[prev].manifest.name, &mods[m].manifest.name);
+                if prev != m && !depends_on(mods, second, first) {
+                    p.mods.problems.push(ModProblem::Conflict {
+                        prototype: format!("{} {}", key.0, key.1),
+                        first: first.clone(),
+                        second: second.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Err(e) = p.parse_prototype(t) {
             errors.push(e);
         }
-        Ok(())
-    });
+    }
 
     if !errors.is_empty() {
         return Err(PrototypeLoadError::MultiError(MultiError(errors)));
     }
 
-    validation::validate(&p)?;
+    validation::validate(&p, base)?;
 
     p.compute_orderings();
     p.print_stats();
 
-    unsafe {
-        PROTOTYPES = Some(Box::leak(p));
-    }
-
-    Ok(())
+    Ok(p)
 }
 
 #[derive(Error, Debug)]
@@ -71,6 +222,8 @@ pub enum PrototypeLoadError {
     LoadingDataLua(#[from] io::Error),
     #[error("lua error: {0}")]
     LuaError(#[from] mlua::Error),
+    #[error("lua error in mod {0}: {1}")]
+    ModError(String, mlua::Error),
     #[error("lua error for {0} {1}: {2}")]
     PrototypeLuaError(String, String, mlua::Error),
     #[error("multiple errors: {0}")]
@@ -78,3 +231,18 @@ pub enum PrototypeLoadError {
     #[error("validation errors: {0}")]
     ValidationErrors(#[from] MultiError<ValidationError>),
 }
+
+impl PrototypeLoadError {
+    /// Every problem found, one per line, to show them all at once
+    pub fn problems(&self) -> Vec<String> {
+        match self {
+            PrototypeLoadError::MultiError(errors) => {
+                errors.0.iter().flat_map(|e| e.problems()).collect()
+            }
+            PrototypeLoadError::ValidationErrors(errors) => {
+                errors.0.iter().map(|e| e.to_string()).collect()
+            }
+            e => vec![e.to_string()],
+        }
+    }
+}
@@ -38,6 +38,7 @@ macro_rules! gen_prototypes {
                 pub(crate) $name: common::TransparentMap<$id, $t>,
             )+
             pub(crate) orderings: Orderings,
+            pub(crate) mods: $crate::ModsReport,
         }
 
         $(
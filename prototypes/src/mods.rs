@@ -0,0 +1,285 @@
+//! Mods are directories of the mods folder extending the prototypes of the base mod.
+//! Each one has a `mod.json` manifest and a `data.lua` calling `data:extend` like the base mod,
+//! prototypes with the name of an existing one replace it.
+//! ```json
+//! { "name": "more-cereals", "version": "1.0", "dependencies": ["farms"] }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use common::saveload::{Encoder, JSONPretty, JSON};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: String,
+    /// Names of the mods loaded before this one
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl ModManifest {
+    /// How the mod is recorded in the saves
+    pub fn tag(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModInfo {
+    pub manifest: ModManifest,
+    /// Directory of the mod, ending with a slash
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModProblem {
+    InvalidManifest {
+        path: String,
+        error: String,
+    },
+    /// Two directories declare the same mod name, only the first one is kept
+    Duplicate(String),
+    /// The mod is not loaded since a dependency is missing or disabled
+    MissingDependency {
+        name: String,
+        dependency: String,
+    },
+    /// The mods depend on each other, none of them is loaded
+    Cycle(Vec<String>),
+    /// Two mods unrelated by their dependencies define the same prototype, the last one loaded wins
+    Conflict {
+        prototype: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl Display for ModProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModProblem::InvalidManifest { path, error } => {
+                write!(f, "invalid manifest in {}: {}", path, error)
+            }
+            ModProblem::Duplicate(name) => write!(f, "several mods are named {}", name),
+            ModProblem::MissingDependency { name, dependency } => {
+                write!(
+                    f,
+                    "{} needs {} which is missing or disabled",
+                    name, dependency
+                )
+            }
+            ModProblem::Cycle(names) => {
+                write!(f, "dependency cycle between {}", names.join(", "))
+            }
+            ModProblem::Conflict {
+                prototype,
+                first,
+                second,
+            } => write!(f, "{} is defined by {} and {}", prototype, first, second),
+        }
+    }
+}
+
+/// The mods found at startup, kept with the prototypes for the mod screen and the saves
+#[derive(Debug, Default)]
+pub struct ModsReport {
+    /// Every mod of the mods folder, enabled or not, by name
+    pub available: Vec<ModManifest>,
+    /// The mods loaded, in load order
+    pub active: Vec<ModManifest>,
+    /// The mods disabled in the profile when loading
+    pub disabled: BTreeSet<String>,
+    pub problems: Vec<ModProblem>,
+}
+
+/// Mods disabled by the player, kept in its own file so that it doesn't depend on the saves.
+/// Changes apply at the next start since the prototypes are loaded once.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ModProfile {
+    pub disabled: BTreeSet<String>,
+}
+
+impl ModProfile {
+    const FILE: &'static str = "mod_profile";
+
+    pub fn load() -> Self {
+        JSON::load(Self::FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        JSONPretty::save_silent(self, Self::FILE);
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name)
+    }
+}
+
+/// Reads the manifests of the subdirectories of `dir`, sorted by name. A missing folder has no mods.
+pub fn discover_mods(dir: &Path) -> (Vec<ModInfo>, Vec<ModProblem>) {
+    let mut mods: Vec<ModInfo> = vec![];
+    let mut problems = vec![];
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (mods, problems);
+    };
+    let mut dirs: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+
+    for path in dirs {
+        let path_str = format!("{}/", path.to_string_lossy());
+        let manifest = common::saveload::load_raw(path.join("mod.json"))
+            .and_then(|bytes| JSON::decode::<ModManifest>(&bytes));
+        match manifest {
+            Ok(manifest) if mods.iter().any(|m| m.manifest.name == manifest.name) => {
+                problems.push(ModProblem::Duplicate(manifest.name));
+            }
+            Ok(manifest) => mods.push(ModInfo {
+                manifest,
+                path: path_str,
+            }),
+            Err(e) => problems.push(ModProblem::InvalidManifest {
+                path: path_str,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    (mods, problems)
+}
+
+/// Orders the mods so that each one comes after its dependencies, ties are broken by name.
+/// Mods with a missing dependency or in a dependency cycle are left out.
+pub fn sort_mods(mods: Vec<ModInfo>) -> (Vec<ModInfo>, Vec<ModProblem>) {
+    let mut problems = vec![];
+    let mut remaining: BTreeMap<String, ModInfo> = mods
+        .into_iter()
+        .map(|m| (m.manifest.name.clone(), m))
+        .collect();
+
+    // removing a mod can make the ones depending on it miss a dependency
+    loop {
+        let missing = remaining.values().find_map(|m| {
+            let dep = m
+                .manifest
+                .dependencies
+                .iter()
+                .find(|d| !remaining.contains_key(*d))?;
+            Some((m.manifest.name.clone(), dep.clone()))
+        });
+        let Some((name, dependency)) = missing else {
+            break;
+        };
+        remaining.remove(&name);
+        problems.push(ModProblem::MissingDependency { name, dependency });
+    }
+
+    let mut sorted: Vec<ModInfo> = vec![];
+    loop {
+        let ready = remaining.values().find(|m| {
+            m.manifest
+                .dependencies
+                .iter()
+                .all(|d| sorted.iter().any(|s| &s.manifest.name == d))
+        });
+        let Some(name) = ready.map(|m| m.manifest.name.clone()) else {
+            break;
+        };
+        sorted.extend(remaining.remove(&name));
+    }
+
+    if !remaining.is_empty() {
+        problems.push(ModProblem::Cycle(remaining.into_keys().collect()));
+    }
+
+    (sorted, problems)
+}
+
+/// Whether `name` depends on `other`, directly or through other mods
+pub(crate) fn depends_on(mods: &[ModInfo], name: &str, other: &str) -> bool {
+    let mut stack = vec![name];
+    let mut seen = BTreeSet::new();
+    while let Some(cur) = stack.pop() {
+        if !seen.insert(cur) {
+            continue;
+        }
+        let Some(m) = mods.iter().find(|m| m.manifest.name == cur) else {
+            continue;
+        };
+        for dep in &m.manifest.dependencies {
+            if dep == other {
+                return true;
+            }
+            stack.push(dep.as_str());
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, deps: &[&str]) -> ModInfo {
+        ModInfo {
+            manifest: ModManifest {
+                name: name.to_string(),
+                version: "1.0".to_string(),
+                dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            },
+            path: format!("mods/{}/", name),
+        }
+    }
+
+    fn names(mods: &[ModInfo]) -> Vec<&str> {
+        mods.iter().map(|m| m.manifest.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_mods() {
+        let (sorted, problems) = sort_mods(vec![
+            info("c", &["b"]),
+            info("a", &[]),
+            info("b", &["a"]),
+            info("d", &[]),
+        ]);
+        assert_eq!(names(&sorted), ["a", "b", "c", "d"]);
+        assert!(problems.is_empty());
+        assert!(depends_on(&sorted, "c", "a"));
+        assert!(!depends_on(&sorted, "a", "c"));
+    }
+
+    #[test]
+    fn test_sort_mods_problems() {
+        let (sorted, problems) = sort_mods(vec![
+            info("a", &[]),
+            info("b", &["missing"]),
+            info("c", &["b"]),
+            info("x", &["y"]),
+            info("y", &["x"]),
+        ]);
+        assert_eq!(names(&sorted), ["a"]);
+        assert_eq!(
+            problems,
+            [
+                ModProblem::MissingDependency {
+                    name: "b".into(),
+                    dependency: "missing".into()
+                },
+                ModProblem::MissingDependency {
+                    name: "c".into(),
+                    dependency: "b".into()
+                },
+                ModProblem::Cycle(vec!["x".into(), "y".into()]),
+            ]
+        );
+    }
+}
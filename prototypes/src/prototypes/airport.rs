@@ -0,0 +1,56 @@
+use crate::{get_lua_opt, BuildingPrototype, Prototype};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// AirportPrototype is a large building with a runway where planes bring tourists
+/// and business travelers from outside the city.
+/// The runway goes along the longest side of the building.
+#[derive(Clone, Debug)]
+pub struct AirportPrototype {
+    pub base: BuildingPrototype,
+    pub id: AirportPrototypeID,
+    /// Flights landing each day once the city is large
+    pub flights_per_day: f32,
+    /// Passengers on a full plane
+    pub seats: u32,
+    /// Share of the passengers coming for business, the others are tourists
+    pub business_share: f32,
+    /// Noise of the planes at the airport, a busy road makes about 4
+    pub noise: f32,
+}
+
+impl Prototype for AirportPrototype {
+    type Parent = BuildingPrototype;
+    type ID = AirportPrototypeID;
+    const NAME: &'static str = "airport";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = BuildingPrototype::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::from(&base.name),
+            base,
+            flights_per_day: get_lua_opt(table, "flights_per_day")?.unwrap_or(8.0),
+            seats: get_lua_opt(table, "seats")?.unwrap_or(60),
+            business_share: get_lua_opt(table, "business_share")?.unwrap_or(0.3),
+            noise: get_lua_opt(table, "noise")?.unwrap_or(12.0),
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for AirportPrototype {
+    type Target = BuildingPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    get_lua, get_v2, Money, NoParent, Power, Prototype, PrototypeBase, RenderAsset, Size2D,
+    get_lua, get_lua_opt, get_v2, Energy, Money, NoParent, Power, Prototype, PrototypeBase,
+    RenderAsset, Size2D,
 };
 use egui_inspect::debug_inspect_impl;
 use geom::Vec2;
@@ -22,6 +23,27 @@ pub enum BuildingGen {
 }
 debug_inspect_impl!(BuildingGen);
 
+/// Which buildings lose power first when their network is overloaded
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PowerPriority {
+    /// Shed first, for buildings that are nice to have
+    Low,
+    #[default]
+    Medium,
+    /// Shed last, for homes
+    High,
+}
+debug_inspect_impl!(PowerPriority);
+
+impl PowerPriority {
+    /// All priorities, in the order they are shed
+    pub const SHED_ORDER: [PowerPriority; 3] = [
+        PowerPriority::Low,
+        PowerPriority::Medium,
+        PowerPriority::High,
+    ];
+}
+
 /// BuildingPrototype is a building
 #[derive(Clone, Debug)]
 pub struct BuildingPrototype {
@@ -33,6 +55,22 @@ pub struct BuildingPrototype {
     pub price: Money,
     pub power_consumption: Option<Power>,
     pub power_production: Option<Power>,
+    /// Energy the building can store, charged from the surplus of its network
+    pub storage_capacity: Option<Energy>,
+    /// Maximum power at which the storage can charge or discharge
+    pub max_charge_rate: Option<Power>,
+    /// Overrides the priority derived from the kind of building
+    pub power_priority: Option<PowerPriority>,
+    /// Water needed at full productivity in cubic meters per hour, none by default
+    pub water_consumption: f32,
+    /// Water pumped at full productivity in cubic meters per hour
+    pub water_production: f32,
+    /// Garbage produced at full productivity in kilograms per hour, none by default
+    pub garbage_production: f32,
+    /// Must be built on the shore, with water next to its footprint, like harbors and water pumps
+    pub shoreline: bool,
+    /// Largest difference of height in meters allowed under the footprint, like for the runway of airports
+    pub max_height_difference: Option<f32>,
 }
 
 impl Prototype for BuildingPrototype {
@@ -51,6 +89,14 @@ impl Prototype for BuildingPrototype {
             price: get_lua(table, "price")?,
             power_consumption: get_lua(table, "power_consumption")?,
             power_production: get_lua(table, "power_production")?,
+            storage_capacity: get_lua(table, "storage_capacity")?,
+            max_charge_rate: get_lua(table, "max_charge_rate")?,
+            power_priority: get_lua(table, "power_priority")?,
+            water_consumption: get_lua_opt(table, "water_consumption")?.unwrap_or(0.0),
+            water_production: get_lua_opt(table, "water_production")?.unwrap_or(0.0),
+            garbage_production: get_lua_opt(table, "garbage_production")?.unwrap_or(0.0),
+            shoreline: get_lua_opt(table, "shoreline")?.unwrap_or(false),
+            max_height_difference: get_lua_opt(table, "max_height_difference")?,
         })
     }
 
@@ -109,3 +155,18 @@ impl<'a> FromLua<'a> for BuildingGen {
         }
     }
 }
+
+impl<'a> FromLua<'a> for PowerPriority {
+    fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown power priority: {}",
+                s
+            ))),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{get_lua, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// DemographicsPrototype holds how citizens age, have children and die
+#[derive(Clone, Debug)]
+pub struct DemographicsPrototype {
+    pub base: PrototypeBase,
+    pub id: DemographicsPrototypeID,
+
+    /// In-game days in a year, citizens get one year older every year
+    pub days_per_year: f32,
+    /// Age at which children start looking for a job
+    pub adult_age: f32,
+
+    /// Chance that a new household moves in as a couple instead of a single adult
+    pub couple_chance: f32,
+    /// Children per year of a couple living in the same home
+    pub birth_rate: f32,
+    /// Age after which women do not have children anymore
+    pub max_parent_age: f32,
+    /// Couples do not have more children once their home has this many residents
+    pub max_household: u32,
+
+    /// Yearly death rate of newborns, it doubles every `mortality_doubling_years`
+    pub mortality_at_birth: f32,
+    pub mortality_doubling_years: f32,
+}
+
+impl Prototype for DemographicsPrototype {
+    type Parent = NoParent;
+    type ID = DemographicsPrototypeID;
+    const NAME: &'static str = "demographics";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+
+            days_per_year: get_lua(table, "days_per_year")?,
+            adult_age: get_lua(table, "adult_age")?,
+
+            couple_chance: get_lua(table, "couple_chance")?,
+            birth_rate: get_lua(table, "birth_rate")?,
+            max_parent_age: get_lua(table, "max_parent_age")?,
+            max_household: get_lua(table, "max_household")?,
+
+            mortality_at_birth: get_lua(table, "mortality_at_birth")?,
+            mortality_doubling_years: get_lua(table, "mortality_doubling_years")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for DemographicsPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -12,6 +12,8 @@ pub struct FreightStationPrototype {
     pub asset: RenderAsset,
     pub price: Money,
     pub size: Size2D,
+    /// Maximum units of external trade handled per in-game hour
+    pub throughput: u32,
 }
 
 impl Prototype for FreightStationPrototype {
@@ -27,6 +29,7 @@ impl Prototype for FreightStationPrototype {
             asset: get_lua(table, "asset")?,
             price: get_lua(table, "price")?,
             size: get_lua(table, "size")?,
+            throughput: get_lua(table, "throughput").unwrap_or(2000),
         })
     }
 
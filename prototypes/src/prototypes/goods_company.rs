@@ -5,7 +5,10 @@ use serde::{Deserialize, Serialize};
 
 use egui_inspect::Inspect;
 
-use crate::{get_lua, get_lua_opt, BuildingPrototype, GoodsCompanyID, Prototype, Recipe, Zone};
+use crate::{
+    get_lua, get_lua_opt, BuildingPrototype, Education, GoodsCompanyID, Money, Prototype,
+    RecTimeInterval, Recipe, RoadVehicleID, SeasonMultipliers, Zone,
+};
 
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Inspect)]
 pub enum CompanyKind {
@@ -22,8 +25,28 @@ pub struct GoodsCompanyPrototype {
     pub kind: CompanyKind,
     pub recipe: Option<Recipe>,
     pub n_trucks: u32,
+    /// Road vehicle used by the trucks, the default truck if not specified
+    pub truck: Option<RoadVehicleID>,
     pub n_workers: u32,
     pub zone: Option<Zone>,
+    /// Money the company starts with
+    pub starting_capital: Money,
+    /// Number of consecutive in-game days with a negative balance before the company closes.
+    /// 0 means the company never goes bankrupt.
+    pub bankruptcy_days: u32,
+    /// Garbage picked up by its truck at each visit, in kilograms.
+    /// 0 means the company does not collect garbage.
+    pub garbage_truck_capacity: f32,
+    /// Distance in meters within which the company sends its truck to put out fires
+    pub service_radius: Option<f32>,
+    /// Education workers need to be hired, none by default
+    pub min_education: Education,
+    /// When the workers come to work, 8h -> 17h by default
+    pub work_hours: RecTimeInterval,
+    /// When households can come to pick up what they bought, 8h -> 20h by default
+    pub opening_hours: RecTimeInterval,
+    /// Productivity multiplier of each season, e.g. farms don't grow much in winter
+    pub season_productivity: SeasonMultipliers,
 }
 
 impl Prototype for GoodsCompanyPrototype {
@@ -39,8 +62,20 @@ impl Prototype for GoodsCompanyPrototype {
             kind: get_lua(table, "kind")?,
             recipe: get_lua(table, "recipe")?,
             n_trucks: get_lua_opt(table, "n_trucks")?.unwrap_or(0),
+            truck: get_lua_opt(table, "truck")?,
             n_workers: get_lua_opt(table, "n_workers")?.unwrap_or(0),
             zone: get_lua(table, "zone").ok(),
+            starting_capital: get_lua_opt(table, "starting_capital")?
+                .unwrap_or(Money::new_bucks(10000)),
+            bankruptcy_days: get_lua_opt(table, "bankruptcy_days")?.unwrap_or(7),
+            garbage_truck_capacity: get_lua_opt(table, "garbage_truck_capacity")?.unwrap_or(0.0),
+            service_radius: get_lua_opt(table, "service_radius")?,
+            min_education: get_lua_opt(table, "min_education")?.unwrap_or_default(),
+            work_hours: get_lua_opt(table, "work_hours")?
+                .unwrap_or(RecTimeInterval::new((8, 0), (17, 0))),
+            opening_hours: get_lua_opt(table, "opening_hours")?
+                .unwrap_or(RecTimeInterval::new((8, 0), (20, 0))),
+            season_productivity: get_lua_opt(table, "season_productivity")?.unwrap_or_default(),
         })
     }
 
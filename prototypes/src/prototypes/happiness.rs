@@ -0,0 +1,81 @@
+use crate::{get_lua, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// HappinessPrototype holds how much each factor weighs in the happiness of citizens
+#[derive(Clone, Debug)]
+pub struct HappinessPrototype {
+    pub base: PrototypeBase,
+    pub id: HappinessPrototypeID,
+
+    pub commute_weight: f32,
+    /// Commutes longer than this many minutes make citizens fully unhappy about it
+    pub max_commute_minutes: f32,
+
+    pub food_weight: f32,
+    /// Days without eating after the first one before citizens are fully unhappy about it
+    pub starving_days: f32,
+
+    /// Weight of power and water outages at home
+    pub utilities_weight: f32,
+
+    pub noise_weight: f32,
+    /// Road noise at home under which citizens don't mind it
+    pub quiet_noise: f32,
+    /// Road noise at home making citizens fully unhappy about it
+    pub max_noise: f32,
+
+    pub leisure_weight: f32,
+    /// Visits to a park or a leisure venue per week for citizens to be fully happy about it
+    pub weekly_leisure_visits: f32,
+    /// Citizens don't go to leisure venues farther than this many minutes away
+    pub max_leisure_trip_minutes: f32,
+}
+
+impl Prototype for HappinessPrototype {
+    type Parent = NoParent;
+    type ID = HappinessPrototypeID;
+    const NAME: &'static str = "happiness";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+
+            commute_weight: get_lua(table, "commute_weight")?,
+            max_commute_minutes: get_lua(table, "max_commute_minutes")?,
+
+            food_weight: get_lua(table, "food_weight")?,
+            starving_days: get_lua(table, "starving_days")?,
+
+            utilities_weight: get_lua(table, "utilities_weight")?,
+
+            noise_weight: get_lua(table, "noise_weight")?,
+            quiet_noise: get_lua(table, "quiet_noise")?,
+            max_noise: get_lua(table, "max_noise")?,
+
+            leisure_weight: get_lua(table, "leisure_weight")?,
+            weekly_leisure_visits: get_lua(table, "weekly_leisure_visits")?,
+            max_leisure_trip_minutes: get_lua(table, "max_leisure_trip_minutes")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for HappinessPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{get_lua, get_lua_opt, BuildingPrototype, LuaVec2, Prototype};
+use geom::Vec2;
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// HarborPrototype is a building on the shore where cargo ships load and unload the goods
+/// traded with the outside.
+#[derive(Clone, Debug)]
+pub struct HarborPrototype {
+    pub base: BuildingPrototype,
+    pub id: HarborPrototypeID,
+    /// Where the ships dock, relative to the center of the building like the door.
+    /// The door is on the -y side, so the docks are usually on the +y side facing the water.
+    pub docks: Vec<Vec2>,
+    /// Maximum units of external trade handled per in-game hour
+    pub throughput: u32,
+}
+
+impl Prototype for HarborPrototype {
+    type Parent = BuildingPrototype;
+    type ID = HarborPrototypeID;
+    const NAME: &'static str = "harbor";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = BuildingPrototype::from_lua(table)?;
+        let docks: Vec<LuaVec2> = get_lua(table, "docks")?;
+        Ok(Self {
+            id: Self::ID::from(&base.name),
+            base,
+            docks: docks.into_iter().map(|d| d.0).collect(),
+            throughput: get_lua_opt(table, "throughput")?.unwrap_or(1000),
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for HarborPrototype {
+    type Target = BuildingPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{get_lua, BuildingPrototype, Prototype};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// HotelPrototype is a building where the tourists visiting the city stay
+#[derive(Clone, Debug)]
+pub struct HotelPrototype {
+    pub base: BuildingPrototype,
+    pub id: HotelPrototypeID,
+    /// Number of tourists that can stay at the same time
+    pub rooms: u32,
+}
+
+impl Prototype for HotelPrototype {
+    type Parent = BuildingPrototype;
+    type ID = HotelPrototypeID;
+    const NAME: &'static str = "hotel";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = BuildingPrototype::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            rooms: get_lua(table, "rooms")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for HotelPrototype {
+    type Target = BuildingPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{get_lua, Money, Prototype, RecTimeInterval};
+use crate::{get_lua, get_lua_opt, Money, Prototype, RecTimeInterval};
 use mlua::Table;
 use std::ops::Deref;
 
@@ -12,6 +12,8 @@ pub struct LeisurePrototype {
     pub opening_hours: RecTimeInterval,
     pub capacity: u32,
     pub entry_fee: Money,
+    /// Visitors walk around the whole area instead of going through the door, e.g. parks
+    pub walkable: bool,
 }
 
 impl Prototype for LeisurePrototype {
@@ -27,6 +29,7 @@ impl Prototype for LeisurePrototype {
             opening_hours: get_lua(table, "opening_hours")?,
             capacity: get_lua(table, "capacity")?,
             entry_fee: get_lua(table, "entry_fee")?,
+            walkable: get_lua_opt(table, "walkable")?.unwrap_or(false),
         })
     }
 
@@ -0,0 +1,65 @@
+use crate::{get_lua, get_lua_opt, Money, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// Road types of the road tools that milestones can unlock, one-way variants included
+pub const ROAD_TYPES: &[&str] = &["street", "avenue", "drive", "highway", "rail"];
+
+/// MilestonePrototype is a step of the progression of the city.
+/// Reaching its population, and its money if it has any, unlocks buildings and road types.
+#[derive(Clone, Debug)]
+pub struct MilestonePrototype {
+    pub base: PrototypeBase,
+    pub id: MilestonePrototypeID,
+    /// Citizens needed to reach the milestone
+    pub population: u32,
+    /// Money the government must have on top of the population
+    pub money: Option<Money>,
+    /// Names of the building prototypes, stations and [`ROAD_TYPES`] unlocked
+    pub unlocks: Vec<String>,
+}
+
+impl MilestonePrototype {
+    /// What it takes to reach the milestone, e.g. "Town: 200 citizens"
+    pub fn requirement(&self) -> String {
+        match self.money {
+            Some(money) => format!("{}: {} citizens and {}", self.label, self.population, money),
+            None => format!("{}: {} citizens", self.label, self.population),
+        }
+    }
+}
+
+impl Prototype for MilestonePrototype {
+    type Parent = NoParent;
+    type ID = MilestonePrototypeID;
+    const NAME: &'static str = "milestone";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            population: get_lua(table, "population")?,
+            money: get_lua_opt(table, "money")?,
+            unlocks: get_lua(table, "unlocks")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for MilestonePrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
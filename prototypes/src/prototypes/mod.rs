@@ -5,6 +5,11 @@ crate::gen_prototypes!(
     mod goods_company: GoodsCompanyID      = GoodsCompanyPrototype => BuildingPrototypeID,
     mod leisure:       LeisurePrototypeID  = LeisurePrototype => BuildingPrototypeID,
     mod solar:         SolarPanelID        = SolarPanelPrototype => GoodsCompanyID,
+    mod warehouse:     WarehousePrototypeID = WarehousePrototype => BuildingPrototypeID,
+    mod school:        SchoolPrototypeID   = SchoolPrototype => BuildingPrototypeID,
+    mod hotel:         HotelPrototypeID    = HotelPrototype => BuildingPrototypeID,
+    mod harbor:        HarborPrototypeID   = HarborPrototype => BuildingPrototypeID,
+    mod airport:       AirportPrototypeID  = AirportPrototype => BuildingPrototypeID,
 
     mod vehicle:       VehiclePrototypeID = VehiclePrototype,
     mod road_vehicle:  RoadVehicleID      = RoadVehiclePrototype => VehiclePrototypeID,
@@ -12,6 +17,12 @@ crate::gen_prototypes!(
 
     mod colors:         ColorsPrototypeID   = ColorsPrototype,
     mod freightstation: FreightStationPrototypeID = FreightStationPrototype,
+    mod passenger_station: PassengerStationPrototypeID = PassengerStationPrototype,
+    mod tree:           TreePrototypeID     = TreePrototype,
+    mod happiness:      HappinessPrototypeID = HappinessPrototype,
+    mod demographics:   DemographicsPrototypeID = DemographicsPrototype,
+    mod milestone:      MilestonePrototypeID = MilestonePrototype,
+    mod scenario:       ScenarioPrototypeID = ScenarioPrototype,
 );
 
 mod base;
@@ -0,0 +1,54 @@
+use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase, RenderAsset, Size2D};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// PassengerStationPrototype is a train station where citizens board passenger trains
+#[derive(Clone, Debug)]
+pub struct PassengerStationPrototype {
+    pub base: PrototypeBase,
+    pub id: PassengerStationPrototypeID,
+    pub asset: RenderAsset,
+    pub price: Money,
+    pub size: Size2D,
+    /// Rolling stock making up the trains serving the station, front first
+    pub train: Vec<RollingStockID>,
+    /// Maximum number of passengers on board of a train
+    pub train_capacity: u32,
+}
+
+impl Prototype for PassengerStationPrototype {
+    type Parent = NoParent;
+    type ID = PassengerStationPrototypeID;
+    const NAME: &'static str = "passenger-station";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            asset: get_lua(table, "asset")?,
+            price: get_lua(table, "price")?,
+            size: get_lua(table, "size")?,
+            train: get_lua(table, "train")?,
+            train_capacity: get_lua(table, "train_capacity")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for PassengerStationPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{get_lua, Prototype};
+use crate::{get_lua, get_lua_opt, Prototype};
 
 use mlua::Table;
 use std::ops::Deref;
@@ -15,6 +15,12 @@ pub struct RoadVehiclePrototype {
     pub acceleration: f32,
     /// m.s^2
     pub deceleration: f32,
+    /// meter
+    pub length: f32,
+    /// How often the vehicle is picked among the cars owned by citizens, 0 means never
+    pub spawn_weight: f32,
+    /// Units of goods carried in one delivery trip, 0 for the vehicles not carrying goods
+    pub capacity: u32,
 }
 
 impl Prototype for RoadVehiclePrototype {
@@ -30,6 +36,9 @@ impl Prototype for RoadVehiclePrototype {
             max_speed: get_lua::<f32>(table, "max_speed")?,
             acceleration: get_lua::<f32>(table, "acceleration")?,
             deceleration: get_lua::<f32>(table, "deceleration")?,
+            length: get_lua::<f32>(table, "length")?,
+            spawn_weight: get_lua_opt::<f32>(table, "spawn_weight")?.unwrap_or(0.0),
+            capacity: get_lua_opt(table, "capacity")?.unwrap_or(0),
         })
     }
     fn id(&self) -> Self::ID {
@@ -0,0 +1,133 @@
+use crate::{
+    get_lua, get_lua_opt, GameDuration, GameTime, ItemID, Money, NoParent, Prototype, PrototypeBase,
+};
+use mlua::{FromLua, Lua, Table, Value};
+use std::ops::Deref;
+
+use super::*;
+
+/// What an objective of a scenario asks for
+#[derive(Clone, Debug)]
+pub enum ObjectiveKind {
+    /// Citizens living in the city
+    Population(u32),
+    /// Money of the government
+    Money(Money),
+    /// Quantity of the item sold to the external market since the start of the scenario
+    Export(ItemID, u64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Objective {
+    pub kind: ObjectiveKind,
+    /// The scenario is lost if the objective is not completed this long after the start
+    pub within: Option<GameDuration>,
+}
+
+impl Objective {
+    /// e.g. "Reach 1000 citizens within 20 days"
+    pub fn description(&self) -> String {
+        let what = match self.kind {
+            ObjectiveKind::Population(n) => format!("Reach {} citizens", n),
+            ObjectiveKind::Money(money) => format!("Have {} in the treasury", money),
+            ObjectiveKind::Export(item, qty) => {
+                format!("Export {} {}", qty, item.prototype().label)
+            }
+        };
+        match self.within {
+            Some(within) => format!(
+                "{} within {:.0} days",
+                what,
+                within.seconds() / GameTime::DAY as f64
+            ),
+            None => what,
+        }
+    }
+
+    /// The quantity to reach, in the unit of the objective
+    pub fn target(&self) -> f64 {
+        match self.kind {
+            ObjectiveKind::Population(n) => n as f64,
+            ObjectiveKind::Money(money) => money.bucks() as f64,
+            ObjectiveKind::Export(_, qty) => qty as f64,
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for Objective {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        let kind = if let Some(n) = get_lua_opt(&table, "population")? {
+            ObjectiveKind::Population(n)
+        } else if let Some(money) = get_lua_opt(&table, "money")? {
+            ObjectiveKind::Money(money)
+        } else if let Some(item) = get_lua_opt::<String>(&table, "export")? {
+            ObjectiveKind::Export(ItemID::new(&item), get_lua(&table, "amount")?)
+        } else {
+            return Err(mlua::Error::external(
+                "objective must have population, money or export",
+            ));
+        };
+        Ok(Self {
+            kind,
+            within: get_lua_opt(&table, "within")?,
+        })
+    }
+}
+
+/// ScenarioPrototype is an alternative to the sandbox: the city starts from a given map and money,
+/// some features are locked and the player must complete all the objectives to win.
+#[derive(Clone, Debug)]
+pub struct ScenarioPrototype {
+    pub base: PrototypeBase,
+    pub id: ScenarioPrototypeID,
+    pub description: String,
+    /// Save to start from, a new map is generated if None
+    pub load: Option<String>,
+    /// Size of the generated terrain, in chunks
+    pub terrain_size: u16,
+    /// Money of the government at the start
+    pub money: Money,
+    /// Names of the building prototypes, stations and [`ROAD_TYPES`] that can't be built
+    pub locked: Vec<String>,
+    pub objectives: Vec<Objective>,
+    /// The scenario is lost when the money of the government goes below
+    pub min_money: Option<Money>,
+}
+
+impl Prototype for ScenarioPrototype {
+    type Parent = NoParent;
+    type ID = ScenarioPrototypeID;
+    const NAME: &'static str = "scenario";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            description: get_lua(table, "description")?,
+            load: get_lua_opt(table, "load")?,
+            terrain_size: get_lua_opt(table, "terrain_size")?.unwrap_or(10),
+            money: get_lua(table, "money")?,
+            locked: get_lua_opt(table, "locked")?.unwrap_or_default(),
+            objectives: get_lua(table, "objectives")?,
+            min_money: get_lua_opt(table, "min_money")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for ScenarioPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
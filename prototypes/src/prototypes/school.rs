@@ -0,0 +1,102 @@
+use crate::{get_lua, BuildingPrototype, Prototype};
+use egui_inspect::debug_inspect_impl;
+use mlua::{FromLua, Lua, Table, Value};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+use super::*;
+
+/// How educated a citizen is, companies can require a minimum level from their workers
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Education {
+    #[default]
+    None,
+    Basic,
+    Higher,
+}
+debug_inspect_impl!(Education);
+
+impl Education {
+    pub const ALL: [Education; 3] = [Education::None, Education::Basic, Education::Higher];
+
+    /// The level reached after graduating, None if there is nothing left to learn
+    pub fn next(self) -> Option<Education> {
+        match self {
+            Education::None => Some(Education::Basic),
+            Education::Basic => Some(Education::Higher),
+            Education::Higher => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Education::None => "No education",
+            Education::Basic => "Basic education",
+            Education::Higher => "Higher education",
+        }
+    }
+}
+
+/// SchoolPrototype is a building where the children living nearby get educated
+#[derive(Clone, Debug)]
+pub struct SchoolPrototype {
+    pub base: BuildingPrototype,
+    pub id: SchoolPrototypeID,
+    /// Maximum number of children enrolled
+    pub capacity: u32,
+    /// Children living farther than this distance in meters cannot go to this school
+    pub service_radius: f32,
+    /// Highest level of education the school teaches
+    pub max_education: Education,
+    /// Years of enrollment needed to reach the next level of education
+    pub years_per_level: f32,
+}
+
+impl Prototype for SchoolPrototype {
+    type Parent = BuildingPrototype;
+    type ID = SchoolPrototypeID;
+    const NAME: &'static str = "school";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = BuildingPrototype::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::from(&base.name),
+            base,
+            capacity: get_lua(table, "capacity")?,
+            service_radius: get_lua(table, "service_radius")?,
+            max_education: get_lua(table, "max_education")?,
+            years_per_level: get_lua(table, "years_per_level")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for SchoolPrototype {
+    type Target = BuildingPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<'a> FromLua<'a> for Education {
+    fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "none" => Ok(Self::None),
+            "basic" => Ok(Self::Basic),
+            "higher" => Ok(Self::Higher),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown education level: {}",
+                s
+            ))),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{get_color, get_lua, Money, NoParent, Prototype, PrototypeBase};
+use geom::Color;
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// TreePrototype is a species of tree that can be planted
+#[derive(Clone, Debug)]
+pub struct TreePrototype {
+    pub base: PrototypeBase,
+    pub id: TreePrototypeID,
+    /// Size of the smallest tree in meters
+    pub min_size: f32,
+    /// Size of the biggest tree in meters
+    pub max_size: f32,
+    pub tint: Color,
+    /// Price of planting one tree
+    pub price: Money,
+}
+
+impl Prototype for TreePrototype {
+    type Parent = NoParent;
+    type ID = TreePrototypeID;
+    const NAME: &'static str = "tree";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            min_size: get_lua(table, "min_size")?,
+            max_size: get_lua(table, "max_size")?,
+            tint: get_color(table, "tint")?,
+            price: get_lua(table, "price")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for TreePrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{get_lua, BuildingPrototype, Prototype, RecipeItem};
+use mlua::Table;
+use std::ops::Deref;
+
+use super::*;
+
+/// WarehousePrototype is a building that stockpiles goods when they are abundant
+/// and sells them back locally when they are scarce.
+#[derive(Clone, Debug)]
+pub struct WarehousePrototype {
+    pub base: BuildingPrototype,
+    pub id: WarehousePrototypeID,
+    /// Maximum stock for each item the warehouse handles
+    pub capacity: Vec<RecipeItem>,
+}
+
+impl Prototype for WarehousePrototype {
+    type Parent = BuildingPrototype;
+    type ID = WarehousePrototypeID;
+    const NAME: &'static str = "warehouse";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = BuildingPrototype::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::from(&base.name),
+            base,
+            capacity: get_lua(table, "capacity")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for WarehousePrototype {
+    type Target = BuildingPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -27,3 +27,59 @@ fn test_base() {
         println!("{:?}", try_prototype(SolarPanelID::new("solar-panel")));
     }
 }
+
+#[test]
+fn test_mods_override() {
+    use crate::mods::{discover_mods, sort_mods};
+    use crate::{load_prototypes_with_mods, ConcretePrototype, ItemPrototype};
+
+    let dir = std::env::temp_dir().join("egregoria_test_mods");
+    let _ = std::fs::remove_dir_all(&dir);
+    let write_mod = |name: &str, deps: &str, data: &str| {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(
+            path.join("mod.json"),
+            format!(
+                r#"{{"name": "{}", "version": "1.0", "dependencies": [{}]}}"#,
+                name, deps
+            ),
+        )
+        .unwrap();
+        std::fs::write(path.join("data.lua"), data).unwrap();
+    };
+
+    // sorted by name the override would come first
+    write_mod(
+        "gems",
+        "",
+        r#"data:extend {
+            { type = "item", name = "gem", label = "Gem" },
+            { type = "item", name = "bread", label = "Gem bread" },
+        }"#,
+    );
+    write_mod(
+        "better-gems",
+        r#""gems""#,
+        r#"data:extend { type = "item", name = "gem", label = "Shiny gem", optout_exttrade = true }"#,
+    );
+
+    let (mods, problems) = discover_mods(&dir);
+    assert!(problems.is_empty());
+    let (mods, problems) = sort_mods(mods);
+    assert!(problems.is_empty());
+    let order: Vec<_> = mods.iter().map(|m| m.manifest.name.as_str()).collect();
+    assert_eq!(order, ["gems", "better-gems"]);
+
+    let p = load_prototypes_with_mods("../", &mods).unwrap();
+    let items = ItemPrototype::storage(&p);
+    let gem = &items[&ItemID::new("gem")];
+    assert_eq!(gem.label, "Shiny gem");
+    assert!(gem.optout_exttrade);
+    assert_eq!(items[&ItemID::new("bread")].label, "Gem bread");
+    assert_eq!(p.mods.active.len(), 2);
+    // the override depends on the mod it replaces, and replacing the base mod is expected
+    assert!(p.mods.problems.is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -31,12 +31,14 @@ impl Money {
         Self(inner)
     }
 
+    /// Saturates at [`Money::MAX`] or its negative like the operators
     pub const fn new_cents(cents: i64) -> Self {
-        Self(cents * 100)
+        Self(cents.saturating_mul(100))
     }
 
+    /// Saturates at [`Money::MAX`] or its negative like the operators
     pub const fn new_bucks(base: i64) -> Self {
-        Self(base * 10000)
+        Self(base.saturating_mul(10000))
     }
 
     pub fn inner(&self) -> i64 {
@@ -50,26 +52,98 @@ impl Money {
     pub fn bucks(&self) -> i64 {
         self.0 / 10000
     }
+
+    /// Like the Display impl, but with the separators and currency placement of the
+    /// active language, for the UI
+    pub fn localized(&self) -> String {
+        let cents = self.cents();
+        let decimals = if cents % 100 != 0 { 2 } else { 0 };
+        let amount = common::i18n::format_number(cents as f64 / 100.0, decimals);
+        common::t!("money-format", amount = amount)
+    }
+
+    /// Short amount without the unit for the charts and tight places, like 530k or 1.2M.
+    /// Amounts under a thousand are shown in full.
+    pub fn fmt_compact(&self) -> String {
+        if self.0.unsigned_abs() < 1000 * 10000 {
+            return self.fmt_amount();
+        }
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let bucks = self.0.unsigned_abs() as f64 / 10000.0;
+        let units = [(1e3, "k"), (1e6, "M"), (1e9, "B"), (1e12, "T")];
+        for (i, &(scale, suffix)) in units.iter().enumerate() {
+            let v = bucks / scale;
+            let (shown, decimals) = match (v * 10.0).round() / 10.0 {
+                shown if shown < 10.0 => (shown, 1),
+                _ => (v.round(), 0),
+            };
+            // 999.96k is shown as 1.0M rather than 1000k
+            if shown >= 1000.0 && i + 1 < units.len() {
+                continue;
+            }
+            return format!("{}{:.*}{}", sign, decimals, shown, suffix);
+        }
+        unreachable!()
+    }
+
+    /// The amount without the unit, with the cents if there are any
+    fn fmt_amount(&self) -> String {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let cents = (abs % 10000) / 100;
+        if cents == 0 {
+            return format!("{}{}", sign, abs / 10000);
+        }
+        format!("{}{}.{:02}", sign, abs / 10000, cents)
+    }
+
+    pub fn checked_add(self, rhs: Money) -> Option<Money> {
+        self.0.checked_add(rhs.0).map(Money)
+    }
+
+    pub fn checked_sub(self, rhs: Money) -> Option<Money> {
+        self.0.checked_sub(rhs.0).map(Money)
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Money> {
+        self.0.checked_mul(rhs).map(Money)
+    }
+
+    pub fn saturating_add(self, rhs: Money) -> Money {
+        Money(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Money) -> Money {
+        Money(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_mul(self, rhs: i64) -> Money {
+        Money(self.0.saturating_mul(rhs))
+    }
+}
+
+/// The operators saturate instead of wrapping, an overflow is a bug so it asserts in debug builds
+fn saturating_op(
+    checked: Option<Money>,
+    saturating: Money,
+    op: &str,
+    a: impl Display,
+    b: impl Display,
+) -> Money {
+    debug_assert!(checked.is_some(), "money overflow: {} {} {}", a, op, b);
+    checked.unwrap_or(saturating)
 }
 
 impl Display for Money {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        Display::fmt(&(self.bucks()), f)?;
-        let cent = (self.0 % 10000) / 100;
-        if cent > 0 {
-            f.write_str(".")?;
-            if cent < 10 {
-                f.write_str("0")?;
-            }
-            Display::fmt(&cent, f)?;
-        }
+        f.write_str(&self.fmt_amount())?;
         f.write_str("$")
     }
 }
 
 #[derive(Debug, Error)]
 pub enum MoneyParseError {
-    #[error("Invalid unit: {0} (accepted: $, c)")]
+    #[error("Invalid unit: {0} (accepted: $, c, k, M, B, T)")]
     InvalidUnit(String),
     #[error("Invalid number")]
     InvalidNumber,
@@ -80,24 +154,42 @@ pub enum MoneyParseError {
 impl FromStr for Money {
     type Err = MoneyParseError;
 
+    /// Accepts what Display and fmt_compact write, the $ before the amount, and the thousands
+    /// separators like in "-$1,234.50" or "1.2M$"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.trim();
+        let mut s = s.trim();
+        let negative = s.starts_with('-');
+        if negative {
+            s = s[1..].trim_start();
+        }
+        s = s.strip_prefix('$').unwrap_or(s);
+        let s: String = s.chars().filter(|&c| c != ',' && c != '_').collect();
+
         let (mut number, rest) =
-            common::parse_f64(s).map_err(|_| MoneyParseError::InvalidNumber)?;
+            common::parse_f64(&s).map_err(|_| MoneyParseError::InvalidNumber)?;
 
         let unit = rest.trim();
+        let unit = unit.strip_suffix('$').unwrap_or(unit).trim_end();
 
         match unit {
-            "$" | "" => {}
+            "" => {}
             "c" => number /= 100.0,
+            "k" => number *= 1e3,
+            "M" => number *= 1e6,
+            "B" => number *= 1e9,
+            "T" => number *= 1e12,
             _ => return Err(MoneyParseError::InvalidUnit(unit.to_string())),
         }
 
-        if number * 10000.0 > i64::MAX as f64 {
+        if number.abs() * 10000.0 > i64::MAX as f64 {
             return Err(MoneyParseError::TooBig);
         }
+        if negative {
+            number = -number;
+        }
 
-        Ok(Money::from_float_bucks(number))
+        // rounded so that the amounts written with cents come back exactly
+        Ok(Money((number * 10000.0).round() as i64))
     }
 }
 
@@ -126,7 +218,7 @@ impl Mul<Money> for i64 {
     type Output = Money;
 
     fn mul(self, rhs: Money) -> Self::Output {
-        Money(self * rhs.0)
+        rhs * self
     }
 }
 
@@ -154,13 +246,19 @@ impl std::ops::Sub for Money {
     type Output = Money;
 
     fn sub(self, other: Money) -> Money {
-        Money(self.0 - other.0)
+        saturating_op(
+            self.checked_sub(other),
+            self.saturating_sub(other),
+            "-",
+            self,
+            other,
+        )
     }
 }
 
 impl SubAssign for Money {
     fn sub_assign(&mut self, other: Money) {
-        self.0 -= other.0;
+        *self = *self - other;
     }
 }
 
@@ -168,13 +266,19 @@ impl Add for Money {
     type Output = Money;
 
     fn add(self, other: Money) -> Money {
-        Money(self.0 + other.0)
+        saturating_op(
+            self.checked_add(other),
+            self.saturating_add(other),
+            "+",
+            self,
+            other,
+        )
     }
 }
 
 impl AddAssign for Money {
     fn add_assign(&mut self, other: Money) {
-        self.0 += other.0;
+        *self = *self + other;
     }
 }
 
@@ -182,7 +286,13 @@ impl Mul<i64> for Money {
     type Output = Money;
 
     fn mul(self, rhs: i64) -> Self::Output {
-        Money(self.0 * rhs)
+        saturating_op(
+            self.checked_mul(rhs),
+            self.saturating_mul(rhs),
+            "*",
+            self,
+            rhs,
+        )
     }
 }
 
@@ -209,3 +319,91 @@ impl Mul<Money> for f64 {
         Money((rhs.0 as f64 * self) as i64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Money;
+
+    #[test]
+    fn test_money_display() {
+        assert_eq!(Money::new_bucks(1234).to_string(), "1234$");
+        assert_eq!(Money::new_cents(123405).to_string(), "1234.05$");
+        assert_eq!(Money::new_cents(-123450).to_string(), "-1234.50$");
+        assert_eq!(Money::new_cents(-50).to_string(), "-0.50$");
+        assert_eq!(Money::ZERO.to_string(), "0$");
+    }
+
+    #[test]
+    fn test_money_compact() {
+        assert_eq!(Money::new_bucks(999).fmt_compact(), "999");
+        assert_eq!(Money::new_bucks(1000).fmt_compact(), "1.0k");
+        assert_eq!(Money::new_bucks(9_999).fmt_compact(), "10k");
+        assert_eq!(Money::new_bucks(530_000).fmt_compact(), "530k");
+        assert_eq!(Money::new_bucks(999_499).fmt_compact(), "999k");
+        assert_eq!(Money::new_bucks(999_500).fmt_compact(), "1.0M");
+        assert_eq!(Money::new_bucks(1_234_567).fmt_compact(), "1.2M");
+        assert_eq!(Money::new_bucks(-1_234_567).fmt_compact(), "-1.2M");
+        assert_eq!(Money::MAX.fmt_compact(), "922T");
+    }
+
+    #[test]
+    fn test_money_overflow() {
+        assert_eq!(Money::new_bucks(i64::MAX), Money::MAX);
+        assert_eq!(Money::new_bucks(i64::MIN), Money::new_inner(i64::MIN));
+        assert_eq!(Money::new_cents(i64::MAX / 10), Money::MAX);
+        assert_eq!(Money::MAX.checked_add(Money::new_inner(1)), None);
+        assert_eq!(Money::MAX.saturating_add(Money::new_bucks(1)), Money::MAX);
+        assert_eq!(
+            Money::new_bucks(-1).saturating_mul(i64::MAX),
+            Money::new_inner(i64::MIN)
+        );
+        assert_eq!(
+            Money::new_bucks(2).checked_mul(3),
+            Some(Money::new_bucks(6))
+        );
+        assert_eq!(
+            Money::new_bucks(5).checked_sub(Money::new_bucks(7)),
+            Some(Money::new_bucks(-2))
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "money overflow")]
+    fn test_money_overflow_asserts() {
+        let _ = Money::MAX + Money::new_bucks(1);
+    }
+
+    #[test]
+    fn test_money_parse() {
+        for m in [
+            Money::ZERO,
+            Money::new_bucks(1234),
+            Money::new_cents(123405),
+            Money::new_cents(-123450),
+            Money::new_cents(-5),
+        ] {
+            assert_eq!(m.to_string().parse::<Money>().unwrap(), m);
+        }
+        for m in [
+            Money::new_cents(99950),
+            Money::new_bucks(-42),
+            Money::new_bucks(530_000),
+            Money::new_bucks(-1_200_000),
+        ] {
+            assert_eq!(m.fmt_compact().parse::<Money>().unwrap(), m);
+        }
+        assert_eq!(
+            "1.2M".parse::<Money>().unwrap(),
+            Money::new_bucks(1_200_000)
+        );
+        assert_eq!("530k$".parse::<Money>().unwrap(), Money::new_bucks(530_000));
+        assert_eq!(
+            "-$1,234.50".parse::<Money>().unwrap(),
+            Money::new_cents(-123450)
+        );
+        assert_eq!("50c".parse::<Money>().unwrap(), Money::new_cents(50));
+        assert!("12 apples".parse::<Money>().is_err());
+        assert!("1e30".parse::<Money>().is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Money;
 use egui_inspect::debug_inspect_impl;
 use mlua::{FromLua, Lua, Value};
 use serde::{Deserialize, Serialize};
@@ -5,6 +6,9 @@ use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use thiserror::Error;
 
+/// Default price of one kilowatt-hour of electricity, the government can change it
+pub const DEFAULT_ELECTRICITY_PRICE: Money = Money::new_cents(15);
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 /// Power in watts (J/s)
 pub struct Power(pub i64);
@@ -37,7 +41,7 @@ impl Power {
 
 #[derive(Debug, Error)]
 pub enum PowerParseError {
-    #[error("Invalid unit: {0} (accepted: W, kW, MW, GW)")]
+    #[error("Invalid unit: {0} (accepted: W, kW, MW, GW, or Wh, kWh, MWh, GWh for energy)")]
     InvalidUnit(String),
     #[error("Invalid number")]
     InvalidNumber,
@@ -203,3 +207,102 @@ impl std::ops::Div<i64> for Power {
         Power(self.0 / rhs)
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+/// Energy in watt-hours
+pub struct Energy(pub i64);
+debug_inspect_impl!(Energy);
+
+impl Energy {
+    pub const ZERO: Energy = Energy(0);
+
+    pub const fn new(watt_hours: i64) -> Self {
+        Self(watt_hours)
+    }
+
+    pub const fn watt_hours(&self) -> i64 {
+        self.0
+    }
+
+    pub fn kilowatt_hours(&self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+impl FromStr for Energy {
+    type Err = PowerParseError;
+
+    /// Parse an energy value from a string. The unit can be Wh, kWh, MWh or GWh.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (mut number, rest) =
+            common::parse_f64(s).map_err(|_| PowerParseError::InvalidNumber)?;
+
+        let unit = rest.trim();
+
+        match unit {
+            "Wh" => {}
+            "kWh" => number *= 1000.0,
+            "MWh" => number *= 1000.0 * 1000.0,
+            "GWh" => number *= 1000.0 * 1000.0 * 1000.0,
+            _ => return Err(PowerParseError::InvalidUnit(unit.to_string())),
+        }
+
+        if number > i64::MAX as f64 {
+            return Err(PowerParseError::TooBig);
+        }
+
+        Ok(Self(number as i64))
+    }
+}
+
+impl Display for Energy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (unit, div) = match self.0 {
+            0..=999 => ("Wh", 1.0),
+            1000..=999_999 => ("kWh", 1000.0),
+            1_000_000..=999_999_999 => ("MWh", 1_000_000.0),
+            _ => ("GWh", 1_000_000_000.0),
+        };
+
+        let v = self.0 as f64 / div;
+
+        if (v.round() - v).abs() < 0.01 {
+            write!(f, "{}{}", v.round(), unit)
+        } else {
+            write!(f, "{:.2}{}", v, unit)
+        }
+    }
+}
+
+impl std::fmt::Debug for Energy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<'lua> FromLua<'lua> for Energy {
+    fn from_lua(value: Value<'lua>, _: &'lua Lua) -> mlua::Result<Self> {
+        match value {
+            Value::Integer(i) => Ok(Self(i as i64)),
+            Value::Number(n) => {
+                if n > i64::MAX as f64 {
+                    return Err(mlua::Error::external(PowerParseError::TooBig));
+                }
+                Ok(Self(n as i64))
+            }
+            Value::String(s) => {
+                let s = s.to_str()?.trim();
+                if s.is_empty() {
+                    return Ok(Self(0));
+                }
+                Self::from_str(s).map_err(mlua::Error::external)
+            }
+            _ => Err(mlua::Error::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Energy",
+                message: Some("expected string or number".into()),
+            }),
+        }
+    }
+}
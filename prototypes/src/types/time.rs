@@ -1,6 +1,6 @@
 use crate::get_lua;
 use egui_inspect::{debug_inspect_impl, Inspect};
-use mlua::{FromLua, Lua, Number, Value};
+use mlua::{FromLua, Lua, Number, Table, Value};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, Sub};
@@ -19,6 +19,11 @@ pub const TICKS_PER_MINUTE: u64 = TICKS_PER_SECOND * SECONDS_PER_MINUTE as u64;
 pub const TICKS_PER_HOUR: u64 = TICKS_PER_SECOND * SECONDS_PER_HOUR as u64;
 pub const DELTA_F64: f64 = 1.0 / TICKS_PER_REALTIME_SECOND as f64;
 pub const DELTA: f32 = DELTA_F64 as f32;
+pub const DAYS_PER_SEASON: i32 = 7;
+pub const DAYS_PER_YEAR: i32 = 4 * DAYS_PER_SEASON;
+pub const DAYS_PER_WEEK: i32 = 7;
+/// The last days of each week are the weekend
+pub const WEEKEND_DAYS: i32 = 2;
 
 /// The amount of time the game was updated
 /// Used as a resource
@@ -71,6 +76,70 @@ pub struct DayTime {
     pub second: i32,
 }
 
+/// Season of the in-game calendar, the year starts in spring
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+debug_inspect_impl!(Season);
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    /// Season of the given day since the start of the game
+    pub fn of_day(day: i32) -> Season {
+        Self::ALL[(day.rem_euclid(DAYS_PER_YEAR) / DAYS_PER_SEASON) as usize]
+    }
+
+    /// Lowercase name, as used in the lua prototypes
+    pub fn name(self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Autumn => "autumn",
+            Season::Winter => "winter",
+        }
+    }
+}
+
+/// A multiplier for each season, 1.0 for the seasons left out in lua
+/// e.g. `{ summer = 1.5, winter = 0 }`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SeasonMultipliers([f32; 4]);
+
+impl Default for SeasonMultipliers {
+    fn default() -> Self {
+        Self([1.0; 4])
+    }
+}
+
+impl SeasonMultipliers {
+    pub fn get(&self, season: Season) -> f32 {
+        self.0[season as usize]
+    }
+}
+
+impl<'lua> FromLua<'lua> for SeasonMultipliers {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        let mut v = Self::default();
+        for season in Season::ALL {
+            if let Some(m) = table.get::<_, Option<f32>>(season.name())? {
+                v.0[season as usize] = m;
+            }
+        }
+        Ok(v)
+    }
+}
+
 /// An interval of in-game time
 /// The interval is inclusive on the start and exclusive on the end
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -164,6 +233,22 @@ impl RecTimeInterval {
         self.inverted ^ (self.start_seconds..self.end_seconds).contains(&t_day)
     }
 
+    /// The same interval moved later in the day by the given number of seconds
+    pub fn shifted(&self, seconds: i32) -> Self {
+        if self.start_seconds == self.end_seconds {
+            return *self;
+        }
+        let (start, end) = if self.inverted {
+            (self.end_seconds, self.start_seconds)
+        } else {
+            (self.start_seconds, self.end_seconds)
+        };
+        Self::new_daysec(
+            (start + seconds).rem_euclid(SECONDS_PER_DAY),
+            (end + seconds).rem_euclid(SECONDS_PER_DAY),
+        )
+    }
+
     /// Time until the next interval
     pub fn dist_start(&self, t: &DayTime) -> i32 {
         let t_day = t.daysec();
@@ -210,6 +295,25 @@ impl DayTime {
     pub fn gamesec(&self) -> i32 {
         self.day * SECONDS_PER_DAY + self.daysec()
     }
+
+    pub fn season(&self) -> Season {
+        Season::of_day(self.day)
+    }
+
+    /// Day of the current season, starting at 1
+    pub fn day_of_season(&self) -> i32 {
+        self.day.rem_euclid(DAYS_PER_SEASON) + 1
+    }
+
+    /// Year of the in-game calendar, starting at 1
+    pub fn year(&self) -> i32 {
+        self.day.div_euclid(DAYS_PER_YEAR) + 1
+    }
+
+    /// Whether the day is one of the last [`WEEKEND_DAYS`] of the week
+    pub fn is_weekend(&self) -> bool {
+        self.day.rem_euclid(DAYS_PER_WEEK) >= DAYS_PER_WEEK - WEEKEND_DAYS
+    }
 }
 
 impl GameTime {
@@ -237,6 +341,10 @@ impl GameTime {
     pub fn daysec(&self) -> f64 {
         self.timestamp % Self::DAY as f64
     }
+
+    pub fn season(&self) -> Season {
+        self.daytime.season()
+    }
 }
 
 impl GameDuration {
@@ -319,12 +427,23 @@ impl Sub<GameDuration> for GameInstant {
 
 #[cfg(test)]
 mod test {
+    use super::{DayTime, DAYS_PER_WEEK, SECONDS_PER_DAY, WEEKEND_DAYS};
     use common::timestep::debug_up_dt;
 
     #[test]
     fn assert_up_dt_ticks_per_second_match() {
         assert!((debug_up_dt().as_secs_f64() - super::DELTA_F64).abs() < 0.0001);
     }
+
+    #[test]
+    fn weekend_is_the_end_of_the_week() {
+        let weekend = (0..2 * DAYS_PER_WEEK)
+            .filter(|&day| DayTime::new(day * SECONDS_PER_DAY).is_weekend())
+            .count();
+        assert_eq!(weekend, 2 * WEEKEND_DAYS as usize);
+        assert!(!DayTime::new(0).is_weekend());
+        assert!(DayTime::new((DAYS_PER_WEEK - 1) * SECONDS_PER_DAY).is_weekend());
+    }
 }
 
 impl Debug for Tick {
@@ -691,6 +810,48 @@ mod tests {
         assert_eq!(interval.dist_start(&h(7)), 0);
     }
 
+    #[test]
+    fn test_rectime_shifted() {
+        use super::*;
+        let interval = RecTimeInterval::new((8, 0), (17, 0));
+        assert_eq!(
+            interval.shifted(30 * SECONDS_PER_MINUTE),
+            RecTimeInterval::new((8, 30), (17, 30))
+        );
+        assert_eq!(
+            interval.shifted(10 * SECONDS_PER_HOUR),
+            RecTimeInterval::new((18, 0), (3, 0))
+        );
+        assert_eq!(
+            RecTimeInterval::new((18, 0), (1, 0)).shifted(SECONDS_PER_HOUR),
+            RecTimeInterval::new((19, 0), (2, 0))
+        );
+        assert_eq!(
+            RecTimeInterval::always().shifted(SECONDS_PER_HOUR),
+            RecTimeInterval::always()
+        );
+        assert_eq!(
+            RecTimeInterval::never().shifted(SECONDS_PER_HOUR),
+            RecTimeInterval::never()
+        );
+    }
+
+    #[test]
+    fn test_seasons() {
+        use super::*;
+        assert_eq!(Season::of_day(0), Season::Spring);
+        assert_eq!(Season::of_day(DAYS_PER_SEASON - 1), Season::Spring);
+        assert_eq!(Season::of_day(DAYS_PER_SEASON), Season::Summer);
+        assert_eq!(Season::of_day(3 * DAYS_PER_SEASON), Season::Winter);
+        assert_eq!(Season::of_day(DAYS_PER_YEAR), Season::Spring);
+        assert_eq!(Season::of_day(-1), Season::Winter);
+
+        let t = DayTime::new((DAYS_PER_YEAR + DAYS_PER_SEASON + 2) * SECONDS_PER_DAY);
+        assert_eq!(t.season(), Season::Summer);
+        assert_eq!(t.day_of_season(), 3);
+        assert_eq!(t.year(), 2);
+    }
+
     #[test]
     #[rustfmt::skip]
     fn test_daytime_parsing() {
@@ -1,28 +1,53 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
 use thiserror::Error;
 
 use common::error::MultiError;
 
-use crate::{CompanyKind, Prototypes};
+use crate::{
+    BuildingPrototypeID, CompanyKind, FreightStationPrototypeID, ItemID, ObjectiveKind,
+    PassengerStationPrototypeID, Prototypes, RenderAsset, ROAD_TYPES,
+};
 
-#[derive(Debug, Error)]
-pub enum ValidationError {
-    #[error("{0}: only factories can have trucks")]
-    WrongTrucks(String),
-    #[error("{0}: factories must have trucks if it produces things")]
-    ZeroTrucks(String),
-    #[error("{0}.{1}: referenced prototype not found")]
-    ReferencedProtoNotFound(String, &'static str),
+/// A problem in the data of a prototype, the loading collects all of them before failing
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{prototype}.{field}: {message}")]
+pub struct ValidationError {
+    /// Name of the prototype
+    pub prototype: String,
+    pub field: &'static str,
+    pub message: String,
+}
 
-    #[error("{0}.{1}: {2}")]
-    InvalidField(String, &'static str, String),
+impl ValidationError {
+    pub fn new(prototype: &str, field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            prototype: prototype.to_string(),
+            field,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(prototype: &str, field: &'static str) -> Self {
+        Self::new(prototype, field, "referenced prototype not found")
+    }
 }
 
-pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationError>> {
+/// Checks the loaded prototypes, and the files they use if loaded from the game folder `assets_base`
+pub(crate) fn validate(
+    proto: &Prototypes,
+    assets_base: Option<&str>,
+) -> Result<(), MultiError<ValidationError>> {
     let mut errors = vec![];
 
     for comp in proto.goods_company.values() {
         if comp.n_trucks > 0 && comp.kind != CompanyKind::Factory {
-            errors.push(ValidationError::WrongTrucks(comp.name.clone()));
+            errors.push(ValidationError::new(
+                &comp.name,
+                "n_trucks",
+                "only factories can have trucks",
+            ));
         }
 
         if comp.n_trucks == 0
@@ -33,44 +58,155 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
                 .map(|r| !r.production.is_empty())
                 .unwrap_or(false)
         {
-            errors.push(ValidationError::ZeroTrucks(comp.name.clone()));
+            errors.push(ValidationError::new(
+                &comp.name,
+                "n_trucks",
+                "factories must have trucks if they produce things",
+            ));
+        }
+
+        if let Some(truck) = comp.truck {
+            if !proto.road_vehicle.contains_key(&truck) {
+                errors.push(ValidationError::not_found(&comp.name, "truck"));
+            }
         }
 
         if let Some(ref r) = comp.recipe {
-            for item in &r.consumption {
+            for (i, item) in r.consumption.iter().enumerate() {
                 if !proto.item.contains_key(&item.id) {
-                    errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
+                    errors.push(ValidationError::new(
+                        &comp.name,
                         "consumption",
+                        format!("item {} is not an item prototype", i + 1),
                     ));
                 }
             }
 
-            for item in &r.production {
+            for (i, item) in r.production.iter().enumerate() {
                 if !proto.item.contains_key(&item.id) {
-                    errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
+                    errors.push(ValidationError::new(
+                        &comp.name,
                         "production",
+                        format!("item {} is not an item prototype", i + 1),
+                    ));
+                }
+                if item.amount <= 0 {
+                    errors.push(ValidationError::new(
+                        &comp.name,
+                        "production",
+                        format!("item {} must be produced in a positive amount", i + 1),
                     ));
                 }
             }
         }
 
+        if comp.size.w <= 0.0 || comp.size.h <= 0.0 {
+            errors.push(ValidationError::new(&comp.name, "size", "must be positive"));
+        }
+
         if comp.power_consumption.map_or(false, |v| v.0 < 0) {
-            errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
+            errors.push(ValidationError::new(
+                &comp.name,
                 "power_consumption",
-                "must not be negative".to_string(),
+                "must not be negative",
             ));
         }
 
         if comp.power_production.map_or(false, |v| v.0 < 0) {
-            errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
+            errors.push(ValidationError::new(
+                &comp.name,
                 "power_production",
-                "must not be negative".to_string(),
+                "must not be negative",
+            ));
+        }
+
+        if comp.storage_capacity.map_or(false, |v| v.0 < 0) {
+            errors.push(ValidationError::new(
+                &comp.name,
+                "storage_capacity",
+                "must not be negative",
+            ));
+        }
+
+        if comp.storage_capacity.is_some() && comp.max_charge_rate.map_or(true, |v| v.0 <= 0) {
+            errors.push(ValidationError::new(
+                &comp.name,
+                "max_charge_rate",
+                "must be positive when the building stores energy",
+            ));
+        }
+    }
+
+    for warehouse in proto.warehouse.values() {
+        for item in &warehouse.capacity {
+            if !proto.item.contains_key(&item.id) {
+                errors.push(ValidationError::not_found(&warehouse.name, "capacity"));
+            }
+
+            if item.amount <= 0 {
+                errors.push(ValidationError::new(
+                    &warehouse.name,
+                    "capacity",
+                    "must be positive",
+                ));
+            }
+        }
+    }
+
+    for vehicle in proto.road_vehicle.values() {
+        if vehicle.length <= 0.0 {
+            errors.push(ValidationError::new(
+                &vehicle.name,
+                "length",
+                "must be positive",
             ));
         }
+
+        if vehicle.spawn_weight < 0.0 {
+            errors.push(ValidationError::new(
+                &vehicle.name,
+                "spawn_weight",
+                "must not be negative",
+            ));
+        }
+    }
+
+    for milestone in proto.milestone.values() {
+        for name in &milestone.unlocks {
+            if !is_buildable(proto, name) {
+                errors.push(ValidationError::not_found(&milestone.name, "unlocks"));
+            }
+        }
+    }
+
+    for scenario in proto.scenario.values() {
+        for name in &scenario.locked {
+            if !is_buildable(proto, name) {
+                errors.push(ValidationError::not_found(&scenario.name, "locked"));
+            }
+        }
+
+        if scenario.objectives.is_empty() {
+            errors.push(ValidationError::new(
+                &scenario.name,
+                "objectives",
+                "must not be empty",
+            ));
+        }
+
+        for objective in &scenario.objectives {
+            if let ObjectiveKind::Export(item, _) = objective.kind {
+                if !proto.item.contains_key(&item) {
+                    errors.push(ValidationError::not_found(&scenario.name, "objectives"));
+                }
+            }
+        }
+    }
+
+    recipe_cycles(proto, &mut errors);
+
+    if let Some(base) = assets_base {
+        validate_assets(proto, base, &mut errors);
     }
 
     if !errors.is_empty() {
@@ -78,3 +214,239 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
     }
     Ok(())
 }
+
+/// Items made from themselves through the recipes, their price would depend on itself
+fn recipe_cycles(proto: &Prototypes, errors: &mut Vec<ValidationError>) {
+    // each produced item points to the items consumed to make it, with the company doing so
+    let mut made_from: BTreeMap<ItemID, Vec<(ItemID, &str)>> = BTreeMap::new();
+    for comp in proto.goods_company.values() {
+        let Some(ref r) = comp.recipe else {
+            continue;
+        };
+        for produced in &r.production {
+            for consumed in &r.consumption {
+                made_from
+                    .entry(produced.id)
+                    .or_default()
+                    .push((consumed.id, &comp.name));
+            }
+        }
+    }
+
+    let mut done = BTreeSet::new();
+    let mut path = vec![];
+    for &item in made_from.keys() {
+        visit_recipes(proto, &made_from, item, &mut path, &mut done, errors);
+    }
+}
+
+fn visit_recipes(
+    proto: &Prototypes,
+    made_from: &BTreeMap<ItemID, Vec<(ItemID, &str)>>,
+    item: ItemID,
+    path: &mut Vec<ItemID>,
+    done: &mut BTreeSet<ItemID>,
+    errors: &mut Vec<ValidationError>,
+) {
+    if done.contains(&item) {
+        return;
+    }
+    path.push(item);
+    for &(next, company) in made_from.get(&item).into_iter().flatten() {
+        let Some(start) = path.iter().position(|&x| x == next) else {
+            visit_recipes(proto, made_from, next, path, done, errors);
+            continue;
+        };
+        let names: Vec<&str> = path[start..]
+            .iter()
+            .chain([&next])
+            .map(|id| proto.item.get(id).map_or("?", |item| item.name.as_str()))
+            .collect();
+        errors.push(ValidationError::new(
+            company,
+            "recipe",
+            format!("circular dependency between items {}", names.join(" <- ")),
+        ));
+    }
+    path.pop();
+    done.insert(item);
+}
+
+/// Checks that the files used by the prototypes exist, `base` being the folder the game runs from
+fn validate_assets(proto: &Prototypes, base: &str, errors: &mut Vec<ValidationError>) {
+    let assets = proto
+        .building
+        .values()
+        .map(|b| (&b.name, &b.asset))
+        .chain(proto.freightstation.values().map(|f| (&f.name, &f.asset)))
+        .chain(
+            proto
+                .passenger_station
+                .values()
+                .map(|p| (&p.name, &p.asset)),
+        )
+        .chain(proto.vehicle.values().map(|v| (&v.name, &v.asset)));
+    for (name, asset) in assets {
+        let missing = match asset {
+            RenderAsset::Mesh { path } => !mesh_exists(base, path),
+            RenderAsset::Sprite { path } => !Path::new(base).join(path).exists(),
+        };
+        if missing {
+            errors.push(ValidationError::new(name, "asset", "file not found"));
+        }
+    }
+
+    for comp in proto.goods_company.values() {
+        let Some(ref zone) = comp.zone else {
+            continue;
+        };
+        if !Path::new(base).join(&zone.floor).exists() {
+            errors.push(ValidationError::new(
+                &comp.name,
+                "zone.floor",
+                format!("texture {} not found", zone.floor),
+            ));
+        }
+        if !mesh_exists(base, Path::new(&zone.filler)) {
+            errors.push(ValidationError::new(
+                &comp.name,
+                "zone.filler",
+                format!("model {} not found", zone.filler),
+            ));
+        }
+    }
+}
+
+/// Meshes are looked up in the models folder, see `engine::meshload`
+fn mesh_exists(base: &str, path: &Path) -> bool {
+    Path::new(base).join("assets/models").join(path).exists()
+}
+
+/// Whether the name is one of the things milestones and scenarios can lock
+fn is_buildable(proto: &Prototypes, name: &str) -> bool {
+    proto.building.contains_key(&BuildingPrototypeID::new(name))
+        || proto
+            .freightstation
+            .contains_key(&FreightStationPrototypeID::new(name))
+        || proto
+            .passenger_station
+            .contains_key(&PassengerStationPrototypeID::new(name))
+        || ROAD_TYPES.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValidationError;
+    use crate::validate_prototypes_str;
+
+    const ITEMS: &str = r#"data:extend {
+        { type = "item", name = "flour", label = "Flour" },
+        { type = "item", name = "bread", label = "Bread" },
+    }"#;
+
+    /// A factory making `production` from `consumption`, the extra fields replace the defaults
+    fn company(name: &str, consumption: &str, production: &str, extra: &str) -> String {
+        format!(
+            r#"data:extend {{{{
+                type = "goods-company",
+                name = "{name}",
+                label = "{name}",
+                kind = "factory",
+                bgen = "farm",
+                recipe = {{
+                    consumption = {{ {consumption} }},
+                    production = {{ {production} }},
+                    duration = "1m",
+                    storage_multiplier = 5,
+                }},
+                n_trucks = 1,
+                n_workers = 1,
+                size = 10.0,
+                asset = "bakery.glb",
+                price = 0,
+                {extra}
+            }}}}"#
+        )
+    }
+
+    fn errors(companies: &[String]) -> Vec<ValidationError> {
+        validate_prototypes_str(&format!("{}\n{}", ITEMS, companies.join("\n")), None)
+    }
+
+    #[test]
+    fn test_valid() {
+        let bakery = company("bakery", r#"{"flour", 1}"#, r#"{"bread", 1}"#, "");
+        assert_eq!(errors(&[bakery]), []);
+    }
+
+    #[test]
+    fn test_missing_item() {
+        let bakery = company("bakery", r#"{"sugar", 1}"#, r#"{"bread", 1}"#, "");
+        assert_eq!(
+            errors(&[bakery]),
+            [ValidationError::new(
+                "bakery",
+                "consumption",
+                "item 1 is not an item prototype"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_zero_production() {
+        let bakery = company("bakery", r#"{"flour", 1}"#, r#"{"bread", 0}"#, "");
+        assert_eq!(
+            errors(&[bakery]),
+            [ValidationError::new(
+                "bakery",
+                "production",
+                "item 1 must be produced in a positive amount"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_size() {
+        let bakery = company("bakery", "", r#"{"bread", 1}"#, "size = 0.0,");
+        assert_eq!(
+            errors(&[bakery]),
+            [ValidationError::new("bakery", "size", "must be positive")]
+        );
+    }
+
+    #[test]
+    fn test_recipe_cycle() {
+        let bakery = company("bakery", r#"{"flour", 1}"#, r#"{"bread", 1}"#, "");
+        let mill = company("mill", r#"{"bread", 1}"#, r#"{"flour", 1}"#, "");
+        let errors = errors(&[bakery, mill]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "recipe");
+        assert!(errors[0]
+            .message
+            .starts_with("circular dependency between items"));
+    }
+
+    #[test]
+    fn test_assets() {
+        let farm = company(
+            "farm",
+            "",
+            r#"{"flour", 1}"#,
+            r#"asset = "missing.glb",
+            zone = { floor = "assets/sprites/missing.jpg", filler = "wheat_up.glb" },"#,
+        );
+        let lua = format!("{}\n{}", ITEMS, farm);
+        assert_eq!(validate_prototypes_str(&lua, None), []);
+        assert_eq!(
+            validate_prototypes_str(&lua, Some("../")),
+            [
+                ValidationError::new("farm", "asset", "file not found"),
+                ValidationError::new(
+                    "farm",
+                    "zone.floor",
+                    "texture assets/sprites/missing.jpg not found"
+                ),
+            ]
+        );
+    }
+}
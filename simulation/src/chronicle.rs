@@ -0,0 +1,278 @@
+//! History of the city: the notable events are kept with their date so that players can look
+//! back on how the city grew. Unlike the notifications, the chronicle is saved.
+//! The systems record their events with [`record`], the oldest entries of the least important
+//! kinds are pruned once there are too many.
+
+use std::collections::BTreeSet;
+
+use geom::Vec3;
+use prototypes::{GameTime, Money, Tick, TICKS_PER_MINUTE};
+use serde::{Deserialize, Serialize};
+
+use crate::map::{BuildingKind, Map};
+use crate::souls::happiness::CityStats;
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// Entries kept in the chronicle, the least important ones are pruned first
+pub const MAX_CHRONICLE_ENTRIES: usize = 500;
+
+/// Population reached that is worth remembering
+pub const POPULATION_THRESHOLDS: &[u32] = &[
+    100, 250, 500, 1000, 2500, 5000, 10000, 25000, 50000, 100000, 250000, 500000, 1000000,
+];
+
+/// Trades worth at least this much are recorded
+pub const BIG_TRADE: Money = Money::new_bucks(10000);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ChronicleKind {
+    Milestone,
+    Population,
+    FirstBuilding,
+    Disaster,
+    Bankruptcy,
+    Trade,
+}
+
+impl ChronicleKind {
+    pub const ALL: [ChronicleKind; 6] = [
+        ChronicleKind::Milestone,
+        ChronicleKind::Population,
+        ChronicleKind::FirstBuilding,
+        ChronicleKind::Disaster,
+        ChronicleKind::Bankruptcy,
+        ChronicleKind::Trade,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChronicleKind::Milestone => "Milestones",
+            ChronicleKind::Population => "Population",
+            ChronicleKind::FirstBuilding => "First buildings",
+            ChronicleKind::Disaster => "Disasters",
+            ChronicleKind::Bankruptcy => "Bankruptcies",
+            ChronicleKind::Trade => "Big trades",
+        }
+    }
+
+    /// Entries of the least important kinds are pruned first
+    pub fn importance(self) -> u8 {
+        match self {
+            ChronicleKind::Milestone | ChronicleKind::Population => 4,
+            ChronicleKind::FirstBuilding => 3,
+            ChronicleKind::Disaster => 2,
+            ChronicleKind::Bankruptcy => 1,
+            ChronicleKind::Trade => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChronicleEntry {
+    pub tick: Tick,
+    pub kind: ChronicleKind,
+    pub text: String,
+    /// Where it happened, for the camera to go there
+    pub pos: Option<Vec3>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Chronicle {
+    /// From the oldest to the newest
+    entries: Vec<ChronicleEntry>,
+    /// Names of the kinds of buildings that were built at least once
+    built: BTreeSet<String>,
+    /// Highest population threshold reached
+    population: u32,
+}
+
+impl Chronicle {
+    pub fn record(
+        &mut self,
+        tick: Tick,
+        kind: ChronicleKind,
+        text: impl Into<String>,
+        pos: Option<Vec3>,
+    ) {
+        self.entries.push(ChronicleEntry {
+            tick,
+            kind,
+            text: text.into(),
+            pos,
+        });
+        if self.entries.len() > MAX_CHRONICLE_ENTRIES {
+            self.prune();
+        }
+    }
+
+    /// Removes the oldest entry of the least important kind
+    fn prune(&mut self) {
+        let Some(least) = self.entries.iter().map(|e| e.kind.importance()).min() else {
+            return;
+        };
+        if let Some(i) = self
+            .entries
+            .iter()
+            .position(|e| e.kind.importance() == least)
+        {
+            self.entries.remove(i);
+        }
+    }
+
+    pub fn entries(&self) -> &[ChronicleEntry] {
+        &self.entries
+    }
+
+    /// Whether the kind of building of this name was built before, marks it as built
+    fn first_built(&mut self, name: &str) -> bool {
+        if self.built.contains(name) {
+            return false;
+        }
+        self.built.insert(name.to_string());
+        true
+    }
+}
+
+/// Records an event in the chronicle of the city, dated with the current tick
+pub fn record(
+    resources: &Resources,
+    kind: ChronicleKind,
+    text: impl Into<String>,
+    pos: Option<Vec3>,
+) {
+    let tick = resources.read::<GameTime>().tick;
+    resources.write::<Chronicle>().record(tick, kind, text, pos);
+}
+
+/// Name and label of the kind of building, None for the buildings not worth mentioning
+fn building_kind_name(kind: BuildingKind) -> Option<(String, String)> {
+    let (name, label) = match kind {
+        BuildingKind::House => ("house".to_string(), "House".to_string()),
+        BuildingKind::TrainStation => ("train_station".to_string(), "Train station".to_string()),
+        BuildingKind::ExternalTrading => return None,
+        BuildingKind::GoodsCompany(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::RailFreightStation(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::RailPassengerStation(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::Warehouse(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::School(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::Leisure(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::Hotel(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::Harbor(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+        BuildingKind::Airport(id) => {
+            let p = id.prototype();
+            (p.name.clone(), p.label.clone())
+        }
+    };
+    Some((name, label))
+}
+
+/// Every minute, looks for the population thresholds reached and the first building of each kind
+pub fn chronicle_system(_: &mut World, resources: &mut Resources) {
+    let tick = resources.read::<GameTime>().tick;
+    if tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    profiling::scope!("chronicle::chronicle_system");
+
+    let mut chronicle = resources.write::<Chronicle>();
+    let population = resources.read::<CityStats>().population;
+    for &threshold in POPULATION_THRESHOLDS {
+        if population < threshold || chronicle.population >= threshold {
+            continue;
+        }
+        chronicle.population = threshold;
+        chronicle.record(
+            tick,
+            ChronicleKind::Population,
+            format!("The city reached {} inhabitants", threshold),
+            None,
+        );
+    }
+
+    let map = resources.read::<Map>();
+    for b in map.buildings().values() {
+        let Some((name, label)) = building_kind_name(b.kind) else {
+            continue;
+        };
+        if chronicle.first_built(&name) {
+            chronicle.record(
+                tick,
+                ChronicleKind::FirstBuilding,
+                format!("First {} built", label),
+                Some(b.door_pos),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::Tick;
+
+    use super::{Chronicle, ChronicleKind, MAX_CHRONICLE_ENTRIES};
+
+    #[test]
+    fn pruning_keeps_important_entries() {
+        let mut c = Chronicle::default();
+        c.record(Tick(1), ChronicleKind::Milestone, "Village", None);
+        c.record(Tick(2), ChronicleKind::Disaster, "Fire", None);
+        for i in 0..2 * MAX_CHRONICLE_ENTRIES as u64 {
+            c.record(Tick(3 + i), ChronicleKind::Trade, "Big trade", None);
+        }
+        c.record(Tick(10000), ChronicleKind::Population, "1000", None);
+
+        assert_eq!(c.entries().len(), MAX_CHRONICLE_ENTRIES);
+        assert_eq!(c.entries()[0].text, "Village");
+        assert_eq!(c.entries()[1].text, "Fire");
+        assert_eq!(c.entries().last().unwrap().text, "1000");
+        // the oldest trades went first
+        assert_eq!(
+            c.entries()[2].tick,
+            Tick(3 + MAX_CHRONICLE_ENTRIES as u64 + 3)
+        );
+    }
+
+    #[test]
+    fn spam_of_important_entries_drops_the_least_important_first() {
+        let mut c = Chronicle::default();
+        for i in 0..MAX_CHRONICLE_ENTRIES as u64 {
+            c.record(Tick(i), ChronicleKind::Bankruptcy, "Bankrupt", None);
+        }
+        c.record(Tick(1000), ChronicleKind::Milestone, "Town", None);
+        c.record(Tick(1001), ChronicleKind::Disaster, "Fire", None);
+
+        let n_bankruptcies = c
+            .entries()
+            .iter()
+            .filter(|e| e.kind == ChronicleKind::Bankruptcy)
+            .count();
+        assert_eq!(n_bankruptcies, MAX_CHRONICLE_ENTRIES - 2);
+        assert!(c.entries().iter().any(|e| e.text == "Town"));
+        assert!(c.entries().iter().any(|e| e.text == "Fire"));
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use geom::{Color, Transform};
+use serde::{Deserialize, Serialize};
+use slotmapd::HopSlotMap;
+
+use prototypes::{GameTime, ItemID, Money, Tick, TICKS_PER_HOUR};
+
+use crate::economy::Market;
+use crate::map::{BuildingID, IntersectionID, Map, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleKind, VehicleState};
+use crate::world::{CompanyEnt, CompanyID, VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+/// Units of goods a road connection can let through during an in-game hour
+pub const ROAD_CONNECTION_THROUGHPUT: u32 = 500;
+
+/// Seconds a truck stays at the building to load or unload
+const TRUCK_STOP_SECONDS: f64 = 30.0;
+
+/// Goods carried by a truck for a soul of the city
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Cargo {
+    pub soul: SoulID,
+    pub kind: ItemID,
+    pub qty: i32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TruckLeg {
+    /// Going from the connection to the building
+    Arriving,
+    /// Loading or unloading at the building
+    Stopped,
+    /// Going back to the connection, where it disappears
+    Leaving,
+}
+
+/// Goods going through a road connection, carried by a single truck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shipment {
+    pub connection: IntersectionID,
+    pub building: BuildingID,
+    /// Imported goods are given to the buyers when the truck reaches the building
+    pub import: bool,
+    pub cargo: Vec<Cargo>,
+    pub leg: TruckLeg,
+    /// What the sellers earn for the exported goods, paid when the truck leaves the building
+    pub earned: Money,
+}
+
+/// External trades going through the road connections of the map.
+/// Each connection has a limited throughput, and the goods are carried by trucks between the
+/// border and the building trading with the outside.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BorderTrade {
+    used: BTreeMap<IntersectionID, u32>,
+    /// Whether the building can be reached from the connection and back, cleared every hour
+    reachable: BTreeMap<(BuildingID, IntersectionID), bool>,
+    hour: u64,
+    /// Shipments waiting for their truck to be spawned
+    pending: Vec<Shipment>,
+    pub trucks: BTreeMap<VehicleID, Shipment>,
+}
+
+impl BorderTrade {
+    /// Resets the counters when a new in-game hour starts
+    pub fn advance(&mut self, tick: Tick) {
+        let hour = tick.0 / TICKS_PER_HOUR;
+        if hour != self.hour {
+            self.hour = hour;
+            self.used.clear();
+            self.reachable.clear();
+        }
+    }
+
+    /// Units that went through the connection during the current hour
+    pub fn used(&self, connection: IntersectionID) -> u32 {
+        self.used.get(&connection).copied().unwrap_or(0)
+    }
+
+    /// Tries to reserve qty units of throughput at the connection, returns whether it succeeded.
+    /// Like freight stations, a connection that wasn't used this hour always accepts.
+    pub fn try_reserve(&mut self, connection: IntersectionID, qty: u32) -> bool {
+        let used = self.used.entry(connection).or_default();
+        if *used > 0 && *used + qty > ROAD_CONNECTION_THROUGHPUT {
+            return false;
+        }
+        *used += qty;
+        true
+    }
+
+    /// Whether trucks can drive from the connection to the building and back
+    pub fn can_reach(
+        &mut self,
+        map: &Map,
+        tick: Tick,
+        building: BuildingID,
+        connection: IntersectionID,
+    ) -> bool {
+        *self
+            .reachable
+            .entry((building, connection))
+            .or_insert_with(|| {
+                let Some(b) = map.buildings().get(building) else {
+                    return false;
+                };
+                let Some(inter) = map.intersections().get(connection) else {
+                    return false;
+                };
+                Itinerary::route(tick, inter.pos, b.door_pos, map, PathKind::Vehicle).is_some()
+                    && Itinerary::route(tick, b.door_pos, inter.pos, map, PathKind::Vehicle)
+                        .is_some()
+            })
+    }
+
+    /// Queues goods to be carried through the connection.
+    /// The cargo of the trades made during the same tick for the same building share a truck.
+    pub fn ship(
+        &mut self,
+        connection: IntersectionID,
+        building: BuildingID,
+        import: bool,
+        cargo: Cargo,
+        earned: Money,
+    ) {
+        if let Some(s) = self
+            .pending
+            .iter_mut()
+            .find(|s| s.connection == connection && s.building == building && s.import == import)
+        {
+            s.cargo.push(cargo);
+            s.earned += earned;
+            return;
+        }
+        self.pending.push(Shipment {
+            connection,
+            building,
+            import,
+            cargo: vec![cargo],
+            leg: TruckLeg::Arriving,
+            earned,
+        });
+    }
+
+    pub fn n_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Spawns the trucks of the new shipments and moves them between the border and the buildings
+pub fn border_trade_system(sim: &mut Simulation) {
+    profiling::scope!("economy::border_trade_system");
+    let pending = std::mem::take(&mut sim.write::<BorderTrade>().pending);
+
+    for shipment in pending {
+        let spawn = {
+            let map = sim.map();
+            let door = map.buildings().get(shipment.building).map(|b| b.door_pos);
+            map.intersections()
+                .get(shipment.connection)
+                .zip(door)
+                .map(|(inter, door)| {
+                    let road = inter.roads.first().and_then(|&r| map.roads().get(r));
+                    let trans = match road {
+                        Some(road) => Transform::new_dir(
+                            road.interface_point(inter.id),
+                            road.dir_from(inter.id).z0(),
+                        ),
+                        None => Transform::new(inter.pos),
+                    };
+                    (trans, door)
+                })
+        };
+
+        let Some((trans, door)) = spawn else {
+            // the connection or the building disappeared, the goods are there already
+            deliver(sim, &shipment);
+            continue;
+        };
+
+        let vehicle = Vehicle {
+            ang_velocity: 0.0,
+            wait_time: 0.0,
+            max_speed_multiplier: 1.0,
+            state: VehicleState::Driving,
+            kind: VehicleKind::Truck,
+            prototype: VehicleKind::Truck.default_prototype().id,
+            tint: Color::WHITE,
+            flag: 0,
+        };
+        let it = Itinerary::wait_for_reroute(PathKind::Vehicle, door);
+        let id = make_vehicle_entity(sim, trans, vehicle, it, true);
+
+        sim.write::<BorderTrade>().trucks.insert(id, shipment);
+    }
+
+    let now = sim.read::<GameTime>().timestamp;
+    let mut border = sim.resources.write::<BorderTrade>();
+    let mut market = sim.resources.write::<Market>();
+    let map = sim.resources.read::<Map>();
+    let cbuf = sim.resources.read::<ParCommandBuffer<VehicleEnt>>();
+    let world = &mut sim.world;
+
+    border.trucks.retain(|&id, shipment| {
+        let Some(v) = world.vehicles.get_mut(id) else {
+            match shipment.leg {
+                TruckLeg::Arriving if shipment.import => receive(&mut market, shipment),
+                TruckLeg::Arriving | TruckLeg::Stopped if !shipment.import => {
+                    pay(&mut world.companies, shipment)
+                }
+                _ => {}
+            }
+            return false;
+        };
+        if !v.it.has_ended(now) {
+            return true;
+        }
+        match shipment.leg {
+            TruckLeg::Arriving => {
+                if shipment.import {
+                    receive(&mut market, shipment);
+                }
+                v.it = Itinerary::wait_until(now + TRUCK_STOP_SECONDS);
+                shipment.leg = TruckLeg::Stopped;
+                true
+            }
+            TruckLeg::Stopped => {
+                if !shipment.import {
+                    pay(&mut world.companies, shipment);
+                }
+                let Some(inter) = map.intersections().get(shipment.connection) else {
+                    cbuf.kill(id);
+                    return false;
+                };
+                v.it = Itinerary::wait_for_reroute(PathKind::Vehicle, inter.pos);
+                shipment.leg = TruckLeg::Leaving;
+                true
+            }
+            TruckLeg::Leaving => {
+                cbuf.kill(id);
+                false
+            }
+        }
+    });
+}
+
+fn deliver(sim: &mut Simulation, shipment: &Shipment) {
+    if shipment.import {
+        receive(&mut sim.write::<Market>(), shipment);
+    } else {
+        pay(&mut sim.world.companies, shipment);
+    }
+}
+
+/// Gives the money of the exported goods to the seller once they left the building
+fn pay(companies: &mut HopSlotMap<CompanyID, CompanyEnt>, shipment: &Shipment) {
+    let Some(&Cargo {
+        soul: SoulID::GoodsCompany(id),
+        ..
+    }) = shipment.cargo.first()
+    else {
+        return;
+    };
+    if let Some(c) = companies.get_mut(id) {
+        c.finances.balance += shipment.earned;
+    }
+}
+
+fn receive(market: &mut Market, shipment: &Shipment) {
+    for c in &shipment.cargo {
+        market.receive(c.soul, c.kind, c.qty);
+    }
+}
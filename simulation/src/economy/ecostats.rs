@@ -6,7 +6,6 @@ use serde_big_array::BigArray;
 use prototypes::{prototypes_iter, ItemPrototype, Money};
 
 use crate::economy::{ItemID, Trade};
-use crate::SoulID;
 
 pub const HISTORY_SIZE: usize = 128;
 /// Tick to wait before the new bin
@@ -112,11 +111,11 @@ impl EcoStats {
         self.internal_trade.advance(tick);
 
         for trade in trades {
-            if matches!(trade.buyer.0, SoulID::FreightStation(_)) {
+            if trade.buyer.0.is_external() {
                 self.exports.handle_trade(trade);
                 continue;
             }
-            if matches!(trade.seller.0, SoulID::FreightStation(_)) {
+            if trade.seller.0.is_external() {
                 self.imports.handle_trade(trade);
                 continue;
             }
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, Money, TICKS_PER_HOUR};
+
+use crate::economy::Government;
+use crate::map::{BuildingID, ElectricityNetworkID, Map};
+use crate::map_dynamic::{BuildingEnergy, BuildingInfos, ElectricityFlow};
+use crate::utils::resources::Resources;
+use crate::{SoulID, World};
+
+/// Once per in-game day, the consumers of electricity pay for the energy they used
+/// and the owners of the plants of the same network are paid for the energy they produced.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ElectricityBilling {
+    last_day: i32,
+    /// Total paid for electricity on the last billing day
+    pub last_total: Money,
+}
+
+/// Price of the given energy in watt-ticks
+pub fn energy_cost(watt_ticks: i64, price_per_kwh: Money) -> Money {
+    let watt_ticks_per_kwh = TICKS_PER_HOUR as i128 * 1000;
+    Money::new_inner(
+        (watt_ticks as i128 * price_per_kwh.inner() as i128 / watt_ticks_per_kwh) as i64,
+    )
+}
+
+/// Computes the money exchanged inside a single network.
+/// Returns the balance change of each building: negative for consumers, positive for producers.
+/// The sum is always zero, producers share what the consumers paid proportionally to the energy they produced.
+pub fn network_bills(
+    energy: &[(BuildingID, BuildingEnergy)],
+    price_per_kwh: Money,
+) -> Vec<(BuildingID, Money)> {
+    let total_produced: i64 = energy.iter().map(|(_, e)| e.produced).sum();
+    if total_produced == 0 {
+        // nobody to pay
+        return Vec::new();
+    }
+
+    let mut bills = Vec::with_capacity(energy.len());
+    let mut total_paid = Money::ZERO;
+
+    for &(building, e) in energy {
+        let cost = energy_cost(e.consumed, price_per_kwh);
+        if cost > Money::ZERO {
+            bills.push((building, -cost));
+            total_paid += cost;
+        }
+    }
+
+    let mut distributed = Money::ZERO;
+    let mut last_producer = None;
+    for &(building, e) in energy {
+        if e.produced == 0 {
+            continue;
+        }
+        let share = Money::new_inner(
+            (total_paid.inner() as i128 * e.produced as i128 / total_produced as i128) as i64,
+        );
+        distributed += share;
+        last_producer = Some(bills.len());
+        bills.push((building, share));
+    }
+
+    // rounding leftovers go to the last producer so that no money is created or destroyed
+    if let Some(i) = last_producer {
+        bills[i].1 += total_paid - distributed;
+    }
+
+    bills
+}
+
+/// Bills the electricity consumed during the day.
+/// Households are billed through their owner's wallet, companies through their finances.
+/// Buildings without anyone to pay or be paid are ignored.
+pub fn electricity_billing_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("economy::electricity_billing_system");
+    let day = resources.read::<GameTime>().daytime.day;
+    let mut billing = resources.write::<ElectricityBilling>();
+    if billing.last_day == day {
+        return;
+    }
+    billing.last_day = day;
+
+    let energy = resources.write::<ElectricityFlow>().take_energy();
+    let price = resources.read::<Government>().electricity_price;
+    let map = resources.read::<Map>();
+    let binfos = resources.read::<BuildingInfos>();
+
+    let mut per_network: BTreeMap<ElectricityNetworkID, Vec<(BuildingID, BuildingEnergy)>> =
+        BTreeMap::new();
+
+    for (building, e) in energy {
+        let Some(net) = map.electricity.net_id(building) else {
+            continue;
+        };
+        let billable = match binfos.owner(building) {
+            Some(SoulID::Human(id)) => world.humans.contains_key(id),
+            Some(SoulID::GoodsCompany(id)) => world.companies.contains_key(id),
+            _ => false,
+        };
+        if !billable {
+            continue;
+        }
+        per_network.entry(net).or_default().push((building, e));
+    }
+
+    let mut total = Money::ZERO;
+
+    for energy in per_network.values() {
+        for (building, delta) in network_bills(energy, price) {
+            if delta < Money::ZERO {
+                total -= delta;
+            }
+            match binfos.owner(building) {
+                Some(SoulID::Human(id)) => {
+                    if let Some(h) = world.humans.get_mut(id) {
+                        h.wallet.0 += delta;
+                    }
+                }
+                Some(SoulID::GoodsCompany(id)) => {
+                    if let Some(c) = world.companies.get_mut(id) {
+                        c.finances.balance += delta;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    billing.last_total = total;
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{Money, Power, TICKS_PER_HOUR};
+
+    use crate::map::BuildingID;
+    use crate::map_dynamic::{BuildingEnergy, BuildingFlow, ElectricityFlow};
+
+    use super::network_bills;
+
+    fn mk_building(id: u64) -> BuildingID {
+        BuildingID::from(slotmapd::KeyData::from_ffi((1 << 32) | id))
+    }
+
+    #[test]
+    fn consumers_pay_producers() {
+        let plant = mk_building(1);
+        let house = mk_building(2);
+        let factory = mk_building(3);
+
+        let mut flow = ElectricityFlow::default();
+
+        // one day: the plant has enough power for 20 hours, then there is a blackout
+        for hour in 0..24 {
+            let blackout = hour >= 20;
+            for _ in 0..TICKS_PER_HOUR {
+                flow.record(
+                    plant,
+                    BuildingFlow {
+                        consumption: Power::ZERO,
+                        production: Power::new(10_000),
+                        ..Default::default()
+                    },
+                    blackout,
+                );
+                flow.record(
+                    house,
+                    BuildingFlow {
+                        consumption: Power::new(1_000),
+                        production: Power::ZERO,
+                        ..Default::default()
+                    },
+                    blackout,
+                );
+                flow.record(
+                    factory,
+                    BuildingFlow {
+                        consumption: Power::new(4_000),
+                        production: Power::ZERO,
+                        ..Default::default()
+                    },
+                    blackout,
+                );
+            }
+        }
+
+        assert_eq!(flow.building_consumption(factory), Power::new(4_000));
+        assert_eq!(flow.building_production(plant), Power::new(10_000));
+
+        let energy: Vec<_> = flow.take_energy().into_iter().collect();
+        assert_eq!(flow.energy(house).consumed, 0);
+
+        let price = Money::new_cents(15);
+        let bills = network_bills(&energy, price);
+
+        let bill = |b| {
+            bills
+                .iter()
+                .find(|(id, _)| *id == b)
+                .map_or(Money::ZERO, |x| x.1)
+        };
+
+        // blackout hours are not billed: 20 hours at 1kW and 4kW
+        assert_eq!(bill(house), -Money::new_bucks(3));
+        assert_eq!(bill(factory), -Money::new_bucks(12));
+
+        let paid: Money = bills
+            .iter()
+            .filter(|x| x.1 < Money::ZERO)
+            .map(|x| -x.1)
+            .sum();
+        let earned: Money = bills
+            .iter()
+            .filter(|x| x.1 > Money::ZERO)
+            .map(|x| x.1)
+            .sum();
+        assert_eq!(paid, earned);
+        assert_eq!(earned, bill(plant));
+    }
+
+    #[test]
+    fn income_shared_between_producers() {
+        let small = mk_building(1);
+        let big = mk_building(2);
+        let house = mk_building(3);
+
+        let wh = TICKS_PER_HOUR as i64;
+        let energy = [
+            (
+                small,
+                BuildingEnergy {
+                    consumed: 0,
+                    produced: 1000 * wh,
+                },
+            ),
+            (
+                big,
+                BuildingEnergy {
+                    consumed: 0,
+                    produced: 2000 * wh,
+                },
+            ),
+            (
+                house,
+                BuildingEnergy {
+                    consumed: 1001 * wh,
+                    produced: 0,
+                },
+            ),
+        ];
+
+        let bills = network_bills(&energy, Money::new_cents(10));
+        let total: Money = bills.iter().map(|x| x.1).sum();
+        assert_eq!(total, Money::ZERO);
+
+        let small_income = bills.iter().find(|x| x.0 == small).unwrap().1;
+        let big_income = bills.iter().find(|x| x.0 == big).unwrap().1;
+        assert!(big_income >= small_income * 2);
+    }
+}
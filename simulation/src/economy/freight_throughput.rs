@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{Tick, TICKS_PER_HOUR};
+
+use crate::map::BuildingID;
+
+/// Tracks how many units of external trade each freight station handled during the current in-game hour.
+/// Stations have a limited throughput, trades exceeding it are deferred by the market.
+#[derive(Default, Serialize, Deserialize)]
+pub struct FreightThroughput {
+    used: BTreeMap<BuildingID, u32>,
+    hour: u64,
+}
+
+impl FreightThroughput {
+    /// Resets the counters when a new in-game hour starts
+    pub fn advance(&mut self, tick: Tick) {
+        let hour = tick.0 / TICKS_PER_HOUR;
+        if hour != self.hour {
+            self.hour = hour;
+            self.used.clear();
+        }
+    }
+
+    /// Units handled by the station during the current hour
+    pub fn used(&self, station: BuildingID) -> u32 {
+        self.used.get(&station).copied().unwrap_or(0)
+    }
+
+    /// Ratio of the capacity used during the current hour, in [0; 1]
+    pub fn utilization(&self, station: BuildingID, capacity: u32) -> f32 {
+        if capacity == 0 {
+            return 1.0;
+        }
+        (self.used(station) as f32 / capacity as f32).min(1.0)
+    }
+
+    /// Tries to reserve qty units of throughput at the station, returns whether it succeeded.
+    /// A station that wasn't used this hour always accepts, so orders bigger than the capacity
+    /// can still go through.
+    pub fn try_reserve(&mut self, station: BuildingID, capacity: u32, qty: u32) -> bool {
+        let used = self.used.entry(station).or_default();
+        if *used > 0 && *used + qty > capacity {
+            return false;
+        }
+        *used += qty;
+        true
+    }
+}
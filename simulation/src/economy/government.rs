@@ -1,31 +1,128 @@
-use crate::map::{LanePattern, MapProject, MAX_ZONE_AREA};
+use crate::economy::{BudgetReason, Ledger, MAX_LOAN};
+use crate::gameplay::GameplayParams;
+use crate::map::{
+    BulldozeSelection, Environment, LanePattern, Map, MapProject, Road, RoadSegmentKind,
+    MAX_ZONE_AREA, TUNNEL_DEPTH,
+};
+use crate::map_dynamic::Fires;
+use crate::transportation::transit::Transit;
 use crate::world_command::WorldCommand;
 use crate::{BuildingKind, Simulation};
-use prototypes::Money;
+use geom::{PolyLine3, Vec2};
+use prototypes::{Money, DEFAULT_ELECTRICITY_PRICE};
 use serde::{Deserialize, Serialize};
 
+/// Share of the build price given back when bulldozing
+pub const BULLDOZE_REFUND_RATIO: f64 = 0.5;
+
+/// Price of a meter of pillar holding a bridge
+const PILLAR_PRICE_PER_M: f32 = 1.0;
+
+/// Price of a meter of tunnel, digging is expensive
+const TUNNEL_PRICE_PER_M: f32 = 5.0;
+
+/// Price of a meter of lane, in cents
+const LANE_PRICE_PER_M_CENTS: i64 = 3;
+
 /// The government represents the player.
 #[derive(Serialize, Deserialize)]
 pub struct Government {
+    /// Only changed through [`Government::transact`], so that the ledger accounts for it
     pub money: Money,
+    /// Total money collected from import and export tariffs
+    pub tariff_income: Money,
+    /// Price of one kilowatt-hour of electricity, paid by consumers to producers
+    pub electricity_price: Money,
+    /// Total money spent on the player's actions since the start of the game, refunds excluded
+    pub construction_spending: Money,
+    /// Every change of the money, by day and by reason
+    pub ledger: Ledger,
+    /// Money borrowed and not repaid yet, interests are paid on it every day
+    pub loan: Money,
 }
 
 impl Default for Government {
     fn default() -> Self {
+        let money = Money::new_bucks(150_000);
         Self {
-            money: Money::new_bucks(150_000),
+            money,
+            tariff_income: Money::ZERO,
+            electricity_price: DEFAULT_ELECTRICITY_PRICE,
+            construction_spending: Money::ZERO,
+            ledger: Ledger::new(money),
+            loan: Money::ZERO,
         }
     }
 }
 
 impl Government {
+    /// Receives the amount, or spends it if negative, and records why in the ledger
+    pub fn transact(&mut self, reason: BudgetReason, amount: Money) {
+        if amount == Money::ZERO {
+            return;
+        }
+        self.money += amount;
+        self.ledger.record(reason, amount);
+    }
+
+    /// Gives or takes what is needed for the government to have this money
+    pub fn set_money(&mut self, reason: BudgetReason, money: Money) {
+        self.transact(reason, money - self.money);
+    }
+
+    /// Starting money of a new game, the ledger starts over from it
+    pub fn reset_money(&mut self, money: Money) {
+        self.money = money;
+        self.ledger = Ledger::new(money);
+    }
+
+    /// Borrows up to the amount, without owing more than [`MAX_LOAN`]
+    pub fn take_loan(&mut self, amount: Money) {
+        let amount = amount.min(MAX_LOAN - self.loan).max(Money::ZERO);
+        self.loan += amount;
+        self.transact(BudgetReason::Loan, amount);
+    }
+
+    /// Repays up to the amount, without repaying more than what is owed or what the government has
+    pub fn repay_loan(&mut self, amount: Money) {
+        let amount = amount.min(self.loan).min(self.money).max(Money::ZERO);
+        self.loan -= amount;
+        self.transact(BudgetReason::LoanRepayment, -amount);
+    }
+
+    /// Why the money changes when the action is applied, given its cost
+    pub fn action_reason(action: &WorldCommand, cost: Money) -> BudgetReason {
+        if cost < Money::ZERO {
+            return BudgetReason::Refunds;
+        }
+        match action {
+            WorldCommand::MapMakeConnection { .. }
+            | WorldCommand::MapMakeMultipleConnections(..)
+            | WorldCommand::MapSetRoadPattern { .. } => BudgetReason::Roads,
+            WorldCommand::AddTrain { .. }
+            | WorldCommand::AddBusStop(_)
+            | WorldCommand::UpdateBusLine { .. } => BudgetReason::Transit,
+            WorldCommand::MapPlantTrees { .. } => BudgetReason::Trees,
+            _ => BudgetReason::Buildings,
+        }
+    }
+
+    /// Price paid for the action, scaled by the building cost multiplier of the gameplay parameters
     pub fn action_cost(action: &WorldCommand, sim: &Simulation) -> Money {
+        Self::base_action_cost(action, sim)
+            * sim.read::<GameplayParams>().building_cost_multiplier as f64
+    }
+
+    fn base_action_cost(action: &WorldCommand, sim: &Simulation) -> Money {
         Money::new_bucks(match action {
-            WorldCommand::MapBuildHouse(_) => 100,
+            WorldCommand::MapBuildHouse(_) => return Self::building_price(BuildingKind::House),
             WorldCommand::AddTrain { n_wagons, .. } => 1000 + 100 * (*n_wagons as i64),
-            WorldCommand::MapMakeConnection { from, to, pat, .. } => {
-                Self::connection_cost(from, to, pat)
-            }
+            WorldCommand::MapMakeConnection {
+                from,
+                to,
+                inter,
+                pat,
+            } => Self::connection_cost(&sim.map(), from, to, *inter, pat),
             WorldCommand::UpdateZone {
                 building: bid,
                 zone: z,
@@ -49,35 +146,232 @@ impl Government {
                 return (newarea - oldarea) as i64 * zonedescr.price_per_area
                     / MAX_ZONE_AREA as i64;
             }
+            WorldCommand::MapSetRoadPattern { road, pattern } => {
+                let m = sim.map();
+                let Some(r) = m.roads.get(*road) else {
+                    return Money::ZERO;
+                };
+                let old = r.pattern(&m.lanes);
+                Self::lanes_cost(r.length(), pattern) - Self::lanes_cost(r.length(), &old)
+            }
             WorldCommand::MapMakeMultipleConnections(ref projs, ref links) => {
                 let mut total = 0;
-                for (from, to, _, pat) in links.iter() {
-                    total += Self::connection_cost(&projs[*from], &projs[*to], pat);
+                let m = sim.map();
+                for (from, to, inter, pat) in links.iter() {
+                    total += Self::connection_cost(&m, &projs[*from], &projs[*to], *inter, pat);
                 }
                 total
             }
-            WorldCommand::MapBuildSpecialBuilding { kind: x, .. } => match x {
-                BuildingKind::GoodsCompany(x) => {
-                    let descr = x.prototype();
-                    let mut price = descr.price;
-                    if let Some(ref z) = descr.zone {
-                        price += z.price_per_area * descr.size.area() as i64 / MAX_ZONE_AREA as i64;
-                    }
-                    return price;
-                }
-                BuildingKind::RailFreightStation(x) => {
-                    return x.prototype().price;
-                }
-                BuildingKind::TrainStation => 1000,
-                _ => 0,
-            },
+            WorldCommand::MapBuildSpecialBuilding { kind, .. } => {
+                return Self::building_price(*kind)
+            }
+            WorldCommand::MapBulldozeArea { area, filter } => {
+                let m = sim.map();
+                return -Self::bulldoze_refund(
+                    &m,
+                    &sim.read::<Fires>(),
+                    &m.bulldoze_selection(*area, *filter),
+                );
+            }
+            WorldCommand::AddBusStop(_) => 500,
+            WorldCommand::UpdateBusLine { line, n_buses, .. } => {
+                let current = sim
+                    .read::<Transit>()
+                    .lines()
+                    .get(*line)
+                    .map_or(0, |l| l.n_buses);
+                2000 * n_buses.saturating_sub(current) as i64
+            }
+            WorldCommand::MapPlantTrees { trees } => {
+                let m = sim.map();
+                return trees
+                    .iter()
+                    .filter(|(pos, _)| m.can_plant_tree(*pos))
+                    .map(|(_, species)| species.prototype().price)
+                    .sum();
+            }
             _ => 0,
         })
     }
 
-    fn connection_cost(p1: &MapProject, p2: &MapProject, pat: &LanePattern) -> i64 {
-        let dist = p1.pos.distance(p2.pos);
-        50 + ((0.03 * dist) as i64).max(1)
-            * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
+    /// Price paid to build a building of this kind
+    pub fn building_price(kind: BuildingKind) -> Money {
+        Money::new_bucks(match kind {
+            BuildingKind::GoodsCompany(x) => {
+                let descr = x.prototype();
+                let mut price = descr.price;
+                if let Some(ref z) = descr.zone {
+                    price += z.price_per_area * descr.size.area() as i64 / MAX_ZONE_AREA as i64;
+                }
+                return price;
+            }
+            BuildingKind::RailFreightStation(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::RailPassengerStation(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Warehouse(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::School(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Leisure(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Hotel(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Harbor(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Airport(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::House => 100,
+            BuildingKind::TrainStation => 1000,
+            BuildingKind::ExternalTrading => 0,
+        })
+    }
+
+    /// Money given back for bulldozing the selection, a share of what it cost to build.
+    /// Burned down buildings are worth nothing.
+    pub fn bulldoze_refund(map: &Map, fires: &Fires, sel: &BulldozeSelection) -> Money {
+        let roads: i64 = sel
+            .roads
+            .iter()
+            .filter_map(|&id| map.roads.get(id))
+            .map(|r| Self::road_cost(r.points(), &map.environment, &r.pattern(&map.lanes)))
+            .sum();
+        let buildings: Money = sel
+            .buildings
+            .iter()
+            .filter(|&&id| !fires.is_ruin(id))
+            .filter_map(|&id| map.buildings.get(id))
+            .map(|b| Self::building_price(b.kind))
+            .sum();
+        (Money::new_bucks(roads) + buildings) * BULLDOZE_REFUND_RATIO
+    }
+
+    fn connection_cost(
+        map: &Map,
+        p1: &MapProject,
+        p2: &MapProject,
+        inter: Option<Vec2>,
+        pat: &LanePattern,
+    ) -> i64 {
+        let segment = match inter {
+            Some(x) => RoadSegmentKind::from_elbow(p1.pos.xy(), p2.pos.xy(), x),
+            None => RoadSegmentKind::Straight,
+        };
+        let (points, _) = Road::generate_points(
+            p1.pos,
+            p2.pos,
+            segment,
+            pat.lanes().any(|(kind, _, _)| kind.is_rail()),
+            &map.environment,
+        );
+        Self::road_cost(&points, &map.environment, pat)
+    }
+
+    /// Cost of a road following these points, including the pillars of bridges and the tunnels
+    pub fn road_cost(points: &PolyLine3, env: &Environment, pat: &LanePattern) -> i64 {
+        let mut structure = 0.0;
+        for pylon in Road::pylons_positions(points, env) {
+            structure += (pylon.pos.z - pylon.terrain_height).max(0.0) * PILLAR_PRICE_PER_M;
+        }
+
+        let (mut samples, mut underground) = (0, 0);
+        for (_, clearance) in Road::clearance(points, env) {
+            samples += 1;
+            if clearance < -TUNNEL_DEPTH {
+                underground += 1;
+            }
+        }
+        if samples > 0 {
+            let tunnel_length = points.length() * underground as f32 / samples as f32;
+            structure += tunnel_length * TUNNEL_PRICE_PER_M;
+        }
+
+        50 + Self::lanes_cost(points.length(), pat) + structure as i64
+    }
+
+    /// Price of a meter of road with this pattern on flat ground, without bridges or tunnels
+    pub fn road_price_per_m(pat: &LanePattern) -> Money {
+        Money::new_cents(
+            LANE_PRICE_PER_M_CENTS * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64,
+        )
+    }
+
+    /// Cost of the lanes of a road of this length, refunded when lanes are removed
+    fn lanes_cost(dist: f32, pat: &LanePattern) -> i64 {
+        let per_m = LANE_PRICE_PER_M_CENTS as f32 / 100.0;
+        ((per_m * dist) as i64).max(1) * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+
+    use crate::map::{Environment, LanePatternBuilder, Road, RoadSegmentKind, RoadStructure};
+
+    use super::Government;
+
+    fn terrain(height: impl Fn(f32) -> f32) -> Environment {
+        let mut env = Environment::new(1, 1);
+        let bounds = env.bounds();
+        env.terrain_apply(bounds, |p| height(p.x));
+        env
+    }
+
+    /// Cost of a straight road crossing the chunk at a height of 50m
+    fn road_cost(env: &Environment) -> (i64, RoadStructure) {
+        let (points, _) = Road::generate_points(
+            vec3(50.0, 250.0, 50.0),
+            vec3(450.0, 250.0, 50.0),
+            RoadSegmentKind::Straight,
+            false,
+            env,
+        );
+        let pat = LanePatternBuilder::default().build();
+        (
+            Government::road_cost(&points, env, &pat),
+            RoadStructure::of(&points, env),
+        )
+    }
+
+    #[test]
+    fn bridge_over_valley_costs_pillars() {
+        let (flat_cost, flat_structure) = road_cost(&terrain(|_| 50.0));
+        let (valley_cost, valley_structure) = road_cost(&terrain(|x| {
+            if (150.0..350.0).contains(&x) {
+                10.0
+            } else {
+                50.0
+            }
+        }));
+
+        assert_eq!(flat_structure, RoadStructure::Ground);
+        assert_eq!(valley_structure, RoadStructure::Bridge);
+        assert!(
+            valley_cost > flat_cost,
+            "valley: {} flat: {}",
+            valley_cost,
+            flat_cost
+        );
+    }
+
+    #[test]
+    fn price_per_m_matches_flat_road_cost() {
+        let env = terrain(|_| 50.0);
+        let (cost, _) = road_cost(&env);
+        let pat = LanePatternBuilder::default().build();
+        let per_m = Government::road_price_per_m(&pat);
+
+        // street with a driving, parking and walking lane each way
+        assert_eq!(per_m.cents(), 18);
+        assert_eq!(cost, 50 + (per_m.cents() * 400) / 100);
     }
 }
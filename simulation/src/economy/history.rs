@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{prototypes_iter, ItemID, ItemPrototype, Money, HOURS_PER_DAY, TICKS_PER_HOUR};
+
+use crate::economy::{Market, Trade};
+
+/// 30 days at hourly resolution
+pub const HOURLY_HISTORY_LEN: usize = 30 * HOURS_PER_DAY as usize;
+/// 1 year at daily resolution
+pub const DAILY_HISTORY_LEN: usize = 365;
+
+const TICKS_PER_DAY: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HistoryLevel {
+    Hourly,
+    Daily,
+}
+
+/// What happened to an item during one hour or one day
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    /// Quantity traded, both between souls and with the external market
+    pub traded: u64,
+    /// Quantity bought from the external market
+    pub imports: u64,
+    /// Quantity sold to the external market
+    pub exports: u64,
+    /// Average external price over the period
+    pub avg_price: Money,
+}
+
+/// Accumulates the trades and prices of a period before it becomes a history point
+#[derive(Default, Serialize, Deserialize)]
+struct PointAccumulator {
+    traded: u64,
+    imports: u64,
+    exports: u64,
+    price_sum: i64,
+    price_samples: i64,
+}
+
+impl PointAccumulator {
+    fn take(&mut self) -> HistoryPoint {
+        let acc = std::mem::take(self);
+        HistoryPoint {
+            traded: acc.traded,
+            imports: acc.imports,
+            exports: acc.exports,
+            avg_price: Money::new_inner(acc.price_sum / acc.price_samples.max(1)),
+        }
+    }
+}
+
+/// A list of points that never grows past its capacity, oldest first.
+/// Encoded like a Vec.
+#[derive(Serialize, Deserialize)]
+struct BoundedSeries {
+    points: VecDeque<HistoryPoint>,
+    capacity: usize,
+}
+
+/// Series of the unknown items
+static NO_POINTS: VecDeque<HistoryPoint> = VecDeque::new();
+
+impl BoundedSeries {
+    fn new(capacity: usize) -> Self {
+        Self {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, p: HistoryPoint) {
+        if self.points.len() >= self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(p);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemSeries {
+    hourly: BoundedSeries,
+    daily: BoundedSeries,
+    hour_acc: PointAccumulator,
+    day_acc: PointAccumulator,
+}
+
+impl Default for ItemSeries {
+    fn default() -> Self {
+        Self {
+            hourly: BoundedSeries::new(HOURLY_HISTORY_LEN),
+            daily: BoundedSeries::new(DAILY_HISTORY_LEN),
+            hour_acc: Default::default(),
+            day_acc: Default::default(),
+        }
+    }
+}
+
+/// Long term history of the economy, sampled every in-game hour and every in-game day.
+/// Memory use is bounded no matter how long the game runs.
+#[derive(Serialize, Deserialize)]
+pub struct EconomyHistory {
+    items: BTreeMap<ItemID, ItemSeries>,
+}
+
+impl Default for EconomyHistory {
+    fn default() -> Self {
+        Self {
+            items: prototypes_iter::<ItemPrototype>()
+                .map(|item| (item.id, ItemSeries::default()))
+                .collect(),
+        }
+    }
+}
+
+impl EconomyHistory {
+    /// Points of the given item at the given level, oldest first
+    pub fn item_series(&self, item: ItemID, level: HistoryLevel) -> &VecDeque<HistoryPoint> {
+        let Some(series) = self.items.get(&item) else {
+            return &NO_POINTS;
+        };
+        match level {
+            HistoryLevel::Hourly => &series.hourly.points,
+            HistoryLevel::Daily => &series.daily.points,
+        }
+    }
+
+    pub fn iter_series(
+        &self,
+        level: HistoryLevel,
+    ) -> impl Iterator<Item = (ItemID, &VecDeque<HistoryPoint>)> {
+        self.items
+            .keys()
+            .map(move |&id| (id, self.item_series(id, level)))
+    }
+
+    /// What happened to the item over the last `hours` hours, summed into a single point
+    pub fn last_hours(&self, item: ItemID, hours: usize) -> HistoryPoint {
+        let series = self.item_series(item, HistoryLevel::Hourly);
+        let recent = series.range(series.len().saturating_sub(hours)..);
+        let n = recent.len();
+        let mut sum = HistoryPoint::default();
+        let mut price_sum = 0;
+        for p in recent {
+            sum.traded += p.traded;
+            sum.imports += p.imports;
+            sum.exports += p.exports;
+            price_sum += p.avg_price.inner();
+        }
+        sum.avg_price = Money::new_inner(price_sum / (n as i64).max(1));
+        sum
+    }
+
+    /// Records the trades made by the market during this tick
+    pub fn record_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            if trade.qty <= 0 {
+                continue;
+            }
+            let Some(series) = self.items.get_mut(&trade.kind) else {
+                continue;
+            };
+            let qty = trade.qty as u64;
+            for acc in [&mut series.hour_acc, &mut series.day_acc] {
+                acc.traded += qty;
+                if trade.buyer.0.is_external() {
+                    acc.exports += qty;
+                } else if trade.seller.0.is_external() {
+                    acc.imports += qty;
+                }
+            }
+        }
+    }
+
+    /// Samples the prices of the market and closes the current hour/day if needed.
+    /// Must be called every tick after the trades were recorded.
+    pub fn advance(&mut self, tick: u64, market: &Market) {
+        for (id, m) in market.iter() {
+            let Some(series) = self.items.get_mut(id) else {
+                continue;
+            };
+            let price = m.price().inner();
+            series.hour_acc.price_sum += price;
+            series.hour_acc.price_samples += 1;
+            series.day_acc.price_sum += price;
+            series.day_acc.price_samples += 1;
+        }
+
+        let new_hour = tick % TICKS_PER_HOUR == 0;
+        let new_day = tick % TICKS_PER_DAY == 0;
+
+        for series in self.items.values_mut() {
+            if new_hour {
+                let p = series.hour_acc.take();
+                series.hourly.push(p);
+            }
+            if new_day {
+                let p = series.day_acc.take();
+                series.daily.push(p);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{ItemID, Money, TICKS_PER_HOUR};
+
+    use crate::economy::{Market, Trade, TradeTarget};
+    use crate::map::IntersectionID;
+    use crate::tests::test_prototypes;
+    use crate::world::CompanyID;
+    use crate::SoulID;
+
+    use super::{
+        EconomyHistory, HistoryLevel, DAILY_HISTORY_LEN, HOURLY_HISTORY_LEN, TICKS_PER_DAY,
+    };
+
+    #[test]
+    fn history_is_bounded() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let cereal = ItemID::new("cereal");
+        let market = Market::default();
+        let mut history = EconomyHistory::default();
+
+        assert!(history.item_series(cereal, HistoryLevel::Hourly).is_empty());
+
+        // 400 days, only advancing on the ticks where something happens
+        for day in 1..=400u64 {
+            for hour in 1..=24u64 {
+                let tick = (day - 1) * TICKS_PER_DAY + hour * (TICKS_PER_DAY / 24);
+                history.advance(tick, &market);
+            }
+        }
+
+        assert_eq!(
+            history.item_series(cereal, HistoryLevel::Hourly).len(),
+            HOURLY_HISTORY_LEN
+        );
+        assert_eq!(
+            history.item_series(cereal, HistoryLevel::Daily).len(),
+            DAILY_HISTORY_LEN
+        );
+        assert!(history
+            .item_series(ItemID::new("unknown"), HistoryLevel::Daily)
+            .is_empty());
+    }
+
+    #[test]
+    fn last_hours_sums_the_recent_trades() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let cereal = ItemID::new("cereal");
+        let company =
+            SoulID::GoodsCompany(CompanyID::from(slotmapd::KeyData::from_ffi((1 << 32) | 1)));
+        let outside = SoulID::RoadConnection(IntersectionID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 2,
+        )));
+        let trade = |buyer, seller, qty| Trade {
+            buyer: TradeTarget(buyer),
+            seller: TradeTarget(seller),
+            qty,
+            kind: cereal,
+            money_delta: Money::ZERO,
+            tariff: Money::ZERO,
+            value: Money::ZERO,
+        };
+
+        let market = Market::default();
+        let mut history = EconomyHistory::default();
+        for hour in 1..=30u64 {
+            history.record_trades(&[trade(company, company, 1), trade(outside, company, 2)]);
+            history.advance(hour * TICKS_PER_HOUR, &market);
+        }
+
+        let day = history.last_hours(cereal, 24);
+        assert_eq!(day.traded, 72);
+        assert_eq!(day.exports, 48);
+        assert_eq!(day.imports, 0);
+        assert_eq!(history.last_hours(cereal, 1000).traded, 90);
+        assert_eq!(history.last_hours(ItemID::new("unknown"), 24).traded, 0);
+    }
+}
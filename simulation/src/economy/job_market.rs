@@ -0,0 +1,427 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{
+    Education, GameTime, GoodsCompanyPrototype, Money, HOURS_PER_DAY, MINUTES_PER_HOUR,
+};
+
+use crate::economy::{Market, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::utils::resources::Resources;
+use crate::world::{CompanyID, HumanID};
+use crate::World;
+
+/// Share of the value added by a worker that is paid back as wage
+const WAGE_SHARE: f64 = 0.8;
+/// Wage increase for each input of the recipe, more complex recipes pay more
+const WAGE_COMPLEXITY_BONUS: f64 = 0.1;
+
+/// Open positions at a company
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct JobOffer {
+    pub pos: Vec2,
+    pub open: u32,
+    /// Wage paid per in-game day
+    pub wage: Money,
+    /// Only workers with at least this education are hired
+    pub min_education: Education,
+}
+
+/// A worker employed by a company
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Contract {
+    pub company: CompanyID,
+    /// Wage paid per in-game day
+    pub wage: Money,
+    /// Where the worker lives, to look for a new job if fired
+    pub home: Vec2,
+    pub education: Education,
+}
+
+/// A human looking for a job
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Seeker {
+    home: Vec2,
+    education: Education,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Hire {
+    pub worker: HumanID,
+    pub company: CompanyID,
+    pub wage: Money,
+}
+
+/// JobMarket matches humans looking for a job to companies with open positions.
+/// Unlike goods, jobs are not traded on the market: a worker signs a contract with a company
+/// which pays a wage every in-game day.
+#[derive(Default, Serialize, Deserialize)]
+pub struct JobMarket {
+    seekers: BTreeMap<HumanID, Seeker>,
+    offers: BTreeMap<CompanyID, JobOffer>,
+    contracts: BTreeMap<HumanID, Contract>,
+    last_payday: i32,
+    // reuse the hires vec to avoid allocations
+    #[serde(skip)]
+    hires: Vec<Hire>,
+}
+
+impl JobMarket {
+    /// Called when a human wants a job, home is used to find a workplace nearby
+    pub fn seek(&mut self, worker: HumanID, home: Vec2, education: Education) {
+        if self.contracts.contains_key(&worker) {
+            return;
+        }
+        self.seekers.insert(worker, Seeker { home, education });
+    }
+
+    /// Called when a company wants to hire workers.
+    /// If an offer is already placed, it will be updated.
+    pub fn offer(
+        &mut self,
+        company: CompanyID,
+        pos: Vec2,
+        open: u32,
+        wage: Money,
+        min_education: Education,
+    ) {
+        self.offers.insert(
+            company,
+            JobOffer {
+                pos,
+                open,
+                wage,
+                min_education,
+            },
+        );
+    }
+
+    /// A human was removed from the world, its position is opened again
+    pub fn remove_worker(&mut self, worker: HumanID) {
+        self.seekers.remove(&worker);
+        let Some(contract) = self.contracts.remove(&worker) else {
+            return;
+        };
+        if let Some(offer) = self.offers.get_mut(&contract.company) {
+            offer.open += 1;
+        }
+    }
+
+    /// A company was removed from the world, its workers are fired and look for a new job.
+    /// Returns the fired workers.
+    pub fn remove_company(&mut self, company: CompanyID) -> Vec<HumanID> {
+        self.offers.remove(&company);
+
+        let fired: Vec<HumanID> = self
+            .contracts
+            .iter()
+            .filter(|(_, c)| c.company == company)
+            .map(|(&worker, _)| worker)
+            .collect();
+
+        for worker in &fired {
+            let contract = self.contracts.remove(worker).unwrap();
+            self.seekers.insert(
+                *worker,
+                Seeker {
+                    home: contract.home,
+                    education: contract.education,
+                },
+            );
+        }
+
+        fired
+    }
+
+    /// Matches job seekers to the nearest company with an open position they are qualified for.
+    /// Seekers prefer the jobs requiring the most education, leaving the others to the less educated.
+    /// Please do not keep the hires around much, it needs to be destroyed by the next time you call this function.
+    pub fn make_hires(&mut self) -> &[Hire] {
+        self.hires.clear();
+
+        let mut total_open: u32 = self.offers.values().map(|o| o.open).sum();
+        if total_open == 0 || self.seekers.is_empty() {
+            return &self.hires;
+        }
+
+        let offers = &mut self.offers;
+        self.seekers.retain(|&worker, &mut seeker| {
+            let Seeker { home, education } = seeker;
+            if total_open == 0 {
+                return true;
+            }
+            let Some((&company, offer)) = offers
+                .iter_mut()
+                .filter(|(_, o)| o.open > 0 && o.min_education <= education)
+                .min_by_key(|(_, o)| {
+                    (
+                        Reverse(o.min_education),
+                        OrderedFloat(o.pos.distance2(home)),
+                    )
+                })
+            else {
+                return true;
+            };
+
+            offer.open -= 1;
+            total_open -= 1;
+
+            self.contracts.insert(
+                worker,
+                Contract {
+                    company,
+                    wage: offer.wage,
+                    home,
+                    education,
+                },
+            );
+            self.hires.push(Hire {
+                worker,
+                company,
+                wage: offer.wage,
+            });
+            false
+        });
+
+        &self.hires
+    }
+
+    pub fn contract(&self, worker: HumanID) -> Option<&Contract> {
+        self.contracts.get(&worker)
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = (HumanID, &Contract)> {
+        self.contracts.iter().map(|(&w, c)| (w, c))
+    }
+
+    pub fn offer_of(&self, company: CompanyID) -> Option<&JobOffer> {
+        self.offers.get(&company)
+    }
+
+    /// Positions offered by companies that nobody took yet
+    pub fn n_open(&self) -> u32 {
+        self.offers.values().map(|o| o.open).sum()
+    }
+
+    /// Number of humans looking for a job
+    pub fn unemployed(&self) -> usize {
+        self.seekers.len()
+    }
+
+    pub fn employed(&self) -> usize {
+        self.contracts.len()
+    }
+
+    /// Returns true once per in-game day, when wages should be paid
+    pub fn is_payday(&mut self, time: &GameTime) -> bool {
+        if time.daytime.day == self.last_payday {
+            return false;
+        }
+        self.last_payday = time.daytime.day;
+        true
+    }
+}
+
+/// Wage for one in-game day of work at this company.
+/// It is negotiated from the value the company adds to its inputs at market prices,
+/// and from the complexity of its recipe.
+pub fn negotiate_wage(proto: &GoodsCompanyPrototype, market: &Market) -> Money {
+    let minutes_per_day = (HOURS_PER_DAY * MINUTES_PER_HOUR) as i64;
+    let base_wage = WORKER_CONSUMPTION_PER_MINUTE * minutes_per_day;
+
+    let Some(ref recipe) = proto.recipe else {
+        return base_wage;
+    };
+    if proto.n_workers == 0 {
+        return base_wage;
+    }
+
+    let price = |id| market.inner().get(&id).map_or(Money::ZERO, |m| m.price());
+
+    let produced: Money = recipe
+        .production
+        .iter()
+        .map(|item| price(item.id) * item.amount as i64)
+        .sum();
+    let consumed: Money = recipe
+        .consumption
+        .iter()
+        .map(|item| price(item.id) * item.amount as i64)
+        .sum();
+
+    let cycles_per_day = minutes_per_day as f64 / recipe.duration.minutes().max(1.0);
+    let value_per_worker =
+        (produced - consumed) * (cycles_per_day / proto.n_workers as f64) * WAGE_SHARE;
+
+    let complexity = 1.0 + WAGE_COMPLEXITY_BONUS * recipe.consumption.len() as f64;
+
+    (value_per_worker * complexity).max(base_wage)
+}
+
+/// Hires job seekers, fires the workers of removed companies and pays wages once per day
+pub fn job_market_update(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("economy::job_market_update");
+    let mut jobs = resources.write::<JobMarket>();
+    let time = resources.read::<GameTime>();
+
+    for hire in jobs.make_hires() {
+        if let Some(comp) = world.companies.get_mut(hire.company) {
+            comp.workers.0.push(hire.worker);
+        }
+    }
+
+    // workers whose contract ended (company removed) lose their job
+    for (id, h) in world.humans.iter_mut() {
+        if h.work.is_some() && jobs.contract(id).is_none() {
+            h.work = None;
+        }
+    }
+    for (id, c) in world.companies.iter_mut() {
+        c.workers
+            .0
+            .retain(|&w| jobs.contract(w).is_some_and(|c| c.company == id));
+        if let Some(driver) = c.comp.driver {
+            if !c.workers.0.contains(&driver) {
+                c.comp.driver = None;
+            }
+        }
+    }
+
+    if jobs.is_payday(&time) {
+        // the company pays the worker, the government is not involved
+        for (worker, contract) in jobs.contracts() {
+            let Some(comp) = world.companies.get_mut(contract.company) else {
+                continue;
+            };
+            let Some(h) = world.humans.get_mut(worker) else {
+                continue;
+            };
+            comp.finances.balance -= contract.wage;
+            h.wallet.0 += contract.wage;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Vec2};
+    use prototypes::{Education, Money};
+
+    use crate::world::{CompanyID, HumanID};
+
+    use super::JobMarket;
+
+    fn mk_human(id: u64) -> HumanID {
+        HumanID::from(slotmapd::KeyData::from_ffi((1 << 32) | id))
+    }
+
+    fn mk_company(id: u64) -> CompanyID {
+        CompanyID::from(slotmapd::KeyData::from_ffi((1 << 32) | id))
+    }
+
+    #[test]
+    fn hire_nearest() {
+        let mut jobs = JobMarket::default();
+        let near = mk_company(1);
+        let far = mk_company(2);
+
+        jobs.offer(near, Vec2::ZERO, 1, Money::new_bucks(10), Education::None);
+        jobs.offer(
+            far,
+            vec2(100.0, 0.0),
+            2,
+            Money::new_bucks(20),
+            Education::None,
+        );
+
+        for i in 0..4 {
+            jobs.seek(mk_human(i + 1), Vec2::X, Education::None);
+        }
+        assert_eq!(jobs.unemployed(), 4);
+
+        let hires = jobs.make_hires();
+        assert_eq!(hires.len(), 3);
+        assert_eq!(hires[0].company, near);
+        assert_eq!(hires[0].wage, Money::new_bucks(10));
+        assert_eq!(hires[1].company, far);
+        assert_eq!(hires[2].company, far);
+
+        assert_eq!(jobs.unemployed(), 1);
+        assert_eq!(jobs.employed(), 3);
+        assert_eq!(jobs.contract(mk_human(1)).unwrap().company, near);
+        assert!(jobs.contract(mk_human(4)).is_none());
+
+        assert!(jobs.make_hires().is_empty());
+    }
+
+    #[test]
+    fn fired_when_company_removed() {
+        let mut jobs = JobMarket::default();
+        let c1 = mk_company(1);
+        let c2 = mk_company(2);
+
+        jobs.offer(c1, Vec2::ZERO, 2, Money::new_bucks(10), Education::None);
+        jobs.seek(mk_human(1), Vec2::ZERO, Education::None);
+        jobs.seek(mk_human(2), Vec2::ZERO, Education::None);
+        jobs.make_hires();
+        assert_eq!(jobs.unemployed(), 0);
+
+        let fired = jobs.remove_company(c1);
+        assert_eq!(fired, vec![mk_human(1), mk_human(2)]);
+        assert_eq!(jobs.unemployed(), 2);
+        assert_eq!(jobs.employed(), 0);
+
+        jobs.offer(c2, Vec2::ZERO, 1, Money::new_bucks(10), Education::None);
+        assert_eq!(jobs.make_hires().len(), 1);
+        assert_eq!(jobs.unemployed(), 1);
+    }
+
+    #[test]
+    fn removed_worker_reopens_position() {
+        let mut jobs = JobMarket::default();
+        let c1 = mk_company(1);
+
+        jobs.offer(c1, Vec2::ZERO, 1, Money::new_bucks(10), Education::None);
+        jobs.seek(mk_human(1), Vec2::ZERO, Education::None);
+        jobs.seek(mk_human(2), Vec2::ZERO, Education::None);
+        jobs.make_hires();
+        assert_eq!(jobs.unemployed(), 1);
+
+        jobs.remove_worker(mk_human(1));
+        assert_eq!(jobs.offer_of(c1).unwrap().open, 1);
+
+        let hires = jobs.make_hires();
+        assert_eq!(hires.len(), 1);
+        assert_eq!(hires[0].worker, mk_human(2));
+        assert_eq!(jobs.unemployed(), 0);
+    }
+
+    #[test]
+    fn qualified_workers_only() {
+        let mut jobs = JobMarket::default();
+        let lab = mk_company(1);
+        let shop = mk_company(2);
+
+        jobs.offer(lab, Vec2::ZERO, 1, Money::new_bucks(30), Education::Higher);
+        jobs.seek(mk_human(1), Vec2::ZERO, Education::Basic);
+        assert!(jobs.make_hires().is_empty());
+
+        jobs.offer(
+            shop,
+            vec2(100.0, 0.0),
+            1,
+            Money::new_bucks(10),
+            Education::None,
+        );
+        jobs.seek(mk_human(2), vec2(100.0, 0.0), Education::Higher);
+
+        // the educated worker takes the skilled job even if the shop is closer
+        let hires = jobs.make_hires();
+        assert_eq!(hires.len(), 2);
+        assert_eq!(jobs.contract(mk_human(1)).unwrap().company, shop);
+        assert_eq!(jobs.contract(mk_human(2)).unwrap().company, lab);
+    }
+}
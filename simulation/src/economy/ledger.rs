@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, ItemID, Money};
+
+use crate::economy::Government;
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// Days kept in the ledger besides the current one
+pub const LEDGER_DAYS: usize = 30;
+
+/// Most money the government can owe
+pub const MAX_LOAN: Money = Money::new_bucks(500_000);
+
+/// Share of the loan paid as interests every day
+pub const LOAN_DAILY_INTEREST: f64 = 0.001;
+
+/// Why the money of the government changed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BudgetReason {
+    /// Goods sold to the outside of the city
+    Exports(ItemID),
+    /// Goods bought from the outside of the city
+    Imports(ItemID),
+    Tariffs,
+    /// Supplies bought every minute for the workers of the city
+    WorkerSupplies,
+    Rent,
+    LeisureFees,
+    Roads,
+    Buildings,
+    Transit,
+    Trees,
+    /// Part of the price of what was bulldozed or downgraded
+    Refunds,
+    /// The funds given by a scenario when it starts
+    Scenario,
+    Cheats,
+    Loan,
+    LoanRepayment,
+    LoanInterest,
+}
+
+impl BudgetReason {
+    pub fn label(&self) -> String {
+        match self {
+            BudgetReason::Exports(item) => format!("Exports of {}", item.prototype().label),
+            BudgetReason::Imports(item) => format!("Imports of {}", item.prototype().label),
+            BudgetReason::Tariffs => "Tariffs".to_string(),
+            BudgetReason::WorkerSupplies => "Worker supplies".to_string(),
+            BudgetReason::Rent => "Rent".to_string(),
+            BudgetReason::LeisureFees => "Leisure fees".to_string(),
+            BudgetReason::Roads => "Roads".to_string(),
+            BudgetReason::Buildings => "Buildings".to_string(),
+            BudgetReason::Transit => "Transit".to_string(),
+            BudgetReason::Trees => "Trees".to_string(),
+            BudgetReason::Refunds => "Refunds".to_string(),
+            BudgetReason::Scenario => "Scenario".to_string(),
+            BudgetReason::Cheats => "Cheats".to_string(),
+            BudgetReason::Loan => "Loan".to_string(),
+            BudgetReason::LoanRepayment => "Loan repayment".to_string(),
+            BudgetReason::LoanInterest => "Loan interest".to_string(),
+        }
+    }
+}
+
+/// Net amount of money received or spent for each reason during a day, positive for income
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerDay {
+    pub entries: BTreeMap<BudgetReason, Money>,
+}
+
+impl LedgerDay {
+    pub fn income(&self) -> Money {
+        self.entries
+            .values()
+            .copied()
+            .filter(|&m| m > Money::ZERO)
+            .sum()
+    }
+
+    /// Negative or zero
+    pub fn expenses(&self) -> Money {
+        self.entries
+            .values()
+            .copied()
+            .filter(|&m| m < Money::ZERO)
+            .sum()
+    }
+
+    pub fn net(&self) -> Money {
+        self.entries.values().copied().sum()
+    }
+
+    fn add(&mut self, reason: BudgetReason, amount: Money) {
+        *self.entries.entry(reason).or_default() += amount;
+    }
+}
+
+/// Every change of the money of the government, summed by day and by reason
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Ledger {
+    /// Day of `today`
+    day: i32,
+    today: LedgerDay,
+    /// The previous days, the most recent last
+    past: VecDeque<LedgerDay>,
+    /// The money before the first transaction plus all the transactions since,
+    /// always equal to the money of the government
+    balance: Money,
+}
+
+impl Ledger {
+    /// An empty ledger for a government starting with this money
+    pub fn new(opening: Money) -> Self {
+        Self {
+            balance: opening,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn record(&mut self, reason: BudgetReason, amount: Money) {
+        self.today.add(reason, amount);
+        self.balance += amount;
+    }
+
+    /// Starts a new day, the oldest one is dropped once there are [`LEDGER_DAYS`]
+    pub(crate) fn close_day(&mut self, day: i32) {
+        let today = std::mem::take(&mut self.today);
+        // nothing happened before the first day of the ledger
+        if !(today.entries.is_empty() && self.past.is_empty()) {
+            if self.past.len() >= LEDGER_DAYS {
+                self.past.pop_front();
+            }
+            self.past.push_back(today);
+        }
+        self.day = day;
+    }
+
+    pub fn today(&self) -> &LedgerDay {
+        &self.today
+    }
+
+    /// The previous days, the most recent last
+    pub fn past(&self) -> impl Iterator<Item = &LedgerDay> {
+        self.past.iter()
+    }
+
+    /// The previous days summed together
+    pub fn trailing(&self) -> LedgerDay {
+        let mut sum = LedgerDay::default();
+        for day in &self.past {
+            for (&reason, &amount) in &day.entries {
+                sum.add(reason, amount);
+            }
+        }
+        sum
+    }
+
+    pub fn balance(&self) -> Money {
+        self.balance
+    }
+}
+
+/// Pays the interests of the loan and starts a new day of the ledger once per in-game day
+pub fn budget_system(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("economy::budget_system");
+    let day = resources.read::<GameTime>().daytime.day;
+    let mut gvt = resources.write::<Government>();
+    debug_assert_eq!(
+        gvt.ledger.balance(),
+        gvt.money,
+        "the money of the government changed without going through Government::transact"
+    );
+    if gvt.ledger.day == day {
+        return;
+    }
+
+    let interest = gvt.loan * LOAN_DAILY_INTEREST;
+    gvt.transact(BudgetReason::LoanInterest, -interest);
+    gvt.ledger.close_day(day);
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::Money;
+
+    use super::{BudgetReason, Ledger, LEDGER_DAYS};
+
+    #[test]
+    fn ledger_sums_by_day_and_reason() {
+        let mut ledger = Ledger::new(Money::new_bucks(100));
+        ledger.close_day(1);
+        // nothing happened yet
+        assert_eq!(ledger.past().count(), 0);
+
+        ledger.record(BudgetReason::Rent, Money::new_bucks(10));
+        ledger.record(BudgetReason::Rent, Money::new_bucks(5));
+        ledger.record(BudgetReason::Roads, Money::new_bucks(-30));
+        assert_eq!(ledger.today().income(), Money::new_bucks(15));
+        assert_eq!(ledger.today().expenses(), Money::new_bucks(-30));
+        assert_eq!(ledger.today().net(), Money::new_bucks(-15));
+        assert_eq!(ledger.balance(), Money::new_bucks(85));
+
+        for day in 2..=LEDGER_DAYS as i32 + 5 {
+            ledger.close_day(day);
+            ledger.record(BudgetReason::Rent, Money::new_bucks(1));
+        }
+        assert_eq!(ledger.past().count(), LEDGER_DAYS);
+        // the first day was dropped
+        assert_eq!(
+            ledger.trailing().net(),
+            Money::new_bucks(LEDGER_DAYS as i64)
+        );
+        assert_eq!(
+            ledger.balance(),
+            Money::new_bucks(85 + LEDGER_DAYS as i64 + 4)
+        );
+    }
+}
@@ -1,13 +1,18 @@
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
 
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
 use geom::Vec2;
-use prototypes::{prototypes_iter, GoodsCompanyID, GoodsCompanyPrototype, ItemPrototype, Money};
+use prototypes::{
+    prototypes_iter, try_prototype, GoodsCompanyID, GoodsCompanyPrototype, ItemPrototype, Money,
+};
 
-use crate::economy::{ItemID, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::economy::order_grid::{OrderGrid, ORDER_GRID_CELL_SIZE};
+use crate::economy::{ItemID, TradePolicy, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::gameplay::GameplayParams;
 use crate::map::BuildingID;
 use crate::map_dynamic::BuildingInfos;
 use crate::SoulID;
@@ -24,6 +29,17 @@ pub struct SellOrder {
 pub struct BuyOrder {
     pub pos: Vec2,
     pub qty: u32,
+    /// Maximum unit price the buyer accepts when buying from the external market.
+    /// None means any price is accepted, Some(Money::ZERO) means only buying locally.
+    pub max_price: Option<Money>,
+}
+
+impl BuyOrder {
+    /// Whether the order can be fulfilled by the external market at this unit cost
+    pub fn allows_external(&self, unit_cost: Money) -> bool {
+        self.max_price
+            .is_none_or(|max| max > Money::ZERO && unit_cost <= max)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -32,10 +48,19 @@ pub struct SingleMarket {
     capital: BTreeMap<SoulID, i32>,
     buy_orders: BTreeMap<SoulID, BuyOrder>,
     sell_orders: BTreeMap<SoulID, SellOrder>,
+    /// Base price computed from the production chain, used as the anchor for price discovery
     pub ext_value: Money,
+    /// Price used for external trades, moves with supply and demand around `ext_value`
+    current_price: Money,
     optout_exttrade: bool,
 }
 
+/// Maximum relative price change per tick
+const PRICE_ADJUST_RATE: f64 = 0.002;
+/// Bounds of the current price relative to the base price
+const MIN_PRICE_MULTIPLIER: f64 = 0.5;
+const MAX_PRICE_MULTIPLIER: f64 = 3.0;
+
 impl SingleMarket {
     pub fn new(ext_value: Money, optout_exttrade: bool) -> Self {
         Self {
@@ -43,10 +68,40 @@ impl SingleMarket {
             buy_orders: Default::default(),
             sell_orders: Default::default(),
             ext_value,
+            current_price: ext_value,
             optout_exttrade,
         }
     }
 
+    /// The current external price of one unit of this item
+    pub fn price(&self) -> Money {
+        self.current_price
+    }
+
+    /// Nudges the current price according to the unfilled demand and the unsold supply.
+    /// Pressure is in [-1; 1], positive when demand exceeds supply.
+    fn adjust_price(&mut self, unfilled_demand: u64, unsold_supply: u64) {
+        let total = unfilled_demand + unsold_supply;
+        if total == 0 {
+            return;
+        }
+        let pressure = (unfilled_demand as f64 - unsold_supply as f64) / total as f64;
+        if pressure == 0.0 {
+            return;
+        }
+        let mut newprice = self.current_price * (1.0 + PRICE_ADJUST_RATE * pressure);
+
+        // make sure the price can still move when it is very small
+        if newprice == self.current_price {
+            newprice.0 += pressure.signum() as i64;
+        }
+
+        self.current_price = newprice.clamp(
+            self.ext_value * MIN_PRICE_MULTIPLIER,
+            self.ext_value * MAX_PRICE_MULTIPLIER,
+        );
+    }
+
     pub fn capital(&self, soul: SoulID) -> Option<i32> {
         self.capital.get(&soul).copied()
     }
@@ -60,20 +115,82 @@ impl SingleMarket {
     pub fn capital_map(&self) -> &BTreeMap<SoulID, i32> {
         &self.capital
     }
+
+    pub fn buy_orders(&self) -> impl Iterator<Item = (SoulID, &BuyOrder)> {
+        self.buy_orders.iter().map(|(&soul, o)| (soul, o))
+    }
+
+    pub fn sell_orders(&self) -> impl Iterator<Item = (SoulID, &SellOrder)> {
+        self.sell_orders.iter().map(|(&soul, o)| (soul, o))
+    }
+
+    /// Total quantity of all the buy orders
+    pub fn total_demand(&self) -> u64 {
+        self.buy_orders.values().map(|o| o.qty as u64).sum()
+    }
+
+    /// Total quantity of all the sell orders
+    pub fn total_supply(&self) -> u64 {
+        self.sell_orders.values().map(|o| o.qty as u64).sum()
+    }
+
+    /// Total quantity of the sell orders, not counting the ones of `except`
+    pub fn supply(&self, except: SoulID) -> u32 {
+        self.sell_orders
+            .iter()
+            .filter(|(&soul, _)| soul != except)
+            .map(|(_, o)| o.qty)
+            .sum()
+    }
+
+    /// Total quantity of the buy orders, not counting the ones of `except`
+    pub fn demand(&self, except: SoulID) -> u32 {
+        self.buy_orders
+            .iter()
+            .filter(|(&soul, _)| soul != except)
+            .map(|(_, o)| o.qty)
+            .sum()
+    }
 }
 
 /// Market handles good exchanging between souls themselves and the external market.
 /// When goods are exchanges between souls, money is not involved.
 /// When goods are exchanged with the external market, money is involved.
 #[derive(Serialize, Deserialize)]
+#[serde(from = "MarketDeser")]
 pub struct Market {
     markets: BTreeMap<ItemID, SingleMarket>,
+    /// Buyers whose orders are matched with local sellers before everyone else's
+    prioritized: BTreeSet<SoulID>,
     // reuse the trade vec to avoid allocations
     #[serde(skip)]
     all_trades: Vec<Trade>,
     // reuse the potential vec to avoid allocations
     #[serde(skip)]
-    potential: Vec<(Trade, f32)>,
+    potential: Vec<Reverse<PotentialTrade>>,
+    // reuse the buyers vec to avoid allocations
+    #[serde(skip)]
+    buyers: Vec<(SoulID, BuyOrder)>,
+    // reuse the grid to avoid allocations
+    #[serde(skip)]
+    grid: OrderGrid,
+    // buyers that were partially served this tick, reused to avoid allocations
+    #[serde(skip)]
+    partially_filled: Vec<SoulID>,
+    /// See [`GameplayParams::price_multiplier`]
+    price_multiplier: f32,
+    /// See [`GameplayParams::external_trade_margin`]
+    trade_margin: f32,
+}
+
+/// A possible trade between a buyer and a seller, ordered by distance then by souls
+/// so that matching is deterministic.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct PotentialTrade {
+    dist2: OrderedFloat<f32>,
+    seller: SoulID,
+    buyer: SoulID,
+    qty: i32,
 }
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
@@ -88,28 +205,137 @@ pub struct Trade {
     pub qty: i32,
     pub kind: ItemID,
     pub money_delta: Money, // money delta from the govt point of view, positive means we gained money
+    /// Part of the traded value taken as tariffs, credited to the government as tariff income
+    pub tariff: Money,
+    /// Value of the goods at the market price, paid by the buyer to the seller in local trades
+    pub value: Money,
 }
 
+/// Where the goods of a trade change hands on the side of the target.
+/// None for the road connections, their trucks come to the building of the other side.
+/// The goods going by sea are loaded and unloaded at the harbor.
 pub fn find_trade_place(target: TradeTarget, binfos: &BuildingInfos) -> Option<BuildingID> {
-    binfos.building_owned_by(target.0)
+    match target.0 {
+        SoulID::RoadConnection(_) => None,
+        SoulID::SeaConnection(harbor) => Some(harbor),
+        soul => binfos.building_owned_by(soul),
+    }
+}
+
+/// What is actually in a save, the markets of items that no longer exist are removed when loading.
+#[derive(Deserialize)]
+struct MarketDeser {
+    markets: BTreeMap<ItemID, SingleMarket>,
+    prioritized: BTreeSet<SoulID>,
+    price_multiplier: f32,
+    trade_margin: f32,
+}
+
+impl From<MarketDeser> for Market {
+    fn from(mut value: MarketDeser) -> Self {
+        // items might have been removed by a mod or a prototype change since the save was made
+        drop_removed_items(&mut value.markets);
+
+        Self {
+            markets: value.markets,
+            prioritized: value.prioritized,
+            all_trades: Default::default(),
+            potential: Default::default(),
+            buyers: Default::default(),
+            grid: Default::default(),
+            partially_filled: Default::default(),
+            price_multiplier: value.price_multiplier,
+            trade_margin: value.trade_margin,
+        }
+    }
+}
+
+fn drop_removed_items(markets: &mut BTreeMap<ItemID, SingleMarket>) {
+    markets.retain(|&id, market| {
+        if try_prototype(id).is_some() {
+            return true;
+        }
+        let orphaned: i32 = market.capital.values().sum();
+        log::warn!(
+            "item {:?} no longer exists, dropping its market ({} orphaned units owned by {} souls)",
+            id,
+            orphaned,
+            market.capital.len()
+        );
+        false
+    });
 }
 
 impl Default for Market {
     fn default() -> Self {
-        let prices = calculate_prices(1.25);
+        Self::new(&GameplayParams::default())
+    }
+}
+
+impl Market {
+    pub fn new(params: &GameplayParams) -> Self {
+        let prices = calculate_prices(params.price_multiplier);
         Self {
             markets: prototypes_iter::<ItemPrototype>()
                 .map(|v| (v.id, SingleMarket::new(prices[&v.id], v.optout_exttrade)))
                 .collect(),
+            prioritized: Default::default(),
             all_trades: Default::default(),
             potential: Default::default(),
+            buyers: Default::default(),
+            grid: Default::default(),
+            partially_filled: Default::default(),
+            price_multiplier: params.price_multiplier,
+            trade_margin: params.external_trade_margin,
         }
     }
-}
 
-impl Market {
+    /// Applies new gameplay parameters to a running market, the current prices keep
+    /// their position relative to the base prices
+    pub fn set_params(&mut self, params: &GameplayParams) {
+        self.trade_margin = params.external_trade_margin;
+        if self.price_multiplier == params.price_multiplier {
+            return;
+        }
+        self.price_multiplier = params.price_multiplier;
+        let prices = calculate_prices(params.price_multiplier);
+        for (id, market) in &mut self.markets {
+            let Some(&base) = prices.get(id) else {
+                continue;
+            };
+            if market.ext_value != Money::ZERO {
+                let ratio = market.current_price.0 as f64 / market.ext_value.0 as f64;
+                market.current_price = base * ratio;
+            } else {
+                market.current_price = base;
+            }
+            market.ext_value = base;
+        }
+    }
+
+    /// Follows a reload of the prototypes: the markets of the new items are created, the ones
+    /// of the removed items dropped and the base prices follow the new recipes
+    pub fn sync_items(&mut self) {
+        drop_removed_items(&mut self.markets);
+        let prices = calculate_prices(self.price_multiplier);
+        for item in prototypes_iter::<ItemPrototype>() {
+            self.m(item.id).ext_value = prices[&item.id];
+        }
+    }
+
+    pub fn price_multiplier(&self) -> f32 {
+        self.price_multiplier
+    }
+
+    /// Returns the market of this item, creating it if the item was unknown
+    /// (for example if it was added by a mod after the save was made).
     pub fn m(&mut self, kind: ItemID) -> &mut SingleMarket {
-        self.markets.get_mut(&kind).unwrap()
+        let price_multiplier = self.price_multiplier;
+        self.markets.entry(kind).or_insert_with(|| {
+            log::info!("creating market for new item {:?}", kind);
+            let optout_exttrade = try_prototype(kind).is_some_and(|item| item.optout_exttrade);
+            SingleMarket::new(calculate_price(kind, price_multiplier), optout_exttrade)
+        })
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&ItemID, &SingleMarket)> {
@@ -139,6 +365,16 @@ impl Market {
         self.sell(soul, near, kind, c as u32, stock);
     }
 
+    /// Removes the sell order of this agent, if any
+    pub fn cancel_sell(&mut self, soul: SoulID, kind: ItemID) {
+        self.m(kind).sell_orders.remove(&soul);
+    }
+
+    /// Removes the buy order of this agent, if any
+    pub fn cancel_buy(&mut self, soul: SoulID, kind: ItemID) {
+        self.m(kind).buy_orders.remove(&soul);
+    }
+
     /// An agent was removed from the world, we need to clean after him
     pub fn remove(&mut self, soul: SoulID) {
         for market in self.markets.values_mut() {
@@ -146,29 +382,80 @@ impl Market {
             market.buy_orders.remove(&soul);
             market.capital.remove(&soul);
         }
+        self.prioritized.remove(&soul);
+    }
+
+    /// Prioritized buyers are matched with local sellers before the other buyers
+    pub fn set_prioritized(&mut self, soul: SoulID, prioritized: bool) {
+        if prioritized {
+            self.prioritized.insert(soul);
+        } else {
+            self.prioritized.remove(&soul);
+        }
+    }
+
+    pub fn is_prioritized(&self, soul: SoulID) -> bool {
+        self.prioritized.contains(&soul)
     }
 
     /// Called when an agent tells the world it wants to buy something
     /// If an order is already placed, it will be updated.
     pub fn buy(&mut self, soul: SoulID, near: Vec2, kind: ItemID, qty: u32) {
+        self.buy_max_price(soul, near, kind, qty, None);
+    }
+
+    /// Same as buy, but the order will only be fulfilled by other souls
+    pub fn buy_local(&mut self, soul: SoulID, near: Vec2, kind: ItemID, qty: u32) {
+        self.buy_max_price(soul, near, kind, qty, Some(Money::ZERO));
+    }
+
+    /// Same as buy, but the external market is only used if the unit price (tariffs included)
+    /// is at most max_price. Otherwise the order stays until another soul sells the item.
+    pub fn buy_max_price(
+        &mut self,
+        soul: SoulID,
+        near: Vec2,
+        kind: ItemID,
+        qty: u32,
+        max_price: Option<Money>,
+    ) {
         log::debug!("{:?} buy {:?} {:?} near {:?}", soul, qty, kind, near);
 
-        self.m(kind)
-            .buy_orders
-            .insert(soul, BuyOrder { pos: near, qty });
+        self.m(kind).buy_orders.insert(
+            soul,
+            BuyOrder {
+                pos: near,
+                qty,
+                max_price,
+            },
+        );
     }
 
     pub fn buy_until(&mut self, soul: SoulID, near: Vec2, kind: ItemID, qty: u32) {
+        self.buy_until_max_price(soul, near, kind, qty, None);
+    }
+
+    pub fn buy_until_max_price(
+        &mut self,
+        soul: SoulID,
+        near: Vec2,
+        kind: ItemID,
+        qty: u32,
+        max_price: Option<Money>,
+    ) {
         let c = self.capital(soul, kind);
         if c >= qty as i32 {
             return;
         }
-        self.buy(soul, near, kind, qty - c as u32);
+        self.buy_max_price(soul, near, kind, qty - c as u32, max_price);
     }
 
     /// Get the capital that this agent owns
     pub fn capital(&self, soul: SoulID, kind: ItemID) -> i32 {
-        self.markets.get(&kind).unwrap().capital(soul).unwrap_or(0)
+        self.markets
+            .get(&kind)
+            .and_then(|m| m.capital(soul))
+            .unwrap_or(0)
     }
 
     /// Registers a soul to the market, not obligatory
@@ -176,6 +463,18 @@ impl Market {
         self.m(kind).capital.entry(soul).or_default();
     }
 
+    /// The goods bought are on their way to the buyer, who receives them with [`Market::receive`]
+    pub fn withhold(&mut self, soul: SoulID, kind: ItemID, qty: i32) {
+        *self.m(kind).capital.entry(soul).or_default() -= qty;
+    }
+
+    /// The goods carried by a truck or a ship reached the buyer
+    pub fn receive(&mut self, soul: SoulID, kind: ItemID, qty: i32) {
+        log::debug!("{:?} received {:?} {:?}", soul, qty, kind);
+
+        *self.m(kind).capital.entry(soul).or_default() += qty;
+    }
+
     /// Called whenever an agent (like a farm) produces something on it's own
     /// for example wheat is harvested or turned into flour. Returns the new quantity owned.
     pub fn produce(&mut self, soul: SoulID, kind: ItemID, delta: i32) -> i32 {
@@ -189,13 +488,27 @@ impl Market {
     /// Returns a list of buy and sell orders matched together.
     /// A trade updates the buy and sell orders from the market, and the capital of the buyers and sellers.
     /// A trade can only be completed if the seller has enough capital.
+    /// A buy order can be served by multiple sellers, what's left of it stays on the market.
+    /// Trades with the external market are taxed according to the trade policy.
+    /// find_external is given the local soul, its position and the quantity of an external trade
+    /// and returns the soul handling it, or None if the goods have no way in or out of the city,
+    /// in which case the trade is deferred.
+    /// The goods imported through a road or sea connection are only given to the buyer when the
+    /// truck or the ship arrives, see [`Market::receive`].
     /// Please do not keep the trades around much, it needs to be destroyed by the next time you call this function.
-    pub fn make_trades(&mut self, find_external: impl Fn(Vec2) -> Option<SoulID>) -> &[Trade] {
+    pub fn make_trades(
+        &mut self,
+        policy: &TradePolicy,
+        mut find_external: impl FnMut(SoulID, Vec2, u32) -> Option<SoulID>,
+    ) -> &[Trade] {
         self.all_trades.clear();
 
         for (&kind, market) in &mut self.markets {
-            // Naive O(n²) alg
             // We don't immediatly apply the trades, because we want to find the nearest-positioned trades
+            // Sellers are put in a grid, and buyers look at it in expanding rings.
+            // Once all the rings up to k have been looked at, all pairs closer than k cells are known
+            // so they can be applied in distance order, exactly like if all pairs were considered.
+            self.grid.clear();
             for (&seller, sorder) in &market.sell_orders {
                 let qty_sell = sorder.qty as i32;
 
@@ -203,103 +516,155 @@ impl Market {
                 if qty_sell > capital_sell {
                     continue;
                 }
-                for (&buyer, &border) in &market.buy_orders {
-                    if seller == buyer {
-                        log::warn!(
-                            "{:?} is both selling and buying same commodity: {:?}",
-                            seller,
-                            kind
-                        );
+                self.grid.insert(seller, sorder.pos, qty_sell);
+            }
+
+            self.partially_filled.clear();
+            if !self.grid.is_empty() {
+                // Prioritized buyers get a first pass on their own, then everyone still buying
+                // is matched with what is left.
+                let has_prioritized = market
+                    .buy_orders
+                    .keys()
+                    .any(|buyer| self.prioritized.contains(buyer));
+                for prioritized_pass in [true, false] {
+                    if prioritized_pass && !has_prioritized {
                         continue;
                     }
-                    let qty_buy = border.qty as i32;
-                    if qty_buy > qty_sell {
-                        continue;
+                    self.buyers.clear();
+                    self.buyers.extend(
+                        market
+                            .buy_orders
+                            .iter()
+                            .filter(|(buyer, _)| {
+                                !prioritized_pass || self.prioritized.contains(buyer)
+                            })
+                            .map(|(&buyer, &border)| (buyer, border)),
+                    );
+
+                    let mut potential = BinaryHeap::from(std::mem::take(&mut self.potential));
+
+                    let mut ring = 0;
+                    while !self.buyers.is_empty() {
+                        for &(buyer, border) in &self.buyers {
+                            self.grid.ring(border.pos, ring, |seller, spos, qty_sell| {
+                                if seller == buyer {
+                                    log::warn!(
+                                        "{:?} is both selling and buying same commodity: {:?}",
+                                        seller,
+                                        kind
+                                    );
+                                    return;
+                                }
+                                potential.push(Reverse(PotentialTrade {
+                                    dist2: OrderedFloat(spos.distance2(border.pos)),
+                                    seller,
+                                    buyer,
+                                    qty: (border.qty as i32).min(qty_sell),
+                                }));
+                            });
+                        }
+
+                        // buyers that looked at all the rings around them are done
+                        self.buyers
+                            .retain(|(_, border)| self.grid.max_ring(border.pos) > ring);
+                        let last_ring = self.buyers.is_empty();
+                        let threshold = ring as f32 * ORDER_GRID_CELL_SIZE;
+                        let threshold2 = threshold * threshold;
+
+                        while let Some(Reverse(p)) = potential.peek() {
+                            if !last_ring && p.dist2.0 >= threshold2 {
+                                break;
+                            }
+                            let Reverse(p) = potential.pop().unwrap();
+                            let mut trade = Trade {
+                                buyer: TradeTarget(p.buyer),
+                                seller: TradeTarget(p.seller),
+                                qty: p.qty,
+                                kind,
+                                money_delta: Money::ZERO,
+                                tariff: Money::ZERO,
+                                value: Money::ZERO,
+                            };
+                            if apply_internal_trade(market, &mut trade) {
+                                trade.value = market.current_price * trade.qty as i64;
+                                if market.buy_orders.contains_key(&p.buyer) {
+                                    self.partially_filled.push(p.buyer);
+                                }
+                                self.all_trades.push(trade);
+                            }
+                        }
+
+                        // nothing left to buy locally
+                        if last_ring || market.sell_orders.is_empty() {
+                            break;
+                        }
+                        ring += 1;
+                        self.buyers
+                            .retain(|(buyer, _)| market.buy_orders.contains_key(buyer));
                     }
-                    let score = sorder.pos.distance2(border.pos);
-                    self.potential.push((
-                        Trade {
-                            buyer: TradeTarget(buyer),
-                            seller: TradeTarget(seller),
-                            qty: qty_buy,
-                            kind,
-                            money_delta: Money::ZERO,
-                        },
-                        score,
-                    ))
+
+                    potential.clear();
+                    self.potential = potential.into_vec();
                 }
+                self.partially_filled.sort_unstable();
+                self.partially_filled.dedup();
             }
-            self.potential
-                .sort_unstable_by_key(|(_, x)| OrderedFloat(*x));
+
+            // Price discovery: what could not be matched internally pushes the price around
+            let unfilled_demand = market.buy_orders.values().map(|o| o.qty as u64).sum();
+            let unsold_supply = market.sell_orders.values().map(|o| o.qty as u64).sum();
+            market.adjust_price(unfilled_demand, unsold_supply);
+
             let SingleMarket {
                 buy_orders,
                 sell_orders,
                 capital,
                 optout_exttrade,
-                ext_value,
+                current_price,
                 ..
             } = market;
 
-            self.all_trades
-                .extend(self.potential.drain(..).filter_map(|(trade, _)| {
-                    let cap_seller = capital.entry(trade.seller.0).or_default();
-                    if *cap_seller < trade.qty {
-                        return None;
-                    }
-
-                    let cap_buyer = capital.entry(trade.buyer.0).or_default();
-                    let border = buy_orders.entry(trade.buyer.0);
-
-                    match border {
-                        Entry::Vacant(_) => return None,
-                        Entry::Occupied(o) => o.remove(),
-                    };
-
-                    let sorderent = sell_orders.entry(trade.seller.0);
-
-                    let mut sorderocc = match sorderent {
-                        Entry::Vacant(_) => return None,
-                        Entry::Occupied(o) => o,
-                    };
-
-                    let sorder = sorderocc.get_mut();
-
-                    if sorder.qty < trade.qty as u32 {
-                        return None;
-                    }
-
-                    sorder.qty -= trade.qty as u32;
-
-                    if sorder.qty == 0 {
-                        sorderocc.remove();
-                    }
-
-                    // Safety: buyer cannot be the same as seller
-                    *cap_buyer += trade.qty;
-                    *capital.get_mut(&trade.seller.0).unwrap() -= trade.qty;
-
-                    Some(trade)
-                }));
-
             // External trading
             if !*optout_exttrade {
                 // All buyers can fullfil since they can buy externally
+                // Except the partially filled ones, which keep the rest of their order for the next tick
+                // and the ones that can't afford the external price, which wait for a local seller
+                let import_price = *current_price * (1.0 + self.trade_margin as f64);
+                let export_price = *current_price * (1.0 - self.trade_margin as f64);
+                let (unit_cost, _) = policy.import_cost(kind, import_price);
                 let btaken = std::mem::take(buy_orders);
                 self.all_trades.reserve(btaken.len());
                 for (buyer, order) in btaken {
+                    if self.partially_filled.binary_search(&buyer).is_ok()
+                        || !order.allows_external(unit_cost)
+                    {
+                        buy_orders.insert(buyer, order);
+                        continue;
+                    }
                     let qty_buy = order.qty as i32;
-                    *capital.entry(buyer).or_default() += qty_buy;
 
-                    let Some(ext) = find_external(order.pos) else {
+                    // No way in for the goods, try again next tick
+                    let Some(ext) = find_external(buyer, order.pos, order.qty) else {
+                        buy_orders.insert(buyer, order);
                         continue;
                     };
 
+                    if !matches!(ext, SoulID::RoadConnection(_) | SoulID::SeaConnection(_)) {
+                        *capital.entry(buyer).or_default() += qty_buy;
+                    }
+
+                    let value = import_price * qty_buy as i64;
+                    let (cost, tariff) = policy.import_cost(kind, value);
+
                     self.all_trades.push(Trade {
                         buyer: TradeTarget(buyer),
                         seller: TradeTarget(ext),
                         qty: qty_buy,
                         kind,
-                        money_delta: -(*ext_value * qty_buy as i64), // we buy from external so we pay
+                        money_delta: -cost, // we buy from external so we pay
+                        tariff,
+                        value,
                     });
                 }
 
@@ -310,23 +675,32 @@ impl Market {
                         continue;
                     }
                     let cap = capital.entry(seller).or_default();
-                    if *cap < qty_sell {
-                        log::warn!("{:?} is selling more than it has: {:?}", &seller, qty_sell);
+                    // the order may outlive the stock when the seller stops producing,
+                    // e.g. a farm out of season, only what is left is sold
+                    let qty_sell = qty_sell.min(*cap);
+                    if qty_sell <= 0 {
                         continue;
                     }
-                    *cap -= qty_sell;
-                    order.qty -= qty_sell as u32;
 
-                    let Some(ext) = find_external(order.pos) else {
+                    // No way out for the goods, try again next tick
+                    let Some(ext) = find_external(seller, order.pos, qty_sell as u32) else {
                         continue;
                     };
 
+                    *cap -= qty_sell;
+                    order.qty -= qty_sell as u32;
+
+                    let value = export_price * qty_sell as i64;
+                    let (earnings, tax) = policy.export_earnings(kind, value);
+
                     self.all_trades.push(Trade {
                         buyer: TradeTarget(ext),
                         seller: TradeTarget(seller),
                         qty: qty_sell,
                         kind,
-                        money_delta: *ext_value * qty_sell as i64,
+                        money_delta: earnings,
+                        tariff: tax,
+                        value,
                     });
                 }
             }
@@ -340,7 +714,55 @@ impl Market {
     }
 }
 
-fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
+/// Applies a trade between two souls if the buy order still exists and the seller can still provide something.
+/// The trade quantity is reduced to what the seller can provide, the rest of the buy order stays on the market.
+/// Returns whether the trade was made.
+fn apply_internal_trade(market: &mut SingleMarket, trade: &mut Trade) -> bool {
+    let SingleMarket {
+        buy_orders,
+        sell_orders,
+        capital,
+        ..
+    } = market;
+
+    let cap_seller = capital.get(&trade.seller.0).copied().unwrap_or(0);
+
+    let Entry::Occupied(mut borderocc) = buy_orders.entry(trade.buyer.0) else {
+        return false;
+    };
+
+    let Entry::Occupied(mut sorderocc) = sell_orders.entry(trade.seller.0) else {
+        return false;
+    };
+
+    let border = borderocc.get_mut();
+    let sorder = sorderocc.get_mut();
+
+    let qty = border.qty.min(sorder.qty).min(cap_seller.max(0) as u32);
+    if qty == 0 {
+        return false;
+    }
+    trade.qty = qty as i32;
+
+    border.qty -= qty;
+    if border.qty == 0 {
+        borderocc.remove();
+    }
+
+    sorder.qty -= qty;
+    if sorder.qty == 0 {
+        sorderocc.remove();
+    }
+
+    // Safety: buyer cannot be the same as seller
+    *capital.entry(trade.buyer.0).or_default() += trade.qty;
+    *capital.entry(trade.seller.0).or_default() -= trade.qty;
+
+    true
+}
+
+/// Which companies produce each item
+fn item_graph() -> BTreeMap<ItemID, Vec<GoodsCompanyID>> {
     let mut item_graph: BTreeMap<ItemID, Vec<GoodsCompanyID>> = BTreeMap::new();
     for company in GoodsCompanyPrototype::iter() {
         let Some(ref recipe) = company.recipe else {
@@ -350,48 +772,52 @@ fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
             item_graph.entry(item.id).or_default().push(company.id);
         }
     }
+    item_graph
+}
 
-    let mut prices = BTreeMap::new();
-    fn calculate_price_inner(
-        item_graph: &BTreeMap<ItemID, Vec<GoodsCompanyID>>,
-        id: ItemID,
-        prices: &mut BTreeMap<ItemID, Money>,
-        price_multiplier: f32,
-    ) {
-        if prices.contains_key(&id) {
-            return;
+fn calculate_price_inner(
+    item_graph: &BTreeMap<ItemID, Vec<GoodsCompanyID>>,
+    id: ItemID,
+    prices: &mut BTreeMap<ItemID, Money>,
+    price_multiplier: f32,
+) {
+    if prices.contains_key(&id) {
+        return;
+    }
+
+    let mut minprice = None;
+    for &comp in item_graph.get(&id).unwrap_or(&vec![]) {
+        let company = &comp.prototype();
+        let mut price_consumption = Money::ZERO;
+        let Some(ref recipe) = company.recipe else {
+            continue;
+        };
+        for recipe_item in &recipe.consumption {
+            calculate_price_inner(item_graph, recipe_item.id, prices, price_multiplier);
+            price_consumption += prices[&recipe_item.id] * recipe_item.amount as i64;
         }
+        let qty = recipe
+            .production
+            .iter()
+            .find_map(|x| (x.id == id).then_some(x.amount))
+            .unwrap_or(0) as i64;
 
-        let mut minprice = None;
-        for &comp in item_graph.get(&id).unwrap_or(&vec![]) {
-            let company = &comp.prototype();
-            let mut price_consumption = Money::ZERO;
-            let Some(ref recipe) = company.recipe else {
-                continue;
-            };
-            for recipe_item in &recipe.consumption {
-                calculate_price_inner(item_graph, recipe_item.id, prices, price_multiplier);
-                price_consumption += prices[&recipe_item.id] * recipe_item.amount as i64;
-            }
-            let qty = recipe
-                .production
-                .iter()
-                .find_map(|x| (x.id == id).then_some(x.amount))
-                .unwrap_or(0) as i64;
+        let price_workers =
+            recipe.duration.minutes() * company.n_workers as f64 * WORKER_CONSUMPTION_PER_MINUTE;
 
-            let price_workers = recipe.duration.minutes()
-                * company.n_workers as f64
-                * WORKER_CONSUMPTION_PER_MINUTE;
+        let newprice = (price_consumption
+            + Money::new_inner((price_workers.inner() as f32 * price_multiplier) as i64))
+            / qty;
 
-            let newprice = (price_consumption
-                + Money::new_inner((price_workers.inner() as f32 * price_multiplier) as i64))
-                / qty;
+        minprice = minprice.map(|x: Money| x.min(newprice)).or(Some(newprice));
+    }
 
-            minprice = minprice.map(|x: Money| x.min(newprice)).or(Some(newprice));
-        }
+    prices.insert(id, minprice.unwrap_or(Money::ZERO));
+}
 
-        prices.insert(id, minprice.unwrap_or(Money::ZERO));
-    }
+fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
+    let item_graph = item_graph();
+    let mut prices = BTreeMap::new();
 
     for item in ItemPrototype::iter() {
         calculate_price_inner(&item_graph, item.id, &mut prices, price_multiplier);
@@ -400,13 +826,26 @@ fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
     prices
 }
 
+/// Same as calculate_prices but for a single item, items that no company produces are free
+fn calculate_price(id: ItemID, price_multiplier: f32) -> Money {
+    let mut prices = BTreeMap::new();
+    calculate_price_inner(&item_graph(), id, &mut prices, price_multiplier);
+    prices[&id]
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use geom::{vec2, Vec2};
-    use prototypes::test_prototypes;
+    use ordered_float::OrderedFloat;
     use prototypes::ItemID;
+    use prototypes::{Money, Tick, TICKS_PER_HOUR};
 
-    use crate::economy::WORKER_CONSUMPTION_PER_MINUTE;
+    use crate::economy::{FreightThroughput, TradePolicy, WORKER_CONSUMPTION_PER_MINUTE};
+    use crate::gameplay::GameplayParams;
+    use crate::map::{BuildingID, IntersectionID};
+    use crate::tests::test_prototypes;
     use crate::world::CompanyID;
     use crate::{FreightStationID, SoulID};
 
@@ -425,7 +864,7 @@ mod tests {
             (1 << 32) | 4,
         )));
 
-        test_prototypes(
+        let _prototypes = test_prototypes(
             r#"
         data:extend {
           {
@@ -453,7 +892,7 @@ mod tests {
         m.sell(seller, Vec2::X, cereal, 3, 5);
         m.sell(seller_far, vec2(10.0, 10.0), cereal, 3, 5);
 
-        let trades = m.make_trades(|_| Some(freight));
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
 
         assert_eq!(trades.len(), 1);
         let t0 = trades[0];
@@ -462,9 +901,543 @@ mod tests {
         assert_eq!(t0.qty, 2);
     }
 
+    #[test]
+    fn road_imports_are_received_with_the_truck() {
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let connection = SoulID::RoadConnection(IntersectionID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 2,
+        )));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+
+        m.buy(buyer, Vec2::ZERO, cereal, 4);
+
+        // no way in, the order stays on the market
+        assert!(m
+            .make_trades(&TradePolicy::default(), |_, _, _| None)
+            .is_empty());
+        assert!(m.inner()[&cereal].buy_order(buyer).is_some());
+
+        let trades = m.make_trades(&TradePolicy::default(), |soul, _, _| {
+            assert_eq!(soul, buyer);
+            Some(connection)
+        });
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seller.0, connection);
+        assert_eq!(trades[0].qty, 4);
+        assert_eq!(m.capital(buyer, cereal), 0);
+
+        m.receive(buyer, cereal, 4);
+        assert_eq!(m.capital(buyer, cereal), 4);
+    }
+
+    #[test]
+    fn test_partial_fill() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let seller_far = SoulID::GoodsCompany(mk_ent((1 << 32) | 2));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 3));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 4,
+        )));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+
+        let cereal = ItemID::new("cereal");
+
+        m.produce(seller, cereal, 3);
+        m.produce(seller_far, cereal, 3);
+
+        m.buy(buyer, Vec2::ZERO, cereal, 5);
+        m.sell(seller, Vec2::X, cereal, 3, 5);
+        m.sell(seller_far, vec2(10.0, 10.0), cereal, 3, 5);
+
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+
+        assert_eq!(trades.len(), 2);
+        let t0 = trades[0];
+        assert_eq!(t0.seller.0, seller);
+        assert_eq!(t0.buyer.0, buyer);
+        assert_eq!(t0.qty, 3);
+        let t1 = trades[1];
+        assert_eq!(t1.seller.0, seller_far);
+        assert_eq!(t1.buyer.0, buyer);
+        assert_eq!(t1.qty, 2);
+
+        assert_eq!(trades.iter().map(|t| t.qty).sum::<i32>(), 5);
+        assert_eq!(m.capital(buyer, cereal), 5);
+        assert_eq!(m.capital(seller_far, cereal), 1);
+        assert!(m.m(cereal).buy_order(buyer).is_none());
+    }
+
+    #[test]
+    fn prioritized_buyer_is_served_first() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 2));
+        let buyer_far = SoulID::GoodsCompany(mk_ent((1 << 32) | 3));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 4,
+        )));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+
+        let cereal = ItemID::new("cereal");
+
+        m.produce(seller, cereal, 3);
+        m.sell(seller, Vec2::X, cereal, 3, 5);
+        m.buy_local(buyer, Vec2::ZERO, cereal, 3);
+        m.buy_local(buyer_far, vec2(100.0, 100.0), cereal, 3);
+        m.set_prioritized(buyer_far, true);
+
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].buyer.0, buyer_far);
+        assert_eq!(trades[0].qty, 3);
+        assert_eq!(m.m(cereal).buy_order(buyer).map(|o| o.qty), Some(3));
+
+        m.remove(buyer_far);
+        assert!(!m.is_prioritized(buyer_far));
+    }
+
+    #[test]
+    fn test_partial_fill_persists() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 3));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 4,
+        )));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+
+        let cereal = ItemID::new("cereal");
+
+        m.produce(seller, cereal, 8);
+
+        m.buy(buyer, Vec2::ZERO, cereal, 10);
+        m.sell(seller, Vec2::X, cereal, 8, 8);
+
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].qty, 8);
+        assert_eq!(m.m(cereal).buy_order(buyer).unwrap().qty, 2);
+    }
+
+    #[test]
+    fn test_local_buy_waits_for_seller() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 2));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 3,
+        )));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+
+        m.buy_local(buyer, Vec2::ZERO, cereal, 2);
+
+        for _ in 0..5 {
+            let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+            assert!(trades.is_empty());
+            assert_eq!(m.m(cereal).buy_order(buyer).unwrap().qty, 2);
+        }
+        assert_eq!(m.capital(buyer, cereal), 0);
+
+        m.produce(seller, cereal, 3);
+        m.sell(seller, Vec2::X, cereal, 3, 3);
+
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seller.0, seller);
+        assert_eq!(trades[0].buyer.0, buyer);
+        assert_eq!(trades[0].qty, 2);
+        assert!(m.m(cereal).buy_order(buyer).is_none());
+    }
+
+    #[test]
+    fn freight_capacity_defers_trades() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 100,
+        )));
+        let station = BuildingID::from(slotmapd::KeyData::from_ffi((1 << 32) | 1));
+        let cereal = ItemID::new("cereal");
+
+        let mut m = Market::default();
+        let mut throughput = FreightThroughput::default();
+
+        for i in 0..5 {
+            m.buy(
+                SoulID::GoodsCompany(mk_ent((1 << 32) | (i + 1))),
+                Vec2::ZERO,
+                cereal,
+                1,
+            );
+        }
+
+        let mut total = 0;
+        let mut hour = 0;
+        let mut per_hour = vec![];
+        while total < 5 {
+            throughput.advance(Tick(hour * TICKS_PER_HOUR));
+
+            let mut n = 0;
+            // a few ticks in the same hour
+            for _ in 0..3 {
+                n += m
+                    .make_trades(&TradePolicy::default(), |_, _, qty| {
+                        throughput.try_reserve(station, 2, qty).then_some(freight)
+                    })
+                    .len();
+            }
+            per_hour.push(n);
+            total += n;
+            hour += 1;
+            assert!(hour < 10);
+        }
+
+        assert_eq!(per_hour, vec![2, 2, 1]);
+        assert_eq!(throughput.used(station), 1);
+        assert_eq!(throughput.utilization(station, 2), 0.5);
+    }
+
+    #[test]
+    fn grid_matching_same_as_naive() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+
+        let mut sellers = Vec::new();
+        let mut buyers = Vec::new();
+
+        for i in 0..5000u32 {
+            let soul = SoulID::GoodsCompany(mk_ent((1 << 32) | (i as u64 + 1)));
+            let pos = vec2(
+                common::rand::randu(i * 3) * 20000.0,
+                common::rand::randu(i * 3 + 1) * 20000.0,
+            );
+            let r = common::rand::randu(i * 3 + 2);
+            if i % 2 == 0 {
+                let qty = 1 + (r * 10.0) as u32;
+                m.produce(soul, cereal, qty as i32);
+                m.sell(soul, pos, cereal, qty, qty);
+                sellers.push((soul, pos, qty));
+            } else {
+                let qty = 1 + (r * 5.0) as u32;
+                m.buy(soul, pos, cereal, qty);
+                buyers.push((soul, pos, qty));
+            }
+        }
+
+        // naive all-pairs greedy matching
+        let mut pairs = Vec::new();
+        for &(seller, spos, _) in &sellers {
+            for &(buyer, bpos, _) in &buyers {
+                pairs.push((OrderedFloat(spos.distance2(bpos)), seller, buyer));
+            }
+        }
+        pairs.sort();
+
+        let mut remaining_sell: BTreeMap<SoulID, u32> =
+            sellers.iter().map(|&(s, _, q)| (s, q)).collect();
+        let mut remaining_buy: BTreeMap<SoulID, u32> =
+            buyers.iter().map(|&(b, _, q)| (b, q)).collect();
+        let mut naive_trades = Vec::new();
+        for (_, seller, buyer) in pairs {
+            let rem_sell = remaining_sell.get_mut(&seller).unwrap();
+            let rem_buy = remaining_buy.get_mut(&buyer).unwrap();
+            let qty = (*rem_sell).min(*rem_buy);
+            if qty == 0 {
+                continue;
+            }
+            *rem_sell -= qty;
+            *rem_buy -= qty;
+            naive_trades.push((seller, buyer, qty));
+        }
+
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| None);
+
+        assert!(!naive_trades.is_empty());
+        assert_eq!(trades.len(), naive_trades.len());
+        for (t, &(seller, buyer, qty)) in trades.iter().zip(naive_trades.iter()) {
+            assert_eq!(t.seller.0, seller);
+            assert_eq!(t.buyer.0, buyer);
+            assert_eq!(t.qty, qty as i32);
+        }
+    }
+
+    #[test]
+    fn import_tariff_doubles_cost() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+
+        data:extend {{
+            type = "goods-company",
+            name = "cereal-farm",
+            label = "Cereal farm",
+            kind = "factory",
+            bgen = "farm",
+            recipe = {
+                production = {
+                    {"cereal", 3}
+                },
+                consumption = {},
+                duration = "3m",
+                storage_multiplier = 5,
+            },
+            n_trucks = 1,
+            n_workers = 2,
+            size = 10.0,
+            asset = "no.jpg",
+            price = 0,
+        }}
+        "#,
+        );
+
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 2,
+        )));
+        let cereal = ItemID::new("cereal");
+
+        let mut m = Market::default();
+        m.buy(buyer, Vec2::ZERO, cereal, 4);
+        let trades = m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+        assert_eq!(trades.len(), 1);
+        let paid_no_tariff = -trades[0].money_delta;
+        assert!(paid_no_tariff > Money::ZERO);
+        assert_eq!(trades[0].tariff, Money::ZERO);
+
+        let mut policy = TradePolicy::default();
+        policy.global.import = 1.0;
+
+        let mut m = Market::default();
+        m.buy(buyer, Vec2::ZERO, cereal, 4);
+        let trades = m.make_trades(&policy, |_, _, _| Some(freight));
+        assert_eq!(trades.len(), 1);
+        assert_eq!(-trades[0].money_delta, paid_no_tariff * 2);
+        assert_eq!(trades[0].tariff, paid_no_tariff);
+    }
+
+    #[test]
+    fn price_drops_on_oversupply() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+
+        data:extend {{
+            type = "goods-company",
+            name = "cereal-farm",
+            label = "Cereal farm",
+            kind = "factory",
+            bgen = "farm",
+            recipe = {
+                production = {
+                    {"cereal", 3}
+                },
+                consumption = {},
+                duration = "3m",
+                storage_multiplier = 5,
+            },
+            n_trucks = 1,
+            n_workers = 2,
+            size = 10.0,
+            asset = "no.jpg",
+            price = 0,
+        }}
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 100,
+        )));
+
+        for i in 0..10 {
+            let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | (i + 1)));
+            m.produce(seller, cereal, 100);
+            // stock == qty so nothing is exported and the orders stay unsold
+            m.sell(seller, vec2(i as f32, 0.0), cereal, 100, 100);
+        }
+
+        let base = m.m(cereal).price();
+        assert_eq!(base, m.m(cereal).ext_value);
+
+        let mut last = base;
+        for _ in 0..10 {
+            m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+            let price = m.m(cereal).price();
+            assert!(price < last, "price should drop: {:?} >= {:?}", price, last);
+            last = price;
+        }
+
+        for _ in 0..10000 {
+            m.make_trades(&TradePolicy::default(), |_, _, _| Some(freight));
+        }
+        assert_eq!(m.m(cereal).price(), base * 0.5);
+    }
+
+    #[test]
+    fn removed_item_is_dropped_on_load() {
+        use common::saveload::{Bincode, Encoder};
+
+        let soul = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          },
+          {
+            type = "item",
+            name = "wheat",
+            label = "Wheat",
+          }
+        }
+        "#,
+        );
+
+        let cereal = ItemID::new("cereal");
+        let wheat = ItemID::new("wheat");
+
+        let mut m = Market::default();
+        m.produce(soul, cereal, 3);
+        m.produce(soul, wheat, 5);
+        m.sell(soul, Vec2::ZERO, wheat, 5, 0);
+
+        let saved = Bincode::encode(&m).unwrap();
+
+        // wheat was removed by a mod change, the guard is still held
+        prototypes::test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m: Market = Bincode::decode(&saved).unwrap();
+
+        assert_eq!(m.inner().len(), 1);
+        assert!(!m.inner().contains_key(&wheat));
+        assert_eq!(m.capital(soul, cereal), 3);
+        assert_eq!(m.capital(soul, wheat), 0);
+
+        // using an unknown item creates a fresh market instead of panicking
+        m.buy(soul, Vec2::ZERO, wheat, 1);
+        assert_eq!(m.inner().len(), 2);
+        assert!(m.inner()[&wheat].sell_order(soul).is_none());
+
+        m.make_trades(&TradePolicy::default(), |_, _, _| None);
+    }
+
     #[test]
     fn calculate_prices() {
-        test_prototypes(
+        let _prototypes = test_prototypes(
             r#"
         data:extend {
           {
@@ -495,7 +1468,7 @@ mod tests {
             },
             n_trucks = 1,
             n_workers = 2,
-            size = 0.0,
+            size = 10.0,
             asset = "no.jpg",
             price = 0,
         },
@@ -517,7 +1490,7 @@ mod tests {
             },
             n_trucks = 1,
             n_workers = 5,
-            size = 0.0,
+            size = 10.0,
             asset = "no.jpg",
             price = 0,
         }}
@@ -536,5 +1509,23 @@ mod tests {
             prices[&wheat],
             (price_cereal * 2 + 5 * WORKER_CONSUMPTION_PER_MINUTE * 10) / 2
         );
+
+        // the market uses the price multiplier of the gameplay parameters
+        let params = GameplayParams {
+            price_multiplier: 2.0,
+            ..Default::default()
+        };
+        let mut m = Market::new(&params);
+        let doubled = super::calculate_prices(2.0);
+        assert_eq!(doubled[&cereal], price_cereal * 2);
+        assert_eq!(m.m(cereal).ext_value, doubled[&cereal]);
+        assert_eq!(m.m(wheat).ext_value, doubled[&wheat]);
+        assert_eq!(m.m(wheat).price(), doubled[&wheat]);
+
+        m.set_params(&GameplayParams::default());
+        assert_eq!(
+            m.m(cereal).ext_value,
+            super::calculate_prices(1.25)[&cereal]
+        );
     }
 }
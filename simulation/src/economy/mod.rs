@@ -7,25 +7,56 @@
 //! - The market, which is the place where goods are exchanged.
 //! - The government, which is the entity representing the player
 //!
+//! Jobs are not goods: they go through the job market, where workers are paid a wage by companies.
+//!
+//! The government can tax external trade through the trade policy.
+//! It also sets the price of electricity, which consumers pay to the producers once per day.
+//! Households pay it a daily rent depending on the land value of their home.
+//!
+use crate::statistics::Statistics;
 use crate::utils::resources::Resources;
 use crate::SoulID;
 use crate::World;
 use egui_inspect::Inspect;
+use geom::Vec2;
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
+mod border;
 mod ecostats;
+mod electricity_bill;
+mod freight_throughput;
 mod government;
+mod history;
+mod job_market;
+mod ledger;
 mod market;
+mod order_grid;
+mod rent;
+mod sea;
+mod trade_policy;
 
-use crate::map::Map;
+use crate::chronicle::{Chronicle, ChronicleKind, BIG_TRADE};
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::objectives::ScenarioRunner;
+use crate::souls::delivery::delivered_by_truck;
 use crate::world::HumanID;
+pub use border::*;
 pub use ecostats::*;
+pub use electricity_bill::*;
+pub use freight_throughput::*;
 pub use government::*;
+pub use history::*;
+pub use job_market::*;
+pub use ledger::*;
 pub use market::*;
 use prototypes::{GameTime, ItemID, Money, TICKS_PER_MINUTE};
+pub use rent::*;
+pub use sea::*;
+pub use trade_policy::*;
 
 const WORKER_CONSUMPTION_PER_MINUTE: Money = Money::new_cents(10);
 
@@ -38,65 +69,317 @@ pub struct Bought(pub BTreeMap<ItemID, Vec<Trade>>);
 #[derive(Inspect, Debug, Default, Serialize, Deserialize)]
 pub struct Workers(pub Vec<HumanID>);
 
+/// Money owned by a human, earned from wages and spent buying goods
+#[derive(Inspect, Debug, Default, Serialize, Deserialize)]
+pub struct Wallet(pub Money);
+
+/// Accounting of a company: it earns money selling goods,
+/// and spends it buying goods and paying wages.
+#[derive(Inspect, Debug, Default, Serialize, Deserialize)]
+pub struct CompanyFinances {
+    pub balance: Money,
+    /// Number of consecutive in-game days that ended with a negative balance
+    pub negative_days: u32,
+    last_day: i32,
+}
+
+impl CompanyFinances {
+    pub fn new(starting_capital: Money, day: i32) -> Self {
+        Self {
+            balance: starting_capital,
+            negative_days: 0,
+            last_day: day,
+        }
+    }
+
+    /// Updates the count of negative days when a new day starts
+    pub fn update_day(&mut self, day: i32) {
+        if day == self.last_day {
+            return;
+        }
+        self.last_day = day;
+        if self.balance < Money::ZERO {
+            self.negative_days += 1;
+        } else {
+            self.negative_days = 0;
+        }
+    }
+
+    /// Whether the company should close. A threshold of 0 means never.
+    pub fn is_bankrupt(&self, bankruptcy_days: u32) -> bool {
+        bankruptcy_days > 0 && self.negative_days >= bankruptcy_days
+    }
+
+    /// Number of days left before closing if the balance stays negative, None if the company is fine
+    pub fn days_until_bankruptcy(&self, bankruptcy_days: u32) -> Option<u32> {
+        if bankruptcy_days == 0 || self.balance >= Money::ZERO {
+            return None;
+        }
+        Some(bankruptcy_days.saturating_sub(self.negative_days))
+    }
+}
+
+/// How the goods of external trades enter or leave the city
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FreightMode {
+    Rail,
+    Road,
+    Sea,
+}
+
+impl FreightMode {
+    /// Cost of carrying a unit of goods over a meter, trucks being the reference
+    pub fn cost_per_meter(self) -> f32 {
+        match self {
+            FreightMode::Rail => 0.3,
+            FreightMode::Road => 1.0,
+            FreightMode::Sea => 0.2,
+        }
+    }
+
+    /// Cost of carrying a unit of goods over the line haul of this mode,
+    /// then by truck over the last mile between the connection and the local soul
+    pub fn cost(self, line_haul: f32, last_mile: f32) -> f32 {
+        line_haul * self.cost_per_meter() + last_mile * FreightMode::Road.cost_per_meter()
+    }
+}
+
 pub fn market_update(world: &mut World, resources: &mut Resources) {
     profiling::scope!("economy::market_update");
-    let n_workers = world.humans.len();
+    let n_workers = world
+        .humans
+        .values()
+        .filter(|h| h.tourist.is_none())
+        .count();
 
     let mut m = resources.write::<Market>();
-    let job_opening = ItemID::new("job-opening");
     let mut gvt = resources.write::<Government>();
     let tick = resources.read::<GameTime>().tick;
 
     if tick.0 % TICKS_PER_MINUTE == 0 {
-        gvt.money -= n_workers as i64 * WORKER_CONSUMPTION_PER_MINUTE;
+        gvt.transact(
+            BudgetReason::WorkerSupplies,
+            -(n_workers as i64 * WORKER_CONSUMPTION_PER_MINUTE),
+        );
     }
 
     let freights = &world.freight_stations;
 
     let map = resources.read::<Map>();
-    let trades = m.make_trades(|pos| {
-        freights
+    let binfos = resources.read::<BuildingInfos>();
+    let policy = resources.read::<TradePolicy>();
+    let mut throughput = resources.write::<FreightThroughput>();
+    let mut border = resources.write::<BorderTrade>();
+    let mut sea = resources.write::<SeaTrade>();
+    throughput.advance(tick);
+    border.advance(tick);
+    sea.update(&map);
+
+    // the harbors are only looked up when a company trades with the outside
+    let mut harbors: Option<Vec<(BuildingID, Vec2, u32)>> = None;
+
+    let mut candidates = Vec::with_capacity(freights.len());
+    let trades = m.make_trades(&policy, |soul, pos, qty| {
+        // go through the cheapest connection that is reachable and still has capacity.
+        // Households shop at the freight stations, trucks and ships only serve the companies.
+        candidates.clear();
+        candidates.extend(freights.iter().filter_map(|(id, f)| {
+            let door = map.buildings.get(f.f.building)?.door_pos.xy();
+            // trains come from the nearest place where the railway leaves the map,
+            // or from the nearest edge of the map when it doesn't leave it yet
+            let line_haul = map
+                .external_train_stations()
+                .iter()
+                .filter_map(|&ext| map.buildings.get(ext))
+                .map(|ext| OrderedFloat(ext.obb.center().distance(door)))
+                .min()
+                .map_or_else(|| map.distance_to_border(door).max(0.0), |d| d.0);
+            Some((
+                OrderedFloat(FreightMode::Rail.cost(line_haul, door.distance(pos))),
+                SoulID::FreightStation(id),
+            ))
+        }));
+        let building = match soul {
+            SoulID::GoodsCompany(_) => binfos.building_owned_by(soul),
+            _ => None,
+        };
+        if building.is_some() {
+            candidates.extend(map.road_connections().map(|(inter, ipos)| {
+                (
+                    OrderedFloat(FreightMode::Road.cost(ipos.xy().distance(pos), 0.0)),
+                    SoulID::RoadConnection(inter),
+                )
+            }));
+
+            let harbors = harbors.get_or_insert_with(|| {
+                map.buildings
+                    .values()
+                    .filter_map(|b| match b.kind {
+                        BuildingKind::Harbor(id) => {
+                            Some((b.id, b.door_pos.xy(), id.prototype().throughput))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            });
+            candidates.extend(harbors.iter().filter_map(|&(harbor, door, _)| {
+                let line_haul = sea.route_length(&map, harbor)?;
+                Some((
+                    OrderedFloat(FreightMode::Sea.cost(line_haul, door.distance(pos))),
+                    SoulID::SeaConnection(harbor),
+                ))
+            }));
+        }
+        candidates.sort_unstable_by_key(|&(cost, _)| cost);
+
+        candidates
             .iter()
-            .min_by_key(|(_, b)| {
-                let Some(b) = map.buildings.get(b.f.building) else {
-                    return OrderedFloat(f32::INFINITY);
-                };
-                OrderedFloat(b.door_pos.xy().distance2(pos))
+            .find(|&&(_, ext)| match ext {
+                SoulID::FreightStation(id) => {
+                    let f = &freights[id].f;
+                    throughput.try_reserve(f.building, f.proto.prototype().throughput, qty)
+                }
+                SoulID::RoadConnection(inter) => {
+                    let building = building.unwrap(); // Unwrap ok: only added with a building
+                    border.can_reach(&map, tick, building, inter) && border.try_reserve(inter, qty)
+                }
+                SoulID::SeaConnection(harbor) => {
+                    let capacity = harbors
+                        .iter()
+                        .flatten()
+                        .find(|&&(id, _, _)| id == harbor)
+                        .map_or(0, |&(_, _, capacity)| capacity);
+                    throughput.try_reserve(harbor, capacity, qty)
+                }
+                _ => false,
             })
-            .map(|(id, _)| SoulID::FreightStation(id))
+            .map(|&(_, ext)| ext)
     });
 
+    // the goods going through the road connections are carried by trucks, and by ships through
+    // the harbors. The exports through the road connections are paid once the truck leaves.
+    for trade in trades.iter() {
+        let (local, import, ext, earned) = match (trade.buyer.0, trade.seller.0) {
+            (local, ext) if ext.is_external() => (local, true, ext, Money::ZERO),
+            (ext, local) if ext.is_external() => (local, false, ext, trade.money_delta),
+            _ => continue,
+        };
+        let cargo = Cargo {
+            soul: local,
+            kind: trade.kind,
+            qty: trade.qty,
+        };
+        match ext {
+            SoulID::RoadConnection(connection) => {
+                // road connections are only given to souls owning a building
+                let Some(building) = binfos.building_owned_by(local) else {
+                    continue;
+                };
+                border.ship(connection, building, import, cargo, earned);
+            }
+            SoulID::SeaConnection(harbor) => sea.ship(harbor, import, cargo),
+            _ => {}
+        }
+    }
+
     resources.write::<EcoStats>().advance(tick.0, trades);
+    resources.write::<Statistics>().record_trades(trades);
+    let mut history = resources.write::<EconomyHistory>();
+    history.record_trades(trades);
+    resources.write::<ScenarioRunner>().record_exports(trades);
+
+    // the goods sold by companies owning trucks are received when they are delivered
+    let mut withheld = vec![];
+    let mut chronicle = resources.write::<Chronicle>();
 
     for &trade in trades.iter() {
         log::debug!("A trade was made! {:?}", trade);
 
-        if trade.kind == job_opening {
-            if let SoulID::GoodsCompany(id) = trade.seller.0 {
-                let comp = world.companies.get_mut(id).unwrap();
-                comp.workers.0.push(trade.buyer.0.try_into().unwrap())
+        if trade.value >= BIG_TRADE {
+            let pos = binfos
+                .building_owned_by(trade.buyer.0)
+                .and_then(|b| map.buildings.get(b))
+                .map(|b| b.door_pos);
+            chronicle.record(
+                tick,
+                ChronicleKind::Trade,
+                format!(
+                    "{} {} sold for {}",
+                    trade.qty,
+                    trade.kind.prototype().label,
+                    trade.value
+                ),
+                pos,
+            );
+        }
+
+        // The local soul pays for its imports, tariffs included, and keeps what its exports earn.
+        // The government takes the tariffs and settles the external trades of the souls
+        // without a wallet.
+        let external = match (trade.buyer.0, trade.seller.0) {
+            (buyer, seller) if buyer.is_external() => {
+                Some((seller, BudgetReason::Exports(trade.kind)))
+            }
+            (buyer, seller) if seller.is_external() => {
+                Some((buyer, BudgetReason::Imports(trade.kind)))
+            }
+            _ => None,
+        };
+        if let Some((local, reason)) = external {
+            if !local.has_wallet() {
+                gvt.transact(reason, trade.money_delta);
             }
+            gvt.transact(BudgetReason::Tariffs, trade.tariff);
+            gvt.tariff_income += trade.tariff;
         }
-        gvt.money += trade.money_delta;
+
+        // What the buyer pays and what the seller earns
+        let (paid, earned) = match (trade.buyer.0, trade.seller.0) {
+            (buyer, _) if buyer.is_external() => (Money::ZERO, trade.money_delta),
+            (_, seller) if seller.is_external() => (-trade.money_delta, Money::ZERO),
+            _ => (trade.value, trade.value),
+        };
 
         if let SoulID::GoodsCompany(id) = trade.seller.0 {
-            if trade.kind != job_opening {
-                world.companies.get_mut(id).unwrap().sold.0.push(trade);
+            let c = world.companies.get_mut(id).unwrap();
+            if !matches!(trade.buyer.0, SoulID::RoadConnection(_)) {
+                c.finances.balance += earned;
+            }
+            // the goods going to a company are received when the driver delivers them,
+            // they are handed over right away when nobody can drive the trucks
+            let by_truck = delivered_by_truck(trade.buyer.0);
+            let delivered = c.comp.delivers_goods() && c.comp.driver.is_some();
+            if delivered && by_truck {
+                withheld.push(trade);
+            }
+            if delivered || !by_truck {
+                c.sold.0.push(trade);
             }
         }
 
         match trade.buyer.0 {
             SoulID::Human(id) => {
                 if let Some(h) = world.humans.get_mut(id) {
+                    h.wallet.0 -= paid;
                     h.bought.0.entry(trade.kind).or_default().push(trade);
                 }
             }
             SoulID::GoodsCompany(id) => {
                 if let Some(c) = world.companies.get_mut(id) {
+                    c.finances.balance -= paid;
                     c.bought.0.entry(trade.kind).or_default().push(trade)
                 }
             }
-            SoulID::FreightStation(_) => {}
+            SoulID::FreightStation(_)
+            | SoulID::RoadConnection(_)
+            | SoulID::SeaConnection(_)
+            | SoulID::Warehouse(_) => {}
         }
     }
+
+    for trade in withheld {
+        m.withhold(trade.buyer.0, trade.kind, trade.qty);
+    }
+
+    history.advance(tick.0, &m);
 }
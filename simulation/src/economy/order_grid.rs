@@ -0,0 +1,140 @@
+use common::FastMap;
+use geom::Vec2;
+
+use crate::SoulID;
+
+/// Size of a grid cell in meters
+pub const ORDER_GRID_CELL_SIZE: f32 = 500.0;
+
+type Cell = (i32, i32);
+
+fn cell_of(pos: Vec2) -> Cell {
+    (
+        (pos.x / ORDER_GRID_CELL_SIZE).floor() as i32,
+        (pos.y / ORDER_GRID_CELL_SIZE).floor() as i32,
+    )
+}
+
+/// Grid hash of the sell orders of a single item, used to find the nearest sellers of a buyer
+/// without going through all of them.
+/// The grid is cleared and refilled every tick, the cells are kept around to avoid allocations.
+#[derive(Default)]
+pub struct OrderGrid {
+    cells: FastMap<Cell, Vec<(SoulID, Vec2, i32)>>,
+    min: Cell,
+    max: Cell,
+    empty: bool,
+}
+
+impl OrderGrid {
+    pub fn clear(&mut self) {
+        for v in self.cells.values_mut() {
+            v.clear();
+        }
+        self.empty = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    pub fn insert(&mut self, soul: SoulID, pos: Vec2, qty: i32) {
+        let cell = cell_of(pos);
+        if self.empty {
+            self.min = cell;
+            self.max = cell;
+            self.empty = false;
+        } else {
+            self.min = (self.min.0.min(cell.0), self.min.1.min(cell.1));
+            self.max = (self.max.0.max(cell.0), self.max.1.max(cell.1));
+        }
+        self.cells.entry(cell).or_default().push((soul, pos, qty));
+    }
+
+    /// Number of rings needed around pos to cover all the inserted orders
+    pub fn max_ring(&self, pos: Vec2) -> i32 {
+        let (x, y) = cell_of(pos);
+        [
+            (x - self.min.0).abs(),
+            (x - self.max.0).abs(),
+            (y - self.min.1).abs(),
+            (y - self.max.1).abs(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+    }
+
+    /// Calls f on all orders within the cells at exactly `ring` cells (chebyshev distance) from pos.
+    /// Once rings 0..=k have been visited, all orders at a distance strictly less than
+    /// k * ORDER_GRID_CELL_SIZE have been visited.
+    /// Only the part of the ring within the bounding box of the orders is iterated.
+    pub fn ring(&self, pos: Vec2, ring: i32, mut f: impl FnMut(SoulID, Vec2, i32)) {
+        if self.empty {
+            return;
+        }
+        let (cx, cy) = cell_of(pos);
+        let mut visit = |cell: Cell| {
+            let Some(v) = self.cells.get(&cell) else {
+                return;
+            };
+            for &(soul, pos, qty) in v {
+                f(soul, pos, qty);
+            }
+        };
+
+        let in_x = |x: i32| self.min.0 <= x && x <= self.max.0;
+        let in_y = |y: i32| self.min.1 <= y && y <= self.max.1;
+
+        if ring == 0 {
+            if in_x(cx) && in_y(cy) {
+                visit((cx, cy));
+            }
+            return;
+        }
+
+        let x_range = (cx - ring).max(self.min.0)..=(cx + ring).min(self.max.0);
+        for y in [cy - ring, cy + ring].into_iter().filter(|&y| in_y(y)) {
+            for x in x_range.clone() {
+                visit((x, y));
+            }
+        }
+
+        let y_range = (cy - ring + 1).max(self.min.1)..=(cy + ring - 1).min(self.max.1);
+        for x in [cx - ring, cx + ring].into_iter().filter(|&x| in_x(x)) {
+            for y in y_range.clone() {
+                visit((x, y));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec2;
+
+    use crate::world::CompanyID;
+    use crate::SoulID;
+
+    use super::{OrderGrid, ORDER_GRID_CELL_SIZE};
+
+    #[test]
+    fn rings_stop_at_the_orders() {
+        let soul =
+            SoulID::GoodsCompany(CompanyID::from(slotmapd::KeyData::from_ffi((1 << 32) | 1)));
+        let mut grid = OrderGrid::default();
+        grid.insert(soul, vec2(10.0, 10.0), 1);
+
+        let far = vec2(10.0 * ORDER_GRID_CELL_SIZE, 0.0);
+        assert_eq!(grid.max_ring(far), 10);
+
+        let mut found = vec![];
+        for ring in 0..=grid.max_ring(far) {
+            grid.ring(far, ring, |soul, _, _| found.push((ring, soul)));
+        }
+        assert_eq!(found, vec![(10, soul)]);
+
+        grid.clear();
+        grid.ring(far, 10, |_, _, _| panic!("the grid was cleared"));
+    }
+}
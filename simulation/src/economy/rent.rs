@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, Money};
+
+use crate::economy::{BudgetReason, Government};
+use crate::map::{BuildingKind, Map};
+use crate::map_dynamic::{BuildingInfos, LandValue, BASE_LAND_VALUE};
+use crate::utils::resources::Resources;
+use crate::{SoulID, World};
+
+/// Rent paid each day by a household whose home stands on land worth [`BASE_LAND_VALUE`]
+pub const BASE_DAILY_RENT: Money = Money::new_bucks(10);
+
+/// Once per in-game day, households pay a rent proportional to the land value of their home
+/// to the government
+#[derive(Default, Serialize, Deserialize)]
+pub struct RentCollection {
+    last_day: i32,
+    /// Total rent collected on the last day
+    pub last_total: Money,
+}
+
+/// Rent of a home standing on land of this value
+pub fn daily_rent(land_value: f32) -> Money {
+    BASE_DAILY_RENT * (land_value / BASE_LAND_VALUE) as f64
+}
+
+/// Collects the rent of the day, paid from the wallet of the owner of each house
+pub fn rent_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("economy::rent_system");
+    let day = resources.read::<GameTime>().daytime.day;
+    let mut rent = resources.write::<RentCollection>();
+    if rent.last_day == day {
+        return;
+    }
+    rent.last_day = day;
+
+    let map = resources.read::<Map>();
+    let binfos = resources.read::<BuildingInfos>();
+    let land = resources.read::<LandValue>();
+
+    let mut total = Money::ZERO;
+    for b in map.buildings().values() {
+        if b.kind != BuildingKind::House {
+            continue;
+        }
+        let Some(SoulID::Human(owner)) = binfos.owner(b.id) else {
+            continue;
+        };
+        let Some(h) = world.humans.get_mut(owner) else {
+            continue;
+        };
+        let due = daily_rent(land.land_value(b.obb.center()));
+        h.wallet.0 -= due;
+        total += due;
+    }
+
+    resources
+        .write::<Government>()
+        .transact(BudgetReason::Rent, total);
+    rent.last_total = total;
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, HarborPrototypeID, DELTA};
+
+use crate::economy::{Cargo, Market};
+use crate::map::{Building, BuildingID, BuildingKind, Map, MapSubscriber, UpdateType, WaterGrid};
+use crate::Simulation;
+
+/// Cruising speed of the cargo ships in meters per second
+pub const SHIP_MAX_SPEED: f32 = 10.0;
+
+/// Ships are heavy, they take a long time to get going
+const SHIP_ACCELERATION: f32 = 0.4;
+
+/// And a long time to stop, they start braking well before the dock
+const SHIP_DECELERATION: f32 = 0.3;
+
+/// Speed at which a ship crawls the last meters to the dock
+const SHIP_MOORING_SPEED: f32 = 0.5;
+
+/// How fast the bow swings toward the next waypoint, part of the gap closed per second
+const SHIP_TURN_RATE: f32 = 0.3;
+
+/// Distance to a waypoint at which the ship heads to the next one
+const WAYPOINT_REACH: f32 = 6.0;
+
+/// Seconds a ship stays at the dock to load or unload
+const SHIP_DOCK_SECONDS: f64 = 60.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShipLeg {
+    /// Going from the edge of the map to the dock
+    Arriving,
+    /// Loading or unloading at the dock
+    Docked,
+    /// Going back to the edge of the map, where it disappears
+    Leaving,
+}
+
+/// Goods waiting for their ship to be sent to a harbor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Voyage {
+    harbor: BuildingID,
+    import: bool,
+    cargo: Vec<Cargo>,
+}
+
+/// A cargo ship carrying goods between a harbor and the open sea
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ship {
+    pub harbor: BuildingID,
+    /// Index of the dock of the harbor the ship goes to
+    pub dock: usize,
+    /// Waypoints of the current leg
+    pub route: Vec<Vec2>,
+    /// Index of the waypoint the ship is heading to
+    pub next: usize,
+    pub pos: Vec2,
+    /// Where the bow points, lags behind the heading when turning
+    pub dir: Vec2,
+    /// Meters per second
+    pub speed: f32,
+    pub leg: ShipLeg,
+    /// Timestamp at which a docked ship leaves
+    pub until: f64,
+    /// Imported goods are given to the buyers when the ship docks
+    pub import: bool,
+    pub cargo: Vec<Cargo>,
+}
+
+impl Ship {
+    /// Moves the ship toward its next waypoint, returns whether it reached the end of its route
+    fn advance(&mut self) -> bool {
+        let Some(&target) = self.route.get(self.next) else {
+            return true;
+        };
+        let last = self.next + 1 == self.route.len();
+        let dist = self.pos.distance(target);
+
+        // only the dock needs to be reached slowly, the edge of the map is passed at full speed
+        let wanted = if self.leg == ShipLeg::Arriving {
+            let remaining = dist
+                + self.route[self.next..]
+                    .windows(2)
+                    .map(|w| w[0].distance(w[1]))
+                    .sum::<f32>();
+            (2.0 * SHIP_DECELERATION * remaining)
+                .sqrt()
+                .clamp(SHIP_MOORING_SPEED, SHIP_MAX_SPEED)
+        } else {
+            SHIP_MAX_SPEED
+        };
+        self.speed +=
+            (wanted - self.speed).clamp(-SHIP_DECELERATION * DELTA, SHIP_ACCELERATION * DELTA);
+
+        let step = self.speed * DELTA;
+        if let Some(heading) = (target - self.pos).try_normalize() {
+            let turn = SHIP_TURN_RATE * DELTA;
+            self.dir = (self.dir + (heading - self.dir) * turn)
+                .try_normalize()
+                .unwrap_or(heading);
+            if dist > step {
+                self.pos += heading * step;
+            }
+        }
+
+        if dist <= step {
+            self.pos = target;
+        } else if last || dist > WAYPOINT_REACH {
+            return false;
+        }
+
+        self.next += 1;
+        if self.next < self.route.len() {
+            return false;
+        }
+        self.speed = 0.0;
+        true
+    }
+}
+
+/// Where the dock of a harbor is on the map
+pub fn dock_pos(b: &Building, dock: Vec2) -> Vec2 {
+    let axis = (b.obb.corners[1] - b.obb.corners[0]).normalize();
+    b.obb.center() + dock.rotated_by(axis)
+}
+
+/// External trades going through the harbors of the map.
+/// The goods are carried by cargo ships that find their way on water between the docks and the
+/// edge of the map, where the sea connection is.
+#[derive(Default, Serialize, Deserialize)]
+pub struct SeaTrade {
+    /// Built when a route is needed, dropped when the terrain changes
+    #[serde(skip)]
+    grid: Option<WaterGrid>,
+    /// The way from each dock to the sea, None if there is none.
+    /// Forgotten when the terrain or the buildings change.
+    #[serde(skip)]
+    routes: BTreeMap<(BuildingID, usize), Option<Vec<Vec2>>>,
+    #[serde(skip)]
+    terrain_sub: Option<MapSubscriber>,
+    #[serde(skip)]
+    building_sub: Option<MapSubscriber>,
+    /// Voyages waiting for their ship to be sent
+    pending: Vec<Voyage>,
+    pub ships: Vec<Ship>,
+}
+
+impl SeaTrade {
+    /// Forgets the routes when the terrain or the buildings changed, the water grid is only built
+    /// again when the terrain changed
+    pub fn update(&mut self, map: &Map) {
+        let (Some(terrain), Some(buildings)) = (&mut self.terrain_sub, &mut self.building_sub)
+        else {
+            self.terrain_sub = Some(map.subscribe(UpdateType::Terrain));
+            self.building_sub = Some(map.subscribe(UpdateType::Building));
+            self.grid = None;
+            self.routes.clear();
+            return;
+        };
+
+        if terrain.take_cleared() | (terrain.take_updated_chunks().count() > 0) {
+            self.grid = None;
+            self.routes.clear();
+        }
+        if buildings.take_cleared() | (buildings.take_updated_chunks().count() > 0) {
+            self.routes.clear();
+        }
+    }
+
+    /// The way from the dock of the harbor to the sea, from the dock to the edge of the map
+    fn route(&mut self, map: &Map, harbor: BuildingID, dock: usize) -> Option<&Vec<Vec2>> {
+        let Self { grid, routes, .. } = self;
+        routes
+            .entry((harbor, dock))
+            .or_insert_with(|| {
+                let b = map.buildings().get(harbor)?;
+                let BuildingKind::Harbor(proto) = b.kind else {
+                    return None;
+                };
+                let pos = dock_pos(b, *proto.prototype().docks.get(dock)?);
+                let grid = grid.get_or_insert_with(|| WaterGrid::new(&map.environment));
+                grid.route_to_sea(&map.environment, pos)
+            })
+            .as_ref()
+    }
+
+    /// Length of the shortest way from a dock of the harbor to the sea, None if ships can't reach it
+    pub fn route_length(&mut self, map: &Map, harbor: BuildingID) -> Option<f32> {
+        let n_docks = harbor_proto(map, harbor)?.prototype().docks.len();
+        (0..n_docks)
+            .filter_map(|dock| {
+                let route = self.route(map, harbor, dock)?;
+                Some(route.windows(2).map(|w| w[0].distance(w[1])).sum::<f32>())
+            })
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Whether ships found a way from the sea to a dock of the harbor,
+    /// None if no trade looked for one since the terrain or the buildings last changed
+    pub fn reaches_sea(&self, harbor: BuildingID) -> Option<bool> {
+        let mut known = self
+            .routes
+            .range((harbor, 0)..=(harbor, usize::MAX))
+            .peekable();
+        known.peek()?;
+        Some(known.any(|(_, route)| route.is_some()))
+    }
+
+    /// Queues goods to be carried through the harbor.
+    /// The cargo of the trades made during the same tick for the same harbor share a ship.
+    pub fn ship(&mut self, harbor: BuildingID, import: bool, cargo: Cargo) {
+        if let Some(v) = self
+            .pending
+            .iter_mut()
+            .find(|v| v.harbor == harbor && v.import == import)
+        {
+            v.cargo.push(cargo);
+            return;
+        }
+        self.pending.push(Voyage {
+            harbor,
+            import,
+            cargo: vec![cargo],
+        });
+    }
+
+    pub fn n_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+fn harbor_proto(map: &Map, harbor: BuildingID) -> Option<HarborPrototypeID> {
+    match map.buildings().get(harbor)?.kind {
+        BuildingKind::Harbor(proto) => Some(proto),
+        _ => None,
+    }
+}
+
+/// Sends the ships of the new voyages and moves them between the sea and the harbors
+pub fn sea_trade_system(sim: &mut Simulation) {
+    profiling::scope!("economy::sea_trade_system");
+    let now = sim.read::<GameTime>().timestamp;
+    let map = sim.resources.read::<Map>();
+    let mut sea = sim.resources.write::<SeaTrade>();
+    let mut market = sim.resources.write::<Market>();
+
+    let pending = std::mem::take(&mut sea.pending);
+    for voyage in pending {
+        // the least busy dock of the harbor that ships can reach
+        let n_docks = harbor_proto(&map, voyage.harbor).map_or(0, |p| p.prototype().docks.len());
+        let reachable: Vec<usize> = (0..n_docks)
+            .filter(|&dock| sea.route(&map, voyage.harbor, dock).is_some())
+            .collect();
+        let dock = reachable.into_iter().min_by_key(|&dock| {
+            sea.ships
+                .iter()
+                .filter(|s| s.harbor == voyage.harbor && s.dock == dock)
+                .count()
+        });
+        let route = dock.and_then(|dock| sea.route(&map, voyage.harbor, dock).cloned());
+
+        let (Some(dock), Some(mut route)) = (dock, route) else {
+            // the harbor disappeared or the water dried up, the goods are there already
+            if voyage.import {
+                receive(&mut market, &voyage.cargo);
+            }
+            continue;
+        };
+
+        // ships come from the sea at cruising speed
+        route.reverse();
+        let dir = (route[1] - route[0]).try_normalize().unwrap_or(Vec2::X);
+        sea.ships.push(Ship {
+            harbor: voyage.harbor,
+            dock,
+            pos: route[0],
+            route,
+            next: 1,
+            dir,
+            speed: SHIP_MAX_SPEED,
+            leg: ShipLeg::Arriving,
+            until: 0.0,
+            import: voyage.import,
+            cargo: voyage.cargo,
+        });
+    }
+
+    sea.ships.retain_mut(|ship| {
+        if ship.leg == ShipLeg::Arriving && !map.buildings().contains_key(ship.harbor) {
+            // the harbor disappeared, turn back from where the ship is
+            if ship.import {
+                receive(&mut market, &ship.cargo);
+            }
+            ship.route.truncate(ship.next);
+            ship.route.reverse();
+            ship.next = 0;
+            ship.leg = ShipLeg::Leaving;
+        }
+
+        if ship.leg == ShipLeg::Docked {
+            if now < ship.until {
+                return true;
+            }
+            ship.route.reverse();
+            ship.next = 1;
+            ship.leg = ShipLeg::Leaving;
+        }
+
+        if !ship.advance() {
+            return true;
+        }
+        match ship.leg {
+            ShipLeg::Arriving => {
+                if ship.import {
+                    receive(&mut market, &ship.cargo);
+                }
+                ship.leg = ShipLeg::Docked;
+                ship.until = now + SHIP_DOCK_SECONDS;
+                true
+            }
+            ShipLeg::Docked => true,
+            ShipLeg::Leaving => false,
+        }
+    });
+}
+
+fn receive(market: &mut Market, cargo: &[Cargo]) {
+    for c in cargo {
+        market.receive(c.soul, c.kind, c.qty);
+    }
+}
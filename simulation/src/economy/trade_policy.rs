@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{ItemID, Money};
+
+/// Tariffs applied to external trade, as a fraction of the traded value
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tariffs {
+    /// Paid on top of the price when buying from the external market
+    pub import: f64,
+    /// Taken from the price when selling to the external market
+    pub export: f64,
+}
+
+/// Trade policy set by the government, applied to all trades with the external market.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TradePolicy {
+    pub global: Tariffs,
+    /// Overrides the global tariffs for some items
+    pub per_item: BTreeMap<ItemID, Tariffs>,
+}
+
+impl TradePolicy {
+    pub fn tariffs(&self, item: ItemID) -> Tariffs {
+        self.per_item.get(&item).copied().unwrap_or(self.global)
+    }
+
+    /// Returns the money paid for an import of the given value and the part of it that is tariff
+    pub fn import_cost(&self, item: ItemID, value: Money) -> (Money, Money) {
+        let tariff = value * self.tariffs(item).import;
+        (value + tariff, tariff)
+    }
+
+    /// Returns the money earned from an export of the given value and the part that was taxed
+    pub fn export_earnings(&self, item: ItemID, value: Money) -> (Money, Money) {
+        let tax = value * self.tariffs(item).export;
+        (value - tax, tax)
+    }
+}
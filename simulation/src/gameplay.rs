@@ -0,0 +1,112 @@
+//! Gameplay parameters chosen when starting a new game, through a difficulty preset or custom values.
+//! They are kept in the save and can only be changed afterwards in sandbox mode.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::Money;
+
+use crate::map::ElectricityNetworkID;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// Values set by the player
+    Custom,
+}
+
+impl Difficulty {
+    pub const PRESETS: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Custom => "Custom",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameplayParams {
+    pub difficulty: Difficulty,
+    /// Multiplier applied to the workers' part of the price, so that companies make a profit
+    pub price_multiplier: f32,
+    /// Money of the government at the start
+    pub starting_money: Money,
+    /// Multiplier applied to the cost of everything the player builds, and to the refunds
+    pub building_cost_multiplier: f32,
+    /// Share of the price added to imports and removed from exports by the external market
+    pub external_trade_margin: f32,
+    /// Multiplier applied to the daily hazard of disasters such as fires
+    pub disaster_frequency: f32,
+    /// Whether the parameters were changed after the start, the save is then marked as sandbox
+    pub sandbox: bool,
+}
+
+impl GameplayParams {
+    pub fn preset(difficulty: Difficulty) -> Self {
+        let normal = Self {
+            difficulty,
+            price_multiplier: 1.25,
+            starting_money: Money::new_bucks(150_000),
+            building_cost_multiplier: 1.0,
+            external_trade_margin: 0.0,
+            disaster_frequency: 1.0,
+            sandbox: false,
+        };
+        match difficulty {
+            Difficulty::Normal | Difficulty::Custom => normal,
+            Difficulty::Easy => Self {
+                price_multiplier: 1.4,
+                starting_money: Money::new_bucks(300_000),
+                building_cost_multiplier: 0.75,
+                disaster_frequency: 0.5,
+                ..normal
+            },
+            Difficulty::Hard => Self {
+                price_multiplier: 1.1,
+                starting_money: Money::new_bucks(75_000),
+                building_cost_multiplier: 1.5,
+                external_trade_margin: 0.1,
+                disaster_frequency: 2.0,
+                ..normal
+            },
+        }
+    }
+}
+
+impl GameplayParams {
+    /// Whether the multipliers are finite and not negative, and the starting money not negative.
+    /// The trade margin is below 1 so that exports still bring money.
+    pub fn is_valid(&self) -> bool {
+        let multipliers = [
+            self.price_multiplier,
+            self.building_cost_multiplier,
+            self.external_trade_margin,
+            self.disaster_frequency,
+        ];
+        multipliers.iter().all(|x| x.is_finite() && *x >= 0.0)
+            && self.external_trade_margin < 1.0
+            && self.starting_money >= Money::ZERO
+    }
+}
+
+impl Default for GameplayParams {
+    fn default() -> Self {
+        Self::preset(Difficulty::Normal)
+    }
+}
+
+/// What the developer console changed in the game, the save is marked once a cheat was used
+#[derive(Default, Serialize, Deserialize)]
+pub struct Cheats {
+    pub used: bool,
+    /// Networks kept in a blackout whatever their production
+    pub blackouts: BTreeSet<ElectricityNetworkID>,
+}
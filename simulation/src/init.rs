@@ -1,33 +1,70 @@
-use crate::economy::{market_update, EcoStats, Government, Market};
-use crate::map::Map;
+use crate::chronicle::{chronicle_system, Chronicle};
+use crate::economy::{
+    border_trade_system, budget_system, electricity_billing_system, job_market_update,
+    market_update, rent_system, sea_trade_system, BorderTrade, EcoStats, EconomyHistory,
+    ElectricityBilling, FreightThroughput, Government, JobMarket, Market, RentCollection, SeaTrade,
+    TradePolicy,
+};
+use crate::gameplay::{Cheats, GameplayParams};
+use crate::map::{Map, MapEditHistory};
 use crate::map_dynamic::{
-    dispatch_system, electricity_flow_system, itinerary_update, routing_changed_system,
-    routing_update_system, BuildingInfos, Dispatcher, ElectricityFlow, ParkingManagement,
+    dispatch_system, electricity_flow_system, fire_system, garbage_system, itinerary_update,
+    land_value_system, routing_changed_system, routing_queue_system, routing_update_system,
+    water_flow_system, zone_growth_system, BuildingInfos, Dispatcher, ElectricityFlow, Fires,
+    Garbage, LandValue, ParkingManagement, RoutingQueue, WaterFlow, ZoneDemand,
 };
+use crate::milestones::{milestones_system, Milestones};
 use crate::multiplayer::MultiplayerState;
+use crate::notifications::{notifications_system, NotificationWatch, SimNotifications};
+use crate::objectives::{scenario_runner_system, ScenarioRunner};
+use crate::souls::activity::ActivityLog;
+use crate::souls::delivery::{delivery_system, Deliveries};
+use crate::souls::demographics::demographics_system;
+use crate::souls::desire::LeisureVisitors;
+use crate::souls::education::{education_system, Schools};
 use crate::souls::freight_station::freight_station_system;
 use crate::souls::goods_company::company_system;
+use crate::souls::happiness::{happiness_system, CityStats};
 use crate::souls::human::update_decision_system;
+use crate::souls::tourism::{tourism_system, Tourism};
+use crate::souls::warehouse::warehouse_system;
+use crate::statistics::{statistics_system, Statistics};
+use crate::transportation::air_traffic::{air_traffic_system, AirTraffic};
+use crate::transportation::passenger_rail::{passenger_rail_system, PassengerRail};
 use crate::transportation::pedestrian_decision_system;
-use crate::transportation::road::{vehicle_decision_system, vehicle_state_update_system};
+use crate::transportation::road::{
+    lane_speeds_system, vehicle_decision_system, vehicle_state_update_system,
+};
 use crate::transportation::testing_vehicles::{random_vehicles_update, RandomVehicles};
 use crate::transportation::train::{
     locomotive_system, train_reservations_update, TrainReservations,
 };
+use crate::transportation::transit::{transit_system, Transit};
 use crate::transportation::{transport_grid_synchronize, TransportGrid};
 use crate::utils::resources::Resources;
-use crate::world::{CompanyEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::weather::{weather_system, Weather};
+use crate::world::{
+    CompanyEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt, WarehouseEnt,
+};
+use crate::world_command::FailedCommands;
 use crate::World;
 use crate::{
     add_souls_to_empty_buildings, utils, ParCommandBuffer, RandProvider, Replay, RunnableSystem,
     Simulation, SimulationOptions, RNG_SEED,
 };
 use common::saveload::{Bincode, Encoder, JSON};
-use prototypes::{GameTime, Tick};
+use prototypes::{GameTime, PrototypeLoadError, Tick};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 pub fn init() {
+    if let Err(e) = try_init() {
+        panic!("Error loading prototypes:\n{}", e.problems().join("\n"))
+    }
+}
+
+/// Same as [`init`] but the problems of the prototypes are returned, to show them all to the player
+pub fn try_init() -> Result<(), PrototypeLoadError> {
     // # Safety
     // This function is called only once, before any other function in this crate.
     unsafe {
@@ -36,33 +73,56 @@ pub fn init() {
         #[cfg(test)]
         let base = "../";
 
-        match prototypes::load_prototypes(base) {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("Error loading prototypes: {}", e)
-            }
-        }
+        prototypes::load_prototypes(base)?;
     }
 
     register_system("electricity_flow_system", electricity_flow_system);
+    register_system("water_flow_system", water_flow_system);
+    register_system("garbage_system", garbage_system);
+    register_system("land_value_system", land_value_system);
+    register_system("fire_system", fire_system);
+    register_system("weather_system", weather_system);
     register_system("dispatch_system", dispatch_system);
     register_system("update_decision_system", update_decision_system);
     register_system("company_system", company_system);
+    register_system("happiness_system", happiness_system);
+    register_system("education_system", education_system);
+    register_system("warehouse_system", warehouse_system);
     register_system("pedestrian_decision_system", pedestrian_decision_system);
     register_system("transport_grid_synchronize", transport_grid_synchronize);
     register_system("locomotive_system", locomotive_system);
     register_system("vehicle_decision_system", vehicle_decision_system);
     register_system("vehicle_state_update_system", vehicle_state_update_system);
+    register_system("lane_speeds_system", lane_speeds_system);
     register_system("routing_changed_system", routing_changed_system);
     register_system("routing_update_system", routing_update_system);
+    register_system("routing_queue", routing_queue_system);
     register_system("itinerary_update", itinerary_update);
     register_system("market_update", market_update);
+    register_system("job_market_update", job_market_update);
+    register_system("electricity_billing", electricity_billing_system);
+    register_system("rent", rent_system);
+    register_system("budget", budget_system);
+    register_system("statistics", statistics_system);
+    register_system("notifications", notifications_system);
+    register_system("milestones", milestones_system);
+    register_system("chronicle", chronicle_system);
+    register_system("scenario_runner", scenario_runner_system);
     register_system("train_reservations_update", train_reservations_update);
     register_system("freight_station", freight_station_system);
+    register_system("delivery", delivery_system);
     register_system("random_vehicles", random_vehicles_update);
     register_system("update_map", |_, res| res.write::<Map>().update());
 
     register_system_sim("add_souls_to_empty_buildings", add_souls_to_empty_buildings);
+    register_system_sim("zone_growth", zone_growth_system);
+    register_system_sim("demographics", demographics_system);
+    register_system_sim("tourism", tourism_system);
+    register_system_sim("transit", transit_system);
+    register_system_sim("passenger_rail", passenger_rail_system);
+    register_system_sim("border_trade", border_trade_system);
+    register_system_sim("sea_trade", sea_trade_system);
+    register_system_sim("air_traffic", air_traffic_system);
 
     register_resource_noserialize::<ParCommandBuffer<VehicleEnt>>();
     register_resource_noserialize::<ParCommandBuffer<TrainEnt>>();
@@ -70,23 +130,60 @@ pub fn init() {
     register_resource_noserialize::<ParCommandBuffer<WagonEnt>>();
     register_resource_noserialize::<ParCommandBuffer<FreightStationEnt>>();
     register_resource_noserialize::<ParCommandBuffer<CompanyEnt>>();
+    register_resource_noserialize::<ParCommandBuffer<WarehouseEnt>>();
+    register_resource_noserialize::<MapEditHistory>();
+    register_resource_noserialize::<FailedCommands>();
+    register_resource_noserialize::<ZoneDemand>();
+    register_resource_noserialize::<ActivityLog>();
+    register_resource_noserialize::<LeisureVisitors>();
+    register_resource_noserialize::<SimNotifications>();
+    register_resource_noserialize::<NotificationWatch>();
     register_resource_noinit::<SimulationOptions, Bincode>("simoptions");
 
     register_resource_default::<ElectricityFlow, Bincode>("electricity_flow");
+    register_resource_default::<WaterFlow, Bincode>("water_flow");
+    register_resource_default::<Garbage, Bincode>("garbage");
+    register_resource_default::<Fires, Bincode>("fires");
+    register_resource_default::<CityStats, Bincode>("city_stats");
+    register_resource_default::<Schools, Bincode>("schools");
+    register_resource_default::<Tourism, Bincode>("tourism");
     register_resource_default::<Market, Bincode>("market");
     register_resource_default::<EcoStats, Bincode>("ecostats");
+    register_resource_default::<EconomyHistory, Bincode>("economy_history");
+    register_resource_default::<Statistics, Bincode>("statistics");
     register_resource_default::<MultiplayerState, Bincode>("multiplayer_state");
     register_resource_default::<RandomVehicles, Bincode>("random_vehicles");
     register_resource_default::<Map, Bincode>("map");
     register_resource_default::<TrainReservations, Bincode>("train_reservations");
     register_resource_default::<Government, Bincode>("government");
+    register_resource_default::<TradePolicy, Bincode>("trade_policy");
+    register_resource_default::<GameplayParams, Bincode>("gameplay_params");
+    register_resource_default::<Cheats, Bincode>("cheats");
+    register_resource_default::<FreightThroughput, Bincode>("freight_throughput");
+    register_resource_default::<BorderTrade, Bincode>("border_trade");
+    register_resource_default::<SeaTrade, Bincode>("sea_trade");
+    register_resource_default::<AirTraffic, Bincode>("air_traffic");
+    register_resource_default::<Deliveries, Bincode>("deliveries");
+    register_resource_default::<JobMarket, Bincode>("job_market");
+    register_resource_default::<ElectricityBilling, Bincode>("electricity_billing");
+    register_resource_default::<RentCollection, Bincode>("rent_collection");
+    register_resource_default::<LandValue, Bincode>("land_value");
     register_resource_default::<ParkingManagement, Bincode>("pmanagement");
+    register_resource_default::<Transit, Bincode>("transit");
+    register_resource_default::<PassengerRail, Bincode>("passenger_rail");
     register_resource_default::<BuildingInfos, Bincode>("binfos");
+    register_resource_default::<Weather, Bincode>("weather");
+    register_resource_default::<Milestones, Bincode>("milestones");
+    register_resource_default::<Chronicle, Bincode>("chronicle");
+    register_resource_default::<ScenarioRunner, Bincode>("scenario_runner");
     register_resource::<GameTime, Bincode>("game_time", || GameTime::new(Tick(1)));
     register_resource::<TransportGrid, Bincode>("transport_grid", || TransportGrid::new(100));
     register_resource::<RandProvider, Bincode>("randprovider", || RandProvider::new(RNG_SEED));
     register_resource_default::<Dispatcher, Bincode>("dispatcher");
+    register_resource_default::<RoutingQueue, Bincode>("routing_queue");
     register_resource_default::<Replay, JSON>("replay");
+
+    Ok(())
 }
 
 pub struct InitFunc {
@@ -1,15 +1,20 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
 
+use crate::gameplay::GameplayParams;
 use crate::init::{GSYSTEMS, INIT_FUNCS, SAVELOAD_FUNCS};
-use crate::map::{BuildingKind, Map};
+use crate::map::procgen::MapGenParams;
+use crate::map::{BuildingID, BuildingKind, Environment, IntersectionID, Map};
 use crate::map_dynamic::{Itinerary, ItineraryLeader};
+use crate::migrations::SAVE_VERSION;
 use crate::souls::add_souls_to_empty_buildings;
 use crate::utils::resources::{Ref, RefMut, Resources};
 use crate::utils::scheduler::RunnableSystem;
-use crate::world_command::WorldCommand;
 use crate::world_command::WorldCommand::Init;
-use common::saveload::Encoder;
+use crate::world_command::{build_starting_infrastructure, WorldCommand};
+use common::saveload::{
+    AutoSaveSlots, CheckedCompressedBincode, CompressedBincode, Encoder, SaveHeader,
+};
 use common::FastMap;
 use derive_more::{From, TryInto};
 use geom::Vec3;
@@ -19,6 +24,7 @@ use std::any::Any;
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
+use std::io::Write;
 use std::ptr::addr_of;
 use std::time::{Duration, Instant};
 use utils::rand_provider::RandProvider;
@@ -37,21 +43,36 @@ extern crate egui_inspect;
 #[macro_use]
 extern crate log as extern_log;
 
+pub mod chronicle;
 pub mod economy;
+pub mod gameplay;
 pub mod init;
 pub mod map;
 pub mod map_dynamic;
+pub mod map_files;
+pub mod migrations;
+pub mod milestones;
 pub mod multiplayer;
+pub mod notifications;
+pub mod objectives;
+pub mod overview;
+pub mod reload;
+pub mod saves;
+pub mod scenario;
 pub mod souls;
+pub mod statistics;
 #[cfg(test)]
 mod tests;
 pub mod transportation;
 pub mod utils;
+pub mod weather;
 mod world;
 pub mod world_command;
 
 pub use world::*;
 
+pub use saves::SaveMetadata;
+
 pub use utils::par_command_buffer::ParCommandBuffer;
 pub use utils::replay::*;
 
@@ -66,6 +87,26 @@ pub enum SoulID {
     Human(HumanID),
     GoodsCompany(CompanyID),
     FreightStation(FreightStationID),
+    Warehouse(WarehouseID),
+    /// The outside of the city, reached by the trucks going through this road connection
+    RoadConnection(IntersectionID),
+    /// The outside of the city, reached by the cargo ships docking at this harbor
+    SeaConnection(BuildingID),
+}
+
+impl SoulID {
+    /// Whether this is the outside side of an external trade
+    pub fn is_external(self) -> bool {
+        matches!(
+            self,
+            SoulID::FreightStation(_) | SoulID::RoadConnection(_) | SoulID::SeaConnection(_)
+        )
+    }
+
+    /// Whether the soul pays for what it buys, the government pays for the others
+    pub fn has_wallet(self) -> bool {
+        matches!(self, SoulID::Human(_) | SoulID::GoodsCompany(_))
+    }
 }
 
 impl Display for SoulID {
@@ -74,16 +115,24 @@ impl Display for SoulID {
             SoulID::Human(id) => write!(f, "{:?}", id),
             SoulID::GoodsCompany(id) => write!(f, "{:?}", id),
             SoulID::FreightStation(id) => write!(f, "{:?}", id),
+            SoulID::Warehouse(id) => write!(f, "{:?}", id),
+            SoulID::RoadConnection(id) => write!(f, "{:?}", id),
+            SoulID::SeaConnection(id) => write!(f, "{:?}", id),
         }
     }
 }
 
-impl From<SoulID> for AnyEntity {
-    fn from(value: SoulID) -> Self {
+impl TryFrom<SoulID> for AnyEntity {
+    type Error = ();
+
+    /// Road and sea connections are not entities
+    fn try_from(value: SoulID) -> Result<Self, Self::Error> {
         match value {
-            SoulID::Human(id) => AnyEntity::HumanID(id),
-            SoulID::GoodsCompany(id) => AnyEntity::CompanyID(id),
-            SoulID::FreightStation(id) => AnyEntity::FreightStationID(id),
+            SoulID::Human(id) => Ok(AnyEntity::HumanID(id)),
+            SoulID::GoodsCompany(id) => Ok(AnyEntity::CompanyID(id)),
+            SoulID::FreightStation(id) => Ok(AnyEntity::FreightStationID(id)),
+            SoulID::Warehouse(id) => Ok(AnyEntity::WarehouseID(id)),
+            SoulID::RoadConnection(_) | SoulID::SeaConnection(_) => Err(()),
         }
     }
 }
@@ -96,6 +145,7 @@ impl TryFrom<AnyEntity> for SoulID {
             AnyEntity::HumanID(id) => Ok(SoulID::Human(id)),
             AnyEntity::CompanyID(id) => Ok(SoulID::GoodsCompany(id)),
             AnyEntity::FreightStationID(id) => Ok(SoulID::FreightStation(id)),
+            AnyEntity::WarehouseID(id) => Ok(SoulID::Warehouse(id)),
             _ => Err(()),
         }
     }
@@ -115,6 +165,19 @@ const VERSION: &str = include_str!("../../VERSION");
 pub struct SimulationOptions {
     pub terrain_size: u16,
     pub save_replay: bool,
+    #[serde(default)]
+    pub params: GameplayParams,
+    /// How the terrain is generated, a new game with the same options starts on the same map
+    #[serde(default)]
+    pub mapgen: MapGenParams,
+    /// Whether the vehicle routes are guided by the landmarks of the map.
+    /// Only turned off to debug them, every player must route the same way.
+    #[serde(default = "landmark_routing")]
+    pub landmark_routing: bool,
+}
+
+fn landmark_routing() -> bool {
+    true
 }
 
 impl Default for SimulationOptions {
@@ -122,6 +185,9 @@ impl Default for SimulationOptions {
         SimulationOptions {
             terrain_size: 50,
             save_replay: true,
+            params: GameplayParams::default(),
+            mapgen: MapGenParams::default(),
+            landmark_routing: true,
         }
     }
 }
@@ -172,6 +238,37 @@ impl Simulation {
     }
 
     pub fn new_with_options(opts: SimulationOptions) -> Simulation {
+        let mut sim = Self::initialized(opts);
+        sim.apply_start_commands();
+        sim
+    }
+
+    /// A new game on a terrain made in the map editor, the railway and the external trading are
+    /// built on it. It isn't replayed since the replay couldn't make the terrain again.
+    pub fn new_on_map(opts: SimulationOptions, terrain: Environment) -> Simulation {
+        let mut sim = Self::initialized(SimulationOptions {
+            terrain_size: 0,
+            save_replay: false,
+            ..opts
+        });
+        sim.map_mut().environment = terrain;
+        build_starting_infrastructure(&mut sim);
+        sim.apply_start_commands();
+        sim
+    }
+
+    /// A terrain to edit in the map editor, nothing is built on it
+    pub fn new_editor(terrain: Environment) -> Simulation {
+        let mut sim = Self::initialized(SimulationOptions {
+            terrain_size: 0,
+            save_replay: false,
+            ..Default::default()
+        });
+        sim.map_mut().environment = terrain;
+        sim
+    }
+
+    fn initialized(opts: SimulationOptions) -> Simulation {
         let mut sim = Simulation {
             world: Default::default(),
             resources: Default::default(),
@@ -187,15 +284,16 @@ impl Simulation {
         }
 
         Init(Box::new(opts)).apply(&mut sim);
+        sim
+    }
 
+    fn apply_start_commands(&mut self) {
         let start_commands: Vec<(u32, WorldCommand)> =
             common::saveload::JSON::decode(START_COMMANDS.as_bytes()).unwrap();
 
         for (_, command) in start_commands {
-            command.apply(&mut sim);
+            command.apply(self);
         }
-
-        sim
     }
 
     pub fn world_res(&mut self) -> (&mut World, &mut Resources) {
@@ -287,16 +385,109 @@ impl Simulation {
     }
 
     pub fn load_from_disk(save_name: &str) -> Option<Self> {
-        let sim: Simulation = common::saveload::CompressedBincode::load(save_name).ok()?;
-        Some(sim)
+        Self::try_load_from_disk(save_name)
+            .map_err(|e| log::error!("{}", e))
+            .ok()
+    }
+
+    /// Fails if the save is missing, truncated, corrupted or from a newer version
+    pub fn try_load_from_disk(save_name: &str) -> std::io::Result<Self> {
+        Self::load_migrated(save_name).map(|(sim, _)| sim)
+    }
+
+    /// Loads the save, upgrading it if it is from an older save version.
+    /// Also returns the names of the migrations that were applied.
+    pub fn load_migrated(save_name: &str) -> std::io::Result<(Self, Vec<&'static str>)> {
+        let data = std::fs::read(CheckedCompressedBincode::filename(save_name))?;
+        let (version, payload) = SaveHeader::check(&data)?;
+        migrations::check_version(version)?;
+
+        let mut simdeser: SimulationDeser =
+            migrations::decode_with_version(version, || CompressedBincode::decode(payload))
+                .map_err(|err| {
+                    std::io::Error::new(
+                        err.kind(),
+                        format!("failed deserializing {}: {}", save_name, err),
+                    )
+                })?;
+        let applied = migrations::migrate(version, &mut simdeser.res)?;
+        if !applied.is_empty() {
+            log::info!(
+                "upgraded {} from save version {} with migrations {:?}",
+                save_name,
+                version,
+                applied
+            );
+        }
+
+        let mut sim = Self::from_deser(simdeser);
+        migrations::migrate_world(version, &mut sim);
+
+        let removed = reload::remove_orphans(&mut sim);
+        if !removed.is_empty() {
+            log::warn!(
+                "{} uses prototypes that are not defined anymore, removed: {}",
+                save_name,
+                removed.join("; ")
+            );
+        }
+        log::info!("successfully loaded {}", save_name);
+        Ok((sim, applied))
+    }
+
+    pub fn load_meta_from_disk(save_name: &str) -> Option<SaveMetadata> {
+        common::saveload::JSON::load(&saves::meta_name(save_name)).ok()
+    }
+
+    /// Loads the newest save that isn't corrupted among the named save and the autosaves,
+    /// so that a crash doesn't bring back an older manual save.
+    /// Also returns the errors of the newer saves that were skipped.
+    pub fn load_latest_save(
+        save_name: &str,
+        slots: &AutoSaveSlots,
+    ) -> (Option<(String, Self)>, Vec<String>) {
+        let mut candidates = slots.list::<CheckedCompressedBincode>();
+        if let Ok(modified) = std::fs::metadata(CheckedCompressedBincode::filename(save_name))
+            .and_then(|m| m.modified())
+        {
+            candidates.push((save_name.to_string(), modified));
+            candidates.sort_by(|a, b| b.1.cmp(&a.1));
+        }
+
+        let mut errors = vec![];
+        for (name, _) in candidates {
+            match Self::try_load_from_disk(&name) {
+                Ok(sim) => return (Some((name, sim)), errors),
+                Err(e) => {
+                    log::error!("skipping save {}: {}", name, e);
+                    errors.push(format!("{name}: {e}"));
+                }
+            }
+        }
+        (None, errors)
     }
 
     pub fn save_to_disk(&self, save_name: &str) {
-        common::saveload::CompressedBincode::save(&self, save_name);
+        self.save_with_metadata(save_name, &self.metadata(save_name, "", 0.0));
+    }
+
+    /// Saves the simulation along with the metadata describing it
+    pub fn save_with_metadata(&self, save_name: &str, meta: &SaveMetadata) -> Option<()> {
+        let data = CheckedCompressedBincode::encode_versioned(self, SAVE_VERSION)
+            .map_err(|e| log::error!("failed serializing: {}", e))
+            .ok()?;
+        common::saveload::write_atomic(&CheckedCompressedBincode::filename(save_name), |w| {
+            w.write_all(&data)
+                .map_err(|e| log::error!("failed writing {}: {}", save_name, e))
+        })?;
+        log::info!("successfully saved {}", save_name);
+        common::saveload::JSON::save_silent(meta, &saves::meta_name(save_name));
+
         let rep = self.resources.read::<Replay>();
         if rep.enabled {
             common::saveload::JSONPretty::save(&*rep, &format!("{save_name}_replay"));
         }
+        Some(())
     }
 
     pub fn pos<E: WorldTransform>(&self, id: E) -> Option<Vec3> {
@@ -387,21 +578,8 @@ struct SimulationDeser {
     res: FastMap<String, Vec<u8>>,
 }
 
-impl<'de> Deserialize<'de> for Simulation {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        log::info!("deserializing sim state");
-        let t = Instant::now();
-
-        let mut simdeser = <SimulationDeser as Deserialize>::deserialize(deserializer)?;
-
-        log::info!(
-            "took {}s to deserialize base deser",
-            t.elapsed().as_secs_f32()
-        );
-
+impl Simulation {
+    fn from_deser(mut simdeser: SimulationDeser) -> Self {
         let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
         let deser_parts = simdeser.version.split('.').collect::<Vec<_>>();
 
@@ -436,6 +614,30 @@ impl<'de> Deserialize<'de> for Simulation {
             }
         }
 
+        let landmark_routing = sim.read::<SimulationOptions>().landmark_routing;
+        sim.map_mut().landmark_routing = landmark_routing;
+
+        sim
+    }
+}
+
+impl<'de> Deserialize<'de> for Simulation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        log::info!("deserializing sim state");
+        let t = Instant::now();
+
+        let simdeser = <SimulationDeser as Deserialize>::deserialize(deserializer)?;
+
+        log::info!(
+            "took {}s to deserialize base deser",
+            t.elapsed().as_secs_f32()
+        );
+
+        let sim = Self::from_deser(simdeser);
+
         log::info!(
             "took {}s to deserialize in total",
             t.elapsed().as_secs_f32()
@@ -0,0 +1,153 @@
+use geom::AABB;
+use serde::{Deserialize, Serialize};
+
+use crate::map::{
+    BuildingID, BuildingKind, ElectricityCache, Map, ProjectFilter, ProjectKind, RoadID, UpdateType,
+};
+
+/// Kinds of objects removed by the area bulldozer
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulldozeFilter {
+    pub roads: bool,
+    pub rails: bool,
+    pub buildings: bool,
+    pub trees: bool,
+}
+
+impl Default for BulldozeFilter {
+    fn default() -> Self {
+        Self {
+            roads: true,
+            rails: true,
+            buildings: true,
+            trees: false,
+        }
+    }
+}
+
+/// The objects inside an area that match a filter
+#[derive(Debug, Default, Clone)]
+pub struct BulldozeSelection {
+    pub roads: Vec<RoadID>,
+    pub buildings: Vec<BuildingID>,
+    pub n_trees: usize,
+    /// Buildings that are kept but lose their road access
+    pub orphaned: Vec<BuildingID>,
+}
+
+impl BulldozeSelection {
+    pub fn is_empty(&self) -> bool {
+        self.roads.is_empty() && self.buildings.is_empty() && self.n_trees == 0
+    }
+}
+
+impl Map {
+    /// Roads with both intersections inside the area, and buildings with their center inside
+    pub fn bulldoze_selection(&self, area: AABB, filter: BulldozeFilter) -> BulldozeSelection {
+        let mut sel = BulldozeSelection::default();
+
+        for kind in self
+            .spatial_map
+            .query(area, ProjectFilter::ROAD | ProjectFilter::BUILDING)
+        {
+            match kind {
+                ProjectKind::Road(id) => {
+                    let Some(r) = self.roads.get(id) else {
+                        continue;
+                    };
+                    let inside = [r.src, r.dst].iter().all(|&i| {
+                        self.intersections
+                            .get(i)
+                            .map_or(false, |i| area.contains(i.pos.xy()))
+                    });
+                    let is_rail = r.lanes_iter().all(|(_, kind)| kind.is_rail());
+                    if inside && (if is_rail { filter.rails } else { filter.roads }) {
+                        sel.roads.push(id);
+                    }
+                }
+                ProjectKind::Building(id) => {
+                    let Some(b) = self.buildings.get(id) else {
+                        continue;
+                    };
+                    if filter.buildings
+                        && b.kind != BuildingKind::ExternalTrading
+                        && area.contains(b.obb.center())
+                    {
+                        sel.buildings.push(id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        sel.roads.sort_unstable();
+        sel.roads.dedup();
+        sel.buildings.sort_unstable();
+        sel.buildings.dedup();
+
+        for &r in &sel.roads {
+            for &b in &self.roads[r].connected_buildings {
+                if !sel.buildings.contains(&b) && !sel.orphaned.contains(&b) {
+                    sel.orphaned.push(b);
+                }
+            }
+        }
+
+        if filter.trees {
+            self.environment
+                .trees
+                .query_aabb_visitor(area.ll, area.ur, |(_, pos)| {
+                    if area.contains(pos) {
+                        sel.n_trees += 1;
+                    }
+                });
+        }
+
+        sel
+    }
+
+    /// Removes the selected objects of the area all at once.
+    /// The electricity networks are computed once at the end instead of after each removal.
+    pub fn bulldoze(&mut self, area: AABB, filter: BulldozeFilter) -> BulldozeSelection {
+        info!("bulldoze {:?} {:?}", area, filter);
+
+        let sel = self.bulldoze_selection(area, filter);
+        if sel.is_empty() {
+            return sel;
+        }
+
+        self.electricity = ElectricityCache::default();
+
+        for &b in &sel.buildings {
+            self.remove_building_inner(b);
+        }
+
+        let mut potentially_empty = Vec::new();
+        for &r in &sel.roads {
+            if let Some(road) = self.remove_road_inner(r) {
+                potentially_empty.push(road.src);
+                potentially_empty.push(road.dst);
+            }
+        }
+        for i in potentially_empty {
+            if self
+                .intersections
+                .get(i)
+                .map_or(false, |i| i.roads.is_empty())
+            {
+                self.remove_intersection_inner(i);
+            }
+        }
+
+        if filter.trees {
+            self.environment.remove_trees_near(area, |tree_chunk| {
+                self.subscribers
+                    .dispatch_chunk(UpdateType::Terrain, tree_chunk)
+            });
+        }
+
+        self.electricity = ElectricityCache::build(self);
+
+        self.check_invariants();
+        sel
+    }
+}
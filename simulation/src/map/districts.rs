@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, SlotMap};
+
+use geom::{Polygon, Vec2};
+
+use crate::map::generated_district_name;
+
+new_key_type! {
+    pub struct DistrictID;
+}
+
+/// Districts smaller than this are most likely misclicks
+pub const MIN_DISTRICT_AREA: f32 = 100.0;
+
+/// An area of the map named by the players
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct District {
+    pub id: DistrictID,
+    pub name: String,
+    pub polygon: Polygon,
+    /// Used for the generated name
+    pub number: u32,
+}
+
+impl District {
+    /// Where the name of the district is drawn: the center of its area, or the closest point
+    /// of the polygon to it when the polygon is concave enough not to contain it
+    pub fn label_pos(&self) -> Vec2 {
+        let p = &self.polygon;
+        let mut area = 0.0;
+        let mut center = Vec2::ZERO;
+        for i in 0..p.len() {
+            let a = *p.get(i);
+            let b = *p.get_next(i);
+            let cross = a.x * b.y - b.x * a.y;
+            area += cross;
+            center += (a + b) * cross;
+        }
+        if area.abs() < f32::EPSILON {
+            return p.barycenter();
+        }
+        let center = center / (3.0 * area);
+        if p.contains(center) {
+            return center;
+        }
+        p.project(center)
+    }
+}
+
+/// The named areas of the map
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Districts {
+    districts: SlotMap<DistrictID, District>,
+    /// Number of districts created so far, for the generated names
+    created: u32,
+}
+
+impl Districts {
+    /// Adds a district, an empty name gives it a generated one like "District 3".
+    /// Returns None if the polygon is too small to be a district.
+    pub fn add(&mut self, polygon: Polygon, name: &str) -> Option<DistrictID> {
+        if polygon.len() < 3 || polygon.area() < MIN_DISTRICT_AREA {
+            return None;
+        }
+        self.created += 1;
+        let number = self.created;
+        let id = self.districts.insert_with_key(|id| District {
+            id,
+            name: String::new(),
+            polygon,
+            number,
+        });
+        self.rename(id, name);
+        Some(id)
+    }
+
+    pub fn remove(&mut self, id: DistrictID) -> Option<District> {
+        self.districts.remove(id)
+    }
+
+    /// Names the district, an empty name gives it back its generated name
+    pub fn rename(&mut self, id: DistrictID, name: &str) {
+        let Some(d) = self.districts.get_mut(id) else {
+            return;
+        };
+        let name = name.trim();
+        d.name = if name.is_empty() {
+            generated_district_name(d.number)
+        } else {
+            name.to_string()
+        };
+    }
+
+    pub fn get(&self, id: DistrictID) -> Option<&District> {
+        self.districts.get(id)
+    }
+
+    pub fn contains(&self, id: DistrictID) -> bool {
+        self.districts.contains_key(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &District> {
+        self.districts.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.districts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.districts.is_empty()
+    }
+
+    /// The district containing the position, the smallest one if they overlap
+    pub fn at(&self, pos: Vec2) -> Option<&District> {
+        self.districts
+            .values()
+            .filter(|d| d.polygon.contains(pos))
+            .min_by(|a, b| a.polygon.area().total_cmp(&b.polygon.area()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Polygon, Vec2};
+
+    use super::Districts;
+
+    #[test]
+    fn districts_are_named_and_found() {
+        let mut d = Districts::default();
+        let big = d
+            .add(Polygon::centered_rect(Vec2::ZERO, 200.0, 200.0), "")
+            .unwrap();
+        let small = d
+            .add(
+                Polygon::centered_rect(vec2(50.0, 50.0), 40.0, 40.0),
+                " Old Town ",
+            )
+            .unwrap();
+        assert!(d
+            .add(Polygon::centered_rect(Vec2::ZERO, 5.0, 5.0), "")
+            .is_none());
+
+        assert_eq!(d.get(big).unwrap().name, "District 1");
+        assert_eq!(d.get(small).unwrap().name, "Old Town");
+        assert_eq!(d.at(vec2(50.0, 50.0)).unwrap().id, small);
+        assert_eq!(d.at(vec2(-50.0, -50.0)).unwrap().id, big);
+        assert!(d.at(vec2(500.0, 0.0)).is_none());
+
+        d.rename(small, "");
+        assert_eq!(d.get(small).unwrap().name, "District 2");
+
+        // numbers are not reused
+        d.remove(big);
+        let other = d
+            .add(Polygon::centered_rect(Vec2::ZERO, 20.0, 20.0), "")
+            .unwrap();
+        assert_eq!(d.get(other).unwrap().name, "District 3");
+    }
+
+    #[test]
+    fn label_inside_concave_district() {
+        let mut d = Districts::default();
+        // a U shape, whose center of area is in the gap between its arms
+        let u = Polygon(vec![
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(80.0, 100.0),
+            vec2(80.0, 20.0),
+            vec2(20.0, 20.0),
+            vec2(20.0, 100.0),
+            vec2(0.0, 100.0),
+        ]);
+        let id = d.add(u, "").unwrap();
+        let district = d.get(id).unwrap();
+        let pos = district.label_pos();
+        assert!(district.polygon.distance(pos) < 1e-3, "{:?}", pos);
+
+        let square = d
+            .add(Polygon::centered_rect(vec2(10.0, 10.0), 30.0, 30.0), "")
+            .unwrap();
+        assert!(d
+            .get(square)
+            .unwrap()
+            .label_pos()
+            .is_close(vec2(10.0, 10.0), 1e-3));
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use geom::{HeightmapPatch, Vec2, Vec3, AABB, OBB};
+use prototypes::{BuildingGen, Money};
+
+use crate::economy::BudgetReason;
+use crate::map::{
+    BuildingID, BuildingKind, Environment, Intersection, IntersectionID, LanePattern, LightPolicy,
+    LightTiming, Map, MapProject, ProjectFilter, ProjectKind, RoadID, RoadSegmentKind, Tree,
+    TurnID, TurnPolicy, Zone,
+};
+
+/// Maximum number of edits that can be undone
+pub const MAX_MAP_EDITS: usize = 100;
+
+/// Distance under which two objects are considered to be at the same position
+const SAME_POS_DIST: f32 = 0.5;
+
+/// A road as it can be rebuilt, identified by the positions of its intersections
+#[derive(Debug, Clone)]
+pub struct RoadSnapshot {
+    pub src: Vec3,
+    pub dst: Vec3,
+    pub segment: RoadSegmentKind,
+    pub pattern: LanePattern,
+}
+
+impl RoadSnapshot {
+    pub fn new(map: &Map, id: RoadID) -> Option<Self> {
+        let r = map.roads.get(id)?;
+        Some(Self {
+            src: map.intersections.get(r.src)?.pos,
+            dst: map.intersections.get(r.dst)?.pos,
+            segment: r.segment,
+            pattern: r.pattern(&map.lanes),
+        })
+    }
+}
+
+/// A building as it can be rebuilt. What it contained is not kept.
+#[derive(Debug, Clone)]
+pub struct BuildingSnapshot {
+    pub obb: OBB,
+    pub kind: BuildingKind,
+    pub gen: BuildingGen,
+    pub zone: Option<Zone>,
+}
+
+impl BuildingSnapshot {
+    pub fn new(map: &Map, id: BuildingID) -> Option<Self> {
+        let b = map.buildings.get(id)?;
+        Some(Self {
+            obb: b.obb,
+            kind: b.kind,
+            gen: building_gen(b.kind)?,
+            zone: b.zone.clone(),
+        })
+    }
+}
+
+/// The generator used to build a building of this kind, None if it cannot be rebuilt
+fn building_gen(kind: BuildingKind) -> Option<BuildingGen> {
+    Some(match kind {
+        BuildingKind::House => BuildingGen::House,
+        BuildingKind::GoodsCompany(id) => id.prototype().bgen,
+        BuildingKind::Warehouse(id) => id.prototype().bgen,
+        BuildingKind::School(id) => id.prototype().bgen,
+        BuildingKind::Leisure(id) => id.prototype().bgen,
+        BuildingKind::Hotel(id) => id.prototype().bgen,
+        BuildingKind::Harbor(id) => id.prototype().bgen,
+        BuildingKind::Airport(id) => id.prototype().bgen,
+        BuildingKind::RailFreightStation(_) | BuildingKind::RailPassengerStation(_) => {
+            BuildingGen::NoWalkway {
+                door_pos: Vec2::ZERO,
+            }
+        }
+        BuildingKind::TrainStation | BuildingKind::ExternalTrading => return None,
+    })
+}
+
+/// The settings of an intersection that can be changed from the road editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicySnapshot {
+    pub turn: TurnPolicy,
+    pub light: LightPolicy,
+    pub timing: Option<LightTiming>,
+    pub disabled_turns: BTreeSet<TurnID>,
+}
+
+impl PolicySnapshot {
+    pub fn new(i: &Intersection) -> Self {
+        Self {
+            turn: i.turn_policy,
+            light: i.light_policy,
+            timing: i.light_timing.clone(),
+            disabled_turns: i.disabled_turns.clone(),
+        }
+    }
+
+    fn apply(&self, i: &mut Intersection) {
+        i.turn_policy = self.turn;
+        i.light_policy = self.light;
+        i.light_timing = self.timing.clone();
+        i.disabled_turns = self.disabled_turns.clone();
+    }
+}
+
+/// Elementary change to the map.
+/// Objects are referenced by position instead of id, so that the operation stays valid
+/// when the objects it refers to were destroyed and rebuilt by another undo.
+#[derive(Debug, Clone)]
+pub enum MapEditOp {
+    AddRoad(RoadSnapshot),
+    RemoveRoad {
+        src: Vec3,
+        dst: Vec3,
+    },
+    AddBuilding(BuildingSnapshot),
+    RemoveBuilding {
+        center: Vec2,
+        kind: BuildingKind,
+    },
+    SetPolicy {
+        pos: Vec3,
+        policy: PolicySnapshot,
+    },
+    SetRoadPattern {
+        src: Vec3,
+        dst: Vec3,
+        pattern: LanePattern,
+    },
+    AddTrees(Vec<Tree>),
+    RemoveTrees(Vec<Vec2>),
+    SetHeights(HeightmapPatch),
+}
+
+/// An edit of the map made by the player, with the operations to undo and redo it
+#[derive(Debug, Clone, Default)]
+pub struct MapEdit {
+    redo: Vec<MapEditOp>,
+    undo: Vec<MapEditOp>,
+}
+
+impl MapEdit {
+    pub fn is_empty(&self) -> bool {
+        self.redo.is_empty() && self.undo.is_empty()
+    }
+}
+
+/// Money paid by the government for an edit, given back when it is undone
+#[derive(Debug, Clone, Copy)]
+pub struct MapEditCost {
+    pub amount: Money,
+    pub reason: BudgetReason,
+}
+
+/// What is captured before a command is applied, to compute its edit once it is applied
+pub enum PendingMapEdit {
+    /// Roads might be created, split or removed
+    Roads {
+        before: BTreeMap<RoadID, RoadSnapshot>,
+        /// Positions of the intersections the new roads are connected to
+        positions: Vec<Vec3>,
+    },
+    AddBuilding(BuildingSnapshot),
+    RemoveBuilding(BuildingID, BuildingSnapshot),
+    SetPolicy {
+        pos: Vec3,
+        old: PolicySnapshot,
+        new: PolicySnapshot,
+    },
+    RoadPattern {
+        src: Vec3,
+        dst: Vec3,
+        old: LanePattern,
+        new: LanePattern,
+    },
+    /// Trees planted or erased by a brush stroke
+    Trees {
+        added: Vec<Tree>,
+        removed: Vec<Tree>,
+    },
+    /// Heights of the terrain before a terraforming brush stroke
+    Heights(HeightmapPatch),
+    /// Several edits undone together, in reverse order
+    Multiple(Vec<PendingMapEdit>),
+}
+
+impl PendingMapEdit {
+    /// Roads that might be removed, and positions where new roads might be connected
+    pub fn roads(
+        map: &Map,
+        candidates: impl IntoIterator<Item = RoadID>,
+        positions: Vec<Vec3>,
+    ) -> Self {
+        Self::Roads {
+            before: candidates
+                .into_iter()
+                .filter_map(|id| Some((id, RoadSnapshot::new(map, id)?)))
+                .collect(),
+            positions,
+        }
+    }
+
+    /// Connections between the projects: projected roads might be split
+    /// and new roads are connected to the intersections at the projects positions
+    pub fn connections(map: &Map, projects: &[MapProject]) -> Self {
+        let mut candidates = Vec::new();
+        let mut positions = Vec::with_capacity(projects.len());
+        for p in projects {
+            match p.kind {
+                ProjectKind::Inter(id) => {
+                    let Some(i) = map.intersections.get(id) else {
+                        continue;
+                    };
+                    candidates.extend_from_slice(&i.roads);
+                    positions.push(i.pos);
+                }
+                ProjectKind::Road(id) => {
+                    candidates.push(id);
+                    positions.push(p.pos);
+                }
+                _ => positions.push(p.pos),
+            }
+        }
+        Self::roads(map, candidates, positions)
+    }
+
+    /// Computes the edit now that the command was applied. None if nothing changed.
+    pub fn finish(self, map: &Map) -> Option<MapEdit> {
+        let edit = match self {
+            PendingMapEdit::Roads { before, positions } => {
+                let removed: Vec<RoadSnapshot> = before
+                    .iter()
+                    .filter(|(id, _)| !map.roads.contains_key(**id))
+                    .map(|(_, r)| r.clone())
+                    .collect();
+
+                let mut added: Vec<RoadID> = positions
+                    .iter()
+                    .filter_map(|&pos| find_intersection(map, pos))
+                    .flat_map(|i| map.intersections[i].roads.iter().copied())
+                    .filter(|id| !before.contains_key(id))
+                    .collect();
+                added.sort_unstable();
+                added.dedup();
+                let added: Vec<RoadSnapshot> = added
+                    .into_iter()
+                    .filter_map(|id| RoadSnapshot::new(map, id))
+                    .collect();
+
+                let remove_op = |r: &RoadSnapshot| MapEditOp::RemoveRoad {
+                    src: r.src,
+                    dst: r.dst,
+                };
+
+                let redo = removed
+                    .iter()
+                    .map(remove_op)
+                    .chain(added.iter().cloned().map(MapEditOp::AddRoad))
+                    .collect();
+                let undo = added
+                    .iter()
+                    .map(remove_op)
+                    .chain(removed.into_iter().map(MapEditOp::AddRoad))
+                    .collect();
+
+                MapEdit { redo, undo }
+            }
+            PendingMapEdit::AddBuilding(snapshot) => {
+                let center = snapshot.obb.center();
+                find_building(map, center, snapshot.kind)?;
+                MapEdit {
+                    undo: vec![MapEditOp::RemoveBuilding {
+                        center,
+                        kind: snapshot.kind,
+                    }],
+                    redo: vec![MapEditOp::AddBuilding(snapshot)],
+                }
+            }
+            PendingMapEdit::RemoveBuilding(id, snapshot) => {
+                if map.buildings.contains_key(id) {
+                    return None;
+                }
+                MapEdit {
+                    redo: vec![MapEditOp::RemoveBuilding {
+                        center: snapshot.obb.center(),
+                        kind: snapshot.kind,
+                    }],
+                    undo: vec![MapEditOp::AddBuilding(snapshot)],
+                }
+            }
+            PendingMapEdit::SetPolicy { pos, old, new } => {
+                if old == new {
+                    return None;
+                }
+                MapEdit {
+                    redo: vec![MapEditOp::SetPolicy { pos, policy: new }],
+                    undo: vec![MapEditOp::SetPolicy { pos, policy: old }],
+                }
+            }
+            PendingMapEdit::RoadPattern { src, dst, old, new } => {
+                if old == new {
+                    return None;
+                }
+                MapEdit {
+                    redo: vec![MapEditOp::SetRoadPattern {
+                        src,
+                        dst,
+                        pattern: new,
+                    }],
+                    undo: vec![MapEditOp::SetRoadPattern {
+                        src,
+                        dst,
+                        pattern: old,
+                    }],
+                }
+            }
+            PendingMapEdit::Trees { added, removed } => {
+                if added.is_empty() && removed.is_empty() {
+                    return None;
+                }
+                let positions = |trees: &[Tree]| trees.iter().map(|t| t.pos).collect::<Vec<_>>();
+                MapEdit {
+                    redo: vec![
+                        MapEditOp::RemoveTrees(positions(&removed)),
+                        MapEditOp::AddTrees(added.clone()),
+                    ],
+                    undo: vec![
+                        MapEditOp::RemoveTrees(positions(&added)),
+                        MapEditOp::AddTrees(removed),
+                    ],
+                }
+            }
+            PendingMapEdit::Heights(before) => {
+                let after = map.environment.heights_like(&before);
+                if after == before {
+                    return None;
+                }
+                MapEdit {
+                    redo: vec![MapEditOp::SetHeights(after)],
+                    undo: vec![MapEditOp::SetHeights(before)],
+                }
+            }
+            PendingMapEdit::Multiple(edits) => {
+                let mut redo = Vec::new();
+                let mut undo = Vec::new();
+                for edit in edits.into_iter().filter_map(|e| e.finish(map)) {
+                    redo.extend(edit.redo);
+                    undo.push(edit.undo);
+                }
+                MapEdit {
+                    redo,
+                    undo: undo.into_iter().rev().flatten().collect(),
+                }
+            }
+        };
+
+        (!edit.is_empty()).then_some(edit)
+    }
+}
+
+/// The edits made to the map that can be undone, and the undone edits that can be redone.
+/// It is not saved: the history starts empty when a map is loaded.
+#[derive(Default)]
+pub struct MapEditHistory {
+    done: VecDeque<(MapEdit, MapEditCost)>,
+    undone: Vec<(MapEdit, MapEditCost)>,
+    /// Heights of the terrain before the terraforming brush stroke in progress
+    terrain_stroke: Option<HeightmapPatch>,
+}
+
+impl MapEditHistory {
+    /// Records a new edit and what was paid for it, the undone edits cannot be redone anymore
+    pub fn push(&mut self, edit: MapEdit, cost: MapEditCost) {
+        self.undone.clear();
+        self.done.push_back((edit, cost));
+        if self.done.len() > MAX_MAP_EDITS {
+            self.done.pop_front();
+        }
+    }
+
+    /// Returns the operations to apply to undo the last edit, and the cost to refund
+    pub fn undo(&mut self) -> Option<(Vec<MapEditOp>, MapEditCost)> {
+        let (edit, cost) = self.done.pop_back()?;
+        let ops = edit.undo.clone();
+        self.undone.push((edit, cost));
+        Some((ops, cost))
+    }
+
+    /// Returns the operations to apply to redo the last undone edit, and the cost to pay again
+    pub fn redo(&mut self) -> Option<(Vec<MapEditOp>, MapEditCost)> {
+        let (edit, cost) = self.undone.pop()?;
+        let ops = edit.redo.clone();
+        self.done.push_back((edit, cost));
+        Some((ops, cost))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Keeps the heights of the bounds before the next dab of the brush stroke modifies them
+    pub fn extend_terrain_stroke(&mut self, env: &Environment, bounds: AABB) {
+        match self.terrain_stroke {
+            Some(ref mut patch) => env.extend_heights_patch(patch, bounds),
+            None => self.terrain_stroke = Some(env.heights_patch(bounds)),
+        }
+    }
+
+    /// Heights of the terrain before the brush stroke, which ends
+    pub fn take_terrain_stroke(&mut self) -> Option<HeightmapPatch> {
+        self.terrain_stroke.take()
+    }
+
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.terrain_stroke = None;
+    }
+}
+
+fn find_intersection(map: &Map, pos: Vec3) -> Option<IntersectionID> {
+    map.spatial_map
+        .query_around(pos.xy(), SAME_POS_DIST, ProjectFilter::INTER)
+        .filter_map(|kind| match kind {
+            ProjectKind::Inter(id) => Some(id),
+            _ => None,
+        })
+        .find(|&id| map.intersections[id].pos.xy().distance(pos.xy()) < SAME_POS_DIST)
+}
+
+fn find_building(map: &Map, center: Vec2, kind: BuildingKind) -> Option<BuildingID> {
+    map.spatial_map
+        .query_around(center, SAME_POS_DIST, ProjectFilter::BUILDING)
+        .filter_map(|k| match k {
+            ProjectKind::Building(id) => Some(id),
+            _ => None,
+        })
+        .find(|&id| {
+            let b = &map.buildings[id];
+            b.kind == kind && b.obb.center().distance(center) < SAME_POS_DIST
+        })
+}
+
+impl Map {
+    /// Applies the operations of an undo or a redo.
+    /// Returns the buildings that were built.
+    pub fn apply_edit_ops(&mut self, ops: &[MapEditOp]) -> Vec<BuildingID> {
+        let mut built = Vec::new();
+        for op in ops {
+            match *op {
+                MapEditOp::AddRoad(ref r) => {
+                    let src = find_intersection(self, r.src)
+                        .unwrap_or_else(|| self.add_intersection(r.src));
+                    let dst = find_intersection(self, r.dst)
+                        .unwrap_or_else(|| self.add_intersection(r.dst));
+                    self.connect(src, dst, &r.pattern, r.segment);
+                }
+                MapEditOp::RemoveRoad { src, dst } => {
+                    let (Some(src), Some(dst)) =
+                        (find_intersection(self, src), find_intersection(self, dst))
+                    else {
+                        continue;
+                    };
+                    if let Some(r) = self.find_road(src, dst) {
+                        self.remove_road(r);
+                    }
+                }
+                MapEditOp::AddBuilding(ref b) => {
+                    if let Some(id) =
+                        self.build_special_building(&b.obb, b.kind, b.gen, b.zone.clone(), None)
+                    {
+                        built.push(id);
+                    }
+                }
+                MapEditOp::RemoveBuilding { center, kind } => {
+                    if let Some(id) = find_building(self, center, kind) {
+                        self.remove_building(id);
+                    }
+                }
+                MapEditOp::SetRoadPattern {
+                    src,
+                    dst,
+                    ref pattern,
+                } => {
+                    let (Some(src), Some(dst)) =
+                        (find_intersection(self, src), find_intersection(self, dst))
+                    else {
+                        continue;
+                    };
+                    if let Some(r) = self.find_road(src, dst) {
+                        self.set_road_pattern(r, pattern);
+                    }
+                }
+                MapEditOp::SetPolicy { pos, ref policy } => {
+                    if let Some(id) = find_intersection(self, pos) {
+                        self.update_intersection(id, |i| policy.apply(i));
+                    }
+                }
+                MapEditOp::AddTrees(ref trees) => self.plant_trees(trees),
+                MapEditOp::RemoveTrees(ref positions) => {
+                    self.remove_trees_in_stroke(positions, SAME_POS_DIST)
+                }
+                MapEditOp::SetHeights(ref patch) => self.set_heights(patch),
+            }
+        }
+        self.check_invariants();
+        built
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::Vec3;
+
+    use crate::economy::Government;
+    use crate::map::{LanePatternBuilder, Map, MapProject, ProjectFilter};
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+
+    /// Roads by the rounded positions of their intersections
+    fn roads_signature(map: &Map) -> Vec<(i32, i32, i32, i32)> {
+        let mut roads: Vec<_> = map
+            .roads()
+            .values()
+            .map(|r| {
+                let src = map.intersections()[r.src].pos;
+                let dst = map.intersections()[r.dst].pos;
+                (src.x as i32, src.y as i32, dst.x as i32, dst.y as i32)
+            })
+            .collect();
+        roads.sort_unstable();
+        roads
+    }
+
+    fn connect(from: MapProject, to: MapProject) -> WorldCommand {
+        WorldCommand::MapMakeConnection {
+            from,
+            to,
+            inter: None,
+            pat: LanePatternBuilder::default().build(),
+        }
+    }
+
+    #[test]
+    fn undo_redo_split_road() {
+        let mut ctx = TestCtx::new();
+
+        let a = Vec3::new(0.0, 0.0, 0.0);
+        let b = Vec3::new(200.0, 0.0, 0.0);
+        ctx.apply(&[connect(MapProject::ground(a), MapProject::ground(b))]);
+        let one_road = roads_signature(&ctx.g.map());
+        assert_eq!(one_road.len(), 1);
+
+        // connecting to the middle of the road splits it
+        let mid = ctx
+            .g
+            .map()
+            .project(Vec3::new(100.0, 0.0, 0.0), 5.0, ProjectFilter::ROAD);
+        let c = Vec3::new(100.0, 150.0, 0.0);
+        ctx.apply(&[connect(mid, MapProject::ground(c))]);
+        let split = roads_signature(&ctx.g.map());
+        assert_eq!(split.len(), 3);
+
+        ctx.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(roads_signature(&ctx.g.map()), one_road);
+        assert_eq!(ctx.g.map().intersections().len(), 2);
+
+        ctx.apply(&[WorldCommand::MapUndo]);
+        assert!(ctx.g.map().roads().is_empty());
+        assert!(ctx.g.map().intersections().is_empty());
+
+        ctx.apply(&[WorldCommand::MapRedo, WorldCommand::MapRedo]);
+        assert_eq!(roads_signature(&ctx.g.map()), split);
+
+        // nothing left to redo
+        ctx.apply(&[WorldCommand::MapRedo]);
+        assert_eq!(roads_signature(&ctx.g.map()), split);
+        ctx.tick();
+    }
+
+    #[test]
+    fn undo_refunds_the_edit() {
+        let mut ctx = TestCtx::new();
+        let money = |ctx: &TestCtx| ctx.g.read::<Government>().money;
+
+        let start = money(&ctx);
+        ctx.apply(&[connect(
+            MapProject::ground(Vec3::new(0.0, 0.0, 0.0)),
+            MapProject::ground(Vec3::new(200.0, 0.0, 0.0)),
+        )]);
+        let built = money(&ctx);
+        assert!(built < start);
+
+        ctx.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(money(&ctx), start);
+
+        ctx.apply(&[WorldCommand::MapRedo]);
+        assert_eq!(money(&ctx), built);
+    }
+}
@@ -0,0 +1,316 @@
+//! Landmarks speeding up the vehicle routes on large maps (ALT: A*, Landmarks, Triangle inequality).
+//! The travel times from and to a few lanes spread on the map are precomputed, and bound the
+//! remaining travel time of a route from below much more tightly than the straight distance.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use common::FastMap;
+use ordered_float::OrderedFloat;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::map::{LaneID, LaneKind, Map};
+
+/// Number of landmarks picked on the map
+pub const N_LANDMARKS: usize = 16;
+
+/// Travel times between the landmarks and every lane the vehicles drive on.
+/// The times are measured at the speed limit, so they never exceed the actual travel times
+/// of the routes, which also account for the congestion.
+#[derive(Default)]
+pub struct Landmarks {
+    index: FastMap<LaneID, u32>,
+    landmarks: Vec<LaneID>,
+    /// Travel time from the landmark to the lane, `lane * n_landmarks + landmark`
+    from: Vec<f32>,
+    /// Travel time from the lane to the landmark, `lane * n_landmarks + landmark`
+    to: Vec<f32>,
+}
+
+/// The travel times of the destination of a route, to estimate the remaining travel time
+pub struct LandmarkTarget<'a> {
+    landmarks: &'a Landmarks,
+    from: &'a [f32],
+    to: &'a [f32],
+}
+
+impl Landmarks {
+    pub fn build(map: &Map) -> Self {
+        profiling::scope!("map::Landmarks::build");
+        let ids: Vec<LaneID> = map
+            .lanes
+            .iter()
+            .filter(|(_, l)| l.kind != LaneKind::Walking)
+            .map(|(id, _)| id)
+            .collect();
+        let index: FastMap<LaneID, u32> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i as u32))
+            .collect();
+        let n = ids.len();
+
+        // lanes are entered through turns, and the cost of a turn is the time to drive the lane
+        let mut cost = vec![f32::INFINITY; n];
+        let mut forward = vec![vec![]; n];
+        let mut backward = vec![vec![]; n];
+        for (i, &id) in ids.iter().enumerate() {
+            let lane = &map.lanes[id];
+            cost[i] = lane.points.length() / lane.speed_limit.max(0.1);
+            let Some(inter) = map.intersections.get(lane.dst) else {
+                continue;
+            };
+            for (turn, _) in inter.turns_from(id) {
+                let Some(&j) = index.get(&turn.dst) else {
+                    continue;
+                };
+                forward[i].push(j);
+                backward[j as usize].push(i as u32);
+            }
+        }
+
+        let landmarks = pick_landmarks(map, &ids);
+        let k = landmarks.len();
+
+        let times: Vec<(Vec<f32>, Vec<f32>)> = landmarks
+            .clone()
+            .into_par_iter()
+            .map(|l| {
+                let l = index[&l];
+                let from = dijkstra(l, &forward, |next, _| cost[next as usize]);
+                let to = dijkstra(l, &backward, |_, cur| cost[cur as usize]);
+                (from, to)
+            })
+            .collect();
+
+        let mut from = vec![f32::INFINITY; n * k];
+        let mut to = vec![f32::INFINITY; n * k];
+        for (j, (f, t)) in times.into_iter().enumerate() {
+            for i in 0..n {
+                from[i * k + j] = f[i];
+                to[i * k + j] = t[i];
+            }
+        }
+
+        Self {
+            index,
+            landmarks,
+            from,
+            to,
+        }
+    }
+
+    pub fn landmarks(&self) -> &[LaneID] {
+        &self.landmarks
+    }
+
+    /// None if the lane is not known, e.g. a walking lane
+    pub fn target(&self, lane: LaneID) -> Option<LandmarkTarget<'_>> {
+        let i = *self.index.get(&lane)? as usize;
+        let k = self.landmarks.len();
+        Some(LandmarkTarget {
+            landmarks: self,
+            from: &self.from[i * k..(i + 1) * k],
+            to: &self.to[i * k..(i + 1) * k],
+        })
+    }
+}
+
+impl LandmarkTarget<'_> {
+    /// Lower bound of the travel time from the lane to the target, infinite if the target
+    /// cannot be reached from the lane
+    pub fn estimate(&self, lane: LaneID) -> f32 {
+        let Some(&i) = self.landmarks.index.get(&lane) else {
+            return 0.0;
+        };
+        let k = self.landmarks.landmarks.len();
+        let i = i as usize * k;
+        let from = &self.landmarks.from[i..i + k];
+        let to = &self.landmarks.to[i..i + k];
+
+        let mut best = 0.0f32;
+        for j in 0..k {
+            // d(l, target) >= d(landmark, target) - d(landmark, l)
+            let a = self.from[j] - from[j];
+            // d(l, target) >= d(l, landmark) - d(target, landmark)
+            let b = to[j] - self.to[j];
+            // inf - inf happens when both are on a part the landmark doesn't see
+            if !a.is_nan() {
+                best = best.max(a);
+            }
+            if !b.is_nan() {
+                best = best.max(b);
+            }
+        }
+        best
+    }
+}
+
+/// Lanes far away from each other, so that most routes go towards or away from one of them
+fn pick_landmarks(map: &Map, ids: &[LaneID]) -> Vec<LaneID> {
+    let pos: Vec<_> = ids
+        .iter()
+        .map(|&id| {
+            let lane = &map.lanes[id];
+            map.intersections
+                .get(lane.dst)
+                .map_or(lane.points.last(), |i| i.pos)
+                .xy()
+        })
+        .collect();
+    if pos.is_empty() {
+        return vec![];
+    }
+    let center = pos.iter().fold(geom::Vec2::ZERO, |acc, &p| acc + p) / pos.len() as f32;
+
+    let mut closest = vec![f32::INFINITY; ids.len()];
+    let mut landmarks = vec![];
+    let mut next = farthest(pos.iter().map(|p| p.distance2(center)));
+    while landmarks.len() < N_LANDMARKS.min(ids.len()) {
+        landmarks.push(ids[next]);
+        for (d, p) in closest.iter_mut().zip(&pos) {
+            *d = d.min(p.distance2(pos[next]));
+        }
+        next = farthest(closest.iter().copied());
+    }
+    landmarks
+}
+
+fn farthest(dists: impl Iterator<Item = f32>) -> usize {
+    dists
+        .enumerate()
+        .max_by_key(|&(i, d)| (OrderedFloat(d), Reverse(i)))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Travel times from the start to every node, `cost(next, current)` is the cost of an edge
+fn dijkstra(start: u32, edges: &[Vec<u32>], cost: impl Fn(u32, u32) -> f32) -> Vec<f32> {
+    let mut dist = vec![f32::INFINITY; edges.len()];
+    let mut heap = BinaryHeap::new();
+    dist[start as usize] = 0.0;
+    heap.push(Reverse((OrderedFloat(0.0), start)));
+
+    while let Some(Reverse((OrderedFloat(d), cur))) = heap.pop() {
+        if d > dist[cur as usize] {
+            continue;
+        }
+        for &next in &edges[cur as usize] {
+            let nd = d + cost(next, cur);
+            if nd < dist[next as usize] {
+                dist[next as usize] = nd;
+                heap.push(Reverse((OrderedFloat(nd), next)));
+            }
+        }
+    }
+    dist
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    use geom::Vec2;
+    use prototypes::Tick;
+
+    use crate::map::procgen::add_grid;
+    use crate::map::{car_route, LaneID, LaneKind, LaneSpeedSample, Map, RouteHeuristic};
+
+    fn driving_lanes(m: &Map) -> Vec<LaneID> {
+        m.lanes
+            .iter()
+            .filter(|(_, l)| l.kind == LaneKind::Driving)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn pairs(lanes: &[LaneID], n: usize) -> Vec<(LaneID, LaneID)> {
+        (0..n)
+            .map(|i| {
+                let a = common::rand::randu(i as u32 * 2) * lanes.len() as f32;
+                let b = common::rand::randu(i as u32 * 2 + 1) * lanes.len() as f32;
+                (lanes[a as usize], lanes[b as usize])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_cost_as_dijkstra() {
+        let mut m = Map::empty();
+        add_grid(&mut m, Vec2::ZERO, 12, 100.0);
+        // holes in the grid so that the fastest routes make detours
+        let roads: Vec<_> = m.roads.keys().collect();
+        for road in roads.into_iter().step_by(7) {
+            m.remove_road(road);
+        }
+        // and congestion, which only makes the routes slower than the landmarks expect
+        let lanes = driving_lanes(&m);
+        let samples: BTreeMap<_, _> = lanes
+            .iter()
+            .step_by(3)
+            .map(|&id| (id, LaneSpeedSample { sum: 0.1, count: 1 }))
+            .collect();
+        for _ in 0..20 {
+            m.lane_speeds.update(&m.lanes, &samples);
+        }
+
+        assert_eq!(m.landmarks().landmarks().len(), super::N_LANDMARKS);
+
+        for (start, end) in pairs(&lanes, 300) {
+            let alt = car_route(&m, Tick(3), start, end, RouteHeuristic::Landmarks);
+            let exact = car_route(&m, Tick(3), start, end, RouteHeuristic::None);
+            match (alt, exact) {
+                (None, None) => {}
+                (Some((_, a)), Some((_, b))) => {
+                    assert!((a - b).abs() <= 1e-3 * b.max(1.0), "{} != {}", a, b)
+                }
+                (a, b) => panic!("routes differ: {:?} {:?}", a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn rebuilt_when_roads_change() {
+        let mut m = Map::empty();
+        add_grid(&mut m, Vec2::ZERO, 3, 100.0);
+        let before = m.landmarks().landmarks().to_vec();
+        let road = m.roads.keys().next().unwrap();
+        m.remove_road(road);
+        assert!(m.landmarks.get().is_none());
+        let after = m.landmarks().landmarks();
+        assert!(after.iter().all(|l| m.lanes.contains_key(*l)));
+        assert_ne!(before.len(), 0);
+    }
+
+    /// cargo test --release -p simulation bench_landmarks -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_landmarks() {
+        let mut m = Map::empty();
+        // 71x71 intersections, about 10k roads
+        add_grid(&mut m, Vec2::ZERO, 71, 100.0);
+        println!("{} roads, {} lanes", m.roads.len(), m.lanes.len());
+
+        let t = Instant::now();
+        m.landmarks();
+        println!("landmarks built in {:?}", t.elapsed());
+
+        let lanes = driving_lanes(&m);
+        let pairs = pairs(&lanes, 200);
+        for heuristic in [
+            RouteHeuristic::Distance,
+            RouteHeuristic::Landmarks,
+            RouteHeuristic::None,
+        ] {
+            let t = Instant::now();
+            for &(start, end) in &pairs {
+                car_route(&m, Tick(1), start, end, heuristic);
+            }
+            println!(
+                "{:?}: {:?} per route",
+                heuristic,
+                t.elapsed() / pairs.len() as u32
+            );
+        }
+    }
+}
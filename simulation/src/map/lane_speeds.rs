@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use prototypes::TICKS_PER_MINUTE;
+use serde::{Deserialize, Serialize};
+
+use crate::map::{Lane, LaneID, Lanes, Map};
+
+/// Number of ticks between two measures of the vehicle speeds on the lanes
+pub const LANE_SPEED_SAMPLE_TICKS: u64 = 25;
+
+/// Measures older than this weigh less than a third of the measured speed
+const LANE_SPEED_DECAY_TICKS: u64 = 3 * TICKS_PER_MINUTE;
+
+/// Lanes flowing faster than this fraction of their speed limit are not stored
+const FREE_FLOW: f32 = 0.99;
+
+/// Congested lanes still have a finite cost so that routes through a jam exist
+const MIN_FLOW: f32 = 0.05;
+
+/// Average speed of the vehicles on the lanes, as a fraction of the speed limit.
+/// Only lanes slower than their speed limit are stored.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct LaneSpeeds {
+    flow: BTreeMap<LaneID, f32>,
+}
+
+/// Sum of the vehicle speeds measured on a lane, as a fraction of their desired speed
+#[derive(Default, Copy, Clone)]
+pub struct LaneSpeedSample {
+    pub sum: f32,
+    pub count: u32,
+}
+
+impl LaneSpeeds {
+    /// Fraction of the speed limit at which vehicles are driving on the lane
+    pub fn flow(&self, lane: LaneID) -> f32 {
+        self.flow.get(&lane).copied().unwrap_or(1.0)
+    }
+
+    /// Blends the samples into the measured speeds, lanes without vehicles go back to free flow.
+    /// Called every [`LANE_SPEED_SAMPLE_TICKS`] so that the decay only depends on the ticks.
+    pub fn update(&mut self, lanes: &Lanes, samples: &BTreeMap<LaneID, LaneSpeedSample>) {
+        let alpha = LANE_SPEED_SAMPLE_TICKS as f32 / LANE_SPEED_DECAY_TICKS as f32;
+
+        for (&id, sample) in samples {
+            if sample.count == 0 || !lanes.contains_key(id) {
+                continue;
+            }
+            let measured = (sample.sum / sample.count as f32).clamp(0.0, 1.0);
+            let flow = self.flow.entry(id).or_insert(1.0);
+            *flow += (measured - *flow) * alpha;
+        }
+
+        self.flow.retain(|id, flow| {
+            if !lanes.contains_key(*id) {
+                return false;
+            }
+            if !samples.contains_key(id) {
+                *flow += (1.0 - *flow) * alpha;
+            }
+            *flow < FREE_FLOW
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LaneID, f32)> + '_ {
+        self.flow.iter().map(|(&id, &flow)| (id, flow))
+    }
+}
+
+impl Map {
+    /// How slow vehicles are on the lane compared to its speed limit,
+    /// from 0 when flowing freely to 1 when stopped
+    pub fn lane_congestion(&self, lane: LaneID) -> f32 {
+        1.0 - self.lane_speeds.flow(lane)
+    }
+
+    /// Average speed measured on the lane, the speed limit if no vehicle was slowed down
+    pub fn lane_measured_speed(&self, lane: &Lane) -> f32 {
+        lane.speed_limit * self.lane_speeds.flow(lane.id).max(MIN_FLOW)
+    }
+
+    /// Expected time to drive along the whole lane given the measured speed
+    pub fn lane_travel_time(&self, lane: &Lane) -> f32 {
+        lane.points.length() / self.lane_measured_speed(lane)
+    }
+}
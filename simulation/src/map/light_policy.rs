@@ -12,9 +12,72 @@ pub enum LightPolicy {
     Auto,
 }
 
+/// Traffic light timings chosen by the player instead of the automatic ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightTiming {
+    /// Duration of a whole cycle, in real seconds
+    pub cycle: u16,
+    /// Share of the cycle given to each phase.
+    /// The roads of a phase get the green light at the same time.
+    pub splits: Vec<f32>,
+}
+
+/// Duration of the orange light, in real seconds
+const ORANGE_LENGTH: u16 = 4;
+
+/// Duration of each phase with the automatic timings, in real seconds
+const AUTO_PHASE_LENGTH: u16 = 14;
+
+impl LightTiming {
+    /// Minimum duration of a phase, in real seconds, so that it has some green after the orange
+    pub const MIN_PHASE: u16 = ORANGE_LENGTH + 2;
+
+    /// Longest cycle, in real seconds, the longer ones are shortened
+    pub const MAX_CYCLE: u16 = 240;
+
+    /// Timings equivalent to the automatic ones
+    pub fn auto(n_phases: usize) -> Self {
+        Self {
+            cycle: AUTO_PHASE_LENGTH * n_phases as u16,
+            splits: vec![1.0 / n_phases as f32; n_phases],
+        }
+    }
+
+    /// Length of each phase in game seconds, None if it doesn't have one split per phase or if
+    /// the whole cycle doesn't fit in the schedules of the lights
+    fn phase_lengths(&self, n_phases: usize) -> Option<Vec<u16>> {
+        if self.splits.len() != n_phases || n_phases == 0 {
+            return None;
+        }
+        let total: f32 = self.splits.iter().map(|x| x.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let cycle = self.cycle.min(Self::MAX_CYCLE) as f32;
+        let lengths: Vec<u16> = self
+            .splits
+            .iter()
+            .map(|x| {
+                let length = x.max(0.0) / total * cycle;
+                (length as u16).max(Self::MIN_PHASE) * SECONDS_PER_REALTIME_SECOND as u16
+            })
+            .collect();
+        lengths
+            .iter()
+            .try_fold(0u16, |sum, &length| sum.checked_add(length))?;
+        Some(lengths)
+    }
+}
+
 impl LightPolicy {
-    pub fn apply(self, inter: &Intersection, lanes: &mut Lanes, roads: &Roads) {
-        let in_road_lanes: Vec<Vec<LaneID>> = inter
+    /// Number of traffic light phases of the intersection, opposite roads share the same phase
+    pub fn n_phases(inter: &Intersection, roads: &Roads) -> usize {
+        (Self::incoming_lanes(inter, roads).len() + 1) / 2
+    }
+
+    /// Incoming lanes that need a traffic control, grouped by road
+    fn incoming_lanes(inter: &Intersection, roads: &Roads) -> Vec<Vec<LaneID>> {
+        inter
             .roads
             .iter()
             .map(|&x| {
@@ -30,7 +93,11 @@ impl LightPolicy {
                     .collect::<Vec<_>>()
             })
             .filter(|v| !v.is_empty())
-            .collect();
+            .collect()
+    }
+
+    pub fn apply(self, inter: &Intersection, lanes: &mut Lanes, roads: &Roads) {
+        let in_road_lanes = Self::incoming_lanes(inter, roads);
 
         for incoming_lanes in &in_road_lanes {
             for &lane in incoming_lanes {
@@ -77,22 +144,46 @@ impl LightPolicy {
     }
 
     fn lights(in_road_lanes: Vec<Vec<LaneID>>, inter: &Intersection, lanes: &mut Lanes) {
-        let n_cycles = ((in_road_lanes.len() + 1) / 2) as u16;
-        let cycle_size = 14 * SECONDS_PER_REALTIME_SECOND as u16;
-        let orange_length = 4 * SECONDS_PER_REALTIME_SECOND as u16;
+        let n_cycles = (in_road_lanes.len() + 1) / 2;
+        let orange_length = ORANGE_LENGTH * SECONDS_PER_REALTIME_SECOND as u16;
 
-        let total_length = cycle_size * n_cycles;
+        // (start of the green, length) of each phase
+        let phases: Vec<(u16, u16)> = match inter
+            .light_timing
+            .as_ref()
+            .and_then(|t| t.phase_lengths(n_cycles))
+        {
+            Some(lengths) => {
+                let mut start = 0;
+                lengths
+                    .into_iter()
+                    .map(|length| {
+                        let phase = (start, length);
+                        start += length;
+                        phase
+                    })
+                    .collect()
+            }
+            None => {
+                let cycle_size = AUTO_PHASE_LENGTH * SECONDS_PER_REALTIME_SECOND as u16;
+                let total_length = cycle_size * n_cycles as u16;
+                (0..n_cycles as u16)
+                    .map(|i| ((total_length - cycle_size * i) % total_length, cycle_size))
+                    .collect()
+            }
+        };
+        let total_length: u16 = phases.iter().map(|(_, length)| length).sum();
 
         let inter_offset =
             (common::rand::rand(inter.id.as_ffi() as f32) * total_length as f32) as u16;
 
         for (i, incoming_lanes) in in_road_lanes.into_iter().enumerate() {
-            let i = i as u16;
+            let (start, length) = phases[i % n_cycles];
             let light = TrafficControl::Light(TrafficLightSchedule::from_basic(
-                cycle_size - orange_length,
+                length - orange_length,
                 orange_length,
-                total_length - cycle_size,
-                cycle_size * (i % n_cycles) + inter_offset,
+                total_length - length,
+                (total_length - start + inter_offset) % total_length,
             ));
 
             for lane in incoming_lanes {
@@ -146,3 +237,30 @@ impl Inspect<LightPolicy> for LightPolicy {
         changed
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_lengths_fit_in_the_schedules() {
+        let timing = LightTiming {
+            cycle: u16::MAX,
+            splits: vec![0.25; 4],
+        };
+        let lengths = timing.phase_lengths(4).unwrap();
+        let total: u16 = lengths.iter().sum();
+        assert_eq!(
+            total,
+            LightTiming::MAX_CYCLE * SECONDS_PER_REALTIME_SECOND as u16
+        );
+
+        // the minimum phases alone are too long for the schedules
+        let timing = LightTiming {
+            cycle: 60,
+            splits: vec![1.0; 2000],
+        };
+        assert_eq!(timing.phase_lengths(2000), None);
+        assert_eq!(timing.phase_lengths(3), None);
+    }
+}
@@ -2,17 +2,21 @@ use crate::map::electricity_cache::ElectricityCache;
 use crate::map::height_override::find_overrides;
 use crate::map::serializing::SerializedMap;
 use crate::map::{
-    Building, BuildingID, BuildingKind, Environment, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, MapSubscriber, MapSubscribers, ParkingSpotID,
+    generated_road_name, Building, BuildingID, BuildingKind, Crossing, CrossingID, Districts,
+    Environment, Intersection, IntersectionID, Landmarks, Lane, LaneID, LaneKind, LanePattern,
+    LaneSpeeds, Lot, LotID, LotKind, MapSubscriber, MapSubscribers, NoiseMap, ParkingSpotID,
     ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap,
-    SubscriberChunkID, TerraformKind, UpdateType, Zone,
+    SubscriberChunkID, TerraformKind, Tree, UpdateType, Zone, ZoneGrid,
 };
-use geom::OBB;
+use geom::{Circle, HeightmapPatch, PolyLine3, OBB};
 use geom::{Spline3, Vec2, Vec3};
 use ordered_float::OrderedFloat;
 use prototypes::{BuildingGen, Tick};
 use serde::{Deserialize, Serialize};
 use slotmapd::HopSlotMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
 
 pub type Roads = HopSlotMap<RoadID, Road>;
 pub type Lanes = HopSlotMap<LaneID, Lane>;
@@ -20,6 +24,12 @@ pub type Intersections = HopSlotMap<IntersectionID, Intersection>;
 pub type Buildings = HopSlotMap<BuildingID, Building>;
 pub type Lots = HopSlotMap<LotID, Lot>;
 
+/// Distance to keep between a planted tree and roads or buildings
+const TREE_CLEARANCE: f32 = 3.0;
+
+/// How close to the edge of the map an intersection must be to become a road connection
+pub const BORDER_CONNECTION_DISTANCE: f32 = 100.0;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MapProject {
     pub pos: Vec3,
@@ -38,6 +48,25 @@ pub struct Map {
     pub electricity: ElectricityCache,
     pub environment: Environment,
     pub parking: ParkingSpots,
+    pub lane_speeds: LaneSpeeds,
+    /// Built on the first vehicle route after the lanes or turns changed
+    pub(crate) landmarks: OnceLock<Landmarks>,
+    /// Whether the vehicle routes are guided by the landmarks, copied from the
+    /// [`crate::SimulationOptions`] as the routes only get the map
+    pub(crate) landmark_routing: bool,
+    pub zones: ZoneGrid,
+    /// Areas named by the players
+    pub districts: Districts,
+    pub(crate) noise: NoiseMap,
+    /// Names given to roads by the players, the others have a generated name
+    pub(crate) road_names: BTreeMap<RoadID, String>,
+    /// Number of the generated name of each road, given when the road is created
+    pub(crate) road_numbers: BTreeMap<RoadID, u32>,
+    /// Roads created so far, the next road gets the next generated name
+    pub(crate) roads_created: u32,
+    /// Intersections at the border where the roads leave the map, the trucks of the
+    /// external trades come and go through them
+    pub(crate) road_connections: Vec<IntersectionID>,
     pub subscribers: MapSubscribers,
     pub(crate) override_subscriber: MapSubscriber,
 }
@@ -59,12 +88,22 @@ impl Map {
             lanes: Lanes::default(),
             intersections: Intersections::default(),
             parking: ParkingSpots::default(),
+            lane_speeds: LaneSpeeds::default(),
+            landmarks: OnceLock::new(),
+            landmark_routing: true,
             buildings: Buildings::default(),
             lots: Lots::default(),
             environment: Environment::default(),
             spatial_map: SpatialMap::default(),
             external_train_stations: Default::default(),
             electricity: Default::default(),
+            zones: ZoneGrid::default(),
+            districts: Districts::default(),
+            noise: NoiseMap::default(),
+            road_names: BTreeMap::new(),
+            road_numbers: BTreeMap::new(),
+            roads_created: 0,
+            road_connections: Vec::new(),
             override_subscriber: subscribers.subscribe(UpdateType::Road | UpdateType::Building),
             subscribers,
         }
@@ -78,6 +117,11 @@ impl Map {
         }
     }
 
+    /// Travel times to the landmarks of the lane graph, built on the first call after a change
+    pub fn landmarks(&self) -> &Landmarks {
+        self.landmarks.get_or_init(|| Landmarks::build(self))
+    }
+
     pub fn update_intersection(&mut self, id: IntersectionID, f: impl Fn(&mut Intersection)) {
         info!("update_intersection {:?}", id);
 
@@ -97,9 +141,10 @@ impl Map {
         self.check_invariants()
     }
 
-    fn remove_intersection_inner(&mut self, src: IntersectionID) {
+    pub(crate) fn remove_intersection_inner(&mut self, src: IntersectionID) {
         let inter = unwrap_ret!(self.intersections.remove(src));
         self.subscribers.dispatch(UpdateType::Road, &inter);
+        self.road_connections.retain(|&id| id != src);
 
         for road in inter.roads {
             let r = unwrap_cont!(self.remove_road_inner(road));
@@ -128,6 +173,12 @@ impl Map {
     pub fn remove_building(&mut self, b: BuildingID) -> Option<Building> {
         info!("remove_building {:?}", b);
 
+        let b = self.remove_building_inner(b);
+        self.check_invariants();
+        b
+    }
+
+    pub(crate) fn remove_building_inner(&mut self, b: BuildingID) -> Option<Building> {
         let b = self.buildings.remove(b)?;
         self.subscribers.dispatch(UpdateType::Building, &b);
 
@@ -142,8 +193,6 @@ impl Map {
         self.electricity.remove_object(b.id);
         self.spatial_map.remove(b.id);
 
-        self.check_invariants();
-
         Some(b)
     }
 
@@ -264,6 +313,7 @@ impl Map {
             *obb,
             kind,
             gen,
+            1,
             zone,
             connected_road,
         ) else {
@@ -283,7 +333,12 @@ impl Map {
     }
 
     pub fn build_house(&mut self, lot_id: LotID) -> Option<BuildingID> {
-        info!("build house on {:?}", lot_id);
+        self.build_house_with_floors(lot_id, 1)
+    }
+
+    /// Builds a house on the lot, richer houses have more floors
+    pub fn build_house_with_floors(&mut self, lot_id: LotID, floors: u32) -> Option<BuildingID> {
+        info!("build house with {} floors on {:?}", floors, lot_id);
 
         let lot = self.lots.remove(lot_id)?;
         self.subscribers.dispatch(UpdateType::Road, &lot);
@@ -298,6 +353,7 @@ impl Map {
             lot.shape,
             BuildingKind::House,
             BuildingGen::House,
+            floors,
             None,
             Some(lot.parent),
         ) else {
@@ -339,9 +395,11 @@ impl Map {
         }
     }
 
-    fn remove_road_inner(&mut self, road_id: RoadID) -> Option<Road> {
+    pub(crate) fn remove_road_inner(&mut self, road_id: RoadID) -> Option<Road> {
         let road = self.remove_raw_road(road_id)?;
         self.subscribers.dispatch(UpdateType::Road, &road);
+        self.road_names.remove(&road_id);
+        self.road_numbers.remove(&road_id);
 
         for (id, _) in road.lanes_iter() {
             self.parking.remove_spots(id);
@@ -396,6 +454,64 @@ impl Map {
         }
     }
 
+    /// Puts back heights copied from the terrain
+    pub fn set_heights(&mut self, patch: &HeightmapPatch) {
+        for id in self.environment.set_heights(patch) {
+            self.subscribers.dispatch_chunk(UpdateType::Terrain, id);
+        }
+    }
+
+    /// Whether a tree can stand here: on land, away from roads and buildings
+    pub fn can_plant_tree(&self, pos: Vec2) -> bool {
+        self.environment
+            .true_height(pos)
+            .map_or(false, |h| h >= 0.0)
+            && self
+                .spatial_map
+                .query_around(
+                    pos,
+                    TREE_CLEARANCE,
+                    ProjectFilter::ROAD | ProjectFilter::INTER | ProjectFilter::BUILDING,
+                )
+                .next()
+                .is_none()
+    }
+
+    /// Plants the trees that can stand where they are
+    pub fn plant_trees(&mut self, trees: &[Tree]) {
+        let trees: Vec<Tree> = trees
+            .iter()
+            .filter(|t| self.can_plant_tree(t.pos))
+            .copied()
+            .collect();
+        self.environment.add_trees(trees, |chunk| {
+            self.subscribers.dispatch_chunk(UpdateType::Terrain, chunk)
+        });
+    }
+
+    /// The trees inside the circles of a brush stroke, each tree only once
+    pub fn trees_in_stroke(&self, stroke: &[Vec2], radius: f32) -> Vec<Tree> {
+        let mut trees = Vec::new();
+        for (i, &center) in stroke.iter().enumerate() {
+            trees.extend(
+                self.environment
+                    .trees_near(Circle { center, radius })
+                    .into_iter()
+                    .filter(|t| !stroke[..i].iter().any(|p| p.is_close(t.pos, radius))),
+            );
+        }
+        trees
+    }
+
+    pub fn remove_trees_in_stroke(&mut self, stroke: &[Vec2], radius: f32) {
+        for &center in stroke {
+            self.environment
+                .remove_trees_near(Circle { center, radius }, |chunk| {
+                    self.subscribers.dispatch_chunk(UpdateType::Terrain, chunk)
+                });
+        }
+    }
+
     // Private mutating
 
     pub(crate) fn add_intersection(&mut self, pos: Vec3) -> IntersectionID {
@@ -411,6 +527,7 @@ impl Map {
 
         let inter = unwrap_ret!(self.intersections.get_mut(id));
         self.subscribers.dispatch(UpdateType::Road, inter);
+        self.landmarks.take();
 
         if inter.roads.is_empty() {
             self.remove_intersection_inner(id);
@@ -451,6 +568,7 @@ impl Map {
     fn remove_raw_road(&mut self, road_id: RoadID) -> Option<Road> {
         let road = self.roads.remove(road_id)?;
 
+        self.landmarks.take();
         self.spatial_map.remove(road_id);
         self.electricity.remove_object(road_id);
 
@@ -483,6 +601,8 @@ impl Map {
             return None;
         });
         self.subscribers.dispatch(UpdateType::Road, &r);
+        let name = self.road_names.remove(&split_road_id);
+        let number = self.road_numbers.remove(&split_road_id);
 
         for (id, _) in r.lanes_iter() {
             self.parking.remove_to_reuse(id);
@@ -536,6 +656,32 @@ impl Map {
             self.parking.clean_reuse()
         );
 
+        let split_dist = r.points.length_at_proj(r.points.project(pos));
+        for c in &r.crossings {
+            let (road, dist) = if c.dist < split_dist {
+                (r1, c.dist)
+            } else {
+                (r2, c.dist - split_dist)
+            };
+            if let Some(road) = self.roads.get_mut(road) {
+                road.crossings.push(Crossing {
+                    dist,
+                    points: c.points.clone(),
+                });
+            }
+        }
+        for road in [r1, r2] {
+            if let Some(road) = self.roads.get_mut(road) {
+                road.update_crossings(&self.lanes);
+            }
+            if let Some(ref name) = name {
+                self.road_names.insert(road, name.clone());
+            }
+            if let Some(number) = number {
+                self.road_numbers.insert(road, number);
+            }
+        }
+
         let r1 = self.roads.get(r1)?;
         let r2 = self.roads.get(r2)?;
 
@@ -591,6 +737,208 @@ impl Map {
         Some(id)
     }
 
+    /// Rebuilds the lanes of the road with another pattern, keeping its geometry,
+    /// its intersections and the buildings connected to it.
+    /// Returns the id of the new road.
+    pub fn set_road_pattern(&mut self, road_id: RoadID, pattern: &LanePattern) -> Option<RoadID> {
+        info!("set_road_pattern {:?} {:?}", road_id, pattern);
+
+        let r = self.remove_raw_road(road_id)?;
+        self.subscribers.dispatch(UpdateType::Road, &r);
+
+        for (id, _) in r.lanes_iter() {
+            self.parking.remove_to_reuse(id);
+        }
+
+        let smap = &mut self.spatial_map;
+        self.lots.retain(|_, lot| {
+            let to_remove = lot.parent == road_id;
+            if to_remove {
+                self.subscribers.dispatch(UpdateType::Road, lot);
+                smap.remove(lot.id);
+            }
+            !to_remove
+        });
+
+        let new_id = self.connect(r.src, r.dst, pattern, r.segment)?;
+
+        log::info!(
+            "{} parking spots reused when changing pattern",
+            self.parking.clean_reuse()
+        );
+
+        if let Some(road) = self.roads.get_mut(new_id) {
+            road.crossings = r.crossings;
+            road.update_crossings(&self.lanes);
+        }
+        if let Some(name) = self.road_names.remove(&road_id) {
+            self.road_names.insert(new_id, name);
+        }
+        if let Some(number) = self.road_numbers.remove(&road_id) {
+            self.road_numbers.insert(new_id, number);
+        }
+
+        for b in r.connected_buildings {
+            let Some(b) = self.buildings.get_mut(b) else {
+                continue;
+            };
+            b.connected_road = Some(new_id);
+            self.roads[new_id].connected_buildings.push(b.id);
+            self.electricity.add_edge(b.id, new_id);
+        }
+
+        self.check_invariants();
+        Some(new_id)
+    }
+
+    /// Reverses the direction of a one-way road, two-way roads are left untouched.
+    /// Returns the id of the new road.
+    pub fn flip_road(&mut self, road_id: RoadID) -> Option<RoadID> {
+        let pattern = self.roads.get(road_id)?.pattern(&self.lanes);
+        if !pattern.is_one_way() {
+            return None;
+        }
+        self.set_road_pattern(road_id, &pattern.flipped())
+    }
+
+    /// Adds a marked crossing where the position projects on the road.
+    /// The road needs sidewalks on both sides.
+    pub fn add_crossing(&mut self, road_id: RoadID, pos: Vec3) -> Option<CrossingID> {
+        info!("add_crossing {:?} {:?}", road_id, pos);
+
+        let road = self.roads.get_mut(road_id)?;
+        road.crossing_sidewalks()?;
+
+        let start = road.interface_from(road.src);
+        let end = road.length() - road.interface_from(road.dst);
+        if end <= start {
+            return None;
+        }
+        let dist = road
+            .points
+            .length_at_proj(road.points.project(pos))
+            .clamp(start, end);
+
+        road.crossings.push(Crossing {
+            dist,
+            points: PolyLine3::new(vec![pos]),
+        });
+        road.update_crossings(&self.lanes);
+        self.subscribers.dispatch(UpdateType::Road, &*road);
+
+        let id = road.crossing_id(road.crossings.len() - 1);
+        self.check_invariants();
+        id
+    }
+
+    /// Name of the road, the one given by the players or a generated one like "Elm Street".
+    /// Generated names come from the number the road got when it was created, so a new road
+    /// doesn't take the name of a removed one.
+    pub fn road_name(&self, road_id: RoadID) -> Cow<'_, str> {
+        match self.road_names.get(&road_id) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(generated_road_name(
+                self.road_numbers.get(&road_id).copied().unwrap_or(0),
+            )),
+        }
+    }
+
+    /// Name given by the players, if any
+    pub fn custom_road_name(&self, road_id: RoadID) -> Option<&str> {
+        self.road_names.get(&road_id).map(String::as_str)
+    }
+
+    /// Names the road, an empty name gives it back its generated name
+    pub fn set_road_name(&mut self, road_id: RoadID, name: &str) {
+        let Some(road) = self.roads.get(road_id) else {
+            return;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            self.road_names.remove(&road_id);
+        } else {
+            self.road_names.insert(road_id, name.to_string());
+        }
+        self.subscribers.dispatch(UpdateType::Road, road);
+    }
+
+    /// Whether the position is close enough to the edge of the map for a road connection
+    pub fn is_near_border(&self, pos: Vec2) -> bool {
+        (0.0..=BORDER_CONNECTION_DISTANCE).contains(&self.distance_to_border(pos))
+    }
+
+    /// Distance to the nearest edge of the map, negative outside of it
+    pub fn distance_to_border(&self, pos: Vec2) -> f32 {
+        let bounds = self.environment.bounds();
+        (pos.x - bounds.ll.x)
+            .min(bounds.ur.x - pos.x)
+            .min(pos.y - bounds.ll.y)
+            .min(bounds.ur.y - pos.y)
+    }
+
+    /// Makes the intersection a road connection to the outside or a normal intersection again.
+    /// Only intersections near the border can be connections.
+    pub fn set_road_connection(&mut self, id: IntersectionID, connection: bool) {
+        info!("set_road_connection {:?} {}", id, connection);
+
+        let Some(inter) = self.intersections.get(id) else {
+            return;
+        };
+        self.road_connections.retain(|&x| x != id);
+        if connection {
+            if !self.is_near_border(inter.pos.xy()) {
+                return;
+            }
+            self.road_connections.push(id);
+        }
+        self.subscribers.dispatch(UpdateType::Road, inter);
+    }
+
+    pub fn is_road_connection(&self, id: IntersectionID) -> bool {
+        self.road_connections.contains(&id)
+    }
+
+    /// The road connections with their position
+    pub fn road_connections(&self) -> impl Iterator<Item = (IntersectionID, Vec3)> + '_ {
+        self.road_connections
+            .iter()
+            .filter_map(|&id| Some((id, self.intersections.get(id)?.pos)))
+    }
+
+    /// Whether the road leaving the connection leads somewhere, connections are generated
+    /// with a piece of road the players have to extend to their city
+    pub fn is_road_connection_used(&self, id: IntersectionID) -> bool {
+        let Some(inter) = self.intersections.get(id) else {
+            return false;
+        };
+        inter.roads.len() > 1
+            || inter.roads.iter().any(|&r| {
+                self.roads
+                    .get(r)
+                    .and_then(|r| r.other_end(id))
+                    .and_then(|other| self.intersections.get(other))
+                    .is_some_and(|other| other.roads.len() > 1)
+            })
+    }
+
+    /// The buildings where the railways leave the map
+    pub fn external_train_stations(&self) -> &[BuildingID] {
+        &self.external_train_stations
+    }
+
+    pub fn remove_crossing(&mut self, road_id: RoadID, idx: usize) {
+        info!("remove_crossing {:?} {:?}", road_id, idx);
+
+        let Some(road) = self.roads.get_mut(road_id) else {
+            return;
+        };
+        if idx >= road.crossings.len() {
+            return;
+        }
+        road.crossings.remove(idx);
+        self.subscribers.dispatch(UpdateType::Road, &*road);
+    }
+
     /// Returns None if one of the intersections don't exist
     pub(crate) fn connect(
         &mut self,
@@ -614,6 +962,9 @@ impl Map {
             &mut self.spatial_map,
         );
 
+        self.road_numbers.insert(rid, self.roads_created);
+        self.roads_created += 1;
+
         self.electricity.add_object(rid);
         self.electricity.add_edge(src_id, rid);
         self.electricity.add_edge(dst_id, rid);
@@ -861,6 +1212,10 @@ impl Map {
             assert!(self.buildings.contains_key(*b));
         }
 
+        for i in self.road_connections.iter() {
+            assert!(self.intersections.contains_key(*i));
+        }
+
         for road in self.roads.values() {
             log::debug!("{:?}", road.id);
             let src = self.intersections.get(road.src).unwrap();
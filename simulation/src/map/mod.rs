@@ -21,37 +21,59 @@ pub use objects::*;
 pub mod procgen {
     mod building;
     pub mod heightmap;
+    mod mapgen;
     mod presets;
 
     pub use building::*;
+    pub use mapgen::*;
     pub use presets::*;
 }
 
+mod bulldoze;
 mod change_detection;
+mod districts;
+mod edit_history;
 mod electricity_cache;
 mod height_override;
+mod landmarks;
+mod lane_speeds;
 mod light_policy;
 #[allow(clippy::module_inception)]
 mod map;
+mod names;
+mod noise;
 mod pathfinding;
 mod serializing;
+mod shoreline;
 mod spatial_map;
 pub mod terrain;
 mod traffic_control;
 mod traversable;
 mod turn_policy;
+mod water_grid;
+mod zone_grid;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use bulldoze::*;
 pub use change_detection::*;
+pub use districts::*;
+pub use edit_history::*;
 pub use electricity_cache::*;
+pub use landmarks::*;
+pub use lane_speeds::*;
 pub use light_policy::*;
 pub use map::*;
+pub use names::*;
+pub use noise::*;
+pub use shoreline::*;
 pub use spatial_map::*;
 pub use terrain::*;
 pub use traffic_control::*;
 pub use traversable::*;
 pub use turn_policy::*;
+pub use water_grid::*;
+pub use zone_grid::*;
 
 pub use ::pathfinding as pathfinding_crate;
 
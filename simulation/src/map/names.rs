@@ -0,0 +1,57 @@
+//! Names given to the roads and districts until the players rename them.
+//! They only depend on a number, so the same road or district keeps its name after loading a save.
+
+const ROAD_NAMES: &[&str] = &[
+    "Elm", "Oak", "Maple", "Pine", "Cedar", "Birch", "Willow", "Ash", "Chestnut", "Walnut",
+    "Spruce", "Poplar", "Cherry", "Magnolia", "Laurel", "Hawthorn", "Juniper", "Hazel", "Holly",
+    "Linden", "Park", "Lake", "River", "Hill", "Meadow", "Church", "Mill", "Market", "Station",
+    "Bridge", "Spring", "Highland", "Sunset", "Valley", "Forest", "Garden", "Harbor", "Orchard",
+    "Prospect", "Union",
+];
+
+const ROAD_SUFFIXES: &[&str] = &[
+    "Street",
+    "Avenue",
+    "Road",
+    "Lane",
+    "Drive",
+    "Boulevard",
+    "Way",
+    "Place",
+];
+
+/// "Elm Street", "Oak Street"... then "Elm Avenue" once every name was used with "Street".
+/// Once all the combinations are used, they come back with a number: "Elm Street 2".
+pub fn generated_road_name(n: u32) -> String {
+    let n = n as usize;
+    let name = ROAD_NAMES[n % ROAD_NAMES.len()];
+    let n = n / ROAD_NAMES.len();
+    let suffix = ROAD_SUFFIXES[n % ROAD_SUFFIXES.len()];
+    match n / ROAD_SUFFIXES.len() {
+        0 => format!("{} {}", name, suffix),
+        round => format!("{} {} {}", name, suffix, round + 1),
+    }
+}
+
+pub fn generated_district_name(n: u32) -> String {
+    format!("District {}", n)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{generated_road_name, ROAD_NAMES, ROAD_SUFFIXES};
+
+    #[test]
+    fn road_names_are_unique() {
+        assert_eq!(generated_road_name(0), "Elm Street");
+        assert_eq!(generated_road_name(1), "Oak Street");
+        assert_eq!(generated_road_name(ROAD_NAMES.len() as u32), "Elm Avenue");
+
+        let n = 3 * ROAD_NAMES.len() * ROAD_SUFFIXES.len();
+        let names: BTreeSet<String> = (0..n as u32).map(generated_road_name).collect();
+        assert_eq!(names.len(), n);
+        assert!(names.contains("Union Place 3"));
+    }
+}
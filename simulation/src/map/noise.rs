@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{vec2, Shape, Vec2, AABB};
+
+use crate::map::{BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind, Road, RoadID};
+
+/// Side of a noise cell in meters
+pub const NOISE_CELL_SIZE: f32 = 20.0;
+
+/// Distance in meters from a road after which it cannot be heard
+pub const NOISE_RADIUS: f32 = 100.0;
+
+/// Distance in meters from an airport after which the planes cannot be heard
+pub const AIRPORT_NOISE_RADIUS: f32 = 500.0;
+
+/// Speed limit of a lane emitting one unit of noise when the traffic flows freely
+const REFERENCE_SPEED: f32 = 10.0;
+
+/// Roads whose emission changed by less than this fraction keep their cells as they are
+const EMISSION_TOLERANCE: f32 = 0.1;
+
+/// Cells quieter than this are not stored
+const MIN_NOISE: f32 = 0.01;
+
+/// Noise of a road or an airport and the area it covered when its cells were last computed
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct Emitter {
+    level: f32,
+    bbox: AABB,
+}
+
+/// Noise made by the road traffic and the planes of the airports, sampled on a coarse grid.
+/// Only the cells within earshot of a road or an airport are stored, and they are only recomputed
+/// around the roads that were built, removed or whose traffic changed and around the airports
+/// that were built or removed.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub(crate) struct NoiseMap {
+    /// Indexed by (y, x) like the zoning cells
+    cells: BTreeMap<(i32, i32), f32>,
+    emitters: BTreeMap<RoadID, Emitter>,
+    airports: BTreeMap<BuildingID, Emitter>,
+}
+
+impl NoiseMap {
+    fn cell(pos: Vec2) -> (i32, i32) {
+        (
+            (pos.y / NOISE_CELL_SIZE).floor() as i32,
+            (pos.x / NOISE_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    fn cell_aabb((y, x): (i32, i32)) -> AABB {
+        let ll = vec2(x as f32, y as f32) * NOISE_CELL_SIZE;
+        AABB::new_ll_ur(ll, ll + Vec2::splat(NOISE_CELL_SIZE))
+    }
+
+    fn get(&self, pos: Vec2) -> f32 {
+        self.cells.get(&Self::cell(pos)).copied().unwrap_or(0.0)
+    }
+
+    /// Adds the cells that something covering the area can be heard from within the radius
+    fn cells_around(bbox: AABB, radius: f32, out: &mut BTreeSet<(i32, i32)>) {
+        let (lly, llx) = Self::cell(bbox.ll - Vec2::splat(radius));
+        let (ury, urx) = Self::cell(bbox.ur + Vec2::splat(radius));
+        for y in lly..=ury {
+            for x in llx..=urx {
+                out.insert((y, x));
+            }
+        }
+    }
+}
+
+/// How loud something is heard at the distance when it can't be heard beyond the radius
+fn falloff(dist: f32, radius: f32) -> f32 {
+    let t = (1.0 - dist / radius).max(0.0);
+    t * t
+}
+
+/// Distance from the position to the nearest point of the area, 0 inside of it
+fn distance_to(bbox: AABB, pos: Vec2) -> f32 {
+    let nearest = vec2(
+        pos.x.clamp(bbox.ll.x, bbox.ur.x),
+        pos.y.clamp(bbox.ll.y, bbox.ur.y),
+    );
+    nearest.distance(pos)
+}
+
+impl Map {
+    /// Noise made by the nearby roads and airports at the position, 0 when none can be heard
+    pub fn noise_at(&self, pos: Vec2) -> f32 {
+        self.noise.get(pos)
+    }
+
+    /// The cells where a road or an airport can be heard with their noise
+    pub fn noise_cells(&self) -> impl Iterator<Item = (AABB, f32)> + '_ {
+        self.noise
+            .cells
+            .iter()
+            .map(|(&cell, &noise)| (NoiseMap::cell_aabb(cell), noise))
+    }
+
+    /// Noise emitted by the road, louder with more lanes, faster speed limits and more traffic.
+    /// Congested lanes are packed with vehicles so they count up to twice.
+    pub fn road_noise(&self, road: &Road) -> f32 {
+        road.lanes_iter()
+            .filter(|(_, kind)| kind.vehicles())
+            .filter_map(|(id, _)| self.lanes.get(id))
+            .map(|lane| lane.speed_limit / REFERENCE_SPEED * (1.0 + self.lane_congestion(lane.id)))
+            .sum()
+    }
+
+    /// Recomputes the noise of the cells around the roads and airports that changed since the last update
+    pub fn update_noise(&mut self) {
+        profiling::scope!("map::update_noise");
+        let mut dirty = BTreeSet::new();
+        self.update_airport_noise(&mut dirty);
+
+        let mut changed: Vec<(RoadID, Option<Emitter>)> = Vec::new();
+
+        for road in self.roads.values() {
+            let e = Emitter {
+                level: self.road_noise(road),
+                bbox: road.points.bbox().flatten(),
+            };
+            match self.noise.emitters.get(&road.id) {
+                Some(old)
+                    if old.bbox == e.bbox
+                        && (old.level - e.level).abs() <= old.level * EMISSION_TOLERANCE => {}
+                _ => changed.push((road.id, Some(e))),
+            }
+        }
+        for &id in self.noise.emitters.keys() {
+            if !self.roads.contains_key(id) {
+                changed.push((id, None));
+            }
+        }
+
+        for (id, e) in changed {
+            let old = match e {
+                Some(e) => self.noise.emitters.insert(id, e),
+                None => self.noise.emitters.remove(&id),
+            };
+            for e in old.iter().chain(e.iter()) {
+                NoiseMap::cells_around(e.bbox, NOISE_RADIUS, &mut dirty);
+            }
+        }
+
+        for cell in dirty {
+            let noise = self.compute_noise(NoiseMap::cell_aabb(cell).center());
+            if noise < MIN_NOISE {
+                self.noise.cells.remove(&cell);
+                continue;
+            }
+            self.noise.cells.insert(cell, noise);
+        }
+    }
+
+    /// Keeps the airport emitters in sync with the airports of the map, adding the cells around
+    /// those that were built or removed to the dirty ones
+    fn update_airport_noise(&mut self, dirty: &mut BTreeSet<(i32, i32)>) {
+        let mut changed: Vec<(BuildingID, Option<Emitter>)> = Vec::new();
+        for b in self.buildings.values() {
+            let BuildingKind::Airport(proto) = b.kind else {
+                continue;
+            };
+            if self.noise.airports.contains_key(&b.id) {
+                continue;
+            }
+            let e = Emitter {
+                level: proto.prototype().noise,
+                bbox: b.obb.bbox(),
+            };
+            changed.push((b.id, Some(e)));
+        }
+        for &id in self.noise.airports.keys() {
+            if !self.buildings.contains_key(id) {
+                changed.push((id, None));
+            }
+        }
+
+        for (id, e) in changed {
+            let old = match e {
+                Some(e) => self.noise.airports.insert(id, e),
+                None => self.noise.airports.remove(&id),
+            };
+            for e in old.iter().chain(e.iter()) {
+                NoiseMap::cells_around(e.bbox, AIRPORT_NOISE_RADIUS, dirty);
+            }
+        }
+    }
+
+    fn compute_noise(&self, pos: Vec2) -> f32 {
+        let roads: f32 = self
+            .spatial_map
+            .query_around(pos, NOISE_RADIUS, ProjectFilter::ROAD)
+            .filter_map(|obj| {
+                let ProjectKind::Road(id) = obj else {
+                    return None;
+                };
+                let road = self.roads.get(id)?;
+                let level = self.noise.emitters.get(&id)?.level;
+                let dist = road.points.project_2d(pos).xy().distance(pos);
+                Some(level * falloff(dist, NOISE_RADIUS))
+            })
+            .sum();
+
+        let planes: f32 = self
+            .noise
+            .airports
+            .values()
+            .map(|e| e.level * falloff(distance_to(e.bbox, pos), AIRPORT_NOISE_RADIUS))
+            .sum();
+
+        roads + planes
+    }
+}
@@ -4,7 +4,11 @@ use crate::map::{
 };
 use egui_inspect::debug_inspect_impl;
 use geom::{Color, Polygon, Vec2, Vec3, OBB};
-use prototypes::{BuildingGen, FreightStationPrototypeID, GoodsCompanyID};
+use prototypes::{
+    AirportPrototypeID, BuildingGen, FreightStationPrototypeID, GoodsCompanyID, HarborPrototypeID,
+    HotelPrototypeID, LeisurePrototypeID, PassengerStationPrototypeID, SchoolPrototypeID,
+    WarehousePrototypeID,
+};
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
 
@@ -21,6 +25,13 @@ pub enum BuildingKind {
     RailFreightStation(FreightStationPrototypeID),
     TrainStation,
     ExternalTrading,
+    RailPassengerStation(PassengerStationPrototypeID),
+    Warehouse(WarehousePrototypeID),
+    School(SchoolPrototypeID),
+    Leisure(LeisurePrototypeID),
+    Hotel(HotelPrototypeID),
+    Harbor(HarborPrototypeID),
+    Airport(AirportPrototypeID),
 }
 
 impl BuildingKind {
@@ -34,6 +45,61 @@ impl BuildingKind {
     pub fn is_cached_in_bkinds(&self) -> bool {
         matches!(self, BuildingKind::ExternalTrading)
     }
+
+    /// Whether the building must be built on the shore, as set by its prototype
+    pub fn needs_shoreline(&self) -> bool {
+        match self {
+            BuildingKind::GoodsCompany(id) => id.prototype().shoreline,
+            BuildingKind::Warehouse(id) => id.prototype().shoreline,
+            BuildingKind::School(id) => id.prototype().shoreline,
+            BuildingKind::Leisure(id) => id.prototype().shoreline,
+            BuildingKind::Hotel(id) => id.prototype().shoreline,
+            BuildingKind::Harbor(id) => id.prototype().shoreline,
+            BuildingKind::Airport(id) => id.prototype().shoreline,
+            BuildingKind::House
+            | BuildingKind::RailFreightStation(_)
+            | BuildingKind::RailPassengerStation(_)
+            | BuildingKind::TrainStation
+            | BuildingKind::ExternalTrading => false,
+        }
+    }
+
+    /// Name of the prototype of the building, as used by the milestones and scenarios to lock it
+    pub fn prototype_name(&self) -> Option<&'static str> {
+        let name = match self {
+            BuildingKind::GoodsCompany(id) => &id.prototype().name,
+            BuildingKind::RailFreightStation(id) => &id.prototype().name,
+            BuildingKind::RailPassengerStation(id) => &id.prototype().name,
+            BuildingKind::Warehouse(id) => &id.prototype().name,
+            BuildingKind::School(id) => &id.prototype().name,
+            BuildingKind::Leisure(id) => &id.prototype().name,
+            BuildingKind::Hotel(id) => &id.prototype().name,
+            BuildingKind::Harbor(id) => &id.prototype().name,
+            BuildingKind::Airport(id) => &id.prototype().name,
+            BuildingKind::House | BuildingKind::TrainStation | BuildingKind::ExternalTrading => {
+                return None
+            }
+        };
+        Some(name.as_str())
+    }
+
+    /// Largest difference of height allowed under the building, as set by its prototype
+    pub fn max_height_difference(&self) -> Option<f32> {
+        match self {
+            BuildingKind::GoodsCompany(id) => id.prototype().max_height_difference,
+            BuildingKind::Warehouse(id) => id.prototype().max_height_difference,
+            BuildingKind::School(id) => id.prototype().max_height_difference,
+            BuildingKind::Leisure(id) => id.prototype().max_height_difference,
+            BuildingKind::Hotel(id) => id.prototype().max_height_difference,
+            BuildingKind::Harbor(id) => id.prototype().max_height_difference,
+            BuildingKind::Airport(id) => id.prototype().max_height_difference,
+            BuildingKind::House
+            | BuildingKind::RailFreightStation(_)
+            | BuildingKind::RailPassengerStation(_)
+            | BuildingKind::TrainStation
+            | BuildingKind::ExternalTrading => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +155,7 @@ impl Building {
         obb: OBB,
         kind: BuildingKind,
         gen: BuildingGen,
+        floors: u32,
         zone: Option<Zone>,
         mut connected_road: Option<RoadID>,
     ) -> Option<BuildingID> {
@@ -99,7 +166,7 @@ impl Building {
         let r = common::rand::rand2(obb.center().x, obb.center().y).to_bits();
 
         let (mut mesh, door_pos) = match gen {
-            BuildingGen::House => gen_exterior_house(size, r as u64),
+            BuildingGen::House => gen_exterior_house(size, r as u64, floors),
             BuildingGen::Farm => gen_exterior_farm(size, r as u64),
             BuildingGen::CenteredDoor {
                 vertical_factor, ..
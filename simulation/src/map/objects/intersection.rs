@@ -1,6 +1,6 @@
 use crate::map::{
-    Intersections, LaneID, LaneKind, Lanes, LightPolicy, Road, RoadID, Roads, SpatialMap,
-    TraverseDirection, Turn, TurnID, TurnPolicy,
+    Intersections, LaneID, LaneKind, Lanes, LightPolicy, LightTiming, Road, RoadID, Roads,
+    SpatialMap, TraverseDirection, Turn, TurnID, TurnPolicy,
 };
 use geom::{pseudo_angle, Circle};
 use geom::{Vec2, Vec3};
@@ -32,6 +32,13 @@ pub struct Intersection {
 
     pub turn_policy: TurnPolicy,
     pub light_policy: LightPolicy,
+
+    /// Replaces the automatic traffic light timings
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub light_timing: Option<LightTiming>,
+    /// Turns that are not generated even though the turn policy allows them
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub disabled_turns: BTreeSet<TurnID>,
 }
 
 impl Intersection {
@@ -44,6 +51,8 @@ impl Intersection {
             roads: Default::default(),
             turn_policy: Default::default(),
             light_policy: Default::default(),
+            light_timing: None,
+            disabled_turns: Default::default(),
         });
         spatial.insert(&store[id]);
         id
@@ -89,10 +98,15 @@ impl Intersection {
     }
 
     pub fn update_turns(&mut self, lanes: &Lanes, roads: &Roads) {
-        self.turns = self
-            .turn_policy
-            .generate_turns(self, lanes, roads)
+        let generated = self.turn_policy.generate_turns(self, lanes, roads);
+
+        // forget the disabled turns that cannot be generated anymore, their lanes were rebuilt
+        self.disabled_turns
+            .retain(|id| generated.iter().any(|(gen_id, _)| gen_id == id));
+
+        self.turns = generated
             .into_iter()
+            .filter(|(id, _)| !self.disabled_turns.contains(id))
             .map(|(id, kind)| Turn::new(id, kind))
             .collect();
 
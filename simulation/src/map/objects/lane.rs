@@ -79,7 +79,7 @@ pub struct Lane {
     pub dist_from_bottom: f32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct LanePattern {
     pub lanes_forward: Vec<(LaneKind, f32)>,
     pub lanes_backward: Vec<(LaneKind, f32)>,
@@ -101,6 +101,40 @@ impl LanePattern {
     pub fn width(&self) -> f32 {
         self.lanes().map(|(kind, _, _)| kind.width()).sum()
     }
+
+    /// Whether vehicles and trains can only go one way, sidewalks are not taken into account
+    pub fn is_one_way(&self) -> bool {
+        let has_traffic = |lanes: &[(LaneKind, f32)]| lanes.iter().any(|(k, _)| k.needs_arrows());
+        has_traffic(&self.lanes_forward) != has_traffic(&self.lanes_backward)
+    }
+
+    /// Which of the [`prototypes::ROAD_TYPES`] the pattern is, as named by the milestones.
+    /// Patterns in between are counted as the widest type they resemble.
+    pub fn road_type(&self) -> &'static str {
+        let count = |kind| {
+            let n = |lanes: &[(LaneKind, f32)]| lanes.iter().filter(|(k, _)| *k == kind).count();
+            n(&self.lanes_forward).max(n(&self.lanes_backward))
+        };
+        if count(LaneKind::Rail) > 0 {
+            "rail"
+        } else if count(LaneKind::Driving) >= 3 {
+            "highway"
+        } else if count(LaneKind::Walking) == 0 {
+            "drive"
+        } else if count(LaneKind::Driving) == 2 {
+            "avenue"
+        } else {
+            "street"
+        }
+    }
+
+    /// The same lanes going the other way
+    pub fn flipped(&self) -> LanePattern {
+        LanePattern {
+            lanes_forward: self.lanes_backward.clone(),
+            lanes_backward: self.lanes_forward.clone(),
+        }
+    }
 }
 
 #[derive(PartialEq, Copy, Clone, Inspect)]
@@ -20,6 +20,44 @@ pub enum RoadSegmentKind {
     Curved((Vec2, Vec2)), // The two derivatives for the spline
 }
 
+/// Below this depth under the terrain, a road is a tunnel
+pub const TUNNEL_DEPTH: f32 = 3.0;
+
+/// Above this height over the terrain, a road is held by pillars
+pub const PILLAR_MIN_HEIGHT: f32 = 2.0;
+
+/// Height of the roads above the water surface, crossing water makes them bridges
+pub const BRIDGE_WATER_CLEARANCE: f32 = 5.0;
+
+/// Distance between the samples used to find out how a road is held
+const CLEARANCE_STEP: f32 = 10.0;
+
+/// How the road is held relative to the terrain
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoadStructure {
+    #[default]
+    Ground,
+    /// Some of the road is held by pillars
+    Bridge,
+    /// Some of the road goes under the terrain
+    Tunnel,
+}
+
+impl RoadStructure {
+    pub fn of(points: &PolyLine3, env: &Environment) -> Self {
+        let mut structure = RoadStructure::Ground;
+        for (_, clearance) in Road::clearance(points, env) {
+            if clearance < -TUNNEL_DEPTH {
+                return RoadStructure::Tunnel;
+            }
+            if clearance > PILLAR_MIN_HEIGHT {
+                structure = RoadStructure::Bridge;
+            }
+        }
+        structure
+    }
+}
+
 impl RoadSegmentKind {
     pub fn from_elbow(from: Vec2, to: Vec2, elbow: Vec2) -> RoadSegmentKind {
         RoadSegmentKind::Curved((
@@ -29,6 +67,24 @@ impl RoadSegmentKind {
     }
 }
 
+/// A marked pedestrian crossing in the middle of a road, vehicles yield to pedestrians on it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Crossing {
+    /// Distance along the road points from src
+    pub dist: f32,
+    /// From the forward sidewalk to the backward sidewalk
+    pub points: PolyLine3,
+}
+
+/// A crossing of a road, along with the two sidewalks it connects
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CrossingID {
+    pub road: RoadID,
+    pub idx: u16,
+    pub src: LaneID,
+    pub dst: LaneID,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Road {
     pub id: RoadID,
@@ -45,6 +101,12 @@ pub struct Road {
 
     pub connected_buildings: Vec<BuildingID>,
 
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub crossings: Vec<Crossing>,
+
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub structure: RoadStructure,
+
     src_interface: f32,
     dst_interface: f32,
 
@@ -104,6 +166,8 @@ impl Road {
             interfaced_points: PolyLine3::new(vec![points.first()]),
             points,
             connected_buildings: vec![],
+            crossings: vec![],
+            structure: RoadStructure::Ground,
         });
         #[allow(clippy::indexing_slicing)]
         let road = &mut roads[id];
@@ -152,6 +216,42 @@ impl Road {
         })
     }
 
+    /// The sidewalks connected by the mid-road crossings, forward side first
+    pub fn crossing_sidewalks(&self) -> Option<(LaneID, LaneID)> {
+        let pair = self.sidewalks(self.src);
+        Some((pair.outgoing?, pair.incoming?))
+    }
+
+    pub fn crossing_id(&self, idx: usize) -> Option<CrossingID> {
+        self.crossings.get(idx)?;
+        let (src, dst) = self.crossing_sidewalks()?;
+        Some(CrossingID {
+            road: self.id,
+            idx: idx as u16,
+            src,
+            dst,
+        })
+    }
+
+    pub fn crossing_ids(&self) -> impl Iterator<Item = CrossingID> + '_ {
+        (0..self.crossings.len()).flat_map(|idx| self.crossing_id(idx))
+    }
+
+    /// Places the crossings between the sidewalks, they are removed if a sidewalk is missing
+    pub(crate) fn update_crossings(&mut self, lanes: &Lanes) {
+        let Some((fw, bw)) = self.crossing_sidewalks() else {
+            self.crossings.clear();
+            return;
+        };
+        let (Some(fw), Some(bw)) = (lanes.get(fw), lanes.get(bw)) else {
+            return;
+        };
+        for c in &mut self.crossings {
+            let p = self.points.point_along(c.dist);
+            c.points = PolyLine3::new(vec![fw.points.project(p), bw.points.project(p)]);
+        }
+    }
+
     pub fn has_sidewalks(&self) -> bool {
         self.lanes_forward
             .iter()
@@ -203,6 +303,7 @@ impl Road {
         env: &Environment,
     ) {
         self.update_interfaced_points(env);
+        self.structure = RoadStructure::of(&self.points, env);
         for (id, _) in self.lanes_iter() {
             let l = unwrap_contlog!(lanes.get_mut(id), "lane in road does not exist anymore");
             l.gen_pos(self);
@@ -210,6 +311,7 @@ impl Road {
                 parking.generate_spots(l);
             }
         }
+        self.update_crossings(lanes);
         parking.clean_reuse();
     }
 
@@ -273,6 +375,19 @@ impl Road {
             })
     }
 
+    /// Height of the road above the terrain every few meters, negative when underground
+    pub fn clearance<'a>(
+        points: &'a PolyLine3,
+        env: &'a Environment,
+    ) -> impl Iterator<Item = (Vec3, f32)> + 'a {
+        points
+            .equipoints_dir(CLEARANCE_STEP, true)
+            .filter_map(move |(pos, _)| {
+                let h = env.true_height(pos.xy())?;
+                Some((pos, pos.z - ROAD_Z_OFFSET - h))
+            })
+    }
+
     pub fn points(&self) -> &PolyLine3 {
         &self.points
     }
@@ -303,13 +418,15 @@ impl Road {
     // Run an algorithm to find the height of the road at each point
     // This is not easy because the terrain can take many shapes
     // The algorithm is as follow:
-    // - First compute the terrain contour every meter
+    // - First compute the terrain contour every meter, raised above the water
     // - Then find out which points are airborn (according to maxslope)
     // - Then find the interface points where points become airborn
     // - Then linear interpolate the points between the interface points
     // - Then smooth out the result to avoid huge derivative changes
     // - Then simplify the result to avoid too many points
     //
+    // Roads starting or ending deep under the terrain are tunnels, they go straight through it
+    //
     // maxslope is the maximum meter of height difference per meter of distance (1.0 is a 45° slope)
     pub fn heightfinder(
         p: &PolyLine,
@@ -318,6 +435,12 @@ impl Road {
         maxslope: f32,
         env: &Environment,
     ) -> (PolyLine3, Option<PointGenerateError>) {
+        let underground =
+            |pos: Vec2, height: f32| env.height(pos).map_or(false, |h| height < h - TUNNEL_DEPTH);
+        if underground(p.first(), start_height) || underground(p.last(), end_height) {
+            return Self::tunnel_points(p, start_height, end_height, maxslope);
+        }
+
         // first calculate the contour
 
         let mut contour = Vec::with_capacity(p.length() as usize + 2);
@@ -331,10 +454,14 @@ impl Road {
             )
             .chain(std::iter::once(p.last()))
         {
-            let h = env.height(pos).unwrap_or_else(|| {
-                height_error = true;
-                0.0
-            });
+            let h = match env.true_height(pos) {
+                Some(h) if h < 0.0 => BRIDGE_WATER_CLEARANCE,
+                Some(h) => h,
+                None => {
+                    height_error = true;
+                    0.0
+                }
+            };
             contour.push(h);
             points.push(pos.z(h));
         }
@@ -459,6 +586,36 @@ impl Road {
         (points, None)
     }
 
+    fn tunnel_points(
+        p: &PolyLine,
+        start_height: f32,
+        end_height: f32,
+        maxslope: f32,
+    ) -> (PolyLine3, Option<PointGenerateError>) {
+        let l = p.length();
+        let height = |d: f32| {
+            let t = if l > 0.0 { (d / l).min(1.0) } else { 1.0 };
+            start_height + (end_height - start_height) * t + ROAD_Z_OFFSET
+        };
+        let mut points = PolyLine3::new(
+            std::iter::once(p.first().z(height(0.0)))
+                .chain(
+                    p.points_dirs_along((1..l as u32).map(|v| v as f32))
+                        .zip(1..)
+                        .map(|((pos, _), d)| pos.z(height(d as f32))),
+                )
+                .chain(std::iter::once(p.last().z(height(l))))
+                .collect::<Vec<_>>(),
+        );
+        points.simplify(Degrees(1.0).into(), 1.0, 100.0);
+
+        if (end_height - start_height).abs() > maxslope * l {
+            return (points, Some(PointGenerateError::TooSteep));
+        }
+
+        (points, None)
+    }
+
     pub fn generate_points(
         from: Vec3,
         to: Vec3,
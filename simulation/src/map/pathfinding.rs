@@ -1,5 +1,6 @@
 use crate::map::{
-    LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection, TraverseKind, TurnID,
+    IntersectionID, LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection,
+    TraverseKind, TurnID,
 };
 use common::hash_u64;
 use geom::{PolyLine3, Vec3};
@@ -21,7 +22,7 @@ pub trait Pathfinder {
     fn authorized_lane(&self, kind: LaneKind) -> bool;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PathKind {
     Pedestrian,
     Vehicle,
@@ -71,6 +72,8 @@ impl Pathfinder for PathKind {
 struct PedestrianPath;
 
 impl Pathfinder for PedestrianPath {
+    /// Walking along a lane is paid when leaving it, so that lanes before and after
+    /// a mid-road crossing are only paid for the part that is walked
     fn path(
         &self,
         map: &Map,
@@ -80,59 +83,113 @@ impl Pathfinder for PedestrianPath {
     ) -> Option<Vec<Traversable>> {
         let inters = &map.intersections;
         let lanes = &map.lanes;
+        let roads = &map.roads;
 
         let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
 
         let heuristic = |t: &Traversable| {
-            let pos = unwrap_ret!(
-                inters.get(unwrap_ret!(
-                    t.destination_intersection(lanes),
-                    OrderedFloat(f32::INFINITY)
-                )),
-                OrderedFloat(f32::INFINITY)
-            )
-            .pos;
+            let pos = match t.kind {
+                TraverseKind::Crossing(_) => t.points(map).map(|p| p.last()),
+                _ => t
+                    .destination_intersection(lanes)
+                    .and_then(|x| inters.get(x))
+                    .map(|i| i.pos),
+            };
+            let pos = unwrap_ret!(pos, OrderedFloat(f32::INFINITY));
 
             OrderedFloat(pos.distance(end_pos) * 1.3) // Inexact but (much) faster
         };
 
         let successors = |t: &Traversable| {
-            let inter = t
-                .destination_intersection(lanes)
-                .and_then(|x| inters.get(x));
-            let lane_from_id = t.destination_lane();
-            let lane_from = lanes.get(lane_from_id);
-
-            let lane_travers = inter.zip(lane_from).map(|(inter, lane_from)| {
-                (
-                    Traversable::new(
-                        TraverseKind::Lane(lane_from_id),
-                        lane_from.dir_from(inter.id),
-                    ),
-                    OrderedFloat(lane_from.points.length()),
-                )
-            });
-
-            inter
-                .into_iter()
-                .flat_map(move |inter| {
-                    inter.turns_from(lane_from_id).map(|(x, dir)| {
-                        (
-                            Traversable::new(TraverseKind::Turn(x), dir),
-                            OrderedFloat(0.001),
-                        )
-                    })
-                })
-                .chain(lane_travers)
+            let mut next = Vec::new();
+            match t.kind {
+                TraverseKind::Turn(_) => {
+                    let inter = t.destination_intersection(lanes);
+                    let lane_id = t.destination_lane();
+                    if let Some((inter, lane)) = inter.zip(lanes.get(lane_id)) {
+                        next.push((
+                            Traversable::new(TraverseKind::Lane(lane_id), lane.dir_from(inter)),
+                            OrderedFloat(0.0),
+                        ));
+                    }
+                }
+                TraverseKind::Lane(lane_id) => {
+                    let Some(lane) = lanes.get(lane_id) else {
+                        return next;
+                    };
+                    let len = lane.points.length();
+                    let inter = unwrap_ret!(t.destination_intersection(lanes), next);
+
+                    push_turns_from(map, inter, lane_id, len, &mut next);
+                    next.push((
+                        Traversable::new(TraverseKind::Lane(lane_id), lane.dir_from(inter)),
+                        OrderedFloat(len),
+                    ));
+
+                    let Some(road) = roads.get(lane.parent) else {
+                        return next;
+                    };
+                    for id in road.crossing_ids() {
+                        let dir = if id.src == lane_id {
+                            TraverseDirection::Forward
+                        } else if id.dst == lane_id {
+                            TraverseDirection::Backward
+                        } else {
+                            continue;
+                        };
+                        let crossing = Traversable::new(TraverseKind::Crossing(id), dir);
+                        let Some(points) = crossing.points(map) else {
+                            continue;
+                        };
+                        let along = lane.points.length_at_proj(points.first());
+                        let walked = match t.dir {
+                            TraverseDirection::Forward => along,
+                            TraverseDirection::Backward => len - along,
+                        };
+                        next.push((crossing, OrderedFloat(walked + points.length())));
+                    }
+                }
+                TraverseKind::Crossing(_) => {
+                    let lane_id = t.destination_lane();
+                    let Some((points, lane)) = t.points(map).zip(lanes.get(lane_id)) else {
+                        return next;
+                    };
+                    let along = lane.points.length_at_proj(points.last());
+                    let len = lane.points.length();
+                    push_turns_from(map, lane.dst, lane_id, len - along, &mut next);
+                    push_turns_from(map, lane.src, lane_id, along, &mut next);
+                }
+            }
+            next
         };
 
         let has_arrived = |p: &Traversable| match p.kind {
             TraverseKind::Lane(id) => id == end,
             TraverseKind::Turn(_) => false,
+            TraverseKind::Crossing(_) => p.destination_lane() == end,
         };
 
-        pathfinding::directed::astar::astar(&start, successors, heuristic, has_arrived)
-            .map(|(v, _)| v)
+        let (path, _) =
+            pathfinding::directed::astar::astar(&start, successors, heuristic, has_arrived)?;
+
+        // the lane walked after a crossing is implied by the search, add it back
+        let mut full = Vec::with_capacity(path.len() + 2);
+        for (i, t) in path.iter().enumerate() {
+            full.push(*t);
+            if matches!(t.kind, TraverseKind::Crossing(_)) {
+                let lane_id = t.destination_lane();
+                let lane = lanes.get(lane_id)?;
+                let dir = match path.get(i + 1) {
+                    Some(&Traversable {
+                        kind: TraverseKind::Turn(id),
+                        ..
+                    }) if id.parent == lane.src => TraverseDirection::Backward,
+                    _ => TraverseDirection::Forward,
+                };
+                full.push(Traversable::new(TraverseKind::Lane(lane_id), dir));
+            }
+        }
+        Some(full)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -149,7 +206,11 @@ impl Pathfinder for PedestrianPath {
             .get(seg_start.min(seg_end)..seg_start.max(seg_end))?;
         let mut v = Vec::with_capacity(3 + segs.len());
         v.push(p_start);
-        v.extend_from_slice(segs);
+        if seg_start <= seg_end {
+            v.extend_from_slice(segs);
+        } else {
+            v.extend(segs.iter().rev());
+        }
         v.push(p_end);
         v.push(end);
         Some(PolyLine3::new(v))
@@ -160,6 +221,25 @@ impl Pathfinder for PedestrianPath {
     }
 }
 
+/// Turns that can be taken from the lane at the intersection, after walking `dist` on the lane
+fn push_turns_from(
+    map: &Map,
+    inter: IntersectionID,
+    lane: LaneID,
+    dist: f32,
+    next: &mut Vec<(Traversable, OrderedFloat<f32>)>,
+) {
+    let Some(inter) = map.intersections.get(inter) else {
+        return;
+    };
+    next.extend(inter.turns_from(lane).map(|(x, dir)| {
+        (
+            Traversable::new(TraverseKind::Turn(x), dir),
+            OrderedFloat(dist + 0.001),
+        )
+    }));
+}
+
 struct RailPath;
 
 impl Pathfinder for RailPath {
@@ -201,50 +281,12 @@ impl Pathfinder for CarPath {
 
         let start_lane = start.destination_lane();
 
-        let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
-
-        let dummy = LaneID::null();
-
-        const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
-
-        let heuristic = |&p: &LaneID| {
-            let pos = unwrap_ret!(
-                inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
-                OrderedFloat(f32::INFINITY)
-            )
-            .pos;
-            OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
-        };
-
-        let base_random = hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
-
-        let successors = move |&p: &LaneID| {
-            let l;
-            let p = if p == dummy {
-                l = lanes.get(start_lane);
-                start_lane
-            } else {
-                l = lanes.get(p);
-                p
-            };
-            l.and_then(move |x| inters.get(x.dst))
-                .into_iter()
-                .flat_map(move |inter| {
-                    inter.turns_from(p).map(move |(x, _)| {
-                        let mut cost = f32::INFINITY;
-
-                        if let Some(l) = lanes.get(x.dst) {
-                            cost = l.points.length() / l.speed_limit;
-                            cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
-                        }
-
-                        (x.dst, OrderedFloat(cost))
-                    })
-                })
+        let heuristic = if map.landmark_routing {
+            RouteHeuristic::Landmarks
+        } else {
+            RouteHeuristic::Distance
         };
-
-        let (v, _) =
-            pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
+        let (v, _) = car_route(map, tick, start_lane, end, heuristic)?;
 
         let mut path = Vec::with_capacity(v.len() * 2);
         path.push(start);
@@ -297,3 +339,82 @@ impl Pathfinder for CarPath {
         matches!(kind, LaneKind::Driving | LaneKind::Bus)
     }
 }
+
+/// Estimate of the remaining travel time guiding the vehicle routes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum RouteHeuristic {
+    /// Lower bound from the travel times to the landmarks of the map, finds the fastest route
+    Landmarks,
+    /// Straight distance at the default speed limit, inexact but needs no preprocessing
+    Distance,
+    /// Explores every direction, only useful as a reference
+    None,
+}
+
+/// Lanes driven after the start lane to reach the end lane, and the travel time.
+/// The travel times are slightly randomized per tick so that vehicles spread on similar routes.
+pub(crate) fn car_route(
+    map: &Map,
+    tick: Tick,
+    start_lane: LaneID,
+    end: LaneID,
+    heuristic: RouteHeuristic,
+) -> Option<(Vec<LaneID>, f32)> {
+    let inters = &map.intersections;
+    let lanes = &map.lanes;
+
+    let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
+
+    let dummy = LaneID::null();
+
+    const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
+
+    let target = match heuristic {
+        RouteHeuristic::Landmarks => map.landmarks().target(end),
+        _ => None,
+    };
+
+    let estimate = |&p: &LaneID| match heuristic {
+        RouteHeuristic::Landmarks => OrderedFloat(target.as_ref().map_or(0.0, |t| t.estimate(p))),
+        RouteHeuristic::Distance => {
+            let pos = unwrap_ret!(
+                inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
+                OrderedFloat(f32::INFINITY)
+            )
+            .pos;
+            OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
+        }
+        RouteHeuristic::None => OrderedFloat(0.0),
+    };
+
+    let base_random = hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
+
+    let successors = move |&p: &LaneID| {
+        let l;
+        let p = if p == dummy {
+            l = lanes.get(start_lane);
+            start_lane
+        } else {
+            l = lanes.get(p);
+            p
+        };
+        l.and_then(move |x| inters.get(x.dst))
+            .into_iter()
+            .flat_map(move |inter| {
+                inter.turns_from(p).map(move |(x, _)| {
+                    let mut cost = f32::INFINITY;
+
+                    if let Some(l) = lanes.get(x.dst) {
+                        cost = map.lane_travel_time(l);
+                        cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
+                    }
+
+                    (x.dst, OrderedFloat(cost))
+                })
+            })
+    };
+
+    let (v, cost) =
+        pathfinding::directed::astar::astar(&dummy, successors, estimate, |p| *p == end)?;
+    Some((v, cost.0))
+}
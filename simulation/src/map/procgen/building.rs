@@ -9,6 +9,9 @@ pub struct ColoredMesh {
     pub faces: Vec<(Vec<Vec3>, LinearColor)>,
 }
 
+/// Height of each floor above the ground floor of a house
+const FLOOR_HEIGHT: f32 = 3.0;
+
 impl ColoredMesh {
     pub fn bbox(&self) -> AABB {
         let (ll, ur) = unwrap_or!(
@@ -27,7 +30,8 @@ impl ColoredMesh {
     }
 }
 
-pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
+/// A house on a lot of the given size, with more floors for the richer variants
+pub fn gen_exterior_house(size: f32, seed: u64, floors: u32) -> (ColoredMesh, Vec2) {
     let mut retry_cnt = 0;
     'retry: loop {
         let mut ri = 0.0;
@@ -138,7 +142,7 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
         let mut roofs = ColoredMesh::default();
         let roof_col = LinearColor::from(crate::colors().roof_col);
 
-        let height = 4.0 + gen_range(0.0, 2.0);
+        let height = 4.0 + gen_range(0.0, 2.0) + FLOOR_HEIGHT * floors.saturating_sub(1) as f32;
 
         for mut face in faces {
             if face.len() < 3 {
@@ -188,7 +192,7 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
 ///     |
 pub fn gen_exterior_farm(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
     let h_size = 30.0;
-    let (mut mesh, mut door_pos) = gen_exterior_house(h_size, seed);
+    let (mut mesh, mut door_pos) = gen_exterior_house(h_size, seed, 1);
 
     let gen_range = |a, b| -> f32 { common::rand::rand(seed as f32 + 7.0) * (b - a) + a };
 
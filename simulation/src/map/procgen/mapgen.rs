@@ -0,0 +1,164 @@
+use geom::{simplex_noise, vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::map::procgen::heightmap;
+use crate::map::TerrainChunkID;
+
+/// Most rivers a map can have
+pub const MAX_RIVERS: u8 = 8;
+
+/// Height in meters of the highest mountains
+const MOUNTAINS_HEIGHT: f32 = 400.0;
+/// Depth in meters of the middle of the rivers
+const RIVER_DEPTH: f32 = 8.0;
+/// Width in meters of the valleys the rivers dig in the mountains, on each side
+const RIVER_VALLEY: f32 = 600.0;
+/// Frequency of the meanders of the rivers, per meter
+const MEANDER_FREQ: f32 = 1.0 / 3000.0;
+
+/// Parameters of the generation of the terrain.
+/// The same parameters and map size always generate the same terrain.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapGenParams {
+    /// Seed 0 generates the terrain of the versions before the parameters
+    pub seed: u32,
+    /// From 0, the land is flat, to 1, high mountains
+    pub mountains: f32,
+    /// Multiplier of the density of the forests
+    pub forests: f32,
+    /// Rivers crossing the map, at most [`MAX_RIVERS`]
+    pub rivers: u8,
+}
+
+impl Default for MapGenParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            mountains: 0.0,
+            forests: 1.0,
+            rivers: 0,
+        }
+    }
+}
+
+/// Generates the heights and the trees of a map of a given size
+pub struct MapGen {
+    params: MapGenParams,
+    /// Size of the map in meters
+    size: Vec2,
+    /// Offset of the noise of the heights, in the coordinates of the noise
+    height_offset: Vec2,
+    /// Offset of the noise of the forests, in meters
+    tree_offset: Vec2,
+    rivers: Vec<River>,
+}
+
+/// A river crossing the map from one side to the other, meandering around a straight line
+struct River {
+    /// Flows from west to east, or else from south to north
+    along_x: bool,
+    /// Coordinate of the straight line across the flow
+    center: f32,
+    /// How far the meanders go from the line
+    amplitude: f32,
+    /// Offset of the noise of the meanders
+    phase: f32,
+    half_width: f32,
+}
+
+impl River {
+    /// Distance in meters from the middle of the river, approximated from the slope of the meanders
+    fn distance(&self, p: Vec2) -> f32 {
+        let (along, across) = if self.along_x { (p.x, p.y) } else { (p.y, p.x) };
+        let (n, grad) = simplex_noise(vec2(along * MEANDER_FREQ + self.phase, self.phase));
+        let middle = self.center + self.amplitude * n;
+        let slope = self.amplitude * grad.x * MEANDER_FREQ;
+        (across - middle).abs() / (1.0 + slope * slope).sqrt()
+    }
+}
+
+impl MapGen {
+    /// A map of `w` by `h` chunks
+    pub fn new(params: &MapGenParams, w: u16, h: u16) -> Self {
+        let mut rng = common::rand::gen(params.seed as u64);
+        let mut offset = |scale: f32| {
+            if params.seed == 0 {
+                return Vec2::ZERO;
+            }
+            vec2(rng.next_f32(), rng.next_f32()) * scale
+        };
+        let height_offset = offset(100.0);
+        let tree_offset = offset(100000.0);
+
+        let size = vec2(w as f32, h as f32) * TerrainChunkID::SIZE_F32;
+        let rivers = (0..params.rivers.min(MAX_RIVERS))
+            .map(|i| {
+                let along_x = i % 2 == 0;
+                let side = if along_x { size.y } else { size.x };
+                River {
+                    along_x,
+                    center: side * (0.15 + 0.7 * rng.next_f32()),
+                    amplitude: side * 0.3,
+                    phase: rng.next_f32() * 100.0,
+                    half_width: 20.0 + 30.0 * rng.next_f32(),
+                }
+            })
+            .collect();
+
+        Self {
+            params: *params,
+            size,
+            height_offset,
+            tree_offset,
+            rivers,
+        }
+    }
+
+    /// Height of the terrain in meters, negative under water
+    pub fn height(&self, p: Vec2) -> f32 {
+        let rh = heightmap::height(p, self.height_offset).0 - 0.12;
+        let mut h = if rh > 0.0 {
+            MOUNTAINS_HEIGHT * self.params.mountains * rh * rh
+        } else {
+            1000.0 * rh
+        };
+
+        for river in &self.rivers {
+            let d = river.distance(p);
+            if h > 0.0 {
+                let t = ((d - river.half_width) / RIVER_VALLEY).clamp(0.0, 1.0);
+                h *= t * t * (3.0 - 2.0 * t);
+            }
+            if d < river.half_width {
+                let t = d / river.half_width;
+                h = h.min(-RIVER_DEPTH * (1.0 - t * t));
+            }
+        }
+        h
+    }
+
+    /// Chance of a tree to grow, on land
+    pub fn tree_density(&self, p: Vec2) -> f32 {
+        heightmap::tree_density(p + self.tree_offset) * self.params.forests
+    }
+
+    /// Height and density of the trees at `res` by `res` points evenly covering the map, row by row
+    /// from the north like an image. Much faster than generating the map.
+    pub fn preview(&self, res: usize) -> Vec<(f32, f32)> {
+        let mut samples = Vec::with_capacity(res * res);
+        for y in 0..res {
+            for x in 0..res {
+                let uv = vec2(x as f32 + 0.5, res as f32 - y as f32 - 0.5) / res as f32;
+                let p = uv * self.size;
+                let h = self.height(p);
+                let trees = if h >= 0.0 {
+                    self.tree_density(p).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                samples.push((h, trees));
+            }
+        }
+        samples
+    }
+}
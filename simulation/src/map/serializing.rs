@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+use std::collections::BTreeMap;
 
 use crate::map::{
-    BuildingID, Buildings, ElectricityCache, Environment, Intersections, Lanes, Lots, Map,
-    ParkingSpots, Roads, SpatialMap,
+    BuildingID, Buildings, Districts, ElectricityCache, Environment, IntersectionID, Intersections,
+    LaneSpeeds, Lanes, Lots, Map, NoiseMap, ParkingSpots, RoadID, Roads, SpatialMap, ZoneGrid,
 };
 
 #[derive(Default, Serialize, Deserialize)]
@@ -15,6 +17,14 @@ pub(crate) struct SerializedMap {
     pub lots: Lots,
     pub environment: Environment,
     pub external_train_stations: Vec<BuildingID>,
+    pub zones: ZoneGrid,
+    pub lane_speeds: LaneSpeeds,
+    pub road_names: BTreeMap<RoadID, String>,
+    pub road_connections: Vec<IntersectionID>,
+    pub districts: Districts,
+    pub noise: NoiseMap,
+    pub road_numbers: BTreeMap<RoadID, u32>,
+    pub roads_created: u32,
 }
 
 impl From<&Map> for SerializedMap {
@@ -28,6 +38,14 @@ impl From<&Map> for SerializedMap {
             lots: m.lots.clone(),
             environment: m.environment.clone(),
             external_train_stations: m.external_train_stations.clone(),
+            zones: m.zones.clone(),
+            lane_speeds: m.lane_speeds.clone(),
+            road_names: m.road_names.clone(),
+            road_connections: m.road_connections.clone(),
+            districts: m.districts.clone(),
+            noise: m.noise.clone(),
+            road_numbers: m.road_numbers.clone(),
+            roads_created: m.roads_created,
         }
     }
 }
@@ -45,13 +63,35 @@ impl From<SerializedMap> for Map {
             parking: sel.parking,
             environment: sel.environment,
             external_train_stations: sel.external_train_stations,
+            zones: sel.zones,
+            lane_speeds: sel.lane_speeds,
+            road_names: sel.road_names,
+            road_connections: sel.road_connections,
+            districts: sel.districts,
+            noise: sel.noise,
+            road_numbers: sel.road_numbers,
+            roads_created: sel.roads_created,
             ..Self::empty()
         };
         m.electricity = ElectricityCache::build(&m);
+        m.number_unnamed_roads();
         m
     }
 }
 
+impl Map {
+    /// Roads of older saves were named after their slot, they get its number to keep their name
+    fn number_unnamed_roads(&mut self) {
+        for id in self.roads.keys() {
+            let number = *self
+                .road_numbers
+                .entry(id)
+                .or_insert(id.data().as_ffi() as u32);
+            self.roads_created = self.roads_created.max(number + 1);
+        }
+    }
+}
+
 fn mk_spatial_map(m: &SerializedMap) -> SpatialMap {
     let mut sm = SpatialMap::default();
     for b in m.buildings.values() {
@@ -0,0 +1,94 @@
+use std::fmt::{Display, Formatter};
+
+use geom::{Vec2, OBB};
+
+use crate::map::terrain::CELL_SIZE;
+use crate::map::{BuildingKind, Map};
+
+/// Distance in meters from the footprint of a building within which water makes it on the shore
+pub const SHORE_REACH: f32 = 20.0;
+
+/// Distance between the samples of the terrain under a footprint, finer than the heightmap
+const SAMPLE_STEP: f32 = CELL_SIZE * 0.5;
+
+/// Why a building cannot be built on a footprint
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SiteError {
+    /// Some of the footprint is under water
+    OnWater,
+    /// The building needs water next to it, like harbors and water pumps
+    NotOnShore,
+    /// The building needs flat ground, like the runway of airports
+    NotFlat,
+}
+
+impl SiteError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SiteError::OnWater => "Can't build on water",
+            SiteError::NotOnShore => "Must be built on the shore",
+            SiteError::NotFlat => "The ground must be flat, the terrain is too steep here",
+        }
+    }
+}
+
+impl Display for SiteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.reason())
+    }
+}
+
+/// Points covering the footprint every few meters, its edges and corners included
+fn footprint_samples(obb: &OBB) -> impl Iterator<Item = Vec2> {
+    let origin = obb.corners[0];
+    let [a, b] = obb.axis();
+    let na = (a.mag() / SAMPLE_STEP).ceil().max(1.0) as u32;
+    let nb = (b.mag() / SAMPLE_STEP).ceil().max(1.0) as u32;
+    (0..=na).flat_map(move |i| {
+        (0..=nb).map(move |j| origin + a * (i as f32 / na as f32) + b * (j as f32 / nb as f32))
+    })
+}
+
+impl Map {
+    /// Depth of the water at the given position in meters, None on land or outside of the map
+    pub fn water_at(&self, pos: Vec2) -> Option<f32> {
+        let h = self.environment.true_height(pos)?;
+        (h < 0.0).then_some(-h)
+    }
+
+    /// Whether some of the footprint is under water
+    pub fn is_on_water(&self, obb: &OBB) -> bool {
+        footprint_samples(obb).any(|p| self.water_at(p).is_some())
+    }
+
+    /// Whether the footprint is on land with water less than [`SHORE_REACH`] away from it
+    pub fn is_on_shore(&self, obb: &OBB) -> bool {
+        !self.is_on_water(obb) && self.is_on_water(&obb.expand(SHORE_REACH))
+    }
+
+    /// Difference in meters between the highest and the lowest ground under the footprint
+    pub fn height_difference(&self, obb: &OBB) -> f32 {
+        let (min, max) = footprint_samples(obb)
+            .filter_map(|p| self.environment.true_height(p))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), h| {
+                (min.min(h), max.max(h))
+            });
+        (max - min).max(0.0)
+    }
+
+    /// Checks that the terrain allows building a building of this kind on the footprint
+    pub fn check_building_site(&self, obb: &OBB, kind: BuildingKind) -> Result<(), SiteError> {
+        if self.is_on_water(obb) {
+            return Err(SiteError::OnWater);
+        }
+        if kind.needs_shoreline() && !self.is_on_water(&obb.expand(SHORE_REACH)) {
+            return Err(SiteError::NotOnShore);
+        }
+        if let Some(max) = kind.max_height_difference() {
+            if self.height_difference(obb) > max {
+                return Err(SiteError::NotFlat);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,7 @@
-use crate::map::procgen::heightmap;
-use crate::map::procgen::heightmap::tree_density;
+use crate::map::procgen::{MapGen, MapGenParams};
 use flat_spatial::Grid;
-use geom::{lerp, pack_height, vec2, Intersect, Radians, Ray3, Vec2, Vec3, AABB};
-use prototypes::{Tick, DELTA};
+use geom::{lerp, pack_height, vec2, HeightmapPatch, Intersect, Radians, Ray3, Vec2, Vec3, AABB};
+use prototypes::{Tick, TreePrototypeID, DELTA};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -16,15 +15,20 @@ pub(super) const CELL_SIZE: f32 = TerrainChunkID::SIZE_F32 / TERRAIN_CHUNK_RESOL
 
 const TREE_GRID_SIZE: usize = 256;
 
+/// Distance the erosion droplets can flow away from the brush
+const EROSION_REACH: f32 = 60.0 * CELL_SIZE;
+
 pub type Chunk = geom::HeightmapChunk<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 pub type Heightmap = geom::Heightmap<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Tree {
     pub pos: Vec2,
     pub size: f32,
     pub col: f32,
     pub dir: Vec2,
+    /// None for the trees generated with the map
+    pub species: Option<TreePrototypeID>,
 }
 
 #[derive(Clone)]
@@ -40,6 +44,8 @@ pub enum TerraformKind {
     Level,
     Slope,
     Erode,
+    /// Digs down to the level to paint water, or fills up to it to paint land back
+    Water,
 }
 
 defer_serialize!(Environment, SerializedEnvironment);
@@ -52,6 +58,12 @@ impl Default for Environment {
 
 impl Environment {
     pub fn new(w: u16, h: u16) -> Self {
+        Self::generate(w, h, &MapGenParams::default())
+    }
+
+    /// A terrain generated from the parameters, they always give the same terrain
+    pub fn generate(w: u16, h: u16, params: &MapGenParams) -> Self {
+        let gen = MapGen::new(params, w, h);
         let mut me = Self {
             heightmap: Heightmap::new(w, h),
             trees: Grid::new(TREE_GRID_SIZE as i32),
@@ -59,7 +71,7 @@ impl Environment {
         for y in 0..h {
             let chunks: Vec<_> = (0..w)
                 .into_par_iter()
-                .map(|x| me.generate_chunk((x, y)))
+                .map(|x| me.generate_chunk(&gen, (x, y)))
                 .collect();
             for (x, chunk) in (0..w).zip(chunks) {
                 if let Some((v, trees)) = chunk {
@@ -73,6 +85,14 @@ impl Environment {
         me
     }
 
+    /// A terrain at ground level everywhere, without trees
+    pub fn flat(w: u16, h: u16) -> Self {
+        Self {
+            heightmap: Heightmap::new(w, h),
+            trees: Grid::new(TREE_GRID_SIZE as i32),
+        }
+    }
+
     /// Returns the height of the terrain at the given position in meters, capped at 0
     pub fn height(&self, pos: Vec2) -> Option<f32> {
         self.heightmap.height(pos).map(|x| x.max(0.0))
@@ -83,6 +103,11 @@ impl Environment {
         self.heightmap.height(pos)
     }
 
+    /// The heights of the terrain, to sample them somewhere the map is not locked
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
     pub fn remove_trees_near(
         &mut self,
         obj: impl Intersect<Vec2>,
@@ -110,6 +135,36 @@ impl Environment {
         self.trees.maintain();
     }
 
+    /// Returns the trees intersecting the object
+    pub fn trees_near(&self, obj: impl Intersect<Vec2>) -> Vec<Tree> {
+        let mut trees = vec![];
+        let bbox = obj.bbox();
+        self.trees.query_aabb_visitor(bbox.ll, bbox.ur, |(h, pos)| {
+            if obj.intersects(&pos) {
+                if let Some((_, tree)) = self.trees.get(h) {
+                    trees.push(*tree);
+                }
+            }
+        });
+        trees
+    }
+
+    pub fn add_trees(
+        &mut self,
+        trees: impl IntoIterator<Item = Tree>,
+        mut f: impl FnMut(TerrainChunkID),
+    ) {
+        let mut seen = HashSet::new();
+        for tree in trees {
+            self.trees.insert(tree.pos, tree);
+            let id = TerrainChunkID::new(tree.pos);
+            if seen.insert(id) {
+                f(id);
+            }
+        }
+        self.trees.maintain();
+    }
+
     pub fn get_chunk(&self, id: TerrainChunkID) -> Option<&Chunk> {
         self.heightmap.get_chunk((id.0 as u16, id.1 as u16))
     }
@@ -162,6 +217,39 @@ impl Environment {
             .collect()
     }
 
+    /// Copies the heights of the terrain in the bounds, to put them back with [`Self::set_heights`]
+    pub fn heights_patch(&self, bounds: AABB) -> HeightmapPatch {
+        self.heightmap.patch(bounds)
+    }
+
+    /// Adds the chunks of the bounds the patch doesn't have yet, the heights it has are kept
+    pub fn extend_heights_patch(&self, patch: &mut HeightmapPatch, bounds: AABB) {
+        self.heightmap.extend_patch(patch, bounds)
+    }
+
+    /// The current heights of the area of the patch
+    pub fn heights_like(&self, patch: &HeightmapPatch) -> HeightmapPatch {
+        self.heightmap.patch_like(patch)
+    }
+
+    /// Returns the chunks that were modified
+    pub fn set_heights(&mut self, patch: &HeightmapPatch) -> Vec<TerrainChunkID> {
+        self.heightmap
+            .apply_patch(patch)
+            .into_iter()
+            .map(|(x, y)| TerrainChunkID::new_i16(x as i16, y as i16))
+            .collect()
+    }
+
+    /// The area a terraforming can modify
+    pub fn terraform_bounds(kind: TerraformKind, center: Vec2, radius: f32) -> AABB {
+        let reach = match kind {
+            TerraformKind::Erode => radius + EROSION_REACH,
+            _ => radius,
+        };
+        AABB::centered(center, Vec2::splat(reach * 2.0))
+    }
+
     pub fn terraform(
         &mut self,
         tick: Tick,
@@ -243,23 +331,31 @@ impl Environment {
                     .map(|(x, y)| TerrainChunkID::new_i16(x as i16, y as i16))
                     .collect()
             }
+            TerraformKind::Water => self.terrain_apply(bbox, |pos| {
+                let dist = pos.xy().distance(center) / radius;
+                if dist >= 1.0 {
+                    return pos.z;
+                }
+                let phi = (-1.0 / (1.0 - dist * dist)).exp();
+                let step = (amount * DELTA) * phi;
+                // deeper water and higher land are left as they are
+                if level < 0.0 {
+                    (pos.z - step).max(level.min(pos.z))
+                } else {
+                    (pos.z + step).min(level.max(pos.z))
+                }
+            }),
         }
     }
 
-    fn generate_chunk(&self, (x, y): (u16, u16)) -> Option<(Chunk, Vec<Tree>)> {
+    fn generate_chunk(&self, gen: &MapGen, (x, y): (u16, u16)) -> Option<(Chunk, Vec<Tree>)> {
         let mut heights = [[0; TERRAIN_CHUNK_RESOLUTION]; TERRAIN_CHUNK_RESOLUTION];
 
         let offchunk = vec2(x as f32, y as f32) * TerrainChunkID::SIZE_F32;
         for (y, l) in heights.iter_mut().enumerate() {
             for (x, h) in l.iter_mut().enumerate() {
                 let offcell = vec2(x as f32, y as f32) * CELL_SIZE;
-                let mut rh = heightmap::height(offchunk + offcell).0 - 0.12;
-
-                if rh > 0.0 {
-                    rh = 0.0;
-                }
-
-                *h = pack_height(1000.0 * rh);
+                *h = pack_height(gen.height(offchunk + offcell));
             }
         }
 
@@ -284,7 +380,7 @@ impl Environment {
 
                 let sample = cellpos + vec2(jitterx, jittery) * TCELLW;
 
-                let tdens = tree_density(pchunk + sample);
+                let tdens = gen.tree_density(pchunk + sample);
 
                 if dens_test < tdens && chunk.height_unchecked(sample) >= 0.0 {
                     trees.push(Tree::new(pchunk + sample));
@@ -311,6 +407,18 @@ impl Tree {
             size: scale,
             col: colscale,
             dir: angle.vec2(),
+            species: None,
+        }
+    }
+
+    /// A tree planted by the player, its size depends on its species
+    pub fn planted(pos: Vec2, species: TreePrototypeID) -> Self {
+        let proto = species.prototype();
+        let srand = common::rand::rand3(pos.x, pos.y, 3.0);
+        Tree {
+            size: lerp(proto.min_size, proto.max_size, srand),
+            species: Some(species),
+            ..Tree::new(pos)
         }
     }
 }
@@ -338,6 +446,9 @@ pub fn to_pos(encoded: SmolTree, chunk: (u32, u32)) -> Vec2 {
 struct SerializedEnvironment {
     h: Heightmap,
     trees: Vec<((u32, u32), Vec<SmolTree>)>,
+    /// Planted trees keep their exact position and species
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    planted: Vec<(Vec2, TreePrototypeID)>,
 }
 
 impl From<SerializedEnvironment> for Environment {
@@ -353,6 +464,9 @@ impl From<SerializedEnvironment> for Environment {
                 terrain.trees.insert(tree.pos, tree);
             }
         }
+        for (pos, species) in ser.planted {
+            terrain.trees.insert(pos, Tree::planted(pos, species));
+        }
         terrain
     }
 }
@@ -362,12 +476,17 @@ impl From<&Environment> for SerializedEnvironment {
         let mut t = SerializedEnvironment {
             h: ter.heightmap.clone(),
             trees: Vec::new(),
+            planted: Vec::new(),
         };
 
         for (cell_id, chunk) in ter.trees.storage().cells.iter() {
             let cell_id = (cell_id.0 as u32, cell_id.1 as u32);
             let mut smoltrees = Vec::with_capacity(chunk.objs.len());
-            for (_, tree_pos) in chunk.objs.iter() {
+            for (h, tree_pos) in chunk.objs.iter() {
+                if let Some(species) = ter.trees.get(*h).and_then(|(_, tree)| tree.species) {
+                    t.planted.push((*tree_pos, species));
+                    continue;
+                }
                 let smol = new_smoltree(*tree_pos, cell_id);
                 smoltrees.push(smol);
             }
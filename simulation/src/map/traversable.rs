@@ -1,4 +1,4 @@
-use crate::map::{IntersectionID, Intersections, LaneID, Lanes, Map, TurnID};
+use crate::map::{CrossingID, IntersectionID, Intersections, LaneID, Lanes, Map, Roads, TurnID};
 use egui_inspect::Inspect;
 use geom::PolyLine3;
 use serde::{Deserialize, Serialize};
@@ -13,16 +13,23 @@ pub enum TraverseDirection {
 pub enum TraverseKind {
     Lane(LaneID),
     Turn(TurnID),
+    Crossing(CrossingID),
 }
 
 impl TraverseKind {
     pub fn is_lane(&self) -> bool {
         matches!(self, TraverseKind::Lane(_))
     }
-    pub fn length(&self, lanes: &Lanes, inters: &Intersections) -> Option<f32> {
+    pub fn length(&self, lanes: &Lanes, inters: &Intersections, roads: &Roads) -> Option<f32> {
         Some(match *self {
             TraverseKind::Lane(i) => lanes.get(i)?.points.length(),
             TraverseKind::Turn(t) => inters.get(t.parent)?.find_turn(t)?.points.length(),
+            TraverseKind::Crossing(c) => roads
+                .get(c.road)?
+                .crossings
+                .get(c.idx as usize)?
+                .points
+                .length(),
         })
     }
 }
@@ -51,6 +58,13 @@ impl Traversable {
         match self.kind {
             TraverseKind::Lane(id) => Some(&m.lanes.get(id)?.points),
             TraverseKind::Turn(id) => Some(&m.intersections.get(id.parent)?.find_turn(id)?.points),
+            TraverseKind::Crossing(id) => {
+                let road = m.roads.get(id.road)?;
+                if road.crossing_id(id.idx as usize)? != id {
+                    return None;
+                }
+                Some(&road.crossings.get(id.idx as usize)?.points)
+            }
         }
     }
 
@@ -60,7 +74,7 @@ impl Traversable {
                 let l = unwrap_or!(lanes.get(id), return true);
                 !l.control.get_behavior(time).is_red()
             }
-            TraverseKind::Turn(_) => true,
+            TraverseKind::Turn(_) | TraverseKind::Crossing(_) => true,
         }
     }
 
@@ -71,6 +85,7 @@ impl Traversable {
                 TraverseDirection::Backward => lanes.get(p)?.src,
             },
             TraverseKind::Turn(id) => id.parent,
+            TraverseKind::Crossing(_) => return None,
         })
     }
 
@@ -81,6 +96,10 @@ impl Traversable {
                 TraverseDirection::Forward => t.dst,
                 TraverseDirection::Backward => t.src,
             },
+            TraverseKind::Crossing(c) => match self.dir {
+                TraverseDirection::Forward => c.dst,
+                TraverseDirection::Backward => c.src,
+            },
         }
     }
 }
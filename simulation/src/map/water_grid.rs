@@ -0,0 +1,199 @@
+use geom::{vec2, Vec2, AABB};
+use ordered_float::OrderedFloat;
+
+use crate::map::pathfinding_crate::directed::astar::astar;
+use crate::map::Environment;
+
+/// Side of a cell of the water navigation grid in meters
+pub const WATER_CELL_SIZE: f32 = 32.0;
+
+/// Depth of water in meters a ship needs to float
+pub const SHIP_DRAFT: f32 = 2.0;
+
+/// Distance in meters around the center of a cell that must be water for ships to go through it
+pub const SHIP_CLEARANCE: f32 = 12.0;
+
+/// How far from a dock in meters ships look for navigable water
+pub const DOCK_REACH: f32 = 80.0;
+
+/// Distance between the samples when bringing a ship as close to a dock as the water allows
+const MOORING_STEP: f32 = 2.0;
+
+type Cell = (i32, i32);
+
+/// Coarse grid of the water that is deep and wide enough for cargo ships.
+/// Ships go from cell center to cell center, and reach the open sea through the cells on the
+/// edge of the map.
+#[derive(Clone)]
+pub struct WaterGrid {
+    bounds: AABB,
+    w: i32,
+    h: i32,
+    /// Indexed by y * w + x
+    navigable: Vec<bool>,
+}
+
+impl WaterGrid {
+    pub fn new(env: &Environment) -> Self {
+        let bounds = env.bounds();
+        let w = (bounds.w() / WATER_CELL_SIZE).ceil() as i32;
+        let h = (bounds.h() / WATER_CELL_SIZE).ceil() as i32;
+
+        let mut grid = Self {
+            bounds,
+            w,
+            h,
+            navigable: Vec::with_capacity((w * h) as usize),
+        };
+        for y in 0..h {
+            for x in 0..w {
+                let clear = is_clear(env, grid.center((x, y)));
+                grid.navigable.push(clear);
+            }
+        }
+        grid
+    }
+
+    fn center(&self, (x, y): Cell) -> Vec2 {
+        self.bounds.ll + vec2(x as f32 + 0.5, y as f32 + 0.5) * WATER_CELL_SIZE
+    }
+
+    fn cell(&self, pos: Vec2) -> Cell {
+        let local = (pos - self.bounds.ll) / WATER_CELL_SIZE;
+        (local.x.floor() as i32, local.y.floor() as i32)
+    }
+
+    fn is_navigable(&self, (x, y): Cell) -> bool {
+        if x < 0 || y < 0 || x >= self.w || y >= self.h {
+            return false;
+        }
+        self.navigable[(y * self.w + x) as usize]
+    }
+
+    /// Whether ships can navigate somewhere on the map
+    pub fn has_water(&self) -> bool {
+        self.navigable.iter().any(|&n| n)
+    }
+
+    fn on_edge(&self, (x, y): Cell) -> bool {
+        x == 0 || y == 0 || x == self.w - 1 || y == self.h - 1
+    }
+
+    /// Distance from the center of the cell to the nearest edge cell center, never more than
+    /// the length of the path to it
+    fn distance_to_edge(&self, cell: Cell) -> f32 {
+        let c = self.center(cell);
+        let ll = c - self.bounds.ll;
+        let ur = self.bounds.ur - c;
+        (ll.x.min(ll.y).min(ur.x).min(ur.y) - WATER_CELL_SIZE * 0.5).max(0.0)
+    }
+
+    /// The navigable neighbours of the cell, diagonals are only allowed if they don't cut a corner
+    fn neighbours(&self, (x, y): Cell) -> impl Iterator<Item = (Cell, OrderedFloat<f32>)> + '_ {
+        const DIRS: [(i32, i32); 8] = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        DIRS.iter().filter_map(move |&(dx, dy)| {
+            let to = (x + dx, y + dy);
+            if !self.is_navigable(to) {
+                return None;
+            }
+            if dx != 0 && dy != 0 {
+                if !self.is_navigable((x + dx, y)) || !self.is_navigable((x, y + dy)) {
+                    return None;
+                }
+                return Some((to, OrderedFloat(WATER_CELL_SIZE * std::f32::consts::SQRT_2)));
+            }
+            Some((to, OrderedFloat(WATER_CELL_SIZE)))
+        })
+    }
+
+    /// The navigable cell nearest to the position, no further than the reach
+    fn nearest_navigable(&self, pos: Vec2, reach: f32) -> Option<Cell> {
+        let (llx, lly) = self.cell(pos - Vec2::splat(reach));
+        let (urx, ury) = self.cell(pos + Vec2::splat(reach));
+        (lly..=ury)
+            .flat_map(|y| (llx..=urx).map(move |x| (x, y)))
+            .filter(|&c| self.is_navigable(c))
+            .map(|c| (c, self.center(c).distance(pos)))
+            .filter(|&(_, d)| d <= reach)
+            .min_by_key(|&(_, d)| OrderedFloat(d))
+            .map(|(c, _)| c)
+    }
+
+    /// The way on water from a dock to the open sea beyond the edge of the map.
+    /// The first point is where the ships moor, as close to the dock as the water allows,
+    /// and the last one is on the edge of the map.
+    pub fn route_to_sea(&self, env: &Environment, dock: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.nearest_navigable(dock, DOCK_REACH)?;
+        let (cells, _) = astar(
+            &start,
+            |&c| self.neighbours(c),
+            |&c| OrderedFloat(self.distance_to_edge(c)),
+            |&c| self.on_edge(c),
+        )?;
+
+        let mut route = Vec::with_capacity(cells.len() + 2);
+        route.push(mooring(env, self.center(start), dock));
+        route.extend(cells.iter().map(|&c| self.center(c)));
+
+        // leave the map through the nearest side
+        let last = *route.last().unwrap(); // Unwrap ok: there is at least the start cell
+        let ll = last - self.bounds.ll;
+        let ur = self.bounds.ur - last;
+        let exit = if ll.x.min(ur.x) < ll.y.min(ur.y) {
+            if ll.x < ur.x {
+                vec2(self.bounds.ll.x, last.y)
+            } else {
+                vec2(self.bounds.ur.x, last.y)
+            }
+        } else if ll.y < ur.y {
+            vec2(last.x, self.bounds.ll.y)
+        } else {
+            vec2(last.x, self.bounds.ur.y)
+        };
+        route.push(exit);
+
+        Some(route)
+    }
+}
+
+/// Whether the water at the center is deep enough, with no land around it
+fn is_clear(env: &Environment, center: Vec2) -> bool {
+    let Some(h) = env.true_height(center) else {
+        return false;
+    };
+    if h > -SHIP_DRAFT {
+        return false;
+    }
+    (0..8).all(|i| {
+        let ang = i as f32 * std::f32::consts::TAU / 8.0;
+        let p = center + vec2(ang.cos(), ang.sin()) * SHIP_CLEARANCE;
+        // beyond the edge of the map is the open sea
+        env.true_height(p).map_or(true, |h| h < 0.0)
+    })
+}
+
+/// Goes from the cell center toward the dock while the water is deep enough
+fn mooring(env: &Environment, center: Vec2, dock: Vec2) -> Vec2 {
+    let Some(dir) = (dock - center).try_normalize() else {
+        return center;
+    };
+    let mut pos = center;
+    loop {
+        let next = pos + dir * MOORING_STEP;
+        if next.distance(center) > center.distance(dock)
+            || env.true_height(next).map_or(true, |h| h > -SHIP_DRAFT)
+        {
+            return pos;
+        }
+        pos = next;
+    }
+}
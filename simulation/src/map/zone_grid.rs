@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{vec2, Vec2, AABB};
+
+/// Side of a zoning cell in meters
+pub const ZONE_CELL_SIZE: f32 = 16.0;
+
+/// What the player wants to grow on a cell
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ZoningKind {
+    Residential,
+    Commercial,
+    Industrial,
+}
+
+impl ZoningKind {
+    pub const ALL: [ZoningKind; 3] = [
+        ZoningKind::Residential,
+        ZoningKind::Commercial,
+        ZoningKind::Industrial,
+    ];
+}
+
+/// Zoning painted by the player, cells grow buildings of their kind when there is demand.
+/// Only painted cells are stored.
+#[derive(Default, Clone, PartialEq)]
+pub struct ZoneGrid {
+    /// Indexed by (y, x) so that rows are contiguous
+    cells: BTreeMap<(i32, i32), ZoningKind>,
+}
+
+defer_serialize!(ZoneGrid, SerializedZoneGrid);
+
+impl ZoneGrid {
+    fn cell(pos: Vec2) -> (i32, i32) {
+        (
+            (pos.y / ZONE_CELL_SIZE).floor() as i32,
+            (pos.x / ZONE_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    fn cell_aabb((y, x): (i32, i32)) -> AABB {
+        let ll = vec2(x as f32, y as f32) * ZONE_CELL_SIZE;
+        AABB::new_ll_ur(ll, ll + Vec2::splat(ZONE_CELL_SIZE))
+    }
+
+    pub fn get(&self, pos: Vec2) -> Option<ZoningKind> {
+        self.cells.get(&Self::cell(pos)).copied()
+    }
+
+    /// Sets the cells whose center is inside the circle, None clears them
+    pub fn paint(&mut self, center: Vec2, radius: f32, kind: Option<ZoningKind>) {
+        let (lly, llx) = Self::cell(center - Vec2::splat(radius));
+        let (ury, urx) = Self::cell(center + Vec2::splat(radius));
+        for y in lly..=ury {
+            for x in llx..=urx {
+                if !Self::cell_aabb((y, x)).center().is_close(center, radius) {
+                    continue;
+                }
+                match kind {
+                    Some(kind) => self.cells.insert((y, x), kind),
+                    None => self.cells.remove(&(y, x)),
+                };
+            }
+        }
+    }
+
+    /// The painted cells intersecting the area
+    pub fn query(&self, area: AABB) -> impl Iterator<Item = (AABB, ZoningKind)> + '_ {
+        let (lly, llx) = Self::cell(area.ll);
+        let (ury, urx) = Self::cell(area.ur);
+        (lly..=ury).flat_map(move |y| {
+            self.cells
+                .range((y, llx)..=(y, urx))
+                .map(|(&cell, &kind)| (Self::cell_aabb(cell), kind))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+/// A run of cells of the same kind along a row
+#[derive(Serialize, Deserialize)]
+struct ZoneRun {
+    x: i32,
+    len: u32,
+    kind: ZoningKind,
+}
+
+/// Rows of runs, as painted zones are mostly large contiguous areas
+#[derive(Default, Serialize, Deserialize)]
+struct SerializedZoneGrid {
+    rows: Vec<(i32, Vec<ZoneRun>)>,
+}
+
+impl From<&ZoneGrid> for SerializedZoneGrid {
+    fn from(grid: &ZoneGrid) -> Self {
+        let mut rows: Vec<(i32, Vec<ZoneRun>)> = Vec::new();
+        for (&(y, x), &kind) in &grid.cells {
+            if rows.last().map_or(true, |(row_y, _)| *row_y != y) {
+                rows.push((y, Vec::new()));
+            }
+            let runs = &mut rows.last_mut().unwrap().1;
+            match runs.last_mut() {
+                Some(run) if run.kind == kind && run.x + run.len as i32 == x => run.len += 1,
+                _ => runs.push(ZoneRun { x, len: 1, kind }),
+            }
+        }
+        Self { rows }
+    }
+}
+
+impl From<SerializedZoneGrid> for ZoneGrid {
+    fn from(ser: SerializedZoneGrid) -> Self {
+        let mut cells = BTreeMap::new();
+        for (y, runs) in ser.rows {
+            for run in runs {
+                for x in run.x..run.x + run.len as i32 {
+                    cells.insert((y, x), run.kind);
+                }
+            }
+        }
+        Self { cells }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::saveload::Encoder;
+    use geom::vec2;
+
+    use super::{SerializedZoneGrid, ZoneGrid, ZoningKind};
+
+    #[test]
+    fn save_load_is_compact() {
+        let mut grid = ZoneGrid::default();
+        grid.paint(vec2(100.0, 100.0), 80.0, Some(ZoningKind::Residential));
+        grid.paint(vec2(100.0, 100.0), 20.0, Some(ZoningKind::Commercial));
+        grid.paint(vec2(300.0, 100.0), 40.0, Some(ZoningKind::Industrial));
+        grid.paint(vec2(300.0, 100.0), 10.0, None);
+
+        let ser = SerializedZoneGrid::from(&grid);
+        let n_runs: usize = ser.rows.iter().map(|(_, runs)| runs.len()).sum();
+        assert!(n_runs * 2 < grid.cells.len());
+
+        let encoded = common::saveload::Bincode::encode(&grid).unwrap();
+        let decoded: ZoneGrid = common::saveload::Bincode::decode(&encoded).unwrap();
+        assert!(decoded == grid);
+    }
+}
@@ -1,15 +1,69 @@
-use crate::map::{BuildingKind, ElectricityNetworkID, Map};
+use crate::gameplay::Cheats;
+use crate::map::{BuildingID, BuildingKind, ElectricityNetworkID, Map, NetworkObjectID};
 use crate::map_dynamic::BuildingInfos;
 use crate::utils::resources::Resources;
 use crate::{SoulID, World};
-use prototypes::Power;
+use prototypes::{BuildingPrototype, PowerPriority};
+use prototypes::{Energy, GameTime, Power, TICKS_PER_HOUR, TICKS_PER_SECOND};
 use serde::Deserialize;
 use slotmapd::__impl::Serialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct ElectricityFlow {
     flowmap: BTreeMap<ElectricityNetworkID, NetworkFlow>,
+    buildings: BTreeMap<BuildingID, BuildingFlow>,
+    /// Energy consumed and produced by each building since the last call to [`ElectricityFlow::take_energy`]
+    energy: BTreeMap<BuildingID, BuildingEnergy>,
+    /// Energy stored by each storage building, in watt-ticks
+    stored: BTreeMap<BuildingID, i64>,
+    /// Buildings that lost power because their network is overloaded
+    shed: BTreeSet<BuildingID>,
+    /// Only used for display, recomputed regularly
+    #[serde(skip)]
+    edge_loads: BTreeMap<ElectricityNetworkID, Vec<(EdgeRef, f32)>>,
+}
+
+/// Maximum power an edge of a network can carry
+pub const EDGE_CAPACITY: Power = Power::new(10_000_000);
+
+/// An edge between two objects of an electricity network, power flows from `from` to `to`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EdgeRef {
+    pub from: NetworkObjectID,
+    pub to: NetworkObjectID,
+}
+
+/// A building that can store energy, such as a battery
+#[derive(Debug, Copy, Clone)]
+pub struct StorageSpec {
+    pub capacity: Energy,
+    pub max_charge_rate: Power,
+}
+
+impl StorageSpec {
+    pub fn from_proto(proto: &BuildingPrototype) -> Option<Self> {
+        Some(Self {
+            capacity: proto.storage_capacity?,
+            max_charge_rate: proto.max_charge_rate.unwrap_or(Power::ZERO),
+        })
+    }
+}
+
+/// Power consumed and produced by a building during the last tick
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct BuildingFlow {
+    pub consumption: Power,
+    pub production: Power,
+    pub priority: PowerPriority,
+}
+
+/// Energy consumed and produced by a building over a period, in watt-ticks.
+/// Ticks where the network was in a blackout are not counted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildingEnergy {
+    pub consumed: i64,
+    pub produced: i64,
 }
 
 impl ElectricityFlow {
@@ -20,49 +74,321 @@ impl ElectricityFlow {
             .unwrap_or(false)
     }
 
+    /// Whether some buildings of the network lost power
+    pub fn brownout(&self, network: ElectricityNetworkID) -> bool {
+        self.flowmap
+            .get(&network)
+            .map(|f| f.brownout)
+            .unwrap_or(false)
+    }
+
+    /// Load of each edge of the network as a fraction of [`EDGE_CAPACITY`], above 1 means overloaded
+    pub fn edge_loads(
+        &self,
+        network: ElectricityNetworkID,
+    ) -> impl Iterator<Item = (EdgeRef, f32)> + '_ {
+        self.edge_loads.get(&network).into_iter().flatten().copied()
+    }
+
+    /// Whether the building lost power because its network is overloaded
+    pub fn is_shed(&self, building: BuildingID) -> bool {
+        self.shed.contains(&building)
+    }
+
     pub fn network_stats(&self, network: ElectricityNetworkID) -> NetworkFlow {
         self.flowmap.get(&network).cloned().unwrap_or(NetworkFlow {
             consumed_power: Power::ZERO,
             produced_power: Power::ZERO,
+            storage_power: Power::ZERO,
+            brownout: false,
             blackout: false,
         })
     }
+
+    /// Energy currently stored in the building
+    pub fn stored_energy(&self, building: BuildingID) -> Energy {
+        Energy::new(self.stored.get(&building).copied().unwrap_or(0) / TICKS_PER_HOUR as i64)
+    }
+
+    /// Charge of the storage building in [0; 1] range
+    pub fn storage_charge(&self, building: BuildingID, capacity: Energy) -> f32 {
+        if capacity.0 <= 0 {
+            return 0.0;
+        }
+        let stored = self.stored.get(&building).copied().unwrap_or(0);
+        (stored as f64 / (capacity.0 * TICKS_PER_HOUR as i64) as f64) as f32
+    }
+
+    /// Power consumed by the building during the last tick
+    pub fn building_consumption(&self, building: BuildingID) -> Power {
+        self.buildings
+            .get(&building)
+            .map_or(Power::ZERO, |f| f.consumption)
+    }
+
+    /// Power produced by the building during the last tick
+    pub fn building_production(&self, building: BuildingID) -> Power {
+        self.buildings
+            .get(&building)
+            .map_or(Power::ZERO, |f| f.production)
+    }
+
+    /// Energy consumed and produced by the building since the energy was last taken
+    pub fn energy(&self, building: BuildingID) -> BuildingEnergy {
+        self.energy.get(&building).copied().unwrap_or_default()
+    }
+
+    /// Returns the energy accumulated by each building and starts a new period
+    pub fn take_energy(&mut self) -> BTreeMap<BuildingID, BuildingEnergy> {
+        std::mem::take(&mut self.energy)
+    }
+
+    /// Records the flow of a building for this tick.
+    /// Energy is only accumulated if its network is not in a blackout.
+    pub fn record(&mut self, building: BuildingID, flow: BuildingFlow, blackout: bool) {
+        self.buildings.insert(building, flow);
+        if blackout || (flow.consumption == Power::ZERO && flow.production == Power::ZERO) {
+            return;
+        }
+        let e = self.energy.entry(building).or_default();
+        e.consumed += flow.consumption.0;
+        e.produced += flow.production.0;
+    }
+
+    /// Computes the flow of a network for this tick from the flow of its buildings.
+    /// Storages charge from the surplus of the network and discharge to cover its deficit.
+    /// If they cannot cover it, the buildings with the lowest priorities are shed until they can.
+    /// A charging storage consumes power and a discharging storage produces power.
+    pub fn solve_network(
+        &mut self,
+        buildings: &mut Vec<(BuildingID, BuildingFlow)>,
+        storages: &[(BuildingID, StorageSpec)],
+    ) -> NetworkFlow {
+        let consumed_power: Power = buildings.iter().map(|(_, f)| f.consumption).sum();
+        let produced_power: Power = buildings.iter().map(|(_, f)| f.production).sum();
+        // positive when charging
+        let mut storage_power = Power::ZERO;
+
+        for (building, _) in buildings.iter() {
+            self.shed.remove(building);
+        }
+
+        if produced_power >= consumed_power {
+            let mut surplus = (produced_power - consumed_power).0;
+            for &(building, spec) in storages {
+                let stored = self.stored.entry(building).or_default();
+                let room = (spec.capacity.0 * TICKS_PER_HOUR as i64 - *stored).max(0);
+                let charge = spec.max_charge_rate.0.min(room).min(surplus);
+                if charge <= 0 {
+                    continue;
+                }
+                *stored += charge;
+                surplus -= charge;
+                storage_power.0 += charge;
+                flow_of(buildings, building).consumption += Power::new(charge);
+            }
+        } else {
+            let mut deficit = (consumed_power - produced_power).0;
+            let available: i64 = storages
+                .iter()
+                .map(|(building, spec)| {
+                    let stored = self.stored.get(building).copied().unwrap_or(0);
+                    spec.max_charge_rate.0.min(stored)
+                })
+                .sum();
+
+            // shed whole priority classes, lowest first, until the storages can cover the rest
+            for priority in PowerPriority::SHED_ORDER {
+                if deficit <= available {
+                    break;
+                }
+                for &(building, bflow) in buildings.iter() {
+                    if bflow.priority == priority && bflow.consumption > Power::ZERO {
+                        self.shed.insert(building);
+                        deficit -= bflow.consumption.0;
+                    }
+                }
+            }
+
+            if deficit > 0 {
+                for &(building, spec) in storages {
+                    let Some(stored) = self.stored.get_mut(&building) else {
+                        continue;
+                    };
+                    let discharge = spec.max_charge_rate.0.min(*stored).min(deficit);
+                    if discharge <= 0 {
+                        continue;
+                    }
+                    *stored -= discharge;
+                    deficit -= discharge;
+                    storage_power.0 -= discharge;
+                    flow_of(buildings, building).production += Power::new(discharge);
+                }
+            }
+        }
+
+        let brownout = buildings.iter().any(|(b, _)| self.shed.contains(b));
+        let blackout = brownout
+            && buildings
+                .iter()
+                .all(|(b, f)| f.consumption == Power::ZERO || self.shed.contains(b));
+
+        for &(building, bflow) in buildings.iter() {
+            let no_power = blackout || self.shed.contains(&building);
+            self.record(building, bflow, no_power);
+        }
+
+        NetworkFlow {
+            consumed_power,
+            produced_power,
+            storage_power,
+            brownout,
+            blackout,
+        }
+    }
+
+    /// Computes the load of the edges of a network from the flows of its buildings.
+    /// Power is routed along a spanning tree rooted at the biggest producer,
+    /// so the edges closing a loop carry nothing.
+    fn compute_edge_loads(
+        &self,
+        objects: &BTreeSet<NetworkObjectID>,
+        graph: &BTreeMap<NetworkObjectID, Vec<NetworkObjectID>>,
+    ) -> Vec<(EdgeRef, f32)> {
+        let injection = |object: &NetworkObjectID| -> i64 {
+            let NetworkObjectID::Building(b) = *object else {
+                return 0;
+            };
+            let Some(f) = self.buildings.get(&b) else {
+                return 0;
+            };
+            if self.shed.contains(&b) {
+                return f.production.0;
+            }
+            f.production.0 - f.consumption.0
+        };
+
+        let Some(&root) = objects
+            .iter()
+            .max_by_key(|o| (injection(o), std::cmp::Reverse(**o)))
+        else {
+            return Vec::new();
+        };
+
+        let mut order = Vec::with_capacity(objects.len());
+        let mut parent = BTreeMap::new();
+        let mut visited = BTreeSet::from([root]);
+        let mut queue = std::collections::VecDeque::from([root]);
+        let mut neighbours = Vec::new();
+
+        while let Some(obj) = queue.pop_front() {
+            order.push(obj);
+            neighbours.clear();
+            neighbours.extend(graph.get(&obj).into_iter().flatten().copied());
+            // the graph order is not deterministic
+            neighbours.sort_unstable();
+            for &n in &neighbours {
+                if visited.insert(n) {
+                    parent.insert(n, obj);
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        let mut subtree: BTreeMap<NetworkObjectID, i64> = BTreeMap::new();
+        let mut loads = Vec::with_capacity(order.len());
+
+        for obj in order.iter().rev() {
+            let net = injection(obj) + subtree.get(obj).copied().unwrap_or(0);
+            let Some(&p) = parent.get(obj) else {
+                continue;
+            };
+            *subtree.entry(p).or_default() += net;
+
+            // a positive subtree sends power towards the root
+            let edge = if net > 0 {
+                EdgeRef { from: *obj, to: p }
+            } else {
+                EdgeRef { from: p, to: *obj }
+            };
+            loads.push((edge, net.abs() as f32 / EDGE_CAPACITY.0 as f32));
+        }
+
+        loads
+    }
+}
+
+fn flow_of(
+    buildings: &mut Vec<(BuildingID, BuildingFlow)>,
+    building: BuildingID,
+) -> &mut BuildingFlow {
+    let i = match buildings.iter().position(|(b, _)| *b == building) {
+        Some(i) => i,
+        None => {
+            buildings.push((building, BuildingFlow::default()));
+            buildings.len() - 1
+        }
+    };
+    &mut buildings[i].1
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NetworkFlow {
     pub consumed_power: Power,
     pub produced_power: Power,
-    /// Whether the network is in a blackout
+    /// Power going into the storages of the network, negative when they are discharging
+    pub storage_power: Power,
+    /// Whether some buildings of the network were shed because it is overloaded
+    pub brownout: bool,
+    /// Whether every consumer of the network was shed
     pub blackout: bool,
 }
 
 /// Compute the electricity flow of the map and store it in the [`ElectricityFlow`] resource
 /// All producing buildings will produce power, and all consuming buildings will consume power
-/// If a network produces less power than it consumes, a blackout will occur
+/// Storage buildings absorb the surplus and cover the deficit of their network
+/// If a network produces less power than it consumes and its storages are empty,
+/// buildings are shed by priority: decorative buildings first, then industry, then homes
 pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::electricity_flow");
 
     let map = resources.read::<Map>();
     let binfos = resources.read::<BuildingInfos>();
     let mut flow = resources.write::<ElectricityFlow>();
+    let cheats = resources.read::<Cheats>();
+    let update_loads = resources.read::<GameTime>().tick.0 % TICKS_PER_SECOND == 0;
 
     flow.flowmap.clear();
+    flow.buildings.clear();
+    flow.shed.clear();
+    flow.stored.retain(|b, _| map.buildings.contains_key(*b));
+
+    let mut building_flows = Vec::new();
+    let mut storages = Vec::new();
 
     for network in map.electricity.networks.values() {
-        let mut consumed_power: Power = Power::ZERO;
-        let mut produced_power: Power = Power::ZERO;
+        building_flows.clear();
+        storages.clear();
 
         for building in network.buildings.iter() {
             let building = map.buildings.get(*building).unwrap();
 
+            let mut bflow = BuildingFlow {
+                priority: power_priority(building.kind),
+                ..Default::default()
+            };
+
             match building.kind {
                 BuildingKind::House => {
-                    consumed_power += Power::new(100);
+                    bflow.consumption = Power::new(100);
                 }
                 BuildingKind::GoodsCompany(comp) => {
                     let proto = comp.prototype();
 
+                    if let Some(spec) = StorageSpec::from_proto(proto) {
+                        storages.push((building.id, spec));
+                    }
+
                     let Some(SoulID::GoodsCompany(owner)) = binfos.owner(building.id) else {
                         continue;
                     };
@@ -72,22 +398,281 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
                     };
                     let productivity = ent.raw_productivity(proto, building.zone.as_ref()) as f64;
 
-                    consumed_power += proto.power_consumption.unwrap_or(Power::ZERO) * productivity;
-                    produced_power += proto.power_production.unwrap_or(Power::ZERO) * productivity;
+                    bflow.consumption =
+                        proto.power_consumption.unwrap_or(Power::ZERO) * productivity;
+                    bflow.production = proto.power_production.unwrap_or(Power::ZERO) * productivity;
+                }
+                BuildingKind::Warehouse(w) => {
+                    let proto = w.prototype();
+                    if let Some(spec) = StorageSpec::from_proto(proto) {
+                        storages.push((building.id, spec));
+                    }
+                    bflow.consumption = proto.power_consumption.unwrap_or(Power::ZERO);
+                }
+                BuildingKind::School(s) => {
+                    bflow.consumption = s.prototype().power_consumption.unwrap_or(Power::ZERO);
+                }
+                BuildingKind::Leisure(l) => {
+                    bflow.consumption = l.prototype().power_consumption.unwrap_or(Power::ZERO);
+                }
+                BuildingKind::Hotel(h) => {
+                    bflow.consumption = h.prototype().power_consumption.unwrap_or(Power::ZERO);
+                }
+                BuildingKind::Harbor(h) => {
+                    bflow.consumption = h.prototype().power_consumption.unwrap_or(Power::ZERO);
+                }
+                BuildingKind::Airport(a) => {
+                    bflow.consumption = a.prototype().power_consumption.unwrap_or(Power::ZERO);
                 }
                 BuildingKind::RailFreightStation(_) => {}
+                BuildingKind::RailPassengerStation(_) => {}
                 BuildingKind::TrainStation => {}
                 BuildingKind::ExternalTrading => {}
             }
+
+            building_flows.push((building.id, bflow));
+        }
+
+        // the console can force a blackout, as if all the producers tripped
+        if cheats.blackouts.contains(&network.id) {
+            for (_, bflow) in building_flows.iter_mut() {
+                bflow.production = Power::ZERO;
+            }
+            storages.clear();
         }
 
-        flow.flowmap.insert(
-            network.id,
-            NetworkFlow {
-                consumed_power,
-                produced_power,
-                blackout: consumed_power > produced_power,
+        let nflow = flow.solve_network(&mut building_flows, &storages);
+        flow.flowmap.insert(network.id, nflow);
+    }
+
+    if update_loads {
+        profiling::scope!("map_dynamic::electricity_edge_loads");
+        flow.edge_loads.clear();
+        for network in map.electricity.networks.values() {
+            let loads = flow.compute_edge_loads(&network.objects, map.electricity.graph());
+            flow.edge_loads.insert(network.id, loads);
+        }
+    }
+}
+
+/// Priority of the building when its network is overloaded.
+/// Homes are shed last and industry before them, prototypes can override it.
+pub fn power_priority(kind: BuildingKind) -> PowerPriority {
+    let overriden = match kind {
+        BuildingKind::GoodsCompany(id) => id.prototype().power_priority,
+        BuildingKind::Warehouse(id) => id.prototype().power_priority,
+        BuildingKind::School(id) => id.prototype().power_priority,
+        BuildingKind::Leisure(id) => id.prototype().power_priority,
+        BuildingKind::Hotel(id) => id.prototype().power_priority,
+        BuildingKind::Harbor(id) => id.prototype().power_priority,
+        BuildingKind::Airport(id) => id.prototype().power_priority,
+        _ => None,
+    };
+
+    overriden.unwrap_or(match kind {
+        BuildingKind::House => PowerPriority::High,
+        BuildingKind::GoodsCompany(_)
+        | BuildingKind::Warehouse(_)
+        | BuildingKind::School(_)
+        | BuildingKind::Hotel(_)
+        | BuildingKind::Harbor(_)
+        | BuildingKind::Airport(_) => PowerPriority::Medium,
+        BuildingKind::Leisure(_)
+        | BuildingKind::RailFreightStation(_)
+        | BuildingKind::RailPassengerStation(_)
+        | BuildingKind::TrainStation
+        | BuildingKind::ExternalTrading => PowerPriority::Low,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{Energy, Power, PowerPriority, TICKS_PER_HOUR};
+
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use crate::map::{BuildingID, NetworkObjectID, RoadID};
+
+    use super::{BuildingFlow, EdgeRef, ElectricityFlow, StorageSpec};
+
+    fn mk_building(id: u64) -> BuildingID {
+        BuildingID::from(slotmapd::KeyData::from_ffi((1 << 32) | id))
+    }
+
+    /// Returns whether the network is in a blackout for each tick of the hour
+    fn run_hour(
+        flow: &mut ElectricityFlow,
+        sun: bool,
+        storages: &[(BuildingID, StorageSpec)],
+    ) -> Vec<bool> {
+        let solar = mk_building(1);
+        let house = mk_building(2);
+
+        (0..TICKS_PER_HOUR)
+            .map(|_| {
+                let mut buildings = vec![
+                    (
+                        solar,
+                        BuildingFlow {
+                            consumption: Power::ZERO,
+                            production: if sun { Power::new(1_500) } else { Power::ZERO },
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        house,
+                        BuildingFlow {
+                            consumption: Power::new(1_000),
+                            production: Power::ZERO,
+                            ..Default::default()
+                        },
+                    ),
+                ];
+                flow.solve_network(&mut buildings, storages).blackout
+            })
+            .collect()
+    }
+
+    #[test]
+    fn storage_prevents_blackout_until_depleted() {
+        let battery = mk_building(3);
+        let storages = [(
+            battery,
+            StorageSpec {
+                capacity: Energy::new(1_000),
+                max_charge_rate: Power::new(1_000),
             },
-        );
+        )];
+
+        // without storage, the blackout starts as soon as the sun sets
+        let mut flow = ElectricityFlow::default();
+        assert!(run_hour(&mut flow, true, &[]).iter().all(|&b| !b));
+        assert!(run_hour(&mut flow, false, &[]).iter().all(|&b| b));
+
+        let mut flow = ElectricityFlow::default();
+        for _ in 0..3 {
+            // the surplus of 500W charges the battery during one hour
+            assert!(run_hour(&mut flow, true, &storages).iter().all(|&b| !b));
+            assert_eq!(flow.stored_energy(battery), Energy::new(500));
+            assert_eq!(flow.storage_charge(battery, Energy::new(1_000)), 0.5);
+            assert_eq!(flow.building_consumption(battery), Power::new(500));
+
+            // the battery covers 1kW for half an hour, then it is depleted
+            let blackouts = run_hour(&mut flow, false, &storages);
+            let half = TICKS_PER_HOUR as usize / 2;
+            assert!(blackouts[..half].iter().all(|&b| !b));
+            assert!(blackouts[half..].iter().all(|&b| b));
+            assert_eq!(flow.stored_energy(battery), Energy::ZERO);
+        }
+    }
+
+    #[test]
+    fn shedding_industry_keeps_homes_powered() {
+        let plant = mk_building(1);
+        let house1 = mk_building(2);
+        let house2 = mk_building(3);
+        let factory = mk_building(4);
+        let fountain = mk_building(5);
+
+        let consumer = |w, priority| BuildingFlow {
+            consumption: Power::new(w),
+            production: Power::ZERO,
+            priority,
+        };
+
+        let mut flow = ElectricityFlow::default();
+        let mut buildings = vec![
+            (
+                plant,
+                BuildingFlow {
+                    consumption: Power::ZERO,
+                    production: Power::new(3_000),
+                    priority: PowerPriority::Medium,
+                },
+            ),
+            (house1, consumer(1_000, PowerPriority::High)),
+            (house2, consumer(1_000, PowerPriority::High)),
+            (factory, consumer(2_000, PowerPriority::Medium)),
+            (fountain, consumer(500, PowerPriority::Low)),
+        ];
+
+        let nflow = flow.solve_network(&mut buildings, &[]);
+
+        // shedding the fountain is not enough, the factory has to go too
+        assert!(nflow.brownout);
+        assert!(!nflow.blackout);
+        assert!(flow.is_shed(fountain));
+        assert!(flow.is_shed(factory));
+        assert!(!flow.is_shed(house1));
+        assert!(!flow.is_shed(house2));
+        assert!(!flow.is_shed(plant));
+
+        // shed buildings don't consume energy
+        assert_eq!(flow.energy(factory).consumed, 0);
+        assert_eq!(flow.energy(house1).consumed, 1_000);
+
+        // once the network is balanced, nothing is shed anymore
+        buildings[3].1.consumption = Power::new(500);
+        let nflow = flow.solve_network(&mut buildings, &[]);
+        assert!(!nflow.brownout);
+        assert!(!flow.is_shed(factory));
+        assert!(!flow.is_shed(fountain));
+    }
+
+    #[test]
+    fn edge_loads_follow_the_tree() {
+        let plant = mk_building(1);
+        let house = mk_building(2);
+        let factory = mk_building(3);
+        let road = NetworkObjectID::Road(RoadID::from(slotmapd::KeyData::from_ffi((1 << 32) | 1)));
+
+        let mut flow = ElectricityFlow::default();
+        let mut buildings = vec![
+            (
+                plant,
+                BuildingFlow {
+                    production: Power::new(9_000_000),
+                    ..Default::default()
+                },
+            ),
+            (
+                house,
+                BuildingFlow {
+                    consumption: Power::new(6_000_000),
+                    ..Default::default()
+                },
+            ),
+            (
+                factory,
+                BuildingFlow {
+                    consumption: Power::new(3_000_000),
+                    ..Default::default()
+                },
+            ),
+        ];
+        flow.solve_network(&mut buildings, &[]);
+
+        let [plant, house, factory] = [plant, house, factory].map(NetworkObjectID::Building);
+        let objects = BTreeSet::from([plant, house, factory, road]);
+        let graph = BTreeMap::from([
+            (plant, vec![road]),
+            (house, vec![road]),
+            (factory, vec![road]),
+            (road, vec![factory, house, plant]),
+        ]);
+
+        let loads = flow.compute_edge_loads(&objects, &graph);
+        assert_eq!(loads.len(), 3);
+
+        let load = |from, to| {
+            loads
+                .iter()
+                .find(|(e, _)| *e == EdgeRef { from, to })
+                .map(|x| x.1)
+                .unwrap()
+        };
+        assert!((load(plant, road) - 0.9).abs() < 1e-5);
+        assert!((load(road, house) - 0.6).abs() < 1e-5);
+        assert!((load(road, factory) - 0.3).abs() < 1e-5);
     }
 }
@@ -0,0 +1,266 @@
+use crate::chronicle::{self, ChronicleKind};
+use crate::gameplay::GameplayParams;
+use crate::map::{BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::souls::desire::WorkKind;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::world::{HumanEnt, HumanID};
+use crate::{ParCommandBuffer, World};
+use prototypes::{CompanyKind, GameDuration, GameInstant, GameTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Buildings closer than this to a burning building catch fire after [`FIRE_SPREAD_DELAY_MINUTES`]
+pub const FIRE_SPREAD_RADIUS: f32 = 30.0;
+
+/// Time before a fire spreads to the buildings around it
+pub const FIRE_SPREAD_DELAY_MINUTES: u64 = 120;
+
+/// Time before a fire nobody fights burns the building down
+pub const FIRE_BURN_MINUTES: u64 = 8 * 60;
+
+/// Time the firefighters have to stay on the scene to put out the fire
+pub const FIRE_EXTINGUISH_MINUTES: u64 = 30;
+
+/// Chance per in-game day that a building catches fire on its own
+pub fn daily_fire_hazard(kind: BuildingKind) -> f32 {
+    match kind {
+        BuildingKind::House => 0.0005,
+        BuildingKind::GoodsCompany(id) => match id.prototype().kind {
+            CompanyKind::Store => 0.001,
+            CompanyKind::Factory => 0.003,
+        },
+        BuildingKind::Warehouse(_) => 0.002,
+        BuildingKind::School(_) => 0.0005,
+        BuildingKind::Leisure(_) => 0.0005,
+        BuildingKind::Hotel(_) => 0.001,
+        BuildingKind::Harbor(_) => 0.001,
+        BuildingKind::Airport(_) => 0.001,
+        BuildingKind::RailFreightStation(_)
+        | BuildingKind::RailPassengerStation(_)
+        | BuildingKind::TrainStation
+        | BuildingKind::ExternalTrading => 0.0,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fire {
+    pub started: GameInstant,
+    /// Whether the fire already spread to the buildings around
+    pub spread: bool,
+    /// Fire station whose truck was sent
+    pub responder: Option<BuildingID>,
+    /// When the firefighters arrived on the scene
+    pub arrived: Option<GameInstant>,
+}
+
+/// Burning and burned down buildings.
+/// Neither of them work until the fire is put out or the ruin is bulldozed.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Fires {
+    burning: BTreeMap<BuildingID, Fire>,
+    ruins: BTreeSet<BuildingID>,
+    last_roll_day: i32,
+}
+
+impl Fires {
+    pub fn get(&self, building: BuildingID) -> Option<&Fire> {
+        self.burning.get(&building)
+    }
+
+    pub fn is_burning(&self, building: BuildingID) -> bool {
+        self.burning.contains_key(&building)
+    }
+
+    pub fn is_ruin(&self, building: BuildingID) -> bool {
+        self.ruins.contains(&building)
+    }
+
+    /// Whether the building is burning or burned down
+    pub fn is_out_of_service(&self, building: BuildingID) -> bool {
+        self.is_burning(building) || self.is_ruin(building)
+    }
+
+    pub fn burning(&self) -> impl Iterator<Item = &BuildingID> + '_ {
+        self.burning.keys()
+    }
+
+    pub fn ruins(&self) -> impl Iterator<Item = &BuildingID> + '_ {
+        self.ruins.iter()
+    }
+
+    /// Sets the building on fire, unless it is already burning or burned down
+    pub fn ignite(&mut self, building: BuildingID, now: GameInstant) {
+        if self.is_out_of_service(building) {
+            return;
+        }
+        self.burning.insert(
+            building,
+            Fire {
+                started: now,
+                spread: false,
+                responder: None,
+                arrived: None,
+            },
+        );
+    }
+
+    /// Firefighters arrived on the scene, the fire is put out after [`FIRE_EXTINGUISH_MINUTES`]
+    pub fn arrive(&mut self, building: BuildingID, now: GameInstant) {
+        if let Some(fire) = self.burning.get_mut(&building) {
+            fire.arrived.get_or_insert(now);
+        }
+    }
+}
+
+/// Rolls the daily fire hazard of every building, spreads and burns down the fires,
+/// and sends the fire trucks of the stations in range
+pub fn fire_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::fire");
+
+    let map = resources.read::<Map>();
+    let time = resources.read::<GameTime>();
+    let mut rng = resources.write::<RandProvider>();
+    let mut fires = resources.write::<Fires>();
+    let params = resources.read::<GameplayParams>();
+
+    let now = time.instant();
+
+    fires.burning.retain(|b, _| map.buildings.contains_key(*b));
+    fires.ruins.retain(|b| map.buildings.contains_key(*b));
+
+    // rolls are made in a fixed order with the simulation rng so that replays see the same fires
+    if fires.last_roll_day != time.daytime.day {
+        fires.last_roll_day = time.daytime.day;
+        for (id, b) in map.buildings.iter() {
+            if rng.next_f32() < daily_fire_hazard(b.kind) * params.disaster_frequency {
+                log::info!("{:?} caught fire", id);
+                fires.ignite(id, now);
+            }
+        }
+    }
+
+    let mut spreading = Vec::new();
+    let mut ruined = Vec::new();
+    let mut extinguished = Vec::new();
+
+    for (&id, fire) in fires.burning.iter_mut() {
+        if let Some(arrived) = fire.arrived {
+            if arrived.elapsed(&time) >= GameDuration::from_minutes(FIRE_EXTINGUISH_MINUTES) {
+                extinguished.push(id);
+            }
+            continue;
+        }
+
+        let elapsed = fire.started.elapsed(&time);
+        if elapsed >= GameDuration::from_minutes(FIRE_BURN_MINUTES) {
+            ruined.push(id);
+            continue;
+        }
+
+        if !fire.spread && elapsed >= GameDuration::from_minutes(FIRE_SPREAD_DELAY_MINUTES) {
+            fire.spread = true;
+            spreading.push(id);
+        }
+    }
+
+    for id in extinguished {
+        fires.burning.remove(&id);
+    }
+
+    // the households of burned down homes move away, the ruin stays empty until rebuilt
+    if !ruined.is_empty() {
+        let cbuf = resources.read::<ParCommandBuffer<HumanEnt>>();
+        for (id, h) in world.humans.iter() {
+            if ruined.contains(&h.home.house) {
+                cbuf.kill(id);
+            }
+        }
+    }
+    for id in ruined {
+        log::info!("{:?} burned down", id);
+        if let Some(b) = map.buildings.get(id) {
+            chronicle::record(
+                resources,
+                ChronicleKind::Disaster,
+                "A building burned down",
+                Some(b.door_pos),
+            );
+        }
+        fires.burning.remove(&id);
+        fires.ruins.insert(id);
+    }
+    for id in spreading {
+        let Some(b) = map.buildings.get(id) else {
+            continue;
+        };
+        for obj in map.spatial_map().query_around(
+            b.obb.center(),
+            FIRE_SPREAD_RADIUS,
+            ProjectFilter::BUILDING,
+        ) {
+            if let ProjectKind::Building(other) = obj {
+                fires.ignite(other, now);
+            }
+        }
+    }
+
+    dispatch_fire_trucks(world, &map, &mut fires);
+}
+
+/// Sends the closest idle fire truck in range to each fire nobody responds to
+fn dispatch_fire_trucks(world: &mut World, map: &Map, fires: &mut Fires) {
+    let mut idle: Vec<(HumanID, BuildingID, f32)> = Vec::new();
+
+    for (_, c) in world.companies.iter() {
+        let Some(radius) = c.comp.proto.prototype().service_radius else {
+            continue;
+        };
+        let Some(driver) = c.comp.driver else {
+            continue;
+        };
+        let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
+            continue;
+        };
+        if matches!(w.kind, WorkKind::Firefighter { target: None, .. })
+            && !fires.is_out_of_service(c.comp.building)
+        {
+            idle.push((driver, c.comp.building, radius));
+        }
+    }
+
+    for (&id, fire) in fires.burning.iter_mut() {
+        if fire
+            .responder
+            .map_or(false, |station| map.buildings.contains_key(station))
+        {
+            continue;
+        }
+        let Some(b) = map.buildings.get(id) else {
+            continue;
+        };
+        let pos = b.door_pos.xy();
+
+        let closest = idle
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(_, station, radius))| {
+                let dist = map.buildings.get(station)?.door_pos.xy().distance(pos);
+                (dist <= radius).then_some((i, dist))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((i, _)) = closest else {
+            continue;
+        };
+        let (driver, station, _) = idle.swap_remove(i);
+
+        let Some(w) = world.humans.get_mut(driver).and_then(|h| h.work.as_mut()) else {
+            continue;
+        };
+        if let WorkKind::Firefighter { target, .. } = &mut w.kind {
+            *target = Some(id);
+            fire.responder = Some(station);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::desire::WorkKind;
+use crate::utils::resources::Resources;
+use crate::world::HumanID;
+use crate::{SoulID, World};
+use geom::Vec2;
+use prototypes::TICKS_PER_HOUR;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Garbage produced by a house, in kilograms per hour
+pub const HOUSE_GARBAGE_PRODUCTION: f32 = 1.0;
+
+/// Above this amount of garbage in kilograms, the building is dirty
+/// and the productivity of its occupants drops
+pub const GARBAGE_THRESHOLD: f32 = 50.0;
+
+/// Garbage piles up to this amount in kilograms, then is not produced anymore
+pub const MAX_GARBAGE: f32 = 500.0;
+
+/// Buildings with less garbage than this are not worth a truck trip
+const MIN_PICKUP: f32 = 5.0;
+
+/// How much garbage lies in each building, in kilograms
+#[derive(Default, Serialize, Deserialize)]
+pub struct Garbage {
+    levels: BTreeMap<BuildingID, f32>,
+}
+
+impl Garbage {
+    pub fn level(&self, building: BuildingID) -> f32 {
+        self.levels.get(&building).copied().unwrap_or(0.0)
+    }
+
+    /// Whether the garbage of the building is above [`GARBAGE_THRESHOLD`]
+    pub fn is_dirty(&self, building: BuildingID) -> bool {
+        self.level(building) > GARBAGE_THRESHOLD
+    }
+
+    pub fn produce(&mut self, building: BuildingID, amount: f32) {
+        let v = self.levels.entry(building).or_default();
+        *v = (*v + amount).min(MAX_GARBAGE);
+    }
+
+    /// Removes up to `capacity` kilograms from the building and returns how much was picked up
+    pub fn collect(&mut self, building: BuildingID, capacity: f32) -> f32 {
+        let Some(v) = self.levels.get_mut(&building) else {
+            return 0.0;
+        };
+        let picked = v.min(capacity);
+        *v -= picked;
+        picked
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BuildingID, f32)> + '_ {
+        self.levels.iter().map(|(&b, &v)| (b, v))
+    }
+
+    /// Average garbage level of the buildings producing garbage
+    pub fn average(&self) -> f32 {
+        if self.levels.is_empty() {
+            return 0.0;
+        }
+        self.levels.values().sum::<f32>() / self.levels.len() as f32
+    }
+}
+
+/// Picks the building where a truck at `from` should go next,
+/// greedily maximizing the garbage level over the distance.
+/// Buildings in `taken` are already the target of another truck.
+pub fn pick_garbage_target(
+    candidates: impl Iterator<Item = (BuildingID, Vec2, f32)>,
+    from: Vec2,
+    taken: &BTreeSet<BuildingID>,
+) -> Option<BuildingID> {
+    candidates
+        .filter(|(b, _, level)| *level >= MIN_PICKUP && !taken.contains(b))
+        .map(|(b, pos, level)| (b, level / (pos.distance(from) + 1.0)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(b, _)| b)
+}
+
+/// Accumulates the garbage of the buildings and sends idle garbage trucks
+/// to the dirtiest buildings reachable from their company
+pub fn garbage_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::garbage");
+
+    let map = resources.read::<Map>();
+    let binfos = resources.read::<BuildingInfos>();
+    let mut garbage = resources.write::<Garbage>();
+
+    garbage.levels.retain(|b, _| map.buildings.contains_key(*b));
+
+    let to_hours = 1.0 / TICKS_PER_HOUR as f32;
+
+    for (id, b) in map.buildings.iter() {
+        let production = match b.kind {
+            BuildingKind::House => HOUSE_GARBAGE_PRODUCTION,
+            BuildingKind::GoodsCompany(comp) => {
+                let proto = comp.prototype();
+                if proto.garbage_production == 0.0 {
+                    continue;
+                }
+                let Some(SoulID::GoodsCompany(owner)) = binfos.owner(id) else {
+                    continue;
+                };
+                let Some(ent) = world.companies.get(owner) else {
+                    continue;
+                };
+                proto.garbage_production * ent.raw_productivity(proto, b.zone.as_ref())
+            }
+            BuildingKind::Warehouse(w) => w.prototype().garbage_production,
+            BuildingKind::School(s) => s.prototype().garbage_production,
+            BuildingKind::Leisure(l) => l.prototype().garbage_production,
+            BuildingKind::Hotel(h) => h.prototype().garbage_production,
+            BuildingKind::Harbor(h) => h.prototype().garbage_production,
+            BuildingKind::Airport(a) => a.prototype().garbage_production,
+            BuildingKind::RailFreightStation(_)
+            | BuildingKind::RailPassengerStation(_)
+            | BuildingKind::TrainStation
+            | BuildingKind::ExternalTrading => continue,
+        };
+        garbage.produce(id, production * to_hours);
+    }
+
+    let mut taken = BTreeSet::new();
+    let mut idle: Vec<(HumanID, BuildingID)> = Vec::new();
+
+    for (_, c) in world.companies.iter() {
+        if c.comp.proto.prototype().garbage_truck_capacity == 0.0 {
+            continue;
+        }
+        let Some(driver) = c.comp.driver else {
+            continue;
+        };
+        let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
+            continue;
+        };
+        match w.kind {
+            WorkKind::GarbageCollector {
+                target: Some(target),
+                ..
+            } => {
+                taken.insert(target);
+            }
+            WorkKind::GarbageCollector { target: None, .. } => {
+                idle.push((driver, c.comp.building));
+            }
+            _ => {}
+        }
+    }
+
+    for (driver, depot) in idle {
+        let Some(depot_b) = map.buildings.get(depot) else {
+            continue;
+        };
+        // trucks only drive on the roads connected to their depot
+        let Some(network) = map.electricity.net_id(depot) else {
+            continue;
+        };
+        let from = depot_b.door_pos.xy();
+
+        let candidates = garbage.iter().filter_map(|(b, level)| {
+            if map.electricity.net_id(b) != Some(network) {
+                return None;
+            }
+            Some((b, map.buildings.get(b)?.door_pos.xy(), level))
+        });
+
+        let Some(target) = pick_garbage_target(candidates, from, &taken) else {
+            continue;
+        };
+        taken.insert(target);
+
+        let Some(w) = world.humans.get_mut(driver).and_then(|h| h.work.as_mut()) else {
+            continue;
+        };
+        if let WorkKind::GarbageCollector { target: t, .. } = &mut w.kind {
+            *t = Some(target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec2;
+
+    fn bid(id: u64) -> BuildingID {
+        BuildingID::from(slotmapd::KeyData::from_ffi((1 << 32) | id))
+    }
+
+    #[test]
+    fn target_is_dirtiest_over_distance() {
+        let candidates = [
+            (bid(1), vec2(10.0, 0.0), 20.0),
+            (bid(2), vec2(100.0, 0.0), 100.0),
+            (bid(3), vec2(1000.0, 0.0), 300.0),
+        ];
+
+        let taken = BTreeSet::new();
+        assert_eq!(
+            pick_garbage_target(candidates.into_iter(), Vec2::ZERO, &taken),
+            Some(bid(1))
+        );
+
+        let taken = BTreeSet::from([bid(1)]);
+        assert_eq!(
+            pick_garbage_target(candidates.into_iter(), Vec2::ZERO, &taken),
+            Some(bid(2))
+        );
+    }
+
+    #[test]
+    fn clean_buildings_are_not_visited() {
+        let candidates = [(bid(1), vec2(10.0, 0.0), MIN_PICKUP * 0.5)];
+        assert_eq!(
+            pick_garbage_target(candidates.into_iter(), Vec2::ZERO, &BTreeSet::new()),
+            None
+        );
+    }
+}
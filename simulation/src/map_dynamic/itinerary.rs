@@ -1,4 +1,4 @@
-use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map::{LaneID, Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
 use crate::utils::resources::Resources;
 use crate::world::TrainID;
 use crate::World;
@@ -48,10 +48,38 @@ pub struct Route {
     pub reversed_route: Vec<Traversable>,
     pub end_pos: Vec3,
     pub cur: Traversable,
+    /// Expected time to drive the route when it was planned
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub planned_cost: f32,
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub planned_at: Tick,
 }
 
 pub const OBJECTIVE_OK_DIST: f32 = 3.0;
 
+/// Ticks to wait before searching again a route that wasn't found
+const ROUTE_RETRY_TICKS: u16 = 200;
+
+/// Routes whose remaining lanes got this much slower than planned are searched again
+const REROUTE_SLOWDOWN: f32 = 1.5;
+
+/// Delay in seconds tolerated on top of the slowdown, so that short trips are left alone
+const REROUTE_SLACK: f32 = 30.0;
+
+impl Route {
+    /// Expected time to drive the lanes left in the route given the measured speeds
+    pub fn remaining_cost(&self, map: &Map) -> f32 {
+        std::iter::once(&self.cur)
+            .chain(&self.reversed_route)
+            .filter_map(|t| match t.kind {
+                TraverseKind::Lane(id) => map.lanes().get(id),
+                _ => None,
+            })
+            .map(|l| map.lane_travel_time(l))
+            .sum()
+    }
+}
+
 impl Itinerary {
     pub const NONE: Self = Self {
         kind: ItineraryKind::None,
@@ -113,6 +141,8 @@ impl Itinerary {
                             reversed_route: vec![],
                             end_pos: end,
                             cur,
+                            planned_cost: 0.0,
+                            planned_at: tick,
                         },
                         pathkind,
                     ),
@@ -140,16 +170,31 @@ impl Itinerary {
             }
         }
 
-        let kind = ItineraryKind::Route(
-            Route {
-                reversed_route,
-                end_pos: end,
-                cur,
-            },
-            pathkind,
-        );
+        let to_crossing = crossing_start(map, &reversed_route);
+
+        let mut route = Route {
+            reversed_route,
+            end_pos: end,
+            cur,
+            planned_cost: 0.0,
+            planned_at: tick,
+        };
+        route.planned_cost = route.remaining_cost(map);
+
+        let kind = ItineraryKind::Route(route, pathkind);
 
         let points = cur.points(map)?;
+
+        // walk along the sidewalk up to the crossing right away
+        if let Some(target) = to_crossing {
+            let mut p = pathkind.local_route(map, start_lane, start, target)?;
+            p.reverse();
+            return Some(Self {
+                kind,
+                reversed_local_path: p.into_vec(),
+            });
+        }
+
         let (proj, segid, dir) = points.project_segment_dir(start);
 
         let mut points = points.into_vec();
@@ -173,7 +218,7 @@ impl Itinerary {
 
         if self.reversed_local_path.is_empty() {
             if let ItineraryKind::Route(ref mut r, pathkind) = self.kind {
-                r.cur = r.reversed_route.pop()?;
+                let prev = std::mem::replace(&mut r.cur, r.reversed_route.pop()?);
 
                 let points = match r.cur.points(map) {
                     Some(x) => x,
@@ -183,11 +228,21 @@ impl Itinerary {
                     }
                 };
 
+                // lanes are only walked partially before and after a mid-road crossing
+                let from_crossing = matches!(prev.kind, TraverseKind::Crossing(_));
+                let to_crossing = crossing_start(map, &r.reversed_route);
+
                 if r.reversed_route.is_empty() {
                     self.reversed_local_path = pathkind
                         .local_route(map, r.cur.destination_lane(), position, r.end_pos)
                         .unwrap_or(points)
                         .into_vec();
+                } else if r.cur.kind.is_lane() && (from_crossing || to_crossing.is_some()) {
+                    let target = to_crossing.unwrap_or_else(|| points.last());
+                    self.reversed_local_path = pathkind
+                        .local_route(map, r.cur.destination_lane(), position, target)
+                        .unwrap_or(points)
+                        .into_vec();
                 } else {
                     self.reversed_local_path = points.into_vec();
                 }
@@ -201,7 +256,6 @@ impl Itinerary {
         &mut self,
         mut position: Vec3,
         mut dist_to_move: f32,
-        tick: Tick,
         time: u32,
         map: &Map,
     ) -> Vec3 {
@@ -235,25 +289,45 @@ impl Itinerary {
             return position + (p - position).normalize_to(dist_to_move);
         }
 
+        // the route itself is computed by the routing queue
         if let ItineraryKind::WaitForReroute {
-            kind,
-            dest,
-            ref mut wait_ticks,
+            ref mut wait_ticks, ..
         } = self.kind
         {
-            if *wait_ticks > 0 {
-                *wait_ticks -= 1;
-                return position;
-            }
-            *self = unwrap_or!(Self::route(tick, position, dest, map, kind), {
-                *wait_ticks = 200;
-                return position;
-            });
+            *wait_ticks = wait_ticks.saturating_sub(1);
         }
 
         position
     }
 
+    /// The kind and destination of the route to search, None if it isn't waiting for a route
+    /// or still waiting before retrying a route that wasn't found
+    pub fn awaited_route(&self) -> Option<(PathKind, Vec3)> {
+        match self.kind {
+            ItineraryKind::WaitForReroute {
+                kind,
+                dest,
+                wait_ticks: 0,
+            } => Some((kind, dest)),
+            _ => None,
+        }
+    }
+
+    /// Follows the route found by the routing queue, or waits before searching again
+    pub fn set_route(&mut self, route: Option<Itinerary>) {
+        match route {
+            Some(route) => *self = route,
+            None => {
+                if let ItineraryKind::WaitForReroute {
+                    ref mut wait_ticks, ..
+                } = self.kind
+                {
+                    *wait_ticks = ROUTE_RETRY_TICKS;
+                }
+            }
+        }
+    }
+
     pub fn random_route(
         rng: u64,
         position: Vec3,
@@ -337,6 +411,19 @@ impl Itinerary {
         &self.reversed_local_path
     }
 
+    /// Points left to follow in order, including the lanes and turns of the route not reached yet
+    pub fn remaining_path(&self, map: &Map) -> Vec<Vec3> {
+        let mut path: Vec<Vec3> = self.reversed_local_path.iter().rev().copied().collect();
+        if let ItineraryKind::Route(ref r, _) = self.kind {
+            for t in r.reversed_route.iter().rev() {
+                if let Some(p) = t.points(map) {
+                    path.extend(p.iter().copied());
+                }
+            }
+        }
+        path
+    }
+
     pub fn prepend_local_path(&mut self, points: impl IntoIterator<Item = Vec3>) {
         self.reversed_local_path.extend(points);
     }
@@ -362,6 +449,41 @@ impl Itinerary {
     pub fn is_simple(&self) -> bool {
         matches!(self.kind, ItineraryKind::Simple(_))
     }
+
+    /// Whether what remains of the route goes through one of the lanes
+    pub fn uses_lanes(&self, lanes: &[LaneID]) -> bool {
+        let ItineraryKind::Route(ref r, _) = self.kind else {
+            return false;
+        };
+        std::iter::once(&r.cur)
+            .chain(&r.reversed_route)
+            .any(|t| match t.kind {
+                TraverseKind::Lane(id) => lanes.contains(&id),
+                TraverseKind::Turn(id) => lanes.contains(&id.src) || lanes.contains(&id.dst),
+                TraverseKind::Crossing(id) => lanes.contains(&id.src) || lanes.contains(&id.dst),
+            })
+    }
+
+    /// Whether the road route became much slower than when it was planned,
+    /// for example because a traffic jam formed on the lanes ahead
+    pub fn is_congested(&self, map: &Map, tick: Tick) -> bool {
+        let ItineraryKind::Route(ref r, PathKind::Vehicle) = self.kind else {
+            return false;
+        };
+        if !r.cur.kind.is_lane() || r.reversed_route.is_empty() {
+            return false;
+        }
+        let elapsed = tick.0.saturating_sub(r.planned_at.0) as f32 * DELTA;
+        let expected = (r.planned_cost - elapsed).max(0.0);
+        r.remaining_cost(map) > expected * REROUTE_SLOWDOWN + REROUTE_SLACK
+    }
+
+    /// Drops the route, a new one to the same destination is searched on the next update
+    pub fn reroute(&mut self) {
+        if let ItineraryKind::Route(ref r, kind) = self.kind {
+            *self = Self::wait_for_reroute(kind, r.end_pos);
+        }
+    }
 }
 
 impl Inspect<ItineraryKind> for ItineraryKind {
@@ -416,11 +538,10 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::itinerary_update");
     let time = &*resources.read::<GameTime>();
     let map = &*resources.read::<Map>();
-    let tick = resources.read::<GameTime>().tick;
 
     world.query_it_trans_speed().for_each(
         |(it, trans, speed): (&mut Itinerary, &mut Transform, f32)| {
-            trans.pos = it.update(trans.pos, speed * DELTA, tick, time.seconds, map);
+            trans.pos = it.update(trans.pos, speed * DELTA, time.seconds, map);
         },
     );
 
@@ -436,3 +557,12 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
         wagon.trans.dir = (dir + dir2).try_normalize().unwrap_or(dir);
     });
 }
+
+/// Where the next traversable starts if it is a mid-road crossing
+fn crossing_start(map: &Map, reversed_route: &[Traversable]) -> Option<Vec3> {
+    let next = reversed_route.last()?;
+    if !matches!(next.kind, TraverseKind::Crossing(_)) {
+        return None;
+    }
+    Some(next.points(map)?.first())
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{vec2, Radians, Vec2, AABB};
+use prototypes::CompanyKind;
+
+use crate::map::{BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::ElectricityFlow;
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// Side of a land value cell in meters
+pub const LAND_VALUE_CELL_SIZE: f32 = 32.0;
+
+/// Value of land with road access and nothing good or bad around
+pub const BASE_LAND_VALUE: f32 = 1.0;
+
+/// Land worth at least this much grows the richer variants of buildings
+pub const RICH_LAND_VALUE: f32 = 1.3;
+
+/// Cells evaluated each tick, the whole map is swept in turn
+const CELLS_PER_TICK: u64 = 256;
+
+/// Land farther than this from a road has no value
+const ROAD_ACCESS_RADIUS: f32 = 60.0;
+
+/// Shops and leisure within this distance make the land more valuable
+const SERVICE_RADIUS: f32 = 300.0;
+const SHOPS_WEIGHT: f32 = 0.3;
+/// Number of shops nearby after which more do not matter
+const MAX_SHOPS: f32 = 3.0;
+const LEISURE_WEIGHT: f32 = 0.5;
+
+/// Land within this distance of the water has a view on it
+const WATER_RADIUS: f32 = 60.0;
+const WATER_WEIGHT: f32 = 0.3;
+
+const NOISE_WEIGHT: f32 = 0.6;
+/// Road noise at which the land loses all of [`NOISE_WEIGHT`]
+const LOUD_NOISE: f32 = 10.0;
+
+const CONGESTION_WEIGHT: f32 = 0.3;
+
+const BLACKOUT_WEIGHT: f32 = 0.4;
+/// How much each evaluation of a cell weighs in its blackout frequency
+const BLACKOUT_ALPHA: f32 = 0.1;
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+struct LandCell {
+    value: f32,
+    /// Share of the evaluations where the buildings of the cell were without power
+    blackouts: f32,
+}
+
+/// How much households are willing to pay to live somewhere, sampled on a coarse grid.
+/// A few cells are evaluated each tick from what is around them: shops and leisure nearby,
+/// the water, road noise, congestion and how often the power goes out.
+/// Only the cells with road access are stored.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LandValue {
+    /// Indexed by (y, x) like the zoning cells
+    cells: BTreeMap<(i32, i32), LandCell>,
+    /// Next cell to evaluate, in row order over the map
+    cursor: u64,
+}
+
+impl LandValue {
+    fn cell(pos: Vec2) -> (i32, i32) {
+        (
+            (pos.y / LAND_VALUE_CELL_SIZE).floor() as i32,
+            (pos.x / LAND_VALUE_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    fn cell_aabb((y, x): (i32, i32)) -> AABB {
+        let ll = vec2(x as f32, y as f32) * LAND_VALUE_CELL_SIZE;
+        AABB::new_ll_ur(ll, ll + Vec2::splat(LAND_VALUE_CELL_SIZE))
+    }
+
+    /// Value of the land at the position, 0 without road access
+    pub fn land_value(&self, pos: Vec2) -> f32 {
+        self.cells
+            .get(&Self::cell(pos))
+            .map_or(0.0, |cell| cell.value)
+    }
+
+    /// Number of floors of a house built on land of this value
+    pub fn house_floors(value: f32) -> u32 {
+        if value >= RICH_LAND_VALUE {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// The cells with road access and their value
+    pub fn iter(&self) -> impl Iterator<Item = (AABB, f32)> + '_ {
+        self.cells
+            .iter()
+            .map(|(&cell, c)| (Self::cell_aabb(cell), c.value))
+    }
+
+    /// Evaluates the next `n` cells of the map
+    pub fn update(&mut self, map: &Map, elec: &ElectricityFlow, n: u64) {
+        let bounds = map.environment.bounds();
+        let (lly, llx) = Self::cell(bounds.ll);
+        let (ury, urx) = Self::cell(bounds.ur - Vec2::splat(0.01));
+        let w = (urx - llx + 1).max(0) as u64;
+        let h = (ury - lly + 1).max(0) as u64;
+        let total = w * h;
+        if total == 0 {
+            return;
+        }
+
+        for _ in 0..n.min(total) {
+            let i = self.cursor % total;
+            self.cursor = (i + 1) % total;
+            let cell = (lly + (i / w) as i32, llx + (i % w) as i32);
+
+            let blackouts = self.cells.get(&cell).map_or(0.0, |c| c.blackouts);
+            match evaluate(map, elec, Self::cell_aabb(cell), blackouts) {
+                Some(c) => self.cells.insert(cell, c),
+                None => self.cells.remove(&cell),
+            };
+        }
+    }
+}
+
+/// Value of the cell, None if no road reaches it
+fn evaluate(map: &Map, elec: &ElectricityFlow, aabb: AABB, blackouts: f32) -> Option<LandCell> {
+    let center = aabb.center();
+
+    let mut has_road = false;
+    let mut congestion = 0.0;
+    let mut n_lanes = 0;
+    for obj in map
+        .spatial_map()
+        .query_around(center, ROAD_ACCESS_RADIUS, ProjectFilter::ROAD)
+    {
+        let ProjectKind::Road(id) = obj else {
+            continue;
+        };
+        let Some(road) = map.roads().get(id) else {
+            continue;
+        };
+        has_road = true;
+        for (lane, _) in road.lanes_iter().filter(|(_, kind)| kind.vehicles()) {
+            congestion += map.lane_congestion(lane);
+            n_lanes += 1;
+        }
+    }
+    if !has_road {
+        return None;
+    }
+    let congestion = congestion / n_lanes.max(1) as f32;
+
+    let mut shops = 0.0;
+    let mut leisure = 0.0;
+    for obj in map
+        .spatial_map()
+        .query_around(center, SERVICE_RADIUS, ProjectFilter::BUILDING)
+    {
+        let ProjectKind::Building(id) = obj else {
+            continue;
+        };
+        let Some(b) = map.buildings().get(id) else {
+            continue;
+        };
+        let closeness = (1.0 - b.obb.center().distance(center) / SERVICE_RADIUS).max(0.0);
+        match b.kind {
+            BuildingKind::GoodsCompany(id) if id.prototype().kind == CompanyKind::Store => {
+                shops += 1.0
+            }
+            BuildingKind::Leisure(_) => leisure += closeness,
+            _ => {}
+        }
+    }
+
+    let water = (0..8).any(|i| {
+        let dir = Vec2::from_angle(Radians(i as f32 * std::f32::consts::FRAC_PI_4));
+        map.environment
+            .true_height(center + dir * WATER_RADIUS)
+            .map_or(false, |h| h < 0.0)
+    });
+
+    let mut powered = 0;
+    let mut unpowered = 0;
+    for obj in map.spatial_map().query(aabb, ProjectFilter::BUILDING) {
+        let ProjectKind::Building(id) = obj else {
+            continue;
+        };
+        let net_blackout = map
+            .electricity
+            .net_id(id)
+            .map_or(false, |net| elec.blackout(net));
+        if net_blackout || elec.is_shed(id) {
+            unpowered += 1;
+        } else {
+            powered += 1;
+        }
+    }
+    let blackouts = if powered + unpowered == 0 {
+        blackouts
+    } else {
+        let sample = unpowered as f32 / (powered + unpowered) as f32;
+        blackouts + (sample - blackouts) * BLACKOUT_ALPHA
+    };
+
+    let value = BASE_LAND_VALUE
+        + SHOPS_WEIGHT * (shops / MAX_SHOPS).min(1.0)
+        + LEISURE_WEIGHT * leisure.min(1.0)
+        + if water { WATER_WEIGHT } else { 0.0 }
+        - NOISE_WEIGHT * (map.noise_at(center) / LOUD_NOISE).min(1.0)
+        - CONGESTION_WEIGHT * congestion
+        - BLACKOUT_WEIGHT * blackouts;
+
+    Some(LandCell {
+        value: value.max(0.0),
+        blackouts,
+    })
+}
+
+/// Evaluates a few cells of the land value grid
+pub fn land_value_system(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::land_value_system");
+    let map = resources.read::<Map>();
+    let elec = resources.read::<ElectricityFlow>();
+    resources
+        .write::<LandValue>()
+        .update(&map, &elec, CELLS_PER_TICK);
+}
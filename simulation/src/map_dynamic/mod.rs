@@ -1,13 +1,25 @@
 mod binfos;
 mod dispatch;
 mod electricity;
+mod fire;
+mod garbage;
 mod itinerary;
+mod land_value;
 mod parking;
 mod router;
+mod routing_queue;
+mod water;
+mod zone_growth;
 
 pub use binfos::*;
 pub use dispatch::*;
 pub use electricity::*;
+pub use fire::*;
+pub use garbage::*;
 pub use itinerary::*;
+pub use land_value::*;
 pub use parking::*;
 pub use router::*;
+pub use routing_queue::*;
+pub use water::*;
+pub use zone_growth::*;
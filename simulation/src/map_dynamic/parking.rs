@@ -1,8 +1,8 @@
-use crate::map::{Lane, LaneKind, Map, ParkingSpot, ParkingSpotID, ParkingSpots};
+use crate::map::{BuildingID, Lane, LaneKind, Map, ParkingSpot, ParkingSpotID, ParkingSpots};
 use common::AccessCmp;
 use geom::Vec3;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::option::Option::None;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,8 +12,13 @@ pub struct SpotReservation(ParkingSpotID);
 #[derive(Default, Serialize, Deserialize)]
 pub struct ParkingManagement {
     reserved_spots: BTreeSet<ParkingSpotID>,
+    /// Number of drivers that couldn't find a spot near each building
+    failed_parking: BTreeMap<BuildingID, u32>,
 }
 
+/// Spots farther than this from the destination are not worth walking from
+pub const PARKING_SEARCH_RADIUS: f32 = 150.0;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum ParkingReserveError {
     FindingNearestLane,
@@ -39,6 +44,20 @@ impl ParkingManagement {
         !self.reserved_spots.contains(&spot)
     }
 
+    pub fn failed_parking(&self, building: BuildingID) -> u32 {
+        self.failed_parking.get(&building).copied().unwrap_or(0)
+    }
+
+    pub fn record_failed_parking(&mut self, building: BuildingID) {
+        *self.failed_parking.entry(building).or_default() += 1;
+    }
+
+    /// Forget the failure counters of buildings that were removed from the map
+    pub fn prune_failed_parking(&mut self, map: &Map) {
+        self.failed_parking
+            .retain(|b, _| map.buildings().contains_key(*b));
+    }
+
     pub fn reserve_random_free_spot(
         &mut self,
         spots: &ParkingSpots,
@@ -99,6 +118,12 @@ impl ParkingManagement {
 
                 if let Some(p_iter) = map.parking.closest_spots(plane, near) {
                     for spot in p_iter {
+                        let Some(p) = map.parking.get(spot) else {
+                            continue;
+                        };
+                        if !p.trans.pos.is_close(near, PARKING_SEARCH_RADIUS) {
+                            continue;
+                        }
                         if self.reserved_spots.insert(spot) {
                             return Ok(SpotReservation(spot));
                         }
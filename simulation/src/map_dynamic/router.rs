@@ -1,5 +1,8 @@
-use crate::map::{BuildingID, Map, PathKind};
+use crate::map::{BuildingID, LaneKind, Map, PathKind};
 use crate::map_dynamic::{Itinerary, ParkingManagement, ParkingReserveError, SpotReservation};
+use crate::statistics::Statistics;
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::transportation::transit::{direct_trip_secs, BusLineID, BusStopID, Transit};
 use crate::transportation::TransportGrid;
 use crate::transportation::{put_pedestrian_in_transport_grid, unpark, Location, VehicleState};
 use crate::utils::resources::Resources;
@@ -7,6 +10,7 @@ use crate::world::{HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::{ParCommandBuffer, World};
 use egui_inspect::Inspect;
 use geom::{Spline3, Transform, Vec3};
+use prototypes::GameTime;
 use serde::{Deserialize, Serialize};
 use slotmapd::HopSlotMap;
 
@@ -19,8 +23,14 @@ pub struct Router {
     vehicle: Option<VehicleID>,
     pub personal_car: Option<VehicleID>,
     pub last_error: Option<RouterError>,
+    /// How many times the driver circled around the destination looking for a spot
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    parking_retries: u32,
 }
 
+/// After circling this many times, drivers park wherever they can and walk the rest
+pub const MAX_PARKING_RETRIES: u32 = 3;
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum RouterError {
     ReservingParkingSpot(ParkingReserveError),
@@ -48,6 +58,12 @@ pub enum RoutingStep {
     GetOutVehicle(VehicleID),
     GetInBuilding(BuildingID),
     GetOutBuilding(BuildingID),
+    /// Circled around without finding a spot, look for one again
+    Reroute,
+    /// Wait at the first stop for a bus of the line, get off at the second one
+    RideBus(BusLineID, BusStopID, BusStopID),
+    /// Wait on the platform of the first station for a train, get off at the second one
+    RideTrain(BuildingID, BuildingID),
 }
 
 debug_inspect_impl!(RoutingStep);
@@ -56,26 +72,36 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::routing_changed_system");
     let map: &Map = &resources.read();
     let parking: &mut ParkingManagement = &mut resources.write();
+    let transit: &Transit = &resources.read();
+    let rail: &PassengerRail = &resources.read();
+    let stats: &mut Statistics = &mut resources.write();
+
+    parking.prune_failed_parking(map);
 
     world.humans.values_mut().for_each(|h| {
         let router = &mut h.router;
         let loc = &h.location;
+        if matches!(
+            router.cur_step,
+            Some(RoutingStep::RideBus(..) | RoutingStep::RideTrain(..))
+        ) {
+            // wait to get off the bus or train before going somewhere else
+            return;
+        }
+        if let Some(Destination::Building(build)) = router.cur_dest {
+            // the destination was removed while on the way, release any reserved spot
+            if !map.buildings.contains_key(build) && !router.steps.is_empty() {
+                router.reset_dest();
+            }
+        }
         if router.cur_dest == router.target_dest {
             return;
         }
         let dest = unwrap_ret!(router.target_dest);
 
         router.clear_steps(parking);
-        match dest {
-            Destination::Outside(pos) => {
-                router.steps = match router.steps_to(pos, parking, map, loc, &world.vehicles) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        router.last_error = Some(e);
-                        return;
-                    }
-                };
-            }
+        let (obj, build) = match dest {
+            Destination::Outside(pos) => (pos, None),
             Destination::Building(build) => {
                 if let Location::Building(cur_build) = loc {
                     if *cur_build == build {
@@ -91,27 +117,40 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
                         return;
                     }
                 };
-                let door_pos = bobj.door_pos;
-                router.steps = match router.steps_to(door_pos, parking, map, loc, &world.vehicles) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        router.last_error = Some(e);
-                        return;
-                    }
-                };
-                router.steps.push(RoutingStep::GetInBuilding(build));
+                (bobj.door_pos, Some(build))
             }
+        };
+
+        let steps =
+            match router.transit_steps(transit, rail, h.trans.pos, obj, loc, &world.vehicles) {
+                Some(x) => Ok(x),
+                None => router.steps_to(obj, build, parking, map, loc, &world.vehicles),
+            };
+        router.steps = match steps {
+            Ok(x) => x,
+            Err(e) => {
+                router.last_error = Some(e);
+                return;
+            }
+        };
+        if let Some(build) = build {
+            router.steps.push(RoutingStep::GetInBuilding(build));
         }
 
         router.cur_dest = router.target_dest;
 
         router.steps.reverse();
+        stats.trip_started();
     });
 }
 
 pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::routing_update_system");
     let map: &Map = &resources.read();
+    let parking: &mut ParkingManagement = &mut resources.write();
+    let transit: &mut Transit = &mut resources.write();
+    let rail: &mut PassengerRail = &mut resources.write();
+    let now = resources.read::<GameTime>().timestamp;
     let cbuf_human: &ParCommandBuffer<HumanEnt> = &resources.read();
     let cbuf_vehicle: &ParCommandBuffer<VehicleEnt> = &resources.read();
 
@@ -130,6 +169,11 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 .get(id)
                 .map(|x| x.trans.pos)
                 .unwrap_or_else(|| trans.pos),
+            Location::Train(id) => world
+                .trains
+                .get(id)
+                .map(|x| x.trans.pos)
+                .unwrap_or_else(|| trans.pos),
             Location::Building(id) => map
                 .buildings()
                 .get(id)
@@ -159,6 +203,9 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 RoutingStep::GetOutVehicle(_) => true,
                 RoutingStep::GetInBuilding(_) => true,
                 RoutingStep::GetOutBuilding(_) => true,
+                RoutingStep::Reroute => true,
+                RoutingStep::RideBus(..) => transit.trip_over(body),
+                RoutingStep::RideTrain(..) => rail.trip_over(body),
             };
         }
         let mut next_step_ready = true;
@@ -181,6 +228,9 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                     .map(|b| b.door_pos.is_close(pos, 3.0))
                     .unwrap_or(true),
                 RoutingStep::GetOutBuilding(_) => true,
+                RoutingStep::Reroute => true,
+                RoutingStep::RideBus(..) => true,
+                RoutingStep::RideTrain(..) => true,
             };
         }
 
@@ -188,6 +238,11 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
             return;
         }
 
+        match h.router.cur_step {
+            Some(RoutingStep::RideBus(..)) => transit.end_trip(body),
+            Some(RoutingStep::RideTrain(..)) => rail.end_trip(body),
+            _ => {}
+        }
         h.router.cur_step = h.router.steps.pop();
 
         if let Some(ref mut next_step) = h.router.cur_step {
@@ -204,12 +259,14 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 RoutingStep::Park(vehicle, ref mut spot) => {
                     if let Some(spot_resa) = spot.take() {
                         if !spot_resa.exists(&map.parking) {
+                            parking.free(spot_resa);
                             h.router.reset_dest();
                             return;
                         }
 
-                        if let Some(vehicle) = world.vehicles.get_mut(vehicle) {
-                            park(map, vehicle, spot_resa)
+                        match world.vehicles.get_mut(vehicle) {
+                            Some(vehicle) => park(map, vehicle, spot_resa),
+                            None => parking.free(spot_resa),
                         }
                     }
                 }
@@ -249,6 +306,15 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                         .unwrap_or(pos);
                     walk_outside(body, wpos, cbuf_human, &mut h.location);
                 }
+                RoutingStep::Reroute => {
+                    h.router.reset_dest();
+                }
+                RoutingStep::RideBus(line, from, to) => {
+                    transit.wait_for_bus(body, line, from, to, now);
+                }
+                RoutingStep::RideTrain(from, to) => {
+                    rail.wait_for_train(body, from, to, now);
+                }
             }
         }
     })
@@ -302,6 +368,7 @@ impl Router {
             vehicle: personal_car,
             cur_dest: None,
             last_error: None,
+            parking_retries: 0,
         }
     }
 
@@ -317,6 +384,28 @@ impl Router {
         }
     }
 
+    /// Estimated duration in seconds of a direct trip, driving if there is a personal car
+    pub fn trip_secs(&self, from: Vec3, to: Vec3) -> f32 {
+        direct_trip_secs(from, to, self.personal_car.is_some())
+    }
+
+    /// Orders the stops of a trip leaving from `from`, such that the next stop is always the
+    /// closest of the ones left. Used by the drivers going to several buildings in one trip.
+    pub fn nearest_neighbor_tour<T>(from: Vec3, stops: &mut [T], pos: impl Fn(&T) -> Vec3) {
+        let mut cur = from;
+        for i in 0..stops.len() {
+            let closest = (i..stops.len())
+                .min_by(|&a, &b| {
+                    pos(&stops[a])
+                        .distance2(cur)
+                        .total_cmp(&pos(&stops[b]).distance2(cur))
+                })
+                .unwrap(); // Unwrap ok: i < stops.len()
+            stops.swap(i, closest);
+            cur = pos(&stops[i]);
+        }
+    }
+
     pub fn reset_dest(&mut self) {
         self.cur_dest = None;
     }
@@ -332,9 +421,63 @@ impl Router {
         false
     }
 
-    fn steps_to(
+    /// Walking to a stop or a station, riding the bus or the train and walking from there, if
+    /// it's faster than going there directly
+    fn transit_steps(
+        &self,
+        transit: &Transit,
+        rail: &PassengerRail,
+        from: Vec3,
+        obj: Vec3,
+        loc: &Location,
+        cars: &HopSlotMap<VehicleID, VehicleEnt>,
+    ) -> Option<Vec<RoutingStep>> {
+        // don't leave a work vehicle behind
+        if matches!(loc, Location::Vehicle(_)) || self.vehicle != self.personal_car {
+            return None;
+        }
+        // the car might have been left elsewhere, e.g. before riding the bus
+        let direct = match self.vehicle.and_then(|car| cars.get(car)) {
+            Some(car) => {
+                let car_pos = car.trans.pos;
+                direct_trip_secs(from, car_pos, false) + direct_trip_secs(car_pos, obj, true)
+            }
+            None => direct_trip_secs(from, obj, false),
+        };
+        let bus = transit.plan(from, obj).filter(|p| p.secs < direct);
+        let train = rail.plan(from, obj).filter(|p| p.secs < direct);
+
+        let (board_pos, ride) = match (bus, train) {
+            (Some(b), Some(t)) if b.secs <= t.secs => (
+                transit.stops().get(b.board)?.pos,
+                RoutingStep::RideBus(b.line, b.board, b.alight),
+            ),
+            (Some(b), None) => (
+                transit.stops().get(b.board)?.pos,
+                RoutingStep::RideBus(b.line, b.board, b.alight),
+            ),
+            (_, Some(t)) => (
+                rail.stations().get(&t.board)?.platform,
+                RoutingStep::RideTrain(t.board, t.alight),
+            ),
+            (None, None) => return None,
+        };
+
+        let mut steps = vec![];
+        if let Location::Building(cur_build) = loc {
+            steps.push(RoutingStep::GetOutBuilding(*cur_build));
+        }
+        steps.push(RoutingStep::WalkTo(board_pos));
+        steps.push(ride);
+        steps.push(RoutingStep::WalkTo(obj));
+        Some(steps)
+    }
+
+    /// `building` is the destination building, used to keep track of parking failures
+    pub(crate) fn steps_to(
         &mut self,
         obj: Vec3,
+        building: Option<BuildingID>,
         parking: &mut ParkingManagement,
         map: &Map,
         loc: &Location,
@@ -346,9 +489,46 @@ impl Router {
         }
 
         if let Some(car) = self.vehicle {
-            let spot_resa = parking
-                .reserve_near(obj, map)
-                .map_err(RouterError::ReservingParkingSpot)?;
+            let in_car = matches!(loc, Location::Vehicle(_));
+            let spot_resa = match parking.reserve_near(obj, map) {
+                Ok(x) => {
+                    self.parking_retries = 0;
+                    x
+                }
+                Err(e) => {
+                    // circling around is still the same failure
+                    if let (Some(b), 0) = (building, self.parking_retries) {
+                        parking.record_failed_parking(b);
+                    }
+                    if !in_car {
+                        // nowhere to park at the destination, leave the car and walk
+                        self.last_error = Some(RouterError::ReservingParkingSpot(e));
+                        steps.push(RoutingStep::WalkTo(obj));
+                        return Ok(steps);
+                    }
+                    if self.parking_retries < MAX_PARKING_RETRIES {
+                        self.parking_retries += 1;
+                        let around = circle_pos(map, obj, self.parking_retries)
+                            .ok_or(RouterError::ReservingParkingSpot(e))?;
+                        steps.push(RoutingStep::DriveTo(car, around));
+                        steps.push(RoutingStep::Reroute);
+                        return Ok(steps);
+                    }
+
+                    // give up, park wherever we are and walk the rest
+                    self.parking_retries = 0;
+                    let car_pos = cars
+                        .get(car)
+                        .map(|x| x.trans.pos)
+                        .ok_or(RouterError::LocatingVehicle)?;
+                    match parking.reserve_near(car_pos, map) {
+                        Ok(x) => x,
+                        Err(_) => parking
+                            .reserve_random_free_spot(&map.parking, common::hash_u64(car))
+                            .ok_or(RouterError::ReservingParkingSpot(e))?,
+                    }
+                }
+            };
             let parking_pos = match spot_resa.park_pos(map) {
                 Some(x) => x,
                 None => {
@@ -357,7 +537,7 @@ impl Router {
                 }
             };
 
-            if !matches!(loc, Location::Vehicle(_)) {
+            if !in_car {
                 if let Some(pos) = cars.get(car).map(|x| x.trans.pos) {
                     steps.push(RoutingStep::WalkTo(pos));
                     steps.push(RoutingStep::GetInVehicle(car));
@@ -378,3 +558,20 @@ impl Router {
         Ok(steps)
     }
 }
+
+/// A point on the road network around `near` to drive to while waiting for a spot to free up.
+/// Each attempt takes a different turn out of the closest lane.
+fn circle_pos(map: &Map, near: Vec3, attempt: u32) -> Option<Vec3> {
+    let lane = map.nearest_lane(near, LaneKind::Driving, None)?;
+    let lane = map.lanes().get(lane)?;
+    let inter = map.intersections().get(lane.dst)?;
+    let turns: Vec<_> = inter
+        .turns_from(lane.id)
+        .map(|(turn, _)| turn.dst)
+        .collect();
+    if turns.is_empty() {
+        return Some(lane.points.last());
+    }
+    let next = map.lanes().get(turns[attempt as usize % turns.len()])?;
+    Some(next.points.last())
+}
@@ -0,0 +1,289 @@
+//! Route searches of the whole tick, computed together on the thread pool.
+//! The entities waiting for a route ([`Itinerary::awaited_route`]) request it, the routes are
+//! searched in parallel against the map as it is at the end of the tick, and the entities
+//! start following them on the next tick.
+
+use std::collections::BTreeMap;
+
+use geom::Vec3;
+use prototypes::{GameTime, Tick};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::map::{Map, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::utils::resources::Resources;
+use crate::world::{HumanID, TrainID, VehicleID};
+use crate::World;
+
+/// Identifies a request, the results are applied in the order of the requests
+pub type RouteToken = u64;
+
+/// The entity following the itinerary the route is searched for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RouteOwner {
+    Human(HumanID),
+    Vehicle(VehicleID),
+    Train(TrainID),
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct RouteRequest {
+    pub owner: RouteOwner,
+    pub start: Vec3,
+    pub end: Vec3,
+    pub kind: PathKind,
+    pub tick: Tick,
+}
+
+impl RouteRequest {
+    /// Only depends on the request, so the routes are the same whichever thread computes them
+    pub fn compute(&self, map: &Map) -> Option<Itinerary> {
+        Itinerary::route(self.tick, self.start, self.end, map, self.kind)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RoutingQueue {
+    next_token: RouteToken,
+    requests: Vec<(RouteToken, RouteRequest)>,
+    /// Latest request of each owner still waiting for its route
+    awaiting: BTreeMap<RouteOwner, RouteToken>,
+    /// Routes found during the last tick, given to their owners on the next one
+    results: Vec<(RouteToken, RouteRequest, Option<Itinerary>)>,
+}
+
+impl RoutingQueue {
+    /// Queues the search of a route, it replaces the previous request of the owner
+    pub fn request(&mut self, req: RouteRequest) -> RouteToken {
+        let token = self.next_token;
+        self.next_token += 1;
+        self.awaiting.insert(req.owner, token);
+        self.requests.push((token, req));
+        token
+    }
+
+    pub fn is_awaiting(&self, owner: RouteOwner) -> bool {
+        self.awaiting.contains_key(&owner)
+    }
+
+    pub fn n_requests(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Searches the routes of all the requests in parallel
+    pub fn compute(&mut self, map: &Map) {
+        profiling::scope!("map_dynamic::RoutingQueue::compute");
+        let requests = std::mem::take(&mut self.requests);
+        let routes: Vec<_> = requests
+            .par_iter()
+            .map(|(_, req)| req.compute(map))
+            .collect();
+        self.results.extend(
+            requests
+                .into_iter()
+                .zip(routes)
+                .map(|((token, req), route)| (token, req, route)),
+        );
+    }
+
+    /// The routes found, sorted by request. The results of superseded requests are dropped.
+    pub fn take_results(&mut self) -> Vec<(RouteRequest, Option<Itinerary>)> {
+        let mut results = std::mem::take(&mut self.results);
+        results.sort_unstable_by_key(|(token, ..)| *token);
+        results
+            .into_iter()
+            .filter(|(token, req, _)| {
+                if self.awaiting.get(&req.owner) != Some(token) {
+                    return false;
+                }
+                self.awaiting.remove(&req.owner);
+                true
+            })
+            .map(|(_, req, route)| (req, route))
+            .collect()
+    }
+}
+
+/// Gives the routes found during the last tick to their owners, then searches the routes of
+/// the entities that are now waiting for one
+pub fn routing_queue_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::routing_queue_system");
+    let mut queue = resources.write::<RoutingQueue>();
+    let map = &*resources.read::<Map>();
+    let tick = resources.read::<GameTime>().tick;
+
+    for (req, route) in queue.take_results() {
+        let Some(it) = itinerary_mut(world, req.owner) else {
+            continue;
+        };
+        // the owner changed its mind while the route was searched
+        let Some((kind, dest)) = it.awaited_route() else {
+            continue;
+        };
+        if kind != req.kind || dest != req.end {
+            continue;
+        }
+        it.set_route(route);
+    }
+
+    let owners = world
+        .humans
+        .iter()
+        .map(|(id, h)| (RouteOwner::Human(id), &h.it, h.trans.pos))
+        .chain(
+            world
+                .vehicles
+                .iter()
+                .map(|(id, v)| (RouteOwner::Vehicle(id), &v.it, v.trans.pos)),
+        )
+        .chain(
+            world
+                .trains
+                .iter()
+                .map(|(id, t)| (RouteOwner::Train(id), &t.it, t.trans.pos)),
+        );
+    for (owner, it, pos) in owners {
+        let Some((kind, end)) = it.awaited_route() else {
+            continue;
+        };
+        if queue.is_awaiting(owner) {
+            continue;
+        }
+        queue.request(RouteRequest {
+            owner,
+            start: pos,
+            end,
+            kind,
+            tick,
+        });
+    }
+
+    queue.compute(map);
+}
+
+fn itinerary_mut(world: &mut World, owner: RouteOwner) -> Option<&mut Itinerary> {
+    match owner {
+        RouteOwner::Human(id) => world.humans.get_mut(id).map(|h| &mut h.it),
+        RouteOwner::Vehicle(id) => world.vehicles.get_mut(id).map(|v| &mut v.it),
+        RouteOwner::Train(id) => world.trains.get_mut(id).map(|t| &mut t.it),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use geom::{vec3, Vec2};
+    use prototypes::Tick;
+
+    use crate::map::procgen::add_grid;
+    use crate::map::{Map, PathKind};
+    use crate::world::VehicleID;
+
+    use super::{RouteOwner, RouteRequest, RoutingQueue};
+
+    fn owner(i: u64) -> RouteOwner {
+        RouteOwner::Vehicle(VehicleID::from(slotmapd::KeyData::from_ffi((1 << 32) | i)))
+    }
+
+    #[test]
+    fn superseded_requests_are_dropped() {
+        let mut m = Map::empty();
+        add_grid(&mut m, Vec2::ZERO, 3, 100.0);
+
+        let mut q = RoutingQueue::default();
+        let req = RouteRequest {
+            owner: owner(1),
+            start: vec3(-100.0, -100.0, 0.0),
+            end: vec3(100.0, 100.0, 0.0),
+            kind: PathKind::Vehicle,
+            tick: Tick(1),
+        };
+        q.request(req);
+        q.compute(&m);
+        q.request(RouteRequest {
+            end: vec3(-100.0, 100.0, 0.0),
+            ..req
+        });
+        q.compute(&m);
+        assert!(q.is_awaiting(owner(1)));
+
+        let results = q.take_results();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.end, vec3(-100.0, 100.0, 0.0));
+        assert!(results[0].1.is_some());
+        assert!(!q.is_awaiting(owner(1)));
+    }
+
+    /// Requests between random points of a `size` wide square centered on the origin
+    fn random_requests(n: u64, size: f32) -> Vec<RouteRequest> {
+        (0..n)
+            .map(|i| {
+                let r = |x: u64| (common::rand::randu(x as u32) - 0.5) * size;
+                RouteRequest {
+                    owner: owner(i),
+                    start: vec3(r(i * 4), r(i * 4 + 1), 0.0),
+                    end: vec3(r(i * 4 + 2), r(i * 4 + 3), 0.0),
+                    kind: if i % 3 == 0 {
+                        PathKind::Pedestrian
+                    } else {
+                        PathKind::Vehicle
+                    },
+                    tick: Tick(i / 100),
+                }
+            })
+            .collect()
+    }
+
+    /// Checks the queue gives the same routes as computing them one by one,
+    /// returns the serial and parallel times
+    fn compare_with_serial(m: &Map, requests: &[RouteRequest]) -> (Duration, Duration) {
+        let t = Instant::now();
+        let serial: Vec<_> = requests.iter().map(|r| r.compute(m)).collect();
+        let serial_time = t.elapsed();
+
+        let mut q = RoutingQueue::default();
+        for &r in requests {
+            q.request(r);
+        }
+        let t = Instant::now();
+        q.compute(m);
+        let parallel_time = t.elapsed();
+
+        let results = q.take_results();
+        assert_eq!(results.len(), serial.len());
+        for ((req, route), (expected_req, expected)) in
+            results.iter().zip(requests.iter().zip(&serial))
+        {
+            assert_eq!(req.owner, expected_req.owner);
+            let a = route.as_ref().and_then(|it| it.get_route());
+            let b = expected.as_ref().and_then(|it| it.get_route());
+            assert_eq!(a.map(|r| &r.reversed_route), b.map(|r| &r.reversed_route));
+        }
+
+        (serial_time, parallel_time)
+    }
+
+    #[test]
+    fn parallel_routes_match_serial() {
+        let mut m = Map::empty();
+        add_grid(&mut m, Vec2::ZERO, 5, 100.0);
+        m.landmarks();
+
+        compare_with_serial(&m, &random_requests(200, 450.0));
+    }
+
+    /// 5000 routes requested during the same tick, prints the serial and parallel times
+    /// cargo test --release -p simulation bench_5k_requests -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_5k_requests() {
+        let mut m = Map::empty();
+        add_grid(&mut m, Vec2::ZERO, 20, 100.0);
+        m.landmarks();
+
+        let (serial, parallel) = compare_with_serial(&m, &random_requests(5000, 1900.0));
+        println!("serial: {:?}, parallel: {:?}", serial, parallel);
+    }
+}
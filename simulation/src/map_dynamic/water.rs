@@ -0,0 +1,137 @@
+use crate::map::{BuildingID, BuildingKind, ElectricityNetworkID, Map};
+use crate::map_dynamic::{BuildingInfos, ElectricityFlow};
+use crate::utils::resources::Resources;
+use crate::{SoulID, World};
+use prototypes::{GameDuration, GameInstant, GameTime, Tick, TICKS_PER_HOUR};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Water pipes are laid along the roads like power lines,
+/// so the water networks are the electricity networks of the map
+pub type WaterNetworkID = ElectricityNetworkID;
+
+/// Water consumed by a house, in cubic meters per hour
+pub const HOUSE_WATER_CONSUMPTION: f32 = 0.5;
+
+/// Time given to build the first pumps before the buildings go dry,
+/// in new cities and in cities saved before water existed
+pub const WATER_GRACE: GameDuration = GameDuration(Tick(2 * 24 * TICKS_PER_HOUR));
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct WaterFlow {
+    flowmap: BTreeMap<WaterNetworkID, WaterNetworkFlow>,
+    /// Buildings that need water but their network does not produce enough
+    dry: BTreeSet<BuildingID>,
+    /// Nothing goes dry before then, starts on the first update
+    grace_until: Option<GameInstant>,
+}
+
+/// Water consumed and produced by a network during the last tick, in cubic meters per hour
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct WaterNetworkFlow {
+    pub consumed: f32,
+    pub produced: f32,
+}
+
+impl WaterFlow {
+    /// Whether the network consumes more water than it produces
+    pub fn shortage(&self, network: WaterNetworkID) -> bool {
+        self.flowmap
+            .get(&network)
+            .map_or(false, |f| f.consumed > f.produced)
+    }
+
+    pub fn network_stats(&self, network: WaterNetworkID) -> WaterNetworkFlow {
+        self.flowmap.get(&network).copied().unwrap_or_default()
+    }
+
+    /// Whether the building needs water and does not get it
+    pub fn is_dry(&self, building: BuildingID) -> bool {
+        self.dry.contains(&building)
+    }
+
+    /// End of the grace period, none before the first update
+    pub fn grace_until(&self) -> Option<GameInstant> {
+        self.grace_until
+    }
+}
+
+/// Compute the water flow of the map and store it in the [`WaterFlow`] resource
+/// Pump stations produce water and every other building consumes it.
+/// If a network consumes more than it produces, all its consumers go dry once the
+/// [`WATER_GRACE`] is over.
+pub fn water_flow_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::water_flow");
+
+    let map = resources.read::<Map>();
+    let binfos = resources.read::<BuildingInfos>();
+    let elec_flow = resources.read::<ElectricityFlow>();
+    let now = resources.read::<GameTime>().instant();
+    let mut flow = resources.write::<WaterFlow>();
+
+    flow.flowmap.clear();
+    flow.dry.clear();
+
+    let grace_until = *flow.grace_until.get_or_insert(now + WATER_GRACE);
+    let in_grace = now < grace_until;
+
+    let mut consumers = Vec::new();
+
+    for network in map.electricity.networks() {
+        consumers.clear();
+        let mut nflow = WaterNetworkFlow::default();
+
+        for &building in network.buildings.iter() {
+            let Some(b) = map.buildings.get(building) else {
+                continue;
+            };
+
+            let (consumption, production) = match b.kind {
+                BuildingKind::House => (HOUSE_WATER_CONSUMPTION, 0.0),
+                BuildingKind::GoodsCompany(comp) => {
+                    let proto = comp.prototype();
+
+                    let Some(SoulID::GoodsCompany(owner)) = binfos.owner(building) else {
+                        continue;
+                    };
+                    let Some(ent) = world.companies.get(owner) else {
+                        continue;
+                    };
+
+                    // pumps need power to run
+                    let productivity = if elec_flow.is_shed(building) {
+                        0.0
+                    } else {
+                        ent.raw_productivity(proto, b.zone.as_ref())
+                    };
+
+                    (
+                        proto.water_consumption * productivity,
+                        proto.water_production * productivity,
+                    )
+                }
+                BuildingKind::Warehouse(w) => (w.prototype().water_consumption, 0.0),
+                BuildingKind::School(s) => (s.prototype().water_consumption, 0.0),
+                BuildingKind::Leisure(l) => (l.prototype().water_consumption, 0.0),
+                BuildingKind::Hotel(h) => (h.prototype().water_consumption, 0.0),
+                BuildingKind::Harbor(h) => (h.prototype().water_consumption, 0.0),
+                BuildingKind::Airport(a) => (a.prototype().water_consumption, 0.0),
+                BuildingKind::RailFreightStation(_)
+                | BuildingKind::RailPassengerStation(_)
+                | BuildingKind::TrainStation
+                | BuildingKind::ExternalTrading => (0.0, 0.0),
+            };
+
+            nflow.consumed += consumption;
+            nflow.produced += production;
+            if consumption > 0.0 {
+                consumers.push(building);
+            }
+        }
+
+        if nflow.consumed > nflow.produced && !in_grace {
+            flow.dry.extend(consumers.iter().copied());
+        }
+        flow.flowmap.insert(network.id, nflow);
+    }
+}
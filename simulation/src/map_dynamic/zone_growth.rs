@@ -0,0 +1,243 @@
+use geom::OBB;
+use prototypes::{prototypes_iter, CompanyKind, GameTime, GoodsCompanyPrototype};
+
+use crate::economy::JobMarket;
+use crate::map::{
+    BuildingID, BuildingKind, LotID, Map, ProjectFilter, ProjectKind, RoadID, ZoningKind,
+};
+use crate::map_dynamic::{BuildingInfos, Garbage, LandValue, RICH_LAND_VALUE};
+use crate::utils::rand_provider::RandProvider;
+use crate::{Simulation, World};
+
+/// Ticks between two growth attempts
+const GROWTH_PERIOD: u64 = 500;
+/// Below this demand, nothing grows
+const MIN_DEMAND: f32 = 0.1;
+/// Number of unmatched workers or positions at which the demand is full
+const FULL_DEMAND: f32 = 20.0;
+/// Lots tried before giving up on placing a company
+const PLACEMENT_TRIES: usize = 10;
+
+/// How much each kind of zone wants to grow, between 0 and 1
+#[derive(Default, Copy, Clone, Debug)]
+pub struct ZoneDemand {
+    pub residential: f32,
+    pub commercial: f32,
+    pub industrial: f32,
+}
+
+impl ZoneDemand {
+    pub fn get(&self, kind: ZoningKind) -> f32 {
+        match kind {
+            ZoningKind::Residential => self.residential,
+            ZoningKind::Commercial => self.commercial,
+            ZoningKind::Industrial => self.industrial,
+        }
+    }
+
+    /// Housing is missing when companies cannot find workers or when citizens lost their home,
+    /// stores and factories are missing when workers cannot find a job.
+    /// The job shortage is shared so that the kind of company that is the rarest grows first.
+    /// Nobody wants to move in a city whose houses are full of garbage.
+    pub fn compute(map: &Map, world: &World, jobs: &JobMarket, garbage: &Garbage) -> Self {
+        let open = jobs.n_open() as f32;
+        let seekers = jobs.unemployed() as f32;
+        let homeless = world
+            .humans
+            .values()
+            .filter(|h| h.tourist.is_none() && !map.buildings.contains_key(h.home.house))
+            .count() as f32;
+
+        let (mut stores, mut factories) = (0.0, 0.0);
+        let (mut houses, mut dirty_houses) = (0.0, 0.0);
+        for b in map.buildings.values() {
+            match b.kind {
+                BuildingKind::GoodsCompany(id) => match id.prototype().kind {
+                    CompanyKind::Store => stores += 1.0,
+                    CompanyKind::Factory => factories += 1.0,
+                },
+                BuildingKind::House => {
+                    houses += 1.0;
+                    if garbage.is_dirty(b.id) {
+                        dirty_houses += 1.0;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let clean_share = if houses > 0.0 {
+            1.0 - dirty_houses / houses
+        } else {
+            1.0
+        };
+        let store_share = (stores + 1.0) / (stores + factories + 2.0);
+
+        let job_shortage = ((seekers - open) / FULL_DEMAND).clamp(0.0, 1.0);
+        Self {
+            residential: (((open - seekers).max(0.0) + homeless) / FULL_DEMAND).min(1.0)
+                * clean_share,
+            commercial: (job_shortage * (1.0 - store_share) * 2.0).min(1.0),
+            industrial: (job_shortage * store_share * 2.0).min(1.0),
+        }
+    }
+}
+
+/// Grows buildings on zoned lots according to the demand, richer ones on valuable land
+pub fn zone_growth_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::zone_growth_system");
+    if sim.read::<GameTime>().tick.0 % GROWTH_PERIOD != 0 {
+        return;
+    }
+
+    let demand = ZoneDemand::compute(
+        &sim.map(),
+        &sim.world,
+        &sim.read::<JobMarket>(),
+        &sim.read::<Garbage>(),
+    );
+    *sim.write::<ZoneDemand>() = demand;
+
+    if sim.map().zones.is_empty() {
+        return;
+    }
+
+    for kind in ZoningKind::ALL {
+        let d = demand.get(kind);
+        if d < MIN_DEMAND || sim.write::<RandProvider>().next_f32() > d {
+            continue;
+        }
+
+        let built = match kind {
+            ZoningKind::Residential => grow_house(sim),
+            ZoningKind::Commercial => grow_company(sim, kind, CompanyKind::Store),
+            ZoningKind::Industrial => grow_company(sim, kind, CompanyKind::Factory),
+        };
+        if let Some(id) = built {
+            sim.write::<BuildingInfos>().insert(id);
+        }
+    }
+}
+
+/// Lots along roads whose center is zoned with this kind
+fn zoned_lots(map: &Map, kind: ZoningKind) -> Vec<LotID> {
+    map.lots
+        .values()
+        .filter(|lot| map.zones.get(lot.shape.center()) == Some(kind))
+        .map(|lot| lot.id)
+        .collect()
+}
+
+fn grow_house(sim: &mut Simulation) -> Option<BuildingID> {
+    let lots = zoned_lots(&sim.map(), ZoningKind::Residential);
+    if lots.is_empty() {
+        return None;
+    }
+    let lot = lots[sim.write::<RandProvider>().next_u32() as usize % lots.len()];
+    let value = lot_value(sim, lot);
+    sim.map_mut()
+        .build_house_with_floors(lot, LandValue::house_floors(value))
+}
+
+fn lot_value(sim: &Simulation, lot: LotID) -> f32 {
+    let Some(center) = sim.map().lots.get(lot).map(|lot| lot.shape.center()) else {
+        return 0.0;
+    };
+    sim.read::<LandValue>().land_value(center)
+}
+
+/// The pricier half of the candidates on rich land, the cheaper half elsewhere.
+/// Candidates are sorted by price.
+fn pick_variant<'a>(
+    candidates: &[&'a GoodsCompanyPrototype],
+    land_value: f32,
+    roll: u32,
+) -> &'a GoodsCompanyPrototype {
+    let n = candidates.len();
+    let half = n / 2;
+    let range = if land_value >= RICH_LAND_VALUE {
+        half..n
+    } else {
+        0..n - half
+    };
+    candidates[range.start + roll as usize % range.len()]
+}
+
+fn grow_company(sim: &mut Simulation, zone: ZoningKind, kind: CompanyKind) -> Option<BuildingID> {
+    let mut candidates: Vec<&GoodsCompanyPrototype> = prototypes_iter::<GoodsCompanyPrototype>()
+        .filter(|descr| descr.kind == kind && descr.zone.is_none())
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    candidates.sort_by_key(|descr| descr.price);
+
+    let lots = zoned_lots(&sim.map(), zone);
+    if lots.is_empty() {
+        return None;
+    }
+
+    for _ in 0..PLACEMENT_TRIES {
+        let lot = lots[sim.write::<RandProvider>().next_u32() as usize % lots.len()];
+        let roll = sim.write::<RandProvider>().next_u32();
+        let descr = pick_variant(&candidates, lot_value(sim, lot), roll);
+        let Some((obb, road)) = company_placement(&sim.map(), lot, descr, zone) else {
+            continue;
+        };
+        return sim.map_mut().build_special_building(
+            &obb,
+            BuildingKind::GoodsCompany(descr.id),
+            descr.bgen,
+            None,
+            Some(road),
+        );
+    }
+    None
+}
+
+/// Where a company would stand on the lot, facing its road like the ones placed by the player
+fn company_placement(
+    map: &Map,
+    lot: LotID,
+    descr: &GoodsCompanyPrototype,
+    zone: ZoningKind,
+) -> Option<(OBB, RoadID)> {
+    let lot = map.lots.get(lot)?;
+    let road = map.roads.get(lot.parent)?;
+
+    if road.sidewalks(road.src).incoming.is_none() {
+        return None;
+    }
+
+    let center = lot.shape.center();
+    let (proj, _, _) = road.points().project_segment_dir(center.z(lot.height));
+    let side = (center - proj.xy()).try_normalize()?;
+
+    let size = descr.size;
+    let obb = OBB::new(
+        proj.xy() + side * (size.h + road.width + 0.5) * 0.5,
+        side,
+        size.w,
+        size.h,
+    );
+
+    if obb
+        .corners
+        .iter()
+        .any(|&corner| map.zones.get(corner) != Some(zone))
+    {
+        return None;
+    }
+
+    if map
+        .spatial_map
+        .query(
+            obb,
+            ProjectFilter::ROAD | ProjectFilter::INTER | ProjectFilter::BUILDING,
+        )
+        .any(|x| x != ProjectKind::Road(road.id))
+    {
+        return None;
+    }
+
+    Some((obb, road.id))
+}
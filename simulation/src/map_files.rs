@@ -0,0 +1,62 @@
+//! Maps made in the map editor, each one in its own file under `world/maps`.
+//! A map only holds the terrain, its heights and its trees, new games can start from it.
+
+use std::io;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use common::saveload::{CheckedCompressedBincode, Encoder};
+
+use crate::map::Environment;
+use crate::saves::is_valid_save_name;
+
+/// Directory of the maps, relative to the world directory
+pub const MAPS_DIR: &str = "maps";
+
+/// Size in chunks of the terrains made in the editor, the railway of a new game needs 7 of them
+pub const MIN_MAP_SIZE: u16 = 8;
+pub const MAX_MAP_SIZE: u16 = 80;
+
+fn maps_dir() -> PathBuf {
+    PathBuf::from("world").join(MAPS_DIR)
+}
+
+/// Name of the map file as given to the encoders
+fn map_name(name: &str) -> String {
+    format!("{MAPS_DIR}/{name}")
+}
+
+/// Names of the saved maps, sorted.
+/// Reads from the disk so it shouldn't be called every frame.
+pub fn list_maps() -> Vec<String> {
+    let Ok(dir) = std::fs::read_dir(maps_dir()) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = dir
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().and_then(|x| x.to_str()) == Some(CheckedCompressedBincode::EXTENSION)
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Map names follow the rules of the save names, an existing map is replaced
+pub fn save_map(name: &str, terrain: &Environment) -> io::Result<()> {
+    if !is_valid_save_name(name) {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "invalid map name"));
+    }
+    std::fs::create_dir_all(maps_dir())?;
+    CheckedCompressedBincode::save(terrain, &map_name(name))
+        .ok_or_else(|| io::Error::new(ErrorKind::Other, format!("failed writing the map {name}")))
+}
+
+pub fn load_map(name: &str) -> io::Result<Environment> {
+    if !is_valid_save_name(name) {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "invalid map name"));
+    }
+    CheckedCompressedBincode::load(&map_name(name))
+}
@@ -0,0 +1,556 @@
+//! Save format versions and the migrations upgrading the saves of older versions.
+//! Migrations work on the resources as they are encoded in the save, before they are deserialized.
+//! The world is decoded directly with the shape it had in the version of the save: the fields
+//! added since are skipped with [`since`] and given their starting values by [`migrate_world`].
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::io::ErrorKind;
+
+use common::saveload::{Bincode, Encoder};
+use common::FastMap;
+use prototypes::{GameInstant, GameTime, ItemID, Money};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::economy::{Cargo, Government, Ledger, SingleMarket, TruckLeg};
+use crate::gameplay::GameplayParams;
+use crate::map::procgen::MapGenParams;
+use crate::map::{
+    BuildingID, Buildings, Districts, Environment, IntersectionID, Intersections, LaneSpeeds,
+    Lanes, Lots, NoiseMap, ParkingSpots, RoadID, Roads, ZoneGrid,
+};
+use crate::souls::desire::Leisure;
+use crate::souls::happiness::{CityStats, AGE_BUCKETS, HAPPINESS_BUCKETS};
+use crate::statistics::StatSeries;
+use crate::world::VehicleID;
+use crate::{Simulation, SoulID};
+
+/// Version of the saves written by this build.
+/// Bump it whenever the shape of a saved resource or of the world changes and register the
+/// migration from the previous version in [`MIGRATIONS`].
+///
+/// - 0: saves from before the save header
+/// - 1: checksummed save header, and the crossings, structures, light timings, disabled turns,
+///   planted trees, zones and lane speeds of the [`crate::map::Map`]
+/// - 2: [`Government::construction_spending`]
+/// - 3: prioritized buyers of the [`crate::economy::Market`]
+/// - 4: road names of the [`crate::map::Map`]
+/// - 5: [`GameplayParams`] of the [`crate::SimulationOptions`] and the [`crate::economy::Market`]
+/// - 6: road connections of the [`crate::map::Map`]
+/// - 7: districts of the [`crate::map::Map`]
+/// - 8: ledger and loan of the [`Government`]
+/// - 9: [`MapGenParams`] of the [`crate::SimulationOptions`]
+/// - 10: noise of the [`crate::map::Map`]
+/// - 11: leisure of the humans and its happiness factor
+/// - 12: tourists of the humans and the tourist arrivals of the [`crate::statistics::Statistics`]
+/// - 13: landmark routing of the [`crate::SimulationOptions`]
+/// - 14: grace period of the [`crate::map_dynamic::WaterFlow`]
+/// - 15: numbers of the generated road names of the [`crate::map::Map`]
+/// - 16: hour of the routes of the [`crate::economy::SeaTrade`] removed
+/// - 17: payment of the exports carried by the [`crate::economy::BorderTrade`] trucks
+pub const SAVE_VERSION: u32 = 17;
+
+/// Resources of a save as they are encoded, by name
+pub type SavedResources = FastMap<String, Vec<u8>>;
+
+pub struct Migration {
+    /// Version of the saves it applies to, they are upgraded to the next version
+    pub from: u32,
+    pub name: &'static str,
+    pub migrate: fn(&mut SavedResources) -> io::Result<()>,
+}
+
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        name: "map objects",
+        migrate: map_objects,
+    },
+    Migration {
+        from: 1,
+        name: "government construction spending",
+        migrate: government_construction_spending,
+    },
+    Migration {
+        from: 2,
+        name: "market priorities",
+        migrate: market_priorities,
+    },
+    Migration {
+        from: 3,
+        name: "road names",
+        migrate: road_names,
+    },
+    Migration {
+        from: 4,
+        name: "gameplay params",
+        migrate: gameplay_params,
+    },
+    Migration {
+        from: 5,
+        name: "road connections",
+        migrate: road_connections,
+    },
+    Migration {
+        from: 6,
+        name: "districts",
+        migrate: districts,
+    },
+    Migration {
+        from: 7,
+        name: "government ledger",
+        migrate: government_ledger,
+    },
+    Migration {
+        from: 8,
+        name: "map generation params",
+        migrate: map_generation_params,
+    },
+    Migration {
+        from: 9,
+        name: "noise map",
+        migrate: noise_map,
+    },
+    Migration {
+        from: 10,
+        name: "leisure",
+        migrate: leisure_happiness,
+    },
+    Migration {
+        from: 11,
+        name: "tourism",
+        migrate: tourist_arrivals,
+    },
+    Migration {
+        from: 12,
+        name: "landmark routing",
+        migrate: landmark_routing,
+    },
+    Migration {
+        from: 13,
+        name: "water grace",
+        migrate: water_grace,
+    },
+    Migration {
+        from: 14,
+        name: "road numbers",
+        migrate: road_numbers,
+    },
+    Migration {
+        from: 15,
+        name: "sea routes",
+        migrate: sea_routes,
+    },
+    Migration {
+        from: 16,
+        name: "border export payments",
+        migrate: border_export_payments,
+    },
+];
+
+thread_local! {
+    /// Version of the save whose world is being decoded
+    static DECODING_VERSION: Cell<u32> = const { Cell::new(SAVE_VERSION) };
+}
+
+/// Runs f, usually decoding a save, with the world types taking the shape they had in the version
+pub fn decode_with_version<T>(version: u32, f: impl FnOnce() -> T) -> T {
+    DECODING_VERSION.with(|v| v.set(version));
+    let res = f();
+    DECODING_VERSION.with(|v| v.set(SAVE_VERSION));
+    res
+}
+
+/// Version of the save being decoded, the current one outside of [`decode_with_version`]
+pub fn decoding_version() -> u32 {
+    DECODING_VERSION.with(Cell::get)
+}
+
+/// Deserializes a field added to the world in the save version V.
+/// Older saves don't have it, it takes its default value without reading anything.
+pub fn since<'de, const V: u32, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    if decoding_version() < V {
+        return Ok(T::default());
+    }
+    T::deserialize(deserializer)
+}
+
+/// Gives the fields of the world skipped by [`since`] their starting values,
+/// once the save of the version is loaded
+pub fn migrate_world(version: u32, sim: &mut Simulation) {
+    if version < 11 {
+        let now = sim.read::<GameTime>().instant();
+        for h in sim.world.humans.values_mut() {
+            h.leisure = Leisure::new(now);
+        }
+    }
+}
+
+/// Saves from a newer version of the game cannot be loaded
+pub fn check_version(version: u32) -> io::Result<()> {
+    if version > SAVE_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "save version {} is newer than the supported version {}, the game needs to be updated",
+                version, SAVE_VERSION
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Upgrades the resources from `version` to [`SAVE_VERSION`] by running the migrations in sequence.
+/// Returns the names of the migrations that were applied.
+pub fn migrate(version: u32, res: &mut SavedResources) -> io::Result<Vec<&'static str>> {
+    check_version(version)?;
+
+    let mut applied = vec![];
+    for from in version..SAVE_VERSION {
+        let Some(m) = MIGRATIONS.iter().find(|m| m.from == from) else {
+            return Err(io::Error::new(
+                ErrorKind::Other,
+                format!("no migration registered from save version {}", from),
+            ));
+        };
+        (m.migrate)(res).map_err(|e| {
+            io::Error::new(e.kind(), format!("migration \"{}\" failed: {}", m.name, e))
+        })?;
+        applied.push(m.name);
+    }
+    Ok(applied)
+}
+
+/// Saves from before the header were written before the crossings and structure of the roads,
+/// the light timings and disabled turns of the intersections and the planted trees. They are
+/// inside the map objects, so the map is decoded with its old shape and encoded again.
+/// The zones and lane speeds are the last fields of the map, they start empty.
+fn map_objects(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct MapV1 {
+        roads: Roads,
+        intersections: Intersections,
+        buildings: Buildings,
+        lanes: Lanes,
+        parking: ParkingSpots,
+        lots: Lots,
+        environment: Environment,
+        external_train_stations: Vec<BuildingID>,
+        #[serde(deserialize_with = "since::<1, _, _>")]
+        zones: ZoneGrid,
+        #[serde(deserialize_with = "since::<1, _, _>")]
+        lane_speeds: LaneSpeeds,
+    }
+
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    let map: MapV1 = decode_with_version(0, || Bincode::decode(data))?;
+    *data = Bincode::encode(&map)?;
+    Ok(())
+}
+
+fn government_construction_spending(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct GovernmentV1 {
+        money: Money,
+        tariff_income: Money,
+        electricity_price: Money,
+    }
+
+    #[derive(Serialize)]
+    struct GovernmentV2 {
+        money: Money,
+        tariff_income: Money,
+        electricity_price: Money,
+        construction_spending: Money,
+    }
+
+    let Some(data) = res.get_mut("government") else {
+        return Ok(());
+    };
+    let old: GovernmentV1 = Bincode::decode(data)?;
+    *data = Bincode::encode(&GovernmentV2 {
+        money: old.money,
+        tariff_income: old.tariff_income,
+        electricity_price: old.electricity_price,
+        construction_spending: Money::ZERO,
+    })?;
+    Ok(())
+}
+
+fn market_priorities(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct MarketV2 {
+        markets: BTreeMap<ItemID, SingleMarket>,
+    }
+
+    #[derive(Serialize)]
+    struct MarketV3 {
+        markets: BTreeMap<ItemID, SingleMarket>,
+        prioritized: BTreeSet<SoulID>,
+    }
+
+    let Some(data) = res.get_mut("market") else {
+        return Ok(());
+    };
+    let old: MarketV2 = Bincode::decode(data)?;
+    *data = Bincode::encode(&MarketV3 {
+        markets: old.markets,
+        prioritized: BTreeSet::new(),
+    })?;
+    Ok(())
+}
+
+/// The road names are the last field of the serialized map, so they are appended to it
+fn road_names(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&BTreeMap::<RoadID, String>::new())?);
+    Ok(())
+}
+
+/// Older saves were played with the normal difficulty.
+/// The parameters are the last field of the options, and the price multiplier and trade margin
+/// the last fields of the market, so they are appended to them.
+fn gameplay_params(res: &mut SavedResources) -> io::Result<()> {
+    let params = GameplayParams::default();
+    if let Some(data) = res.get_mut("simoptions") {
+        data.extend(Bincode::encode(&params)?);
+    }
+    if let Some(data) = res.get_mut("market") {
+        data.extend(Bincode::encode(&params.price_multiplier)?);
+        data.extend(Bincode::encode(&params.external_trade_margin)?);
+    }
+    Ok(())
+}
+
+/// The road connections are the last field of the serialized map, so they are appended to it.
+/// Older maps have none, their external trades go through the railway.
+fn road_connections(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&Vec::<IntersectionID>::new())?);
+    Ok(())
+}
+
+/// The districts are the last field of the serialized map, so they are appended to it
+fn districts(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&Districts::default())?);
+    Ok(())
+}
+
+/// Older saves start their ledger from the money they had, and owe nothing
+fn government_ledger(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct GovernmentV7 {
+        money: Money,
+        tariff_income: Money,
+        electricity_price: Money,
+        construction_spending: Money,
+    }
+
+    let Some(data) = res.get_mut("government") else {
+        return Ok(());
+    };
+    let old: GovernmentV7 = Bincode::decode(data)?;
+    *data = Bincode::encode(&Government {
+        money: old.money,
+        tariff_income: old.tariff_income,
+        electricity_price: old.electricity_price,
+        construction_spending: old.construction_spending,
+        ledger: Ledger::new(old.money),
+        loan: Money::ZERO,
+    })?;
+    Ok(())
+}
+
+/// Older maps were generated with the default parameters.
+/// They are the last field of the options, so they are appended to them.
+fn map_generation_params(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("simoptions") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&MapGenParams::default())?);
+    Ok(())
+}
+
+/// The noise is the last field of the serialized map, so it is appended to it.
+/// Older maps start quiet, the noise is computed again around every road and airport on the
+/// first map update.
+fn noise_map(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&NoiseMap::default())?);
+    Ok(())
+}
+
+/// The city statistics get a loss for the leisure happiness factor, after the four others
+fn leisure_happiness(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct CityStatsV10 {
+        mean: f32,
+        histogram: [u32; HAPPINESS_BUCKETS],
+        losses: [f32; 4],
+        population: u32,
+        last_update: Option<GameInstant>,
+        births: u32,
+        deaths: u32,
+        ages: [u32; AGE_BUCKETS],
+        education: [u32; 3],
+        last_aging: Option<GameInstant>,
+    }
+
+    let Some(data) = res.get_mut("city_stats") else {
+        return Ok(());
+    };
+    let old: CityStatsV10 = Bincode::decode(data)?;
+    let [commute, food, utilities, noise] = old.losses;
+    *data = Bincode::encode(&CityStats {
+        mean: old.mean,
+        histogram: old.histogram,
+        losses: [commute, food, utilities, noise, 0.0],
+        population: old.population,
+        last_update: old.last_update,
+        births: old.births,
+        deaths: old.deaths,
+        ages: old.ages,
+        education: old.education,
+        last_aging: old.last_aging,
+    })?;
+    Ok(())
+}
+
+/// No tourist came to older cities.
+/// The arrivals of the hour are the last field of the accumulator, which is the last field of the
+/// statistics before their series of arrivals, so both are appended to them.
+fn tourist_arrivals(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("statistics") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&0u32)?);
+    data.extend(Bincode::encode(&StatSeries::default())?);
+    Ok(())
+}
+
+/// Older games were routed with the landmarks, the option is the last field of the options so it
+/// is appended to them.
+fn landmark_routing(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("simoptions") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&true)?);
+    Ok(())
+}
+
+/// Cities saved before the grace period get one from their first update, to build their pumps.
+/// It is the last field of the water flow, so it is appended to it.
+fn water_grace(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("water_flow") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&None::<GameInstant>)?);
+    Ok(())
+}
+
+/// The road numbers are the last fields of the serialized map, so they are appended to it.
+/// The roads of older maps are numbered after their slot when the map is loaded, which keeps
+/// their name.
+fn road_numbers(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("map") else {
+        return Ok(());
+    };
+    data.extend(Bincode::encode(&BTreeMap::<RoadID, u32>::new())?);
+    data.extend(Bincode::encode(&0u32)?);
+    Ok(())
+}
+
+/// The sea routes are kept until the terrain changes instead of being forgotten every hour.
+/// The hour they were found during is the first field of the sea trade, so it is removed.
+fn sea_routes(res: &mut SavedResources) -> io::Result<()> {
+    let Some(data) = res.get_mut("sea_trade") else {
+        return Ok(());
+    };
+    let mut rest = &data[..];
+    let _hour: u64 = Bincode::decode_reader(&mut rest)?;
+    *data = rest.to_vec();
+    Ok(())
+}
+
+/// The exports through the road connections are paid when the truck leaves the building instead
+/// of when they are sold. The exports of the trucks already on the road were paid already.
+fn border_export_payments(res: &mut SavedResources) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct ShipmentV16 {
+        connection: IntersectionID,
+        building: BuildingID,
+        import: bool,
+        cargo: Vec<Cargo>,
+        leg: TruckLeg,
+    }
+
+    #[derive(Deserialize)]
+    struct BorderTradeV16 {
+        used: BTreeMap<IntersectionID, u32>,
+        reachable: BTreeMap<(BuildingID, IntersectionID), bool>,
+        hour: u64,
+        pending: Vec<ShipmentV16>,
+        trucks: BTreeMap<VehicleID, ShipmentV16>,
+    }
+
+    #[derive(Serialize)]
+    struct ShipmentV17 {
+        connection: IntersectionID,
+        building: BuildingID,
+        import: bool,
+        cargo: Vec<Cargo>,
+        leg: TruckLeg,
+        earned: Money,
+    }
+
+    #[derive(Serialize)]
+    struct BorderTradeV17 {
+        used: BTreeMap<IntersectionID, u32>,
+        reachable: BTreeMap<(BuildingID, IntersectionID), bool>,
+        hour: u64,
+        pending: Vec<ShipmentV17>,
+        trucks: BTreeMap<VehicleID, ShipmentV17>,
+    }
+
+    let upgrade = |s: ShipmentV16| ShipmentV17 {
+        connection: s.connection,
+        building: s.building,
+        import: s.import,
+        cargo: s.cargo,
+        leg: s.leg,
+        earned: Money::ZERO,
+    };
+
+    let Some(data) = res.get_mut("border_trade") else {
+        return Ok(());
+    };
+    let old: BorderTradeV16 = Bincode::decode(data)?;
+    *data = Bincode::encode(&BorderTradeV17 {
+        used: old.used,
+        reachable: old.reachable,
+        hour: old.hour,
+        pending: old.pending.into_iter().map(upgrade).collect(),
+        trucks: old
+            .trucks
+            .into_iter()
+            .map(|(id, s)| (id, upgrade(s)))
+            .collect(),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Progression of the city: milestones reached by growing the population unlock buildings and road
+//! types in the tools. They are defined by the `milestone` prototypes so that mods can add their own.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{
+    prototypes_iter, GameTime, MilestonePrototype, MilestonePrototypeID, Money, TICKS_PER_MINUTE,
+};
+
+use crate::chronicle::{Chronicle, ChronicleKind};
+use crate::economy::Government;
+use crate::notifications::{Severity, SimNotifications};
+use crate::objectives::ScenarioRunner;
+use crate::souls::happiness::CityStats;
+use crate::utils::resources::Resources;
+use crate::{Simulation, World};
+
+/// The milestones the city reached, they stay reached even if the population goes back down
+#[derive(Default, Serialize, Deserialize)]
+pub struct Milestones {
+    reached: BTreeSet<MilestonePrototypeID>,
+}
+
+impl Milestones {
+    /// Every milestone reached, for the tests building anything
+    #[cfg(test)]
+    pub(crate) fn all_reached() -> Self {
+        Self {
+            reached: prototypes_iter::<MilestonePrototype>()
+                .map(|m| m.id)
+                .collect(),
+        }
+    }
+
+    pub fn is_reached(&self, id: MilestonePrototypeID) -> bool {
+        self.reached.contains(&id)
+    }
+
+    /// The milestone to reach before the building prototype or road type of this name
+    /// can be built, None if it is unlocked
+    pub fn locked_by(&self, name: &str) -> Option<&'static MilestonePrototype> {
+        prototypes_iter::<MilestonePrototype>()
+            .filter(|m| !self.is_reached(m.id))
+            .find(|m| m.unlocks.iter().any(|u| u == name))
+    }
+
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        self.locked_by(name).is_none()
+    }
+
+    /// Marks the milestones the city just reached and returns them
+    pub fn update(&mut self, population: u32, money: Money) -> Vec<&'static MilestonePrototype> {
+        let mut new = Vec::new();
+        for m in prototypes_iter::<MilestonePrototype>() {
+            if self.is_reached(m.id)
+                || population < m.population
+                || m.money.is_some_and(|needed| money < needed)
+            {
+                continue;
+            }
+            self.reached.insert(m.id);
+            new.push(m);
+        }
+        new
+    }
+}
+
+/// Why the building prototype or road type of this name can't be built,
+/// None if it is unlocked
+pub fn lock_reason(sim: &Simulation, name: &str) -> Option<String> {
+    if sim.read::<ScenarioRunner>().is_locked(name) {
+        return Some("Not available in this scenario".to_string());
+    }
+    let milestone = sim.read::<Milestones>().locked_by(name)?;
+    Some(format!("Locked until {}", milestone.requirement()))
+}
+
+/// Every minute, checks whether the city reached new milestones and celebrates them
+pub fn milestones_system(_: &mut World, resources: &mut Resources) {
+    let time = resources.read::<GameTime>();
+    if time.tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    profiling::scope!("milestones::milestones_system");
+
+    let population = resources.read::<CityStats>().population;
+    let money = resources.read::<Government>().money;
+    let reached = resources.write::<Milestones>().update(population, money);
+
+    let mut notifs = resources.write::<SimNotifications>();
+    let mut chronicle = resources.write::<Chronicle>();
+    for m in reached {
+        log::info!("milestone {} reached", m.name);
+        chronicle.record(
+            time.tick,
+            ChronicleKind::Milestone,
+            format!("{} reached", m.label),
+            None,
+        );
+        notifs.push(
+            time.tick,
+            Severity::Milestone,
+            format!("{} reached! Unlocked: {}", m.label, m.unlocks.join(", ")),
+            None,
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Important events of the simulation for the UI to report, e.g. blackouts.
+//! Notifications are numbered like [`crate::world_command::FailedCommands`] so that readers can tell
+//! which ones are new. They are not saved.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use geom::Vec3;
+use prototypes::{GameTime, Tick, HOURS_PER_DAY, TICKS_PER_HOUR, TICKS_PER_MINUTE};
+
+use crate::map::{BuildingID, ElectricityNetworkID, Map};
+use crate::map_dynamic::ElectricityFlow;
+use crate::utils::resources::Resources;
+use crate::world::CompanyID;
+use crate::{AnyEntity, World};
+
+/// How long a company must stay without workers before it is reported
+pub const NO_WORKERS_NOTIFY_AFTER: u64 = 2 * HOURS_PER_DAY as u64 * TICKS_PER_HOUR;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    /// Something to celebrate, e.g. a milestone reached
+    Milestone,
+    Warning,
+    Critical,
+}
+
+/// What the notification is about, to move the camera to it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NotificationTarget {
+    Building(BuildingID),
+    Entity(AnyEntity),
+    Position(Vec3),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    pub target: Option<NotificationTarget>,
+    pub tick: Tick,
+}
+
+/// The last notifications, for the UI to pick up
+#[derive(Default)]
+pub struct SimNotifications {
+    notifications: VecDeque<(u64, Notification)>,
+    next_id: u64,
+}
+
+impl SimNotifications {
+    const MAX_NOTIFICATIONS: usize = 64;
+
+    pub fn push(
+        &mut self,
+        tick: Tick,
+        severity: Severity,
+        message: impl Into<String>,
+        target: Option<NotificationTarget>,
+    ) {
+        if self.notifications.len() >= Self::MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back((
+            self.next_id,
+            Notification {
+                severity,
+                message: message.into(),
+                target,
+                tick,
+            },
+        ));
+        self.next_id += 1;
+    }
+
+    /// Notifications pushed since `seen` was last updated, `seen` starts at 0
+    pub fn since<'a>(&'a self, seen: &mut u64) -> impl Iterator<Item = &'a Notification> {
+        // the simulation was replaced
+        if *seen > self.next_id {
+            *seen = 0;
+        }
+        let from = *seen;
+        *seen = self.next_id;
+        self.notifications
+            .iter()
+            .filter(move |(id, _)| *id >= from)
+            .map(|(_, n)| n)
+    }
+}
+
+/// What the producers of notifications remember between two checks
+#[derive(Default)]
+pub struct NotificationWatch {
+    blackouts: BTreeSet<ElectricityNetworkID>,
+    /// Since when the company has no workers, and whether it was reported
+    no_workers: BTreeMap<CompanyID, (Tick, bool)>,
+}
+
+/// Looks for the events to report every minute
+pub fn notifications_system(world: &mut World, resources: &mut Resources) {
+    let tick = resources.read::<GameTime>().tick;
+    if tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    profiling::scope!("notifications::notifications_system");
+
+    let mut watch = resources.write::<NotificationWatch>();
+    let mut notifs = resources.write::<SimNotifications>();
+    watch.check_blackouts(
+        tick,
+        &resources.read::<Map>(),
+        &resources.read::<ElectricityFlow>(),
+        &mut notifs,
+    );
+    watch.check_workers(tick, world, &mut notifs);
+}
+
+impl NotificationWatch {
+    fn check_blackouts(
+        &mut self,
+        tick: Tick,
+        map: &Map,
+        flow: &ElectricityFlow,
+        notifs: &mut SimNotifications,
+    ) {
+        let mut blackouts = BTreeSet::new();
+        for network in map.electricity.networks.values() {
+            let target = network
+                .buildings
+                .first()
+                .map(|&b| NotificationTarget::Building(b));
+            let n = network.buildings.len();
+
+            if !flow.blackout(network.id) {
+                if self.blackouts.contains(&network.id) {
+                    notifs.push(
+                        tick,
+                        Severity::Info,
+                        format!("Power is back in a network of {n} buildings"),
+                        target,
+                    );
+                }
+                continue;
+            }
+            blackouts.insert(network.id);
+            if !self.blackouts.contains(&network.id) {
+                notifs.push(
+                    tick,
+                    Severity::Critical,
+                    format!("Blackout in a network of {n} buildings"),
+                    target,
+                );
+            }
+        }
+        self.blackouts = blackouts;
+    }
+
+    fn check_workers(&mut self, tick: Tick, world: &World, notifs: &mut SimNotifications) {
+        self.no_workers.retain(|&id, _| {
+            world
+                .companies
+                .get(id)
+                .is_some_and(|c| c.workers.0.is_empty())
+        });
+
+        for (id, c) in world.companies.iter() {
+            if c.comp.max_workers == 0 || !c.workers.0.is_empty() {
+                continue;
+            }
+            let (since, reported) = self.no_workers.entry(id).or_insert((tick, false));
+            if *reported || tick.0 - since.0 < NO_WORKERS_NOTIFY_AFTER {
+                continue;
+            }
+            *reported = true;
+            notifs.push(
+                tick,
+                Severity::Warning,
+                format!(
+                    "{} has had no workers for 2 days",
+                    c.comp.proto.prototype().label
+                ),
+                Some(NotificationTarget::Building(c.comp.building)),
+            );
+        }
+    }
+}
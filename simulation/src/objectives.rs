@@ -0,0 +1,265 @@
+//! Scenarios are an alternative to the sandbox: a [`ScenarioPrototype`] sets the starting map
+//! and money, locks some features and gives objectives. The [`ScenarioRunner`] checks the
+//! objectives every hour and decides whether the player won or lost, the wins are recorded in the
+//! [`ScenarioProfile`].
+//! Not to be confused with [`crate::scenario`], the scripted runs used to test the balance.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use common::saveload::{Encoder, JSONPretty, JSON};
+use prototypes::{
+    GameDuration, GameInstant, GameTime, ItemID, ObjectiveKind, ScenarioPrototype,
+    ScenarioPrototypeID, Tick, TICKS_PER_HOUR,
+};
+
+use crate::economy::{BudgetReason, Government, Trade};
+use crate::notifications::{Severity, SimNotifications};
+use crate::souls::happiness::CityStats;
+use crate::utils::resources::Resources;
+use crate::{Simulation, SimulationOptions, World};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScenarioOutcome {
+    Won,
+    /// With the reason, e.g. the objective that was not completed in time
+    Lost(String),
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ObjectiveProgress {
+    /// In the unit of the objective, see [`prototypes::Objective::target`]
+    pub current: f64,
+    /// Objectives stay completed even if the city goes back below the target
+    pub completed: bool,
+}
+
+/// The scenario being played, if any
+#[derive(Serialize, Deserialize)]
+pub struct ActiveScenario {
+    pub id: ScenarioPrototypeID,
+    pub start: GameInstant,
+    /// In the order of the objectives of the prototype
+    pub progress: Vec<ObjectiveProgress>,
+    pub outcome: Option<ScenarioOutcome>,
+    /// When the scenario was won or lost
+    pub end: Option<GameInstant>,
+    /// Quantity of each item exported since the start
+    exported: BTreeMap<ItemID, u64>,
+}
+
+impl ActiveScenario {
+    /// Time it took to win or lose the scenario, None while it is being played
+    pub fn duration(&self) -> Option<GameDuration> {
+        self.end
+            .map(|end| GameDuration(Tick(end.0 .0 - self.start.0 .0)))
+    }
+}
+
+/// Keeps track of the objectives of the scenario, does nothing in the sandbox
+#[derive(Default, Serialize, Deserialize)]
+pub struct ScenarioRunner {
+    active: Option<ActiveScenario>,
+}
+
+impl ScenarioRunner {
+    pub fn active(&self) -> Option<&ActiveScenario> {
+        self.active.as_ref()
+    }
+
+    pub fn scenario(&self) -> Option<&'static ScenarioPrototype> {
+        self.active.as_ref().map(|a| a.id.prototype())
+    }
+
+    /// Whether the scenario forbids building the prototype or road type of this name
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.scenario()
+            .is_some_and(|s| s.locked.iter().any(|l| l == name))
+    }
+
+    pub fn outcome(&self) -> Option<&ScenarioOutcome> {
+        self.active.as_ref()?.outcome.as_ref()
+    }
+
+    /// Counts the goods sold to the outside during the scenario, called by the market with the
+    /// trades of the tick so that each trade is counted once
+    pub fn record_exports(&mut self, trades: &[Trade]) {
+        let Some(ref mut active) = self.active else {
+            return;
+        };
+        if active.outcome.is_some() {
+            return;
+        }
+        for trade in trades {
+            if trade.qty > 0 && trade.buyer.0.is_external() {
+                *active.exported.entry(trade.kind).or_default() += trade.qty as u64;
+            }
+        }
+    }
+}
+
+/// Gives the starting money of the scenario to the government and starts tracking its objectives
+pub fn start_scenario(sim: &mut Simulation, id: ScenarioPrototypeID) {
+    let proto = id.prototype();
+    sim.write::<Government>()
+        .set_money(BudgetReason::Scenario, proto.money);
+    let start = sim.read::<GameTime>().instant();
+    sim.write::<ScenarioRunner>().active = Some(ActiveScenario {
+        id,
+        start,
+        progress: vec![ObjectiveProgress::default(); proto.objectives.len()],
+        outcome: None,
+        end: None,
+        exported: BTreeMap::new(),
+    });
+    log::info!("started scenario {}", proto.name);
+}
+
+/// Loads or generates the map of the scenario and starts it.
+/// No replay is recorded as the scenario changes the world outside of the commands.
+pub fn new_scenario(id: ScenarioPrototypeID) -> io::Result<Simulation> {
+    let proto = id.prototype();
+    let mut sim = match proto.load {
+        Some(ref name) => Simulation::try_load_from_disk(name)?,
+        None => Simulation::new_with_options(SimulationOptions {
+            terrain_size: proto.terrain_size,
+            save_replay: false,
+            ..Default::default()
+        }),
+    };
+    start_scenario(&mut sim, id);
+    Ok(sim)
+}
+
+/// Every hour, updates the progress of the objectives and checks whether the scenario is won or lost
+pub fn scenario_runner_system(_: &mut World, resources: &mut Resources) {
+    let time = resources.read::<GameTime>();
+    if time.tick.0 % TICKS_PER_HOUR != 0 {
+        return;
+    }
+    let mut runner = resources.write::<ScenarioRunner>();
+    let Some(ref mut active) = runner.active else {
+        return;
+    };
+    if active.outcome.is_some() {
+        return;
+    }
+    profiling::scope!("objectives::scenario_runner_system");
+
+    let proto = active.id.prototype();
+    let population = resources.read::<CityStats>().population;
+    let money = resources.read::<Government>().money;
+    let mut notifs = resources.write::<SimNotifications>();
+
+    for (objective, progress) in proto.objectives.iter().zip(&mut active.progress) {
+        progress.current = match objective.kind {
+            ObjectiveKind::Population(_) => population as f64,
+            ObjectiveKind::Money(_) => money.bucks() as f64,
+            ObjectiveKind::Export(item, _) => {
+                active.exported.get(&item).copied().unwrap_or_default() as f64
+            }
+        };
+
+        if progress.completed || progress.current < objective.target() {
+            continue;
+        }
+        progress.completed = true;
+        notifs.push(
+            time.tick,
+            Severity::Milestone,
+            format!("Objective completed: {}", objective.description()),
+            None,
+        );
+    }
+
+    let missed = proto
+        .objectives
+        .iter()
+        .zip(&active.progress)
+        .find(|(objective, progress)| {
+            !progress.completed
+                && objective
+                    .within
+                    .is_some_and(|within| time.instant() >= active.start + within)
+        });
+
+    active.outcome = if let Some((objective, _)) = missed {
+        Some(ScenarioOutcome::Lost(format!(
+            "{} was not completed in time",
+            objective.description()
+        )))
+    } else if proto.min_money.is_some_and(|min| money < min) {
+        Some(ScenarioOutcome::Lost("the city went bankrupt".to_string()))
+    } else if active.progress.iter().all(|p| p.completed) {
+        Some(ScenarioOutcome::Won)
+    } else {
+        None
+    };
+
+    if active.outcome.is_some() {
+        active.end = Some(time.instant());
+    }
+
+    match active.outcome {
+        Some(ScenarioOutcome::Won) => {
+            log::info!("scenario {} won", proto.name);
+            if let Some(duration) = active.duration() {
+                let mut profile = ScenarioProfile::load();
+                if profile.record(&proto.name, duration) {
+                    profile.save();
+                }
+            }
+            notifs.push(
+                time.tick,
+                Severity::Milestone,
+                format!("{} completed, congratulations!", proto.label),
+                None,
+            );
+        }
+        Some(ScenarioOutcome::Lost(ref reason)) => {
+            log::info!("scenario {} lost: {}", proto.name, reason);
+            notifs.push(
+                time.tick,
+                Severity::Critical,
+                format!("{} lost: {}", proto.label, reason),
+                None,
+            );
+        }
+        None => {}
+    }
+}
+
+/// The scenarios the player completed, kept in its own file so that it doesn't depend on the saves.
+/// Written by the [`scenario_runner_system`] when a scenario is won.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ScenarioProfile {
+    /// Fastest completion of each scenario by name
+    completed: BTreeMap<String, GameDuration>,
+}
+
+impl ScenarioProfile {
+    const FILE: &'static str = "scenario_profile";
+
+    pub fn load() -> Self {
+        JSON::load(Self::FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        JSONPretty::save_silent(self, Self::FILE);
+    }
+
+    pub fn best(&self, name: &str) -> Option<GameDuration> {
+        self.completed.get(name).copied()
+    }
+
+    /// Records the completion of the scenario, returns true if it is the fastest one
+    pub fn record(&mut self, name: &str, duration: GameDuration) -> bool {
+        if self.best(name).is_some_and(|best| best <= duration) {
+            return false;
+        }
+        self.completed.insert(name.to_string(), duration);
+        true
+    }
+}
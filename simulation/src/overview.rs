@@ -0,0 +1,174 @@
+//! Read-only snapshots of what happens in a building, for the inspector panels.
+//! They copy what they need while holding the read locks for as short as possible,
+//! so that the UI can take its time to render them.
+
+use prototypes::{ItemID, Power, RecipeItem};
+
+use crate::economy::Market;
+use crate::map::BuildingID;
+use crate::map_dynamic::{BuildingInfos, ElectricityFlow, WaterFlow};
+use crate::souls::delivery::Deliveries;
+use crate::transportation::{Location, VehicleState};
+use crate::world::{CompanyID, HumanID, VehicleID};
+use crate::{Simulation, SoulID};
+
+/// Whether a building gets the power or the water it needs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UtilityStatus {
+    /// Does not need it
+    Unused,
+    /// Not connected to any network
+    Disconnected,
+    /// Connected but the network does not provide enough
+    Shortage,
+    Ok,
+}
+
+/// Stock of an item of the recipe, compared to what one batch uses or makes
+#[derive(Debug, Copy, Clone)]
+pub struct ItemStock {
+    pub item: ItemID,
+    pub stock: i32,
+    pub per_batch: i32,
+}
+
+/// Quantity still wanted or offered on the market
+#[derive(Debug, Copy, Clone)]
+pub struct OpenOrder {
+    pub item: ItemID,
+    pub qty: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompanyOverview {
+    pub company: CompanyID,
+    /// Workers inside the building right now
+    pub workers_present: u32,
+    pub workers: u32,
+    pub max_workers: u32,
+    pub inputs: Vec<ItemStock>,
+    pub outputs: Vec<ItemStock>,
+    pub buy_orders: Vec<OpenOrder>,
+    pub sell_orders: Vec<OpenOrder>,
+    /// Trucks that left their parking spot, e.g. for a delivery
+    pub trucks_out: Vec<VehicleID>,
+    pub trucks: u32,
+    /// Trips avoided by delivering several trades at once
+    pub trips_saved: u32,
+    pub prioritized: bool,
+    pub power: UtilityStatus,
+    pub water: UtilityStatus,
+}
+
+impl Simulation {
+    /// Snapshot of the goods company in the building, None if there is none
+    pub fn company_overview(&self, building: BuildingID) -> Option<CompanyOverview> {
+        let Some(SoulID::GoodsCompany(id)) = self.read::<BuildingInfos>().owner(building) else {
+            return None;
+        };
+        let c = self.world.companies.get(id)?;
+        let proto = c.comp.proto.prototype();
+        let soul = SoulID::GoodsCompany(id);
+
+        let workers_present = c
+            .workers
+            .0
+            .iter()
+            .filter_map(|&w| self.world.humans.get(w))
+            .filter(|h| h.location == Location::Building(building))
+            .count() as u32;
+
+        let trucks_out = c
+            .comp
+            .trucks
+            .iter()
+            .copied()
+            .filter(|&t| {
+                self.world
+                    .vehicles
+                    .get(t)
+                    .is_some_and(|v| !matches!(v.vehicle.state, VehicleState::Parked(_)))
+            })
+            .collect();
+
+        let market = self.read::<Market>();
+        let stocks = |items: &[RecipeItem]| {
+            items
+                .iter()
+                .map(|item| ItemStock {
+                    item: item.id,
+                    stock: market.capital(soul, item.id),
+                    per_batch: item.amount,
+                })
+                .collect::<Vec<_>>()
+        };
+        let (inputs, outputs) = match proto.recipe {
+            Some(ref r) => (stocks(&r.consumption), stocks(&r.production)),
+            None => (vec![], vec![]),
+        };
+
+        let mut buy_orders = vec![];
+        let mut sell_orders = vec![];
+        for (&item, m) in market.iter() {
+            if let Some(o) = m.buy_order(soul) {
+                buy_orders.push(OpenOrder { item, qty: o.qty });
+            }
+            if let Some(o) = m.sell_order(soul) {
+                sell_orders.push(OpenOrder { item, qty: o.qty });
+            }
+        }
+        let prioritized = market.is_prioritized(soul);
+        drop(market);
+
+        let net = self.map().electricity.net_id(building);
+        let utility = |used: bool, short: bool| match (used, net) {
+            (false, _) => UtilityStatus::Unused,
+            (true, None) => UtilityStatus::Disconnected,
+            (true, Some(_)) if short => UtilityStatus::Shortage,
+            (true, Some(_)) => UtilityStatus::Ok,
+        };
+        let power = utility(
+            proto.power_consumption > Some(Power::ZERO),
+            self.read::<ElectricityFlow>().is_shed(building),
+        );
+        let water = utility(
+            proto.water_consumption > 0.0,
+            self.read::<WaterFlow>().is_dry(building),
+        );
+
+        Some(CompanyOverview {
+            company: id,
+            workers_present,
+            workers: c.workers.0.len() as u32,
+            max_workers: c.comp.max_workers,
+            inputs,
+            outputs,
+            buy_orders,
+            sell_orders,
+            trucks_out,
+            trucks: c.comp.trucks.len() as u32,
+            trips_saved: self.read::<Deliveries>().trips_saved(id),
+            prioritized,
+            power,
+            water,
+        })
+    }
+
+    /// Humans living in the house, the owner first
+    pub fn residents(&self, house: BuildingID) -> Vec<HumanID> {
+        let owner = self.read::<BuildingInfos>().owner(house);
+        let mut residents: Vec<HumanID> = self
+            .world
+            .humans
+            .iter()
+            .filter(|(_, h)| h.home.house == house)
+            .map(|(id, _)| id)
+            .collect();
+        if let Some(SoulID::Human(owner)) = owner {
+            if let Some(i) = residents.iter().position(|&h| h == owner) {
+                residents[..=i].rotate_right(1);
+            }
+        }
+        residents
+    }
+}
@@ -0,0 +1,149 @@
+//! Reloading the prototypes while the game runs, to iterate on their values without restarting.
+//! The new registry replaces the old one as a whole, then the state built from it is patched.
+
+use prototypes::{prototypes, swap_prototypes, ConcretePrototype, ItemID, PrototypeID, Prototypes};
+use slotmapd::{HopSlotMap, Key};
+
+use crate::economy::Market;
+use crate::map::{Building, BuildingID, BuildingKind, Map};
+use crate::souls::goods_company::recipe_init;
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::utils::par_command_buffer::SimDrop;
+use crate::{Simulation, SoulID};
+
+/// Makes the candidate registry the current one, unless the world uses prototypes it doesn't define.
+/// The companies read their recipe from the prototypes on each cycle, only their orders are updated.
+pub fn reload_prototypes(sim: &mut Simulation, candidate: Box<Prototypes>) -> Result<(), String> {
+    check_in_use(sim, &candidate)?;
+
+    swap_prototypes(candidate);
+
+    let (world, res) = sim.world_res();
+    let mut market = res.write::<Market>();
+    let map = res.read::<Map>();
+    market.sync_items();
+
+    for (id, c) in world.companies.iter() {
+        let soul = SoulID::GoodsCompany(id);
+        let Some(b) = map.buildings.get(c.comp.building) else {
+            continue;
+        };
+        let Some(recipe) = &c.comp.proto.prototype().recipe else {
+            continue;
+        };
+
+        // the orders are placed again for the new amounts, the items no longer consumed are not bought
+        let items: Vec<ItemID> = market.iter().map(|(&item, _)| item).collect();
+        for item in items {
+            market.cancel_buy(soul, item);
+        }
+        recipe_init(recipe, soul, b.door_pos.xy(), &mut market);
+    }
+
+    log::info!("prototypes reloaded");
+    Ok(())
+}
+
+/// Errors on the first prototype used by the world that the candidate doesn't define,
+/// as looking it up later would panic
+fn check_in_use(sim: &Simulation, candidate: &Prototypes) -> Result<(), String> {
+    for c in sim.world.companies.values() {
+        defined(candidate, c.comp.proto, "a company")?;
+    }
+
+    for b in sim.map().buildings().values() {
+        building_defined(candidate, b)?;
+    }
+
+    for v in sim.world.vehicles.values() {
+        defined(candidate, v.vehicle.prototype, "a vehicle")?;
+    }
+
+    Ok(())
+}
+
+/// Removes the buildings and the companies, warehouses and stations in them whose prototype is not
+/// defined anymore, as when the mod defining them was disabled since the save was made.
+/// Looking them up would panic. Returns why each one was removed, to warn about it.
+pub fn remove_orphans(sim: &mut Simulation) -> Vec<String> {
+    let current = prototypes();
+    let mut removed = vec![];
+
+    let buildings: Vec<BuildingID> = sim
+        .map()
+        .buildings()
+        .values()
+        .filter_map(|b| {
+            let err = building_defined(current, b).err()?;
+            removed.push(err);
+            Some(b.id)
+        })
+        .collect();
+    for b in buildings {
+        sim.map_mut().remove_building(b);
+        sim.write::<PassengerRail>().forget_station(b);
+    }
+
+    let companies = orphans(&sim.world.companies, &mut removed, |c| {
+        defined(current, c.comp.proto, "a company")
+    });
+    remove_entities(sim, companies);
+    let warehouses = orphans(&sim.world.warehouses, &mut removed, |w| {
+        defined(current, w.w.proto, "a warehouse")
+    });
+    remove_entities(sim, warehouses);
+    let stations = orphans(&sim.world.freight_stations, &mut removed, |f| {
+        defined(current, f.f.proto, "a freight station")
+    });
+    remove_entities(sim, stations);
+
+    removed
+}
+
+fn orphans<ID: Key, E>(
+    storage: &HopSlotMap<ID, E>,
+    removed: &mut Vec<String>,
+    check: impl Fn(&E) -> Result<(), String>,
+) -> Vec<ID> {
+    storage
+        .iter()
+        .filter_map(|(id, e)| {
+            removed.push(check(e).err()?);
+            Some(id)
+        })
+        .collect()
+}
+
+fn remove_entities<E: SimDrop>(sim: &mut Simulation, ids: Vec<E::ID>) {
+    for id in ids {
+        let Some(e) = E::storage_mut(&mut sim.world).remove(id) else {
+            continue;
+        };
+        e.sim_drop(id, &mut sim.resources);
+    }
+}
+
+fn building_defined(candidate: &Prototypes, b: &Building) -> Result<(), String> {
+    match b.kind {
+        BuildingKind::GoodsCompany(id) => defined(candidate, id, "a building"),
+        BuildingKind::RailFreightStation(id) => defined(candidate, id, "a building"),
+        BuildingKind::RailPassengerStation(id) => defined(candidate, id, "a building"),
+        BuildingKind::Warehouse(id) => defined(candidate, id, "a building"),
+        BuildingKind::School(id) => defined(candidate, id, "a building"),
+        BuildingKind::Leisure(id) => defined(candidate, id, "a building"),
+        BuildingKind::Hotel(id) => defined(candidate, id, "a building"),
+        BuildingKind::Harbor(id) => defined(candidate, id, "a building"),
+        BuildingKind::Airport(id) => defined(candidate, id, "a building"),
+        BuildingKind::House | BuildingKind::TrainStation | BuildingKind::ExternalTrading => Ok(()),
+    }
+}
+
+fn defined<ID: PrototypeID>(candidate: &Prototypes, id: ID, user: &str) -> Result<(), String>
+where
+    ID::Prototype: ConcretePrototype,
+{
+    if <ID::Prototype as ConcretePrototype>::storage(candidate).contains_key(&id) {
+        return Ok(());
+    }
+    Err(format!("{:?} is removed but still used by {}", id, user))
+}
@@ -0,0 +1,240 @@
+//! Named saves, each one in its own directory under `world/saves`.
+//! The directory contains the simulation, a manifest describing it and a thumbnail.
+
+use std::io;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use common::saveload::{CheckedCompressedBincode, Encoder, JSON};
+use prototypes::{DayTime, GameTime, ModManifest, Money, Tick};
+use serde::{Deserialize, Serialize};
+
+use crate::economy::Government;
+use crate::gameplay::{Cheats, Difficulty, GameplayParams};
+use crate::Simulation;
+
+/// Directory of the named saves, relative to the world directory
+pub const SAVES_DIR: &str = "saves";
+
+/// Maximum length of a save name
+pub const MAX_SAVE_NAME_LEN: usize = 64;
+
+/// Names Windows keeps for its devices, also with an extension like `nul.txt`
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Written next to a save so that it can be described without loading it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    /// Name of the save, the name of its directory for named saves
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub city_name: String,
+    #[serde(default)]
+    pub population: u32,
+    #[serde(default)]
+    pub money: Money,
+    pub tick: Tick,
+    pub daytime: DayTime,
+    /// Real seconds spent playing the city
+    #[serde(default)]
+    pub play_time: f64,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// The gameplay parameters were changed during the game
+    #[serde(default)]
+    pub sandbox: bool,
+    /// The developer console changed the game
+    #[serde(default)]
+    pub cheats_used: bool,
+    /// Mods active when saving, as name@version in load order
+    #[serde(default)]
+    pub mods: Vec<String>,
+    /// Seconds since the unix epoch
+    pub saved_at: u64,
+}
+
+impl SaveMetadata {
+    /// Reads the manifests of all the named saves, the most recent first.
+    /// Reads from the disk so it shouldn't be called every frame.
+    pub fn scan() -> Vec<SaveMetadata> {
+        let Ok(dir) = std::fs::read_dir(saves_dir()) else {
+            return vec![];
+        };
+        let mut saves: Vec<SaveMetadata> = dir
+            .flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .filter_map(|entry| Self::load(entry.file_name().to_str()?))
+            .collect();
+        saves.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then(a.name.cmp(&b.name)));
+        saves
+    }
+
+    /// Manifest of the named save, None if it doesn't exist
+    pub fn load(name: &str) -> Option<Self> {
+        let mut meta: SaveMetadata = JSON::load(&meta_name(&world_name(name))).ok()?;
+        meta.name = name.to_string();
+        Some(meta)
+    }
+
+    /// Describes how the mods of the save differ from the active ones, None if they match.
+    /// Loading still works, the markets of the missing items are dropped and the buildings of the
+    /// missing prototypes are removed.
+    pub fn mods_mismatch(&self) -> Option<String> {
+        let active = active_mods();
+        let missing: Vec<&str> = self
+            .mods
+            .iter()
+            .filter(|m| !active.contains(m))
+            .map(String::as_str)
+            .collect();
+        let added: Vec<&str> = active
+            .iter()
+            .filter(|m| !self.mods.contains(m))
+            .map(String::as_str)
+            .collect();
+        let mut parts = vec![];
+        if !missing.is_empty() {
+            parts.push(format!("saved with {}", missing.join(", ")));
+        }
+        if !added.is_empty() {
+            parts.push(format!("saved without {}", added.join(", ")));
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        Some(parts.join("; "))
+    }
+
+    /// Screenshot of the city when it was saved, might not exist
+    pub fn thumbnail_path(&self) -> PathBuf {
+        thumbnail_path(&self.name)
+    }
+}
+
+fn active_mods() -> Vec<String> {
+    prototypes::loaded_mods()
+        .active
+        .iter()
+        .map(ModManifest::tag)
+        .collect()
+}
+
+/// Name of the metadata file written next to a save
+pub fn meta_name(save_name: &str) -> String {
+    format!("{save_name}_meta")
+}
+
+fn saves_dir() -> PathBuf {
+    PathBuf::from("world").join(SAVES_DIR)
+}
+
+pub fn save_dir(name: &str) -> PathBuf {
+    saves_dir().join(name)
+}
+
+/// Name of the simulation file of the named save, as given to the encoders
+pub fn world_name(name: &str) -> String {
+    format!("{SAVES_DIR}/{name}/world")
+}
+
+pub fn thumbnail_path(name: &str) -> PathBuf {
+    save_dir(name).join("thumbnail.png")
+}
+
+/// Save names are used as directory names, so they must be valid on every platform
+pub fn is_valid_save_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name.len() <= MAX_SAVE_NAME_LEN
+        && name == name.trim()
+        && !name.starts_with('.')
+        && !name.ends_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        && !is_reserved_name(name)
+}
+
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES.iter().any(|r| stem.eq_ignore_ascii_case(r))
+}
+
+pub fn save_exists(name: &str) -> bool {
+    std::fs::metadata(CheckedCompressedBincode::filename(&world_name(name))).is_ok()
+}
+
+pub fn delete_save(name: &str) -> io::Result<()> {
+    if !is_valid_save_name(name) {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "invalid save name"));
+    }
+    std::fs::remove_dir_all(save_dir(name))
+}
+
+/// Fails if a save already uses the new name
+pub fn rename_save(from: &str, to: &str) -> io::Result<()> {
+    if !is_valid_save_name(from) || !is_valid_save_name(to) {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "invalid save name"));
+    }
+    if save_dir(to).exists() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("a save named {to} already exists"),
+        ));
+    }
+    std::fs::rename(save_dir(from), save_dir(to))?;
+
+    if let Some(mut meta) = SaveMetadata::load(to) {
+        meta.name = to.to_string();
+        JSON::save_silent(&meta, &meta_name(&world_name(to)));
+    }
+    Ok(())
+}
+
+impl Simulation {
+    pub fn metadata(&self, name: &str, city_name: &str, play_time: f64) -> SaveMetadata {
+        let time = self.read::<GameTime>();
+        let params = self.read::<GameplayParams>();
+        SaveMetadata {
+            name: name.to_string(),
+            city_name: city_name.to_string(),
+            population: self.world.humans.len() as u32,
+            money: self.read::<Government>().money,
+            tick: time.tick,
+            daytime: time.daytime,
+            play_time,
+            difficulty: params.difficulty,
+            sandbox: params.sandbox,
+            cheats_used: self.read::<Cheats>().used,
+            mods: active_mods(),
+            saved_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Saves into the directory of the named save given by the metadata
+    pub fn save_named(&self, meta: &SaveMetadata) -> Option<()> {
+        if !is_valid_save_name(&meta.name) {
+            log::error!("invalid save name: {}", meta.name);
+            return None;
+        }
+        std::fs::create_dir_all(save_dir(&meta.name))
+            .map_err(|e| log::error!("failed creating save directory: {}", e))
+            .ok()?;
+        self.save_with_metadata(&world_name(&meta.name), meta)
+    }
+
+    /// Saves made with other mods are loaded anyway, with a warning
+    pub fn load_named(name: &str) -> io::Result<Self> {
+        if let Some(mismatch) = SaveMetadata::load(name).and_then(|m| m.mods_mismatch()) {
+            log::warn!("loading {} made with other mods: {}", name, mismatch);
+        }
+        Self::try_load_from_disk(&world_name(name))
+    }
+}
@@ -0,0 +1,262 @@
+//! Scenarios run the simulation without rendering, as fast as possible, to test the balance of
+//! the game. A scenario is a json file of timed actions, the run produces a daily report of the
+//! population, the money, the blackouts and the trade volume of each item.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use common::saveload::{Encoder, JSONPretty, JSON};
+use geom::{Vec2, Vec3};
+use prototypes::{Money, HOURS_PER_DAY, TICKS_PER_HOUR};
+use serde::{Deserialize, Serialize};
+
+use crate::economy::{EconomyHistory, Government, HistoryLevel};
+use crate::map::{LanePatternBuilder, ProjectFilter};
+use crate::map_dynamic::ElectricityFlow;
+use crate::utils::scheduler::SeqSchedule;
+use crate::world_command::WorldCommand;
+use crate::{Simulation, SimulationOptions};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Save to start from, a new map is generated if None
+    #[serde(default)]
+    pub load: Option<String>,
+    /// Size of the generated terrain, in chunks
+    #[serde(default = "default_terrain_size")]
+    pub terrain_size: u16,
+    /// In-game days to run
+    #[serde(default = "default_days")]
+    pub days: u32,
+    #[serde(default)]
+    pub steps: Vec<ScenarioStep>,
+}
+
+fn default_terrain_size() -> u16 {
+    1
+}
+
+fn default_days() -> u32 {
+    10
+}
+
+/// An action done at the start of the given hour, counted from the start of the run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioStep {
+    pub day: u32,
+    #[serde(default)]
+    pub hour: u32,
+    pub action: ScenarioAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioAction {
+    /// Roads going through the points, connected to the existing roads they touch
+    Road { points: Vec<Vec3> },
+    /// Houses on the free lots nearest to the position
+    Houses { near: Vec2, count: u32 },
+    /// Any command, as sent by the players
+    Command(WorldCommand),
+    /// Logs the current stats
+    Stats,
+}
+
+/// Trade volume of one item over a day
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradeVolume {
+    pub traded: u64,
+    pub imports: u64,
+    pub exports: u64,
+}
+
+/// Stats at the end of one day of the run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayReport {
+    pub day: u32,
+    pub tick: u64,
+    pub population: u32,
+    pub money: Money,
+    /// Hours that started with at least one electricity network in a blackout
+    pub blackout_hours: u32,
+    /// By item name
+    pub trade: BTreeMap<String, TradeVolume>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    pub days: Vec<DayReport>,
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        JSON::decode(&std::fs::read(path)?)
+    }
+
+    /// Loads or generates the map of the scenario
+    pub fn simulation(&self) -> io::Result<Simulation> {
+        match self.load {
+            Some(ref name) => Simulation::try_load_from_disk(name),
+            None => Ok(Simulation::new_with_options(SimulationOptions {
+                terrain_size: self.terrain_size,
+                save_replay: false,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Runs the scenario for `days` in-game days, one hour at a time.
+    /// The run starts at the next hour so that the report follows the economy history.
+    pub fn run(&self, sim: &mut Simulation, days: u32) -> ScenarioReport {
+        let mut sched = Simulation::schedule();
+        let mut report = ScenarioReport::default();
+
+        run_until(sim, &mut sched, next_hour(sim.get_tick()));
+
+        let mut blackout_hours = 0;
+        let mut trade: BTreeMap<String, TradeVolume> = BTreeMap::new();
+        for hour in 0..days * HOURS_PER_DAY as u32 {
+            for step in &self.steps {
+                if step.day * HOURS_PER_DAY as u32 + step.hour == hour {
+                    step.action.apply(sim);
+                }
+            }
+
+            if is_blackout(sim) {
+                blackout_hours += 1;
+            }
+
+            run_until(sim, &mut sched, next_hour(sim.get_tick()));
+
+            let history = sim.read::<EconomyHistory>();
+            for (item, series) in history.iter_series(HistoryLevel::Hourly) {
+                let Some(p) = series.back() else {
+                    continue;
+                };
+                let v = trade.entry(item.prototype().name.clone()).or_default();
+                v.traded += p.traded;
+                v.imports += p.imports;
+                v.exports += p.exports;
+            }
+            drop(history);
+
+            if (hour + 1) % HOURS_PER_DAY as u32 == 0 {
+                report.days.push(DayReport {
+                    day: hour / HOURS_PER_DAY as u32,
+                    tick: sim.get_tick(),
+                    population: sim.world.humans.len() as u32,
+                    money: sim.read::<Government>().money,
+                    blackout_hours: std::mem::take(&mut blackout_hours),
+                    trade: std::mem::take(&mut trade),
+                });
+            }
+        }
+
+        report
+    }
+}
+
+impl ScenarioAction {
+    pub fn apply(&self, sim: &mut Simulation) {
+        match *self {
+            ScenarioAction::Road { ref points } => {
+                for w in points.windows(2) {
+                    let map = sim.map();
+                    let command = WorldCommand::MapMakeConnection {
+                        from: map.project(w[0], 0.0, ProjectFilter::ALL),
+                        to: map.project(w[1], 0.0, ProjectFilter::ALL),
+                        inter: None,
+                        pat: LanePatternBuilder::default().build(),
+                    };
+                    drop(map);
+                    command.apply(sim);
+                }
+            }
+            ScenarioAction::Houses { near, count } => {
+                for _ in 0..count {
+                    let Some(lot) = sim
+                        .map()
+                        .lots()
+                        .values()
+                        .min_by_key(|lot| lot.shape.center().distance2(near) as i64)
+                        .map(|lot| lot.id)
+                    else {
+                        log::warn!("scenario: no free lot to build a house near {}", near);
+                        return;
+                    };
+                    WorldCommand::MapBuildHouse(lot).apply(sim);
+                }
+            }
+            ScenarioAction::Command(ref command) => command.apply(sim),
+            ScenarioAction::Stats => {
+                log::info!(
+                    "scenario: tick {} population {} money {}",
+                    sim.get_tick(),
+                    sim.world.humans.len(),
+                    sim.read::<Government>().money
+                );
+            }
+        }
+    }
+}
+
+impl ScenarioReport {
+    /// Writes the report as csv or as json depending on the extension of the path
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let data = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.to_csv().into_bytes(),
+            _ => JSONPretty::encode(self)?,
+        };
+        std::fs::write(path, data)
+    }
+
+    /// One row per day, with three columns per item
+    pub fn to_csv(&self) -> String {
+        let items: Vec<&String> = self
+            .days
+            .first()
+            .map(|d| d.trade.keys().collect())
+            .unwrap_or_default();
+
+        let mut csv = String::from("day,tick,population,money,blackout_hours");
+        for item in &items {
+            csv += &format!(",{item}_traded,{item}_imports,{item}_exports");
+        }
+        csv.push('\n');
+
+        for d in &self.days {
+            csv += &format!(
+                "{},{},{},{},{}",
+                d.day,
+                d.tick,
+                d.population,
+                d.money.bucks(),
+                d.blackout_hours
+            );
+            for item in &items {
+                let v = d.trade.get(*item).copied().unwrap_or_default();
+                csv += &format!(",{},{},{}", v.traded, v.imports, v.exports);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn next_hour(tick: u64) -> u64 {
+    (tick / TICKS_PER_HOUR + 1) * TICKS_PER_HOUR
+}
+
+fn run_until(sim: &mut Simulation, sched: &mut SeqSchedule, tick: u64) {
+    while sim.get_tick() < tick {
+        sim.tick(sched, &[]);
+    }
+}
+
+fn is_blackout(sim: &Simulation) -> bool {
+    let map = sim.map();
+    let flow = sim.read::<ElectricityFlow>();
+    map.electricity.networks().any(|n| flow.blackout(n.id))
+}
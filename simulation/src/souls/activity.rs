@@ -0,0 +1,180 @@
+//! What the citizens did recently, shown by the inspector as a timeline of their day.
+//! Activities are recorded by the decision system when they change. The log is not saved,
+//! so it starts over when a game is loaded.
+
+use std::collections::VecDeque;
+
+use common::FastMap;
+use prototypes::{GameTime, Tick, TICKS_PER_HOUR, TICKS_PER_SECOND};
+
+use crate::map::BuildingID;
+use crate::souls::desire::Work;
+use crate::transportation::Location;
+use crate::world::HumanID;
+
+/// Activities kept per citizen, enough for more than a day of a usual schedule
+pub const ACTIVITY_HISTORY_LEN: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Activity {
+    Sleep,
+    Home,
+    Commute,
+    Work,
+    /// In a building that is neither the home nor the workplace, e.g. buying food
+    Shop,
+    /// At a park or a leisure venue
+    Leisure,
+}
+
+impl Activity {
+    /// What the citizen is doing given where they are and the venue they are visiting
+    pub fn classify(
+        loc: &Location,
+        home: BuildingID,
+        work: Option<&Work>,
+        leisure: Option<BuildingID>,
+        hour: i32,
+    ) -> Self {
+        if leisure.is_some() {
+            return Activity::Leisure;
+        }
+        match *loc {
+            Location::Building(b) if b == home => {
+                if !(6..22).contains(&hour) {
+                    Activity::Sleep
+                } else {
+                    Activity::Home
+                }
+            }
+            Location::Building(b) if work.is_some_and(|w| w.workplace == b) => Activity::Work,
+            Location::Building(_) => Activity::Shop,
+            Location::Vehicle(v) if work.is_some_and(|w| w.kind.truck() == Some(v)) => {
+                Activity::Work
+            }
+            Location::Outside | Location::Vehicle(_) | Location::Train(_) => Activity::Commute,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Activity::Sleep => "Sleep",
+            Activity::Home => "Home",
+            Activity::Commute => "Commute",
+            Activity::Work => "Work",
+            Activity::Shop => "Shop",
+            Activity::Leisure => "Leisure",
+        }
+    }
+}
+
+/// Span of the current day spent doing an activity, in hours since midnight
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ActivitySpan {
+    pub start: f32,
+    pub end: f32,
+    pub activity: Activity,
+}
+
+#[derive(Default)]
+pub struct ActivityLog {
+    history: FastMap<HumanID, VecDeque<(Tick, Activity)>>,
+}
+
+impl ActivityLog {
+    /// Records the activity if it changed since the last one
+    pub fn record(&mut self, human: HumanID, tick: Tick, activity: Activity) {
+        let h = self.history.entry(human).or_default();
+        if h.back().is_some_and(|&(_, last)| last == activity) {
+            return;
+        }
+        if h.len() >= ACTIVITY_HISTORY_LEN {
+            h.pop_front();
+        }
+        h.push_back((tick, activity));
+    }
+
+    pub fn current(&self, human: HumanID) -> Option<Activity> {
+        self.history.get(&human)?.back().map(|&(_, a)| a)
+    }
+
+    /// Activities since midnight, the first one might have started the day before.
+    /// Empty if nothing was recorded yet.
+    pub fn today(&self, human: HumanID, time: &GameTime) -> Vec<ActivitySpan> {
+        let Some(h) = self.history.get(&human) else {
+            return vec![];
+        };
+        let d = &time.daytime;
+        let since_midnight =
+            (d.hour as u64 * 3600 + d.minute as u64 * 60 + d.second as u64) * TICKS_PER_SECOND;
+        let midnight = time.tick.0.saturating_sub(since_midnight);
+        let hours = |tick: u64| tick.saturating_sub(midnight) as f32 / TICKS_PER_HOUR as f32;
+
+        let mut spans = Vec::new();
+        for (i, &(start, activity)) in h.iter().enumerate() {
+            let end = h.get(i + 1).map_or(time.tick.0, |&(t, _)| t.0);
+            if end <= midnight {
+                continue;
+            }
+            spans.push(ActivitySpan {
+                start: hours(start.0),
+                end: hours(end),
+                activity,
+            });
+        }
+        spans
+    }
+
+    /// Forgets the citizens that no longer exist
+    pub fn retain(&mut self, mut f: impl FnMut(HumanID) -> bool) {
+        self.history.retain(|&id, _| f(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn today_spans_start_at_midnight() {
+        let h = HumanID::default();
+        let mut log = ActivityLog::default();
+
+        // the game starts at 8am, midnight is 16 hours after the start
+        let midnight = 16 * TICKS_PER_HOUR;
+        log.record(h, Tick(midnight - TICKS_PER_HOUR), Activity::Sleep);
+        log.record(h, Tick(midnight + 7 * TICKS_PER_HOUR), Activity::Commute);
+        log.record(
+            h,
+            Tick(midnight + 7 * TICKS_PER_HOUR + 1),
+            Activity::Commute,
+        );
+        log.record(h, Tick(midnight + 8 * TICKS_PER_HOUR), Activity::Work);
+
+        let time = GameTime::new(Tick(midnight + 10 * TICKS_PER_HOUR));
+        let spans = log.today(h, &time);
+
+        assert_eq!(log.current(h), Some(Activity::Work));
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].start, 0.0);
+        assert_eq!(spans[0].end, 7.0);
+        assert_eq!(spans[1].activity, Activity::Commute);
+        assert_eq!(spans[2].start, 8.0);
+        assert_eq!(spans[2].end, 10.0);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let h = HumanID::default();
+        let mut log = ActivityLog::default();
+        for i in 0..3 * ACTIVITY_HISTORY_LEN as u64 {
+            let a = if i % 2 == 0 {
+                Activity::Home
+            } else {
+                Activity::Shop
+            };
+            log.record(h, Tick(i), a);
+        }
+        assert_eq!(log.history[&h].len(), ACTIVITY_HISTORY_LEN);
+    }
+}
@@ -0,0 +1,520 @@
+//! Deliveries of the goods sold by the companies owning trucks.
+//! The sold trades going to buildings near each other are grouped into a single trip,
+//! and the buyers only receive their goods when the truck stops at their building.
+
+use std::collections::BTreeMap;
+
+use geom::Vec3;
+use prototypes::ItemID;
+use serde::{Deserialize, Serialize};
+
+use crate::economy::{Cargo, Market, Trade, TradeTarget};
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::Router;
+use crate::utils::resources::Resources;
+use crate::world::{CompanyID, VehicleID};
+use crate::{SoulID, World};
+
+/// Trades going to buildings at most this far from the first one are delivered in the same trip
+pub const CLUSTER_RADIUS: f32 = 300.0;
+
+/// Whether the goods bought by the soul from a company owning trucks are only received when
+/// they are delivered. Households pick up their goods themselves and external trades are
+/// handled by the freight stations.
+pub fn delivered_by_truck(buyer: SoulID) -> bool {
+    matches!(buyer, SoulID::GoodsCompany(_) | SoulID::Warehouse(_))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryStop {
+    pub building: BuildingID,
+    pub pos: Vec3,
+    pub cargo: Vec<Cargo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryTrip {
+    pub company: CompanyID,
+    /// The stops left, in visiting order
+    pub stops: Vec<DeliveryStop>,
+}
+
+/// The delivery trips of the trucks of the companies
+#[derive(Default, Serialize, Deserialize)]
+pub struct Deliveries {
+    trips: BTreeMap<VehicleID, DeliveryTrip>,
+    /// Trips avoided by delivering several trades at once, per company
+    trips_saved: BTreeMap<CompanyID, u32>,
+}
+
+impl Deliveries {
+    pub fn trip(&self, truck: VehicleID) -> Option<&DeliveryTrip> {
+        self.trips.get(&truck)
+    }
+
+    /// The buildings the truck still has to go to, in visiting order
+    pub fn stops(&self, truck: VehicleID) -> impl Iterator<Item = BuildingID> + '_ {
+        self.trips
+            .get(&truck)
+            .into_iter()
+            .flat_map(|t| t.stops.iter().map(|s| s.building))
+    }
+
+    pub fn trips_saved(&self, company: CompanyID) -> u32 {
+        self.trips_saved.get(&company).copied().unwrap_or(0)
+    }
+
+    /// Sends the truck on a trip delivering `n_trades` trades at the given stops
+    pub fn start(
+        &mut self,
+        truck: VehicleID,
+        company: CompanyID,
+        stops: Vec<DeliveryStop>,
+        n_trades: usize,
+    ) {
+        if stops.is_empty() {
+            return;
+        }
+        *self.trips_saved.entry(company).or_default() += n_trades.saturating_sub(1) as u32;
+        self.trips.insert(truck, DeliveryTrip { company, stops });
+    }
+
+    /// The truck stopped at the building, its buyers receive their goods.
+    /// The goods of the buyers who are gone go back to the seller.
+    /// Returns the next stop, None once the trip is over.
+    pub fn complete_stop(
+        &mut self,
+        truck: VehicleID,
+        building: BuildingID,
+        market: &mut Market,
+    ) -> Option<BuildingID> {
+        let trip = self.trips.get_mut(&truck)?;
+        if let Some(i) = trip.stops.iter().position(|s| s.building == building) {
+            let stop = trip.stops.remove(i);
+            hand_over(market, SoulID::GoodsCompany(trip.company), stop.cargo);
+        }
+        let next = trip.stops.first().map(|s| s.building);
+        if next.is_none() {
+            self.trips.remove(&truck);
+        }
+        next
+    }
+
+    /// Drops the stops at the buildings that were removed, their goods go back to the seller.
+    /// The trips left without stops are over.
+    pub fn drop_missing_stops(&mut self, map: &Map, market: &mut Market) {
+        let mut missing = vec![];
+        for (&truck, trip) in &self.trips {
+            for stop in &trip.stops {
+                if !map.buildings().contains_key(stop.building) {
+                    missing.push((truck, stop.building));
+                }
+            }
+        }
+        for (truck, building) in missing {
+            self.complete_stop(truck, building, market);
+        }
+    }
+
+    /// Gives the goods of the trip straight to the buyers, who already paid for them,
+    /// when nobody is left to drive the truck or the truck was destroyed
+    pub fn hand_over(&mut self, truck: VehicleID, market: &mut Market) {
+        let Some(trip) = self.trips.remove(&truck) else {
+            return;
+        };
+        let seller = SoulID::GoodsCompany(trip.company);
+        hand_over(market, seller, trip.stops.into_iter().flat_map(|s| s.cargo));
+    }
+
+    /// The company is gone, the goods of its trucks are handed over to the buyers
+    pub fn remove_company(&mut self, company: CompanyID, market: &mut Market) {
+        let trucks: Vec<VehicleID> = self
+            .trips
+            .iter()
+            .filter(|(_, t)| t.company == company)
+            .map(|(&truck, _)| truck)
+            .collect();
+        for truck in trucks {
+            self.hand_over(truck, market);
+        }
+        self.trips_saved.remove(&company);
+    }
+}
+
+/// Gives the goods to the buyers waiting for them.
+/// The goods of the buyers who are gone go back to the seller.
+pub fn hand_over(market: &mut Market, seller: SoulID, cargo: impl IntoIterator<Item = Cargo>) {
+    for c in cargo {
+        if !delivered_by_truck(c.soul) {
+            continue;
+        }
+        if is_registered(market, c.soul, c.kind) {
+            market.receive(c.soul, c.kind, c.qty);
+        } else if is_registered(market, seller, c.kind) {
+            market.receive(seller, c.kind, c.qty);
+        }
+    }
+}
+
+/// Souls removed from the market don't get goods anymore
+fn is_registered(market: &Market, soul: SoulID, kind: ItemID) -> bool {
+    market
+        .inner()
+        .get(&kind)
+        .and_then(|m| m.capital(soul))
+        .is_some()
+}
+
+/// Groups the oldest sold trade with the ones going near it into a single trip, within the
+/// capacity of the truck. The grouped trades are removed from `sold`, as well as the ones
+/// that have nowhere to be delivered. A truck that carries nothing plans no trip.
+/// Returns the stops in visiting order, how many trades they deliver and the goods of the
+/// trades that have nowhere to be delivered, to be handed over with [`hand_over`].
+pub fn plan_delivery(
+    sold: &mut Vec<Trade>,
+    from: Vec3,
+    capacity: u32,
+    mut place: impl FnMut(TradeTarget) -> Option<(BuildingID, Vec3)>,
+) -> (Vec<DeliveryStop>, usize, Vec<Cargo>) {
+    let mut stops: Vec<DeliveryStop> = vec![];
+    let mut anchor = None;
+    let mut load = 0;
+    let mut n_trades = 0;
+    let mut undeliverable = vec![];
+    if capacity == 0 {
+        return (stops, n_trades, undeliverable);
+    }
+
+    sold.retain(|trade| {
+        let cargo = Cargo {
+            soul: trade.buyer.0,
+            kind: trade.kind,
+            qty: trade.qty,
+        };
+        let Some((building, pos)) = place(trade.buyer) else {
+            if !trade.buyer.0.is_external() {
+                log::warn!("driver can't find the place to deliver for {:?}", trade);
+            }
+            undeliverable.push(cargo);
+            return false;
+        };
+        let qty = trade.qty.max(0) as u32;
+        match anchor {
+            None => anchor = Some(pos),
+            Some(anchor) => {
+                if !pos.is_close(anchor, CLUSTER_RADIUS) || load + qty > capacity {
+                    return true;
+                }
+            }
+        }
+        load += qty;
+        n_trades += 1;
+
+        match stops.iter_mut().find(|s| s.building == building) {
+            Some(stop) => stop.cargo.push(cargo),
+            None => stops.push(DeliveryStop {
+                building,
+                pos,
+                cargo: vec![cargo],
+            }),
+        }
+        false
+    });
+
+    Router::nearest_neighbor_tour(from, &mut stops, |s| s.pos);
+    (stops, n_trades, undeliverable)
+}
+
+/// Hands over to the buyers the goods of the trucks that disappeared during their trip,
+/// and gives back to the sellers the goods of the stops at buildings that were removed
+pub fn delivery_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::delivery_system");
+    let mut deliveries = resources.write::<Deliveries>();
+    let mut market = resources.write::<Market>();
+    deliveries.drop_missing_stops(&resources.read::<Map>(), &mut market);
+
+    let lost: Vec<VehicleID> = deliveries
+        .trips
+        .keys()
+        .filter(|&&truck| !world.vehicles.contains_key(truck))
+        .copied()
+        .collect();
+    for truck in lost {
+        deliveries.hand_over(truck, &mut market);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec3, Vec3};
+    use prototypes::{ItemID, Money};
+
+    use crate::economy::{Market, Trade, TradeTarget};
+    use crate::map::BuildingID;
+    use crate::tests::{test_prototypes, PrototypesGuard};
+    use crate::world::{CompanyID, VehicleID};
+    use crate::SoulID;
+
+    use super::{hand_over, plan_delivery, Deliveries};
+
+    fn key(id: u64) -> slotmapd::KeyData {
+        slotmapd::KeyData::from_ffi((1 << 32) | id)
+    }
+
+    #[test]
+    fn clustered_trades_share_a_truck() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "flour",
+            label = "Flour"
+          }
+        }
+        "#,
+        );
+        let flour = ItemID::new("flour");
+        let company = CompanyID::from(key(1));
+        let seller = SoulID::GoodsCompany(company);
+        let truck = VehicleID::from(key(2));
+
+        let buyers: Vec<(SoulID, BuildingID, Vec3)> = (0..5)
+            .map(|i| {
+                (
+                    SoulID::GoodsCompany(CompanyID::from(key(10 + i))),
+                    BuildingID::from(key(20 + i)),
+                    vec3(100.0 + 20.0 * i as f32, 50.0, 0.0),
+                )
+            })
+            .collect();
+
+        let mut m = Market::default();
+        let mut sold = vec![];
+        for &(buyer, ..) in buyers.iter().rev() {
+            m.register(buyer, flour);
+            sold.push(Trade {
+                buyer: TradeTarget(buyer),
+                seller: TradeTarget(seller),
+                qty: 2,
+                kind: flour,
+                money_delta: Money::ZERO,
+                tariff: Money::ZERO,
+                value: Money::ZERO,
+            });
+        }
+        // a far away buyer gets its own trip
+        let far = SoulID::GoodsCompany(CompanyID::from(key(30)));
+        sold.push(Trade {
+            buyer: TradeTarget(far),
+            ..sold[0]
+        });
+
+        let (stops, n_trades, _) = plan_delivery(&mut sold, Vec3::ZERO, 50, |t| {
+            if t.0 == far {
+                return Some((BuildingID::from(key(31)), vec3(5000.0, 0.0, 0.0)));
+            }
+            buyers
+                .iter()
+                .find(|b| b.0 == t.0)
+                .map(|&(_, building, pos)| (building, pos))
+        });
+        assert_eq!(n_trades, 5);
+        assert_eq!(sold.len(), 1);
+        // nearest first
+        let order: Vec<BuildingID> = stops.iter().map(|s| s.building).collect();
+        let expected: Vec<BuildingID> = buyers.iter().map(|b| b.1).collect();
+        assert_eq!(order, expected);
+
+        let mut deliveries = Deliveries::default();
+        deliveries.start(truck, company, stops, n_trades);
+        assert_eq!(deliveries.trips_saved(company), 4);
+        assert_eq!(deliveries.stops(truck).count(), 5);
+
+        // each buyer gets its goods when the truck stops at its building
+        for (i, &(buyer, building, _)) in buyers.iter().enumerate() {
+            let next = deliveries.complete_stop(truck, building, &mut m);
+            assert_eq!(next, buyers.get(i + 1).map(|b| b.1));
+            assert_eq!(m.capital(buyer, flour), 2);
+            for &(other, ..) in &buyers[i + 1..] {
+                assert_eq!(m.capital(other, flour), 0);
+            }
+        }
+        assert!(deliveries.trip(truck).is_none());
+    }
+
+    #[test]
+    fn lost_truck_still_delivers_the_buyers() {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "flour",
+            label = "Flour"
+          }
+        }
+        "#,
+        );
+        let flour = ItemID::new("flour");
+        let company = CompanyID::from(key(1));
+        let seller = SoulID::GoodsCompany(company);
+        let buyer = SoulID::GoodsCompany(CompanyID::from(key(3)));
+        let truck = VehicleID::from(key(2));
+
+        let mut m = Market::default();
+        m.register(seller, flour);
+        m.register(buyer, flour);
+
+        let mut sold = vec![Trade {
+            buyer: TradeTarget(buyer),
+            seller: TradeTarget(seller),
+            qty: 3,
+            kind: flour,
+            money_delta: Money::ZERO,
+            tariff: Money::ZERO,
+            value: Money::ZERO,
+        }];
+        let building = BuildingID::from(key(4));
+        let (stops, n, _) = plan_delivery(&mut sold, Vec3::ZERO, 50, |_| Some((building, Vec3::X)));
+
+        let mut deliveries = Deliveries::default();
+        deliveries.start(truck, company, stops, n);
+        deliveries.hand_over(truck, &mut m);
+
+        // the buyer paid for the goods
+        assert_eq!(m.capital(seller, flour), 0);
+        assert_eq!(m.capital(buyer, flour), 3);
+        assert!(deliveries.trip(truck).is_none());
+    }
+
+    fn flour_trade(buyer: SoulID, seller: SoulID) -> (Trade, PrototypesGuard) {
+        let prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "flour",
+            label = "Flour"
+          }
+        }
+        "#,
+        );
+        let trade = Trade {
+            buyer: TradeTarget(buyer),
+            seller: TradeTarget(seller),
+            qty: 3,
+            kind: ItemID::new("flour"),
+            money_delta: Money::ZERO,
+            tariff: Money::ZERO,
+            value: Money::ZERO,
+        };
+        (trade, prototypes)
+    }
+
+    #[test]
+    fn five_trades_fill_one_truck() {
+        let company = CompanyID::from(key(1));
+        let seller = SoulID::GoodsCompany(company);
+        let truck = VehicleID::from(key(2));
+        let (trade, _prototypes) = flour_trade(seller, seller);
+
+        let buyers: Vec<(SoulID, BuildingID, Vec3)> = (0..6)
+            .map(|i| {
+                (
+                    SoulID::GoodsCompany(CompanyID::from(key(10 + i))),
+                    BuildingID::from(key(20 + i)),
+                    vec3(10.0 * i as f32, 0.0, 0.0),
+                )
+            })
+            .collect();
+        let mut sold: Vec<Trade> = buyers
+            .iter()
+            .map(|&(buyer, ..)| Trade {
+                buyer: TradeTarget(buyer),
+                ..trade
+            })
+            .collect();
+        let place = |t: TradeTarget| {
+            buyers
+                .iter()
+                .find(|b| b.0 == t.0)
+                .map(|&(_, building, pos)| (building, pos))
+        };
+
+        // a truck without capacity doesn't leave
+        let (stops, n_trades, undeliverable) = plan_delivery(&mut sold, Vec3::ZERO, 0, place);
+        assert!(stops.is_empty());
+        assert_eq!(n_trades, 0);
+        assert!(undeliverable.is_empty());
+        assert_eq!(sold.len(), 6);
+
+        // five trades of 3 fit in the truck, the sixth waits for the next trip
+        let (stops, n_trades, _) = plan_delivery(&mut sold, Vec3::ZERO, 15, place);
+        assert_eq!(n_trades, 5);
+        assert_eq!(stops.len(), 5);
+        assert_eq!(sold.len(), 1);
+
+        let mut deliveries = Deliveries::default();
+        deliveries.start(truck, company, stops, n_trades);
+        assert_eq!(deliveries.stops(truck).count(), 5);
+        assert_eq!(deliveries.trips_saved(company), 4);
+    }
+
+    #[test]
+    fn goods_of_a_gone_buyer_go_back_to_the_seller() {
+        let company = CompanyID::from(key(1));
+        let seller = SoulID::GoodsCompany(company);
+        let buyer = SoulID::GoodsCompany(CompanyID::from(key(3)));
+        let truck = VehicleID::from(key(2));
+        let (trade, _prototypes) = flour_trade(buyer, seller);
+        let flour = trade.kind;
+
+        let mut m = Market::default();
+        m.register(seller, flour);
+        m.register(buyer, flour);
+
+        let building = BuildingID::from(key(4));
+        let (stops, n, _) = plan_delivery(&mut vec![trade], Vec3::ZERO, 50, |_| {
+            Some((building, Vec3::X))
+        });
+        let mut deliveries = Deliveries::default();
+        deliveries.start(truck, company, stops, n);
+
+        // the buyer closed during the trip
+        m.remove(buyer);
+        assert_eq!(deliveries.complete_stop(truck, building, &mut m), None);
+        assert_eq!(m.capital(seller, flour), 3);
+        assert!(deliveries.trip(truck).is_none());
+    }
+
+    #[test]
+    fn goods_of_a_removed_seller_are_handed_over() {
+        let company = CompanyID::from(key(1));
+        let seller = SoulID::GoodsCompany(company);
+        let buyer = SoulID::GoodsCompany(CompanyID::from(key(3)));
+        let truck = VehicleID::from(key(2));
+        let (trade, _prototypes) = flour_trade(buyer, seller);
+        let flour = trade.kind;
+
+        let mut m = Market::default();
+        m.register(buyer, flour);
+
+        // the buyer of an unknown building gets its goods without waiting for a truck
+        let (stops, n, undeliverable) = plan_delivery(&mut vec![trade], Vec3::ZERO, 50, |_| None);
+        assert!(stops.is_empty());
+        hand_over(&mut m, seller, undeliverable);
+        assert_eq!(m.capital(buyer, flour), 3);
+
+        let building = BuildingID::from(key(4));
+        let (stops, n2, _) = plan_delivery(&mut vec![trade], Vec3::ZERO, 50, |_| {
+            Some((building, Vec3::X))
+        });
+        let mut deliveries = Deliveries::default();
+        deliveries.start(truck, company, stops, n + n2);
+
+        deliveries.remove_company(company, &mut m);
+        assert_eq!(m.capital(buyer, flour), 6);
+        assert!(deliveries.trip(truck).is_none());
+    }
+}
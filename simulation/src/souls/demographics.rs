@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+use prototypes::{
+    prototype, DemographicsPrototype, DemographicsPrototypeID, Education, GameDuration, GameTime,
+};
+
+use crate::economy::JobMarket;
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::happiness::{CityStats, AGE_BUCKETS};
+use crate::souls::human::{spawn_resident, Gender, PersonalInfo};
+use crate::utils::rand_provider::RandProvider;
+use crate::world::{HumanEnt, HumanID};
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+pub fn demographics() -> &'static DemographicsPrototype {
+    prototype::<DemographicsPrototypeID>(DemographicsPrototypeID::new("demographics"))
+}
+
+/// Yearly death rate at this age, doubling every `mortality_doubling_years` (Gompertz law)
+pub fn mortality(proto: &DemographicsPrototype, age: f32) -> f32 {
+    proto.mortality_at_birth * 2.0f32.powf(age / proto.mortality_doubling_years)
+}
+
+/// Chance that something happening `rate` times per year happens at least once in `years`
+fn chance(rate: f32, years: f32) -> f32 {
+    1.0 - (-rate * years).exp()
+}
+
+/// The living residents of a home
+#[derive(Default)]
+struct Household {
+    residents: Vec<HumanID>,
+    father: bool,
+    mother: bool,
+}
+
+/// Every in-game hour, ages the citizens, makes some of them die and
+/// couples living in the same home have children.
+/// Uses the time elapsed since the last update so that time skips age everyone accordingly.
+pub fn demographics_system(sim: &mut Simulation) {
+    profiling::scope!("souls::demographics_system");
+    let proto = demographics();
+
+    let years = {
+        let time = sim.resources.read::<GameTime>();
+        let mut stats = sim.resources.write::<CityStats>();
+        let now = time.instant();
+        let Some(last) = stats.last_aging else {
+            stats.last_aging = Some(now);
+            return;
+        };
+        let elapsed = last.elapsed(&time);
+        if elapsed < GameDuration::from_minutes(60) {
+            return;
+        }
+        stats.last_aging = Some(now);
+        elapsed.seconds() as f32 / (GameTime::DAY as f32 * proto.days_per_year)
+    };
+
+    let mut rng = sim.resources.write::<RandProvider>();
+    let mut dead = Vec::new();
+    let mut grown_up = Vec::new();
+    let mut households: BTreeMap<BuildingID, Household> = BTreeMap::new();
+
+    for (id, h) in sim.world.humans.iter_mut() {
+        // tourists are only passing through
+        if h.tourist.is_some() {
+            continue;
+        }
+        let info = &mut h.personal_info;
+        let was_child = info.age < proto.adult_age;
+        info.age += years;
+
+        if rng.next_f32() < chance(mortality(proto, info.age), years) {
+            dead.push((id, h.home.house));
+            continue;
+        }
+
+        let adult = info.age >= proto.adult_age;
+        if was_child && adult {
+            grown_up.push((id, h.home.house, info.education));
+        }
+
+        let household = households.entry(h.home.house).or_default();
+        household.residents.push(id);
+        match info.gender {
+            Gender::M if adult => household.father = true,
+            Gender::F if adult && info.age <= proto.max_parent_age => household.mother = true,
+            _ => {}
+        }
+    }
+
+    let births: Vec<BuildingID> = households
+        .iter()
+        .filter(|(_, household)| {
+            household.father
+                && household.mother
+                && household.residents.len() < proto.max_household as usize
+        })
+        .filter(|_| rng.next_f32() < chance(proto.birth_rate, years))
+        .map(|(&house, _)| house)
+        .collect();
+    drop(rng);
+
+    {
+        let mut binfos = sim.resources.write::<BuildingInfos>();
+        let cbuf = sim.resources.read::<ParCommandBuffer<HumanEnt>>();
+        for &(id, house) in &dead {
+            // the home stays in the family as long as someone lives there
+            if binfos.owner(house) == Some(SoulID::Human(id)) {
+                if let Some(&heir) = households
+                    .get(&house)
+                    .and_then(|household| household.residents.first())
+                {
+                    binfos.set_owner(house, SoulID::Human(heir));
+                }
+            }
+            cbuf.kill(id);
+        }
+    }
+
+    {
+        let map = sim.resources.read::<Map>();
+        let mut jobs = sim.resources.write::<JobMarket>();
+        for (id, house, education) in grown_up {
+            if let Some(b) = map.buildings().get(house) {
+                jobs.seek(id, b.door_pos.xy(), education);
+            }
+        }
+    }
+
+    let mut n_births = 0;
+    for house in births {
+        let mut info = PersonalInfo::new(&mut sim.write::<RandProvider>());
+        info.age = 0.0;
+        info.education = Education::None;
+        if spawn_resident(sim, house, info).is_some() {
+            n_births += 1;
+        }
+    }
+
+    if n_births > 0 || !dead.is_empty() {
+        log::info!("{} births and {} deaths", n_births, dead.len());
+    }
+
+    let mut ages = [0; AGE_BUCKETS];
+    for (id, h) in sim.world.humans.iter() {
+        if h.tourist.is_some() || dead.iter().any(|&(d, _)| d == id) {
+            continue;
+        }
+        ages[((h.personal_info.age / 10.0) as usize).min(AGE_BUCKETS - 1)] += 1;
+    }
+
+    let mut stats = sim.resources.write::<CityStats>();
+    stats.births += n_births;
+    stats.deaths += dead.len() as u32;
+    stats.ages = ages;
+}
@@ -2,16 +2,40 @@ use serde::{Deserialize, Serialize};
 
 use egui_inspect::Inspect;
 use geom::Transform;
-use prototypes::{GameInstant, GameTime, ItemID};
+use prototypes::{GameInstant, GameTime, ItemID, Money};
 
 use crate::economy::{find_trade_place, Bought, Market};
-use crate::map::BuildingID;
+use crate::map::{BuildingID, Map};
 use crate::map_dynamic::{BuildingInfos, Destination};
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
 use crate::world::{HumanEnt, HumanID};
 use crate::{ParCommandBuffer, SoulID};
 
+/// Share of their money households are ready to pay for imported bread,
+/// when it costs more they wait for a local bakery instead.
+const IMPORTED_FOOD_BUDGET_SHARE: f64 = 0.05;
+
+/// Highest price a household with this money pays for imported bread.
+/// Households out of money only buy locally.
+fn max_food_price(wallet: Money) -> Money {
+    if wallet <= Money::ZERO {
+        return Money::ZERO;
+    }
+    wallet * IMPORTED_FOOD_BUDGET_SHARE
+}
+
+/// Whether households can come to the building to pick up what they bought,
+/// see the opening hours of the company prototypes
+pub fn is_open(map: &Map, building: BuildingID, time: &GameTime) -> bool {
+    map.buildings()
+        .get(building)
+        .and_then(|b| b.kind.as_goods_company())
+        .map_or(true, |id| {
+            id.prototype().opening_hours.is_active(&time.daytime)
+        })
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum BuyFoodState {
     Empty,
@@ -37,7 +61,7 @@ impl BuyFood {
         }
     }
 
-    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought) -> f32 {
+    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought, map: &Map) -> f32 {
         if matches!(self.state, BuyFoodState::WaitingForTrade)
             && bought
                 .0
@@ -51,6 +75,10 @@ impl BuyFood {
             if loc == &Location::Building(id) {
                 return 1.0;
             }
+            // wait for the shop to open
+            if !is_open(map, id, time) {
+                return 0.0;
+            }
         }
         self.last_ate.elapsed(time).seconds() as f32 / GameTime::DAY as f32 - 1.0
     }
@@ -64,13 +92,16 @@ impl BuyFood {
         trans: &Transform,
         loc: &Location,
         bought: &mut Bought,
+        wallet: Money,
     ) -> HumanDecisionKind {
         use HumanDecisionKind::*;
         match self.state {
             BuyFoodState::Empty => {
                 let pos = trans.pos;
+                let budget = max_food_price(wallet);
                 cbuf.exec_on(id, move |market: &mut Market| {
-                    market.buy(SoulID::Human(id), pos.xy(), ItemID::new("bread"), 1)
+                    let bread = ItemID::new("bread");
+                    market.buy_max_price(SoulID::Human(id), pos.xy(), bread, 1, Some(budget))
                 });
                 self.state = BuyFoodState::WaitingForTrade;
                 Yield
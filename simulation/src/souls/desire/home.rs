@@ -1,5 +1,5 @@
 use crate::map::BuildingID;
-use crate::map_dynamic::Destination;
+use crate::map_dynamic::{Destination, Fires};
 use crate::souls::human::HumanDecisionKind;
 use egui_inspect::Inspect;
 use serde::{Deserialize, Serialize};
@@ -22,7 +22,11 @@ impl Home {
         HumanDecisionKind::GoTo(Destination::Building(self.house))
     }
 
-    pub fn score(&self) -> f32 {
+    /// Nobody goes back to a burning or burned down home
+    pub fn score(&self, fires: &Fires) -> f32 {
+        if fires.is_out_of_service(self.house) {
+            return f32::NEG_INFINITY;
+        }
         0.2
     }
 }
@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+
+use common::FastMap;
+use egui_inspect::Inspect;
+use geom::{Transform, Vec3};
+use prototypes::{GameDuration, GameInstant, GameTime, Money, Tick, DAYS_PER_WEEK};
+
+use crate::economy::{BudgetReason, Government};
+use crate::map::{BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{Destination, Router};
+use crate::souls::desire::Work;
+use crate::souls::happiness::happiness_weights;
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::world::{HumanEnt, HumanID};
+use crate::ParCommandBuffer;
+
+/// How long a visit to a park or a leisure venue lasts, in minutes
+const VISIT_MINUTES: u64 = 2 * 60;
+
+/// Citizens don't go out again less than this many minutes after the start of their last visit
+const REST_MINUTES: u64 = 12 * 60;
+
+/// Venues farther than this are never considered, whatever the travel-time budget
+const SEARCH_RADIUS: f32 = 3000.0;
+
+/// How long the venue found by a search from a building is kept before searching again
+const SEARCH_MINUTES: u64 = 30;
+
+/// Hours of the weekday evenings when citizens are free to go out
+const EVENING_HOURS: std::ops::Range<i32> = 18..22;
+
+/// Hours of the weekend when citizens are free to go out
+const WEEKEND_HOURS: std::ops::Range<i32> = 9..21;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+enum LeisureState {
+    Idle,
+    /// Heading to the venue, to a position inside its area if it is walkable
+    Going(BuildingID, Option<Vec3>),
+    Visiting(BuildingID, Option<Vec3>, GameInstant),
+}
+
+debug_inspect_impl!(LeisureState);
+
+/// Result of the last search for a venue, so that idle citizens don't search at every decision
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+struct VenueSearch {
+    from: BuildingID,
+    at: GameInstant,
+    venue: Option<BuildingID>,
+}
+
+debug_inspect_impl!(VenueSearch);
+
+/// Going to the nearest park or leisure venue in the evenings and on weekends
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Leisure {
+    state: LeisureState,
+    /// Start of the visits of the last week, the oldest first
+    visits: Vec<GameInstant>,
+    /// Since when the visits are counted, newcomers are not expected a whole week of visits
+    since: GameInstant,
+    last_search: Option<VenueSearch>,
+    pub last_score: f32,
+}
+
+/// Number of citizens visiting each leisure venue, counted before the decisions of each tick
+#[derive(Default)]
+pub struct LeisureVisitors(FastMap<BuildingID, u32>);
+
+impl LeisureVisitors {
+    pub fn visitors(&self, venue: BuildingID) -> u32 {
+        self.0.get(&venue).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn count(&mut self, visiting: impl Iterator<Item = BuildingID>) {
+        self.0.clear();
+        for venue in visiting {
+            *self.0.entry(venue).or_default() += 1;
+        }
+    }
+}
+
+/// Whether the citizen is free to go out: weekday evenings and weekend days, outside of work
+pub fn has_free_time(time: &GameTime, work: Option<&Work>) -> bool {
+    if work.is_some_and(|w| w.work_inter.is_active(&time.daytime)) {
+        return false;
+    }
+    let hours = if time.daytime.is_weekend() {
+        WEEKEND_HOURS
+    } else {
+        EVENING_HOURS
+    };
+    hours.contains(&time.daytime.hour)
+}
+
+fn week() -> GameDuration {
+    GameDuration::from_secs(DAYS_PER_WEEK as u64 * GameTime::DAY as u64)
+}
+
+/// Whether the venue still exists and lets visitors in at this time
+fn is_open(map: &Map, venue: BuildingID, time: &GameTime) -> bool {
+    let Some(BuildingKind::Leisure(id)) = map.buildings().get(venue).map(|b| b.kind) else {
+        return false;
+    };
+    id.prototype().opening_hours.is_active(&time.daytime)
+}
+
+/// Whether the venue is open and has room left for one more visitor
+fn has_room(map: &Map, visitors: &LeisureVisitors, venue: BuildingID, time: &GameTime) -> bool {
+    let Some(BuildingKind::Leisure(id)) = map.buildings().get(venue).map(|b| b.kind) else {
+        return false;
+    };
+    let proto = id.prototype();
+    proto.opening_hours.is_active(&time.daytime) && visitors.visitors(venue) < proto.capacity
+}
+
+/// The open venue with room left that is the quickest to reach within the travel-time budget
+pub fn find_venue(
+    map: &Map,
+    visitors: &LeisureVisitors,
+    router: &Router,
+    time: &GameTime,
+    pos: Vec3,
+) -> Option<BuildingID> {
+    let mut best = None;
+    let mut best_secs = happiness_weights().max_leisure_trip_minutes * 60.0;
+    for obj in map
+        .spatial_map()
+        .query_around(pos.xy(), SEARCH_RADIUS, ProjectFilter::BUILDING)
+    {
+        let ProjectKind::Building(id) = obj else {
+            continue;
+        };
+        let Some(b) = map.buildings().get(id) else {
+            continue;
+        };
+        let BuildingKind::Leisure(proto) = b.kind else {
+            continue;
+        };
+        let proto = proto.prototype();
+        if !proto.opening_hours.is_active(&time.daytime) || visitors.visitors(id) >= proto.capacity
+        {
+            continue;
+        }
+        let secs = router.trip_secs(pos, b.door_pos);
+        if secs <= best_secs {
+            best_secs = secs;
+            best = Some(id);
+        }
+    }
+    best
+}
+
+/// A position inside the area of walkable venues, so that visitors spread out
+fn walk_spot(map: &Map, venue: BuildingID, seed: Vec3) -> Option<Vec3> {
+    let b = map.buildings().get(venue)?;
+    let BuildingKind::Leisure(proto) = b.kind else {
+        return None;
+    };
+    if !proto.prototype().walkable {
+        return None;
+    }
+    let [u, v] = b.obb.axis();
+    let r = |offset: f32| common::rand::rand2(seed.x + offset, seed.y) - 0.5;
+    let p = b.obb.center() + u * 0.8 * r(0.0) + v * 0.8 * r(1.0);
+    Some(p.z(b.door_pos.z))
+}
+
+/// Only used when loading older saves, see [`crate::migrations::migrate_world`]
+impl Default for Leisure {
+    fn default() -> Self {
+        Self::new(GameInstant(Tick(0)))
+    }
+}
+
+impl Leisure {
+    pub fn new(start: GameInstant) -> Self {
+        Leisure {
+            state: LeisureState::Idle,
+            visits: vec![],
+            since: start,
+            last_search: None,
+            last_score: 0.0,
+        }
+    }
+
+    /// The venue the citizen is at, if any
+    pub fn visiting(&self) -> Option<BuildingID> {
+        match self.state {
+            LeisureState::Visiting(venue, _, _) => Some(venue),
+            _ => None,
+        }
+    }
+
+    /// Number of visits started during the last week
+    pub fn visits_last_week(&self, time: &GameTime) -> usize {
+        self.visits
+            .iter()
+            .filter(|v| v.elapsed(time) < week())
+            .count()
+    }
+
+    /// How happy the citizen is about going out, between 0 and 1.
+    /// Newcomers are only expected the visits of the time they spent in the city.
+    pub fn satisfaction(&self, time: &GameTime) -> f32 {
+        let weeks = (self.since.elapsed(time).seconds() / week().seconds()).min(1.0) as f32;
+        let expected = happiness_weights().weekly_leisure_visits * weeks;
+        if expected < 1.0 {
+            return 1.0;
+        }
+        (self.visits_last_week(time) as f32 / expected).min(1.0)
+    }
+
+    /// Ends the visits that lasted long enough and gives up on the venues that closed
+    pub fn update(&mut self, time: &GameTime, map: &Map, work: Option<&Work>) {
+        self.visits.retain(|v| v.elapsed(time) < week());
+        match self.state {
+            LeisureState::Idle => {}
+            LeisureState::Going(venue, _) => {
+                if !is_open(map, venue, time) || !has_free_time(time, work) {
+                    self.state = LeisureState::Idle;
+                }
+            }
+            LeisureState::Visiting(venue, _, start) => {
+                if !is_open(map, venue, time)
+                    || start.elapsed(time) >= GameDuration::from_minutes(VISIT_MINUTES)
+                {
+                    self.state = LeisureState::Idle;
+                }
+            }
+        }
+    }
+
+    /// The venue to go to from the building, the last search is reused for [`SEARCH_MINUTES`]
+    /// as long as the citizen stays in the same building
+    fn venue(
+        &mut self,
+        map: &Map,
+        visitors: &LeisureVisitors,
+        router: &Router,
+        time: &GameTime,
+        from: BuildingID,
+        pos: Vec3,
+    ) -> Option<BuildingID> {
+        if let Some(search) = self.last_search {
+            if search.from == from
+                && search.at.elapsed(time) < GameDuration::from_minutes(SEARCH_MINUTES)
+            {
+                return search
+                    .venue
+                    .filter(|&venue| has_room(map, visitors, venue, time));
+            }
+        }
+        let venue = find_venue(map, visitors, router, time, pos);
+        self.last_search = Some(VenueSearch {
+            from,
+            at: time.instant(),
+            venue,
+        });
+        venue
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn score(
+        &mut self,
+        time: &GameTime,
+        loc: &Location,
+        trans: &Transform,
+        router: &Router,
+        visitors: &LeisureVisitors,
+        map: &Map,
+        work: Option<&Work>,
+    ) -> f32 {
+        match self.state {
+            LeisureState::Going(..) | LeisureState::Visiting(..) => 0.4,
+            LeisureState::Idle => {
+                let rested = self.visits.last().map_or(true, |v| {
+                    v.elapsed(time) >= GameDuration::from_minutes(REST_MINUTES)
+                });
+                // only leave from a building, not on the way somewhere else
+                let Location::Building(from) = *loc else {
+                    return 0.0;
+                };
+                if !rested
+                    || !has_free_time(time, work)
+                    || self
+                        .venue(map, visitors, router, time, from, trans.pos)
+                        .is_none()
+                {
+                    return 0.0;
+                }
+                0.3
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+        visitors: &LeisureVisitors,
+        router: &Router,
+        map: &Map,
+        time: &GameTime,
+        me: HumanID,
+        trans: &Transform,
+        loc: &Location,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        match self.state {
+            LeisureState::Idle => {
+                let venue = match *loc {
+                    Location::Building(from) => {
+                        self.venue(map, visitors, router, time, from, trans.pos)
+                    }
+                    _ => find_venue(map, visitors, router, time, trans.pos),
+                };
+                let Some(venue) = venue else {
+                    return Yield;
+                };
+                let spot = walk_spot(map, venue, trans.pos);
+                self.state = LeisureState::Going(venue, spot);
+                Self::go_to(venue, spot)
+            }
+            LeisureState::Going(venue, spot) => {
+                let arrived = match spot {
+                    Some(_) => {
+                        *loc == Location::Outside
+                            && map
+                                .buildings()
+                                .get(venue)
+                                .is_some_and(|b| b.obb.contains(trans.pos.xy()))
+                    }
+                    None => *loc == Location::Building(venue),
+                };
+                if !arrived {
+                    return Self::go_to(venue, spot);
+                }
+
+                self.state = LeisureState::Visiting(venue, spot, time.instant());
+                self.visits.push(time.instant());
+                log::debug!("{:?} went out at {:?}", me, venue);
+
+                let Some(BuildingKind::Leisure(proto)) = map.buildings().get(venue).map(|b| b.kind)
+                else {
+                    return Yield;
+                };
+                let fee = proto.prototype().entry_fee;
+                if fee > Money::ZERO {
+                    cbuf.exec_ent(me, move |sim| {
+                        let Some(h) = sim.world.humans.get_mut(me) else {
+                            return;
+                        };
+                        h.wallet.0 -= fee;
+                        sim.write::<Government>()
+                            .transact(BudgetReason::LeisureFees, fee);
+                    });
+                }
+                Yield
+            }
+            LeisureState::Visiting(venue, spot, _) => Self::go_to(venue, spot),
+        }
+    }
+
+    fn go_to(venue: BuildingID, spot: Option<Vec3>) -> HumanDecisionKind {
+        match spot {
+            Some(spot) => HumanDecisionKind::GoTo(Destination::Outside(spot)),
+            None => HumanDecisionKind::GoTo(Destination::Building(venue)),
+        }
+    }
+}
@@ -1,7 +1,11 @@
 mod buyfood;
 mod home;
+mod leisure;
+mod tourist;
 mod work;
 
 pub use buyfood::*;
 pub use home::*;
+pub use leisure::*;
+pub use tourist::*;
 pub use work::*;
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use egui_inspect::Inspect;
+use geom::{Transform, Vec3};
+use prototypes::{CompanyKind, GameDuration, GameInstant, GameTime, Money};
+
+use crate::map::{BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::desire::is_open;
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::world::{HumanEnt, HumanID};
+use crate::{ParCommandBuffer, SoulID};
+
+/// What a tourist spends at each shop visit
+pub const SHOP_SPEND: Money = Money::new_bucks(40);
+
+/// Money a tourist brings to spend in the city
+pub const TOURIST_BUDGET: Money = Money::new_bucks(300);
+
+/// Tourists don't go shopping again less than this many minutes after their last visit
+const SHOPPING_REST_MINUTES: u64 = 4 * 60;
+
+/// Hours of the day when tourists go shopping
+const SHOPPING_HOURS: std::ops::Range<i32> = 10..20;
+
+/// Shops farther than this from the tourist are not considered
+const SHOP_RADIUS: f32 = 1000.0;
+
+/// Tourists standing this close to the exit have left the city
+pub const EXIT_RADIUS: f32 = 5.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+enum TouristState {
+    Staying,
+    Shopping(BuildingID),
+    Leaving,
+}
+
+debug_inspect_impl!(TouristState);
+
+/// A visitor from outside the city staying a few days at a hotel, see [`crate::souls::tourism`].
+/// Tourists never look for a job nor a home, they go shopping during the day
+/// and leave through where they came from when their stay is over.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Tourist {
+    pub hotel: BuildingID,
+    /// Where the tourist arrived, the train station, the airport or the edge of the map
+    pub exit: Vec3,
+    pub departure: GameInstant,
+    state: TouristState,
+    last_shopping: GameInstant,
+    /// Money spent at the shops of the city since the arrival
+    pub spent: Money,
+    pub last_score: f32,
+}
+
+/// The nearest open shop around the position
+fn find_shop(map: &Map, time: &GameTime, pos: Vec3) -> Option<BuildingID> {
+    map.spatial_map()
+        .query_around(pos.xy(), SHOP_RADIUS, ProjectFilter::BUILDING)
+        .filter_map(|obj| {
+            let ProjectKind::Building(id) = obj else {
+                return None;
+            };
+            let b = map.buildings().get(id)?;
+            let BuildingKind::GoodsCompany(proto) = b.kind else {
+                return None;
+            };
+            if proto.prototype().kind != CompanyKind::Store || !is_open(map, id, time) {
+                return None;
+            }
+            Some((id, b.door_pos.distance2(pos)))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+impl Tourist {
+    pub fn new(hotel: BuildingID, exit: Vec3, arrival: GameInstant, stay: GameDuration) -> Self {
+        Tourist {
+            hotel,
+            exit,
+            departure: arrival + stay,
+            state: TouristState::Staying,
+            last_shopping: arrival,
+            spent: Money::ZERO,
+            last_score: 0.0,
+        }
+    }
+
+    pub fn is_leaving(&self) -> bool {
+        matches!(self.state, TouristState::Leaving)
+    }
+
+    /// Whether what is left of the budget pays for another shop visit
+    fn can_afford_shopping(&self) -> bool {
+        self.spent + SHOP_SPEND <= TOURIST_BUDGET
+    }
+
+    /// The stay is over and the tourist stands at the exit
+    pub fn has_left(&self, loc: &Location, pos: Vec3) -> bool {
+        self.is_leaving()
+            && *loc == Location::Outside
+            && pos.xy().distance(self.exit.xy()) <= EXIT_RADIUS
+    }
+
+    pub fn score(&self, time: &GameTime, loc: &Location, map: &Map, pos: Vec3) -> f32 {
+        if self.is_leaving() || time.instant() >= self.departure {
+            return 1.0;
+        }
+        match self.state {
+            TouristState::Shopping(_) => 0.35,
+            _ => {
+                let rested = self.last_shopping.elapsed(time)
+                    >= GameDuration::from_minutes(SHOPPING_REST_MINUTES);
+                if !rested
+                    || !self.can_afford_shopping()
+                    || !SHOPPING_HOURS.contains(&time.daytime.hour)
+                    || !matches!(loc, Location::Building(_))
+                    || find_shop(map, time, pos).is_none()
+                {
+                    return 0.0;
+                }
+                0.35
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+        binfos: &BuildingInfos,
+        map: &Map,
+        time: &GameTime,
+        me: HumanID,
+        trans: &Transform,
+        loc: &Location,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        if self.is_leaving() || time.instant() >= self.departure {
+            self.state = TouristState::Leaving;
+            return GoTo(Destination::Outside(self.exit));
+        }
+        match self.state {
+            TouristState::Staying | TouristState::Leaving => {
+                let Some(shop) = find_shop(map, time, trans.pos) else {
+                    return Yield;
+                };
+                self.state = TouristState::Shopping(shop);
+                GoTo(Destination::Building(shop))
+            }
+            TouristState::Shopping(shop) => {
+                if *loc != Location::Building(shop) {
+                    if !is_open(map, shop, time) || map.buildings().get(shop).is_none() {
+                        self.state = TouristState::Staying;
+                        return Yield;
+                    }
+                    return GoTo(Destination::Building(shop));
+                }
+
+                self.state = TouristState::Staying;
+                self.last_shopping = time.instant();
+                if !self.can_afford_shopping() {
+                    return Yield;
+                }
+                log::debug!("{:?} went shopping at {:?}", me, shop);
+
+                // the money comes from outside the city and goes to the shop company
+                let Some(SoulID::GoodsCompany(company)) = binfos.owner(shop) else {
+                    return Yield;
+                };
+                cbuf.exec_ent(me, move |sim| {
+                    let Some(h) = sim.world.humans.get_mut(me) else {
+                        return;
+                    };
+                    // the entry fees of the venues are paid from the same wallet
+                    if h.wallet.0 < SHOP_SPEND {
+                        return;
+                    }
+                    h.wallet.0 -= SHOP_SPEND;
+                    if let Some(t) = h.tourist.as_mut() {
+                        t.spent += SHOP_SPEND;
+                    }
+                    if let Some(c) = sim.world.companies.get_mut(company) {
+                        c.finances.balance += SHOP_SPEND;
+                    }
+                });
+                Yield
+            }
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use crate::map::BuildingID;
 use crate::map_dynamic::{Destination, Router};
+use crate::souls::delivery::Deliveries;
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
 use crate::world::VehicleID;
 use egui_inspect::Inspect;
-use prototypes::{GameTime, RecTimeInterval, MINUTES_PER_HOUR};
+use prototypes::{GameTime, Money, RecTimeInterval};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -14,56 +15,120 @@ pub enum WorkKind {
         truck: VehicleID,
     },
     Worker,
+    /// Drives the truck of a landfill to the buildings picked by the garbage system
+    GarbageCollector {
+        target: Option<BuildingID>,
+        truck: VehicleID,
+    },
+    /// Drives the truck of a fire station to the fires picked by the fire system
+    Firefighter {
+        target: Option<BuildingID>,
+        truck: VehicleID,
+    },
 }
 debug_inspect_impl!(WorkKind);
 
+impl WorkKind {
+    /// The truck driven for the job, if any
+    pub fn truck(&self) -> Option<VehicleID> {
+        match *self {
+            WorkKind::Driver { truck, .. }
+            | WorkKind::GarbageCollector { truck, .. }
+            | WorkKind::Firefighter { truck, .. } => Some(truck),
+            WorkKind::Worker => None,
+        }
+    }
+}
+
 #[derive(Inspect, Debug, Clone, Serialize, Deserialize)]
 pub struct Work {
     pub workplace: BuildingID,
     pub work_inter: RecTimeInterval,
     pub kind: WorkKind,
+    /// Wage paid per in-game day, as negotiated on the job market
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub wage: Money,
     pub last_score: f32,
 }
 
 impl Work {
-    pub fn new(workplace: BuildingID, kind: WorkKind, offset: f32) -> Self {
+    pub fn new(
+        workplace: BuildingID,
+        kind: WorkKind,
+        work_inter: RecTimeInterval,
+        wage: Money,
+    ) -> Self {
         Work {
             workplace,
-            work_inter: RecTimeInterval::new(
-                (8, (offset * MINUTES_PER_HOUR as f32) as i32),
-                (18, (offset * MINUTES_PER_HOUR as f32) as i32),
-            ),
+            work_inter,
             kind,
+            wage,
             last_score: 0.0,
         }
     }
 
-    pub fn apply(&mut self, loc: &Location, router: &Router) -> HumanDecisionKind {
+    pub fn apply(
+        &mut self,
+        loc: &Location,
+        router: &Router,
+        deliveries: &Deliveries,
+    ) -> HumanDecisionKind {
         use HumanDecisionKind::*;
         match self.kind {
             WorkKind::Worker => GoTo(Destination::Building(self.workplace)),
-            WorkKind::Driver {
-                deliver_order,
-                truck,
-            } => {
-                if &Location::Building(self.workplace) != loc {
-                    MultiStack(vec![
-                        GoTo(Destination::Building(self.workplace)),
-                        SetVehicle(router.personal_car),
-                    ])
-                } else if let Some(b) = deliver_order {
-                    MultiStack(vec![
-                        SetVehicle(router.personal_car),
-                        GoTo(Destination::Building(self.workplace)),
-                        DeliverAtBuilding(b),
-                        GoTo(Destination::Building(b)),
-                        SetVehicle(Some(truck)),
-                    ])
-                } else {
-                    Yield
-                }
+            WorkKind::Driver { truck, .. } => {
+                let stops = deliveries
+                    .stops(truck)
+                    .map(|b| (b, DeliverAtBuilding(b)))
+                    .collect();
+                self.truck_trip(loc, router, truck, stops)
             }
+            WorkKind::GarbageCollector { target, truck } => self.truck_trip(
+                loc,
+                router,
+                truck,
+                target.map(|b| (b, CollectGarbage(b))).into_iter().collect(),
+            ),
+            WorkKind::Firefighter { target, truck } => self.truck_trip(
+                loc,
+                router,
+                truck,
+                target.map(|b| (b, ExtinguishFire(b))).into_iter().collect(),
+            ),
+        }
+    }
+
+    /// Drives the truck to the targets in order, does the job at each of them,
+    /// then comes back to the workplace
+    fn truck_trip(
+        &self,
+        loc: &Location,
+        router: &Router,
+        truck: VehicleID,
+        targets: Vec<(BuildingID, HumanDecisionKind)>,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        if &Location::Building(self.workplace) != loc {
+            return MultiStack(vec![
+                GoTo(Destination::Building(self.workplace)),
+                SetVehicle(router.personal_car),
+            ]);
+        }
+        if targets.is_empty() {
+            return Yield;
+        }
+
+        // the stack is executed from the end
+        let mut stack = vec![
+            SetVehicle(router.personal_car),
+            GoTo(Destination::Building(self.workplace)),
+        ];
+        for (b, job) in targets.into_iter().rev() {
+            stack.push(job);
+            stack.push(GoTo(Destination::Building(b)));
         }
+        stack.push(SetVehicle(Some(truck)));
+        MultiStack(stack)
     }
 
     pub fn score(&self, time: &GameTime) -> f32 {
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{Education, GameDuration, GameInstant, GameTime, SchoolPrototype};
+
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::map_dynamic::{ElectricityFlow, Fires};
+use crate::souls::demographics::demographics;
+use crate::souls::happiness::CityStats;
+use crate::utils::resources::Resources;
+use crate::world::HumanID;
+use crate::World;
+
+/// Children enrolled in each school, updated every in-game hour
+#[derive(Default, Serialize, Deserialize)]
+pub struct Schools {
+    enrolled: BTreeMap<BuildingID, Vec<HumanID>>,
+    last_update: Option<GameInstant>,
+}
+
+impl Schools {
+    pub fn enrolled(&self, school: BuildingID) -> &[HumanID] {
+        self.enrolled.get(&school).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+struct Seats {
+    school: BuildingID,
+    pos: Vec2,
+    proto: &'static SchoolPrototype,
+    /// Schools without power or on fire keep their children but do not teach them anything
+    teaching: bool,
+    free: u32,
+}
+
+/// Enrolls the children in the nearest school in range with free seats,
+/// and raises their education with the time they spend there.
+/// Also counts the citizens at each level of education for [`CityStats`].
+pub fn education_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::education_system");
+    let time = resources.read::<GameTime>();
+    let mut schools = resources.write::<Schools>();
+
+    let proto = demographics();
+    let elapsed = schools
+        .last_update
+        .map_or(GameDuration::from_minutes(60), |last| last.elapsed(&time));
+    if elapsed < GameDuration::from_minutes(60) {
+        return;
+    }
+    schools.last_update = Some(time.instant());
+    let years = elapsed.seconds() as f32 / (GameTime::DAY as f32 * proto.days_per_year);
+
+    let map = resources.read::<Map>();
+    let elec_flow = resources.read::<ElectricityFlow>();
+    let fires = resources.read::<Fires>();
+
+    let mut seats: Vec<Seats> = map
+        .buildings
+        .iter()
+        .filter_map(|(id, b)| {
+            let BuildingKind::School(school) = b.kind else {
+                return None;
+            };
+            let proto = school.prototype();
+            Some(Seats {
+                school: id,
+                pos: b.door_pos.xy(),
+                proto,
+                teaching: !elec_flow.is_shed(id) && !fires.is_out_of_service(id),
+                free: proto.capacity,
+            })
+        })
+        .collect();
+
+    let mut enrolled: BTreeMap<BuildingID, Vec<HumanID>> = BTreeMap::new();
+
+    for (id, h) in world.humans.iter_mut() {
+        let info = &mut h.personal_info;
+        if info.age >= proto.adult_age {
+            continue;
+        }
+        let Some(home) = map.buildings.get(h.home.house) else {
+            continue;
+        };
+        let home = home.door_pos.xy();
+
+        let Some(seat) = seats
+            .iter_mut()
+            .filter(|s| {
+                s.free > 0
+                    && s.proto.max_education > info.education
+                    && s.pos.distance(home) <= s.proto.service_radius
+            })
+            .min_by_key(|s| OrderedFloat(s.pos.distance2(home)))
+        else {
+            continue;
+        };
+
+        seat.free -= 1;
+        enrolled.entry(seat.school).or_default().push(id);
+
+        if !seat.teaching {
+            continue;
+        }
+        info.schooling += years;
+        while info.schooling >= seat.proto.years_per_level
+            && info.education < seat.proto.max_education
+        {
+            let Some(next) = info.education.next() else {
+                break;
+            };
+            info.schooling -= seat.proto.years_per_level;
+            info.education = next;
+        }
+    }
+
+    schools.enrolled = enrolled;
+
+    let mut education = [0; Education::ALL.len()];
+    for h in world.humans.values().filter(|h| h.tourist.is_none()) {
+        education[h.personal_info.education as usize] += 1;
+    }
+    resources.write::<CityStats>().education = education;
+}
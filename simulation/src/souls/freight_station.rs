@@ -106,7 +106,10 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
                 }
                 FreightTrainState::Loading => {
                     if itin.has_ended(time.timestamp) {
-                        let ext = *map.external_train_stations.first().unwrap();
+                        let Some(&ext) = map.external_train_stations.first() else {
+                            *itin = Itinerary::wait_until(time.timestamp + 10.0);
+                            continue;
+                        };
                         let bpos = map.buildings[ext].obb.center().z(0.0);
 
                         *itin = if let Some(r) =
@@ -3,15 +3,19 @@ use serde::{Deserialize, Serialize};
 use egui_inspect::Inspect;
 use geom::{Transform, Vec2};
 use prototypes::{
-    CompanyKind, GoodsCompanyID, GoodsCompanyPrototype, ItemID, Power, Recipe, DELTA,
+    CompanyKind, GameTime, GoodsCompanyID, GoodsCompanyPrototype, Money, Power, Recipe, DELTA,
+    SECONDS_PER_HOUR,
 };
 
-use crate::economy::{find_trade_place, Market};
+use crate::chronicle::{self, ChronicleKind};
+use crate::economy::{find_trade_place, negotiate_wage, Cargo, CompanyFinances, JobMarket, Market};
 use crate::map::{Building, BuildingID, Map, Zone, MAX_ZONE_AREA};
-use crate::map_dynamic::{BuildingInfos, ElectricityFlow};
+use crate::map_dynamic::{BuildingInfos, ElectricityFlow, Fires, Garbage, WaterFlow};
+use crate::souls::delivery::{hand_over, plan_delivery, Deliveries};
 use crate::souls::desire::WorkKind;
-use crate::transportation::{spawn_parked_vehicle, VehicleKind};
+use crate::transportation::{spawn_parked_vehicle_of, VehicleKind};
 use crate::utils::resources::Resources;
+use crate::weather::Weather;
 use crate::world::{CompanyEnt, HumanEnt, HumanID, VehicleID};
 use crate::{ParCommandBuffer, SoulID, VehicleEnt};
 use crate::{Simulation, World};
@@ -57,6 +61,9 @@ pub fn recipe_act(recipe: &Recipe, soul: SoulID, near: Vec2, market: &mut Market
     }
 }
 
+/// Productivity multiplier of a company whose garbage is not collected
+pub const GARBAGE_PRODUCTIVITY: f32 = 0.75;
+
 #[derive(Clone, Serialize, Deserialize, Inspect)]
 pub struct GoodsCompanyState {
     pub proto: GoodsCompanyID,
@@ -68,6 +75,18 @@ pub struct GoodsCompanyState {
     pub trucks: Vec<VehicleID>,
 }
 
+impl GoodsCompanyState {
+    /// Whether the goods sold are carried to the buyers by the trucks of the company,
+    /// the trucks of the services are used by firefighters and garbage collectors instead
+    pub fn delivers_goods(&self) -> bool {
+        let proto = self.proto.prototype();
+        !self.trucks.is_empty()
+            && proto.kind == CompanyKind::Factory
+            && proto.service_radius.is_none()
+            && proto.garbage_truck_capacity <= 0.0
+    }
+}
+
 impl CompanyEnt {
     /// Returns the productivity of the company, in [0; 1] range _before_ taking electricity into account
     pub fn raw_productivity(&self, proto: &GoodsCompanyPrototype, zone: Option<&Zone>) -> f32 {
@@ -87,17 +106,28 @@ impl CompanyEnt {
         &self,
         proto: &GoodsCompanyPrototype,
         zone: Option<&Zone>,
-        map: &Map,
         elec_flow: &ElectricityFlow,
+        water_flow: &WaterFlow,
+        garbage: &Garbage,
+        fires: &Fires,
+        weather: &Weather,
     ) -> f32 {
-        let p = self.raw_productivity(proto, zone);
+        let mut p = self.raw_productivity(proto, zone) * weather.productivity_factor(proto);
 
-        if proto.power_consumption > Some(Power::ZERO) {
-            if let Some(net_id) = map.electricity.net_id(self.comp.building) {
-                if elec_flow.blackout(net_id) {
-                    return 0.0;
-                }
-            }
+        if fires.is_out_of_service(self.comp.building) {
+            return 0.0;
+        }
+
+        if proto.power_consumption > Some(Power::ZERO) && elec_flow.is_shed(self.comp.building) {
+            return 0.0;
+        }
+
+        if water_flow.is_dry(self.comp.building) {
+            p *= 0.5;
+        }
+
+        if garbage.is_dirty(self.comp.building) {
+            p *= GARBAGE_PRODUCTIVITY;
         }
 
         p
@@ -118,11 +148,21 @@ pub fn company_soul(
     let height = b.height;
     drop(map);
 
+    let day = sim.read::<GameTime>().daytime.day;
+
     let ckind = proto.kind;
     let mut trucks = vec![];
     if ckind == CompanyKind::Factory {
+        let truck = proto
+            .truck
+            .unwrap_or_else(|| VehicleKind::Truck.default_prototype().id);
         for _ in 0..proto.n_trucks {
-            trucks.extend(spawn_parked_vehicle(sim, VehicleKind::Truck, door_pos))
+            trucks.extend(spawn_parked_vehicle_of(
+                sim,
+                VehicleKind::Truck,
+                truck,
+                door_pos,
+            ))
         }
         if trucks.len() as u32 != proto.n_trucks {
             for truck in trucks {
@@ -147,18 +187,24 @@ pub fn company_soul(
         workers: Default::default(),
         sold: Default::default(),
         bought: Default::default(),
+        finances: CompanyFinances::new(proto.starting_capital, day),
     });
 
     let company = &sim.world.get(id).unwrap().comp;
 
     let soul = SoulID::GoodsCompany(id);
 
-    let job_opening = ItemID::new("job-opening");
-
     {
         let m = &mut *sim.write::<Market>();
-        m.produce(soul, job_opening, company.max_workers as i32);
-        m.sell_all(soul, door_pos.xy(), job_opening, 0);
+
+        let wage = negotiate_wage(proto, m);
+        sim.write::<JobMarket>().offer(
+            id,
+            door_pos.xy(),
+            company.max_workers,
+            wage,
+            proto.min_education,
+        );
 
         if let Some(ref r) = proto.recipe {
             recipe_init(r, soul, door_pos.xy(), m);
@@ -177,8 +223,15 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
     let cbuf_human: &ParCommandBuffer<HumanEnt> = &res.read();
     let binfos: &BuildingInfos = &res.read();
     let market: &Market = &res.read();
+    let jobs: &JobMarket = &res.read();
     let map: &Map = &res.read();
     let elec_flow: &ElectricityFlow = &res.read();
+    let water_flow: &WaterFlow = &res.read();
+    let garbage: &Garbage = &res.read();
+    let fires: &Fires = &res.read();
+    let weather: &Weather = &res.read();
+    let deliveries: &Deliveries = &res.read();
+    let day = res.read::<GameTime>().daytime.day;
 
     world.companies.iter_mut().for_each(|(me, c)| {
         let soul = SoulID::GoodsCompany(me);
@@ -189,9 +242,31 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
 
         let proto = c.comp.proto.prototype();
 
+        c.finances.update_day(day);
+        if c.finances.is_bankrupt(proto.bankruptcy_days) {
+            // workers are released and the building is freed for a new company when dropped
+            log::info!("{:?} went bankrupt", me);
+            chronicle::record(
+                res,
+                ChronicleKind::Bankruptcy,
+                format!("{} went bankrupt", proto.label),
+                Some(b.door_pos),
+            );
+            cbuf.kill(me);
+            return;
+        }
+
         if let Some(recipe) = &proto.recipe {
             if recipe_should_produce(recipe, soul, market) {
-                let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
+                let productivity = c.productivity(
+                    proto,
+                    b.zone.as_ref(),
+                    elec_flow,
+                    water_flow,
+                    garbage,
+                    fires,
+                    weather,
+                );
 
                 c.comp.progress += productivity * DELTA / recipe.duration.seconds() as f32;
             }
@@ -228,28 +303,64 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
 
         (|| {
             let Some(driver) = c.comp.driver else {
+                // the driver left, the buyers get the goods that were waiting for a truck
+                let sold = std::mem::take(&mut c.sold.0);
+                let trucks: Vec<VehicleID> = c
+                    .comp
+                    .trucks
+                    .iter()
+                    .copied()
+                    .filter(|&t| deliveries.trip(t).is_some())
+                    .collect();
+                if sold.is_empty() && trucks.is_empty() {
+                    return;
+                }
+                cbuf.exec_ent(me, move |sim| {
+                    let mut market = sim.write::<Market>();
+                    let mut deliveries = sim.write::<Deliveries>();
+                    for truck in trucks {
+                        deliveries.hand_over(truck, &mut market);
+                    }
+                    hand_over(
+                        &mut market,
+                        soul,
+                        sold.into_iter().map(|t| Cargo {
+                            soul: t.buyer.0,
+                            kind: t.kind,
+                            qty: t.qty,
+                        }),
+                    );
+                });
                 return;
             };
             let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
                 return;
             };
-            if !matches!(
-                w.kind,
-                WorkKind::Driver {
-                    deliver_order: None,
-                    ..
-                }
-            ) {
-                return;
-            }
-            let Some(trade) = c.sold.0.pop() else {
+            let WorkKind::Driver { truck, .. } = w.kind else {
                 return;
             };
-            let Some(owner_build) = find_trade_place(trade.buyer, binfos) else {
-                log::warn!("driver can't find the place to deliver for {:?}", &trade);
+            if c.sold.0.is_empty() || deliveries.trip(truck).is_some() {
+                return;
+            }
+            let capacity = world
+                .vehicles
+                .get(truck)
+                .map_or(0, |v| v.vehicle.proto().capacity);
+            let (stops, n_trades, undeliverable) =
+                plan_delivery(&mut c.sold.0, b.door_pos, capacity, |buyer| {
+                    let building = find_trade_place(buyer, binfos)?;
+                    Some((building, map.buildings.get(building)?.door_pos))
+                });
+            if !undeliverable.is_empty() {
+                cbuf.exec_on(me, move |market: &mut Market| {
+                    hand_over(market, soul, undeliverable)
+                });
+            }
+            let Some(first) = stops.first().map(|s| s.building) else {
                 return;
             };
             cbuf.exec_ent(me, move |sim| {
+                sim.write::<Deliveries>().start(truck, me, stops, n_trades);
                 let Some(h) = sim.world.humans.get_mut(driver) else {
                     return;
                 };
@@ -259,7 +370,7 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 let WorkKind::Driver { deliver_order, .. } = &mut w.kind else {
                     return;
                 };
-                *deliver_order = Some(owner_build)
+                *deliver_order = Some(first)
             });
         })();
 
@@ -272,7 +383,21 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 let mut kind = WorkKind::Worker;
 
                 if let Some(truck) = c.comp.trucks.first() {
-                    if proto.kind == CompanyKind::Factory && c.comp.driver.is_none() {
+                    if proto.service_radius.is_some() && c.comp.driver.is_none() {
+                        kind = WorkKind::Firefighter {
+                            target: None,
+                            truck: *truck,
+                        };
+
+                        c.comp.driver = Some(worker);
+                    } else if proto.garbage_truck_capacity > 0.0 && c.comp.driver.is_none() {
+                        kind = WorkKind::GarbageCollector {
+                            target: None,
+                            truck: *truck,
+                        };
+
+                        c.comp.driver = Some(worker);
+                    } else if proto.kind == CompanyKind::Factory && c.comp.driver.is_none() {
                         kind = WorkKind::Driver {
                             deliver_order: None,
                             truck: *truck,
@@ -282,16 +407,155 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                     }
                 }
 
+                // workers don't all arrive at the same minute
                 let offset = common::rand::randu(common::hash_u64(worker) as u32);
+                let work_inter = proto
+                    .work_hours
+                    .shifted((offset * SECONDS_PER_HOUR as f32) as i32);
+                let wage = jobs.contract(worker).map_or(Money::ZERO, |c| c.wage);
 
                 let b = c.comp.building;
                 cbuf_human.exec_ent(worker, move |sim| {
                     let Some(w) = sim.world.humans.get_mut(worker) else {
                         return;
                     };
-                    w.work = Some(Work::new(b, kind, offset));
+                    w.work = Some(Work::new(b, kind, work_inter, wage));
                 });
             }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3, OBB};
+    use prototypes::{BuildingGen, GameTime, GoodsCompanyID, Money};
+
+    use crate::economy::{CompanyFinances, JobMarket, Market};
+    use crate::map_dynamic::BuildingInfos;
+    use crate::tests::TestCtx;
+    use crate::{BuildingKind, SoulID, WorldCommand};
+
+    #[test]
+    fn finances_count_negative_days() {
+        let mut f = CompanyFinances::new(Money::new_bucks(-1), 0);
+
+        f.update_day(0);
+        assert_eq!(f.negative_days, 0);
+
+        f.update_day(1);
+        f.update_day(2);
+        assert_eq!(f.negative_days, 2);
+        assert!(f.is_bankrupt(2));
+        assert!(!f.is_bankrupt(0));
+        assert_eq!(f.days_until_bankruptcy(3), Some(1));
+
+        f.balance = Money::new_bucks(1);
+        f.update_day(3);
+        assert_eq!(f.negative_days, 0);
+        assert_eq!(f.days_until_bankruptcy(3), None);
+    }
+
+    #[test]
+    fn company_without_buyers_goes_bankrupt() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+            pos: OBB::new(vec2(50.0, 50.0), vec2(1.0, 0.0), 5.0, 5.0),
+            kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("bakery")),
+            gen: BuildingGen::NoWalkway {
+                door_pos: vec2(50.0, 50.0),
+            },
+            zone: None,
+            connected_road: None,
+        }]);
+        test.tick();
+
+        let building = test
+            .g
+            .map()
+            .buildings()
+            .iter()
+            .find(|(_, b)| matches!(b.kind, BuildingKind::GoodsCompany(_)))
+            .unwrap()
+            .0;
+
+        let Some(SoulID::GoodsCompany(company)) = test.g.read::<BuildingInfos>().owner(building)
+        else {
+            panic!("company should have been spawned")
+        };
+
+        // nobody buys bread: the company is in debt and this is its last day
+        let day = test.g.read::<GameTime>().daytime.day;
+        let c = test
+            .g
+            .world_mut_unchecked()
+            .companies
+            .get_mut(company)
+            .unwrap();
+        let bankruptcy_days = c.comp.proto.prototype().bankruptcy_days;
+        c.finances = CompanyFinances::new(Money::new_bucks(-1), day - 1);
+        c.finances.negative_days = bankruptcy_days - 1;
+
+        test.tick();
+
+        assert!(test.g.world().companies.get(company).is_none());
+
+        let soul = SoulID::GoodsCompany(company);
+        assert_ne!(test.g.read::<BuildingInfos>().owner(building), Some(soul));
+        assert!(test
+            .g
+            .read::<Market>()
+            .iter()
+            .all(|(_, m)| m.capital(soul).is_none()));
+        assert!(test.g.read::<JobMarket>().offer_of(company).is_none());
+    }
+
+    #[test]
+    fn company_is_prioritized_then_closed() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+            pos: OBB::new(vec2(50.0, 50.0), vec2(1.0, 0.0), 5.0, 5.0),
+            kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("bakery")),
+            gen: BuildingGen::NoWalkway {
+                door_pos: vec2(50.0, 50.0),
+            },
+            zone: None,
+            connected_road: None,
+        }]);
+        test.tick();
+
+        let building = test
+            .g
+            .map()
+            .buildings()
+            .iter()
+            .find(|(_, b)| matches!(b.kind, BuildingKind::GoodsCompany(_)))
+            .unwrap()
+            .0;
+        let overview = test.g.company_overview(building).unwrap();
+        let soul = SoulID::GoodsCompany(overview.company);
+        assert!(!overview.prioritized);
+
+        test.apply(&[WorldCommand::SetCompanyPriority {
+            building,
+            prioritized: true,
+        }]);
+        assert!(test.g.read::<Market>().is_prioritized(soul));
+        assert!(test.g.company_overview(building).unwrap().prioritized);
+
+        test.apply(&[WorldCommand::CloseCompany(building)]);
+        test.tick();
+
+        assert!(test.g.world().companies.get(overview.company).is_none());
+        // the building might already host a new company
+        assert!(test
+            .g
+            .company_overview(building)
+            .map_or(true, |o| o.company != overview.company));
+        assert!(!test.g.read::<Market>().is_prioritized(soul));
+    }
+}
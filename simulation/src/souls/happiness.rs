@@ -0,0 +1,266 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use prototypes::{
+    prototype, GameDuration, GameInstant, GameTime, HappinessPrototype, HappinessPrototypeID,
+};
+
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::{ElectricityFlow, Garbage, WaterFlow};
+use crate::migrations::decoding_version;
+use crate::transportation::Location;
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// Number of buckets of the happiness histogram, each covering the same range of 0..100
+pub const HAPPINESS_BUCKETS: usize = 10;
+
+/// Number of buckets of the age histogram, one per decade
+pub const AGE_BUCKETS: usize = 10;
+
+pub fn happiness_weights() -> &'static HappinessPrototype {
+    prototype::<HappinessPrototypeID>(HappinessPrototypeID::new("happiness"))
+}
+
+/// What makes citizens happy or not
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HappinessFactor {
+    Commute,
+    Food,
+    Utilities,
+    Noise,
+    Leisure,
+}
+
+/// Number of [`HappinessFactor`]s
+pub const N_HAPPINESS_FACTORS: usize = 5;
+
+impl HappinessFactor {
+    pub const ALL: [HappinessFactor; N_HAPPINESS_FACTORS] = [
+        HappinessFactor::Commute,
+        HappinessFactor::Food,
+        HappinessFactor::Utilities,
+        HappinessFactor::Noise,
+        HappinessFactor::Leisure,
+    ];
+
+    pub fn weight(self, proto: &HappinessPrototype) -> f32 {
+        match self {
+            HappinessFactor::Commute => proto.commute_weight,
+            HappinessFactor::Food => proto.food_weight,
+            HappinessFactor::Utilities => proto.utilities_weight,
+            HappinessFactor::Noise => proto.noise_weight,
+            HappinessFactor::Leisure => proto.leisure_weight,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HappinessFactor::Commute => "Long commutes",
+            HappinessFactor::Food => "Hunger",
+            HappinessFactor::Utilities => "Outages and uncollected garbage",
+            HappinessFactor::Noise => "Road noise",
+            HappinessFactor::Leisure => "Nowhere to go out",
+        }
+    }
+}
+
+/// How happy a citizen is about each [`HappinessFactor`], between 0 and 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Happiness {
+    #[serde(deserialize_with = "deserialize_scores")]
+    scores: [f32; N_HAPPINESS_FACTORS],
+    /// When the citizen left the last building, to measure the commutes
+    trip_start: Option<GameInstant>,
+    /// Duration of the last trip in minutes
+    pub last_trip_minutes: f32,
+}
+
+debug_inspect_impl!(Happiness);
+
+/// Saves from before version 11 have no leisure score, the citizens start satisfied
+fn deserialize_scores<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[f32; N_HAPPINESS_FACTORS], D::Error> {
+    if decoding_version() >= 11 {
+        return <[f32; N_HAPPINESS_FACTORS]>::deserialize(deserializer);
+    }
+    let [commute, food, utilities, noise] = <[f32; 4]>::deserialize(deserializer)?;
+    Ok([commute, food, utilities, noise, 1.0])
+}
+
+impl Default for Happiness {
+    fn default() -> Self {
+        Self {
+            scores: [1.0; N_HAPPINESS_FACTORS],
+            trip_start: None,
+            last_trip_minutes: 0.0,
+        }
+    }
+}
+
+impl Happiness {
+    pub fn score(&self, factor: HappinessFactor) -> f32 {
+        self.scores[factor as usize]
+    }
+
+    /// Weighted happiness between 0 and 100
+    pub fn value(&self) -> f32 {
+        let lost: f32 = HappinessFactor::ALL.iter().map(|&f| self.loss(f)).sum();
+        (100.0 - lost).clamp(0.0, 100.0)
+    }
+
+    /// Happiness points out of 100 lost because of the factor
+    pub fn loss(&self, factor: HappinessFactor) -> f32 {
+        let proto = happiness_weights();
+        let total: f32 = HappinessFactor::ALL.iter().map(|f| f.weight(proto)).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        100.0 * factor.weight(proto) * (1.0 - self.score(factor)) / total
+    }
+
+    /// Starts timing a trip when leaving a building and stops it when entering the next one
+    fn track_trip(&mut self, loc: &Location, time: &GameTime) {
+        match (loc, self.trip_start) {
+            (Location::Building(_), Some(start)) => {
+                self.last_trip_minutes = start.elapsed(time).minutes() as f32;
+                self.trip_start = None;
+            }
+            (Location::Building(_), None) => {}
+            (_, Some(_)) => {}
+            (_, None) => self.trip_start = Some(time.instant()),
+        }
+    }
+}
+
+/// City-wide happiness, aggregated every in-game hour
+#[derive(Default, Serialize, Deserialize)]
+pub struct CityStats {
+    /// Mean happiness of the citizens between 0 and 100
+    pub mean: f32,
+    /// Number of citizens in each range of happiness, from the unhappiest to the happiest
+    pub histogram: [u32; HAPPINESS_BUCKETS],
+    /// Mean happiness points lost to each factor, in the order of [`HappinessFactor::ALL`]
+    pub losses: [f32; N_HAPPINESS_FACTORS],
+    pub population: u32,
+    pub(crate) last_update: Option<GameInstant>,
+
+    /// Citizens born since the start of the game
+    pub births: u32,
+    /// Citizens who died since the start of the game
+    pub deaths: u32,
+    /// Number of citizens in each decade of age, the last one counts everyone older
+    pub(crate) ages: [u32; AGE_BUCKETS],
+    /// Number of citizens at each level of education, in the order of [`prototypes::Education::ALL`]
+    pub education: [u32; 3],
+    pub(crate) last_aging: Option<GameInstant>,
+}
+
+impl CityStats {
+    /// Factors costing the most happiness first, ignoring those that cost nothing
+    pub fn top_losses(&self) -> Vec<(HappinessFactor, f32)> {
+        let mut v: Vec<_> = HappinessFactor::ALL
+            .iter()
+            .map(|&f| (f, self.losses[f as usize]))
+            .filter(|(_, loss)| *loss > 0.0)
+            .collect();
+        v.sort_by(|a, b| b.1.total_cmp(&a.1));
+        v
+    }
+
+    /// Number of citizens aged 0-9, 10-19, ... and 90 or more
+    pub fn age_histogram(&self) -> &[u32; AGE_BUCKETS] {
+        &self.ages
+    }
+}
+
+/// Part of the utilities score lost when the garbage of the home is above the threshold
+const DIRTY_HOME_LOSS: f32 = 0.25;
+
+/// How happy the citizen is about the road noise at home
+fn noise_score(map: &Map, home: BuildingID, proto: &HappinessPrototype) -> f32 {
+    let Some(b) = map.buildings().get(home) else {
+        return 1.0;
+    };
+    let noise = map.noise_at(b.obb.center());
+    let range = (proto.max_noise - proto.quiet_noise).max(f32::EPSILON);
+    1.0 - ((noise - proto.quiet_noise) / range).clamp(0.0, 1.0)
+}
+
+/// Times the trips of the citizens, and every hour scores their happiness
+/// and aggregates it into [`CityStats`]
+pub fn happiness_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::happiness_system");
+    let time = resources.read::<GameTime>();
+
+    for h in world.humans.values_mut() {
+        h.happiness.track_trip(&h.location, &time);
+    }
+
+    let mut stats = resources.write::<CityStats>();
+    if stats.last_update.map_or(false, |last| {
+        last.elapsed(&time) < GameDuration::from_minutes(60)
+    }) {
+        return;
+    }
+    stats.last_update = Some(time.instant());
+
+    let map = resources.read::<Map>();
+    let elec_flow = resources.read::<ElectricityFlow>();
+    let water_flow = resources.read::<WaterFlow>();
+    let garbage = resources.read::<Garbage>();
+    let proto = happiness_weights();
+
+    let mut sum = 0.0;
+    let mut histogram = [0; HAPPINESS_BUCKETS];
+    let mut losses = [0.0; N_HAPPINESS_FACTORS];
+
+    let mut population = 0;
+    // tourists are not citizens, they don't count in the happiness of the city
+    for h in world.humans.values_mut().filter(|h| h.tourist.is_none()) {
+        population += 1;
+        let home = h.home.house;
+        let happiness = &mut h.happiness;
+
+        happiness.scores[HappinessFactor::Commute as usize] =
+            1.0 - (happiness.last_trip_minutes / proto.max_commute_minutes).clamp(0.0, 1.0);
+
+        let days_since_ate = h.food.last_ate.elapsed(&time).seconds() as f32 / GameTime::DAY as f32;
+        happiness.scores[HappinessFactor::Food as usize] =
+            1.0 - ((days_since_ate - 1.0) / proto.starving_days).clamp(0.0, 1.0);
+
+        let mut utilities = 1.0;
+        if elec_flow.is_shed(home) {
+            utilities -= 0.5;
+        }
+        if water_flow.is_dry(home) {
+            utilities -= 0.5;
+        }
+        if garbage.is_dirty(home) {
+            utilities -= DIRTY_HOME_LOSS;
+        }
+        happiness.scores[HappinessFactor::Utilities as usize] = utilities.max(0.0);
+
+        happiness.scores[HappinessFactor::Noise as usize] = noise_score(&map, home, proto);
+
+        happiness.scores[HappinessFactor::Leisure as usize] = h.leisure.satisfaction(&time);
+
+        let value = happiness.value();
+        sum += value;
+        histogram
+            [((value / 100.0 * HAPPINESS_BUCKETS as f32) as usize).min(HAPPINESS_BUCKETS - 1)] += 1;
+        for f in HappinessFactor::ALL {
+            losses[f as usize] += happiness.loss(f);
+        }
+    }
+
+    stats.population = population;
+    stats.histogram = histogram;
+    if population == 0 {
+        stats.mean = 100.0;
+        stats.losses = [0.0; N_HAPPINESS_FACTORS];
+        return;
+    }
+    stats.mean = sum / population as f32;
+    stats.losses = losses.map(|l| l / population as f32);
+}
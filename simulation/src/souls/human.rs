@@ -1,7 +1,11 @@
-use crate::economy::{Bought, Market};
+use crate::economy::{Bought, JobMarket, Market, Wallet};
 use crate::map::BuildingID;
-use crate::map_dynamic::{BuildingInfos, Destination, Itinerary, Router};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::map_dynamic::{BuildingInfos, Destination, Fires, Garbage, Itinerary, Router};
+use crate::migrations::decoding_version;
+use crate::souls::activity::{Activity, ActivityLog};
+use crate::souls::delivery::Deliveries;
+use crate::souls::demographics::demographics;
+use crate::souls::desire::{BuyFood, Home, Leisure, LeisureVisitors, Tourist, Work, WorkKind};
 use crate::transportation::Speed;
 use crate::transportation::{
     random_pedestrian_shirt_color, spawn_parked_vehicle, Location, Pedestrian, VehicleKind,
@@ -14,8 +18,8 @@ use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SoulID};
 use egui_inspect::Inspect;
 use geom::Transform;
 use lazy_static::lazy_static;
-use prototypes::{GameTime, ItemID};
-use serde::{Deserialize, Serialize};
+use prototypes::{Education, GameTime, TICKS_PER_HOUR};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Inspect, Serialize, Deserialize, Default)]
 pub struct HumanDecision {
@@ -30,23 +34,52 @@ pub enum HumanDecisionKind {
     GoTo(Destination),
     DeliverAtBuilding(BuildingID),
     MultiStack(Vec<HumanDecisionKind>),
+    /// Empties the garbage of the building into the truck
+    CollectGarbage(BuildingID),
+    /// Stays at the building until the fire is put out
+    ExtinguishFire(BuildingID),
 }
 
-#[derive(Debug, Serialize, Deserialize, Inspect)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Inspect)]
 pub enum Gender {
     M,
     F,
 }
 
+impl Gender {
+    pub fn other(self) -> Gender {
+        match self {
+            Gender::M => Gender::F,
+            Gender::F => Gender::M,
+        }
+    }
+}
+
 #[derive(Inspect, Serialize, Deserialize)]
 pub struct PersonalInfo {
     pub name: String,
-    pub age: u8,
+    /// Age in years, see [`crate::souls::demographics`] for how it grows
+    #[serde(deserialize_with = "deserialize_age")]
+    pub age: f32,
     pub gender: Gender,
+    /// Kept when moving out, it belongs to the person and not to the home
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub education: Education,
+    /// Years spent at school since the last level of education was reached
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub schooling: f32,
 }
 
 debug_inspect_impl!(HumanDecisionKind);
 
+/// Saves from before version 1 count the age in whole years
+fn deserialize_age<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    if decoding_version() >= 1 {
+        return f32::deserialize(deserializer);
+    }
+    u8::deserialize(deserializer).map(f32::from)
+}
+
 static FIRST_NAMES_BYTES: &str = include_str!("first_names.txt");
 static LAST_NAMES_BYTES: &str = include_str!("names.txt");
 
@@ -57,7 +90,7 @@ lazy_static! {
 
 impl PersonalInfo {
     pub fn new(rng: &mut RandProvider) -> Self {
-        let age = (rng.next_f32() * 30.0 + 20.0) as u8;
+        let age = rng.next_f32() * 30.0 + 20.0;
         let gender = match rng.next_u32() % 2 {
             0 => Gender::M,
             1 => Gender::F,
@@ -69,7 +102,20 @@ impl PersonalInfo {
 
         let name = format!("{} {}", first_name, last_name);
 
-        Self { name, age, gender }
+        // newcomers went to school elsewhere, but rarely to the higher levels
+        let education = match rng.next_u32() % 2 {
+            0 => Education::None,
+            1 => Education::Basic,
+            _ => unreachable!(),
+        };
+
+        Self {
+            name,
+            age,
+            gender,
+            education,
+            schooling: 0.0,
+        }
     }
 }
 
@@ -80,18 +126,22 @@ impl Default for HumanDecisionKind {
 }
 
 impl HumanDecisionKind {
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
+        me: HumanID,
         router: &mut Router,
         binfos: &BuildingInfos,
         map: &Map,
+        fires: &Fires,
+        cbuf: &ParCommandBuffer<HumanEnt>,
         cbuf_freight: &ParCommandBuffer<FreightStationEnt>,
     ) -> bool {
         match *self {
             HumanDecisionKind::GoTo(dest) => router.go_to(dest),
             HumanDecisionKind::MultiStack(ref mut decisions) => {
                 if let Some(d) = decisions.last_mut() {
-                    if d.update(router, binfos, map, cbuf_freight) {
+                    if d.update(me, router, binfos, map, fires, cbuf, cbuf_freight) {
                         decisions.pop();
                     }
                     false
@@ -104,6 +154,25 @@ impl HumanDecisionKind {
                 true
             }
             HumanDecisionKind::DeliverAtBuilding(bid) => {
+                // the stop is completed even if the building was removed on the way,
+                // so that the trip ends and its goods go back to the seller
+                cbuf.exec_ent(me, move |sim| {
+                    let Some(w) = sim.world.humans.get_mut(me).and_then(|h| h.work.as_mut()) else {
+                        return;
+                    };
+                    let WorkKind::Driver {
+                        truck,
+                        deliver_order,
+                    } = &mut w.kind
+                    else {
+                        return;
+                    };
+                    let mut market = sim.resources.write::<Market>();
+                    *deliver_order =
+                        sim.resources
+                            .write::<Deliveries>()
+                            .complete_stop(*truck, bid, &mut market);
+                });
                 let Some(b) = map.buildings().get(bid) else {
                     return true;
                 };
@@ -119,6 +188,49 @@ impl HumanDecisionKind {
                 }
                 true
             }
+            HumanDecisionKind::CollectGarbage(bid) => {
+                cbuf.exec_ent(me, move |sim| {
+                    let Some(w) = sim.world.humans.get_mut(me).and_then(|h| h.work.as_mut()) else {
+                        return;
+                    };
+                    let WorkKind::GarbageCollector { target, .. } = &mut w.kind else {
+                        return;
+                    };
+                    *target = None;
+                    let workplace = w.workplace;
+
+                    let Some(BuildingKind::GoodsCompany(depot)) =
+                        sim.map().buildings().get(workplace).map(|b| b.kind)
+                    else {
+                        return;
+                    };
+                    let capacity = depot.prototype().garbage_truck_capacity;
+                    sim.write::<Garbage>().collect(bid, capacity);
+                });
+                true
+            }
+            HumanDecisionKind::ExtinguishFire(bid) => {
+                let Some(fire) = fires.get(bid) else {
+                    // put out or burned down, the truck can go back to the station
+                    cbuf.exec_ent(me, move |sim| {
+                        let Some(w) = sim.world.humans.get_mut(me).and_then(|h| h.work.as_mut())
+                        else {
+                            return;
+                        };
+                        if let WorkKind::Firefighter { target, .. } = &mut w.kind {
+                            *target = None;
+                        }
+                    });
+                    return true;
+                };
+                if fire.arrived.is_none() {
+                    cbuf.exec_ent(me, move |sim| {
+                        let now = sim.read::<GameTime>().instant();
+                        sim.write::<Fires>().arrive(bid, now);
+                    });
+                }
+                false
+            }
             HumanDecisionKind::Yield => true,
         }
     }
@@ -130,6 +242,8 @@ enum NextDesire<'a> {
     Home(&'a mut Home),
     Work(&'a mut Work),
     Food(&'a mut BuyFood),
+    Leisure(&'a mut Leisure),
+    Tourist(&'a mut Tourist),
 }
 
 pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
@@ -139,23 +253,51 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
     let rc = &*resources.read();
     let rd = &*resources.read();
     let re = &*resources.read();
+    let rf = &*resources.read();
+    let rg = &*resources.read();
+    let time: &GameTime = &resources.read();
+    let mut activities = resources.write::<ActivityLog>();
+    let mut visitors = resources.write::<LeisureVisitors>();
+
+    if time.tick.0 % TICKS_PER_HOUR == 0 {
+        activities.retain(|id| world.humans.contains_key(id));
+    }
+    visitors.count(world.humans.values().filter_map(|h| h.leisure.visiting()));
 
     world.humans.iter_mut().for_each(|(ent, h)| {
+        if h.decision.wait == 0 {
+            let activity = Activity::classify(
+                &h.location,
+                h.home.house,
+                h.work.as_ref(),
+                h.leisure.visiting(),
+                time.daytime.hour,
+            );
+            activities.record(ent, time.tick, activity);
+        }
+
         update_decision(
             ra,
             rb,
             rc,
             rd,
             re,
+            rf,
+            rg,
+            &visitors,
             ent,
             &h.trans,
             &h.location,
             &mut h.router,
             &mut h.bought,
+            &h.wallet,
             &mut h.decision,
-            Some(&mut h.food),
+            // tourists eat at the hotel
+            h.tourist.is_none().then_some(&mut h.food),
+            Some(&mut h.leisure),
             Some(&mut h.home),
             h.work.as_mut(),
+            h.tourist.as_mut(),
         )
     });
 }
@@ -167,15 +309,21 @@ pub fn update_decision(
     time: &GameTime,
     binfos: &BuildingInfos,
     map: &Map,
+    fires: &Fires,
+    deliveries: &Deliveries,
+    visitors: &LeisureVisitors,
     me: HumanID,
     trans: &Transform,
     loc: &Location,
     router: &mut Router,
     bought: &mut Bought,
+    wallet: &Wallet,
     decision: &mut HumanDecision,
     food: Option<&mut BuyFood>,
+    leisure: Option<&mut Leisure>,
     home: Option<&mut Home>,
     work: Option<&mut Work>,
+    tourist: Option<&mut Tourist>,
 ) {
     if decision.wait != 0 {
         decision.wait -= 1;
@@ -183,7 +331,10 @@ pub fn update_decision(
     }
     let pos = trans.pos;
     decision.wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
-    if !decision.kind.update(router, binfos, map, cbuf_freight) {
+    if !decision
+        .kind
+        .update(me, router, binfos, map, fires, cbuf, cbuf_freight)
+    {
         return;
     }
 
@@ -191,7 +342,7 @@ pub fn update_decision(
     let mut max_score = f32::NEG_INFINITY;
 
     if let Some(home) = home {
-        let score = home.score();
+        let score = home.score(fires);
         home.last_score = score;
 
         if score > max_score {
@@ -200,6 +351,17 @@ pub fn update_decision(
         }
     }
 
+    if let Some(leisure) = leisure {
+        leisure.update(time, map, work.as_deref());
+        let score = leisure.score(time, loc, trans, router, visitors, map, work.as_deref());
+        leisure.last_score = score;
+
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Leisure(leisure);
+        }
+    }
+
     if let Some(work) = work {
         let score = work.score(time);
         work.last_score = score;
@@ -210,8 +372,18 @@ pub fn update_decision(
         }
     }
 
+    if let Some(tourist) = tourist {
+        let score = tourist.score(time, loc, map, trans.pos);
+        tourist.last_score = score;
+
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Tourist(tourist);
+        }
+    }
+
     if let Some(food) = food {
-        let score = food.score(time, loc, bought);
+        let score = food.score(time, loc, bought, map);
         food.last_score = score;
 
         #[allow(unused_assignments)]
@@ -223,33 +395,65 @@ pub fn update_decision(
 
     match decision_id {
         NextDesire::Home(home) => decision.kind = home.apply(),
-        NextDesire::Work(work) => decision.kind = work.apply(loc, router),
+        NextDesire::Work(work) => decision.kind = work.apply(loc, router, deliveries),
         NextDesire::Food(food) => {
-            decision.kind = food.apply(cbuf, binfos, time, me, trans, loc, bought)
+            decision.kind = food.apply(cbuf, binfos, time, me, trans, loc, bought, wallet.0)
+        }
+        NextDesire::Leisure(leisure) => {
+            decision.kind = leisure.apply(cbuf, visitors, router, map, time, me, trans, loc)
+        }
+        NextDesire::Tourist(tourist) => {
+            decision.kind = tourist.apply(cbuf, binfos, map, time, me, trans, loc)
         }
         NextDesire::None => {}
     }
 }
 
+/// Spawns a new household in the empty house: its owner and sometimes a partner
 pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     profiling::scope!("spawn_human");
+    let info = PersonalInfo::new(&mut sim.write::<RandProvider>());
+    let gender = info.gender;
+    let id = spawn_resident(sim, house, info)?;
+    sim.write::<BuildingInfos>()
+        .set_owner(house, SoulID::Human(id));
+
+    if sim.write::<RandProvider>().next_f32() < demographics().couple_chance {
+        let mut partner = PersonalInfo::new(&mut sim.write::<RandProvider>());
+        partner.gender = gender.other();
+        spawn_resident(sim, house, partner);
+    }
+
+    Some(id)
+}
+
+/// Spawns a human living in the house without owning it.
+/// Only adults get a car and look for a job.
+pub fn spawn_resident(
+    sim: &mut Simulation,
+    house: BuildingID,
+    info: PersonalInfo,
+) -> Option<HumanID> {
     let map = sim.map();
     let housepos = map.buildings().get(house)?.door_pos;
     drop(map);
 
     let _color = random_pedestrian_shirt_color(&mut sim.write::<RandProvider>());
 
-    let hpos = sim.map().buildings().get(house)?.door_pos;
     let p = Pedestrian::new(&mut sim.write::<RandProvider>());
 
     let time = sim.read::<GameTime>().instant();
 
-    let car = spawn_parked_vehicle(sim, VehicleKind::Car, housepos);
-
-    let personal_info = Box::new(PersonalInfo::new(&mut sim.write::<RandProvider>()));
+    let adult = info.age >= demographics().adult_age;
+    let education = info.education;
+    let car = if adult {
+        spawn_parked_vehicle(sim, VehicleKind::Car, housepos)
+    } else {
+        None
+    };
 
     let id = sim.world.insert(HumanEnt {
-        trans: Transform::new(hpos),
+        trans: Transform::new(housepos),
         location: Location::Building(house),
         pedestrian: p,
         it: Itinerary::NONE,
@@ -257,19 +461,22 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
         decision: HumanDecision::default(),
         home: Home::new(house),
         food: BuyFood::new(time),
+        leisure: Leisure::new(time),
         bought: Bought::default(),
         router: Router::new(car),
         collider: None,
         work: None,
-        personal_info,
+        tourist: None,
+        wallet: Default::default(),
+        happiness: Default::default(),
+        personal_info: Box::new(info),
     });
 
-    let soul = SoulID::Human(id);
-    let mut m = sim.write::<Market>();
-    m.buy(soul, housepos.xy(), ItemID::new("job-opening"), 1);
-
-    sim.write::<BuildingInfos>().get_in(house, soul);
-    sim.write::<BuildingInfos>().set_owner(house, soul);
+    if adult {
+        sim.write::<JobMarket>().seek(id, housepos.xy(), education);
+    }
+    sim.write::<BuildingInfos>()
+        .get_in(house, SoulID::Human(id));
 
     Some(id)
 }
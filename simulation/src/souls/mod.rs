@@ -1,32 +1,46 @@
 use crate::map::BuildingKind;
-use crate::map_dynamic::BuildingInfos;
+use crate::map_dynamic::{BuildingInfos, Fires};
 use crate::souls::freight_station::freight_station_soul;
 use crate::souls::goods_company::company_soul;
 use crate::souls::human::spawn_human;
+use crate::souls::warehouse::warehouse_soul;
 use crate::Simulation;
 
 #[macro_use]
 pub mod desire;
 
+pub mod activity;
+pub mod delivery;
+pub mod demographics;
+pub mod education;
 pub mod freight_station;
 pub mod goods_company;
+pub mod happiness;
 pub mod human;
+pub mod tourism;
+pub mod warehouse;
 
 /// Adds souls to empty buildings
 pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
     profiling::scope!("souls::add_souls_to_empty_buildings");
     let map = sim.map();
     let infos = sim.read::<BuildingInfos>();
+    let fires = sim.read::<Fires>();
     let mut empty_buildings = Vec::with_capacity(16);
 
     for (id, building) in map.buildings() {
         if unwrap_cont!(infos.get(id)).owner.is_some() {
             continue;
         }
+        // nobody moves in a burned down building until it is bulldozed and rebuilt
+        if fires.is_ruin(id) {
+            continue;
+        }
 
         empty_buildings.push((building.kind, id));
     }
     drop(infos);
+    drop(fires);
     drop(map);
 
     let mut n_souls_added = 0;
@@ -46,6 +60,10 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
                 freight_station_soul(sim, build_id, id);
                 n_souls_added += 1;
             }
+            BuildingKind::Warehouse(id) => {
+                warehouse_soul(sim, build_id, id);
+                n_souls_added += 1;
+            }
             _ => {}
         }
     }
@@ -0,0 +1,342 @@
+//! Tourists come from outside the city by train, by plane or by the road at the edge of the map,
+//! stay a few days at a hotel, spend their money at the shops and go back where they came from.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Transform, Vec3};
+use prototypes::{GameDuration, GameInstant, GameTime};
+
+use crate::economy::{Bought, Wallet};
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::map_dynamic::{Garbage, Itinerary, Router};
+use crate::souls::desire::{BuyFood, Home, Leisure, Tourist, TOURIST_BUDGET};
+use crate::souls::human::{HumanDecision, PersonalInfo};
+use crate::statistics::Statistics;
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::transportation::{
+    put_pedestrian_in_transport_grid, Location, Pedestrian, Speed, TransportGrid,
+};
+use crate::utils::rand_provider::RandProvider;
+use crate::world::{HumanEnt, HumanID};
+use crate::{ParCommandBuffer, Simulation};
+
+/// Tourists arriving each day in a city with hotels and nothing to see
+const BASE_DAILY_ARRIVALS: f32 = 6.0;
+
+/// Each park or leisure venue attracts this many more times the base arrivals
+const VENUE_ATTRACTION: f32 = 0.25;
+
+/// Venues after which more do not attract more tourists
+const MAX_VENUES: f32 = 12.0;
+
+/// A city where every building is dirty loses this share of its tourists
+const POLLUTION_PENALTY: f32 = 0.8;
+
+/// Tourists stay between this many days and one more
+const STAY_DAYS: f32 = 2.0;
+
+/// Tourists who could not reach the exit this long after the end of their stay are removed anyway
+const OVERSTAY_MINUTES: u64 = 6 * 60;
+
+/// Tourists coming to the city and how attractive it is to them
+#[derive(Default, Serialize, Deserialize)]
+pub struct Tourism {
+    /// Arrivals owed by the rate but not spawned yet, they accumulate fractionally
+    pending: f32,
+    last_update: Option<GameInstant>,
+    /// Multiplier of [`BASE_DAILY_ARRIVALS`] from the venues and the pollution of the city
+    pub attractiveness: f32,
+    /// Tourists currently in the city
+    pub tourists: u32,
+    /// Rooms of all the hotels of the city
+    pub rooms: u32,
+    /// Tourists who arrived since the start of the game
+    pub arrivals: u32,
+    /// Tourists who could not come because the hotels were full since the start of the game
+    pub turned_away: u32,
+}
+
+impl Tourism {
+    /// Tourists arriving each day at the current attractiveness, if there are free rooms
+    pub fn daily_arrivals(&self) -> f32 {
+        BASE_DAILY_ARRIVALS * self.attractiveness
+    }
+}
+
+/// How much the city attracts tourists: the parks and leisure venues
+/// make it more attractive and the uncollected garbage less
+fn attractiveness(map: &Map, garbage: &Garbage) -> f32 {
+    let mut venues = 0.0;
+    let mut dirty = 0;
+    for (id, b) in map.buildings() {
+        if matches!(b.kind, BuildingKind::Leisure(_)) {
+            venues += 1.0;
+        }
+        if garbage.is_dirty(id) {
+            dirty += 1;
+        }
+    }
+    let pollution = dirty as f32 / map.buildings().len().max(1) as f32;
+    (1.0 + VENUE_ATTRACTION * f32::min(venues, MAX_VENUES)) * (1.0 - POLLUTION_PENALTY * pollution)
+}
+
+/// Where tourists get in and out of the city: the platform of a station served by trains,
+/// otherwise a road connection leading to the city, or the intersection closest to the edge of the map
+fn arrival_point(map: &Map, rail: &PassengerRail, rng: &mut RandProvider) -> Option<Vec3> {
+    if !rail.trains().is_empty() && !rail.stations().is_empty() {
+        let i = rng.next_u32() as usize % rail.stations().len();
+        return rail.stations().values().nth(i).map(|s| s.platform);
+    }
+
+    let (used, unused): (Vec<_>, Vec<_>) = map
+        .road_connections()
+        .partition(|&(id, _)| map.is_road_connection_used(id));
+    if !used.is_empty() {
+        return Some(used[rng.next_u32() as usize % used.len()].1);
+    }
+
+    // the generated connections lead nowhere until they are linked to the city
+    let mut dead_ends = BTreeSet::new();
+    for (id, _) in unused {
+        dead_ends.insert(id);
+        for &r in &map.intersections()[id].roads {
+            dead_ends.extend(map.roads().get(r).and_then(|r| r.other_end(id)));
+        }
+    }
+
+    let bounds = map.environment.bounds();
+    map.intersections()
+        .values()
+        .filter(|inter| !dead_ends.contains(&inter.id))
+        .map(|inter| {
+            let p = inter.pos.xy();
+            let to_edge = (p.x - bounds.ll.x)
+                .min(bounds.ur.x - p.x)
+                .min(p.y - bounds.ll.y)
+                .min(bounds.ur.y - p.y);
+            (inter.pos, to_edge)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(pos, _)| pos)
+}
+
+/// Every in-game hour, removes the tourists who left and brings new ones
+/// at a rate depending on the attractiveness of the city, as long as the hotels have free rooms.
+/// Uses the time elapsed since the last update so that time skips bring everyone accordingly.
+pub fn tourism_system(sim: &mut Simulation) {
+    profiling::scope!("souls::tourism_system");
+
+    let (now, days) = {
+        let time = sim.resources.read::<GameTime>();
+        let mut tourism = sim.resources.write::<Tourism>();
+        let now = time.instant();
+        let Some(last) = tourism.last_update else {
+            tourism.last_update = Some(now);
+            return;
+        };
+        let elapsed = last.elapsed(&time);
+        if elapsed < GameDuration::from_minutes(60) {
+            return;
+        }
+        tourism.last_update = Some(now);
+        (now, elapsed.seconds() as f32 / GameTime::DAY as f32)
+    };
+
+    let map = sim.resources.read::<Map>();
+    let mut guests: BTreeMap<BuildingID, u32> = BTreeMap::new();
+    let mut rooms: BTreeMap<BuildingID, u32> = BTreeMap::new();
+    for (id, b) in map.buildings() {
+        if let BuildingKind::Hotel(proto) = b.kind {
+            rooms.insert(id, proto.prototype().rooms);
+        }
+    }
+
+    let mut n_left = 0;
+    {
+        let cbuf = sim.resources.read::<ParCommandBuffer<HumanEnt>>();
+        for (id, h) in sim.world.humans.iter_mut() {
+            let Some(ref mut tourist) = h.tourist else {
+                continue;
+            };
+            let overstayed =
+                now >= tourist.departure + GameDuration::from_minutes(OVERSTAY_MINUTES);
+            if tourist.has_left(&h.location, h.trans.pos) || overstayed {
+                cbuf.kill(id);
+                n_left += 1;
+                continue;
+            }
+            // the hotel was bulldozed, the stay is cut short
+            if !rooms.contains_key(&tourist.hotel) {
+                tourist.departure = tourist.departure.min(now);
+                continue;
+            }
+            *guests.entry(tourist.hotel).or_default() += 1;
+        }
+    }
+    let n_tourists = sim
+        .world
+        .humans
+        .values()
+        .filter(|h| h.tourist.is_some())
+        .count() as u32
+        - n_left;
+
+    let attractiveness = attractiveness(&map, &sim.resources.read::<Garbage>());
+    let free: Vec<(BuildingID, u32)> = rooms
+        .iter()
+        .map(|(&hotel, &n)| {
+            (
+                hotel,
+                n.saturating_sub(guests.get(&hotel).copied().unwrap_or(0)),
+            )
+        })
+        .filter(|&(_, free)| free > 0)
+        .collect();
+    let n_free: u32 = free.iter().map(|&(_, free)| free).sum();
+
+    let exit = arrival_point(
+        &map,
+        &sim.resources.read::<PassengerRail>(),
+        &mut sim.resources.write::<RandProvider>(),
+    );
+    drop(map);
+
+    let n_arrivals = {
+        let mut tourism = sim.resources.write::<Tourism>();
+        tourism.attractiveness = attractiveness;
+        tourism.rooms = rooms.values().sum();
+        tourism.tourists = n_tourists;
+        if rooms.is_empty() || exit.is_none() {
+            tourism.pending = 0.0;
+            return;
+        }
+
+        tourism.pending += tourism.daily_arrivals() * days;
+        let wanted = tourism.pending as u32;
+        let n = wanted.min(n_free);
+        tourism.pending -= wanted as f32;
+        tourism.turned_away += wanted - n;
+        tourism.arrivals += n;
+        tourism.tourists += n;
+        n
+    };
+    let Some(exit) = exit else {
+        return;
+    };
+
+    let mut spawned = 0;
+    for (hotel, free) in free {
+        for _ in 0..free {
+            if spawned == n_arrivals {
+                break;
+            }
+            let stay = random_stay(sim);
+            spawn_tourist(sim, hotel, exit, stay);
+            spawned += 1;
+        }
+    }
+
+    if spawned > 0 || n_left > 0 {
+        log::info!("{} tourists arrived and {} left", spawned, n_left);
+    }
+}
+
+/// How long a new tourist stays, between [`STAY_DAYS`] and one more day
+fn random_stay(sim: &Simulation) -> GameDuration {
+    let extra = sim.write::<RandProvider>().next_f32();
+    let stay = (STAY_DAYS + extra) * GameTime::DAY as f32;
+    GameDuration::from_secs(stay as u64)
+}
+
+/// The hotels with free rooms and how many rooms are free
+pub fn free_rooms(sim: &Simulation) -> Vec<(BuildingID, u32)> {
+    let mut free: BTreeMap<BuildingID, u32> = sim
+        .map()
+        .buildings()
+        .iter()
+        .filter_map(|(id, b)| match b.kind {
+            BuildingKind::Hotel(proto) => Some((id, proto.prototype().rooms)),
+            _ => None,
+        })
+        .collect();
+    for h in sim.world.humans.values() {
+        let Some(ref tourist) = h.tourist else {
+            continue;
+        };
+        if let Some(n) = free.get_mut(&tourist.hotel) {
+            *n = n.saturating_sub(1);
+        }
+    }
+    free.into_iter().filter(|&(_, n)| n > 0).collect()
+}
+
+/// Brings tourists who came another way than the trains and roads, like the planes.
+/// They go to the hotels with free rooms and the others are turned away.
+/// Returns how many stayed.
+pub fn welcome_tourists(sim: &mut Simulation, arrival: Vec3, n: u32) -> u32 {
+    let mut spawned = 0;
+    for (hotel, free) in free_rooms(sim) {
+        for _ in 0..free {
+            if spawned == n {
+                break;
+            }
+            let stay = random_stay(sim);
+            spawn_tourist(sim, hotel, arrival, stay);
+            spawned += 1;
+        }
+    }
+
+    let mut tourism = sim.write::<Tourism>();
+    tourism.arrivals += spawned;
+    tourism.tourists += spawned;
+    tourism.turned_away += n - spawned;
+    spawned
+}
+
+/// Spawns a tourist walking from the arrival point to the hotel
+pub fn spawn_tourist(
+    sim: &mut Simulation,
+    hotel: BuildingID,
+    arrival: Vec3,
+    stay: GameDuration,
+) -> HumanID {
+    let info = PersonalInfo::new(&mut sim.write::<RandProvider>());
+    let p = Pedestrian::new(&mut sim.write::<RandProvider>());
+    let time = sim.read::<GameTime>().instant();
+    let collider = put_pedestrian_in_transport_grid(&mut sim.write::<TransportGrid>(), arrival);
+
+    let id = sim.world.insert(HumanEnt {
+        trans: Transform::new(arrival),
+        location: Location::Outside,
+        pedestrian: p,
+        it: Itinerary::NONE,
+        speed: Speed::default(),
+        decision: HumanDecision::default(),
+        home: Home::new(hotel),
+        food: BuyFood::new(time),
+        leisure: Leisure::new(time),
+        bought: Bought::default(),
+        router: Router::new(None),
+        collider: Some(collider),
+        work: None,
+        tourist: Some(Tourist::new(hotel, arrival, time, stay)),
+        wallet: Wallet(TOURIST_BUDGET),
+        happiness: Default::default(),
+        personal_info: Box::new(info),
+    });
+
+    sim.write::<Statistics>().tourist_arrived();
+
+    id
+}
+
+/// The tourists staying at the hotel
+pub fn hotel_guests(sim: &Simulation, hotel: BuildingID) -> usize {
+    sim.world
+        .humans
+        .values()
+        .filter(|h| h.tourist.as_ref().is_some_and(|t| t.hotel == hotel))
+        .count()
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Transform, Vec2};
+use prototypes::{RecipeItem, WarehousePrototypeID};
+
+use crate::economy::Market;
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::utils::resources::Resources;
+use crate::world::{WarehouseEnt, WarehouseID};
+use crate::World;
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+/// A warehouse stockpiles goods when the market has more sellers than buyers
+/// and sells them back locally when there are more buyers than sellers,
+/// smoothing out producers and consumers that are out of phase.
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct Warehouse {
+    pub proto: WarehousePrototypeID,
+    pub building: BuildingID,
+}
+
+pub fn warehouse_soul(
+    sim: &mut Simulation,
+    building: BuildingID,
+    proto: WarehousePrototypeID,
+) -> Option<WarehouseID> {
+    let map = sim.map();
+    let b = map.buildings.get(building)?;
+    let height = b.height;
+    let pos = b.obb.center();
+    drop(map);
+
+    let id = sim.world.insert(WarehouseEnt {
+        trans: Transform::new(pos.z(height)),
+        w: Warehouse { proto, building },
+    });
+
+    let soul = SoulID::Warehouse(id);
+
+    {
+        let mut m = sim.write::<Market>();
+        for item in &proto.prototype().capacity {
+            m.register(soul, item.id);
+        }
+    }
+
+    sim.write::<BuildingInfos>().set_owner(building, soul);
+
+    Some(id)
+}
+
+/// Places the orders of a warehouse for this tick.
+/// When other souls sell more than what is bought, the warehouse buys the surplus up to its capacity.
+/// Otherwise it sells from its stock what is missing.
+/// The warehouse never trades with the external market.
+pub fn warehouse_act(capacity: &[RecipeItem], soul: SoulID, near: Vec2, market: &mut Market) {
+    for item in capacity {
+        let Some(m) = market.inner().get(&item.id) else {
+            continue;
+        };
+        let supply = m.supply(soul);
+        let demand = m.demand(soul);
+        let stock = market.capital(soul, item.id).max(0) as u32;
+
+        if supply > demand {
+            market.cancel_sell(soul, item.id);
+
+            let qty = (item.amount.max(0) as u32)
+                .saturating_sub(stock)
+                .min(supply - demand);
+            if qty == 0 {
+                market.cancel_buy(soul, item.id);
+                continue;
+            }
+            market.buy_local(soul, near, item.id, qty);
+        } else {
+            market.cancel_buy(soul, item.id);
+
+            let qty = stock.min(demand - supply);
+            if qty == 0 {
+                market.cancel_sell(soul, item.id);
+                continue;
+            }
+            // stock == qty so that nothing is exported
+            market.sell(soul, near, item.id, qty, qty);
+        }
+    }
+}
+
+pub fn warehouse_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::warehouse_system");
+    let cbuf = resources.read::<ParCommandBuffer<WarehouseEnt>>();
+    let map = resources.read::<Map>();
+    let mut market = resources.write::<Market>();
+
+    for (me, w) in world.warehouses.iter() {
+        let Some(b) = map.buildings.get(w.w.building) else {
+            cbuf.kill(me);
+            continue;
+        };
+
+        warehouse_act(
+            &w.w.proto.prototype().capacity,
+            SoulID::Warehouse(me),
+            b.door_pos.xy(),
+            &mut market,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Vec2};
+    use prototypes::{ItemID, RecipeItem};
+
+    use crate::economy::{Market, TradePolicy};
+    use crate::tests::test_prototypes;
+    use crate::world::{CompanyID, WarehouseID};
+    use crate::SoulID;
+
+    use super::warehouse_act;
+
+    const PHASE: u32 = 10;
+    const PRODUCER_STORAGE: i32 = 20;
+
+    /// The producer only works during the first half of the cycle and can't store much,
+    /// the consumer only buys during the second half.
+    /// Returns the quantity the consumer got and the highest stock of the warehouse
+    fn run_out_of_phase(with_warehouse: bool) -> (i32, i32) {
+        let _prototypes = test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal",
+            optout_exttrade = true,
+          }
+        }
+        "#,
+        );
+
+        let cereal = ItemID::new("cereal");
+        let producer =
+            SoulID::GoodsCompany(CompanyID::from(slotmapd::KeyData::from_ffi((1 << 32) | 1)));
+        let consumer =
+            SoulID::GoodsCompany(CompanyID::from(slotmapd::KeyData::from_ffi((1 << 32) | 2)));
+        let warehouse = SoulID::Warehouse(WarehouseID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 3,
+        )));
+        let capacity = [RecipeItem {
+            id: cereal,
+            amount: 200,
+        }];
+
+        let mut m = Market::default();
+        let policy = TradePolicy::default();
+
+        let mut max_stock = 0;
+        for _ in 0..10 {
+            for t in 0..2 * PHASE {
+                let producing = t < PHASE;
+                if producing {
+                    m.cancel_buy(consumer, cereal);
+                    if m.capital(producer, cereal) < PRODUCER_STORAGE {
+                        m.produce(producer, cereal, 10);
+                    }
+                    m.sell_all(producer, Vec2::ZERO, cereal, 0);
+                } else {
+                    m.buy(consumer, vec2(100.0, 0.0), cereal, 10);
+                }
+
+                if with_warehouse {
+                    warehouse_act(&capacity, warehouse, vec2(50.0, 0.0), &mut m);
+                }
+
+                m.make_trades(&policy, |_, _, _| None);
+
+                max_stock = max_stock.max(m.capital(warehouse, cereal));
+            }
+        }
+
+        (m.capital(consumer, cereal), max_stock)
+    }
+
+    #[test]
+    fn warehouse_absorbs_oscillation() {
+        let (without, stock_without) = run_out_of_phase(false);
+        let (with, stock_with) = run_out_of_phase(true);
+
+        assert_eq!(stock_without, 0);
+        // without the warehouse, the consumer only gets what the producer could store
+        assert_eq!(without, 10 * PRODUCER_STORAGE);
+        // the warehouse buys the whole production and sells it back during the consumption phase
+        assert!(stock_with >= 90, "stock: {}", stock_with);
+        assert!(with >= 4 * without, "with: {} without: {}", with, without);
+    }
+}
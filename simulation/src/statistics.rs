@@ -0,0 +1,285 @@
+//! City-wide statistics sampled every in-game hour, shown as plots by the UI.
+//! Values are accumulated every minute or when something happens, so that no system has to scan
+//! every entity on every tick.
+
+use std::collections::VecDeque;
+
+use prototypes::{
+    GameTime, Money, HOURS_PER_DAY, SECONDS_PER_HOUR, TICKS_PER_HOUR, TICKS_PER_MINUTE,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::economy::{Government, Trade};
+use crate::map::Map;
+use crate::map_dynamic::ElectricityFlow;
+use crate::souls::happiness::CityStats;
+use crate::transportation::{Location, VehicleState};
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// 30 days at hourly resolution
+pub const STATISTICS_LEN: usize = 30 * HOURS_PER_DAY as usize;
+
+/// Hourly values of a statistic, oldest first.
+/// Never grows past [`STATISTICS_LEN`], the oldest values are dropped.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct StatSeries {
+    values: VecDeque<f32>,
+}
+
+impl StatSeries {
+    pub fn push(&mut self, v: f32) {
+        if self.values.len() >= STATISTICS_LEN {
+            self.values.pop_front();
+        }
+        self.values.push_back(v);
+    }
+
+    pub fn values(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.values.back().copied()
+    }
+
+    /// Sum of the last `n` values, e.g. the last day
+    pub fn sum_last(&self, n: usize) -> f32 {
+        self.values.iter().rev().take(n).sum()
+    }
+
+    /// Sum of each day, oldest first. The last day is the last 24 hours, the first one may be
+    /// partial.
+    pub fn per_day(&self) -> Vec<f32> {
+        let hours: Vec<f32> = self.values.iter().rev().copied().collect();
+        let mut days: Vec<f32> = hours
+            .chunks(HOURS_PER_DAY as usize)
+            .map(|day| day.iter().sum())
+            .collect();
+        days.reverse();
+        days
+    }
+}
+
+/// What happened during the current hour
+#[derive(Default, Serialize, Deserialize)]
+struct HourAccumulator {
+    export_income: Money,
+    import_expenses: Money,
+    tariffs: Money,
+    trips_started: u32,
+    minutes: u32,
+    humans_en_route: u64,
+    vehicles_en_route: u64,
+    produced_power: i64,
+    consumed_power: i64,
+    blackout_minutes: u32,
+
+    /// Values at the end of the last hour, to compute the changes.
+    /// Not set before the first hour is closed.
+    has_last: bool,
+    last_money: Money,
+    last_construction_spending: Money,
+    last_births: u32,
+    last_deaths: u32,
+
+    tourist_arrivals: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Statistics {
+    pub population: StatSeries,
+    pub births: StatSeries,
+    pub deaths: StatSeries,
+
+    /// Government money at the end of the hour, in $
+    pub money: StatSeries,
+    /// Income from the goods the government sold to the external market, in $
+    pub export_income: StatSeries,
+    /// Expenses for the goods the government bought from the external market, in $
+    pub import_expenses: StatSeries,
+    /// Income from the tariffs on the external trade, in $
+    pub tariff_income: StatSeries,
+    /// Roads and buildings built by the players, in $
+    pub construction_expenses: StatSeries,
+    /// Everything else, electricity bills and upkeep, in $
+    pub other: StatSeries,
+
+    /// Average duration of the trips, in in-game seconds
+    pub trip_time: StatSeries,
+    pub vehicles_en_route: StatSeries,
+    /// Sum of the congestion of the lanes divided by the number of lanes, between 0 and 1
+    pub congestion: StatSeries,
+
+    /// Average power produced during the hour, in kW
+    pub power_production: StatSeries,
+    /// Average power consumed during the hour, in kW
+    pub power_consumption: StatSeries,
+    /// Fraction of the hour some network was in a blackout, between 0 and 1
+    pub blackout: StatSeries,
+
+    acc: HourAccumulator,
+
+    /// Tourists who arrived at a hotel of the city
+    pub tourist_arrivals: StatSeries,
+}
+
+impl Statistics {
+    /// Called by the market with the trades of the tick
+    pub fn record_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            // the souls with a wallet settle their own trades, the government only takes the tariffs
+            if trade.buyer.0.is_external() {
+                if !trade.seller.0.has_wallet() {
+                    self.acc.export_income += trade.money_delta;
+                }
+            } else if trade.seller.0.is_external() {
+                if !trade.buyer.0.has_wallet() {
+                    self.acc.import_expenses -= trade.money_delta;
+                }
+            } else {
+                continue;
+            }
+            self.acc.tariffs += trade.tariff;
+        }
+    }
+
+    /// Called by the router when a citizen starts going somewhere
+    pub fn trip_started(&mut self) {
+        self.acc.trips_started += 1;
+    }
+
+    /// Called when a tourist arrives in the city
+    pub fn tourist_arrived(&mut self) {
+        self.acc.tourist_arrivals += 1;
+    }
+
+    /// Average duration of the trips during the hour, by Little's law:
+    /// the mean number of people on the road divided by the rate at which trips start
+    fn mean_trip_time(acc: &HourAccumulator) -> f32 {
+        if acc.trips_started == 0 || acc.minutes == 0 {
+            return 0.0;
+        }
+        let mean_en_route = acc.humans_en_route as f32 / acc.minutes as f32;
+        mean_en_route * SECONDS_PER_HOUR as f32 / acc.trips_started as f32
+    }
+
+    fn sample_minute(&mut self, world: &World, resources: &Resources) {
+        let acc = &mut self.acc;
+        acc.minutes += 1;
+        acc.humans_en_route += world
+            .humans
+            .values()
+            .filter(|h| !matches!(h.location, Location::Building(_)))
+            .count() as u64;
+        acc.vehicles_en_route += world
+            .vehicles
+            .values()
+            .filter(|v| {
+                matches!(
+                    v.vehicle.state,
+                    VehicleState::Driving | VehicleState::Panicking(_)
+                )
+            })
+            .count() as u64;
+
+        let map = resources.read::<Map>();
+        let flow = resources.read::<ElectricityFlow>();
+        let mut blackout = false;
+        for network in map.electricity.networks() {
+            let stats = flow.network_stats(network.id);
+            acc.produced_power += stats.produced_power.0;
+            acc.consumed_power += stats.consumed_power.0;
+            blackout |= stats.blackout;
+        }
+        if blackout {
+            acc.blackout_minutes += 1;
+        }
+    }
+
+    fn close_hour(&mut self, resources: &Resources) {
+        let city = resources.read::<CityStats>();
+        let gvt = resources.read::<Government>();
+        let map = resources.read::<Map>();
+
+        let acc = std::mem::take(&mut self.acc);
+        let minutes = acc.minutes.max(1) as f32;
+
+        // the changes of the first hour are unknown
+        let first = !acc.has_last;
+        let acc = if !first {
+            acc
+        } else {
+            HourAccumulator {
+                last_money: gvt.money,
+                last_construction_spending: gvt.construction_spending,
+                last_births: city.births,
+                last_deaths: city.deaths,
+                ..acc
+            }
+        };
+
+        self.population.push(city.population as f32);
+        self.births
+            .push(city.births.saturating_sub(acc.last_births) as f32);
+        self.deaths
+            .push(city.deaths.saturating_sub(acc.last_deaths) as f32);
+        self.tourist_arrivals.push(acc.tourist_arrivals as f32);
+
+        let construction = gvt.construction_spending - acc.last_construction_spending;
+        let known = acc.export_income - acc.import_expenses + acc.tariffs - construction;
+        self.money.push(gvt.money.bucks() as f32);
+        self.export_income.push(acc.export_income.bucks() as f32);
+        self.import_expenses
+            .push(acc.import_expenses.bucks() as f32);
+        self.tariff_income.push(acc.tariffs.bucks() as f32);
+        self.construction_expenses.push(construction.bucks() as f32);
+        let other = if first {
+            Money::ZERO
+        } else {
+            gvt.money - acc.last_money - known
+        };
+        self.other.push(other.bucks() as f32);
+
+        self.trip_time.push(Self::mean_trip_time(&acc));
+        self.vehicles_en_route
+            .push(acc.vehicles_en_route as f32 / minutes);
+        let congestion: f32 = map.lane_speeds.iter().map(|(_, flow)| 1.0 - flow).sum();
+        self.congestion
+            .push(congestion / map.lanes().len().max(1) as f32);
+
+        self.power_production
+            .push(acc.produced_power as f32 / 1000.0 / minutes);
+        self.power_consumption
+            .push(acc.consumed_power as f32 / 1000.0 / minutes);
+        self.blackout.push(acc.blackout_minutes as f32 / minutes);
+
+        self.acc.has_last = true;
+        self.acc.last_money = gvt.money;
+        self.acc.last_construction_spending = gvt.construction_spending;
+        self.acc.last_births = city.births;
+        self.acc.last_deaths = city.deaths;
+    }
+}
+
+pub fn statistics_system(world: &mut World, resources: &mut Resources) {
+    let tick = resources.read::<GameTime>().tick.0;
+    if tick % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    profiling::scope!("statistics::statistics_system");
+
+    let mut stats = resources.write::<Statistics>();
+    stats.sample_minute(world, resources);
+    if tick % TICKS_PER_HOUR == 0 {
+        stats.close_hour(resources);
+    }
+}
@@ -0,0 +1,184 @@
+use geom::{vec2, Vec2, AABB, OBB};
+use prototypes::{
+    AirportPrototypeID, BuildingGen, GameTime, GoodsCompanyID, HotelPrototypeID, ItemID, Money,
+};
+
+use crate::economy::Market;
+use crate::map::{BuildingID, SiteError, LANE_SPEED_SAMPLE_TICKS};
+use crate::souls::tourism::Tourism;
+use crate::transportation::air_traffic::{AirTraffic, BusinessTrip, BUSINESS_HOURLY_SPEND};
+use crate::world_command::{CommandError, FailedCommands};
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+fn special_building(kind: BuildingKind, center: Vec2, w: f32, h: f32) -> WorldCommand {
+    WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(center, vec2(1.0, 0.0), w, h),
+        kind,
+        gen: BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        },
+        zone: None,
+        connected_road: None,
+    }
+}
+
+fn airport() -> BuildingKind {
+    BuildingKind::Airport(AirportPrototypeID::new("small-airport"))
+}
+
+fn find(ctx: &TestCtx, f: impl Fn(BuildingKind) -> bool) -> Option<BuildingID> {
+    ctx.g
+        .map()
+        .buildings()
+        .values()
+        .find(|b| f(b.kind))
+        .map(|b| b.id)
+}
+
+#[test]
+fn airport_needs_flat_ground() {
+    let mut ctx = TestCtx::new();
+    // a hillside on the south of the map
+    ctx.g
+        .map_mut()
+        .environment
+        .terrain_apply(AABB::new_ll_ur(vec2(0.0, 0.0), vec2(512.0, 160.0)), |pos| {
+            pos.z + pos.x * 0.1
+        });
+
+    ctx.apply(&[special_building(airport(), vec2(256.0, 75.0), 360.0, 80.0)]);
+    let failed: Vec<_> = ctx
+        .g
+        .read::<FailedCommands>()
+        .since(&mut 0)
+        .cloned()
+        .collect();
+    assert_eq!(failed, vec![CommandError::Site(SiteError::NotFlat)]);
+    assert!(find(&ctx, |k| matches!(k, BuildingKind::Airport(_))).is_none());
+
+    ctx.apply(&[special_building(airport(), vec2(256.0, 400.0), 360.0, 80.0)]);
+    assert!(find(&ctx, |k| matches!(k, BuildingKind::Airport(_))).is_some());
+}
+
+#[test]
+fn plane_brings_tourists_and_business_travelers() {
+    let mut ctx = TestCtx::new();
+    ctx.apply(&[
+        special_building(airport(), vec2(256.0, 400.0), 360.0, 80.0),
+        special_building(
+            BuildingKind::Hotel(HotelPrototypeID::new("hotel")),
+            vec2(100.0, 200.0),
+            40.0,
+            40.0,
+        ),
+        special_building(
+            BuildingKind::GoodsCompany(GoodsCompanyID::new("supermarket")),
+            vec2(320.0, 200.0),
+            80.0,
+            80.0,
+        ),
+    ]);
+    for _ in 0..LANE_SPEED_SAMPLE_TICKS {
+        ctx.tick();
+    }
+
+    let airport = find(&ctx, |k| matches!(k, BuildingKind::Airport(_))).unwrap();
+    let (company, c) = ctx.g.world().companies.iter().next().unwrap();
+    let balance = c.finances.balance;
+    let soul = SoulID::GoodsCompany(company);
+    let meat = ItemID::new("meat");
+    ctx.g.write::<Market>().produce(soul, meat, 1000);
+
+    // the planes are heard from far away
+    let noise = ctx.g.map().noise_at(vec2(256.0, 300.0));
+    assert!(noise > 5.0, "noise {}", noise);
+
+    let now = ctx.g.read::<GameTime>().timestamp;
+    let landed = ctx
+        .g
+        .write::<AirTraffic>()
+        .schedule_flight(&ctx.g.map(), airport, 50, now);
+    assert!(landed);
+
+    // the plane landed, waited at the gate and left during the hour
+    ctx.skip_hours(1);
+    let air = ctx.g.read::<AirTraffic>();
+    let stats = &air.airports[&airport];
+    assert_eq!(stats.flights_today, 1);
+    assert_eq!(stats.passengers_today, 50);
+    assert_eq!(air.business_travelers(airport), 15);
+    assert!(air.flights.is_empty());
+    drop(air);
+
+    assert_eq!(ctx.g.read::<Tourism>().arrivals, 35);
+    let tourists = ctx
+        .g
+        .world
+        .humans
+        .values()
+        .filter(|h| h.tourist.is_some())
+        .count();
+    assert_eq!(tourists, 35);
+
+    // the business travelers spent an hour buying meat at the supermarket
+    let spent = ctx.g.read::<AirTraffic>().airports[&airport].business_spending;
+    assert!(spent > Money::ZERO);
+    assert!(spent <= BUSINESS_HOURLY_SPEND * 15, "spent {:?}", spent);
+    assert!(ctx.g.read::<Market>().capital(soul, meat) < 1000);
+
+    let earned = ctx
+        .g
+        .world()
+        .companies
+        .get(company)
+        .unwrap()
+        .finances
+        .balance
+        - balance;
+    assert!(earned >= spent, "earned {:?}", earned);
+
+    // the business travelers flew back at the end of the day
+    ctx.skip_hours(9);
+    assert_eq!(ctx.g.read::<AirTraffic>().business_travelers(airport), 0);
+}
+
+#[test]
+fn business_travelers_stop_shopping_when_they_fly_back() {
+    let mut ctx = TestCtx::new();
+    ctx.apply(&[
+        special_building(airport(), vec2(256.0, 400.0), 360.0, 80.0),
+        special_building(
+            BuildingKind::GoodsCompany(GoodsCompanyID::new("supermarket")),
+            vec2(320.0, 200.0),
+            80.0,
+            80.0,
+        ),
+    ]);
+    for _ in 0..LANE_SPEED_SAMPLE_TICKS {
+        ctx.tick();
+    }
+
+    let airport = find(&ctx, |k| matches!(k, BuildingKind::Airport(_))).unwrap();
+    let (company, _) = ctx.g.world().companies.iter().next().unwrap();
+    ctx.g
+        .write::<Market>()
+        .produce(SoulID::GoodsCompany(company), ItemID::new("meat"), 1000);
+
+    let now = ctx.g.read::<GameTime>().timestamp;
+    ctx.g.write::<AirTraffic>().trips.push(BusinessTrip {
+        airport,
+        travelers: 10,
+        until: now + GameTime::HOUR as f64,
+    });
+
+    // a day later at the same time, when the supermarket is open
+    ctx.skip_hours(25);
+    let air = ctx.g.read::<AirTraffic>();
+    assert_eq!(air.business_travelers(airport), 0);
+    // they only shopped for the hour they stayed, and the few ticks before they came
+    let spent = air.airports[&airport].business_spending;
+    assert!(spent > Money::ZERO);
+    assert!(spent <= BUSINESS_HOURLY_SPEND * 20, "spent {:?}", spent);
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::time::{Duration, SystemTime};
+
+use common::saveload::{AutoSaveSlots, CheckedCompressedBincode, Encoder};
+
+use crate::Simulation;
+
+use super::TestCtx;
+
+fn remove_saves(prefix: &str) {
+    let Ok(dir) = std::fs::read_dir("world") else {
+        return;
+    };
+    for entry in dir.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(prefix) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn set_modified(name: &str, time: SystemTime) {
+    File::options()
+        .write(true)
+        .open(CheckedCompressedBincode::filename(name))
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn truncated_autosave_is_skipped() {
+    let mut ctx = TestCtx::new();
+    let slots = AutoSaveSlots::new("test_truncated_autosave", 3);
+    remove_saves(&slots.prefix);
+
+    ctx.tick();
+    let older = slots.next::<CheckedCompressedBincode>();
+    ctx.g.save_to_disk(&older);
+    let older_tick = ctx.g.get_tick();
+
+    for _ in 0..10 {
+        ctx.tick();
+    }
+    let newer = slots.next::<CheckedCompressedBincode>();
+    assert_ne!(older, newer);
+    ctx.g.save_to_disk(&newer);
+
+    // cut the newest autosave in half, as if the game crashed while writing it
+    let path = CheckedCompressedBincode::filename(&newer);
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+    let now = SystemTime::now();
+    set_modified(&older, now - Duration::from_secs(60));
+    set_modified(&newer, now);
+
+    let err = Simulation::try_load_from_disk(&newer)
+        .err()
+        .expect("truncated save was loaded");
+    assert!(err.to_string().contains("truncated"), "{}", err);
+
+    let (loaded, errors) = Simulation::load_latest_save("test_truncated_autosave_world", &slots);
+    let (name, sim) = loaded.expect("no autosave could be loaded");
+    assert_eq!(name, older);
+    assert_eq!(sim.get_tick(), older_tick);
+    assert_eq!(errors.len(), 1);
+
+    let meta = Simulation::load_meta_from_disk(&older).unwrap();
+    assert_eq!(meta.tick.0, older_tick);
+
+    remove_saves(&slots.prefix);
+}
+
+#[test]
+fn newer_autosave_wins_over_the_save() {
+    let mut ctx = TestCtx::new();
+    let slots = AutoSaveSlots::new("test_newer_autosave", 2);
+    let world = "test_newer_autosave_world";
+    remove_saves(&slots.prefix);
+
+    ctx.tick();
+    ctx.g.save_to_disk(world);
+
+    for _ in 0..10 {
+        ctx.tick();
+    }
+    let autosave = slots.next::<CheckedCompressedBincode>();
+    ctx.g.save_to_disk(&autosave);
+    let autosave_tick = ctx.g.get_tick();
+
+    // the game crashed after the autosave, the manual save is stale
+    let now = SystemTime::now();
+    set_modified(world, now - Duration::from_secs(60));
+    set_modified(&autosave, now);
+
+    let (loaded, errors) = Simulation::load_latest_save(world, &slots);
+    let (name, sim) = loaded.expect("no save could be loaded");
+    assert_eq!(name, autosave);
+    assert_eq!(sim.get_tick(), autosave_tick);
+    assert!(errors.is_empty());
+
+    // a manual save made after the autosave is loaded instead
+    set_modified(world, now + Duration::from_secs(60));
+    let (loaded, _) = Simulation::load_latest_save(world, &slots);
+    assert_eq!(loaded.unwrap().0, world);
+
+    remove_saves(&slots.prefix);
+}
@@ -0,0 +1,236 @@
+use geom::{vec2, vec3, OBB};
+use prototypes::{BuildingGen, FreightStationPrototypeID, GoodsCompanyID, ItemID, Money};
+
+use crate::economy::{
+    BorderTrade, BudgetReason, FreightThroughput, Government, Market, Tariffs, TradePolicy,
+    TruckLeg,
+};
+use crate::map::{ProjectFilter, ProjectKind};
+use crate::map_dynamic::BuildingInfos;
+use crate::world::CompanyID;
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+/// Builds a road from the south border of the map and a farm along it
+fn farm_near_border(ctx: &mut TestCtx) -> CompanyID {
+    ctx.build_roads(&[vec3(256.0, 5.0, 0.0), vec3(256.0, 300.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(300.0, 200.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("vegetable-farm")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(290.0, 200.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+
+    let (id, _) = ctx.g.world().companies.iter().next().unwrap();
+    id
+}
+
+fn buy_cereal(ctx: &mut TestCtx, company: CompanyID, qty: u32) {
+    let soul = SoulID::GoodsCompany(company);
+    let building = ctx.g.read::<BuildingInfos>().building_owned_by(soul);
+    let door = ctx.g.map().buildings()[building.unwrap()].door_pos;
+    ctx.g
+        .write::<Market>()
+        .buy(soul, door.xy(), ItemID::new("cereal"), qty);
+}
+
+fn n_import_trucks(ctx: &TestCtx) -> usize {
+    let border = ctx.g.read::<BorderTrade>();
+    border.trucks.values().filter(|s| s.import).count()
+}
+
+#[test]
+fn no_connection_no_external_trade() {
+    let mut ctx = TestCtx::new();
+    let company = farm_near_border(&mut ctx);
+    assert_eq!(ctx.g.map().road_connections().count(), 0);
+
+    buy_cereal(&mut ctx, company, 10);
+    for _ in 0..10 {
+        ctx.tick();
+    }
+
+    let soul = SoulID::GoodsCompany(company);
+    let cereal = ItemID::new("cereal");
+    let market = ctx.g.read::<Market>();
+    assert!(market.inner()[&cereal].buy_order(soul).is_some());
+    assert_eq!(market.capital(soul, cereal), 0);
+    drop(market);
+    assert_eq!(n_import_trucks(&ctx), 0);
+}
+
+/// Makes the intersection of the road at the border a road connection
+fn connect_border(ctx: &mut TestCtx) {
+    let border = ctx
+        .g
+        .map()
+        .project(vec3(256.0, 5.0, 0.0), 5.0, ProjectFilter::INTER);
+    let ProjectKind::Inter(border) = border.kind else {
+        panic!("no intersection at the border");
+    };
+    ctx.apply(&[WorldCommand::MapSetRoadConnection {
+        inter: border,
+        connection: true,
+    }]);
+    assert!(ctx.g.map().is_road_connection(border));
+}
+
+#[test]
+fn one_truck_per_batch() {
+    let mut ctx = TestCtx::new();
+    let company = farm_near_border(&mut ctx);
+    connect_border(&mut ctx);
+
+    buy_cereal(&mut ctx, company, 10);
+    ctx.tick();
+    ctx.tick();
+
+    let soul = SoulID::GoodsCompany(company);
+    let cereal = ItemID::new("cereal");
+    assert_eq!(n_import_trucks(&ctx), 1);
+    // the goods are on their way
+    assert_eq!(ctx.g.read::<Market>().capital(soul, cereal), 0);
+    let truck = *ctx.g.read::<BorderTrade>().trucks.keys().next().unwrap();
+    assert!(ctx.g.world().vehicles.contains_key(truck));
+
+    buy_cereal(&mut ctx, company, 5);
+    ctx.tick();
+    ctx.tick();
+    assert_eq!(n_import_trucks(&ctx), 2);
+}
+
+#[test]
+fn importer_pays_the_tariff_to_the_government() {
+    let mut ctx = TestCtx::new();
+    let company = farm_near_border(&mut ctx);
+    connect_border(&mut ctx);
+    ctx.apply(&[WorldCommand::SetTradePolicy(TradePolicy {
+        global: Tariffs {
+            import: 1.0,
+            export: 0.0,
+        },
+        ..Default::default()
+    })]);
+
+    let balance = |ctx: &TestCtx| ctx.g.world().companies[company].finances.balance;
+    let balance_before = balance(&ctx);
+    let ledger_before = ctx.g.read::<Government>().ledger.today().clone();
+
+    buy_cereal(&mut ctx, company, 10);
+    ctx.tick();
+    ctx.tick();
+    assert_eq!(n_import_trucks(&ctx), 1);
+
+    let gvt = ctx.g.read::<Government>();
+    let tariff = gvt.tariff_income;
+    assert!(tariff > Money::ZERO);
+    // the company pays the value of the goods and the tariff, which is as much
+    assert_eq!(balance_before - balance(&ctx), tariff * 2);
+
+    // the government only gets the tariff
+    let today = gvt.ledger.today();
+    let cereal = ItemID::new("cereal");
+    assert_eq!(today.entries.get(&BudgetReason::Imports(cereal)), None);
+    assert_eq!(
+        today.entries.get(&BudgetReason::Tariffs).copied(),
+        Some(
+            ledger_before
+                .entries
+                .get(&BudgetReason::Tariffs)
+                .copied()
+                .unwrap_or_default()
+                + tariff
+        )
+    );
+}
+
+#[test]
+fn export_is_paid_when_the_truck_leaves() {
+    let mut ctx = TestCtx::new();
+    let company = farm_near_border(&mut ctx);
+    connect_border(&mut ctx);
+
+    let soul = SoulID::GoodsCompany(company);
+    let vegetable = ItemID::new("vegetable");
+    let building = ctx.g.read::<BuildingInfos>().building_owned_by(soul);
+    let door = ctx.g.map().buildings()[building.unwrap()].door_pos;
+    let mut market = ctx.g.write::<Market>();
+    market.produce(soul, vegetable, 10);
+    market.sell(soul, door.xy(), vegetable, 10, 0);
+    drop(market);
+
+    let balance = |ctx: &TestCtx| ctx.g.world().companies[company].finances.balance;
+    let balance_before = balance(&ctx);
+    ctx.tick();
+    ctx.tick();
+    assert_eq!(ctx.g.read::<Market>().capital(soul, vegetable), 0);
+
+    let mut last = balance(&ctx);
+    for _ in 0..20000 {
+        let border = ctx.g.read::<BorderTrade>();
+        let shipment = border
+            .trucks
+            .values()
+            .next()
+            .expect("the truck disappeared");
+        assert!(!shipment.import);
+        let (leg, earned) = (shipment.leg, shipment.earned);
+        drop(border);
+        assert!(earned > Money::ZERO);
+
+        let now = balance(&ctx);
+        if leg == TruckLeg::Leaving {
+            assert_eq!(now - last, earned);
+            return;
+        }
+        // the goods are not paid while they are still at the farm
+        assert!(now <= balance_before, "{} > {}", now, balance_before);
+        last = now;
+        ctx.tick_unchecked();
+    }
+    panic!("the truck never left the farm");
+}
+
+#[test]
+fn freight_station_trades_without_railway_to_the_outside() {
+    let mut ctx = TestCtx::new();
+    let company = farm_near_border(&mut ctx);
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(400.0, 200.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::RailFreightStation(FreightStationPrototypeID::new("freight-station")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(390.0, 200.0),
+        },
+        zone: None,
+        connected_road: None,
+    }]);
+    ctx.tick();
+    assert!(ctx.g.map().external_train_stations().is_empty());
+    let station = ctx
+        .g
+        .world()
+        .freight_stations
+        .values()
+        .next()
+        .unwrap()
+        .f
+        .building;
+
+    buy_cereal(&mut ctx, company, 10);
+    ctx.tick();
+    ctx.tick();
+
+    // the trains come from the edge of the map
+    assert_eq!(ctx.g.read::<FreightThroughput>().used(station), 10);
+    let market = ctx.g.read::<Market>();
+    let cereal = ItemID::new("cereal");
+    assert!(market.inner()[&cereal]
+        .buy_order(SoulID::GoodsCompany(company))
+        .is_none());
+}
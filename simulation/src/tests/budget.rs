@@ -0,0 +1,39 @@
+use prototypes::Money;
+
+use crate::economy::{BudgetReason, Government, MAX_LOAN};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn loan_charges_interest_every_day() {
+    let mut ctx = TestCtx::new();
+    ctx.tick();
+
+    let money = ctx.g.read::<Government>().money;
+    ctx.apply(&[WorldCommand::TakeLoan(Money::new_bucks(100_000))]);
+    {
+        let gvt = ctx.g.read::<Government>();
+        assert_eq!(gvt.loan, Money::new_bucks(100_000));
+        assert_eq!(gvt.money, money + Money::new_bucks(100_000));
+        assert_eq!(gvt.ledger.balance(), gvt.money);
+    }
+
+    ctx.skip_hours(24);
+    {
+        let gvt = ctx.g.read::<Government>();
+        let day = gvt.ledger.past().last().unwrap();
+        assert_eq!(day.entries[&BudgetReason::Loan], Money::new_bucks(100_000));
+        assert_eq!(
+            day.entries[&BudgetReason::LoanInterest],
+            Money::new_bucks(-100)
+        );
+        assert_eq!(gvt.ledger.balance(), gvt.money);
+    }
+
+    // cannot repay more than what is owed, nor borrow more than the maximum
+    ctx.apply(&[WorldCommand::RepayLoan(Money::new_bucks(200_000))]);
+    assert_eq!(ctx.g.read::<Government>().loan, Money::ZERO);
+    ctx.apply(&[WorldCommand::TakeLoan(MAX_LOAN + MAX_LOAN)]);
+    assert_eq!(ctx.g.read::<Government>().loan, MAX_LOAN);
+}
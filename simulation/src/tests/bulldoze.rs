@@ -0,0 +1,45 @@
+use geom::{vec2, vec3, Vec3, AABB};
+
+use crate::economy::Government;
+use crate::map::BulldozeFilter;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn filtered_bulldoze_only_removes_matching_kinds() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+
+    let area = AABB::new_ll_ur(vec2(-50.0, -100.0), vec2(250.0, 100.0));
+    let money_before = ctx.g.read::<Government>().money;
+
+    ctx.apply(&[WorldCommand::MapBulldozeArea {
+        area,
+        filter: BulldozeFilter {
+            roads: false,
+            rails: false,
+            buildings: true,
+            trees: false,
+        },
+    }]);
+
+    assert!(ctx.g.map().buildings().get(house).is_none());
+    assert_eq!(ctx.g.map().roads().len(), 1);
+    assert!(ctx.g.read::<Government>().money > money_before);
+
+    ctx.apply(&[WorldCommand::MapBulldozeArea {
+        area,
+        filter: BulldozeFilter {
+            roads: true,
+            rails: false,
+            buildings: false,
+            trees: false,
+        },
+    }]);
+
+    assert!(ctx.g.map().roads().is_empty());
+    assert!(ctx.g.map().intersections().is_empty());
+}
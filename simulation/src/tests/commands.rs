@@ -0,0 +1,187 @@
+use common::saveload::{Encoder, JSON};
+use geom::{vec2, vec3, Polygon, Vec2, Vec3};
+use prototypes::Money;
+
+use crate::gameplay::GameplayParams;
+use crate::map::{LanePatternBuilder, MapProject, ProjectKind};
+use crate::world_command::{CommandError, FailedCommands, WorldCommand};
+use crate::{Simulation, SimulationOptions};
+
+use super::TestCtx;
+
+/// Commands applied at the given tick
+type Batch = Vec<(u64, Vec<WorldCommand>)>;
+
+fn road(from: Vec3, to: Vec3) -> WorldCommand {
+    WorldCommand::MapMakeConnection {
+        from: MapProject {
+            pos: from,
+            kind: ProjectKind::Ground,
+        },
+        to: MapProject {
+            pos: to,
+            kind: ProjectKind::Ground,
+        },
+        inter: None,
+        pat: LanePatternBuilder::default().build(),
+    }
+}
+
+fn tick_record(ctx: &mut TestCtx, batch: &mut Batch, commands: Vec<WorldCommand>) {
+    batch.push((ctx.g.get_tick(), commands.clone()));
+    ctx.g.tick(&mut ctx.sched, &commands);
+}
+
+/// Records the commands of a player building a street, some of them refer to objects removed in
+/// the meantime and must fail
+fn record() -> (TestCtx, Batch) {
+    let mut ctx = TestCtx::new();
+    let mut batch = Batch::new();
+
+    tick_record(
+        &mut ctx,
+        &mut batch,
+        vec![
+            road(Vec3::ZERO, vec3(300.0, 0.0, 0.0)),
+            road(vec3(0.0, 150.0, 0.0), vec3(300.0, 150.0, 0.0)),
+        ],
+    );
+
+    for _ in 0..10 {
+        ctx.tick_unchecked();
+    }
+
+    let lot_near = |p: Vec2| {
+        ctx.g
+            .map()
+            .lots()
+            .values()
+            .min_by_key(|lot| lot.shape.center().distance2(p) as i32)
+            .unwrap()
+            .id
+    };
+    let lots = [lot_near(vec2(30.0, 20.0)), lot_near(vec2(250.0, 20.0))];
+    let second_road = ctx.g.map().roads().keys().nth(1).unwrap();
+    tick_record(
+        &mut ctx,
+        &mut batch,
+        vec![
+            WorldCommand::MapBuildHouse(lots[0]),
+            WorldCommand::MapBuildHouse(lots[1]),
+            WorldCommand::MapBuildHouse(lots[0]),
+            WorldCommand::MapRemoveRoad(second_road),
+            WorldCommand::MapFlipRoad(second_road),
+            WorldCommand::SetElectricityPrice(Money::new_cents(30)),
+        ],
+    );
+
+    for _ in 0..50 {
+        ctx.tick_unchecked();
+    }
+
+    (ctx, batch)
+}
+
+fn play(batch: &Batch, until: u64) -> TestCtx {
+    let mut ctx = TestCtx::new();
+    for (tick, commands) in batch {
+        while ctx.g.get_tick() < *tick {
+            ctx.tick_unchecked();
+        }
+        ctx.g.tick(&mut ctx.sched, commands);
+    }
+    while ctx.g.get_tick() < until {
+        ctx.tick_unchecked();
+    }
+    ctx
+}
+
+fn failed(sim: &Simulation) -> Vec<CommandError> {
+    sim.read::<FailedCommands>()
+        .since(&mut 0)
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn command_batch_is_deterministic() {
+    let (recorded, batch) = record();
+    let recorded = &recorded.g;
+    assert_eq!(
+        failed(recorded),
+        vec![CommandError::Missing("lot"), CommandError::Missing("road")]
+    );
+
+    // commands are sent over the network and saved in replays
+    let batch: Batch = JSON::decode(&JSON::encode(&batch).unwrap()).unwrap();
+
+    let first = play(&batch, recorded.get_tick());
+    let first = &first.g;
+    let second = play(&batch, recorded.get_tick());
+    let second = &second.g;
+
+    assert_eq!(first.get_tick(), recorded.get_tick());
+    assert_eq!(first.hashes(), second.hashes());
+    assert_eq!(first.hashes(), recorded.hashes());
+    assert_eq!(failed(first), failed(recorded));
+}
+
+#[test]
+fn landmark_routing_is_a_saved_option() {
+    let mut ctx = TestCtx::new();
+    assert!(ctx.g.map().landmark_routing);
+    ctx.apply(&[WorldCommand::SetLandmarkRouting(false)]);
+    assert!(!ctx.g.read::<SimulationOptions>().landmark_routing);
+    assert!(!ctx.g.map().landmark_routing);
+
+    // players joining or loading the game route the same way
+    let serialized = common::saveload::Bincode::encode(&ctx.g).unwrap();
+    let decoded: Simulation = common::saveload::Bincode::decode(&serialized).unwrap();
+    assert!(!decoded.map().landmark_routing);
+}
+
+#[test]
+fn unbounded_commands_are_rejected() {
+    let mut ctx = TestCtx::new();
+    let poisoned = GameplayParams {
+        price_multiplier: f32::NAN,
+        ..GameplayParams::default()
+    };
+    let negative = GameplayParams {
+        disaster_frequency: -1.0,
+        ..GameplayParams::default()
+    };
+
+    ctx.apply(&[
+        WorldCommand::SpawnRandomCars { n_cars: usize::MAX },
+        WorldCommand::MapPaintZone {
+            center: Vec2::ZERO,
+            radius: 1e9,
+            kind: None,
+        },
+        WorldCommand::MapRemoveTrees {
+            stroke: vec![Vec2::ZERO],
+            radius: f32::INFINITY,
+        },
+        WorldCommand::AddBusLine {
+            name: "a".repeat(10_000),
+        },
+        WorldCommand::MapAddDistrict {
+            polygon: Polygon(
+                (0..100_000)
+                    .map(|i| {
+                        let a = i as f32 * 0.001;
+                        vec2(a.cos(), a.sin()) * 100.0
+                    })
+                    .collect(),
+            ),
+            name: String::new(),
+        },
+        WorldCommand::SetGameplayParams(poisoned),
+        WorldCommand::SetGameplayParams(negative),
+    ]);
+
+    assert_eq!(failed(&ctx.g), vec![CommandError::Invalid; 7]);
+    assert!(ctx.g.map().districts.is_empty());
+    assert_eq!(*ctx.g.read::<GameplayParams>(), GameplayParams::default());
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+
+use geom::{vec3, Vec3};
+use prototypes::GameTime;
+
+use crate::map::{LaneID, PathKind, TraverseKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind};
+use crate::world::VehicleID;
+
+use super::TestCtx;
+
+/// Which of the two parallel roads the lane belongs to, north is true
+fn side(ctx: &TestCtx, lane: LaneID) -> Option<bool> {
+    let y = ctx.g.map().lanes().get(lane)?.points.middle().y;
+    (y.abs() > 10.0).then_some(y > 0.0)
+}
+
+fn spawn_driving_car(ctx: &mut TestCtx, end: Vec3) -> VehicleID {
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec3(-100.0, 0.0, 0.0)).unwrap();
+    unpark(&mut ctx.g, car);
+
+    let start = ctx.g.world().vehicles[car].trans.pos;
+    let tick = ctx.g.read::<GameTime>().tick;
+    let it = Itinerary::route(tick, start, end, &ctx.g.map(), PathKind::Vehicle).unwrap();
+    ctx.g.world_mut_unchecked().vehicles[car].it = it;
+    car
+}
+
+#[test]
+fn congestion_splits_traffic() {
+    let mut ctx = TestCtx::new();
+
+    // the northern road is shorter, so it is the only one used when traffic is free
+    ctx.build_roads(&[
+        vec3(-150.0, 0.0, 0.0),
+        Vec3::ZERO,
+        vec3(100.0, 25.0, 0.0),
+        vec3(200.0, 0.0, 0.0),
+        vec3(350.0, 0.0, 0.0),
+    ]);
+    ctx.build_roads(&[Vec3::ZERO, vec3(100.0, -60.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+
+    let end = vec3(330.0, 0.0, 0.0);
+
+    let first = spawn_driving_car(&mut ctx, end);
+    let route = ctx.g.world().vehicles[first].it.get_route().unwrap();
+    let sides: Vec<bool> = route
+        .reversed_route
+        .iter()
+        .filter_map(|t| match t.kind {
+            TraverseKind::Lane(id) => side(&ctx, id),
+            _ => None,
+        })
+        .collect();
+    assert!(!sides.is_empty());
+    assert!(sides.iter().all(|&north| north));
+
+    let mut cars = vec![first];
+    let mut north = BTreeSet::new();
+    let mut south = BTreeSet::new();
+
+    for i in 0..5000 {
+        if i % 20 == 0 && cars.len() < 60 {
+            cars.push(spawn_driving_car(&mut ctx, end));
+        }
+        ctx.tick();
+
+        for &car in &cars {
+            let Some(v) = ctx.g.world().vehicles.get(car) else {
+                continue;
+            };
+            let Some(TraverseKind::Lane(lane)) = v.it.get_travers().map(|t| t.kind) else {
+                continue;
+            };
+            match side(&ctx, lane) {
+                Some(true) => north.insert(car),
+                Some(false) => south.insert(car),
+                None => continue,
+            };
+        }
+    }
+
+    assert!(
+        north.len() >= 5,
+        "only {} cars took the north road",
+        north.len()
+    );
+    assert!(
+        south.len() >= 5,
+        "only {} cars took the south road",
+        south.len()
+    );
+}
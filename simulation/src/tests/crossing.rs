@@ -0,0 +1,65 @@
+use geom::{vec3, Vec3};
+use prototypes::GameTime;
+
+use crate::map::{Map, PathKind, RoadID, TraverseKind};
+use crate::map_dynamic::Itinerary;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+/// Points in the middle of the two sidewalks of the road
+fn sidewalks_middle(map: &Map, road: RoadID) -> (Vec3, Vec3) {
+    let (fw, bw) = map.roads()[road].crossing_sidewalks().unwrap();
+    let middle = |id| {
+        let points = &map.lanes()[id].points;
+        points.point_along(points.length() * 0.5)
+    };
+    (middle(fw), middle(bw))
+}
+
+fn walk_route(ctx: &TestCtx, start: Vec3, end: Vec3) -> Itinerary {
+    let tick = ctx.g.read::<GameTime>().tick;
+    Itinerary::route(tick, start, end, &ctx.g.map(), PathKind::Pedestrian).unwrap()
+}
+
+fn uses_crossing(it: &Itinerary) -> bool {
+    let route = it.get_route().unwrap();
+    std::iter::once(&route.cur)
+        .chain(&route.reversed_route)
+        .any(|t| matches!(t.kind, TraverseKind::Crossing(_)))
+}
+
+#[test]
+fn pedestrian_uses_new_crossing() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let (start, end) = sidewalks_middle(&ctx.g.map(), road);
+
+    // without a crossing, the pedestrian goes around the end of the road
+    assert!(!uses_crossing(&walk_route(&ctx, start, end)));
+
+    ctx.apply(&[WorldCommand::MapAddCrossing {
+        road,
+        pos: vec3(150.0, 0.0, 0.0),
+    }]);
+    assert_eq!(ctx.g.map().roads()[road].crossings.len(), 1);
+
+    let mut it = walk_route(&ctx, start, end);
+    assert!(uses_crossing(&it));
+
+    // walking the route stays close to the crossing instead of going around
+    let map = ctx.g.map();
+    let mut pos = start;
+    for _ in 0..200 {
+        pos = it.update(pos, 1.0, 0, &map);
+        assert!((pos.x - 150.0).abs() < 20.0, "{:?}", pos);
+        if it.is_terminal() && pos.is_close(end, 1.0) {
+            return;
+        }
+    }
+
+    panic!("pedestrian did not arrive, at {:?}", pos);
+}
@@ -0,0 +1,226 @@
+use geom::{vec2, vec3, Transform, Vec3};
+use prototypes::{GameTime, DELTA};
+
+use crate::map::{Map, PathKind};
+use crate::map_dynamic::{Destination, Itinerary};
+use crate::souls::human::{spawn_human, HumanDecisionKind};
+use crate::transportation::{
+    put_pedestrian_in_transport_grid, sidestep, Location, TransportGrid, Transporter, PED_RADIUS,
+};
+
+use super::TestCtx;
+
+#[derive(Copy, Clone)]
+struct Corridor {
+    n_walkers: usize,
+    /// Length of the sidewalk used as a corridor
+    length: f32,
+    /// How far each walker goes, the two streams fully cross each other
+    walk_length: f32,
+}
+
+impl Corridor {
+    fn build(self) -> TestCtx {
+        let ctx = TestCtx::new();
+        ctx.build_roads(&[Vec3::ZERO, Vec3::x(self.length)]);
+        ctx
+    }
+}
+
+struct Walker {
+    collider: Transporter,
+    trans: Transform,
+    it: Itinerary,
+    speed: f32,
+    arrived: bool,
+}
+
+/// Half of the walkers go down the sidewalk and the other half come up, one meter apart
+fn walkers(ctx: &TestCtx, corridor: Corridor, grid: &mut TransportGrid) -> Vec<Walker> {
+    let map = ctx.g.map();
+    let tick = ctx.g.read::<GameTime>().tick;
+    let road = map.roads().keys().next().unwrap();
+    let (sidewalk, _) = map.roads()[road].crossing_sidewalks().unwrap();
+    let points = &map.lanes()[sidewalk].points;
+
+    (0..corridor.n_walkers)
+        .map(|i| {
+            let along = 5.0 + (i / 2) as f32;
+            let (start, end) = if i % 2 == 0 {
+                (along, along + corridor.walk_length)
+            } else {
+                (
+                    corridor.length - along,
+                    corridor.length - along - corridor.walk_length,
+                )
+            };
+            let start = points.point_along(start);
+            let end = points.point_along(end);
+            let it = Itinerary::route(tick, start, end, &map, PathKind::Pedestrian).unwrap();
+            let dir = (end - start).normalize();
+
+            Walker {
+                collider: put_pedestrian_in_transport_grid(grid, start),
+                trans: Transform::new_dir(start, dir),
+                it,
+                speed: 0.8 + (i * 37 % 80) as f32 / 100.0,
+                arrived: false,
+            }
+        })
+        .collect()
+}
+
+fn sync_grid(grid: &mut TransportGrid, walkers: &[Walker]) {
+    for w in walkers.iter().filter(|w| !w.arrived) {
+        grid.set_position(w.collider.0, w.trans.pos.xy());
+        let (_, state) = grid.get_mut(w.collider.0).unwrap();
+        state.dir = w.trans.dir.xy();
+        state.speed = w.speed;
+    }
+    grid.maintain();
+}
+
+/// Walks everyone through the corridor, returns the average overlap between pedestrians
+/// in meters per walker and per tick
+fn walk_corridor(ctx: &TestCtx, corridor: Corridor, avoidance: bool) -> f32 {
+    let map: &Map = &ctx.g.map();
+    let mut grid = TransportGrid::new(100);
+    let mut walkers = walkers(ctx, corridor, &mut grid);
+    sync_grid(&mut grid, &walkers);
+
+    let mut total_overlap = 0.0;
+    let mut samples = 0;
+    for _ in 0..12000 {
+        if walkers.iter().all(|w| w.arrived) {
+            break;
+        }
+
+        let sidesteps: Vec<Vec3> = walkers
+            .iter()
+            .map(|w| {
+                if !avoidance || w.arrived {
+                    return Vec3::ZERO;
+                }
+                sidestep(&grid, map, w.collider, &w.trans, &w.it, w.speed)
+            })
+            .collect();
+
+        for (w, step) in walkers.iter_mut().zip(sidesteps) {
+            if w.arrived {
+                continue;
+            }
+            w.trans.pos += step;
+            w.trans.pos = w.it.update(w.trans.pos, w.speed * DELTA, 0, map);
+            if let Some(dir) =
+                w.it.get_point()
+                    .and_then(|p| (p - w.trans.pos).try_normalize())
+            {
+                w.trans.dir = dir;
+            }
+            if w.it.get_point().is_none() {
+                w.arrived = true;
+                grid.remove_maintain(w.collider.0);
+            }
+        }
+        sync_grid(&mut grid, &walkers);
+
+        for w in walkers.iter().filter(|w| !w.arrived) {
+            let pos = w.trans.pos.xy();
+            total_overlap += grid
+                .query_around(pos, 2.0 * PED_RADIUS)
+                .filter(|&(h, _)| h != w.collider.0)
+                .map(|(_, p)| (2.0 * PED_RADIUS - p.distance(pos)).max(0.0))
+                .sum::<f32>();
+            samples += 1;
+        }
+    }
+
+    assert!(
+        walkers.iter().all(|w| w.arrived),
+        "everyone should have gone through the corridor"
+    );
+    total_overlap / samples as f32
+}
+
+/// Walks the corridor without and with avoidance, the overlap must drop below `ratio` of the
+/// one without
+fn assert_avoidance_reduces_overlap(corridor: Corridor, ratio: f32) {
+    let ctx = corridor.build();
+
+    let without = walk_corridor(&ctx, corridor, false);
+    let with = walk_corridor(&ctx, corridor, true);
+
+    // the two streams go through each other when nobody steps aside
+    assert!(without > 0.0);
+    assert!(
+        with < without * ratio,
+        "overlap with avoidance {with} should be well below {without}"
+    );
+}
+
+#[test]
+fn crowd_avoidance_reduces_overlap() {
+    assert_avoidance_reduces_overlap(
+        Corridor {
+            n_walkers: 40,
+            length: 100.0,
+            walk_length: 50.0,
+        },
+        0.5,
+    );
+}
+
+/// cargo test --release -p simulation crowd_avoidance_long_corridor -- --ignored
+#[test]
+#[ignore]
+fn crowd_avoidance_long_corridor() {
+    assert_avoidance_reduces_overlap(
+        Corridor {
+            n_walkers: 200,
+            length: 300.0,
+            walk_length: 150.0,
+        },
+        0.5,
+    );
+}
+
+/// Two neighbours walk to each other's door on the same sidewalk, moved by the simulation systems
+#[test]
+fn neighbours_step_aside_when_crossing() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let houses = [60.0, 110.0].map(|x| ctx.build_house_near(vec2(x, 20.0)));
+    ctx.tick();
+
+    let walkers = houses.map(|house| spawn_human(&mut ctx.g, house).unwrap());
+    let doors = houses.map(|house| ctx.g.map().buildings()[house].door_pos);
+    for (&human, &dest) in walkers.iter().zip(doors.iter().rev()) {
+        let h = ctx.g.world.humans.get_mut(human).unwrap();
+        h.router.personal_car = None;
+        h.router.use_vehicle(None);
+        h.decision.kind = HumanDecisionKind::GoTo(Destination::Outside(dest));
+        h.decision.wait = 0;
+    }
+
+    let mut closest = f32::INFINITY;
+    for _ in 0..3000 {
+        ctx.tick_unchecked();
+        let [a, b] = walkers.map(|id| &ctx.g.world.humans[id]);
+        if a.location == Location::Outside && b.location == Location::Outside {
+            closest = closest.min(a.trans.pos.xy().distance(b.trans.pos.xy()));
+        }
+    }
+    // the sidesteps are applied in the same order after a reload
+    ctx.tick();
+
+    assert!(
+        closest < 2.0,
+        "the two should have crossed, closest: {closest}"
+    );
+    // following the sidewalk, they would have walked through each other
+    assert!(
+        closest > PED_RADIUS * 0.5,
+        "nobody stepped aside, closest: {closest}"
+    );
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use geom::{vec2, vec3, Vec3};
+
+use crate::economy::{JobMarket, Market};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::demographics::demographics;
+use crate::souls::happiness::CityStats;
+use crate::SoulID;
+
+use super::TestCtx;
+
+#[test]
+fn town_survives_a_century() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    let houses: Vec<_> = (0..5)
+        .map(|i| ctx.build_house_near(vec2(30.0 + 50.0 * i as f32, 20.0)))
+        .collect();
+    ctx.tick();
+    ctx.tick();
+
+    let max_population = houses.len() * demographics().max_household as usize;
+    // personal car of every citizen ever seen
+    let mut seen = BTreeMap::new();
+
+    for _ in 0..100 {
+        ctx.skip_year();
+
+        let world = ctx.g.world();
+        let population = world.humans.len();
+        assert!(population > 0, "the town died out");
+        assert!(population <= max_population, "{} citizens", population);
+
+        for (id, h) in world.humans.iter() {
+            seen.insert(id, h.router.personal_car);
+        }
+
+        let exists = |soul: &SoulID| match soul {
+            SoulID::Human(h) => world.humans.contains_key(*h),
+            _ => true,
+        };
+
+        let binfos = ctx.g.read::<BuildingInfos>();
+        for &house in &houses {
+            let Some(info) = binfos.get(house) else {
+                continue;
+            };
+            assert!(info.owner.iter().all(exists), "{:?}", info.owner);
+            assert!(info.inside.iter().all(exists), "{:?}", info.inside);
+        }
+
+        for (worker, _) in ctx.g.read::<JobMarket>().contracts() {
+            assert!(world.humans.contains_key(worker));
+        }
+
+        let market = ctx.g.read::<Market>();
+        for (&id, &car) in &seen {
+            if world.humans.contains_key(id) {
+                continue;
+            }
+            let soul = SoulID::Human(id);
+            assert!(market.iter().all(|(_, m)| m.buy_order(soul).is_none()));
+            assert!(binfos.building_owned_by(soul).is_none());
+            if let Some(car) = car {
+                assert!(
+                    !world.vehicles.contains_key(car),
+                    "the car of {:?} was left",
+                    id
+                );
+            }
+        }
+    }
+
+    let stats = ctx.g.read::<CityStats>();
+    assert!(stats.births > 0);
+    assert!(stats.deaths > 0);
+    assert_eq!(
+        stats.age_histogram().iter().sum::<u32>() as usize,
+        ctx.g.world().humans.len()
+    );
+}
@@ -0,0 +1,94 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{BuildingGen, GoodsCompanyID, SchoolPrototypeID};
+
+use crate::economy::JobMarket;
+use crate::map::BuildingID;
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::demographics::demographics;
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+fn build_special(ctx: &mut TestCtx, x: f32, kind: BuildingKind) {
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(x, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind,
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(x, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+}
+
+/// Workers of the company in the building, companies going bankrupt are replaced by new ones
+fn n_workers(ctx: &TestCtx, building: BuildingID) -> usize {
+    let Some(SoulID::GoodsCompany(company)) = ctx.g.read::<BuildingInfos>().owner(building) else {
+        return 0;
+    };
+    ctx.g
+        .read::<JobMarket>()
+        .contracts()
+        .filter(|(_, c)| c.company == company)
+        .count()
+}
+
+#[test]
+fn skilled_jobs_need_a_school() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(400.0, 0.0, 0.0)]);
+    for i in 0..5 {
+        ctx.build_house_near(vec2(30.0 + 40.0 * i as f32, 20.0));
+    }
+    build_special(
+        &mut ctx,
+        50.0,
+        BuildingKind::GoodsCompany(GoodsCompanyID::new("solar-panel")),
+    );
+    build_special(
+        &mut ctx,
+        200.0,
+        BuildingKind::GoodsCompany(GoodsCompanyID::new("high-tech-facility")),
+    );
+    ctx.tick();
+
+    let facility = ctx
+        .g
+        .map()
+        .buildings()
+        .iter()
+        .find_map(|(id, b)| {
+            (b.kind == BuildingKind::GoodsCompany(GoodsCompanyID::new("high-tech-facility")))
+                .then_some(id)
+        })
+        .unwrap();
+    assert!(matches!(
+        ctx.g.read::<BuildingInfos>().owner(facility),
+        Some(SoulID::GoodsCompany(_))
+    ));
+
+    // newcomers are not educated enough and there is nowhere to learn
+    for _ in 0..30 {
+        ctx.skip_year();
+        assert_eq!(n_workers(&ctx, facility), 0);
+    }
+
+    build_special(
+        &mut ctx,
+        350.0,
+        BuildingKind::School(SchoolPrototypeID::new("school")),
+    );
+
+    let mut hired = false;
+    for _ in 0..40 {
+        ctx.skip_year();
+        hired |= n_workers(&ctx, facility) > 0;
+    }
+    assert!(
+        hired,
+        "children educated at the school should have been hired"
+    );
+}
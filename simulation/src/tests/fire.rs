@@ -0,0 +1,82 @@
+use geom::{vec2, vec3, Vec3};
+use prototypes::GameTime;
+
+use crate::map_dynamic::{BuildingInfos, Fires, FIRE_SPREAD_RADIUS};
+
+use super::TestCtx;
+
+#[test]
+fn unattended_fire_spreads_then_burns_down() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    let neighbor = ctx.build_house_near(vec2(100.0 + FIRE_SPREAD_RADIUS * 0.5, 20.0));
+    assert_ne!(house, neighbor);
+    ctx.tick();
+
+    let now = ctx.g.read::<GameTime>().instant();
+    ctx.g.write::<Fires>().ignite(house, now);
+
+    ctx.skip_hours(3);
+    {
+        let fires = ctx.g.read::<Fires>();
+        assert!(fires.is_burning(house));
+        assert!(fires.is_burning(neighbor));
+    }
+
+    ctx.skip_hours(6);
+    let fires = ctx.g.read::<Fires>();
+    assert!(fires.is_ruin(house));
+    assert!(!fires.is_burning(house));
+    assert!(fires.is_out_of_service(neighbor));
+}
+
+#[test]
+fn firefighters_on_scene_put_out_the_fire() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    ctx.tick();
+
+    let now = ctx.g.read::<GameTime>().instant();
+    ctx.g.write::<Fires>().ignite(house, now);
+    ctx.g.write::<Fires>().arrive(house, now);
+
+    ctx.skip_hours(1);
+    let fires = ctx.g.read::<Fires>();
+    assert!(!fires.is_out_of_service(house));
+}
+
+#[test]
+fn residents_leave_burning_and_ruined_houses() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    ctx.tick();
+    assert!(ctx.g.world().humans.values().any(|h| h.home.house == house));
+
+    let now = ctx.g.read::<GameTime>().instant();
+    ctx.g.write::<Fires>().ignite(house, now);
+
+    // everyone inside gets out instead of staying home
+    for _ in 0..300 {
+        ctx.tick_unchecked();
+    }
+    ctx.tick();
+    assert!(ctx
+        .g
+        .read::<BuildingInfos>()
+        .get(house)
+        .unwrap()
+        .inside
+        .is_empty());
+
+    ctx.skip_hours(9);
+    ctx.tick();
+    assert!(ctx.g.read::<Fires>().is_ruin(house));
+    assert!(ctx.g.world().humans.values().all(|h| h.home.house != house));
+    assert_eq!(ctx.g.read::<BuildingInfos>().owner(house), None);
+}
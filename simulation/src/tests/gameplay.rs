@@ -0,0 +1,117 @@
+use prototypes::Money;
+
+use crate::economy::{Government, Market};
+use crate::gameplay::{Cheats, Difficulty, GameplayParams};
+use crate::map::BuildingKind;
+use crate::saves::delete_save;
+use crate::world_command::WorldCommand;
+use crate::{SaveMetadata, Simulation, SimulationOptions};
+
+use super::TestCtx;
+
+fn new_sim(params: GameplayParams) -> TestCtx {
+    TestCtx::with_options(SimulationOptions {
+        terrain_size: 1,
+        save_replay: false,
+        params,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn new_game_uses_the_difficulty() {
+    let hard = GameplayParams::preset(Difficulty::Hard);
+    let ctx = new_sim(hard);
+    let sim = &ctx.g;
+
+    assert_eq!(*sim.read::<GameplayParams>(), hard);
+    // the start commands might have built something
+    let gvt = sim.read::<Government>();
+    assert_eq!(gvt.money + gvt.construction_spending, hard.starting_money);
+    drop(gvt);
+    assert_eq!(
+        sim.read::<Market>().price_multiplier(),
+        hard.price_multiplier
+    );
+
+    let house = WorldCommand::MapBuildHouse(Default::default());
+    assert_eq!(
+        Government::action_cost(&house, sim),
+        Government::building_price(BuildingKind::House) * 1.5
+    );
+
+    let easy = new_sim(GameplayParams::preset(Difficulty::Easy));
+    let easy = &easy.g;
+    assert!(easy.read::<Government>().money > sim.read::<Government>().money);
+    assert!(
+        easy.read::<Market>()
+            .inner()
+            .values()
+            .map(|m| m.ext_value)
+            .sum::<Money>()
+            > sim
+                .read::<Market>()
+                .inner()
+                .values()
+                .map(|m| m.ext_value)
+                .sum::<Money>()
+    );
+}
+
+#[test]
+fn params_are_restored_from_the_save() {
+    let name = "test gameplay params";
+    let _ = delete_save(name);
+
+    let params = GameplayParams {
+        difficulty: Difficulty::Custom,
+        price_multiplier: 1.5,
+        starting_money: Money::new_bucks(42_000),
+        building_cost_multiplier: 0.5,
+        external_trade_margin: 0.2,
+        disaster_frequency: 0.0,
+        sandbox: false,
+    };
+    let ctx = new_sim(params);
+    let sim = &ctx.g;
+    sim.save_named(&sim.metadata(name, "", 0.0)).unwrap();
+
+    let meta = SaveMetadata::load(name).unwrap();
+    assert_eq!(meta.difficulty, Difficulty::Custom);
+    assert!(!meta.sandbox);
+
+    let mut loaded = Simulation::load_named(name).unwrap();
+    assert_eq!(*loaded.read::<GameplayParams>(), params);
+    assert_eq!(loaded.read::<Market>().price_multiplier(), 1.5);
+    assert_eq!(
+        loaded.read::<SimulationOptions>().params,
+        sim.read::<SimulationOptions>().params
+    );
+
+    // changing the params mid-game marks the save as sandbox
+    let mut sched = Simulation::schedule();
+    let normal = GameplayParams::default();
+    loaded.tick(&mut sched, &[WorldCommand::SetGameplayParams(normal)]);
+    assert!(loaded.read::<GameplayParams>().sandbox);
+    assert_eq!(loaded.read::<Market>().price_multiplier(), 1.25);
+    assert!(loaded.metadata(name, "", 0.0).sandbox);
+
+    delete_save(name).unwrap();
+}
+
+#[test]
+fn cheats_mark_the_save() {
+    let mut ctx = new_sim(GameplayParams::default());
+    let sim = &mut ctx.g;
+    let mut sched = Simulation::schedule();
+    assert!(!sim.metadata("cheats", "", 0.0).cheats_used);
+
+    let money = sim.read::<Government>().money;
+    sim.tick(
+        &mut sched,
+        &[WorldCommand::AddMoney(Money::new_bucks(10_000))],
+    );
+    assert!(sim.read::<Government>().money > money);
+    assert!(sim.read::<Cheats>().used);
+    assert!(sim.metadata("cheats", "", 0.0).cheats_used);
+}
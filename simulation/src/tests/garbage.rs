@@ -0,0 +1,84 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{GameTime, GoodsCompanyID, Tick, TICKS_PER_HOUR};
+
+use crate::map::BuildingID;
+use crate::map_dynamic::{BuildingInfos, Garbage, GARBAGE_THRESHOLD};
+use crate::souls::happiness::HappinessFactor;
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+/// Three houses along a road, their families move in
+fn three_houses(ctx: &mut TestCtx) -> [BuildingID; 3] {
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    let houses = [60.0, 120.0, 180.0].map(|x| ctx.build_house_near(vec2(x, 20.0)));
+    ctx.tick();
+    ctx.tick();
+    houses
+}
+
+#[test]
+fn single_truck_keeps_three_houses_clean() {
+    let mut ctx = TestCtx::new();
+    let houses = three_houses(&mut ctx);
+
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let landfill = GoodsCompanyID::new("landfill");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(250.0, -40.0), vec2(1.0, 0.0), 40.0, 40.0),
+        kind: BuildingKind::GoodsCompany(landfill),
+        gen: landfill.prototype().bgen,
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+
+    // the residents are hired at the landfill and everyone is at work by 9h30
+    let tick = ctx.g.read::<GameTime>().tick.0 + TICKS_PER_HOUR * 3 / 2;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick));
+    for &house in &houses {
+        ctx.g
+            .write::<Garbage>()
+            .produce(house, 3.0 * GARBAGE_THRESHOLD);
+    }
+
+    // the truck drives to each house in turn, within the work day
+    for _ in 0..40000 {
+        ctx.tick_unchecked();
+
+        let garbage = ctx.g.read::<Garbage>();
+        if houses.iter().all(|&h| !garbage.is_dirty(h)) {
+            drop(garbage);
+            ctx.tick();
+            return;
+        }
+    }
+    let garbage = ctx.g.read::<Garbage>();
+    panic!(
+        "the truck should have emptied the three houses, left: {:?}",
+        houses.map(|h| garbage.level(h))
+    );
+}
+
+#[test]
+fn dirty_house_makes_residents_unhappy() {
+    let mut ctx = TestCtx::new();
+    let [house, ..] = three_houses(&mut ctx);
+
+    let Some(SoulID::Human(resident)) = ctx.g.read::<BuildingInfos>().owner(house) else {
+        panic!("a family should have moved in");
+    };
+
+    ctx.skip_hours(1);
+    let clean = ctx.g.world().humans[resident]
+        .happiness
+        .score(HappinessFactor::Utilities);
+
+    ctx.g
+        .write::<Garbage>()
+        .produce(house, 2.0 * GARBAGE_THRESHOLD);
+    ctx.skip_hours(1);
+
+    let h = &ctx.g.world().humans[resident].happiness;
+    assert!(h.score(HappinessFactor::Utilities) < clean);
+}
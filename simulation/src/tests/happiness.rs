@@ -0,0 +1,61 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{BuildingGen, GoodsCompanyID};
+
+use crate::map_dynamic::{BuildingInfos, ElectricityFlow};
+use crate::souls::happiness::{CityStats, HappinessFactor};
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+#[test]
+fn power_cut_makes_residents_unhappy() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(50.0, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("solar-panel")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(50.0, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+    ctx.tick();
+
+    let Some(SoulID::Human(resident)) = ctx.g.read::<BuildingInfos>().owner(house) else {
+        panic!("a family should have moved in");
+    };
+    let solar = ctx
+        .g
+        .map()
+        .buildings()
+        .iter()
+        .find(|(_, b)| matches!(b.kind, BuildingKind::GoodsCompany(_)))
+        .unwrap()
+        .0;
+
+    ctx.skip_hours(1);
+    assert!(!ctx.g.read::<ElectricityFlow>().is_shed(house));
+    let powered = ctx
+        .g
+        .world()
+        .humans
+        .get(resident)
+        .unwrap()
+        .happiness
+        .value();
+
+    ctx.apply(&[WorldCommand::MapRemoveBuilding(solar)]);
+    ctx.tick();
+    assert!(ctx.g.read::<ElectricityFlow>().is_shed(house));
+
+    ctx.skip_hours(2);
+    let h = &ctx.g.world().humans.get(resident).unwrap().happiness;
+    assert!(h.score(HappinessFactor::Utilities) < 1.0);
+    assert!(h.value() < powered);
+    assert!(ctx.g.read::<CityStats>().mean < powered);
+}
@@ -0,0 +1,60 @@
+use geom::{vec2, vec3, Vec2, OBB};
+use prototypes::LeisurePrototypeID;
+
+use crate::map::{BuildingKind, LanePatternBuilder, ProjectFilter, LANE_SPEED_SAMPLE_TICKS};
+use crate::map_dynamic::LandValue;
+use crate::WorldCommand;
+
+use super::TestCtx;
+
+fn value_at(ctx: &TestCtx, pos: Vec2) -> f32 {
+    ctx.g.read::<LandValue>().land_value(pos)
+}
+
+fn tick_until_noise_update(ctx: &mut TestCtx) {
+    for _ in 0..LANE_SPEED_SAMPLE_TICKS {
+        ctx.tick();
+    }
+}
+
+#[test]
+fn park_raises_and_highway_lowers_land_value() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[vec3(20.0, 100.0, 0.0), vec3(480.0, 100.0, 0.0)]);
+    tick_until_noise_update(&mut ctx);
+
+    let home = vec2(250.0, 120.0);
+    let initial = value_at(&ctx, home);
+    assert!(initial > 0.0);
+    assert_eq!(value_at(&ctx, vec2(250.0, 400.0)), 0.0);
+
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let park = LeisurePrototypeID::new("park");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(250.0, 70.0), vec2(1.0, 0.0), 40.0, 40.0),
+        kind: BuildingKind::Leisure(park),
+        gen: park.prototype().bgen,
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+
+    let with_park = value_at(&ctx, home);
+    assert!(with_park > initial + 0.1);
+
+    {
+        let mut m = ctx.g.map_mut();
+        let a = m.project(vec3(20.0, 150.0, 0.0), 0.0, ProjectFilter::ALL);
+        let b = m.project(vec3(480.0, 150.0, 0.0), 0.0, ProjectFilter::ALL);
+        let highway = LanePatternBuilder::new()
+            .n_lanes(3)
+            .speed_limit(25.0)
+            .parking(false)
+            .build();
+        m.make_connection(a, b, None, &highway);
+    }
+    tick_until_noise_update(&mut ctx);
+
+    assert!(value_at(&ctx, home) < with_park - 0.1);
+}
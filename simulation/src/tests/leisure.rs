@@ -0,0 +1,70 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{GameTime, LeisurePrototypeID, Tick, TICKS_PER_HOUR};
+
+use crate::souls::desire::LeisureVisitors;
+use crate::souls::human::spawn_human;
+use crate::transportation::Location;
+use crate::{BuildingKind, WorldCommand};
+
+use super::TestCtx;
+
+#[test]
+fn citizens_visit_the_park_every_week() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(60.0, 20.0));
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let park = LeisurePrototypeID::new("park");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(130.0, 35.0), vec2(1.0, 0.0), 30.0, 30.0),
+        kind: BuildingKind::Leisure(park),
+        gen: park.prototype().bgen,
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    let park_id = ctx
+        .g
+        .map()
+        .buildings()
+        .iter()
+        .find(|(_, b)| matches!(b.kind, BuildingKind::Leisure(_)))
+        .unwrap()
+        .0;
+
+    // the game starts on the first day at 8h, the fifth day is the start of the weekend
+    let tick = (4 * 24 + 6) * TICKS_PER_HOUR;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick));
+    assert!(ctx.g.read::<GameTime>().daytime.is_weekend());
+
+    let human = spawn_human(&mut ctx.g, house).unwrap();
+    {
+        let h = ctx.g.world.humans.get_mut(human).unwrap();
+        // walking is enough to get to the park next door
+        h.router.personal_car = None;
+        h.router.use_vehicle(None);
+        h.decision.wait = 0;
+    }
+
+    for _ in 0..6000 {
+        ctx.tick();
+
+        let time = *ctx.g.read::<GameTime>();
+        let h = &ctx.g.world.humans[human];
+        if h.leisure.visits_last_week(&time) == 0 {
+            continue;
+        }
+
+        assert_eq!(h.leisure.visiting(), Some(park_id));
+        // visitors walk around the park instead of going inside
+        assert_eq!(h.location, Location::Outside);
+        let map = ctx.g.map();
+        assert!(map.buildings()[park_id].obb.contains(h.trans.pos.xy()));
+        drop(map);
+
+        ctx.tick();
+        assert!(ctx.g.read::<LeisureVisitors>().visitors(park_id) >= 1);
+        return;
+    }
+    panic!("no visit to the park during the weekend");
+}
@@ -0,0 +1,136 @@
+use common::saveload::{Bincode, Encoder};
+use geom::{vec2, Vec2};
+use prototypes::Tick;
+
+use crate::map::{BuildingKind, Environment, TerraformKind, Tree, TERRAIN_CHUNK_RESOLUTION};
+use crate::map_files::{list_maps, load_map, save_map, MIN_MAP_SIZE};
+use crate::world_command::WorldCommand;
+use crate::{Simulation, SimulationOptions};
+
+use super::TestCtx;
+
+const HILL: Vec2 = vec2(1000.0, 1000.0);
+const LAKE: Vec2 = vec2(2500.0, 2500.0);
+
+type Heights = Vec<[[u16; TERRAIN_CHUNK_RESOLUTION]; TERRAIN_CHUNK_RESOLUTION]>;
+
+fn heights(terrain: &Environment) -> Heights {
+    terrain.chunks().map(|(_, c)| *c.heights()).collect()
+}
+
+fn n_trees(terrain: &Environment) -> usize {
+    let bounds = terrain.bounds();
+    terrain.trees.query(bounds.ll, bounds.ur).count()
+}
+
+/// A flat terrain with a hill, a lake and a few trees
+fn edited_terrain() -> Environment {
+    let mut terrain = Environment::flat(MIN_MAP_SIZE, MIN_MAP_SIZE);
+    terrain.terraform(
+        Tick(1),
+        TerraformKind::Elevation,
+        HILL,
+        300.0,
+        5000.0,
+        0.0,
+        None,
+    );
+    terrain.terraform(
+        Tick(2),
+        TerraformKind::Water,
+        LAKE,
+        300.0,
+        5000.0,
+        -10.0,
+        None,
+    );
+    terrain.add_trees(
+        (0..10).map(|i| Tree::new(vec2(200.0 + i as f32 * 20.0, 200.0))),
+        |_| {},
+    );
+    terrain
+}
+
+fn terraform(center: Vec2, amount: f32) -> WorldCommand {
+    WorldCommand::Terraform {
+        kind: TerraformKind::Elevation,
+        center,
+        radius: 100.0,
+        amount,
+        level: 0.0,
+        slope: None,
+    }
+}
+
+#[test]
+fn edited_terrain_is_encoded_exactly() {
+    let terrain = edited_terrain();
+    assert!(terrain.true_height(HILL).unwrap() > 10.0);
+    let lake = terrain.true_height(LAKE).unwrap();
+    assert!(lake < 0.0 && lake >= -10.1, "lake depth {}", lake);
+
+    let decoded: Environment = Bincode::decode(&Bincode::encode(&terrain).unwrap()).unwrap();
+    assert_eq!(decoded.size(), terrain.size());
+    assert!(heights(&decoded) == heights(&terrain));
+    assert_eq!(n_trees(&decoded), 10);
+}
+
+#[test]
+fn map_file_roundtrip() {
+    let _ = TestCtx::new();
+    let name = "test edited map";
+    let terrain = edited_terrain();
+
+    save_map(name, &terrain).unwrap();
+    assert!(list_maps().iter().any(|m| m == name));
+    let loaded = load_map(name).unwrap();
+    assert!(heights(&loaded) == heights(&terrain));
+    assert_eq!(n_trees(&loaded), 10);
+
+    // a new game keeps the terrain and gets its railway
+    let sim = Simulation::new_on_map(SimulationOptions::default(), loaded);
+    assert_eq!(
+        sim.map().environment.true_height(LAKE),
+        terrain.true_height(LAKE)
+    );
+    assert!(sim
+        .map()
+        .buildings()
+        .values()
+        .any(|b| b.kind == BuildingKind::ExternalTrading));
+
+    assert!(save_map("../outside", &terrain).is_err());
+    std::fs::remove_file(format!("world/maps/{name}.zip")).unwrap();
+    assert!(load_map(name).is_err());
+}
+
+#[test]
+fn terraform_stroke_is_undone_at_once() {
+    let mut ctx = TestCtx::new();
+    let original = heights(&ctx.g.map().environment);
+
+    // the dabs of a stroke overlap and spill over the edge of the map
+    ctx.apply(&[
+        terraform(vec2(150.0, 150.0), 3000.0),
+        terraform(vec2(220.0, 180.0), 3000.0),
+        terraform(vec2(480.0, 400.0), 3000.0),
+        WorldCommand::MapEndTerraform,
+    ]);
+    let first = heights(&ctx.g.map().environment);
+    assert!(first != original);
+
+    ctx.apply(&[
+        terraform(vec2(300.0, 300.0), -3000.0),
+        WorldCommand::MapEndTerraform,
+    ]);
+    let second = heights(&ctx.g.map().environment);
+    assert!(second != first);
+
+    ctx.apply(&[WorldCommand::MapUndo]);
+    assert!(heights(&ctx.g.map().environment) == first);
+    ctx.apply(&[WorldCommand::MapUndo]);
+    assert!(heights(&ctx.g.map().environment) == original);
+    ctx.apply(&[WorldCommand::MapRedo]);
+    assert!(heights(&ctx.g.map().environment) == first);
+    ctx.tick();
+}
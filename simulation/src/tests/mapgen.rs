@@ -0,0 +1,50 @@
+use crate::map::procgen::{MapGen, MapGenParams};
+use crate::map::Environment;
+
+const SIZE: u16 = 8;
+
+fn params(seed: u32) -> MapGenParams {
+    MapGenParams {
+        seed,
+        mountains: 0.5,
+        forests: 1.5,
+        rivers: 2,
+    }
+}
+
+/// Hash of the heights of every chunk
+fn heights_hash(terrain: &Environment) -> u64 {
+    common::hash_u64(
+        terrain
+            .chunks()
+            .map(|(_, c)| *c.heights())
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn n_trees(terrain: &Environment) -> usize {
+    let bounds = terrain.bounds();
+    terrain.trees.query(bounds.ll, bounds.ur).count()
+}
+
+#[test]
+fn same_params_generate_same_map() {
+    let a = Environment::generate(SIZE, SIZE, &params(42));
+    let b = Environment::generate(SIZE, SIZE, &params(42));
+    assert_eq!(heights_hash(&a), heights_hash(&b));
+    assert_eq!(n_trees(&a), n_trees(&b));
+
+    let other = Environment::generate(SIZE, SIZE, &params(43));
+    assert_ne!(heights_hash(&a), heights_hash(&other));
+}
+
+#[test]
+fn mountains_rise_above_the_flat_land() {
+    let gen = MapGen::new(&MapGenParams::default(), SIZE, SIZE);
+    let samples = gen.preview(32);
+    assert_eq!(samples.len(), 32 * 32);
+    assert!(samples.iter().all(|&(h, _)| h <= 0.0));
+
+    let gen = MapGen::new(&params(42), SIZE, SIZE);
+    assert!(gen.preview(32).iter().any(|&(h, _)| h > 0.0));
+}
@@ -0,0 +1,1181 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ptr::addr_of;
+
+use common::saveload::{Bincode, CheckedCompressedBincode, Encoder};
+use common::FastMap;
+use geom::{vec2, vec3, PolyLine3, Vec2, Vec3, OBB};
+use prototypes::{
+    AirportPrototypeID, BuildingGen, Education, FreightStationPrototypeID, GameInstant,
+    GoodsCompanyID, ItemID, Money,
+};
+use serde::Serialize;
+use slotmapd::{HopSlotMap, Key};
+
+use crate::economy::{
+    BorderTrade, BudgetReason, Cargo, Government, Market, SeaTrade, Ship, ShipLeg, SingleMarket,
+    TruckLeg,
+};
+use crate::gameplay::GameplayParams;
+use crate::init::SAVELOAD_FUNCS;
+use crate::map::procgen::MapGenParams;
+use crate::map::{
+    generated_road_name, BuildingID, Buildings, Districts, Environment, IntersectionID,
+    Intersections, LaneID, LaneKind, LaneSpeeds, Lanes, LightPolicy, Lots, Map, NoiseMap,
+    ParkingSpots, RoadID, RoadSegmentKind, RoadStructure, Roads, Turn, TurnPolicy, ZoneGrid,
+};
+use crate::map_dynamic::{WaterFlow, WaterNetworkFlow, WaterNetworkID};
+use crate::migrations::{decode_with_version, migrate, SavedResources, SAVE_VERSION};
+use crate::souls::happiness::{
+    CityStats, Happiness, HappinessFactor, AGE_BUCKETS, HAPPINESS_BUCKETS,
+};
+use crate::souls::human::{Gender, PersonalInfo};
+use crate::statistics::{StatSeries, Statistics};
+use crate::transportation::Location;
+use crate::world::{CompanyID, VehicleID};
+use crate::{
+    BuildingKind, Simulation, SimulationOptions, SimulationSer, SoulID, WorldCommand, VERSION,
+};
+
+use super::TestCtx;
+
+/// Government as encoded by save version 1, before `construction_spending`:
+/// 123456$ of money, 5$ of tariff income and an electricity price of 0.25$
+static GOVERNMENT_V1: &[u8] = include_bytes!("government_v1.bc");
+
+/// Government as encoded by save version 7, before the ledger and the loan
+#[derive(Serialize)]
+struct GovernmentV7 {
+    money: Money,
+    tariff_income: Money,
+    electricity_price: Money,
+    construction_spending: Money,
+}
+
+fn government_v7(sim: &Simulation) -> Vec<u8> {
+    let gvt = sim.read::<Government>();
+    Bincode::encode(&GovernmentV7 {
+        money: gvt.money,
+        tariff_income: gvt.tariff_income,
+        electricity_price: gvt.electricity_price,
+        construction_spending: gvt.construction_spending,
+    })
+    .unwrap()
+}
+
+/// Market as encoded by save version 2, before the prioritized buyers
+#[derive(Serialize)]
+struct MarketV2<'a> {
+    markets: BTreeMap<ItemID, &'a SingleMarket>,
+}
+
+/// Market as encoded by save version 4, before the price multiplier and the trade margin
+#[derive(Serialize)]
+struct MarketV4<'a> {
+    v2: MarketV2<'a>,
+    prioritized: BTreeSet<SoulID>,
+}
+
+fn market_v2(sim: &Simulation) -> Vec<u8> {
+    let market = sim.read::<Market>();
+    Bincode::encode(&MarketV2 {
+        markets: market.iter().map(|(&item, m)| (item, m)).collect(),
+    })
+    .unwrap()
+}
+
+/// Nobody is prioritized in the tests
+fn market_v4(sim: &Simulation) -> Vec<u8> {
+    let market = sim.read::<Market>();
+    Bincode::encode(&MarketV4 {
+        v2: MarketV2 {
+            markets: market.iter().map(|(&item, m)| (item, m)).collect(),
+        },
+        prioritized: BTreeSet::new(),
+    })
+    .unwrap()
+}
+
+/// Options as encoded by save version 4, before the gameplay params
+#[derive(Serialize)]
+struct SimulationOptionsV4 {
+    terrain_size: u16,
+    save_replay: bool,
+}
+
+/// Options as encoded by save version 8, before the map generation params
+#[derive(Serialize)]
+struct SimulationOptionsV8 {
+    v4: SimulationOptionsV4,
+    params: GameplayParams,
+}
+
+fn simoptions_v4(sim: &Simulation) -> Vec<u8> {
+    let opts = sim.read::<SimulationOptions>();
+    Bincode::encode(&SimulationOptionsV4 {
+        terrain_size: opts.terrain_size,
+        save_replay: opts.save_replay,
+    })
+    .unwrap()
+}
+
+fn simoptions_v8(sim: &Simulation) -> Vec<u8> {
+    let opts = sim.read::<SimulationOptions>();
+    Bincode::encode(&SimulationOptionsV8 {
+        v4: SimulationOptionsV4 {
+            terrain_size: opts.terrain_size,
+            save_replay: opts.save_replay,
+        },
+        params: opts.params,
+    })
+    .unwrap()
+}
+
+/// Map as encoded by save version 3, before the road names
+#[derive(Serialize)]
+struct MapV3<'a> {
+    roads: &'a Roads,
+    intersections: &'a Intersections,
+    buildings: &'a Buildings,
+    lanes: &'a Lanes,
+    parking: &'a ParkingSpots,
+    lots: &'a Lots,
+    environment: &'a Environment,
+    external_train_stations: &'a Vec<BuildingID>,
+    zones: &'a ZoneGrid,
+    lane_speeds: &'a LaneSpeeds,
+}
+
+/// Map as encoded by save version 5, before the road connections
+#[derive(Serialize)]
+struct MapV5<'a> {
+    v3: MapV3<'a>,
+    road_names: &'a BTreeMap<RoadID, String>,
+}
+
+/// Map as encoded by save version 6, before the districts
+#[derive(Serialize)]
+struct MapV6<'a> {
+    v5: MapV5<'a>,
+    road_connections: &'a Vec<IntersectionID>,
+}
+
+/// Map as encoded by save version 9, before the noise
+#[derive(Serialize)]
+struct MapV9<'a> {
+    v6: MapV6<'a>,
+    districts: &'a Districts,
+}
+
+/// Map as encoded by save version 14, before the road numbers
+#[derive(Serialize)]
+struct MapV14<'a> {
+    v9: MapV9<'a>,
+    noise: &'a NoiseMap,
+}
+
+impl<'a> MapV3<'a> {
+    fn new(map: &'a Map) -> Self {
+        Self {
+            roads: &map.roads,
+            intersections: &map.intersections,
+            buildings: &map.buildings,
+            lanes: &map.lanes,
+            parking: &map.parking,
+            lots: &map.lots,
+            environment: &map.environment,
+            external_train_stations: &map.external_train_stations,
+            zones: &map.zones,
+            lane_speeds: &map.lane_speeds,
+        }
+    }
+}
+
+impl<'a> MapV5<'a> {
+    fn new(map: &'a Map) -> Self {
+        Self {
+            v3: MapV3::new(map),
+            road_names: &map.road_names,
+        }
+    }
+}
+
+impl<'a> MapV6<'a> {
+    fn new(map: &'a Map) -> Self {
+        Self {
+            v5: MapV5::new(map),
+            road_connections: &map.road_connections,
+        }
+    }
+}
+
+fn map_v3(sim: &Simulation) -> Vec<u8> {
+    Bincode::encode(&MapV3::new(&sim.map())).unwrap()
+}
+
+fn map_v5(sim: &Simulation) -> Vec<u8> {
+    Bincode::encode(&MapV5::new(&sim.map())).unwrap()
+}
+
+fn map_v6(sim: &Simulation) -> Vec<u8> {
+    Bincode::encode(&MapV6::new(&sim.map())).unwrap()
+}
+
+fn map_v9(sim: &Simulation) -> Vec<u8> {
+    let map = sim.map();
+    Bincode::encode(&MapV9 {
+        v6: MapV6::new(&map),
+        districts: &map.districts,
+    })
+    .unwrap()
+}
+
+fn map_v14(sim: &Simulation) -> Vec<u8> {
+    let map = sim.map();
+    Bincode::encode(&MapV14 {
+        v9: MapV9 {
+            v6: MapV6::new(&map),
+            districts: &map.districts,
+        },
+        noise: &map.noise,
+    })
+    .unwrap()
+}
+
+/// City statistics as encoded by save version 10, with the losses of four happiness factors
+#[derive(Serialize)]
+struct CityStatsV10 {
+    mean: f32,
+    histogram: [u32; HAPPINESS_BUCKETS],
+    losses: [f32; 4],
+    population: u32,
+    last_update: Option<GameInstant>,
+    births: u32,
+    deaths: u32,
+    ages: [u32; AGE_BUCKETS],
+    education: [u32; 3],
+    last_aging: Option<GameInstant>,
+}
+
+fn city_stats_v10(sim: &Simulation) -> Vec<u8> {
+    let stats = sim.read::<CityStats>();
+    let [commute, food, utilities, noise, _] = stats.losses;
+    Bincode::encode(&CityStatsV10 {
+        mean: stats.mean,
+        histogram: stats.histogram,
+        losses: [commute, food, utilities, noise],
+        population: stats.population,
+        last_update: stats.last_update,
+        births: stats.births,
+        deaths: stats.deaths,
+        ages: stats.ages,
+        education: stats.education,
+        last_aging: stats.last_aging,
+    })
+    .unwrap()
+}
+
+/// Hour accumulator of the statistics as encoded by save version 11, before the tourist arrivals
+#[derive(Default, Serialize)]
+struct HourAccumulatorV11 {
+    export_income: Money,
+    import_expenses: Money,
+    tariffs: Money,
+    trips_started: u32,
+    minutes: u32,
+    humans_en_route: u64,
+    vehicles_en_route: u64,
+    produced_power: i64,
+    consumed_power: i64,
+    blackout_minutes: u32,
+    has_last: bool,
+    last_money: Money,
+    last_construction_spending: Money,
+    last_births: u32,
+    last_deaths: u32,
+}
+
+/// Statistics as encoded by save version 11, before the tourist arrivals.
+/// The hour being accumulated starts over.
+#[derive(Serialize)]
+struct StatisticsV11<'a> {
+    population: &'a StatSeries,
+    births: &'a StatSeries,
+    deaths: &'a StatSeries,
+    money: &'a StatSeries,
+    export_income: &'a StatSeries,
+    import_expenses: &'a StatSeries,
+    tariff_income: &'a StatSeries,
+    construction_expenses: &'a StatSeries,
+    other: &'a StatSeries,
+    trip_time: &'a StatSeries,
+    vehicles_en_route: &'a StatSeries,
+    congestion: &'a StatSeries,
+    power_production: &'a StatSeries,
+    power_consumption: &'a StatSeries,
+    blackout: &'a StatSeries,
+    acc: HourAccumulatorV11,
+}
+
+fn statistics_v11(sim: &Simulation) -> Vec<u8> {
+    let stats = sim.read::<Statistics>();
+    Bincode::encode(&StatisticsV11 {
+        population: &stats.population,
+        births: &stats.births,
+        deaths: &stats.deaths,
+        money: &stats.money,
+        export_income: &stats.export_income,
+        import_expenses: &stats.import_expenses,
+        tariff_income: &stats.tariff_income,
+        construction_expenses: &stats.construction_expenses,
+        other: &stats.other,
+        trip_time: &stats.trip_time,
+        vehicles_en_route: &stats.vehicles_en_route,
+        congestion: &stats.congestion,
+        power_production: &stats.power_production,
+        power_consumption: &stats.power_consumption,
+        blackout: &stats.blackout,
+        acc: HourAccumulatorV11::default(),
+    })
+    .unwrap()
+}
+
+/// Water flow as encoded by save version 13, before the grace period
+#[derive(Serialize)]
+struct WaterFlowV13 {
+    flowmap: BTreeMap<WaterNetworkID, WaterNetworkFlow>,
+    dry: BTreeSet<BuildingID>,
+}
+
+fn water_flow_v13() -> Vec<u8> {
+    Bincode::encode(&WaterFlowV13 {
+        flowmap: BTreeMap::new(),
+        dry: BTreeSet::new(),
+    })
+    .unwrap()
+}
+
+/// Writes the simulation as a save of the given version, with some resources replaced
+fn write_save(sim: &Simulation, name: &str, version: u32, replace: &[(&str, &[u8])]) {
+    let mut res: FastMap<String, Vec<u8>> = FastMap::default();
+    unsafe {
+        for l in &*addr_of!(SAVELOAD_FUNCS) {
+            res.insert(l.name.to_string(), (l.save)(sim));
+        }
+    }
+    for (name, data) in replace {
+        res.insert(name.to_string(), data.to_vec());
+    }
+
+    let ser = SimulationSer {
+        world: &sim.world,
+        version: VERSION.to_string(),
+        res,
+    };
+    let data = CheckedCompressedBincode::encode_versioned(&ser, version).unwrap();
+    let _ = std::fs::create_dir("world");
+    std::fs::write(CheckedCompressedBincode::filename(name), data).unwrap();
+}
+
+fn remove_save(name: &str) {
+    let _ = std::fs::remove_file(CheckedCompressedBincode::filename(name));
+    let _ = std::fs::remove_file(format!("world/{name}_meta.json"));
+    let _ = std::fs::remove_file(format!("world/{name}_replay.json"));
+}
+
+#[test]
+fn current_save_roundtrip() {
+    let mut ctx = TestCtx::new();
+    let name = "test_migrations_current";
+
+    for _ in 0..10 {
+        ctx.tick();
+    }
+    ctx.g.write::<Government>().construction_spending = Money::new_bucks(42);
+    ctx.g.save_to_disk(name);
+
+    let (sim, applied) = Simulation::load_migrated(name).unwrap();
+    assert!(applied.is_empty(), "applied {:?}", applied);
+    assert_eq!(sim.get_tick(), ctx.g.get_tick());
+    assert_eq!(
+        sim.read::<Government>().construction_spending,
+        Money::new_bucks(42)
+    );
+
+    remove_save(name);
+}
+
+#[test]
+fn government_v1_is_migrated() {
+    let mut res = SavedResources::default();
+    res.insert("government".to_string(), GOVERNMENT_V1.to_vec());
+
+    let applied = migrate(1, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "government construction spending",
+            "market priorities",
+            "road names",
+            "gameplay params",
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let gvt: Government = Bincode::decode(&res["government"]).unwrap();
+    assert_eq!(gvt.money, Money::new_bucks(123456));
+    assert_eq!(gvt.tariff_income, Money::new_bucks(5));
+    assert_eq!(gvt.electricity_price, Money::new_cents(25));
+    assert_eq!(gvt.construction_spending, Money::ZERO);
+    assert_eq!(gvt.ledger.balance(), gvt.money);
+    assert_eq!(gvt.loan, Money::ZERO);
+}
+
+#[test]
+fn market_v2_is_migrated() {
+    let ctx = TestCtx::new();
+    let mut res = SavedResources::default();
+    res.insert("market".to_string(), market_v2(&ctx.g));
+
+    let applied = migrate(2, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "market priorities",
+            "road names",
+            "gameplay params",
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let market: Market = Bincode::decode(&res["market"]).unwrap();
+    assert_eq!(
+        Bincode::encode(&market).unwrap(),
+        Bincode::encode(&*ctx.g.read::<Market>()).unwrap()
+    );
+}
+
+#[test]
+fn map_v0_is_migrated() {
+    /// Road as encoded by save version 0, before the crossings and the structure
+    #[derive(Serialize)]
+    struct RoadV0<'a> {
+        id: RoadID,
+        src: IntersectionID,
+        dst: IntersectionID,
+        segment: &'a RoadSegmentKind,
+        points: &'a PolyLine3,
+        interfaced_points: &'a PolyLine3,
+        width: f32,
+        connected_buildings: &'a Vec<BuildingID>,
+        src_interface: f32,
+        dst_interface: f32,
+        lanes_forward: &'a Vec<(LaneID, LaneKind)>,
+        lanes_backward: &'a Vec<(LaneID, LaneKind)>,
+    }
+
+    /// Intersection as encoded by save version 0, before the light timing and the disabled turns
+    #[derive(Serialize)]
+    struct IntersectionV0<'a> {
+        id: IntersectionID,
+        pos: Vec3,
+        radius: f32,
+        turns: Vec<&'a Turn>,
+        roads: &'a Vec<RoadID>,
+        turn_policy: &'a TurnPolicy,
+        light_policy: &'a LightPolicy,
+    }
+
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+    let map = ctx.g.map();
+
+    let mut roads = HopSlotMap::<RoadID, RoadV0>::with_key();
+    for r in map.roads.values() {
+        roads.insert(RoadV0 {
+            id: r.id,
+            src: r.src,
+            dst: r.dst,
+            segment: &r.segment,
+            points: &r.points,
+            interfaced_points: r.interfaced_points(),
+            width: r.width,
+            connected_buildings: &r.connected_buildings,
+            src_interface: r.interface_from(r.src),
+            dst_interface: r.interface_from(r.dst),
+            lanes_forward: r.outgoing_lanes_from(r.src),
+            lanes_backward: r.outgoing_lanes_from(r.dst),
+        });
+    }
+    let mut intersections = HopSlotMap::<IntersectionID, IntersectionV0>::with_key();
+    for i in map.intersections.values() {
+        intersections.insert(IntersectionV0 {
+            id: i.id,
+            pos: i.pos,
+            radius: i.radius,
+            turns: i.turns().collect(),
+            roads: &i.roads,
+            turn_policy: &i.turn_policy,
+            light_policy: &i.light_policy,
+        });
+    }
+
+    // the fields of a struct are encoded one after the other
+    let mut data = Bincode::encode(&roads).unwrap();
+    data.extend(Bincode::encode(&intersections).unwrap());
+    data.extend(Bincode::encode(&map.buildings).unwrap());
+    data.extend(Bincode::encode(&map.lanes).unwrap());
+    data.extend(Bincode::encode(&map.parking).unwrap());
+    data.extend(Bincode::encode(&map.lots).unwrap());
+    let mut environment = Bincode::encode(&map.environment).unwrap();
+    // the planted trees are the last field of the environment, an empty list is a single byte
+    assert_eq!(environment.pop(), Some(0));
+    data.extend(environment);
+    data.extend(Bincode::encode(&map.external_train_stations).unwrap());
+    let n_lanes = map.roads.values().next().unwrap().n_lanes();
+    drop(map);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), data);
+
+    let applied = migrate(0, &mut res).unwrap();
+    assert_eq!(applied[0], "map objects");
+    assert_eq!(applied.len(), SAVE_VERSION as usize);
+
+    let map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 1);
+    assert_eq!(map.intersections().len(), 2);
+    let road = map.roads().values().next().unwrap();
+    assert_eq!(road.n_lanes(), n_lanes);
+    assert!(road.crossings.is_empty());
+    assert_eq!(road.structure, RoadStructure::Ground);
+    for i in map.intersections().values() {
+        assert!(i.light_timing.is_none());
+        assert!(i.disabled_turns.is_empty());
+    }
+}
+
+#[test]
+fn map_v3_is_migrated() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v3(&ctx.g));
+
+    let applied = migrate(3, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "road names",
+            "gameplay params",
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 1);
+    let (id, _) = map.roads().iter().next().unwrap();
+    assert_eq!(map.custom_road_name(id), None);
+}
+
+#[test]
+fn gameplay_params_v4_are_migrated() {
+    let ctx = TestCtx::new();
+    let mut res = SavedResources::default();
+    res.insert("market".to_string(), market_v4(&ctx.g));
+    res.insert("simoptions".to_string(), simoptions_v4(&ctx.g));
+
+    let applied = migrate(4, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "gameplay params",
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let market: Market = Bincode::decode(&res["market"]).unwrap();
+    assert_eq!(market.price_multiplier(), 1.25);
+    let opts: SimulationOptions = Bincode::decode(&res["simoptions"]).unwrap();
+    assert_eq!(opts.params, GameplayParams::default());
+}
+
+#[test]
+fn map_v5_is_migrated() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v5(&ctx.g));
+
+    let applied = migrate(5, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 1);
+    assert_eq!(map.road_connections().count(), 0);
+}
+
+#[test]
+fn map_v6_is_migrated() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v6(&ctx.g));
+
+    let applied = migrate(6, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 1);
+    assert!(map.districts.is_empty());
+}
+
+#[test]
+fn government_v7_is_migrated() {
+    let ctx = TestCtx::new();
+    ctx.g
+        .write::<Government>()
+        .set_money(BudgetReason::Cheats, Money::new_bucks(4242));
+    ctx.g.write::<Government>().construction_spending = Money::new_bucks(42);
+
+    let mut res = SavedResources::default();
+    res.insert("government".to_string(), government_v7(&ctx.g));
+
+    let applied = migrate(7, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let gvt: Government = Bincode::decode(&res["government"]).unwrap();
+    assert_eq!(gvt.money, Money::new_bucks(4242));
+    assert_eq!(gvt.construction_spending, Money::new_bucks(42));
+    assert_eq!(gvt.ledger.balance(), gvt.money);
+    assert_eq!(gvt.ledger.past().count(), 0);
+    assert_eq!(gvt.loan, Money::ZERO);
+}
+
+#[test]
+fn simoptions_v8_are_migrated() {
+    let ctx = TestCtx::new();
+    let mut res = SavedResources::default();
+    res.insert("simoptions".to_string(), simoptions_v8(&ctx.g));
+
+    let applied = migrate(8, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let opts: SimulationOptions = Bincode::decode(&res["simoptions"]).unwrap();
+    assert_eq!(opts.terrain_size, 1);
+    assert_eq!(opts.mapgen, MapGenParams::default());
+    assert!(opts.landmark_routing);
+}
+
+#[test]
+fn map_v9_is_migrated() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+    ctx.g.map_mut().update_noise();
+    assert!(ctx.g.map().noise_at(vec2(50.0, 10.0)) > 0.0);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v9(&ctx.g));
+
+    let applied = migrate(9, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let mut map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 1);
+    assert_eq!(map.noise_cells().count(), 0);
+
+    map.update_noise();
+    assert_eq!(
+        map.noise_at(vec2(50.0, 10.0)),
+        ctx.g.map().noise_at(vec2(50.0, 10.0))
+    );
+}
+
+#[test]
+fn map_v14_keeps_its_road_names() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[
+        vec3(0.0, 0.0, 0.0),
+        vec3(100.0, 0.0, 0.0),
+        vec3(100.0, 100.0, 0.0),
+    ]);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v14(&ctx.g));
+
+    let applied = migrate(14, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec!["road numbers", "sea routes", "border export payments"]
+    );
+
+    // the names used to come from the slot of the road
+    let map: Map = Bincode::decode(&res["map"]).unwrap();
+    assert_eq!(map.roads().len(), 2);
+    for id in map.roads().keys() {
+        let slot = id.data().as_ffi() as u32;
+        assert_eq!(map.road_name(id), generated_road_name(slot));
+        assert!(map.roads_created > slot);
+    }
+}
+
+#[test]
+fn airport_noise_is_rebuilt_after_migration() {
+    let mut ctx = TestCtx::new();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(256.0, 400.0), vec2(1.0, 0.0), 360.0, 80.0),
+        kind: BuildingKind::Airport(AirportPrototypeID::new("small-airport")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        },
+        zone: None,
+        connected_road: None,
+    }]);
+    ctx.g.map_mut().update_noise();
+    let noise = ctx.g.map().noise_at(vec2(256.0, 300.0));
+    assert!(noise > 0.0);
+
+    let mut res = SavedResources::default();
+    res.insert("map".to_string(), map_v9(&ctx.g));
+    migrate(9, &mut res).unwrap();
+
+    let mut map: Map = Bincode::decode(&res["map"]).unwrap();
+    map.update_noise();
+    assert_eq!(map.noise_at(vec2(256.0, 300.0)), noise);
+}
+
+#[test]
+fn city_stats_v10_are_migrated() {
+    let ctx = TestCtx::new();
+    ctx.g.write::<CityStats>().losses = [1.0, 2.0, 3.0, 4.0, 0.0];
+    ctx.g.write::<CityStats>().population = 42;
+
+    let mut res = SavedResources::default();
+    res.insert("city_stats".to_string(), city_stats_v10(&ctx.g));
+
+    let applied = migrate(10, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let stats: CityStats = Bincode::decode(&res["city_stats"]).unwrap();
+    assert_eq!(stats.losses, [1.0, 2.0, 3.0, 4.0, 0.0]);
+    assert_eq!(stats.population, 42);
+}
+
+#[test]
+fn happiness_v10_gets_a_leisure_score() {
+    #[derive(Serialize)]
+    struct HappinessV10 {
+        scores: [f32; 4],
+        trip_start: Option<GameInstant>,
+        last_trip_minutes: f32,
+    }
+
+    let data = Bincode::encode(&HappinessV10 {
+        scores: [0.1, 0.2, 0.3, 0.4],
+        trip_start: None,
+        last_trip_minutes: 12.0,
+    })
+    .unwrap();
+    let h: Happiness = decode_with_version(10, || Bincode::decode(&data)).unwrap();
+    assert_eq!(h.score(HappinessFactor::Noise), 0.4);
+    assert_eq!(h.score(HappinessFactor::Leisure), 1.0);
+    assert_eq!(h.last_trip_minutes, 12.0);
+
+    // the current saves are decoded as they are written
+    let data = Bincode::encode(&h).unwrap();
+    let h: Happiness = Bincode::decode(&data).unwrap();
+    assert_eq!(h.score(HappinessFactor::Leisure), 1.0);
+    assert_eq!(h.last_trip_minutes, 12.0);
+}
+
+#[test]
+fn statistics_v11_are_migrated() {
+    let ctx = TestCtx::new();
+    ctx.g.write::<Statistics>().population.push(42.0);
+
+    let mut res = SavedResources::default();
+    res.insert("statistics".to_string(), statistics_v11(&ctx.g));
+
+    let applied = migrate(11, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    let stats: Statistics = Bincode::decode(&res["statistics"]).unwrap();
+    assert_eq!(stats.population.last(), Some(42.0));
+    assert!(stats.tourist_arrivals.is_empty());
+}
+
+#[test]
+fn water_flow_v13_gets_a_grace_period() {
+    let mut res = SavedResources::default();
+    res.insert("water_flow".to_string(), water_flow_v13());
+
+    let applied = migrate(13, &mut res).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+
+    // the grace period starts on the first update after loading
+    let flow: WaterFlow = Bincode::decode(&res["water_flow"]).unwrap();
+    assert_eq!(flow.grace_until(), None);
+}
+
+#[test]
+fn sea_trade_v15_loses_its_hour() {
+    let mut sea = SeaTrade::default();
+    sea.ships.push(Ship {
+        harbor: BuildingID::default(),
+        dock: 1,
+        route: vec![Vec2::ZERO, vec2(100.0, 0.0)],
+        next: 1,
+        pos: Vec2::ZERO,
+        dir: Vec2::X,
+        speed: 3.0,
+        leg: ShipLeg::Arriving,
+        until: 0.0,
+        import: true,
+        cargo: vec![Cargo {
+            soul: SoulID::GoodsCompany(CompanyID::default()),
+            kind: ItemID::new("cereal"),
+            qty: 10,
+        }],
+    });
+
+    // the hour was the first field, big enough not to fit in a byte
+    let mut data = Bincode::encode(&1000u64).unwrap();
+    data.extend(Bincode::encode(&sea).unwrap());
+    let mut res = SavedResources::default();
+    res.insert("sea_trade".to_string(), data);
+
+    let applied = migrate(15, &mut res).unwrap();
+    assert_eq!(applied, vec!["sea routes", "border export payments"]);
+
+    let sea: SeaTrade = Bincode::decode(&res["sea_trade"]).unwrap();
+    assert_eq!(sea.ships.len(), 1);
+    assert_eq!(sea.ships[0].dock, 1);
+    assert_eq!(sea.ships[0].cargo[0].qty, 10);
+}
+
+#[test]
+fn border_trade_v16_owes_nothing_for_its_exports() {
+    #[derive(Serialize)]
+    struct ShipmentV16 {
+        connection: IntersectionID,
+        building: BuildingID,
+        import: bool,
+        cargo: Vec<Cargo>,
+        leg: TruckLeg,
+    }
+
+    #[derive(Serialize)]
+    struct BorderTradeV16 {
+        used: BTreeMap<IntersectionID, u32>,
+        reachable: BTreeMap<(BuildingID, IntersectionID), bool>,
+        hour: u64,
+        pending: Vec<ShipmentV16>,
+        trucks: BTreeMap<VehicleID, ShipmentV16>,
+    }
+
+    let shipment = || ShipmentV16 {
+        connection: IntersectionID::default(),
+        building: BuildingID::default(),
+        import: false,
+        cargo: vec![Cargo {
+            soul: SoulID::GoodsCompany(CompanyID::default()),
+            kind: ItemID::new("cereal"),
+            qty: 10,
+        }],
+        leg: TruckLeg::Stopped,
+    };
+    let data = Bincode::encode(&BorderTradeV16 {
+        used: BTreeMap::from([(IntersectionID::default(), 10)]),
+        reachable: BTreeMap::new(),
+        hour: 1000,
+        pending: vec![shipment()],
+        trucks: BTreeMap::from([(VehicleID::default(), shipment())]),
+    })
+    .unwrap();
+    let mut res = SavedResources::default();
+    res.insert("border_trade".to_string(), data);
+
+    let applied = migrate(16, &mut res).unwrap();
+    assert_eq!(applied, vec!["border export payments"]);
+
+    let border: BorderTrade = Bincode::decode(&res["border_trade"]).unwrap();
+    assert_eq!(border.used(IntersectionID::default()), 10);
+    assert_eq!(border.n_pending(), 1);
+    let truck = &border.trucks[&VehicleID::default()];
+    assert_eq!(truck.leg, TruckLeg::Stopped);
+    assert_eq!(truck.cargo[0].qty, 10);
+    assert_eq!(truck.earned, Money::ZERO);
+}
+
+#[test]
+fn personal_info_v0_gets_an_age_in_years() {
+    #[derive(Serialize)]
+    struct PersonalInfoV0 {
+        name: String,
+        age: u8,
+        gender: Gender,
+    }
+
+    let data = Bincode::encode(&PersonalInfoV0 {
+        name: "Jane Doe".to_string(),
+        age: 42,
+        gender: Gender::F,
+    })
+    .unwrap();
+    let info: PersonalInfo = decode_with_version(0, || Bincode::decode(&data)).unwrap();
+    assert_eq!(info.name, "Jane Doe");
+    assert_eq!(info.age, 42.0);
+    assert_eq!(info.gender, Gender::F);
+    assert_eq!(info.education, Education::None);
+    assert_eq!(info.schooling, 0.0);
+}
+
+#[test]
+fn enums_v0_keep_their_variants() {
+    #[allow(dead_code)]
+    #[derive(Serialize)]
+    enum LocationV0 {
+        Outside,
+        Vehicle(VehicleID),
+        Building(BuildingID),
+    }
+
+    #[allow(dead_code)]
+    #[derive(Serialize)]
+    enum BuildingKindV0 {
+        House,
+        GoodsCompany(GoodsCompanyID),
+        RailFreightStation(FreightStationPrototypeID),
+        TrainStation,
+        ExternalTrading,
+    }
+
+    let data = Bincode::encode(&LocationV0::Building(BuildingID::default())).unwrap();
+    let loc: Location = Bincode::decode(&data).unwrap();
+    assert_eq!(loc, Location::Building(BuildingID::default()));
+
+    let data = Bincode::encode(&BuildingKindV0::ExternalTrading).unwrap();
+    let kind: BuildingKind = Bincode::decode(&data).unwrap();
+    assert_eq!(kind, BuildingKind::ExternalTrading);
+}
+
+#[test]
+fn old_save_is_upgraded() {
+    let mut ctx = TestCtx::new();
+    let name = "test_migrations_old";
+    ctx.tick();
+
+    let market = market_v2(&ctx.g);
+    let map = map_v3(&ctx.g);
+    let simoptions = simoptions_v4(&ctx.g);
+    let city_stats = city_stats_v10(&ctx.g);
+    let statistics = statistics_v11(&ctx.g);
+    let water_flow = water_flow_v13();
+    write_save(
+        &ctx.g,
+        name,
+        1,
+        &[
+            ("government", GOVERNMENT_V1),
+            ("market", &market),
+            ("map", &map),
+            ("simoptions", &simoptions),
+            ("city_stats", &city_stats),
+            ("statistics", &statistics),
+            ("water_flow", &water_flow),
+        ],
+    );
+
+    let (sim, applied) = Simulation::load_migrated(name).unwrap();
+    assert_eq!(
+        applied,
+        vec![
+            "government construction spending",
+            "market priorities",
+            "road names",
+            "gameplay params",
+            "road connections",
+            "districts",
+            "government ledger",
+            "map generation params",
+            "noise map",
+            "leisure",
+            "tourism",
+            "landmark routing",
+            "water grace",
+            "road numbers",
+            "sea routes",
+            "border export payments"
+        ]
+    );
+    assert_eq!(sim.get_tick(), ctx.g.get_tick());
+    assert_eq!(sim.read::<Government>().money, Money::new_bucks(123456));
+
+    remove_save(name);
+}
+
+#[test]
+fn future_save_is_rejected() {
+    let mut ctx = TestCtx::new();
+    let name = "test_migrations_future";
+    ctx.tick();
+
+    write_save(
+        &ctx.g,
+        name,
+        SAVE_VERSION + 1,
+        &[("government", &b"not a government"[..])],
+    );
+
+    let err = Simulation::load_migrated(name)
+        .err()
+        .expect("save from a newer version was loaded");
+    assert!(err.to_string().contains("newer"), "{}", err);
+
+    remove_save(name);
+}
@@ -0,0 +1,136 @@
+use geom::Vec3;
+use prototypes::{MilestonePrototypeID, Money, TICKS_PER_MINUTE};
+
+use crate::economy::{BudgetReason, Government};
+use crate::map::{LanePatternBuilder, MapProject, ProjectKind};
+use crate::milestones::Milestones;
+use crate::notifications::{Severity, SimNotifications};
+use crate::souls::happiness::CityStats;
+use crate::world_command::{CommandError, FailedCommands, WorldCommand};
+use crate::Simulation;
+
+use super::TestCtx;
+
+fn tick_minute(ctx: &mut TestCtx) {
+    for _ in 0..TICKS_PER_MINUTE {
+        ctx.tick();
+    }
+}
+
+fn highway(x: f32) -> WorldCommand {
+    let ground = |pos| MapProject {
+        pos,
+        kind: ProjectKind::Ground,
+    };
+    WorldCommand::MapMakeConnection {
+        from: ground(Vec3::new(x, 0.0, 0.0)),
+        to: ground(Vec3::new(x, 300.0, 0.0)),
+        inter: None,
+        pat: LanePatternBuilder::new()
+            .n_lanes(3)
+            .speed_limit(25.0)
+            .parking(false)
+            .sidewalks(false)
+            .build(),
+    }
+}
+
+fn milestone_notifs(ctx: &TestCtx, seen: &mut u64) -> Vec<String> {
+    ctx.g
+        .read::<SimNotifications>()
+        .since(seen)
+        .filter(|n| n.severity == Severity::Milestone)
+        .map(|n| n.message.clone())
+        .collect()
+}
+
+#[test]
+fn milestones_unlock_once_as_the_city_grows() {
+    let mut ctx = TestCtx::new();
+    *ctx.g.write::<Milestones>() = Milestones::default();
+    let village = MilestonePrototypeID::new("village");
+    let town = MilestonePrototypeID::new("town");
+    let city = MilestonePrototypeID::new("city");
+    assert_eq!(village.prototype().population, 50);
+    assert!(city.prototype().money.is_some());
+
+    // the first tick makes the happiness system wait an hour before counting the citizens again
+    ctx.tick();
+    {
+        let milestones = ctx.g.read::<Milestones>();
+        assert!(!milestones.is_unlocked("hotel"));
+        assert!(!milestones.is_unlocked("highway"));
+        assert!(milestones.is_unlocked("street"));
+        assert!(milestones.is_unlocked("house"));
+        assert_eq!(milestones.locked_by("hotel").unwrap().id, town);
+    }
+
+    // the commands are refused too, they may come from another player or a replay
+    let n_roads = ctx.g.map().roads().len();
+    ctx.apply(&[highway(100.0)]);
+    assert_eq!(ctx.g.map().roads().len(), n_roads);
+    let failed: Vec<_> = ctx
+        .g
+        .read::<FailedCommands>()
+        .since(&mut 0)
+        .cloned()
+        .collect();
+    assert!(
+        matches!(failed[..], [CommandError::Locked(_)]),
+        "{:?}",
+        failed
+    );
+
+    let mut seen = 0;
+    tick_minute(&mut ctx);
+    assert!(milestone_notifs(&ctx, &mut seen).is_empty());
+
+    ctx.g.write::<CityStats>().population = 60;
+    tick_minute(&mut ctx);
+    let notifs = milestone_notifs(&ctx, &mut seen);
+    assert_eq!(notifs.len(), 1, "{:?}", notifs);
+    assert!(notifs[0].starts_with(&village.prototype().label));
+    assert!(ctx.g.read::<Milestones>().is_unlocked("school"));
+
+    // enough citizens for the city but not enough money
+    ctx.g.write::<CityStats>().population = 2000;
+    ctx.g
+        .write::<Government>()
+        .set_money(BudgetReason::Cheats, Money::ZERO);
+    tick_minute(&mut ctx);
+    let notifs = milestone_notifs(&ctx, &mut seen);
+    assert_eq!(notifs.len(), 1, "{:?}", notifs);
+    assert!(notifs[0].starts_with(&town.prototype().label));
+    {
+        let milestones = ctx.g.read::<Milestones>();
+        assert!(milestones.is_unlocked("hotel"));
+        assert!(!milestones.is_reached(city));
+    }
+
+    ctx.g.write::<Government>().set_money(
+        BudgetReason::Cheats,
+        city.prototype().money.unwrap() + Money::new_bucks(10_000),
+    );
+    tick_minute(&mut ctx);
+    assert_eq!(milestone_notifs(&ctx, &mut seen).len(), 1);
+    assert!(ctx.g.read::<Milestones>().is_unlocked("highway"));
+    ctx.apply(&[highway(100.0)]);
+    assert_eq!(ctx.g.map().roads().len(), n_roads + 1);
+
+    // the unlocks are saved and the city can shrink without losing them
+    let serialized = common::saveload::Bincode::encode(&ctx.g).unwrap();
+    let decoded: Simulation = common::saveload::Bincode::decode(&serialized).unwrap();
+    {
+        let milestones = decoded.read::<Milestones>();
+        assert!([village, town, city]
+            .iter()
+            .all(|&m| milestones.is_reached(m)));
+    }
+
+    ctx.g = decoded;
+    ctx.g.write::<CityStats>().population = 0;
+    tick_minute(&mut ctx);
+    tick_minute(&mut ctx);
+    assert!(milestone_notifs(&ctx, &mut seen).is_empty());
+    assert!(ctx.g.read::<Milestones>().is_unlocked("highway"));
+}
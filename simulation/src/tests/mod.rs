@@ -3,33 +3,164 @@
 
 use crate::map::{BuildingID, LanePatternBuilder, ProjectFilter};
 use crate::map_dynamic::BuildingInfos;
+use crate::milestones::Milestones;
+use crate::souls::demographics::demographics;
 use crate::utils::scheduler::SeqSchedule;
 use crate::world_command::{WorldCommand, WorldCommands};
 use crate::{Simulation, SimulationOptions};
 use common::logger::MyLog;
 use common::saveload::Encoder;
 use geom::{Vec2, Vec3};
+use prototypes::{
+    load_candidate, swap_prototypes, GameTime, Tick, TICKS_PER_HOUR, TICKS_PER_SECOND,
+};
+use std::cell::RefCell;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+mod airport;
+mod autosave;
+mod border_trade;
+mod budget;
+mod bulldoze;
+mod commands;
+mod congestion;
+mod crossing;
+mod crowd;
+mod demographics;
+mod education;
+mod fire;
+mod gameplay;
+mod garbage;
+mod happiness;
+mod land_value;
+mod leisure;
+mod map_editor;
+mod mapgen;
+mod migrations;
+mod milestones;
+mod noise;
+mod notifications;
+mod objectives;
+mod parking;
+mod passenger_rail;
+mod reload;
+mod road_names;
+mod road_pattern;
+mod saves;
+mod scenario;
+mod sea_trade;
+mod seasons;
+mod shoreline;
+mod statistics;
 mod test_iso;
+mod tourism;
+mod transit;
+mod trees;
+mod turns;
+mod vehicle_prototypes;
 mod vehicles;
+mod water;
+mod weather;
+mod zoning;
+
+/// The prototypes are global, the tests replacing them run alone
+static PROTOTYPES: RwLock<()> = RwLock::new(());
+
+thread_local! {
+    /// The read lock is taken once per test, as it may deadlock when taken again while a
+    /// replacing test waits
+    static SHARED: RefCell<(usize, Option<RwLockReadGuard<'static, ()>>)> =
+        const { RefCell::new((0, None)) };
+}
+
+pub(crate) enum PrototypesGuard {
+    Shared,
+    Exclusive(RwLockWriteGuard<'static, ()>),
+}
+
+impl PrototypesGuard {
+    pub(crate) fn shared() -> Self {
+        SHARED.with_borrow_mut(|(count, guard)| {
+            if *count == 0 {
+                *guard = Some(PROTOTYPES.read().unwrap_or_else(PoisonError::into_inner));
+            }
+            *count += 1;
+        });
+        Self::Shared
+    }
+
+    /// For the tests replacing the prototypes, the default ones are loaded back when dropped
+    pub(crate) fn exclusive() -> Self {
+        Self::Exclusive(PROTOTYPES.write().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl Drop for PrototypesGuard {
+    fn drop(&mut self) {
+        match self {
+            Self::Shared => SHARED.with_borrow_mut(|(count, guard)| {
+                *count -= 1;
+                if *count == 0 {
+                    *guard = None;
+                }
+            }),
+            Self::Exclusive(_) => swap_prototypes(load_candidate("../").unwrap()),
+        }
+    }
+}
+
+/// Makes the prototypes defined by the lua code current until the guard is dropped
+pub(crate) fn test_prototypes(lua: &str) -> PrototypesGuard {
+    let guard = PrototypesGuard::exclusive();
+    prototypes::test_prototypes(lua);
+    guard
+}
 
 pub(crate) struct TestCtx {
     pub g: Simulation,
     sched: SeqSchedule,
+    _prototypes: PrototypesGuard,
 }
 
 impl TestCtx {
     pub(crate) fn new() -> Self {
+        Self::with_options(SimulationOptions {
+            terrain_size: 1,
+            save_replay: false,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn with_options(opts: SimulationOptions) -> Self {
+        Self::with_guard(opts, PrototypesGuard::shared())
+    }
+
+    /// For the tests replacing the prototypes while the simulation runs
+    pub(crate) fn new_replacing_prototypes() -> Self {
+        Self::with_guard(
+            SimulationOptions {
+                terrain_size: 1,
+                save_replay: false,
+                ..Default::default()
+            },
+            PrototypesGuard::exclusive(),
+        )
+    }
+
+    fn with_guard(opts: SimulationOptions, guard: PrototypesGuard) -> Self {
         MyLog::init();
         crate::init::init();
 
-        let g = Simulation::new_with_options(SimulationOptions {
-            terrain_size: 1,
-            save_replay: false,
-        });
+        let g = Simulation::new_with_options(opts);
+        // the tests build anything, the milestones tests lock them back
+        *g.write::<Milestones>() = Milestones::all_reached();
         let sched = Simulation::schedule();
 
-        Self { g, sched }
+        Self {
+            g,
+            sched,
+            _prototypes: guard,
+        }
     }
 
     pub(crate) fn build_roads(&self, v: &[Vec3]) {
@@ -63,8 +194,7 @@ impl TestCtx {
     }
 
     pub(crate) fn tick(&mut self) {
-        self.g
-            .tick(&mut self.sched, WorldCommands::default().as_ref());
+        self.tick_unchecked();
 
         let serialized = common::saveload::Bincode::encode(&self.g).unwrap();
         let deserialized: Simulation = common::saveload::Bincode::decode(&serialized).unwrap();
@@ -80,4 +210,26 @@ impl TestCtx {
             );
         }
     }
+
+    /// Ticks without checking that the simulation survives a save,
+    /// for the tests that need many ticks
+    pub(crate) fn tick_unchecked(&mut self) {
+        self.g
+            .tick(&mut self.sched, WorldCommands::default().as_ref());
+    }
+
+    /// Jumps forward in time, then ticks once for the systems to catch up
+    pub(crate) fn skip_hours(&mut self, hours: u64) {
+        let tick = self.g.read::<GameTime>().tick.0 + hours * TICKS_PER_HOUR;
+        *self.g.write::<GameTime>() = GameTime::new(Tick(tick));
+        self.tick();
+    }
+
+    /// Jumps forward by a year of the demographics, then ticks once
+    pub(crate) fn skip_year(&mut self) {
+        let year = (GameTime::DAY as f32 * demographics().days_per_year) as u64 * TICKS_PER_SECOND;
+        let tick = self.g.read::<GameTime>().tick.0 + year;
+        *self.g.write::<GameTime>() = GameTime::new(Tick(tick));
+        self.tick();
+    }
 }
@@ -0,0 +1,36 @@
+use geom::{vec2, vec3};
+
+use crate::map::{LanePatternBuilder, ProjectFilter, LANE_SPEED_SAMPLE_TICKS};
+
+use super::TestCtx;
+
+#[test]
+fn highway_is_louder_than_two_blocks_away() {
+    let mut ctx = TestCtx::new();
+
+    {
+        let mut m = ctx.g.map_mut();
+        let a = m.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+        let b = m.project(vec3(400.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+        let highway = LanePatternBuilder::new()
+            .n_lanes(3)
+            .speed_limit(25.0)
+            .parking(false)
+            .build();
+        m.make_connection(a, b, None, &highway);
+    }
+    ctx.build_roads(&[vec3(0.0, 200.0, 0.0), vec3(400.0, 200.0, 0.0)]);
+
+    let near = ctx.build_house_near(vec2(200.0, 25.0));
+    let far = ctx.build_house_near(vec2(200.0, 225.0));
+
+    for _ in 0..LANE_SPEED_SAMPLE_TICKS {
+        ctx.tick();
+    }
+
+    let map = ctx.g.map();
+    let noise = |b| map.noise_at(map.buildings()[b].obb.center());
+    assert!(noise(near) > 3.0 * noise(far));
+
+    assert_eq!(map.noise_at(vec2(200.0, 1000.0)), 0.0);
+}
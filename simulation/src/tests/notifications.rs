@@ -0,0 +1,101 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{BuildingGen, GoodsCompanyID, Tick, HOURS_PER_DAY, TICKS_PER_MINUTE};
+
+use crate::notifications::{Notification, NotificationTarget, Severity, SimNotifications};
+use crate::{BuildingKind, WorldCommand};
+
+use super::TestCtx;
+
+#[test]
+fn since_returns_new_notifications_once() {
+    let mut notifs = SimNotifications::default();
+    let mut seen = 0;
+    notifs.push(Tick(1), Severity::Info, "a", None);
+    notifs.push(Tick(2), Severity::Warning, "b", None);
+
+    let new: Vec<_> = notifs.since(&mut seen).map(|n| n.message.clone()).collect();
+    assert_eq!(new, ["a", "b"]);
+    assert_eq!(notifs.since(&mut seen).count(), 0);
+
+    notifs.push(Tick(3), Severity::Critical, "c", None);
+    let new: Vec<_> = notifs.since(&mut seen).map(|n| n.severity).collect();
+    assert_eq!(new, [Severity::Critical]);
+
+    // the simulation was replaced by a new one
+    let mut seen = 10;
+    assert_eq!(SimNotifications::default().since(&mut seen).count(), 0);
+    assert_eq!(seen, 0);
+}
+
+#[test]
+fn unpowered_houses_report_a_blackout() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    ctx.build_house_near(vec2(100.0, 20.0));
+
+    for _ in 0..2 * TICKS_PER_MINUTE {
+        ctx.tick();
+    }
+
+    let mut seen = 0;
+    let notifs = ctx.g.read::<SimNotifications>();
+    let blackouts: Vec<_> = notifs
+        .since(&mut seen)
+        .filter(|n| n.severity == Severity::Critical)
+        .collect();
+    assert_eq!(blackouts.len(), 1);
+    assert!(matches!(
+        blackouts[0].target,
+        Some(NotificationTarget::Building(_))
+    ));
+}
+
+#[test]
+fn company_without_workers_is_reported_after_two_days() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(100.0, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("vegetable-farm")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(100.0, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+
+    // nobody lives in town to work at the farm
+    let warnings = |ctx: &mut TestCtx, seen: &mut u64| {
+        for _ in 0..TICKS_PER_MINUTE {
+            ctx.tick();
+        }
+        let notifs = ctx.g.read::<SimNotifications>();
+        let found: Vec<Notification> = notifs
+            .since(seen)
+            .filter(|n| n.severity == Severity::Warning)
+            .cloned()
+            .collect();
+        found
+    };
+    let mut seen = 0;
+    assert!(warnings(&mut ctx, &mut seen).is_empty());
+
+    ctx.skip_hours(HOURS_PER_DAY as u64 * 2 - 1);
+    assert!(warnings(&mut ctx, &mut seen).is_empty());
+
+    ctx.skip_hours(1);
+    let reported = warnings(&mut ctx, &mut seen);
+    assert_eq!(reported.len(), 1);
+    assert!(reported[0].message.contains("no workers"));
+    assert!(matches!(
+        reported[0].target,
+        Some(NotificationTarget::Building(_))
+    ));
+
+    // reported only once
+    ctx.skip_hours(HOURS_PER_DAY as u64);
+    assert!(warnings(&mut ctx, &mut seen).is_empty());
+}
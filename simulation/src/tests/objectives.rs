@@ -0,0 +1,221 @@
+use common::saveload::{Encoder, JSON};
+use geom::Vec3;
+use prototypes::{
+    load_prototypes_with_mods, swap_prototypes, GameDuration, GameTime, ItemID, ModInfo,
+    ModManifest, Money, ScenarioPrototypeID, Tick, TICKS_PER_HOUR, TICKS_PER_SECOND,
+};
+
+use crate::economy::{BudgetReason, Government, Trade, TradeTarget};
+use crate::map::{LanePatternBuilder, MapProject, ProjectKind};
+use crate::objectives::{start_scenario, ScenarioOutcome, ScenarioProfile, ScenarioRunner};
+use crate::scenario::Scenario;
+use crate::world::CompanyID;
+use crate::world_command::{CommandError, FailedCommands, WorldCommand};
+use crate::SoulID;
+
+use super::TestCtx;
+
+/// A road lined with houses, enough to settle the first village
+static FIRST_VILLAGE: &str = r#"{
+    "days": 2,
+    "steps": [
+        { "day": 0, "action": { "road": { "points": [[0, 0, 0], [600, 0, 0]] } } },
+        { "day": 0, "hour": 1, "action": { "houses": { "near": [50, 20], "count": 4 } } },
+        { "day": 0, "hour": 2, "action": { "houses": { "near": [150, 20], "count": 4 } } },
+        { "day": 0, "hour": 3, "action": { "houses": { "near": [250, 20], "count": 4 } } },
+        { "day": 0, "hour": 4, "action": { "houses": { "near": [350, 20], "count": 4 } } },
+        { "day": 0, "hour": 5, "action": { "houses": { "near": [450, 20], "count": 4 } } },
+        { "day": 0, "hour": 6, "action": { "houses": { "near": [550, 20], "count": 4 } } }
+    ]
+}"#;
+
+fn first_village() -> ScenarioPrototypeID {
+    ScenarioPrototypeID::new("first-village")
+}
+
+/// Makes current the base prototypes and a scenario won by exporting 30 cereal
+fn cereal_exports() -> ScenarioPrototypeID {
+    let dir = std::env::temp_dir().join("egregoria_test_objectives");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("data.lua"),
+        r#"data:extend {
+            {
+                type = "scenario",
+                name = "cereal-exports",
+                label = "Cereal exports",
+                description = "Export 30 cereal",
+                money = 100000,
+                objectives = {
+                    { export = "cereal", amount = 30 },
+                },
+            },
+        }"#,
+    )
+    .unwrap();
+    let scenario_mod = ModInfo {
+        manifest: ModManifest {
+            name: "cereal-exports".to_string(),
+            version: "1.0".to_string(),
+            dependencies: vec![],
+        },
+        path: format!("{}/", dir.to_string_lossy()),
+    };
+    swap_prototypes(load_prototypes_with_mods("../", &[scenario_mod]).unwrap());
+    ScenarioPrototypeID::new("cereal-exports")
+}
+
+/// Ticks until the runner checked the objectives of the next hour
+fn next_hour(ctx: &mut TestCtx) {
+    let tick = (ctx.g.read::<GameTime>().tick.0 / TICKS_PER_HOUR + 1) * TICKS_PER_HOUR;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick - 1));
+    ctx.tick();
+}
+
+#[test]
+fn first_village_is_won_by_building_houses() {
+    let mut ctx = TestCtx::new();
+    let id = first_village();
+    start_scenario(&mut ctx.g, id);
+
+    assert_eq!(ctx.g.read::<Government>().money, id.prototype().money);
+    {
+        let runner = ctx.g.read::<ScenarioRunner>();
+        assert!(runner.is_locked("highway"));
+        assert!(!runner.is_locked("street"));
+        assert_eq!(runner.outcome(), None);
+    }
+    let ground = |pos| MapProject {
+        pos,
+        kind: ProjectKind::Ground,
+    };
+    ctx.apply(&[WorldCommand::MapMakeConnection {
+        from: ground(Vec3::ZERO),
+        to: ground(Vec3::x(300.0)),
+        inter: None,
+        pat: LanePatternBuilder::new()
+            .n_lanes(3)
+            .parking(false)
+            .sidewalks(false)
+            .build(),
+    }]);
+    let failed: Vec<_> = ctx
+        .g
+        .read::<FailedCommands>()
+        .since(&mut 0)
+        .cloned()
+        .collect();
+    assert!(
+        matches!(failed[..], [CommandError::Locked(_)]),
+        "{:?}",
+        failed
+    );
+
+    let script: Scenario = JSON::decode(FIRST_VILLAGE.as_bytes()).unwrap();
+    script.run(&mut ctx.g, script.days);
+
+    let runner = ctx.g.read::<ScenarioRunner>();
+    assert_eq!(runner.outcome(), Some(&ScenarioOutcome::Won));
+    let active = runner.active().unwrap();
+    assert!(active.progress.iter().all(|p| p.completed));
+    assert!(active.progress[0].current >= 20.0);
+
+    // the outcome is kept in the save
+    let serialized = common::saveload::Bincode::encode(&*runner).unwrap();
+    let decoded: ScenarioRunner = common::saveload::Bincode::decode(&serialized).unwrap();
+    assert_eq!(decoded.outcome(), Some(&ScenarioOutcome::Won));
+    assert_eq!(decoded.scenario().unwrap().id, id);
+
+    // the win is recorded in the profile of the player by the simulation
+    let best = ScenarioProfile::load().best(&id.prototype().name);
+    assert!(best.is_some_and(|best| best <= active.duration().unwrap()));
+}
+
+#[test]
+fn exports_are_counted_once() {
+    let mut ctx = TestCtx::new_replacing_prototypes();
+    let id = cereal_exports();
+    start_scenario(&mut ctx.g, id);
+
+    let cereal = ItemID::new("cereal");
+    let company = SoulID::GoodsCompany(CompanyID::default());
+    let external = SoulID::FreightStation(Default::default());
+    let trade = |buyer, seller, qty| Trade {
+        buyer: TradeTarget(buyer),
+        seller: TradeTarget(seller),
+        qty,
+        kind: cereal,
+        money_delta: Money::ZERO,
+        tariff: Money::ZERO,
+        value: Money::ZERO,
+    };
+
+    // the imports don't count
+    ctx.g.write::<ScenarioRunner>().record_exports(&[
+        trade(external, company, 10),
+        trade(external, company, 10),
+        trade(company, external, 50),
+    ]);
+    let progress =
+        |ctx: &TestCtx| ctx.g.read::<ScenarioRunner>().active().unwrap().progress[0].clone();
+
+    // each check of the objectives sees the same exports
+    for _ in 0..3 {
+        next_hour(&mut ctx);
+        let p = progress(&ctx);
+        assert_eq!(p.current, 20.0);
+        assert!(!p.completed);
+    }
+
+    ctx.g
+        .write::<ScenarioRunner>()
+        .record_exports(&[trade(external, company, 10)]);
+    next_hour(&mut ctx);
+    assert_eq!(progress(&ctx).current, 30.0);
+    assert_eq!(
+        ctx.g.read::<ScenarioRunner>().outcome(),
+        Some(&ScenarioOutcome::Won)
+    );
+}
+
+#[test]
+fn first_village_is_lost_when_bankrupt_or_too_slow() {
+    let mut ctx = TestCtx::new();
+    start_scenario(&mut ctx.g, first_village());
+    ctx.g
+        .write::<Government>()
+        .set_money(BudgetReason::Cheats, Money::new_bucks(-1));
+    // the runner checks the objectives on the next hour
+    let tick = (ctx.g.read::<GameTime>().tick.0 / TICKS_PER_HOUR + 1) * TICKS_PER_HOUR;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick - 1));
+    ctx.tick();
+    assert!(matches!(
+        ctx.g.read::<ScenarioRunner>().outcome(),
+        Some(ScenarioOutcome::Lost(_))
+    ));
+
+    let mut ctx = TestCtx::new();
+    start_scenario(&mut ctx.g, first_village());
+    let tick = 6 * GameTime::DAY as u64 * TICKS_PER_SECOND;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick - 1));
+    ctx.tick();
+    let runner = ctx.g.read::<ScenarioRunner>();
+    let Some(ScenarioOutcome::Lost(reason)) = runner.outcome() else {
+        panic!("the scenario was not lost: {:?}", runner.outcome());
+    };
+    assert!(reason.contains("in time"), "{}", reason);
+}
+
+#[test]
+fn profile_keeps_the_fastest_completion() {
+    let mut profile = ScenarioProfile::default();
+    let hours = |h| GameDuration(Tick(h * TICKS_PER_HOUR));
+    assert_eq!(profile.best("first-village"), None);
+    assert!(profile.record("first-village", hours(30)));
+    assert!(!profile.record("first-village", hours(40)));
+    assert!(profile.record("first-village", hours(20)));
+
+    let decoded: ScenarioProfile = JSON::decode(&JSON::encode(&profile).unwrap()).unwrap();
+    assert_eq!(decoded.best("first-village"), Some(hours(20)));
+    assert_eq!(decoded.best("other"), None);
+}
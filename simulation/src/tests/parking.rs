@@ -0,0 +1,109 @@
+use geom::{vec2, vec3, Vec3};
+
+use crate::map::BuildingID;
+use crate::map_dynamic::{
+    ParkingManagement, Router, RoutingStep, SpotReservation, MAX_PARKING_RETRIES,
+    PARKING_SEARCH_RADIUS,
+};
+use crate::transportation::{spawn_parked_vehicle, Location, VehicleKind};
+use crate::world::VehicleID;
+use crate::WorldCommand;
+
+use super::TestCtx;
+
+fn steps_to(
+    ctx: &TestCtx,
+    router: &mut Router,
+    building: BuildingID,
+    loc: Location,
+) -> Vec<RoutingStep> {
+    let map = ctx.g.map();
+    let door = map.buildings()[building].door_pos;
+    router
+        .steps_to(
+            door,
+            Some(building),
+            &mut ctx.g.write::<ParkingManagement>(),
+            &map,
+            &loc,
+            &ctx.g.world.vehicles,
+        )
+        .expect("router should always find a way")
+}
+
+fn parks_at(steps: &[RoutingStep]) -> Option<VehicleID> {
+    steps.iter().find_map(|s| match s {
+        RoutingStep::Park(car, Some(_)) => Some(*car),
+        _ => None,
+    })
+}
+
+#[test]
+fn full_parking_doesnt_block_drivers() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(1500.0, 0.0, 0.0)]);
+    let dest = ctx.build_house_near(vec2(1400.0, 20.0));
+    let door = ctx.g.map().buildings()[dest].door_pos;
+
+    // every spot around the destination is taken
+    let mut taken: Vec<SpotReservation> = vec![];
+    {
+        let map = ctx.g.map();
+        let mut parking = ctx.g.write::<ParkingManagement>();
+        while let Ok(resa) = parking.reserve_near(door, &map) {
+            let spot = resa.get(&map.parking).unwrap();
+            assert!(spot.trans.pos.is_close(door, PARKING_SEARCH_RADIUS));
+            taken.push(resa);
+        }
+    }
+    assert!(!taken.is_empty());
+
+    let cars: Vec<VehicleID> = (0..3)
+        .map(|i| {
+            spawn_parked_vehicle(
+                &mut ctx.g,
+                VehicleKind::Car,
+                vec3(50.0 + 50.0 * i as f32, 0.0, 0.0),
+            )
+            .unwrap()
+        })
+        .collect();
+
+    // drivers that haven't left yet walk instead
+    for &car in &cars {
+        let mut router = Router::new(Some(car));
+        let steps = steps_to(&ctx, &mut router, dest, Location::Outside);
+        assert!(parks_at(&steps).is_none());
+        assert!(matches!(steps.last(), Some(RoutingStep::WalkTo(p)) if *p == door));
+    }
+    assert_eq!(ctx.g.read::<ParkingManagement>().failed_parking(dest), 3);
+
+    // drivers already on the road circle around a few times then park farther away,
+    // which counts as a single failure
+    let car = cars[0];
+    let mut router = Router::new(Some(car));
+    for _ in 0..MAX_PARKING_RETRIES {
+        let steps = steps_to(&ctx, &mut router, dest, Location::Vehicle(car));
+        assert!(matches!(
+            steps[..],
+            [RoutingStep::DriveTo(..), RoutingStep::Reroute]
+        ));
+    }
+    let steps = steps_to(&ctx, &mut router, dest, Location::Vehicle(car));
+    assert_eq!(parks_at(&steps), Some(car));
+    assert_eq!(ctx.g.read::<ParkingManagement>().failed_parking(dest), 4);
+
+    // once a spot frees up, it is used right away
+    ctx.g
+        .write::<ParkingManagement>()
+        .free(taken.pop().unwrap());
+    let mut router = Router::new(Some(cars[1]));
+    let steps = steps_to(&ctx, &mut router, dest, Location::Outside);
+    assert_eq!(parks_at(&steps), Some(cars[1]));
+
+    // counters of bulldozed buildings are forgotten
+    ctx.apply(&[WorldCommand::MapRemoveBuilding(dest)]);
+    ctx.tick();
+    assert_eq!(ctx.g.read::<ParkingManagement>().failed_parking(dest), 0);
+}
@@ -0,0 +1,110 @@
+use geom::{vec2, vec3, Vec2, Vec3, OBB};
+use prototypes::{BuildingGen, PassengerStationPrototypeID};
+
+use crate::map::{BuildingID, LanePatternBuilder, ProjectFilter};
+use crate::map_dynamic::Destination;
+use crate::souls::human::{spawn_human, HumanDecisionKind};
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::transportation::train::TrainReservations;
+use crate::transportation::Location;
+use crate::{BuildingKind, WorldCommand};
+
+use super::TestCtx;
+
+fn build_rails(ctx: &TestCtx, from: Vec3, to: Vec3) {
+    let mut m = ctx.g.map_mut();
+    let a = m.project(from, 0.0, ProjectFilter::ALL);
+    let b = m.project(to, 0.0, ProjectFilter::ALL);
+    m.make_connection(a, b, None, &LanePatternBuilder::new().rail(true).build())
+        .unwrap();
+}
+
+fn build_station(ctx: &mut TestCtx, x: f32) -> BuildingID {
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(x, -40.0), vec2(1.0, 0.0), 5.0, 5.0),
+        kind: BuildingKind::RailPassengerStation(PassengerStationPrototypeID::new(
+            "passenger-station",
+        )),
+        gen: BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        },
+        zone: None,
+        connected_road: None,
+    }]);
+    ctx.g
+        .map()
+        .buildings()
+        .iter()
+        .find(|(_, b)| {
+            matches!(b.kind, BuildingKind::RailPassengerStation(_))
+                && b.obb.center().is_close(vec2(x, -40.0), 1.0)
+        })
+        .unwrap()
+        .0
+}
+
+#[test]
+fn citizens_commute_by_train() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(800.0, 0.0, 0.0)]);
+    build_rails(&ctx, vec3(0.0, -80.0, 0.0), vec3(800.0, -80.0, 0.0));
+    let house = ctx.build_house_near(vec2(80.0, 20.0));
+    let a = build_station(&mut ctx, 100.0);
+    let b = build_station(&mut ctx, 700.0);
+
+    for _ in 0..30 {
+        ctx.tick();
+    }
+    let train = {
+        let rail = ctx.g.read::<PassengerRail>();
+        assert_eq!(rail.stations().len(), 2);
+        assert_eq!(rail.trains().len(), 1);
+        *rail.trains().keys().next().unwrap()
+    };
+    // the consist is made of several wagons following the locomotive
+    assert_eq!(ctx.g.world.wagons.len(), 3);
+
+    let human = spawn_human(&mut ctx.g, house).unwrap();
+    let dest = vec3(710.0, -30.0, 0.0);
+    {
+        let h = ctx.g.world.humans.get_mut(human).unwrap();
+        // no car, walking is slower than the train
+        h.router.personal_car = None;
+        h.router.use_vehicle(None);
+        h.decision.kind = HumanDecisionKind::GoTo(Destination::Outside(dest));
+        h.decision.wait = 0;
+    }
+
+    let plan = ctx
+        .g
+        .read::<PassengerRail>()
+        .plan(vec3(80.0, 20.0, 0.0), dest)
+        .unwrap();
+    assert_eq!((plan.board, plan.alight), (a, b));
+
+    let mut rode = false;
+    for _ in 0..5000 {
+        ctx.tick();
+        {
+            // the train only ever holds blocks for itself
+            let reservs = ctx.g.read::<TrainReservations>();
+            assert!(!reservs.blocks.is_empty());
+            assert!(reservs.blocks.values().all(|&t| t == train));
+        }
+
+        let rail = ctx.g.read::<PassengerRail>();
+        let h = &ctx.g.world.humans[human];
+        if h.location == Location::Train(train) {
+            rode = true;
+        }
+        if rail.stations()[&a].ridership == 1 {
+            assert!(rode);
+            assert!(rail.trip(human).is_none());
+            assert_eq!(h.location, Location::Outside);
+            assert!(h.trans.pos.is_close(rail.stations()[&b].platform, 20.0));
+            return;
+        }
+    }
+    panic!("no commute was completed, rode the train: {}", rode);
+}
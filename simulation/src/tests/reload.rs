@@ -0,0 +1,174 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{
+    load_prototypes_with_mods, swap_prototypes, try_prototype, BuildingGen, GoodsCompanyID, ItemID,
+    ModInfo, ModManifest, SchoolPrototypeID,
+};
+
+use crate::economy::Market;
+use crate::map_dynamic::BuildingInfos;
+use crate::reload::reload_prototypes;
+use crate::saves::delete_save;
+use crate::{BuildingKind, Simulation, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+/// A mod running the lua code on each prototype `p` of the base mod
+fn patch_mod(patch: &str) -> ModInfo {
+    let dir = std::env::temp_dir().join("egregoria_test_reload");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("data.lua"),
+        format!(
+            r#"for i = 1, rawlen(data) do
+                local p = rawget(data, i)
+                {}
+            end"#,
+            patch
+        ),
+    )
+    .unwrap();
+    ModInfo {
+        manifest: ModManifest {
+            name: "patch".to_string(),
+            version: "1.0".to_string(),
+            dependencies: vec![],
+        },
+        path: format!("{}/", dir.to_string_lossy()),
+    }
+}
+
+/// A mod changing the flour consumed by the bakeries
+fn bakery_mod(flour: i32) -> ModInfo {
+    patch_mod(&format!(
+        r#"if p.type == "goods-company" and p.name == "bakery" then
+            p.recipe.consumption = {{{{"flour", {}}}}}
+        end"#,
+        flour
+    ))
+}
+
+#[test]
+fn reloaded_recipe_is_used_by_running_companies() {
+    let mut ctx = TestCtx::new_replacing_prototypes();
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(50.0, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("bakery")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(50.0, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+
+    let bakery = ctx.g.map().buildings().keys().next().unwrap();
+    let Some(SoulID::GoodsCompany(company)) = ctx.g.read::<BuildingInfos>().owner(bakery) else {
+        panic!("no bakery company");
+    };
+    let soul = SoulID::GoodsCompany(company);
+    let flour = ItemID::new("flour");
+    {
+        let mut market = ctx.g.write::<Market>();
+        let capital = market.capital(soul, flour);
+        market.produce(soul, flour, -capital);
+    }
+
+    let candidate = load_prototypes_with_mods("../", &[bakery_mod(3)]).unwrap();
+    reload_prototypes(&mut ctx.g, candidate).unwrap();
+    {
+        let mut market = ctx.g.write::<Market>();
+        let order = market.inner()[&flour].buy_order(soul).copied();
+        assert_eq!(order.map(|o| o.qty), Some(3));
+
+        // stocked so that the next cycle doesn't wait for deliveries
+        market.cancel_buy(soul, flour);
+        market.produce(soul, flour, 10);
+    }
+
+    ctx.g
+        .world
+        .companies
+        .get_mut(company)
+        .unwrap()
+        .comp
+        .progress = 1.0;
+    ctx.tick();
+    assert_eq!(ctx.g.read::<Market>().capital(soul, flour), 7);
+}
+
+#[test]
+fn removed_prototype_in_use_is_refused() {
+    let mut ctx = TestCtx::new_replacing_prototypes();
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let school = SchoolPrototypeID::new("school");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(50.0, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::School(school),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(50.0, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+
+    let candidate = load_prototypes_with_mods(
+        "../",
+        &[patch_mod(
+            r#"if p.type == "school" and p.name == "school" then
+                p.name = "old-school"
+            end"#,
+        )],
+    )
+    .unwrap();
+    assert!(reload_prototypes(&mut ctx.g, candidate).is_err());
+
+    // the current prototypes are kept
+    assert!(try_prototype(school).is_some());
+    ctx.tick();
+}
+
+#[test]
+fn save_using_a_disabled_mod_still_loads() {
+    let name = "test disabled mod";
+    let _ = delete_save(name);
+
+    let mut ctx = TestCtx::new_replacing_prototypes();
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(50.0, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("bakery")),
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(50.0, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+    assert_eq!(ctx.g.world.companies.len(), 1);
+    ctx.g.save_named(&ctx.g.metadata(name, "", 0.0)).unwrap();
+
+    // as if the bakery came from a mod that was disabled since
+    swap_prototypes(
+        load_prototypes_with_mods(
+            "../",
+            &[patch_mod(
+                r#"if p.type == "goods-company" and p.name == "bakery" then
+                    p.name = "old-bakery"
+                end"#,
+            )],
+        )
+        .unwrap(),
+    );
+    let sim = Simulation::load_named(name).unwrap();
+    delete_save(name).unwrap();
+
+    assert!(sim.map().buildings().is_empty());
+    assert!(sim.world.companies.is_empty());
+    ctx.g = sim;
+    ctx.tick();
+}
@@ -0,0 +1,127 @@
+use common::saveload::{Bincode, Encoder};
+use geom::{vec2, vec3, Polygon, Vec2, Vec3};
+use slotmapd::Key;
+
+use crate::map::{LanePatternBuilder, Map, RoadID};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+fn road_at(map: &Map, pos: Vec3) -> RoadID {
+    map.roads()
+        .values()
+        .min_by_key(|r| r.points.project(pos).distance(pos) as i32)
+        .unwrap()
+        .id
+}
+
+#[test]
+fn generated_names_survive_save_and_load() {
+    let ctx = TestCtx::new();
+    ctx.build_roads(&[Vec3::ZERO, vec3(100.0, 0.0, 0.0), vec3(100.0, 100.0, 0.0)]);
+
+    let map = ctx.g.map();
+    let names: Vec<String> = map
+        .roads()
+        .keys()
+        .map(|id| map.road_name(id).into_owned())
+        .collect();
+    assert!(names.iter().all(|n| n.ends_with(" Street")));
+    assert_ne!(names[0], names[1]);
+
+    let loaded: Map = Bincode::decode(&Bincode::encode(&*map).unwrap()).unwrap();
+    for (id, name) in map.roads().keys().zip(&names) {
+        assert_eq!(&loaded.road_name(id), name);
+    }
+}
+
+#[test]
+fn names_follow_the_road_when_rebuilt() {
+    let mut ctx = TestCtx::new();
+    ctx.build_roads(&[Vec3::ZERO, vec3(100.0, 0.0, 0.0)]);
+
+    let road = road_at(&ctx.g.map(), Vec3::ZERO);
+    ctx.apply(&[WorldCommand::MapSetRoadName {
+        road,
+        name: " Main Street ".to_string(),
+    }]);
+    assert_eq!(ctx.g.map().custom_road_name(road), Some("Main Street"));
+
+    ctx.apply(&[WorldCommand::MapSetRoadPattern {
+        road,
+        pattern: LanePatternBuilder::new().n_lanes(2).build(),
+    }]);
+    let road = road_at(&ctx.g.map(), Vec3::ZERO);
+    assert_eq!(ctx.g.map().road_name(road), "Main Street");
+
+    // connecting a road in the middle splits it in two
+    ctx.build_roads(&[vec3(50.0, 0.0, 0.0), vec3(50.0, 100.0, 0.0)]);
+    let map = ctx.g.map();
+    assert_eq!(
+        map.road_name(road_at(&map, vec3(10.0, 0.0, 0.0))),
+        "Main Street"
+    );
+    assert_eq!(
+        map.road_name(road_at(&map, vec3(90.0, 0.0, 0.0))),
+        "Main Street"
+    );
+    let side = road_at(&map, vec3(50.0, 90.0, 0.0));
+    assert_eq!(map.custom_road_name(side), None);
+    drop(map);
+
+    ctx.apply(&[WorldCommand::MapSetRoadName {
+        road: side,
+        name: String::new(),
+    }]);
+    assert!(ctx.g.map().road_name(side).ends_with(" Street"));
+
+    ctx.apply(&[WorldCommand::MapRemoveRoad(road_at(
+        &ctx.g.map(),
+        vec3(10.0, 0.0, 0.0),
+    ))]);
+    assert_eq!(ctx.g.map().road_names.len(), 1);
+}
+
+#[test]
+fn districts_survive_save_and_load() {
+    let mut ctx = TestCtx::new();
+    ctx.apply(&[
+        WorldCommand::MapAddDistrict {
+            polygon: Polygon::centered_rect(Vec2::ZERO, 100.0, 100.0),
+            name: "Old Town".to_string(),
+        },
+        WorldCommand::MapAddDistrict {
+            polygon: Polygon::centered_rect(vec2(200.0, 0.0), 100.0, 100.0),
+            name: String::new(),
+        },
+    ]);
+
+    let map = ctx.g.map();
+    let loaded: Map = Bincode::decode(&Bincode::encode(&*map).unwrap()).unwrap();
+    let names: Vec<&str> = loaded.districts.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["Old Town", "District 2"]);
+    assert_eq!(
+        loaded
+            .districts
+            .at(vec2(190.0, 10.0))
+            .map(|d| d.name.as_str()),
+        Some("District 2")
+    );
+}
+
+#[test]
+fn new_roads_dont_take_the_name_of_removed_ones() {
+    let mut ctx = TestCtx::new();
+    ctx.build_roads(&[Vec3::ZERO, vec3(100.0, 0.0, 0.0)]);
+    let old = road_at(&ctx.g.map(), Vec3::ZERO);
+    let old_name = ctx.g.map().road_name(old).into_owned();
+
+    ctx.apply(&[WorldCommand::MapRemoveRoad(old)]);
+    ctx.build_roads(&[vec3(0.0, 200.0, 0.0), vec3(100.0, 200.0, 0.0)]);
+
+    // the new road reuses the slot of the removed one
+    let map = ctx.g.map();
+    let new = road_at(&map, vec3(0.0, 200.0, 0.0));
+    assert_eq!(new.data().as_ffi() as u32, old.data().as_ffi() as u32);
+    assert_ne!(map.road_name(new), old_name);
+}
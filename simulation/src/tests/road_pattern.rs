@@ -0,0 +1,123 @@
+use geom::{vec3, Vec3};
+use prototypes::GameTime;
+
+use crate::map::{LaneID, LanePatternBuilder, PathKind, ProjectFilter, ProjectKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn set_road_pattern_reroutes_vehicles() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(100.0, 0.0, 0.0), vec3(100.0, 50.0, 0.0)]);
+
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, Vec3::ZERO).unwrap();
+    unpark(&mut ctx.g, car);
+
+    let start = ctx.g.world().vehicles[car].trans.pos;
+    let end = vec3(100.0, 45.0, 0.0);
+    let tick = ctx.g.read::<GameTime>().tick;
+    let it = Itinerary::route(tick, start, end, &ctx.g.map(), PathKind::Vehicle).unwrap();
+    ctx.g.world_mut_unchecked().vehicles[car].it = it;
+
+    for _ in 0..20 {
+        ctx.tick();
+    }
+
+    let road = ctx
+        .g
+        .map()
+        .roads()
+        .values()
+        .find(|r| r.points.first().distance(Vec3::ZERO) < 1.0)
+        .unwrap()
+        .id;
+    ctx.apply(&[WorldCommand::MapSetRoadPattern {
+        road,
+        pattern: LanePatternBuilder::new().n_lanes(2).build(),
+    }]);
+
+    assert_eq!(ctx.g.map().roads().len(), 2);
+    assert!(ctx.g.world().vehicles[car]
+        .it
+        .is_wait_for_reroute()
+        .is_some());
+
+    for _ in 0..2000 {
+        ctx.tick();
+
+        let it = &ctx.g.world().vehicles[car].it;
+        if let Some(route) = it.get_route() {
+            let map = ctx.g.map();
+            assert!(route.cur.points(&map).is_some());
+        }
+        if it.get_route().is_some() && it.has_ended(ctx.g.read::<GameTime>().timestamp) {
+            return;
+        }
+    }
+
+    panic!("car has not arrived after 2000 ticks")
+}
+
+#[test]
+fn flip_one_way_road() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[vec3(-100.0, 0.0, 0.0), Vec3::ZERO]);
+    ctx.build_roads(&[vec3(100.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+
+    let (from, to) = {
+        let map = ctx.g.map();
+        (
+            map.project(Vec3::ZERO, 0.0, ProjectFilter::INTER),
+            map.project(vec3(100.0, 0.0, 0.0), 0.0, ProjectFilter::INTER),
+        )
+    };
+    let (ProjectKind::Inter(a), ProjectKind::Inter(b)) = (from.kind, to.kind) else {
+        panic!("roads were not built");
+    };
+    ctx.apply(&[WorldCommand::MapMakeConnection {
+        from,
+        to,
+        inter: None,
+        pat: LanePatternBuilder::new().one_way(true).build(),
+    }]);
+
+    let road = ctx.g.map().find_road(a, b).unwrap();
+    ctx.apply(&[WorldCommand::MapFlipRoad(road)]);
+
+    let map = ctx.g.map();
+    let road = &map.roads()[map.find_road(a, b).unwrap()];
+    let traffic: Vec<LaneID> = road
+        .lanes_iter()
+        .filter(|(_, kind)| kind.needs_arrows())
+        .map(|(id, _)| id)
+        .collect();
+    assert!(!traffic.is_empty());
+    for id in &traffic {
+        let lane = &map.lanes()[*id];
+        assert_eq!((lane.src, lane.dst), (b, a));
+    }
+
+    // the road can only be entered from b and left at a
+    let inter_a = &map.intersections()[a];
+    let inter_b = &map.intersections()[b];
+    assert!(inter_a.turns().any(|t| traffic.contains(&t.id.src)));
+    assert!(inter_b.turns().any(|t| traffic.contains(&t.id.dst)));
+    assert!(inter_a.turns().all(|t| !traffic.contains(&t.id.dst)));
+    assert!(inter_b.turns().all(|t| !traffic.contains(&t.id.src)));
+
+    // flipping a two-way road does nothing
+    let two_way = map.intersections()[a]
+        .roads
+        .iter()
+        .copied()
+        .find(|&id| id != road.id)
+        .unwrap();
+    drop(map);
+    ctx.apply(&[WorldCommand::MapFlipRoad(two_way)]);
+    assert!(ctx.g.map().roads().contains_key(two_way));
+}
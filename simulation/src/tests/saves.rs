@@ -0,0 +1,63 @@
+use crate::saves::{delete_save, is_valid_save_name, rename_save, save_exists};
+use crate::{SaveMetadata, Simulation};
+
+use super::TestCtx;
+
+#[test]
+fn named_saves_roundtrip() {
+    let mut ctx = TestCtx::new();
+    let (name, renamed) = ("test named save", "test named save renamed");
+    let _ = delete_save(name);
+    let _ = delete_save(renamed);
+
+    for _ in 0..10 {
+        ctx.tick();
+    }
+    let meta = ctx.g.metadata(name, "Testville", 42.0);
+    ctx.g.save_named(&meta).unwrap();
+    assert!(save_exists(name));
+
+    let scanned = SaveMetadata::scan();
+    let found = scanned.iter().find(|m| m.name == name).unwrap();
+    assert_eq!(found.city_name, "Testville");
+    assert_eq!(found.tick.0, ctx.g.get_tick());
+    assert_eq!(found.play_time, 42.0);
+
+    // renaming onto an existing save must not overwrite it
+    ctx.g.save_named(&ctx.g.metadata(renamed, "", 0.0)).unwrap();
+    assert!(rename_save(name, renamed).is_err());
+    delete_save(renamed).unwrap();
+
+    rename_save(name, renamed).unwrap();
+    assert!(!save_exists(name));
+    assert_eq!(SaveMetadata::load(renamed).unwrap().name, renamed);
+    let sim = Simulation::load_named(renamed).unwrap();
+    assert_eq!(sim.get_tick(), ctx.g.get_tick());
+
+    delete_save(renamed).unwrap();
+    assert!(!save_exists(renamed));
+    assert!(SaveMetadata::scan().iter().all(|m| m.name != renamed));
+}
+
+#[test]
+fn save_names_are_valid_directories() {
+    assert!(is_valid_save_name("My city 2"));
+    assert!(is_valid_save_name("city_v1.5-final"));
+    assert!(!is_valid_save_name(""));
+    assert!(!is_valid_save_name("   "));
+    assert!(!is_valid_save_name(" padded"));
+    assert!(!is_valid_save_name(".."));
+    assert!(!is_valid_save_name("../world"));
+    assert!(!is_valid_save_name("a/b"));
+    assert!(!is_valid_save_name("a\\b"));
+    assert!(!is_valid_save_name(&"a".repeat(100)));
+    // reserved on Windows
+    assert!(!is_valid_save_name("city."));
+    assert!(!is_valid_save_name("CON"));
+    assert!(!is_valid_save_name("nul"));
+    assert!(!is_valid_save_name("Com1.city"));
+    assert!(!is_valid_save_name("aux .1"));
+    assert!(is_valid_save_name("console"));
+    assert!(is_valid_save_name("COM10"));
+    assert!(is_valid_save_name("my con"));
+}
@@ -0,0 +1,49 @@
+use common::saveload::{Encoder, JSON};
+
+use crate::scenario::Scenario;
+
+use super::TestCtx;
+
+/// A road lined with houses, a few more houses are built every day
+static TINY_SCENARIO: &str = r#"{
+    "days": 10,
+    "steps": [
+        { "day": 0, "action": { "road": { "points": [[0, 0, 0], [600, 0, 0]] } } },
+        { "day": 0, "hour": 1, "action": { "houses": { "near": [30, 20], "count": 2 } } },
+        { "day": 1, "action": { "houses": { "near": [90, 20], "count": 2 } } },
+        { "day": 2, "action": { "houses": { "near": [150, 20], "count": 2 } } },
+        { "day": 3, "action": { "houses": { "near": [210, 20], "count": 2 } } },
+        { "day": 4, "action": { "houses": { "near": [270, 20], "count": 2 } } },
+        { "day": 5, "action": { "houses": { "near": [330, 20], "count": 2 } } },
+        { "day": 6, "action": { "houses": { "near": [390, 20], "count": 2 } } },
+        { "day": 7, "action": { "houses": { "near": [450, 20], "count": 2 } } },
+        { "day": 8, "action": { "houses": { "near": [510, 20], "count": 2 } } },
+        { "day": 9, "action": { "houses": { "near": [570, 20], "count": 2 } } },
+        { "day": 9, "hour": 12, "action": "stats" }
+    ]
+}"#;
+
+#[test]
+fn tiny_scenario_grows() {
+    let mut ctx = TestCtx::new();
+    let scenario: Scenario = JSON::decode(TINY_SCENARIO.as_bytes()).unwrap();
+
+    let report = scenario.run(&mut ctx.g, scenario.days);
+    assert_eq!(report.days.len(), 10);
+
+    let population: Vec<u32> = report.days.iter().map(|d| d.population).collect();
+    assert!(
+        population.windows(2).all(|w| w[0] <= w[1]),
+        "population decreased: {:?}",
+        population
+    );
+    assert!(
+        population[0] < population[9],
+        "population did not grow: {:?}",
+        population
+    );
+
+    let csv = report.to_csv();
+    assert_eq!(csv.lines().count(), 11);
+    assert!(csv.starts_with("day,tick,population,money,blackout_hours"));
+}
@@ -0,0 +1,252 @@
+use geom::{vec2, Vec2, AABB, OBB};
+use prototypes::{
+    BuildingGen, FreightStationPrototypeID, GoodsCompanyID, HarborPrototypeID, ItemID,
+};
+
+use crate::economy::{FreightMode, FreightThroughput, Market, SeaTrade, ShipLeg};
+use crate::map::BuildingID;
+use crate::map_dynamic::BuildingInfos;
+use crate::world::CompanyID;
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+/// Middle of the river, it flows from the south edge of the map to the north edge
+const RIVER_X: f32 = 256.0;
+
+fn special_building(kind: BuildingKind, center: Vec2, size: f32, axis: Vec2) -> WorldCommand {
+    WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(center, axis, size, size),
+        kind,
+        gen: BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        },
+        zone: None,
+        connected_road: None,
+    }
+}
+
+/// A flat map crossed by a wide river, with a harbor and a farm on its east bank
+/// and the railway leaving the map far away on the west bank
+fn river_port() -> (TestCtx, CompanyID, BuildingID) {
+    let mut ctx = TestCtx::new();
+    ctx.g.map_mut().environment.terrain_apply(
+        AABB::new_ll_ur(vec2(RIVER_X - 80.0, 0.0), vec2(RIVER_X + 80.0, 512.0)),
+        |pos| {
+            if (pos.x - RIVER_X).abs() < 60.0 {
+                -8.0
+            } else {
+                pos.z
+            }
+        },
+    );
+
+    ctx.g
+        .map_mut()
+        .build_special_building(
+            &OBB::new(vec2(60.0, 460.0), vec2(1.0, 0.0), 40.0, 40.0),
+            BuildingKind::ExternalTrading,
+            BuildingGen::NoWalkway {
+                door_pos: Vec2::ZERO,
+            },
+            None,
+            None,
+        )
+        .unwrap();
+
+    ctx.apply(&[
+        special_building(
+            BuildingKind::RailFreightStation(FreightStationPrototypeID::new("freight-station")),
+            vec2(60.0, 60.0),
+            20.0,
+            vec2(1.0, 0.0),
+        ),
+        // the docks face the river
+        special_building(
+            BuildingKind::Harbor(HarborPrototypeID::new("cargo-harbor")),
+            vec2(RIVER_X + 90.0, 256.0),
+            50.0,
+            vec2(-1.0, 0.0),
+        ),
+        special_building(
+            BuildingKind::GoodsCompany(GoodsCompanyID::new("vegetable-farm")),
+            vec2(440.0, 256.0),
+            20.0,
+            vec2(1.0, 0.0),
+        ),
+    ]);
+    ctx.tick();
+
+    let map = ctx.g.map();
+    let harbor = map
+        .buildings()
+        .values()
+        .find(|b| matches!(b.kind, BuildingKind::Harbor(_)))
+        .expect("the harbor should be on the shore")
+        .id;
+    drop(map);
+    assert_eq!(ctx.g.world().freight_stations.len(), 1);
+    let (company, _) = ctx.g.world().companies.iter().next().unwrap();
+
+    (ctx, company, harbor)
+}
+
+/// Buys cereal from the outside for the farm, returns the soul of the farm
+fn buy_cereal(ctx: &mut TestCtx, company: CompanyID, qty: u32) -> SoulID {
+    let soul = SoulID::GoodsCompany(company);
+    let building = ctx.g.read::<BuildingInfos>().building_owned_by(soul);
+    let door = ctx.g.map().buildings()[building.unwrap()].door_pos;
+    ctx.g
+        .write::<Market>()
+        .buy(soul, door.xy(), ItemID::new("cereal"), qty);
+    ctx.tick();
+    ctx.tick();
+    soul
+}
+
+/// Ticks until the first ship is on this leg, panics if it takes too long
+fn wait_for_leg(ctx: &mut TestCtx, leg: ShipLeg) {
+    for _ in 0..20000 {
+        if ctx.g.read::<SeaTrade>().ships[0].leg == leg {
+            return;
+        }
+        ctx.tick_unchecked();
+    }
+    panic!("the ship never got to {:?}", leg);
+}
+
+#[test]
+fn import_through_the_harbor_arrives_by_ship() {
+    let (mut ctx, company, harbor) = river_port();
+    let cereal = ItemID::new("cereal");
+    let soul = buy_cereal(&mut ctx, company, 10);
+
+    // the railway leaves the map, but the harbor is cheaper than the far away freight station
+    let farm = ctx
+        .g
+        .read::<BuildingInfos>()
+        .building_owned_by(soul)
+        .unwrap();
+    let map = ctx.g.map();
+    let farm_door = map.buildings()[farm].door_pos.xy();
+    let station = ctx
+        .g
+        .world()
+        .freight_stations
+        .values()
+        .next()
+        .unwrap()
+        .f
+        .building;
+    let door = map.buildings()[station].door_pos.xy();
+    let ext = map.external_train_stations();
+    assert!(!ext.is_empty());
+    let rail = FreightMode::Rail.cost(
+        map.buildings()[ext[0]].obb.center().distance(door),
+        door.distance(farm_door),
+    );
+    let harbor_door = map.buildings()[harbor].door_pos.xy();
+    let sea_haul = ctx
+        .g
+        .write::<SeaTrade>()
+        .route_length(&map, harbor)
+        .unwrap();
+    let sea = FreightMode::Sea.cost(sea_haul, harbor_door.distance(farm_door));
+    assert!(rail > sea, "rail {} sea {}", rail, sea);
+    drop(map);
+
+    let throughput = ctx.g.read::<FreightThroughput>();
+    assert_eq!(throughput.used(station), 0);
+    assert_eq!(throughput.used(harbor), 10);
+    drop(throughput);
+
+    let sea = ctx.g.read::<SeaTrade>();
+    assert_eq!(sea.ships.len(), 1);
+    let ship = &sea.ships[0];
+    assert_eq!(ship.harbor, harbor);
+    assert!(ship.import);
+    assert_eq!(ship.cargo[0].soul, soul);
+    drop(sea);
+
+    for _ in 0..5000 {
+        let docked = ctx.g.read::<SeaTrade>().ships[0].leg == ShipLeg::Docked;
+        let capital = ctx.g.read::<Market>().capital(soul, cereal);
+        if docked {
+            assert_eq!(capital, 10);
+            return;
+        }
+        // the goods are on their way
+        assert_eq!(capital, 0);
+        ctx.tick();
+    }
+    panic!("the ship should have docked");
+}
+
+#[test]
+fn export_through_the_harbor_leaves_by_ship() {
+    let (mut ctx, company, harbor) = river_port();
+    let soul = SoulID::GoodsCompany(company);
+    let meat = ItemID::new("meat");
+
+    let building = ctx.g.read::<BuildingInfos>().building_owned_by(soul);
+    let door = ctx.g.map().buildings()[building.unwrap()].door_pos;
+    let mut market = ctx.g.write::<Market>();
+    market.produce(soul, meat, 10);
+    market.sell(soul, door.xy(), meat, 10, 0);
+    drop(market);
+    ctx.tick();
+    ctx.tick();
+
+    // the goods left the farm, the ship comes to pick them up
+    assert_eq!(ctx.g.read::<Market>().capital(soul, meat), 0);
+    let sea = ctx.g.read::<SeaTrade>();
+    assert_eq!(sea.ships.len(), 1);
+    let ship = &sea.ships[0];
+    assert_eq!(ship.harbor, harbor);
+    assert!(!ship.import);
+    assert_eq!(ship.cargo[0].qty, 10);
+    drop(sea);
+
+    wait_for_leg(&mut ctx, ShipLeg::Docked);
+    // exported goods are not given back at the dock
+    assert_eq!(ctx.g.read::<Market>().capital(soul, meat), 0);
+}
+
+#[test]
+fn ship_leaves_after_docking() {
+    let (mut ctx, company, _) = river_port();
+    buy_cereal(&mut ctx, company, 10);
+
+    wait_for_leg(&mut ctx, ShipLeg::Docked);
+    wait_for_leg(&mut ctx, ShipLeg::Leaving);
+
+    for _ in 0..20000 {
+        if ctx.g.read::<SeaTrade>().ships.is_empty() {
+            ctx.tick();
+            return;
+        }
+        ctx.tick_unchecked();
+    }
+    panic!("the ship should have left the map");
+}
+
+#[test]
+fn ship_turns_back_when_the_harbor_is_removed() {
+    let (mut ctx, company, harbor) = river_port();
+    let soul = buy_cereal(&mut ctx, company, 10);
+    let cereal = ItemID::new("cereal");
+
+    for _ in 0..50 {
+        ctx.tick_unchecked();
+    }
+    assert_eq!(ctx.g.read::<SeaTrade>().ships[0].leg, ShipLeg::Arriving);
+    assert_eq!(ctx.g.read::<Market>().capital(soul, cereal), 0);
+
+    ctx.apply(&[WorldCommand::MapRemoveBuilding(harbor)]);
+    ctx.tick();
+
+    // the goods are given anyway, and the ship goes back to sea
+    assert!(!ctx.g.map().buildings().contains_key(harbor));
+    assert_eq!(ctx.g.read::<SeaTrade>().ships[0].leg, ShipLeg::Leaving);
+    assert_eq!(ctx.g.read::<Market>().capital(soul, cereal), 10);
+}
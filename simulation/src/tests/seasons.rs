@@ -0,0 +1,99 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{
+    BuildingGen, GameTime, GoodsCompanyID, Season, Tick, DAYS_PER_SEASON, TICKS_PER_HOUR,
+};
+
+use crate::weather::{Weather, WeatherKind};
+use crate::{BuildingKind, WorldCommand};
+
+use super::TestCtx;
+
+fn build_special(ctx: &mut TestCtx, x: f32, kind: BuildingKind) {
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(x, -50.0), vec2(1.0, 0.0), 20.0, 20.0),
+        kind,
+        gen: BuildingGen::NoWalkway {
+            door_pos: vec2(x, -40.0),
+        },
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.tick();
+}
+
+/// Moves to noon of the given day
+fn go_to_day(ctx: &mut TestCtx, day: i32) {
+    let now = *ctx.g.read::<GameTime>();
+    let ticks = (day - now.daytime.day) as i64 * 24 * TICKS_PER_HOUR as i64
+        + (12 - now.daytime.hour) as i64 * TICKS_PER_HOUR as i64;
+    let tick = (now.tick.0 as i64 + ticks) as u64;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(tick));
+    ctx.tick();
+}
+
+/// Progress made by the farm over a few ticks
+fn farm_progress(ctx: &mut TestCtx) -> f32 {
+    let farm = GoodsCompanyID::new("vegetable-farm");
+    let progress = |ctx: &TestCtx| {
+        ctx.g
+            .world()
+            .companies
+            .values()
+            .find(|c| c.comp.proto == farm)
+            .map(|c| c.comp.progress)
+            .unwrap()
+    };
+    let before = progress(ctx);
+    for _ in 0..10 {
+        ctx.tick();
+    }
+    progress(ctx) - before
+}
+
+#[test]
+fn farm_stops_in_winter() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(400.0, 0.0, 0.0)]);
+    for i in 0..8 {
+        ctx.build_house_near(vec2(30.0 + 40.0 * i as f32, 20.0));
+    }
+    build_special(
+        &mut ctx,
+        50.0,
+        BuildingKind::GoodsCompany(GoodsCompanyID::new("solar-panel")),
+    );
+    build_special(
+        &mut ctx,
+        200.0,
+        BuildingKind::GoodsCompany(GoodsCompanyID::new("vegetable-farm")),
+    );
+    ctx.g.write::<Weather>().forced = Some(WeatherKind::Clear);
+
+    let farm = GoodsCompanyID::new("vegetable-farm");
+    let proto = farm.prototype();
+    assert_eq!(proto.season_productivity.get(Season::Winter), 0.0);
+
+    for _ in 0..50 {
+        ctx.tick();
+        let hired = ctx
+            .g
+            .world()
+            .companies
+            .values()
+            .any(|c| c.comp.proto == farm && !c.workers.0.is_empty());
+        if hired {
+            break;
+        }
+    }
+
+    go_to_day(&mut ctx, DAYS_PER_SEASON + 1);
+    assert_eq!(ctx.g.read::<GameTime>().season(), Season::Summer);
+    assert_ne!(farm_progress(&mut ctx), 0.0);
+
+    go_to_day(&mut ctx, 3 * DAYS_PER_SEASON + 1);
+    assert_eq!(ctx.g.read::<GameTime>().season(), Season::Winter);
+    assert_eq!(ctx.g.read::<Weather>().season(), Season::Winter);
+    assert_eq!(farm_progress(&mut ctx), 0.0);
+}
@@ -0,0 +1,101 @@
+use geom::{vec2, vec3, Vec2, AABB, OBB};
+use prototypes::{BuildingGen, GoodsCompanyID};
+
+use crate::map::{BuildingKind, RoadStructure, SiteError, BRIDGE_WATER_CLEARANCE};
+use crate::world_command::{CommandError, FailedCommands};
+use crate::WorldCommand;
+
+use super::TestCtx;
+
+/// Middle of the river, it flows from south to north
+const RIVER_X: f32 = 256.0;
+
+/// A flat map crossed by a 6m deep river
+fn river() -> TestCtx {
+    let ctx = TestCtx::new();
+    ctx.g.map_mut().environment.terrain_apply(
+        AABB::new_ll_ur(vec2(RIVER_X - 50.0, 0.0), vec2(RIVER_X + 50.0, 512.0)),
+        |pos| {
+            if (pos.x - RIVER_X).abs() < 30.0 {
+                -6.0
+            } else {
+                pos.z
+            }
+        },
+    );
+    ctx
+}
+
+fn build(ctx: &mut TestCtx, company: &str, center: Vec2) -> Vec<CommandError> {
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(center, vec2(1.0, 0.0), 40.0, 40.0),
+        kind: BuildingKind::GoodsCompany(GoodsCompanyID::new(company)),
+        gen: BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        },
+        zone: None,
+        connected_road: None,
+    }]);
+    ctx.g
+        .read::<FailedCommands>()
+        .since(&mut 0)
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn water_at_gives_the_depth() {
+    let ctx = river();
+    let map = ctx.g.map();
+    let depth = map.water_at(vec2(RIVER_X, 256.0)).unwrap();
+    assert!((depth - 6.0).abs() < 0.1, "depth {}", depth);
+    assert_eq!(map.water_at(vec2(400.0, 256.0)), None);
+
+    let shore = OBB::new(vec2(RIVER_X + 64.0, 256.0), vec2(1.0, 0.0), 40.0, 40.0);
+    assert!(!map.is_on_water(&shore));
+    assert!(map.is_on_shore(&shore));
+    let inland = OBB::new(vec2(440.0, 256.0), vec2(1.0, 0.0), 40.0, 40.0);
+    assert!(!map.is_on_shore(&inland));
+}
+
+#[test]
+fn normal_building_on_water_fails() {
+    let mut ctx = river();
+    let n_buildings = ctx.g.map().buildings().len();
+
+    let failed = build(&mut ctx, "bakery", vec2(RIVER_X, 256.0));
+    assert_eq!(failed, vec![CommandError::Site(SiteError::OnWater)]);
+    assert_eq!(ctx.g.map().buildings().len(), n_buildings);
+
+    let failed = build(&mut ctx, "bakery", vec2(440.0, 256.0));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(ctx.g.map().buildings().len(), n_buildings + 1);
+}
+
+#[test]
+fn harbor_on_shoreline_succeeds() {
+    let mut ctx = river();
+    let n_buildings = ctx.g.map().buildings().len();
+
+    let failed = build(&mut ctx, "fishing-harbor", vec2(440.0, 256.0));
+    assert_eq!(failed, vec![CommandError::Site(SiteError::NotOnShore)]);
+    assert_eq!(ctx.g.map().buildings().len(), n_buildings);
+
+    let failed = build(&mut ctx, "fishing-harbor", vec2(RIVER_X + 64.0, 256.0));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(ctx.g.map().buildings().len(), n_buildings + 1);
+}
+
+#[test]
+fn road_across_river_is_a_bridge() {
+    let ctx = river();
+    ctx.build_roads(&[vec3(100.0, 256.0, 0.0), vec3(420.0, 256.0, 0.0)]);
+
+    let map = ctx.g.map();
+    let road = map.roads().values().next().unwrap();
+    assert_eq!(road.structure, RoadStructure::Bridge);
+    let deck = road.points().project(vec3(RIVER_X, 256.0, 0.0));
+    assert!(deck.z >= BRIDGE_WATER_CLEARANCE, "deck at {}", deck.z);
+
+    assert!(map.lots().values().all(|lot| !map.is_on_water(&lot.shape)));
+}
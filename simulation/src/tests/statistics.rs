@@ -0,0 +1,51 @@
+use geom::{vec2, vec3, Vec3};
+use prototypes::{HOURS_PER_DAY, TICKS_PER_HOUR};
+
+use crate::statistics::{StatSeries, Statistics, STATISTICS_LEN};
+
+use super::TestCtx;
+
+#[test]
+fn series_are_bounded() {
+    let mut s = StatSeries::default();
+    for i in 0..STATISTICS_LEN + 10 {
+        s.push(i as f32);
+    }
+    assert_eq!(s.len(), STATISTICS_LEN);
+    assert_eq!(s.values().next(), Some(10.0));
+    assert_eq!(s.last(), Some((STATISTICS_LEN + 9) as f32));
+    assert_eq!(s.sum_last(2), (2 * STATISTICS_LEN + 17) as f32);
+}
+
+#[test]
+fn series_sum_per_day() {
+    let mut s = StatSeries::default();
+    for _ in 0..HOURS_PER_DAY * 2 + 3 {
+        s.push(1.0);
+    }
+    assert_eq!(s.per_day(), vec![3.0, 24.0, 24.0]);
+    assert!(StatSeries::default().per_day().is_empty());
+}
+
+#[test]
+fn statistics_are_sampled_hourly() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    ctx.build_house_near(vec2(100.0, 20.0));
+    ctx.build_house_near(vec2(200.0, 20.0));
+
+    let end = (ctx.g.get_tick() / TICKS_PER_HOUR + 3) * TICKS_PER_HOUR;
+    while ctx.g.get_tick() <= end {
+        ctx.tick();
+    }
+
+    let stats = ctx.g.read::<Statistics>();
+    assert_eq!(stats.population.len(), 3);
+    assert_eq!(stats.blackout.len(), 3);
+    assert!(stats.population.last().unwrap() > 0.0);
+    assert!(stats.trip_time.values().all(|t| t >= 0.0));
+    assert!(stats.congestion.values().all(|c| (0.0..=1.0).contains(&c)));
+    // the changes of the first hour are unknown
+    assert_eq!(stats.births.values().next(), Some(0.0));
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeSet;
+
+use geom::{vec2, vec3, OBB};
+use prototypes::{GameTime, HotelPrototypeID, Money, TICKS_PER_MINUTE};
+
+use crate::economy::Market;
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::tourism::Tourism;
+use crate::statistics::Statistics;
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::transportation::Location;
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+/// Ticks until the tourists who arrived walked into their hotel
+fn walk_to_hotels(ctx: &mut TestCtx) {
+    for _ in 0..30 * TICKS_PER_MINUTE {
+        let now = ctx.g.read::<GameTime>().instant();
+        let walking = ctx.g.world.humans.values().any(|h| {
+            h.tourist
+                .as_ref()
+                .is_some_and(|t| !t.is_leaving() && now < t.departure)
+                && h.location == Location::Outside
+        });
+        if !walking {
+            return;
+        }
+        ctx.tick_unchecked();
+    }
+    panic!("tourists could not walk to their hotel");
+}
+
+fn n_tourists(ctx: &TestCtx) -> usize {
+    ctx.g
+        .world
+        .humans
+        .values()
+        .filter(|h| h.tourist.is_some())
+        .count()
+}
+
+#[test]
+fn tourists_come_and_go_for_a_month() {
+    let mut ctx = TestCtx::new();
+
+    // the tourists arrive at the end of the road closest to the edge of the map, by the hotel
+    ctx.build_roads(&[vec3(20.0, 60.0, 0.0), vec3(300.0, 60.0, 0.0)]);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let hotel = HotelPrototypeID::new("hotel");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(60.0, 95.0), vec2(1.0, 0.0), 40.0, 40.0),
+        kind: BuildingKind::Hotel(hotel),
+        gen: hotel.prototype().bgen,
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    let hotel_id = ctx
+        .g
+        .map()
+        .buildings()
+        .iter()
+        .find(|(_, b)| matches!(b.kind, BuildingKind::Hotel(_)))
+        .unwrap()
+        .0;
+    let rooms = hotel.prototype().rooms as usize;
+
+    ctx.tick();
+    ctx.tick();
+
+    // many days without an update, more tourists want to come than there are rooms
+    ctx.skip_hours(24 * 10);
+    assert_eq!(n_tourists(&ctx), rooms);
+    assert!(ctx.g.read::<Tourism>().turned_away > 0);
+    walk_to_hotels(&mut ctx);
+    assert_eq!(
+        ctx.g
+            .read::<BuildingInfos>()
+            .get(hotel_id)
+            .unwrap()
+            .inside
+            .len(),
+        rooms
+    );
+
+    let mut seen = BTreeSet::new();
+    for _ in 0..30 * 24 {
+        ctx.skip_hours(1);
+
+        let n = n_tourists(&ctx);
+        assert!(n <= rooms, "{} tourists for {} rooms", n, rooms);
+
+        // time skips don't move pedestrians, the new tourists walk to the hotel
+        walk_to_hotels(&mut ctx);
+
+        for (id, h) in ctx.g.world.humans.iter() {
+            if h.tourist.is_some() {
+                assert!(
+                    h.wallet.0 >= Money::ZERO,
+                    "{:?} spent beyond its budget",
+                    id
+                );
+                seen.insert(id);
+            }
+        }
+    }
+
+    let tourism = ctx.g.read::<Tourism>();
+    assert!(
+        tourism.arrivals as usize > 2 * rooms,
+        "only {} arrivals",
+        tourism.arrivals
+    );
+    assert_eq!(tourism.tourists as usize, n_tourists(&ctx));
+    drop(tourism);
+    assert!(ctx.g.read::<Statistics>().tourist_arrivals.sum_last(24) > 0.0);
+
+    // the tourists who left didn't leave anything behind
+    let world = ctx.g.world();
+    let binfos = ctx.g.read::<BuildingInfos>();
+    let market = ctx.g.read::<Market>();
+    let rail = ctx.g.read::<PassengerRail>();
+    let info = binfos.get(hotel_id).unwrap();
+    assert!(info.owner.is_none());
+    let mut n_left = 0;
+    for &id in &seen {
+        if world.humans.contains_key(id) {
+            continue;
+        }
+        n_left += 1;
+        let soul = SoulID::Human(id);
+        assert!(!info.inside.contains(&soul), "{:?} is still inside", id);
+        assert!(market.iter().all(|(_, m)| m.buy_order(soul).is_none()));
+        assert!(binfos.building_owned_by(soul).is_none());
+        assert!(rail.trip(id).is_none());
+    }
+    assert!(n_left > rooms, "only {} tourists left", n_left);
+    assert_eq!(
+        info.inside.len(),
+        world
+            .humans
+            .values()
+            .filter(|h| h.location == Location::Building(hotel_id))
+            .count()
+    );
+}
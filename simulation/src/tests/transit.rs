@@ -0,0 +1,155 @@
+use geom::{vec2, vec3, Vec3};
+
+use crate::map_dynamic::Destination;
+use crate::souls::human::{spawn_human, HumanDecisionKind};
+use crate::transportation::transit::{closest_stop, BusStopID, Transit};
+use crate::transportation::{Location, VehicleKind};
+use crate::WorldCommand;
+
+use super::TestCtx;
+
+fn add_stop(ctx: &mut TestCtx, x: f32) -> BusStopID {
+    let pos = vec3(x, 8.0, 0.0);
+    ctx.apply(&[WorldCommand::AddBusStop(pos)]);
+    closest_stop(&ctx.g.read::<Transit>(), pos, 10.0).unwrap()
+}
+
+fn add_line(ctx: &mut TestCtx, stops: Vec<BusStopID>, n_buses: u32) {
+    ctx.apply(&[WorldCommand::AddBusLine {
+        name: "test".to_string(),
+    }]);
+    let line = ctx.g.read::<Transit>().lines().keys().last().unwrap();
+    ctx.apply(&[WorldCommand::UpdateBusLine {
+        line,
+        stops,
+        n_buses,
+    }]);
+}
+
+fn n_buses(ctx: &TestCtx) -> usize {
+    ctx.g
+        .world
+        .vehicles
+        .values()
+        .filter(|v| matches!(v.vehicle.kind, VehicleKind::Bus))
+        .count()
+}
+
+#[test]
+fn citizens_ride_the_bus() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(400.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(20.0, 20.0));
+    let a = add_stop(&mut ctx, 30.0);
+    let b = add_stop(&mut ctx, 330.0);
+    add_line(&mut ctx, vec![a, b], 1);
+    ctx.tick();
+    assert_eq!(n_buses(&ctx), 1);
+
+    let human = spawn_human(&mut ctx.g, house).unwrap();
+    let dest = vec3(340.0, 15.0, 0.0);
+    {
+        let h = ctx.g.world.humans.get_mut(human).unwrap();
+        // no car, walking is slower than the bus
+        h.router.personal_car = None;
+        h.router.use_vehicle(None);
+        h.decision.kind = HumanDecisionKind::GoTo(Destination::Outside(dest));
+        h.decision.wait = 0;
+    }
+
+    let plan = ctx
+        .g
+        .read::<Transit>()
+        .plan(vec3(20.0, 20.0, 0.0), dest)
+        .unwrap();
+    assert_eq!((plan.board, plan.alight), (a, b));
+
+    let mut rode = false;
+    for _ in 0..3000 {
+        ctx.tick();
+        let transit = ctx.g.read::<Transit>();
+        let h = &ctx.g.world.humans[human];
+        if matches!(h.location, Location::Vehicle(_)) {
+            rode = true;
+        }
+        if transit.lines().values().next().unwrap().ridership == 1 {
+            assert!(transit.trip(human).is_none());
+            assert_eq!(h.location, Location::Outside);
+            assert!(h.trans.pos.is_close(transit.stops()[b].pos, 20.0));
+            return;
+        }
+    }
+    panic!("no trip was completed, rode the bus: {}", rode);
+}
+
+#[test]
+fn car_left_at_home_is_not_walked_back_to() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(400.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(20.0, 20.0));
+    let a = add_stop(&mut ctx, 30.0);
+    let b = add_stop(&mut ctx, 330.0);
+    add_line(&mut ctx, vec![a, b], 1);
+    ctx.tick();
+
+    let human = spawn_human(&mut ctx.g, house).unwrap();
+    let car = ctx.g.world.humans[human].router.personal_car.unwrap();
+    let from = vec3(340.0, 15.0, 0.0);
+    let dest = vec3(25.0, 15.0, 0.0);
+    {
+        // rode the bus to work, the car stayed at home
+        let h = ctx.g.world.humans.get_mut(human).unwrap();
+        h.location = Location::Outside;
+        h.trans.pos = from;
+        h.decision.kind = HumanDecisionKind::GoTo(Destination::Outside(dest));
+        h.decision.wait = 0;
+    }
+    assert!(ctx.g.read::<Transit>().plan(from, dest).is_some());
+
+    for _ in 0..1000 {
+        ctx.tick();
+        let h = &ctx.g.world.humans[human];
+        assert_ne!(h.location, Location::Vehicle(car));
+        if ctx.g.read::<Transit>().trip(human).is_some() {
+            return;
+        }
+    }
+    panic!("the bus should be faster than walking back to the car");
+}
+
+#[test]
+fn removing_shared_stops_doesnt_break_lines() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(400.0, 0.0, 0.0)]);
+    let a = add_stop(&mut ctx, 30.0);
+    let b = add_stop(&mut ctx, 200.0);
+    let c = add_stop(&mut ctx, 370.0);
+    add_line(&mut ctx, vec![a, b], 2);
+    add_line(&mut ctx, vec![b, c, b], 1);
+
+    for _ in 0..200 {
+        ctx.tick();
+    }
+    assert_eq!(n_buses(&ctx), 3);
+    assert!(ctx
+        .g
+        .read::<Transit>()
+        .plan(vec3(30.0, 8.0, 0.0), vec3(370.0, 8.0, 0.0))
+        .is_some());
+
+    ctx.apply(&[WorldCommand::RemoveBusStop(b)]);
+    for _ in 0..10 {
+        ctx.tick();
+    }
+
+    // a line needs two stops to run
+    assert_eq!(n_buses(&ctx), 0);
+    let transit = ctx.g.read::<Transit>();
+    assert!(transit.lines().values().all(|l| !l.stops.contains(&b)));
+    assert!(transit
+        .plan(vec3(30.0, 8.0, 0.0), vec3(370.0, 8.0, 0.0))
+        .is_none());
+}
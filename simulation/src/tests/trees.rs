@@ -0,0 +1,71 @@
+use geom::{vec2, Circle, Vec2};
+use prototypes::{prototypes_iter, TreePrototype};
+
+use crate::economy::Government;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+const RADIUS: f32 = 50.0;
+
+/// A place where the whole circle is on land
+fn find_land(ctx: &TestCtx) -> Vec2 {
+    let map = ctx.g.map();
+    (0..8)
+        .flat_map(|y| (0..8).map(move |x| vec2(100.0 + x as f32 * 40.0, 100.0 + y as f32 * 40.0)))
+        .find(|&center| {
+            (0..16).all(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / 16.0;
+                let p = center + vec2(angle.cos(), angle.sin()) * RADIUS;
+                map.can_plant_tree(center) && map.can_plant_tree(p)
+            })
+        })
+        .expect("no land to plant trees on")
+}
+
+#[test]
+fn plant_then_erase_trees() {
+    let mut ctx = TestCtx::new();
+    let center = find_land(&ctx);
+    let circle = Circle {
+        center,
+        radius: RADIUS,
+    };
+
+    // the trees generated with the map are not counted
+    ctx.apply(&[WorldCommand::MapRemoveTrees {
+        stroke: vec![center],
+        radius: RADIUS,
+    }]);
+    assert!(ctx.g.map().environment.trees_near(circle).is_empty());
+
+    const N: usize = 100;
+    let species = prototypes_iter::<TreePrototype>().next().unwrap().id;
+    let mut rng = common::rand::gen(1);
+    let trees = (0..N)
+        .map(|_| {
+            let r = RADIUS * 0.9 * rng.next_f32().sqrt();
+            let angle = std::f32::consts::TAU * rng.next_f32();
+            (center + vec2(angle.cos(), angle.sin()) * r, species)
+        })
+        .collect();
+
+    let money_before = ctx.g.read::<Government>().money;
+    ctx.apply(&[WorldCommand::MapPlantTrees { trees }]);
+    assert!(ctx.g.read::<Government>().money < money_before);
+
+    let planted = ctx.g.map().environment.trees_near(circle).len();
+    assert!(planted >= N * 9 / 10 && planted <= N, "{} trees", planted);
+
+    let money_before = ctx.g.read::<Government>().money;
+    ctx.apply(&[WorldCommand::MapRemoveTrees {
+        stroke: vec![center],
+        radius: RADIUS,
+    }]);
+    assert_eq!(ctx.g.read::<Government>().money, money_before);
+    assert!(ctx.g.map().environment.trees_near(circle).is_empty());
+
+    ctx.apply(&[WorldCommand::MapUndo]);
+    assert_eq!(ctx.g.map().environment.trees_near(circle).len(), planted);
+    ctx.tick();
+}
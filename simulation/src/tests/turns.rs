@@ -0,0 +1,105 @@
+use geom::{vec3, Vec3};
+use prototypes::GameTime;
+
+use crate::map::{
+    IntersectionID, LaneKind, Map, PathKind, ProjectFilter, ProjectKind, TraverseKind, TurnID,
+};
+use crate::map_dynamic::Itinerary;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+/// Driving lane of the road between the two intersections, going from src
+fn driving_lane_from(map: &Map, src: IntersectionID, dst: IntersectionID) -> Vec3 {
+    let road = map
+        .find_road(src, dst)
+        .or_else(|| map.find_road(dst, src))
+        .unwrap();
+    let lane = map.roads()[road]
+        .lanes_iter()
+        .map(|(id, _)| &map.lanes()[id])
+        .find(|l| l.kind == LaneKind::Driving && l.src == src)
+        .unwrap();
+    lane.points.point_along(lane.points.length() * 0.5)
+}
+
+fn route_turns(ctx: &TestCtx, start: Vec3, end: Vec3) -> Vec<TurnID> {
+    let tick = ctx.g.read::<GameTime>().tick;
+    let it = Itinerary::route(tick, start, end, &ctx.g.map(), PathKind::Vehicle).unwrap();
+    let route = it.get_route().unwrap();
+    std::iter::once(&route.cur)
+        .chain(&route.reversed_route)
+        .filter_map(|t| match t.kind {
+            TraverseKind::Turn(id) => Some(id),
+            TraverseKind::Lane(_) | TraverseKind::Crossing(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn disabled_left_turn_is_avoided() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[vec3(0.0, -100.0, 0.0), Vec3::ZERO, vec3(0.0, 100.0, 0.0)]);
+    ctx.build_roads(&[vec3(-100.0, 0.0, 0.0), Vec3::ZERO, vec3(100.0, 0.0, 0.0)]);
+
+    let inter_id = |pos: Vec3| match ctx.g.map().project(pos, 0.0, ProjectFilter::INTER).kind {
+        ProjectKind::Inter(id) => id,
+        _ => panic!("no intersection at {:?}", pos),
+    };
+    let center = inter_id(Vec3::ZERO);
+    let south = inter_id(vec3(0.0, -100.0, 0.0));
+    let west = inter_id(vec3(-100.0, 0.0, 0.0));
+
+    // going north from the south road then west is a left turn at the center
+    let (start, end) = {
+        let map = ctx.g.map();
+        (
+            driving_lane_from(&map, south, center),
+            driving_lane_from(&map, center, west),
+        )
+    };
+
+    let (from_road, to_road) = {
+        let map = ctx.g.map();
+        let from = map
+            .find_road(south, center)
+            .or(map.find_road(center, south));
+        let to = map.find_road(center, west).or(map.find_road(west, center));
+        (from.unwrap(), to.unwrap())
+    };
+    let is_left_turn = |map: &Map, id: &TurnID| {
+        id.parent == center
+            && map.lanes()[id.src].parent == from_road
+            && map.lanes()[id.dst].parent == to_road
+    };
+
+    let left_turns: Vec<TurnID> = route_turns(&ctx, start, end)
+        .into_iter()
+        .filter(|id| is_left_turn(&ctx.g.map(), id))
+        .collect();
+    assert!(!left_turns.is_empty());
+
+    let (turn, light) = {
+        let map = ctx.g.map();
+        let i = &map.intersections()[center];
+        (i.turn_policy, i.light_policy)
+    };
+    ctx.apply(&[WorldCommand::MapUpdateIntersectionPolicy {
+        inter: center,
+        turn,
+        light,
+        timing: None,
+        disabled_turns: left_turns.iter().copied().collect(),
+    }]);
+
+    let map = ctx.g.map();
+    assert!(map.intersections()[center]
+        .turns()
+        .all(|t| !left_turns.contains(&t.id)));
+    drop(map);
+
+    let turns = route_turns(&ctx, start, end);
+    assert!(!turns.is_empty());
+    assert!(turns.iter().all(|id| !is_left_turn(&ctx.g.map(), id)));
+}
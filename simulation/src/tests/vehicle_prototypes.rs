@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use geom::{vec3, Color, Transform, Vec3};
+use prototypes::{GameTime, RoadVehicleID, RoadVehiclePrototype, DELTA};
+
+use crate::map::{LaneKind, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleKind, VehicleState};
+use crate::utils::rand_provider::RandProvider;
+use crate::world::VehicleID;
+
+use super::TestCtx;
+
+#[test]
+fn car_spawns_follow_weights() {
+    let _ctx = TestCtx::new();
+
+    let spawnable: Vec<_> = RoadVehiclePrototype::iter()
+        .filter(|p| p.spawn_weight > 0.0)
+        .collect();
+    assert!(spawnable.len() >= 2);
+    let total: f32 = spawnable.iter().map(|p| p.spawn_weight).sum();
+
+    const N: u32 = 4000;
+    let mut rng = RandProvider::new(42);
+    let mut counts: BTreeMap<RoadVehicleID, u32> = BTreeMap::new();
+    for _ in 0..N {
+        *counts
+            .entry(VehicleKind::Car.random_prototype(&mut rng))
+            .or_default() += 1;
+    }
+
+    for proto in RoadVehiclePrototype::iter() {
+        let share = counts.get(&proto.id).copied().unwrap_or(0) as f32 / N as f32;
+        let expected = proto.spawn_weight / total;
+        assert!(
+            (share - expected).abs() < 0.03,
+            "{} spawned {share}, expected {expected}",
+            proto.name
+        );
+    }
+
+    let truck = VehicleKind::Truck.random_prototype(&mut rng);
+    assert_eq!(truck.prototype().spawn_weight, 0.0);
+}
+
+/// Vehicle already driving at `along` meters on the eastbound lane, heading to its end
+fn spawn_driving(
+    ctx: &mut TestCtx,
+    kind: VehicleKind,
+    proto: &str,
+    along: f32,
+    end: f32,
+) -> VehicleID {
+    let map = ctx.g.map();
+    let lane = map
+        .lanes()
+        .values()
+        .find(|l| l.kind == LaneKind::Driving && l.points.last().x > l.points.first().x)
+        .unwrap();
+    let pos = lane.points.point_along(along);
+    let end = lane.points.point_along(end);
+    let tick = ctx.g.read::<GameTime>().tick;
+    let it = Itinerary::route(tick, pos, end, &map, PathKind::Vehicle).unwrap();
+    drop(map);
+
+    let vehicle = Vehicle {
+        ang_velocity: 0.0,
+        wait_time: 0.0,
+        max_speed_multiplier: 1.0,
+        state: VehicleState::Driving,
+        kind,
+        prototype: RoadVehicleID::new(proto),
+        tint: Color::WHITE,
+        flag: 0,
+    };
+    make_vehicle_entity(
+        &mut ctx.g,
+        Transform::new_dir(pos, Vec3::X),
+        vehicle,
+        it,
+        true,
+    )
+}
+
+#[test]
+fn slow_truck_delays_followers() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(1000.0, 0.0, 0.0)]);
+
+    let truck = spawn_driving(&mut ctx, VehicleKind::Truck, "heavy_truck", 150.0, 900.0);
+    let car = spawn_driving(&mut ctx, VehicleKind::Car, "simple_car", 100.0, 900.0);
+
+    let (truck_len, car_len, car_speed) = {
+        let vehicles = &ctx.g.world().vehicles;
+        let car = &vehicles[car].vehicle;
+        (
+            vehicles[truck].vehicle.length(),
+            car.length(),
+            car.cruise_speed(9.0),
+        )
+    };
+    assert!(truck_len > car_len);
+
+    let mut caught_up = None;
+    for i in 0..5000 {
+        ctx.tick();
+
+        let vehicles = &ctx.g.world().vehicles;
+        let t = vehicles[truck].trans.pos;
+        let c = vehicles[car].trans.pos;
+
+        assert!(c.x < t.x, "car passed through the truck at tick {i}");
+        assert!(
+            t.distance(c) >= (truck_len + car_len) * 0.5,
+            "car overlaps the truck at tick {i}"
+        );
+
+        if caught_up.is_none() && t.distance(c) < 15.0 {
+            caught_up = Some((i, c.x));
+        }
+        if let Some((since, x)) = caught_up {
+            if i == since + 1000 {
+                // stuck behind the truck instead of driving at its own speed
+                let speed = (c.x - x) / (1000.0 * DELTA);
+                assert!(speed < car_speed * 0.9, "follower drove at {speed}m/s");
+                return;
+            }
+        }
+    }
+
+    panic!("car never caught up with the truck");
+}
@@ -0,0 +1,97 @@
+use geom::{vec2, vec3, Vec3, OBB};
+use prototypes::{GameTime, GoodsCompanyID};
+
+use crate::map_dynamic::{
+    BuildingInfos, ElectricityFlow, Fires, Garbage, WaterFlow, HOUSE_WATER_CONSUMPTION, WATER_GRACE,
+};
+use crate::weather::{Weather, WeatherKind};
+use crate::{BuildingKind, SoulID, WorldCommand};
+
+use super::TestCtx;
+
+fn skip_grace(ctx: &mut TestCtx) {
+    let end = ctx.g.read::<GameTime>().instant() + WATER_GRACE;
+    *ctx.g.write::<GameTime>() = GameTime::new(end.0);
+    ctx.tick();
+}
+
+#[test]
+fn houses_without_pump_are_dry() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    ctx.tick();
+
+    let network = ctx.g.map().electricity.net_id(house).unwrap();
+
+    // the city has time to build its first pump
+    assert!(ctx.g.read::<WaterFlow>().shortage(network));
+    assert!(!ctx.g.read::<WaterFlow>().is_dry(house));
+
+    skip_grace(&mut ctx);
+    let flow = ctx.g.read::<WaterFlow>();
+
+    assert!(flow.shortage(network));
+    assert!(flow.is_dry(house));
+    assert_eq!(
+        flow.network_stats(network).consumed,
+        HOUSE_WATER_CONSUMPTION
+    );
+    assert_eq!(flow.network_stats(network).produced, 0.0);
+}
+
+#[test]
+fn dry_companies_produce_half() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(300.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(60.0, 20.0));
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let bakery = GoodsCompanyID::new("bakery");
+    ctx.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(200.0, -40.0), vec2(1.0, 0.0), 30.0, 30.0),
+        kind: BuildingKind::GoodsCompany(bakery),
+        gen: bakery.prototype().bgen,
+        zone: None,
+        connected_road: Some(road),
+    }]);
+    ctx.g.write::<Weather>().forced = Some(WeatherKind::Clear);
+    ctx.tick();
+
+    let Some(SoulID::Human(resident)) = ctx.g.read::<BuildingInfos>().owner(house) else {
+        panic!("a family should have moved in");
+    };
+    let (company, _) = ctx.g.world().companies.iter().next().unwrap();
+    ctx.g.world_mut_unchecked().companies[company]
+        .workers
+        .0
+        .push(resident);
+
+    let productivity = |ctx: &TestCtx| {
+        let c = &ctx.g.world().companies[company];
+        let proto = c.comp.proto.prototype();
+        let raw = c.raw_productivity(proto, None);
+        // the city has no power plant, only the water is looked at
+        let p = c.productivity(
+            proto,
+            None,
+            &ElectricityFlow::default(),
+            &ctx.g.read::<WaterFlow>(),
+            &Garbage::default(),
+            &Fires::default(),
+            &ctx.g.read::<Weather>(),
+        );
+        (raw, p)
+    };
+
+    let (raw, p) = productivity(&ctx);
+    assert!(raw > 0.0);
+    assert_eq!(p, raw);
+
+    skip_grace(&mut ctx);
+    let building = ctx.g.world().companies[company].comp.building;
+    assert!(ctx.g.read::<WaterFlow>().is_dry(building));
+    let (raw, p) = productivity(&ctx);
+    assert_eq!(p, raw * 0.5);
+}
@@ -0,0 +1,75 @@
+use geom::{vec3, Vec3};
+use prototypes::{GameTime, GoodsCompanyID, Season, Tick, DAYS_PER_SEASON, TICKS_PER_SECOND};
+
+use crate::map::PathKind;
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind};
+use crate::weather::{Weather, WeatherKind, SNOW_SPEED_FACTOR};
+
+use super::TestCtx;
+
+/// Highest speed reached by a car driving down a long road
+fn top_speed(weather: Option<WeatherKind>) -> f32 {
+    let mut ctx = TestCtx::new();
+    ctx.build_roads(&[vec3(-150.0, 0.0, 0.0), Vec3::ZERO, vec3(350.0, 0.0, 0.0)]);
+    ctx.g.write::<Weather>().forced = weather;
+
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec3(-100.0, 0.0, 0.0)).unwrap();
+    unpark(&mut ctx.g, car);
+    let start = ctx.g.world().vehicles[car].trans.pos;
+    let tick = ctx.g.read::<GameTime>().tick;
+    let it = Itinerary::route(
+        tick,
+        start,
+        vec3(330.0, 0.0, 0.0),
+        &ctx.g.map(),
+        PathKind::Vehicle,
+    )
+    .unwrap();
+    ctx.g.world_mut_unchecked().vehicles[car].it = it;
+
+    let mut top = 0.0f32;
+    for _ in 0..1000 {
+        ctx.tick();
+        if let Some(v) = ctx.g.world().vehicles.get(car) {
+            top = top.max(v.speed.0);
+        }
+    }
+    top
+}
+
+#[test]
+fn snow_slows_down_traffic() {
+    let clear = top_speed(Some(WeatherKind::Clear));
+    let snow = top_speed(Some(WeatherKind::Snow));
+
+    assert!(clear > 1.0);
+    assert!(snow > 1.0);
+    assert!(snow <= clear * SNOW_SPEED_FACTOR + 0.5);
+}
+
+#[test]
+fn snow_and_winter_slow_down_farms() {
+    let mut ctx = TestCtx::new();
+
+    let farm = GoodsCompanyID::new("cereal-farm").prototype();
+    let bakery = GoodsCompanyID::new("bakery").prototype();
+
+    ctx.g.write::<Weather>().forced = Some(WeatherKind::Clear);
+    ctx.tick();
+    assert_eq!(ctx.g.read::<Weather>().season(), Season::Spring);
+    assert_eq!(ctx.g.read::<Weather>().productivity_factor(farm), 1.0);
+
+    ctx.g.write::<Weather>().forced = Some(WeatherKind::Snow);
+    let snowy = ctx.g.read::<Weather>().productivity_factor(farm);
+    assert!(snowy < 1.0);
+    assert_eq!(ctx.g.read::<Weather>().productivity_factor(bakery), 1.0);
+
+    let winter = 3 * DAYS_PER_SEASON as u64 * GameTime::DAY as u64 * TICKS_PER_SECOND;
+    *ctx.g.write::<GameTime>() = GameTime::new(Tick(winter));
+    ctx.tick();
+    let weather = ctx.g.read::<Weather>();
+    assert_eq!(weather.season(), Season::Winter);
+    assert!(weather.productivity_factor(farm) < snowy);
+    assert_eq!(weather.productivity_factor(bakery), 1.0);
+}
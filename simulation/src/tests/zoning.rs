@@ -0,0 +1,32 @@
+use geom::{vec2, vec3, Vec3};
+
+use crate::economy::JobMarket;
+use crate::map_dynamic::{Garbage, ZoneDemand};
+use crate::WorldCommand;
+
+use super::TestCtx;
+
+fn demand(ctx: &TestCtx) -> ZoneDemand {
+    ZoneDemand::compute(
+        &ctx.g.map(),
+        &ctx.g.world,
+        &ctx.g.read::<JobMarket>(),
+        &ctx.g.read::<Garbage>(),
+    )
+}
+
+#[test]
+fn bulldozed_homes_create_residential_demand() {
+    let mut ctx = TestCtx::new();
+
+    ctx.build_roads(&[Vec3::ZERO, vec3(200.0, 0.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(100.0, 20.0));
+    ctx.tick();
+
+    // nobody is hiring, the residents are enough
+    assert_eq!(demand(&ctx).residential, 0.0);
+
+    ctx.apply(&[WorldCommand::MapRemoveBuilding(house)]);
+    ctx.tick();
+    assert!(demand(&ctx).residential > 0.0);
+}
@@ -0,0 +1,538 @@
+//! Planes land at the airports of the city, bringing tourists and business travelers,
+//! wait at the gate and take off again. Their flights are scripted along splines timed with
+//! the simulation clock, there is no flight physics.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Spline3, Vec2, Vec3};
+use prototypes::{
+    AirportPrototypeID, CompanyKind, GameDuration, GameInstant, GameTime, Money, Recipe,
+};
+
+use crate::economy::Market;
+use crate::map::{Building, BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::desire::is_open;
+use crate::souls::happiness::CityStats;
+use crate::souls::tourism::welcome_tourists;
+use crate::{CompanyID, Simulation, SoulID};
+
+/// Distance from the runway at which the planes start their approach
+const APPROACH_DISTANCE: f32 = 2000.0;
+
+/// Altitude of the planes when they start their approach
+const APPROACH_ALTITUDE: f32 = 250.0;
+
+/// Distance from the runway at which the planes leaving are out of sight
+const DEPARTURE_DISTANCE: f32 = 2500.0;
+
+/// Altitude of the planes when they are out of sight
+const DEPARTURE_ALTITUDE: f32 = 400.0;
+
+/// Population at which the airports get half of their flights
+const HALF_FLIGHTS_POPULATION: f32 = 2000.0;
+
+/// Business travelers stay this many hours in the city before flying back
+const BUSINESS_STAY_HOURS: u64 = 8;
+
+/// What a business traveler spends each hour at the shops around the airport
+pub const BUSINESS_HOURLY_SPEND: Money = Money::new_bucks(15);
+
+/// Shops farther than this from the airport are not visited by the business travelers
+const BUSINESS_RADIUS: f32 = 1500.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlightPhase {
+    /// Gliding down from the sky to the start of the runway
+    Approach,
+    /// Braking on the runway and rolling to the gate
+    Rollout,
+    /// Parked at the gate while the passengers get off and on
+    AtGate,
+    /// Rolling down the runway and climbing out of sight, or going around if the airport is gone
+    TakeOff,
+}
+
+impl FlightPhase {
+    /// How long the phase lasts in in-game seconds
+    pub fn duration(self) -> f64 {
+        match self {
+            FlightPhase::Approach => 300.0,
+            FlightPhase::Rollout => 120.0,
+            FlightPhase::AtGate => 45.0 * 60.0,
+            FlightPhase::TakeOff => 300.0,
+        }
+    }
+}
+
+/// A plane coming to an airport of the city and leaving it afterwards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flight {
+    pub airport: BuildingID,
+    pub phase: FlightPhase,
+    /// Timestamp at which the current phase started
+    pub since: f64,
+    /// Where the plane goes during the current phase, the gate is at its end when parked.
+    /// It is kept so that the planes in the air can finish it when the airport disappears.
+    pub path: Spline3,
+    pub passengers: u32,
+}
+
+impl Flight {
+    /// Where the plane is and where its nose points at the timestamp
+    pub fn pos_dir(&self, now: f64) -> (Vec3, Vec3) {
+        let s = ((now - self.since) / self.phase.duration()).clamp(0.0, 1.0) as f32;
+        let t = match self.phase {
+            FlightPhase::Approach => s,
+            // braking after touchdown
+            FlightPhase::Rollout => 1.0 - (1.0 - s) * (1.0 - s),
+            FlightPhase::AtGate => 1.0,
+            // accelerating down the runway
+            FlightPhase::TakeOff => s * s,
+        };
+        let dir = self
+            .path
+            .derivative(t)
+            .try_normalize()
+            .unwrap_or(self.path.to_derivative.normalize());
+        (self.path.get(t), dir)
+    }
+
+    /// Whether the plane is off the ground
+    pub fn is_airborne(&self, now: f64) -> bool {
+        let ground = match self.phase {
+            FlightPhase::Approach => self.path.to.z,
+            _ => self.path.from.z,
+        };
+        self.pos_dir(now).0.z > ground + 0.5
+    }
+}
+
+/// The runway goes along the longest side of the airport, the planes land toward the far end
+struct Runway {
+    /// Where the planes touch down
+    threshold: Vec3,
+    dir: Vec3,
+    length: f32,
+    /// Where the planes park, at the door of the terminal
+    gate: Vec3,
+}
+
+impl Runway {
+    fn new(b: &Building) -> Self {
+        let [a, c] = b.obb.axis();
+        let along = if a.mag2() >= c.mag2() { a } else { c };
+        let length = along.mag();
+        let dir = (along / length).z0();
+        let ground = b.door_pos.z;
+        Self {
+            threshold: (b.obb.center() - along * 0.4).z(ground),
+            dir,
+            length,
+            gate: b.door_pos,
+        }
+    }
+
+    fn approach(&self) -> Spline3 {
+        let from = (self.threshold - self.dir * APPROACH_DISTANCE).up(APPROACH_ALTITUDE);
+        Spline3 {
+            from,
+            to: self.threshold,
+            from_derivative: (self.threshold - from) / 3.0,
+            // flaring above the runway
+            to_derivative: self.dir * (APPROACH_DISTANCE / 3.0),
+        }
+    }
+
+    fn rollout(&self) -> Spline3 {
+        Spline3 {
+            from: self.threshold,
+            to: self.gate,
+            from_derivative: self.dir * (self.length * 0.3),
+            to_derivative: self.dir * (self.length * 0.1),
+        }
+    }
+
+    fn takeoff(&self) -> Spline3 {
+        climb_out(self.gate, self.dir, self.length * 0.6)
+    }
+}
+
+/// Rolls along the direction on the ground for a while before climbing out of sight
+fn climb_out(from: Vec3, dir: Vec3, ground_run: f32) -> Spline3 {
+    let to = (from + dir * (ground_run + DEPARTURE_DISTANCE)).up(DEPARTURE_ALTITUDE);
+    Spline3 {
+        from,
+        to,
+        from_derivative: dir * ground_run,
+        to_derivative: (to - from) / 3.0,
+    }
+}
+
+/// Flights and passengers of an airport
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AirportStats {
+    /// Flights owed by the schedule but not sent yet, they accumulate fractionally
+    pending: f32,
+    /// Flights landing each day at the current size of the city
+    pub schedule: f32,
+    day: i32,
+    pub flights_today: u32,
+    pub passengers_today: u32,
+    pub flights_yesterday: u32,
+    pub passengers_yesterday: u32,
+    /// Passengers who landed since the airport was built
+    pub passengers: u64,
+    /// Money spent by the business travelers at the shops since the airport was built
+    pub business_spending: Money,
+}
+
+impl AirportStats {
+    /// Starts counting a new day when the day changed
+    fn roll(&mut self, day: i32) {
+        if self.day == day {
+            return;
+        }
+        let consecutive = self.day + 1 == day;
+        self.day = day;
+        self.flights_yesterday = if consecutive { self.flights_today } else { 0 };
+        self.passengers_yesterday = if consecutive {
+            self.passengers_today
+        } else {
+            0
+        };
+        self.flights_today = 0;
+        self.passengers_today = 0;
+    }
+}
+
+/// Business travelers who came by plane and spend money at the shops around the airport
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusinessTrip {
+    pub airport: BuildingID,
+    pub travelers: u32,
+    /// Timestamp at which they fly back
+    pub until: f64,
+}
+
+/// The planes coming and going at the airports of the city
+#[derive(Default, Serialize, Deserialize)]
+pub struct AirTraffic {
+    last_update: Option<GameInstant>,
+    pub flights: Vec<Flight>,
+    pub airports: BTreeMap<BuildingID, AirportStats>,
+    pub trips: Vec<BusinessTrip>,
+}
+
+impl AirTraffic {
+    /// Sends a plane to the airport, it starts its approach right away
+    pub fn schedule_flight(
+        &mut self,
+        map: &Map,
+        airport: BuildingID,
+        passengers: u32,
+        now: f64,
+    ) -> bool {
+        let Some(b) = map.buildings().get(airport) else {
+            return false;
+        };
+        if !matches!(b.kind, BuildingKind::Airport(_)) {
+            return false;
+        }
+        self.flights.push(Flight {
+            airport,
+            phase: FlightPhase::Approach,
+            since: now,
+            path: Runway::new(b).approach(),
+            passengers,
+        });
+        true
+    }
+
+    /// Business travelers currently in the city who came through the airport
+    pub fn business_travelers(&self, airport: BuildingID) -> u32 {
+        self.trips
+            .iter()
+            .filter(|t| t.airport == airport)
+            .map(|t| t.travelers)
+            .sum()
+    }
+}
+
+/// Share of the flights an airport gets in a city of this size, between 0 and 1
+fn city_size_factor(population: u32) -> f32 {
+    population as f32 / (population as f32 + HALF_FLIGHTS_POPULATION)
+}
+
+/// Moves the planes through their phases, lands their passengers,
+/// and every in-game hour sends new planes and lets the business travelers shop
+pub fn air_traffic_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::air_traffic_system");
+    let (now, day) = {
+        let time = sim.read::<GameTime>();
+        (time.timestamp, time.daytime.day)
+    };
+
+    let mut landed: Vec<(BuildingID, u32)> = Vec::new();
+    {
+        let map = sim.map();
+        let mut air = sim.write::<AirTraffic>();
+        air.flights.retain_mut(|f| {
+            // time skips go through as many phases as needed
+            while now >= f.since + f.phase.duration() {
+                let end = f.since + f.phase.duration();
+                let airport = map.buildings().get(f.airport);
+                match f.phase {
+                    FlightPhase::Approach => match airport {
+                        Some(b) => {
+                            f.phase = FlightPhase::Rollout;
+                            f.path = Runway::new(b).rollout();
+                        }
+                        None => {
+                            // the airport disappeared, go around and leave
+                            f.phase = FlightPhase::TakeOff;
+                            f.path = climb_out(f.path.to, f.path.to_derivative.normalize(), 0.0);
+                            f.passengers = 0;
+                        }
+                    },
+                    FlightPhase::Rollout => {
+                        if airport.is_none() {
+                            return false;
+                        }
+                        f.phase = FlightPhase::AtGate;
+                        landed.push((f.airport, f.passengers));
+                    }
+                    FlightPhase::AtGate => {
+                        let Some(b) = airport else {
+                            return false;
+                        };
+                        f.phase = FlightPhase::TakeOff;
+                        f.path = Runway::new(b).takeoff();
+                    }
+                    FlightPhase::TakeOff => return false,
+                }
+                f.since = end;
+            }
+            // planes on the ground disappear with the airport
+            matches!(f.phase, FlightPhase::Approach | FlightPhase::TakeOff)
+                || map.buildings().contains_key(f.airport)
+        });
+    }
+
+    for (airport, passengers) in landed {
+        land_passengers(sim, airport, passengers, now, day);
+    }
+
+    let hours = {
+        let time = sim.read::<GameTime>();
+        let mut air = sim.write::<AirTraffic>();
+        let Some(last) = air.last_update else {
+            air.last_update = Some(time.instant());
+            return;
+        };
+        let elapsed = last.elapsed(&time);
+        if elapsed < GameDuration::from_minutes(60) {
+            return;
+        }
+        air.last_update = Some(time.instant());
+        elapsed.seconds() as f32 / GameTime::HOUR as f32
+    };
+
+    schedule_flights(sim, hours / 24.0, now, day);
+    business_shopping(sim, hours, now);
+}
+
+/// The tourists go to the hotels and the business travelers stay a few hours around the airport
+fn land_passengers(sim: &mut Simulation, airport: BuildingID, passengers: u32, now: f64, day: i32) {
+    let Some((proto, door)) = sim
+        .map()
+        .buildings()
+        .get(airport)
+        .and_then(|b| match b.kind {
+            BuildingKind::Airport(proto) => Some((proto, b.door_pos)),
+            _ => None,
+        })
+    else {
+        return;
+    };
+
+    let business = (passengers as f32 * proto.prototype().business_share).round() as u32;
+    let tourists = passengers.saturating_sub(business);
+    welcome_tourists(sim, door, tourists);
+
+    let mut air = sim.write::<AirTraffic>();
+    if business > 0 {
+        air.trips.push(BusinessTrip {
+            airport,
+            travelers: business,
+            until: now + (BUSINESS_STAY_HOURS * GameTime::HOUR as u64) as f64,
+        });
+    }
+    let stats = air.airports.entry(airport).or_default();
+    stats.roll(day);
+    stats.flights_today += 1;
+    stats.passengers_today += passengers;
+    stats.passengers += passengers as u64;
+}
+
+/// Sends new planes to the airports, more as the city grows
+fn schedule_flights(sim: &mut Simulation, days: f32, now: f64, day: i32) {
+    let size = city_size_factor(sim.read::<CityStats>().population);
+    let map = sim.map();
+    let mut air = sim.write::<AirTraffic>();
+
+    air.airports
+        .retain(|&id, _| map.buildings().contains_key(id));
+
+    let airports: Vec<(BuildingID, AirportPrototypeID)> = map
+        .buildings()
+        .iter()
+        .filter_map(|(id, b)| match b.kind {
+            BuildingKind::Airport(proto) => Some((id, proto)),
+            _ => None,
+        })
+        .collect();
+
+    for (id, proto) in airports {
+        let proto = proto.prototype();
+        let stats = air.airports.entry(id).or_default();
+        stats.roll(day);
+        stats.schedule = proto.flights_per_day * size;
+        stats.pending += stats.schedule * days;
+        if stats.pending < 1.0 {
+            continue;
+        }
+        // at most one new plane per hour, the others are cancelled
+        stats.pending = (stats.pending - 1.0).min(1.0);
+
+        let passengers = (proto.seats as f32 * (0.5 + 0.5 * size)).round() as u32;
+        air.schedule_flight(&map, id, passengers, now);
+    }
+}
+
+/// The business travelers buy the goods of the open shops around the airport they came through,
+/// then fly back when their trip is over
+fn business_shopping(sim: &mut Simulation, hours: f32, now: f64) {
+    let trips = std::mem::take(&mut sim.write::<AirTraffic>().trips);
+    let mut earnings: BTreeMap<CompanyID, Money> = BTreeMap::new();
+    let mut spending: BTreeMap<BuildingID, Money> = BTreeMap::new();
+    let since = now - hours as f64 * GameTime::HOUR as f64;
+    {
+        let map = sim.map();
+        let time = sim.read::<GameTime>();
+        let binfos = sim.read::<BuildingInfos>();
+        let mut market = sim.write::<Market>();
+        for trip in &trips {
+            let Some(airport) = map.buildings().get(trip.airport) else {
+                continue;
+            };
+            let shops: Vec<(CompanyID, Vec2)> = map
+                .spatial_map()
+                .query_around(
+                    airport.door_pos.xy(),
+                    BUSINESS_RADIUS,
+                    ProjectFilter::BUILDING,
+                )
+                .filter_map(|obj| {
+                    let ProjectKind::Building(id) = obj else {
+                        return None;
+                    };
+                    let b = map.buildings().get(id)?;
+                    let BuildingKind::GoodsCompany(proto) = b.kind else {
+                        return None;
+                    };
+                    if proto.prototype().kind != CompanyKind::Store || !is_open(&map, id, &time) {
+                        return None;
+                    }
+                    let Some(SoulID::GoodsCompany(company)) = binfos.owner(id) else {
+                        return None;
+                    };
+                    Some((company, b.door_pos.xy()))
+                })
+                .collect();
+            if shops.is_empty() {
+                continue;
+            }
+            // the travelers who flew back during a time skip only shop until they left
+            let stayed = ((trip.until - since) / GameTime::HOUR as f64).clamp(0.0, hours as f64);
+            let budget = BUSINESS_HOURLY_SPEND * (trip.travelers as f64 * stayed);
+            let share = budget / shops.len() as i64;
+            for (company, door) in shops {
+                let Some(c) = sim.world.companies.get(company) else {
+                    continue;
+                };
+                let recipe = &c.comp.proto.prototype().recipe;
+                let spent = buy_from_store(
+                    &mut market,
+                    SoulID::GoodsCompany(company),
+                    door,
+                    recipe,
+                    share,
+                );
+                *earnings.entry(company).or_default() += spent;
+                *spending.entry(trip.airport).or_default() += spent;
+            }
+        }
+    }
+
+    // the money comes from outside the city and pays for the goods sold by the shops
+    for (company, money) in earnings {
+        if let Some(c) = sim.world.companies.get_mut(company) {
+            c.finances.balance += money;
+        }
+    }
+
+    let mut air = sim.write::<AirTraffic>();
+    for (airport, money) in spending {
+        air.airports.entry(airport).or_default().business_spending += money;
+    }
+    air.trips = trips.into_iter().filter(|t| t.until > now).collect();
+}
+
+/// Buys as much as the budget allows of the goods on the shelves of the store at the market
+/// price: what it makes, or what it sells as is when it makes nothing.
+/// The store buys its supplies again to fill its shelves. Returns the money spent.
+fn buy_from_store(
+    market: &mut Market,
+    soul: SoulID,
+    near: Vec2,
+    recipe: &Recipe,
+    budget: Money,
+) -> Money {
+    let shelves = if recipe.production.is_empty() {
+        &recipe.consumption
+    } else {
+        &recipe.production
+    };
+
+    let mut spent = Money::ZERO;
+    for item in shelves {
+        let price = market.m(item.id).price();
+        let stock = market.capital(soul, item.id);
+        if price <= Money::ZERO || stock <= 0 {
+            continue;
+        }
+        let qty = ((budget - spent).inner() / price.inner()).min(stock as i64) as i32;
+        if qty <= 0 {
+            continue;
+        }
+        let left = market.produce(soul, item.id, -qty);
+        spent += price * qty as i64;
+
+        // the sell order cannot ask for more than what is left
+        if let Some(order) = market.m(item.id).sell_order(soul) {
+            let (pos, qty, stock) = (order.pos, order.qty, order.stock);
+            if left <= 0 {
+                market.cancel_sell(soul, item.id);
+            } else if qty > left as u32 {
+                market.sell(soul, pos, item.id, left as u32, stock);
+            }
+        }
+        if recipe.production.is_empty() {
+            market.buy_until(soul, near, item.id, item.amount as u32);
+        }
+    }
+    spent
+}
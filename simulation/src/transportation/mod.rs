@@ -10,13 +10,16 @@ pub use vehicle::*;
 
 use crate::map::BuildingID;
 use crate::utils::resources::Resources;
-use crate::world::VehicleID;
-use crate::{Simulation, World};
+use crate::world::{TrainID, VehicleID};
+use crate::{AnyEntity, Simulation, World};
 
+pub mod air_traffic;
+pub mod passenger_rail;
 pub mod pedestrian;
 pub mod road;
 pub mod testing_vehicles;
 pub mod train;
+pub mod transit;
 mod vehicle;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -24,6 +27,7 @@ pub enum Location {
     Outside,
     Vehicle(VehicleID),
     Building(BuildingID),
+    Train(TrainID),
 }
 debug_inspect_impl!(Location);
 
@@ -54,6 +58,10 @@ pub struct TransportState {
     pub height: f32,
     pub group: TransportationGroup,
     pub flag: u64,
+    /// Entity moving with it, set when the grid is synchronized
+    #[serde(skip)]
+    #[inspect(skip)]
+    pub owner: Option<AnyEntity>,
 }
 
 impl Default for TransportState {
@@ -65,6 +73,7 @@ impl Default for TransportState {
             height: 0.0,
             group: TransportationGroup::Unknown,
             flag: 0,
+            owner: None,
         }
     }
 }
@@ -89,9 +98,16 @@ pub fn transport_grid_synchronize(world: &mut World, resources: &mut Resources)
     let mut transport_grid = resources.write::<TransportGrid>();
 
     world.query_trans_speed_coll_vehicle().for_each(
-        |(trans, kin, coll, v): (&Transform, &Speed, Transporter, Option<&Vehicle>)| {
+        |(id, trans, kin, coll, v): (
+            AnyEntity,
+            &Transform,
+            &Speed,
+            Transporter,
+            Option<&Vehicle>,
+        )| {
             transport_grid.set_position(coll.0, trans.pos.xy());
             let (_, po) = transport_grid.get_mut(coll.0).unwrap(); // Unwrap ok: handle is deleted only when entity is deleted too
+            po.owner = Some(id);
             po.dir = trans.dir.xy();
             po.speed = kin.0;
             po.height = trans.pos.z;
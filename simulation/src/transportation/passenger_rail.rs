@@ -0,0 +1,531 @@
+use crate::map::{BuildingID, BuildingKind, LaneID, LaneKind, Map, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::transportation::transit::MAX_WAIT_TIME;
+use crate::transportation::{put_pedestrian_in_transport_grid, Location, TransportGrid};
+use crate::world::{HumanEnt, HumanID, TrainEnt, TrainID, WagonEnt};
+use crate::{ParCommandBuffer, Simulation, World};
+use geom::Vec3;
+use ordered_float::OrderedFloat;
+use prototypes::{GameTime, PassengerStationPrototypeID, Tick};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// Seconds a train waits at each station for passengers to get on and off
+pub const TRAIN_DWELL_TIME: f64 = 30.0;
+/// Stations farther than this from the tracks are not served
+pub const MAX_RAIL_DIST: f32 = 100.0;
+
+/// How often the stations are matched with the station buildings and the tracks
+const STATION_SYNC_TICKS: u64 = 25;
+/// How close to the stop a train must be to open its doors
+const STOP_TOLERANCE: f32 = 30.0;
+/// Space between two passengers queuing on the platform
+const QUEUE_SPACING: f32 = 1.2;
+
+/// Average speeds in m/s used to estimate how long a trip takes
+const WALK_SPEED: f32 = 1.4;
+const TRAIN_SPEED: f32 = 15.0;
+
+/// A passenger station building that is close enough to the tracks to be served
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RailStation {
+    pub proto: PassengerStationPrototypeID,
+    /// Where the queue of waiting passengers starts, at the door of the station
+    pub platform: Vec3,
+    /// Direction of the tracks along the platform, the queue grows that way
+    pub dir: Vec3,
+    /// Where the trains stop, on the closest rail lane
+    pub stop_pos: Vec3,
+    pub lane: LaneID,
+    /// Distance of the stop along the lane
+    pub dist: f32,
+    /// Number of completed trips that started at this station
+    pub ridership: u64,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum PassengerTrainState {
+    Moving,
+    Dwelling,
+}
+
+/// Trains go through every station in order and loop back to the first one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassengerTrain {
+    /// The station we are going to or are at
+    pub next: BuildingID,
+    pub state: PassengerTrainState,
+    pub capacity: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RailTripState {
+    Waiting,
+    Riding(TrainID),
+    /// The passenger got off at its station, or gave up
+    Done {
+        completed: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RailTrip {
+    pub from: BuildingID,
+    pub to: BuildingID,
+    /// Timestamp at which the passenger started waiting
+    pub since: f64,
+    pub state: RailTripState,
+}
+
+/// The stations to take the train between for a given trip
+#[derive(Debug, Copy, Clone)]
+pub struct RailPlan {
+    pub board: BuildingID,
+    pub alight: BuildingID,
+    /// Estimated duration of the whole trip in seconds, including walking
+    pub secs: f32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct PassengerRail {
+    stations: BTreeMap<BuildingID, RailStation>,
+    trains: BTreeMap<TrainID, PassengerTrain>,
+    trips: BTreeMap<HumanID, RailTrip>,
+}
+
+impl PassengerRail {
+    pub fn stations(&self) -> &BTreeMap<BuildingID, RailStation> {
+        &self.stations
+    }
+
+    /// Drops the station of a removed building without waiting for the next sync
+    pub(crate) fn forget_station(&mut self, building: BuildingID) {
+        self.stations.remove(&building);
+    }
+
+    pub fn trains(&self) -> &BTreeMap<TrainID, PassengerTrain> {
+        &self.trains
+    }
+
+    pub fn train(&self, t: TrainID) -> Option<&PassengerTrain> {
+        self.trains.get(&t)
+    }
+
+    pub fn trip(&self, h: HumanID) -> Option<&RailTrip> {
+        self.trips.get(&h)
+    }
+
+    /// The passenger waits on the platform of `from` for a train stopping at `to`
+    pub fn wait_for_train(&mut self, h: HumanID, from: BuildingID, to: BuildingID, now: f64) {
+        self.trips.insert(
+            h,
+            RailTrip {
+                from,
+                to,
+                since: now,
+                state: RailTripState::Waiting,
+            },
+        );
+    }
+
+    /// Whether the passenger got off the train (or gave up waiting)
+    pub fn trip_over(&self, h: HumanID) -> bool {
+        self.trips
+            .get(&h)
+            .map_or(true, |t| matches!(t.state, RailTripState::Done { .. }))
+    }
+
+    /// Forgets the trip, counting it in the station's ridership if it was completed
+    pub fn end_trip(&mut self, h: HumanID) {
+        let Some(trip) = self.trips.remove(&h) else {
+            return;
+        };
+        if trip.state == (RailTripState::Done { completed: true }) {
+            if let Some(station) = self.stations.get_mut(&trip.from) {
+                station.ridership += 1;
+            }
+        }
+    }
+
+    /// Cancels the trip of a passenger that doesn't exist anymore
+    pub fn remove_passenger(&mut self, h: HumanID) {
+        self.trips.remove(&h);
+    }
+
+    /// Forgets a train that doesn't exist anymore, its passengers are let out by the system
+    pub fn remove_train(&mut self, t: TrainID) {
+        self.trains.remove(&t);
+    }
+
+    /// Number of passengers waiting on the platform of the station
+    pub fn waiting_at(&self, station: BuildingID) -> usize {
+        self.trips
+            .values()
+            .filter(|t| t.from == station && t.state == RailTripState::Waiting)
+            .count()
+    }
+
+    pub fn n_passengers(&self, train: TrainID) -> usize {
+        self.trips
+            .values()
+            .filter(|t| t.state == RailTripState::Riding(train))
+            .count()
+    }
+
+    /// Boards at the station closest to `from` and gets off at the one closest to `to`
+    pub fn plan(&self, from: Vec3, to: Vec3) -> Option<RailPlan> {
+        if self.trains.is_empty() || self.stations.len() < 2 {
+            return None;
+        }
+        let closest = |pos: Vec3| {
+            self.stations
+                .iter()
+                .min_by_key(|(_, s)| OrderedFloat(s.platform.distance2(pos)))
+                .map(|(&id, _)| id)
+        };
+        let board = closest(from)?;
+        let alight = closest(to)?;
+        if board == alight {
+            return None;
+        }
+
+        let loop_secs: f32 = self
+            .stations
+            .keys()
+            .map(|&id| ride_secs(&self.stations, id))
+            .sum();
+        let wait = loop_secs / self.trains.len() as f32 * 0.5;
+
+        let mut ride = 0.0;
+        let mut cur = board;
+        while cur != alight {
+            ride += ride_secs(&self.stations, cur);
+            cur = next_station(&self.stations, cur)?;
+        }
+
+        let walk_to = self.stations[&board].platform.distance(from) / WALK_SPEED;
+        let walk_from = self.stations[&alight].platform.distance(to) / WALK_SPEED;
+        Some(RailPlan {
+            board,
+            alight,
+            secs: walk_to + wait + ride + walk_from,
+        })
+    }
+}
+
+/// Estimated seconds from the station to the next one, including the stop
+fn ride_secs(stations: &BTreeMap<BuildingID, RailStation>, from: BuildingID) -> f32 {
+    let (Some(a), Some(b)) = (
+        stations.get(&from),
+        next_station(stations, from).and_then(|id| stations.get(&id)),
+    ) else {
+        return 0.0;
+    };
+    a.stop_pos.distance(b.stop_pos) / TRAIN_SPEED + TRAIN_DWELL_TIME as f32
+}
+
+/// The station served after `cur`, looping back to the first one
+fn next_station(
+    stations: &BTreeMap<BuildingID, RailStation>,
+    cur: BuildingID,
+) -> Option<BuildingID> {
+    stations
+        .range((Excluded(cur), Unbounded))
+        .next()
+        .or_else(|| stations.iter().next())
+        .map(|(&id, _)| id)
+}
+
+/// Finds the tracks the station is next to
+pub fn snap_station(
+    map: &Map,
+    proto: PassengerStationPrototypeID,
+    center: Vec3,
+    door: Vec3,
+) -> Option<RailStation> {
+    let lane = map.nearest_lane(center, LaneKind::Rail, Some(MAX_RAIL_DIST))?;
+    let lane = map.lanes().get(lane)?;
+    let (stop_pos, _, dir) = lane.points.project_segment_dir(center);
+    Some(RailStation {
+        proto,
+        platform: door,
+        dir,
+        stop_pos,
+        lane: lane.id,
+        dist: lane.points.length_at_proj(stop_pos),
+        ridership: 0,
+    })
+}
+
+/// Runs the passenger trains from station to station and moves the passengers in and out of them
+pub fn passenger_rail_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::passenger_rail_system");
+    let to_spawn = {
+        let time = sim.resources.read::<GameTime>();
+        let map = sim.resources.read::<Map>();
+        let mut rail = sim.resources.write::<PassengerRail>();
+        let mut grid = sim.resources.write::<TransportGrid>();
+        let cbuf_train = sim.resources.read::<ParCommandBuffer<TrainEnt>>();
+        let cbuf_wagon = sim.resources.read::<ParCommandBuffer<WagonEnt>>();
+        update_passenger_rail(
+            &mut sim.world,
+            &mut rail,
+            &map,
+            &mut grid,
+            (&cbuf_train, &cbuf_wagon),
+            time.tick,
+            time.timestamp,
+        )
+    };
+
+    for station_id in to_spawn {
+        let Some(station) = sim
+            .read::<PassengerRail>()
+            .stations
+            .get(&station_id)
+            .cloned()
+        else {
+            continue;
+        };
+        let proto = station.proto.prototype();
+        let Some(id) = spawn_train(
+            sim,
+            &proto.train,
+            RailWagonKind::Passenger,
+            station.lane,
+            station.dist,
+        ) else {
+            continue;
+        };
+
+        let mut rail = sim.write::<PassengerRail>();
+        let next = next_station(&rail.stations, station_id).unwrap_or(station_id);
+        rail.trains.insert(
+            id,
+            PassengerTrain {
+                next,
+                state: PassengerTrainState::Moving,
+                capacity: proto.train_capacity,
+            },
+        );
+    }
+}
+
+/// Returns the stations at which a new train should be spawned
+fn update_passenger_rail(
+    world: &mut World,
+    rail: &mut PassengerRail,
+    map: &Map,
+    grid: &mut TransportGrid,
+    (cbuf_train, cbuf_wagon): (&ParCommandBuffer<TrainEnt>, &ParCommandBuffer<WagonEnt>),
+    tick: Tick,
+    now: f64,
+) -> Vec<BuildingID> {
+    if tick.0 % STATION_SYNC_TICKS == 0 {
+        sync_stations(rail, map);
+    }
+
+    let PassengerRail {
+        stations,
+        trains,
+        trips,
+    } = rail;
+
+    // one train for every two stations
+    trains.retain(|id, _| world.trains.contains_key(*id));
+    let wanted = stations.len() / 2;
+    while trains.len() > wanted {
+        let (id, _) = trains.pop_last().unwrap(); // Unwrap ok: len > wanted >= 0
+        despawn_train(world, id, cbuf_train, cbuf_wagon);
+    }
+    let to_spawn = stations
+        .keys()
+        .step_by(2)
+        .skip(trains.len())
+        .take(wanted - trains.len())
+        .copied()
+        .collect();
+
+    // move the trains from station to station
+    for (&train_id, train) in trains.iter_mut() {
+        let Some(t) = world.trains.get_mut(train_id) else {
+            continue;
+        };
+        let Some(station) = stations.get(&train.next) else {
+            // the station was removed, go to the next one
+            if let Some(next) = next_station(stations, train.next) {
+                train.next = next;
+            }
+            train.state = PassengerTrainState::Moving;
+            t.it = Itinerary::NONE;
+            continue;
+        };
+
+        match train.state {
+            PassengerTrainState::Moving => {
+                if !t.it.has_ended(now) {
+                    continue;
+                }
+                if !t.trans.pos.is_close(station.stop_pos, STOP_TOLERANCE) {
+                    t.it = match Itinerary::route(
+                        tick,
+                        t.trans.pos,
+                        station.stop_pos,
+                        map,
+                        PathKind::Rail,
+                    ) {
+                        Some(r) => r,
+                        None => {
+                            // not reachable from here, try the next one after a while
+                            train.next = next_station(stations, train.next).unwrap_or(train.next);
+                            Itinerary::wait_until(now + TRAIN_DWELL_TIME)
+                        }
+                    };
+                    continue;
+                }
+                t.it = Itinerary::wait_until(now + TRAIN_DWELL_TIME);
+                train.state = PassengerTrainState::Dwelling;
+
+                let mut riding: u32 = 0;
+                for (&h, trip) in trips.iter_mut() {
+                    if trip.state != RailTripState::Riding(train_id) {
+                        continue;
+                    }
+                    if trip.to != train.next {
+                        riding += 1;
+                        continue;
+                    }
+                    trip.state = RailTripState::Done { completed: true };
+                    if let Some(h) = world.humans.get_mut(h) {
+                        alight(h, station.platform, grid);
+                    }
+                }
+
+                let mut waiting: Vec<_> = trips
+                    .iter_mut()
+                    .filter(|(_, t)| t.state == RailTripState::Waiting && t.from == train.next)
+                    .collect();
+                waiting.sort_by_key(|(_, t)| OrderedFloat(t.since));
+                for (&h, trip) in waiting {
+                    if riding >= train.capacity {
+                        break;
+                    }
+                    let Some(h) = world.humans.get_mut(h) else {
+                        continue;
+                    };
+                    trip.state = RailTripState::Riding(train_id);
+                    h.location = Location::Train(train_id);
+                    h.speed.0 = 0.0;
+                    if let Some(coll) = h.collider.take() {
+                        grid.remove_maintain(coll.0);
+                    }
+                    riding += 1;
+                }
+            }
+            PassengerTrainState::Dwelling => {
+                if !t.it.has_ended(now) {
+                    continue;
+                }
+                train.next = next_station(stations, train.next).unwrap_or(train.next);
+                train.state = PassengerTrainState::Moving;
+                t.it = Itinerary::NONE;
+            }
+        }
+    }
+
+    // passengers whose station or train disappeared give up
+    trips.retain(|&h, trip| {
+        let Some(human) = world.humans.get_mut(h) else {
+            return false;
+        };
+        match trip.state {
+            RailTripState::Waiting => {
+                let served = !trains.is_empty()
+                    && stations.contains_key(&trip.from)
+                    && stations.contains_key(&trip.to);
+                if !served || now - trip.since > MAX_WAIT_TIME {
+                    trip.state = RailTripState::Done { completed: false };
+                }
+            }
+            RailTripState::Riding(train) => {
+                if !trains.contains_key(&train) || !stations.contains_key(&trip.to) {
+                    let pos = world
+                        .trains
+                        .get(train)
+                        .map_or(human.trans.pos, |t| t.trans.pos);
+                    alight(human, pos, grid);
+                    trip.state = RailTripState::Done { completed: false };
+                }
+            }
+            RailTripState::Done { .. } => {}
+        }
+        true
+    });
+
+    // waiting passengers line up along the platform, first come first served
+    for (&station_id, station) in stations.iter() {
+        let mut waiting: Vec<_> = trips
+            .iter()
+            .filter(|(_, t)| t.state == RailTripState::Waiting && t.from == station_id)
+            .map(|(&h, t)| (h, t.since))
+            .collect();
+        waiting.sort_by_key(|&(_, since)| OrderedFloat(since));
+        for (i, (h, _)) in waiting.into_iter().enumerate() {
+            let Some(h) = world.humans.get_mut(h) else {
+                continue;
+            };
+            let slot = station.platform + station.dir * (i as f32 * QUEUE_SPACING);
+            if h.location == Location::Outside
+                && h.it.has_ended(0.0)
+                && !h.trans.pos.is_close(slot, 1.0)
+            {
+                h.it = Itinerary::wait_for_reroute(PathKind::Pedestrian, slot);
+            }
+        }
+    }
+
+    to_spawn
+}
+
+/// Matches the passenger station buildings with the closest tracks
+fn sync_stations(rail: &mut PassengerRail, map: &Map) {
+    let mut stations = BTreeMap::new();
+    for (id, b) in map.buildings() {
+        let BuildingKind::RailPassengerStation(proto) = b.kind else {
+            continue;
+        };
+        let center = b.obb.center().z(b.door_pos.z);
+        let Some(mut station) = snap_station(map, proto, center, b.door_pos) else {
+            continue;
+        };
+        if let Some(old) = rail.stations.get(&id) {
+            station.ridership = old.ridership;
+        }
+        stations.insert(id, station);
+    }
+    rail.stations = stations;
+}
+
+fn despawn_train(
+    world: &World,
+    id: TrainID,
+    cbuf_train: &ParCommandBuffer<TrainEnt>,
+    cbuf_wagon: &ParCommandBuffer<WagonEnt>,
+) {
+    cbuf_train.kill(id);
+    for (wagon_id, wagon) in world.wagons.iter() {
+        if wagon.itfollower.leader == id {
+            cbuf_wagon.kill(wagon_id);
+        }
+    }
+}
+
+fn alight(h: &mut HumanEnt, pos: Vec3, grid: &mut TransportGrid) {
+    h.location = Location::Outside;
+    h.trans.pos = pos;
+    if h.collider.is_none() {
+        h.collider = Some(put_pedestrian_in_transport_grid(grid, pos));
+    }
+}
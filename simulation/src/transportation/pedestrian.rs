@@ -1,3 +1,4 @@
+use crate::map::{LaneKind, Map, TraverseKind};
 use crate::map_dynamic::Itinerary;
 use crate::transportation::{
     Speed, TransportGrid, TransportState, TransportationGroup, Transporter,
@@ -6,8 +7,9 @@ use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
 use crate::World;
 use egui_inspect::Inspect;
-use geom::{angle_lerpxy, Color, Transform, Vec3};
+use geom::{angle_lerpxy, Color, Transform, Vec2, Vec3};
 use prototypes::DELTA;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Inspect)]
@@ -18,6 +20,26 @@ pub struct Pedestrian {
 
 const PED_SIZE: f32 = 0.5;
 
+/// Radius of a pedestrian in the transport grid
+pub const PED_RADIUS: f32 = PED_SIZE * 0.6;
+
+/// How far in meters pedestrians look around them to avoid each other
+pub const AVOIDANCE_RADIUS: f32 = 2.5;
+
+/// Only the closest neighbours are avoided
+pub const AVOIDANCE_MAX_NEIGHBORS: usize = 6;
+
+/// Above this many neighbours in the avoidance radius, the crowd is too dense to weave through
+/// and pedestrians simply follow their lane, which also bounds the cost of packed plazas
+pub const CROWD_DENSITY_THRESHOLD: usize = 12;
+
+/// How fast in meters per second pedestrians step aside
+pub const MAX_SIDESTEP_SPEED: f32 = 0.8;
+
+/// How much more pedestrians step aside for people walking toward them, to their right,
+/// or for slower people in front of them, to their left
+const PASSING_BIAS: f32 = 0.6;
+
 pub fn put_pedestrian_in_transport_grid(
     transport_grid: &mut TransportGrid,
     pos: Vec3,
@@ -25,7 +47,7 @@ pub fn put_pedestrian_in_transport_grid(
     Transporter(transport_grid.insert(
         pos.xy(),
         TransportState {
-            radius: PED_SIZE * 0.6,
+            radius: PED_RADIUS,
             group: TransportationGroup::Pedestrians,
             ..Default::default()
         },
@@ -65,12 +87,128 @@ pub fn random_pedestrian_shirt_color(r: &mut RandProvider) -> Color {
     unreachable!();
 }
 
-pub fn pedestrian_decision_system(world: &mut World, _resources: &mut Resources) {
+pub fn pedestrian_decision_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::pedestrian_decision_system");
-    world.humans
+    let map = &*resources.read::<Map>();
+    let grid = &*resources.read::<TransportGrid>();
+
+    // every pedestrian only reads the grid as it was at the end of the last tick,
+    // so the sidesteps are computed in parallel and applied in id order
+    let walkers: Vec<_> = world
+        .humans
+        .values()
+        .map(|h| (h.collider, &h.trans, &h.it, h.speed.0))
+        .collect();
+    let sidesteps: Vec<Vec3> = walkers
+        .par_iter()
+        .map(|&(collider, trans, it, speed)| match collider {
+            Some(collider) => sidestep(grid, map, collider, trans, it, speed),
+            None => Vec3::ZERO,
+        })
+        .collect();
+    drop(walkers);
+
+    world
+        .humans
         .values_mut()
-        //.par_bridge()
-        .for_each(|human| pedestrian_decision(&mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian))
+        .zip(sidesteps)
+        .for_each(|(human, step)| {
+            human.trans.pos += step;
+            pedestrian_decision(
+                &mut human.it,
+                &mut human.trans,
+                &mut human.speed,
+                &mut human.pedestrian,
+            )
+        })
+}
+
+/// How far the pedestrian steps aside this tick to avoid the people around it.
+/// Pedestrians only step across their sidewalk and stay on it, they keep following their lane
+/// elsewhere (crossings, turns) and in crowds denser than [`CROWD_DENSITY_THRESHOLD`].
+pub fn sidestep(
+    grid: &TransportGrid,
+    map: &Map,
+    me: Transporter,
+    trans: &Transform,
+    it: &Itinerary,
+    speed: f32,
+) -> Vec3 {
+    let Some(&TraverseKind::Lane(lane)) = it.get_travers().map(|t| &t.kind) else {
+        return Vec3::ZERO;
+    };
+    let Some(lane) = map.lanes().get(lane) else {
+        return Vec3::ZERO;
+    };
+    if lane.kind != LaneKind::Walking {
+        return Vec3::ZERO;
+    }
+    let Some(fwd) = it
+        .get_point()
+        .and_then(|p| (p - trans.pos).xy().try_normalize())
+    else {
+        return Vec3::ZERO;
+    };
+    let right = fwd.perpendicular();
+    let pos = trans.pos.xy();
+
+    // stop looking once the crowd is known to be too dense, the neighbours are sorted so that
+    // the result doesn't depend on the order of the grid
+    let mut neighbours: Vec<(f32, Vec2, &TransportState)> = grid
+        .query_around(pos, AVOIDANCE_RADIUS)
+        .filter(|&(h, _)| h != me.0)
+        .filter_map(|(h, p)| {
+            let (_, state) = grid.get(h)?;
+            let dist = p.distance(pos);
+            (state.group == TransportationGroup::Pedestrians && dist < AVOIDANCE_RADIUS)
+                .then_some((dist, p, state))
+        })
+        .take(CROWD_DENSITY_THRESHOLD + 1)
+        .collect();
+    if neighbours.is_empty() || neighbours.len() > CROWD_DENSITY_THRESHOLD {
+        return Vec3::ZERO;
+    }
+    neighbours.sort_unstable_by(|a, b| {
+        a.0.total_cmp(&b.0)
+            .then(a.1.x.total_cmp(&b.1.x))
+            .then(a.1.y.total_cmp(&b.1.y))
+    });
+
+    let mut push = Vec2::ZERO;
+    for &(dist, p, state) in neighbours.iter().take(AVOIDANCE_MAX_NEIGHBORS) {
+        let weight = 1.0 - dist / AVOIDANCE_RADIUS;
+        let away = pos - p;
+        // two pedestrians on the same spot split to their right
+        push += away.try_normalize().unwrap_or(right) * weight;
+
+        if away.dot(fwd) >= 0.0 {
+            // behind me, it is up to them to get out of the way
+            continue;
+        }
+        if state.dir.dot(fwd) < 0.0 {
+            push += right * weight * PASSING_BIAS;
+        } else if state.speed < speed {
+            push -= right * weight * PASSING_BIAS;
+        }
+    }
+
+    // only step across the sidewalk, and not off it
+    let (proj, _, lane_dir) = lane.points.project_segment_dir(trans.pos);
+    let Some(across) = lane_dir.xy().try_normalize().map(Vec2::perpendicular) else {
+        return Vec3::ZERO;
+    };
+    let max_offset = (lane.kind.width() * 0.5 - PED_RADIUS).max(0.0);
+    let offset = (pos - proj.xy()).dot(across);
+    if offset.abs() > max_offset {
+        // still getting on the sidewalk
+        return Vec3::ZERO;
+    }
+    let step = (push.dot(across) * MAX_SIDESTEP_SPEED)
+        .clamp(-MAX_SIDESTEP_SPEED, MAX_SIDESTEP_SPEED)
+        * DELTA;
+    let new_offset = (offset + step).clamp(-max_offset, max_offset);
+
+    (across * (new_offset - offset)).z(0.0)
 }
 
 pub fn pedestrian_decision(
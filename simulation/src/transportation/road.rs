@@ -1,22 +1,28 @@
-use crate::map::{Map, TrafficBehavior, Traversable, TraverseKind};
+use crate::map::{
+    LaneID, LaneKind, LaneSpeedSample, Map, TrafficBehavior, Traversable, TraverseKind,
+    LANE_SPEED_SAMPLE_TICKS,
+};
 use crate::map_dynamic::{Itinerary, OBJECTIVE_OK_DIST};
 use crate::transportation::{
     Speed, TransportGrid, TransportState, TransportationGroup, Transporter,
 };
 use crate::transportation::{Vehicle, VehicleState, TIME_TO_PARK};
 use crate::utils::resources::Resources;
+use crate::weather::Weather;
 use crate::world::{VehicleEnt, VehicleID};
 use crate::ParCommandBuffer;
 use crate::World;
 use geom::{angle_lerpxy, Ray, Transform, Vec2, Vec3};
-use prototypes::{GameTime, DELTA};
+use prototypes::{GameTime, DELTA, TICKS_PER_MINUTE};
 use slotmapd::Key;
+use std::collections::BTreeMap;
 
 pub fn vehicle_decision_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::vehicle_decision_system");
     let ra = &*resources.read();
     let rb = &*resources.read();
     let rc = &*resources.read();
+    let speed_factor = resources.read::<Weather>().speed_limit_factor();
 
     world.vehicles.iter_mut().for_each(|(ent, v)| {
         let Some(ref coll) = v.collider else {
@@ -27,6 +33,7 @@ pub fn vehicle_decision_system(world: &mut World, resources: &mut Resources) {
             ra,
             rb,
             rc,
+            speed_factor,
             ent,
             &mut v.it,
             &mut v.trans,
@@ -41,6 +48,7 @@ pub fn vehicle_decision(
     map: &Map,
     time: &GameTime,
     cow: &TransportGrid,
+    speed_factor: f32,
     me: VehicleID,
     it: &mut Itinerary,
     trans: &mut Transform,
@@ -56,13 +64,25 @@ pub fn vehicle_decision(
         vehicle.state,
         VehicleState::Driving | VehicleState::Panicking(_)
     ) {
-        let danger_length =
-            (self_obj.speed.powi(2) / (2.0 * vehicle.kind.deceleration())).min(100.0);
+        let danger_length = (self_obj.speed.powi(2) / (2.0 * vehicle.deceleration())).min(100.0);
         let neighbors = cow.query_around(trans.pos.xy(), 12.0 + danger_length);
         let objs =
             neighbors.map(|(id, pos)| (pos, cow.get(id).expect("Handle not in transport grid").1));
 
-        let (s, d) = calc_decision(me, vehicle, map, time, trans, self_obj, it, objs);
+        let crossing_dist = occupied_crossing_ahead(map, cow, it, trans, danger_length);
+
+        let (s, d) = calc_decision(
+            me,
+            vehicle,
+            map,
+            time,
+            speed_factor,
+            trans,
+            self_obj,
+            it,
+            objs,
+            crossing_dist,
+        );
         desired_speed = s;
         desired_dir = d;
     }
@@ -96,6 +116,63 @@ pub fn vehicle_state_update_system(world: &mut World, resources: &mut Resources)
     });
 }
 
+/// Number of ticks between two checks of a vehicle's route against the measured speeds
+const REROUTE_CHECK_TICKS: u64 = TICKS_PER_MINUTE;
+
+/// Measures the speed of the vehicles on each lane, updates the road noise it causes and reroutes
+/// the vehicles whose route got congested. Checks are spread over the ticks so that vehicles
+/// don't all reroute at once.
+pub fn lane_speeds_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("transportation::lane_speeds_system");
+    let tick = resources.read::<GameTime>().tick;
+    let speed_factor = resources.read::<Weather>().speed_limit_factor();
+
+    if tick.0 % LANE_SPEED_SAMPLE_TICKS == 0 {
+        let mut map = resources.write::<Map>();
+        let mut samples: BTreeMap<LaneID, LaneSpeedSample> = BTreeMap::new();
+
+        for v in world.vehicles.values() {
+            if !matches!(
+                v.vehicle.state,
+                VehicleState::Driving | VehicleState::Panicking(_)
+            ) {
+                continue;
+            }
+            let Some(Traversable {
+                kind: TraverseKind::Lane(id),
+                ..
+            }) = v.it.get_travers()
+            else {
+                continue;
+            };
+            let Some(lane) = map.lanes().get(*id) else {
+                continue;
+            };
+            let desired = v.vehicle.cruise_speed(lane.speed_limit * speed_factor);
+            if desired <= 0.0 {
+                continue;
+            }
+            let sample = samples.entry(*id).or_default();
+            sample.sum += v.speed.0 / desired;
+            sample.count += 1;
+        }
+
+        let map = &mut *map;
+        map.lane_speeds.update(&map.lanes, &samples);
+        map.update_noise();
+    }
+
+    let map = &*resources.read::<Map>();
+    world.vehicles.iter_mut().for_each(|(ent, v)| {
+        if tick.0.wrapping_add(ent.data().as_ffi()) % REROUTE_CHECK_TICKS != 0 {
+            return;
+        }
+        if v.it.is_congested(map, tick) {
+            v.it.reroute();
+        }
+    });
+}
+
 /// Decides whether a vehicle should change states, from parked to unparking to driving etc
 pub fn vehicle_state_update(
     buf: &ParCommandBuffer<VehicleEnt>,
@@ -165,7 +242,10 @@ fn physics(
     let kind = vehicle.kind;
 
     let speed = speed
-        + (desired_speed - speed).clamp(-DELTA * kind.deceleration(), DELTA * kind.acceleration());
+        + (desired_speed - speed).clamp(
+            -DELTA * vehicle.deceleration(),
+            DELTA * vehicle.acceleration(),
+        );
 
     let max_ang_vel = (speed.abs() / kind.min_turning_radius()).clamp(0.0, 3.0);
 
@@ -183,15 +263,18 @@ fn physics(
 }
 
 /// Decide the appropriate velocity and direction to aim for.
+/// `speed_factor` scales the speed limits, to drive slower in bad weather.
 pub fn calc_decision<'a>(
     me: VehicleID,
     vehicle: &mut Vehicle,
     map: &Map,
     time: &GameTime,
+    speed_factor: f32,
     trans: &Transform,
     self_obj: &TransportState,
     it: &Itinerary,
     neighs: impl Iterator<Item = (Vec2, &'a TransportState)>,
+    crossing_dist: Option<f32>,
 ) -> (f32, Vec3) {
     let default_return = (0.0, trans.dir);
     if vehicle.wait_time > 0.0 {
@@ -201,7 +284,7 @@ pub fn calc_decision<'a>(
     let objective: Vec3 = unwrap_or!(it.get_point(), return default_return);
 
     let speed = self_obj.speed;
-    let time_to_stop = speed / vehicle.kind.deceleration();
+    let time_to_stop = speed / vehicle.deceleration();
     let stop_dist = time_to_stop * speed * 0.5;
 
     let cutoff = (0.8 + stop_dist).min(1.5);
@@ -246,6 +329,13 @@ pub fn calc_decision<'a>(
         }
     }
 
+    // Yield to the pedestrians on the crossing ahead
+    if let Some(d) = crossing_dist {
+        if d - CROSSING_STOP_DIST < stop_dist + 1.0 {
+            return (0.0, dir_to_pos);
+        }
+    }
+
     let mut speed = 9.0;
 
     if let Some(Traversable {
@@ -254,7 +344,7 @@ pub fn calc_decision<'a>(
     }) = it.get_travers()
     {
         if let Some(l) = map.lanes().get(*l_id) {
-            speed = l.speed_limit;
+            speed = l.speed_limit * speed_factor;
 
             let light = l.control_point();
 
@@ -265,7 +355,7 @@ pub fn calc_decision<'a>(
                         OBJECTIVE_OK_DIST * 1.05
                             + 2.0
                             + stop_dist
-                            + (vehicle.kind.width() * 0.5 - OBJECTIVE_OK_DIST).max(0.0),
+                            + (vehicle.length() * 0.5 - OBJECTIVE_OK_DIST).max(0.0),
                     ) {
                         return (0.0, dir_to_pos);
                     }
@@ -290,10 +380,47 @@ pub fn calc_decision<'a>(
         return (6.0, dir_to_pos);
     }
 
-    (
-        vehicle.kind.speed_factor() * vehicle.max_speed_multiplier * speed,
-        dir_to_pos,
-    )
+    (vehicle.cruise_speed(speed), dir_to_pos)
+}
+
+/// Distance from the middle of a crossing at which vehicles stop to let pedestrians cross
+const CROSSING_STOP_DIST: f32 = 4.0;
+
+/// Distance to the closest crossing ahead on the current lane with a pedestrian on it.
+/// Crossings that are too close to stop before are ignored.
+fn occupied_crossing_ahead(
+    map: &Map,
+    cow: &TransportGrid,
+    it: &Itinerary,
+    trans: &Transform,
+    lookahead: f32,
+) -> Option<f32> {
+    let Some(Traversable {
+        kind: TraverseKind::Lane(lane),
+        ..
+    }) = it.get_travers()
+    else {
+        return None;
+    };
+    let road = map.roads().get(map.lanes().get(*lane)?.parent)?;
+    let radius = (road.width * 0.5 - LaneKind::Walking.width()).max(1.0);
+
+    road.crossings
+        .iter()
+        .filter_map(|c| {
+            let mid = c.points.middle();
+            let along = (mid - trans.pos).xy().dot(trans.dir.xy());
+            if along < CROSSING_STOP_DIST || along > lookahead + CROSSING_STOP_DIST + 5.0 {
+                return None;
+            }
+            let occupied = cow.query_around(mid.xy(), radius).any(|(id, _)| {
+                cow.get(id).map_or(false, |(_, obj)| {
+                    matches!(obj.group, TransportationGroup::Pedestrians)
+                })
+            });
+            occupied.then_some(along)
+        })
+        .min_by(|a, b| a.total_cmp(b))
 }
 
 /// Calculates the distance to the closest problematic object in front of the car.
@@ -315,7 +442,7 @@ fn calc_front_dist<'a>(
     let mut min_front_dist: f32 = 50.0;
 
     let my_ray = Ray {
-        from: position.xy() - direction.xy() * vehicle.kind.width() * 0.5,
+        from: position.xy() - direction.xy() * vehicle.length() * 0.5,
         dir: direction.xy(),
     };
 
@@ -20,6 +20,8 @@ use crate::{Itinerary, ItineraryLeader, Simulation, World};
 pub struct TrainReservations {
     pub reservations: BTreeMap<IntersectionID, TrainID>,
     pub localisations: BTreeMap<TraverseKind, BTreeMap<TrainID, f32>>,
+    /// Rail lanes act as signaling blocks, a train can only enter a block it holds
+    pub blocks: BTreeMap<LaneID, TrainID>,
 }
 
 #[derive(Serialize, Deserialize, Inspect)]
@@ -40,6 +42,7 @@ pub struct LocomotiveReservation {
     pub waited_for: f32,
     past_travers: BTreeMap<TraverseKind, f32>,
     upcoming_inters: Vec<IntersectionID>,
+    upcoming_blocks: Vec<LaneID>,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Inspect)]
@@ -157,6 +160,7 @@ pub fn spawn_train(
                 dist - lane.points.length(),
             )]),
             upcoming_inters: Default::default(),
+            upcoming_blocks: Default::default(),
         },
         leader: ItineraryLeader {
             past: Polyline3Queue::new(points.into_iter(), locopos, train_length + 20.0),
@@ -198,6 +202,18 @@ pub fn spawn_train(
     Some(loco)
 }
 
+impl TrainReservations {
+    /// Releases everything held by a train that doesn't exist anymore
+    pub fn remove_train(&mut self, id: TrainID) {
+        self.reservations.retain(|_, &mut t| t != id);
+        self.blocks.retain(|_, &mut t| t != id);
+        self.localisations.retain(|_, l| {
+            l.remove(&id);
+            !l.is_empty()
+        });
+    }
+}
+
 pub fn traverse_forward<'a>(
     map: &'a Map,
     itin: &'a Itinerary,
@@ -208,13 +224,14 @@ pub fn traverse_forward<'a>(
     let route = itin.get_route();
     let lanes = map.lanes();
     let inters = map.intersections();
+    let roads = map.roads();
     let mut acc_inter = 0.0;
     route
         .into_iter()
         .flat_map(move |route| route.reversed_route.iter().rev())
         .filter_map(move |v| {
             let oldacc = acc;
-            let l = v.kind.length(lanes, inters)?;
+            let l = v.kind.length(lanes, inters, roads)?;
 
             match v.kind {
                 TraverseKind::Turn(id) if inters.get(id.parent)?.roads.len() > 2 => acc_inter = 0.0,
@@ -233,12 +250,13 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
     let reservations = &mut *resources.write::<TrainReservations>();
     let lanes = map.lanes();
     let inters = map.intersections();
+    let roads = map.roads();
     world.trains.iter_mut().for_each(move |(me, train)| {
         // Remember when we've been
         if let Some(travers) = train.it.get_travers() {
             match train.res.past_travers.entry(travers.kind) {
                 Entry::Vacant(v) => {
-                    v.insert(10.0 - travers.kind.length(lanes, inters).unwrap_or(0.0));
+                    v.insert(10.0 - travers.kind.length(lanes, inters, roads).unwrap_or(0.0));
                     train.res.cur_travers_dist = 0.0;
                 }
                 Entry::Occupied(_) => {}
@@ -249,11 +267,17 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
             for v in train.res.upcoming_inters.drain(..) {
                 reservations.reservations.remove(&v);
             }
+            for v in train.res.upcoming_blocks.drain(..) {
+                if reservations.blocks.get(&v) == Some(&me) {
+                    reservations.blocks.remove(&v);
+                }
+            }
 
-            let dist_to_next =
-                travers.kind.length(lanes, inters).unwrap_or(0.0) - train.res.cur_travers_dist;
+            let dist_to_next = travers.kind.length(lanes, inters, roads).unwrap_or(0.0)
+                - train.res.cur_travers_dist;
 
             let mut want_to_reserve = vec![];
+            let mut want_blocks = vec![];
             let mut all_ok = true;
             // Then look ahead stop_dist to reserve all intersections
             let stop_dist = train.speed.0 * train.speed.0 / (2.0 * train.locomotive.dec_force);
@@ -281,6 +305,16 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                             break;
                         }
                     }
+                    if let TraverseKind::Lane(id) = id {
+                        match reservations.blocks.get(&id) {
+                            Some(&other) if other != me => {
+                                all_ok = false;
+                                break;
+                            }
+                            Some(_) => {}
+                            None => want_blocks.push(id),
+                        }
+                    }
                     if let TraverseKind::Turn(id) = id {
                         if inters
                             .get(id.parent)
@@ -303,6 +337,10 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                         reservations.reservations.insert(id, me);
                         train.res.upcoming_inters.push(id);
                     }
+                    for id in want_blocks {
+                        reservations.blocks.insert(id, me);
+                        train.res.upcoming_blocks.push(id);
+                    }
                 }
             }
         }
@@ -316,8 +354,16 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                 .or_default()
                 .insert(me, *dist);
             if *dist >= length {
-                if let TraverseKind::Turn(id) = id {
-                    reservations.reservations.remove(&id.parent);
+                match id {
+                    TraverseKind::Turn(id) => {
+                        reservations.reservations.remove(&id.parent);
+                    }
+                    TraverseKind::Lane(id) => {
+                        if reservations.blocks.get(&id) == Some(&me) {
+                            reservations.blocks.remove(&id);
+                        }
+                    }
+                    TraverseKind::Crossing(_) => {}
                 }
                 let l = unwrap_ret!(reservations.localisations.get_mut(&id), false);
                 l.remove(&me);
@@ -326,8 +372,14 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                 }
                 return false;
             }
-            if let TraverseKind::Turn(id) = id {
-                reservations.reservations.entry(id.parent).or_insert(me);
+            match id {
+                TraverseKind::Turn(id) => {
+                    reservations.reservations.entry(id.parent).or_insert(me);
+                }
+                TraverseKind::Lane(id) => {
+                    reservations.blocks.entry(id).or_insert(me);
+                }
+                TraverseKind::Crossing(_) => {}
             }
 
             true
@@ -405,7 +457,7 @@ pub fn locomotive_desired_speed(
 
         let startl = travers
             .kind
-            .length(lanes, map.intersections())
+            .length(lanes, map.intersections(), map.roads())
             .unwrap_or(0.0);
         let dist_to_next = startl - mydist;
 
@@ -446,6 +498,16 @@ pub fn locomotive_desired_speed(
                     }
                 }
             }
+            if let TraverseKind::Lane(lane) = id {
+                // never enter a block held by another train, the one we're on is already ours
+                if id != travers.kind {
+                    if let Some(reserved_by) = reservs.blocks.get(&lane) {
+                        if *reserved_by != me {
+                            return 0.0;
+                        }
+                    }
+                }
+            }
             lastid = Some(id);
         }
     }
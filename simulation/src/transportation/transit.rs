@@ -0,0 +1,535 @@
+use crate::map::{LaneKind, Map, PathKind, RoadID};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::{
+    make_vehicle_entity, put_pedestrian_in_transport_grid, Location, TransportGrid, Vehicle,
+    VehicleKind, VehicleState,
+};
+use crate::world::{HumanEnt, HumanID, VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, Simulation, World};
+use geom::{Color, Transform, Vec3};
+use ordered_float::OrderedFloat;
+use prototypes::GameTime;
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, HopSlotMap};
+use std::collections::BTreeMap;
+
+new_key_type! {
+    pub struct BusStopID;
+    pub struct BusLineID;
+}
+
+/// Seconds a bus waits at each stop for passengers to get on and off
+pub const DWELL_TIME: f64 = 15.0;
+/// Passengers that don't see their bus coming after this many seconds give up and walk
+pub const MAX_WAIT_TIME: f64 = 1800.0;
+pub const BUS_CAPACITY: usize = 40;
+
+const LINE_COLORS: [u64; 6] = [
+    0xd8_22_00, 0x1a_6c_c0, 0x2e_a0_3c, 0xe0_a0_10, 0x8a_3c_b0, 0x20_a0_a0,
+];
+
+/// Average speeds in m/s used to estimate how long a trip takes
+const WALK_SPEED: f32 = 1.4;
+const CAR_SPEED: f32 = 8.0;
+const BUS_SPEED: f32 = 6.0;
+/// Time lost finding a spot and walking from and to the car
+const PARKING_OVERHEAD: f32 = 60.0;
+
+/// A bus stop on the side of a road
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusStop {
+    pub road: RoadID,
+    /// Where passengers wait, on the sidewalk
+    pub pos: Vec3,
+    /// Where the bus stops, on the closest driving lane
+    pub drive_pos: Vec3,
+    pub dir: Vec3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusLine {
+    pub name: String,
+    pub color: Color,
+    /// Stops in the order they are served, the line loops back to the first one
+    pub stops: Vec<BusStopID>,
+    /// How many buses should run on the line
+    pub n_buses: u32,
+    /// Number of completed trips on this line
+    pub ridership: u64,
+    pub buses: Vec<VehicleID>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum BusState {
+    Driving,
+    Dwelling,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bus {
+    pub line: BusLineID,
+    /// Index in the line's stops of the stop we are going to or are at
+    pub next: usize,
+    pub state: BusState,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TripState {
+    Waiting,
+    Riding(VehicleID),
+    /// The passenger got off at its stop, or gave up
+    Done {
+        completed: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trip {
+    pub line: BusLineID,
+    pub from: BusStopID,
+    pub to: BusStopID,
+    /// Timestamp at which the passenger started waiting
+    pub since: f64,
+    pub state: TripState,
+}
+
+/// The best way to ride the bus network for a given trip
+#[derive(Debug, Copy, Clone)]
+pub struct TransitPlan {
+    pub line: BusLineID,
+    pub board: BusStopID,
+    pub alight: BusStopID,
+    /// Estimated duration of the whole trip in seconds, including walking
+    pub secs: f32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Transit {
+    stops: HopSlotMap<BusStopID, BusStop>,
+    lines: HopSlotMap<BusLineID, BusLine>,
+    buses: BTreeMap<VehicleID, Bus>,
+    trips: BTreeMap<HumanID, Trip>,
+}
+
+impl Transit {
+    pub fn stops(&self) -> &HopSlotMap<BusStopID, BusStop> {
+        &self.stops
+    }
+
+    pub fn lines(&self) -> &HopSlotMap<BusLineID, BusLine> {
+        &self.lines
+    }
+
+    pub fn bus(&self, v: VehicleID) -> Option<&Bus> {
+        self.buses.get(&v)
+    }
+
+    pub fn trip(&self, h: HumanID) -> Option<&Trip> {
+        self.trips.get(&h)
+    }
+
+    pub fn add_stop(&mut self, stop: BusStop) -> BusStopID {
+        self.stops.insert(stop)
+    }
+
+    /// Removes the stop and takes it out of every line serving it
+    pub fn remove_stop(&mut self, id: BusStopID) {
+        self.stops.remove(id);
+        for line in self.lines.values_mut() {
+            line.stops.retain(|&s| s != id);
+        }
+    }
+
+    pub fn add_line(&mut self, name: String) -> BusLineID {
+        let color = LINE_COLORS[self.lines.len() % LINE_COLORS.len()];
+        self.lines.insert(BusLine {
+            name,
+            color: Color::from_hex(color),
+            stops: vec![],
+            n_buses: 1,
+            ridership: 0,
+            buses: vec![],
+        })
+    }
+
+    /// The buses of a removed line are taken off the road by the transit system
+    pub fn remove_line(&mut self, id: BusLineID) {
+        self.lines.remove(id);
+    }
+
+    pub fn update_line(&mut self, id: BusLineID, stops: &[BusStopID], n_buses: u32) {
+        let Some(line) = self.lines.get_mut(id) else {
+            return;
+        };
+        line.stops = stops
+            .iter()
+            .copied()
+            .filter(|&s| self.stops.contains_key(s))
+            .collect();
+        line.n_buses = n_buses;
+    }
+
+    /// The passenger waits at `from` for a bus of the line going to `to`
+    pub fn wait_for_bus(
+        &mut self,
+        h: HumanID,
+        line: BusLineID,
+        from: BusStopID,
+        to: BusStopID,
+        now: f64,
+    ) {
+        self.trips.insert(
+            h,
+            Trip {
+                line,
+                from,
+                to,
+                since: now,
+                state: TripState::Waiting,
+            },
+        );
+    }
+
+    /// Whether the passenger got off the bus (or gave up waiting)
+    pub fn trip_over(&self, h: HumanID) -> bool {
+        self.trips
+            .get(&h)
+            .map_or(true, |t| matches!(t.state, TripState::Done { .. }))
+    }
+
+    /// Forgets the trip, counting it in the line's ridership if it was completed
+    pub fn end_trip(&mut self, h: HumanID) {
+        let Some(trip) = self.trips.remove(&h) else {
+            return;
+        };
+        if trip.state == (TripState::Done { completed: true }) {
+            if let Some(line) = self.lines.get_mut(trip.line) {
+                line.ridership += 1;
+            }
+        }
+    }
+
+    /// Cancels the trip of a passenger that doesn't exist anymore
+    pub fn remove_passenger(&mut self, h: HumanID) {
+        self.trips.remove(&h);
+    }
+
+    /// Number of passengers waiting at the stop
+    pub fn waiting_at(&self, stop: BusStopID) -> usize {
+        self.trips
+            .values()
+            .filter(|t| t.from == stop && t.state == TripState::Waiting)
+            .count()
+    }
+
+    pub fn n_passengers(&self, bus: VehicleID) -> usize {
+        self.trips
+            .values()
+            .filter(|t| t.state == TripState::Riding(bus))
+            .count()
+    }
+
+    /// Finds the fastest line to go from `from` to `to`, transfers are not considered
+    pub fn plan(&self, from: Vec3, to: Vec3) -> Option<TransitPlan> {
+        let mut best: Option<TransitPlan> = None;
+        for (line_id, line) in &self.lines {
+            if line.buses.is_empty() || line.stops.len() < 2 {
+                continue;
+            }
+            let stops: Option<Vec<&BusStop>> =
+                line.stops.iter().map(|&s| self.stops.get(s)).collect();
+            let Some(stops) = stops else {
+                continue;
+            };
+            let n = stops.len();
+            let loop_secs: f32 = (0..n)
+                .map(|i| ride_secs(stops[i], stops[(i + 1) % n]))
+                .sum();
+            let wait = loop_secs / line.buses.len() as f32 * 0.5;
+
+            for i in 0..n {
+                let walk_to = stops[i].pos.distance(from) / WALK_SPEED;
+                let mut ride = 0.0;
+                for k in 1..n {
+                    let j = (i + k) % n;
+                    ride += ride_secs(stops[(j + n - 1) % n], stops[j]);
+                    let walk_from = stops[j].pos.distance(to) / WALK_SPEED;
+                    let secs = walk_to + wait + ride + walk_from;
+                    if best.map_or(true, |b| secs < b.secs) {
+                        best = Some(TransitPlan {
+                            line: line_id,
+                            board: line.stops[i],
+                            alight: line.stops[j],
+                            secs,
+                        });
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+fn ride_secs(a: &BusStop, b: &BusStop) -> f32 {
+    a.drive_pos.distance(b.drive_pos) / BUS_SPEED + DWELL_TIME as f32
+}
+
+/// Estimated duration in seconds of a trip without public transport
+pub fn direct_trip_secs(from: Vec3, to: Vec3, has_car: bool) -> f32 {
+    let d = from.distance(to);
+    if has_car {
+        d / CAR_SPEED + PARKING_OVERHEAD
+    } else {
+        d / WALK_SPEED
+    }
+}
+
+/// Snaps a position to the side of the closest road that has both a sidewalk and a driving lane
+pub fn snap_bus_stop(map: &Map, pos: Vec3) -> Option<BusStop> {
+    let walk = map.nearest_lane(pos, LaneKind::Walking, Some(20.0))?;
+    let walk = map.lanes().get(walk)?;
+    let road = map.roads().get(walk.parent)?;
+
+    let side = road
+        .outgoing_lanes_from(walk.src)
+        .iter()
+        .rfind(|&&(_, kind)| kind == LaneKind::Driving)?;
+    let drive = map.lanes().get(side.0)?;
+
+    let stop_pos = walk.points.project(pos);
+    let (drive_pos, _, dir) = drive.points.project_segment_dir(stop_pos);
+
+    Some(BusStop {
+        road: road.id,
+        pos: stop_pos,
+        drive_pos,
+        dir,
+    })
+}
+
+/// Moves the buses along their lines and the passengers in and out of them
+pub fn transit_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::transit_system");
+    let to_spawn = {
+        let now = sim.resources.read::<GameTime>().timestamp;
+        let map = sim.resources.read::<Map>();
+        let mut transit = sim.resources.write::<Transit>();
+        let mut grid = sim.resources.write::<TransportGrid>();
+        let cbuf = sim.resources.read::<ParCommandBuffer<VehicleEnt>>();
+        update_transit(&mut sim.world, &mut transit, &map, &mut grid, &cbuf, now)
+    };
+
+    for (line, first, color) in to_spawn {
+        let Some(stop) = sim.read::<Transit>().stops.get(first).cloned() else {
+            continue;
+        };
+        let vehicle = Vehicle {
+            ang_velocity: 0.0,
+            wait_time: 0.0,
+            max_speed_multiplier: 1.0,
+            state: VehicleState::Driving,
+            kind: VehicleKind::Bus,
+            prototype: VehicleKind::Bus.default_prototype().id,
+            tint: color,
+            flag: 0,
+        };
+        let it = Itinerary::wait_for_reroute(PathKind::Vehicle, stop.drive_pos);
+        let id = make_vehicle_entity(
+            sim,
+            Transform::new_dir(stop.drive_pos, stop.dir),
+            vehicle,
+            it,
+            true,
+        );
+
+        let mut transit = sim.write::<Transit>();
+        transit.buses.insert(
+            id,
+            Bus {
+                line,
+                next: 0,
+                state: BusState::Driving,
+            },
+        );
+        if let Some(l) = transit.lines.get_mut(line) {
+            l.buses.push(id);
+        }
+    }
+}
+
+/// Returns the buses to spawn, with the line they serve, its first stop and its color
+fn update_transit(
+    world: &mut World,
+    transit: &mut Transit,
+    map: &Map,
+    grid: &mut TransportGrid,
+    cbuf: &ParCommandBuffer<VehicleEnt>,
+    now: f64,
+) -> Vec<(BusLineID, BusStopID, Color)> {
+    // stops on removed roads disappear
+    let removed: Vec<BusStopID> = transit
+        .stops
+        .iter()
+        .filter(|(_, s)| !map.roads().contains_key(s.road))
+        .map(|(id, _)| id)
+        .collect();
+    for id in removed {
+        transit.remove_stop(id);
+    }
+
+    // match the number of buses with what was asked
+    let mut to_spawn = vec![];
+    for (line_id, line) in transit.lines.iter_mut() {
+        line.buses.retain(|b| world.vehicles.contains_key(*b));
+        let wanted = if line.stops.len() >= 2 {
+            line.n_buses as usize
+        } else {
+            0
+        };
+        while line.buses.len() > wanted {
+            let bus = line.buses.pop().unwrap(); // Unwrap ok: len > wanted >= 0
+            cbuf.kill(bus);
+        }
+        for _ in line.buses.len()..wanted {
+            to_spawn.push((line_id, line.stops[0], line.color));
+        }
+    }
+
+    transit.buses.retain(|id, bus| {
+        let alive = world.vehicles.contains_key(*id)
+            && transit
+                .lines
+                .get(bus.line)
+                .map_or(false, |l| l.buses.contains(id));
+        if !alive {
+            cbuf.kill(*id);
+        }
+        alive
+    });
+
+    // move the buses from stop to stop
+    for (&bus_id, bus) in transit.buses.iter_mut() {
+        let Some(line) = transit.lines.get(bus.line) else {
+            continue;
+        };
+        let Some(v) = world.vehicles.get_mut(bus_id) else {
+            continue;
+        };
+        bus.next %= line.stops.len();
+        let stop_id = line.stops[bus.next];
+        let Some(stop) = transit.stops.get(stop_id) else {
+            continue;
+        };
+
+        match bus.state {
+            BusState::Driving => {
+                if !v.it.has_ended(now) {
+                    continue;
+                }
+                if !v.trans.pos.is_close(stop.drive_pos, 15.0) {
+                    v.it = Itinerary::wait_for_reroute(PathKind::Vehicle, stop.drive_pos);
+                    continue;
+                }
+                v.it = Itinerary::wait_until(now + DWELL_TIME);
+                bus.state = BusState::Dwelling;
+
+                let mut riding = 0;
+                for (&h, trip) in transit.trips.iter_mut() {
+                    if trip.state != TripState::Riding(bus_id) {
+                        continue;
+                    }
+                    if trip.to != stop_id {
+                        riding += 1;
+                        continue;
+                    }
+                    trip.state = TripState::Done { completed: true };
+                    if let Some(h) = world.humans.get_mut(h) {
+                        alight(h, stop.pos, grid);
+                    }
+                }
+
+                for (&h, trip) in transit.trips.iter_mut() {
+                    if riding >= BUS_CAPACITY {
+                        break;
+                    }
+                    if trip.state != TripState::Waiting
+                        || trip.line != bus.line
+                        || trip.from != stop_id
+                    {
+                        continue;
+                    }
+                    let Some(h) = world.humans.get_mut(h) else {
+                        continue;
+                    };
+                    trip.state = TripState::Riding(bus_id);
+                    h.location = Location::Vehicle(bus_id);
+                    h.speed.0 = 0.0;
+                    if let Some(coll) = h.collider.take() {
+                        grid.remove_maintain(coll.0);
+                    }
+                    riding += 1;
+                }
+            }
+            BusState::Dwelling => {
+                if !v.it.has_ended(now) {
+                    continue;
+                }
+                bus.next = (bus.next + 1) % line.stops.len();
+                let Some(next) = transit.stops.get(line.stops[bus.next]) else {
+                    continue;
+                };
+                v.it = Itinerary::wait_for_reroute(PathKind::Vehicle, next.drive_pos);
+                bus.state = BusState::Driving;
+            }
+        }
+    }
+
+    // passengers whose line or bus disappeared give up
+    transit.trips.retain(|&h, trip| {
+        let Some(human) = world.humans.get_mut(h) else {
+            return false;
+        };
+        match trip.state {
+            TripState::Waiting => {
+                let served = transit.lines.get(trip.line).map_or(false, |l| {
+                    !l.buses.is_empty() && l.stops.contains(&trip.from)
+                }) && transit.stops.contains_key(trip.to);
+                if !served || now - trip.since > MAX_WAIT_TIME {
+                    trip.state = TripState::Done { completed: false };
+                }
+            }
+            TripState::Riding(bus) => {
+                if !transit.buses.contains_key(&bus) || !transit.stops.contains_key(trip.to) {
+                    let pos = world
+                        .vehicles
+                        .get(bus)
+                        .map_or(human.trans.pos, |v| v.trans.pos);
+                    alight(human, pos, grid);
+                    trip.state = TripState::Done { completed: false };
+                }
+            }
+            TripState::Done { .. } => {}
+        }
+        true
+    });
+
+    to_spawn
+}
+
+fn alight(h: &mut HumanEnt, pos: Vec3, grid: &mut TransportGrid) {
+    h.location = Location::Outside;
+    h.trans.pos = pos;
+    if h.collider.is_none() {
+        h.collider = Some(put_pedestrian_in_transport_grid(grid, pos));
+    }
+}
+
+/// The stop closest to the position, used to pick stops with the mouse
+pub fn closest_stop(transit: &Transit, pos: Vec3, max_dist: f32) -> Option<BusStopID> {
+    transit
+        .stops
+        .iter()
+        .filter(|(_, s)| s.pos.is_close(pos, max_dist))
+        .min_by_key(|(_, s)| OrderedFloat(s.pos.distance2(pos)))
+        .map(|(id, _)| id)
+}
@@ -1,4 +1,5 @@
 use crate::map_dynamic::{Itinerary, ParkingManagement, SpotReservation};
+use crate::migrations::decoding_version;
 use crate::transportation::{TransportGrid, TransportState, TransportationGroup, Transporter};
 use crate::utils::rand_provider::RandProvider;
 use crate::world::{VehicleEnt, VehicleID};
@@ -6,8 +7,8 @@ use crate::Simulation;
 use egui_inspect::Inspect;
 use geom::Transform;
 use geom::{Color, Spline3, Vec3};
-use prototypes::GameInstant;
-use serde::{Deserialize, Serialize};
+use prototypes::{try_prototype, GameInstant, RoadVehicleID, RoadVehiclePrototype};
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// The duration for the parking animation.
 pub const TIME_TO_PARK: f32 = 4.0;
@@ -38,6 +39,9 @@ pub struct Vehicle {
 
     pub state: VehicleState,
     pub kind: VehicleKind,
+    /// Falls back to the default prototype of the kind if its mod was removed
+    #[serde(deserialize_with = "deserialize_prototype")]
+    pub prototype: RoadVehicleID,
     pub tint: Color,
 
     /// Used to detect gridlock
@@ -58,26 +62,43 @@ pub fn put_vehicle_in_transport_grid(sim: &Simulation, w: f32, trans: Transform)
 }
 
 impl VehicleKind {
-    pub fn width(self) -> f32 {
-        match self {
-            VehicleKind::Car => 4.5,
-            VehicleKind::Truck => 6.0,
-            VehicleKind::Bus => 9.0,
-        }
+    /// Prototype used when none is specified or when the one of a saved vehicle was removed
+    pub fn default_prototype(self) -> &'static RoadVehiclePrototype {
+        let name = match self {
+            VehicleKind::Car => "simple_car",
+            VehicleKind::Truck => "simple_truck",
+            VehicleKind::Bus => "city_bus",
+        };
+        try_prototype(RoadVehicleID::new(name))
+            .or_else(|| RoadVehiclePrototype::iter().next())
+            .expect("no road-vehicle prototype loaded")
     }
 
-    pub fn acceleration(self) -> f32 {
-        match self {
-            VehicleKind::Car => 3.0,
-            VehicleKind::Truck => 2.5,
-            VehicleKind::Bus => 2.0,
+    /// Cars are picked according to the spawn weight of the prototypes,
+    /// other kinds use their default prototype
+    pub fn random_prototype(self, rng: &mut RandProvider) -> RoadVehicleID {
+        let default = self.default_prototype().id;
+        if !matches!(self, VehicleKind::Car) {
+            return default;
         }
-    }
 
-    pub fn deceleration(self) -> f32 {
-        match self {
-            VehicleKind::Car | VehicleKind::Bus | VehicleKind::Truck => 6.0,
+        let total: f32 = RoadVehiclePrototype::iter().map(|p| p.spawn_weight).sum();
+        if total <= 0.0 {
+            return default;
+        }
+
+        let r = rng.next_f32() * total;
+        let mut partial = 0.0;
+        for proto in RoadVehiclePrototype::iter() {
+            if proto.spawn_weight <= 0.0 {
+                continue;
+            }
+            partial += proto.spawn_weight;
+            if partial >= r {
+                return proto.id;
+            }
         }
+        default
     }
 
     pub fn min_turning_radius(self) -> f32 {
@@ -106,7 +127,7 @@ impl VehicleKind {
 
 pub fn unpark(sim: &mut Simulation, vehicle: VehicleID) {
     let v = unwrap_ret!(sim.world.vehicles.get_mut(vehicle));
-    let w = v.vehicle.kind.width();
+    let w = v.vehicle.length();
     let trans = v.trans;
 
     if let VehicleState::Parked(spot) =
@@ -123,22 +144,34 @@ pub fn unpark(sim: &mut Simulation, vehicle: VehicleID) {
     v.collider = Some(coll);
 }
 
+/// Spawns a vehicle of a random prototype of the kind
 pub fn spawn_parked_vehicle(
     sim: &mut Simulation,
     kind: VehicleKind,
     near: Vec3,
+) -> Option<VehicleID> {
+    let proto = kind.random_prototype(&mut sim.write::<RandProvider>());
+    spawn_parked_vehicle_of(sim, kind, proto, near)
+}
+
+pub fn spawn_parked_vehicle_of(
+    sim: &mut Simulation,
+    kind: VehicleKind,
+    proto: RoadVehicleID,
+    near: Vec3,
 ) -> Option<VehicleID> {
     let map = sim.map();
     let mut pm = sim.write::<ParkingManagement>();
     let spot_id = pm.reserve_near(near, &map).ok()?;
     drop((map, pm));
 
-    spawn_parked_vehicle_with_spot(sim, kind, spot_id)
+    spawn_parked_vehicle_with_spot(sim, kind, proto, spot_id)
 }
 
 pub fn spawn_parked_vehicle_with_spot(
     sim: &mut Simulation,
     kind: VehicleKind,
+    proto: RoadVehicleID,
     spot_id: SpotReservation,
 ) -> Option<VehicleID> {
     let map = sim.map();
@@ -151,7 +184,7 @@ pub fn spawn_parked_vehicle_with_spot(
         _ => Color::WHITE,
     };
 
-    let vehicle = Vehicle::new(kind, spot_id, tint, &mut sim.write::<RandProvider>());
+    let vehicle = Vehicle::new(kind, proto, spot_id, tint, &mut sim.write::<RandProvider>());
 
     Some(make_vehicle_entity(sim, pos, vehicle, it, false))
 }
@@ -163,7 +196,7 @@ pub fn make_vehicle_entity(
     it: Itinerary,
     mk_collider: bool,
 ) -> VehicleID {
-    let w = vehicle.kind.width();
+    let w = vehicle.length();
 
     let mut collider = None;
     if mk_collider {
@@ -204,9 +237,24 @@ pub fn get_random_car_color(r: &mut RandProvider) -> Color {
     unreachable!();
 }
 
+fn unknown_prototype() -> RoadVehicleID {
+    RoadVehicleID::new("")
+}
+
+/// Vehicles of saves from before version 1 had no prototype
+fn deserialize_prototype<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<RoadVehicleID, D::Error> {
+    if decoding_version() < 1 {
+        return Ok(unknown_prototype());
+    }
+    RoadVehicleID::deserialize(deserializer)
+}
+
 impl Vehicle {
     pub fn new(
         kind: VehicleKind,
+        prototype: RoadVehicleID,
         spot: SpotReservation,
         tint: Color,
         rng: &mut RandProvider,
@@ -217,8 +265,32 @@ impl Vehicle {
             max_speed_multiplier: 0.95 + 0.1 * rng.next_f32(),
             state: VehicleState::Parked(spot),
             kind,
+            prototype,
             tint,
             flag: 0,
         }
     }
+
+    /// The prototype of the vehicle, or the default one of its kind if it doesn't exist anymore
+    pub fn proto(&self) -> &'static RoadVehiclePrototype {
+        try_prototype(self.prototype).unwrap_or_else(|| self.kind.default_prototype())
+    }
+
+    pub fn length(&self) -> f32 {
+        self.proto().length
+    }
+
+    pub fn acceleration(&self) -> f32 {
+        self.proto().acceleration
+    }
+
+    pub fn deceleration(&self) -> f32 {
+        self.proto().deceleration
+    }
+
+    /// Speed the vehicle drives at on a lane with the given speed limit
+    pub fn cruise_speed(&self, speed_limit: f32) -> f32 {
+        (self.kind.speed_factor() * self.max_speed_multiplier * speed_limit)
+            .min(self.proto().max_speed)
+    }
 }
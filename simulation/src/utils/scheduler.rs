@@ -1,4 +1,4 @@
-use crate::world::{CompanyEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::world::{CompanyEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt, WarehouseEnt};
 use crate::{FreightStationEnt, ParCommandBuffer, Simulation};
 use common::history::History;
 use ordered_float::OrderedFloat;
@@ -49,6 +49,7 @@ impl SeqSchedule {
             ParCommandBuffer::<WagonEnt>::apply(sim);
             ParCommandBuffer::<FreightStationEnt>::apply(sim);
             ParCommandBuffer::<CompanyEnt>::apply(sim);
+            ParCommandBuffer::<WarehouseEnt>::apply(sim);
 
             let elapsed = start.elapsed();
 
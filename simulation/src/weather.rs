@@ -0,0 +1,159 @@
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::World;
+use prototypes::{BuildingGen, GameTime, GoodsCompanyPrototype, Season};
+use serde::{Deserialize, Serialize};
+
+/// Number of days after today covered by the forecast
+pub const FORECAST_DAYS: usize = 3;
+
+/// Speed limits are multiplied by this under the rain
+pub const RAIN_SPEED_FACTOR: f32 = 0.85;
+
+/// Speed limits are multiplied by this on snowy roads
+pub const SNOW_SPEED_FACTOR: f32 = 0.6;
+
+/// Snow covering the fields slows down the farms, on top of their seasonal productivity
+pub const SNOW_FARM_FACTOR: f32 = 0.5;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Cloudy,
+    Rain,
+    Snow,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 4] = [
+        WeatherKind::Clear,
+        WeatherKind::Cloudy,
+        WeatherKind::Rain,
+        WeatherKind::Snow,
+    ];
+
+    /// Chances of each weather for a day of the season, in the order of [`WeatherKind::ALL`]
+    pub fn season_odds(season: Season) -> [f32; 4] {
+        match season {
+            Season::Spring => [0.45, 0.3, 0.25, 0.0],
+            Season::Summer => [0.65, 0.2, 0.15, 0.0],
+            Season::Autumn => [0.3, 0.35, 0.35, 0.0],
+            Season::Winter => [0.25, 0.3, 0.1, 0.35],
+        }
+    }
+
+    /// Picks the weather of a day of the season, `roll` is in [0; 1)
+    pub fn roll(season: Season, roll: f32) -> WeatherKind {
+        let mut acc = 0.0;
+        for (kind, odds) in Self::ALL.into_iter().zip(Self::season_odds(season)) {
+            acc += odds;
+            if roll < acc {
+                return kind;
+            }
+        }
+        WeatherKind::Clear
+    }
+
+    /// Multiplier applied to the speed limit of every lane
+    pub fn speed_limit_factor(self) -> f32 {
+        match self {
+            WeatherKind::Clear | WeatherKind::Cloudy => 1.0,
+            WeatherKind::Rain => RAIN_SPEED_FACTOR,
+            WeatherKind::Snow => SNOW_SPEED_FACTOR,
+        }
+    }
+
+    /// Whether something falls from the sky
+    pub fn has_precipitation(self) -> bool {
+        matches!(self, WeatherKind::Rain | WeatherKind::Snow)
+    }
+}
+
+/// The weather of today and the forecast for the next days.
+/// A new day is rolled each morning from the odds of its season with the simulation rng,
+/// so that replays see the same weather.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Weather {
+    current: WeatherKind,
+    /// Weather of the next days, the first one is tomorrow
+    forecast: [WeatherKind; FORECAST_DAYS],
+    season: Option<Season>,
+    /// Day of the current weather, none until the first roll
+    day: Option<i32>,
+    /// Weather set by hand from the debug tools and the tests, the rolls don't change it
+    pub forced: Option<WeatherKind>,
+}
+
+impl Weather {
+    pub fn current(&self) -> WeatherKind {
+        self.forced.unwrap_or(self.current)
+    }
+
+    pub fn forecast(&self) -> &[WeatherKind; FORECAST_DAYS] {
+        &self.forecast
+    }
+
+    pub fn season(&self) -> Season {
+        self.season.unwrap_or(Season::Spring)
+    }
+
+    /// Multiplier applied to the speed limit of every lane
+    pub fn speed_limit_factor(&self) -> f32 {
+        self.current().speed_limit_factor()
+    }
+
+    /// Multiplier applied to the productivity of the company from its seasonal productivity
+    /// and the snow on the fields for farms
+    pub fn productivity_factor(&self, proto: &GoodsCompanyPrototype) -> f32 {
+        let f = proto.season_productivity.get(self.season());
+        if !matches!(proto.bgen, BuildingGen::Farm) {
+            return f;
+        }
+        f * self.farm_factor()
+    }
+
+    /// Farms produce less under the snow
+    pub fn farm_factor(&self) -> f32 {
+        if self.current() == WeatherKind::Snow {
+            return SNOW_FARM_FACTOR;
+        }
+        1.0
+    }
+
+    fn advance(&mut self, day: i32, rng: &mut RandProvider) {
+        let roll =
+            |rng: &mut RandProvider, d: i32| WeatherKind::roll(Season::of_day(d), rng.next_f32());
+
+        match self.day {
+            Some(last) if day > last && day - last <= FORECAST_DAYS as i32 => {
+                for d in last + 1..=day {
+                    self.current = self.forecast[0];
+                    self.forecast.rotate_left(1);
+                    self.forecast[FORECAST_DAYS - 1] = roll(rng, d + FORECAST_DAYS as i32);
+                }
+            }
+            _ => {
+                self.current = roll(rng, day);
+                for (i, f) in self.forecast.iter_mut().enumerate() {
+                    *f = roll(rng, day + 1 + i as i32);
+                }
+            }
+        }
+
+        self.day = Some(day);
+        self.season = Some(Season::of_day(day));
+    }
+}
+
+pub fn weather_system(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("weather::weather_system");
+    let day = resources.read::<GameTime>().daytime.day;
+    let mut weather = resources.write::<Weather>();
+    if weather.day == Some(day) {
+        return;
+    }
+    let mut rng = resources.write::<RandProvider>();
+    weather.advance(day, &mut rng);
+    log::info!("weather of day {} is {:?}", day, weather.current);
+}
@@ -1,17 +1,24 @@
-use crate::economy::{Bought, Market, Sold, Workers};
+use crate::economy::{Bought, CompanyFinances, JobMarket, Market, Sold, Wallet, Workers};
 use crate::map_dynamic::{
-    DispatchID, Dispatcher, Itinerary, ItineraryFollower, ItineraryLeader, ParkingManagement,
-    Router,
+    BuildingInfos, DispatchID, Dispatcher, Itinerary, ItineraryFollower, ItineraryLeader,
+    ParkingManagement, Router,
 };
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::delivery::Deliveries;
+use crate::souls::desire::{BuyFood, Home, Leisure, Tourist, Work};
 use crate::souls::freight_station::FreightStation;
 use crate::souls::goods_company::GoodsCompanyState;
+use crate::souls::happiness::Happiness;
 use crate::souls::human::{HumanDecision, PersonalInfo};
-use crate::transportation::train::{Locomotive, LocomotiveReservation, RailWagon};
+use crate::souls::warehouse::Warehouse;
+use crate::transportation::passenger_rail::PassengerRail;
+use crate::transportation::train::{
+    Locomotive, LocomotiveReservation, RailWagon, TrainReservations,
+};
+use crate::transportation::transit::Transit;
 use crate::transportation::{
     Location, Pedestrian, Speed, TransportGrid, Transporter, Vehicle, VehicleKind, VehicleState,
 };
-use crate::utils::par_command_buffer::SimDrop;
+use crate::utils::par_command_buffer::{ParCommandBuffer, SimDrop};
 use crate::utils::resources::Resources;
 use crate::{impl_entity, impl_trans, SoulID};
 use common::iter::chain;
@@ -29,6 +36,7 @@ new_key_type! {
     pub struct WagonID;
     pub struct FreightStationID;
     pub struct CompanyID;
+    pub struct WarehouseID;
 }
 
 impl_entity!(VehicleID, VehicleEnt, vehicles);
@@ -37,6 +45,7 @@ impl_entity!(TrainID, TrainEnt, trains);
 impl_entity!(WagonID, WagonEnt, wagons);
 impl_entity!(FreightStationID, FreightStationEnt, freight_stations);
 impl_entity!(CompanyID, CompanyEnt, companies);
+impl_entity!(WarehouseID, WarehouseEnt, warehouses);
 
 impl_trans!(HumanID);
 impl_trans!(VehicleID);
@@ -44,6 +53,7 @@ impl_trans!(TrainID);
 impl_trans!(WagonID);
 impl_trans!(FreightStationID);
 impl_trans!(CompanyID);
+impl_trans!(WarehouseID);
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, From, TryInto)]
 pub enum AnyEntity {
@@ -53,6 +63,7 @@ pub enum AnyEntity {
     FreightStationID(FreightStationID),
     CompanyID(CompanyID),
     HumanID(HumanID),
+    WarehouseID(WarehouseID),
 }
 
 #[derive(Inspect, Serialize, Deserialize)]
@@ -97,8 +108,16 @@ pub struct HumanEnt {
     pub decision: HumanDecision,
     pub home: Home,
     pub food: BuyFood,
+    #[serde(deserialize_with = "crate::migrations::since::<11, _, _>")]
+    pub leisure: Leisure,
     pub bought: Bought,
     pub work: Option<Work>,
+    #[serde(deserialize_with = "crate::migrations::since::<12, _, _>")]
+    pub tourist: Option<Tourist>,
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub wallet: Wallet,
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub happiness: Happiness,
 
     pub personal_info: Box<PersonalInfo>,
 }
@@ -109,10 +128,25 @@ impl SimDrop for HumanEnt {
             res.write::<TransportGrid>().remove_maintain(collider.0);
         }
 
-        res.write::<Market>().remove(SoulID::Human(id));
+        let soul = SoulID::Human(id);
+        res.write::<Market>().remove(soul);
+        res.write::<JobMarket>().remove_worker(id);
+        res.write::<Transit>().remove_passenger(id);
+        res.write::<PassengerRail>().remove_passenger(id);
+
+        let mut binfos = res.write::<BuildingInfos>();
+        if let Location::Building(b) = self.location {
+            binfos.get_out(b, soul);
+        }
+        binfos.remove_owner(soul);
+        drop(binfos);
 
         self.router
-            .clear_steps(&mut res.write::<ParkingManagement>())
+            .clear_steps(&mut res.write::<ParkingManagement>());
+
+        if let Some(car) = self.router.personal_car {
+            res.write::<ParCommandBuffer<VehicleEnt>>().kill(car);
+        }
     }
 }
 
@@ -131,6 +165,8 @@ impl SimDrop for TrainEnt {
     fn sim_drop(self, id: TrainID, res: &mut Resources) {
         res.write::<Dispatcher>()
             .unregister(DispatchID::FreightTrain(id));
+        res.write::<TrainReservations>().remove_train(id);
+        res.write::<PassengerRail>().remove_train(id);
     }
 }
 
@@ -171,11 +207,30 @@ pub struct CompanyEnt {
     pub workers: Workers,
     pub sold: Sold,
     pub bought: Bought,
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub finances: CompanyFinances,
 }
 
 impl SimDrop for CompanyEnt {
     fn sim_drop(self, id: CompanyID, res: &mut Resources) {
         res.write::<Market>().remove(SoulID::GoodsCompany(id));
+        res.write::<JobMarket>().remove_company(id);
+        res.write::<Deliveries>()
+            .remove_company(id, &mut res.write::<Market>());
+        res.write::<BuildingInfos>()
+            .remove_owner(SoulID::GoodsCompany(id));
+    }
+}
+
+#[derive(Inspect, Serialize, Deserialize)]
+pub struct WarehouseEnt {
+    pub trans: Transform,
+    pub w: Warehouse,
+}
+
+impl SimDrop for WarehouseEnt {
+    fn sim_drop(self, id: WarehouseID, res: &mut Resources) {
+        res.write::<Market>().remove(SoulID::Warehouse(id));
     }
 }
 
@@ -187,6 +242,8 @@ pub struct World {
     pub wagons: HopSlotMap<WagonID, WagonEnt>,
     pub freight_stations: HopSlotMap<FreightStationID, FreightStationEnt>,
     pub companies: HopSlotMap<CompanyID, CompanyEnt>,
+    #[serde(deserialize_with = "crate::migrations::since::<1, _, _>")]
+    pub warehouses: HopSlotMap<WarehouseID, WarehouseEnt>,
 }
 
 impl World {
@@ -214,6 +271,7 @@ impl World {
             AnyEntity::FreightStationID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::CompanyID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::HumanID(id) => self.storage_id(id).contains_key(id),
+            AnyEntity::WarehouseID(id) => self.storage_id(id).contains_key(id),
         }
     }
 
@@ -278,10 +336,10 @@ impl World {
     #[rustfmt::skip]
     pub fn query_trans_speed_coll_vehicle(
         &self,
-    ) -> impl Iterator<Item = (&Transform, &Speed, Transporter, Option<&Vehicle>)> {
+    ) -> impl Iterator<Item = (AnyEntity, &Transform, &Speed, Transporter, Option<&Vehicle>)> {
         chain((
-              self.vehicles.values().filter_map(|x| { x.collider.map(|coll| (&x.trans, &x.speed, coll, Some(&x.vehicle))) }),
-              self.humans  .values().filter_map(|x| { x.collider.map(|coll| (&x.trans, &x.speed, coll, None)) }),
+              self.vehicles.iter().filter_map(|(id, x)| { x.collider.map(|coll| (AnyEntity::VehicleID(id), &x.trans, &x.speed, coll, Some(&x.vehicle))) }),
+              self.humans  .iter().filter_map(|(id, x)| { x.collider.map(|coll| (AnyEntity::HumanID(id), &x.trans, &x.speed, coll, None)) }),
         ))
     }
 
@@ -298,6 +356,7 @@ impl World {
                     .keys()
                     .map(AnyEntity::FreightStationID),
                 self.companies.keys().map(AnyEntity::CompanyID),
+                self.warehouses.keys().map(AnyEntity::WarehouseID),
             )),
         ))
     }
@@ -366,6 +425,7 @@ impl Display for AnyEntity {
             AnyEntity::WagonID(id) => write!(f, "{:?}", id),
             AnyEntity::FreightStationID(id) => write!(f, "{:?}", id),
             AnyEntity::CompanyID(id) => write!(f, "{:?}", id),
+            AnyEntity::WarehouseID(id) => write!(f, "{:?}", id),
         }
     }
 }
@@ -1,28 +1,55 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{Display, Formatter};
 use std::time::Instant;
 
-use prototypes::RollingStockID;
+use prototypes::{RollingStockID, TreePrototypeID};
 use serde::{Deserialize, Serialize};
 
-use geom::{vec3, Vec2, Vec3, OBB};
+use geom::{vec3, Polygon, Vec2, Vec3, AABB, OBB};
 use prototypes::BuildingGen;
 use prototypes::GameTime;
+use prototypes::Money;
 use WorldCommand::*;
 
-use crate::economy::Government;
-use crate::map::procgen::{load_parismap, load_testfield};
+use crate::economy::{BudgetReason, Government, Market, TradePolicy};
+use crate::gameplay::{Cheats, GameplayParams};
+use crate::map::procgen::{load_parismap, load_testfield, MapGenParams};
 use crate::map::{
-    BuildingID, BuildingKind, Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
-    LightPolicy, LotID, Map, MapProject, ProjectKind, RoadID, TerraformKind, TurnPolicy, Zone,
+    BuildingID, BuildingKind, BuildingSnapshot, BulldozeFilter, DistrictID, ElectricityNetworkID,
+    Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder, LightPolicy, LightTiming,
+    LotID, Map, MapEditCost, MapEditHistory, MapEditOp, MapProject, PendingMapEdit, PolicySnapshot,
+    ProjectKind, RoadID, SiteError, TerraformKind, Tree, TurnID, TurnPolicy, Zone, ZoningKind,
 };
 use crate::map_dynamic::{BuildingInfos, ParkingManagement};
+use crate::milestones::lock_reason;
 use crate::multiplayer::chat::Message;
 use crate::multiplayer::MultiplayerState;
+use crate::souls::human::spawn_human;
 use crate::transportation::testing_vehicles::RandomVehicles;
 use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::transportation::transit::{snap_bus_stop, BusLineID, BusStopID, Transit};
 use crate::transportation::{spawn_parked_vehicle_with_spot, unpark, VehicleKind};
 use crate::utils::rand_provider::RandProvider;
-use crate::{Replay, Simulation, SimulationOptions};
+use crate::world::{CompanyEnt, CompanyID};
+use crate::{ParCommandBuffer, Replay, Simulation, SimulationOptions, SoulID};
+
+/// Largest brush of the terraforming, forestry and zoning commands, in meters
+pub const MAX_BRUSH_RADIUS: f32 = 1000.0;
+
+/// Most cars spawned by a single debug command
+pub const MAX_SPAWNED_CARS: usize = 1000;
+
+/// Most wagons pulled by a single train
+pub const MAX_WAGONS: usize = 100;
+
+/// Most buses running on a single line
+pub const MAX_BUSES_PER_LINE: u32 = 100;
+
+/// Longest name given to a road, a district or a bus line, in bytes
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Most points of the polygon of a district
+pub const MAX_DISTRICT_POINTS: usize = 256;
 
 #[derive(Clone, Default)]
 pub struct WorldCommands {
@@ -76,6 +103,62 @@ pub enum WorldCommand {
         inter: IntersectionID,
         turn: TurnPolicy,
         light: LightPolicy,
+        #[serde(default)]
+        timing: Option<LightTiming>,
+        #[serde(default)]
+        disabled_turns: BTreeSet<TurnID>,
+    },
+    /// Changes the lanes of a road, keeping its geometry
+    MapSetRoadPattern {
+        road: RoadID,
+        pattern: LanePattern,
+    },
+    /// Reverses the direction of a one-way road
+    MapFlipRoad(RoadID),
+    /// Names the road, an empty name gives it back its generated name
+    MapSetRoadName {
+        road: RoadID,
+        name: String,
+    },
+    /// Names an area of the map, an empty name gives it a generated one
+    MapAddDistrict {
+        polygon: Polygon,
+        name: String,
+    },
+    MapRemoveDistrict(DistrictID),
+    /// Renames the district, an empty name gives it back its generated name
+    MapSetDistrictName {
+        district: DistrictID,
+        name: String,
+    },
+    /// Adds a marked pedestrian crossing where the position projects on the road
+    MapAddCrossing {
+        road: RoadID,
+        pos: Vec3,
+    },
+    MapRemoveCrossing {
+        road: RoadID,
+        idx: u16,
+    },
+    /// Removes everything matching the filter inside the area in a single edit
+    MapBulldozeArea {
+        area: AABB,
+        filter: BulldozeFilter,
+    },
+    /// Plants the trees of a brush stroke in a single edit
+    MapPlantTrees {
+        trees: Vec<(Vec2, TreePrototypeID)>,
+    },
+    /// Removes the trees inside the circles of a brush stroke in a single edit
+    MapRemoveTrees {
+        stroke: Vec<Vec2>,
+        radius: f32,
+    },
+    /// Paints the zoning cells inside the circle, None erases them
+    MapPaintZone {
+        center: Vec2,
+        radius: f32,
+        kind: Option<ZoningKind>,
     },
     MapBuildSpecialBuilding {
         pos: OBB,
@@ -97,6 +180,120 @@ pub enum WorldCommand {
         zone: Zone,
     },
     SetGameTime(GameTime),
+    SetTradePolicy(TradePolicy),
+    /// Price of one kilowatt-hour of electricity
+    SetElectricityPrice(Money),
+    /// Changes the gameplay parameters of a running game, which marks it as sandbox
+    SetGameplayParams(GameplayParams),
+    /// Sent by the console before the commands of a cheat, marks the save
+    MarkCheatsUsed,
+    /// Gives money to the government, or takes it if negative
+    AddMoney(Money),
+    /// Borrows money, up to the most the government can owe
+    TakeLoan(Money),
+    /// Repays the loan, up to what is owed and what the government has
+    RepayLoan(Money),
+    /// Spawns a household in the house
+    SpawnHuman(BuildingID),
+    /// Keeps the network in a blackout, or lets it recover
+    ToggleBlackout(ElectricityNetworkID),
+    /// The buy orders of the company in the building are matched before the others
+    SetCompanyPriority {
+        building: BuildingID,
+        prioritized: bool,
+    },
+    /// Shuts down the company in the building, which is then free for a new one
+    CloseCompany(BuildingID),
+    /// Adds a bus stop on the side of the road closest to the position
+    AddBusStop(Vec3),
+    RemoveBusStop(BusStopID),
+    AddBusLine {
+        name: String,
+    },
+    RemoveBusLine(BusLineID),
+    /// Sets the stops served by the line in order, and how many buses run on it
+    UpdateBusLine {
+        line: BusLineID,
+        stops: Vec<BusStopID>,
+        n_buses: u32,
+    },
+    /// Undoes the last map edit
+    MapUndo,
+    /// Redoes the last undone map edit
+    MapRedo,
+    /// Makes the intersection near the border a connection to the outside for trucks, or not
+    MapSetRoadConnection {
+        inter: IntersectionID,
+        connection: bool,
+    },
+    /// Ends the brush stroke of the terraforming, it is undone in a single edit
+    MapEndTerraform,
+    /// Turns the landmarks guiding the vehicle routes on or off, to debug them
+    SetLandmarkRouting(bool),
+}
+
+/// Why a command could not be applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// An object the command refers to was removed before the command was applied
+    Missing(&'static str),
+    /// The command refers to an index that does not exist
+    Invalid,
+    /// The roads could not be connected, e.g. the terrain or the other roads changed
+    ConnectionFailed,
+    /// Something is in the way of the building
+    Blocked,
+    /// The terrain does not allow the building there
+    Site(SiteError),
+    /// The building or road type is locked by a milestone or the scenario, with the reason
+    Locked(String),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Missing(what) => write!(f, "The {what} no longer exists"),
+            CommandError::Invalid => write!(f, "Invalid command"),
+            CommandError::ConnectionFailed => write!(f, "The roads could not be connected"),
+            CommandError::Blocked => write!(f, "Something is in the way"),
+            CommandError::Site(err) => write!(f, "{err}"),
+            CommandError::Locked(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+/// The last commands that could not be applied, for the UI to report them.
+/// Errors are numbered with a counter that never resets so that readers can tell which ones are new.
+#[derive(Default)]
+pub struct FailedCommands {
+    errors: VecDeque<(u64, CommandError)>,
+    next_id: u64,
+}
+
+impl FailedCommands {
+    const MAX_ERRORS: usize = 32;
+
+    pub fn push(&mut self, err: CommandError) {
+        if self.errors.len() >= Self::MAX_ERRORS {
+            self.errors.pop_front();
+        }
+        self.errors.push_back((self.next_id, err));
+        self.next_id += 1;
+    }
+
+    /// Errors pushed since `seen` was last updated, `seen` starts at 0
+    pub fn since<'a>(&'a self, seen: &mut u64) -> impl Iterator<Item = &'a CommandError> {
+        // the simulation was replaced
+        if *seen > self.next_id {
+            *seen = 0;
+        }
+        let from = *seen;
+        *seen = self.next_id;
+        self.errors
+            .iter()
+            .filter(move |(id, _)| *id >= from)
+            .map(|(_, err)| err)
+    }
 }
 
 impl AsRef<[WorldCommand]> for WorldCommands {
@@ -194,16 +391,89 @@ impl WorldCommands {
         })
     }
 
+    pub fn map_set_road_pattern(&mut self, road: RoadID, pattern: LanePattern) {
+        self.commands.push(MapSetRoadPattern { road, pattern })
+    }
+
+    pub fn map_set_road_connection(&mut self, inter: IntersectionID, connection: bool) {
+        self.commands
+            .push(MapSetRoadConnection { inter, connection })
+    }
+
+    pub fn map_flip_road(&mut self, road: RoadID) {
+        self.commands.push(MapFlipRoad(road))
+    }
+
+    pub fn map_set_road_name(&mut self, road: RoadID, name: String) {
+        self.commands.push(MapSetRoadName { road, name })
+    }
+
+    pub fn map_add_district(&mut self, polygon: Polygon, name: String) {
+        self.commands.push(MapAddDistrict { polygon, name })
+    }
+
+    pub fn map_remove_district(&mut self, district: DistrictID) {
+        self.commands.push(MapRemoveDistrict(district))
+    }
+
+    pub fn map_set_district_name(&mut self, district: DistrictID, name: String) {
+        self.commands.push(MapSetDistrictName { district, name })
+    }
+
+    pub fn map_add_crossing(&mut self, road: RoadID, pos: Vec3) {
+        self.commands.push(MapAddCrossing { road, pos })
+    }
+
+    pub fn map_remove_crossing(&mut self, road: RoadID, idx: u16) {
+        self.commands.push(MapRemoveCrossing { road, idx })
+    }
+
+    pub fn map_bulldoze_area(&mut self, area: AABB, filter: BulldozeFilter) {
+        self.commands.push(MapBulldozeArea { area, filter })
+    }
+
+    pub fn map_plant_trees(&mut self, trees: Vec<(Vec2, TreePrototypeID)>) {
+        self.commands.push(MapPlantTrees { trees })
+    }
+
+    pub fn map_remove_trees(&mut self, stroke: Vec<Vec2>, radius: f32) {
+        self.commands.push(MapRemoveTrees { stroke, radius })
+    }
+
+    pub fn map_paint_zone(&mut self, center: Vec2, radius: f32, kind: Option<ZoningKind>) {
+        self.commands.push(MapPaintZone {
+            center,
+            radius,
+            kind,
+        })
+    }
+
+    pub fn map_end_terraform(&mut self) {
+        self.commands.push(MapEndTerraform)
+    }
+
+    pub fn map_undo(&mut self) {
+        self.commands.push(MapUndo)
+    }
+
+    pub fn map_redo(&mut self) {
+        self.commands.push(MapRedo)
+    }
+
     pub fn map_update_intersection_policy(
         &mut self,
         id: IntersectionID,
         tp: TurnPolicy,
         lp: LightPolicy,
+        timing: Option<LightTiming>,
+        disabled_turns: BTreeSet<TurnID>,
     ) {
         self.commands.push(MapUpdateIntersectionPolicy {
             inter: id,
             turn: tp,
             light: lp,
+            timing,
+            disabled_turns,
         })
     }
 }
@@ -215,15 +485,212 @@ impl WorldCommand {
             self,
             MapBuildHouse(_)
                 | MapUpdateIntersectionPolicy { .. }
+                | MapSetRoadName { .. }
+                | MapAddDistrict { .. }
+                | MapRemoveDistrict(_)
+                | MapSetDistrictName { .. }
+                | MapSetRoadConnection { .. }
                 | UpdateZone { .. }
+                | MapPaintZone { .. }
+                | MapPlantTrees { .. }
+                | MapRemoveTrees { .. }
+                | Terraform { .. }
+                | MapEndTerraform
                 | SetGameTime(_)
+                | SetTradePolicy(_)
+                | SetElectricityPrice(_)
+                | SetGameplayParams(_)
+                | MarkCheatsUsed
+                | AddMoney(_)
+                | TakeLoan(_)
+                | RepayLoan(_)
+                | ToggleBlackout(_)
+                | SetCompanyPriority { .. }
+                | SetLandmarkRouting(_)
         )
     }
 
-    pub fn apply(&self, sim: &mut Simulation) {
-        let cost = Government::action_cost(self, sim);
-        sim.write::<Government>().money -= cost;
+    /// Checks that what the command refers to still exists, and that what it builds is unlocked.
+    /// Commands are created by the UI some ticks before being applied, or by other players,
+    /// so the world might have changed in between.
+    pub fn validate(&self, sim: &Simulation) -> Result<(), CommandError> {
+        let map = sim.map();
+        let exists = |ok: bool, what| {
+            if ok {
+                Ok(())
+            } else {
+                Err(CommandError::Missing(what))
+            }
+        };
+        let project = |p: &MapProject| match p.kind {
+            ProjectKind::Inter(id) => exists(map.intersections.contains_key(id), "intersection"),
+            ProjectKind::Road(id) => exists(map.roads.contains_key(id), "road"),
+            ProjectKind::Building(id) => exists(map.buildings.contains_key(id), "building"),
+            ProjectKind::Lot(id) => exists(map.lots.contains_key(id), "lot"),
+            ProjectKind::Ground => Ok(()),
+        };
+        let unlocked = |name: &str| match lock_reason(sim, name) {
+            Some(reason) => Err(CommandError::Locked(reason)),
+            None => Ok(()),
+        };
+        // replays and clients could otherwise freeze the simulation or poison it with NaNs
+        let valid = |ok: bool| {
+            if ok {
+                Ok(())
+            } else {
+                Err(CommandError::Invalid)
+            }
+        };
+        let brush = |center: Vec2, radius: f32| {
+            valid(center.is_finite() && (0.0..=MAX_BRUSH_RADIUS).contains(&radius))
+        };
+        let name = |name: &str| valid(name.len() <= MAX_NAME_LEN);
+
+        match *self {
+            Terraform {
+                center,
+                radius,
+                amount,
+                level,
+                ..
+            } => {
+                brush(center, radius)?;
+                valid(amount.is_finite() && level.is_finite())
+            }
+            MapPaintZone { center, radius, .. } => brush(center, radius),
+            MapRemoveTrees { ref stroke, radius } => {
+                for &p in stroke {
+                    brush(p, radius)?;
+                }
+                Ok(())
+            }
+            SpawnRandomCars { n_cars } => valid(n_cars <= MAX_SPAWNED_CARS),
+            MapAddDistrict {
+                ref polygon,
+                name: ref n,
+            } => {
+                valid(
+                    polygon.len() <= MAX_DISTRICT_POINTS && polygon.iter().all(|p| p.is_finite()),
+                )?;
+                name(n)
+            }
+            AddBusLine { name: ref n } => name(n),
+            SetGameplayParams(ref params) => valid(params.is_valid()),
+            Init(ref opts) => valid(opts.params.is_valid()),
+            MapSetRoadName { road, name: ref n } => {
+                exists(map.roads.contains_key(road), "road")?;
+                name(n)
+            }
+            MapSetDistrictName {
+                district,
+                name: ref n,
+            } => {
+                exists(map.districts.contains(district), "district")?;
+                name(n)
+            }
+            MapRemoveIntersection(id)
+            | MapUpdateIntersectionPolicy { inter: id, .. }
+            | MapSetRoadConnection { inter: id, .. } => {
+                exists(map.intersections.contains_key(id), "intersection")
+            }
+            MapSetRoadPattern { road, ref pattern } => {
+                exists(map.roads.contains_key(road), "road")?;
+                unlocked(pattern.road_type())
+            }
+            MapRemoveRoad(id)
+            | MapFlipRoad(id)
+            | MapAddCrossing { road: id, .. }
+            | MapRemoveCrossing { road: id, .. } => exists(map.roads.contains_key(id), "road"),
+            MapBuildSpecialBuilding {
+                ref pos,
+                kind,
+                connected_road,
+                ..
+            } => {
+                if let Some(id) = connected_road {
+                    exists(map.roads.contains_key(id), "road")?;
+                }
+                if let Some(name) = kind.prototype_name() {
+                    unlocked(name)?;
+                }
+                map.check_building_site(pos, kind)
+                    .map_err(CommandError::Site)
+            }
+            MapRemoveDistrict(id) => exists(map.districts.contains(id), "district"),
+            MapRemoveBuilding(id) | UpdateZone { building: id, .. } => {
+                exists(map.buildings.contains_key(id), "building")
+            }
+            MapBuildHouse(id) => exists(map.lots.contains_key(id), "lot"),
+            SpawnHuman(id) => exists(
+                map.buildings
+                    .get(id)
+                    .is_some_and(|b| b.kind == BuildingKind::House),
+                "house",
+            ),
+            SetCompanyPriority { building, .. } | CloseCompany(building) => exists(
+                company_in(&sim.read::<BuildingInfos>(), building).is_some(),
+                "company",
+            ),
+            AddTrain { lane, n_wagons, .. } => {
+                exists(map.lanes.contains_key(lane), "lane")?;
+                valid(n_wagons as usize <= MAX_WAGONS)
+            }
+            SpawnTrain {
+                lane, ref wagons, ..
+            } => {
+                exists(map.lanes.contains_key(lane), "lane")?;
+                valid(wagons.len() <= MAX_WAGONS)
+            }
+            MapMakeConnection {
+                ref from,
+                ref to,
+                ref pat,
+                ..
+            } => {
+                project(from)?;
+                project(to)?;
+                unlocked(pat.road_type())
+            }
+            MapMakeMultipleConnections(ref projects, ref links) => {
+                for p in projects {
+                    project(p)?;
+                }
+                if links
+                    .iter()
+                    .any(|&(from, to, ..)| from >= projects.len() || to >= projects.len())
+                {
+                    return Err(CommandError::Invalid);
+                }
+                for (_, _, _, pat) in links {
+                    unlocked(pat.road_type())?;
+                }
+                Ok(())
+            }
+            RemoveBusStop(id) => exists(sim.read::<Transit>().stops().contains_key(id), "bus stop"),
+            RemoveBusLine(id) => exists(sim.read::<Transit>().lines().contains_key(id), "bus line"),
+            UpdateBusLine {
+                line,
+                ref stops,
+                n_buses,
+            } => {
+                valid(n_buses <= MAX_BUSES_PER_LINE)?;
+                let transit = sim.read::<Transit>();
+                exists(transit.lines().contains_key(line), "bus line")?;
+                exists(
+                    stops.iter().all(|&s| transit.stops().contains_key(s)),
+                    "bus stop",
+                )
+            }
+            TakeLoan(amount) | RepayLoan(amount) if amount <= Money::ZERO => {
+                Err(CommandError::Invalid)
+            }
+            _ => Ok(()),
+        }
+    }
 
+    /// Applies the command, or records why it could not be in [`FailedCommands`].
+    /// Commands are applied at the start of a tick, in the order they were pushed.
+    pub fn apply(&self, sim: &mut Simulation) {
         let mut rep = sim.resources.write::<Replay>();
         if rep.enabled {
             let tick = sim.read::<GameTime>().tick;
@@ -231,23 +698,45 @@ impl WorldCommand {
         }
         drop(rep);
 
+        if let Err(err) = self.validate(sim) {
+            log::warn!("command could not be applied: {}", err);
+            sim.write::<FailedCommands>().push(err);
+            return;
+        }
+
+        let cost = Government::action_cost(self, sim);
+        let reason = Government::action_reason(self, cost);
+        let mut gvt = sim.write::<Government>();
+        gvt.transact(reason, -cost);
+        if cost > Money::ZERO {
+            gvt.construction_spending += cost;
+        }
+        drop(gvt);
+
+        let pending_edit = self.pending_map_edit(&sim.map());
+
+        let mut failure = None;
         match *self {
             MapRemoveIntersection(id) => sim.map_mut().remove_intersection(id),
             MapRemoveRoad(id) => drop(sim.map_mut().remove_road(id)),
             MapRemoveBuilding(id) => drop(sim.map_mut().remove_building(id)),
-            MapBuildHouse(id) => {
-                if let Some(build) = sim.map_mut().build_house(id) {
-                    let mut infos = sim.write::<BuildingInfos>();
-                    infos.insert(build);
-                }
-            }
+            MapBuildHouse(id) => match sim.map_mut().build_house(id) {
+                Some(build) => sim.write::<BuildingInfos>().insert(build),
+                None => failure = Some(CommandError::Blocked),
+            },
             MapMakeConnection {
                 from,
                 to,
                 inter,
                 ref pat,
             } => {
-                sim.write::<Map>().make_connection(from, to, inter, pat);
+                if sim
+                    .write::<Map>()
+                    .make_connection(from, to, inter, pat)
+                    .is_none()
+                {
+                    failure = Some(CommandError::ConnectionFailed);
+                }
             }
             MapMakeMultipleConnections(ref projects, ref links) => {
                 let mut map = sim.map_mut();
@@ -277,28 +766,162 @@ impl WorldCommand {
                 inter: id,
                 turn: tp,
                 light: lp,
+                ref timing,
+                ref disabled_turns,
             } => sim.map_mut().update_intersection(id, move |i| {
                 i.light_policy = lp;
                 i.turn_policy = tp;
+                i.light_timing = timing.clone();
+                i.disabled_turns = disabled_turns.clone();
             }),
+            MapSetRoadPattern { road, ref pattern } => {
+                rebuild_road_lanes(sim, road, |map| map.set_road_pattern(road, pattern))
+            }
+            MapFlipRoad(road) => rebuild_road_lanes(sim, road, |map| map.flip_road(road)),
+            MapSetRoadName { road, ref name } => sim.map_mut().set_road_name(road, name),
+            MapAddDistrict {
+                ref polygon,
+                ref name,
+            } => {
+                if sim.map_mut().districts.add(polygon.clone(), name).is_none() {
+                    failure = Some(CommandError::Invalid);
+                }
+            }
+            MapRemoveDistrict(id) => drop(sim.map_mut().districts.remove(id)),
+            MapSetDistrictName { district, ref name } => {
+                sim.map_mut().districts.rename(district, name)
+            }
+            MapSetRoadConnection { inter, connection } => {
+                sim.map_mut().set_road_connection(inter, connection)
+            }
+            SetLandmarkRouting(on) => {
+                sim.write::<SimulationOptions>().landmark_routing = on;
+                sim.map_mut().landmark_routing = on;
+            }
+            MapAddCrossing { road, pos } => {
+                let _ = sim.map_mut().add_crossing(road, pos);
+            }
+            MapRemoveCrossing { road, idx } => {
+                let sidewalks = sim
+                    .map()
+                    .roads()
+                    .get(road)
+                    .and_then(|r| r.crossing_sidewalks());
+                sim.map_mut().remove_crossing(road, idx as usize);
+                // the crossings after it were shifted
+                if let Some((fw, bw)) = sidewalks {
+                    reroute_through(sim, &[fw, bw]);
+                }
+            }
+            MapBulldozeArea { area, filter } => drop(sim.map_mut().bulldoze(area, filter)),
+            MapPlantTrees { ref trees } => {
+                let trees: Vec<Tree> = trees
+                    .iter()
+                    .map(|&(pos, species)| Tree::planted(pos, species))
+                    .collect();
+                sim.map_mut().plant_trees(&trees);
+            }
+            MapRemoveTrees { ref stroke, radius } => {
+                sim.map_mut().remove_trees_in_stroke(stroke, radius)
+            }
+            MapPaintZone {
+                center,
+                radius,
+                kind,
+            } => sim.map_mut().zones.paint(center, radius, kind),
             MapBuildSpecialBuilding {
                 pos: obb,
                 kind,
                 gen,
                 ref zone,
                 connected_road,
+            } => match sim.write::<Map>().build_special_building(
+                &obb,
+                kind,
+                gen,
+                zone.clone(),
+                connected_road,
+            ) {
+                Some(id) => sim.write::<BuildingInfos>().insert(id),
+                None => failure = Some(CommandError::Blocked),
+            },
+            SetGameTime(gt) => *sim.write::<GameTime>() = gt,
+            SetTradePolicy(ref policy) => *sim.write::<TradePolicy>() = policy.clone(),
+            SetElectricityPrice(price) => sim.write::<Government>().electricity_price = price,
+            SetGameplayParams(params) => {
+                sim.write::<Market>().set_params(&params);
+                *sim.write::<GameplayParams>() = GameplayParams {
+                    sandbox: true,
+                    ..params
+                };
+            }
+            MarkCheatsUsed => sim.write::<Cheats>().used = true,
+            AddMoney(money) => {
+                sim.write::<Cheats>().used = true;
+                sim.write::<Government>()
+                    .transact(BudgetReason::Cheats, money);
+            }
+            TakeLoan(amount) => sim.write::<Government>().take_loan(amount),
+            RepayLoan(amount) => sim.write::<Government>().repay_loan(amount),
+            SpawnHuman(house) => {
+                sim.write::<Cheats>().used = true;
+                if spawn_human(sim, house).is_none() {
+                    failure = Some(CommandError::Blocked);
+                }
+            }
+            ToggleBlackout(network) => {
+                let mut cheats = sim.write::<Cheats>();
+                cheats.used = true;
+                if !cheats.blackouts.remove(&network) {
+                    cheats.blackouts.insert(network);
+                }
+            }
+            SetCompanyPriority {
+                building,
+                prioritized,
             } => {
-                if let Some(id) = sim.write::<Map>().build_special_building(
-                    &obb,
-                    kind,
-                    gen,
-                    zone.clone(),
-                    connected_road,
-                ) {
-                    sim.write::<BuildingInfos>().insert(id);
+                if let Some(company) = company_in(&sim.read::<BuildingInfos>(), building) {
+                    sim.write::<Market>()
+                        .set_prioritized(SoulID::GoodsCompany(company), prioritized);
+                }
+            }
+            CloseCompany(building) => {
+                if let Some(company) = company_in(&sim.read::<BuildingInfos>(), building) {
+                    // workers are released and the building is freed like on bankruptcy
+                    log::info!("{:?} was closed", company);
+                    sim.read::<ParCommandBuffer<CompanyEnt>>().kill(company);
+                }
+            }
+            AddBusStop(pos) => {
+                let stop = snap_bus_stop(&sim.map(), pos);
+                if let Some(stop) = stop {
+                    sim.write::<Transit>().add_stop(stop);
+                }
+            }
+            RemoveBusStop(id) => sim.write::<Transit>().remove_stop(id),
+            AddBusLine { ref name } => {
+                sim.write::<Transit>().add_line(name.clone());
+            }
+            RemoveBusLine(id) => sim.write::<Transit>().remove_line(id),
+            UpdateBusLine {
+                line,
+                ref stops,
+                n_buses,
+            } => sim.write::<Transit>().update_line(line, stops, n_buses),
+            MapUndo => {
+                let undo = sim.write::<MapEditHistory>().undo();
+                if let Some((ops, cost)) = undo {
+                    pay_map_edit(sim, cost, false);
+                    apply_map_edit_ops(sim, &ops);
+                }
+            }
+            MapRedo => {
+                let redo = sim.write::<MapEditHistory>().redo();
+                if let Some((ops, cost)) = redo {
+                    pay_map_edit(sim, cost, true);
+                    apply_map_edit_ops(sim, &ops);
                 }
             }
-            SetGameTime(gt) => *sim.write::<GameTime>() = gt,
             AddTrain {
                 dist: _,
                 n_wagons: _,
@@ -312,9 +935,13 @@ impl WorldCommand {
                 spawn_train(sim, wagons, RailWagonKind::Freight, lane, dist);
             }
 
-            MapLoadParis => load_parismap(&mut sim.map_mut()),
+            MapLoadParis => {
+                load_parismap(&mut sim.map_mut());
+                sim.write::<MapEditHistory>().clear();
+            }
             MapLoadTestField { pos, size, spacing } => {
-                load_testfield(&mut sim.map_mut(), pos, size, spacing)
+                load_testfield(&mut sim.map_mut(), pos, size, spacing);
+                sim.write::<MapEditHistory>().clear();
             }
             Init(ref opts) => {
                 if opts.save_replay {
@@ -325,11 +952,17 @@ impl WorldCommand {
                 }
 
                 if opts.terrain_size > 0 {
-                    generate_terrain(sim, opts.terrain_size);
+                    generate_terrain(sim, opts.terrain_size, &opts.mapgen);
                 }
 
+                sim.resources.insert::<GameplayParams>(opts.params);
+                sim.write::<Government>()
+                    .reset_money(opts.params.starting_money);
+                *sim.write::<Market>() = Market::new(&opts.params);
+
                 sim.resources
                     .insert::<SimulationOptions>(SimulationOptions::clone(opts));
+                sim.map_mut().landmark_routing = opts.landmark_routing;
             }
             UpdateZone { building, ref zone } => {
                 let mut map = sim.map_mut();
@@ -347,9 +980,12 @@ impl WorldCommand {
                         continue;
                     };
 
+                    let proto = VehicleKind::Car.random_prototype(&mut rng);
+
                     drop((map, pm, rng));
 
-                    let Some(v_id) = spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, spot)
+                    let Some(v_id) =
+                        spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, proto, spot)
                     else {
                         continue;
                     };
@@ -372,20 +1008,232 @@ impl WorldCommand {
                 slope,
             } => {
                 let tick = sim.read::<GameTime>().tick;
+                let bounds = Environment::terraform_bounds(kind, center, radius);
+                sim.write::<MapEditHistory>()
+                    .extend_terrain_stroke(&sim.map().environment, bounds);
                 sim.map_mut()
                     .terraform(tick, kind, center, radius, amount, level, slope);
             }
+            MapEndTerraform => {
+                let before = sim.write::<MapEditHistory>().take_terrain_stroke();
+                let edit = before.and_then(|b| PendingMapEdit::Heights(b).finish(&sim.map()));
+                if let Some(edit) = edit {
+                    // terraforming is free
+                    let cost = MapEditCost {
+                        amount: Money::ZERO,
+                        reason: BudgetReason::Buildings,
+                    };
+                    sim.write::<MapEditHistory>().push(edit, cost);
+                }
+            }
+        }
+
+        if let Some(err) = failure {
+            log::warn!("command could not be applied: {}", err);
+            // nothing was built
+            let mut gvt = sim.write::<Government>();
+            gvt.transact(reason, cost);
+            if cost > Money::ZERO {
+                gvt.construction_spending -= cost;
+            }
+            drop(gvt);
+            sim.write::<FailedCommands>().push(err);
+            return;
+        }
+
+        if let Some(edit) = pending_edit.and_then(|p| p.finish(&sim.map())) {
+            let cost = MapEditCost {
+                amount: cost,
+                reason,
+            };
+            sim.write::<MapEditHistory>().push(edit, cost);
+        }
+    }
+
+    /// Captures what is needed to undo this command, if it is a map edit
+    fn pending_map_edit(&self, map: &Map) -> Option<PendingMapEdit> {
+        Some(match *self {
+            MapRemoveIntersection(id) => {
+                PendingMapEdit::roads(map, map.intersections.get(id)?.roads.clone(), vec![])
+            }
+            MapRemoveRoad(id) => PendingMapEdit::roads(map, [id], vec![]),
+            MapRemoveBuilding(id) => {
+                PendingMapEdit::RemoveBuilding(id, BuildingSnapshot::new(map, id)?)
+            }
+            MapBulldozeArea { area, filter } => {
+                let sel = map.bulldoze_selection(area, filter);
+                let mut edits = sel
+                    .buildings
+                    .iter()
+                    .filter_map(|&id| {
+                        Some(PendingMapEdit::RemoveBuilding(
+                            id,
+                            BuildingSnapshot::new(map, id)?,
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                edits.push(PendingMapEdit::roads(map, sel.roads, vec![]));
+                PendingMapEdit::Multiple(edits)
+            }
+            MapPlantTrees { ref trees } => PendingMapEdit::Trees {
+                added: trees
+                    .iter()
+                    .filter(|(pos, _)| map.can_plant_tree(*pos))
+                    .map(|&(pos, species)| Tree::planted(pos, species))
+                    .collect(),
+                removed: vec![],
+            },
+            MapRemoveTrees { ref stroke, radius } => PendingMapEdit::Trees {
+                added: vec![],
+                removed: map.trees_in_stroke(stroke, radius),
+            },
+            MapBuildHouse(id) => PendingMapEdit::AddBuilding(BuildingSnapshot {
+                obb: map.lots.get(id)?.shape,
+                kind: BuildingKind::House,
+                gen: BuildingGen::House,
+                zone: None,
+            }),
+            MapMakeConnection { from, to, .. } => PendingMapEdit::connections(map, &[from, to]),
+            MapMakeMultipleConnections(ref projects, _) => {
+                PendingMapEdit::connections(map, projects)
+            }
+            MapUpdateIntersectionPolicy {
+                inter,
+                turn,
+                light,
+                ref timing,
+                ref disabled_turns,
+            } => {
+                let i = map.intersections.get(inter)?;
+                PendingMapEdit::SetPolicy {
+                    pos: i.pos,
+                    old: PolicySnapshot::new(i),
+                    new: PolicySnapshot {
+                        turn,
+                        light,
+                        timing: timing.clone(),
+                        disabled_turns: disabled_turns.clone(),
+                    },
+                }
+            }
+            MapSetRoadPattern { road, ref pattern } => {
+                let r = map.roads.get(road)?;
+                PendingMapEdit::RoadPattern {
+                    src: map.intersections.get(r.src)?.pos,
+                    dst: map.intersections.get(r.dst)?.pos,
+                    old: r.pattern(&map.lanes),
+                    new: pattern.clone(),
+                }
+            }
+            MapFlipRoad(road) => {
+                let r = map.roads.get(road)?;
+                let old = r.pattern(&map.lanes);
+                if !old.is_one_way() {
+                    return None;
+                }
+                PendingMapEdit::RoadPattern {
+                    src: map.intersections.get(r.src)?.pos,
+                    dst: map.intersections.get(r.dst)?.pos,
+                    new: old.flipped(),
+                    old,
+                }
+            }
+            MapBuildSpecialBuilding {
+                pos,
+                kind,
+                gen,
+                ref zone,
+                ..
+            } => PendingMapEdit::AddBuilding(BuildingSnapshot {
+                obb: pos,
+                kind,
+                gen,
+                zone: zone.clone(),
+            }),
+            _ => return None,
+        })
+    }
+}
+
+/// Gives back what was paid for an edit when it is undone, or pays it again when it is redone
+fn pay_map_edit(sim: &mut Simulation, cost: MapEditCost, redo: bool) {
+    let amount = if redo { -cost.amount } else { cost.amount };
+    let mut gvt = sim.write::<Government>();
+    gvt.transact(cost.reason, amount);
+    if cost.amount > Money::ZERO {
+        gvt.construction_spending -= amount;
+    }
+}
+
+/// Applies the operations of an undo or redo, buildings that are rebuilt start empty
+fn apply_map_edit_ops(sim: &mut Simulation, ops: &[MapEditOp]) {
+    let old_lanes: Vec<LaneID> = sim.map().lanes().keys().collect();
+    let built = sim.map_mut().apply_edit_ops(ops);
+    let mut infos = sim.write::<BuildingInfos>();
+    for b in built {
+        infos.insert(b);
+    }
+    drop(infos);
+
+    let map = sim.map();
+    let removed_lanes: Vec<LaneID> = old_lanes
+        .into_iter()
+        .filter(|id| !map.lanes().contains_key(*id))
+        .collect();
+    drop(map);
+    reroute_through(sim, &removed_lanes);
+}
+
+/// Rebuilds the lanes of the road, what was going through its old lanes looks for a new route
+fn rebuild_road_lanes(
+    sim: &mut Simulation,
+    road: RoadID,
+    rebuild: impl FnOnce(&mut Map) -> Option<RoadID>,
+) {
+    let mut map = sim.map_mut();
+    let Some(r) = map.roads.get(road) else {
+        return;
+    };
+    let old_lanes: Vec<LaneID> = r.lanes_iter().map(|(id, _)| id).collect();
+    if rebuild(&mut map).is_none() {
+        return;
+    }
+    drop(map);
+    reroute_through(sim, &old_lanes);
+}
+
+/// Whoever was going to follow one of the lanes, which do not exist anymore, looks for a new route
+fn reroute_through(sim: &mut Simulation, lanes: &[LaneID]) {
+    if lanes.is_empty() {
+        return;
+    }
+    for (it, _, _) in sim.world_mut_unchecked().query_it_trans_speed() {
+        if it.uses_lanes(lanes) {
+            it.reroute();
         }
     }
 }
 
-fn generate_terrain(sim: &mut Simulation, size: u16) {
+/// The goods company owning the building, if any
+fn company_in(binfos: &BuildingInfos, building: BuildingID) -> Option<CompanyID> {
+    match binfos.owner(building)? {
+        SoulID::GoodsCompany(id) => Some(id),
+        _ => None,
+    }
+}
+
+fn generate_terrain(sim: &mut Simulation, size: u16, params: &MapGenParams) {
     info!("generating terrain..");
     let t = Instant::now();
 
-    sim.map_mut().environment = Environment::new(size, size);
+    sim.map_mut().environment = Environment::generate(size, size, params);
     info!("took {}s", t.elapsed().as_secs_f32());
 
+    build_starting_infrastructure(sim);
+}
+
+/// The railway with the external trading the trains come to, and a road for the trucks from outside
+pub(crate) fn build_starting_infrastructure(sim: &mut Simulation) {
     let c = vec3(3000.0 + 72.2 / 2.0, 200.0 / 2.0 + 1.0, 0.0);
     let obb = OBB::new(c.xy(), -Vec2::X, 72.2, 200.0);
 
@@ -400,6 +1248,23 @@ fn generate_terrain(sim: &mut Simulation, size: u16) {
         &pat,
     );
 
+    // a piece of road next to the railway for the trucks coming from outside
+    let road_from = vec3(c.x + 150.0, 150.0, 0.0);
+    let road_to = vec3(c.x + 150.0, 2.0, 0.0);
+    let bounds = sim.map().environment.bounds();
+    if bounds.contains(road_from.xy()) && bounds.contains(road_to.xy()) {
+        let pat = LanePatternBuilder::new().build();
+        let stub = sim.map_mut().make_connection(
+            MapProject::ground(road_from),
+            MapProject::ground(road_to),
+            None,
+            &pat,
+        );
+        if let Some((border_inter, _)) = stub {
+            sim.map_mut().set_road_connection(border_inter, true);
+        }
+    }
+
     if sim
         .map_mut()
         .build_special_building(